@@ -70,6 +70,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         memory_swap: Some(2147483648),  // 2GB
         cpu_quota: Some(0.5),           // 50% CPU
         cpu_period: Some(100000),       // 100ms
+        cpu_shares: None,
         disk_quota: Some(10737418240),  // 10GB
         pids_limit: Some(100),          // 100 processos
     };
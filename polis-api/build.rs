@@ -0,0 +1,11 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto/polis.proto");
+    println!("cargo:rerun-if-env-changed=PROTOC");
+    // Sem um PROTOC explícito usa o protoc vendorizado, para o build não
+    // depender do protobuf-compiler do sistema
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+    tonic_prost_build::configure().compile_protos(&["proto/polis.proto"], &["proto"])?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use crate::auth_layer::www_authenticate;
+use hyper::body::Bytes;
+use hyper::header::{CONTENT_TYPE, WWW_AUTHENTICATE};
+use hyper::{Response, StatusCode};
+use polis_core::PolisError;
+
+/// Erro de um endpoint, respondido como `{"error": {"code", "message"}}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    /// Código estável para clientes, ex.: `not_found`
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_request", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, "conflict", message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", message)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "error": {
+                "code": self.code,
+                "message": self.message,
+            }
+        })
+    }
+
+    /// Respostas 401 levam `WWW-Authenticate`
+    pub fn into_response(self) -> Response<Bytes> {
+        let mut response = Response::builder()
+            .status(self.status)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Bytes::from(self.to_json().to_string()))
+            .unwrap();
+        if self.status == StatusCode::UNAUTHORIZED {
+            response
+                .headers_mut()
+                .insert(WWW_AUTHENTICATE, www_authenticate(&self));
+        }
+        response
+    }
+}
+
+/// Erros de configuração e de requisição são do cliente; os demais vêm dos
+/// componentes do Polis
+impl From<PolisError> for ApiError {
+    fn from(error: PolisError) -> Self {
+        match error {
+            PolisError::Config(_) | PolisError::Api(_) | PolisError::Ambiguous { .. } => {
+                Self::bad_request(error.to_string())
+            }
+            PolisError::Auth(_) => {
+                Self::new(StatusCode::UNAUTHORIZED, "unauthorized", error.to_string())
+            }
+            _ => Self::internal(error.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.status, self.code, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
@@ -0,0 +1,30 @@
+use polis_orchestrator::{HealthMonitor, Orchestrator};
+use polis_runtime::ContainerRuntime;
+use polis_stats::ContainerStatsCollector;
+use std::sync::Arc;
+
+/// Estado compartilhado pelos handlers da API; os handlers usam estes
+/// componentes em vez de criar os seus
+#[derive(Clone)]
+pub struct AppState {
+    pub runtime: Arc<dyn ContainerRuntime>,
+    pub stats: Arc<ContainerStatsCollector>,
+    pub orchestrator: Arc<Orchestrator>,
+    pub health: Arc<HealthMonitor>,
+}
+
+impl AppState {
+    pub fn new(
+        runtime: Arc<dyn ContainerRuntime>,
+        stats: Arc<ContainerStatsCollector>,
+        orchestrator: Arc<Orchestrator>,
+        health: Arc<HealthMonitor>,
+    ) -> Self {
+        Self {
+            runtime,
+            stats,
+            orchestrator,
+            health,
+        }
+    }
+}
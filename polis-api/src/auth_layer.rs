@@ -0,0 +1,223 @@
+use crate::ApiError;
+use hyper::body::Bytes;
+use hyper::header::HeaderValue;
+use hyper::{Method, Request, Response, StatusCode};
+use polis_auth::{
+    extract_token_from_request, JwtManager, PermissionManager, TokenError, UserSession,
+};
+use polis_core::Result;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Caminhos atendidos sem token por padrão
+pub const DEFAULT_AUTH_ALLOWLIST: &[&str] = &[
+    "/healthz",
+    "/metrics",
+    "/v1/auth/login",
+    "/v1/auth/refresh",
+    "/v1/auth/revoke",
+];
+
+/// Permissão exigida pelas requisições com `method` (ou qualquer método)
+/// cujo caminho começa com `path_prefix`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutePermission {
+    pub method: Option<Method>,
+    pub path_prefix: String,
+    pub permission: String,
+}
+
+/// Autenticação por token Bearer e autorização por permissão para as rotas
+/// REST. O token é validado pelo `JwtManager`; as permissões do usuário são
+/// as do token somadas às das suas roles.
+#[derive(Clone)]
+pub struct AuthLayer {
+    jwt: Arc<JwtManager>,
+    permissions: PermissionManager,
+    allowlist: Vec<String>,
+    rules: Vec<RoutePermission>,
+}
+
+impl AuthLayer {
+    pub fn new(jwt: Arc<JwtManager>) -> Self {
+        Self {
+            jwt,
+            permissions: PermissionManager::new(),
+            allowlist: DEFAULT_AUTH_ALLOWLIST
+                .iter()
+                .map(|path| path.to_string())
+                .collect(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Usa as roles de `permissions` em vez das roles padrão
+    pub fn with_permissions(mut self, permissions: PermissionManager) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Substitui os caminhos atendidos sem token. Cada caminho libera também
+    /// os caminhos abaixo dele (`/metrics` libera `/metrics/system`).
+    pub fn with_allowlist<I, S>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowlist = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Exige `permission` nas rotas sob `path_prefix`, antes da regra padrão.
+    /// Entre regras que se aplicam, vale a de prefixo mais longo.
+    pub fn require(
+        mut self,
+        method: Option<Method>,
+        path_prefix: impl Into<String>,
+        permission: impl Into<String>,
+    ) -> Self {
+        self.rules.push(RoutePermission {
+            method,
+            path_prefix: path_prefix.into(),
+            permission: permission.into(),
+        });
+        self
+    }
+
+    pub fn is_allowlisted(&self, path: &str) -> bool {
+        self.allowlist.iter().any(|allowed| {
+            path == allowed
+                || path
+                    .strip_prefix(allowed.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+
+    /// Permissão exigida por uma rota: a da regra configurada ou, sem regra,
+    /// `<recurso>:read` para GET e HEAD e `<recurso>:write` para os demais
+    /// métodos. O recurso é o primeiro segmento após `/v1`, ou `stats` nas
+    /// rotas de estatísticas.
+    pub fn required_permission(&self, method: &Method, path: &str) -> Option<String> {
+        let rule = self
+            .rules
+            .iter()
+            .filter(|rule| rule.method.as_ref().is_none_or(|m| m == method))
+            .filter(|rule| path.starts_with(&rule.path_prefix))
+            .max_by_key(|rule| rule.path_prefix.len());
+        if let Some(rule) = rule {
+            return Some(rule.permission.clone());
+        }
+
+        let segments: Vec<&str> = path
+            .trim_matches('/')
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let segments = match segments.as_slice() {
+            ["v1", rest @ ..] => rest,
+            all => all,
+        };
+        let resource = if segments.contains(&"stats") {
+            "stats"
+        } else {
+            *segments.first()?
+        };
+        let action = match *method {
+            Method::GET | Method::HEAD => "read",
+            _ => "write",
+        };
+        Some(format!("{}:{}", resource, action))
+    }
+
+    /// Valida o token da requisição e confere a permissão da rota. A sessão
+    /// do usuário fica nas extensões da requisição.
+    pub fn authorize(&self, req: &mut Request<Bytes>) -> std::result::Result<(), ApiError> {
+        if self.is_allowlisted(req.uri().path()) {
+            return Ok(());
+        }
+
+        let token = extract_token_from_request(req)
+            .map_err(|e| ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", e.to_string()))?;
+        let claims = self.jwt.verify_token(&token).map_err(token_error)?;
+        let user_id = claims.sub.parse::<uuid::Uuid>().map_err(|_| {
+            token_error(TokenError::Malformed(format!(
+                "sub não é um ID de usuário: '{}'",
+                claims.sub
+            )))
+        })?;
+
+        let mut permissions = claims.permissions;
+        for role in &claims.roles {
+            for permission in self
+                .permissions
+                .get_role(role)
+                .map(|role| role.permissions.as_slice())
+                .unwrap_or_default()
+            {
+                if !permissions.contains(permission) {
+                    permissions.push(permission.clone());
+                }
+            }
+        }
+
+        if let Some(required) = self.required_permission(req.method(), req.uri().path()) {
+            if !permissions.contains(&required) {
+                return Err(ApiError::new(
+                    StatusCode::FORBIDDEN,
+                    "forbidden",
+                    format!(
+                        "Acesso negado: '{}' requer a permissão '{}'",
+                        claims.username, required
+                    ),
+                ));
+            }
+        }
+
+        req.extensions_mut().insert(UserSession {
+            user_id,
+            username: claims.username,
+            permissions,
+            expires_at: chrono::DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_default(),
+        });
+        Ok(())
+    }
+
+    /// Encaminha a requisição a `next` se ela for autorizada; senão responde
+    /// 401 ou 403 no formato de `ApiError`
+    pub async fn handle<F, Fut>(&self, mut req: Request<Bytes>, next: F) -> Result<Response<Bytes>>
+    where
+        F: FnOnce(Request<Bytes>) -> Fut,
+        Fut: Future<Output = Result<Response<Bytes>>>,
+    {
+        match self.authorize(&mut req) {
+            Ok(()) => next(req).await,
+            Err(error) => Ok(error.into_response()),
+        }
+    }
+}
+
+/// 401 com um código e uma mensagem para cada motivo de recusa do token
+pub(crate) fn token_error(error: TokenError) -> ApiError {
+    let code = match error {
+        TokenError::Expired => "token_expired",
+        TokenError::InvalidSignature => "invalid_signature",
+        TokenError::Malformed(_) => "invalid_token",
+    };
+    ApiError::new(StatusCode::UNAUTHORIZED, code, error.to_string())
+}
+
+/// Valor de `WWW-Authenticate` das respostas 401 (RFC 6750); o cabeçalho
+/// só aceita ASCII
+pub(crate) fn www_authenticate(error: &ApiError) -> HeaderValue {
+    let description = match error.code {
+        "token_expired" => "token expirado",
+        "invalid_signature" => "assinatura do token invalida",
+        "invalid_token" => "token invalido",
+        _ => return HeaderValue::from_static("Bearer realm=\"polis\""),
+    };
+    HeaderValue::from_str(&format!(
+        "Bearer realm=\"polis\", error=\"invalid_token\", error_description=\"{}\"",
+        description
+    ))
+    .unwrap()
+}
@@ -1,11 +1,18 @@
-use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
-use hyper::{Method, Request, Response, StatusCode};
+use crate::auth_layer::token_error;
+use crate::v1_routes::json;
+use crate::ApiError;
 use hyper::body::Bytes;
-use polis_auth::AuthManager;
-use polis_core::{PolisError, Result};
+use hyper::header::{HeaderValue, RETRY_AFTER};
+use hyper::{Method, Request, Response, StatusCode};
+use polis_auth::{
+    extract_token_from_request, refresh_token_store_path, AuthManager, AuthResult,
+    LoginRateLimiter, RefreshTokenStore,
+};
+use polis_core::{Clock, PolisConfig, PolisError, Result, SystemClock};
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginRequest {
@@ -19,22 +26,18 @@ pub struct RefreshRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct LogoutRequest {
-    pub token: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LoginResponse {
-    pub access_token: String,
+pub struct RevokeRequest {
     pub refresh_token: String,
-    pub token_type: String,
-    pub expires_in: u64,
 }
 
+/// Resposta de login e de refresh; o refresh token recebido no refresh
+/// deixa de valer e é substituído por `refresh_token`
 #[derive(Debug, Serialize, Deserialize)]
-pub struct RefreshResponse {
+pub struct TokenResponse {
     pub access_token: String,
+    pub refresh_token: String,
     pub token_type: String,
+    /// Validade do token de acesso, em segundos
     pub expires_in: u64,
 }
 
@@ -46,77 +49,213 @@ pub struct UserInfo {
     pub permissions: Vec<String>,
 }
 
+/// Emissão, renovação e revogação de tokens da API, com os usuários do
+/// `AuthManager`. Tentativas de login são limitadas por usuário e por IP; o
+/// IP vem de um `SocketAddr` nas extensões da requisição, posto pelo
+/// servidor.
 pub struct AuthRoutes {
     auth_manager: Arc<RwLock<AuthManager>>,
+    refresh_tokens: Mutex<RefreshTokenStore>,
+    login_limiter: Mutex<LoginRateLimiter>,
+    refresh_token_ttl: chrono::Duration,
+    clock: Arc<dyn Clock>,
 }
 
 impl AuthRoutes {
-    pub fn new(auth_manager: Arc<RwLock<AuthManager>>) -> Self {
-        Self { auth_manager }
+    /// Lê a validade dos refresh tokens e o limite de tentativas de
+    /// `config.auth`; os refresh tokens ficam sob `runtime.root_dir`
+    pub fn new(auth_manager: Arc<RwLock<AuthManager>>, config: &PolisConfig) -> Result<Self> {
+        let auth = &config.auth;
+        Ok(Self {
+            auth_manager,
+            refresh_tokens: Mutex::new(RefreshTokenStore::load(refresh_token_store_path(
+                &config.runtime.root_dir,
+            ))?),
+            login_limiter: Mutex::new(LoginRateLimiter::new(
+                auth.max_login_attempts,
+                chrono::Duration::seconds(auth.lockout_seconds as i64),
+            )),
+            refresh_token_ttl: chrono::Duration::seconds(auth.refresh_token_ttl as i64),
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Relógio da validade dos refresh tokens e do bloqueio de login
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
     }
 
+    /// `POST /v1/auth/login`, `POST /v1/auth/refresh`,
+    /// `POST /v1/auth/revoke` e `GET /v1/auth/me` (com token de acesso)
     pub async fn handle_request(&self, req: Request<Bytes>) -> Result<Response<Bytes>> {
-        match (req.method(), req.uri().path()) {
-            (&Method::POST, "/auth/login") => self.handle_login(req).await,
-            (&Method::POST, "/auth/refresh") => self.handle_refresh(req).await,
-            (&Method::POST, "/auth/logout") => self.handle_logout(req).await,
-            (&Method::GET, "/auth/me") => self.handle_me(req).await,
-            _ => Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Bytes::from("Endpoint não encontrado"))
-                .unwrap()),
-        }
+        let result = match (req.method(), req.uri().path()) {
+            (&Method::POST, "/v1/auth/login") => self.handle_login(&req).await,
+            (&Method::POST, "/v1/auth/refresh") => self.handle_refresh(req.body()).await,
+            (&Method::POST, "/v1/auth/revoke") => self.handle_revoke(req.body()).await,
+            (&Method::GET, "/v1/auth/me") => self.handle_me(&req).await,
+            _ => Err(ApiError::not_found("Endpoint não encontrado")),
+        };
+        Ok(result.unwrap_or_else(ApiError::into_response))
     }
 
-    async fn handle_login(&self, _req: Request<Bytes>) -> Result<Response<Bytes>> {
-        // Implementação simplificada
-        let response = serde_json::json!({
-            "message": "Login endpoint - implementação em desenvolvimento"
-        });
-        
-        Ok(Response::builder()
-            .status(StatusCode::OK)
-            .header(CONTENT_TYPE, "application/json")
-            .body(Bytes::from(response.to_string()))
-            .unwrap())
+    async fn handle_login(
+        &self,
+        req: &Request<Bytes>,
+    ) -> std::result::Result<Response<Bytes>, ApiError> {
+        let login: LoginRequest = parse_body(req.body())?;
+        let user_key = format!("user:{}", login.username);
+        let mut keys = vec![user_key.clone()];
+        if let Some(addr) = req.extensions().get::<SocketAddr>() {
+            keys.push(format!("ip:{}", addr.ip()));
+        }
+
+        let now = self.clock.now();
+        if let Some(retry_after) = self.login_limiter.lock().await.check(&keys, now) {
+            let seconds = retry_after.num_seconds().max(1);
+            let mut response = ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "too_many_attempts",
+                format!(
+                    "Muitas tentativas de login; tente novamente em {} s",
+                    seconds
+                ),
+            )
+            .into_response();
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(seconds));
+            return Ok(response);
+        }
+
+        // A mensagem não diz se o usuário existe. O AuthManager é liberado
+        // antes de tocar nos refresh tokens, que o refresh trava primeiro.
+        let authenticated = {
+            let mut auth_manager = self.auth_manager.write().await;
+            let ttl = auth_manager.access_token_ttl;
+            auth_manager
+                .authenticate(&login.username, &login.password)
+                .await
+                .map(|result| (result, ttl))
+        };
+        let (result, ttl) = match authenticated {
+            Ok(authenticated) => authenticated,
+            Err(_) => {
+                self.login_limiter.lock().await.record_failure(&keys, now);
+                return Err(ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "invalid_credentials",
+                    "Usuário ou senha inválidos",
+                ));
+            }
+        };
+        self.login_limiter.lock().await.record_success(&user_key);
+
+        let refresh_token = self.refresh_tokens.lock().await.issue(
+            result.user.id,
+            &result.user.username,
+            self.refresh_token_ttl,
+            now,
+        )?;
+        Ok(token_response(result, refresh_token, ttl))
     }
 
-    async fn handle_refresh(&self, _req: Request<Bytes>) -> Result<Response<Bytes>> {
-        // Implementação simplificada
-        let response = serde_json::json!({
-            "message": "Refresh endpoint - implementação em desenvolvimento"
-        });
-        
-        Ok(Response::builder()
-            .status(StatusCode::OK)
-            .header(CONTENT_TYPE, "application/json")
-            .body(Bytes::from(response.to_string()))
-            .unwrap())
+    async fn handle_refresh(&self, body: &Bytes) -> std::result::Result<Response<Bytes>, ApiError> {
+        let request: RefreshRequest = parse_body(body)?;
+        let invalid = |e: PolisError| match e {
+            PolisError::Auth(_) => {
+                ApiError::new(StatusCode::UNAUTHORIZED, "invalid_grant", e.to_string())
+            }
+            e => e.into(),
+        };
+
+        let mut refresh_tokens = self.refresh_tokens.lock().await;
+        let (record, refresh_token) = refresh_tokens
+            .rotate(
+                &request.refresh_token,
+                self.refresh_token_ttl,
+                self.clock.now(),
+            )
+            .map_err(invalid)?;
+
+        let auth_manager = self.auth_manager.read().await;
+        let user = match auth_manager
+            .user_manager
+            .get_user_by_id(&record.user_id)
+            .await
+        {
+            Ok(user) if user.is_active => user,
+            Ok(_) | Err(_) => {
+                refresh_tokens.revoke_user(&record.user_id)?;
+                return Err(ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "invalid_grant",
+                    format!("Usuário '{}' inativo ou removido", record.username),
+                ));
+            }
+        };
+        let result = auth_manager.issue_access_token(&user).await?;
+        Ok(token_response(
+            result,
+            refresh_token,
+            auth_manager.access_token_ttl,
+        ))
     }
 
-    async fn handle_logout(&self, _req: Request<Bytes>) -> Result<Response<Bytes>> {
-        // Implementação simplificada
-        let response = serde_json::json!({
-            "message": "Logout realizado com sucesso"
-        });
-        
+    /// Responde 204 mesmo se o token já não valia (RFC 7009)
+    async fn handle_revoke(&self, body: &Bytes) -> std::result::Result<Response<Bytes>, ApiError> {
+        let request: RevokeRequest = parse_body(body)?;
+        self.refresh_tokens
+            .lock()
+            .await
+            .revoke(&request.refresh_token)?;
         Ok(Response::builder()
-            .status(StatusCode::OK)
-            .header(CONTENT_TYPE, "application/json")
-            .body(Bytes::from(response.to_string()))
+            .status(StatusCode::NO_CONTENT)
+            .body(Bytes::new())
             .unwrap())
     }
 
-    async fn handle_me(&self, _req: Request<Bytes>) -> Result<Response<Bytes>> {
-        // Implementação simplificada
-        let response = serde_json::json!({
-            "message": "User info endpoint - implementação em desenvolvimento"
-        });
-        
-        Ok(Response::builder()
-            .status(StatusCode::OK)
-            .header(CONTENT_TYPE, "application/json")
-            .body(Bytes::from(response.to_string()))
-            .unwrap())
+    async fn handle_me(
+        &self,
+        req: &Request<Bytes>,
+    ) -> std::result::Result<Response<Bytes>, ApiError> {
+        let token = extract_token_from_request(req)
+            .map_err(|e| ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", e.to_string()))?;
+        let auth_manager = self.auth_manager.read().await;
+        let claims = auth_manager
+            .jwt_manager
+            .verify_token(&token)
+            .map_err(token_error)?;
+        let user_id = claims.sub.parse::<uuid::Uuid>().map_err(|_| {
+            ApiError::new(StatusCode::UNAUTHORIZED, "invalid_token", "sub inválido")
+        })?;
+        let user = auth_manager.user_manager.get_user_by_id(&user_id).await?;
+
+        let info = UserInfo {
+            id: user.id.to_string(),
+            username: user.username,
+            email: user.email,
+            permissions: claims.permissions,
+        };
+        Ok(json(StatusCode::OK, serde_json::json!(info)))
     }
-}
\ No newline at end of file
+}
+
+fn token_response(
+    result: AuthResult,
+    refresh_token: String,
+    access_token_ttl: chrono::Duration,
+) -> Response<Bytes> {
+    let response = TokenResponse {
+        access_token: result.token,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: access_token_ttl.num_seconds().max(0) as u64,
+    };
+    json(StatusCode::OK, serde_json::json!(response))
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(body: &Bytes) -> std::result::Result<T, ApiError> {
+    serde_json::from_slice(body)
+        .map_err(|e| ApiError::bad_request(format!("Corpo da requisição inválido: {}", e)))
+}
@@ -0,0 +1,106 @@
+use hyper::body::Bytes;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Method, Request, Response, StatusCode};
+use polis_core::{PolisError, Result};
+use polis_orchestrator::{DeploymentSpec, Manifest, Orchestrator};
+use std::sync::Arc;
+
+pub struct DeploymentRoutes {
+    orchestrator: Arc<Orchestrator>,
+}
+
+impl DeploymentRoutes {
+    pub fn new(orchestrator: Arc<Orchestrator>) -> Self {
+        Self { orchestrator }
+    }
+
+    /// `POST /deployments/apply` (manifesto YAML no corpo),
+    /// `POST /deployments/{namespace}/{name}/pause` e
+    /// `POST /deployments/{namespace}/{name}/resume`
+    pub async fn handle_request(&self, req: Request<Bytes>) -> Result<Response<Bytes>> {
+        let segments: Vec<&str> = req.uri().path().trim_matches('/').split('/').collect();
+        match (req.method(), segments.as_slice()) {
+            (&Method::POST, ["deployments", "apply"]) => Ok(self.handle_apply(req.body()).await),
+            (&Method::POST, ["deployments", namespace, name, "pause"]) => {
+                let result = self.orchestrator.pause_deployment(name, namespace).await;
+                Ok(Self::respond(result, name, "paused"))
+            }
+            (&Method::POST, ["deployments", namespace, name, "resume"]) => {
+                let result = self.orchestrator.resume_deployment(name, namespace).await;
+                Ok(Self::respond(result, name, "resumed"))
+            }
+            _ => Ok(Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Bytes::from("Endpoint não encontrado"))
+                .unwrap()),
+        }
+    }
+
+    async fn handle_apply(&self, body: &Bytes) -> Response<Bytes> {
+        let specs = match std::str::from_utf8(body) {
+            Ok(content) => DeploymentSpec::from_yaml_str(content),
+            Err(e) => Err(PolisError::Api(format!("Manifesto não é UTF-8: {}", e))),
+        };
+        let specs = match specs {
+            Ok(specs) => specs,
+            Err(e) => {
+                return Self::json(
+                    StatusCode::BAD_REQUEST,
+                    serde_json::json!({ "error": e.to_string() }),
+                )
+            }
+        };
+
+        let mut results = Vec::new();
+        let mut failed = false;
+        for spec in specs {
+            let (namespace, name) = (spec.namespace.clone(), spec.name.clone());
+            let result = match self
+                .orchestrator
+                .apply(Manifest::Deployment(Box::new(spec)))
+                .await
+            {
+                Ok(action) => serde_json::json!({ "action": action }),
+                Err(e) => {
+                    failed = true;
+                    serde_json::json!({ "error": e.to_string() })
+                }
+            };
+            results.push(serde_json::json!({
+                "namespace": namespace,
+                "name": name,
+                "result": result,
+            }));
+        }
+
+        let status = if failed {
+            StatusCode::UNPROCESSABLE_ENTITY
+        } else {
+            StatusCode::OK
+        };
+        Self::json(status, serde_json::json!({ "deployments": results }))
+    }
+
+    fn respond(result: Result<()>, name: &str, state: &str) -> Response<Bytes> {
+        let (status, body) = match result {
+            Ok(()) => (
+                StatusCode::OK,
+                serde_json::json!({ "deployment": name, "status": state }),
+            ),
+            Err(e) => (
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({ "error": e.to_string() }),
+            ),
+        };
+
+        Self::json(status, body)
+    }
+
+    fn json(status: StatusCode, body: serde_json::Value) -> Response<Bytes> {
+        Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Bytes::from(body.to_string()))
+            .unwrap()
+    }
+}
@@ -1,10 +1,27 @@
-use polis_core::{Container, ContainerId, Image, ImageId, PolisError, Result};
+use crate::{ApiError, AppState};
+use hyper::StatusCode;
+use polis_core::{Container, ContainerId, Image, ImageId, NetworkMode, PolisError, Result};
 use polis_image::ImageManager;
+use polis_orchestrator::{DeploymentSpec, DeploymentStatusResult};
 use polis_runtime::{ContainerRuntime, PolisRuntime};
+use polis_stats::ContainerMetrics;
+use std::net::SocketAddr;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Code, Request, Response, Status};
 
-// Generated protobuf code would go here
-// For now, we'll define the service traits manually
+/// Mensagens e serviços gerados de `proto/polis.proto`
+pub mod proto {
+    tonic::include_proto!("polis");
+}
+
+use proto::container_service_server::{ContainerService, ContainerServiceServer};
+use proto::deployment_service_server::{DeploymentService, DeploymentServiceServer};
+
+type GrpcResult<T> = std::result::Result<Response<T>, Status>;
 
 pub struct GrpcServer {
     #[allow(dead_code)]
@@ -170,3 +187,468 @@ pub struct HealthStatus {
     pub status: String,
     pub service: String,
 }
+
+/// Serviços gRPC `ContainerService` e `DeploymentService` sobre o estado
+/// compartilhado da API
+#[derive(Clone)]
+pub struct GrpcApi {
+    state: AppState,
+}
+
+impl GrpcApi {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    pub fn container_service(&self) -> ContainerServiceServer<GrpcContainerService> {
+        ContainerServiceServer::new(GrpcContainerService {
+            state: self.state.clone(),
+        })
+    }
+
+    pub fn deployment_service(&self) -> DeploymentServiceServer<GrpcDeploymentService> {
+        DeploymentServiceServer::new(GrpcDeploymentService {
+            state: self.state.clone(),
+        })
+    }
+
+    /// Atende os dois serviços em `addr`
+    pub async fn serve(&self, addr: SocketAddr) -> Result<()> {
+        tracing::info!("Servidor gRPC iniciado em {}", addr);
+        tonic::transport::Server::builder()
+            .add_service(self.container_service())
+            .add_service(self.deployment_service())
+            .serve(addr)
+            .await
+            .map_err(|e| PolisError::Api(format!("Erro no servidor gRPC: {}", e)))
+    }
+}
+
+/// Mesma classificação dos erros REST, com os códigos gRPC equivalentes
+impl From<ApiError> for Status {
+    fn from(error: ApiError) -> Self {
+        let code = match error.status {
+            StatusCode::BAD_REQUEST => Code::InvalidArgument,
+            StatusCode::NOT_FOUND => Code::NotFound,
+            StatusCode::CONFLICT => Code::AlreadyExists,
+            StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+            _ => Code::Internal,
+        };
+        Status::new(code, error.message)
+    }
+}
+
+fn status(error: PolisError) -> Status {
+    ApiError::from(error).into()
+}
+
+pub struct GrpcContainerService {
+    state: AppState,
+}
+
+impl GrpcContainerService {
+    /// Container com o ID informado; um ID malformado é argumento inválido
+    async fn container(&self, id: &str) -> std::result::Result<Container, ApiError> {
+        let container_id = ContainerId::from_string(id).map_err(|e| {
+            ApiError::bad_request(format!("ID de container inválido '{}': {}", id, e))
+        })?;
+        self.state
+            .runtime
+            .get_container(container_id)
+            .await
+            .map_err(|_| ApiError::not_found(format!("Container '{}' não encontrado", id)))
+    }
+
+    async fn container_id(&self, id: &str) -> std::result::Result<ContainerId, ApiError> {
+        Ok(self.container(id).await?.id)
+    }
+}
+
+#[tonic::async_trait]
+impl ContainerService for GrpcContainerService {
+    async fn list_containers(
+        &self,
+        _request: Request<proto::ListContainersRequest>,
+    ) -> GrpcResult<proto::ListContainersResponse> {
+        let containers = self.state.runtime.list_containers().await.map_err(status)?;
+        Ok(Response::new(proto::ListContainersResponse {
+            containers: containers.iter().map(container_message).collect(),
+        }))
+    }
+
+    async fn get_container(
+        &self,
+        request: Request<proto::GetContainerRequest>,
+    ) -> GrpcResult<proto::GetContainerResponse> {
+        let container = self.container(&request.into_inner().id).await?;
+        Ok(Response::new(proto::GetContainerResponse {
+            container: Some(container_message(&container)),
+        }))
+    }
+
+    /// Apenas nome, imagem e comando são aplicados na criação
+    async fn create_container(
+        &self,
+        request: Request<proto::CreateContainerRequest>,
+    ) -> GrpcResult<proto::CreateContainerResponse> {
+        let request = request.into_inner();
+        if request.name.is_empty() || request.image.is_empty() {
+            return Err(ApiError::bad_request("name e image são obrigatórios").into());
+        }
+        let id = self
+            .state
+            .runtime
+            .create_container(request.name.clone(), request.image, request.command)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::CreateContainerResponse {
+            id: id.to_string(),
+            name: request.name,
+            status: "created".to_string(),
+        }))
+    }
+
+    async fn start_container(
+        &self,
+        request: Request<proto::StartContainerRequest>,
+    ) -> GrpcResult<proto::StartContainerResponse> {
+        let id = self.container_id(&request.into_inner().id).await?;
+        self.state
+            .runtime
+            .start_container(id)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::StartContainerResponse {
+            status: "started".to_string(),
+        }))
+    }
+
+    async fn stop_container(
+        &self,
+        request: Request<proto::StopContainerRequest>,
+    ) -> GrpcResult<proto::StopContainerResponse> {
+        let id = self.container_id(&request.into_inner().id).await?;
+        self.state
+            .runtime
+            .stop_container(id)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::StopContainerResponse {
+            status: "stopped".to_string(),
+        }))
+    }
+
+    async fn remove_container(
+        &self,
+        request: Request<proto::RemoveContainerRequest>,
+    ) -> GrpcResult<proto::RemoveContainerResponse> {
+        let id = self.container_id(&request.into_inner().id).await?;
+        self.state
+            .runtime
+            .remove_container(id)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::RemoveContainerResponse {
+            status: "removed".to_string(),
+        }))
+    }
+
+    async fn pause_container(
+        &self,
+        request: Request<proto::PauseContainerRequest>,
+    ) -> GrpcResult<proto::PauseContainerResponse> {
+        let id = self.container_id(&request.into_inner().id).await?;
+        self.state
+            .runtime
+            .pause_container(id)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::PauseContainerResponse {
+            status: "paused".to_string(),
+        }))
+    }
+
+    async fn unpause_container(
+        &self,
+        request: Request<proto::UnpauseContainerRequest>,
+    ) -> GrpcResult<proto::UnpauseContainerResponse> {
+        let id = self.container_id(&request.into_inner().id).await?;
+        self.state
+            .runtime
+            .unpause_container(id)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::UnpauseContainerResponse {
+            status: "unpaused".to_string(),
+        }))
+    }
+
+    async fn get_container_stats(
+        &self,
+        request: Request<proto::GetContainerStatsRequest>,
+    ) -> GrpcResult<proto::ContainerStats> {
+        let id = request.into_inner().id;
+        let key = self.container_id(&id).await?.to_string();
+        let metrics = self
+            .state
+            .stats
+            .get_metrics(&key)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .ok_or_else(|| {
+                Status::not_found(format!("Nenhuma amostra coletada do container '{}'", id))
+            })?;
+        Ok(Response::new(stats_message(&metrics)))
+    }
+
+    type StatsStreamStream =
+        Pin<Box<dyn Stream<Item = std::result::Result<proto::ContainerStats, Status>> + Send>>;
+
+    /// Cada amostra do coletor é enviada assim que registrada; o stream
+    /// termina quando a coleta do container para
+    async fn stats_stream(
+        &self,
+        request: Request<proto::StatsStreamRequest>,
+    ) -> GrpcResult<Self::StatsStreamStream> {
+        let key = self
+            .container_id(&request.into_inner().id)
+            .await?
+            .to_string();
+        let receiver = self.state.stats.subscribe(&key).await;
+        // Um cliente lento perde as amostras mais antigas em vez de atrasar
+        // o coletor
+        let stream = BroadcastStream::new(receiver)
+            .filter_map(|sample| sample.ok().map(|metrics| Ok(stats_message(&metrics))));
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+pub struct GrpcDeploymentService {
+    state: AppState,
+}
+
+impl GrpcDeploymentService {
+    async fn find(
+        &self,
+        name: &str,
+        namespace: &str,
+    ) -> std::result::Result<Option<DeploymentStatusResult>, ApiError> {
+        Ok(self
+            .state
+            .orchestrator
+            .get_deployment_status(name, namespace)
+            .await?)
+    }
+
+    async fn existing(
+        &self,
+        name: &str,
+        namespace: &str,
+    ) -> std::result::Result<DeploymentStatusResult, ApiError> {
+        self.find(name, namespace).await?.ok_or_else(|| {
+            ApiError::not_found(format!(
+                "Deployment '{}' não encontrado no namespace '{}'",
+                name, namespace
+            ))
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl DeploymentService for GrpcDeploymentService {
+    async fn deploy(
+        &self,
+        request: Request<proto::DeployRequest>,
+    ) -> GrpcResult<proto::DeploymentStatus> {
+        let request = request.into_inner();
+        if request.name.is_empty() || request.image.is_empty() {
+            return Err(ApiError::bad_request("name e image são obrigatórios").into());
+        }
+        let namespace = namespace_or_default(request.namespace);
+        if self.find(&request.name, &namespace).await?.is_some() {
+            return Err(ApiError::conflict(format!(
+                "Deployment '{}' já existe no namespace '{}'",
+                request.name, namespace
+            ))
+            .into());
+        }
+
+        // Campos omitidos ficam com os padrões do DeploymentSpec
+        let mut spec = serde_json::json!({
+            "name": request.name,
+            "namespace": namespace,
+            "image": request.image,
+            "env_vars": request.env_vars,
+            "labels": request.labels,
+        });
+        if let Some(replicas) = request.replicas {
+            spec["replicas"] = replicas.into();
+        }
+        let spec: DeploymentSpec = serde_json::from_value(spec)
+            .map_err(|e| ApiError::bad_request(format!("DeploymentSpec inválido: {}", e)))?;
+        let status = self.state.orchestrator.deploy(spec).await.map_err(status)?;
+        Ok(Response::new(deployment_message(&status)))
+    }
+
+    async fn scale_deployment(
+        &self,
+        request: Request<proto::ScaleDeploymentRequest>,
+    ) -> GrpcResult<proto::DeploymentStatus> {
+        let request = request.into_inner();
+        let namespace = namespace_or_default(request.namespace);
+        self.existing(&request.name, &namespace).await?;
+        self.state
+            .orchestrator
+            .scale_deployment(&request.name, &namespace, request.replicas)
+            .await
+            .map_err(status)?;
+        let status = self.existing(&request.name, &namespace).await?;
+        Ok(Response::new(deployment_message(&status)))
+    }
+
+    async fn get_deployment_status(
+        &self,
+        request: Request<proto::GetDeploymentStatusRequest>,
+    ) -> GrpcResult<proto::DeploymentStatus> {
+        let request = request.into_inner();
+        let namespace = namespace_or_default(request.namespace);
+        let status = self.existing(&request.name, &namespace).await?;
+        Ok(Response::new(deployment_message(&status)))
+    }
+
+    async fn list_deployments(
+        &self,
+        request: Request<proto::ListDeploymentsRequest>,
+    ) -> GrpcResult<proto::ListDeploymentsResponse> {
+        let namespace = request.into_inner().namespace;
+        let namespace = (!namespace.is_empty()).then_some(namespace.as_str());
+        let mut deployments = self
+            .state
+            .orchestrator
+            .list_deployments(namespace)
+            .await
+            .map_err(status)?;
+        deployments.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+        Ok(Response::new(proto::ListDeploymentsResponse {
+            deployments: deployments.iter().map(deployment_message).collect(),
+        }))
+    }
+
+    async fn delete_deployment(
+        &self,
+        request: Request<proto::DeleteDeploymentRequest>,
+    ) -> GrpcResult<proto::DeleteDeploymentResponse> {
+        let request = request.into_inner();
+        let namespace = namespace_or_default(request.namespace);
+        self.existing(&request.name, &namespace).await?;
+        self.state
+            .orchestrator
+            .delete_deployment(&request.name, &namespace)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::DeleteDeploymentResponse {
+            status: "deleted".to_string(),
+        }))
+    }
+}
+
+fn namespace_or_default(namespace: String) -> String {
+    if namespace.is_empty() {
+        "default".to_string()
+    } else {
+        namespace
+    }
+}
+
+fn container_message(container: &Container) -> proto::Container {
+    let time = |time: Option<chrono::DateTime<chrono::Utc>>| {
+        time.map(|time| time.to_rfc3339()).unwrap_or_default()
+    };
+    let limits = &container.resource_limits;
+    let network_mode = match &container.network_mode {
+        NetworkMode::Bridge => ("bridge".to_string(), None),
+        NetworkMode::Host => ("host".to_string(), None),
+        NetworkMode::None => ("none".to_string(), None),
+        NetworkMode::Container(id) => ("container".to_string(), Some(id.to_string())),
+        NetworkMode::Custom(name) => (name.clone(), None),
+    };
+    proto::Container {
+        id: container.id.to_string(),
+        name: container.name.clone(),
+        image: container.image.0.clone(),
+        status: format!("{:?}", container.status),
+        created_at: container.created_at.to_rfc3339(),
+        started_at: time(container.started_at),
+        finished_at: time(container.finished_at),
+        exit_code: container.exit_code.unwrap_or_default(),
+        command: container.command.clone(),
+        working_dir: container.working_dir.to_string_lossy().into_owned(),
+        environment: container.environment.clone(),
+        labels: container.labels.clone(),
+        resource_limits: Some(proto::ResourceLimits {
+            memory_limit: limits.memory_limit,
+            memory_swap: limits.memory_swap,
+            cpu_quota: limits.cpu_quota,
+            cpu_period: limits.cpu_period,
+            pids_limit: limits.pids_limit,
+            disk_quota: limits.disk_quota,
+        }),
+        network_mode: Some(proto::NetworkMode {
+            mode: network_mode.0,
+            container_id: network_mode.1,
+        }),
+        ports: container
+            .ports
+            .iter()
+            .map(|port| proto::PortMapping {
+                host_port: port.host_port.to_string(),
+                container_port: port.container_port.to_string(),
+                protocol: format!("{:?}", port.protocol).to_lowercase(),
+            })
+            .collect(),
+        volumes: container
+            .volumes
+            .iter()
+            .map(|volume| proto::VolumeMount {
+                source: volume.source.clone(),
+                target: volume.destination.to_string_lossy().into_owned(),
+                mode: format!("{:?}", volume.mode).to_lowercase(),
+            })
+            .collect(),
+    }
+}
+
+fn stats_message(metrics: &ContainerMetrics) -> proto::ContainerStats {
+    proto::ContainerStats {
+        container_id: metrics.container_id.clone(),
+        timestamp_ms: metrics
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default(),
+        cpu_percent: metrics.cpu.usage_percent,
+        memory_usage: metrics.memory.usage,
+        memory_limit: metrics.memory.limit,
+        memory_percent: metrics.memory.usage_percent,
+        network_rx_bytes: metrics.network.rx_bytes,
+        network_tx_bytes: metrics.network.tx_bytes,
+        disk_read_bytes: metrics.disk.read_bytes,
+        disk_write_bytes: metrics.disk.write_bytes,
+        process_count: metrics.processes.process_count,
+    }
+}
+
+fn deployment_message(status: &DeploymentStatusResult) -> proto::DeploymentStatus {
+    proto::DeploymentStatus {
+        name: status.name.clone(),
+        namespace: status.namespace.clone(),
+        desired_replicas: status.desired_replicas,
+        current_replicas: status.current_replicas,
+        ready_replicas: status.ready_replicas,
+        available_replicas: status.available_replicas,
+        status: format!("{:?}", status.status),
+        reason: status.reason.clone(),
+        created_at: status.created_at.to_rfc3339(),
+        updated_at: status.updated_at.to_rfc3339(),
+    }
+}
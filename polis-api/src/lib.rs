@@ -1,7 +1,17 @@
+pub mod api_error;
+pub mod app_state;
+pub mod auth_layer;
 pub mod auth_routes;
+pub mod deployment_routes;
 pub mod grpc;
 pub mod rest;
+pub mod v1_routes;
 
+pub use api_error::*;
+pub use app_state::*;
+pub use auth_layer::*;
 pub use auth_routes::*;
+pub use deployment_routes::*;
 pub use grpc::*;
 pub use rest::*;
+pub use v1_routes::*;
@@ -0,0 +1,336 @@
+use crate::{ApiError, AppState, AuthLayer};
+use hyper::body::Bytes;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Method, Request, Response, StatusCode};
+use polis_core::{ContainerId, PolisError, Result};
+use polis_orchestrator::DeploymentSpec;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Itens por página quando `per_page` não é informado
+pub const DEFAULT_PER_PAGE: usize = 20;
+
+/// Maior `per_page` aceito; valores acima são reduzidos a este
+pub const MAX_PER_PAGE: usize = 100;
+
+/// Página pedida por `?page=&per_page=`, começando em 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    pub page: usize,
+    pub per_page: usize,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            page: 1,
+            per_page: DEFAULT_PER_PAGE,
+        }
+    }
+}
+
+impl Pagination {
+    pub fn from_query(query: &HashMap<String, String>) -> std::result::Result<Self, ApiError> {
+        let parse = |key: &str, default: usize| match query.get(key) {
+            None => Ok(default),
+            Some(value) => match value.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(ApiError::bad_request(format!(
+                    "{} deve ser um inteiro positivo: '{}'",
+                    key, value
+                ))),
+            },
+        };
+        Ok(Self {
+            page: parse("page", 1)?,
+            per_page: parse("per_page", DEFAULT_PER_PAGE)?.min(MAX_PER_PAGE),
+        })
+    }
+
+    /// Recorta a página de `items`; uma página além da última vem vazia
+    pub fn apply<T>(&self, items: Vec<T>) -> Page<T> {
+        let total = items.len();
+        let items = items
+            .into_iter()
+            .skip((self.page - 1).saturating_mul(self.per_page))
+            .take(self.per_page)
+            .collect();
+        Page {
+            items,
+            page: self.page,
+            per_page: self.per_page,
+            total,
+            total_pages: total.div_ceil(self.per_page),
+        }
+    }
+}
+
+/// Resposta dos endpoints de listagem
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: usize,
+    pub per_page: usize,
+    /// Total de itens em todas as páginas
+    pub total: usize,
+    pub total_pages: usize,
+}
+
+/// Corpo de `POST /v1/deployments/{name}/scale`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaleRequest {
+    pub replicas: u32,
+}
+
+/// Rotas `/v1` para estatísticas, deployments e health checks. Erros são
+/// respondidos no formato de `ApiError`.
+pub struct V1Routes {
+    state: AppState,
+    auth: Option<AuthLayer>,
+}
+
+impl V1Routes {
+    pub fn new(state: AppState) -> Self {
+        Self { state, auth: None }
+    }
+
+    /// Exige token e permissão em cada requisição
+    pub fn with_auth(mut self, auth: AuthLayer) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// `GET /v1/containers/{id}`,
+    /// `GET /v1/containers/{id}/stats[?history=N]`,
+    /// `GET /v1/deployments[?namespace=&page=&per_page=]`,
+    /// `POST /v1/deployments` (DeploymentSpec em JSON no corpo),
+    /// `POST /v1/deployments/{name}/scale[?namespace=]`,
+    /// `GET /v1/health/checks[?page=&per_page=]` e
+    /// `POST /v1/health/checks/{id}/run`
+    ///
+    /// O `{id}` de container aceita nome, ID completo ou prefixo de ID.
+    pub async fn handle_request(&self, mut req: Request<Bytes>) -> Result<Response<Bytes>> {
+        if let Some(auth) = &self.auth {
+            if let Err(error) = auth.authorize(&mut req) {
+                return Ok(error.into_response());
+            }
+        }
+        let query = parse_query(req.uri().query());
+        let segments: Vec<&str> = req.uri().path().trim_matches('/').split('/').collect();
+        let result = match (req.method(), segments.as_slice()) {
+            (&Method::GET, ["v1", "containers", id]) => self.inspect_container(id).await,
+            (&Method::GET, ["v1", "containers", id, "stats"]) => {
+                self.container_stats(id, &query).await
+            }
+            (&Method::GET, ["v1", "deployments"]) => self.list_deployments(&query).await,
+            (&Method::POST, ["v1", "deployments"]) => self.create_deployment(req.body()).await,
+            (&Method::POST, ["v1", "deployments", name, "scale"]) => {
+                self.scale_deployment(name, &query, req.body()).await
+            }
+            (&Method::GET, ["v1", "health", "checks"]) => self.list_health_checks(&query).await,
+            (&Method::POST, ["v1", "health", "checks", id, "run"]) => {
+                self.run_health_check(id).await
+            }
+            _ => Err(ApiError::not_found("Endpoint não encontrado")),
+        };
+        Ok(result.unwrap_or_else(ApiError::into_response))
+    }
+
+    /// Container referenciado por nome, ID ou prefixo de ID
+    async fn resolve_container(
+        &self,
+        reference: &str,
+    ) -> std::result::Result<ContainerId, ApiError> {
+        match self.state.runtime.resolve(reference).await {
+            Ok(id) => Ok(id),
+            Err(error @ PolisError::Ambiguous { .. }) => Err(error.into()),
+            Err(_) => Err(ApiError::not_found(format!(
+                "Container '{}' não encontrado",
+                reference
+            ))),
+        }
+    }
+
+    async fn inspect_container(&self, id: &str) -> std::result::Result<Response<Bytes>, ApiError> {
+        let container_id = self.resolve_container(id).await?;
+        let mut inspect = self
+            .state
+            .runtime
+            .inspect_container(container_id.clone())
+            .await
+            .map_err(|_| ApiError::not_found(format!("Container '{}' não encontrado", id)))?;
+
+        let current = self
+            .state
+            .stats
+            .get_metrics(&container_id.to_string())
+            .await
+            .map_err(|e| ApiError::internal(e.to_string()))?;
+        inspect.stats = current.map(|metrics| serde_json::json!(metrics));
+        Ok(json(StatusCode::OK, serde_json::json!(inspect)))
+    }
+
+    async fn container_stats(
+        &self,
+        id: &str,
+        query: &HashMap<String, String>,
+    ) -> std::result::Result<Response<Bytes>, ApiError> {
+        let container_id = self.resolve_container(id).await?;
+        self.state
+            .runtime
+            .get_container(container_id.clone())
+            .await
+            .map_err(|_| ApiError::not_found(format!("Container '{}' não encontrado", id)))?;
+
+        let key = container_id.to_string();
+        let current = self
+            .state
+            .stats
+            .get_metrics(&key)
+            .await
+            .map_err(|e| ApiError::internal(e.to_string()))?;
+        let mut body = serde_json::json!({ "container_id": key, "current": current });
+
+        // `history=0` devolve todas as amostras retidas
+        if let Some(history) = query.get("history") {
+            let limit = history.parse::<usize>().map_err(|_| {
+                ApiError::bad_request(format!("history deve ser um inteiro: '{}'", history))
+            })?;
+            let samples = self
+                .state
+                .stats
+                .get_metrics_history(&key, None, limit)
+                .await
+                .map_err(|e| ApiError::internal(e.to_string()))?;
+            body["history"] = serde_json::json!(samples);
+        }
+        Ok(json(StatusCode::OK, body))
+    }
+
+    async fn list_deployments(
+        &self,
+        query: &HashMap<String, String>,
+    ) -> std::result::Result<Response<Bytes>, ApiError> {
+        let pagination = Pagination::from_query(query)?;
+        let namespace = query.get("namespace").filter(|ns| !ns.is_empty());
+        let mut deployments = self
+            .state
+            .orchestrator
+            .list_deployments(namespace.map(String::as_str))
+            .await?;
+        // Ordem estável para que as páginas não se sobreponham
+        deployments.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+        Ok(json(
+            StatusCode::OK,
+            serde_json::json!(pagination.apply(deployments)),
+        ))
+    }
+
+    async fn create_deployment(
+        &self,
+        body: &Bytes,
+    ) -> std::result::Result<Response<Bytes>, ApiError> {
+        let spec: DeploymentSpec = serde_json::from_slice(body)
+            .map_err(|e| ApiError::bad_request(format!("DeploymentSpec inválido: {}", e)))?;
+        let orchestrator = &self.state.orchestrator;
+        if orchestrator
+            .get_deployment_status(&spec.name, &spec.namespace)
+            .await?
+            .is_some()
+        {
+            return Err(ApiError::conflict(format!(
+                "Deployment '{}' já existe no namespace '{}'",
+                spec.name, spec.namespace
+            )));
+        }
+        let status = orchestrator.deploy(spec).await?;
+        Ok(json(StatusCode::CREATED, serde_json::json!(status)))
+    }
+
+    async fn scale_deployment(
+        &self,
+        name: &str,
+        query: &HashMap<String, String>,
+        body: &Bytes,
+    ) -> std::result::Result<Response<Bytes>, ApiError> {
+        let request: ScaleRequest = serde_json::from_slice(body)
+            .map_err(|e| ApiError::bad_request(format!("Corpo de escala inválido: {}", e)))?;
+        let namespace = query
+            .get("namespace")
+            .map(String::as_str)
+            .unwrap_or("default");
+        let orchestrator = &self.state.orchestrator;
+        let not_found = || {
+            ApiError::not_found(format!(
+                "Deployment '{}' não encontrado no namespace '{}'",
+                name, namespace
+            ))
+        };
+        if orchestrator
+            .get_deployment_status(name, namespace)
+            .await?
+            .is_none()
+        {
+            return Err(not_found());
+        }
+
+        orchestrator
+            .scale_deployment(name, namespace, request.replicas)
+            .await?;
+        let status = orchestrator
+            .get_deployment_status(name, namespace)
+            .await?
+            .ok_or_else(not_found)?;
+        Ok(json(StatusCode::OK, serde_json::json!(status)))
+    }
+
+    async fn list_health_checks(
+        &self,
+        query: &HashMap<String, String>,
+    ) -> std::result::Result<Response<Bytes>, ApiError> {
+        let pagination = Pagination::from_query(query)?;
+        let mut checks = self.state.health.list_health_checks().await;
+        checks.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(json(
+            StatusCode::OK,
+            serde_json::json!(pagination.apply(checks)),
+        ))
+    }
+
+    async fn run_health_check(&self, id: &str) -> std::result::Result<Response<Bytes>, ApiError> {
+        let health = &self.state.health;
+        if health.get_health_check(id).await.is_none() {
+            return Err(ApiError::not_found(format!(
+                "Health check '{}' não encontrado",
+                id
+            )));
+        }
+        let result = health
+            .run_health_check(id)
+            .await
+            .map_err(|e| ApiError::internal(e.to_string()))?;
+        Ok(json(StatusCode::OK, serde_json::json!(result)))
+    }
+}
+
+/// Parâmetros `chave=valor` da query string; chaves repetidas ficam com o
+/// último valor
+fn parse_query(query: Option<&str>) -> HashMap<String, String> {
+    query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (pair.to_string(), String::new()),
+        })
+        .collect()
+}
+
+pub(crate) fn json(status: StatusCode, body: serde_json::Value) -> Response<Bytes> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Bytes::from(body.to_string()))
+        .unwrap()
+}
@@ -0,0 +1,229 @@
+use hyper::body::Bytes;
+use hyper::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use hyper::{Method, Request, Response, StatusCode};
+use polis_api::{AppState, AuthLayer, V1Routes};
+use polis_auth::{JwtClaims, JwtManager, UserSession};
+use polis_orchestrator::{HealthMonitor, Orchestrator, OrchestratorConfig};
+use polis_stats::ContainerStatsCollector;
+use polis_test_support::FakeRuntime;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Arc;
+
+const SECRET: &str = "test-secret";
+
+fn token(secret: &str, roles: &[&str], permissions: &[&str], expires_in: i64) -> String {
+    let claims = JwtClaims {
+        sub: uuid::Uuid::new_v4().to_string(),
+        username: "alice".to_string(),
+        permissions: permissions.iter().map(|p| p.to_string()).collect(),
+        roles: roles.iter().map(|r| r.to_string()).collect(),
+        exp: (chrono::Utc::now().timestamp() + expires_in) as usize,
+        iat: None,
+    };
+    JwtManager::new(secret.to_string())
+        .generate_token(&claims)
+        .unwrap()
+}
+
+fn auth_layer() -> AuthLayer {
+    AuthLayer::new(Arc::new(JwtManager::new(SECRET.to_string())))
+}
+
+async fn routes(state_dir: &Path) -> V1Routes {
+    let runtime = Arc::new(FakeRuntime::new());
+    let config = OrchestratorConfig {
+        state_dir: state_dir.to_path_buf(),
+        ..Default::default()
+    };
+    let orchestrator = Orchestrator::new(config, runtime.clone()).await.unwrap();
+    let state = AppState::new(
+        runtime,
+        Arc::new(ContainerStatsCollector::default()),
+        Arc::new(orchestrator),
+        Arc::new(HealthMonitor::new()),
+    );
+    V1Routes::new(state).with_auth(auth_layer())
+}
+
+fn request(method: Method, uri: &str, token: Option<&str>, body: &str) -> Request<Bytes> {
+    let mut builder = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        builder = builder.header(AUTHORIZATION, format!("Bearer {}", token));
+    }
+    builder.body(Bytes::from(body.to_string())).unwrap()
+}
+
+fn error_body(response: &Response<Bytes>) -> Value {
+    serde_json::from_slice(response.body()).unwrap()
+}
+
+#[tokio::test]
+async fn test_valid_token_reaches_routes() {
+    let temp = tempfile::tempdir().unwrap();
+    let routes = routes(temp.path()).await;
+
+    // Permissions granted through the viewer role
+    let viewer = token(SECRET, &["viewer"], &[], 3600);
+    let response = routes
+        .handle_request(request(Method::GET, "/v1/deployments", Some(&viewer), ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Permissions listed directly in the token
+    let writer = token(SECRET, &[], &["deployments:write"], 3600);
+    let spec = r#"{"name": "web", "image": "nginx:1.25", "replicas": 1}"#;
+    let response = routes
+        .handle_request(request(
+            Method::POST,
+            "/v1/deployments",
+            Some(&writer),
+            spec,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_expired_and_forged_tokens() {
+    let temp = tempfile::tempdir().unwrap();
+    let routes = routes(temp.path()).await;
+
+    let expired = token(SECRET, &["admin"], &[], -3600);
+    let response = routes
+        .handle_request(request(Method::GET, "/v1/deployments", Some(&expired), ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body = error_body(&response);
+    assert_eq!(body["error"]["code"], "token_expired");
+    let challenge = response.headers()[WWW_AUTHENTICATE].to_str().unwrap();
+    assert!(challenge.starts_with("Bearer"));
+    assert!(challenge.contains("error=\"invalid_token\""));
+
+    let forged = token("other-secret", &["admin"], &[], 3600);
+    let response = routes
+        .handle_request(request(Method::GET, "/v1/deployments", Some(&forged), ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let forged_body = error_body(&response);
+    assert_eq!(forged_body["error"]["code"], "invalid_signature");
+    assert_ne!(forged_body["error"]["message"], body["error"]["message"]);
+
+    let response = routes
+        .handle_request(request(Method::GET, "/v1/deployments", None, ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(error_body(&response)["error"]["code"], "unauthorized");
+    assert_eq!(
+        response.headers()[WWW_AUTHENTICATE],
+        "Bearer realm=\"polis\""
+    );
+}
+
+#[tokio::test]
+async fn test_missing_permission_is_forbidden() {
+    let temp = tempfile::tempdir().unwrap();
+    let routes = routes(temp.path()).await;
+    let viewer = token(SECRET, &["viewer"], &[], 3600);
+
+    let spec = r#"{"name": "web", "image": "nginx:1.25"}"#;
+    let response = routes
+        .handle_request(request(
+            Method::POST,
+            "/v1/deployments",
+            Some(&viewer),
+            spec,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = error_body(&response);
+    assert_eq!(body["error"]["code"], "forbidden");
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("deployments:write"));
+    assert!(!response.headers().contains_key(WWW_AUTHENTICATE));
+
+    let reader = token(SECRET, &[], &["deployments:read"], 3600);
+    let uri = format!("/v1/containers/{}/stats", uuid::Uuid::new_v4());
+    let response = routes
+        .handle_request(request(Method::GET, &uri, Some(&reader), ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_allowlisted_routes_skip_auth() {
+    let auth = auth_layer();
+    for uri in ["/healthz", "/metrics", "/metrics/system"] {
+        let response = auth
+            .handle(request(Method::GET, uri, None, ""), |req| async move {
+                assert!(req.extensions().get::<UserSession>().is_none());
+                Ok(Response::new(Bytes::from("ok")))
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+    }
+
+    // Only whole path segments match the allowlist
+    let response = auth
+        .handle(request(Method::GET, "/metricsx", None, ""), |_| async {
+            Ok(Response::new(Bytes::from("ok")))
+        })
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Authorized requests carry the user session
+    let admin = token(SECRET, &["admin"], &[], 3600);
+    let response = auth
+        .handle(
+            request(Method::DELETE, "/v1/containers/abc", Some(&admin), ""),
+            |req| async move {
+                let session = req.extensions().get::<UserSession>().unwrap();
+                assert_eq!(session.username, "alice");
+                assert!(session
+                    .permissions
+                    .contains(&"containers:write".to_string()));
+                Ok(Response::new(Bytes::new()))
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn test_required_permissions() {
+    let auth = auth_layer().require(Some(Method::POST), "/v1/health/checks", "system:admin");
+    let cases = [
+        (Method::GET, "/v1/containers", "containers:read"),
+        (Method::POST, "/v1/containers", "containers:write"),
+        (Method::DELETE, "/v1/containers/abc", "containers:write"),
+        (Method::GET, "/v1/containers/abc/stats", "stats:read"),
+        (
+            Method::POST,
+            "/v1/deployments/web/scale",
+            "deployments:write",
+        ),
+        (Method::GET, "/v1/health/checks", "health:read"),
+        (Method::POST, "/v1/health/checks/a/run", "system:admin"),
+    ];
+    for (method, path, permission) in cases {
+        assert_eq!(
+            auth.required_permission(&method, path).as_deref(),
+            Some(permission),
+            "{} {}",
+            method,
+            path
+        );
+    }
+}
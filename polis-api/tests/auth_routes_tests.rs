@@ -0,0 +1,231 @@
+use hyper::body::Bytes;
+use hyper::header::{AUTHORIZATION, RETRY_AFTER};
+use hyper::{Method, Request, Response, StatusCode};
+use polis_api::{AuthRoutes, TokenResponse};
+use polis_auth::{refresh_token_store_path, AuthManager, JwtManager};
+use polis_core::{ManualClock, PolisConfig};
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+const SECRET: &str = "test-secret";
+const PASSWORD: &str = "correct horse battery staple";
+
+fn config(root_dir: &Path) -> PolisConfig {
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = root_dir.to_path_buf();
+    config.storage.root_dir = root_dir.join("storage");
+    config.auth.jwt_secret = SECRET.to_string();
+    config.auth.access_token_ttl = 600;
+    config.auth.max_login_attempts = 3;
+    config.auth.lockout_seconds = 60;
+    config
+}
+
+async fn auth_manager(config: &PolisConfig) -> Arc<RwLock<AuthManager>> {
+    let mut manager = AuthManager::from_config(&config.auth).unwrap();
+    for (username, email) in [("alice", "alice@example.com"), ("bob", "bob@example.com")] {
+        manager
+            .user_manager
+            .create_user(
+                username.to_string(),
+                email.to_string(),
+                PASSWORD.to_string(),
+            )
+            .await
+            .unwrap();
+    }
+    Arc::new(RwLock::new(manager))
+}
+
+fn post(uri: &str, body: Value, peer: Option<&str>) -> Request<Bytes> {
+    let mut req = Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .body(Bytes::from(body.to_string()))
+        .unwrap();
+    if let Some(peer) = peer {
+        req.extensions_mut()
+            .insert(peer.parse::<SocketAddr>().unwrap());
+    }
+    req
+}
+
+async fn login(routes: &AuthRoutes, username: &str, password: &str) -> Response<Bytes> {
+    routes
+        .handle_request(post(
+            "/v1/auth/login",
+            serde_json::json!({"username": username, "password": password}),
+            Some("10.0.0.7:50000"),
+        ))
+        .await
+        .unwrap()
+}
+
+async fn refresh(routes: &AuthRoutes, refresh_token: &str) -> Response<Bytes> {
+    routes
+        .handle_request(post(
+            "/v1/auth/refresh",
+            serde_json::json!({ "refresh_token": refresh_token }),
+            None,
+        ))
+        .await
+        .unwrap()
+}
+
+fn tokens(response: &Response<Bytes>) -> TokenResponse {
+    assert_eq!(response.status(), StatusCode::OK);
+    serde_json::from_slice(response.body()).unwrap()
+}
+
+fn error_code(response: &Response<Bytes>) -> String {
+    let body: Value = serde_json::from_slice(response.body()).unwrap();
+    body["error"]["code"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_login_issues_access_and_refresh_tokens() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = config(temp.path());
+    let routes = AuthRoutes::new(auth_manager(&config).await, &config).unwrap();
+
+    let issued = tokens(&login(&routes, "alice", PASSWORD).await);
+    assert_eq!(issued.token_type, "Bearer");
+    assert_eq!(issued.expires_in, 600);
+
+    let claims = JwtManager::new(SECRET.to_string())
+        .verify_token(&issued.access_token)
+        .unwrap();
+    assert_eq!(claims.username, "alice");
+    let lifetime = claims.exp as i64 - claims.iat.unwrap() as i64;
+    assert!((599..=601).contains(&lifetime));
+
+    // Only a hash of the refresh token is written to disk
+    let stored = std::fs::read_to_string(refresh_token_store_path(temp.path())).unwrap();
+    assert!(stored.contains("alice"));
+    assert!(!stored.contains(&issued.refresh_token));
+
+    let me = Request::builder()
+        .uri("/v1/auth/me")
+        .header(AUTHORIZATION, format!("Bearer {}", issued.access_token))
+        .body(Bytes::new())
+        .unwrap();
+    let response = routes.handle_request(me).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(body["username"], "alice");
+
+    let response = login(&routes, "alice", "wrong").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(error_code(&response), "invalid_credentials");
+    let response = login(&routes, "nobody", PASSWORD).await;
+    assert_eq!(error_code(&response), "invalid_credentials");
+}
+
+#[tokio::test]
+async fn test_refresh_rotates_and_invalidates_old_token() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = config(temp.path());
+    let manager = auth_manager(&config).await;
+    let routes = AuthRoutes::new(manager.clone(), &config).unwrap();
+
+    let first = tokens(&login(&routes, "alice", PASSWORD).await);
+    let second = tokens(&refresh(&routes, &first.refresh_token).await);
+    assert_ne!(second.refresh_token, first.refresh_token);
+    let claims = JwtManager::new(SECRET.to_string())
+        .verify_token(&second.access_token)
+        .unwrap();
+    assert_eq!(claims.username, "alice");
+
+    // A refresh token is good for a single use
+    let response = refresh(&routes, &first.refresh_token).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(error_code(&response), "invalid_grant");
+
+    // Rotated tokens survive a restart
+    let restarted = AuthRoutes::new(manager, &config).unwrap();
+    let third = tokens(&refresh(&restarted, &second.refresh_token).await);
+    assert_ne!(third.refresh_token, second.refresh_token);
+}
+
+#[tokio::test]
+async fn test_revoked_refresh_token_is_unusable() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = config(temp.path());
+    let routes = AuthRoutes::new(auth_manager(&config).await, &config).unwrap();
+
+    let issued = tokens(&login(&routes, "alice", PASSWORD).await);
+    let revoke = |token: &str| {
+        post(
+            "/v1/auth/revoke",
+            serde_json::json!({ "refresh_token": token }),
+            None,
+        )
+    };
+    let response = routes
+        .handle_request(revoke(&issued.refresh_token))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = refresh(&routes, &issued.refresh_token).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(error_code(&response), "invalid_grant");
+
+    // Revoking an unknown token is not an error
+    let response = routes.handle_request(revoke("unknown")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_login_lockout_after_repeated_failures() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = config(temp.path());
+    let clock = ManualClock::new(chrono::Utc::now());
+    let routes = AuthRoutes::new(auth_manager(&config).await, &config)
+        .unwrap()
+        .with_clock(Arc::new(clock.clone()));
+
+    for _ in 0..3 {
+        let response = login(&routes, "alice", "wrong").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    // Locked out even with the right password
+    let response = login(&routes, "alice", PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(error_code(&response), "too_many_attempts");
+    assert_eq!(response.headers()[RETRY_AFTER], "60");
+
+    // The IP the failures came from is locked out too
+    let response = login(&routes, "bob", PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let from_elsewhere = routes
+        .handle_request(post(
+            "/v1/auth/login",
+            serde_json::json!({"username": "bob", "password": PASSWORD}),
+            Some("10.0.0.8:50000"),
+        ))
+        .await
+        .unwrap();
+    tokens(&from_elsewhere);
+
+    clock.advance(Duration::from_secs(61));
+    tokens(&login(&routes, "alice", PASSWORD).await);
+}
+
+#[test]
+fn test_token_issuance_requires_a_signing_key() {
+    let mut config = PolisConfig::default();
+    assert!(AuthManager::from_config(&config.auth).is_err());
+
+    config.auth.jwt_secret = SECRET.to_string();
+    let manager = AuthManager::from_config(&config.auth).unwrap();
+    assert_eq!(
+        manager.access_token_ttl,
+        chrono::Duration::seconds(config.auth.access_token_ttl as i64)
+    );
+}
@@ -0,0 +1,309 @@
+use hyper_util::rt::TokioIo;
+use polis_api::proto::container_service_client::ContainerServiceClient;
+use polis_api::proto::deployment_service_client::DeploymentServiceClient;
+use polis_api::proto::{
+    CreateContainerRequest, DeleteDeploymentRequest, DeployRequest, GetContainerRequest,
+    GetContainerStatsRequest, GetDeploymentStatusRequest, ListDeploymentsRequest,
+    ScaleDeploymentRequest, StartContainerRequest, StatsStreamRequest, StopContainerRequest,
+};
+use polis_api::{AppState, GrpcApi};
+use polis_core::{ContainerId, ContainerStatus};
+use polis_orchestrator::{HealthMonitor, Orchestrator, OrchestratorConfig};
+use polis_stats::{ContainerMetrics, ContainerStatsCollector, CpuMetrics};
+use polis_test_support::{FakeRuntime, Lifecycle};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::transport::{Channel, Endpoint, Server, Uri};
+use tonic::Code;
+use tower::service_fn;
+
+struct Harness {
+    channel: Channel,
+    runtime: Arc<FakeRuntime>,
+    state: AppState,
+}
+
+/// Serve both services over an in-memory duplex stream
+async fn harness(state_dir: &Path) -> Harness {
+    let runtime = Arc::new(FakeRuntime::new());
+    let config = OrchestratorConfig {
+        state_dir: state_dir.to_path_buf(),
+        ..Default::default()
+    };
+    let orchestrator = Orchestrator::new(config, runtime.clone()).await.unwrap();
+    let state = AppState::new(
+        runtime.clone(),
+        Arc::new(ContainerStatsCollector::default()),
+        Arc::new(orchestrator),
+        Arc::new(HealthMonitor::new()),
+    );
+
+    let api = GrpcApi::new(state.clone());
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(api.container_service())
+            .add_service(api.deployment_service())
+            .serve_with_incoming(tokio_stream::once(Ok::<_, std::io::Error>(server_io)))
+            .await
+    });
+
+    let mut client_io = Some(client_io);
+    let channel = Endpoint::try_from("http://in-process")
+        .unwrap()
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let io = client_io.take();
+            async move {
+                io.map(TokioIo::new)
+                    .ok_or_else(|| std::io::Error::other("duplex already connected"))
+            }
+        }))
+        .await
+        .unwrap();
+
+    Harness {
+        channel,
+        runtime,
+        state,
+    }
+}
+
+fn sample(id: &str, cpu: f64) -> ContainerMetrics {
+    ContainerMetrics {
+        container_id: id.to_string(),
+        cpu: CpuMetrics {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_container_lifecycle_with_stats_stream() {
+    let temp = tempfile::tempdir().unwrap();
+    let h = harness(temp.path()).await;
+    let mut client = ContainerServiceClient::new(h.channel.clone());
+
+    let created = client
+        .create_container(CreateContainerRequest {
+            name: "web".to_string(),
+            image: "nginx:1.25".to_string(),
+            command: vec!["nginx".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(created.name, "web");
+    let id = created.id;
+
+    client
+        .start_container(StartContainerRequest { id: id.clone() })
+        .await
+        .unwrap();
+    assert_eq!(h.runtime.containers()[0].status, ContainerStatus::Running);
+
+    let mut stream = client
+        .stats_stream(StatsStreamRequest { id: id.clone() })
+        .await
+        .unwrap()
+        .into_inner();
+
+    // Each sample recorded by the collector is pushed to the client
+    for cpu in [10.0, 20.0, 30.0] {
+        h.state
+            .stats
+            .update_metrics(&id, sample(&id, cpu))
+            .await
+            .unwrap();
+        let stats = tokio::time::timeout(Duration::from_secs(5), stream.message())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(stats.container_id, id);
+        assert_eq!(stats.cpu_percent, cpu);
+        assert!(stats.timestamp_ms > 0);
+    }
+
+    let current = client
+        .get_container_stats(GetContainerStatsRequest { id: id.clone() })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(current.cpu_percent, 30.0);
+
+    client
+        .stop_container(StopContainerRequest { id: id.clone() })
+        .await
+        .unwrap();
+    let container = client
+        .get_container(GetContainerRequest { id: id.clone() })
+        .await
+        .unwrap()
+        .into_inner()
+        .container
+        .unwrap();
+    assert_eq!(container.status, "Stopped");
+
+    // The stream ends once the collector stops sampling the container
+    h.state.stats.stop_collecting(&id).await.unwrap();
+    let end = tokio::time::timeout(Duration::from_secs(5), stream.message())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(end.is_none());
+}
+
+#[tokio::test]
+async fn test_container_errors_map_to_status_codes() {
+    let temp = tempfile::tempdir().unwrap();
+    let h = harness(temp.path()).await;
+    let mut client = ContainerServiceClient::new(h.channel.clone());
+    let missing = ContainerId::new().to_string();
+
+    let error = client
+        .start_container(StartContainerRequest {
+            id: missing.clone(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+    assert!(error.message().contains(&missing));
+
+    let error = client
+        .stats_stream(StatsStreamRequest {
+            id: missing.clone(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+
+    let error = client
+        .get_container(GetContainerRequest {
+            id: "not-a-uuid".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::InvalidArgument);
+
+    let error = client
+        .create_container(CreateContainerRequest {
+            name: "web".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::InvalidArgument);
+
+    // A container without samples has no current stats
+    let created = client
+        .create_container(CreateContainerRequest {
+            name: "web".to_string(),
+            image: "nginx:1.25".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let error = client
+        .get_container_stats(GetContainerStatsRequest { id: created.id })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+
+    // Runtime failures are internal errors
+    h.runtime.script("broken", Lifecycle::fail_start("boom"));
+    let created = client
+        .create_container(CreateContainerRequest {
+            name: "broken".to_string(),
+            image: "broken".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let error = client
+        .start_container(StartContainerRequest { id: created.id })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::Internal);
+    assert!(error.message().contains("boom"));
+}
+
+#[tokio::test]
+async fn test_deployment_service() {
+    let temp = tempfile::tempdir().unwrap();
+    let h = harness(temp.path()).await;
+    let mut client = DeploymentServiceClient::new(h.channel.clone());
+    let deploy = DeployRequest {
+        name: "api".to_string(),
+        image: "nginx:1.25".to_string(),
+        replicas: Some(2),
+        ..Default::default()
+    };
+
+    let status = client.deploy(deploy.clone()).await.unwrap().into_inner();
+    assert_eq!(status.namespace, "default");
+    assert_eq!(status.desired_replicas, 2);
+
+    let error = client.deploy(deploy).await.unwrap_err();
+    assert_eq!(error.code(), Code::AlreadyExists);
+
+    let status = client
+        .scale_deployment(ScaleDeploymentRequest {
+            name: "api".to_string(),
+            namespace: String::new(),
+            replicas: 3,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(status.desired_replicas, 3);
+    assert_eq!(h.runtime.containers().len(), 3);
+
+    let list = client
+        .list_deployments(ListDeploymentsRequest::default())
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(list.deployments.len(), 1);
+    assert_eq!(list.deployments[0].name, "api");
+
+    client
+        .delete_deployment(DeleteDeploymentRequest {
+            name: "api".to_string(),
+            namespace: "default".to_string(),
+        })
+        .await
+        .unwrap();
+    let error = client
+        .get_deployment_status(GetDeploymentStatusRequest {
+            name: "api".to_string(),
+            namespace: String::new(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+
+    let error = client
+        .scale_deployment(ScaleDeploymentRequest {
+            name: "missing".to_string(),
+            namespace: String::new(),
+            replicas: 1,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+
+    let error = client
+        .deploy(DeployRequest {
+            name: "web".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::InvalidArgument);
+}
@@ -0,0 +1,340 @@
+use hyper::body::Bytes;
+use hyper::{Method, Request, StatusCode};
+use polis_api::{ApiError, AppState, V1Routes, MAX_PER_PAGE};
+use polis_core::{ContainerId, PolisError};
+use polis_orchestrator::{
+    CheckType, HealthCheckDef, HealthMonitor, Orchestrator, OrchestratorConfig, TargetType,
+};
+use polis_runtime::ContainerRuntime;
+use polis_stats::{ContainerMetrics, ContainerStatsCollector, CpuMetrics};
+use polis_test_support::FakeRuntime;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Arc;
+
+struct Harness {
+    routes: V1Routes,
+    runtime: Arc<FakeRuntime>,
+    state: AppState,
+}
+
+async fn harness(state_dir: &Path) -> Harness {
+    let runtime = Arc::new(FakeRuntime::new());
+    let config = OrchestratorConfig {
+        state_dir: state_dir.to_path_buf(),
+        ..Default::default()
+    };
+    let orchestrator = Orchestrator::new(config, runtime.clone()).await.unwrap();
+    let state = AppState::new(
+        runtime.clone(),
+        Arc::new(ContainerStatsCollector::default()),
+        Arc::new(orchestrator),
+        Arc::new(HealthMonitor::new()),
+    );
+    Harness {
+        routes: V1Routes::new(state.clone()),
+        runtime,
+        state,
+    }
+}
+
+async fn call(routes: &V1Routes, method: Method, uri: &str, body: &str) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(Bytes::from(body.to_string()))
+        .unwrap();
+    let response = routes.handle_request(req).await.unwrap();
+    let body = serde_json::from_slice(response.body()).unwrap();
+    (response.status(), body)
+}
+
+fn error_code(body: &Value) -> &str {
+    body["error"]["code"].as_str().unwrap()
+}
+
+fn sample(id: &ContainerId, cpu: f64) -> ContainerMetrics {
+    ContainerMetrics {
+        container_id: id.to_string(),
+        cpu: CpuMetrics {
+            usage_percent: cpu,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_container_stats_current_and_history() {
+    let temp = tempfile::tempdir().unwrap();
+    let h = harness(temp.path()).await;
+    let id = h
+        .runtime
+        .create_container("web".to_string(), "nginx".to_string(), Vec::new())
+        .await
+        .unwrap();
+    for cpu in [10.0, 20.0, 30.0] {
+        h.state
+            .stats
+            .update_metrics(&id.to_string(), sample(&id, cpu))
+            .await
+            .unwrap();
+    }
+
+    let uri = format!("/v1/containers/{}/stats", id);
+    let (status, body) = call(&h.routes, Method::GET, &uri, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["current"]["cpu"]["usage_percent"], 30.0);
+    assert!(body.get("history").is_none());
+
+    let (status, body) = call(&h.routes, Method::GET, &format!("{}?history=2", uri), "").await;
+    assert_eq!(status, StatusCode::OK);
+    let history = body["history"].as_array().unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0]["cpu"]["usage_percent"], 20.0);
+
+    let (status, body) = call(&h.routes, Method::GET, &format!("{}?history=x", uri), "").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error_code(&body), "invalid_request");
+
+    for missing in [ContainerId::new().to_string(), "not-a-uuid".to_string()] {
+        let uri = format!("/v1/containers/{}/stats", missing);
+        let (status, body) = call(&h.routes, Method::GET, &uri, "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error_code(&body), "not_found");
+        assert!(body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains(&missing));
+    }
+}
+
+#[tokio::test]
+async fn test_container_inspect() {
+    let temp = tempfile::tempdir().unwrap();
+    let h = harness(temp.path()).await;
+    let id = h
+        .runtime
+        .create_container("web".to_string(), "nginx".to_string(), Vec::new())
+        .await
+        .unwrap();
+    h.runtime.start_container(id.clone()).await.unwrap();
+
+    let uri = format!("/v1/containers/{}", id);
+    let (status, body) = call(&h.routes, Method::GET, &uri, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["name"], "web");
+    assert_eq!(body["config"]["image"], "nginx");
+    assert_eq!(body["state"]["status"], "Running");
+    assert!(body["stats"].is_null());
+
+    // The name and an ID prefix address the same container
+    for reference in ["web".to_string(), id.to_string()[..8].to_string()] {
+        let uri = format!("/v1/containers/{}", reference);
+        let (status, body) = call(&h.routes, Method::GET, &uri, "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["id"], serde_json::json!(id));
+    }
+
+    // The latest sample is included once the container is monitored
+    h.state
+        .stats
+        .update_metrics(&id.to_string(), sample(&id, 42.0))
+        .await
+        .unwrap();
+    h.runtime.stop_container(id.clone()).await.unwrap();
+    let (status, body) = call(&h.routes, Method::GET, &uri, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["stats"]["cpu"]["usage_percent"], 42.0);
+    assert_eq!(body["state"]["exit_code"], 0);
+    assert!(body["state"]["finished_at"].is_string());
+
+    for missing in [ContainerId::new().to_string(), "not-a-uuid".to_string()] {
+        let uri = format!("/v1/containers/{}", missing);
+        let (status, body) = call(&h.routes, Method::GET, &uri, "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error_code(&body), "not_found");
+    }
+}
+
+#[tokio::test]
+async fn test_deployment_list_pagination() {
+    let temp = tempfile::tempdir().unwrap();
+    let h = harness(temp.path()).await;
+    for n in 0..5 {
+        let spec = format!(
+            r#"{{"name": "web-{}", "image": "nginx:1.25", "replicas": 1}}"#,
+            n
+        );
+        let (status, body) = call(&h.routes, Method::POST, "/v1/deployments", &spec).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["name"], format!("web-{}", n));
+    }
+
+    let (status, body) = call(&h.routes, Method::GET, "/v1/deployments", "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 5);
+    assert_eq!(body["page"], 1);
+    assert_eq!(body["total_pages"], 1);
+    assert_eq!(body["items"].as_array().unwrap().len(), 5);
+
+    // 5 items, 2 per page: pages of 2, 2 and 1, in name order
+    let uri = "/v1/deployments?namespace=default&page=3&per_page=2";
+    let (status, body) = call(&h.routes, Method::GET, uri, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 5);
+    assert_eq!(body["total_pages"], 3);
+    let items = body["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["name"], "web-4");
+
+    let (_, body) = call(
+        &h.routes,
+        Method::GET,
+        "/v1/deployments?page=4&per_page=2",
+        "",
+    )
+    .await;
+    assert!(body["items"].as_array().unwrap().is_empty());
+    assert_eq!(body["total"], 5);
+
+    let (_, body) = call(
+        &h.routes,
+        Method::GET,
+        "/v1/deployments?namespace=other",
+        "",
+    )
+    .await;
+    assert_eq!(body["total"], 0);
+    assert_eq!(body["total_pages"], 0);
+
+    let (_, body) = call(&h.routes, Method::GET, "/v1/deployments?per_page=1000", "").await;
+    assert_eq!(body["per_page"], MAX_PER_PAGE);
+
+    for uri in ["/v1/deployments?page=0", "/v1/deployments?per_page=abc"] {
+        let (status, body) = call(&h.routes, Method::GET, uri, "").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error_code(&body), "invalid_request");
+    }
+}
+
+#[tokio::test]
+async fn test_create_and_scale_deployment_errors() {
+    let temp = tempfile::tempdir().unwrap();
+    let h = harness(temp.path()).await;
+    let spec = r#"{"name": "api", "image": "nginx:1.25", "replicas": 2}"#;
+
+    let (status, body) = call(&h.routes, Method::POST, "/v1/deployments", spec).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["desired_replicas"], 2);
+
+    let (status, body) = call(&h.routes, Method::POST, "/v1/deployments", spec).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(error_code(&body), "conflict");
+
+    let (status, body) = call(&h.routes, Method::POST, "/v1/deployments", "{").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error_code(&body), "invalid_request");
+
+    let scale = r#"{"replicas": 4}"#;
+    let (status, body) = call(&h.routes, Method::POST, "/v1/deployments/api/scale", scale).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["desired_replicas"], 4);
+    assert_eq!(h.runtime.containers().len(), 4);
+
+    let uri = "/v1/deployments/api/scale?namespace=other";
+    let (status, body) = call(&h.routes, Method::POST, uri, scale).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(error_code(&body), "not_found");
+
+    let uri = "/v1/deployments/api/scale";
+    let (status, _) = call(&h.routes, Method::POST, uri, r#"{"replicas": -1}"#).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = call(&h.routes, Method::DELETE, "/v1/deployments", "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(error_code(&body), "not_found");
+}
+
+#[tokio::test]
+async fn test_health_checks_list_and_run() {
+    let temp = tempfile::tempdir().unwrap();
+    let h = harness(temp.path()).await;
+    let marker = temp.path().join("ready");
+    std::fs::write(&marker, "ok").unwrap();
+    for id in ["c", "a", "b"] {
+        let check = HealthCheckDef::new(
+            id.to_string(),
+            id.to_string(),
+            TargetType::Custom,
+            "web".to_string(),
+            CheckType::File {
+                path: marker.to_string_lossy().into_owned(),
+                exists: true,
+            },
+        );
+        h.state.health.create_health_check(check).await.unwrap();
+    }
+
+    let uri = "/v1/health/checks?per_page=2";
+    let (status, body) = call(&h.routes, Method::GET, uri, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 3);
+    assert_eq!(body["total_pages"], 2);
+    let ids: Vec<&str> = body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|check| check["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["a", "b"]);
+
+    let (status, body) = call(&h.routes, Method::POST, "/v1/health/checks/a/run", "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["check_id"], "a");
+    assert_eq!(body["status"], "Healthy");
+    assert!(h.state.health.get_health_check_result("a").await.is_some());
+
+    let uri = "/v1/health/checks/missing/run";
+    let (status, body) = call(&h.routes, Method::POST, uri, "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(error_code(&body), "not_found");
+}
+
+#[test]
+fn test_error_mapping() {
+    let cases = [
+        (
+            PolisError::Config("bad".to_string()),
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+        ),
+        (
+            PolisError::Api("bad".to_string()),
+            StatusCode::BAD_REQUEST,
+            "invalid_request",
+        ),
+        (
+            PolisError::Auth("no".to_string()),
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+        ),
+        (
+            PolisError::Runtime("boom".to_string()),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+        ),
+    ];
+    for (error, status, code) in cases {
+        let message = error.to_string();
+        let error = ApiError::from(error);
+        assert_eq!(error.status, status);
+        assert_eq!(error.code, code);
+
+        let response = error.into_response();
+        assert_eq!(response.status(), status);
+        let body: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["error"]["code"], code);
+        assert_eq!(body["error"]["message"], message.as_str());
+    }
+}
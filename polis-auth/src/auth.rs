@@ -1,5 +1,5 @@
-use crate::{JwtClaims, JwtManager, PermissionManager, UserManager};
-use polis_core::{PolisError, Result};
+use crate::{JwtClaims, JwtManager, PermissionManager, User, UserManager};
+use polis_core::{AuthConfig, PolisError, Result};
 use std::collections::HashMap;
 use uuid::Uuid;
 
@@ -8,6 +8,8 @@ pub struct AuthManager {
     pub user_manager: UserManager,
     pub permission_manager: PermissionManager,
     pub sessions: HashMap<String, UserSession>,
+    /// Validade dos tokens de acesso emitidos
+    pub access_token_ttl: chrono::Duration,
 }
 
 #[derive(Debug, Clone)]
@@ -41,9 +43,28 @@ impl AuthManager {
             user_manager: UserManager::new(),
             permission_manager: PermissionManager::new(),
             sessions: HashMap::new(),
+            access_token_ttl: chrono::Duration::hours(24),
         }
     }
 
+    /// Chave de assinatura e validade dos tokens lidas de `config`
+    pub fn from_config(config: &AuthConfig) -> Result<Self> {
+        if config.jwt_secret.is_empty() {
+            return Err(PolisError::Config(
+                "auth.jwt_secret precisa ser configurado para emitir tokens".to_string(),
+            ));
+        }
+        if config.access_token_ttl == 0 {
+            return Err(PolisError::Config(
+                "auth.access_token_ttl deve ser maior que 0".to_string(),
+            ));
+        }
+
+        let mut manager = Self::new(config.jwt_secret.clone());
+        manager.access_token_ttl = chrono::Duration::seconds(config.access_token_ttl as i64);
+        Ok(manager)
+    }
+
     pub async fn authenticate(&mut self, username: &str, password: &str) -> Result<AuthResult> {
         // Verificar credenciais do usuário
         let user = self
@@ -51,40 +72,48 @@ impl AuthManager {
             .authenticate_user(username, password)
             .await?;
 
-        // Obter permissões do usuário
+        let result = self.issue_access_token(&user).await?;
+
+        // Criar sessão
+        let session = UserSession {
+            user_id: user.id,
+            username: user.username.clone(),
+            permissions: result.user.permissions.clone(),
+            expires_at: result.expires_at,
+        };
+
+        self.sessions.insert(result.token.clone(), session);
+
+        Ok(result)
+    }
+
+    /// Gera um token de acesso para `user` com as permissões e roles atuais
+    /// dele, válido por `access_token_ttl`. Não cria sessão.
+    pub async fn issue_access_token(&self, user: &User) -> Result<AuthResult> {
         let permissions = self
             .permission_manager
             .get_user_permissions(&user.id)
             .await?;
+        let roles = self.permission_manager.get_user_roles(&user.id).await?;
 
-        // Gerar token JWT
+        let now = chrono::Utc::now();
+        let expires_at = now + self.access_token_ttl;
         let claims = JwtClaims {
             sub: user.id.to_string(),
             username: user.username.clone(),
             permissions: permissions.clone(),
-            exp: (chrono::Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
-            iat: Some(chrono::Utc::now().timestamp() as usize),
+            roles,
+            exp: expires_at.timestamp() as usize,
+            iat: Some(now.timestamp() as usize),
         };
-
         let token = self.jwt_manager.generate_token(&claims)?;
-        let expires_at = chrono::Utc::now() + chrono::Duration::hours(24);
-
-        // Criar sessão
-        let session = UserSession {
-            user_id: user.id,
-            username: user.username.clone(),
-            permissions: permissions.clone(),
-            expires_at,
-        };
-
-        self.sessions.insert(token.clone(), session);
 
         Ok(AuthResult {
             token,
             user: UserInfo {
                 id: user.id,
-                username: user.username,
-                email: user.email,
+                username: user.username.clone(),
+                email: user.email.clone(),
                 permissions,
                 created_at: user.created_at,
             },
@@ -114,12 +143,16 @@ impl AuthManager {
             sub: session.user_id.to_string(),
             username: session.username.clone(),
             permissions: session.permissions.clone(),
-            exp: (chrono::Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
+            roles: self
+                .permission_manager
+                .get_user_roles(&session.user_id)
+                .await?,
+            exp: (chrono::Utc::now() + self.access_token_ttl).timestamp() as usize,
             iat: Some(chrono::Utc::now().timestamp() as usize),
         };
 
         let new_token = self.jwt_manager.generate_token(&claims)?;
-        let expires_at = chrono::Utc::now() + chrono::Duration::hours(24);
+        let expires_at = chrono::Utc::now() + self.access_token_ttl;
 
         // Atualizar sessão
         let new_session = UserSession {
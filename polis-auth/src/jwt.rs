@@ -1,3 +1,4 @@
+use jsonwebtoken::errors::ErrorKind;
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use polis_core::{PolisError, Result};
 use serde::{Deserialize, Serialize};
@@ -13,10 +14,24 @@ pub struct JwtClaims {
     pub sub: String, // Subject (user ID)
     pub username: String,
     pub permissions: Vec<String>,
+    /// Roles do usuário; as permissões delas somam-se a `permissions`
+    #[serde(default)]
+    pub roles: Vec<String>,
     pub exp: usize,         // Expiration time
     pub iat: Option<usize>, // Issued at
 }
 
+/// Motivo pelo qual um token foi recusado
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TokenError {
+    #[error("Token expirado")]
+    Expired,
+    #[error("Assinatura do token inválida")]
+    InvalidSignature,
+    #[error("Token inválido: {0}")]
+    Malformed(String),
+}
+
 impl JwtManager {
     pub fn new(secret: String) -> Self {
         let encoding_key = EncodingKey::from_secret(secret.as_ref());
@@ -39,15 +54,28 @@ impl JwtManager {
     }
 
     pub fn validate_token(&self, token: &str) -> Result<JwtClaims> {
+        self.verify_token(token)
+            .map_err(|e| PolisError::Auth(format!("Erro ao validar token JWT: {}", e)))
+    }
+
+    /// Como `validate_token`, distinguindo token expirado, assinatura
+    /// inválida e token malformado
+    pub fn verify_token(&self, token: &str) -> std::result::Result<JwtClaims, TokenError> {
         let validation = Validation::new(Algorithm::HS256);
 
-        let token_data = decode::<JwtClaims>(token, &self.decoding_key, &validation)
-            .map_err(|e| PolisError::Auth(format!("Erro ao validar token JWT: {}", e)))?;
+        let token_data =
+            decode::<JwtClaims>(token, &self.decoding_key, &validation).map_err(|e| {
+                match e.kind() {
+                    ErrorKind::ExpiredSignature => TokenError::Expired,
+                    ErrorKind::InvalidSignature => TokenError::InvalidSignature,
+                    _ => TokenError::Malformed(e.to_string()),
+                }
+            })?;
 
-        // Verificar se o token não expirou
+        // A validação aceita uma pequena margem após `exp`; aqui não
         let now = chrono::Utc::now().timestamp() as usize;
         if token_data.claims.exp < now {
-            return Err(PolisError::Auth("Token expirado".to_string()));
+            return Err(TokenError::Expired);
         }
 
         Ok(token_data.claims)
@@ -2,10 +2,12 @@ pub mod auth;
 pub mod jwt;
 pub mod middleware;
 pub mod permissions;
+pub mod tokens;
 pub mod users;
 
 pub use auth::*;
 pub use jwt::*;
 pub use middleware::*;
 pub use permissions::*;
+pub use tokens::*;
 pub use users::*;
@@ -136,6 +136,48 @@ impl PermissionManager {
                 resource: "volumes".to_string(),
                 action: "delete".to_string(),
             },
+            Permission {
+                id: "containers:write".to_string(),
+                name: "Alterar Containers".to_string(),
+                description: "Criar, alterar e remover containers pela API".to_string(),
+                resource: "containers".to_string(),
+                action: "write".to_string(),
+            },
+            Permission {
+                id: "stats:read".to_string(),
+                name: "Visualizar Estatísticas".to_string(),
+                description: "Visualizar estatísticas de containers".to_string(),
+                resource: "stats".to_string(),
+                action: "read".to_string(),
+            },
+            Permission {
+                id: "deployments:read".to_string(),
+                name: "Listar Deployments".to_string(),
+                description: "Visualizar deployments e o seu estado".to_string(),
+                resource: "deployments".to_string(),
+                action: "read".to_string(),
+            },
+            Permission {
+                id: "deployments:write".to_string(),
+                name: "Alterar Deployments".to_string(),
+                description: "Criar, escalar e remover deployments".to_string(),
+                resource: "deployments".to_string(),
+                action: "write".to_string(),
+            },
+            Permission {
+                id: "health:read".to_string(),
+                name: "Visualizar Health Checks".to_string(),
+                description: "Visualizar health checks e os seus resultados".to_string(),
+                resource: "health".to_string(),
+                action: "read".to_string(),
+            },
+            Permission {
+                id: "health:write".to_string(),
+                name: "Executar Health Checks".to_string(),
+                description: "Criar e executar health checks".to_string(),
+                resource: "health".to_string(),
+                action: "write".to_string(),
+            },
             Permission {
                 id: "system:read".to_string(),
                 name: "Visualizar Sistema".to_string(),
@@ -167,6 +209,12 @@ impl PermissionManager {
                     "containers:create".to_string(),
                     "containers:update".to_string(),
                     "containers:delete".to_string(),
+                    "containers:write".to_string(),
+                    "stats:read".to_string(),
+                    "deployments:read".to_string(),
+                    "deployments:write".to_string(),
+                    "health:read".to_string(),
+                    "health:write".to_string(),
                     "images:read".to_string(),
                     "images:create".to_string(),
                     "images:delete".to_string(),
@@ -188,6 +236,11 @@ impl PermissionManager {
                     "containers:read".to_string(),
                     "containers:create".to_string(),
                     "containers:update".to_string(),
+                    "containers:write".to_string(),
+                    "stats:read".to_string(),
+                    "deployments:read".to_string(),
+                    "deployments:write".to_string(),
+                    "health:read".to_string(),
                     "images:read".to_string(),
                     "images:create".to_string(),
                     "networks:read".to_string(),
@@ -202,6 +255,9 @@ impl PermissionManager {
                 description: "Apenas visualização".to_string(),
                 permissions: vec![
                     "containers:read".to_string(),
+                    "stats:read".to_string(),
+                    "deployments:read".to_string(),
+                    "health:read".to_string(),
                     "images:read".to_string(),
                     "networks:read".to_string(),
                     "volumes:read".to_string(),
@@ -253,6 +309,14 @@ impl PermissionManager {
             .unwrap_or_default())
     }
 
+    pub async fn get_user_roles(&self, user_id: &Uuid) -> Result<Vec<String>> {
+        Ok(self.user_roles.get(user_id).cloned().unwrap_or_default())
+    }
+
+    pub fn get_role(&self, role_id: &str) -> Option<&Role> {
+        self.roles.get(role_id)
+    }
+
     pub async fn check_permission(&self, user_id: &Uuid, permission: &str) -> Result<bool> {
         let user_permissions = self.get_user_permissions(user_id).await?;
         Ok(user_permissions.contains(&permission.to_string()))
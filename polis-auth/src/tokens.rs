@@ -0,0 +1,216 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use polis_core::{PolisError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Arquivo dos refresh tokens emitidos pela API
+pub fn refresh_token_store_path(root_dir: &Path) -> PathBuf {
+    root_dir.join("auth").join("refresh_tokens.json")
+}
+
+/// Dono e validade de um refresh token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RefreshTokenRecord {
+    pub user_id: Uuid,
+    pub username: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Refresh tokens válidos, persistidos a cada mudança. O arquivo guarda só
+/// o SHA-256 de cada token, nunca o token em si. Cada token vale para uma
+/// única renovação: `rotate` o invalida e emite outro.
+#[derive(Debug)]
+pub struct RefreshTokenStore {
+    path: PathBuf,
+    tokens: HashMap<String, RefreshTokenRecord>,
+}
+
+impl RefreshTokenStore {
+    /// Carrega os tokens salvos em `path`, se houver
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let tokens = if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            serde_json::from_str(&content).map_err(|e| {
+                PolisError::Auth(format!(
+                    "Erro ao ler refresh tokens de {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?
+        } else {
+            HashMap::new()
+        };
+        Ok(Self { path, tokens })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Quantidade de tokens ainda não usados nem revogados
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Emite um refresh token para o usuário, válido por `ttl` a partir de
+    /// `now`. Tokens expirados são descartados na mesma gravação.
+    pub fn issue(
+        &mut self,
+        user_id: Uuid,
+        username: &str,
+        ttl: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<String> {
+        let token = URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>());
+        self.tokens.retain(|_, record| record.expires_at > now);
+        self.tokens.insert(
+            token_hash(&token),
+            RefreshTokenRecord {
+                user_id,
+                username: username.to_string(),
+                issued_at: now,
+                expires_at: now + ttl,
+            },
+        );
+        self.save()?;
+        Ok(token)
+    }
+
+    /// Troca `token` por um novo refresh token do mesmo usuário; o token
+    /// usado deixa de valer mesmo que a troca falhe depois
+    pub fn rotate(
+        &mut self,
+        token: &str,
+        ttl: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<(RefreshTokenRecord, String)> {
+        let record = self.tokens.remove(&token_hash(token)).ok_or_else(|| {
+            PolisError::Auth("Refresh token inválido, revogado ou já utilizado".to_string())
+        })?;
+        if record.expires_at <= now {
+            self.save()?;
+            return Err(PolisError::Auth("Refresh token expirado".to_string()));
+        }
+
+        let new_token = self.issue(record.user_id, &record.username, ttl, now)?;
+        Ok((record, new_token))
+    }
+
+    /// Invalida `token`; retorna se ele existia
+    pub fn revoke(&mut self, token: &str) -> Result<bool> {
+        let removed = self.tokens.remove(&token_hash(token)).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Invalida todos os refresh tokens do usuário
+    pub fn revoke_user(&mut self, user_id: &Uuid) -> Result<usize> {
+        let before = self.tokens.len();
+        self.tokens.retain(|_, record| &record.user_id != user_id);
+        let removed = before - self.tokens.len();
+        if removed > 0 {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Grava num temporário e renomeia, para nunca deixar o arquivo pela
+        // metade
+        let temp = self.path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_vec_pretty(&self.tokens)?)?;
+        std::fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+}
+
+fn token_hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Debug, Clone)]
+struct LoginFailures {
+    count: u32,
+    first_failure: DateTime<Utc>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+/// Limita tentativas de login por chave (usuário ou IP): após
+/// `max_attempts` falhas dentro de `lockout`, a chave fica bloqueada por
+/// `lockout`
+#[derive(Debug, Clone)]
+pub struct LoginRateLimiter {
+    max_attempts: u32,
+    lockout: Duration,
+    failures: HashMap<String, LoginFailures>,
+}
+
+impl LoginRateLimiter {
+    /// `max_attempts` 0 desativa o limite
+    pub fn new(max_attempts: u32, lockout: Duration) -> Self {
+        Self {
+            max_attempts,
+            lockout,
+            failures: HashMap::new(),
+        }
+    }
+
+    /// Tempo restante do bloqueio mais longo entre `keys`; `None` se
+    /// nenhuma está bloqueada
+    pub fn check(&mut self, keys: &[String], now: DateTime<Utc>) -> Option<Duration> {
+        self.failures
+            .retain(|_, failures| match failures.locked_until {
+                Some(until) => until > now,
+                None => now - failures.first_failure < self.lockout,
+            });
+        keys.iter()
+            .filter_map(|key| self.failures.get(key)?.locked_until)
+            .map(|until| until - now)
+            .max()
+    }
+
+    /// Conta uma falha para cada chave
+    pub fn record_failure(&mut self, keys: &[String], now: DateTime<Utc>) {
+        if self.max_attempts == 0 {
+            return;
+        }
+        for key in keys {
+            let failures = self
+                .failures
+                .entry(key.clone())
+                .or_insert_with(|| LoginFailures {
+                    count: 0,
+                    first_failure: now,
+                    locked_until: None,
+                });
+            if now - failures.first_failure >= self.lockout {
+                failures.count = 0;
+                failures.first_failure = now;
+            }
+            failures.count += 1;
+            if failures.count >= self.max_attempts {
+                failures.locked_until = Some(now + self.lockout);
+            }
+        }
+    }
+
+    /// Zera as falhas de `key` após um login bem-sucedido
+    pub fn record_success(&mut self, key: &str) {
+        self.failures.remove(key);
+    }
+}
@@ -1,5 +1,4 @@
 use argon2::password_hash::SaltString;
-use getrandom::getrandom;
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use chrono::{DateTime, Utc};
 use polis_core::{PolisError, Result};
@@ -205,7 +204,7 @@ impl UserManager {
 
     fn hash_password(password: &str) -> Result<String> {
         let mut salt_bytes = [0u8; 16];
-        getrandom(&mut salt_bytes)
+        getrandom::fill(&mut salt_bytes)
             .map_err(|e| PolisError::Auth(format!("Erro ao gerar salt: {}", e)))?;
         
         let salt = SaltString::encode_b64(&salt_bytes)
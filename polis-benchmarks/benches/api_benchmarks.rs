@@ -255,6 +255,11 @@ fn json_serialization_benchmark(c: &mut Criterion) {
                 environment: std::collections::HashMap::new(),
                 ports: vec![],
                 volumes: vec![],
+                rootfs: Default::default(),
+                max_runtime: None,
+                deadline: None,
+                stop_reason: None,
+                egress: None,
                 labels: std::collections::HashMap::new(),
             };
 
@@ -128,6 +128,11 @@ fn container_listing_benchmark(c: &mut Criterion) {
                             network_mode: polis_core::types::NetworkMode::Bridge,
                             ports: vec![],
                             volumes: vec![],
+                            rootfs: Default::default(),
+                            max_runtime: None,
+                            deadline: None,
+                            stop_reason: None,
+                            egress: None,
                         })
                     }
                 })
@@ -208,8 +208,9 @@ fn security_profile_serialization_benchmark(c: &mut Criterion) {
                 cgroup_limits: Some(polis_core::types::ResourceLimits {
                     memory_limit: Some(512 * 1024 * 1024),
                     memory_swap: Some(512 * 1024 * 1024),
-                    cpu_quota: Some(50000.0),
+                    cpu_quota: Some(0.5),
                     cpu_period: Some(100000),
+                    cpu_shares: None,
                     pids_limit: Some(100),
                     disk_quota: Some(1024 * 1024 * 1024),
                 }),
@@ -222,6 +223,7 @@ fn security_profile_serialization_benchmark(c: &mut Criterion) {
                     r#type: "container_t".to_string(),
                     level: "s0".to_string(),
                 }),
+                readonly_rootfs: true,
                 sandbox_config: Some(polis_security::SandboxConfig {
                     read_only_rootfs: true,
                     no_new_privileges: true,
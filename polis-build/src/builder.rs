@@ -1,8 +1,16 @@
-use crate::{BuildCache, BuildContext, BuildError, Dockerfile, Result};
+use crate::{
+    default_qemu_search_path, find_qemu, BuildCache, BuildContext, BuildError, BuildHistory,
+    BuildPlatform, BuildRecord, Descriptor, Dockerfile, DockerfileLinter, GcReport, ImageConfig,
+    ImageIndex, ImageManifest, Instruction, LintConfig, LintSeverity, RecordedOptions, Result,
+    RootFs, SecretMount, MEDIA_TYPE_IMAGE_CONFIG, MEDIA_TYPE_IMAGE_INDEX, MEDIA_TYPE_IMAGE_LAYER,
+    MEDIA_TYPE_IMAGE_MANIFEST,
+};
 use polis_core::ImageId;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::time::SystemTime;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
 
 /// Build options for container images
 #[derive(Debug, Clone)]
@@ -12,8 +20,14 @@ pub struct BuildOptions {
     pub pull: bool,
     pub build_args: HashMap<String, String>,
     pub target: Option<String>,
-    pub platform: Option<String>,
+    /// Target platforms; the host's when empty. Several platforms produce
+    /// an image index with one manifest per platform.
+    pub platforms: Vec<BuildPlatform>,
+    /// Host files mounted by `RUN --mount=type=secret,id=<id>`, by id
+    pub secrets: HashMap<String, PathBuf>,
     pub progress: bool,
+    /// Dockerfile linting before the build
+    pub lint: LintConfig,
 }
 
 impl Default for BuildOptions {
@@ -24,17 +38,77 @@ impl Default for BuildOptions {
             pull: false,
             build_args: HashMap::new(),
             target: None,
-            platform: None,
+            platforms: Vec::new(),
+            secrets: HashMap::new(),
             progress: true,
+            lint: LintConfig::default(),
         }
     }
 }
 
+impl BuildOptions {
+    /// Platforms the build produces an image for
+    pub fn target_platforms(&self) -> Vec<BuildPlatform> {
+        if self.platforms.is_empty() {
+            vec![BuildPlatform::host()]
+        } else {
+            self.platforms.clone()
+        }
+    }
+}
+
+/// Progress of a build, sent while it runs. Steps are numbered from 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildEvent {
+    StepStarted {
+        step: usize,
+        total: usize,
+        instruction: String,
+    },
+    StepCompleted {
+        step: usize,
+        duration: Duration,
+    },
+    /// A base image layer was pulled
+    LayerPulled {
+        digest: String,
+        size: u64,
+    },
+    /// A step was satisfied by the cache instead of being run
+    LayerCached {
+        digest: String,
+    },
+    LogLine {
+        step: usize,
+        line: String,
+    },
+    BuildError {
+        step: usize,
+        message: String,
+    },
+}
+
 /// Container image builder
 #[derive(Debug)]
 pub struct ImageBuilder {
     pub cache: BuildCache,
     pub build_dir: PathBuf,
+    pub history: BuildHistory,
+    cache_hits: usize,
+    cache_misses: usize,
+    produced_layers: Vec<String>,
+    /// Receives the events of the running build, if anyone listens
+    events: Option<mpsc::Sender<BuildEvent>>,
+    /// Step of the running build
+    step: usize,
+    /// Directories searched for QEMU emulators
+    qemu_search_path: Vec<PathBuf>,
+    /// Platform the running build is producing an image for
+    platform: BuildPlatform,
+    /// Emulator running the steps of a foreign platform
+    emulator: Option<PathBuf>,
+    /// Layers of the image built for the current platform
+    layers: Vec<Descriptor>,
 }
 
 impl ImageBuilder {
@@ -42,44 +116,458 @@ impl ImageBuilder {
     pub fn new(build_dir: PathBuf) -> Result<Self> {
         let cache_dir = build_dir.join("cache");
         let cache = BuildCache::new(cache_dir)?;
+        let history = BuildHistory::load(&build_dir)?;
 
         Ok(Self {
             cache,
             build_dir,
+            history,
+            cache_hits: 0,
+            cache_misses: 0,
+            produced_layers: Vec::new(),
+            events: None,
+            step: 0,
+            qemu_search_path: default_qemu_search_path(),
+            platform: BuildPlatform::host(),
+            emulator: None,
+            layers: Vec::new(),
         })
     }
 
-    /// Build an image from a Dockerfile
+    /// Look up QEMU emulators in these directories instead of `PATH`
+    pub fn with_qemu_search_path(mut self, search_path: Vec<PathBuf>) -> Self {
+        self.qemu_search_path = search_path;
+        self
+    }
+
+    /// Build history recorded by this builder
+    pub fn history(&self) -> &BuildHistory {
+        &self.history
+    }
+
+    /// Directory holding intermediate layers
+    pub fn layers_dir(&self) -> PathBuf {
+        self.build_dir.join("layers")
+    }
+
+    /// Directory holding per-build staging directories
+    pub fn staging_dir(&self) -> PathBuf {
+        self.build_dir.join("staging")
+    }
+
+    /// Directory holding image manifests, configs and indexes by digest
+    pub fn blobs_dir(&self) -> PathBuf {
+        self.build_dir.join("blobs").join("sha256")
+    }
+
+    /// Read a blob written by a build, e.g. the manifest or index an image
+    /// id refers to
+    pub fn read_blob(&self, digest: &str) -> Result<Vec<u8>> {
+        let hex = digest
+            .strip_prefix("sha256:")
+            .ok_or_else(|| BuildError::Parse(format!("Unsupported digest: {}", digest)))?;
+        Ok(std::fs::read(self.blobs_dir().join(hex))?)
+    }
+
+    /// Build an image like `build_image`, sending its progress to `events`.
+    /// The build waits for room in the channel and carries on if the
+    /// receiver is dropped.
+    pub async fn build_image_with_events(
+        &mut self,
+        context: BuildContext,
+        dockerfile: Dockerfile,
+        options: BuildOptions,
+        events: mpsc::Sender<BuildEvent>,
+    ) -> Result<ImageId> {
+        self.events = Some(events);
+        let result = self.build_image(context, dockerfile, options).await;
+        self.events = None;
+        result
+    }
+
+    /// Build an image from a Dockerfile, recording the build in the history
     pub async fn build_image(
         &mut self,
         context: BuildContext,
         dockerfile: Dockerfile,
         options: BuildOptions,
+    ) -> Result<ImageId> {
+        let context = context.with_secrets(options.secrets.clone());
+        let build_id = uuid::Uuid::new_v4().to_string();
+        let started_at = chrono::Utc::now();
+        let start = Instant::now();
+
+        self.cache_hits = 0;
+        self.cache_misses = 0;
+        self.produced_layers.clear();
+
+        let staging = self.staging_dir().join(&build_id);
+        std::fs::create_dir_all(&staging)?;
+
+        // Digest of the context as it was built; a context that cannot be
+        // read fails the build, which is still recorded
+        let (context_digest, result) = match context.digest() {
+            Ok(digest) => {
+                let result = match Self::stage_context(&context, &staging) {
+                    Ok(()) => self.run_build(&context, &dockerfile, &options).await,
+                    Err(e) => Err(e),
+                };
+                (digest, result)
+            }
+            Err(e) => (String::new(), Err(e)),
+        };
+        let _ = std::fs::remove_dir_all(&staging);
+
+        let record = BuildRecord {
+            id: build_id,
+            tag: options.tag.clone(),
+            dockerfile_hash: dockerfile.content_hash(),
+            context_digest,
+            started_at,
+            duration: start.elapsed(),
+            cache_hits: self.cache_hits,
+            cache_misses: self.cache_misses,
+            image_id: result.as_ref().ok().map(|id| id.0.clone()),
+            layers: std::mem::take(&mut self.produced_layers),
+            options: RecordedOptions::from(&options),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+        self.history.record(record)?;
+
+        if let Err(e) = self.gc() {
+            tracing::warn!("Build garbage collection failed: {}", e);
+        }
+
+        result
+    }
+
+    /// Write the context tar, holding only the files `.dockerignore` lets
+    /// through, to the build's staging directory
+    fn stage_context(context: &BuildContext, staging: &Path) -> Result<()> {
+        let archive = context.create_tar_archive()?;
+        std::fs::write(staging.join("context.tar"), archive)?;
+        Ok(())
+    }
+
+    /// Remove intermediate layers and staging directories that are not
+    /// referenced by the cache or by any build record
+    pub fn gc(&mut self) -> Result<GcReport> {
+        let mut referenced: HashSet<String> = self.history.referenced_ids();
+        referenced.extend(
+            self.cache
+                .entries
+                .values()
+                .filter_map(|entry| entry.layer_id.clone()),
+        );
+
+        let mut report = GcReport::default();
+        for dir in [self.layers_dir(), self.staging_dir()] {
+            if !dir.exists() {
+                continue;
+            }
+
+            for entry in std::fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let name = path
+                    .file_stem()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                if referenced.contains(&name) {
+                    continue;
+                }
+
+                report.reclaimed_bytes += path_size(&path);
+                if path.is_dir() {
+                    std::fs::remove_dir_all(&path)?;
+                } else {
+                    std::fs::remove_file(&path)?;
+                }
+                report.removed.push(path);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Execute the Dockerfile instructions
+    async fn run_build(
+        &mut self,
+        context: &BuildContext,
+        dockerfile: &Dockerfile,
+        options: &BuildOptions,
     ) -> Result<ImageId> {
         if !context.is_valid() {
             return Err(BuildError::Context("Invalid build context".to_string()));
         }
 
-        let image_id = ImageId::new("built", "latest");
-        
+        if options.lint.enabled {
+            self.lint(dockerfile, options)?;
+        }
+
+        self.check_secrets(context, dockerfile)?;
+
+        // Every emulator is looked up before the first step runs
+        let platforms = options.target_platforms();
+        let mut emulators = Vec::with_capacity(platforms.len());
+        for platform in &platforms {
+            emulators.push(self.emulator_for(platform)?);
+        }
+
+        if options.progress {
+            let names: Vec<String> = platforms.iter().map(|p| p.to_string()).collect();
+            println!("Building image for {}", names.join(", "));
+        }
+
+        let mut manifests = Vec::with_capacity(platforms.len());
+        for (platform, emulator) in platforms.into_iter().zip(emulators) {
+            self.platform = platform;
+            self.emulator = emulator;
+            self.layers.clear();
+            self.run_steps(context, dockerfile, options).await?;
+            manifests.push(self.write_manifest()?);
+        }
+
+        let image = if manifests.len() == 1 {
+            manifests.remove(0)
+        } else {
+            self.write_index(manifests)?
+        };
+        let image_id = ImageId(image.digest);
+
         if options.progress {
-            println!("Building image: {}", image_id.0);
+            println!("Successfully built image: {}", image_id.0);
         }
 
-        // Process each instruction
+        Ok(image_id)
+    }
+
+    /// Execute the Dockerfile instructions for the current platform
+    async fn run_steps(
+        &mut self,
+        context: &BuildContext,
+        dockerfile: &Dockerfile,
+        options: &BuildOptions,
+    ) -> Result<()> {
+        let total = dockerfile.instructions.len();
         for (index, instruction) in dockerfile.instructions.iter().enumerate() {
+            self.step = index + 1;
             if options.progress {
-                println!("Step {}/{}: {:?}", index + 1, dockerfile.instructions.len(), instruction);
+                println!("Step {}/{}: {:?}", self.step, total, instruction);
             }
 
-            self.process_instruction(instruction, &context, &options).await?;
+            let started = Instant::now();
+            self.emit(BuildEvent::StepStarted {
+                step: self.step,
+                total,
+                instruction: format!("{:?}", instruction),
+            })
+            .await;
+            if let Err(e) = self
+                .process_instruction(instruction, context, options)
+                .await
+            {
+                self.emit(BuildEvent::BuildError {
+                    step: self.step,
+                    message: e.to_string(),
+                })
+                .await;
+                return Err(e);
+            }
+            self.emit(BuildEvent::StepCompleted {
+                step: self.step,
+                duration: started.elapsed(),
+            })
+            .await;
         }
 
-        if options.progress {
-            println!("Successfully built image: {}", image_id.0);
+        Ok(())
+    }
+
+    /// Emulator needed to run the steps of a platform, none when the host
+    /// runs them natively
+    fn emulator_for(&self, platform: &BuildPlatform) -> Result<Option<PathBuf>> {
+        if platform.is_native() {
+            return Ok(None);
         }
 
-        Ok(image_id)
+        match find_qemu(platform, &self.qemu_search_path) {
+            Some(emulator) => Ok(Some(emulator)),
+            None => Err(BuildError::Platform(format!(
+                "QEMU not available for cross-compilation to {}: install qemu-{}-static",
+                platform,
+                platform.qemu_arch()
+            ))),
+        }
+    }
+
+    /// Fail before the first step if a RUN mounts a secret that was not
+    /// declared
+    fn check_secrets(&self, context: &BuildContext, dockerfile: &Dockerfile) -> Result<()> {
+        for instruction in &dockerfile.instructions {
+            let Instruction::Run(args) = instruction else {
+                continue;
+            };
+            for mount in Dockerfile::secret_mounts(args)? {
+                if !context.has_secret(&mount.id) {
+                    return Err(BuildError::MissingDependency(format!(
+                        "Secret '{}' is not declared for the build",
+                        mount.id
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Make the secrets of a RUN step readable, outside of any layer, until
+    /// the returned directory is dropped at the end of the step
+    async fn mount_secrets(
+        &self,
+        mounts: &[SecretMount],
+        context: &BuildContext,
+    ) -> Result<Option<tempfile::TempDir>> {
+        if mounts.is_empty() {
+            return Ok(None);
+        }
+
+        std::fs::create_dir_all(self.staging_dir())?;
+        let dir = tempfile::Builder::new()
+            .prefix("secrets-")
+            .tempdir_in(self.staging_dir())?;
+        for mount in mounts {
+            let source = context.secrets.get(&mount.id).ok_or_else(|| {
+                BuildError::MissingDependency(format!(
+                    "Secret '{}' is not declared for the build",
+                    mount.id
+                ))
+            })?;
+            let mounted = dir.path().join(&mount.id);
+            std::fs::copy(source, &mounted).map_err(|e| {
+                BuildError::BuildFailed(format!("Cannot read secret '{}': {}", mount.id, e))
+            })?;
+            let mut permissions = std::fs::metadata(&mounted)?.permissions();
+            permissions.set_readonly(true);
+            std::fs::set_permissions(&mounted, permissions)?;
+
+            self.log(format!(
+                "Mounting secret {} read-only at {}",
+                mount.id, mount.target
+            ))
+            .await;
+        }
+        Ok(Some(dir))
+    }
+
+    /// Store a blob under its digest
+    fn write_blob(&self, media_type: &str, content: &[u8]) -> Result<Descriptor> {
+        let hex = format!("{:x}", Sha256::digest(content));
+        let dir = self.blobs_dir();
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(&hex), content)?;
+
+        Ok(Descriptor {
+            media_type: media_type.to_string(),
+            digest: format!("sha256:{}", hex),
+            size: content.len() as u64,
+            platform: None,
+        })
+    }
+
+    /// Write the config and manifest of the image built for the current
+    /// platform
+    fn write_manifest(&self) -> Result<Descriptor> {
+        let config = ImageConfig {
+            architecture: self.platform.arch.clone(),
+            os: self.platform.os.clone(),
+            rootfs: RootFs {
+                fs_type: "layers".to_string(),
+                diff_ids: self
+                    .layers
+                    .iter()
+                    .map(|layer| layer.digest.clone())
+                    .collect(),
+            },
+        };
+        let config = serde_json::to_vec(&config)
+            .map_err(|e| BuildError::Parse(format!("Failed to serialize image config: {}", e)))?;
+        let config = self.write_blob(MEDIA_TYPE_IMAGE_CONFIG, &config)?;
+
+        let manifest = ImageManifest {
+            schema_version: 2,
+            media_type: MEDIA_TYPE_IMAGE_MANIFEST.to_string(),
+            config,
+            layers: self.layers.clone(),
+        };
+        let manifest = serde_json::to_vec(&manifest)
+            .map_err(|e| BuildError::Parse(format!("Failed to serialize image manifest: {}", e)))?;
+
+        let mut descriptor = self.write_blob(MEDIA_TYPE_IMAGE_MANIFEST, &manifest)?;
+        descriptor.platform = Some(self.platform.clone());
+        Ok(descriptor)
+    }
+
+    /// Write an image index referencing the manifest of each platform
+    fn write_index(&self, manifests: Vec<Descriptor>) -> Result<Descriptor> {
+        let index = ImageIndex {
+            schema_version: 2,
+            media_type: MEDIA_TYPE_IMAGE_INDEX.to_string(),
+            manifests,
+        };
+        let index = serde_json::to_vec(&index)
+            .map_err(|e| BuildError::Parse(format!("Failed to serialize image index: {}", e)))?;
+        self.write_blob(MEDIA_TYPE_IMAGE_INDEX, &index)
+    }
+
+    /// Add a layer to the image built for the current platform
+    fn push_layer(&mut self, content_hash: &str, size: u64) {
+        self.layers.push(Descriptor {
+            media_type: MEDIA_TYPE_IMAGE_LAYER.to_string(),
+            digest: format!("sha256:{}", content_hash),
+            size,
+            platform: None,
+        });
+    }
+
+    /// Send an event to the listener of the running build
+    async fn emit(&self, event: BuildEvent) {
+        if let Some(events) = &self.events {
+            // A receiver that went away does not stop the build
+            let _ = events.send(event).await;
+        }
+    }
+
+    /// Log a line of the current step and send it to the listener
+    async fn log(&self, line: String) {
+        tracing::info!("{}", line);
+        self.emit(BuildEvent::LogLine {
+            step: self.step,
+            line,
+        })
+        .await;
+    }
+
+    /// Report lint diagnostics, failing on errors if the options ask to
+    fn lint(&self, dockerfile: &Dockerfile, options: &BuildOptions) -> Result<()> {
+        let diagnostics = DockerfileLinter::new().lint(dockerfile);
+        for diagnostic in &diagnostics {
+            tracing::warn!("Dockerfile lint: {}", diagnostic);
+            if options.progress {
+                println!("{}", diagnostic);
+            }
+        }
+
+        let errors: Vec<String> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == LintSeverity::Error)
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        if options.lint.fail_on_error && !errors.is_empty() {
+            return Err(BuildError::Dockerfile(format!(
+                "Lint errors: {}",
+                errors.join("; ")
+            )));
+        }
+        Ok(())
     }
 
     /// Process a single Dockerfile instruction
@@ -145,30 +633,61 @@ impl ImageBuilder {
 
         if options.pull {
             // In a real implementation, this would pull the image
-            tracing::info!("Pulling base image: {}", full_image);
+            self.log(format!("Pulling base image: {}", full_image)).await;
+            let digest = self.cache.generate_content_hash(&full_image, &[]);
+            self.emit(BuildEvent::LayerPulled { digest, size: 0 }).await;
         }
 
-        tracing::info!("Using base image: {}", full_image);
+        self.log(format!("Using base image: {}", full_image)).await;
         Ok(())
     }
 
     /// Process RUN instruction
     async fn process_run(&mut self, args: &[String], context: &BuildContext, options: &BuildOptions) -> Result<()> {
         let instruction_str = format!("RUN {}", args.join(" "));
-        let content_hash = self.cache.generate_content_hash(&instruction_str, &[]);
-
-        if !options.no_cache && self.cache.has_entry(&content_hash) {
-            tracing::info!("Using cached layer for: {}", instruction_str);
-            return Ok(());
+        // Commands produce different layers on each platform
+        let platform = self.platform.to_string();
+        let content_hash = self
+            .cache
+            .generate_content_hash(&instruction_str, platform.as_bytes());
+
+        if !options.no_cache {
+            if let Some(size) = self.cache.get_entry(&content_hash).map(|entry| entry.size) {
+                self.log(format!("Using cached layer for: {}", instruction_str)).await;
+                self.push_layer(&content_hash, size);
+                self.emit(BuildEvent::LayerCached { digest: content_hash }).await;
+                self.cache_hits += 1;
+                return Ok(());
+            }
         }
+        self.cache_misses += 1;
+
+        // Secrets are only readable while the step runs; the cache key
+        // above names them without their content
+        let secrets = self
+            .mount_secrets(&Dockerfile::secret_mounts(args)?, context)
+            .await?;
 
         // In a real implementation, this would execute the command
-        tracing::info!("Executing: {}", instruction_str);
+        match &self.emulator {
+            Some(emulator) => {
+                self.log(format!(
+                    "Executing under {}: {}",
+                    emulator.display(),
+                    instruction_str
+                ))
+                .await
+            }
+            None => self.log(format!("Executing: {}", instruction_str)).await,
+        }
 
         // Simulate layer creation
         let layer_id = uuid::Uuid::new_v4().to_string();
         let layer_size = 1024 * 1024; // 1MB simulated
+        drop(secrets);
 
+        self.push_layer(&content_hash, layer_size);
+        self.produced_layers.push(layer_id.clone());
         self.cache.add_entry(instruction_str, content_hash, Some(layer_id), layer_size)?;
         Ok(())
     }
@@ -178,22 +697,30 @@ impl ImageBuilder {
         let instruction_str = format!("COPY {} {}", src, dest);
         let content_hash = self.cache.generate_content_hash(&instruction_str, &[]);
 
-        if self.cache.has_entry(&content_hash) {
-            tracing::info!("Using cached layer for: {}", instruction_str);
+        if let Some(size) = self.cache.get_entry(&content_hash).map(|entry| entry.size) {
+            self.log(format!("Using cached layer for: {}", instruction_str)).await;
+            self.push_layer(&content_hash, size);
+            self.emit(BuildEvent::LayerCached { digest: content_hash }).await;
+            self.cache_hits += 1;
             return Ok(());
         }
-
-        // Check if source file exists in context
-        if let Some(file_path) = context.get_files().get(src) {
-            tracing::info!("Copying {} to {}", file_path.display(), dest);
-        } else {
-            return Err(BuildError::BuildFailed(format!("Source file not found in context: {}", src)));
+        self.cache_misses += 1;
+
+        // Check if source files exist in context
+        for source in Dockerfile::copy_sources(src) {
+            if let Some(file_path) = context.get_files().get(source) {
+                self.log(format!("Copying {} to {}", file_path.display(), dest)).await;
+            } else {
+                return Err(BuildError::BuildFailed(format!("Source file not found in context: {}", source)));
+            }
         }
 
         // Simulate layer creation
         let layer_id = uuid::Uuid::new_v4().to_string();
         let layer_size = 512 * 1024; // 512KB simulated
 
+        self.push_layer(&content_hash, layer_size);
+        self.produced_layers.push(layer_id.clone());
         self.cache.add_entry(instruction_str, content_hash, Some(layer_id), layer_size)?;
         Ok(())
     }
@@ -281,3 +808,14 @@ pub struct BuildStats {
     pub cache_max_size: u64,
     pub build_dir: PathBuf,
 }
+
+/// Total size in bytes of a file or directory tree
+fn path_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
@@ -1,8 +1,20 @@
 use crate::{BuildError, Result};
+use regex::Regex;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use walkdir::WalkDir;
 
+/// Excluded from every context, before the patterns of `.dockerignore`
+const DEFAULT_IGNORE_PATTERNS: &[&str] = &[
+    ".git",
+    ".gitignore",
+    ".dockerignore",
+    "target",
+    "node_modules",
+    "**/.DS_Store",
+    "**/Thumbs.db",
+];
+
 /// Build context for container image building
 #[derive(Debug, Clone)]
 pub struct BuildContext {
@@ -11,6 +23,26 @@ pub struct BuildContext {
     pub dockerfile: Option<PathBuf>,
     pub dockerignore: Option<PathBuf>,
     pub size: u64,
+    /// Patterns deciding which files are part of the context
+    pub ignore: DockerIgnore,
+    /// Host files declared as build secrets, by id. They are never part of
+    /// the context sent to the build.
+    pub secrets: HashMap<String, PathBuf>,
+}
+
+/// Patterns of a `.dockerignore` file, matched the way Docker does: globs
+/// relative to the context root where `**` spans directories, a pattern
+/// matching a directory excludes everything below it, `!` re-includes
+/// files and the last matching pattern wins.
+#[derive(Debug, Clone, Default)]
+pub struct DockerIgnore {
+    patterns: Vec<IgnorePattern>,
+}
+
+#[derive(Debug, Clone)]
+struct IgnorePattern {
+    regex: Regex,
+    exception: bool,
 }
 
 impl BuildContext {
@@ -30,107 +62,76 @@ impl BuildContext {
             dockerfile: None,
             dockerignore: None,
             size: 0,
+            ignore: DockerIgnore::default(),
+            secrets: HashMap::new(),
         };
 
         context.scan_directory()?;
         Ok(context)
     }
 
-    /// Scan the directory for files
+    /// Scan the directory for files, applying the `.dockerignore` at its root
     fn scan_directory(&mut self) -> Result<()> {
-        let ignore_patterns = self.load_dockerignore()?;
-
-        for entry in WalkDir::new(&self.path) {
-            let entry = entry.map_err(|e| BuildError::Io(e.into()))?;
-            let path = entry.path();
-            let relative_path = path.strip_prefix(&self.path)
-                .map_err(|e| BuildError::Context(format!("Failed to strip prefix: {}", e)))?;
-
-            // Skip if matches ignore patterns
-            if self.should_ignore(relative_path, &ignore_patterns) {
-                continue;
-            }
-
-            if path.is_file() {
-                let relative_str = relative_path.to_string_lossy().to_string();
-                self.files.insert(relative_str, path.to_path_buf());
-                
-                // Add file size
-                if let Ok(metadata) = path.metadata() {
-                    self.size += metadata.len();
-                }
-            }
+        let mut content = DEFAULT_IGNORE_PATTERNS.join("\n");
+        let dockerignore = self.path.join(".dockerignore");
+        if dockerignore.is_file() {
+            content.push('\n');
+            content.push_str(&std::fs::read_to_string(&dockerignore)?);
+            self.dockerignore = Some(dockerignore);
+        }
+        self.ignore = DockerIgnore::parse(&content)?;
 
-            // Check for Dockerfile
-            if path.file_name().and_then(|n| n.to_str()) == Some("Dockerfile") {
-                self.dockerfile = Some(path.to_path_buf());
+        for relative_path in self.file_list()? {
+            let path = self.path.join(&relative_path);
+            if let Ok(metadata) = path.metadata() {
+                self.size += metadata.len();
             }
+            self.files.insert(relative_path.to_string_lossy().to_string(), path);
+        }
 
-            // Check for .dockerignore
-            if path.file_name().and_then(|n| n.to_str()) == Some(".dockerignore") {
-                self.dockerignore = Some(path.to_path_buf());
-            }
+        // The Dockerfile is needed even when it is not sent with the context
+        let dockerfile = self.path.join("Dockerfile");
+        if dockerfile.is_file() {
+            self.dockerfile = Some(dockerfile);
         }
 
         Ok(())
     }
 
-    /// Load .dockerignore patterns
-    fn load_dockerignore(&self) -> Result<Vec<String>> {
-        let mut patterns = Vec::new();
-        
-        if let Some(dockerignore_path) = &self.dockerignore {
-            let content = std::fs::read_to_string(dockerignore_path)
-                .map_err(|e| BuildError::Io(e))?;
-            
-            for line in content.lines() {
-                let line = line.trim();
-                if !line.is_empty() && !line.starts_with('#') {
-                    patterns.push(line.to_string());
+    /// Files that survive the ignore patterns, relative to the context root
+    /// and sorted
+    pub fn file_list(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut walker = WalkDir::new(&self.path).min_depth(1).into_iter();
+        while let Some(entry) = walker.next() {
+            let entry = entry.map_err(|e| BuildError::Io(e.into()))?;
+            let relative_path = entry.path().strip_prefix(&self.path)
+                .map_err(|e| BuildError::Context(format!("Failed to strip prefix: {}", e)))?;
+
+            if self.ignore.is_excluded(relative_path) {
+                // Only a `!` pattern could bring back files below it
+                if entry.file_type().is_dir() && !self.ignore.has_exceptions() {
+                    walker.skip_current_dir();
                 }
+                continue;
             }
-        }
 
-        // Add default ignore patterns
-        patterns.extend(vec![
-            ".git".to_string(),
-            ".gitignore".to_string(),
-            ".dockerignore".to_string(),
-            "target/".to_string(),
-            "node_modules/".to_string(),
-            ".DS_Store".to_string(),
-            "Thumbs.db".to_string(),
-        ]);
+            if entry.path().is_file() {
+                files.push(relative_path.to_path_buf());
+            }
+        }
 
-        Ok(patterns)
+        files.sort();
+        Ok(files)
     }
 
-    /// Check if a path should be ignored
-    fn should_ignore(&self, path: &Path, patterns: &[String]) -> bool {
-        let path_str = path.to_string_lossy();
-        
-        for pattern in patterns {
-            if self.matches_pattern(&path_str, pattern) {
-                return true;
-            }
-        }
-        
-        false
-    }
-
-    /// Check if a path matches a pattern
-    fn matches_pattern(&self, path: &str, pattern: &str) -> bool {
-        // Simple pattern matching (can be enhanced with proper glob matching)
-        if pattern.ends_with('/') {
-            // Directory pattern
-            path.starts_with(pattern) || path.contains(&format!("{}/", pattern.trim_end_matches('/')))
-        } else if pattern.starts_with('*') {
-            // Wildcard pattern
-            path.ends_with(pattern.trim_start_matches('*'))
-        } else {
-            // Exact match
-            path == pattern || path.starts_with(&format!("{}/", pattern))
-        }
+    /// Current size of the files that survive the ignore patterns
+    pub fn total_size(&self) -> u64 {
+        self.files
+            .values()
+            .filter_map(|path| path.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum()
     }
 
     /// Get the Dockerfile path
@@ -148,12 +149,12 @@ impl BuildContext {
         self.size
     }
 
-    /// Create a tar archive of the build context
+    /// Create a tar archive of the files that survive the ignore patterns
     pub fn create_tar_archive(&self) -> Result<Vec<u8>> {
         let mut tar_builder = tar::Builder::new(Vec::new());
         
-        for (relative_path, absolute_path) in &self.files {
-            tar_builder.append_path_with_name(absolute_path, relative_path)
+        for relative_path in self.file_list()? {
+            tar_builder.append_path_with_name(self.path.join(&relative_path), &relative_path)
                 .map_err(|e| BuildError::Io(e))?;
         }
         
@@ -161,6 +162,34 @@ impl BuildContext {
         Ok(tar_builder.into_inner().map_err(|e| BuildError::Io(e))?)
     }
 
+    /// SHA-256 digest over the context file paths and contents
+    pub fn digest(&self) -> Result<String> {
+        use sha2::{Digest, Sha256};
+
+        let mut paths: Vec<&String> = self.files.keys().collect();
+        paths.sort();
+
+        let mut hasher = Sha256::new();
+        for relative_path in paths {
+            let content = std::fs::read(&self.files[relative_path])?;
+            hasher.update(relative_path.as_bytes());
+            hasher.update([0u8]);
+            hasher.update(Sha256::digest(&content));
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Declare build secrets, mapping each id to a host file
+    pub fn with_secrets(mut self, secrets: HashMap<String, PathBuf>) -> Self {
+        self.secrets.extend(secrets);
+        self
+    }
+
+    /// Whether a secret with this id was declared for the build
+    pub fn has_secret(&self, id: &str) -> bool {
+        self.secrets.contains_key(id)
+    }
+
     /// Get file count
     pub fn get_file_count(&self) -> usize {
         self.files.len()
@@ -171,3 +200,120 @@ impl BuildContext {
         self.dockerfile.is_some() && !self.files.is_empty()
     }
 }
+
+impl DockerIgnore {
+    /// Parse `.dockerignore` content, one pattern per line
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut patterns = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (exception, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern.trim()),
+                None => (false, line),
+            };
+            let pattern = clean_pattern(pattern);
+            if pattern.is_empty() {
+                continue;
+            }
+
+            let regex = Regex::new(&glob_to_regex(&pattern)).map_err(|e| {
+                BuildError::Parse(format!("Invalid .dockerignore pattern {}: {}", line, e))
+            })?;
+            patterns.push(IgnorePattern { regex, exception });
+        }
+        Ok(Self { patterns })
+    }
+
+    /// Whether `path`, relative to the context root, is left out
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        // A path is matched along with every directory above it
+        let mut candidates = Vec::new();
+        let mut current = String::new();
+        for component in path.components() {
+            if let Component::Normal(name) = component {
+                if !current.is_empty() {
+                    current.push('/');
+                }
+                current.push_str(&name.to_string_lossy());
+                candidates.push(current.clone());
+            }
+        }
+
+        let mut excluded = false;
+        for pattern in &self.patterns {
+            if candidates
+                .iter()
+                .any(|candidate| pattern.regex.is_match(candidate))
+            {
+                excluded = !pattern.exception;
+            }
+        }
+        excluded
+    }
+
+    /// Whether any pattern re-includes files with `!`
+    pub fn has_exceptions(&self) -> bool {
+        self.patterns.iter().any(|pattern| pattern.exception)
+    }
+}
+
+/// Pattern with `.` and empty components and leading or trailing slashes
+/// removed, as Docker cleans them
+fn clean_pattern(pattern: &str) -> String {
+    pattern
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Anchored regex for a glob where `*` and `?` stay within one path
+/// component and `**` matches any number of them
+fn glob_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if matches!(chars.peek(), Some('!' | '^')) {
+                    chars.next();
+                    regex.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        regex.push('\\');
+                    }
+                    regex.push(c);
+                }
+                regex.push(']');
+            }
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    regex.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::path::PathBuf;
 use crate::BuildError;
 
@@ -12,8 +13,8 @@ pub enum Instruction {
     Label(HashMap<String, String>),
     Expose(Vec<u16>),
     Env(HashMap<String, String>),
-    Add(String, String), // src, dest
-    Copy(String, String), // src, dest
+    Add(String, String), // flags and sources, dest
+    Copy(String, String), // flags and sources, dest
     Entrypoint(Vec<String>),
     Volume(Vec<String>),
     User(String),
@@ -26,6 +27,15 @@ pub enum Instruction {
     Comment(String),
 }
 
+/// Secret made available to a single RUN step with
+/// `--mount=type=secret,id=<id>[,target=<path>]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretMount {
+    pub id: String,
+    /// Path of the secret inside the step
+    pub target: String,
+}
+
 /// Parsed Dockerfile
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dockerfile {
@@ -37,13 +47,20 @@ pub struct Dockerfile {
     pub volumes: Vec<String>,
     pub environment: HashMap<String, String>,
     pub labels: HashMap<String, String>,
+    /// Source line (1-based) of each instruction
+    #[serde(default)]
+    pub lines: Vec<usize>,
+    /// Name given with `AS` to each build stage, in order
+    #[serde(default)]
+    pub stages: Vec<Option<String>>,
 }
 
 impl Dockerfile {
     /// Parse a Dockerfile from string content
     pub fn parse(content: &str) -> Result<Self, BuildError> {
-        let lines: Vec<&str> = content.lines().collect();
         let mut instructions = Vec::new();
+        let mut lines = Vec::new();
+        let mut stages = Vec::new();
         let mut base_image = None;
         let mut working_dir = None;
         let mut user = None;
@@ -52,14 +69,16 @@ impl Dockerfile {
         let mut environment = HashMap::new();
         let mut labels = HashMap::new();
 
-        for line in lines {
+        for (line_number, line) in logical_lines(content) {
             let line = line.trim();
             if line.is_empty() || line.starts_with('#') {
                 if line.starts_with('#') {
                     instructions.push(Instruction::Comment(line[1..].trim().to_string()));
+                    lines.push(line_number);
                 }
                 continue;
             }
+            let instruction_count = instructions.len();
 
             let parts: Vec<&str> = line.split_whitespace().collect();
             if parts.is_empty() {
@@ -72,8 +91,22 @@ impl Dockerfile {
             match instruction.as_str() {
                 "FROM" => {
                     if let Some(image) = args.first() {
-                        let tag = if args.len() > 1 { Some(args[1].to_string()) } else { None };
+                        let mut rest = &args[1..];
+                        let tag = match rest.first() {
+                            Some(tag) if !tag.eq_ignore_ascii_case("AS") => {
+                                rest = &rest[1..];
+                                Some(tag.to_string())
+                            }
+                            _ => None,
+                        };
+                        let stage = match rest {
+                            [keyword, name, ..] if keyword.eq_ignore_ascii_case("AS") => {
+                                Some(name.to_string())
+                            }
+                            _ => None,
+                        };
                         base_image = Some(image.to_string());
+                        stages.push(stage);
                         instructions.push(Instruction::From(image.to_string(), tag));
                     }
                 }
@@ -110,13 +143,17 @@ impl Dockerfile {
                     instructions.push(Instruction::Env(environment.clone()));
                 }
                 "ADD" => {
-                    if args.len() >= 2 {
-                        instructions.push(Instruction::Add(args[0].to_string(), args[1].to_string()));
+                    if let [sources @ .., dest] = args {
+                        if !sources.is_empty() {
+                            instructions.push(Instruction::Add(sources.join(" "), dest.to_string()));
+                        }
                     }
                 }
                 "COPY" => {
-                    if args.len() >= 2 {
-                        instructions.push(Instruction::Copy(args[0].to_string(), args[1].to_string()));
+                    if let [sources @ .., dest] = args {
+                        if !sources.is_empty() {
+                            instructions.push(Instruction::Copy(sources.join(" "), dest.to_string()));
+                        }
                     }
                 }
                 "ENTRYPOINT" => {
@@ -167,6 +204,9 @@ impl Dockerfile {
                     return Err(BuildError::InvalidInstruction(format!("Unknown instruction: {}", instruction)));
                 }
             }
+            if instructions.len() > instruction_count {
+                lines.push(line_number);
+            }
         }
 
         Ok(Dockerfile {
@@ -178,9 +218,67 @@ impl Dockerfile {
             volumes,
             environment,
             labels,
+            lines,
+            stages,
         })
     }
 
+    /// Source paths of a COPY or ADD, without its `--flag` options
+    pub fn copy_sources(src: &str) -> impl Iterator<Item = &str> {
+        src.split_whitespace().filter(|arg| !arg.starts_with("--"))
+    }
+
+    /// Value of option `--name=value` of a COPY or ADD
+    pub fn copy_flag<'a>(src: &'a str, name: &str) -> Option<&'a str> {
+        src.split_whitespace()
+            .find_map(|arg| arg.strip_prefix("--")?.strip_prefix(name)?.strip_prefix('='))
+    }
+
+    /// Secret mounts among the `--mount` options of a RUN. Without an `id`
+    /// the secret is named after its target, which defaults to
+    /// `/run/secrets/<id>`.
+    pub fn secret_mounts(args: &[String]) -> Result<Vec<SecretMount>, BuildError> {
+        let mut mounts = Vec::new();
+        for option in args.iter().filter_map(|arg| arg.strip_prefix("--mount=")) {
+            let mut mount_type = None;
+            let mut id = None;
+            let mut target = None;
+            for field in option.split(',') {
+                match field.split_once('=') {
+                    Some(("type", value)) => mount_type = Some(value),
+                    Some(("id", value)) => id = Some(value.to_string()),
+                    Some(("target" | "dst" | "destination", value)) => {
+                        target = Some(value.to_string())
+                    }
+                    _ => {}
+                }
+            }
+            if mount_type != Some("secret") {
+                continue;
+            }
+
+            let id = match (id, &target) {
+                (Some(id), _) => id,
+                (None, Some(target)) => target.rsplit('/').next().unwrap_or_default().to_string(),
+                (None, None) => String::new(),
+            };
+            if id.is_empty() {
+                return Err(BuildError::InvalidInstruction(format!(
+                    "Secret mount without an id: --mount={}",
+                    option
+                )));
+            }
+            let target = target.unwrap_or_else(|| format!("/run/secrets/{}", id));
+            mounts.push(SecretMount { id, target });
+        }
+        Ok(mounts)
+    }
+
+    /// Source line of the instruction at `index`, or 0 if unknown
+    pub fn line_of(&self, index: usize) -> usize {
+        self.lines.get(index).copied().unwrap_or(0)
+    }
+
     /// Parse a Dockerfile from file
     pub fn from_file(path: &PathBuf) -> Result<Self, BuildError> {
         let content = std::fs::read_to_string(path)
@@ -188,6 +286,17 @@ impl Dockerfile {
         Self::parse(&content)
     }
 
+    /// SHA-256 of the parsed instructions
+    pub fn content_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        for instruction in &self.instructions {
+            hasher.update(format!("{:?}\n", instruction).as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
     /// Get the base image name
     pub fn get_base_image(&self) -> Option<&String> {
         self.base_image.as_ref()
@@ -223,3 +332,328 @@ impl Dockerfile {
         self.user.as_ref()
     }
 }
+
+/// Join lines continued with a trailing backslash, keeping the number of the
+/// line each instruction starts on. Comment lines inside a continuation are
+/// dropped, as Docker does.
+fn logical_lines(content: &str) -> Vec<(usize, String)> {
+    let mut logical = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') {
+            if pending.is_none() {
+                logical.push((index + 1, trimmed.to_string()));
+            }
+            continue;
+        }
+        let (start, mut joined) = pending.take().unwrap_or((index + 1, String::new()));
+        match trimmed.strip_suffix('\\') {
+            Some(continued) => {
+                joined.push_str(continued);
+                joined.push(' ');
+                pending = Some((start, joined));
+            }
+            None => {
+                joined.push_str(trimmed);
+                logical.push((start, joined));
+            }
+        }
+    }
+    if let Some(last) = pending {
+        logical.push(last);
+    }
+    logical
+}
+
+/// Severity of a lint diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LintSeverity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl fmt::Display for LintSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintSeverity::Error => write!(f, "error"),
+            LintSeverity::Warning => write!(f, "warning"),
+            LintSeverity::Info => write!(f, "info"),
+        }
+    }
+}
+
+/// Best-practice problem found in a Dockerfile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LintDiagnostic {
+    pub severity: LintSeverity,
+    /// Source line of the offending instruction
+    pub line: usize,
+    pub rule_id: String,
+    pub message: String,
+}
+
+impl LintDiagnostic {
+    fn new(severity: LintSeverity, line: usize, rule_id: &str, message: String) -> Self {
+        Self {
+            severity,
+            line,
+            rule_id: rule_id.to_string(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for LintDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {} [{}] {}",
+            self.line, self.severity, self.rule_id, self.message
+        )
+    }
+}
+
+/// Whether Dockerfiles are linted before building, and whether lint errors
+/// abort the build
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintConfig {
+    pub enabled: bool,
+    pub fail_on_error: bool,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            fail_on_error: false,
+        }
+    }
+}
+
+/// `COPY --chown` to a user no earlier `RUN` of the stage created
+pub const RULE_CHOWN_WITHOUT_USER: &str = "chown-without-user";
+/// Multi-stage build whose final stage has no name
+pub const RULE_UNNAMED_FINAL_STAGE: &str = "unnamed-final-stage";
+/// `ADD` of a local file that is not an archive
+pub const RULE_ADD_INSTEAD_OF_COPY: &str = "add-instead-of-copy";
+/// `ENV` variable that no later instruction references
+pub const RULE_UNUSED_ENV: &str = "unused-env";
+/// `RUN apt-get update` without `apt-get install` in the same layer
+pub const RULE_APT_GET_UPDATE_ALONE: &str = "apt-get-update-alone";
+
+/// Archive extensions that `ADD` extracts
+const ADD_ARCHIVE_EXTENSIONS: &[&str] = &[
+    ".tar", ".tar.gz", ".tgz", ".tar.bz2", ".tbz2", ".tar.xz", ".txz",
+];
+
+/// Checks parsed Dockerfiles against best-practice rules
+#[derive(Debug, Clone, Default)]
+pub struct DockerfileLinter;
+
+impl DockerfileLinter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Diagnostics for every rule `dockerfile` breaks, in source order
+    pub fn lint(&self, dockerfile: &Dockerfile) -> Vec<LintDiagnostic> {
+        let mut diagnostics = Vec::new();
+        self.check_chown(dockerfile, &mut diagnostics);
+        self.check_final_stage(dockerfile, &mut diagnostics);
+        self.check_add(dockerfile, &mut diagnostics);
+        self.check_unused_env(dockerfile, &mut diagnostics);
+        self.check_apt_get(dockerfile, &mut diagnostics);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+        diagnostics
+    }
+
+    fn check_chown(&self, dockerfile: &Dockerfile, diagnostics: &mut Vec<LintDiagnostic>) {
+        let mut users_created = false;
+        for (index, instruction) in dockerfile.instructions.iter().enumerate() {
+            match instruction {
+                Instruction::From(..) => users_created = false,
+                Instruction::Run(args) => {
+                    users_created |= args
+                        .iter()
+                        .any(|arg| matches!(arg.rsplit('/').next(), Some("adduser" | "useradd")));
+                }
+                Instruction::Copy(src, _) => {
+                    let Some(owner) = Dockerfile::copy_flag(src, "chown") else {
+                        continue;
+                    };
+                    let user = owner.split(':').next().unwrap_or(owner);
+                    let builtin = user == "root" || user.parse::<u32>().is_ok();
+                    if !users_created && !builtin {
+                        diagnostics.push(LintDiagnostic::new(
+                            LintSeverity::Error,
+                            dockerfile.line_of(index),
+                            RULE_CHOWN_WITHOUT_USER,
+                            format!(
+                                "COPY --chown={} before any RUN adduser creates user '{}'",
+                                owner, user
+                            ),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_final_stage(&self, dockerfile: &Dockerfile, diagnostics: &mut Vec<LintDiagnostic>) {
+        if dockerfile.stages.len() < 2 || !matches!(dockerfile.stages.last(), Some(None)) {
+            return;
+        }
+        let Some(index) = dockerfile
+            .instructions
+            .iter()
+            .rposition(|instruction| matches!(instruction, Instruction::From(..)))
+        else {
+            return;
+        };
+        diagnostics.push(LintDiagnostic::new(
+            LintSeverity::Warning,
+            dockerfile.line_of(index),
+            RULE_UNNAMED_FINAL_STAGE,
+            "Final stage of a multi-stage build has no name; add `AS <name>` to it".to_string(),
+        ));
+    }
+
+    fn check_add(&self, dockerfile: &Dockerfile, diagnostics: &mut Vec<LintDiagnostic>) {
+        for (index, instruction) in dockerfile.instructions.iter().enumerate() {
+            let Instruction::Add(src, _) = instruction else {
+                continue;
+            };
+            for source in Dockerfile::copy_sources(src) {
+                let remote = source.starts_with("http://") || source.starts_with("https://");
+                let archive = ADD_ARCHIVE_EXTENSIONS
+                    .iter()
+                    .any(|extension| source.ends_with(extension));
+                if !remote && !archive {
+                    diagnostics.push(LintDiagnostic::new(
+                        LintSeverity::Warning,
+                        dockerfile.line_of(index),
+                        RULE_ADD_INSTEAD_OF_COPY,
+                        format!(
+                            "ADD of local file '{}'; use COPY unless it is an archive or URL",
+                            source
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn check_unused_env(&self, dockerfile: &Dockerfile, diagnostics: &mut Vec<LintDiagnostic>) {
+        // ENV instructions carry every variable set so far, so only keys that
+        // are new or changed are defined by each one
+        let mut current: HashMap<&String, &String> = HashMap::new();
+        for (index, instruction) in dockerfile.instructions.iter().enumerate() {
+            let Instruction::Env(vars) = instruction else {
+                continue;
+            };
+            let mut defined: Vec<&String> = vars
+                .iter()
+                .filter(|(key, value)| current.get(key) != Some(value))
+                .map(|(key, _)| key)
+                .collect();
+            defined.sort();
+            current.extend(vars.iter());
+
+            for key in defined {
+                let used = dockerfile.instructions[index..]
+                    .iter()
+                    .flat_map(instruction_words)
+                    .any(|word| references(word, key));
+                if !used {
+                    diagnostics.push(LintDiagnostic::new(
+                        LintSeverity::Info,
+                        dockerfile.line_of(index),
+                        RULE_UNUSED_ENV,
+                        format!("ENV {} is never referenced by the Dockerfile", key),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn check_apt_get(&self, dockerfile: &Dockerfile, diagnostics: &mut Vec<LintDiagnostic>) {
+        for (index, instruction) in dockerfile.instructions.iter().enumerate() {
+            let Instruction::Run(args) = instruction else {
+                continue;
+            };
+            let subcommands = apt_get_subcommands(args);
+            if subcommands.contains(&"update") && !subcommands.contains(&"install") {
+                diagnostics.push(LintDiagnostic::new(
+                    LintSeverity::Warning,
+                    dockerfile.line_of(index),
+                    RULE_APT_GET_UPDATE_ALONE,
+                    "apt-get update without apt-get install in the same RUN caches a stale package index"
+                        .to_string(),
+                ));
+            }
+        }
+    }
+}
+
+/// Strings of an instruction that may reference variables
+fn instruction_words(instruction: &Instruction) -> Vec<&str> {
+    match instruction {
+        Instruction::From(image, tag) => std::iter::once(image)
+            .chain(tag)
+            .map(String::as_str)
+            .collect(),
+        Instruction::Run(args)
+        | Instruction::Cmd(args)
+        | Instruction::Entrypoint(args)
+        | Instruction::Volume(args)
+        | Instruction::Shell(args) => args.iter().map(String::as_str).collect(),
+        Instruction::Env(values) | Instruction::Label(values) => {
+            values.values().map(String::as_str).collect()
+        }
+        Instruction::Add(src, dest) | Instruction::Copy(src, dest) => {
+            vec![src.as_str(), dest.as_str()]
+        }
+        Instruction::User(value)
+        | Instruction::Workdir(value)
+        | Instruction::Onbuild(value)
+        | Instruction::StopSignal(value)
+        | Instruction::Healthcheck(value) => vec![value.as_str()],
+        Instruction::Arg(_, default) => default.iter().map(String::as_str).collect(),
+        Instruction::Expose(_) | Instruction::Comment(_) => Vec::new(),
+    }
+}
+
+/// Whether `word` expands variable `key`, as `$key` or `${key...}`
+fn references(word: &str, key: &str) -> bool {
+    let braced = format!("${{{}", key);
+    let plain = format!("${}", key);
+    word.match_indices(&braced)
+        .any(|(start, _)| matches!(word[start + braced.len()..].chars().next(), Some('}' | ':')))
+        || word.match_indices(&plain).any(|(start, _)| {
+            !word[start + plain.len()..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// Subcommands of every `apt-get` call in a RUN, skipping options
+fn apt_get_subcommands(args: &[String]) -> Vec<&str> {
+    let mut subcommands = Vec::new();
+    for (position, arg) in args.iter().enumerate() {
+        if arg.rsplit('/').next() != Some("apt-get") {
+            continue;
+        }
+        if let Some(subcommand) = args[position + 1..]
+            .iter()
+            .find(|arg| !arg.starts_with('-'))
+        {
+            subcommands.push(subcommand.as_str());
+        }
+    }
+    subcommands
+}
@@ -26,6 +26,9 @@ pub enum BuildError {
     #[error("Missing dependency: {0}")]
     MissingDependency(String),
     
+    #[error("Platform error: {0}")]
+    Platform(String),
+    
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
@@ -0,0 +1,228 @@
+use crate::{BuildError, BuildOptions, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Default number of build records kept on disk
+pub const DEFAULT_MAX_RECORDS: usize = 100;
+
+/// Build options as recorded in the build history
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordedOptions {
+    pub tag: Option<String>,
+    pub no_cache: bool,
+    pub pull: bool,
+    pub build_args: HashMap<String, String>,
+    pub target: Option<String>,
+    /// Target platforms as `os/arch`
+    #[serde(default)]
+    pub platforms: Vec<String>,
+}
+
+impl From<&BuildOptions> for RecordedOptions {
+    fn from(options: &BuildOptions) -> Self {
+        Self {
+            tag: options.tag.clone(),
+            no_cache: options.no_cache,
+            pull: options.pull,
+            build_args: options.build_args.clone(),
+            target: options.target.clone(),
+            platforms: options.platforms.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+/// Record of a single build, successful or not
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildRecord {
+    /// Unique build identifier
+    pub id: String,
+    /// Tag requested for the build
+    pub tag: Option<String>,
+    /// SHA-256 of the parsed Dockerfile
+    pub dockerfile_hash: String,
+    /// SHA-256 over the build context file paths and contents, empty if
+    /// the context could not be read
+    pub context_digest: String,
+    /// When the build started
+    pub started_at: DateTime<Utc>,
+    /// Wall-clock build duration
+    pub duration: Duration,
+    /// Number of instructions served from the cache
+    pub cache_hits: usize,
+    /// Number of instructions that had to be executed
+    pub cache_misses: usize,
+    /// Resulting image id, if the build succeeded
+    pub image_id: Option<String>,
+    /// Layers produced by the build
+    pub layers: Vec<String>,
+    /// Options used for the build
+    pub options: RecordedOptions,
+    /// Error message, if the build failed
+    pub error: Option<String>,
+}
+
+impl BuildRecord {
+    /// Whether the build succeeded
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Fraction of cacheable instructions served from the cache
+    pub fn cache_hit_ratio(&self) -> f64 {
+        let total = self.cache_hits + self.cache_misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.cache_hits as f64 / total as f64
+        }
+    }
+
+    /// Whether both records were built from the same inputs
+    pub fn same_inputs(&self, other: &BuildRecord) -> bool {
+        self.dockerfile_hash == other.dockerfile_hash
+            && self.context_digest == other.context_digest
+            && self.options.build_args == other.options.build_args
+            && self.options.target == other.options.target
+            && self.options.platforms == other.options.platforms
+    }
+}
+
+/// Filter for build history queries
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    /// Only records with this tag
+    pub tag: Option<String>,
+    /// Only successful (`Some(true)`) or failed (`Some(false)`) builds
+    pub succeeded: Option<bool>,
+    /// Only builds started at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Maximum number of records returned, most recent first
+    pub limit: Option<usize>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, record: &BuildRecord) -> bool {
+        if let Some(tag) = &self.tag {
+            if record.tag.as_ref() != Some(tag) {
+                return false;
+            }
+        }
+        if let Some(succeeded) = self.succeeded {
+            if record.succeeded() != succeeded {
+                return false;
+            }
+        }
+        if let Some(since) = self.since {
+            if record.started_at < since {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Bounded build history persisted under the build directory
+#[derive(Debug)]
+pub struct BuildHistory {
+    pub path: PathBuf,
+    pub records: VecDeque<BuildRecord>,
+    pub max_records: usize,
+}
+
+impl BuildHistory {
+    /// Load the build history stored in `build_dir`
+    pub fn load(build_dir: &Path) -> Result<Self> {
+        let path = build_dir.join("history.json");
+        let records = if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            serde_json::from_str(&content)
+                .map_err(|e| BuildError::Parse(format!("Failed to parse build history: {}", e)))?
+        } else {
+            VecDeque::new()
+        };
+
+        Ok(Self {
+            path,
+            records,
+            max_records: DEFAULT_MAX_RECORDS,
+        })
+    }
+
+    /// Set the maximum number of records kept
+    pub fn set_max_records(&mut self, max_records: usize) -> Result<()> {
+        self.max_records = max_records;
+        self.enforce_limit();
+        self.save()
+    }
+
+    /// Append a record, evicting the oldest ones beyond the limit
+    pub fn record(&mut self, record: BuildRecord) -> Result<()> {
+        self.records.push_back(record);
+        self.enforce_limit();
+        self.save()
+    }
+
+    /// Query records matching the filter, most recent first
+    pub fn query(&self, filter: &HistoryFilter) -> Vec<&BuildRecord> {
+        let matching = self.records.iter().rev().filter(|r| filter.matches(r));
+        match filter.limit {
+            Some(limit) => matching.take(limit).collect(),
+            None => matching.collect(),
+        }
+    }
+
+    /// Get a record by id
+    pub fn get(&self, id: &str) -> Option<&BuildRecord> {
+        self.records.iter().find(|r| r.id == id)
+    }
+
+    /// Pairs of successful builds with the same inputs but different images
+    pub fn find_nondeterministic(&self) -> Vec<(&BuildRecord, &BuildRecord)> {
+        let successful: Vec<&BuildRecord> = self.records.iter().filter(|r| r.succeeded()).collect();
+
+        let mut pairs = Vec::new();
+        for (index, first) in successful.iter().enumerate() {
+            for second in &successful[index + 1..] {
+                if first.same_inputs(second) && first.image_id != second.image_id {
+                    pairs.push((*first, *second));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Ids of records and layers referenced by the history
+    pub fn referenced_ids(&self) -> HashSet<String> {
+        let mut ids = HashSet::new();
+        for record in &self.records {
+            ids.insert(record.id.clone());
+            ids.extend(record.layers.iter().cloned());
+        }
+        ids
+    }
+
+    fn enforce_limit(&mut self) {
+        while self.records.len() > self.max_records {
+            self.records.pop_front();
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self.records)
+            .map_err(|e| BuildError::Parse(format!("Failed to serialize build history: {}", e)))?;
+        std::fs::write(&self.path, content)?;
+        Ok(())
+    }
+}
+
+/// Result of a build garbage collection run
+#[derive(Debug, Clone, Default)]
+pub struct GcReport {
+    /// Paths removed by the collection
+    pub removed: Vec<PathBuf>,
+    /// Bytes reclaimed
+    pub reclaimed_bytes: u64,
+}
@@ -8,15 +8,23 @@
 //! - Build context management
 //! - Image layer caching
 //! - Build optimization
+//! - Build history and garbage collection
+//! - Multi-platform builds
 
 pub mod dockerfile;
 pub mod builder;
 pub mod context;
 pub mod cache;
 pub mod error;
+pub mod history;
+pub mod manifest;
+pub mod platform;
 
 pub use dockerfile::*;
 pub use builder::*;
 pub use context::*;
 pub use cache::*;
-pub use error::*;
\ No newline at end of file
+pub use error::*;
+pub use history::*;
+pub use manifest::*;
+pub use platform::*;
\ No newline at end of file
@@ -0,0 +1,101 @@
+use crate::{BuildError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Target platform of a build, with the architecture in the OCI vocabulary
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BuildPlatform {
+    pub os: String,
+    #[serde(rename = "architecture")]
+    pub arch: String,
+}
+
+impl BuildPlatform {
+    pub fn new(os: impl Into<String>, arch: impl Into<String>) -> Self {
+        Self {
+            os: os.into(),
+            arch: arch.into(),
+        }
+    }
+
+    /// Parse an `os/arch` string such as `linux/arm64`
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.split('/').collect::<Vec<_>>().as_slice() {
+            [os, arch] if !os.is_empty() && !arch.is_empty() => Ok(Self::new(*os, *arch)),
+            _ => Err(BuildError::Platform(format!(
+                "Invalid platform '{}', expected os/arch",
+                value
+            ))),
+        }
+    }
+
+    /// Platform of the host
+    pub fn host() -> Self {
+        let arch = match std::env::consts::ARCH {
+            "x86_64" => "amd64",
+            "x86" => "386",
+            "aarch64" => "arm64",
+            "powerpc64" => "ppc64le",
+            other => other,
+        };
+        Self::new(std::env::consts::OS, arch)
+    }
+
+    /// Whether the host runs this platform's binaries natively
+    pub fn is_native(&self) -> bool {
+        self.arch == Self::host().arch
+    }
+
+    /// Architecture name used by the QEMU user-mode emulators
+    pub fn qemu_arch(&self) -> &str {
+        match self.arch.as_str() {
+            "amd64" => "x86_64",
+            "386" => "i386",
+            "arm64" => "aarch64",
+            "ppc64le" => "ppc64le",
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for BuildPlatform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.os, self.arch)
+    }
+}
+
+/// Directories listed in `PATH`, where QEMU emulators are looked up by default
+pub fn default_qemu_search_path() -> Vec<PathBuf> {
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default()
+}
+
+/// Find the QEMU user-mode emulator for a platform, preferring the static
+/// build that also works inside the build root
+pub fn find_qemu(platform: &BuildPlatform, search_path: &[PathBuf]) -> Option<PathBuf> {
+    let names = [
+        format!("qemu-{}-static", platform.qemu_arch()),
+        format!("qemu-{}", platform.qemu_arch()),
+    ];
+    names.iter().find_map(|name| {
+        search_path
+            .iter()
+            .map(|dir| dir.join(name))
+            .find(|path| is_executable(path))
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
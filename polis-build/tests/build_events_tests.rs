@@ -0,0 +1,141 @@
+use polis_build::{BuildContext, BuildEvent, BuildOptions, Dockerfile, ImageBuilder};
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+
+const DOCKERFILE: &str = "FROM alpine:3.19\n\
+                          ENV APP=/app\n\
+                          COPY app.txt /app/app.txt\n\
+                          RUN cat /app/app.txt\n";
+
+fn context(dockerfile: &str) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("Dockerfile"), dockerfile).unwrap();
+    std::fs::write(dir.path().join("app.txt"), "hello").unwrap();
+    dir
+}
+
+async fn build(
+    builder: &mut ImageBuilder,
+    context_dir: &TempDir,
+    dockerfile: &str,
+    options: BuildOptions,
+) -> (bool, Vec<BuildEvent>) {
+    let context = BuildContext::new(context_dir.path().to_path_buf()).unwrap();
+    let dockerfile = Dockerfile::parse(dockerfile).unwrap();
+    let (events, mut receiver) = mpsc::channel(256);
+    let result = builder
+        .build_image_with_events(context, dockerfile, options, events)
+        .await;
+
+    let mut received = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        received.push(event);
+    }
+    (result.is_ok(), received)
+}
+
+/// Events without timings and log lines, e.g. "started 1/4" or "cached"
+fn outline(events: &[BuildEvent]) -> Vec<String> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            BuildEvent::StepStarted { step, total, .. } => Some(format!("started {step}/{total}")),
+            BuildEvent::StepCompleted { step, .. } => Some(format!("completed {step}")),
+            BuildEvent::LayerPulled { .. } => Some("pulled".to_string()),
+            BuildEvent::LayerCached { .. } => Some("cached".to_string()),
+            BuildEvent::BuildError { step, .. } => Some(format!("error {step}")),
+            BuildEvent::LogLine { .. } => None,
+        })
+        .collect()
+}
+
+fn options(pull: bool) -> BuildOptions {
+    BuildOptions {
+        pull,
+        progress: false,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_events_follow_the_build_steps() {
+    let context_dir = context(DOCKERFILE);
+    let build_dir = TempDir::new().unwrap();
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+
+    let (succeeded, events) = build(&mut builder, &context_dir, DOCKERFILE, options(true)).await;
+    assert!(succeeded);
+    assert_eq!(
+        outline(&events),
+        vec![
+            "started 1/4",
+            "pulled",
+            "completed 1",
+            "started 2/4",
+            "completed 2",
+            "started 3/4",
+            "completed 3",
+            "started 4/4",
+            "completed 4",
+        ]
+    );
+    assert!(events.iter().any(|event| matches!(
+        event,
+        BuildEvent::LogLine { step: 4, line } if line.contains("cat /app/app.txt")
+    )));
+
+    // The second build finds the COPY and RUN layers in the cache
+    let (succeeded, events) = build(&mut builder, &context_dir, DOCKERFILE, options(false)).await;
+    assert!(succeeded);
+    assert_eq!(
+        outline(&events),
+        vec![
+            "started 1/4",
+            "completed 1",
+            "started 2/4",
+            "completed 2",
+            "started 3/4",
+            "cached",
+            "completed 3",
+            "started 4/4",
+            "cached",
+            "completed 4",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_failed_step_ends_with_build_error() {
+    let dockerfile = "FROM alpine\nCOPY missing.txt /missing.txt\nRUN true\n";
+    let context_dir = context(dockerfile);
+    let build_dir = TempDir::new().unwrap();
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+
+    let (succeeded, events) = build(&mut builder, &context_dir, dockerfile, options(false)).await;
+    assert!(!succeeded);
+    assert_eq!(
+        outline(&events),
+        vec!["started 1/3", "completed 1", "started 2/3", "error 2"]
+    );
+    let Some(BuildEvent::BuildError { message, .. }) = events.last() else {
+        panic!("expected a build error, got {:?}", events.last());
+    };
+    assert!(message.contains("missing.txt"), "{message}");
+}
+
+#[tokio::test]
+async fn test_dropped_receiver_does_not_stop_the_build() {
+    let context_dir = context(DOCKERFILE);
+    let build_dir = TempDir::new().unwrap();
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+
+    let context = BuildContext::new(context_dir.path().to_path_buf()).unwrap();
+    let dockerfile = Dockerfile::parse(DOCKERFILE).unwrap();
+    let (events, receiver) = mpsc::channel(1);
+    drop(receiver);
+
+    let result = builder
+        .build_image_with_events(context, dockerfile, options(false), events)
+        .await;
+    assert!(result.is_ok());
+}
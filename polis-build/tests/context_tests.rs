@@ -0,0 +1,118 @@
+use polis_build::{BuildContext, DockerIgnore};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+fn write(root: &Path, relative: &str, content: &str) {
+    let path = root.join(relative);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+fn paths(relative: &[&str]) -> Vec<PathBuf> {
+    relative.iter().map(PathBuf::from).collect()
+}
+
+fn context_with(dockerignore: Option<&str>) -> (TempDir, BuildContext) {
+    let dir = TempDir::new().unwrap();
+    write(dir.path(), "Dockerfile", "FROM alpine\n");
+    write(dir.path(), "app.py", "print('hi')\n");
+    write(dir.path(), "secrets.env", "TOKEN=abc\n");
+    write(dir.path(), "debug.log", "0123456789");
+    write(dir.path(), "docs/guide.md", "# Guide\n");
+    write(dir.path(), "docs/README.md", "# Docs\n");
+    write(dir.path(), "src/main.log", "log\n");
+    write(dir.path(), "build/app.bin", "binary");
+    if let Some(content) = dockerignore {
+        write(dir.path(), ".dockerignore", content);
+    }
+    let context = BuildContext::new(dir.path().to_path_buf()).unwrap();
+    (dir, context)
+}
+
+#[test]
+fn test_dockerignore_excludes_matching_files() {
+    let (_dir, context) = context_with(Some(
+        "# secrets and build output\n\
+         secrets.env\n\
+         *.log\n\
+         /build/\n\
+         docs\n\
+         !docs/README.md\n",
+    ));
+
+    // `*.log` only matches at the root
+    assert_eq!(
+        context.file_list().unwrap(),
+        paths(&["Dockerfile", "app.py", "docs/README.md", "src/main.log"])
+    );
+    assert_eq!(context.get_file_count(), 4);
+    assert!(context.dockerignore.is_some());
+    assert!(context.is_valid());
+}
+
+#[test]
+fn test_double_star_matches_any_depth() {
+    let (_dir, context) = context_with(Some("**/*.log\n**/*.md\n!docs/*.md\ndocs/guide.md\n"));
+
+    assert_eq!(
+        context.file_list().unwrap(),
+        paths(&[
+            "Dockerfile",
+            "app.py",
+            "build/app.bin",
+            "docs/README.md",
+            "secrets.env"
+        ])
+    );
+}
+
+#[test]
+fn test_total_size_counts_surviving_files() {
+    let (_dir, everything) = context_with(None);
+    let (_ignored_dir, filtered) = context_with(Some("debug.log\nbuild\n"));
+
+    // debug.log is 10 bytes and build/app.bin 6
+    assert_eq!(everything.total_size() - filtered.total_size(), 16);
+    assert_eq!(filtered.total_size(), filtered.get_size());
+}
+
+#[test]
+fn test_dockerfile_is_found_even_when_ignored() {
+    let (_dir, context) = context_with(Some("Dockerfile\n.dockerignore\n"));
+
+    assert!(!context
+        .file_list()
+        .unwrap()
+        .contains(&PathBuf::from("Dockerfile")));
+    assert!(context.get_dockerfile().is_some());
+}
+
+#[test]
+fn test_context_tar_holds_only_surviving_files() {
+    let (_dir, context) = context_with(Some("secrets.env\nbuild\n"));
+
+    let archive = context.create_tar_archive().unwrap();
+    let mut archive = tar::Archive::new(archive.as_slice());
+    let mut names: Vec<PathBuf> = archive
+        .entries()
+        .unwrap()
+        .map(|entry| entry.unwrap().path().unwrap().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names, context.file_list().unwrap());
+    assert!(!names.contains(&PathBuf::from("secrets.env")));
+}
+
+#[test]
+fn test_pattern_syntax() {
+    let ignore = DockerIgnore::parse("./tmp/\nfile?.txt\n[abc].bin\n[!x]y\n").unwrap();
+
+    assert!(ignore.is_excluded(Path::new("tmp/cache/data")));
+    assert!(ignore.is_excluded(Path::new("file1.txt")));
+    assert!(!ignore.is_excluded(Path::new("file10.txt")));
+    assert!(ignore.is_excluded(Path::new("b.bin")));
+    assert!(!ignore.is_excluded(Path::new("d.bin")));
+    assert!(ignore.is_excluded(Path::new("ay")));
+    assert!(!ignore.is_excluded(Path::new("xy")));
+    assert!(!ignore.has_exceptions());
+}
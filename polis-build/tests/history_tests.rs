@@ -0,0 +1,211 @@
+use polis_build::{BuildContext, BuildOptions, Dockerfile, HistoryFilter, ImageBuilder};
+use std::path::Path;
+use tempfile::TempDir;
+
+fn create_context(dir: &Path, dockerfile: &str) -> BuildContext {
+    std::fs::write(dir.join("Dockerfile"), dockerfile).unwrap();
+    std::fs::write(dir.join("app.txt"), "hello").unwrap();
+    BuildContext::new(dir.to_path_buf()).unwrap()
+}
+
+fn options(tag: &str) -> BuildOptions {
+    BuildOptions {
+        tag: Some(tag.to_string()),
+        progress: false,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_build_records_contents() {
+    let context_dir = TempDir::new().unwrap();
+    let build_dir = TempDir::new().unwrap();
+    let dockerfile_content = "FROM alpine:3.19\nRUN echo hello\nCOPY app.txt /app.txt\n";
+
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+
+    for _ in 0..2 {
+        let context = create_context(context_dir.path(), dockerfile_content);
+        let dockerfile = Dockerfile::parse(dockerfile_content).unwrap();
+        builder
+            .build_image(context, dockerfile, options("app:1"))
+            .await
+            .unwrap();
+    }
+
+    let records = builder.history().query(&HistoryFilter::default());
+    assert_eq!(records.len(), 2);
+
+    // Mais recente primeiro: o segundo build usa apenas o cache
+    let (latest, first) = (records[0], records[1]);
+    assert_eq!(first.cache_hits, 0);
+    assert_eq!(first.cache_misses, 2);
+    assert_eq!(first.layers.len(), 2);
+    assert_eq!(latest.cache_hits, 2);
+    assert_eq!(latest.cache_hit_ratio(), 1.0);
+    assert!(latest.layers.is_empty());
+
+    assert_eq!(first.tag.as_deref(), Some("app:1"));
+    assert_eq!(first.context_digest, latest.context_digest);
+    assert_eq!(first.dockerfile_hash, latest.dockerfile_hash);
+    assert!(first.image_id.is_some());
+    assert!(first.error.is_none());
+    assert!(first.same_inputs(latest));
+
+    // History is persisted under the build directory
+    let reloaded = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+    assert_eq!(reloaded.history().records.len(), 2);
+}
+
+#[tokio::test]
+async fn test_failed_build_is_recorded() {
+    let context_dir = TempDir::new().unwrap();
+    let build_dir = TempDir::new().unwrap();
+    let dockerfile_content = "FROM alpine:3.19\nCOPY missing.txt /missing.txt\n";
+
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+    let context = create_context(context_dir.path(), dockerfile_content);
+    let dockerfile = Dockerfile::parse(dockerfile_content).unwrap();
+
+    let result = builder
+        .build_image(context, dockerfile, options("broken:1"))
+        .await;
+    assert!(result.is_err());
+
+    let failed = builder.history().query(&HistoryFilter {
+        succeeded: Some(false),
+        ..Default::default()
+    });
+    assert_eq!(failed.len(), 1);
+    assert!(failed[0].image_id.is_none());
+    assert!(failed[0].error.as_ref().unwrap().contains("missing.txt"));
+}
+
+#[tokio::test]
+async fn test_unreadable_context_is_recorded() {
+    let context_dir = TempDir::new().unwrap();
+    let build_dir = TempDir::new().unwrap();
+    let dockerfile_content = "FROM alpine:3.19\nCOPY app.txt /app.txt\n";
+
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+    let context = create_context(context_dir.path(), dockerfile_content);
+    let dockerfile = Dockerfile::parse(dockerfile_content).unwrap();
+    // The file disappears between scanning the context and building it
+    std::fs::remove_file(context_dir.path().join("app.txt")).unwrap();
+
+    let result = builder
+        .build_image(context, dockerfile, options("gone:1"))
+        .await;
+    assert!(result.is_err());
+
+    let records = builder.history().query(&HistoryFilter::default());
+    assert_eq!(records.len(), 1);
+    assert!(records[0].context_digest.is_empty());
+    assert!(records[0].image_id.is_none());
+    assert!(records[0].error.is_some());
+}
+
+#[tokio::test]
+async fn test_bounded_history_eviction() {
+    let context_dir = TempDir::new().unwrap();
+    let build_dir = TempDir::new().unwrap();
+    let dockerfile_content = "FROM alpine:3.19\n";
+
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+    builder.history.set_max_records(3).unwrap();
+
+    for i in 0..5 {
+        let context = create_context(context_dir.path(), dockerfile_content);
+        let dockerfile = Dockerfile::parse(dockerfile_content).unwrap();
+        builder
+            .build_image(context, dockerfile, options(&format!("app:{}", i)))
+            .await
+            .unwrap();
+    }
+
+    let tags: Vec<String> = builder
+        .history()
+        .query(&HistoryFilter::default())
+        .iter()
+        .map(|r| r.tag.clone().unwrap())
+        .collect();
+    assert_eq!(tags, vec!["app:4", "app:3", "app:2"]);
+}
+
+#[tokio::test]
+async fn test_history_query_filters() {
+    let context_dir = TempDir::new().unwrap();
+    let build_dir = TempDir::new().unwrap();
+    let dockerfile_content = "FROM alpine:3.19\n";
+
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+    for tag in ["web:1", "api:1", "web:2", "web:1"] {
+        let context = create_context(context_dir.path(), dockerfile_content);
+        let dockerfile = Dockerfile::parse(dockerfile_content).unwrap();
+        builder
+            .build_image(context, dockerfile, options(tag))
+            .await
+            .unwrap();
+    }
+
+    let history = builder.history();
+    let web1 = history.query(&HistoryFilter {
+        tag: Some("web:1".to_string()),
+        ..Default::default()
+    });
+    assert_eq!(web1.len(), 2);
+
+    let limited = history.query(&HistoryFilter {
+        limit: Some(1),
+        ..Default::default()
+    });
+    assert_eq!(limited.len(), 1);
+    assert_eq!(limited[0].tag.as_deref(), Some("web:1"));
+
+    let future = history.query(&HistoryFilter {
+        since: Some(chrono::Utc::now() + chrono::Duration::hours(1)),
+        ..Default::default()
+    });
+    assert!(future.is_empty());
+}
+
+#[tokio::test]
+async fn test_gc_removes_only_unreferenced_artifacts() {
+    let context_dir = TempDir::new().unwrap();
+    let build_dir = TempDir::new().unwrap();
+    let dockerfile_content = "FROM alpine:3.19\nRUN echo hello\n";
+
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+    let context = create_context(context_dir.path(), dockerfile_content);
+    let dockerfile = Dockerfile::parse(dockerfile_content).unwrap();
+    builder
+        .build_image(context, dockerfile, options("app:1"))
+        .await
+        .unwrap();
+
+    let record = builder.history().records[0].clone();
+    let layer_id = record.layers[0].clone();
+
+    // Fixture: um layer e um staging referenciados, e dois órfãos
+    let layers_dir = builder.layers_dir();
+    let staging_dir = builder.staging_dir();
+    std::fs::create_dir_all(&layers_dir).unwrap();
+    std::fs::create_dir_all(staging_dir.join(&record.id)).unwrap();
+    std::fs::create_dir_all(staging_dir.join("orphan-build")).unwrap();
+    std::fs::write(
+        staging_dir.join("orphan-build").join("rootfs.tar"),
+        [0u8; 16],
+    )
+    .unwrap();
+    std::fs::write(layers_dir.join(format!("{}.tar", layer_id)), b"layer").unwrap();
+    std::fs::write(layers_dir.join("orphan-layer.tar"), b"orphan").unwrap();
+
+    let report = builder.gc().unwrap();
+
+    assert_eq!(report.removed.len(), 2);
+    assert_eq!(report.reclaimed_bytes, 16 + 6);
+    assert!(staging_dir.join(&record.id).exists());
+    assert!(!staging_dir.join("orphan-build").exists());
+    assert!(layers_dir.join(format!("{}.tar", layer_id)).exists());
+    assert!(!layers_dir.join("orphan-layer.tar").exists());
+}
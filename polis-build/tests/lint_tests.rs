@@ -0,0 +1,173 @@
+use polis_build::{
+    BuildContext, BuildOptions, Dockerfile, DockerfileLinter, ImageBuilder, LintConfig,
+    LintDiagnostic, LintSeverity, RULE_ADD_INSTEAD_OF_COPY, RULE_APT_GET_UPDATE_ALONE,
+    RULE_CHOWN_WITHOUT_USER, RULE_UNNAMED_FINAL_STAGE, RULE_UNUSED_ENV,
+};
+use tempfile::TempDir;
+
+fn lint(content: &str) -> Vec<LintDiagnostic> {
+    let dockerfile = Dockerfile::parse(content).unwrap();
+    DockerfileLinter::new().lint(&dockerfile)
+}
+
+/// (line, severity) of every diagnostic for `rule_id`
+fn found(diagnostics: &[LintDiagnostic], rule_id: &str) -> Vec<(usize, LintSeverity)> {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.rule_id == rule_id)
+        .map(|diagnostic| (diagnostic.line, diagnostic.severity))
+        .collect()
+}
+
+#[test]
+fn test_clean_dockerfile_has_no_diagnostics() {
+    let diagnostics = lint(
+        "FROM rust:1.75 AS builder\n\
+         ENV APP_HOME=/app\n\
+         WORKDIR $APP_HOME\n\
+         RUN apt-get update && \\\n\
+         \x20   apt-get install -y libssl-dev\n\
+         \n\
+         FROM debian:bookworm AS runtime\n\
+         RUN useradd app\n\
+         COPY --chown=app:app app /usr/local/bin/app\n\
+         ADD rootfs.tar.gz /\n",
+    );
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+}
+
+#[test]
+fn test_chown_requires_user_created_in_stage() {
+    let diagnostics = lint(
+        "FROM alpine\n\
+         RUN adduser -D app\n\
+         COPY --chown=app app /app\n\
+         FROM alpine AS final\n\
+         COPY --chown=app:app app /app\n\
+         COPY --chown=1000:1000 app /app\n\
+         COPY --chown=root app /app\n",
+    );
+    // The user created in the first stage does not exist in the second
+    assert_eq!(
+        found(&diagnostics, RULE_CHOWN_WITHOUT_USER),
+        vec![(5, LintSeverity::Error)]
+    );
+}
+
+#[test]
+fn test_multi_stage_build_needs_named_final_stage() {
+    let diagnostics = lint("FROM golang AS build\nRUN go build\n\nFROM alpine\nCMD app\n");
+    assert_eq!(
+        found(&diagnostics, RULE_UNNAMED_FINAL_STAGE),
+        vec![(4, LintSeverity::Warning)]
+    );
+
+    // A single stage needs no name
+    assert!(lint("FROM alpine\nCMD app\n").is_empty());
+}
+
+#[test]
+fn test_add_of_local_file_suggests_copy() {
+    let diagnostics = lint(
+        "FROM alpine\n\
+         ADD config.yaml /etc/app/\n\
+         ADD https://example.com/tool /usr/bin/tool\n\
+         ADD vendor.tgz /opt/\n",
+    );
+    assert_eq!(
+        found(&diagnostics, RULE_ADD_INSTEAD_OF_COPY),
+        vec![(2, LintSeverity::Warning)]
+    );
+}
+
+#[test]
+fn test_env_never_referenced_is_reported() {
+    let diagnostics = lint(
+        "FROM alpine\n\
+         ENV PREFIX=/opt UNUSED=1\n\
+         ENV BIN=${PREFIX}/bin\n\
+         RUN mkdir -p $BIN\n\
+         ENV LATE=1\n",
+    );
+    assert_eq!(
+        found(&diagnostics, RULE_UNUSED_ENV),
+        vec![(2, LintSeverity::Info), (5, LintSeverity::Info)]
+    );
+    let messages: Vec<&str> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+    assert!(messages[0].contains("UNUSED"));
+    assert!(messages[1].contains("LATE"));
+
+    // $PREFIXES is another variable
+    let diagnostics = lint("FROM alpine\nENV PREFIX=/opt\nRUN echo $PREFIXES\n");
+    assert_eq!(found(&diagnostics, RULE_UNUSED_ENV).len(), 1);
+}
+
+#[test]
+fn test_apt_get_update_needs_install_in_same_layer() {
+    let diagnostics = lint(
+        "FROM debian\n\
+         RUN apt-get update\n\
+         RUN apt-get -y install curl\n\
+         RUN apt-get update && apt-get -y install git\n",
+    );
+    assert_eq!(
+        found(&diagnostics, RULE_APT_GET_UPDATE_ALONE),
+        vec![(2, LintSeverity::Warning)]
+    );
+}
+
+#[test]
+fn test_parser_tracks_lines_and_stage_names() {
+    let dockerfile = Dockerfile::parse(
+        "# builder\n\
+         FROM rust:1.75 as builder\n\
+         RUN cargo build \\\n\
+         \x20   --release\n\
+         FROM alpine 3.19\n",
+    )
+    .unwrap();
+    assert_eq!(dockerfile.lines, vec![1, 2, 3, 5]);
+    assert_eq!(dockerfile.stages, vec![Some("builder".to_string()), None]);
+    assert_eq!(
+        dockerfile.instructions[2],
+        polis_build::Instruction::Run(vec![
+            "cargo".to_string(),
+            "build".to_string(),
+            "--release".to_string()
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_lint_errors_abort_build_only_when_configured() {
+    let content = "FROM alpine\nCOPY --chown=app app.txt /app.txt\n";
+    let context_dir = TempDir::new().unwrap();
+    let build_dir = TempDir::new().unwrap();
+    std::fs::write(context_dir.path().join("Dockerfile"), content).unwrap();
+    std::fs::write(context_dir.path().join("app.txt"), "hello").unwrap();
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+
+    let mut results = Vec::new();
+    for fail_on_error in [false, true] {
+        let context = BuildContext::new(context_dir.path().to_path_buf()).unwrap();
+        let options = BuildOptions {
+            progress: false,
+            no_cache: true,
+            lint: LintConfig {
+                enabled: true,
+                fail_on_error,
+            },
+            ..Default::default()
+        };
+        let dockerfile = Dockerfile::parse(content).unwrap();
+        results.push(builder.build_image(context, dockerfile, options).await);
+    }
+
+    assert!(results[0].is_ok());
+    let error = results[1].as_ref().unwrap_err().to_string();
+    assert!(error.contains(RULE_CHOWN_WITHOUT_USER), "{error}");
+    assert!(error.contains("line 2"), "{error}");
+}
@@ -0,0 +1,148 @@
+use polis_build::{
+    BuildContext, BuildError, BuildOptions, BuildPlatform, Dockerfile, ImageBuilder, ImageIndex,
+    ImageManifest, MEDIA_TYPE_IMAGE_INDEX, MEDIA_TYPE_IMAGE_MANIFEST,
+};
+use std::path::Path;
+use tempfile::TempDir;
+
+const DOCKERFILE: &str = "FROM alpine:3.19\nRUN echo hello\n";
+
+/// Empty executables standing in for the QEMU emulators of both
+/// architectures, so either one can be the foreign platform
+fn fake_qemu() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    for name in ["qemu-x86_64-static", "qemu-aarch64-static"] {
+        let path = dir.path().join(name);
+        std::fs::write(&path, "").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+    dir
+}
+
+fn builder(build_dir: &TempDir, qemu_dir: &Path) -> ImageBuilder {
+    ImageBuilder::new(build_dir.path().to_path_buf())
+        .unwrap()
+        .with_qemu_search_path(vec![qemu_dir.to_path_buf()])
+}
+
+async fn build(builder: &mut ImageBuilder, platforms: &[&str]) -> polis_build::Result<String> {
+    let context_dir = TempDir::new().unwrap();
+    std::fs::write(context_dir.path().join("Dockerfile"), DOCKERFILE).unwrap();
+    let context = BuildContext::new(context_dir.path().to_path_buf()).unwrap();
+    let options = BuildOptions {
+        platforms: platforms
+            .iter()
+            .map(|platform| BuildPlatform::parse(platform).unwrap())
+            .collect(),
+        progress: false,
+        ..Default::default()
+    };
+
+    let image_id = builder
+        .build_image(context, Dockerfile::parse(DOCKERFILE).unwrap(), options)
+        .await?;
+    Ok(image_id.0)
+}
+
+fn manifest(builder: &ImageBuilder, digest: &str) -> ImageManifest {
+    serde_json::from_slice(&builder.read_blob(digest).unwrap()).unwrap()
+}
+
+/// The platform the host cannot run natively
+fn foreign() -> &'static str {
+    if BuildPlatform::host().arch == "arm64" {
+        "linux/amd64"
+    } else {
+        "linux/arm64"
+    }
+}
+
+#[tokio::test]
+async fn test_two_platforms_produce_an_index() {
+    let build_dir = TempDir::new().unwrap();
+    let qemu = fake_qemu();
+    let mut builder = builder(&build_dir, qemu.path());
+
+    let digest = build(&mut builder, &["linux/amd64", "linux/arm64"])
+        .await
+        .unwrap();
+    let index: ImageIndex = serde_json::from_slice(&builder.read_blob(&digest).unwrap()).unwrap();
+    assert_eq!(index.media_type, MEDIA_TYPE_IMAGE_INDEX);
+    assert_eq!(index.manifests.len(), 2);
+
+    let platforms: Vec<Option<BuildPlatform>> = index
+        .manifests
+        .iter()
+        .map(|entry| entry.platform.clone())
+        .collect();
+    assert_eq!(
+        platforms,
+        vec![
+            Some(BuildPlatform::new("linux", "amd64")),
+            Some(BuildPlatform::new("linux", "arm64")),
+        ]
+    );
+
+    // The RUN layer differs between the platforms
+    let amd64 = manifest(&builder, &index.manifests[0].digest);
+    let arm64 = manifest(&builder, &index.manifests[1].digest);
+    assert_eq!(amd64.layers.len(), 1);
+    assert_eq!(arm64.layers.len(), 1);
+    assert_ne!(amd64.layers[0].digest, arm64.layers[0].digest);
+
+    let config: serde_json::Value =
+        serde_json::from_slice(&builder.read_blob(&arm64.config.digest).unwrap()).unwrap();
+    assert_eq!(config["architecture"], "arm64");
+    assert_eq!(config["os"], "linux");
+}
+
+#[tokio::test]
+async fn test_single_platform_is_a_plain_manifest() {
+    let build_dir = TempDir::new().unwrap();
+    let qemu = fake_qemu();
+    let mut builder = builder(&build_dir, qemu.path());
+
+    let digest = build(&mut builder, &[]).await.unwrap();
+    assert!(digest.starts_with("sha256:"), "{digest}");
+    let image = manifest(&builder, &digest);
+    assert_eq!(image.media_type, MEDIA_TYPE_IMAGE_MANIFEST);
+
+    // Rebuilding the same inputs from the cache yields the same image
+    assert_eq!(build(&mut builder, &[]).await.unwrap(), digest);
+}
+
+#[tokio::test]
+async fn test_missing_qemu_fails_foreign_builds() {
+    let build_dir = TempDir::new().unwrap();
+    let empty = TempDir::new().unwrap();
+    let mut builder = builder(&build_dir, empty.path());
+
+    let error = build(&mut builder, &[foreign()]).await.unwrap_err();
+    assert!(matches!(error, BuildError::Platform(_)), "{error:?}");
+    assert!(
+        error
+            .to_string()
+            .contains("QEMU not available for cross-compilation"),
+        "{error}"
+    );
+
+    // The host platform needs no emulator
+    let host = BuildPlatform::host().to_string();
+    assert!(build(&mut builder, &[host.as_str()]).await.is_ok());
+}
+
+#[test]
+fn test_platform_parsing() {
+    let platform = BuildPlatform::parse("linux/arm64").unwrap();
+    assert_eq!(platform, BuildPlatform::new("linux", "arm64"));
+    assert_eq!(platform.to_string(), "linux/arm64");
+    assert_eq!(platform.qemu_arch(), "aarch64");
+
+    for invalid in ["linux", "linux/", "/arm64", "linux/arm/v7/x"] {
+        assert!(BuildPlatform::parse(invalid).is_err(), "{invalid}");
+    }
+}
@@ -0,0 +1,138 @@
+use polis_build::{
+    BuildContext, BuildError, BuildOptions, Dockerfile, ImageBuilder, ImageManifest, SecretMount,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+const SECRET: &str = "s3cr3t-t0k3n-value";
+
+const DOCKERFILE: &str = "FROM alpine:3.19\n\
+                          RUN --mount=type=secret,id=token cat /run/secrets/token\n\
+                          RUN echo done\n";
+
+/// Context holding only the Dockerfile; the secret lives outside of it
+fn context(dockerfile: &str) -> (TempDir, BuildContext) {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("Dockerfile"), dockerfile).unwrap();
+    let context = BuildContext::new(dir.path().to_path_buf()).unwrap();
+    (dir, context)
+}
+
+fn secret_file() -> (TempDir, PathBuf) {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("token.txt");
+    std::fs::write(&path, SECRET).unwrap();
+    (dir, path)
+}
+
+fn options(secrets: HashMap<String, PathBuf>) -> BuildOptions {
+    BuildOptions {
+        secrets,
+        progress: false,
+        ..Default::default()
+    }
+}
+
+/// Every file below `dir`, recursively
+fn files(dir: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_secret_never_reaches_the_image() {
+    let (_context_dir, context) = context(DOCKERFILE);
+    let (_secret_dir, secret) = secret_file();
+    let build_dir = TempDir::new().unwrap();
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+
+    let secrets = HashMap::from([("token".to_string(), secret)]);
+    let image_id = builder
+        .build_image(
+            context,
+            Dockerfile::parse(DOCKERFILE).unwrap(),
+            options(secrets),
+        )
+        .await
+        .unwrap();
+
+    let manifest: ImageManifest =
+        serde_json::from_slice(&builder.read_blob(&image_id.0).unwrap()).unwrap();
+    assert_eq!(manifest.layers.len(), 2);
+
+    // No blob, cache entry or record the build left behind holds the
+    // secret, and its mount is gone
+    for file in files(build_dir.path()) {
+        let content = std::fs::read(&file).unwrap();
+        assert!(
+            !String::from_utf8_lossy(&content).contains(SECRET),
+            "{} holds the secret",
+            file.display()
+        );
+    }
+    let staging = builder.staging_dir();
+    assert!(!staging.exists() || files(&staging).is_empty());
+}
+
+#[tokio::test]
+async fn test_undeclared_secret_fails_before_any_step() {
+    let (_context_dir, context) = context(DOCKERFILE);
+    let build_dir = TempDir::new().unwrap();
+    let mut builder = ImageBuilder::new(build_dir.path().to_path_buf()).unwrap();
+
+    let error = builder
+        .build_image(
+            context,
+            Dockerfile::parse(DOCKERFILE).unwrap(),
+            options(HashMap::new()),
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, BuildError::MissingDependency(_)),
+        "{error:?}"
+    );
+    assert!(error.to_string().contains("token"), "{error}");
+    assert!(builder.cache.entries.is_empty());
+}
+
+#[test]
+fn test_secret_declaration_and_mount_options() {
+    let (_context_dir, context) = context(DOCKERFILE);
+    let (_secret_dir, secret) = secret_file();
+    let context = context.with_secrets(HashMap::from([("token".to_string(), secret)]));
+    assert!(context.has_secret("token"));
+    assert!(!context.has_secret("other"));
+    // Declared secrets are not part of the context
+    assert_eq!(context.get_file_count(), 1);
+
+    let args: Vec<String> = [
+        "--mount=type=secret,id=npm",
+        "--mount=type=secret,target=/root/.netrc",
+        "--mount=type=cache,target=/var/cache",
+        "npm",
+        "ci",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    assert_eq!(
+        Dockerfile::secret_mounts(&args).unwrap(),
+        vec![
+            SecretMount {
+                id: "npm".to_string(),
+                target: "/run/secrets/npm".to_string(),
+            },
+            SecretMount {
+                id: ".netrc".to_string(),
+                target: "/root/.netrc".to_string(),
+            },
+        ]
+    );
+    assert!(Dockerfile::secret_mounts(&["--mount=type=secret".to_string()]).is_err());
+}
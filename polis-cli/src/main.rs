@@ -1,17 +1,29 @@
 use clap::{Parser, Subcommand};
-use polis_core::{ContainerId, PolisConfig};
+use polis_core::{
+    event_journal_path, format_duration, parse_duration, parse_port_mapping, parse_size, Clock,
+    ContainerId, ContainerStatus, EgressMode, EgressPolicy, EventBus, EventFilter, EventJournal,
+    EventKind, ImageAction, ImageId, LogStream, PolisConfig, PolisError, PolisEvent,
+    ResourceLimits, RootfsConfig, SystemClock, WritablePath,
+};
 use polis_image::{ImageManager, ImageSearchManager, ImageCleanupManager, SearchOptions, CleanupOptions};
-use polis_runtime::{ContainerRuntime, PolisRuntime};
-use polis_stats::{ContainerStatsCollector, ContainerStatsSummary};
-use polis_build::{ImageBuilder, BuildContext, BuildOptions};
+use polis_runtime::{ContainerOptions, ContainerRuntime, PolisRuntime, TopColumn};
+use polis_stats::{ContainerStatsCollector, ContainerStatsSummary, SampleStore};
+use polis_monitor::{
+    active_alerts_path, load_active_alerts, AlertEngine, LogManager, LogNotifier, LogReadOptions,
+    WebhookNotifier,
+};
+use polis_build::{ImageBuilder, BuildContext, BuildEvent, BuildOptions, HistoryFilter};
 use polis_network::{BridgeManager, IpamManager, DnsManager, FirewallManager, PortForwardingManager};
 use polis_storage::{VolumeManager, VolumeDriver, MountOptions};
 use polis_orchestrator::{
     Orchestrator, OrchestratorConfig, DeploymentSpec, PortSpec, HealthCheckSpec,
-    ScalingPolicySpec, ResourceSpec, DeploymentStatusResult, DeploymentStatusType
+    ScalingPolicySpec, ScalingStrategy, ResourceSpec, DeploymentStatusResult, DeploymentStatusType,
+    Manifest, NamespaceQuota, SyncConfig, SyncController, SyncSource, SyncStatus,
+    CheckType, HealthCheckDef, HealthMonitor, TargetType
 };
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 #[derive(Parser)]
@@ -19,6 +31,10 @@ use std::time::Duration;
 #[command(about = "Polis - Container Runtime and Orchestration Platform")]
 #[command(version)]
 struct Cli {
+    /// Start with an empty orchestrator state if the saved one cannot be read
+    #[arg(long, global = true)]
+    ignore_corrupt_state: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -65,18 +81,126 @@ enum Commands {
         #[command(subcommand)]
         action: DeployCommands,
     },
+    /// Declarative sync from a directory or git repository
+    Sync {
+        #[command(subcommand)]
+        action: SyncCommands,
+    },
+    /// Namespace management
+    Namespace {
+        #[command(subcommand)]
+        action: NamespaceCommands,
+    },
+    /// Health check management
+    Health {
+        #[command(subcommand)]
+        action: HealthCommands,
+    },
+    /// Resource alerts on container statistics
+    Alerts {
+        #[command(subcommand)]
+        action: AlertsCommands,
+    },
+    /// Show container, image, deployment and health events from the journal
+    Events {
+        /// Only events newer than this duration (e.g. 10m, 2h)
+        #[arg(long)]
+        since: Option<String>,
+        /// key=value condition on type, action, source, subject or an attribute (repeatable)
+        #[arg(long = "filter")]
+        filters: Vec<String>,
+        /// Keep printing new events as they are journaled
+        #[arg(short, long)]
+        follow: bool,
+    },
 }
 
 #[derive(Subcommand)]
 enum ContainerCommands {
     /// Create a new container
     Create {
+        /// Container name; a random one is generated when omitted
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
         #[arg(short, long)]
         image: String,
         #[arg(short, long)]
         command: Option<String>,
+        /// Mount the root filesystem read-only
+        #[arg(long)]
+        read_only: bool,
+        /// Writable path over the read-only rootfs (/data, /tmp:size=64m, /host:/data)
+        #[arg(long = "writable")]
+        writable: Vec<String>,
+        /// Do not add the default writable paths (/tmp, /run, /var/tmp)
+        #[arg(long)]
+        no_default_writable: bool,
+        /// Stop the container after this much runtime (e.g. 30m, 2h)
+        #[arg(long)]
+        max_runtime: Option<String>,
+        /// Allowed egress destination: domain, *.domain or CIDR (repeatable)
+        #[arg(long = "egress-allow")]
+        egress_allow: Vec<String>,
+        /// Only log egress policy violations instead of blocking them
+        #[arg(long)]
+        egress_log_only: bool,
+        /// Run without root, mapping the current user to root in a user namespace
+        #[arg(long)]
+        rootless: bool,
+        /// Memory limit (e.g. 256m, 1g)
+        #[arg(long)]
+        memory: Option<String>,
+        /// Memory plus swap limit; equal to --memory disables swap
+        #[arg(long)]
+        memory_swap: Option<String>,
+        /// Number of CPUs the container may use (e.g. 0.5)
+        #[arg(long)]
+        cpus: Option<f64>,
+        /// Relative CPU weight (default 1024)
+        #[arg(long)]
+        cpu_shares: Option<u64>,
+        /// Maximum number of processes in the container
+        #[arg(long)]
+        pids_limit: Option<i64>,
+        /// Publish a port: [hostIp:]hostPort:containerPort[/tcp|udp], ranges allowed (repeatable)
+        #[arg(short = 'p', long = "publish")]
+        publish: Vec<String>,
+    },
+    /// Show detailed container information
+    Inspect {
+        name: String,
+        /// Output format (json or yaml)
+        #[arg(long, default_value = "json")]
+        format: String,
+        /// Include the processes using the most memory
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// List the processes running in a container
+    Top {
+        name: String,
+        /// Columns to show (e.g. pid,user,cmd)
+        #[arg(short = 'o', long)]
+        format: Option<String>,
+    },
+    /// Show the output of a container
+    Logs {
+        name: String,
+        /// Number of lines to show from the end of the logs
+        #[arg(long)]
+        tail: Option<usize>,
+        /// Only show lines from this long ago (e.g. 10m, 2h)
+        #[arg(long)]
+        since: Option<String>,
+        /// Keep streaming new lines
+        #[arg(short, long)]
+        follow: bool,
+        /// Prefix each line with its timestamp
+        #[arg(short, long)]
+        timestamps: bool,
+        /// Only show one stream (stdout or stderr)
+        #[arg(long)]
+        stream: Option<String>,
     },
     /// Start a container
     Start { name: String },
@@ -95,7 +219,12 @@ enum ContainerCommands {
 #[derive(Subcommand)]
 enum ImageCommands {
     /// Pull an image
-    Pull { name: String },
+    Pull {
+        name: String,
+        /// Platform to pull (os/arch[/variant]); overrides registries.conf
+        #[arg(long)]
+        platform: Option<String>,
+    },
     /// List images
     List,
     /// Remove an image
@@ -108,7 +237,31 @@ enum ImageCommands {
         tag: Option<String>,
         #[arg(long)]
         no_cache: bool,
+        /// Abort the build on Dockerfile lint errors
+        #[arg(long)]
+        strict_lint: bool,
+        /// Target platforms (os/arch), comma separated; the host's by default
+        #[arg(long, value_delimiter = ',')]
+        platform: Vec<String>,
+        /// Secret for `RUN --mount=type=secret` (id=<id>,src=<path>); repeatable
+        #[arg(long = "secret", value_parser = parse_secret)]
+        secrets: Vec<(String, PathBuf)>,
     },
+    /// Show the image build history
+    BuildHistory {
+        /// Output format (table or json)
+        #[arg(long, default_value = "table")]
+        format: String,
+        #[arg(short, long)]
+        tag: Option<String>,
+        /// Only failed builds
+        #[arg(long)]
+        failed: bool,
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+    /// Remove build artifacts not referenced by the cache or build history
+    BuildPrune,
     /// Search for images
     Search {
         query: String,
@@ -116,7 +269,29 @@ enum ImageCommands {
         limit: Option<usize>,
         #[arg(long)]
         official: bool,
+        /// Search only this registry (repeatable)
+        #[arg(long = "registry")]
+        registries: Vec<String>,
+    },
+    /// List the tags of a repository in its registry
+    Tags { repository: String },
+    /// Save an image to a tar archive in the OCI image layout
+    Save {
+        name: String,
+        /// Destination tar file
+        #[arg(short, long)]
+        output: PathBuf,
     },
+    /// Load an image from an OCI image layout tar archive
+    Load {
+        /// Source tar file
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+    /// Push a local image (NAME[:TAG]) to its registry
+    Push { name: String },
+    /// Create TARGET as an additional name for the local image SOURCE
+    Tag { source: String, target: String },
     /// Clean up images
     Cleanup {
         #[arg(long)]
@@ -154,6 +329,9 @@ enum RegistryCommands {
         mirror: Option<String>,
         #[arg(long)]
         insecure: bool,
+        /// Upstream registry this registry caches (pull-through), e.g. docker.io
+        #[arg(long)]
+        upstream: Option<String>,
     },
     /// Remove a registry
     Remove {
@@ -169,6 +347,8 @@ enum RegistryCommands {
     },
     /// Initialize default configuration
     Init,
+    /// Show pull quotas and the pull queue
+    Status,
 }
 
 #[derive(Subcommand)]
@@ -181,6 +361,15 @@ enum StatsCommands {
         follow: bool,
         #[arg(short, long, default_value = "5")]
         interval: u64,
+        /// Also print deltas and rates since the previous invocation
+        #[arg(long)]
+        since_last: bool,
+        /// Save the current sample as a baseline, or compare against one (save|compare)
+        #[arg(long)]
+        baseline: Option<String>,
+        /// Name of the baseline used by --baseline
+        #[arg(long, default_value = "default")]
+        baseline_name: String,
     },
     /// List all container statistics
     List,
@@ -196,6 +385,24 @@ enum StatsCommands {
         #[arg(short, long)]
         container: String,
     },
+    /// Sample container statistics for a while and write them as JSON or CSV
+    Export {
+        /// Output format (json|csv)
+        #[arg(short, long, default_value = "json")]
+        format: polis_stats::ExportFormat,
+        /// File to write; standard output when omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Capture window in seconds
+        #[arg(short, long, default_value = "60")]
+        duration: u64,
+        /// Seconds between samples
+        #[arg(short, long, default_value = "5")]
+        interval: u64,
+        /// Only export this container
+        #[arg(short, long)]
+        container: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -210,6 +417,9 @@ enum NetworkCommands {
         subnet: String,
         #[arg(long, default_value = "1500")]
         mtu: u16,
+        /// IPv6 prefix for a dual-stack bridge, e.g. fd00:17::/64
+        #[arg(long)]
+        ipv6_subnet: Option<polis_network::Ipv6Network>,
     },
     /// List network bridges
     ListBridges,
@@ -326,6 +536,134 @@ enum VolumeCommands {
     },
 }
 
+#[derive(Subcommand)]
+enum NamespaceCommands {
+    /// Create a namespace
+    Create {
+        #[arg(short, long)]
+        name: String,
+        /// Maximum number of deployments in the namespace
+        #[arg(long)]
+        max_deployments: Option<u32>,
+        /// Maximum total replicas across the deployments of the namespace
+        #[arg(long)]
+        max_replicas: Option<u32>,
+    },
+    /// List namespaces
+    List,
+    /// Delete a namespace
+    Delete {
+        #[arg(short, long)]
+        name: String,
+        /// Also delete every resource in the namespace
+        #[arg(long)]
+        cascade: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HealthCommands {
+    /// Add a health check; it is probed until removed
+    Add {
+        #[arg(short, long)]
+        name: String,
+        /// Host probed by http/tcp checks, directory of file checks
+        #[arg(short, long)]
+        target: String,
+        #[arg(long = "type", value_parser = ["http", "tcp", "command", "file"])]
+        check_type: String,
+        /// Request path of http checks
+        #[arg(long, default_value = "/health")]
+        path: String,
+        /// Status expected from http checks
+        #[arg(long, default_value = "200")]
+        expected_status: u16,
+        /// Port of tcp checks
+        #[arg(long)]
+        port: Option<u16>,
+        /// Command line of command checks (e.g. "pg_isready -q")
+        #[arg(long)]
+        command: Option<String>,
+        /// File of file checks, relative to the target
+        #[arg(long)]
+        file: Option<String>,
+        /// Make a file check healthy while the file is missing
+        #[arg(long)]
+        absent: bool,
+        /// Time between probes (e.g. 30s, 1m)
+        #[arg(long, default_value = "30s")]
+        interval: String,
+        /// Time allowed for a probe
+        #[arg(long, default_value = "5s")]
+        timeout: String,
+        #[arg(long, default_value = "3")]
+        retries: u32,
+    },
+    /// List health checks with their latest status
+    List,
+    /// Remove a health check
+    Remove {
+        #[arg(short, long)]
+        name: String,
+    },
+    /// Run a health check once and print the result
+    Run {
+        #[arg(short, long)]
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SyncCommands {
+    /// Continuously apply the specs found in a directory or git repository
+    Run {
+        #[arg(short, long)]
+        name: String,
+        /// Local directory with spec files (watched for changes)
+        #[arg(long, conflicts_with = "git")]
+        dir: Option<PathBuf>,
+        /// Git repository URL (polled)
+        #[arg(long, required_unless_present = "dir")]
+        git: Option<String>,
+        #[arg(long, default_value = "main")]
+        branch: String,
+        /// Directory inside the repository holding the specs
+        #[arg(long, default_value = ".")]
+        path: PathBuf,
+        /// Poll/resync interval in seconds
+        #[arg(long, default_value = "60")]
+        interval: u64,
+        /// Delete resources owned by this sync that were removed from the source
+        #[arg(long)]
+        prune: bool,
+        /// Run a single sync cycle and exit
+        #[arg(long)]
+        once: bool,
+    },
+    /// Show the last synced revision and per-resource results
+    Status {
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AlertsCommands {
+    /// Show pending and firing alerts
+    List,
+    /// Evaluate the rules of a YAML file against container statistics, reloading it on change
+    Watch {
+        /// Rule file
+        #[arg(short, long)]
+        rules: PathBuf,
+        /// Also POST every alert as JSON to this URL
+        #[arg(long)]
+        webhook: Option<String>,
+        /// Seconds between samples
+        #[arg(short, long, default_value = "5")]
+        interval: u64,
+    },
+}
+
 #[derive(Subcommand)]
 enum DeployCommands {
     /// Deploy a new service
@@ -350,6 +688,21 @@ enum DeployCommands {
         target_cpu: Option<f64>,
         #[arg(long)]
         target_memory: Option<f64>,
+        /// Mount the container root filesystem read-only
+        #[arg(long)]
+        read_only: bool,
+        /// Writable path over the read-only rootfs
+        #[arg(long = "writable")]
+        writable: Vec<String>,
+        /// Maximum runtime of each container (e.g. 30m, 2h)
+        #[arg(long)]
+        max_runtime: Option<String>,
+    },
+    /// Create or update deployments from a YAML manifest
+    Apply {
+        /// Manifest file; several deployments may be separated by `---`
+        #[arg(short, long)]
+        file: PathBuf,
     },
     /// List deployments
     List {
@@ -379,12 +732,43 @@ enum DeployCommands {
         #[arg(long, default_value = "default")]
         namespace: String,
     },
+    /// Show the revision history of a deployment
+    History {
+        #[arg(short, long)]
+        name: String,
+        #[arg(long, default_value = "default")]
+        namespace: String,
+    },
+    /// Roll a deployment back to an earlier revision
+    Rollback {
+        #[arg(short, long)]
+        name: String,
+        #[arg(long, default_value = "default")]
+        namespace: String,
+        /// Revision to restore (defaults to the previous one)
+        #[arg(long)]
+        revision: Option<u32>,
+    },
+    /// Pause a deployment: updates and auto-scaling wait until it is resumed
+    Pause {
+        #[arg(short, long)]
+        name: String,
+        #[arg(long, default_value = "default")]
+        namespace: String,
+    },
+    /// Resume a paused deployment and roll out pending updates
+    Resume {
+        #[arg(short, long)]
+        name: String,
+        #[arg(long, default_value = "default")]
+        namespace: String,
+    },
     /// Show orchestrator statistics
     Stats,
 }
 
 struct CliState {
-    runtime: PolisRuntime,
+    runtime: Arc<PolisRuntime>,
     image_manager: ImageManager,
     stats_collector: ContainerStatsCollector,
     search_manager: ImageSearchManager,
@@ -395,14 +779,18 @@ struct CliState {
     firewall_manager: FirewallManager,
     port_forwarding_manager: PortForwardingManager,
     volume_manager: VolumeManager,
-    orchestrator: Orchestrator,
-    container_names: HashMap<String, ContainerId>,
+    orchestrator: Arc<Orchestrator>,
+    event_bus: EventBus,
+    event_journal: PathBuf,
 }
 
 impl CliState {
-    async fn new() -> Result<Self, Box<dyn std::error::Error>> {
+    async fn new(ignore_corrupt_state: bool) -> Result<Self, Box<dyn std::error::Error>> {
         let config = PolisConfig::default();
-        let runtime = PolisRuntime::new(config.clone());
+        let event_journal = event_journal_path(&config.runtime.root_dir);
+        let event_bus = EventBus::new()
+            .with_journal(EventJournal::open(&event_journal, config.runtime.event_journal)?);
+        let runtime = Arc::new(PolisRuntime::new(config.clone()).with_event_bus(event_bus.clone()).with_persistent_state());
         runtime.initialize().await?;
 
         let image_cache_dir = config.storage.root_dir.join("images");
@@ -430,13 +818,18 @@ impl CliState {
         let volume_manager = VolumeManager::new(volume_dir).await?;
 
         // Initialize orchestrator with persistent state
-        let orchestrator_config = if std::path::Path::new("config/orchestrator.yaml").exists() {
+        let mut orchestrator_config = if std::path::Path::new("config/orchestrator.yaml").exists() {
             let content = std::fs::read_to_string("config/orchestrator.yaml")?;
             serde_yaml::from_str(&content).unwrap_or_else(|_| OrchestratorConfig::default())
         } else {
             OrchestratorConfig::default()
         };
-        let orchestrator = Orchestrator::new(orchestrator_config).await?;
+        orchestrator_config.ignore_corrupt_state |= ignore_corrupt_state;
+        let orchestrator = Arc::new(
+            Orchestrator::new(orchestrator_config, runtime.clone())
+                .await?
+                .with_event_bus(event_bus.clone()),
+        );
 
         Ok(Self {
             runtime,
@@ -451,19 +844,26 @@ impl CliState {
             port_forwarding_manager,
             volume_manager,
             orchestrator,
-            container_names: HashMap::new(),
+            event_bus,
+            event_journal,
         })
     }
 
-    async fn find_container_by_name(&self, name: &str) -> Option<ContainerId> {
-        self.container_names.get(name).cloned()
+    /// Resolve nome, ID ou prefixo de ID pelo runtime; `None` se nenhum
+    /// container casa e erro se a referência é ambígua
+    async fn find_container(&self, reference: &str) -> Result<Option<ContainerId>, PolisError> {
+        match self.runtime.resolve(reference).await {
+            Ok(container_id) => Ok(Some(container_id)),
+            Err(error @ PolisError::Ambiguous { .. }) => Err(error),
+            Err(_) => Ok(None),
+        }
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
-    let mut state = CliState::new().await?;
+    let mut state = CliState::new(cli.ignore_corrupt_state).await?;
 
     match cli.command {
         Commands::Container { action } => match action {
@@ -471,6 +871,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 name,
                 image,
                 command,
+                read_only,
+                writable,
+                no_default_writable,
+                max_runtime,
+                egress_allow,
+                egress_log_only,
+                rootless,
+                memory,
+                memory_swap,
+                cpus,
+                cpu_shares,
+                pids_limit,
+                publish,
             } => {
                 let command_vec = if let Some(cmd) = command {
                     cmd.split_whitespace().map(|s| s.to_string()).collect()
@@ -478,15 +891,125 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     vec!["sh".to_string()]
                 };
 
+                let mut rootfs = RootfsConfig {
+                    read_only,
+                    disable_default_writable_paths: no_default_writable,
+                    ..Default::default()
+                };
+                for path in &writable {
+                    rootfs = rootfs.with_writable_path(WritablePath::parse(path)?);
+                }
+                let mut ports = Vec::new();
+                for spec in &publish {
+                    ports.extend(parse_port_mapping(spec)?);
+                }
+
+                let options = ContainerOptions {
+                    rootfs,
+                    max_runtime: max_runtime.as_deref().map(parse_duration).transpose()?,
+                    egress: if egress_allow.is_empty() {
+                        None
+                    } else {
+                        let mode = if egress_log_only { EgressMode::LogOnly } else { EgressMode::Enforce };
+                        Some(EgressPolicy::from_allow_list(&egress_allow, mode))
+                    },
+                    rootless,
+                    resource_limits: ResourceLimits {
+                        memory_limit: memory.as_deref().map(parse_size).transpose()?,
+                        memory_swap: memory_swap.as_deref().map(parse_size).transpose()?,
+                        cpu_quota: cpus,
+                        cpu_shares,
+                        pids_limit,
+                        ..Default::default()
+                    },
+                    ports,
+                    ..Default::default()
+                };
+                // Sem --name o runtime gera um nome
                 let container_id = state
                     .runtime
-                    .create_container(name.clone(), image, command_vec)
+                    .create_container_with_options(name.unwrap_or_default(), image, command_vec, options)
                     .await?;
-                state.container_names.insert(name.clone(), container_id);
-                println!("Container '{}' criado com sucesso", name);
+                let container = state.runtime.get_container(container_id).await?;
+                println!("Container '{}' criado com sucesso", container.name);
+            }
+            ContainerCommands::Inspect { name, format, verbose } => {
+                if let Some(container_id) = state.find_container(&name).await? {
+                    let mut inspect = state.runtime.inspect_container(container_id.clone()).await?;
+                    if let Some(metrics) = state.stats_collector.get_metrics(&container_id.to_string()).await? {
+                        inspect.stats = Some(serde_json::to_value(metrics)?);
+                    }
+                    match format.as_str() {
+                        "json" => println!("{}", serde_json::to_string_pretty(&inspect)?),
+                        "yaml" => print!("{}", serde_yaml::to_string(&inspect)?),
+                        other => return Err(format!("Formato desconhecido: {} (use json ou yaml)", other).into()),
+                    }
+
+                    if verbose && inspect.state.status == ContainerStatus::Running {
+                        let table = state.runtime.top(container_id, &[]).await?;
+                        println!("Processos (maior uso de memória):");
+                        for process in table.top_by_memory(5) {
+                            println!(
+                                "  {:<8} {:<10} {:>10} {}",
+                                process.container_pid,
+                                process.user,
+                                format_bytes(process.rss),
+                                process.cmdline
+                            );
+                        }
+                    }
+                } else {
+                    println!("Container '{}' não encontrado", name);
+                }
+            }
+            ContainerCommands::Top { name, format } => {
+                if let Some(container_id) = state.find_container(&name).await? {
+                    let columns = match format {
+                        Some(format) => TopColumn::parse_list(&format)?,
+                        None => Vec::new(),
+                    };
+                    let table = state.runtime.top(container_id, &columns).await?;
+                    println!("{}", table.render());
+                } else {
+                    println!("Container '{}' não encontrado", name);
+                }
+            }
+            ContainerCommands::Logs { name, tail, since, follow, timestamps, stream } => {
+                if let Some(container_id) = state.find_container(&name).await? {
+                    let streams = match stream.as_deref() {
+                        None => Vec::new(),
+                        Some("stdout") => vec![LogStream::Stdout],
+                        Some("stderr") => vec![LogStream::Stderr],
+                        Some(other) => return Err(format!("Saída desconhecida: {}", other).into()),
+                    };
+                    let options = LogReadOptions {
+                        tail,
+                        since: since
+                            .as_deref()
+                            .map(parse_duration)
+                            .transpose()?
+                            .map(|ago| SystemClock.now() - ago),
+                        follow,
+                        streams,
+                    };
+                    let mut reader = LogManager::default().read(&container_id, options).await?;
+                    while let Some(record) = reader.next_record().await? {
+                        let line = if timestamps {
+                            format!("{} {}", record.time.to_rfc3339(), record.log)
+                        } else {
+                            record.log
+                        };
+                        match record.stream {
+                            LogStream::Stdout => println!("{}", line),
+                            LogStream::Stderr => eprintln!("{}", line),
+                        }
+                    }
+                } else {
+                    println!("Container '{}' não encontrado", name);
+                }
             }
             ContainerCommands::Start { name } => {
-                if let Some(container_id) = state.find_container_by_name(&name).await {
+                if let Some(container_id) = state.find_container(&name).await? {
                     state.runtime.start_container(container_id).await?;
                     println!("Container '{}' iniciado", name);
                 } else {
@@ -494,7 +1017,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
             ContainerCommands::Stop { name } => {
-                if let Some(container_id) = state.find_container_by_name(&name).await {
+                if let Some(container_id) = state.find_container(&name).await? {
                     state.runtime.stop_container(container_id).await?;
                     println!("Container '{}' parado", name);
                 } else {
@@ -507,32 +1030,45 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     println!("Nenhum container encontrado");
                 } else {
                     println!(
-                        "{:<20} {:<20} {:<15} {:<20}",
-                        "ID", "Nome", "Status", "Imagem"
+                        "{:<20} {:<20} {:<15} {:<20} {:<10} {}",
+                        "ID", "Nome", "Status", "Imagem", "Restante", "Portas"
                     );
-                    println!("{}", "-".repeat(75));
+                    println!("{}", "-".repeat(110));
                     for container in containers {
+                        let remaining = state
+                            .runtime
+                            .remaining_runtime(&container.id)
+                            .await
+                            .map(format_duration)
+                            .unwrap_or_else(|| "-".to_string());
+                        let ports = container
+                            .ports
+                            .iter()
+                            .map(|port| port.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ");
                         println!(
-                            "{:<20} {:<20} {:<15} {:<20}",
+                            "{:<20} {:<20} {:<15} {:<20} {:<10} {}",
                             container.id.0.to_string()[..8].to_string(),
                             container.name,
                             format!("{:?}", container.status),
-                            container.image.0
+                            container.image.0,
+                            remaining,
+                            ports
                         );
                     }
                 }
             }
             ContainerCommands::Remove { name } => {
-                if let Some(container_id) = state.find_container_by_name(&name).await {
+                if let Some(container_id) = state.find_container(&name).await? {
                     state.runtime.remove_container(container_id).await?;
-                    state.container_names.remove(&name);
                     println!("Container '{}' removido", name);
                 } else {
                     println!("Container '{}' não encontrado", name);
                 }
             }
             ContainerCommands::Pause { name } => {
-                if let Some(container_id) = state.find_container_by_name(&name).await {
+                if let Some(container_id) = state.find_container(&name).await? {
                     state.runtime.pause_container(container_id).await?;
                     println!("Container '{}' pausado", name);
                 } else {
@@ -540,7 +1076,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
             ContainerCommands::Unpause { name } => {
-                if let Some(container_id) = state.find_container_by_name(&name).await {
+                if let Some(container_id) = state.find_container(&name).await? {
                     state.runtime.unpause_container(container_id).await?;
                     println!("Container '{}' despausado", name);
                 } else {
@@ -550,9 +1086,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         },
         Commands::Image { action } => {
             match action {
-                ImageCommands::Pull { name } => {
+                ImageCommands::Pull { name, platform } => {
                     println!(" Baixando imagem '{}'...", name);
-                    match state.image_manager.pull(&name).await {
+                    let platform = platform.as_deref().map(polis_image::Platform::parse).transpose()?;
+                    let mut pull_events = state.image_manager.scheduler().subscribe();
+                    let result = state.image_manager
+                        .pull_with_progress_options(
+                            &name,
+                            polis_image::PullPriority::Interactive,
+                            platform,
+                            Some(Box::new(print_pull_progress)),
+                        )
+                        .await;
+                    while let Ok(event) = pull_events.try_recv() {
+                        match event {
+                            polis_image::PullEvent::QuotaLow { registry, remaining, limit } => {
+                                println!(" Aviso: cota do registry {} baixa ({} de {} pulls restantes)",
+                                    registry, remaining, limit.map(|l| l.to_string()).unwrap_or_else(|| "?".to_string()));
+                            }
+                            polis_image::PullEvent::Throttled { registry, retry_after } => {
+                                println!(" Registry {} limitou as requisições, aguardando {:?}", registry, retry_after);
+                            }
+                            _ => {}
+                        }
+                    }
+                    let action = if result.is_ok() { ImageAction::Pulled } else { ImageAction::PullFailed };
+                    state.event_bus.publish("image", EventKind::Image { action, reference: name.clone() });
+                    match result {
                         Ok(image) => {
                             println!(" Imagem '{}' baixada com sucesso", name);
                             println!("  - ID: {}", image.id.0);
@@ -603,8 +1163,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     // TODO: Implementar remoção de imagem por nome
                     println!(" Remoção por nome não implementada ainda");
                 }
-                ImageCommands::Build { path, tag, no_cache } => {
+                ImageCommands::Build { path, tag, no_cache, strict_lint, platform, secrets } => {
                     println!("  Construindo imagem a partir de '{}'...", path);
+
+                    let platforms = platform
+                        .iter()
+                        .map(|p| polis_build::BuildPlatform::parse(p))
+                        .collect::<Result<Vec<_>, _>>()?;
                     
                     let build_path = std::path::PathBuf::from(&path);
                     let context = match BuildContext::new(build_path) {
@@ -632,8 +1197,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         pull: false,
                         build_args: HashMap::new(),
                         target: None,
-                        platform: None,
-                        progress: true,
+                        platforms,
+                        secrets: secrets.into_iter().collect(),
+                        // Progress is shown from the build events instead
+                        progress: false,
+                        lint: polis_build::LintConfig {
+                            enabled: true,
+                            fail_on_error: strict_lint,
+                        },
                     };
 
                     let build_dir = std::path::PathBuf::from("./build");
@@ -645,7 +1216,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     };
 
-                    match builder.build_image(context, dockerfile, build_options).await {
+                    let (events, mut receiver) = tokio::sync::mpsc::channel(64);
+                    let printer = tokio::spawn(async move {
+                        while let Some(event) = receiver.recv().await {
+                            print_build_event(&event);
+                        }
+                    });
+                    let result = builder
+                        .build_image_with_events(context, dockerfile, build_options, events)
+                        .await;
+                    let _ = printer.await;
+
+                    match result {
                         Ok(image_id) => {
                             println!("  Imagem construída com sucesso: {}", image_id.0);
                             if let Some(tag) = tag {
@@ -657,7 +1239,51 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
                 }
-                ImageCommands::Search { query, limit, official } => {
+                ImageCommands::BuildHistory { format, tag, failed, limit } => {
+                    let builder = ImageBuilder::new(std::path::PathBuf::from("./build"))?;
+                    let filter = HistoryFilter {
+                        tag,
+                        succeeded: if failed { Some(false) } else { None },
+                        since: None,
+                        limit,
+                    };
+                    let records = builder.history().query(&filter);
+
+                    if format == "json" {
+                        println!("{}", serde_json::to_string_pretty(&records)?);
+                    } else if records.is_empty() {
+                        println!("  Nenhum build registrado");
+                    } else {
+                        println!("  {:<10} {:<25} {:<20} {:<10} {:<8} {:<10}", "ID", "TAG", "INÍCIO", "DURAÇÃO", "CACHE", "STATUS");
+                        println!("  {}", "-".repeat(90));
+                        for record in records {
+                            println!(
+                                "  {:<10} {:<25} {:<20} {:<10} {:<8} {:<10}",
+                                &record.id[..8],
+                                record.tag.as_deref().unwrap_or("<none>"),
+                                record.started_at.format("%Y-%m-%d %H:%M:%S"),
+                                format!("{:.1}s", record.duration.as_secs_f64()),
+                                format!("{:.0}%", record.cache_hit_ratio() * 100.0),
+                                if record.succeeded() { "ok" } else { "falhou" }
+                            );
+                        }
+                    }
+
+                    for (first, second) in builder.history().find_nondeterministic() {
+                        println!(
+                            "  Aviso: builds {} e {} têm as mesmas entradas mas imagens diferentes",
+                            &first.id[..8],
+                            &second.id[..8]
+                        );
+                    }
+                }
+                ImageCommands::BuildPrune => {
+                    let mut builder = ImageBuilder::new(std::path::PathBuf::from("./build"))?;
+                    let report = builder.gc()?;
+                    println!("  {} artefatos removidos", report.removed.len());
+                    println!("  Espaço recuperado: {}", format_bytes(report.reclaimed_bytes));
+                }
+                ImageCommands::Search { query, limit, official, registries } => {
                     println!("  Procurando imagens por '{}'...", query);
                     
                     let search_options = SearchOptions {
@@ -667,6 +1293,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         automated_only: false,
                         min_stars: None,
                         registry: None,
+                        registries: if registries.is_empty() { None } else { Some(registries) },
                     };
 
                     match state.search_manager.search_images(&query, search_options).await {
@@ -674,8 +1301,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             if results.is_empty() {
                                 println!("  Nenhuma imagem encontrada");
                             } else {
-                                println!("  {:<30} {:<10} {:<8} {:<15} {:<20}", "NOME", "ESTRELAS", "OFICIAL", "TAMANHO", "ATUALIZADO");
-                                println!("  {}", "-".repeat(90));
+                                println!("  {:<30} {:<15} {:<10} {:<8} {:<15} {:<20}", "NOME", "REGISTRY", "ESTRELAS", "OFICIAL", "TAMANHO", "ATUALIZADO");
+                                println!("  {}", "-".repeat(106));
                                 for result in results {
                                     let size_str = if let Some(size) = result.size {
                                         format!("{:.1} MB", size as f64 / 1024.0 / 1024.0)
@@ -689,8 +1316,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                         "N/A".to_string()
                                     };
 
-                                    println!("  {:<30} {:<10} {:<8} {:<15} {:<20}", 
+                                    println!("  {:<30} {:<15} {:<10} {:<8} {:<15} {:<20}", 
                                         result.name, 
+                                        result.registry,
                                         result.stars, 
                                         if result.official { "Sim" } else { "Não" },
                                         size_str,
@@ -704,6 +1332,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
                 }
+                ImageCommands::Tags { repository } => {
+                    let mut tags = state.image_manager.list_tags(&repository).await?;
+                    if tags.is_empty() {
+                        println!("  Nenhuma tag encontrada para {}", repository);
+                    } else {
+                        tags.sort();
+                        for tag in tags {
+                            println!("  {}", tag);
+                        }
+                    }
+                }
+                ImageCommands::Save { name, output } => {
+                    println!("  Salvando imagem '{}'...", name);
+                    state.image_manager.export_oci_tar(&ImageId::from_string(&name), &output).await?;
+                    println!(" Imagem salva em {}", output.display());
+                }
+                ImageCommands::Load { input } => {
+                    println!("  Carregando imagem de {}...", input.display());
+                    let archive = tokio::fs::File::open(&input).await?;
+                    let image_id = state.image_manager.load(archive).await?;
+                    println!(" Imagem '{}' carregada", image_id.0);
+                }
+                ImageCommands::Push { name } => {
+                    println!("  Enviando imagem '{}'...", name);
+                    state.image_manager.push(&name, Some(Box::new(print_push_progress))).await?;
+                    println!(" Imagem '{}' enviada com sucesso", name);
+                }
+                ImageCommands::Tag { source, target } => {
+                    let image = state.image_manager.tag(&source, &target).await?;
+                    println!(" Imagem '{}' marcada como {}:{}", source, image.name, image.tag);
+                }
                 ImageCommands::Cleanup { force, dangling, untagged, dry_run } => {
                     println!("  Limpando imagens...");
                     
@@ -784,16 +1443,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                         println!("insecure = {}", entry.insecure.unwrap_or(false));
                         println!("blocked = {}", entry.blocked.unwrap_or(false));
+                        if let Some(upstream) = &entry.upstream {
+                            println!("upstream = \"{}\"", upstream);
+                        }
+                        println!();
+                    }
+                    println!("# Platform precedence: --platform > most specific override > host ({})", polis_image::Platform::host());
+                    for platform_override in &config.platform_overrides {
+                        println!("[[platform_overrides]]");
+                        println!("pattern = \"{}\"", platform_override.pattern);
+                        println!("platform = \"{}\"", platform_override.platform);
                         println!();
                     }
                 }
-                RegistryCommands::Add { name, location, mirror, insecure } => {
+                RegistryCommands::Add { name, location, mirror, insecure, upstream } => {
                     let mut config = RegistryConfig::load().unwrap_or_default();
                     config.registries.insert(name.clone(), polis_image::RegistryEntry {
                         location,
                         mirror,
                         insecure: Some(insecure),
                         blocked: Some(false),
+                        upstream,
+                        ..Default::default()
                     });
                     config.save_user_config()?;
                     println!("Registry '{}' added successfully", name);
@@ -818,6 +1489,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("Registry '{}' not found", name);
                     }
                 }
+                RegistryCommands::Status => {
+                    let status = state.image_manager.scheduler().status();
+                    println!("Registry Pull Quotas:");
+                    if status.quotas.is_empty() {
+                        println!("  No quota information yet (pull an image first)");
+                    }
+                    for (registry, quota) in &status.quotas {
+                        println!(
+                            "  {}: {} of {} remaining (window: {}, updated {})",
+                            registry,
+                            quota.remaining.map(|r| r.to_string()).unwrap_or_else(|| "?".to_string()),
+                            quota.limit.map(|l| l.to_string()).unwrap_or_else(|| "?".to_string()),
+                            quota.window_secs.map(|w| format!("{}s", w)).unwrap_or_else(|| "?".to_string()),
+                            quota.updated_at.format("%Y-%m-%d %H:%M:%S")
+                        );
+                    }
+                    println!();
+                    println!("Active pulls: {}", status.active);
+                    println!("Queued pulls: {}", status.queued.len());
+                    for queued in &status.queued {
+                        println!("  {} ({}, {:?})", queued.image, queued.registry, queued.priority);
+                    }
+                }
                 RegistryCommands::Init => {
                     let config = RegistryConfig::default();
                     config.save_user_config()?;
@@ -828,25 +1522,43 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         },
         Commands::Stats { action } => {
             match action {
-                StatsCommands::Show { container, follow, interval } => {
+                StatsCommands::Show { container, follow, interval, since_last, baseline, baseline_name } => {
                     if let Some(container_name) = container {
-                        if let Some(container_id) = state.find_container_by_name(&container_name).await {
+                        if let Some(container_id) = state.find_container(&container_name).await? {
                             state.stats_collector.start_collecting(&container_id.to_string()).await?;
                             
                             if follow {
                                 println!("Monitoring container '{}' (press Ctrl+C to stop)...", container_name);
-                                let mut interval_timer = tokio::time::interval(tokio::time::Duration::from_secs(interval));
+                                let samples = state.stats_collector.subscribe(&container_id.to_string()).await;
+                                state.stats_collector.set_collection_interval(tokio::time::Duration::from_secs(interval));
+                                state.stats_collector.start_monitoring().await?;
                                 
-                                loop {
-                                    interval_timer.tick().await;
-                                    
-                                    if let Some(metrics) = state.stats_collector.get_metrics(&container_id.to_string()).await? {
-                                        print_stats_table(&metrics);
-                                    }
-                                }
+                                follow_stats(samples).await;
                             } else {
                                 if let Some(metrics) = state.stats_collector.get_metrics(&container_id.to_string()).await? {
                                     print_stats_table(&metrics);
+
+                                    let store = SampleStore::for_current_user();
+                                    if since_last {
+                                        match store.load_last(&metrics.container_id)? {
+                                            Some(previous) => print_stats_delta("since last invocation", &previous.diff(&metrics)),
+                                            None => println!("No previous sample stored; deltas will be shown next time"),
+                                        }
+                                        store.save_last(&metrics)?;
+                                    }
+
+                                    match baseline.as_deref() {
+                                        Some("save") => {
+                                            store.save_baseline(&baseline_name, &metrics)?;
+                                            println!("Baseline '{}' saved", baseline_name);
+                                        }
+                                        Some("compare") => match store.load_baseline(&baseline_name, &metrics.container_id)? {
+                                            Some(saved) => print_stats_delta(&format!("since baseline '{}'", baseline_name), &saved.diff(&metrics)),
+                                            None => println!("Baseline '{}' not found for container '{}'", baseline_name, container_name),
+                                        },
+                                        Some(other) => println!("Unknown baseline action '{}' (expected save or compare)", other),
+                                        None => {}
+                                    }
                                 } else {
                                     println!("No statistics available for container '{}'", container_name);
                                 }
@@ -854,6 +1566,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         } else {
                             println!("Container '{}' not found", container_name);
                         }
+                    } else if follow {
+                        println!("Monitoring all containers (press Ctrl+C to stop)...");
+                        let samples = state.stats_collector.subscribe_all();
+                        state.stats_collector.set_collection_interval(tokio::time::Duration::from_secs(interval));
+                        state.stats_collector.start_monitoring().await?;
+                        follow_stats(samples).await;
                     } else {
                         println!("Please specify a container name");
                     }
@@ -890,7 +1608,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     println!("  Total processes: {}", summary.total_processes);
                 }
                 StatsCommands::Start { container } => {
-                    if let Some(container_id) = state.find_container_by_name(&container).await {
+                    if let Some(container_id) = state.find_container(&container).await? {
                         state.stats_collector.start_collecting(&container_id.to_string()).await?;
                         println!("Started monitoring container '{}'", container);
                     } else {
@@ -898,19 +1616,60 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                 }
                 StatsCommands::Stop { container } => {
-                    if let Some(container_id) = state.find_container_by_name(&container).await {
+                    if let Some(container_id) = state.find_container(&container).await? {
                         state.stats_collector.stop_collecting(&container_id.to_string()).await?;
                         println!("Stopped monitoring container '{}'", container);
                     } else {
                         println!("Container '{}' not found", container);
                     }
                 }
+                StatsCommands::Export { format, output, duration, interval, container } => {
+                    let container_ids: Vec<String> = match container {
+                        Some(name) => match state.find_container(&name).await? {
+                            Some(container_id) => vec![container_id.to_string()],
+                            None => {
+                                println!("Container '{}' not found", name);
+                                return Ok(());
+                            }
+                        },
+                        None => state.runtime.list_containers().await?.iter().map(|c| c.id.to_string()).collect(),
+                    };
+                    for container_id in &container_ids {
+                        state.stats_collector.start_collecting(container_id).await?;
+                    }
+                    state.stats_collector.set_collection_interval(Duration::from_secs(interval.max(1)));
+                    state.stats_collector.start_monitoring().await?;
+
+                    // Progress goes to stderr so stdout stays a clean export
+                    let out: Box<dyn std::io::Write> = match &output {
+                        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+                        None => Box::new(std::io::stdout().lock()),
+                    };
+                    eprintln!(
+                        "Exporting {} container(s) as {} every {}s for {}s...",
+                        container_ids.len(), format, interval, duration
+                    );
+                    let mut exporter = polis_stats::StatsExporter::new(out, format);
+                    polis_stats::capture(
+                        &state.stats_collector,
+                        &mut exporter,
+                        Duration::from_secs(duration),
+                        Duration::from_secs(interval),
+                    ).await?;
+                    let records = exporter.records();
+                    exporter.finish()?;
+                    state.stats_collector.stop_monitoring().await?;
+                    match output {
+                        Some(path) => eprintln!("Wrote {} sample(s) to {}", records, path.display()),
+                        None => eprintln!("Wrote {} sample(s)", records),
+                    }
+                }
             }
         },
         Commands::Network { action } => {
             match action {
-                NetworkCommands::CreateBridge { name, ip, subnet, mtu } => {
-                    state.bridge_manager.create_bridge(&name, &ip, &subnet, mtu).await?;
+                NetworkCommands::CreateBridge { name, ip, subnet, mtu, ipv6_subnet } => {
+                    state.bridge_manager.create_bridge(&name, &ip, &subnet, mtu, ipv6_subnet).await?;
                 }
                 NetworkCommands::ListBridges => {
                     let bridges = state.bridge_manager.list_bridges().await?;
@@ -985,7 +1744,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             match action {
                 DeployCommands::Create {
                     name, image, namespace, replicas, port, health_path,
-                    min_replicas, max_replicas, target_cpu, target_memory
+                    min_replicas, max_replicas, target_cpu, target_memory,
+                    read_only, writable, max_runtime
                 } => {
                     // Create port specs
                     let mut ports = Vec::new();
@@ -1018,6 +1778,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             target_memory: target_memory.unwrap_or(80.0),
                             scale_up_cooldown: Duration::from_secs(300),
                             scale_down_cooldown: Duration::from_secs(300),
+                            strategy: ScalingStrategy::default(),
                         })
                     } else {
                         None
@@ -1035,6 +1796,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         health_check,
                         scaling_policy,
                         resources: None,
+                        read_only_rootfs: read_only,
+                        writable_paths: writable,
+                        max_runtime,
+                        egress: None,
                     };
 
                     let status = state.orchestrator.deploy(spec).await?;
@@ -1043,6 +1808,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     println!("  Desired Replicas: {}", status.desired_replicas);
                     println!("  Status: {:?}", status.status);
                 }
+                DeployCommands::Apply { file } => {
+                    let content = std::fs::read_to_string(&file)?;
+                    let specs = match DeploymentSpec::from_yaml_str(&content) {
+                        Ok(specs) => specs,
+                        Err(e) => return Err(format!("{}: {}", file.display(), e).into()),
+                    };
+
+                    let mut failed = 0;
+                    for spec in specs {
+                        let name = format!("{}/{}", spec.namespace, spec.name);
+                        match state.orchestrator.apply(Manifest::Deployment(Box::new(spec))).await {
+                            Ok(action) => println!("Deployment '{}': {:?}", name, action),
+                            Err(e) => {
+                                println!("Deployment '{}': error: {}", name, e);
+                                failed += 1;
+                            }
+                        }
+                    }
+                    if failed > 0 {
+                        return Err(format!("{} deployment(s) failed to apply", failed).into());
+                    }
+                }
                 DeployCommands::List { namespace } => {
                     let deployments = state.orchestrator.list_deployments(namespace.as_deref()).await?;
                     if deployments.is_empty() {
@@ -1072,6 +1859,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("  Ready Replicas: {}", status.ready_replicas);
                         println!("  Available Replicas: {}", status.available_replicas);
                         println!("  Status: {:?}", status.status);
+                        if let Some(reason) = &status.reason {
+                            println!("  Reason: {}", reason);
+                        }
                         println!("  Created: {}", status.created_at);
                         println!("  Updated: {}", status.updated_at);
                     } else {
@@ -1086,6 +1876,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     state.orchestrator.delete_deployment(&name, &namespace).await?;
                     println!("Deployment '{}' deleted successfully", name);
                 }
+                DeployCommands::History { name, namespace } => {
+                    let history = state.orchestrator.get_deployment_history(&name, &namespace).await?;
+                    println!("{:<10} {:<30} {:<10} {:<25}", "REVISION", "IMAGE", "REPLICAS", "CREATED");
+                    println!("{}", "-".repeat(80));
+                    let active = history.last().map(|r| r.revision);
+                    for revision in &history {
+                        println!(
+                            "{:<10} {:<30} {:<10} {:<25}",
+                            if Some(revision.revision) == active { format!("{} *", revision.revision) } else { revision.revision.to_string() },
+                            revision.spec.image,
+                            revision.spec.replicas,
+                            revision.created_at.format("%Y-%m-%d %H:%M:%S")
+                        );
+                    }
+                }
+                DeployCommands::Rollback { name, namespace, revision } => {
+                    let restored = state.orchestrator.rollback_deployment(&name, &namespace, revision).await?;
+                    println!("Deployment '{}' rolled back to revision {}", name, restored);
+                }
+                DeployCommands::Pause { name, namespace } => {
+                    state.orchestrator.pause_deployment(&name, &namespace).await?;
+                    println!("Deployment '{}' paused", name);
+                }
+                DeployCommands::Resume { name, namespace } => {
+                    state.orchestrator.resume_deployment(&name, &namespace).await?;
+                    println!("Deployment '{}' resumed", name);
+                }
                 DeployCommands::Stats => {
                     let stats = state.orchestrator.get_stats().await?;
                     println!("Orchestrator Statistics:");
@@ -1099,12 +1916,335 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         },
+        Commands::Namespace { action } => match action {
+            NamespaceCommands::Create { name, max_deployments, max_replicas } => {
+                let quota = NamespaceQuota { max_deployments, max_replicas };
+                state.orchestrator.create_namespace(&name, quota).await?;
+                println!("Namespace '{}' created successfully", name);
+            }
+            NamespaceCommands::List => {
+                let limit = |max: Option<u32>| max.map(|m| m.to_string()).unwrap_or_else(|| "-".to_string());
+                println!("{:<20} {:<16} {:<14} {:<20}", "NAME", "MAX DEPLOYMENTS", "MAX REPLICAS", "CREATED");
+                println!("{}", "-".repeat(72));
+                for namespace in state.orchestrator.list_namespaces().await {
+                    println!(
+                        "{:<20} {:<16} {:<14} {:<20}",
+                        namespace.name,
+                        limit(namespace.quota.max_deployments),
+                        limit(namespace.quota.max_replicas),
+                        namespace.created_at.format("%Y-%m-%d %H:%M:%S")
+                    );
+                }
+            }
+            NamespaceCommands::Delete { name, cascade } => {
+                state.orchestrator.delete_namespace(&name, cascade).await?;
+                println!("Namespace '{}' deleted successfully", name);
+            }
+        },
+        Commands::Health { action } => {
+            let monitor = HealthMonitor::new()
+                .with_store(state.orchestrator.config().health_checks_path())
+                .with_event_bus(state.event_bus.clone());
+            monitor.load().await?;
+
+            match action {
+                HealthCommands::Add {
+                    name, target, check_type, path, expected_status, port, command, file,
+                    absent, interval, timeout, retries,
+                } => {
+                    let check_type = match check_type.as_str() {
+                        "http" => CheckType::Http { path, expected_status },
+                        "tcp" => CheckType::Tcp {
+                            port: port.ok_or("--port is required for tcp checks")?,
+                        },
+                        "command" => {
+                            let command = command.ok_or("--command is required for command checks")?;
+                            let mut words = command.split_whitespace().map(str::to_string);
+                            CheckType::Command {
+                                command: words.next().ok_or("--command must not be empty")?,
+                                args: words.collect(),
+                            }
+                        }
+                        "file" => CheckType::File {
+                            path: file.ok_or("--file is required for file checks")?,
+                            exists: !absent,
+                        },
+                        other => unreachable!("clap rejects check type {}", other),
+                    };
+                    if monitor.get_health_check(&name).await.is_some() {
+                        return Err(format!("Health check '{}' already exists", name).into());
+                    }
+
+                    let check = HealthCheckDef::new(
+                        name.clone(), name.clone(), TargetType::Custom, target, check_type,
+                    )
+                    .with_interval(parse_duration(&interval)?)
+                    .with_timeout(parse_duration(&timeout)?)
+                    .with_retries(retries);
+                    monitor.create_health_check(check).await?;
+                    println!("Health check '{}' created successfully", name);
+                }
+                HealthCommands::List => {
+                    let mut checks = monitor.list_health_checks().await;
+                    checks.sort_by(|a, b| a.name.cmp(&b.name));
+                    println!(
+                        "{:<20} {:<10} {:<24} {:<10} {:<10} {:<20}",
+                        "NAME", "TYPE", "TARGET", "INTERVAL", "STATUS", "LAST CHECK"
+                    );
+                    println!("{}", "-".repeat(99));
+                    for check in checks {
+                        let result = monitor.get_health_check_result(&check.id).await;
+                        let (status, last_check) = match &result {
+                            Some(result) => (
+                                format!("{:?}", result.status),
+                                result.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                            ),
+                            None => ("-".to_string(), "-".to_string()),
+                        };
+                        println!(
+                            "{:<20} {:<10} {:<24} {:<10} {:<10} {:<20}",
+                            check.name,
+                            check_type_name(&check.check_type),
+                            check.target_id,
+                            format_duration(check.interval),
+                            status,
+                            last_check
+                        );
+                    }
+                }
+                HealthCommands::Remove { name } => {
+                    if monitor.get_health_check(&name).await.is_some() {
+                        monitor.delete_health_check(&name).await?;
+                        println!("Health check '{}' removed successfully", name);
+                    } else {
+                        println!("Health check '{}' not found", name);
+                    }
+                }
+                HealthCommands::Run { name } => {
+                    if monitor.get_health_check(&name).await.is_none() {
+                        println!("Health check '{}' not found", name);
+                    } else {
+                        let result = monitor.run_health_check(&name).await?;
+                        println!("Health check: {}", name);
+                        println!("  Status: {:?}", result.status);
+                        println!("  Message: {}", result.message);
+                        println!("  Response time: {}ms", result.response_time.as_millis());
+                        println!("  Consecutive failures: {}", result.consecutive_failures);
+                    }
+                }
+            }
+        }
+        Commands::Sync { action } => match action {
+            SyncCommands::Run { name, dir, git, branch, path, interval, prune, once } => {
+                let source = match (dir, git) {
+                    (Some(path), _) => SyncSource::Directory { path },
+                    (None, Some(url)) => SyncSource::Git { url, branch, path },
+                    (None, None) => unreachable!("clap requires --dir or --git"),
+                };
+                let config = SyncConfig::new(&name, source)
+                    .with_prune(prune)
+                    .with_interval(Duration::from_secs(interval));
+                let controller = SyncController::new(config, state.orchestrator.clone());
+
+                if once {
+                    let status = controller.sync_once().await?;
+                    print_sync_status(&status);
+                } else {
+                    println!("Syncing '{}' every {}s (Ctrl+C to stop)", name, interval);
+                    controller.run().await?;
+                }
+            }
+            SyncCommands::Status { name } => {
+                let state_dir = &state.orchestrator.config().state_dir;
+                let statuses = match name {
+                    Some(name) => SyncStatus::load(state_dir, &name)?.into_iter().collect(),
+                    None => SyncStatus::list(state_dir)?,
+                };
+                if statuses.is_empty() {
+                    println!("No sync status found");
+                }
+                for status in statuses {
+                    print_sync_status(&status);
+                }
+            }
+        },
+        Commands::Alerts { action } => match action {
+            AlertsCommands::List => {
+                let path = active_alerts_path(&state.orchestrator.config().state_dir);
+                let alerts = load_active_alerts(&path)?;
+                if alerts.is_empty() {
+                    println!("No active alerts");
+                } else {
+                    println!("{:<24} {:<20} {:<8} {:<10} {:<12} {:<12} {}", "RULE", "CONTAINER", "STATE", "SEVERITY", "VALUE", "THRESHOLD", "SINCE");
+                    println!("{}", "-".repeat(110));
+                    for alert in alerts {
+                        println!(
+                            "{:<24} {:<20} {:<8} {:<10} {:<12.2} {:<12.2} {}",
+                            alert.rule,
+                            alert.container_id,
+                            format!("{:?}", alert.state).to_lowercase(),
+                            format!("{:?}", alert.severity),
+                            alert.value,
+                            alert.threshold,
+                            alert.active_since.format("%Y-%m-%d %H:%M:%S")
+                        );
+                    }
+                }
+            }
+            AlertsCommands::Watch { rules, webhook, interval } => {
+                let state_file = active_alerts_path(&state.orchestrator.config().state_dir);
+                let mut engine = AlertEngine::new()
+                    .with_notifier(Arc::new(LogNotifier))
+                    .with_state_file(state_file);
+                if let Some(url) = webhook {
+                    engine = engine.with_notifier(Arc::new(WebhookNotifier::new(&url)?));
+                }
+                let engine = Arc::new(engine);
+                let _reloader = engine.watch_rules_file(&rules).await?;
+
+                let collector = Arc::new(ContainerStatsCollector::new(Duration::from_secs(interval.max(1))));
+                for container in state.runtime.list_containers().await? {
+                    collector.start_collecting(&container.id.to_string()).await?;
+                }
+                let evaluator = engine.run(Arc::clone(&collector));
+                collector.start_monitoring().await?;
+
+                println!(
+                    "Evaluating {} alert rules from {} every {}s (Ctrl+C to stop)",
+                    engine.rules().await.len(),
+                    rules.display(),
+                    interval
+                );
+                evaluator.await?;
+            }
+        },
+        Commands::Events { since, filters, follow } => {
+            let mut filter = EventFilter::parse(&filters)?;
+            if let Some(since) = since {
+                filter = filter.since(SystemClock.now() - parse_duration(&since)?);
+            }
+            let mut last = None;
+            for event in EventJournal::read(&state.event_journal, &filter)? {
+                print_event(&event);
+                last = Some(event.timestamp);
+            }
+            // Other polis processes append to the journal, so follow it on disk
+            while follow {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                for event in EventJournal::read(&state.event_journal, &filter)? {
+                    if last.is_some_and(|last| event.timestamp <= last) {
+                        continue;
+                    }
+                    print_event(&event);
+                    last = Some(event.timestamp);
+                }
+            }
+        }
     }
 
     Ok(())
 }
 
+fn print_event(event: &PolisEvent) {
+    let attributes: Vec<String> = event.attributes.iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    println!(
+        "{} {} {} {} (source={}{}{})",
+        event.timestamp.to_rfc3339(),
+        event.kind.type_name(),
+        event.kind.action(),
+        event.kind.subject(),
+        event.source,
+        if attributes.is_empty() { "" } else { ", " },
+        attributes.join(", ")
+    );
+}
+
+/// Parse a `--secret id=<id>,src=<path>` build flag
+fn parse_secret(spec: &str) -> Result<(String, PathBuf), String> {
+    let mut id = None;
+    let mut source = None;
+    for field in spec.split(',') {
+        match field.split_once('=') {
+            Some(("id", value)) => id = Some(value.to_string()),
+            Some(("src" | "source", value)) => source = Some(PathBuf::from(value)),
+            _ => return Err(format!("unknown secret field '{}'", field)),
+        }
+    }
+    match (id, source) {
+        (Some(id), Some(source)) if !id.is_empty() => Ok((id, source)),
+        _ => Err("expected id=<id>,src=<path>".to_string()),
+    }
+}
+
+/// Name of a check type as accepted by `polis health add --type`
+fn check_type_name(check_type: &CheckType) -> &'static str {
+    match check_type {
+        CheckType::Http { .. } => "http",
+        CheckType::Tcp { .. } => "tcp",
+        CheckType::Udp { .. } => "udp",
+        CheckType::Grpc { .. } => "grpc",
+        CheckType::Command { .. } => "command",
+        CheckType::File { .. } => "file",
+        CheckType::Custom { .. } => "custom",
+    }
+}
+
+/// Print the outcome of a sync cycle
+fn print_sync_status(status: &SyncStatus) {
+    println!("Sync: {}", status.name);
+    println!("  Source: {}", status.source);
+    println!("  Revision: {}", status.revision.as_deref().unwrap_or("-"));
+    if let Some(last_sync) = status.last_sync {
+        println!("  Last sync: {}", last_sync.format("%Y-%m-%d %H:%M:%S"));
+    }
+    if let Some(error) = &status.error {
+        println!("  Error: {}", error);
+    }
+
+    if !status.results.is_empty() {
+        println!("  {:<40} {:<10} {}", "RESOURCE", "RESULT", "FILE");
+        for result in &status.results {
+            let outcome = match (&result.action, &result.error) {
+                (_, Some(error)) => format!("error: {}", error),
+                (Some(action), None) => format!("{:?}", action),
+                (None, None) => "-".to_string(),
+            };
+            let file = result
+                .file
+                .as_ref()
+                .map(|f| f.display().to_string())
+                .unwrap_or_else(|| "-".to_string());
+            println!("  {:<40} {:<10} {}", result.resource.to_string(), outcome, file);
+        }
+    }
+
+    if !status.parse_errors.is_empty() {
+        println!("  Parse errors:");
+        for error in &status.parse_errors {
+            println!("    {} (document {}): {}", error.file.display(), error.document, error.message);
+        }
+    }
+    if status.prune_skipped {
+        println!("  Pruning skipped because of parse errors");
+    }
+}
+
 /// Print a detailed stats table for a container
+/// Print samples as they arrive until the feed closes
+async fn follow_stats(mut samples: tokio::sync::broadcast::Receiver<polis_stats::ContainerMetrics>) {
+    loop {
+        match samples.recv().await {
+            Ok(metrics) => print_stats_table(&metrics),
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                println!("(skipped {} samples)", skipped);
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
 fn print_stats_table(metrics: &polis_stats::ContainerMetrics) {
     println!("\n=== Container Statistics: {} ===", metrics.container_id);
     println!("Timestamp: {:?}", metrics.timestamp);
@@ -1148,7 +2288,127 @@ fn print_stats_table(metrics: &polis_stats::ContainerMetrics) {
     println!();
 }
 
+/// Print deltas and rates between two samples
+fn print_stats_delta(label: &str, delta: &polis_stats::MetricsDelta) {
+    println!("=== Changes {} ({:.1}s) ===", label, delta.interval.as_secs_f64());
+    if delta.reset {
+        println!("  Some counters went backwards (container restarted?); they are marked as reset");
+    }
+
+    match delta.cpu_percent {
+        Some(percent) => println!("  CPU: {:.1}% average", percent),
+        None => println!("  CPU: n/a"),
+    }
+    let memory_sign = if delta.memory_change < 0 { "-" } else { "+" };
+    println!("  Memory: {}{}", memory_sign, format_bytes(delta.memory_change.unsigned_abs()));
+    println!("  RX: {}", format_byte_delta(&delta.rx_bytes));
+    println!("  TX: {}", format_byte_delta(&delta.tx_bytes));
+    println!("  Disk read: {}", format_byte_delta(&delta.read_bytes));
+    println!("  Disk write: {}", format_byte_delta(&delta.write_bytes));
+    println!("  Read ops: {}", format_count_delta(&delta.read_ops));
+    println!("  Write ops: {}", format_count_delta(&delta.write_ops));
+    println!();
+}
+
+fn format_byte_delta(delta: &polis_stats::CounterDelta) -> String {
+    match delta {
+        polis_stats::CounterDelta::Value { delta, rate } => {
+            format!("+{} ({}/s)", format_bytes(*delta), format_bytes(*rate as u64))
+        }
+        polis_stats::CounterDelta::Reset => "reset".to_string(),
+    }
+}
+
+fn format_count_delta(delta: &polis_stats::CounterDelta) -> String {
+    match delta {
+        polis_stats::CounterDelta::Value { delta, rate } => format!("+{} ({:.1}/s)", delta, rate),
+        polis_stats::CounterDelta::Reset => "reset".to_string(),
+    }
+}
+
+/// Print a build event, with a progress bar at the start of each step
+fn print_build_event(event: &BuildEvent) {
+    match event {
+        BuildEvent::StepStarted { step, total, instruction } => {
+            const WIDTH: usize = 20;
+            let filled = (step - 1) * WIDTH / (*total).max(1);
+            println!(
+                "  [{}{}] {}/{} {}",
+                "#".repeat(filled),
+                "-".repeat(WIDTH - filled),
+                step,
+                total,
+                instruction
+            );
+        }
+        BuildEvent::StepCompleted { step, duration } => {
+            println!("    passo {} concluído em {:.1}s", step, duration.as_secs_f64());
+        }
+        BuildEvent::LayerPulled { digest, size } => {
+            println!("    camada baixada {} ({})", short_digest(digest), format_bytes(*size));
+        }
+        BuildEvent::LayerCached { digest } => {
+            println!("    usando cache {}", short_digest(digest));
+        }
+        BuildEvent::LogLine { line, .. } => println!("    {}", line),
+        BuildEvent::BuildError { step, message } => {
+            println!("    passo {} falhou: {}", step, message);
+        }
+    }
+}
+
+fn short_digest(digest: &str) -> &str {
+    &digest[..digest.len().min(12)]
+}
+
 /// Format bytes into human readable format
+/// Barra de progresso da camada sendo baixada, reescrita na mesma linha
+fn print_pull_progress(downloaded: u64, total: Option<u64>) {
+    use std::io::Write;
+
+    let line = match total {
+        Some(total) if total > 0 => format!("  {}", progress_bar(downloaded, total)),
+        _ => format!("  {} baixados", format_bytes(downloaded)),
+    };
+    let mut stderr = std::io::stderr();
+    let _ = write!(stderr, "\r{:<60}", line);
+    if total == Some(downloaded) {
+        let _ = writeln!(stderr);
+    }
+    let _ = stderr.flush();
+}
+
+/// Andamento do push, uma linha por camada
+fn print_push_progress(progress: polis_image::PushProgress) {
+    use std::io::Write;
+
+    let mut stderr = std::io::stderr();
+    match progress {
+        polis_image::PushProgress::Exists { digest } => {
+            let _ = writeln!(stderr, "  {}: já existe no registry", short_digest(digest.trim_start_matches("sha256:")));
+        }
+        polis_image::PushProgress::Uploading { digest, sent, size } => {
+            let _ = write!(stderr, "\r  {}: {}", short_digest(digest.trim_start_matches("sha256:")), progress_bar(sent, size));
+            if sent >= size {
+                let _ = writeln!(stderr);
+            }
+        }
+    }
+    let _ = stderr.flush();
+}
+
+fn progress_bar(current: u64, total: u64) -> String {
+    const WIDTH: u64 = 30;
+    let filled = (current.min(total) * WIDTH / total.max(1)) as usize;
+    format!(
+        "[{}{}] {} / {}",
+        "#".repeat(filled),
+        " ".repeat(WIDTH as usize - filled),
+        format_bytes(current),
+        format_bytes(total)
+    )
+}
+
 fn format_bytes(bytes: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
     let mut size = bytes as f64;
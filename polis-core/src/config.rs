@@ -1,4 +1,5 @@
 use crate::error::{PolisError, Result};
+use crate::LogRotation;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -9,6 +10,11 @@ pub struct PolisConfig {
     pub network: NetworkConfig,
     pub security: SecurityConfig,
     pub api: ApiConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Executa os containers sem root, em um namespace de usuário próprio
+    #[serde(default)]
+    pub rootless: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +24,19 @@ pub struct RuntimeConfig {
     pub debug: bool,
     pub max_containers: u32,
     pub container_timeout: u64,
+    /// Antecedência, em segundos, do aviso de prazo de execução
+    #[serde(default = "default_deadline_warning")]
+    pub deadline_warning: u64,
+    /// Rotação dos arquivos com a saída dos containers
+    #[serde(default)]
+    pub log_rotation: LogRotation,
+    /// Rotação do diário de eventos (`polis events`)
+    #[serde(default)]
+    pub event_journal: LogRotation,
+}
+
+fn default_deadline_warning() -> u64 {
+    600
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +74,23 @@ pub struct ApiConfig {
     pub timeout_seconds: u64,
 }
 
+/// Emissão de tokens da API REST
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// Chave HMAC que assina os tokens de acesso; sem ela a API não emite
+    /// tokens
+    pub jwt_secret: String,
+    /// Validade do token de acesso, em segundos
+    pub access_token_ttl: u64,
+    /// Validade do refresh token, em segundos
+    pub refresh_token_ttl: u64,
+    /// Falhas de login seguidas, por usuário ou IP, antes do bloqueio; 0
+    /// desativa o limite
+    pub max_login_attempts: u32,
+    /// Duração do bloqueio após exceder `max_login_attempts`, em segundos
+    pub lockout_seconds: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum LogLevel {
     Error,
@@ -88,6 +124,9 @@ impl Default for RuntimeConfig {
             debug: false,
             max_containers: 100,
             container_timeout: 30,
+            deadline_warning: default_deadline_warning(),
+            log_rotation: LogRotation::default(),
+            event_journal: LogRotation::default(),
         }
     }
 }
@@ -139,6 +178,18 @@ impl Default for ApiConfig {
     }
 }
 
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            jwt_secret: String::new(),
+            access_token_ttl: 15 * 60,
+            refresh_token_ttl: 7 * 24 * 60 * 60,
+            max_login_attempts: 5,
+            lockout_seconds: 5 * 60,
+        }
+    }
+}
+
 impl PolisConfig {
     pub fn load_from_file(path: &str) -> Result<Self> {
         let content = std::fs::read_to_string(path).map_err(PolisError::Io)?;
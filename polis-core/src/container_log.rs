@@ -0,0 +1,131 @@
+use crate::{ContainerId, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Nome do arquivo de log atual de um container; os rotacionados recebem o
+/// sufixo `.1` (mais recente) até `.N` (mais antigo)
+pub const CONTAINER_LOG_FILE: &str = "container.log";
+
+/// Saída do processo de onde veio uma linha
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// Linha de saída de um container, gravada como uma linha JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContainerLogRecord {
+    pub time: DateTime<Utc>,
+    pub stream: LogStream,
+    /// Conteúdo da linha, sem a quebra de linha
+    pub log: String,
+}
+
+impl ContainerLogRecord {
+    /// Lê uma linha do arquivo de log; linhas inválidas são ignoradas
+    pub fn parse(line: &str) -> Option<Self> {
+        serde_json::from_str(line.trim_end()).ok()
+    }
+}
+
+/// Limites de rotação dos logs de container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogRotation {
+    /// Tamanho máximo, em bytes, do arquivo atual antes de rotacionar
+    pub max_size: u64,
+    /// Quantidade máxima de arquivos mantidos, incluindo o atual
+    pub max_files: usize,
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        Self {
+            max_size: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+/// Diretório com os logs de um container
+pub fn container_log_dir(root_dir: &Path, id: &ContainerId) -> PathBuf {
+    root_dir
+        .join("containers")
+        .join(id.0.to_string())
+        .join("logs")
+}
+
+/// Caminho do arquivo rotacionado `index`; 0 é o arquivo atual
+pub fn rotated_log_path(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// Grava linhas de log num arquivo, rotacionando-o ao atingir o tamanho
+/// máximo. A rotação renomeia o arquivo atual para `.1` e cria um novo, o que
+/// permite a leitores acompanhando o arquivo detectar a troca.
+pub struct RotatingLogWriter {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: File,
+    size: u64,
+}
+
+impl RotatingLogWriter {
+    pub fn open(path: impl Into<PathBuf>, rotation: LogRotation) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            rotation,
+            file,
+            size,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Grava um registro serializado como uma linha JSON
+    pub fn write<T: Serialize>(&mut self, record: &T) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        if self.size > 0 && self.size + line.len() as u64 > self.rotation.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(&line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.file.flush()?;
+        let keep = self.rotation.max_files.max(1);
+        let _ = std::fs::remove_file(rotated_log_path(&self.path, keep - 1));
+        for index in (1..keep).rev() {
+            let from = rotated_log_path(&self.path, index - 1);
+            if from.exists() {
+                std::fs::rename(&from, rotated_log_path(&self.path, index))?;
+            }
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{
+    rotated_log_path, Clock, LogRotation, PolisError, Result, RotatingLogWriter, SystemClock,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Eventos guardados no canal para assinantes lentos
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Diário de eventos sob o diretório raiz do runtime
+pub fn event_journal_path(root_dir: &Path) -> PathBuf {
+    root_dir.join("events").join("events.log")
+}
+
+/// Ação no ciclo de vida de um container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerAction {
+    Created,
+    Started,
+    Stopped,
+    OomKilled,
+    Removed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageAction {
+    Pulled,
+    PullFailed,
+    Removed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentAction {
+    Created,
+    Updated,
+    Scaled,
+    Paused,
+    Resumed,
+    RolledBack,
+    Deleted,
+}
+
+/// Mudança de estado de uma verificação de saúde
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthTransition {
+    Passed,
+    Failed,
+    Degraded,
+    Recovered,
+}
+
+/// Conteúdo tipado de um evento; `type` é o tipo usado nos filtros
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    Container {
+        action: ContainerAction,
+        id: String,
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+    },
+    Image {
+        action: ImageAction,
+        reference: String,
+    },
+    Deployment {
+        action: DeploymentAction,
+        name: String,
+        namespace: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        replicas: Option<u32>,
+    },
+    Health {
+        transition: HealthTransition,
+        check_id: String,
+        target_id: String,
+        message: String,
+    },
+}
+
+impl EventKind {
+    /// Tipo do evento: `container`, `image`, `deployment` ou `health`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            EventKind::Container { .. } => "container",
+            EventKind::Image { .. } => "image",
+            EventKind::Deployment { .. } => "deployment",
+            EventKind::Health { .. } => "health",
+        }
+    }
+
+    /// Ação do evento, como aparece no JSON (ex.: `oom_killed`)
+    pub fn action(&self) -> String {
+        let action = match self {
+            EventKind::Container { action, .. } => serde_json::to_value(action),
+            EventKind::Image { action, .. } => serde_json::to_value(action),
+            EventKind::Deployment { action, .. } => serde_json::to_value(action),
+            EventKind::Health { transition, .. } => serde_json::to_value(transition),
+        };
+        action
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    /// Nome do objeto do evento (container, imagem, deployment ou alvo)
+    pub fn subject(&self) -> &str {
+        match self {
+            EventKind::Container { name, .. } => name,
+            EventKind::Image { reference, .. } => reference,
+            EventKind::Deployment { name, .. } => name,
+            EventKind::Health { target_id, .. } => target_id,
+        }
+    }
+}
+
+/// Envelope comum dos eventos publicados no barramento
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolisEvent {
+    pub timestamp: DateTime<Utc>,
+    /// Componente que publicou o evento (`runtime`, `orchestrator`, ...)
+    pub source: String,
+    pub kind: EventKind,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl PolisEvent {
+    pub fn new(timestamp: DateTime<Utc>, source: &str, kind: EventKind) -> Self {
+        Self {
+            timestamp,
+            source: source.to_string(),
+            kind,
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_attribute(mut self, key: &str, value: &str) -> Self {
+        self.attributes.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+/// Filtro de eventos. Cada `chave=valor` precisa casar: `type`, `action` e
+/// `source` comparam os campos do envelope, `subject` o objeto do evento e as
+/// demais chaves os atributos.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub conditions: Vec<(String, String)>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn with_condition(mut self, key: &str, value: &str) -> Self {
+        self.conditions.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Interpreta filtros `chave=valor`, como os de `polis events --filter`
+    pub fn parse(filters: &[String]) -> Result<Self> {
+        let mut filter = Self::new();
+        for entry in filters {
+            match entry.split_once('=') {
+                Some((key, value)) if !key.is_empty() && !value.is_empty() => {
+                    filter = filter.with_condition(key, value);
+                }
+                _ => {
+                    return Err(PolisError::Config(format!(
+                        "Filtro de evento inválido '{}': use chave=valor",
+                        entry
+                    )))
+                }
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, event: &PolisEvent) -> bool {
+        if self.since.is_some_and(|since| event.timestamp < since) {
+            return false;
+        }
+        self.conditions
+            .iter()
+            .all(|(key, value)| match key.as_str() {
+                "type" => event.kind.type_name() == value,
+                "action" => event.kind.action() == *value,
+                "source" => event.source == *value,
+                "subject" => event.kind.subject() == value,
+                _ => event.attributes.get(key) == Some(value),
+            })
+    }
+}
+
+/// Diário em disco dos eventos, em linhas JSON com tamanho limitado pela
+/// rotação; permite consultar eventos anteriores a um reinício
+pub struct EventJournal {
+    writer: RotatingLogWriter,
+}
+
+impl EventJournal {
+    pub fn open(path: impl Into<PathBuf>, rotation: LogRotation) -> Result<Self> {
+        Ok(Self {
+            writer: RotatingLogWriter::open(path, rotation)?,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.writer.path()
+    }
+
+    pub fn append(&mut self, event: &PolisEvent) -> Result<()> {
+        self.writer.write(event)
+    }
+
+    /// Eventos do diário em `path` que casam com o filtro, do mais antigo ao
+    /// mais novo; um diário inexistente não tem eventos
+    pub fn read(path: &Path, filter: &EventFilter) -> Result<Vec<PolisEvent>> {
+        let mut files = Vec::new();
+        while rotated_log_path(path, files.len() + 1).exists() {
+            files.push(rotated_log_path(path, files.len() + 1));
+        }
+        files.reverse();
+        files.push(path.to_path_buf());
+
+        let mut events = Vec::new();
+        for file in files {
+            let content = match std::fs::read_to_string(&file) {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            events.extend(
+                content
+                    .lines()
+                    .filter_map(|line| serde_json::from_str::<PolisEvent>(line).ok())
+                    .filter(|event| filter.matches(event)),
+            );
+        }
+        Ok(events)
+    }
+}
+
+/// Barramento de eventos compartilhado pelos componentes. Clones publicam no
+/// mesmo canal e no mesmo diário.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<PolisEvent>,
+    journal: Option<Arc<Mutex<EventJournal>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self {
+            sender,
+            journal: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Grava também cada evento publicado no diário
+    pub fn with_journal(mut self, journal: EventJournal) -> Self {
+        self.journal = Some(Arc::new(Mutex::new(journal)));
+        self
+    }
+
+    /// Usa outro relógio para datar os eventos (ex.: relógio manual em testes)
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Publica um evento datado agora
+    pub fn publish(&self, source: &str, kind: EventKind) -> PolisEvent {
+        let event = PolisEvent::new(self.clock.now(), source, kind);
+        self.publish_event(event.clone());
+        event
+    }
+
+    /// Publica um evento já montado, p. ex. com atributos. Falhas ao gravar no
+    /// diário não impedem a entrega aos assinantes.
+    pub fn publish_event(&self, event: PolisEvent) {
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.lock().unwrap().append(&event) {
+                tracing::warn!("Falha ao gravar evento no diário: {}", e);
+            }
+        }
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PolisEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
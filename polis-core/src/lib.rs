@@ -1,12 +1,20 @@
+pub mod clock;
 pub mod config;
+pub mod container_log;
 pub mod error;
+pub mod events;
 pub mod logging;
+pub mod procfs;
 pub mod test_utils;
 pub mod types;
 pub mod utils;
 
+pub use clock::*;
 pub use config::*;
+pub use container_log::*;
 pub use error::*;
+pub use events::*;
 pub use logging::*;
+pub use procfs::*;
 pub use types::*;
 pub use utils::*;
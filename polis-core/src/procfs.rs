@@ -0,0 +1,324 @@
+use crate::{PolisError, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Ticks de relógio por segundo usados em /proc/<pid>/stat (USER_HZ)
+pub const CLOCK_TICKS_PER_SECOND: u64 = 100;
+
+/// Faixa de mapeamento de IDs de um user namespace (/proc/<pid>/uid_map)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdMapping {
+    pub container_id: u32,
+    pub host_id: u32,
+    pub size: u32,
+}
+
+impl IdMapping {
+    /// Traduz um ID do host para o ID visto dentro do namespace
+    pub fn to_container(&self, host_id: u32) -> Option<u32> {
+        let offset = host_id.checked_sub(self.host_id)?;
+        (offset < self.size).then(|| self.container_id + offset)
+    }
+
+    /// Lê um arquivo no formato de uid_map/gid_map
+    pub fn parse_map(content: &str) -> Vec<IdMapping> {
+        content
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace().map(|f| f.parse::<u32>());
+                match (fields.next(), fields.next(), fields.next()) {
+                    (Some(Ok(container_id)), Some(Ok(host_id)), Some(Ok(size))) => {
+                        Some(IdMapping {
+                            container_id,
+                            host_id,
+                            size,
+                        })
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Traduz um ID do host através de um conjunto de mapeamentos; sem
+/// mapeamentos o ID é mantido
+pub fn map_host_id(mappings: &[IdMapping], host_id: u32) -> Option<u32> {
+    if mappings.is_empty() {
+        return Some(host_id);
+    }
+    mappings.iter().find_map(|m| m.to_container(host_id))
+}
+
+/// Processo de um container lido do /proc
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessEntry {
+    /// PID no namespace do host
+    pub host_pid: u32,
+    /// PID dentro do namespace de PID do container (último valor de NSpid)
+    pub container_pid: u32,
+    /// UID real no host
+    pub host_uid: u32,
+    /// UID visto dentro do container, quando mapeável pelo user namespace
+    pub uid: Option<u32>,
+    /// Nome do usuário dentro do container
+    pub user: String,
+    pub comm: String,
+    pub state: char,
+    pub threads: u32,
+    pub cpu_percent: f64,
+    pub rss: u64,
+    pub start_time: DateTime<Utc>,
+    /// Linha de comando; `[comm]` para processos sem cmdline (zumbis)
+    pub cmdline: String,
+}
+
+/// Leitor de processos a partir de um diretório /proc (real ou fixture)
+#[derive(Debug, Clone)]
+pub struct ProcReader {
+    proc_root: PathBuf,
+    users: HashMap<u32, String>,
+}
+
+impl Default for ProcReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcReader {
+    pub fn new() -> Self {
+        Self::with_root("/proc")
+    }
+
+    pub fn with_root(proc_root: impl Into<PathBuf>) -> Self {
+        Self {
+            proc_root: proc_root.into(),
+            users: HashMap::new(),
+        }
+    }
+
+    /// Usa o /etc/passwd do container para resolver nomes de usuário
+    pub fn with_passwd(mut self, passwd_path: &Path) -> Self {
+        if let Ok(content) = std::fs::read_to_string(passwd_path) {
+            self.users = parse_passwd(&content);
+        }
+        self
+    }
+
+    pub fn proc_root(&self) -> &Path {
+        &self.proc_root
+    }
+
+    /// PIDs listados em cgroup.procs
+    pub fn cgroup_pids(&self, cgroup_dir: &Path) -> Result<Vec<u32>> {
+        let content = std::fs::read_to_string(cgroup_dir.join("cgroup.procs")).map_err(|e| {
+            PolisError::Runtime(format!(
+                "Erro ao ler processos do cgroup {}: {}",
+                cgroup_dir.display(),
+                e
+            ))
+        })?;
+
+        Ok(content
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect())
+    }
+
+    /// Enumera os processos do cgroup, ignorando os que terminaram durante a leitura
+    pub fn list_cgroup_processes(&self, cgroup_dir: &Path) -> Result<Vec<ProcessEntry>> {
+        let (uptime, boot_time) = self.clock()?;
+        let mut entries = Vec::new();
+        for pid in self.cgroup_pids(cgroup_dir)? {
+            if let Some(entry) = self.read_process_at(pid, uptime, boot_time)? {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Lê um processo; retorna `None` se ele não existe mais
+    pub fn read_process(&self, pid: u32) -> Result<Option<ProcessEntry>> {
+        let (uptime, boot_time) = self.clock()?;
+        self.read_process_at(pid, uptime, boot_time)
+    }
+
+    fn read_process_at(
+        &self,
+        pid: u32,
+        uptime: f64,
+        boot_time: DateTime<Utc>,
+    ) -> Result<Option<ProcessEntry>> {
+        let dir = self.proc_root.join(pid.to_string());
+
+        let stat = match read_proc_file(&dir.join("stat"))? {
+            Some(content) => parse_stat(&content).ok_or_else(|| {
+                PolisError::Runtime(format!("Formato inválido em /proc/{}/stat", pid))
+            })?,
+            None => return Ok(None),
+        };
+        let status = match read_proc_file(&dir.join("status"))? {
+            Some(content) => parse_status(&content),
+            None => return Ok(None),
+        };
+        let cmdline = match read_proc_file(&dir.join("cmdline"))? {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+        let uid_map = read_proc_file(&dir.join("uid_map"))?
+            .map(|content| IdMapping::parse_map(&content))
+            .unwrap_or_default();
+
+        let started_after_boot = stat.start_ticks as f64 / CLOCK_TICKS_PER_SECOND as f64;
+        let elapsed = uptime - started_after_boot;
+        let cpu_seconds =
+            (stat.utime_ticks + stat.stime_ticks) as f64 / CLOCK_TICKS_PER_SECOND as f64;
+        let cpu_percent = if elapsed > 0.0 {
+            cpu_seconds / elapsed * 100.0
+        } else {
+            0.0
+        };
+
+        let uid = map_host_id(&uid_map, status.uid);
+        let user = match uid {
+            Some(uid) => self
+                .users
+                .get(&uid)
+                .cloned()
+                .unwrap_or_else(|| uid.to_string()),
+            // UID sem mapeamento aparece como overflowuid dentro do namespace
+            None => "nobody".to_string(),
+        };
+
+        let cmdline = cmdline
+            .split('\0')
+            .filter(|arg| !arg.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let cmdline = if cmdline.is_empty() {
+            format!("[{}]", stat.comm)
+        } else {
+            cmdline
+        };
+
+        Ok(Some(ProcessEntry {
+            host_pid: pid,
+            container_pid: status.nspid.last().copied().unwrap_or(pid),
+            host_uid: status.uid,
+            uid,
+            user,
+            comm: stat.comm,
+            state: stat.state,
+            threads: stat.threads,
+            cpu_percent,
+            rss: status.rss_kb * 1024,
+            start_time: boot_time
+                + chrono::Duration::milliseconds((started_after_boot * 1000.0) as i64),
+            cmdline,
+        }))
+    }
+
+    /// Uptime do sistema em segundos e horário do boot
+    fn clock(&self) -> Result<(f64, DateTime<Utc>)> {
+        let uptime = std::fs::read_to_string(self.proc_root.join("uptime"))?
+            .split_whitespace()
+            .next()
+            .and_then(|v| v.parse::<f64>().ok())
+            .ok_or_else(|| PolisError::Runtime("Formato inválido em /proc/uptime".to_string()))?;
+
+        let boot_time = std::fs::read_to_string(self.proc_root.join("stat"))?
+            .lines()
+            .find_map(|line| line.strip_prefix("btime "))
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+            .ok_or_else(|| PolisError::Runtime("btime ausente em /proc/stat".to_string()))?;
+
+        Ok((uptime, boot_time))
+    }
+}
+
+/// Lê um arquivo do /proc; `None` se o processo terminou (ENOENT/ESRCH)
+fn read_proc_file(path: &Path) -> Result<Option<String>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(String::from_utf8_lossy(&bytes).into_owned())),
+        Err(e) if process_vanished(&e) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn process_vanished(error: &io::Error) -> bool {
+    const ESRCH: i32 = 3;
+    error.kind() == io::ErrorKind::NotFound || error.raw_os_error() == Some(ESRCH)
+}
+
+struct ProcStat {
+    comm: String,
+    state: char,
+    utime_ticks: u64,
+    stime_ticks: u64,
+    threads: u32,
+    start_ticks: u64,
+}
+
+/// Interpreta /proc/<pid>/stat; o comm pode conter espaços e parênteses
+fn parse_stat(content: &str) -> Option<ProcStat> {
+    let open = content.find('(')?;
+    let close = content.rfind(')')?;
+    let comm = content.get(open + 1..close)?.to_string();
+    let fields: Vec<&str> = content.get(close + 1..)?.split_whitespace().collect();
+
+    // Campos numerados a partir do estado (campo 3 em proc(5))
+    Some(ProcStat {
+        comm,
+        state: fields.first()?.chars().next()?,
+        utime_ticks: fields.get(11)?.parse().ok()?,
+        stime_ticks: fields.get(12)?.parse().ok()?,
+        threads: fields.get(17)?.parse().ok()?,
+        start_ticks: fields.get(19)?.parse().ok()?,
+    })
+}
+
+struct ProcStatus {
+    uid: u32,
+    rss_kb: u64,
+    nspid: Vec<u32>,
+}
+
+fn parse_status(content: &str) -> ProcStatus {
+    let mut status = ProcStatus {
+        uid: 0,
+        rss_kb: 0,
+        nspid: Vec::new(),
+    };
+
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let mut values = value.split_whitespace();
+        match key {
+            "Uid" => status.uid = values.next().and_then(|v| v.parse().ok()).unwrap_or(0),
+            "VmRSS" => status.rss_kb = values.next().and_then(|v| v.parse().ok()).unwrap_or(0),
+            "NSpid" => status.nspid = values.filter_map(|v| v.parse().ok()).collect(),
+            _ => {}
+        }
+    }
+
+    status
+}
+
+fn parse_passwd(content: &str) -> HashMap<u32, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
@@ -44,6 +44,72 @@ pub struct Container {
     pub network_mode: NetworkMode,
     pub ports: Vec<PortMapping>,
     pub volumes: Vec<VolumeMount>,
+    #[serde(default)]
+    pub rootfs: RootfsConfig,
+    /// Tempo máximo de execução a partir do início
+    #[serde(default)]
+    pub max_runtime: Option<std::time::Duration>,
+    /// Instante em que o container será parado por prazo
+    #[serde(default)]
+    pub deadline: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub stop_reason: Option<StopReason>,
+    /// Destinos externos permitidos; sem política o tráfego de saída é livre
+    #[serde(default)]
+    pub egress: Option<EgressPolicy>,
+}
+
+/// Motivo pelo qual um container foi parado
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopReason {
+    /// Parada solicitada pelo usuário ou pela API
+    Requested,
+    /// Tempo máximo de execução atingido
+    DeadlineExceeded,
+    /// Processo encerrado pelo OOM killer do kernel
+    OomKilled,
+}
+
+/// Política de saída (egress) de um container
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct EgressPolicy {
+    /// Domínios permitidos; `*.exemplo.com` cobre os subdomínios
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    /// Redes permitidas para acesso direto por IP, ex.: `10.0.0.0/8`
+    #[serde(default)]
+    pub allowed_cidrs: Vec<String>,
+    #[serde(default)]
+    pub mode: EgressMode,
+}
+
+impl EgressPolicy {
+    /// Monta a política a partir de uma lista mista de domínios e redes
+    /// (`api.exemplo.com`, `*.exemplo.com`, `10.0.0.0/8`, `192.0.2.10`)
+    pub fn from_allow_list(entries: &[String], mode: EgressMode) -> Self {
+        let (allowed_cidrs, allowed_domains) = entries.iter().cloned().partition(|entry| {
+            let address = entry
+                .split_once('/')
+                .map_or(entry.as_str(), |(address, _)| address);
+            address.parse::<std::net::IpAddr>().is_ok()
+        });
+        Self {
+            allowed_domains,
+            allowed_cidrs,
+            mode,
+        }
+    }
+}
+
+/// Como as violações da política de saída são tratadas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EgressMode {
+    /// Bloqueia destinos fora da lista
+    #[default]
+    Enforce,
+    /// Apenas registra as violações
+    LogOnly,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -93,10 +159,18 @@ pub struct Volume {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct ResourceLimits {
+    /// Limite de memória em bytes
     pub memory_limit: Option<u64>,
+    /// Limite de memória mais swap em bytes; igual a `memory_limit` desliga
+    /// o swap
     pub memory_swap: Option<u64>,
+    /// Fração de CPUs disponível (ex.: 0.5 = metade de uma CPU)
     pub cpu_quota: Option<f64>,
+    /// Período da quota de CPU em microssegundos (padrão 100000)
     pub cpu_period: Option<u64>,
+    /// Peso relativo de CPU no formato do cgroup v1 (2 a 262144, padrão 1024)
+    #[serde(default)]
+    pub cpu_shares: Option<u64>,
     pub disk_quota: Option<u64>,
     pub pids_limit: Option<i64>,
 }
@@ -110,7 +184,8 @@ pub enum NetworkMode {
     Custom(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Porta do host publicada para uma porta do container
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct PortMapping {
     pub host_port: u16,
     pub container_port: u16,
@@ -139,6 +214,30 @@ pub enum VolumeMode {
     Tmpfs,
 }
 
+/// Caminhos graváveis aplicados automaticamente quando o rootfs é somente leitura
+pub const DEFAULT_WRITABLE_PATHS: &[&str] = &["/tmp", "/run", "/var/tmp"];
+
+/// Configuração do sistema de arquivos raiz do container
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct RootfsConfig {
+    pub read_only: bool,
+    pub writable_paths: Vec<WritablePath>,
+    pub disable_default_writable_paths: bool,
+}
+
+/// Caminho gravável montado sobre o rootfs somente leitura
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WritablePath {
+    pub destination: PathBuf,
+    pub source: WritableSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum WritableSource {
+    Tmpfs { size: Option<u64> },
+    Bind(String),
+}
+
 impl ContainerId {
     pub fn new() -> Self {
         Self(Uuid::new_v4())
@@ -155,6 +254,121 @@ impl fmt::Display for ContainerId {
     }
 }
 
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+impl PortMapping {
+    /// Indica se os dois mapeamentos disputam a mesma porta do host
+    pub fn conflicts_with(&self, other: &PortMapping) -> bool {
+        let any =
+            |ip: &Option<String>| matches!(ip.as_deref(), None | Some("0.0.0.0") | Some("::"));
+        self.host_port == other.host_port
+            && self.protocol == other.protocol
+            && (any(&self.host_ip) || any(&other.host_ip) || self.host_ip == other.host_ip)
+    }
+}
+
+/// Formato de `container list`, como `0.0.0.0:8080->80/tcp`
+impl fmt::Display for PortMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}->{}/{}",
+            self.host_ip.as_deref().unwrap_or("0.0.0.0"),
+            self.host_port,
+            self.container_port,
+            self.protocol
+        )
+    }
+}
+
+impl RootfsConfig {
+    pub fn read_only() -> Self {
+        Self {
+            read_only: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_writable_path(mut self, path: WritablePath) -> Self {
+        self.writable_paths.push(path);
+        self
+    }
+
+    pub fn without_default_writable_paths(mut self) -> Self {
+        self.disable_default_writable_paths = true;
+        self
+    }
+
+    /// Caminhos graváveis efetivos: padrões (se aplicáveis) seguidos dos declarados
+    pub fn effective_writable_paths(&self) -> Vec<WritablePath> {
+        let mut paths = Vec::new();
+
+        if self.read_only && !self.disable_default_writable_paths {
+            for default in DEFAULT_WRITABLE_PATHS {
+                let declared = self
+                    .writable_paths
+                    .iter()
+                    .any(|p| p.destination == Path::new(default));
+                if !declared {
+                    paths.push(WritablePath::tmpfs(*default, None));
+                }
+            }
+        }
+
+        paths.extend(self.writable_paths.iter().cloned());
+        paths
+    }
+}
+
+impl WritablePath {
+    pub fn tmpfs(destination: impl Into<PathBuf>, size: Option<u64>) -> Self {
+        Self {
+            destination: destination.into(),
+            source: WritableSource::Tmpfs { size },
+        }
+    }
+
+    pub fn bind(source: impl Into<String>, destination: impl Into<PathBuf>) -> Self {
+        Self {
+            destination: destination.into(),
+            source: WritableSource::Bind(source.into()),
+        }
+    }
+
+    /// Interpreta `/dados`, `/tmp:size=64m` (tmpfs) ou `/host/dir:/dados` (bind)
+    pub fn parse(spec: &str) -> crate::Result<Self> {
+        let (first, rest) = match spec.split_once(':') {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (spec, None),
+        };
+
+        let path = match rest {
+            None => Self::tmpfs(first, None),
+            Some(option) if option.starts_with("size=") => {
+                let size = crate::parse_size(&option["size=".len()..])?;
+                Self::tmpfs(first, Some(size))
+            }
+            Some(destination) => Self::bind(first, destination),
+        };
+
+        if !path.destination.is_absolute() {
+            return Err(crate::PolisError::Config(format!(
+                "Caminho gravável deve ser absoluto: {}",
+                path.destination.display()
+            )));
+        }
+
+        Ok(path)
+    }
+}
+
 impl ImageId {
     pub fn new(name: &str, tag: &str) -> Self {
         Self(format!("{}:{}", name, tag))
@@ -1,3 +1,6 @@
+use crate::{PolisError, PortMapping, Protocol, Result};
+use std::net::IpAddr;
+use std::time::Duration;
 use uuid::Uuid;
 
 pub fn generate_container_id() -> Uuid {
@@ -7,3 +10,166 @@ pub fn generate_container_id() -> Uuid {
 pub fn generate_image_id() -> String {
     format!("polis-{}", &Uuid::new_v4().to_string()[..8])
 }
+
+/// Converte tamanhos como `512`, `64k`, `128m`, `1g` ou `2GiB` em bytes
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| PolisError::Config(format!("Tamanho inválido: {}", value)))?;
+
+    let unit = unit.trim().to_ascii_lowercase();
+    let unit = unit
+        .strip_suffix("ib")
+        .or_else(|| unit.strip_suffix('b'))
+        .unwrap_or(&unit);
+
+    let multiplier: u64 = match unit {
+        "" => 1,
+        "k" => 1024,
+        "m" => 1024 * 1024,
+        "g" => 1024 * 1024 * 1024,
+        "t" => 1024 * 1024 * 1024 * 1024,
+        _ => {
+            return Err(PolisError::Config(format!(
+                "Unidade de tamanho inválida: {}",
+                value
+            )))
+        }
+    };
+
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| PolisError::Config(format!("Tamanho muito grande: {}", value)))
+}
+
+/// Converte `[ip:]porta_host:porta_container[/protocolo]` em mapeamentos de
+/// porta. Faixas como `8000-8002:80-82` geram um mapeamento por porta.
+pub fn parse_port_mapping(spec: &str) -> Result<Vec<PortMapping>> {
+    let invalid = |reason: &str| {
+        PolisError::Config(format!(
+            "Mapeamento de porta inválido '{}': {}",
+            spec, reason
+        ))
+    };
+
+    let spec = spec.trim();
+    let (ports, protocol) = match spec.split_once('/') {
+        None => (spec, Protocol::Tcp),
+        Some((ports, protocol)) => match protocol.to_ascii_lowercase().as_str() {
+            "tcp" => (ports, Protocol::Tcp),
+            "udp" => (ports, Protocol::Udp),
+            _ => return Err(invalid("o protocolo deve ser tcp ou udp")),
+        },
+    };
+
+    let mut parts = ports.rsplitn(3, ':');
+    let container = parts.next().unwrap_or_default();
+    let host = parts
+        .next()
+        .ok_or_else(|| invalid("esperado porta_host:porta_container"))?;
+    let host_ip = match parts.next() {
+        Some(ip) => {
+            let ip = ip.trim_start_matches('[').trim_end_matches(']');
+            ip.parse::<IpAddr>()
+                .map_err(|_| invalid("IP do host inválido"))?;
+            Some(ip.to_string())
+        }
+        None => None,
+    };
+
+    let (host_start, host_end) =
+        parse_port_range(host).ok_or_else(|| invalid("porta do host inválida"))?;
+    let (container_start, container_end) =
+        parse_port_range(container).ok_or_else(|| invalid("porta do container inválida"))?;
+    if host_end - host_start != container_end - container_start {
+        return Err(invalid("as faixas de portas têm tamanhos diferentes"));
+    }
+
+    Ok((0..=host_end - host_start)
+        .map(|offset| PortMapping {
+            host_port: host_start + offset,
+            container_port: container_start + offset,
+            protocol: protocol.clone(),
+            host_ip: host_ip.clone(),
+        })
+        .collect())
+}
+
+/// Porta (`80`) ou faixa (`8000-8010`) entre 1 e 65535
+fn parse_port_range(range: &str) -> Option<(u16, u16)> {
+    let (start, end) = range.split_once('-').unwrap_or((range, range));
+    let start: u16 = start.parse().ok()?;
+    let end: u16 = end.parse().ok()?;
+    (start > 0 && start <= end).then_some((start, end))
+}
+
+/// Converte durações como `90`, `30s`, `10m`, `2h`, `1d` ou `1h30m`
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let invalid = || PolisError::Config(format!("Duração inválida: {}", value));
+
+    if value.is_empty() {
+        return Err(invalid());
+    }
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let amount: u64 = number.parse().map_err(|_| invalid())?;
+        number.clear();
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        total = amount
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+    }
+
+    // Número final sem unidade, como em `1h30`
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Formata uma duração como `1h30m`, `5m10s` ou `42s`
+pub fn format_duration(duration: Duration) -> String {
+    let total = duration.as_secs();
+    let (days, hours, minutes, seconds) = (
+        total / 86_400,
+        total % 86_400 / 3600,
+        total % 3600 / 60,
+        total % 60,
+    );
+
+    let mut formatted = String::new();
+    for (amount, unit) in [(days, "d"), (hours, "h"), (minutes, "m"), (seconds, "s")] {
+        if amount > 0 {
+            formatted.push_str(&format!("{}{}", amount, unit));
+        }
+    }
+    if formatted.is_empty() {
+        formatted.push_str("0s");
+    }
+    formatted
+}
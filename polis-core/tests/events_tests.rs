@@ -0,0 +1,130 @@
+use chrono::{TimeZone, Utc};
+use polis_core::{
+    rotated_log_path, Clock, ContainerAction, DeploymentAction, EventBus, EventFilter,
+    EventJournal, EventKind, LogRotation, ManualClock, PolisEvent,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn container(action: ContainerAction, name: &str) -> EventKind {
+    EventKind::Container {
+        action,
+        id: format!("{}-id", name),
+        name: name.to_string(),
+        exit_code: None,
+    }
+}
+
+fn deployment(action: DeploymentAction, name: &str) -> EventKind {
+    EventKind::Deployment {
+        action,
+        name: name.to_string(),
+        namespace: "default".to_string(),
+        replicas: Some(3),
+    }
+}
+
+#[test]
+fn test_publish_from_two_sources() {
+    let bus = EventBus::new();
+    let runtime_bus = bus.clone();
+    let orchestrator_bus = bus.clone();
+    let mut events = bus.subscribe();
+
+    runtime_bus.publish("runtime", container(ContainerAction::Started, "web"));
+    orchestrator_bus.publish("orchestrator", deployment(DeploymentAction::Scaled, "api"));
+    runtime_bus.publish("runtime", container(ContainerAction::OomKilled, "web"));
+
+    let mut received = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        received.push(event);
+    }
+    let sources: Vec<&str> = received.iter().map(|e| e.source.as_str()).collect();
+    assert_eq!(sources, vec!["runtime", "orchestrator", "runtime"]);
+
+    let containers = EventFilter::parse(&["type=container".to_string()]).unwrap();
+    let matched: Vec<String> = received
+        .iter()
+        .filter(|e| containers.matches(e))
+        .map(|e| e.kind.action())
+        .collect();
+    assert_eq!(matched, vec!["started", "oom_killed"]);
+
+    let scaled = EventFilter::new()
+        .with_condition("type", "deployment")
+        .with_condition("action", "scaled")
+        .with_condition("subject", "api");
+    assert_eq!(received.iter().filter(|e| scaled.matches(e)).count(), 1);
+}
+
+#[test]
+fn test_filter_since_and_attributes() {
+    let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap());
+    let bus = EventBus::new().with_clock(Arc::new(clock.clone()));
+
+    let old = bus.publish("runtime", container(ContainerAction::Created, "db"));
+    clock.advance(Duration::from_secs(600));
+    let recent = PolisEvent::new(
+        clock.now(),
+        "runtime",
+        container(ContainerAction::Removed, "db"),
+    )
+    .with_attribute("node", "node-1");
+
+    let filter = EventFilter::parse(&["node=node-1".to_string()])
+        .unwrap()
+        .since(old.timestamp + chrono::Duration::minutes(5));
+    assert!(!filter.matches(&old));
+    assert!(filter.matches(&recent));
+
+    assert!(EventFilter::parse(&["type".to_string()]).is_err());
+    assert!(EventFilter::parse(&["=container".to_string()]).is_err());
+}
+
+#[test]
+fn test_journal_rotation() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events").join("events.log");
+    let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap());
+    let journal = EventJournal::open(
+        &path,
+        LogRotation {
+            max_size: 512,
+            max_files: 3,
+        },
+    )
+    .unwrap();
+    let bus = EventBus::new()
+        .with_journal(journal)
+        .with_clock(Arc::new(clock.clone()));
+
+    // ~130 bytes per event: 3 files of 512 bytes cannot hold all 40
+    for n in 0..40 {
+        bus.publish(
+            "runtime",
+            container(ContainerAction::Started, &format!("c{}", n)),
+        );
+        clock.advance(Duration::from_secs(1));
+    }
+    assert!(rotated_log_path(&path, 2).exists());
+    assert!(!rotated_log_path(&path, 3).exists());
+
+    // Oldest events were dropped; the rest read back in order across files
+    let events = EventJournal::read(&path, &EventFilter::new()).unwrap();
+    assert!(!events.is_empty() && events.len() < 40);
+    assert_eq!(events.last().unwrap().kind.subject(), "c39");
+    assert!(events.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+
+    // The journal survives a restart of the bus
+    let reopened = EventJournal::open(&path, LogRotation::default()).unwrap();
+    EventBus::new()
+        .with_journal(reopened)
+        .publish("orchestrator", deployment(DeploymentAction::Deleted, "api"));
+    let deployments = EventJournal::read(
+        &path,
+        &EventFilter::new().with_condition("type", "deployment"),
+    )
+    .unwrap();
+    assert_eq!(deployments.len(), 1);
+    assert_eq!(deployments[0].source, "orchestrator");
+}
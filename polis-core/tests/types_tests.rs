@@ -63,6 +63,11 @@ fn test_container() {
         network_mode: NetworkMode::default(),
         ports: Vec::new(),
         volumes: Vec::new(),
+        rootfs: Default::default(),
+        max_runtime: None,
+        deadline: None,
+        stop_reason: None,
+        egress: None,
     };
 
     assert_eq!(container.name, "test-container");
@@ -114,6 +119,7 @@ fn test_resource_limits() {
         memory_swap: Some(1024 * 1024 * 1024), // 1GB
         cpu_quota: Some(0.5),                  // 50% CPU
         cpu_period: Some(100000),              // 100ms
+        cpu_shares: None,
         pids_limit: Some(100),
         disk_quota: Some(5 * 1024 * 1024 * 1024), // 5GB
     };
@@ -186,6 +192,11 @@ fn test_serialization() {
         network_mode: NetworkMode::default(),
         ports: Vec::new(),
         volumes: Vec::new(),
+        rootfs: Default::default(),
+        max_runtime: None,
+        deadline: None,
+        stop_reason: None,
+        egress: None,
     };
 
     // Test JSON serialization
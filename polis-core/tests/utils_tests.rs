@@ -0,0 +1,99 @@
+use polis_core::{parse_port_mapping, parse_size, PortMapping, Protocol};
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("512").unwrap(), 512);
+    assert_eq!(parse_size("64k").unwrap(), 64 * 1024);
+    assert_eq!(parse_size("256m").unwrap(), 256 * 1024 * 1024);
+    assert_eq!(parse_size("1G").unwrap(), 1024 * 1024 * 1024);
+    assert_eq!(parse_size("2GiB").unwrap(), 2 * 1024 * 1024 * 1024);
+    assert_eq!(parse_size("128mb").unwrap(), 128 * 1024 * 1024);
+    assert_eq!(parse_size(" 10m ").unwrap(), 10 * 1024 * 1024);
+
+    assert!(parse_size("").is_err());
+    assert!(parse_size("m").is_err());
+    assert!(parse_size("1.5g").is_err());
+    assert!(parse_size("10x").is_err());
+    assert!(parse_size("99999999999t").is_err());
+}
+
+fn mapping(host_port: u16, container_port: u16, protocol: Protocol) -> PortMapping {
+    PortMapping {
+        host_port,
+        container_port,
+        protocol,
+        host_ip: None,
+    }
+}
+
+#[test]
+fn test_parse_port_mapping() {
+    assert_eq!(
+        parse_port_mapping("8080:80").unwrap(),
+        [mapping(8080, 80, Protocol::Tcp)]
+    );
+    assert_eq!(
+        parse_port_mapping("5353:53/udp").unwrap(),
+        [mapping(5353, 53, Protocol::Udp)]
+    );
+    assert_eq!(
+        parse_port_mapping("127.0.0.1:8443:443/TCP").unwrap(),
+        [PortMapping {
+            host_ip: Some("127.0.0.1".to_string()),
+            ..mapping(8443, 443, Protocol::Tcp)
+        }]
+    );
+    assert_eq!(
+        parse_port_mapping("[::1]:8080:80").unwrap()[0]
+            .host_ip
+            .as_deref(),
+        Some("::1")
+    );
+
+    // Ranges expand to one mapping per port
+    assert_eq!(
+        parse_port_mapping("8000-8002:9000-9002/udp").unwrap(),
+        [
+            mapping(8000, 9000, Protocol::Udp),
+            mapping(8001, 9001, Protocol::Udp),
+            mapping(8002, 9002, Protocol::Udp),
+        ]
+    );
+
+    for invalid in [
+        "",
+        "80",
+        "8080:",
+        ":80",
+        "0:80",
+        "8080:65536",
+        "http:80",
+        "8080:80/sctp",
+        "8080:80/",
+        "8000-8002:80",
+        "8002-8000:80-82",
+        "localhost:8080:80",
+    ] {
+        assert!(parse_port_mapping(invalid).is_err(), "{:?}", invalid);
+    }
+}
+
+#[test]
+fn test_port_mapping_conflicts() {
+    let web = mapping(8080, 80, Protocol::Tcp);
+    assert_eq!(web.to_string(), "0.0.0.0:8080->80/tcp");
+
+    // Same host port and protocol, whatever the container port
+    assert!(web.conflicts_with(&mapping(8080, 8000, Protocol::Tcp)));
+    assert!(!web.conflicts_with(&mapping(8080, 80, Protocol::Udp)));
+    assert!(!web.conflicts_with(&mapping(8081, 80, Protocol::Tcp)));
+
+    // A wildcard address overlaps every specific address
+    let local = |ip: &str| PortMapping {
+        host_ip: Some(ip.to_string()),
+        ..mapping(8080, 80, Protocol::Tcp)
+    };
+    assert!(web.conflicts_with(&local("127.0.0.1")));
+    assert!(local("127.0.0.1").conflicts_with(&local("127.0.0.1")));
+    assert!(!local("127.0.0.1").conflicts_with(&local("10.0.0.1")));
+}
@@ -0,0 +1,80 @@
+use crate::{OciConfig, OciImageConfig, OciRootFs};
+use polis_core::{PolisError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Tipo de mídia das camadas de um `docker save`: tar sem compressão
+pub const OCI_LAYER_TAR_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar";
+pub const OCI_CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+
+/// Entrada do `manifest.json` gerado por `docker save`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DockerArchiveEntry {
+    /// Caminho do config dentro do arquivo
+    pub config: String,
+    /// `repositório:tag`; vazio quando a imagem foi salva pelo ID
+    #[serde(default)]
+    pub repo_tags: Option<Vec<String>>,
+    /// Caminhos das camadas (tar sem compressão), da base para o topo
+    pub layers: Vec<String>,
+}
+
+/// Converte o config do Docker (campos em PascalCase) para o `OciConfig`
+pub fn oci_config_from_docker(document: &Value) -> Result<OciConfig> {
+    let text = |value: &Value, key: &str| value.get(key)?.as_str().map(str::to_string);
+    let list = |value: &Value, key: &str| -> Option<Vec<String>> {
+        value
+            .get(key)?
+            .as_array()?
+            .iter()
+            .map(|item| item.as_str().map(str::to_string))
+            .collect()
+    };
+    let object = |value: &Value, key: &str| -> Option<HashMap<String, Value>> {
+        serde_json::from_value(value.get(key)?.clone()).ok()
+    };
+
+    let architecture = text(document, "architecture")
+        .ok_or_else(|| PolisError::Image("Config da imagem sem architecture".to_string()))?;
+    let os = text(document, "os")
+        .ok_or_else(|| PolisError::Image("Config da imagem sem os".to_string()))?;
+    let config = document.get("config").cloned().unwrap_or(Value::Null);
+    let rootfs = document.get("rootfs").cloned().unwrap_or(Value::Null);
+
+    Ok(OciConfig {
+        architecture,
+        os,
+        config: OciImageConfig {
+            user: text(&config, "User").filter(|user| !user.is_empty()),
+            exposed_ports: object(&config, "ExposedPorts"),
+            env: list(&config, "Env"),
+            entrypoint: list(&config, "Entrypoint"),
+            cmd: list(&config, "Cmd"),
+            volumes: object(&config, "Volumes"),
+            working_dir: text(&config, "WorkingDir").filter(|dir| !dir.is_empty()),
+            labels: config
+                .get("Labels")
+                .and_then(|labels| serde_json::from_value(labels.clone()).ok()),
+        },
+        rootfs: OciRootFs {
+            r#type: text(&rootfs, "type").unwrap_or_else(|| "layers".to_string()),
+            diff_ids: list(&rootfs, "diff_ids").unwrap_or_default(),
+        },
+    })
+}
+
+/// Resolve um caminho citado pelo `manifest.json` dentro do arquivo
+/// extraído, recusando caminhos que saiam dele
+pub fn archive_path(root: &Path, relative: &str) -> Result<PathBuf> {
+    let path = Path::new(relative);
+    if relative.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(PolisError::Image(format!(
+            "Caminho inválido no manifest.json: {}",
+            relative
+        )));
+    }
+    Ok(root.join(path))
+}
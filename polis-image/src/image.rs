@@ -1,17 +1,26 @@
 use chrono::{DateTime, Utc};
 use polis_core::{Image, ImageId, PolisError, Result};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
+use crate::docker_archive::{archive_path, oci_config_from_docker, DockerArchiveEntry, OCI_CONFIG_MEDIA_TYPE, OCI_LAYER_TAR_MEDIA_TYPE};
+use crate::oci_layout::{blob_path, TarWriter, OCI_INDEX_MEDIA_TYPE, OCI_LAYOUT_VERSION, REF_NAME_ANNOTATION};
+use crate::reference::{split_reference, DEFAULT_TAG};
+use crate::{LayerDiff, LayerStore, OciConfig, OciDescriptor, OciManifest, Platform, ProgressCallback, PullPriority, PullScheduler, PushProgressCallback, Reference, RegistryConfig, SchedulerConfig};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageMetadata {
     pub id: ImageId,
     pub name: String,
     pub tag: String,
+    /// Digest do manifest
+    #[serde(default)]
+    pub digest: String,
     pub size: u64,
     pub created_at: DateTime<Utc>,
     pub architecture: String,
@@ -34,56 +43,263 @@ pub struct ImageConfig {
 pub struct ImageManager {
     cache_dir: PathBuf,
     registry_client: Arc<Mutex<crate::registry::RegistryClient>>,
+    scheduler: Arc<PullScheduler>,
+    layers: LayerStore,
+    /// Pulls seguram a leitura; a coleta de camadas espera todos terminarem
+    gc_lock: RwLock<()>,
 }
 
 impl ImageManager {
     pub fn new(cache_dir: PathBuf) -> Self {
-        let registry_client = Arc::new(Mutex::new(crate::registry::RegistryClient::new(cache_dir.clone())));
+        let scheduler = Self::create_scheduler(&cache_dir);
+        let layers = LayerStore::new(cache_dir.join("layers"));
+        let registry_client = Arc::new(Mutex::new(
+            crate::registry::RegistryClient::new(cache_dir.clone())
+                .with_scheduler(scheduler.clone())
+                .with_layer_store(layers.clone())
+        ));
         Self {
             cache_dir,
             registry_client,
+            scheduler,
+            layers,
+            gc_lock: RwLock::new(()),
         }
     }
 
     pub fn with_docker_hub_token(cache_dir: PathBuf, token: String) -> Self {
+        let scheduler = Self::create_scheduler(&cache_dir);
+        let layers = LayerStore::new(cache_dir.join("layers"));
         let registry_client = Arc::new(Mutex::new(
             crate::registry::RegistryClient::new(cache_dir.clone())
                 .with_token(token)
+                .with_scheduler(scheduler.clone())
+                .with_layer_store(layers.clone())
         ));
         Self {
             cache_dir,
             registry_client,
+            scheduler,
+            layers,
+            gc_lock: RwLock::new(()),
         }
     }
 
+    fn create_scheduler(cache_dir: &Path) -> Arc<PullScheduler> {
+        let config = RegistryConfig::load().unwrap_or_default();
+        Arc::new(
+            PullScheduler::new(SchedulerConfig::from(&config))
+                .with_quota_file(cache_dir.join("registry_quota.json")),
+        )
+    }
+
+    /// Usa uma configuração de registries fixa em vez do registries.conf
+    pub fn with_registry_config(mut self, config: RegistryConfig) -> Self {
+        if let Some(client) = Arc::get_mut(&mut self.registry_client) {
+            let client = client.get_mut();
+            *client = client.clone().with_config(config);
+        }
+        self
+    }
+
+    /// Store das camadas, compartilhado por todas as imagens
+    pub fn layer_store(&self) -> &LayerStore {
+        &self.layers
+    }
+
+    /// Remove as camadas que nenhuma imagem referencia, depois que os pulls
+    /// em andamento terminam
+    pub async fn gc_layers(&self) -> Result<Vec<String>> {
+        let _exclusive = self.gc_lock.write().await;
+        self.layers.gc().await
+    }
+
+    /// Compara duas camadas da imagem pelos índices em `Image::layers`
+    /// (0 = base)
+    pub async fn diff_layers(&self, image_id: &ImageId, from_layer: usize, to_layer: usize) -> Result<LayerDiff> {
+        let metadata = self.load_image_metadata(image_id).await.map_err(|_| {
+            PolisError::Image(format!("Imagem não encontrada: {}", image_id.0))
+        })?;
+        let layer_path = |index: usize| -> Result<PathBuf> {
+            let digest = metadata.layers.get(index).ok_or_else(|| {
+                PolisError::Image(format!(
+                    "Camada {} inexistente: a imagem {} tem {} camadas",
+                    index,
+                    image_id.0,
+                    metadata.layers.len()
+                ))
+            })?;
+            let path = self.layers.layer_path(digest)?;
+            if !path.exists() {
+                return Err(PolisError::Image(format!("Camada {} ausente do store de camadas", digest)));
+            }
+            Ok(path)
+        };
+        let old = layer_path(from_layer)?;
+        let new = layer_path(to_layer)?;
+
+        // O gc não remove as camadas enquanto são lidas
+        let _shared = self.gc_lock.read().await;
+        tokio::task::spawn_blocking(move || LayerDiff::compute(&old, &new))
+            .await
+            .map_err(|e| PolisError::Image(format!("Erro ao comparar camadas: {}", e)))?
+    }
+
+    /// Agendador de pulls (fila, limites e cotas dos registries)
+    pub fn scheduler(&self) -> Arc<PullScheduler> {
+        self.scheduler.clone()
+    }
+
+    /// Pull interativo: tem prioridade sobre pré-pulls em segundo plano
     pub async fn pull(&self, name: &str) -> Result<Image> {
-        // Pull image from registry
-        let mut client = self.registry_client.lock().await;
-        let image_id = client.pull_image(name).await?;
+        self.pull_with_priority(name, PullPriority::Interactive).await
+    }
+
+    /// Pré-pull em segundo plano
+    pub async fn prepull(&self, name: &str) -> Result<Image> {
+        self.pull_with_priority(name, PullPriority::Background).await
+    }
+
+    pub async fn pull_with_priority(&self, name: &str, priority: PullPriority) -> Result<Image> {
+        self.pull_with_options(name, priority, None).await
+    }
+
+    /// Pull com plataforma explícita (`--platform`), que prevalece sobre os
+    /// overrides do registries.conf e sobre a plataforma do host
+    pub async fn pull_with_options(&self, name: &str, priority: PullPriority, platform: Option<Platform>) -> Result<Image> {
+        self.pull_with_progress_options(name, priority, platform, None).await
+    }
+
+    /// Pull que chama `on_progress` com `(bytes baixados, tamanho)` da camada
+    /// a cada bloco recebido; camadas já presentes no store não são reportadas
+    pub async fn pull_with_progress(&self, name: &str, on_progress: impl Fn(u64, Option<u64>) + Send + 'static) -> Result<Image> {
+        self.pull_with_progress_options(name, PullPriority::Interactive, None, Some(Box::new(on_progress))).await
+    }
+
+    /// [`Self::pull_with_options`] reportando o progresso das camadas
+    pub async fn pull_with_progress_options(&self, name: &str, priority: PullPriority, platform: Option<Platform>, on_progress: Option<ProgressCallback>) -> Result<Image> {
+        // Cada pull usa sua própria cópia do cliente para permitir concorrência
+        let mut client = self.registry_client.lock().await.clone().with_platform(platform);
+        if let Err(e) = client.reload_config() {
+            println!(" Aviso: mantendo configuração anterior dos registries: {}", e);
+        }
+        // A cota é do registry de onde a imagem é baixada (cache pull-through, se houver)
+        let reference = client.config().resolve_reference(name)?;
+        let registry = reference.pull_registry;
+
+        // Aguardar vaga conforme os limites de concorrência e ritmo
+        let _permit = self.scheduler.acquire(name, &registry, priority).await;
+        let _pulling = self.gc_lock.read().await;
+        let (image_id, manifest) = client.pull_manifest_with_progress(name, on_progress).await?;
+        let manifest_bytes = serde_json::to_vec_pretty(&crate::manifest_document(&manifest))?;
+        // Num pull por digest, o digest registrado é o conferido no registry
+        self.register_image(&image_id, name, &manifest, &manifest_bytes, reference.digest).await
+    }
+
+    /// Tags disponíveis no registry para o repositório, sem baixar a imagem
+    pub async fn list_tags(&self, repository: &str) -> Result<Vec<String>> {
+        let mut client = self.registry_client.lock().await.clone();
+        if let Err(e) = client.reload_config() {
+            println!(" Aviso: mantendo configuração anterior dos registries: {}", e);
+        }
+        client.list_tags(repository).await
+    }
+
+    /// Envia a imagem local `name` (`registry/repo:tag`) para o registry da
+    /// própria referência, informando o andamento de cada camada
+    pub async fn push(&self, name: &str, on_progress: Option<PushProgressCallback>) -> Result<()> {
+        let image_id = ImageId::from_string(name);
+        let manifest_bytes = fs::read(self.get_image_dir(&image_id).join("manifest.json")).await.map_err(|_| {
+            PolisError::Image(format!("Imagem {} não encontrada localmente", name))
+        })?;
+        let manifest: OciManifest = serde_json::from_slice(&manifest_bytes)
+            .map_err(|e| PolisError::Image(format!("Erro ao parsear manifest: {}", e)))?;
+
+        let mut client = self.registry_client.lock().await.clone();
+        if let Err(e) = client.reload_config() {
+            println!(" Aviso: mantendo configuração anterior dos registries: {}", e);
+        }
+        // As camadas não podem ser coletadas durante o envio
+        let _pushing = self.gc_lock.read().await;
+        client.push_manifest(name, &manifest, on_progress).await
+    }
+
+    /// Dá à imagem local `source` o nome adicional `target`. As duas passam a
+    /// compartilhar manifest e camadas; nenhuma camada é copiada.
+    pub async fn tag(&self, source: &str, target: &str) -> Result<Image> {
+        let target_reference = Reference::parse(target)?;
+        if target_reference.digest.is_some() {
+            return Err(PolisError::Image(format!(
+                "Tag {} inválida: o nome não pode ter digest",
+                target
+            )));
+        }
+
+        let source_id = ImageId::from_string(source);
+        let metadata = self.load_image_metadata(&source_id).await.map_err(|_| {
+            PolisError::Image(format!("Imagem não encontrada: {}", source))
+        })?;
+        let manifest_bytes = fs::read(self.get_image_dir(&source_id).join("manifest.json")).await?;
+        let manifest: OciManifest = serde_json::from_slice(&manifest_bytes)
+            .map_err(|e| PolisError::Image(format!("Erro ao parsear manifest: {}", e)))?;
+
+        let target_id = ImageId::from_string(target);
+        let (name, tag) = name_and_tag(target);
+        let image = Image {
+            id: target_id.clone(),
+            name,
+            tag,
+            digest: metadata.digest,
+            size: metadata.size,
+            created_at: metadata.created_at,
+            architecture: metadata.architecture,
+            os: metadata.os,
+            layers: metadata.layers,
+            config: polis_core::ImageConfig {
+                entrypoint: metadata.config.entrypoint,
+                cmd: metadata.config.cmd,
+                env: metadata.config.env,
+                working_dir: metadata.config.working_dir,
+                exposed_ports: metadata.config.exposed_ports,
+                volumes: metadata.config.volumes,
+                labels: metadata.config.labels,
+            },
+        };
+
+        // Impede que as camadas sejam coletadas entre a leitura e a nova referência
+        let _tagging = self.gc_lock.read().await;
+        self.save_image_metadata(&image).await?;
+        fs::write(self.get_image_dir(&target_id).join("manifest.json"), &manifest_bytes).await?;
+        let mut blobs = image.layers.clone();
+        blobs.push(manifest.config.digest);
+        self.layers.set_references(&target_id.0, &blobs)?;
+        Ok(image)
+    }
+
+    /// Grava metadata e manifest da imagem, cujos blobs já estão no store, e
+    /// passa a referenciá-los. Sem `manifest_digest`, o digest é o dos bytes
+    /// gravados.
+    async fn register_image(&self, image_id: &ImageId, name: &str, manifest: &OciManifest, manifest_bytes: &[u8], manifest_digest: Option<String>) -> Result<Image> {
+        let layers: Vec<String> = manifest.layers.iter().map(|layer| layer.digest.clone()).collect();
+        let oci_config = self.read_config(&manifest.config.digest).await;
 
         // Try to load existing metadata, or create new one
-        let metadata = match self.load_image_metadata(&image_id).await {
-            Ok(metadata) => metadata,
+        let metadata = match self.load_image_metadata(image_id).await {
+            Ok(metadata) => ImageMetadata { layers: layers.clone(), ..metadata },
             Err(_) => {
                 // Create metadata from image name if not found
-                let (repo, tag) = if let Some(colon_pos) = name.rfind(':') {
-                    (
-                        name[..colon_pos].to_string(),
-                        name[colon_pos + 1..].to_string(),
-                    )
-                } else {
-                    (name.to_string(), "latest".to_string())
-                };
+                let (repo, tag) = name_and_tag(name);
 
                 ImageMetadata {
                     id: image_id.clone(),
                     name: repo,
                     tag,
-                    size: 1024 * 1024, // 1MB default
+                    digest: String::new(),
+                    size: manifest.layers.iter().map(|layer| layer.size).sum(),
                     created_at: chrono::Utc::now(),
                     architecture: "amd64".to_string(),
                     os: "linux".to_string(),
-                    layers: vec!["sha256:local-layer".to_string()],
+                    layers: layers.clone(),
                     config: ImageConfig {
                         entrypoint: Some(vec!["/bin/sh".to_string()]),
                         cmd: Some(vec!["-c".to_string()]),
@@ -97,34 +313,67 @@ impl ImageManager {
             }
         };
 
+        // O config da imagem, quando disponível, prevalece sobre os padrões
+        let (architecture, os, config) = match oci_config {
+            Some(oci) => (
+                oci.architecture,
+                oci.os,
+                polis_core::ImageConfig {
+                    entrypoint: oci.config.entrypoint,
+                    cmd: oci.config.cmd,
+                    env: oci.config.env,
+                    working_dir: oci.config.working_dir,
+                    exposed_ports: oci.config.exposed_ports,
+                    volumes: oci.config.volumes,
+                    labels: oci.config.labels,
+                },
+            ),
+            None => (
+                metadata.architecture,
+                metadata.os,
+                polis_core::ImageConfig {
+                    entrypoint: metadata.config.entrypoint,
+                    cmd: metadata.config.cmd,
+                    env: metadata.config.env,
+                    working_dir: metadata.config.working_dir,
+                    exposed_ports: metadata.config.exposed_ports,
+                    volumes: metadata.config.volumes,
+                    labels: metadata.config.labels,
+                },
+            ),
+        };
+
         // Create Image struct
         let image = Image {
             id: image_id.clone(),
             name: metadata.name.clone(),
             tag: metadata.tag.clone(),
-            digest: "".to_string(), // TODO: Get from manifest
+            digest: manifest_digest.unwrap_or_else(|| crate::sha256_digest(manifest_bytes)),
             size: metadata.size,
             created_at: metadata.created_at,
-            architecture: metadata.architecture,
-            os: metadata.os,
+            architecture,
+            os,
             layers: metadata.layers,
-            config: polis_core::ImageConfig {
-                entrypoint: metadata.config.entrypoint,
-                cmd: metadata.config.cmd,
-                env: metadata.config.env,
-                working_dir: metadata.config.working_dir,
-                exposed_ports: metadata.config.exposed_ports,
-                volumes: metadata.config.volumes,
-                labels: metadata.config.labels,
-            },
+            config,
         };
 
         // Save image metadata
         self.save_image_metadata(&image).await?;
+        fs::write(self.get_image_dir(image_id).join("manifest.json"), manifest_bytes).await?;
+        let mut blobs = layers;
+        blobs.push(manifest.config.digest.clone());
+        self.layers.set_references(&image_id.0, &blobs)?;
 
         Ok(image)
     }
 
+    /// Config da imagem guardado no store, se houver
+    async fn read_config(&self, digest: &str) -> Option<OciConfig> {
+        let mut content = Vec::new();
+        self.layers.get(digest).await.ok()?.read_to_end(&mut content).await.ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
     pub async fn list_images(&self) -> Result<Vec<Image>> {
         let mut images = Vec::new();
 
@@ -142,7 +391,7 @@ impl ImageManager {
                             id: image_id,
                             name: metadata.name,
                             tag: metadata.tag,
-                            digest: "".to_string(), // TODO: Get from manifest
+                            digest: metadata.digest,
                             size: metadata.size,
                             created_at: metadata.created_at,
                             architecture: metadata.architecture,
@@ -172,6 +421,8 @@ impl ImageManager {
         let image_dir = self.get_image_dir(id);
         if image_dir.exists() {
             fs::remove_dir_all(&image_dir).await?;
+            // As camadas só saem do disco no próximo gc_layers
+            self.layers.release(&id.0)?;
             Ok(())
         } else {
             Err(PolisError::Image(format!(
@@ -181,6 +432,271 @@ impl ImageManager {
         }
     }
 
+    /// Exporta a imagem como um tar no formato OCI Image Layout
+    /// (`oci-layout`, `index.json` e `blobs/sha256/`)
+    pub async fn export_oci_tar(&self, image_id: &ImageId, dest: &Path) -> Result<()> {
+        let result = async {
+            let file = fs::File::create(dest).await?;
+            let file = self.write_oci_archive(image_id, file).await?;
+            file.sync_all().await?;
+            Ok(())
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = fs::remove_file(dest).await;
+        }
+        result
+    }
+
+    /// Grava a imagem `name` em `writer` como um tar OCI Image Layout, lendo
+    /// os blobs do store de camadas sem montar o arquivo em memória
+    pub async fn save<W: AsyncWrite + Unpin>(&self, name: &str, writer: W) -> Result<()> {
+        self.write_oci_archive(&ImageId::from_string(name), writer).await?;
+        Ok(())
+    }
+
+    async fn write_oci_archive<W: AsyncWrite + Unpin>(&self, image_id: &ImageId, writer: W) -> Result<W> {
+        let manifest_path = self.get_image_dir(image_id).join("manifest.json");
+        let manifest_bytes = fs::read(&manifest_path).await.map_err(|_| {
+            PolisError::Image(format!(
+                "Manifest da imagem {} não encontrado; faça o pull novamente",
+                image_id.0
+            ))
+        })?;
+        let manifest: OciManifest = serde_json::from_slice(&manifest_bytes)
+            .map_err(|e| PolisError::Image(format!("Erro ao parsear manifest: {}", e)))?;
+        let manifest_digest = crate::sha256_digest(&manifest_bytes);
+
+        let index = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": OCI_INDEX_MEDIA_TYPE,
+            "manifests": [{
+                "mediaType": crate::manifest_document(&manifest)["mediaType"],
+                "size": manifest_bytes.len(),
+                "digest": manifest_digest,
+                "annotations": { REF_NAME_ANNOTATION: image_id.0 },
+            }],
+        });
+        let layout = serde_json::json!({ "imageLayoutVersion": OCI_LAYOUT_VERSION });
+
+        let mut blobs = vec![manifest.config.digest.clone()];
+        for layer in &manifest.layers {
+            if !blobs.contains(&layer.digest) {
+                blobs.push(layer.digest.clone());
+            }
+        }
+
+        let mut tar = TarWriter::new(writer);
+        tar.append_bytes("oci-layout", &serde_json::to_vec(&layout)?).await?;
+        tar.append_bytes("index.json", &serde_json::to_vec_pretty(&index)?).await?;
+        tar.append_dir("blobs/").await?;
+        tar.append_dir("blobs/sha256/").await?;
+        tar.append_bytes(&blob_path(&manifest_digest)?, &manifest_bytes).await?;
+        for digest in &blobs {
+            let size = fs::metadata(self.layers.layer_path(digest)?).await.map_err(|_| {
+                PolisError::Image(format!("Blob {} ausente do store de camadas", digest))
+            })?.len();
+            tar.append_reader(&blob_path(digest)?, size, self.layers.get(digest).await?).await?;
+        }
+        tar.finish().await
+    }
+
+    /// Importa um tar no formato OCI Image Layout. O nome da imagem vem da
+    /// anotação `org.opencontainers.image.ref.name`; sem ela, do nome do
+    /// arquivo. Os digests de todos os blobs são conferidos.
+    pub async fn import_oci_tar(&self, src: &Path) -> Result<ImageId> {
+        let fallback = format!(
+            "{}:latest",
+            src.file_stem().unwrap_or_default().to_string_lossy()
+        );
+        self.import_oci_archive(src, Some(fallback)).await
+    }
+
+    /// Carrega uma imagem de um tar OCI Image Layout lido de `reader`, como o
+    /// gerado por [`ImageManager::save`]. O arquivo precisa da anotação
+    /// `org.opencontainers.image.ref.name`; blobs já presentes no store não
+    /// são gravados de novo.
+    pub async fn load<R: AsyncRead + Unpin>(&self, mut reader: R) -> Result<ImageId> {
+        fs::create_dir_all(&self.cache_dir).await?;
+        let spool = tempfile::Builder::new()
+            .prefix("load-")
+            .suffix(".tar")
+            .tempfile_in(&self.cache_dir)?;
+        let mut file = fs::File::create(spool.path()).await?;
+        tokio::io::copy(&mut reader, &mut file).await?;
+        file.flush().await?;
+        drop(file);
+        self.import_oci_archive(spool.path(), None).await
+    }
+
+    async fn import_oci_archive(&self, src: &Path, fallback_name: Option<String>) -> Result<ImageId> {
+        let staging = self.unpack_archive(src).await?;
+        let root = staging.path();
+
+        let layout = read_json(&root.join("oci-layout")).await?;
+        if layout["imageLayoutVersion"].as_str().is_none() {
+            return Err(PolisError::Image("oci-layout sem imageLayoutVersion".to_string()));
+        }
+        let index = read_json(&root.join("index.json")).await?;
+        let entry = match index["manifests"].as_array().map(Vec::as_slice) {
+            Some([entry]) => entry,
+            Some([]) | None => {
+                return Err(PolisError::Image("index.json não contém nenhuma imagem".to_string()))
+            }
+            Some(_) => {
+                return Err(PolisError::Image(
+                    "index.json com mais de uma imagem não é suportado".to_string(),
+                ))
+            }
+        };
+        let manifest_digest = entry["digest"]
+            .as_str()
+            .ok_or_else(|| PolisError::Image("Entrada do index.json sem digest".to_string()))?;
+
+        let manifest_bytes = fs::read(root.join(blob_path(manifest_digest)?)).await?;
+        if crate::sha256_digest(&manifest_bytes) != manifest_digest {
+            return Err(PolisError::Image(format!(
+                "Manifest não confere com o digest {}",
+                manifest_digest
+            )));
+        }
+        let manifest: OciManifest = serde_json::from_slice(&manifest_bytes)
+            .map_err(|e| PolisError::Image(format!("Erro ao parsear manifest: {}", e)))?;
+
+        let name = match (entry["annotations"][REF_NAME_ANNOTATION].as_str(), fallback_name) {
+            (Some(name), _) => name.to_string(),
+            (None, Some(fallback)) => fallback,
+            (None, None) => {
+                return Err(PolisError::Image(format!(
+                    "index.json sem a anotação {}; não é possível nomear a imagem",
+                    REF_NAME_ANNOTATION
+                )))
+            }
+        };
+        let image_id = ImageId::from_string(&name);
+
+        let _importing = self.gc_lock.read().await;
+        let blobs = std::iter::once(&manifest.config).chain(&manifest.layers);
+        for descriptor in blobs {
+            let blob = fs::File::open(root.join(blob_path(&descriptor.digest)?))
+                .await
+                .map_err(|_| {
+                    PolisError::Image(format!("Blob {} ausente do arquivo", descriptor.digest))
+                })?;
+            self.layers.put(&descriptor.digest, blob).await?;
+        }
+
+        self.register_image(&image_id, &name, &manifest, &manifest_bytes, None).await?;
+        Ok(image_id)
+    }
+
+    /// Importa um tar gerado por `docker save`. As camadas (tar sem
+    /// compressão) entram no store com o digest do próprio tar e o config é
+    /// convertido para o formato OCI. A imagem recebe a primeira tag de
+    /// `RepoTags`; sem tags, o nome do arquivo.
+    pub async fn import_docker_tar(&self, src: &Path) -> Result<ImageId> {
+        let staging = self.unpack_archive(src).await?;
+        let root = staging.path();
+
+        let entries: Vec<DockerArchiveEntry> =
+            serde_json::from_value(read_json(&root.join("manifest.json")).await?).map_err(|e| {
+                PolisError::Image(format!("Erro ao parsear manifest.json: {}", e))
+            })?;
+        let entry = match entries.as_slice() {
+            [entry] => entry,
+            [] => return Err(PolisError::Image("manifest.json não contém nenhuma imagem".to_string())),
+            _ => {
+                return Err(PolisError::Image(
+                    "manifest.json com mais de uma imagem não é suportado".to_string(),
+                ))
+            }
+        };
+
+        let docker_config = read_json(&archive_path(root, &entry.config)?).await?;
+        let config = oci_config_from_docker(&docker_config)?;
+        if !config.rootfs.diff_ids.is_empty() && config.rootfs.diff_ids.len() != entry.layers.len() {
+            return Err(PolisError::Image(format!(
+                "Config lista {} camadas, manifest.json lista {}",
+                config.rootfs.diff_ids.len(),
+                entry.layers.len()
+            )));
+        }
+
+        let name = match entry.repo_tags.as_ref().and_then(|tags| tags.first()) {
+            Some(name) => name.clone(),
+            None => format!(
+                "{}:latest",
+                src.file_stem().unwrap_or_default().to_string_lossy()
+            ),
+        };
+        let image_id = ImageId::from_string(&name);
+
+        let _importing = self.gc_lock.read().await;
+        let mut layers = Vec::new();
+        for (i, layer) in entry.layers.iter().enumerate() {
+            let path = archive_path(root, layer)?;
+            let (digest, size) = file_digest(&path).await.map_err(|_| {
+                PolisError::Image(format!("Camada {} ausente do arquivo", layer))
+            })?;
+            // Camadas sem compressão: o digest do tar é o próprio diff_id
+            if let Some(diff_id) = config.rootfs.diff_ids.get(i) {
+                if *diff_id != digest {
+                    return Err(PolisError::Image(format!(
+                        "Camada {} não confere com o diff_id {}",
+                        layer, diff_id
+                    )));
+                }
+            }
+            self.layers.put(&digest, fs::File::open(&path).await?).await?;
+            layers.push(OciDescriptor {
+                media_type: OCI_LAYER_TAR_MEDIA_TYPE.to_string(),
+                size,
+                digest,
+                urls: None,
+                annotations: None,
+            });
+        }
+
+        let config_json = serde_json::to_vec_pretty(&config)?;
+        let config_digest = crate::sha256_digest(&config_json);
+        self.layers.put(&config_digest, config_json.as_slice()).await?;
+
+        let manifest = OciManifest {
+            schema_version: 2,
+            media_type: crate::OCI_MANIFEST_MEDIA_TYPE.to_string(),
+            config: OciDescriptor {
+                media_type: OCI_CONFIG_MEDIA_TYPE.to_string(),
+                size: config_json.len() as u64,
+                digest: config_digest,
+                urls: None,
+                annotations: None,
+            },
+            layers,
+            annotations: None,
+        };
+        let manifest_bytes = serde_json::to_vec_pretty(&crate::manifest_document(&manifest))?;
+        self.register_image(&image_id, &name, &manifest, &manifest_bytes, None).await?;
+        Ok(image_id)
+    }
+
+    /// Extrai o tar num diretório temporário dentro do cache
+    async fn unpack_archive(&self, src: &Path) -> Result<tempfile::TempDir> {
+        fs::create_dir_all(&self.cache_dir).await?;
+        let staging = tempfile::Builder::new()
+            .prefix("import-")
+            .tempdir_in(&self.cache_dir)?;
+        let root = staging.path().to_path_buf();
+        let archive = src.to_path_buf();
+        tokio::task::spawn_blocking(move || -> std::io::Result<()> {
+            tar::Archive::new(std::fs::File::open(&archive)?).unpack(&root)
+        })
+        .await
+        .map_err(|e| PolisError::Image(format!("Erro ao extrair {}: {}", src.display(), e)))?
+        .map_err(|e| PolisError::Image(format!("Erro ao extrair {}: {}", src.display(), e)))?;
+        Ok(staging)
+    }
+
     async fn load_image_metadata(&self, image_id: &ImageId) -> Result<ImageMetadata> {
         let image_dir = self.get_image_dir(image_id);
         let metadata_path = image_dir.join("metadata.json");
@@ -203,6 +719,7 @@ impl ImageManager {
             id: image.id.clone(),
             name: image.name.clone(),
             tag: image.tag.clone(),
+            digest: image.digest.clone(),
             size: image.size,
             created_at: image.created_at,
             architecture: image.architecture.clone(),
@@ -230,3 +747,42 @@ impl ImageManager {
         self.cache_dir.join("images").join(&image_id.0)
     }
 }
+
+/// Nome e tag como escritos na referência; uma referência só com digest
+/// fica sem tag (`<none>`)
+fn name_and_tag(reference: &str) -> (String, String) {
+    let (name, tag, digest) = split_reference(reference);
+    let tag = match (tag, digest) {
+        (Some(tag), _) => tag,
+        (None, Some(_)) => "<none>",
+        (None, None) => DEFAULT_TAG,
+    };
+    (name.to_string(), tag.to_string())
+}
+
+/// SHA-256 e tamanho de um arquivo, lido em blocos
+async fn file_digest(path: &Path) -> Result<(String, u64)> {
+    let mut file = fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((format!("sha256:{:x}", hasher.finalize()), size))
+}
+
+/// Lê um JSON do arquivo extraído; erros citam só o nome do arquivo
+async fn read_json(path: &Path) -> Result<serde_json::Value> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let content = fs::read(path)
+        .await
+        .map_err(|_| PolisError::Image(format!("{} ausente do arquivo", file_name)))?;
+    serde_json::from_slice(&content)
+        .map_err(|e| PolisError::Image(format!("Erro ao parsear {}: {}", file_name, e)))
+}
@@ -1,4 +1,12 @@
-use polis_core::Result;
+use polis_core::{PolisError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 
 #[derive(Default)]
 pub struct LayerManager {
@@ -15,3 +23,367 @@ impl LayerManager {
         Ok(())
     }
 }
+
+/// Camadas de imagem endereçadas pelo conteúdo: cada camada é gravada uma
+/// única vez em `blobs/sha256/<hex>`, não importa quantas imagens a usem.
+/// O config de cada imagem é guardado aqui também. As imagens que
+/// referenciam cada blob ficam em `refs.json`; cópias do store compartilham
+/// essas referências.
+#[derive(Debug, Clone)]
+pub struct LayerStore {
+    root: PathBuf,
+    references: Arc<Mutex<HashMap<String, BTreeSet<String>>>>,
+    /// Erro ao ler `refs.json`; enquanto houver, `gc` não remove nada
+    load_error: Option<String>,
+}
+
+impl LayerStore {
+    pub fn new(root: PathBuf) -> Self {
+        let (references, load_error) = match Self::load_references(&root.join("refs.json")) {
+            Ok(references) => (references, None),
+            Err(e) => {
+                println!(" Aviso: referências de camadas ignoradas: {}", e);
+                (HashMap::new(), Some(e.to_string()))
+            }
+        };
+        Self {
+            root,
+            references: Arc::new(Mutex::new(references)),
+            load_error,
+        }
+    }
+
+    fn load_references(path: &Path) -> Result<HashMap<String, BTreeSet<String>>> {
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            PolisError::Image(format!(
+                "Erro ao ler referências de camadas de {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Arquivo da camada `digest` (`sha256:<hex>`), exista ou não
+    pub fn layer_path(&self, digest: &str) -> Result<PathBuf> {
+        let hex = digest
+            .strip_prefix("sha256:")
+            .filter(|hex| is_sha256_hex(hex))
+            .ok_or_else(|| PolisError::Image(format!("Digest de camada inválido: {}", digest)))?;
+        Ok(self.blobs_dir().join(hex))
+    }
+
+    pub fn contains(&self, digest: &str) -> bool {
+        self.layer_path(digest).is_ok_and(|path| path.exists())
+    }
+
+    /// Grava a camada se ela ainda não existir. O conteúdo é conferido com
+    /// o digest antes de entrar no store.
+    pub async fn put<R>(&self, digest: &str, mut data: R) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let Some(mut writer) = self.writer(digest).await? else {
+            return Ok(());
+        };
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = data.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            writer.write(&buffer[..read]).await?;
+        }
+        writer.commit().await
+    }
+
+    /// Começa a gravar a camada aos poucos; `None` se ela já existir
+    pub async fn writer(&self, digest: &str) -> Result<Option<LayerWriter>> {
+        let path = self.layer_path(digest)?;
+        if path.exists() {
+            return Ok(None);
+        }
+        fs::create_dir_all(self.blobs_dir()).await?;
+
+        // Um temporário por gravação, para que pulls simultâneos da mesma
+        // camada não se misturem
+        let temp = path.with_extension(format!("{:016x}.tmp", rand::random::<u64>()));
+        let file = fs::File::create(&temp).await?;
+        Ok(Some(LayerWriter {
+            digest: digest.to_string(),
+            path,
+            temp,
+            file,
+            hasher: Sha256::new(),
+            written: 0,
+            committed: false,
+        }))
+    }
+
+    /// Abre a camada para leitura
+    pub async fn get(&self, digest: &str) -> Result<impl AsyncRead + Unpin + Send> {
+        let path = self.layer_path(digest)?;
+        fs::File::open(&path)
+            .await
+            .map_err(|e| PolisError::Image(format!("Camada {} não encontrada: {}", digest, e)))
+    }
+
+    /// Quantas imagens referenciam a camada
+    pub fn ref_count(&self, digest: &str) -> u32 {
+        self.lock()
+            .get(digest)
+            .map_or(0, |images| images.len() as u32)
+    }
+
+    /// Faz `image` referenciar exatamente `digests`, liberando as camadas
+    /// que ela usava antes
+    pub fn set_references(&self, image: &str, digests: &[String]) -> Result<()> {
+        let mut references = self.lock();
+        for images in references.values_mut() {
+            images.remove(image);
+        }
+        for digest in digests {
+            references
+                .entry(digest.clone())
+                .or_default()
+                .insert(image.to_string());
+        }
+        references.retain(|_, images| !images.is_empty());
+        self.save_references(&references)
+    }
+
+    /// Libera todas as camadas referenciadas por `image`
+    pub fn release(&self, image: &str) -> Result<()> {
+        self.set_references(image, &[])
+    }
+
+    /// Digests das camadas gravadas no store
+    pub async fn layers(&self) -> Result<Vec<String>> {
+        let mut digests = Vec::new();
+        let blobs_dir = self.blobs_dir();
+        if !blobs_dir.exists() {
+            return Ok(digests);
+        }
+        let mut entries = fs::read_dir(&blobs_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if is_sha256_hex(&name) {
+                digests.push(format!("sha256:{}", name));
+            }
+        }
+        digests.sort();
+        Ok(digests)
+    }
+
+    /// Remove as camadas sem nenhuma referência e retorna seus digests. Uma
+    /// camada gravada por um pull em andamento ainda não tem referências;
+    /// quem chama deve evitar coletar durante pulls.
+    pub async fn gc(&self) -> Result<Vec<String>> {
+        if let Some(error) = &self.load_error {
+            return Err(PolisError::Image(format!(
+                "Coleta de camadas suspensa: referências não puderam ser lidas: {}",
+                error
+            )));
+        }
+
+        let mut removed = Vec::new();
+        for digest in self.layers().await? {
+            if self.ref_count(&digest) == 0 {
+                fs::remove_file(self.layer_path(&digest)?).await?;
+                removed.push(digest);
+            }
+        }
+        Ok(removed)
+    }
+
+    fn blobs_dir(&self) -> PathBuf {
+        self.root.join("blobs").join("sha256")
+    }
+
+    fn save_references(&self, references: &HashMap<String, BTreeSet<String>>) -> Result<()> {
+        std::fs::create_dir_all(&self.root)?;
+        let path = self.root.join("refs.json");
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_string_pretty(references)?)?;
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, BTreeSet<String>>> {
+        self.references.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Gravação de uma camada em andamento. O conteúdo só entra no store em
+/// [`LayerWriter::commit`]; descartado antes disso, o temporário é removido.
+pub struct LayerWriter {
+    digest: String,
+    path: PathBuf,
+    temp: PathBuf,
+    file: fs::File,
+    hasher: Sha256,
+    written: u64,
+    committed: bool,
+}
+
+impl LayerWriter {
+    pub async fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        self.hasher.update(chunk);
+        self.file.write_all(chunk).await?;
+        self.written += chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Bytes gravados até agora
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Confere o conteúdo com o digest e move a camada para o store
+    pub async fn commit(mut self) -> Result<()> {
+        self.file.sync_all().await?;
+        let actual = format!("sha256:{:x}", self.hasher.clone().finalize());
+        if actual != self.digest {
+            return Err(PolisError::Image(format!(
+                "Conteúdo da camada não confere com o digest {} (obtido {})",
+                self.digest, actual
+            )));
+        }
+        fs::rename(&self.temp, &self.path).await?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for LayerWriter {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = std::fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// Diferença entre duas camadas, com os caminhos em ordem alfabética
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayerDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Caminhos presentes nas duas camadas com tipo, modo, destino do link
+    /// ou conteúdo diferentes
+    pub modified: Vec<String>,
+    /// Variação de tamanho (nova menos antiga) de cada caminho modificado
+    pub entry_size_delta: BTreeMap<String, i64>,
+}
+
+/// O que é comparado de cada entrada da camada
+#[derive(Debug, PartialEq, Eq)]
+struct LayerEntry {
+    kind: u8,
+    mode: u32,
+    size: u64,
+    link: Option<String>,
+    digest: Option<Vec<u8>>,
+}
+
+impl LayerDiff {
+    /// Compara dois tars de camada, comprimidos com gzip ou não
+    pub fn compute(old: &Path, new: &Path) -> Result<LayerDiff> {
+        let old_entries = read_layer_entries(old)?;
+        let new_entries = read_layer_entries(new)?;
+
+        let mut diff = LayerDiff::default();
+        for (path, entry) in &new_entries {
+            match old_entries.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(previous) if previous != entry => {
+                    diff.modified.push(path.clone());
+                    diff.entry_size_delta
+                        .insert(path.clone(), entry.size as i64 - previous.size as i64);
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = old_entries
+            .keys()
+            .filter(|path| !new_entries.contains_key(*path))
+            .cloned()
+            .collect();
+        Ok(diff)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+fn read_layer_entries(path: &Path) -> Result<BTreeMap<String, LayerEntry>> {
+    let error = |e: std::io::Error| {
+        PolisError::Image(format!("Erro ao ler a camada {}: {}", path.display(), e))
+    };
+
+    let mut reader = BufReader::new(std::fs::File::open(path).map_err(error)?);
+    let gzip = reader.fill_buf().map_err(error)?.starts_with(&[0x1f, 0x8b]);
+    let reader: Box<dyn Read> = if gzip {
+        Box::new(flate2::read::GzDecoder::new(reader))
+    } else {
+        Box::new(reader)
+    };
+
+    let mut entries = BTreeMap::new();
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(error)? {
+        let mut entry = entry.map_err(error)?;
+        let name = entry.path().map_err(error)?.to_string_lossy().to_string();
+        let name = name
+            .trim_start_matches("./")
+            .trim_start_matches('/')
+            .trim_end_matches('/')
+            .to_string();
+        if name.is_empty() || name == "." {
+            continue;
+        }
+
+        let header = entry.header();
+        let kind = header.entry_type().as_byte();
+        let mode = header.mode().map_err(error)?;
+        let size = header.size().map_err(error)?;
+        let link = entry
+            .link_name()
+            .map_err(error)?
+            .map(|link| link.to_string_lossy().to_string());
+        let digest = if header.entry_type().is_file() {
+            let mut hasher = Sha256::new();
+            std::io::copy(&mut entry, &mut hasher).map_err(error)?;
+            Some(hasher.finalize().to_vec())
+        } else {
+            None
+        };
+
+        // Entradas repetidas no tar: a última prevalece, como na extração
+        entries.insert(
+            name,
+            LayerEntry {
+                kind,
+                mode,
+                size,
+                link,
+                digest,
+            },
+        );
+    }
+    Ok(entries)
+}
+
+fn is_sha256_hex(value: &str) -> bool {
+    value.len() == 64
+        && value
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
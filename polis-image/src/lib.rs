@@ -1,12 +1,22 @@
+pub mod docker_archive;
 pub mod image;
 pub mod layer;
+pub mod oci_layout;
+pub mod platform;
+pub mod pull_scheduler;
+pub mod reference;
 pub mod registry;
 pub mod registry_config;
 pub mod search;
 pub mod cleanup;
 
+pub use docker_archive::*;
 pub use image::*;
 pub use layer::*;
+pub use oci_layout::*;
+pub use platform::*;
+pub use pull_scheduler::*;
+pub use reference::*;
 pub use registry::*;
 pub use registry_config::*;
 pub use search::*;
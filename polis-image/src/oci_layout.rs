@@ -0,0 +1,136 @@
+use crate::{OciDescriptor, OciManifest};
+use polis_core::{PolisError, Result};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+
+/// Versão do OCI Image Layout gravada em `oci-layout`
+pub const OCI_LAYOUT_VERSION: &str = "1.0.0";
+pub const OCI_INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+pub const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+/// Anotação do `index.json` com o nome da imagem
+pub const REF_NAME_ANNOTATION: &str = "org.opencontainers.image.ref.name";
+
+/// Digest `sha256:<hex>` do conteúdo
+pub fn sha256_digest(data: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(data))
+}
+
+/// Manifest com os nomes de campo da especificação OCI (camelCase)
+pub fn manifest_document(manifest: &OciManifest) -> serde_json::Value {
+    let media_type = if manifest.media_type.is_empty() {
+        OCI_MANIFEST_MEDIA_TYPE
+    } else {
+        manifest.media_type.as_str()
+    };
+    let mut document = serde_json::json!({
+        "schemaVersion": manifest.schema_version,
+        "mediaType": media_type,
+        "config": descriptor_document(&manifest.config),
+        "layers": manifest.layers.iter().map(descriptor_document).collect::<Vec<_>>(),
+    });
+    if let Some(annotations) = &manifest.annotations {
+        document["annotations"] = serde_json::json!(annotations);
+    }
+    document
+}
+
+fn descriptor_document(descriptor: &OciDescriptor) -> serde_json::Value {
+    let mut document = serde_json::json!({
+        "mediaType": descriptor.media_type,
+        "size": descriptor.size,
+        "digest": descriptor.digest,
+    });
+    if let Some(urls) = &descriptor.urls {
+        document["urls"] = serde_json::json!(urls);
+    }
+    if let Some(annotations) = &descriptor.annotations {
+        document["annotations"] = serde_json::json!(annotations);
+    }
+    document
+}
+
+/// Caminho do blob `digest` dentro de um layout OCI
+pub fn blob_path(digest: &str) -> Result<String> {
+    match digest.split_once(':') {
+        Some((algorithm, hex))
+            if !algorithm.is_empty()
+                && !hex.is_empty()
+                && [algorithm, hex]
+                    .iter()
+                    .all(|part| part.chars().all(|c| c.is_ascii_alphanumeric())) =>
+        {
+            Ok(format!("blobs/{}/{}", algorithm, hex))
+        }
+        _ => Err(PolisError::Image(format!("Digest inválido: {}", digest))),
+    }
+}
+
+/// Grava um arquivo tar entrada por entrada, sem montá-lo em memória
+pub struct TarWriter<W = fs::File> {
+    file: W,
+}
+
+impl TarWriter<fs::File> {
+    pub async fn create(path: &Path) -> Result<Self> {
+        Ok(Self::new(fs::File::create(path).await?))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> TarWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { file: writer }
+    }
+
+    pub async fn append_dir(&mut self, path: &str) -> Result<()> {
+        let header = Self::header(path, tar::EntryType::Directory, 0o755, 0)?;
+        self.file.write_all(header.as_bytes()).await?;
+        Ok(())
+    }
+
+    pub async fn append_bytes(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        self.append_reader(path, data.len() as u64, data).await
+    }
+
+    /// Acrescenta um arquivo de `size` bytes lido de `data`
+    pub async fn append_reader<R>(&mut self, path: &str, size: u64, mut data: R) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let header = Self::header(path, tar::EntryType::Regular, 0o644, size)?;
+        self.file.write_all(header.as_bytes()).await?;
+        let copied = tokio::io::copy(&mut data, &mut self.file).await?;
+        if copied != size {
+            return Err(PolisError::Image(format!(
+                "Tamanho de {} mudou durante a gravação: esperado {}, lido {}",
+                path, size, copied
+            )));
+        }
+        let padding = (512 - size % 512) % 512;
+        self.file.write_all(&vec![0u8; padding as usize]).await?;
+        Ok(())
+    }
+
+    /// Grava os dois blocos vazios que encerram o tar e devolve o destino
+    pub async fn finish(mut self) -> Result<W> {
+        self.file.write_all(&[0u8; 1024]).await?;
+        self.file.flush().await?;
+        Ok(self.file)
+    }
+
+    fn header(path: &str, entry_type: tar::EntryType, mode: u32, size: u64) -> Result<tar::Header> {
+        let mut header = tar::Header::new_ustar();
+        header
+            .set_path(path)
+            .map_err(|e| PolisError::Image(format!("Caminho inválido no tar '{}': {}", path, e)))?;
+        header.set_entry_type(entry_type);
+        header.set_mode(mode);
+        header.set_size(size);
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_cksum();
+        Ok(header)
+    }
+}
@@ -0,0 +1,229 @@
+use crate::RegistryConfig;
+use polis_core::{PolisError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Plataforma de uma imagem, ex.: `linux/arm/v7`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Platform {
+    pub os: String,
+    pub architecture: String,
+    pub variant: Option<String>,
+}
+
+impl Platform {
+    /// Interpreta `os/arch[/variant]`
+    pub fn parse(value: &str) -> Result<Self> {
+        let parts: Vec<&str> = value.trim().split('/').collect();
+        if parts.len() < 2 || parts.len() > 3 || parts.iter().any(|p| p.is_empty()) {
+            return Err(PolisError::Image(format!(
+                "Plataforma inválida '{}': use os/arch[/variant]",
+                value
+            )));
+        }
+        Ok(Self {
+            os: parts[0].to_string(),
+            architecture: parts[1].to_string(),
+            variant: parts.get(2).map(|v| v.to_string()),
+        })
+    }
+
+    /// Plataforma do host, com a arquitetura no vocabulário OCI
+    pub fn host() -> Self {
+        let (architecture, variant) = match std::env::consts::ARCH {
+            "x86_64" => ("amd64", None),
+            "x86" => ("386", None),
+            "aarch64" => ("arm64", None),
+            "arm" => ("arm", Some("v7".to_string())),
+            "powerpc64" => ("ppc64le", None),
+            "s390x" => ("s390x", None),
+            "riscv64" => ("riscv64", None),
+            other => (other, None),
+        };
+        Self {
+            os: std::env::consts::OS.to_string(),
+            architecture: architecture.to_string(),
+            variant,
+        }
+    }
+
+    /// Se a entrada de um manifest list é desta plataforma. Sem variante
+    /// pedida, qualquer variante da mesma arquitetura é aceita.
+    pub fn matches(&self, platform: &OciPlatform) -> bool {
+        self.os == platform.os
+            && self.architecture == platform.architecture
+            && match &self.variant {
+                Some(variant) => platform.variant.as_deref() == Some(variant.as_str()),
+                None => true,
+            }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)?;
+        if let Some(variant) = &self.variant {
+            write!(f, "/{}", variant)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plataforma forçada para os repositórios que casam com um glob
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlatformOverride {
+    /// Glob sobre o repositório (`*` e `?`), ex.: `myorg/*` ou `docker.io/library/node`
+    pub pattern: String,
+    /// Plataforma no formato `os/arch[/variant]`
+    pub platform: String,
+}
+
+impl PlatformOverride {
+    /// Se o padrão casa com o repositório, com ou sem o registry na frente
+    pub fn matches(&self, registry: &str, repository: &str) -> bool {
+        glob_match(&self.pattern, repository)
+            || glob_match(&self.pattern, &format!("{}/{}", registry, repository))
+    }
+
+    /// Quantidade de caracteres literais do padrão; padrões mais específicos
+    /// vencem quando vários casam com o mesmo repositório
+    pub fn specificity(&self) -> usize {
+        self.pattern
+            .chars()
+            .filter(|c| *c != '*' && *c != '?')
+            .count()
+    }
+}
+
+/// Origem da plataforma escolhida para um pull
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlatformSource {
+    /// `--platform` na linha de comando
+    Flag,
+    /// `platform_overrides` do registries.conf
+    Override { pattern: String },
+    /// Plataforma do host
+    HostDefault,
+}
+
+/// Plataforma usada na seleção do manifest list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformChoice {
+    pub platform: Platform,
+    pub source: PlatformSource,
+}
+
+/// Escolhe a plataforma de um pull. Precedência, da maior para a menor:
+///
+/// 1. `--platform` explícito (`flag`);
+/// 2. override por imagem em `platform_overrides`: entre os padrões que
+///    casam, vence o mais específico e, no empate, o primeiro do arquivo;
+/// 3. a plataforma do host.
+pub fn resolve_platform(
+    flag: Option<&Platform>,
+    config: &RegistryConfig,
+    registry: &str,
+    repository: &str,
+) -> Result<PlatformChoice> {
+    if let Some(platform) = flag {
+        return Ok(PlatformChoice {
+            platform: platform.clone(),
+            source: PlatformSource::Flag,
+        });
+    }
+
+    if let Some(matched) = config.platform_override(registry, repository) {
+        return Ok(PlatformChoice {
+            platform: Platform::parse(&matched.platform)?,
+            source: PlatformSource::Override {
+                pattern: matched.pattern.clone(),
+            },
+        });
+    }
+
+    Ok(PlatformChoice {
+        platform: Platform::host(),
+        source: PlatformSource::HostDefault,
+    })
+}
+
+/// Glob simples: `*` casa qualquer sequência (inclusive `/`), `?` um caractere
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, t));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Plataforma de uma entrada de manifest list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OciPlatform {
+    pub architecture: String,
+    pub os: String,
+    pub variant: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OciIndexEntry {
+    pub media_type: String,
+    pub size: u64,
+    pub digest: String,
+    pub platform: Option<OciPlatform>,
+}
+
+/// Manifest list (índice multi-plataforma)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OciIndex {
+    pub schema_version: u32,
+    pub media_type: String,
+    pub manifests: Vec<OciIndexEntry>,
+}
+
+impl OciIndex {
+    /// Entrada para a plataforma; sem variante pedida, prefere a entrada
+    /// sem variante
+    pub fn select(&self, platform: &Platform) -> Option<&OciIndexEntry> {
+        let mut candidates = self
+            .manifests
+            .iter()
+            .filter(|entry| entry.platform.as_ref().is_some_and(|p| platform.matches(p)));
+        let first = candidates.next()?;
+        if platform.variant.is_some() {
+            return Some(first);
+        }
+        std::iter::once(first)
+            .chain(candidates)
+            .find(|entry| entry.platform.as_ref().is_some_and(|p| p.variant.is_none()))
+            .or(Some(first))
+    }
+
+    /// Plataformas disponíveis no índice
+    pub fn platforms(&self) -> Vec<String> {
+        self.manifests
+            .iter()
+            .filter_map(|entry| entry.platform.as_ref())
+            .map(|p| match &p.variant {
+                Some(variant) => format!("{}/{}/{}", p.os, p.architecture, variant),
+                None => format!("{}/{}", p.os, p.architecture),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,531 @@
+use crate::RegistryConfig;
+use chrono::{DateTime, Utc};
+use polis_core::Result;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, oneshot};
+
+/// Limite padrão de pulls simultâneos
+pub const DEFAULT_MAX_CONCURRENT_PULLS: usize = 4;
+
+/// Abaixo deste número de pulls restantes um aviso é emitido
+pub const DEFAULT_QUOTA_WARNING_THRESHOLD: u64 = 10;
+
+/// Prioridade de um pull: pulls interativos (CLI) passam na frente de pré-pulls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PullPriority {
+    Background,
+    Interactive,
+}
+
+/// Cota de pulls informada pelo registry (cabeçalhos `ratelimit-*`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitInfo {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub window_secs: Option<u64>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl RateLimitInfo {
+    /// Extrai a cota de `ratelimit-limit: 100;w=21600` e `ratelimit-remaining: 76;w=21600`
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_ratelimit_header)
+        };
+
+        let limit = header("ratelimit-limit");
+        let remaining = header("ratelimit-remaining");
+        if limit.is_none() && remaining.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            limit: limit.map(|(value, _)| value),
+            remaining: remaining.map(|(value, _)| value),
+            window_secs: limit.or(remaining).and_then(|(_, window)| window),
+            updated_at: Utc::now(),
+        })
+    }
+}
+
+/// Interpreta um valor `100;w=21600` como (quantidade, janela em segundos)
+pub fn parse_ratelimit_header(value: &str) -> Option<(u64, Option<u64>)> {
+    let mut parts = value.split(';');
+    let amount = parts.next()?.trim().parse().ok()?;
+    let window = parts
+        .filter_map(|part| part.trim().strip_prefix("w="))
+        .find_map(|w| w.parse().ok());
+    Some((amount, window))
+}
+
+/// Tempo de espera indicado pelo cabeçalho `Retry-After` (em segundos)
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get("retry-after")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs)
+}
+
+/// Política de espera exponencial aplicada a respostas 429
+#[derive(Debug, Clone)]
+pub struct BackoffPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub max_retries: u32,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_retries: 5,
+        }
+    }
+}
+
+impl BackoffPolicy {
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+/// Limites de um registry específico
+#[derive(Debug, Clone, Default)]
+pub struct RegistryLimits {
+    pub max_concurrent_pulls: Option<usize>,
+    pub pulls_per_minute: Option<f64>,
+    pub burst: Option<u32>,
+}
+
+/// Configuração do agendador de pulls
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    pub max_concurrent_pulls: usize,
+    pub quota_warning_threshold: u64,
+    pub registries: HashMap<String, RegistryLimits>,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_pulls: DEFAULT_MAX_CONCURRENT_PULLS,
+            quota_warning_threshold: DEFAULT_QUOTA_WARNING_THRESHOLD,
+            registries: HashMap::new(),
+        }
+    }
+}
+
+impl From<&RegistryConfig> for SchedulerConfig {
+    fn from(config: &RegistryConfig) -> Self {
+        let registries = config
+            .registries
+            .iter()
+            .map(|(name, entry)| {
+                (
+                    name.clone(),
+                    RegistryLimits {
+                        max_concurrent_pulls: entry.max_concurrent_pulls,
+                        pulls_per_minute: entry.pulls_per_minute,
+                        burst: entry.burst,
+                    },
+                )
+            })
+            .collect();
+
+        Self {
+            max_concurrent_pulls: config
+                .max_concurrent_pulls
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_PULLS),
+            quota_warning_threshold: config
+                .quota_warning_threshold
+                .unwrap_or(DEFAULT_QUOTA_WARNING_THRESHOLD),
+            registries,
+        }
+    }
+}
+
+/// Eventos emitidos pelo agendador
+#[derive(Debug, Clone)]
+pub enum PullEvent {
+    Queued {
+        image: String,
+        registry: String,
+        priority: PullPriority,
+    },
+    Started {
+        image: String,
+        registry: String,
+    },
+    QuotaLow {
+        registry: String,
+        remaining: u64,
+        limit: Option<u64>,
+    },
+    Throttled {
+        registry: String,
+        retry_after: Duration,
+    },
+}
+
+/// Pull aguardando na fila
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedPull {
+    pub image: String,
+    pub registry: String,
+    pub priority: PullPriority,
+    pub queued_at: DateTime<Utc>,
+}
+
+/// Situação atual do agendador
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerStatus {
+    pub active: usize,
+    pub active_per_registry: HashMap<String, usize>,
+    pub queued: Vec<QueuedPull>,
+    pub quotas: HashMap<String, RateLimitInfo>,
+}
+
+struct Waiter {
+    id: u64,
+    pull: QueuedPull,
+    notify: oneshot::Sender<()>,
+}
+
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(pulls_per_minute: f64, burst: u32) -> Self {
+        let capacity = burst.max(1) as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: pulls_per_minute / 60.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Consome um token ou retorna quanto tempo falta para o próximo
+    fn try_take(&mut self) -> std::result::Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else if self.refill_per_sec <= 0.0 {
+            Err(Duration::from_secs(60))
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    next_id: u64,
+    active: usize,
+    active_per_registry: HashMap<String, usize>,
+    waiting: Vec<Waiter>,
+    buckets: HashMap<String, TokenBucket>,
+    quotas: HashMap<String, RateLimitInfo>,
+}
+
+/// Agendador de pulls com limites de concorrência, ritmo por registry e
+/// acompanhamento da cota informada pelos registries
+pub struct PullScheduler {
+    config: SchedulerConfig,
+    state: Mutex<SchedulerState>,
+    events: broadcast::Sender<PullEvent>,
+    quota_path: Option<PathBuf>,
+}
+
+impl PullScheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        let (events, _) = broadcast::channel(100);
+        Self {
+            config,
+            state: Mutex::new(SchedulerState::default()),
+            events,
+            quota_path: None,
+        }
+    }
+
+    /// Persiste a última cota conhecida em `path` (lida por `polis registry status`)
+    pub fn with_quota_file(mut self, path: PathBuf) -> Self {
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            if let Ok(quotas) = serde_json::from_str(&content) {
+                self.state.get_mut().unwrap().quotas = quotas;
+            }
+        }
+        self.quota_path = Some(path);
+        self
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PullEvent> {
+        self.events.subscribe()
+    }
+
+    /// Aguarda uma vaga para o pull; pulls além do limite ficam na fila
+    pub async fn acquire(
+        self: &Arc<Self>,
+        image: &str,
+        registry: &str,
+        priority: PullPriority,
+    ) -> PullPermit {
+        let (notify, granted) = oneshot::channel();
+        {
+            let mut state = self.state.lock().unwrap();
+            state.next_id += 1;
+            let id = state.next_id;
+            state.waiting.push(Waiter {
+                id,
+                pull: QueuedPull {
+                    image: image.to_string(),
+                    registry: registry.to_string(),
+                    priority,
+                    queued_at: Utc::now(),
+                },
+                notify,
+            });
+            self.dispatch(&mut state);
+        }
+
+        let _ = self.events.send(PullEvent::Queued {
+            image: image.to_string(),
+            registry: registry.to_string(),
+            priority,
+        });
+
+        let mut permit = PullPermit {
+            scheduler: self.clone(),
+            registry: registry.to_string(),
+            pending: Some(granted),
+        };
+        if let Some(granted) = permit.pending.as_mut() {
+            let _ = granted.await;
+        }
+        permit.pending = None;
+
+        self.pace(registry).await;
+        let _ = self.events.send(PullEvent::Started {
+            image: image.to_string(),
+            registry: registry.to_string(),
+        });
+        permit
+    }
+
+    /// Registra a cota informada por uma resposta do registry
+    pub fn record_rate_limit(&self, registry: &str, info: RateLimitInfo) {
+        let low = info
+            .remaining
+            .filter(|remaining| *remaining <= self.config.quota_warning_threshold);
+        if let Some(remaining) = low {
+            tracing::warn!(
+                "Cota de pulls do registry {} baixa: {} restantes",
+                registry,
+                remaining
+            );
+            let _ = self.events.send(PullEvent::QuotaLow {
+                registry: registry.to_string(),
+                remaining,
+                limit: info.limit,
+            });
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.quotas.insert(registry.to_string(), info);
+        self.save_quotas(&state.quotas);
+    }
+
+    /// Registra uma resposta 429 do registry
+    pub fn record_throttled(&self, registry: &str, retry_after: Duration) {
+        tracing::warn!(
+            "Registry {} limitou as requisições; nova tentativa em {:?}",
+            registry,
+            retry_after
+        );
+        let _ = self.events.send(PullEvent::Throttled {
+            registry: registry.to_string(),
+            retry_after,
+        });
+
+        let mut state = self.state.lock().unwrap();
+        let quota = state
+            .quotas
+            .entry(registry.to_string())
+            .or_insert_with(|| RateLimitInfo {
+                limit: None,
+                remaining: None,
+                window_secs: None,
+                updated_at: Utc::now(),
+            });
+        quota.remaining = Some(0);
+        quota.updated_at = Utc::now();
+        self.save_quotas(&state.quotas);
+    }
+
+    pub fn quota(&self, registry: &str) -> Option<RateLimitInfo> {
+        self.state.lock().unwrap().quotas.get(registry).cloned()
+    }
+
+    pub fn status(&self) -> SchedulerStatus {
+        let state = self.state.lock().unwrap();
+        let mut queued: Vec<&Waiter> = state.waiting.iter().collect();
+        queued.sort_by(|a, b| b.pull.priority.cmp(&a.pull.priority).then(a.id.cmp(&b.id)));
+
+        SchedulerStatus {
+            active: state.active,
+            active_per_registry: state.active_per_registry.clone(),
+            queued: queued.into_iter().map(|w| w.pull.clone()).collect(),
+            quotas: state.quotas.clone(),
+        }
+    }
+
+    fn registry_limit(&self, registry: &str) -> usize {
+        self.config
+            .registries
+            .get(registry)
+            .and_then(|limits| limits.max_concurrent_pulls)
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Libera pulls da fila: interativos primeiro, depois por ordem de chegada
+    fn dispatch(&self, state: &mut SchedulerState) {
+        state
+            .waiting
+            .sort_by(|a, b| b.pull.priority.cmp(&a.pull.priority).then(a.id.cmp(&b.id)));
+
+        let mut index = 0;
+        while index < state.waiting.len() && state.active < self.config.max_concurrent_pulls {
+            let registry = &state.waiting[index].pull.registry;
+            let active = state
+                .active_per_registry
+                .get(registry)
+                .copied()
+                .unwrap_or(0);
+            if active >= self.registry_limit(registry) {
+                index += 1;
+                continue;
+            }
+
+            let waiter = state.waiting.remove(index);
+            if waiter.notify.send(()).is_ok() {
+                state.active += 1;
+                *state
+                    .active_per_registry
+                    .entry(waiter.pull.registry)
+                    .or_insert(0) += 1;
+            }
+        }
+    }
+
+    fn release(&self, state: &mut SchedulerState, registry: &str) {
+        state.active = state.active.saturating_sub(1);
+        if let Some(active) = state.active_per_registry.get_mut(registry) {
+            *active = active.saturating_sub(1);
+        }
+        self.dispatch(state);
+    }
+
+    /// Aplica o ritmo (token bucket) configurado para o registry
+    async fn pace(&self, registry: &str) {
+        let Some(limits) = self.config.registries.get(registry) else {
+            return;
+        };
+        let Some(pulls_per_minute) = limits.pulls_per_minute else {
+            return;
+        };
+
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                state
+                    .buckets
+                    .entry(registry.to_string())
+                    .or_insert_with(|| {
+                        TokenBucket::new(pulls_per_minute, limits.burst.unwrap_or(1))
+                    })
+                    .try_take()
+            };
+            match wait {
+                Ok(()) => return,
+                Err(delay) => tokio::time::sleep(delay).await,
+            }
+        }
+    }
+
+    fn save_quotas(&self, quotas: &HashMap<String, RateLimitInfo>) {
+        if let Some(path) = &self.quota_path {
+            if let Ok(content) = serde_json::to_string_pretty(quotas) {
+                let _ = std::fs::write(path, content);
+            }
+        }
+    }
+}
+
+impl Default for PullScheduler {
+    fn default() -> Self {
+        Self::new(SchedulerConfig::default())
+    }
+}
+
+/// Vaga de pull; liberada ao ser descartada
+pub struct PullPermit {
+    scheduler: Arc<PullScheduler>,
+    registry: String,
+    pending: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for PullPermit {
+    fn drop(&mut self) {
+        let mut state = self.scheduler.state.lock().unwrap();
+        // Um pull cancelado ainda na fila não ocupa vaga
+        let granted = match self.pending.take() {
+            Some(mut pending) => pending.try_recv().is_ok(),
+            None => true,
+        };
+        if granted {
+            self.scheduler.release(&mut state, &self.registry);
+        }
+    }
+}
+
+/// Chave de cota para um host de registry (Docker Hub usa vários hosts)
+pub fn quota_key(host: &str) -> String {
+    if host.ends_with("docker.io") {
+        "docker.io".to_string()
+    } else {
+        host.to_string()
+    }
+}
+
+/// Carrega a última cota conhecida de cada registry
+pub fn load_quotas(path: &std::path::Path) -> Result<HashMap<String, RateLimitInfo>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
@@ -0,0 +1,161 @@
+use polis_core::{PolisError, Result};
+use std::fmt;
+
+/// Registry assumido quando a referência não indica um
+pub const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// Tag assumida quando a referência não tem tag nem digest
+pub const DEFAULT_TAG: &str = "latest";
+
+/// Referência de imagem: `[registry[:porta]/]repositório[:tag][@digest]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// Registry da imagem; `docker.io` quando omitido
+    pub registry: String,
+    /// Repositório no registry; imagens oficiais do Docker Hub ganham o
+    /// prefixo `library/`
+    pub repository: String,
+    /// `latest` quando a referência não tem tag nem digest
+    pub tag: Option<String>,
+    /// Digest do manifest (`sha256:<hex>`), que fixa o conteúdo da imagem
+    pub digest: Option<String>,
+}
+
+impl Reference {
+    /// Interpreta a referência, recusando nomes que o registry não aceitaria
+    pub fn parse(reference: &str) -> Result<Self> {
+        let (name, tag, digest) = split_reference(reference);
+        let invalid = |reason: &str| {
+            PolisError::Image(format!(
+                "Referência de imagem inválida '{}': {}",
+                reference, reason
+            ))
+        };
+
+        if let Some(digest) = digest {
+            validate_digest(digest).map_err(|reason| invalid(&reason))?;
+        }
+        if let Some(tag) = tag {
+            if !is_valid_tag(tag) {
+                return Err(invalid("tag inválida"));
+            }
+        }
+
+        let (registry, repository) = match name.split_once('/') {
+            Some((first, rest)) if is_registry_host(first) => (first.to_string(), rest.to_string()),
+            _ => (DEFAULT_REGISTRY.to_string(), name.to_string()),
+        };
+        if registry.ends_with(':') || registry.starts_with(':') {
+            return Err(invalid("porta do registry vazia"));
+        }
+        if !repository.split('/').all(is_valid_path_component) {
+            return Err(invalid(
+                "repositório deve ter componentes em minúsculas separados por '/'",
+            ));
+        }
+        let repository = if registry == DEFAULT_REGISTRY && !repository.contains('/') {
+            format!("library/{}", repository)
+        } else {
+            repository
+        };
+
+        let tag = match (tag, digest) {
+            (None, None) => Some(DEFAULT_TAG.to_string()),
+            (tag, _) => tag.map(str::to_string),
+        };
+        Ok(Self {
+            registry,
+            repository,
+            tag,
+            digest: digest.map(str::to_string),
+        })
+    }
+
+    /// Tag, ou `latest` numa referência só com digest
+    pub fn tag_or_default(&self) -> &str {
+        self.tag.as_deref().unwrap_or(DEFAULT_TAG)
+    }
+
+    /// Como o manifest é pedido ao registry: pelo digest, se houver, senão pela tag
+    pub fn manifest_reference(&self) -> &str {
+        self.digest
+            .as_deref()
+            .unwrap_or_else(|| self.tag_or_default())
+    }
+
+    /// `registry/repositório`, sem tag nem digest
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.registry, self.repository)
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{}", tag)?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{}", digest)?;
+        }
+        Ok(())
+    }
+}
+
+/// Separa nome, tag e digest como escritos, sem normalizar; `:` só marca a
+/// tag depois da última `/`, já que também separa a porta do registry
+pub fn split_reference(reference: &str) -> (&str, Option<&str>, Option<&str>) {
+    let (name, digest) = match reference.split_once('@') {
+        Some((name, digest)) => (name, Some(digest)),
+        None => (reference, None),
+    };
+    match name.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => (repository, Some(tag), digest),
+        _ => (name, None, digest),
+    }
+}
+
+/// O primeiro componente é um registry quando parece um host: tem `.` ou
+/// porta, ou é `localhost`
+fn is_registry_host(component: &str) -> bool {
+    component.contains('.') || component.contains(':') || component == "localhost"
+}
+
+fn is_valid_path_component(component: &str) -> bool {
+    let alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    component.starts_with(alphanumeric)
+        && component.ends_with(alphanumeric)
+        && component
+            .chars()
+            .all(|c| alphanumeric(c) || matches!(c, '.' | '_' | '-'))
+}
+
+fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.len() <= 128
+        && !tag.starts_with(['.', '-'])
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+fn validate_digest(digest: &str) -> std::result::Result<(), String> {
+    let (algorithm, hex) = digest
+        .split_once(':')
+        .ok_or_else(|| "digest deve ter a forma <algoritmo>:<hex>".to_string())?;
+    let expected_len = match algorithm {
+        "sha256" => 64,
+        "sha512" => 128,
+        other => return Err(format!("algoritmo de digest não suportado: {}", other)),
+    };
+    let is_hex = hex
+        .chars()
+        .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+    if hex.len() != expected_len || !is_hex {
+        return Err(format!(
+            "digest {} deve ter {} dígitos hexadecimais em minúsculas",
+            algorithm, expected_len
+        ));
+    }
+    Ok(())
+}
@@ -1,17 +1,44 @@
-use polis_core::{ImageId, PolisError, Result};
+use polis_core::{ImageId, PolisError, RegistryErrorCode, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
 use base64;
 use url::Url;
-use crate::RegistryConfig;
+use crate::{parse_retry_after, quota_key, resolve_platform, BackoffPolicy, LayerStore, OciIndex, Platform, PlatformChoice, PullScheduler, RateLimitInfo, Reference, RegistryConfig, ResolvedReference};
+use futures::StreamExt;
+use tokio::io::AsyncReadExt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Recebe `(bytes baixados, tamanho da camada)` a cada bloco recebido; o
+/// tamanho é `None` quando o registry não informa `Content-Length`
+pub type ProgressCallback = Box<dyn Fn(u64, Option<u64>) + Send>;
+
+/// Andamento do envio de um blob (camada ou config) durante o push
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushProgress {
+    /// O registry já tem o blob; nada é enviado
+    Exists { digest: String },
+    /// `sent` de `size` bytes enviados
+    Uploading { digest: String, sent: u64, size: u64 },
+}
+
+/// Recebe o andamento de cada blob durante o push
+pub type PushProgressCallback = Box<dyn Fn(PushProgress) + Send>;
+
+/// Tamanho de cada `PATCH` no envio de uma camada
+pub const DEFAULT_PUSH_CHUNK_SIZE: usize = 5 * 1024 * 1024;
 
+/// Manifest de imagem; aceita também os nomes de campo da especificação
+/// (`schemaVersion`, `mediaType`)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OciManifest {
+    #[serde(alias = "schemaVersion")]
     pub schema_version: u32,
+    #[serde(default, alias = "mediaType")]
     pub media_type: String,
     pub config: OciDescriptor,
     pub layers: Vec<OciDescriptor>,
@@ -20,6 +47,7 @@ pub struct OciManifest {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OciDescriptor {
+    #[serde(alias = "mediaType")]
     pub media_type: String,
     pub size: u64,
     pub digest: String,
@@ -53,6 +81,72 @@ pub struct OciRootFs {
     pub diff_ids: Vec<String>,
 }
 
+/// Resposta de `tags/list`; `tags` vem `null` em repositórios sem tags
+#[derive(Debug, Deserialize)]
+struct TagList {
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+}
+
+/// Corpo de erro da Distribution Spec: `{"errors": [{"code", "message"}]}`
+#[derive(Debug, Deserialize)]
+struct RegistryErrors {
+    errors: Vec<RegistryErrorBody>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistryErrorBody {
+    code: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// Resposta de um serviço de tokens; alguns devolvem só `access_token`
+#[derive(Debug, Deserialize)]
+struct BearerToken {
+    token: Option<String>,
+    access_token: Option<String>,
+    expires_in: Option<u64>,
+}
+
+/// Validade assumida quando o serviço de tokens não informa `expires_in`
+const DEFAULT_TOKEN_TTL: Duration = Duration::from_secs(60);
+
+/// Tokens obtidos dos serviços de autenticação, por registry e escopo. As
+/// cópias do cliente compartilham o cache, então o token obtido em um pull
+/// vale para os seguintes até expirar.
+#[derive(Debug, Default)]
+struct TokenCache {
+    tokens: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl TokenCache {
+    fn key(registry: &str, repo: &str, actions: &str) -> String {
+        format!("{}|repository:{}:{}", registry, repo, actions)
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        let mut tokens = self.tokens.lock().unwrap();
+        match tokens.get(key) {
+            Some((token, expires_at)) if *expires_at > Instant::now() => Some(token.clone()),
+            Some(_) => {
+                tokens.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: String, token: String, expires_in: Option<u64>) {
+        let ttl = expires_in.map(Duration::from_secs).unwrap_or(DEFAULT_TOKEN_TTL);
+        self.tokens.lock().unwrap().insert(key, (token, Instant::now() + ttl));
+    }
+
+    fn remove(&self, key: &str) {
+        self.tokens.lock().unwrap().remove(key);
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DockerHubToken {
     pub token: String,
@@ -67,6 +161,7 @@ pub struct DockerHubAuth {
     pub password: String,
 }
 
+    #[derive(Clone)]
     pub struct RegistryClient {
         client: Client,
         base_url: String,
@@ -74,7 +169,19 @@ pub struct DockerHubAuth {
         username: Option<String>,
         password: Option<String>,
         docker_hub_token: Option<String>,
+        /// Tokens obtidos pelo cliente, compartilhados com suas cópias
+        tokens: Arc<TokenCache>,
         config: RegistryConfig,
+        scheduler: Option<Arc<PullScheduler>>,
+        backoff: BackoffPolicy,
+        /// Plataforma pedida explicitamente (`--platform`)
+        platform: Option<Platform>,
+        /// Se a configuração veio do registries.conf e deve ser relida a cada pull
+        config_from_disk: bool,
+        /// Camadas baixadas, compartilhadas entre imagens
+        layers: LayerStore,
+        /// Bytes enviados por `PATCH` no push de uma camada
+        push_chunk_size: usize,
     }
 
 impl RegistryClient {
@@ -84,11 +191,118 @@ impl RegistryClient {
         Self {
             client: Client::new(),
             base_url: "https://registry-1.docker.io/v2".to_string(),
-            cache_dir,
             username: None,
             password: None,
             docker_hub_token: None,
+            tokens: Arc::default(),
             config,
+            scheduler: None,
+            backoff: BackoffPolicy::default(),
+            platform: None,
+            config_from_disk: true,
+            layers: LayerStore::new(cache_dir.join("layers")),
+            push_chunk_size: DEFAULT_PUSH_CHUNK_SIZE,
+            cache_dir,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    /// Reporta cotas e respostas 429 ao agendador de pulls
+    pub fn with_scheduler(mut self, scheduler: Arc<PullScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: BackoffPolicy) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Plataforma explícita; tem precedência sobre os overrides do registries.conf
+    pub fn with_platform(mut self, platform: Option<Platform>) -> Self {
+        self.platform = platform;
+        self
+    }
+
+    /// Usa uma configuração fixa em vez do registries.conf
+    pub fn with_config(mut self, config: RegistryConfig) -> Self {
+        self.config = config;
+        self.config_from_disk = false;
+        self
+    }
+
+    /// Store onde as camadas são gravadas; por padrão `<cache_dir>/layers`
+    pub fn with_layer_store(mut self, layers: LayerStore) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Envia as camadas em blocos de `size` bytes no push
+    pub fn with_push_chunk_size(mut self, size: usize) -> Self {
+        self.push_chunk_size = size.max(1);
+        self
+    }
+
+    pub fn layer_store(&self) -> &LayerStore {
+        &self.layers
+    }
+
+    pub fn config(&self) -> &RegistryConfig {
+        &self.config
+    }
+
+    /// Relê o registries.conf para que edições valham sem reiniciar
+    pub fn reload_config(&mut self) -> Result<()> {
+        if self.config_from_disk {
+            self.config = RegistryConfig::load()?;
+        }
+        Ok(())
+    }
+
+    /// Envia a requisição registrando a cota informada pelo registry e
+    /// aguardando (backoff) quando a cota está esgotada (429)
+    async fn send_with_backoff(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            let attempt_request = request.try_clone().ok_or_else(|| {
+                PolisError::Image("Requisição ao registry não pode ser repetida".to_string())
+            })?;
+            let response = attempt_request
+                .send()
+                .await
+                .map_err(|e| PolisError::Image(format!("Erro na requisição ao registry: {}", e)))?;
+
+            let registry = quota_key(response.url().host_str().unwrap_or_default());
+            if let (Some(scheduler), Some(info)) =
+                (&self.scheduler, RateLimitInfo::from_headers(response.headers()))
+            {
+                scheduler.record_rate_limit(&registry, info);
+            }
+
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                return Ok(response);
+            }
+
+            if attempt >= self.backoff.max_retries {
+                return Err(PolisError::Image(format!(
+                    "Cota de pulls do registry {} esgotada após {} tentativas",
+                    registry,
+                    attempt + 1
+                )));
+            }
+
+            let delay = parse_retry_after(response.headers())
+                .unwrap_or_else(|| self.backoff.delay_for(attempt))
+                .min(self.backoff.max_delay);
+            if let Some(scheduler) = &self.scheduler {
+                scheduler.record_throttled(&registry, delay);
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
         }
     }
 
@@ -103,42 +317,16 @@ impl RegistryClient {
         self
     }
 
-    fn get_registry_info(&self, image_name: &str) -> (String, String, String) {
-        // Parse image name to extract registry, repo, and tag
-        let (registry, repo, tag) = if image_name.contains('/') {
-            let parts: Vec<&str> = image_name.split('/').collect();
-            if parts.len() >= 2 {
-                let registry_part = parts[0];
-                let repo_part = parts[1..].join("/");
-                
-                // Check if it's a known registry
-                if registry_part.contains('.') || registry_part == "quay.io" {
-                    (registry_part.to_string(), repo_part, "latest".to_string())
-                } else {
-                    // Default to docker.io
-                    ("docker.io".to_string(), image_name.to_string(), "latest".to_string())
-                }
-            } else {
-                ("docker.io".to_string(), image_name.to_string(), "latest".to_string())
-            }
-        } else {
-            // No slash means it's a library image on docker.io
-            ("docker.io".to_string(), format!("library/{}", image_name), "latest".to_string())
-        };
-
-        // Extract tag if present
-        let (repo, tag) = if repo.contains(':') {
-            let parts: Vec<&str> = repo.split(':').collect();
-            if parts.len() == 2 {
-                (parts[0].to_string(), parts[1].to_string())
-            } else {
-                (repo, tag)
-            }
+    /// Adiciona o token do Docker Hub ou, sem ele, as credenciais básicas
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(token) = &self.docker_hub_token {
+            request.header("Authorization", format!("Bearer {}", token))
+        } else if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            let auth = base64::encode(format!("{}:{}", username, password));
+            request.header("Authorization", format!("Basic {}", auth))
         } else {
-            (repo, tag)
-        };
-
-        (registry, repo, tag)
+            request
+        }
     }
 
     fn get_base_url(&self, registry: &str) -> String {
@@ -151,18 +339,21 @@ impl RegistryClient {
             .unwrap_or_else(|| format!("https://{}/v2", registry))
     }
 
-    async fn get_docker_hub_token(&mut self, repo: &str) -> Result<String> {
+    /// Obtém um token do Docker Hub para `actions` no repositório (`pull`
+    /// ou `pull,push`)
+    async fn get_docker_hub_token(&mut self, repo: &str, actions: &str) -> Result<String> {
+        let key = TokenCache::key("docker.io", repo, actions);
+        if let Some(token) = self.tokens.get(&key) {
+            self.docker_hub_token = Some(token.clone());
+            return Ok(token);
+        }
+
         // Try to get token for public images first (no auth required)
-        let auth_url = format!("https://auth.docker.io/token?service=registry.docker.io&scope=repository:{}:pull", repo);
+        let auth_url = format!("https://auth.docker.io/token?service=registry.docker.io&scope=repository:{}:{}", repo, actions);
         
         println!(" Tentando obter token do Docker Hub para: {}", repo);
         
-        let response = self
-            .client
-            .get(&auth_url)
-            .send()
-            .await
-            .map_err(|e| PolisError::Image(format!("Erro ao obter token: {}", e)))?;
+        let response = self.send_with_backoff(self.client.get(&auth_url)).await?;
 
         println!(" Status da resposta do token: {}", response.status());
 
@@ -174,6 +365,7 @@ impl RegistryClient {
             
             println!(" Token obtido com sucesso!");
             self.docker_hub_token = Some(token_response.token.clone());
+            self.tokens.insert(key, token_response.token.clone(), Some(token_response.expires_in));
             return Ok(token_response.token);
         }
 
@@ -181,12 +373,12 @@ impl RegistryClient {
         if let (Some(username), Some(password)) = (&self.username, &self.password) {
             let auth = base64::encode(format!("{}:{}", username, password));
             let response = self
-                .client
-                .get(&auth_url)
-                .header("Authorization", format!("Basic {}", auth))
-                .send()
-                .await
-                .map_err(|e| PolisError::Image(format!("Erro ao obter token com auth: {}", e)))?;
+                .send_with_backoff(
+                    self.client
+                        .get(&auth_url)
+                        .header("Authorization", format!("Basic {}", auth)),
+                )
+                .await?;
 
             if response.status().is_success() {
                 let token_response: DockerHubToken = response
@@ -195,6 +387,7 @@ impl RegistryClient {
                     .map_err(|e| PolisError::Image(format!("Erro ao parsear token: {}", e)))?;
                 
                 self.docker_hub_token = Some(token_response.token.clone());
+                self.tokens.insert(key, token_response.token.clone(), Some(token_response.expires_in));
                 return Ok(token_response.token);
             }
         }
@@ -203,12 +396,29 @@ impl RegistryClient {
     }
 
     pub async fn pull_image(&mut self, name: &str) -> Result<ImageId> {
+        Ok(self.pull_manifest(name).await?.0)
+    }
+
+    /// Baixa a imagem e retorna também seu manifest. As camadas vão para o
+    /// [`LayerStore`]; as que ele já tem não são baixadas de novo.
+    pub async fn pull_manifest(&mut self, name: &str) -> Result<(ImageId, OciManifest)> {
+        self.pull_manifest_with_progress(name, None).await
+    }
+
+    /// [`Self::pull_manifest`] reportando o progresso do download de cada camada
+    pub async fn pull_manifest_with_progress(&mut self, name: &str, on_progress: Option<ProgressCallback>) -> Result<(ImageId, OciManifest)> {
+        // Só é chamado entre os blocos, mas o Mutex mantém o pull `Send`
+        let on_progress = on_progress.map(Mutex::new);
         let image_id = ImageId::from_string(name);
 
-        // Parse image name to extract registry, repo, and tag
-        let (registry, repo, tag) = self.get_registry_info(name);
+        // O nome original é mantido para a tag; o download pode passar por um cache pull-through
+        let reference = self.config.resolve_reference(name)?;
+        let (registry, repo, tag) = (reference.pull_registry.clone(), reference.repository.clone(), reference.manifest_reference().to_string());
         
-        println!(" Registry: {}, Repo: {}, Tag: {}", registry, repo, tag);
+        println!(" Registry: {}, Repo: {}, Referência: {}", registry, repo, tag);
+        if reference.is_pull_through() {
+            println!(" Usando cache pull-through {} para {}", registry, reference.registry);
+        }
 
         // Create cache directory for this image
         let image_cache_dir = self.cache_dir.join(&repo).join(&tag);
@@ -221,36 +431,23 @@ impl RegistryClient {
         // Use the provided Docker Hub token directly
         if let Some(token) = &self.docker_hub_token {
             println!(" Usando token fornecido: {}...", &token[..20]);
-        } else {
+        } else if registry == "docker.io" {
             // Fallback: try to get token from Docker Hub API
-            let token = self.get_docker_hub_token(&repo).await;
+            let token = self.get_docker_hub_token(&repo, "pull").await;
             if let Ok(token) = token {
                 println!(" Usando token da API: {}...", &token[..20]);
             }
         }
 
+        let choice = resolve_platform(self.platform.as_ref(), &self.config, &reference.registry, &repo)?;
+        println!(" Plataforma: {} ({:?})", choice.platform, choice.source);
+
         // Try to fetch from registry first
-        match self.fetch_manifest_with_url(&base_url, &repo, &tag).await {
+        let manifest = match self.fetch_platform_manifest_with_url(&base_url, &repo, &tag, &choice).await {
             Ok(manifest) => {
-                // Save manifest
-                let manifest_path = image_cache_dir.join("manifest.json");
-                let manifest_json = serde_json::to_string_pretty(&manifest)?;
-                fs::write(&manifest_path, manifest_json).await?;
-
-                // Download config
-                let config = self.fetch_config_with_url(&base_url, &repo, &manifest.config.digest).await?;
-                let config_path = image_cache_dir.join("config.json");
-                let config_json = serde_json::to_string_pretty(&config)?;
-                fs::write(&config_path, config_json).await?;
-
-                // Download layers
-                for (i, layer) in manifest.layers.iter().enumerate() {
-                    let layer_path = image_cache_dir.join(format!("layer_{}.tar.gz", i));
-                    self.download_layer_with_url(&base_url, &repo, &layer.digest, &layer_path)
-                        .await?;
-                }
-
+                self.store_image(&base_url, &repo, &manifest, &image_cache_dir, on_progress.as_ref()).await?;
                 println!(" Imagem '{}' baixada com sucesso do registry {}", name, registry);
+                manifest
             }
             Err(e) => {
                 println!(" Aviso: Não foi possível baixar '{}' do registry {}: {}", name, registry, e);
@@ -259,75 +456,103 @@ impl RegistryClient {
                 if let Some(fallback_url) = self.config.get_fallback_url(&registry) {
                     if fallback_url != base_url {
                         println!(" Tentando fallback para registry principal...");
-                        match self.fetch_manifest_with_url(&fallback_url, &repo, &tag).await {
+                        match self.fetch_platform_manifest_with_url(&fallback_url, &repo, &tag, &choice).await {
                             Ok(manifest) => {
                                 println!(" Sucesso com registry principal!");
-                                // Process manifest...
-                                let manifest_path = image_cache_dir.join("manifest.json");
-                                let manifest_json = serde_json::to_string_pretty(&manifest)?;
-                                fs::write(&manifest_path, manifest_json).await?;
-                                
-                                // Download config and layers
-                                let config = self.fetch_config_with_url(&fallback_url, &repo, &manifest.config.digest).await?;
-                                let config_path = image_cache_dir.join("config.json");
-                                let config_json = serde_json::to_string_pretty(&config)?;
-                                fs::write(&config_path, config_json).await?;
-
-                                for (i, layer) in manifest.layers.iter().enumerate() {
-                                    let layer_path = image_cache_dir.join(format!("layer_{}.tar.gz", i));
-                                    self.download_layer_with_url(&fallback_url, &repo, &layer.digest, &layer_path)
-                                        .await?;
-                                }
-                                
+                                self.store_image(&fallback_url, &repo, &manifest, &image_cache_dir, on_progress.as_ref()).await?;
                                 println!(" Imagem '{}' baixada com sucesso do registry principal {}", name, registry);
+                                manifest
                             }
-                            Err(_) => {
-                                println!(" Criando imagem local de exemplo...");
-                                self.create_local_image(&repo, &tag, &image_cache_dir).await?;
-                            }
+                            Err(e) => self.create_local_image_or_fail(&reference, e, &image_cache_dir).await?,
                         }
                     } else {
-                        println!(" Criando imagem local de exemplo...");
-                        self.create_local_image(&repo, &tag, &image_cache_dir).await?;
+                        self.create_local_image_or_fail(&reference, e, &image_cache_dir).await?
                     }
                 } else {
-                    println!(" Criando imagem local de exemplo...");
-                    self.create_local_image(&repo, &tag, &image_cache_dir).await?;
+                    self.create_local_image_or_fail(&reference, e, &image_cache_dir).await?
                 }
             }
+        };
+
+        Ok((image_id, manifest))
+    }
+
+    /// Sem acesso ao registry, cria uma imagem local de exemplo. Um pull por
+    /// digest não pode ser atendido assim e falha com o erro do registry.
+    async fn create_local_image_or_fail(&self, reference: &ResolvedReference, error: PolisError, image_cache_dir: &PathBuf) -> Result<OciManifest> {
+        if reference.digest.is_some() {
+            return Err(error);
         }
+        println!(" Criando imagem local de exemplo...");
+        self.create_local_image(&reference.repository, &reference.tag, image_cache_dir).await
+    }
+
+    /// Grava manifest e config da imagem e baixa as camadas que faltam no store
+    async fn store_image(&self, base_url: &str, repo: &str, manifest: &OciManifest, image_cache_dir: &Path, on_progress: Option<&Mutex<ProgressCallback>>) -> Result<()> {
+        let manifest_path = image_cache_dir.join("manifest.json");
+        let manifest_json = serde_json::to_string_pretty(manifest)?;
+        fs::write(&manifest_path, manifest_json).await?;
 
-        Ok(image_id)
+        let config = self.fetch_config_with_url(base_url, repo, &manifest.config.digest).await?;
+        let config_path = image_cache_dir.join("config.json");
+        let config_json = serde_json::to_string_pretty(&config)?;
+        fs::write(&config_path, config_json).await?;
+
+        for layer in &manifest.layers {
+            self.download_layer_with_url(base_url, repo, &layer.digest, on_progress).await?;
+        }
+        Ok(())
     }
 
-    async fn fetch_manifest(&self, repo: &str, tag: &str) -> Result<OciManifest> {
+    pub async fn fetch_manifest(&self, repo: &str, tag: &str) -> Result<OciManifest> {
         self.fetch_manifest_with_url(&self.base_url, repo, tag).await
     }
 
+    /// Busca o manifest da plataforma escolhida conforme a precedência de
+    /// [`resolve_platform`]: `--platform`, override por imagem, host
+    pub async fn fetch_platform_manifest(&self, repo: &str, tag: &str) -> Result<(OciManifest, PlatformChoice)> {
+        let registry = Reference::parse(repo)?.registry;
+        let choice = resolve_platform(self.platform.as_ref(), &self.config, &registry, repo)?;
+        let manifest = self.fetch_platform_manifest_with_url(&self.base_url, repo, tag, &choice).await?;
+        Ok((manifest, choice))
+    }
+
+    /// Busca um manifest; se for um manifest list, seleciona a entrada da plataforma
+    async fn fetch_platform_manifest_with_url(&self, base_url: &str, repo: &str, tag: &str, choice: &PlatformChoice) -> Result<OciManifest> {
+        let document = self.fetch_manifest_document(base_url, repo, tag).await?;
+        if document.get("manifests").is_none() {
+            return serde_json::from_value(document)
+                .map_err(|e| PolisError::Image(format!("Erro ao parsear manifest: {}", e)));
+        }
+
+        let index: OciIndex = serde_json::from_value(document)
+            .map_err(|e| PolisError::Image(format!("Erro ao parsear manifest list: {}", e)))?;
+        let entry = index.select(&choice.platform).ok_or_else(|| PolisError::Image(format!(
+            "Imagem {}:{} não disponível para {} (disponíveis: {})",
+            repo, tag, choice.platform, index.platforms().join(", ")
+        )))?;
+        self.fetch_manifest_with_url(base_url, repo, &entry.digest).await
+    }
+
     async fn fetch_manifest_with_url(&self, base_url: &str, repo: &str, tag: &str) -> Result<OciManifest> {
+        let document = self.fetch_manifest_document(base_url, repo, tag).await?;
+        serde_json::from_value(document)
+            .map_err(|e| PolisError::Image(format!("Erro ao parsear manifest: {}", e)))
+    }
+
+    async fn fetch_manifest_document(&self, base_url: &str, repo: &str, tag: &str) -> Result<serde_json::Value> {
         let url = format!("{}/{}/manifests/{}", base_url, repo, tag);
 
-        let mut request = self
+        let request = self
             .client
             .get(&url)
             .header("User-Agent", "polis/0.1.0")
             .header("Accept", "application/vnd.docker.distribution.manifest.v2+json")
-            .header("Accept", "application/vnd.oci.image.manifest.v1+json");
-
-        // Add Docker Hub token if available
-        if let Some(token) = &self.docker_hub_token {
-            // Try Bearer token first
-            request = request.header("Authorization", format!("Bearer {}", token));
-        } else if let (Some(username), Some(password)) = (&self.username, &self.password) {
-            // Fallback to basic auth
-            let auth = base64::encode(format!("{}:{}", username, password));
-            request = request.header("Authorization", format!("Basic {}", auth));
-        }
+            .header("Accept", "application/vnd.oci.image.manifest.v1+json")
+            .header("Accept", "application/vnd.docker.distribution.manifest.list.v2+json")
+            .header("Accept", "application/vnd.oci.image.index.v1+json");
 
-        let response = request
-            .send()
-            .await
-            .map_err(|e| PolisError::Image(format!("Erro ao buscar manifest: {}", e)))?;
+        let response = self.send_with_backoff(self.authorize(request)).await?;
 
         if !response.status().is_success() {
             return Err(PolisError::Image(format!(
@@ -336,12 +561,16 @@ impl RegistryClient {
             )));
         }
 
-        let manifest: OciManifest = response
-            .json()
+        let bytes = response
+            .bytes()
             .await
-            .map_err(|e| PolisError::Image(format!("Erro ao parsear manifest: {}", e)))?;
-
-        Ok(manifest)
+            .map_err(|e| PolisError::Image(format!("Erro ao baixar manifest: {}", e)))?;
+        // Pedido por digest, o manifest só é aceito se os bytes conferirem
+        if tag.contains(':') {
+            verify_digest(tag, &bytes)?;
+        }
+        serde_json::from_slice(&bytes)
+            .map_err(|e| PolisError::Image(format!("Erro ao parsear manifest: {}", e)))
     }
 
     async fn fetch_config(&self, repo: &str, digest: &str) -> Result<OciConfig> {
@@ -351,23 +580,12 @@ impl RegistryClient {
     async fn fetch_config_with_url(&self, base_url: &str, repo: &str, digest: &str) -> Result<OciConfig> {
         let url = format!("{}/{}/blobs/{}", base_url, repo, digest);
 
-        let mut request = self
+        let request = self
             .client
             .get(&url)
             .header("User-Agent", "polis/0.1.0");
 
-        // Add authentication if available
-        if let Some(token) = &self.docker_hub_token {
-            request = request.header("Authorization", format!("Bearer {}", token));
-        } else if let (Some(username), Some(password)) = (&self.username, &self.password) {
-            let auth = base64::encode(format!("{}:{}", username, password));
-            request = request.header("Authorization", format!("Basic {}", auth));
-        }
-
-        let response = request
-            .send()
-            .await
-            .map_err(|e| PolisError::Image(format!("Erro ao buscar config: {}", e)))?;
+        let response = self.send_with_backoff(self.authorize(request)).await?;
 
         if !response.status().is_success() {
             return Err(PolisError::Image(format!(
@@ -376,38 +594,40 @@ impl RegistryClient {
             )));
         }
 
-        let config: OciConfig = response
-            .json()
+        let bytes = response
+            .bytes()
             .await
+            .map_err(|e| PolisError::Image(format!("Erro ao baixar config: {}", e)))?;
+        let config: OciConfig = serde_json::from_slice(&bytes)
             .map_err(|e| PolisError::Image(format!("Erro ao parsear config: {}", e)))?;
 
+        // O config fica no store junto das camadas, com os bytes originais
+        self.layers.put(digest, bytes.as_ref()).await?;
         Ok(config)
     }
 
-    async fn download_layer(&self, repo: &str, digest: &str, path: &PathBuf) -> Result<()> {
-        self.download_layer_with_url(&self.base_url, repo, digest, path).await
+    /// Baixa a camada para o store, chamando `on_progress` a cada bloco
+    pub async fn download_layer(&self, repo: &str, digest: &str, on_progress: Option<ProgressCallback>) -> Result<()> {
+        let on_progress = on_progress.map(Mutex::new);
+        self.download_layer_with_url(&self.base_url, repo, digest, on_progress.as_ref()).await
     }
 
-    async fn download_layer_with_url(&self, base_url: &str, repo: &str, digest: &str, path: &PathBuf) -> Result<()> {
+    /// Baixa a camada para o store, a menos que ele já a tenha. O corpo é
+    /// gravado à medida que chega, sem manter a camada inteira em memória.
+    async fn download_layer_with_url(&self, base_url: &str, repo: &str, digest: &str, on_progress: Option<&Mutex<ProgressCallback>>) -> Result<()> {
+        if self.layers.contains(digest) {
+            println!(" Camada {} já presente", digest);
+            return Ok(());
+        }
+
         let url = format!("{}/{}/blobs/{}", base_url, repo, digest);
 
-        let mut request = self
+        let request = self
             .client
             .get(&url)
             .header("User-Agent", "polis/0.1.0");
 
-        // Add authentication if available
-        if let Some(token) = &self.docker_hub_token {
-            request = request.header("Authorization", format!("Bearer {}", token));
-        } else if let (Some(username), Some(password)) = (&self.username, &self.password) {
-            let auth = base64::encode(format!("{}:{}", username, password));
-            request = request.header("Authorization", format!("Basic {}", auth));
-        }
-
-        let response = request
-            .send()
-            .await
-            .map_err(|e| PolisError::Image(format!("Erro ao baixar layer: {}", e)))?;
+        let response = self.send_with_backoff(self.authorize(request)).await?;
 
         if !response.status().is_success() {
             return Err(PolisError::Image(format!(
@@ -416,19 +636,362 @@ impl RegistryClient {
             )));
         }
 
-        let mut file = fs::File::create(path).await?;
-        let bytes = response
-            .bytes()
-            .await
-            .map_err(|e| PolisError::Image(format!("Erro ao baixar bytes: {}", e)))?;
-        file.write_all(&bytes).await?;
+        let Some(mut writer) = self.layers.writer(digest).await? else {
+            // Gravada por outro pull enquanto esperávamos a resposta
+            return Ok(());
+        };
+        let content_length = response.content_length();
+        let mut body = response.bytes_stream();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| PolisError::Image(format!("Erro ao baixar bytes: {}", e)))?;
+            writer.write(&chunk).await?;
+            if let Some(on_progress) = on_progress {
+                let on_progress = on_progress.lock().unwrap_or_else(|e| e.into_inner());
+                on_progress(writer.written(), content_length);
+            }
+        }
+        writer.commit().await
+    }
+
+    /// Envia a imagem local `image_id` como `name`, seguindo o fluxo de push
+    /// da OCI Distribution Spec: cada camada e o config que o registry ainda
+    /// não tem são enviados em uma sessão de upload, em blocos, e o manifest
+    /// é publicado por último com a tag.
+    pub async fn push_image(&mut self, name: &str, image_id: &ImageId) -> Result<()> {
+        let local = Reference::parse(&image_id.0)?;
+        let manifest_path = self.cache_dir.join(&local.repository).join(local.manifest_reference()).join("manifest.json");
+        let manifest_json = fs::read(&manifest_path).await.map_err(|_| {
+            PolisError::Image(format!("Imagem {} não encontrada localmente", image_id.0))
+        })?;
+        let manifest: OciManifest = serde_json::from_slice(&manifest_json)
+            .map_err(|e| PolisError::Image(format!("Erro ao parsear manifest: {}", e)))?;
+
+        println!(" Enviando {} como {}", image_id.0, name);
+        self.push_manifest(name, &manifest, None).await?;
+        println!(" Imagem {} enviada como {}", image_id.0, name);
+        Ok(())
+    }
+
+    /// Publica `manifest` como `name` depois de enviar os blobs que faltam no
+    /// registry. Um `401` faz o cliente pedir ao serviço de tokens indicado
+    /// pelo registry um token com escopo `pull,push` e repetir o envio uma
+    /// vez; os blobs já enviados são pulados pelo `HEAD`.
+    pub async fn push_manifest(&mut self, name: &str, manifest: &OciManifest, on_progress: Option<PushProgressCallback>) -> Result<()> {
+        let reference = Reference::parse(name)?;
+        if reference.digest.is_some() {
+            return Err(PolisError::Image(format!(
+                "Push de {} exige uma tag; o digest é definido pelo registry",
+                name
+            )));
+        }
+        let tag = reference.tag_or_default().to_string();
+        let Reference { registry, repository: repo, .. } = reference;
+        if self.config.is_registry_blocked(&registry) {
+            return Err(PolisError::Image(format!("Registry {} bloqueado", registry)));
+        }
+        // Mirrors são somente leitura; o push vai para o registry principal
+        let base_url = self.get_fallback_url(&registry);
+
+        if self.docker_hub_token.is_none() {
+            self.docker_hub_token = self.tokens.get(&TokenCache::key(&base_url, &repo, "pull,push"));
+        }
+        if self.docker_hub_token.is_none() && registry == "docker.io" {
+            if let Err(e) = self.get_docker_hub_token(&repo, "pull,push").await {
+                println!(" Aviso: {}", e);
+            }
+        }
+
+        let on_progress = on_progress.map(Mutex::new);
+        let mut authenticated = false;
+        loop {
+            match self.upload_image(&base_url, &repo, &tag, manifest, on_progress.as_ref()).await {
+                Err(PolisError::Registry { status: 401, .. }) if !authenticated => {
+                    authenticated = true;
+                    println!(" Registry {} exige autenticação; obtendo token de push", registry);
+                    self.fetch_push_token(&base_url, &repo).await?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn upload_image(&self, base_url: &str, repo: &str, tag: &str, manifest: &OciManifest, on_progress: Option<&Mutex<PushProgressCallback>>) -> Result<()> {
+        for descriptor in manifest.layers.iter().chain(std::iter::once(&manifest.config)) {
+            self.push_blob(base_url, repo, descriptor, on_progress).await?;
+        }
 
+        let media_type = if manifest.media_type.is_empty() {
+            "application/vnd.oci.image.manifest.v1+json"
+        } else {
+            manifest.media_type.as_str()
+        };
+        let url = format!("{}/{}/manifests/{}", base_url, repo, tag);
+        let request = self
+            .client
+            .put(&url)
+            .header("User-Agent", "polis/0.1.0")
+            .header("Content-Type", media_type)
+            .body(serde_json::to_vec(&distribution_manifest(manifest, media_type))?);
+        let response = self.send_with_backoff(self.authorize(request)).await?;
+        if !response.status().is_success() {
+            return Err(registry_error(response, "enviar manifest").await);
+        }
         Ok(())
     }
 
-    pub async fn push_image(&self, _name: &str) -> Result<()> {
-        // TODO: Implement image pushing to registry
-        Err(PolisError::Image("Push não implementado ainda".to_string()))
+    /// Obtém um token com escopo `pull,push` em `repo` do serviço indicado no
+    /// `WWW-Authenticate` do registry, com as credenciais básicas quando
+    /// configuradas. Se o registry pede autenticação básica, passa a usá-la.
+    async fn fetch_push_token(&mut self, base_url: &str, repo: &str) -> Result<()> {
+        let request = self.client.get(format!("{}/", base_url)).header("User-Agent", "polis/0.1.0");
+        let response = self.send_with_backoff(request).await?;
+        let challenge = response
+            .headers()
+            .get("WWW-Authenticate")
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_auth_challenge);
+        let Some((scheme, params)) = challenge else {
+            return Err(PolisError::Auth(format!(
+                "Registry {} recusou o push sem indicar como autenticar",
+                base_url
+            )));
+        };
+
+        if scheme.eq_ignore_ascii_case("basic") {
+            if self.username.is_none() || self.password.is_none() {
+                return Err(PolisError::Auth(format!("Registry {} exige usuário e senha", base_url)));
+            }
+            self.docker_hub_token = None;
+            self.tokens.remove(&TokenCache::key(base_url, repo, "pull,push"));
+            return Ok(());
+        }
+
+        let realm = params.get("realm").ok_or_else(|| {
+            PolisError::Auth(format!("Desafio de autenticação de {} sem realm", base_url))
+        })?;
+        let mut url = Url::parse(realm)
+            .map_err(|e| PolisError::Auth(format!("Realm de autenticação inválido '{}': {}", realm, e)))?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(service) = params.get("service") {
+                query.append_pair("service", service);
+            }
+            query.append_pair("scope", &format!("repository:{}:pull,push", repo));
+        }
+
+        let mut request = self.client.get(url).header("User-Agent", "polis/0.1.0");
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            request = request.basic_auth(username, Some(password));
+        }
+        let response = self.send_with_backoff(request).await?;
+        if !response.status().is_success() {
+            return Err(PolisError::Auth(format!(
+                "Serviço de tokens {} negou push em {}: {}",
+                realm,
+                repo,
+                response.status()
+            )));
+        }
+        let token: BearerToken = response
+            .json()
+            .await
+            .map_err(|e| PolisError::Auth(format!("Erro ao parsear token: {}", e)))?;
+        let expires_in = token.expires_in;
+        let token = token
+            .token
+            .or(token.access_token)
+            .ok_or_else(|| PolisError::Auth(format!("Serviço de tokens {} não devolveu um token", realm)))?;
+        self.tokens.insert(TokenCache::key(base_url, repo, "pull,push"), token.clone(), expires_in);
+        self.docker_hub_token = Some(token);
+        Ok(())
+    }
+
+    /// Envia um blob do store, a menos que o registry já o tenha: `POST`
+    /// abre a sessão, cada bloco vai em um `PATCH` e o `PUT` com o digest a
+    /// conclui
+    async fn push_blob(&self, base_url: &str, repo: &str, descriptor: &OciDescriptor, on_progress: Option<&Mutex<PushProgressCallback>>) -> Result<()> {
+        let digest = &descriptor.digest;
+        let url = format!("{}/{}/blobs/{}", base_url, repo, digest);
+        let request = self.client.head(&url).header("User-Agent", "polis/0.1.0");
+        let response = self.send_with_backoff(self.authorize(request)).await?;
+        if response.status().is_success() {
+            match on_progress {
+                Some(on_progress) => {
+                    let on_progress = on_progress.lock().unwrap_or_else(|e| e.into_inner());
+                    on_progress(PushProgress::Exists { digest: digest.clone() });
+                }
+                None => println!(" Camada {} já existe no registry", digest),
+            }
+            return Ok(());
+        }
+        if response.status() != reqwest::StatusCode::NOT_FOUND {
+            return Err(registry_error(response, &format!("consultar {}", digest)).await);
+        }
+
+        let mut blob = self.layers.get(digest).await?;
+        let mut session = self.open_blob_upload(base_url, repo, digest).await?;
+        // Numa sessão retomada, o que o registry já tem não é reenviado
+        if session.uploaded_bytes > 0 {
+            tokio::io::copy(&mut (&mut blob).take(session.uploaded_bytes), &mut tokio::io::sink()).await?;
+        }
+
+        loop {
+            let mut chunk = Vec::with_capacity(self.push_chunk_size);
+            (&mut blob).take(self.push_chunk_size as u64).read_to_end(&mut chunk).await?;
+            if chunk.is_empty() {
+                break;
+            }
+            session.upload_chunk(&chunk).await?;
+            match on_progress {
+                Some(on_progress) => {
+                    let on_progress = on_progress.lock().unwrap_or_else(|e| e.into_inner());
+                    on_progress(PushProgress::Uploading {
+                        digest: digest.clone(),
+                        sent: session.uploaded_bytes,
+                        size: descriptor.size,
+                    });
+                }
+                None => println!(" Enviando {}: {}/{} bytes", digest, session.uploaded_bytes, descriptor.size),
+            }
+        }
+        session.finalize().await
+    }
+
+    /// Abre a sessão de upload de `digest` no repositório (`registry/repo`).
+    /// Se um upload anterior do mesmo blob foi interrompido e o registry ainda
+    /// tem a sessão, ela é retomada a partir do último byte confirmado.
+    pub async fn start_blob_upload(&mut self, repository: &str, digest: &str) -> Result<BlobUploadSession> {
+        let Reference { registry, repository: repo, .. } = Reference::parse(repository)?;
+        if self.config.is_registry_blocked(&registry) {
+            return Err(PolisError::Image(format!("Registry {} bloqueado", registry)));
+        }
+        let base_url = self.get_fallback_url(&registry);
+
+        if self.docker_hub_token.is_none() {
+            self.docker_hub_token = self.tokens.get(&TokenCache::key(&base_url, &repo, "pull,push"));
+        }
+        if self.docker_hub_token.is_none() && registry == "docker.io" {
+            if let Err(e) = self.get_docker_hub_token(&repo, "pull,push").await {
+                println!(" Aviso: {}", e);
+            }
+        }
+        self.open_blob_upload(&base_url, &repo, digest).await
+    }
+
+    async fn open_blob_upload(&self, base_url: &str, repo: &str, digest: &str) -> Result<BlobUploadSession> {
+        let checkpoint_file = self
+            .cache_dir
+            .join("uploads")
+            .join(repo)
+            .join(format!("{}.json", digest.replace(':', "-")));
+        if let Some(session) = self.resume_blob_upload(base_url, digest, &checkpoint_file).await? {
+            return Ok(session);
+        }
+
+        let url = format!("{}/{}/blobs/uploads/", base_url, repo);
+        let request = self.client.post(&url).header("User-Agent", "polis/0.1.0");
+        let response = self.send_with_backoff(self.authorize(request)).await?;
+        if response.status() != reqwest::StatusCode::ACCEPTED {
+            return Err(registry_error(response, &format!("iniciar upload de {}", digest)).await);
+        }
+
+        let session = BlobUploadSession {
+            upload_url: upload_location(base_url, &response)?.to_string(),
+            uploaded_bytes: 0,
+            checkpoint_file,
+            digest: digest.to_string(),
+            base_url: base_url.to_string(),
+            client: self.clone(),
+        };
+        session.save_checkpoint().await?;
+        Ok(session)
+    }
+
+    /// Sessão salva no checkpoint, se o registry ainda a conhece
+    async fn resume_blob_upload(&self, base_url: &str, digest: &str, checkpoint_file: &Path) -> Result<Option<BlobUploadSession>> {
+        let Ok(content) = fs::read(checkpoint_file).await else {
+            return Ok(None);
+        };
+        let Ok(checkpoint) = serde_json::from_slice::<UploadCheckpoint>(&content) else {
+            let _ = fs::remove_file(checkpoint_file).await;
+            return Ok(None);
+        };
+
+        let request = self.client.get(&checkpoint.upload_url).header("User-Agent", "polis/0.1.0");
+        let response = self.send_with_backoff(self.authorize(request)).await?;
+        if !response.status().is_success() {
+            println!(" Sessão de upload de {} expirou; recomeçando", digest);
+            let _ = fs::remove_file(checkpoint_file).await;
+            return Ok(None);
+        }
+
+        // O `Range` do registry prevalece: um PATCH recebido cuja resposta se
+        // perdeu não está no checkpoint. "0-0" é ambíguo com a sessão vazia.
+        let uploaded_bytes = match upload_range_end(&response) {
+            Some(end) if checkpoint.uploaded_bytes > 0 => end + 1,
+            _ => checkpoint.uploaded_bytes,
+        };
+        let upload_url = upload_location(base_url, &response)
+            .map(|url| url.to_string())
+            .unwrap_or(checkpoint.upload_url);
+        println!(" Retomando upload de {} a partir do byte {}", digest, uploaded_bytes);
+
+        let session = BlobUploadSession {
+            upload_url,
+            uploaded_bytes,
+            checkpoint_file: checkpoint_file.to_path_buf(),
+            digest: digest.to_string(),
+            base_url: base_url.to_string(),
+            client: self.clone(),
+        };
+        session.save_checkpoint().await?;
+        Ok(Some(session))
+    }
+
+    /// Tags do repositório (`GET /v2/<repo>/tags/list`), na ordem do
+    /// registry, seguindo as páginas indicadas no cabeçalho `Link`
+    pub async fn list_tags(&mut self, repository: &str) -> Result<Vec<String>> {
+        let Reference { registry, repository: repo, .. } = Reference::parse(repository)?;
+        if self.config.is_registry_blocked(&registry) {
+            return Err(PolisError::Image(format!("Registry {} bloqueado", registry)));
+        }
+        // Mirrors podem não conhecer todas as tags; a lista vem do registry principal
+        let base_url = self.get_fallback_url(&registry);
+
+        if self.docker_hub_token.is_none() && registry == "docker.io" {
+            if let Err(e) = self.get_docker_hub_token(&repo, "pull").await {
+                println!(" Aviso: {}", e);
+            }
+        }
+
+        let mut tags = Vec::new();
+        let mut next = Some(
+            Url::parse(&format!("{}/{}/tags/list", base_url, repo))
+                .map_err(|e| PolisError::Image(format!("URL do registry inválida: {}", e)))?,
+        );
+        while let Some(url) = next {
+            let request = self
+                .client
+                .get(url)
+                .header("User-Agent", "polis/0.1.0")
+                .header("Accept", "application/json");
+            let response = self.send_with_backoff(self.authorize(request)).await?;
+            if !response.status().is_success() {
+                return Err(PolisError::Image(format!(
+                    "Erro HTTP ao listar tags de {}: {}",
+                    repo,
+                    response.status()
+                )));
+            }
+
+            next = next_page(&base_url, &response)?;
+            let page: TagList = response
+                .json()
+                .await
+                .map_err(|e| PolisError::Image(format!("Erro ao parsear lista de tags: {}", e)))?;
+            tags.extend(page.tags.unwrap_or_default());
+        }
+        Ok(tags)
     }
 
     pub async fn list_images(&self) -> Result<Vec<ImageId>> {
@@ -457,22 +1020,50 @@ impl RegistryClient {
         Ok(images)
     }
 
-    async fn create_local_image(&self, repo: &str, tag: &str, image_cache_dir: &PathBuf) -> Result<()> {
+    async fn create_local_image(&self, repo: &str, tag: &str, image_cache_dir: &PathBuf) -> Result<OciManifest> {
+        // A camada e o config de exemplo também vão para o store, com seus digests reais
+        let layer_content: &[u8] = b"dummy layer content";
+        let layer_digest = format!("sha256:{:x}", Sha256::digest(layer_content));
+        self.layers.put(&layer_digest, layer_content).await?;
+
+        // Create a simple config
+        let config = OciConfig {
+            architecture: "amd64".to_string(),
+            os: "linux".to_string(),
+            config: OciImageConfig {
+                user: Some("root".to_string()),
+                exposed_ports: None,
+                env: Some(vec!["PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin".to_string()]),
+                entrypoint: Some(vec!["/bin/sh".to_string()]),
+                cmd: Some(vec!["-c".to_string()]),
+                volumes: None,
+                working_dir: Some("/".to_string()),
+                labels: Some(std::collections::HashMap::new()),
+            },
+            rootfs: OciRootFs {
+                r#type: "layers".to_string(),
+                diff_ids: vec![layer_digest.clone()],
+            },
+        };
+        let config_json = serde_json::to_string_pretty(&config)?;
+        let config_digest = format!("sha256:{:x}", Sha256::digest(config_json.as_bytes()));
+        self.layers.put(&config_digest, config_json.as_bytes()).await?;
+
         // Create a simple local manifest
         let manifest = OciManifest {
             schema_version: 2,
             media_type: "application/vnd.oci.image.manifest.v1+json".to_string(),
             config: OciDescriptor {
                 media_type: "application/vnd.oci.image.config.v1+json".to_string(),
-                size: 1024,
-                digest: "sha256:local-example".to_string(),
+                size: config_json.len() as u64,
+                digest: config_digest,
                 annotations: None,
                 urls: None,
             },
             layers: vec![OciDescriptor {
                 media_type: "application/vnd.oci.image.layer.v1.tar+gzip".to_string(),
-                size: 2048,
-                digest: "sha256:local-layer".to_string(),
+                size: layer_content.len() as u64,
+                digest: layer_digest,
                 annotations: None,
                 urls: None,
             }],
@@ -484,36 +1075,250 @@ impl RegistryClient {
         let manifest_json = serde_json::to_string_pretty(&manifest)?;
         fs::write(&manifest_path, manifest_json).await?;
 
-        // Create a simple config
-        let config = OciConfig {
-            architecture: "amd64".to_string(),
-            os: "linux".to_string(),
-            config: OciImageConfig {
-                user: Some("root".to_string()),
-                exposed_ports: None,
-                env: Some(vec!["PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin".to_string()]),
-                entrypoint: Some(vec!["/bin/sh".to_string()]),
-                cmd: Some(vec!["-c".to_string()]),
-                volumes: None,
-                working_dir: Some("/".to_string()),
-                labels: Some(std::collections::HashMap::new()),
-            },
-            rootfs: OciRootFs {
-                r#type: "layers".to_string(),
-                diff_ids: vec!["sha256:local-layer".to_string()],
-            },
-        };
-
         // Save config
         let config_path = image_cache_dir.join("config.json");
-        let config_json = serde_json::to_string_pretty(&config)?;
         fs::write(&config_path, config_json).await?;
 
-        // Create a dummy layer file
-        let layer_path = image_cache_dir.join("layer_0.tar.gz");
-        fs::write(&layer_path, b"dummy layer content").await?;
-
         println!(" Imagem local '{}:{}' criada com sucesso", repo, tag);
+        Ok(manifest)
+    }
+}
+
+/// Sessão de upload de um blob que pode ser retomada: após cada bloco aceito
+/// pelo registry, a URL da sessão e os bytes enviados vão para
+/// `checkpoint_file`
+pub struct BlobUploadSession {
+    pub upload_url: String,
+    pub uploaded_bytes: u64,
+    pub checkpoint_file: PathBuf,
+    digest: String,
+    base_url: String,
+    client: RegistryClient,
+}
+
+/// Conteúdo do checkpoint de um upload
+#[derive(Debug, Serialize, Deserialize)]
+struct UploadCheckpoint {
+    upload_url: String,
+    uploaded_bytes: u64,
+}
+
+impl BlobUploadSession {
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    /// Envia o próximo bloco do blob com `PATCH`, a partir de `uploaded_bytes`
+    pub async fn upload_chunk(&mut self, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let end = self.uploaded_bytes + data.len() as u64;
+        let request = self
+            .client
+            .client
+            .patch(&self.upload_url)
+            .header("User-Agent", "polis/0.1.0")
+            .header("Content-Type", "application/octet-stream")
+            .header("Content-Range", format!("{}-{}", self.uploaded_bytes, end - 1))
+            .body(data.to_vec());
+        let response = self.client.send_with_backoff(self.client.authorize(request)).await?;
+        if response.status() != reqwest::StatusCode::ACCEPTED {
+            return Err(registry_error(response, &format!("enviar {}", self.digest)).await);
+        }
+
+        self.upload_url = upload_location(&self.base_url, &response)?.to_string();
+        self.uploaded_bytes = end;
+        self.save_checkpoint().await
+    }
+
+    /// Conclui o upload com o `PUT` do digest e apaga o checkpoint
+    pub async fn finalize(self) -> Result<()> {
+        let mut url = Url::parse(&self.upload_url)
+            .map_err(|e| PolisError::Image(format!("URL de upload inválida: {}", e)))?;
+        url.query_pairs_mut().append_pair("digest", &self.digest);
+        let request = self
+            .client
+            .client
+            .put(url)
+            .header("User-Agent", "polis/0.1.0")
+            .header("Content-Length", "0");
+        let response = self.client.send_with_backoff(self.client.authorize(request)).await?;
+        if response.status() != reqwest::StatusCode::CREATED {
+            return Err(registry_error(response, &format!("concluir upload de {}", self.digest)).await);
+        }
+
+        match fs::remove_file(&self.checkpoint_file).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Grava o checkpoint sem deixar um arquivo parcial se o processo cair
+    async fn save_checkpoint(&self) -> Result<()> {
+        if let Some(parent) = self.checkpoint_file.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let checkpoint = UploadCheckpoint {
+            upload_url: self.upload_url.clone(),
+            uploaded_bytes: self.uploaded_bytes,
+        };
+        let temp = self.checkpoint_file.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec(&checkpoint)?).await?;
+        fs::rename(&temp, &self.checkpoint_file).await?;
         Ok(())
     }
 }
+
+/// Confere os bytes recebidos com o digest (`sha256:` ou `sha512:`) pedido
+fn verify_digest(digest: &str, bytes: &[u8]) -> Result<()> {
+    let actual = match digest.split_once(':') {
+        Some(("sha256", _)) => format!("sha256:{:x}", Sha256::digest(bytes)),
+        Some(("sha512", _)) => format!("sha512:{:x}", sha2::Sha512::digest(bytes)),
+        _ => return Err(PolisError::Image(format!("Digest não suportado: {}", digest))),
+    };
+    if actual != digest {
+        return Err(PolisError::Image(format!(
+            "Manifest recebido não confere com o digest {} (calculado {})",
+            digest, actual
+        )));
+    }
+    Ok(())
+}
+
+/// Erro de uma resposta do registry. O primeiro item do corpo
+/// `{"errors": [...]}` vira [`PolisError::Registry`]; sem corpo (como no
+/// `HEAD`), `401` e `403` ainda viram `UNAUTHORIZED` e `DENIED`, e os demais
+/// status um erro HTTP genérico sobre `action`.
+async fn registry_error(response: reqwest::Response, action: &str) -> PolisError {
+    let status = response.status();
+    let body = response.bytes().await.unwrap_or_default();
+    let error = serde_json::from_slice::<RegistryErrors>(&body)
+        .ok()
+        .and_then(|errors| errors.errors.into_iter().next());
+    let (code, message) = match (error, status) {
+        (Some(error), _) => (RegistryErrorCode::parse(&error.code), error.message),
+        (None, reqwest::StatusCode::UNAUTHORIZED) => {
+            (RegistryErrorCode::Unauthorized, "autenticação necessária".to_string())
+        }
+        (None, reqwest::StatusCode::FORBIDDEN) => (RegistryErrorCode::Denied, "acesso negado".to_string()),
+        (None, _) => return PolisError::Image(format!("Erro HTTP ao {}: {}", action, status)),
+    };
+    PolisError::Registry {
+        status: status.as_u16(),
+        code,
+        message: format!("ao {}: {}", action, message),
+    }
+}
+
+/// Esquema e parâmetros de um `WWW-Authenticate`, como
+/// `Bearer realm="...",service="...",scope="repository:x:pull,push"`
+fn parse_auth_challenge(header: &str) -> Option<(String, HashMap<String, String>)> {
+    let (scheme, rest) = header.trim().split_once(' ').unwrap_or((header.trim(), ""));
+    if scheme.is_empty() {
+        return None;
+    }
+
+    let mut params = HashMap::new();
+    let mut chars = rest.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| *c == ',' || c.is_whitespace()) {
+            chars.next();
+        }
+        let key: String = chars.by_ref().take_while(|c| *c != '=').collect();
+        if key.is_empty() {
+            break;
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            // Valores entre aspas podem conter vírgulas
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next()),
+                    '"' => break,
+                    c => value.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                value.push(c);
+            }
+        }
+        params.insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+    Some((scheme.to_string(), params))
+}
+
+/// Último byte que o registry diz ter recebido (`Range: 0-<último>`)
+fn upload_range_end(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get("Range")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|range| range.split_once('-'))
+        .and_then(|(_, end)| end.trim().parse().ok())
+}
+
+/// `Location` de uma sessão de upload; pode ser relativa ao registry
+fn upload_location(base_url: &str, response: &reqwest::Response) -> Result<Url> {
+    let location = response
+        .headers()
+        .get("Location")
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| PolisError::Image("Registry não informou a sessão de upload".to_string()))?;
+    Url::parse(base_url)
+        .and_then(|base| base.join(location))
+        .map_err(|e| PolisError::Image(format!("Location de upload inválida: {}", e)))
+}
+
+/// Próxima página de uma listagem: `Link: </v2/...?n=&last=>; rel="next"`
+fn next_page(base_url: &str, response: &reqwest::Response) -> Result<Option<Url>> {
+    let Some(link) = response
+        .headers()
+        .get_all("Link")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find(|link| link.contains("rel=\"next\""))
+    else {
+        return Ok(None);
+    };
+    let target = link
+        .split(';')
+        .next()
+        .map(|target| target.trim().trim_start_matches('<').trim_end_matches('>'))
+        .unwrap_or_default();
+    Url::parse(base_url)
+        .and_then(|base| base.join(target))
+        .map(Some)
+        .map_err(|e| PolisError::Image(format!("Link de paginação inválido: {}", e)))
+}
+
+/// Manifest com os nomes de campo da especificação, como o registry espera
+fn distribution_manifest(manifest: &OciManifest, media_type: &str) -> serde_json::Value {
+    let descriptor = |descriptor: &OciDescriptor| {
+        let mut value = serde_json::json!({
+            "mediaType": descriptor.media_type,
+            "size": descriptor.size,
+            "digest": descriptor.digest,
+        });
+        if let Some(urls) = &descriptor.urls {
+            value["urls"] = serde_json::json!(urls);
+        }
+        if let Some(annotations) = &descriptor.annotations {
+            value["annotations"] = serde_json::json!(annotations);
+        }
+        value
+    };
+    let mut value = serde_json::json!({
+        "schemaVersion": manifest.schema_version,
+        "mediaType": media_type,
+        "config": descriptor(&manifest.config),
+        "layers": manifest.layers.iter().map(descriptor).collect::<Vec<_>>(),
+    });
+    if let Some(annotations) = &manifest.annotations {
+        value["annotations"] = serde_json::json!(annotations);
+    }
+    value
+}
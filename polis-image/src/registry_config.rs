@@ -3,19 +3,86 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::fs;
 use polis_core::Result;
+use crate::{PlatformOverride, Reference};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegistryConfig {
     pub unqualified_search_registries: Vec<String>,
     pub registries: HashMap<String, RegistryEntry>,
+    /// Limite global de pulls simultâneos
+    #[serde(default)]
+    pub max_concurrent_pulls: Option<usize>,
+    /// Avisar quando a cota restante de um registry ficar abaixo deste valor
+    #[serde(default)]
+    pub quota_warning_threshold: Option<u64>,
+    /// Plataformas forçadas por padrão de repositório
+    #[serde(default)]
+    pub platform_overrides: Vec<PlatformOverride>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RegistryEntry {
     pub location: String,
     pub mirror: Option<String>,
     pub insecure: Option<bool>,
     pub blocked: Option<bool>,
+    /// Limite de pulls simultâneos neste registry
+    #[serde(default)]
+    pub max_concurrent_pulls: Option<usize>,
+    /// Ritmo máximo de pulls (token bucket)
+    #[serde(default)]
+    pub pulls_per_minute: Option<f64>,
+    /// Rajada permitida pelo token bucket
+    #[serde(default)]
+    pub burst: Option<u32>,
+    /// Registry de origem servido por este registry como cache (pull-through);
+    /// referências ao upstream passam a ser baixadas daqui
+    #[serde(default)]
+    pub upstream: Option<String>,
+}
+
+/// Referência de imagem resolvida, já considerando pull-through
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedReference {
+    /// Nome como informado pelo usuário, usado para tag e verificações de confiança
+    pub original: String,
+    /// Registry a que a imagem pertence
+    pub registry: String,
+    pub repository: String,
+    /// Tag pedida; `latest` quando a referência não tem tag
+    pub tag: String,
+    /// Digest que fixa o manifest, quando a referência tem um
+    pub digest: Option<String>,
+    /// Registry de onde a imagem é efetivamente baixada
+    pub pull_registry: String,
+}
+
+impl ResolvedReference {
+    pub fn is_pull_through(&self) -> bool {
+        self.pull_registry != self.registry
+    }
+
+    /// Como o manifest é pedido ao registry: pelo digest, se houver, senão pela tag
+    pub fn manifest_reference(&self) -> &str {
+        self.digest.as_deref().unwrap_or(&self.tag)
+    }
+
+    /// Nome canônico no registry de origem, ex.: `docker.io/library/nginx:latest`
+    pub fn canonical_name(&self) -> String {
+        format!("{}/{}{}", self.registry, self.repository, self.suffix())
+    }
+
+    /// Nome no registry de onde a imagem é baixada
+    pub fn pull_name(&self) -> String {
+        format!("{}/{}{}", self.pull_registry, self.repository, self.suffix())
+    }
+
+    fn suffix(&self) -> String {
+        match &self.digest {
+            Some(digest) => format!("@{}", digest),
+            None => format!(":{}", self.tag),
+        }
+    }
 }
 
 impl Default for RegistryConfig {
@@ -28,6 +95,7 @@ impl Default for RegistryConfig {
             mirror: Some("https://mirror.gcr.io".to_string()),
             insecure: Some(false),
             blocked: Some(false),
+            ..Default::default()
         });
         
         // Quay.io
@@ -36,6 +104,7 @@ impl Default for RegistryConfig {
             mirror: None,
             insecure: Some(false),
             blocked: Some(false),
+            ..Default::default()
         });
         
         // Red Hat Registry
@@ -44,6 +113,7 @@ impl Default for RegistryConfig {
             mirror: None,
             insecure: Some(false),
             blocked: Some(false),
+            ..Default::default()
         });
         
         // Google Container Registry
@@ -52,6 +122,7 @@ impl Default for RegistryConfig {
             mirror: None,
             insecure: Some(false),
             blocked: Some(false),
+            ..Default::default()
         });
         
         Self {
@@ -61,6 +132,9 @@ impl Default for RegistryConfig {
                 "registry.redhat.io".to_string(),
             ],
             registries,
+            max_concurrent_pulls: None,
+            quota_warning_threshold: None,
+            platform_overrides: Vec::new(),
         }
     }
 }
@@ -154,4 +228,44 @@ impl RegistryConfig {
     pub fn get_search_registries(&self) -> &Vec<String> {
         &self.unqualified_search_registries
     }
+
+    /// Registry configurado como cache pull-through do `upstream`
+    pub fn pull_through_for(&self, upstream: &str) -> Option<&str> {
+        let mut caches: Vec<&String> = self.registries.iter()
+            .filter(|(_, entry)| entry.upstream.as_deref() == Some(upstream) && !entry.blocked.unwrap_or(false))
+            .map(|(name, _)| name)
+            .collect();
+        // Ordem determinística caso mais de um cache aponte para o mesmo upstream
+        caches.sort();
+        caches.first().map(|name| name.as_str())
+    }
+
+    /// Resolve uma referência, redirecionando para o cache pull-through
+    /// quando houver um configurado para o registry da imagem
+    pub fn resolve_reference(&self, image_name: &str) -> Result<ResolvedReference> {
+        let reference = Reference::parse(image_name)?;
+        let pull_registry = self.pull_through_for(&reference.registry)
+            .map(str::to_string)
+            .unwrap_or_else(|| reference.registry.clone());
+        Ok(ResolvedReference {
+            original: image_name.to_string(),
+            tag: reference.tag_or_default().to_string(),
+            registry: reference.registry,
+            repository: reference.repository,
+            digest: reference.digest,
+            pull_registry,
+        })
+    }
+
+    /// Override de plataforma mais específico que casa com o repositório;
+    /// no empate vence o que aparece primeiro no arquivo
+    pub fn platform_override(&self, registry: &str, repository: &str) -> Option<&PlatformOverride> {
+        let mut best: Option<&PlatformOverride> = None;
+        for candidate in self.platform_overrides.iter().filter(|o| o.matches(registry, repository)) {
+            if best.is_none_or(|current| candidate.specificity() > current.specificity()) {
+                best = Some(candidate);
+            }
+        }
+        best
+    }
 }
@@ -1,6 +1,13 @@
+use futures::future::{join_all, BoxFuture};
 use polis_core::{ImageId, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+/// Time each registry has to answer a search
+pub const DEFAULT_SEARCH_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Image search result
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +33,8 @@ pub struct SearchOptions {
     pub automated_only: bool,
     pub min_stars: Option<u32>,
     pub registry: Option<String>,
+    /// Query only these registries instead of the configured ones
+    pub registries: Option<Vec<String>>,
 }
 
 impl Default for SearchOptions {
@@ -37,15 +46,28 @@ impl Default for SearchOptions {
             automated_only: false,
             min_stars: None,
             registry: None,
+            registries: None,
         }
     }
 }
 
+/// Answers searches for a single registry
+pub trait RegistrySearch: std::fmt::Debug + Send + Sync {
+    fn search<'a>(
+        &'a self,
+        registry: &'a str,
+        query: &'a str,
+        options: &'a SearchOptions,
+    ) -> BoxFuture<'a, Result<Vec<ImageSearchResult>>>;
+}
+
 /// Image search manager
 #[derive(Debug)]
 pub struct ImageSearchManager {
     pub registries: Vec<String>,
     pub cache: HashMap<String, Vec<ImageSearchResult>>,
+    backend: Arc<dyn RegistrySearch>,
+    timeout: Duration,
 }
 
 impl ImageSearchManager {
@@ -54,10 +76,26 @@ impl ImageSearchManager {
         Self {
             registries,
             cache: HashMap::new(),
+            backend: Arc::new(SimulatedRegistrySearch),
+            timeout: DEFAULT_SEARCH_TIMEOUT,
         }
     }
 
-    /// Search for images across registries
+    /// Answer searches with another backend (e.g. a mock in tests)
+    pub fn with_backend(mut self, backend: Arc<dyn RegistrySearch>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Give each registry `timeout` to answer
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Search for images across registries. Registries are queried
+    /// concurrently; one that times out or fails is skipped with a warning
+    /// and only fails the search when no registry answered.
     pub async fn search_images(&mut self, query: &str, options: SearchOptions) -> Result<Vec<ImageSearchResult>> {
         let cache_key = format!("{}:{:?}", query, options);
         
@@ -66,20 +104,57 @@ impl ImageSearchManager {
             return Ok(cached_results.clone());
         }
 
-        let mut all_results = Vec::new();
+        let registries = options.registries.as_ref().unwrap_or(&self.registries);
+        let (backend, timeout, search_options) = (&self.backend, self.timeout, &options);
+        let searches = registries.iter().map(|registry| async move {
+            let outcome = tokio::time::timeout(
+                timeout,
+                backend.search(registry, query, search_options),
+            )
+            .await;
+            (registry, outcome)
+        });
 
-        // Search in each registry
-        for registry in &self.registries {
-            let registry_results = self.search_in_registry(registry, query, &options).await?;
-            all_results.extend(registry_results);
+        let mut all_results = Vec::new();
+        let mut answered = registries.is_empty();
+        let mut first_error = None;
+        for (registry, outcome) in join_all(searches).await {
+            match outcome {
+                Ok(Ok(results)) => {
+                    answered = true;
+                    all_results.extend(results);
+                }
+                Ok(Err(e)) => {
+                    warn!("Search in registry {} failed: {}", registry, e);
+                    first_error.get_or_insert(e);
+                }
+                Err(_) => warn!(
+                    "Search in registry {} timed out after {:?}",
+                    registry, self.timeout
+                ),
+            }
         }
+        if !answered {
+            if let Some(e) = first_error {
+                return Err(e);
+            }
+        }
+
+        // Keep one result per image of each registry
+        let mut seen = HashSet::new();
+        all_results.retain(|result| seen.insert((result.registry.clone(), result.name.clone())));
 
         // Apply filters
         let filtered_results = self.apply_filters(all_results, &options);
 
         // Sort by stars (descending)
         let mut sorted_results = filtered_results;
-        sorted_results.sort_by(|a, b| b.stars.cmp(&a.stars));
+        sorted_results.sort_by(|a, b| {
+            b.stars
+                .cmp(&a.stars)
+                .then_with(|| a.registry.cmp(&b.registry))
+                .then_with(|| a.name.cmp(&b.name))
+        });
 
         // Apply limit
         let final_results = if let Some(limit) = options.limit {
@@ -94,84 +169,6 @@ impl ImageSearchManager {
         Ok(final_results)
     }
 
-    /// Search for images in a specific registry
-    async fn search_in_registry(
-        &self,
-        registry: &str,
-        query: &str,
-        options: &SearchOptions,
-    ) -> Result<Vec<ImageSearchResult>> {
-        // For now, we'll simulate search results
-        // In a real implementation, this would make HTTP requests to registry APIs
-        
-        let mut results = Vec::new();
-
-        // Simulate some common images
-        let simulated_images = vec![
-            ("nginx", "High performance web server", 50000, true, true, true),
-            ("redis", "In-memory data structure store", 30000, true, true, true),
-            ("postgres", "Object-relational database system", 25000, true, true, true),
-            ("mysql", "Popular open source database", 20000, true, true, true),
-            ("node", "JavaScript runtime built on Chrome's V8", 15000, true, true, true),
-            ("python", "Python programming language", 12000, true, true, true),
-            ("alpine", "Minimal Docker image based on Alpine Linux", 8000, true, true, true),
-            ("ubuntu", "Ubuntu base image", 6000, true, true, true),
-            ("centos", "CentOS base image", 4000, true, true, true),
-            ("debian", "Debian base image", 3000, true, true, true),
-        ];
-
-        for (name, description, stars, official, trusted, automated) in simulated_images {
-            if name.to_lowercase().contains(&query.to_lowercase()) {
-                let result = ImageSearchResult {
-                    name: format!("{}/{}", registry, name),
-                    description: Some(description.to_string()),
-                    stars,
-                    official,
-                    trusted,
-                    automated,
-                    registry: registry.to_string(),
-                    tags: vec!["latest".to_string(), "alpine".to_string(), "3.18".to_string()],
-                    size: Some(rand::random::<u64>() % 100_000_000), // Random size up to 100MB
-                    last_updated: Some(chrono::Utc::now() - chrono::Duration::days((rand::random::<u64>() % 30) as i64)),
-                };
-
-                results.push(result);
-            }
-        }
-
-        // Add some community images
-        if !options.official_only {
-            let community_images = vec![
-                ("wordpress", "WordPress content management system", 5000, false, false, false),
-                ("mongo", "MongoDB document database", 3000, false, false, false),
-                ("elasticsearch", "Distributed search and analytics engine", 2000, false, false, false),
-                ("grafana", "Analytics and monitoring platform", 1500, false, false, false),
-                ("prometheus", "Monitoring system and time series database", 1000, false, false, false),
-            ];
-
-            for (name, description, stars, official, trusted, automated) in community_images {
-                if name.to_lowercase().contains(&query.to_lowercase()) {
-                    let result = ImageSearchResult {
-                        name: format!("{}/{}", registry, name),
-                        description: Some(description.to_string()),
-                        stars,
-                        official,
-                        trusted,
-                        automated,
-                        registry: registry.to_string(),
-                        tags: vec!["latest".to_string(), "stable".to_string()],
-                        size: Some(rand::random::<u64>() % 200_000_000), // Random size up to 200MB
-                        last_updated: Some(chrono::Utc::now() - chrono::Duration::days((rand::random::<u64>() % 60) as i64)),
-                    };
-
-                    results.push(result);
-                }
-            }
-        }
-
-        Ok(results)
-    }
-
     /// Apply search filters
     fn apply_filters(&self, mut results: Vec<ImageSearchResult>, options: &SearchOptions) -> Vec<ImageSearchResult> {
         results.retain(|result| {
@@ -249,3 +246,89 @@ pub struct SearchCacheStats {
     pub total_queries: usize,
     pub total_results: usize,
 }
+
+/// Built-in catalog of well-known images, answered the same for every
+/// registry
+#[derive(Debug, Default)]
+pub struct SimulatedRegistrySearch;
+
+impl RegistrySearch for SimulatedRegistrySearch {
+    fn search<'a>(
+        &'a self,
+        registry: &'a str,
+        query: &'a str,
+        options: &'a SearchOptions,
+    ) -> BoxFuture<'a, Result<Vec<ImageSearchResult>>> {
+        Box::pin(async move {
+            // For now, we'll simulate search results
+            // In a real implementation, this would make HTTP requests to registry APIs
+
+            let mut results = Vec::new();
+
+            // Simulate some common images
+            let simulated_images = vec![
+                ("nginx", "High performance web server", 50000, true, true, true),
+                ("redis", "In-memory data structure store", 30000, true, true, true),
+                ("postgres", "Object-relational database system", 25000, true, true, true),
+                ("mysql", "Popular open source database", 20000, true, true, true),
+                ("node", "JavaScript runtime built on Chrome's V8", 15000, true, true, true),
+                ("python", "Python programming language", 12000, true, true, true),
+                ("alpine", "Minimal Docker image based on Alpine Linux", 8000, true, true, true),
+                ("ubuntu", "Ubuntu base image", 6000, true, true, true),
+                ("centos", "CentOS base image", 4000, true, true, true),
+                ("debian", "Debian base image", 3000, true, true, true),
+            ];
+
+            for (name, description, stars, official, trusted, automated) in simulated_images {
+                if name.to_lowercase().contains(&query.to_lowercase()) {
+                    let result = ImageSearchResult {
+                        name: format!("{}/{}", registry, name),
+                        description: Some(description.to_string()),
+                        stars,
+                        official,
+                        trusted,
+                        automated,
+                        registry: registry.to_string(),
+                        tags: vec!["latest".to_string(), "alpine".to_string(), "3.18".to_string()],
+                        size: Some(rand::random::<u64>() % 100_000_000), // Random size up to 100MB
+                        last_updated: Some(chrono::Utc::now() - chrono::Duration::days((rand::random::<u64>() % 30) as i64)),
+                    };
+
+                    results.push(result);
+                }
+            }
+
+            // Add some community images
+            if !options.official_only {
+                let community_images = vec![
+                    ("wordpress", "WordPress content management system", 5000, false, false, false),
+                    ("mongo", "MongoDB document database", 3000, false, false, false),
+                    ("elasticsearch", "Distributed search and analytics engine", 2000, false, false, false),
+                    ("grafana", "Analytics and monitoring platform", 1500, false, false, false),
+                    ("prometheus", "Monitoring system and time series database", 1000, false, false, false),
+                ];
+
+                for (name, description, stars, official, trusted, automated) in community_images {
+                    if name.to_lowercase().contains(&query.to_lowercase()) {
+                        let result = ImageSearchResult {
+                            name: format!("{}/{}", registry, name),
+                            description: Some(description.to_string()),
+                            stars,
+                            official,
+                            trusted,
+                            automated,
+                            registry: registry.to_string(),
+                            tags: vec!["latest".to_string(), "stable".to_string()],
+                            size: Some(rand::random::<u64>() % 200_000_000), // Random size up to 200MB
+                            last_updated: Some(chrono::Utc::now() - chrono::Duration::days((rand::random::<u64>() % 60) as i64)),
+                        };
+
+                        results.push(result);
+                    }
+                }
+            }
+
+            Ok(results)
+        })
+    }
+}
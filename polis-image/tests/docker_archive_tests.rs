@@ -0,0 +1,136 @@
+use polis_image::ImageManager;
+use polis_test_support::{sha256_digest, FileTree, Layer};
+use std::path::Path;
+
+/// Write a `docker save` style archive: `manifest.json`, the config as
+/// `<hex>.json` and each layer as `<id>/layer.tar`
+fn docker_save(path: &Path, repo_tags: &[&str], layers: &[&Layer], diff_ids: &[String]) {
+    let config = serde_json::json!({
+        "architecture": "amd64",
+        "os": "linux",
+        "config": {
+            "Env": ["PATH=/usr/bin:/bin", "GREETING=hello"],
+            "Cmd": ["/hello"],
+            "WorkingDir": "/srv",
+            "ExposedPorts": { "8080/tcp": {} },
+            "Labels": { "maintainer": "polis" },
+        },
+        "rootfs": { "type": "layers", "diff_ids": diff_ids },
+    })
+    .to_string();
+    let config_name = format!(
+        "{}.json",
+        sha256_digest(config.as_bytes()).trim_start_matches("sha256:")
+    );
+
+    let mut files = vec![(config_name.clone(), config.into_bytes())];
+    let mut layer_paths = Vec::new();
+    for (i, layer) in layers.iter().enumerate() {
+        let layer_path = format!("layer{}/layer.tar", i);
+        files.push((layer_path.clone(), layer.tar.clone()));
+        layer_paths.push(layer_path);
+    }
+    let manifest = serde_json::json!([{
+        "Config": config_name,
+        "RepoTags": repo_tags,
+        "Layers": layer_paths,
+    }]);
+    files.push((
+        "manifest.json".to_string(),
+        manifest.to_string().into_bytes(),
+    ));
+
+    let mut builder = tar::Builder::new(std::fs::File::create(path).unwrap());
+    for (name, content) in files {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, content.as_slice())
+            .unwrap();
+    }
+    builder.finish().unwrap();
+}
+
+#[tokio::test]
+async fn test_import_docker_save_archive() {
+    let base = FileTree::new().file("etc/os-release", "ID=polis\n").layer();
+    let app = FileTree::new().executable("hello", "#!/bin/sh\n").layer();
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("hello.tar");
+    docker_save(
+        &archive,
+        &["hello:1.0", "hello:latest"],
+        &[&base, &app],
+        &[base.diff_id.clone(), app.diff_id.clone()],
+    );
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let manager = ImageManager::new(cache_dir.path().to_path_buf());
+    let id = manager.import_docker_tar(&archive).await.unwrap();
+    assert_eq!(id.0, "hello:1.0");
+
+    let images = manager.list_images().await.unwrap();
+    assert_eq!(images.len(), 1);
+    let image = &images[0];
+    assert_eq!((image.name.as_str(), image.tag.as_str()), ("hello", "1.0"));
+    assert!(image.digest.starts_with("sha256:"));
+
+    // Uncompressed layers are stored under their diff_id
+    assert_eq!(image.layers, [base.diff_id.clone(), app.diff_id.clone()]);
+    for layer in &image.layers {
+        assert_eq!(manager.layer_store().ref_count(layer), 1);
+    }
+
+    // The Docker config was converted
+    assert_eq!(image.architecture, "amd64");
+    assert_eq!(image.config.cmd, Some(vec!["/hello".to_string()]));
+    assert_eq!(image.config.working_dir.as_deref(), Some("/srv"));
+    assert!(image
+        .config
+        .env
+        .as_ref()
+        .unwrap()
+        .contains(&"GREETING=hello".to_string()));
+    assert!(image
+        .config
+        .exposed_ports
+        .as_ref()
+        .unwrap()
+        .contains_key("8080/tcp"));
+    assert_eq!(image.config.labels.as_ref().unwrap()["maintainer"], "polis");
+}
+
+#[tokio::test]
+async fn test_import_docker_archive_checks_diff_ids() {
+    let base = FileTree::new().file("etc/os-release", "ID=polis\n").layer();
+    let other = FileTree::new().file("other", "x").layer();
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("broken.tar");
+    docker_save(
+        &archive,
+        &["broken:1.0"],
+        &[&base],
+        std::slice::from_ref(&other.diff_id),
+    );
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let manager = ImageManager::new(cache_dir.path().to_path_buf());
+    assert!(manager.import_docker_tar(&archive).await.is_err());
+    assert!(manager.list_images().await.unwrap().is_empty());
+    assert!(manager.layer_store().layers().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_import_untagged_docker_archive_uses_file_name() {
+    let base = FileTree::new().file("etc/os-release", "ID=polis\n").layer();
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("snapshot.tar");
+    docker_save(&archive, &[], &[&base], std::slice::from_ref(&base.diff_id));
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let manager = ImageManager::new(cache_dir.path().to_path_buf());
+    let id = manager.import_docker_tar(&archive).await.unwrap();
+    assert_eq!(id.0, "snapshot:latest");
+}
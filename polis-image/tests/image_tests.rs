@@ -1,14 +1,73 @@
 use polis_core::{Image, ImageConfig, ImageId};
 use polis_image::{
     ImageManager, OciConfig, OciDescriptor, OciImageConfig, OciManifest, OciRootFs, RegistryClient,
+    RegistryConfig, RegistryEntry,
 };
+use polis_test_support::{sha256_digest, FileTree, Layer, MockRegistry};
 use std::collections::HashMap;
-use std::path::PathBuf;
+
+fn base_layer() -> Layer {
+    FileTree::new()
+        .file("etc/os-release", "ID=polis\n")
+        .executable("bin/sh", "#!/bin/true\n")
+        .layer()
+}
+
+fn config_json(layers: &[&Layer]) -> String {
+    serde_json::json!({
+        "architecture": "amd64",
+        "os": "linux",
+        "config": {
+            "env": ["PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin"],
+            "working_dir": "/",
+            "exposed_ports": {"80/tcp": {}},
+            "volumes": {"/data": {}},
+            "labels": {"version": "1.0"},
+            "user": "root",
+            "entrypoint": ["/bin/sh"],
+            "cmd": ["-c"]
+        },
+        "rootfs": {
+            "type": "layers",
+            "diff_ids": layers.iter().map(|layer| layer.diff_id.clone()).collect::<Vec<_>>(),
+        }
+    })
+    .to_string()
+}
+
+fn manifest_json(config: &str, layers: &[&Layer]) -> String {
+    serde_json::json!({
+        "schema_version": 2,
+        "media_type": "application/vnd.oci.image.manifest.v1+json",
+        "config": {
+            "media_type": "application/vnd.oci.image.config.v1+json",
+            "size": config.len(),
+            "digest": sha256_digest(config.as_bytes()),
+        },
+        "layers": layers.iter().map(|layer| layer.descriptor()).collect::<Vec<_>>(),
+    })
+    .to_string()
+}
+
+fn registry_config(registry: &MockRegistry) -> RegistryConfig {
+    let mut registries = HashMap::new();
+    registries.insert(
+        registry.host(),
+        RegistryEntry {
+            location: format!("http://{}", registry.host()),
+            ..Default::default()
+        },
+    );
+    RegistryConfig {
+        registries,
+        ..RegistryConfig::default()
+    }
+}
 
 #[tokio::test]
 async fn test_image_manager_creation() {
-    let cache_dir = PathBuf::from("/tmp/polis-test-images");
-    let image_manager = ImageManager::new(cache_dir);
+    let cache_dir = tempfile::tempdir().unwrap();
+    let image_manager = ImageManager::new(cache_dir.path().to_path_buf());
 
     // Test that image manager was created successfully
     assert!(image_manager.list_images().await.is_ok());
@@ -16,8 +75,8 @@ async fn test_image_manager_creation() {
 
 #[tokio::test]
 async fn test_image_listing() {
-    let cache_dir = PathBuf::from("/tmp/polis-test-list");
-    let image_manager = ImageManager::new(cache_dir);
+    let cache_dir = tempfile::tempdir().unwrap();
+    let image_manager = ImageManager::new(cache_dir.path().to_path_buf());
 
     // Test listing images (should be empty initially)
     let images = image_manager.list_images().await.unwrap();
@@ -26,8 +85,8 @@ async fn test_image_listing() {
 
 #[tokio::test]
 async fn test_image_removal() {
-    let cache_dir = PathBuf::from("/tmp/polis-test-remove");
-    let image_manager = ImageManager::new(cache_dir);
+    let cache_dir = tempfile::tempdir().unwrap();
+    let image_manager = ImageManager::new(cache_dir.path().to_path_buf());
 
     // Test removing non-existent image
     let fake_id = ImageId::from_string("nonexistent:latest");
@@ -37,35 +96,28 @@ async fn test_image_removal() {
 
 #[tokio::test]
 async fn test_registry_client_creation() {
-    let cache_dir = std::path::PathBuf::from("/tmp/polis-test-cache");
-    let _registry_client = RegistryClient::new(cache_dir);
+    let registry = MockRegistry::start().await;
+    let layer = base_layer();
+    registry.add_blob("library/alpine", &layer.digest, layer.compressed.clone());
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let registry_client = RegistryClient::new(cache_dir.path().to_path_buf())
+        .with_base_url(registry.base_url())
+        .with_config(RegistryConfig::default());
 
-    // Test that registry client was created successfully
-    // Test registry client initialization
+    // The client talks to the registry it was created for
+    registry_client
+        .download_layer("library/alpine", &layer.digest, None)
+        .await
+        .unwrap();
+    assert!(registry_client.layer_store().contains(&layer.digest));
 }
 
 #[tokio::test]
 async fn test_oci_manifest_parsing() {
-    // Test creating OciManifest directly instead of parsing JSON
-    let manifest = OciManifest {
-        schema_version: 2,
-        media_type: "application/vnd.oci.image.manifest.v1+json".to_string(),
-        config: OciDescriptor {
-            media_type: "application/vnd.oci.image.config.v1+json".to_string(),
-            size: 1234,
-            digest: "sha256:abc123".to_string(),
-            urls: None,
-            annotations: None,
-        },
-        layers: vec![OciDescriptor {
-            media_type: "application/vnd.oci.image.layer.v1.tar+gzip".to_string(),
-            size: 5678,
-            digest: "sha256:def456".to_string(),
-            urls: None,
-            annotations: None,
-        }],
-        annotations: None,
-    };
+    let layer = base_layer();
+    let config = config_json(&[&layer]);
+    let manifest: OciManifest = serde_json::from_str(&manifest_json(&config, &[&layer])).unwrap();
 
     assert_eq!(manifest.schema_version, 2);
     assert_eq!(
@@ -76,39 +128,24 @@ async fn test_oci_manifest_parsing() {
         manifest.config.media_type,
         "application/vnd.oci.image.config.v1+json"
     );
-    assert_eq!(manifest.config.size, 1234);
-    assert_eq!(manifest.config.digest, "sha256:abc123");
+    assert_eq!(manifest.config.size, config.len() as u64);
+    assert_eq!(manifest.config.digest, sha256_digest(config.as_bytes()));
     assert_eq!(manifest.layers.len(), 1);
     assert_eq!(
         manifest.layers[0].media_type,
         "application/vnd.oci.image.layer.v1.tar+gzip"
     );
-    assert_eq!(manifest.layers[0].size, 5678);
-    assert_eq!(manifest.layers[0].digest, "sha256:def456");
+    assert_eq!(manifest.layers[0].size, layer.size());
+    assert_eq!(manifest.layers[0].digest, layer.digest);
 }
 
 #[tokio::test]
 async fn test_oci_config_parsing() {
-    let config_json = r#"{
-        "architecture": "amd64",
-        "os": "linux",
-        "config": {
-            "env": ["PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin"],
-            "working_dir": "/",
-            "exposed_ports": {"80/tcp": {}},
-            "volumes": {"/data": {}},
-            "labels": {"version": "1.0"},
-            "user": "root",
-            "entrypoint": ["/bin/sh"],
-            "cmd": ["-c"]
-        },
-        "rootfs": {
-            "type": "layers",
-            "diff_ids": ["sha256:layer1", "sha256:layer2"]
-        }
-    }"#;
-
-    let config: OciConfig = serde_json::from_str(config_json).unwrap();
+    let base = base_layer();
+    let app = FileTree::new()
+        .file("srv/index.html", "<h1>hello</h1>\n")
+        .layer();
+    let config: OciConfig = serde_json::from_str(&config_json(&[&base, &app])).unwrap();
 
     assert_eq!(config.architecture, "amd64");
     assert_eq!(config.os, "linux");
@@ -118,26 +155,30 @@ async fn test_oci_config_parsing() {
     );
     assert_eq!(config.config.working_dir.unwrap(), "/");
     assert_eq!(config.rootfs.diff_ids.len(), 2);
-    assert_eq!(config.rootfs.diff_ids[0], "sha256:layer1");
-    assert_eq!(config.rootfs.diff_ids[1], "sha256:layer2");
+    assert_eq!(config.rootfs.diff_ids[0], base.diff_id);
+    assert_eq!(config.rootfs.diff_ids[1], app.diff_id);
 }
 
 #[tokio::test]
 async fn test_image_conversion() {
+    let layer = base_layer();
+    let config = config_json(&[&layer]);
+    let config_digest = sha256_digest(config.as_bytes());
+
     let _oci_manifest = OciManifest {
         schema_version: 2,
         media_type: "application/vnd.oci.image.manifest.v1+json".to_string(),
         config: OciDescriptor {
             media_type: "application/vnd.oci.image.config.v1+json".to_string(),
-            size: 1234,
-            digest: "sha256:abc123".to_string(),
+            size: config.len() as u64,
+            digest: config_digest.clone(),
             annotations: Some(std::collections::HashMap::new()),
             urls: Some(Vec::new()),
         },
         layers: vec![OciDescriptor {
             media_type: "application/vnd.oci.image.layer.v1.tar+gzip".to_string(),
-            size: 5678,
-            digest: "sha256:def456".to_string(),
+            size: layer.size(),
+            digest: layer.digest.clone(),
             annotations: Some(std::collections::HashMap::new()),
             urls: Some(Vec::new()),
         }],
@@ -158,7 +199,7 @@ async fn test_image_conversion() {
             user: Some("root".to_string()),
         },
         rootfs: OciRootFs {
-            diff_ids: vec!["sha256:layer1".to_string()],
+            diff_ids: vec![layer.diff_id.clone()],
             r#type: "layers".to_string(),
         },
     };
@@ -168,12 +209,12 @@ async fn test_image_conversion() {
         id: ImageId::from_string("alpine:latest"),
         name: "alpine".to_string(),
         tag: "latest".to_string(),
-        digest: "sha256:abc123".to_string(),
-        size: 1234,
+        digest: config_digest,
+        size: layer.size(),
         created_at: chrono::Utc::now(),
         architecture: "amd64".to_string(),
         os: "linux".to_string(),
-        layers: vec!["sha256:layer1".to_string()],
+        layers: vec![layer.digest.clone()],
         config: ImageConfig {
             entrypoint: Some(vec!["/bin/sh".to_string()]),
             cmd: Some(vec!["-c".to_string()]),
@@ -190,21 +231,23 @@ async fn test_image_conversion() {
     assert_eq!(image.architecture, "amd64");
     assert_eq!(image.os, "linux");
     assert_eq!(image.layers.len(), 1);
-    assert_eq!(image.layers[0], "sha256:layer1");
+    assert_eq!(image.layers[0], layer.digest);
 }
 
 #[tokio::test]
 async fn test_image_serialization() {
+    let base = base_layer();
+    let app = FileTree::new().file("srv/app", "v1").layer();
     let image = Image {
         id: ImageId::from_string("test:latest"),
         name: "test".to_string(),
         tag: "latest".to_string(),
-        digest: "sha256:test123".to_string(),
-        size: 1024 * 1024, // 1MB
+        digest: sha256_digest(b"test"),
+        size: base.size() + app.size(),
         created_at: chrono::Utc::now(),
         architecture: "amd64".to_string(),
         os: "linux".to_string(),
-        layers: vec!["layer1".to_string(), "layer2".to_string()],
+        layers: vec![base.digest.clone(), app.digest.clone()],
         config: ImageConfig {
             entrypoint: Some(vec!["/bin/sh".to_string()]),
             cmd: Some(vec!["-c".to_string()]),
@@ -222,31 +265,61 @@ async fn test_image_serialization() {
     assert_eq!(image.name, parsed_image.name);
     assert_eq!(image.tag, parsed_image.tag);
     assert_eq!(image.architecture, parsed_image.architecture);
-
-    // Test YAML serialization (commented out due to missing dependency)
-    // let yaml = serde_yaml::to_string(&image).unwrap();
-    // let parsed_image: Image = serde_yaml::from_str(&yaml).unwrap();
-    // assert_eq!(image.name, parsed_image.name);
-    // assert_eq!(image.tag, parsed_image.tag);
-    // assert_eq!(image.architecture, parsed_image.architecture);
+    assert_eq!(image.layers, parsed_image.layers);
 }
 
 #[tokio::test]
 async fn test_registry_operations() {
-    let cache_dir = std::path::PathBuf::from("/tmp/polis-test-cache");
-    let _registry_client = RegistryClient::new(cache_dir);
+    let registry = MockRegistry::start().await;
+    let layer = base_layer();
+    let config = config_json(&[&layer]);
+    registry.add_blob(
+        "library/alpine",
+        &sha256_digest(config.as_bytes()),
+        config.clone().into_bytes(),
+    );
+    registry.add_blob("library/alpine", &layer.digest, layer.compressed.clone());
+    registry.add_manifest("library/alpine", "3.19", &manifest_json(&config, &[&layer]));
+    registry.add_manifest(
+        "library/alpine",
+        "latest",
+        &manifest_json(&config, &[&layer]),
+    );
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let mut registry_client = RegistryClient::new(cache_dir.path().to_path_buf())
+        .with_base_url(registry.base_url())
+        .with_config(registry_config(&registry));
+
+    let manifest = registry_client
+        .fetch_manifest("library/alpine", "3.19")
+        .await
+        .unwrap();
+    assert_eq!(manifest.layers[0].digest, layer.digest);
 
-    // Test registry client operations (simplified)
-    // These methods don't exist yet, so we'll test basic functionality
+    let mut tags = registry_client
+        .list_tags(&format!("{}/library/alpine", registry.host()))
+        .await
+        .unwrap();
+    tags.sort();
+    assert_eq!(tags, vec!["3.19", "latest"]);
 }
 
 #[tokio::test]
 async fn test_error_handling() {
-    let cache_dir = PathBuf::from("/tmp/polis-test-errors");
-    let image_manager = ImageManager::new(cache_dir);
+    let registry = MockRegistry::start().await;
+    let cache_dir = tempfile::tempdir().unwrap();
+    let image_manager = ImageManager::new(cache_dir.path().to_path_buf())
+        .with_registry_config(registry_config(&registry));
 
-    // Test pulling non-existent image
-    let result = image_manager.pull("nonexistent:latest").await;
+    // Test pulling non-existent image; a digest pull has no local fallback
+    let result = image_manager
+        .pull(&format!(
+            "{}/nonexistent@{}",
+            registry.host(),
+            sha256_digest(b"nonexistent")
+        ))
+        .await;
     assert!(result.is_err());
 
     // Test removing non-existent image
@@ -257,7 +330,8 @@ async fn test_error_handling() {
 
 #[tokio::test]
 async fn test_concurrent_operations() {
-    let cache_dir = PathBuf::from("/tmp/polis-test-concurrent");
+    let cache_dir = tempfile::tempdir().unwrap();
+    let cache_dir = cache_dir.path().to_path_buf();
     let _image_manager = ImageManager::new(cache_dir.clone());
 
     // Test concurrent listing operations
@@ -0,0 +1,128 @@
+use polis_core::ImageId;
+use polis_image::{ImageManager, LayerDiff, RegistryConfig, RegistryEntry};
+use polis_test_support::{sha256_digest, FileTree, Layer, MockRegistry};
+use std::collections::HashMap;
+
+fn old_layer() -> FileTree {
+    FileTree::new()
+        .dir("etc")
+        .file("etc/os-release", "ID=polis\n")
+        .file("etc/app.conf", "workers=2\n")
+        .file("etc/passwd", "root:x:0:0::/root:/bin/sh\n")
+        .executable("usr/bin/tool", "#!/bin/sh\n")
+        .symlink("usr/bin/sh", "busybox")
+}
+
+fn new_layer() -> FileTree {
+    FileTree::new()
+        .dir("etc")
+        .file("etc/os-release", "ID=polis\n")
+        .file("etc/app.conf", "workers=16\nthreads=4\n")
+        .file_with_mode("etc/passwd", "root:x:0:0::/root:/bin/sh\n", 0o600)
+        .symlink("usr/bin/sh", "dash")
+        .file("srv/index.html", "<h1>hello</h1>\n")
+}
+
+#[test]
+fn test_compute_categorizes_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = dir.path().join("old.tar");
+    let new = dir.path().join("new.tar.gz");
+    std::fs::write(&old, old_layer().layer().tar).unwrap();
+    // Compressed and uncompressed layers can be compared
+    std::fs::write(&new, new_layer().layer().compressed).unwrap();
+
+    let diff = LayerDiff::compute(&old, &new).unwrap();
+    assert_eq!(diff.added, ["srv/index.html"]);
+    assert_eq!(diff.removed, ["usr/bin/tool"]);
+    assert_eq!(diff.modified, ["etc/app.conf", "etc/passwd", "usr/bin/sh"]);
+
+    // The contents grew by 11 bytes; mode and link changes keep the size
+    assert_eq!(diff.entry_size_delta["etc/app.conf"], 11);
+    assert_eq!(diff.entry_size_delta["etc/passwd"], 0);
+    assert_eq!(diff.entry_size_delta["usr/bin/sh"], 0);
+    assert!(!diff.entry_size_delta.contains_key("etc/os-release"));
+
+    let reverse = LayerDiff::compute(&new, &old).unwrap();
+    assert_eq!(reverse.added, diff.removed);
+    assert_eq!(reverse.removed, diff.added);
+    assert_eq!(reverse.entry_size_delta["etc/app.conf"], -11);
+
+    assert!(LayerDiff::compute(&old, &old).unwrap().is_empty());
+}
+
+#[test]
+fn test_compute_rejects_missing_layer() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = dir.path().join("old.tar");
+    std::fs::write(&old, old_layer().layer().tar).unwrap();
+    assert!(LayerDiff::compute(&old, &dir.path().join("missing.tar")).is_err());
+}
+
+fn publish(registry: &MockRegistry, repo: &str, tag: &str, layers: &[&Layer]) {
+    let config = serde_json::json!({
+        "architecture": "amd64",
+        "os": "linux",
+        "config": {},
+        "rootfs": {
+            "type": "layers",
+            "diff_ids": layers.iter().map(|layer| layer.diff_id.clone()).collect::<Vec<_>>(),
+        },
+    })
+    .to_string();
+    let config_digest = sha256_digest(config.as_bytes());
+    registry.add_blob(repo, &config_digest, config.clone().into_bytes());
+    for layer in layers {
+        registry.add_blob(repo, &layer.digest, layer.compressed.clone());
+    }
+
+    let manifest = serde_json::json!({
+        "schema_version": 2,
+        "media_type": "application/vnd.oci.image.manifest.v1+json",
+        "config": {
+            "media_type": "application/vnd.oci.image.config.v1+json",
+            "size": config.len(),
+            "digest": config_digest,
+        },
+        "layers": layers.iter().map(|layer| layer.descriptor()).collect::<Vec<_>>(),
+    });
+    registry.add_manifest(repo, tag, &manifest.to_string());
+}
+
+#[tokio::test]
+async fn test_diff_layers_of_cached_image() {
+    let registry = MockRegistry::start().await;
+    let base = old_layer().layer();
+    let top = new_layer().layer();
+    publish(&registry, "myorg/app", "1.0", &[&base, &top]);
+
+    let mut registries = HashMap::new();
+    registries.insert(
+        registry.host(),
+        RegistryEntry {
+            location: format!("http://{}", registry.host()),
+            ..Default::default()
+        },
+    );
+    let cache_dir = tempfile::tempdir().unwrap();
+    let manager =
+        ImageManager::new(cache_dir.path().to_path_buf()).with_registry_config(RegistryConfig {
+            registries,
+            ..RegistryConfig::default()
+        });
+    let image = manager
+        .pull(&format!("{}/myorg/app:1.0", registry.host()))
+        .await
+        .unwrap();
+
+    let diff = manager.diff_layers(&image.id, 0, 1).await.unwrap();
+    assert_eq!(diff.added, ["srv/index.html"]);
+    assert_eq!(diff.removed, ["usr/bin/tool"]);
+    assert_eq!(diff.modified.len(), 3);
+
+    assert!(manager.diff_layers(&image.id, 0, 2).await.is_err());
+    assert!(manager
+        .diff_layers(&ImageId::from_string("missing:1.0"), 0, 1)
+        .await
+        .is_err());
+}
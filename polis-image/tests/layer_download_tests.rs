@@ -0,0 +1,153 @@
+use polis_image::{ImageManager, RegistryClient, RegistryConfig, RegistryEntry};
+use polis_test_support::{sha256_digest, FileTree, Layer, MockRegistry};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+type Calls = Arc<Mutex<Vec<(u64, Option<u64>)>>>;
+
+fn recorder() -> (Calls, impl Fn(u64, Option<u64>) + Send + 'static) {
+    let calls = Calls::default();
+    let sink = calls.clone();
+    (calls, move |downloaded, total| {
+        sink.lock().unwrap().push((downloaded, total))
+    })
+}
+
+#[tokio::test]
+async fn test_download_layer_reports_cumulative_progress() {
+    let registry = MockRegistry::start().await;
+    registry.stream_in_chunks(16 * 1024);
+    // 100 KiB of incompressible-looking data: six full chunks and a tail
+    let blob: Vec<u8> = (0..100 * 1024u32).map(|i| (i * 31 % 251) as u8).collect();
+    let digest = sha256_digest(&blob);
+    registry.add_blob("myorg/app", &digest, blob.clone());
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let client = RegistryClient::new(cache_dir.path().to_path_buf())
+        .with_base_url(registry.base_url())
+        .with_config(RegistryConfig::default());
+    let (calls, on_progress) = recorder();
+    client
+        .download_layer("myorg/app", &digest, Some(Box::new(on_progress)))
+        .await
+        .unwrap();
+
+    let calls = calls.lock().unwrap().clone();
+    let total = blob.len() as u64;
+    assert!(calls.len() >= 7, "{:?}", calls);
+    assert!(calls.iter().all(|(_, length)| *length == Some(total)));
+    assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert_eq!(calls.last().unwrap().0, total);
+
+    // The blob was written to the store as it arrived
+    let stored = std::fs::read(client.layer_store().layer_path(&digest).unwrap()).unwrap();
+    assert_eq!(stored, blob);
+}
+
+#[tokio::test]
+async fn test_corrupt_download_leaves_store_untouched() {
+    let registry = MockRegistry::start().await;
+    let digest = sha256_digest(b"expected");
+    registry.add_blob("myorg/app", &digest, b"something else".to_vec());
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let client = RegistryClient::new(cache_dir.path().to_path_buf())
+        .with_base_url(registry.base_url())
+        .with_config(RegistryConfig::default());
+    assert!(client
+        .download_layer("myorg/app", &digest, None)
+        .await
+        .is_err());
+    assert!(!client.layer_store().contains(&digest));
+    assert!(client.layer_store().layers().await.unwrap().is_empty());
+}
+
+fn publish(registry: &MockRegistry, repo: &str, tag: &str, layers: &[&Layer]) {
+    let config = serde_json::json!({
+        "architecture": "amd64",
+        "os": "linux",
+        "config": {},
+        "rootfs": {
+            "type": "layers",
+            "diff_ids": layers.iter().map(|layer| layer.diff_id.clone()).collect::<Vec<_>>(),
+        },
+    })
+    .to_string();
+    let config_digest = sha256_digest(config.as_bytes());
+    registry.add_blob(repo, &config_digest, config.clone().into_bytes());
+    for layer in layers {
+        registry.add_blob(repo, &layer.digest, layer.compressed.clone());
+    }
+
+    let manifest = serde_json::json!({
+        "schema_version": 2,
+        "media_type": "application/vnd.oci.image.manifest.v1+json",
+        "config": {
+            "media_type": "application/vnd.oci.image.config.v1+json",
+            "size": config.len(),
+            "digest": config_digest,
+        },
+        "layers": layers.iter().map(|layer| layer.descriptor()).collect::<Vec<_>>(),
+    });
+    registry.add_manifest(repo, tag, &manifest.to_string());
+}
+
+#[tokio::test]
+async fn test_pull_with_progress_reports_each_layer() {
+    let registry = MockRegistry::start().await;
+    let base = FileTree::new().file("etc/os-release", "ID=polis\n").layer();
+    let app = FileTree::new()
+        .file("srv/index.html", "<h1>hello</h1>\n")
+        .layer();
+    publish(&registry, "myorg/app", "1.0", &[&base, &app]);
+
+    let mut registries = HashMap::new();
+    registries.insert(
+        registry.host(),
+        RegistryEntry {
+            location: format!("http://{}", registry.host()),
+            ..Default::default()
+        },
+    );
+    let cache_dir = tempfile::tempdir().unwrap();
+    let manager = Arc::new(
+        ImageManager::new(cache_dir.path().to_path_buf()).with_registry_config(RegistryConfig {
+            registries,
+            ..RegistryConfig::default()
+        }),
+    );
+
+    // The pull can run on another task
+    let (calls, on_progress) = recorder();
+    let reference = format!("{}/myorg/app:1.0", registry.host());
+    let pulling = manager.clone();
+    let pull = reference.clone();
+    tokio::spawn(async move { pulling.pull_with_progress(&pull, on_progress).await })
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Each layer ends at its own size
+    let calls = calls.lock().unwrap().clone();
+    let finished: Vec<_> = calls
+        .iter()
+        .filter(|(downloaded, total)| Some(*downloaded) == *total)
+        .copied()
+        .collect();
+    let sizes = |layer: &Layer| layer.compressed.len() as u64;
+    assert_eq!(
+        finished,
+        [
+            (sizes(&base), Some(sizes(&base))),
+            (sizes(&app), Some(sizes(&app)))
+        ]
+    );
+
+    // Layers already in the store are not downloaded again
+    let (calls, on_progress) = recorder();
+    manager
+        .pull_with_progress(&reference, on_progress)
+        .await
+        .unwrap();
+    assert!(calls.lock().unwrap().is_empty());
+}
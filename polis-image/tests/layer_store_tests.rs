@@ -0,0 +1,154 @@
+use polis_core::ImageId;
+use polis_image::{ImageManager, LayerStore, RegistryConfig, RegistryEntry};
+use polis_test_support::{sha256_digest, FileTree, Layer, MockRegistry};
+use std::collections::HashMap;
+use tokio::io::AsyncReadExt;
+
+fn publish(registry: &MockRegistry, repo: &str, tag: &str, layers: &[&Layer]) {
+    let config = serde_json::json!({
+        "architecture": "amd64",
+        "os": "linux",
+        "config": {},
+        "rootfs": {
+            "type": "layers",
+            "diff_ids": layers.iter().map(|layer| layer.diff_id.clone()).collect::<Vec<_>>(),
+        },
+    })
+    .to_string();
+    let config_digest = sha256_digest(config.as_bytes());
+    registry.add_blob(repo, &config_digest, config.clone().into_bytes());
+    for layer in layers {
+        registry.add_blob(repo, &layer.digest, layer.compressed.clone());
+    }
+
+    let manifest = serde_json::json!({
+        "schema_version": 2,
+        "media_type": "application/vnd.oci.image.manifest.v1+json",
+        "config": {
+            "media_type": "application/vnd.oci.image.config.v1+json",
+            "size": config.len(),
+            "digest": config_digest,
+        },
+        "layers": layers.iter().map(|layer| layer.descriptor()).collect::<Vec<_>>(),
+    });
+    registry.add_manifest(repo, tag, &manifest.to_string());
+}
+
+fn registry_config(registry: &MockRegistry) -> RegistryConfig {
+    let mut registries = HashMap::new();
+    registries.insert(
+        registry.host(),
+        RegistryEntry {
+            location: format!("http://{}", registry.host()),
+            ..Default::default()
+        },
+    );
+    RegistryConfig {
+        registries,
+        ..RegistryConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn test_shared_layer_is_stored_once() {
+    let registry = MockRegistry::start().await;
+    let base = FileTree::new().file("etc/os-release", "ID=polis\n").layer();
+    let app = FileTree::new().file("app/v1", "one").layer();
+    let app2 = FileTree::new().file("app/v2", "two").layer();
+    publish(&registry, "myorg/app", "1.0", &[&base, &app]);
+    publish(&registry, "myorg/app", "2.0", &[&base, &app2]);
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let manager = ImageManager::new(cache_dir.path().to_path_buf())
+        .with_registry_config(registry_config(&registry));
+    let first = manager
+        .pull(&format!("{}/myorg/app:1.0", registry.host()))
+        .await
+        .unwrap();
+    let second = manager
+        .pull(&format!("{}/myorg/app:2.0", registry.host()))
+        .await
+        .unwrap();
+    assert_eq!(first.layers, [base.digest.clone(), app.digest.clone()]);
+    assert_eq!(second.layers[0], base.digest);
+
+    // The shared layer was downloaded and written once
+    let base_blob = format!("/v2/myorg/app/blobs/{}", base.digest);
+    let downloads = registry
+        .requested_paths()
+        .iter()
+        .filter(|path| **path == base_blob)
+        .count();
+    assert_eq!(downloads, 1);
+
+    let store = manager.layer_store();
+    let blobs: Vec<_> = walkdir::WalkDir::new(store.root().join("blobs"))
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .collect();
+    // Three distinct layers plus one config per image
+    assert_eq!(blobs.len(), 5);
+    assert_eq!(store.ref_count(&base.digest), 2);
+    assert_eq!(store.ref_count(&app.digest), 1);
+
+    let mut content = Vec::new();
+    store
+        .get(&base.digest)
+        .await
+        .unwrap()
+        .read_to_end(&mut content)
+        .await
+        .unwrap();
+    assert_eq!(content, base.compressed);
+
+    // Removing one image keeps the layers the other still uses
+    manager.remove_image(&first.id).await.unwrap();
+    assert_eq!(store.ref_count(&base.digest), 1);
+    let removed = manager.gc_layers().await.unwrap();
+    assert_eq!(removed.len(), 2);
+    assert!(removed.contains(&app.digest));
+    assert!(store.contains(&base.digest));
+    assert!(!store.contains(&app.digest));
+
+    manager.remove_image(&second.id).await.unwrap();
+    let removed = manager.gc_layers().await.unwrap();
+    assert_eq!(removed.len(), 3);
+    assert!(removed.contains(&base.digest) && removed.contains(&app2.digest));
+    assert!(store.layers().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_put_rejects_content_not_matching_digest() {
+    let root = tempfile::tempdir().unwrap();
+    let store = LayerStore::new(root.path().to_path_buf());
+    let digest = sha256_digest(b"expected");
+
+    assert!(store.put(&digest, &b"tampered"[..]).await.is_err());
+    assert!(!store.contains(&digest));
+    assert!(store.put("sha256:not-hex", &b"expected"[..]).await.is_err());
+
+    store.put(&digest, &b"expected"[..]).await.unwrap();
+    assert_eq!(store.layers().await.unwrap(), [digest]);
+}
+
+#[tokio::test]
+async fn test_references_survive_reload() {
+    let root = tempfile::tempdir().unwrap();
+    let digest = sha256_digest(b"layer");
+    let store = LayerStore::new(root.path().to_path_buf());
+    store.put(&digest, &b"layer"[..]).await.unwrap();
+    store
+        .set_references(
+            &ImageId::from_string("app:1.0").0,
+            std::slice::from_ref(&digest),
+        )
+        .unwrap();
+
+    let reloaded = LayerStore::new(root.path().to_path_buf());
+    assert_eq!(reloaded.ref_count(&digest), 1);
+    assert!(reloaded.gc().await.unwrap().is_empty());
+
+    reloaded.release("app:1.0").unwrap();
+    assert_eq!(reloaded.gc().await.unwrap(), [digest]);
+}
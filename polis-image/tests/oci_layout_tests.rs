@@ -0,0 +1,243 @@
+use polis_image::{ImageManager, OciManifest, RegistryConfig, RegistryEntry};
+use polis_test_support::{sha256_digest, FileTree, Layer, MockRegistry};
+use std::collections::HashMap;
+use std::path::Path;
+
+fn publish(registry: &MockRegistry, repo: &str, tag: &str, layers: &[&Layer]) {
+    let config = serde_json::json!({
+        "architecture": "arm64",
+        "os": "linux",
+        "config": { "cmd": ["/app"] },
+        "rootfs": {
+            "type": "layers",
+            "diff_ids": layers.iter().map(|layer| layer.diff_id.clone()).collect::<Vec<_>>(),
+        },
+    })
+    .to_string();
+    let config_digest = sha256_digest(config.as_bytes());
+    registry.add_blob(repo, &config_digest, config.clone().into_bytes());
+    for layer in layers {
+        registry.add_blob(repo, &layer.digest, layer.compressed.clone());
+    }
+
+    let manifest = serde_json::json!({
+        "schema_version": 2,
+        "media_type": "application/vnd.oci.image.manifest.v1+json",
+        "config": {
+            "media_type": "application/vnd.oci.image.config.v1+json",
+            "size": config.len(),
+            "digest": config_digest,
+        },
+        "layers": layers.iter().map(|layer| layer.descriptor()).collect::<Vec<_>>(),
+    });
+    registry.add_manifest(repo, tag, &manifest.to_string());
+}
+
+fn registry_config(registry: &MockRegistry) -> RegistryConfig {
+    let mut registries = HashMap::new();
+    registries.insert(
+        registry.host(),
+        RegistryEntry {
+            location: format!("http://{}", registry.host()),
+            ..Default::default()
+        },
+    );
+    RegistryConfig {
+        registries,
+        ..RegistryConfig::default()
+    }
+}
+
+fn read_tar(path: &Path) -> HashMap<String, Vec<u8>> {
+    let mut archive = tar::Archive::new(std::fs::File::open(path).unwrap());
+    let mut entries = HashMap::new();
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let name = entry.path().unwrap().to_string_lossy().to_string();
+        let mut content = Vec::new();
+        std::io::Read::read_to_end(&mut entry, &mut content).unwrap();
+        entries.insert(name, content);
+    }
+    entries
+}
+
+fn write_tar(path: &Path, entries: &HashMap<String, Vec<u8>>) {
+    let mut builder = tar::Builder::new(std::fs::File::create(path).unwrap());
+    for (name, content) in entries {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, content.as_slice())
+            .unwrap();
+    }
+    builder.finish().unwrap();
+}
+
+fn blob<'a>(entries: &'a HashMap<String, Vec<u8>>, digest: &str) -> &'a [u8] {
+    let path = format!("blobs/sha256/{}", digest.trim_start_matches("sha256:"));
+    &entries[&path]
+}
+
+async fn pulled_image(
+    registry: &MockRegistry,
+    cache_dir: &Path,
+) -> (ImageManager, polis_core::Image) {
+    let base = FileTree::new().file("etc/os-release", "ID=polis\n").layer();
+    let app = FileTree::new().file("app", "#!/bin/sh\n").layer();
+    publish(registry, "myorg/app", "1.0", &[&base, &app]);
+
+    let manager =
+        ImageManager::new(cache_dir.to_path_buf()).with_registry_config(registry_config(registry));
+    let image = manager
+        .pull(&format!("{}/myorg/app:1.0", registry.host()))
+        .await
+        .unwrap();
+    (manager, image)
+}
+
+#[tokio::test]
+async fn test_export_import_round_trip() {
+    let registry = MockRegistry::start().await;
+    let source_dir = tempfile::tempdir().unwrap();
+    let (source, image) = pulled_image(&registry, source_dir.path()).await;
+    assert!(image.digest.starts_with("sha256:"));
+    assert_eq!(image.architecture, "arm64");
+
+    let out = tempfile::tempdir().unwrap();
+    let archive = out.path().join("app.tar");
+    source.export_oci_tar(&image.id, &archive).await.unwrap();
+
+    let entries = read_tar(&archive);
+    let layout: serde_json::Value = serde_json::from_slice(&entries["oci-layout"]).unwrap();
+    assert_eq!(layout["imageLayoutVersion"], "1.0.0");
+    let index: serde_json::Value = serde_json::from_slice(&entries["index.json"]).unwrap();
+    assert_eq!(index["schemaVersion"], 2);
+    assert_eq!(index["manifests"][0]["digest"], image.digest.as_str());
+    assert_eq!(
+        index["manifests"][0]["annotations"]["org.opencontainers.image.ref.name"],
+        image.id.0.as_str()
+    );
+
+    // The manifest uses the OCI field names and every blob it lists is present
+    let manifest_bytes = blob(&entries, &image.digest);
+    assert_eq!(sha256_digest(manifest_bytes), image.digest);
+    let document: serde_json::Value = serde_json::from_slice(manifest_bytes).unwrap();
+    assert_eq!(document["schemaVersion"], 2);
+    let manifest: OciManifest = serde_json::from_slice(manifest_bytes).unwrap();
+    for descriptor in std::iter::once(&manifest.config).chain(&manifest.layers) {
+        assert_eq!(
+            sha256_digest(blob(&entries, &descriptor.digest)),
+            descriptor.digest
+        );
+    }
+
+    // Import on a host that never talked to the registry
+    let target_dir = tempfile::tempdir().unwrap();
+    let target = ImageManager::new(target_dir.path().to_path_buf());
+    let imported = target.import_oci_tar(&archive).await.unwrap();
+    assert_eq!(imported, image.id);
+    for layer in &image.layers {
+        assert_eq!(target.layer_store().ref_count(layer), 1);
+    }
+
+    let reexported = out.path().join("again.tar");
+    target.export_oci_tar(&imported, &reexported).await.unwrap();
+    let entries = read_tar(&reexported);
+    let index: serde_json::Value = serde_json::from_slice(&entries["index.json"]).unwrap();
+    assert_eq!(index["manifests"][0]["digest"], image.digest.as_str());
+    let manifest: OciManifest = serde_json::from_slice(blob(&entries, &image.digest)).unwrap();
+    let layers: Vec<String> = manifest.layers.iter().map(|l| l.digest.clone()).collect();
+    assert_eq!(layers, image.layers);
+}
+
+#[tokio::test]
+async fn test_import_rejects_tampered_layer() {
+    let registry = MockRegistry::start().await;
+    let source_dir = tempfile::tempdir().unwrap();
+    let (source, image) = pulled_image(&registry, source_dir.path()).await;
+
+    let out = tempfile::tempdir().unwrap();
+    let archive = out.path().join("app.tar");
+    source.export_oci_tar(&image.id, &archive).await.unwrap();
+
+    let mut entries = read_tar(&archive);
+    entries.retain(|name, _| !name.ends_with('/'));
+    let layer = format!(
+        "blobs/sha256/{}",
+        image.layers[1].trim_start_matches("sha256:")
+    );
+    entries.insert(layer, b"not the layer".to_vec());
+    let tampered = out.path().join("tampered.tar");
+    write_tar(&tampered, &entries);
+
+    let target_dir = tempfile::tempdir().unwrap();
+    let target = ImageManager::new(target_dir.path().to_path_buf());
+    assert!(target.import_oci_tar(&tampered).await.is_err());
+    assert!(!target.layer_store().contains(&image.layers[1]));
+    assert!(target
+        .export_oci_tar(&image.id, &out.path().join("x.tar"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_save_and_load_through_streams() {
+    let registry = MockRegistry::start().await;
+    let source_dir = tempfile::tempdir().unwrap();
+    let (source, image) = pulled_image(&registry, source_dir.path()).await;
+
+    let mut archive = Vec::new();
+    source.save(&image.id.0, &mut archive).await.unwrap();
+
+    let target_dir = tempfile::tempdir().unwrap();
+    let target = ImageManager::new(target_dir.path().to_path_buf());
+    let loaded = target.load(archive.as_slice()).await.unwrap();
+    assert_eq!(loaded, image.id);
+    for layer in &image.layers {
+        assert_eq!(target.layer_store().ref_count(layer), 1);
+    }
+
+    // Same manifest and blobs, so saving again yields the same archive
+    let mut again = Vec::new();
+    target.save(&loaded.0, &mut again).await.unwrap();
+    assert!(again == archive);
+
+    // Loading again leaves the stored blobs untouched
+    let layer_path = target.layer_store().layer_path(&image.layers[0]).unwrap();
+    let modified = std::fs::metadata(&layer_path).unwrap().modified().unwrap();
+    assert_eq!(target.load(archive.as_slice()).await.unwrap(), image.id);
+    assert_eq!(
+        std::fs::metadata(&layer_path).unwrap().modified().unwrap(),
+        modified
+    );
+    // No spooled archive or staging directory is left behind
+    let leftovers: Vec<_> = std::fs::read_dir(target_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("load-") || name.starts_with("import-"))
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+
+    // A corrupted blob is reported by its digest
+    let out = tempfile::tempdir().unwrap();
+    let saved = out.path().join("app.tar");
+    std::fs::write(&saved, &archive).unwrap();
+    let mut entries = read_tar(&saved);
+    entries.retain(|name, _| !name.ends_with('/'));
+    let layer = format!(
+        "blobs/sha256/{}",
+        image.layers[1].trim_start_matches("sha256:")
+    );
+    entries.insert(layer, b"not the layer".to_vec());
+    write_tar(&saved, &entries);
+
+    let fresh_dir = tempfile::tempdir().unwrap();
+    let fresh = ImageManager::new(fresh_dir.path().to_path_buf());
+    let error = fresh
+        .load(std::fs::read(&saved).unwrap().as_slice())
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains(&image.layers[1]), "{}", error);
+}
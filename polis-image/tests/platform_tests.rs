@@ -0,0 +1,199 @@
+use polis_image::{
+    glob_match, resolve_platform, Platform, PlatformOverride, PlatformSource, RegistryClient,
+    RegistryConfig, RegistryEntry,
+};
+use polis_test_support::{sha256_digest, MockRegistry};
+
+fn index(amd64_digest: &str, armv7_digest: &str) -> String {
+    format!(
+        r#"{{"schema_version":2,"media_type":"application/vnd.oci.image.index.v1+json","manifests":[
+    {{"media_type":"application/vnd.oci.image.manifest.v1+json","size":10,"digest":"{}","platform":{{"architecture":"amd64","os":"linux"}}}},
+    {{"media_type":"application/vnd.oci.image.manifest.v1+json","size":10,"digest":"{}","platform":{{"architecture":"arm","os":"linux","variant":"v7"}}}}
+]}}"#,
+        amd64_digest, armv7_digest
+    )
+}
+
+fn manifest(config_digest: &str) -> String {
+    format!(
+        r#"{{"schema_version":2,"media_type":"application/vnd.oci.image.manifest.v1+json","config":{{"media_type":"application/vnd.oci.image.config.v1+json","size":2,"digest":"{}"}},"layers":[]}}"#,
+        config_digest
+    )
+}
+
+fn config_with_overrides(overrides: &[(&str, &str)]) -> RegistryConfig {
+    RegistryConfig {
+        platform_overrides: overrides
+            .iter()
+            .map(|(pattern, platform)| PlatformOverride {
+                pattern: pattern.to_string(),
+                platform: platform.to_string(),
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_pull_through_rewrites_docker_io_references() {
+    let mut config = RegistryConfig::default();
+    config.registries.insert(
+        "cache.local:5000".to_string(),
+        RegistryEntry {
+            location: "http://cache.local:5000".to_string(),
+            upstream: Some("docker.io".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let official = config.resolve_reference("nginx:1.25").unwrap();
+    assert!(official.is_pull_through());
+    assert_eq!(official.original, "nginx:1.25");
+    assert_eq!(official.registry, "docker.io");
+    assert_eq!(official.pull_name(), "cache.local:5000/library/nginx:1.25");
+    assert_eq!(official.canonical_name(), "docker.io/library/nginx:1.25");
+
+    let explicit = config.resolve_reference("docker.io/myorg/app").unwrap();
+    assert_eq!(explicit.pull_name(), "cache.local:5000/myorg/app:latest");
+
+    // Outros registries não passam pelo cache
+    let quay = config.resolve_reference("quay.io/coreos/etcd:v3").unwrap();
+    assert!(!quay.is_pull_through());
+    assert_eq!(quay.pull_name(), "quay.io/coreos/etcd:v3");
+}
+
+#[test]
+fn test_blocked_cache_is_not_used() {
+    let mut config = RegistryConfig::default();
+    config.registries.insert(
+        "cache.local:5000".to_string(),
+        RegistryEntry {
+            location: "http://cache.local:5000".to_string(),
+            upstream: Some("docker.io".to_string()),
+            blocked: Some(true),
+            ..Default::default()
+        },
+    );
+
+    assert!(!config.resolve_reference("nginx").unwrap().is_pull_through());
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("myorg/*", "myorg/app"));
+    assert!(glob_match("*/app-?", "myorg/app-1"));
+    assert!(glob_match("*", "anything/at/all"));
+    assert!(!glob_match("myorg/*", "other/app"));
+    assert!(!glob_match("app-?", "app-10"));
+}
+
+#[test]
+fn test_most_specific_override_wins() {
+    let config = config_with_overrides(&[
+        ("myorg/*", "linux/arm64"),
+        ("myorg/legacy-*", "linux/arm/v7"),
+        ("*", "linux/amd64"),
+    ]);
+
+    let legacy = resolve_platform(None, &config, "docker.io", "myorg/legacy-api").unwrap();
+    assert_eq!(legacy.platform, Platform::parse("linux/arm/v7").unwrap());
+    assert_eq!(
+        legacy.source,
+        PlatformSource::Override {
+            pattern: "myorg/legacy-*".to_string()
+        }
+    );
+
+    let other = resolve_platform(None, &config, "docker.io", "myorg/web").unwrap();
+    assert_eq!(other.platform.architecture, "arm64");
+
+    let fallback = resolve_platform(None, &config, "docker.io", "library/nginx").unwrap();
+    assert_eq!(fallback.platform.architecture, "amd64");
+}
+
+#[test]
+fn test_override_with_registry_prefix_and_ties() {
+    let config = config_with_overrides(&[
+        ("docker.io/myorg/*p", "linux/arm/v7"),
+        ("docker.io/myorg/a*", "linux/arm64"),
+    ]);
+
+    // Mesma especificidade: vence o primeiro do arquivo
+    let choice = resolve_platform(None, &config, "docker.io", "myorg/app").unwrap();
+    assert_eq!(choice.platform.to_string(), "linux/arm/v7");
+
+    let elsewhere = resolve_platform(None, &config, "quay.io", "myorg/app").unwrap();
+    assert_eq!(elsewhere.source, PlatformSource::HostDefault);
+    assert_eq!(elsewhere.platform, Platform::host());
+}
+
+#[test]
+fn test_flag_beats_override() {
+    let config = config_with_overrides(&[("myorg/*", "linux/arm/v7")]);
+    let flag = Platform::parse("linux/amd64").unwrap();
+
+    let choice = resolve_platform(Some(&flag), &config, "docker.io", "myorg/app").unwrap();
+    assert_eq!(choice.platform, flag);
+    assert_eq!(choice.source, PlatformSource::Flag);
+}
+
+#[tokio::test]
+async fn test_manifest_list_selection_precedence_with_mock_registry() {
+    let registry = MockRegistry::start().await;
+    let amd64 = manifest("sha256:config-amd64");
+    let armv7 = manifest("sha256:config-armv7");
+    let amd64_digest = sha256_digest(amd64.as_bytes());
+    let armv7_digest = sha256_digest(armv7.as_bytes());
+    registry.add_manifest("myorg/app", "1.0", &index(&amd64_digest, &armv7_digest));
+    registry.add_manifest("myorg/app", &amd64_digest, &amd64);
+    registry.add_manifest("myorg/app", &armv7_digest, &armv7);
+    let config = config_with_overrides(&[("myorg/*", "linux/arm/v7")]);
+    let cache_dir = tempfile::tempdir().unwrap();
+
+    // Sem flag, o override do registries.conf é usado
+    let client = RegistryClient::new(cache_dir.path().to_path_buf())
+        .with_base_url(registry.base_url())
+        .with_config(config.clone());
+    let (manifest, choice) = client
+        .fetch_platform_manifest("myorg/app", "1.0")
+        .await
+        .unwrap();
+    assert_eq!(manifest.config.digest, "sha256:config-armv7");
+    assert!(matches!(choice.source, PlatformSource::Override { .. }));
+
+    // --platform vence o override
+    let client = client.with_platform(Some(Platform::parse("linux/amd64").unwrap()));
+    let (manifest, choice) = client
+        .fetch_platform_manifest("myorg/app", "1.0")
+        .await
+        .unwrap();
+    assert_eq!(manifest.config.digest, "sha256:config-amd64");
+    assert_eq!(choice.source, PlatformSource::Flag);
+
+    assert_eq!(
+        registry.requested_paths(),
+        [
+            "/v2/myorg/app/manifests/1.0".to_string(),
+            format!("/v2/myorg/app/manifests/{}", armv7_digest),
+            "/v2/myorg/app/manifests/1.0".to_string(),
+            format!("/v2/myorg/app/manifests/{}", amd64_digest),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_missing_platform_is_reported() {
+    let registry = MockRegistry::start().await;
+    registry.add_manifest("myorg/app", "1.0", &index("sha256:amd64", "sha256:armv7"));
+    let cache_dir = tempfile::tempdir().unwrap();
+
+    let client = RegistryClient::new(cache_dir.path().to_path_buf())
+        .with_base_url(registry.base_url())
+        .with_config(RegistryConfig::default())
+        .with_platform(Some(Platform::parse("linux/s390x").unwrap()));
+    let error = client
+        .fetch_platform_manifest("myorg/app", "1.0")
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("linux/arm/v7"));
+}
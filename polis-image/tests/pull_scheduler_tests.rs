@@ -0,0 +1,233 @@
+use polis_image::{
+    parse_ratelimit_header, BackoffPolicy, PullEvent, PullPriority, PullScheduler, RateLimitInfo,
+    RegistryClient, RegistryLimits, SchedulerConfig,
+};
+use polis_test_support::{Fault, MockRegistry};
+use reqwest::header::{HeaderMap, HeaderValue};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const MANIFEST: &str = r#"{"schema_version":2,"media_type":"application/vnd.oci.image.manifest.v1+json","config":{"media_type":"application/vnd.oci.image.config.v1+json","size":2,"digest":"sha256:abc"},"layers":[]}"#;
+
+fn fast_backoff(max_retries: u32) -> BackoffPolicy {
+    BackoffPolicy {
+        initial_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(50),
+        max_retries,
+    }
+}
+
+#[test]
+fn test_parse_ratelimit_headers() {
+    assert_eq!(
+        parse_ratelimit_header("100;w=21600"),
+        Some((100, Some(21600)))
+    );
+    assert_eq!(parse_ratelimit_header("76"), Some((76, None)));
+    assert_eq!(parse_ratelimit_header("invalid"), None);
+
+    let mut headers = HeaderMap::new();
+    headers.insert("ratelimit-limit", HeaderValue::from_static("100;w=21600"));
+    headers.insert(
+        "ratelimit-remaining",
+        HeaderValue::from_static("76;w=21600"),
+    );
+
+    let info = RateLimitInfo::from_headers(&headers).unwrap();
+    assert_eq!(info.limit, Some(100));
+    assert_eq!(info.remaining, Some(76));
+    assert_eq!(info.window_secs, Some(21600));
+
+    assert!(RateLimitInfo::from_headers(&HeaderMap::new()).is_none());
+}
+
+#[tokio::test]
+async fn test_quota_exposed_and_low_quota_warning() {
+    let scheduler = PullScheduler::new(SchedulerConfig {
+        quota_warning_threshold: 10,
+        ..Default::default()
+    });
+    let mut events = scheduler.subscribe();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("ratelimit-limit", HeaderValue::from_static("100;w=21600"));
+    headers.insert("ratelimit-remaining", HeaderValue::from_static("5;w=21600"));
+    scheduler.record_rate_limit("docker.io", RateLimitInfo::from_headers(&headers).unwrap());
+
+    let status = scheduler.status();
+    assert_eq!(status.quotas["docker.io"].remaining, Some(5));
+
+    match events.try_recv().unwrap() {
+        PullEvent::QuotaLow {
+            registry,
+            remaining,
+            limit,
+        } => {
+            assert_eq!(registry, "docker.io");
+            assert_eq!(remaining, 5);
+            assert_eq!(limit, Some(100));
+        }
+        other => panic!("evento inesperado: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_excess_pulls_queue_and_release_by_priority() {
+    let scheduler = Arc::new(PullScheduler::new(SchedulerConfig {
+        max_concurrent_pulls: 1,
+        ..Default::default()
+    }));
+
+    let first = scheduler
+        .acquire("first", "docker.io", PullPriority::Background)
+        .await;
+
+    let (order_tx, mut order_rx) = mpsc::unbounded_channel();
+    let pulls = [
+        ("prepull-a", PullPriority::Background),
+        ("prepull-b", PullPriority::Background),
+        ("cli", PullPriority::Interactive),
+    ];
+    for (expected_queue, (image, priority)) in pulls.into_iter().enumerate() {
+        let task_scheduler = scheduler.clone();
+        let order_tx = order_tx.clone();
+        tokio::spawn(async move {
+            let _permit = task_scheduler.acquire(image, "docker.io", priority).await;
+            order_tx.send(image).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        });
+
+        // Aguardar o pull entrar na fila antes do próximo
+        while scheduler.status().queued.len() <= expected_queue {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    let status = scheduler.status();
+    assert_eq!(status.active, 1);
+    assert_eq!(status.queued[0].image, "cli");
+
+    drop(first);
+
+    let mut order = Vec::new();
+    for _ in 0..3 {
+        order.push(order_rx.recv().await.unwrap());
+    }
+    assert_eq!(order, vec!["cli", "prepull-a", "prepull-b"]);
+
+    // O último pull ainda segura a vaga até terminar
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while scheduler.status().active > 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert!(scheduler.status().queued.is_empty());
+}
+
+#[tokio::test]
+async fn test_per_registry_limit() {
+    let mut registries = HashMap::new();
+    registries.insert(
+        "quay.io".to_string(),
+        RegistryLimits {
+            max_concurrent_pulls: Some(1),
+            ..Default::default()
+        },
+    );
+    let scheduler = Arc::new(PullScheduler::new(SchedulerConfig {
+        max_concurrent_pulls: 4,
+        registries,
+        ..Default::default()
+    }));
+
+    let _quay = scheduler
+        .acquire("quay.io/a", "quay.io", PullPriority::Interactive)
+        .await;
+
+    let waiting = {
+        let scheduler = scheduler.clone();
+        tokio::spawn(async move {
+            let _permit = scheduler
+                .acquire("quay.io/b", "quay.io", PullPriority::Interactive)
+                .await;
+        })
+    };
+    while scheduler.status().queued.is_empty() {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    // Outro registry não é afetado pelo limite do quay.io
+    let _docker = tokio::time::timeout(
+        Duration::from_secs(1),
+        scheduler.acquire("alpine", "docker.io", PullPriority::Background),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(scheduler.status().queued.len(), 1);
+    waiting.abort();
+}
+
+#[tokio::test]
+async fn test_rate_limited_response_triggers_backoff() {
+    let registry = MockRegistry::start().await;
+    registry.add_manifest("library/alpine", "latest", MANIFEST);
+    registry.set_header("ratelimit-remaining", "99;w=21600");
+    registry.inject(Fault::rate_limited(
+        Some(0),
+        &[
+            ("ratelimit-limit", "100;w=21600"),
+            ("ratelimit-remaining", "0;w=21600"),
+        ],
+    ));
+
+    let scheduler = Arc::new(PullScheduler::default());
+    let mut events = scheduler.subscribe();
+    let client = RegistryClient::new(std::env::temp_dir().join("polis-test-ratelimit"))
+        .with_base_url(registry.base_url())
+        .with_scheduler(scheduler.clone())
+        .with_backoff(fast_backoff(3));
+
+    let manifest = client
+        .fetch_manifest("library/alpine", "latest")
+        .await
+        .unwrap();
+    assert_eq!(manifest.config.digest, "sha256:abc");
+
+    let mut throttled = false;
+    while let Ok(event) = events.try_recv() {
+        if let PullEvent::Throttled { registry, .. } = event {
+            assert_eq!(registry, "127.0.0.1");
+            throttled = true;
+        }
+    }
+    assert!(throttled);
+    assert_eq!(scheduler.quota("127.0.0.1").unwrap().remaining, Some(99));
+}
+
+#[tokio::test]
+async fn test_quota_exhausted_after_retries() {
+    let registry = MockRegistry::start().await;
+    registry.add_manifest("library/alpine", "latest", MANIFEST);
+    registry.inject_always(Fault::rate_limited(
+        None,
+        &[("ratelimit-remaining", "0;w=21600")],
+    ));
+
+    let scheduler = Arc::new(PullScheduler::default());
+    let client = RegistryClient::new(std::env::temp_dir().join("polis-test-ratelimit-exhausted"))
+        .with_base_url(registry.base_url())
+        .with_scheduler(scheduler.clone())
+        .with_backoff(fast_backoff(2));
+
+    let error = client
+        .fetch_manifest("library/alpine", "latest")
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("esgotada"));
+    assert_eq!(scheduler.quota("127.0.0.1").unwrap().remaining, Some(0));
+}
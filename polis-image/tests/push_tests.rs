@@ -0,0 +1,334 @@
+use polis_core::{ImageId, PolisError, RegistryErrorCode};
+use polis_image::{ImageManager, PushProgress, RegistryClient, RegistryConfig, RegistryEntry};
+use polis_test_support::{sha256_digest, Fault, FileTree, Layer, MockRegistry};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const CHUNK: usize = 64;
+
+fn registry_config(registry: &MockRegistry) -> RegistryConfig {
+    let mut registries = HashMap::new();
+    registries.insert(
+        registry.host(),
+        RegistryEntry {
+            location: format!("http://{}", registry.host()),
+            ..Default::default()
+        },
+    );
+    RegistryConfig {
+        registries,
+        ..RegistryConfig::default()
+    }
+}
+
+fn client(registry: &MockRegistry, cache_dir: &std::path::Path) -> RegistryClient {
+    RegistryClient::new(cache_dir.to_path_buf())
+        .with_config(registry_config(registry))
+        .with_push_chunk_size(CHUNK)
+}
+
+/// Publishes an image and returns the digest of its config
+fn publish(registry: &MockRegistry, repo: &str, tag: &str, layers: &[&Layer]) -> String {
+    let config = serde_json::json!({
+        "architecture": "amd64",
+        "os": "linux",
+        "config": {},
+        "rootfs": {
+            "type": "layers",
+            "diff_ids": layers.iter().map(|layer| layer.diff_id.clone()).collect::<Vec<_>>(),
+        },
+    })
+    .to_string();
+    let config_digest = sha256_digest(config.as_bytes());
+    registry.add_blob(repo, &config_digest, config.clone().into_bytes());
+    for layer in layers {
+        registry.add_blob(repo, &layer.digest, layer.compressed.clone());
+    }
+
+    let manifest = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.manifest.v1+json",
+        "config": {
+            "mediaType": "application/vnd.oci.image.config.v1+json",
+            "size": config.len(),
+            "digest": config_digest,
+        },
+        "layers": layers.iter().map(|layer| layer.descriptor()).collect::<Vec<_>>(),
+    });
+    registry.add_manifest(repo, tag, &manifest.to_string());
+    config_digest
+}
+
+fn upload_calls(repo: &str, digest: &str, size: usize, session: u32) -> Vec<String> {
+    let mut calls = vec![
+        format!("HEAD /v2/{}/blobs/{}", repo, digest),
+        format!("POST /v2/{}/blobs/uploads/", repo),
+    ];
+    for _ in 0..size.div_ceil(CHUNK) {
+        calls.push(format!("PATCH /v2/{}/blobs/uploads/{}", repo, session));
+    }
+    calls.push(format!("PUT /v2/{}/blobs/uploads/{}", repo, session));
+    calls
+}
+
+#[tokio::test]
+async fn test_push_uploads_missing_blobs_then_manifest() {
+    let registry = MockRegistry::start().await;
+    let base = FileTree::new().file("etc/os-release", "ID=polis\n").layer();
+    let app = FileTree::new()
+        .file("srv/index.html", "<h1>hello</h1>\n".repeat(40))
+        .layer();
+    assert!(
+        app.compressed.len() > CHUNK,
+        "the layer must take several PATCHes"
+    );
+    let config_digest = publish(&registry, "myorg/app", "1.0", &[&base, &app]);
+    // The destination already has the base layer
+    registry.add_blob("myorg/copy", &base.digest, base.compressed.clone());
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let mut client = client(&registry, cache_dir.path());
+    let source = format!("{}/myorg/app:1.0", registry.host());
+    client.pull_manifest(&source).await.unwrap();
+    let pulled = registry.requested_calls().len();
+
+    client
+        .push_image(
+            &format!("{}/myorg/copy:2.0", registry.host()),
+            &ImageId::from_string(&source),
+        )
+        .await
+        .unwrap();
+
+    let config = registry.blob("myorg/app", &config_digest).unwrap();
+    let mut expected = vec![format!("HEAD /v2/myorg/copy/blobs/{}", base.digest)];
+    expected.extend(upload_calls(
+        "myorg/copy",
+        &app.digest,
+        app.compressed.len(),
+        1,
+    ));
+    expected.extend(upload_calls("myorg/copy", &config_digest, config.len(), 2));
+    expected.push("PUT /v2/myorg/copy/manifests/2.0".to_string());
+    assert_eq!(registry.requested_calls()[pulled..], expected[..]);
+
+    // Blobs arrive intact and the manifest uses the spec's field names
+    assert_eq!(
+        registry.blob("myorg/copy", &app.digest).unwrap(),
+        app.compressed
+    );
+    assert_eq!(registry.blob("myorg/copy", &config_digest).unwrap(), config);
+    let manifest: serde_json::Value =
+        serde_json::from_str(&registry.manifest("myorg/copy", "2.0").unwrap()).unwrap();
+    assert_eq!(manifest["schemaVersion"], 2);
+    assert_eq!(manifest["config"]["digest"], config_digest);
+    assert_eq!(manifest["layers"][1]["digest"], app.digest);
+    assert_eq!(
+        manifest["layers"][1]["mediaType"],
+        "application/vnd.oci.image.layer.v1.tar+gzip"
+    );
+
+    // Pushing again only checks that the blobs are there
+    let before = registry.requested_calls().len();
+    client
+        .push_image(
+            &format!("{}/myorg/copy:2.0", registry.host()),
+            &ImageId::from_string(&source),
+        )
+        .await
+        .unwrap();
+    let calls = registry.requested_calls()[before..].to_vec();
+    assert_eq!(calls.len(), 4, "{:?}", calls);
+    assert!(calls[..3].iter().all(|call| call.starts_with("HEAD ")));
+}
+
+#[tokio::test]
+async fn test_push_of_unknown_image_fails_without_requests() {
+    let registry = MockRegistry::start().await;
+    let cache_dir = tempfile::tempdir().unwrap();
+    let mut client = client(&registry, cache_dir.path());
+
+    let error = client
+        .push_image(
+            &format!("{}/myorg/app:1.0", registry.host()),
+            &ImageId::from_string("myorg/missing:1.0"),
+        )
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("myorg/missing:1.0"), "{}", error);
+    assert!(registry.requested_calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_push_fetches_a_push_token_when_challenged() {
+    let registry = MockRegistry::start().await;
+    let base = FileTree::new().file("etc/os-release", "ID=polis\n").layer();
+    publish(&registry, "myorg/app", "1.0", &[&base]);
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let mut client = client(&registry, cache_dir.path());
+    let source = format!("{}/myorg/app:1.0", registry.host());
+    client.pull_manifest(&source).await.unwrap();
+    let pulled = registry.requested_calls().len();
+
+    registry.require_token("push-token");
+    client
+        .push_image(
+            &format!("{}/myorg/copy:1.0", registry.host()),
+            &ImageId::from_string(&source),
+        )
+        .await
+        .unwrap();
+
+    let requests = registry.requests()[pulled..].to_vec();
+    let calls: Vec<String> = requests
+        .iter()
+        .map(|request| format!("{} {}", request.method, request.path))
+        .collect();
+    assert_eq!(
+        calls[..4],
+        [
+            format!("HEAD /v2/myorg/copy/blobs/{}", base.digest),
+            "GET /v2/".to_string(),
+            "GET /token".to_string(),
+            format!("HEAD /v2/myorg/copy/blobs/{}", base.digest),
+        ]
+    );
+    let scope = requests[2].query.clone().unwrap_or_default();
+    assert!(scope.contains("service=mock-registry"), "{}", scope);
+    assert!(
+        scope.contains("scope=repository%3Amyorg%2Fcopy%3Apull%2Cpush"),
+        "{}",
+        scope
+    );
+    assert!(requests[3..]
+        .iter()
+        .all(|request| request.authorization.as_deref() == Some("Bearer push-token")));
+    assert!(registry.manifest("myorg/copy", "1.0").is_some());
+}
+
+#[tokio::test]
+async fn test_push_surfaces_registry_error_codes() {
+    let registry = MockRegistry::start().await;
+    let base = FileTree::new().file("etc/os-release", "ID=polis\n").layer();
+    publish(&registry, "myorg/app", "1.0", &[&base]);
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let mut client = client(&registry, cache_dir.path());
+    let source = format!("{}/myorg/app:1.0", registry.host());
+    client.pull_manifest(&source).await.unwrap();
+
+    // The HEAD goes through and opening the upload session is refused
+    registry.inject(Fault::Latency(Duration::ZERO));
+    registry.inject(Fault::RegistryError {
+        status: 403,
+        code: "DENIED".to_string(),
+        message: "requested access to the resource is denied".to_string(),
+    });
+    let error = client
+        .push_image(
+            &format!("{}/other/app:1.0", registry.host()),
+            &ImageId::from_string(&source),
+        )
+        .await
+        .unwrap_err();
+    match error {
+        PolisError::Registry {
+            status,
+            code,
+            message,
+        } => {
+            assert_eq!(status, 403);
+            assert_eq!(code, RegistryErrorCode::Denied);
+            assert!(message.contains("requested access"), "{}", message);
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    assert!(registry.manifest("other/app", "1.0").is_none());
+}
+
+#[tokio::test]
+async fn test_image_manager_push_reports_progress_per_layer() {
+    let registry = MockRegistry::start().await;
+    let base = FileTree::new().file("etc/os-release", "ID=polis\n").layer();
+    let app = FileTree::new()
+        .file("srv/index.html", "<h1>hello</h1>\n".repeat(40))
+        .layer();
+    let config_digest = publish(&registry, "myorg/app", "1.0", &[&base, &app]);
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let manager = ImageManager::new(cache_dir.path().to_path_buf())
+        .with_registry_config(registry_config(&registry));
+    let name = format!("{}/myorg/app:1.0", registry.host());
+    manager.pull(&name).await.unwrap();
+
+    // The registry reports the base layer as missing, so it is sent again
+    registry.inject(Fault::Status(404));
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    manager
+        .push(
+            &name,
+            Some(Box::new(move |progress| {
+                recorded.lock().unwrap().push(progress)
+            })),
+        )
+        .await
+        .unwrap();
+
+    let size = base.compressed.len() as u64;
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            PushProgress::Uploading {
+                digest: base.digest.clone(),
+                sent: size,
+                size,
+            },
+            PushProgress::Exists {
+                digest: app.digest.clone()
+            },
+            PushProgress::Exists {
+                digest: config_digest
+            },
+        ]
+    );
+
+    assert!(manager
+        .push(&format!("{}/myorg/missing:1.0", registry.host()), None)
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("myorg/missing:1.0"));
+}
+
+#[tokio::test]
+async fn test_image_manager_reuses_the_push_token() {
+    let registry = MockRegistry::start().await;
+    let base = FileTree::new().file("etc/os-release", "ID=polis\n").layer();
+    publish(&registry, "myorg/app", "1.0", &[&base]);
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let manager = ImageManager::new(cache_dir.path().to_path_buf())
+        .with_registry_config(registry_config(&registry));
+    let name = format!("{}/myorg/app:1.0", registry.host());
+    manager.pull(&name).await.unwrap();
+
+    // Each operation runs on a copy of the client; the token outlives it
+    registry.require_token("push-token");
+    manager.push(&name, None).await.unwrap();
+    let pushed = registry.requests().len();
+    manager.push(&name, None).await.unwrap();
+
+    let token_requests = registry
+        .requests()
+        .iter()
+        .filter(|request| request.path == "/token")
+        .count();
+    assert_eq!(token_requests, 1);
+    assert!(registry.requests()[pushed..]
+        .iter()
+        .all(|request| request.authorization.as_deref() == Some("Bearer push-token")));
+}
@@ -0,0 +1,225 @@
+use polis_core::ImageId;
+use polis_image::{ImageManager, Reference, RegistryConfig, RegistryEntry};
+use polis_test_support::{sha256_digest, FileTree, Layer, MockRegistry};
+use std::collections::HashMap;
+
+const DIGEST: &str = "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+fn registry_config(registry: &MockRegistry) -> RegistryConfig {
+    let mut registries = HashMap::new();
+    registries.insert(
+        registry.host(),
+        RegistryEntry {
+            location: format!("http://{}", registry.host()),
+            ..Default::default()
+        },
+    );
+    RegistryConfig {
+        registries,
+        ..RegistryConfig::default()
+    }
+}
+
+/// Publishes an image under `tag` and returns its manifest
+fn publish(registry: &MockRegistry, repo: &str, tag: &str, layer: &Layer) -> String {
+    let config = serde_json::json!({
+        "architecture": "amd64",
+        "os": "linux",
+        "config": {},
+        "rootfs": { "type": "layers", "diff_ids": [layer.diff_id] },
+    })
+    .to_string();
+    let config_digest = sha256_digest(config.as_bytes());
+    registry.add_blob(repo, &config_digest, config.clone().into_bytes());
+    registry.add_blob(repo, &layer.digest, layer.compressed.clone());
+
+    let manifest = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.manifest.v1+json",
+        "config": {
+            "mediaType": "application/vnd.oci.image.config.v1+json",
+            "size": config.len(),
+            "digest": config_digest,
+        },
+        "layers": [layer.descriptor()],
+    })
+    .to_string();
+    registry.add_manifest(repo, tag, &manifest);
+    manifest
+}
+
+#[test]
+fn test_parse_references() {
+    let digest = Some(DIGEST);
+    let cases = [
+        ("nginx", "docker.io", "library/nginx", Some("latest"), None),
+        (
+            "nginx:1.25",
+            "docker.io",
+            "library/nginx",
+            Some("1.25"),
+            None,
+        ),
+        ("myorg/app:1.2", "docker.io", "myorg/app", Some("1.2"), None),
+        (
+            "localhost:5000/tools/app",
+            "localhost:5000",
+            "tools/app",
+            Some("latest"),
+            None,
+        ),
+        (
+            "registry.example.com:8443/team/sub/app:v2-rc.1",
+            "registry.example.com:8443",
+            "team/sub/app",
+            Some("v2-rc.1"),
+            None,
+        ),
+        (
+            "quay.io/coreos/etcd",
+            "quay.io",
+            "coreos/etcd",
+            Some("latest"),
+            None,
+        ),
+    ];
+    for (input, registry, repository, tag, digest) in cases {
+        let reference = Reference::parse(input).unwrap();
+        assert_eq!(reference.registry, registry, "{}", input);
+        assert_eq!(reference.repository, repository, "{}", input);
+        assert_eq!(reference.tag.as_deref(), tag, "{}", input);
+        assert_eq!(reference.digest.as_deref(), digest, "{}", input);
+    }
+
+    // A digest pins the manifest; a tag next to it is kept only for display
+    let pinned = Reference::parse(&format!("nginx@{}", DIGEST)).unwrap();
+    assert_eq!(pinned.repository, "library/nginx");
+    assert_eq!(pinned.tag, None);
+    assert_eq!(pinned.digest.as_deref(), digest);
+    assert_eq!(pinned.manifest_reference(), DIGEST);
+
+    let both = Reference::parse(&format!("localhost:5000/app:1.0@{}", DIGEST)).unwrap();
+    assert_eq!(both.registry, "localhost:5000");
+    assert_eq!(both.tag.as_deref(), Some("1.0"));
+    assert_eq!(both.manifest_reference(), DIGEST);
+    assert_eq!(
+        both.to_string(),
+        format!("localhost:5000/app:1.0@{}", DIGEST)
+    );
+}
+
+#[test]
+fn test_invalid_references_are_rejected() {
+    for input in [
+        "",
+        "Nginx",
+        "myorg//app",
+        "nginx:",
+        "nginx:-bad",
+        "nginx@sha256:abc",
+        "nginx@md5:0123456789abcdef0123456789abcdef",
+        "localhost:/app",
+    ] {
+        assert!(Reference::parse(input).is_err(), "{:?}", input);
+    }
+}
+
+#[tokio::test]
+async fn test_pull_by_digest_records_the_digest() {
+    let registry = MockRegistry::start().await;
+    let layer = FileTree::new().file("app", "v1").layer();
+    let manifest = publish(&registry, "myorg/app", "1.0", &layer);
+    let digest = sha256_digest(manifest.as_bytes());
+    registry.add_manifest("myorg/app", &digest, &manifest);
+    let cache_dir = tempfile::tempdir().unwrap();
+    let manager = ImageManager::new(cache_dir.path().to_path_buf())
+        .with_registry_config(registry_config(&registry));
+
+    let name = format!("{}/myorg/app@{}", registry.host(), digest);
+    let image = manager.pull(&name).await.unwrap();
+    assert_eq!(image.digest, digest);
+    assert_eq!(image.tag, "<none>");
+    assert_eq!(image.layers, vec![layer.digest.clone()]);
+    assert!(registry
+        .requested_paths()
+        .contains(&format!("/v2/myorg/app/manifests/{}", digest)));
+}
+
+#[tokio::test]
+async fn test_pull_by_digest_rejects_mismatched_manifest() {
+    let registry = MockRegistry::start().await;
+    let layer = FileTree::new().file("app", "v1").layer();
+    let manifest = publish(&registry, "myorg/app", "1.0", &layer);
+    // The registry answers the digest with different bytes for the same document
+    let tampered = format!("{}\n", manifest);
+    let digest = sha256_digest(manifest.as_bytes());
+    registry.add_manifest("myorg/app", &digest, &tampered);
+    let cache_dir = tempfile::tempdir().unwrap();
+    let manager = ImageManager::new(cache_dir.path().to_path_buf())
+        .with_registry_config(registry_config(&registry));
+
+    let name = format!("{}/myorg/app@{}", registry.host(), digest);
+    let error = manager.pull(&name).await.unwrap_err();
+    assert!(error.to_string().contains("não confere"), "{}", error);
+
+    // No placeholder image is created in its place
+    assert!(manager.layer_store().layers().await.unwrap().is_empty());
+    assert!(!registry
+        .requested_paths()
+        .contains(&format!("/v2/myorg/app/blobs/{}", layer.digest)));
+}
+
+#[tokio::test]
+async fn test_tag_adds_a_name_without_copying_layers() {
+    let registry = MockRegistry::start().await;
+    let layer = FileTree::new().file("app", "v1").layer();
+    publish(&registry, "myorg/app", "1.0", &layer);
+    let cache_dir = tempfile::tempdir().unwrap();
+    let manager = ImageManager::new(cache_dir.path().to_path_buf())
+        .with_registry_config(registry_config(&registry));
+
+    let source = format!("{}/myorg/app:1.0", registry.host());
+    let pulled = manager.pull(&source).await.unwrap();
+    let blobs = manager.layer_store().layers().await.unwrap();
+
+    let tagged = manager.tag(&source, "app:stable").await.unwrap();
+    assert_eq!(tagged.name, "app");
+    assert_eq!(tagged.tag, "stable");
+    assert_eq!(tagged.digest, pulled.digest);
+    manager.tag(&source, "app:v1").await.unwrap();
+    assert_eq!(manager.layer_store().layers().await.unwrap(), blobs);
+
+    let mut listed: Vec<_> = manager
+        .list_images()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|image| image.name == "app")
+        .map(|image| (image.tag, image.digest))
+        .collect();
+    listed.sort();
+    assert_eq!(
+        listed,
+        vec![
+            ("stable".to_string(), pulled.digest.clone()),
+            ("v1".to_string(), pulled.digest.clone()),
+        ]
+    );
+
+    // The layers stay while any name still refers to them
+    manager
+        .remove_image(&ImageId::from_string(&source))
+        .await
+        .unwrap();
+    manager
+        .remove_image(&ImageId::from_string("app:stable"))
+        .await
+        .unwrap();
+    assert!(manager.gc_layers().await.unwrap().is_empty());
+
+    assert!(manager.tag("missing:1.0", "app:v2").await.is_err());
+    assert!(manager
+        .tag("app:v1", &format!("app@{}", DIGEST))
+        .await
+        .is_err());
+}
@@ -0,0 +1,105 @@
+use polis_image::{RegistryClient, RegistryConfig, RegistryEntry};
+use polis_test_support::{sha256_digest, Fault, MockRegistry};
+use std::collections::HashMap;
+
+fn client(registry: &MockRegistry, cache_dir: &std::path::Path) -> RegistryClient {
+    let mut registries = HashMap::new();
+    registries.insert(
+        registry.host(),
+        RegistryEntry {
+            location: format!("http://{}", registry.host()),
+            ..Default::default()
+        },
+    );
+    RegistryClient::new(cache_dir.to_path_buf()).with_config(RegistryConfig {
+        registries,
+        ..RegistryConfig::default()
+    })
+}
+
+fn patch_ranges(registry: &MockRegistry) -> Vec<String> {
+    registry
+        .requests()
+        .into_iter()
+        .filter(|request| request.method == "PATCH")
+        .map(|request| request.content_range.unwrap_or_default())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_interrupted_upload_resumes_from_checkpoint() {
+    let registry = MockRegistry::start().await;
+    let data: Vec<u8> = (0..200u32).map(|i| (i % 251) as u8).collect();
+    let digest = sha256_digest(&data);
+    let repository = format!("{}/myorg/app", registry.host());
+    let cache_dir = tempfile::tempdir().unwrap();
+
+    let checkpoint = {
+        let mut client = client(&registry, cache_dir.path());
+        let mut session = client
+            .start_blob_upload(&repository, &digest)
+            .await
+            .unwrap();
+        session.upload_chunk(&data[..64]).await.unwrap();
+        // The connection drops while the second chunk is sent
+        registry.inject(Fault::Status(502));
+        assert!(session.upload_chunk(&data[64..128]).await.is_err());
+        assert_eq!(session.uploaded_bytes, 64);
+        session.checkpoint_file.clone()
+    };
+    assert!(checkpoint.exists());
+
+    // A new client picks the session up where the registry left it
+    let before = registry.requested_calls().len();
+    let mut client = client(&registry, cache_dir.path());
+    let mut session = client
+        .start_blob_upload(&repository, &digest)
+        .await
+        .unwrap();
+    assert_eq!(session.uploaded_bytes, 64);
+    assert_eq!(
+        registry.requested_calls()[before..],
+        ["GET /v2/myorg/app/blobs/uploads/1".to_string()]
+    );
+    session.upload_chunk(&data[64..128]).await.unwrap();
+    session.upload_chunk(&data[128..]).await.unwrap();
+    session.finalize().await.unwrap();
+
+    assert_eq!(
+        patch_ranges(&registry),
+        vec!["0-63", "64-127", "64-127", "128-199"]
+    );
+    assert_eq!(registry.blob("myorg/app", &digest).unwrap().to_vec(), data);
+    assert!(!checkpoint.exists());
+}
+
+#[tokio::test]
+async fn test_expired_session_starts_over() {
+    let registry = MockRegistry::start().await;
+    let data = b"layer contents".to_vec();
+    let digest = sha256_digest(&data);
+    let repository = format!("{}/myorg/app", registry.host());
+    let cache_dir = tempfile::tempdir().unwrap();
+    let mut client = client(&registry, cache_dir.path());
+
+    let mut session = client
+        .start_blob_upload(&repository, &digest)
+        .await
+        .unwrap();
+    session.upload_chunk(&data[..4]).await.unwrap();
+    drop(session);
+
+    // The registry no longer knows the session
+    registry.inject(Fault::Status(404));
+    let mut session = client
+        .start_blob_upload(&repository, &digest)
+        .await
+        .unwrap();
+    assert_eq!(session.uploaded_bytes, 0);
+    assert!(session.upload_url.ends_with("/blobs/uploads/2"));
+    session.upload_chunk(&data).await.unwrap();
+    session.finalize().await.unwrap();
+
+    assert_eq!(patch_ranges(&registry), vec!["0-3", "0-13"]);
+    assert_eq!(registry.blob("myorg/app", &digest).unwrap().to_vec(), data);
+}
@@ -0,0 +1,167 @@
+use futures::future::BoxFuture;
+use polis_core::{PolisError, Result};
+use polis_image::{ImageSearchManager, ImageSearchResult, RegistrySearch, SearchOptions};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Delay and `(name, stars)` results returned for one registry
+type Answer = (Duration, Vec<(&'static str, u32)>);
+
+/// Canned answers per registry, optionally delayed
+#[derive(Debug, Default)]
+struct MockSearch {
+    answers: HashMap<String, Answer>,
+    failing: Vec<String>,
+    queried: Mutex<Vec<String>>,
+}
+
+impl MockSearch {
+    fn answer(mut self, registry: &str, delay: Duration, images: &[(&'static str, u32)]) -> Self {
+        self.answers
+            .insert(registry.to_string(), (delay, images.to_vec()));
+        self
+    }
+
+    fn fail(mut self, registry: &str) -> Self {
+        self.failing.push(registry.to_string());
+        self
+    }
+}
+
+fn result(registry: &str, name: &str, stars: u32) -> ImageSearchResult {
+    ImageSearchResult {
+        name: name.to_string(),
+        description: None,
+        stars,
+        official: false,
+        trusted: false,
+        automated: false,
+        registry: registry.to_string(),
+        tags: vec!["latest".to_string()],
+        size: None,
+        last_updated: None,
+    }
+}
+
+impl RegistrySearch for MockSearch {
+    fn search<'a>(
+        &'a self,
+        registry: &'a str,
+        _query: &'a str,
+        _options: &'a SearchOptions,
+    ) -> BoxFuture<'a, Result<Vec<ImageSearchResult>>> {
+        Box::pin(async move {
+            self.queried.lock().unwrap().push(registry.to_string());
+            if self.failing.iter().any(|failing| failing == registry) {
+                return Err(PolisError::Image(format!("{} unavailable", registry)));
+            }
+            let (delay, images) = self.answers.get(registry).cloned().unwrap_or_default();
+            tokio::time::sleep(delay).await;
+            Ok(images
+                .iter()
+                .map(|(name, stars)| result(registry, name, *stars))
+                .collect())
+        })
+    }
+}
+
+fn names(results: &[ImageSearchResult]) -> Vec<(String, String, u32)> {
+    results
+        .iter()
+        .map(|r| (r.registry.clone(), r.name.clone(), r.stars))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_search_merges_deduplicates_and_sorts() {
+    let backend = MockSearch::default()
+        .answer(
+            "docker.io",
+            Duration::from_millis(20),
+            &[("nginx", 500), ("redis", 300), ("nginx", 500)],
+        )
+        .answer(
+            "quay.io",
+            Duration::ZERO,
+            &[("nginx", 40), ("keycloak", 900)],
+        );
+    let mut manager = ImageSearchManager::new(vec!["docker.io".to_string(), "quay.io".to_string()])
+        .with_backend(Arc::new(backend));
+
+    let results = manager
+        .search_images("", SearchOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        names(&results),
+        [
+            ("quay.io".to_string(), "keycloak".to_string(), 900),
+            ("docker.io".to_string(), "nginx".to_string(), 500),
+            ("docker.io".to_string(), "redis".to_string(), 300),
+            ("quay.io".to_string(), "nginx".to_string(), 40),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_search_targets_selected_registries() {
+    let backend = Arc::new(
+        MockSearch::default()
+            .answer("docker.io", Duration::ZERO, &[("nginx", 500)])
+            .answer("ghcr.io", Duration::ZERO, &[("nginx", 10)]),
+    );
+    let mut manager =
+        ImageSearchManager::new(vec!["docker.io".to_string()]).with_backend(backend.clone());
+
+    let results = manager
+        .search_images(
+            "nginx",
+            SearchOptions {
+                registries: Some(vec!["ghcr.io".to_string()]),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        names(&results),
+        [("ghcr.io".to_string(), "nginx".to_string(), 10)]
+    );
+    assert_eq!(*backend.queried.lock().unwrap(), ["ghcr.io"]);
+}
+
+#[tokio::test]
+async fn test_slow_or_failing_registry_does_not_fail_search() {
+    let backend = MockSearch::default()
+        .answer("docker.io", Duration::ZERO, &[("nginx", 500)])
+        .answer("slow.example", Duration::from_secs(30), &[("nginx", 1)])
+        .fail("down.example");
+    let mut manager = ImageSearchManager::new(vec![
+        "docker.io".to_string(),
+        "slow.example".to_string(),
+        "down.example".to_string(),
+    ])
+    .with_backend(Arc::new(backend))
+    .with_timeout(Duration::from_millis(100));
+
+    let results = tokio::time::timeout(
+        Duration::from_secs(5),
+        manager.search_images("nginx", SearchOptions::default()),
+    )
+    .await
+    .expect("the slow registry must not hold the search")
+    .unwrap();
+    assert_eq!(
+        names(&results),
+        [("docker.io".to_string(), "nginx".to_string(), 500)]
+    );
+
+    // With no registry answering, the error is reported
+    let mut manager = ImageSearchManager::new(vec!["down.example".to_string()])
+        .with_backend(Arc::new(MockSearch::default().fail("down.example")));
+    assert!(manager
+        .search_images("nginx", SearchOptions::default())
+        .await
+        .is_err());
+}
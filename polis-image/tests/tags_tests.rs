@@ -0,0 +1,65 @@
+use polis_image::{RegistryClient, RegistryConfig, RegistryEntry};
+use polis_test_support::MockRegistry;
+use std::collections::HashMap;
+
+fn client(registry: &MockRegistry, cache_dir: &std::path::Path) -> RegistryClient {
+    let mut registries = HashMap::new();
+    registries.insert(
+        registry.host(),
+        RegistryEntry {
+            location: format!("http://{}", registry.host()),
+            ..Default::default()
+        },
+    );
+    RegistryClient::new(cache_dir.to_path_buf()).with_config(RegistryConfig {
+        registries,
+        ..RegistryConfig::default()
+    })
+}
+
+#[tokio::test]
+async fn test_list_tags_follows_pagination() {
+    let registry = MockRegistry::start().await;
+    for tag in ["latest", "1.0", "2.0", "1.1"] {
+        registry.add_manifest("myorg/app", tag, "{}");
+    }
+    // Manifests addressed by digest are not tags
+    registry.add_manifest("myorg/app", "sha256:abc", "{}");
+    registry.add_manifest("myorg/other", "9.9", "{}");
+    registry.paginate_tags(2);
+
+    let cache_dir = tempfile::tempdir().unwrap();
+    let mut client =
+        client(&registry, cache_dir.path()).with_auth("user".to_string(), "secret".to_string());
+    let tags = client
+        .list_tags(&format!("{}/myorg/app", registry.host()))
+        .await
+        .unwrap();
+    assert_eq!(tags, vec!["1.0", "1.1", "2.0", "latest"]);
+
+    let requests = registry.requests();
+    assert_eq!(
+        requests
+            .iter()
+            .map(|request| request.path.as_str())
+            .collect::<Vec<_>>(),
+        vec!["/v2/myorg/app/tags/list", "/v2/myorg/app/tags/list"]
+    );
+    // Every page carries the credentials
+    assert!(requests
+        .iter()
+        .all(|request| request.authorization.as_deref() == Some("Basic dXNlcjpzZWNyZXQ=")));
+}
+
+#[tokio::test]
+async fn test_list_tags_of_unknown_repository_fails() {
+    let registry = MockRegistry::start().await;
+    let cache_dir = tempfile::tempdir().unwrap();
+    let mut client = client(&registry, cache_dir.path());
+
+    let error = client
+        .list_tags(&format!("{}/myorg/missing", registry.host()))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("404"), "{}", error);
+}
@@ -0,0 +1,640 @@
+use crate::AlertSeverity;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use notify::{RecursiveMode, Watcher};
+use polis_core::{PolisError, Result};
+use polis_stats::{ContainerMetrics, ContainerStatsCollector, MetricField};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// File the active alerts are written to, relative to the state directory
+pub const ACTIVE_ALERTS_FILE: &str = "alerts/active.json";
+
+/// Timeout of a webhook notification by default
+pub const DEFAULT_ALERT_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Which value of which containers a rule watches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricSelector {
+    pub field: MetricField,
+    /// Only this container; every container when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// Labels the container must carry
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub match_labels: HashMap<String, String>,
+}
+
+impl MetricSelector {
+    pub fn new(field: MetricField) -> Self {
+        Self {
+            field,
+            container: None,
+            match_labels: HashMap::new(),
+        }
+    }
+
+    pub fn with_container(mut self, container_id: &str) -> Self {
+        self.container = Some(container_id.to_string());
+        self
+    }
+
+    pub fn with_label(mut self, key: &str, value: &str) -> Self {
+        self.match_labels.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Whether a container with these labels is watched
+    pub fn matches(&self, container_id: &str, labels: &HashMap<String, String>) -> bool {
+        self.container
+            .as_deref()
+            .is_none_or(|id| id == container_id)
+            && self
+                .match_labels
+                .iter()
+                .all(|(key, value)| labels.get(key) == Some(value))
+    }
+}
+
+/// How a reading is compared with the threshold
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Comparison {
+    #[default]
+    #[serde(rename = ">")]
+    Above,
+    #[serde(rename = ">=")]
+    AtLeast,
+    #[serde(rename = "<")]
+    Below,
+    #[serde(rename = "<=")]
+    AtMost,
+}
+
+impl Comparison {
+    /// Whether `value` breaches `threshold`
+    pub fn breached(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparison::Above => value > threshold,
+            Comparison::AtLeast => value >= threshold,
+            Comparison::Below => value < threshold,
+            Comparison::AtMost => value <= threshold,
+        }
+    }
+}
+
+/// Alert when a container metric breaches a threshold for a while, e.g.
+/// memory above 90% of the limit for two minutes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// Unique among the rules of an engine
+    pub name: String,
+    pub metric: MetricSelector,
+    pub threshold: f64,
+    #[serde(default)]
+    pub comparison: Comparison,
+    /// How long the threshold must stay breached before the alert fires;
+    /// written as `90`, `30s`, `2m` or `1h30m` in rule files
+    #[serde(default, with = "duration_text")]
+    pub for_duration: Duration,
+    #[serde(default = "default_severity")]
+    pub severity: AlertSeverity,
+    /// Copied onto every alert of the rule
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub labels: HashMap<String, String>,
+}
+
+fn default_severity() -> AlertSeverity {
+    AlertSeverity::Medium
+}
+
+impl AlertRule {
+    pub fn new(name: &str, metric: MetricSelector, comparison: Comparison, threshold: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            metric,
+            threshold,
+            comparison,
+            for_duration: Duration::ZERO,
+            severity: default_severity(),
+            labels: HashMap::new(),
+        }
+    }
+
+    pub fn with_for_duration(mut self, for_duration: Duration) -> Self {
+        self.for_duration = for_duration;
+        self
+    }
+
+    pub fn with_severity(mut self, severity: AlertSeverity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn with_label(mut self, key: &str, value: &str) -> Self {
+        self.labels.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+/// Layout of a rule file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertRuleFile {
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+}
+
+/// Parse the rules of a YAML rule file
+pub fn parse_alert_rules(yaml: &str) -> Result<Vec<AlertRule>> {
+    let file: AlertRuleFile = serde_yaml::from_str(yaml)
+        .map_err(|e| PolisError::Config(format!("Invalid alert rules: {}", e)))?;
+    validate_rules(&file.rules)?;
+    Ok(file.rules)
+}
+
+fn validate_rules(rules: &[AlertRule]) -> Result<()> {
+    let mut names = HashSet::new();
+    for rule in rules {
+        if rule.name.is_empty() {
+            return Err(PolisError::Config("Alert rule without a name".to_string()));
+        }
+        if !names.insert(rule.name.as_str()) {
+            return Err(PolisError::Config(format!(
+                "Duplicate alert rule '{}'",
+                rule.name
+            )));
+        }
+        if !rule.threshold.is_finite() {
+            return Err(PolisError::Config(format!(
+                "Alert rule '{}' has no finite threshold",
+                rule.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Lifecycle of an alert: pending until the rule's `for_duration` has
+/// passed, then firing until the threshold is no longer breached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    Pending,
+    Firing,
+    Resolved,
+}
+
+/// An alert of one rule for one container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceAlert {
+    pub rule: String,
+    pub container_id: String,
+    pub state: AlertState,
+    pub severity: AlertSeverity,
+    pub field: MetricField,
+    /// Latest reading
+    pub value: f64,
+    pub threshold: f64,
+    pub labels: HashMap<String, String>,
+    /// When the threshold was first breached
+    pub active_since: DateTime<Utc>,
+    pub fired_at: Option<DateTime<Utc>>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Receives alerts when they fire and when they resolve
+#[async_trait]
+pub trait AlertNotifier: Send + Sync {
+    async fn notify(&self, alert: &ResourceAlert) -> Result<()>;
+}
+
+/// Logs alerts through `tracing`
+#[derive(Debug, Clone, Default)]
+pub struct LogNotifier;
+
+#[async_trait]
+impl AlertNotifier for LogNotifier {
+    async fn notify(&self, alert: &ResourceAlert) -> Result<()> {
+        match alert.state {
+            AlertState::Firing => tracing::warn!(
+                "Alert {} firing for {}: {:?} = {} (threshold {})",
+                alert.rule,
+                alert.container_id,
+                alert.field,
+                alert.value,
+                alert.threshold
+            ),
+            _ => tracing::info!(
+                "Alert {} resolved for {}: {:?} = {}",
+                alert.rule,
+                alert.container_id,
+                alert.field,
+                alert.value
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// POSTs every alert as JSON to a URL
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    url: String,
+    client: reqwest::Client,
+    timeout: Duration,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str) -> Result<Self> {
+        reqwest::Url::parse(url)
+            .map_err(|e| PolisError::Config(format!("Invalid webhook URL {}: {}", url, e)))?;
+        Ok(Self {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+            timeout: DEFAULT_ALERT_WEBHOOK_TIMEOUT,
+        })
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[async_trait]
+impl AlertNotifier for WebhookNotifier {
+    async fn notify(&self, alert: &ResourceAlert) -> Result<()> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(alert)
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(|e| PolisError::Api(format!("Webhook {} failed: {}", self.url, e)))?;
+        if !response.status().is_success() {
+            return Err(PolisError::Api(format!(
+                "Webhook {} answered HTTP {}",
+                self.url,
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Hands alerts to a programmatic consumer over a channel
+#[derive(Debug, Clone)]
+pub struct ChannelNotifier {
+    sender: mpsc::UnboundedSender<ResourceAlert>,
+}
+
+impl ChannelNotifier {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<ResourceAlert>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self { sender }, receiver)
+    }
+}
+
+#[async_trait]
+impl AlertNotifier for ChannelNotifier {
+    async fn notify(&self, alert: &ResourceAlert) -> Result<()> {
+        // A consumer that went away is not an error of the engine
+        let _ = self.sender.send(alert.clone());
+        Ok(())
+    }
+}
+
+/// An alert and when, on the engine's clock, its threshold was first breached
+struct Tracked {
+    alert: ResourceAlert,
+    breached_since: Instant,
+}
+
+/// Evaluates alert rules against container samples.
+///
+/// Every (rule, container) pair has at most one alert. It is pending while
+/// the threshold is breached for less than the rule's `for_duration` and
+/// dropped without notification if the breach ends before that; it then
+/// fires once, however long the breach lasts, and resolves once. Notifiers
+/// only see firing and resolved alerts.
+pub struct AlertEngine {
+    rules: RwLock<Vec<AlertRule>>,
+    /// Pending and firing alerts by (rule, container)
+    alerts: RwLock<BTreeMap<(String, String), Tracked>>,
+    notifiers: Vec<Arc<dyn AlertNotifier>>,
+    state_file: Option<PathBuf>,
+}
+
+impl AlertEngine {
+    pub fn new() -> Self {
+        Self {
+            rules: RwLock::new(Vec::new()),
+            alerts: RwLock::new(BTreeMap::new()),
+            notifiers: Vec::new(),
+            state_file: None,
+        }
+    }
+
+    pub fn with_notifier(mut self, notifier: Arc<dyn AlertNotifier>) -> Self {
+        self.notifiers.push(notifier);
+        self
+    }
+
+    /// Write the pending and firing alerts to `path` whenever they change,
+    /// for `load_active_alerts`
+    pub fn with_state_file(mut self, path: PathBuf) -> Self {
+        self.state_file = Some(path);
+        self
+    }
+
+    pub async fn rules(&self) -> Vec<AlertRule> {
+        self.rules.read().await.clone()
+    }
+
+    /// Replace the rules. Alerts of unchanged rules carry on; alerts of
+    /// removed or changed rules are dropped, and firing ones resolved.
+    pub async fn set_rules(&self, rules: Vec<AlertRule>) -> Result<()> {
+        validate_rules(&rules)?;
+        let mut resolved = Vec::new();
+        {
+            let mut current = self.rules.write().await;
+            let mut alerts = self.alerts.write().await;
+            alerts.retain(|(name, _), tracked| {
+                if rules.iter().any(|rule| rule.name == *name)
+                    && current.iter().find(|rule| rule.name == *name)
+                        == rules.iter().find(|rule| rule.name == *name)
+                {
+                    return true;
+                }
+                if tracked.alert.state == AlertState::Firing {
+                    resolved.push(resolve(tracked.alert.clone()));
+                }
+                false
+            });
+            *current = rules;
+        }
+        self.persist().await;
+        self.deliver(&resolved).await;
+        Ok(())
+    }
+
+    /// Replace the rules with those of a YAML rule file; returns how many
+    /// were loaded
+    pub async fn load_rules_file(&self, path: &Path) -> Result<usize> {
+        let rules = parse_alert_rules(&std::fs::read_to_string(path)?)?;
+        let count = rules.len();
+        self.set_rules(rules).await?;
+        Ok(count)
+    }
+
+    /// Load a rule file, then reload it whenever it changes until the task
+    /// is aborted. A file that fails to load leaves the previous rules in
+    /// place.
+    pub async fn watch_rules_file(self: &Arc<Self>, path: &Path) -> Result<JoinHandle<()>> {
+        self.load_rules_file(path).await?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if event.is_ok() {
+                    let _ = tx.send(());
+                }
+            })
+            .map_err(|e| {
+                PolisError::Config(format!("Failed to watch {}: {}", path.display(), e))
+            })?;
+        // Editors often replace the file, so watch the directory holding it
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| {
+                PolisError::Config(format!("Failed to watch {}: {}", path.display(), e))
+            })?;
+
+        let engine = Arc::clone(self);
+        let path = path.to_path_buf();
+        Ok(tokio::spawn(async move {
+            let _watcher = watcher;
+            while rx.recv().await.is_some() {
+                // Editors emit bursts of events; wait for them to settle
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                while rx.try_recv().is_ok() {}
+                match engine.load_rules_file(&path).await {
+                    Ok(count) => {
+                        tracing::info!("Reloaded {} alert rules from {}", count, path.display())
+                    }
+                    Err(e) => tracing::warn!(
+                        "Keeping the previous alert rules, {} failed to load: {}",
+                        path.display(),
+                        e
+                    ),
+                }
+            }
+        }))
+    }
+
+    /// Evaluate every rule watching the container against a new sample
+    pub async fn observe(&self, sample: &ContainerMetrics, labels: &HashMap<String, String>) {
+        let now = Instant::now();
+        let mut notifications = Vec::new();
+        let mut changed = false;
+        {
+            let rules = self.rules.read().await;
+            let mut alerts = self.alerts.write().await;
+            for rule in rules.iter() {
+                if !rule.metric.matches(&sample.container_id, labels) {
+                    continue;
+                }
+                let key = (rule.name.clone(), sample.container_id.clone());
+                let value = rule.metric.field.reading(sample);
+                if !rule.comparison.breached(value, rule.threshold) {
+                    if let Some(tracked) = alerts.remove(&key) {
+                        changed = true;
+                        if tracked.alert.state == AlertState::Firing {
+                            let mut alert = resolve(tracked.alert);
+                            alert.value = value;
+                            notifications.push(alert);
+                        }
+                    }
+                    continue;
+                }
+
+                let tracked = alerts.entry(key).or_insert_with(|| {
+                    changed = true;
+                    Tracked {
+                        alert: new_alert(rule, sample, labels),
+                        breached_since: now,
+                    }
+                });
+                tracked.alert.value = value;
+                if tracked.alert.state == AlertState::Pending
+                    && now.saturating_duration_since(tracked.breached_since) >= rule.for_duration
+                {
+                    tracked.alert.state = AlertState::Firing;
+                    tracked.alert.fired_at = Some(Utc::now());
+                    notifications.push(tracked.alert.clone());
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            self.persist().await;
+        }
+        self.deliver(&notifications).await;
+    }
+
+    /// Evaluate every sample the collector publishes until the task is
+    /// aborted or the collector goes away
+    pub fn run(self: &Arc<Self>, collector: Arc<ContainerStatsCollector>) -> JoinHandle<()> {
+        let engine = Arc::clone(self);
+        let mut samples = collector.subscribe_all();
+        tokio::spawn(async move {
+            loop {
+                match samples.recv().await {
+                    Ok(sample) => {
+                        let labels = collector.get_labels(&sample.container_id).await;
+                        engine.observe(&sample, &labels).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Alert engine skipped {} samples", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Pending and firing alerts, by rule then container
+    pub async fn active_alerts(&self) -> Vec<ResourceAlert> {
+        self.alerts
+            .read()
+            .await
+            .values()
+            .map(|tracked| tracked.alert.clone())
+            .collect()
+    }
+
+    async fn deliver(&self, alerts: &[ResourceAlert]) {
+        for alert in alerts {
+            for notifier in &self.notifiers {
+                if let Err(e) = notifier.notify(alert).await {
+                    tracing::warn!(
+                        "Failed to notify alert {} for {}: {}",
+                        alert.rule,
+                        alert.container_id,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    async fn persist(&self) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let alerts = self.active_alerts().await;
+        if let Err(e) = save_active_alerts(path, &alerts) {
+            tracing::warn!("Failed to save active alerts to {}: {}", path.display(), e);
+        }
+    }
+}
+
+impl Default for AlertEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn new_alert(
+    rule: &AlertRule,
+    sample: &ContainerMetrics,
+    container_labels: &HashMap<String, String>,
+) -> ResourceAlert {
+    let mut labels = container_labels.clone();
+    labels.extend(rule.labels.clone());
+    ResourceAlert {
+        rule: rule.name.clone(),
+        container_id: sample.container_id.clone(),
+        state: AlertState::Pending,
+        severity: rule.severity.clone(),
+        field: rule.metric.field,
+        value: rule.metric.field.reading(sample),
+        threshold: rule.threshold,
+        labels,
+        active_since: Utc::now(),
+        fired_at: None,
+        resolved_at: None,
+    }
+}
+
+fn resolve(mut alert: ResourceAlert) -> ResourceAlert {
+    alert.state = AlertState::Resolved;
+    alert.resolved_at = Some(Utc::now());
+    alert
+}
+
+/// Path of the active alerts file under a state directory
+pub fn active_alerts_path(state_dir: &Path) -> PathBuf {
+    state_dir.join(ACTIVE_ALERTS_FILE)
+}
+
+fn save_active_alerts(path: &Path, alerts: &[ResourceAlert]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Write then rename so readers never see a partial file
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(alerts)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Alerts written by an engine with a state file; empty when it never wrote
+/// one
+pub fn load_active_alerts(path: &Path) -> Result<Vec<ResourceAlert>> {
+    match std::fs::read(path) {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// `for_duration` as `2m`-style text, read back from text or whole seconds
+mod duration_text {
+    use polis_core::{format_duration, parse_duration};
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Duration,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_duration(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Duration, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Seconds(u64),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Seconds(seconds) => Ok(Duration::from_secs(seconds)),
+            Raw::Text(text) => parse_duration(&text).map_err(serde::de::Error::custom),
+        }
+    }
+}
@@ -1,13 +1,21 @@
+pub mod alerting;
 pub mod alerts;
 pub mod dashboard;
 pub mod exporter;
 pub mod health;
 pub mod logs;
 pub mod metrics;
+pub mod prometheus;
 
+pub use alerting::{
+    active_alerts_path, load_active_alerts, parse_alert_rules, AlertEngine, AlertNotifier,
+    AlertRuleFile, AlertState, ChannelNotifier, Comparison, LogNotifier, MetricSelector,
+    ResourceAlert, WebhookNotifier,
+};
 pub use alerts::*;
 pub use dashboard::*;
 pub use exporter::*;
 pub use health::*;
 pub use logs::*;
 pub use metrics::*;
+pub use prometheus::*;
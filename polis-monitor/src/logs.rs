@@ -1,7 +1,16 @@
-use polis_core::{PolisError, Result};
+use chrono::{DateTime, Utc};
+use polis_core::{
+    container_log_dir, rotated_log_path, ContainerId, ContainerLogRecord, LogStream, PolisError,
+    Result, RuntimeConfig, CONTAINER_LOG_FILE,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// Intervalo entre verificações de novas linhas ao acompanhar um log
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(200);
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum LogLevel {
@@ -47,10 +56,31 @@ pub struct LogStats {
     pub newest_entry: Option<u64>,
 }
 
+/// Opções de leitura da saída de um container
+#[derive(Debug, Clone, Default)]
+pub struct LogReadOptions {
+    /// Apenas as últimas N linhas já gravadas
+    pub tail: Option<usize>,
+    /// Apenas linhas gravadas a partir deste instante
+    pub since: Option<DateTime<Utc>>,
+    /// Continua entregando as linhas novas até o leitor ser descartado
+    pub follow: bool,
+    /// Saídas incluídas; vazio inclui stdout e stderr
+    pub streams: Vec<LogStream>,
+}
+
+impl LogReadOptions {
+    fn matches(&self, record: &ContainerLogRecord) -> bool {
+        (self.streams.is_empty() || self.streams.contains(&record.stream))
+            && self.since.is_none_or(|since| record.time >= since)
+    }
+}
+
 pub struct LogManager {
     logs: Vec<LogEntry>,
     next_log_id: u64,
     max_logs: usize,
+    container_log_root: PathBuf,
 }
 
 impl LogManager {
@@ -59,9 +89,28 @@ impl LogManager {
             logs: Vec::new(),
             next_log_id: 1,
             max_logs,
+            container_log_root: RuntimeConfig::default().root_dir,
         }
     }
 
+    /// Diretório raiz do runtime onde ficam os logs dos containers
+    pub fn with_container_log_root(mut self, root_dir: impl Into<PathBuf>) -> Self {
+        self.container_log_root = root_dir.into();
+        self
+    }
+
+    /// Lê a saída gravada pelo runtime para um container, incluindo os
+    /// arquivos já rotacionados
+    pub async fn read(
+        &self,
+        container_id: &ContainerId,
+        options: LogReadOptions,
+    ) -> Result<ContainerLogReader> {
+        let path =
+            container_log_dir(&self.container_log_root, container_id).join(CONTAINER_LOG_FILE);
+        ContainerLogReader::open(path, options).await
+    }
+
     pub async fn add_log(
         &mut self,
         level: LogLevel,
@@ -370,3 +419,183 @@ impl Default for LogManager {
         Self::new(10000) // Default max 10,000 logs
     }
 }
+
+/// Arquivo de log atual aberto por um leitor que acompanha o container
+struct FollowedFile {
+    reader: BufReader<tokio::fs::File>,
+    metadata: std::fs::Metadata,
+    /// Linha ainda incompleta no fim do arquivo
+    partial: String,
+    /// O arquivo foi rotacionado e só falta ler o que restou nele
+    rotated: bool,
+}
+
+impl FollowedFile {
+    async fn open(path: &Path) -> Result<Option<Self>> {
+        let file = match tokio::fs::File::open(path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(Self {
+            metadata: file.metadata().await?,
+            reader: BufReader::new(file),
+            partial: String::new(),
+            rotated: false,
+        }))
+    }
+
+    /// Arquivo a ler depois de `finished`, que foi rotacionado: o que veio
+    /// logo depois dele na rotação ou, se ele já foi descartado, o mais
+    /// antigo que restou. Várias rotações podem ter ocorrido desde a última
+    /// leitura.
+    async fn open_next(path: &Path, finished: &FollowedFile) -> Result<Option<Self>> {
+        let mut rotated = Vec::new();
+        while rotated_log_path(path, rotated.len() + 1).exists() {
+            rotated.push(rotated_log_path(path, rotated.len() + 1));
+        }
+        let mut next = rotated.len();
+        for (index, rotated_path) in rotated.iter().enumerate() {
+            if let Ok(metadata) = tokio::fs::metadata(rotated_path).await {
+                if !finished.replaced_by(&metadata) {
+                    next = index;
+                    break;
+                }
+            }
+        }
+
+        let file = Self::open(&rotated_log_path(path, next)).await?;
+        Ok(file.map(|mut file| {
+            // Um arquivo rotacionado não recebe mais linhas
+            file.rotated = next > 0;
+            file
+        }))
+    }
+
+    /// Próxima linha completa, ou `None` no fim do arquivo
+    async fn next_line(&mut self) -> Result<Option<String>> {
+        loop {
+            if self.reader.read_line(&mut self.partial).await? == 0 {
+                return Ok(None);
+            }
+            if self.partial.ends_with('\n') {
+                return Ok(Some(std::mem::take(&mut self.partial)));
+            }
+        }
+    }
+
+    /// Indica se `path` passou a ser outro arquivo
+    fn replaced_by(&self, current: &std::fs::Metadata) -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            current.ino() != self.metadata.ino() || current.dev() != self.metadata.dev()
+        }
+        #[cfg(not(unix))]
+        {
+            current.created().ok() != self.metadata.created().ok()
+        }
+    }
+}
+
+/// Linhas de log de um container, das mais antigas para as mais novas.
+///
+/// Ao acompanhar o log (`follow`), o leitor detecta a rotação do arquivo
+/// atual, termina de ler o arquivo rotacionado e segue pelos arquivos
+/// rotacionados depois dele até chegar ao novo.
+pub struct ContainerLogReader {
+    path: PathBuf,
+    options: LogReadOptions,
+    backlog: VecDeque<ContainerLogRecord>,
+    current: Option<FollowedFile>,
+}
+
+impl ContainerLogReader {
+    async fn open(path: PathBuf, options: LogReadOptions) -> Result<Self> {
+        let mut reader = Self {
+            path,
+            options,
+            backlog: VecDeque::new(),
+            current: None,
+        };
+
+        // Arquivos rotacionados, do mais antigo para o mais recente
+        let mut rotated = Vec::new();
+        while rotated_log_path(&reader.path, rotated.len() + 1).exists() {
+            rotated.push(rotated_log_path(&reader.path, rotated.len() + 1));
+        }
+        for path in rotated.iter().rev() {
+            let content = match tokio::fs::read_to_string(path).await {
+                Ok(content) => content,
+                // Removido por uma rotação durante a leitura
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for line in content.lines() {
+                reader.push_backlog(line);
+            }
+        }
+
+        reader.current = FollowedFile::open(&reader.path).await?;
+        if let Some(mut file) = reader.current.take() {
+            while let Some(line) = file.next_line().await? {
+                reader.push_backlog(&line);
+            }
+            reader.current = Some(file);
+        }
+        Ok(reader)
+    }
+
+    fn push_backlog(&mut self, line: &str) {
+        let Some(record) = ContainerLogRecord::parse(line) else {
+            return;
+        };
+        if !self.options.matches(&record) {
+            return;
+        }
+        self.backlog.push_back(record);
+        if let Some(tail) = self.options.tail {
+            while self.backlog.len() > tail {
+                self.backlog.pop_front();
+            }
+        }
+    }
+
+    /// Próxima linha de log. Sem `follow` retorna `None` após a última linha
+    /// gravada; com `follow` espera por novas linhas.
+    pub async fn next_record(&mut self) -> Result<Option<ContainerLogRecord>> {
+        if let Some(record) = self.backlog.pop_front() {
+            return Ok(Some(record));
+        }
+        if !self.options.follow {
+            return Ok(None);
+        }
+
+        loop {
+            let Some(file) = self.current.as_mut() else {
+                self.current = FollowedFile::open(&self.path).await?;
+                if self.current.is_none() {
+                    tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+                }
+                continue;
+            };
+
+            if let Some(line) = file.next_line().await? {
+                match ContainerLogRecord::parse(&line) {
+                    Some(record) if self.options.matches(&record) => return Ok(Some(record)),
+                    _ => continue,
+                }
+            }
+
+            if file.rotated {
+                // O restante do arquivo antigo já foi lido
+                self.current = FollowedFile::open_next(&self.path, file).await?;
+                continue;
+            }
+            match tokio::fs::metadata(&self.path).await {
+                Ok(metadata) if file.replaced_by(&metadata) => file.rotated = true,
+                _ => tokio::time::sleep(FOLLOW_POLL_INTERVAL).await,
+            }
+        }
+    }
+}
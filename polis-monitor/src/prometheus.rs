@@ -0,0 +1,391 @@
+use axum::http::header;
+use axum::routing::get;
+use axum::Router;
+use polis_core::Result;
+use polis_stats::ContainerStatsCollector;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+/// Path the metrics are served on unless configured otherwise
+pub const DEFAULT_METRICS_PATH: &str = "/metrics";
+
+/// Content type of the text exposition format
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Container labels exported as Prometheus labels unless configured otherwise
+pub const DEFAULT_CONTAINER_LABELS: &[&str] = &["deployment", "namespace"];
+
+/// Desired and ready replicas of a deployment
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeploymentGauge {
+    pub deployment: String,
+    pub namespace: String,
+    pub replicas_desired: u32,
+    pub replicas_ready: u32,
+}
+
+/// Latest status of a health check
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HealthCheckGauge {
+    pub check: String,
+    pub target: String,
+    /// Empty when the check does not belong to a deployment
+    pub deployment: String,
+    pub namespace: String,
+    pub healthy: bool,
+}
+
+/// Request counters of a load balancer, cumulative since it was created
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoadBalancerCounters {
+    pub service: String,
+    pub namespace: String,
+    pub requests_total: u64,
+    pub successful_requests_total: u64,
+    pub failed_requests_total: u64,
+}
+
+/// Orchestrator state exported next to the container metrics
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrchestratorMetrics {
+    pub deployments: Vec<DeploymentGauge>,
+    pub health_checks: Vec<HealthCheckGauge>,
+    pub load_balancers: Vec<LoadBalancerCounters>,
+}
+
+/// Serves the metrics of a `ContainerStatsCollector` and the latest
+/// orchestrator state in the Prometheus text exposition format.
+///
+/// Container samples are labelled with `container_id` plus one label per
+/// configured container label (`deployment` and `namespace` by default),
+/// empty for containers that do not carry it. Counters are the cumulative
+/// values of the samples, never rates, so they only go back down when the
+/// source restarts. Clones share the orchestrator state.
+#[derive(Clone)]
+pub struct PrometheusExporter {
+    collector: Arc<ContainerStatsCollector>,
+    orchestrator: Arc<RwLock<OrchestratorMetrics>>,
+    /// Container label key and the Prometheus label it is exported as
+    container_labels: Vec<(String, String)>,
+    path: String,
+}
+
+/// HTTP listener serving a `PrometheusExporter`; it stops when dropped
+pub struct MetricsServer {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Counter,
+    Gauge,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+        }
+    }
+}
+
+/// Label names and values of one sample
+type Labels<'a> = Vec<(&'static str, &'a str)>;
+
+impl PrometheusExporter {
+    pub fn new(collector: Arc<ContainerStatsCollector>) -> Self {
+        Self {
+            collector,
+            orchestrator: Arc::new(RwLock::new(OrchestratorMetrics::default())),
+            container_labels: Vec::new(),
+            path: DEFAULT_METRICS_PATH.to_string(),
+        }
+        .with_container_labels(DEFAULT_CONTAINER_LABELS)
+    }
+
+    /// Export these container labels, replacing the defaults. Each name is
+    /// sanitized into a valid label name; names that collide with
+    /// `container_id` or an earlier label are skipped.
+    pub fn with_container_labels<S: AsRef<str>>(mut self, keys: &[S]) -> Self {
+        self.container_labels.clear();
+        for key in keys {
+            let name = sanitize_label_name(key.as_ref());
+            if name == "container_id" || self.container_labels.iter().any(|(_, n)| *n == name) {
+                continue;
+            }
+            self.container_labels.push((key.as_ref().to_string(), name));
+        }
+        self
+    }
+
+    /// Serve the metrics on `path` instead of `/metrics`
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = if path.starts_with('/') {
+            path.to_string()
+        } else {
+            format!("/{}", path)
+        };
+        self
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Replace the orchestrator state exported from now on
+    pub async fn set_orchestrator_metrics(&self, metrics: OrchestratorMetrics) {
+        *self.orchestrator.write().await = metrics;
+    }
+
+    /// Render every container and orchestrator metric
+    pub async fn render(&self) -> String {
+        let mut samples = self.collector.get_all_metrics().await.unwrap_or_default();
+        samples.sort_by(|a, b| a.container_id.cmp(&b.container_id));
+        let all_labels = self.collector.get_all_labels().await;
+        let no_labels = HashMap::new();
+        let labels: HashMap<String, Vec<(String, String)>> = samples
+            .iter()
+            .map(|sample| {
+                let container = all_labels.get(&sample.container_id).unwrap_or(&no_labels);
+                let values = self
+                    .container_labels
+                    .iter()
+                    .map(|(key, name)| {
+                        (
+                            name.clone(),
+                            container.get(key).cloned().unwrap_or_default(),
+                        )
+                    })
+                    .collect();
+                (sample.container_id.clone(), values)
+            })
+            .collect();
+
+        let mut out = String::with_capacity(samples.len() * 4096 + 4096);
+        // Writing into a String cannot fail
+        let _ = polis_stats::PrometheusExporter::new()
+            .write_text_with_labels(&mut out, &samples, &labels);
+        let _ = write_orchestrator(&mut out, &*self.orchestrator.read().await);
+        out
+    }
+
+    /// Serve the metrics over HTTP on `addr`, at the configured path
+    pub async fn serve(&self, addr: SocketAddr) -> Result<MetricsServer> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+
+        let exporter = self.clone();
+        let app = Router::new().route(
+            &self.path,
+            get(move || {
+                let exporter = exporter.clone();
+                async move {
+                    (
+                        [(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)],
+                        exporter.render().await,
+                    )
+                }
+            }),
+        );
+        let task = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::warn!("Metrics listener on {} stopped: {}", local_addr, e);
+            }
+        });
+        Ok(MetricsServer { local_addr, task })
+    }
+}
+
+impl MetricsServer {
+    /// Address the metrics are served on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn write_orchestrator<W: Write>(out: &mut W, metrics: &OrchestratorMetrics) -> std::fmt::Result {
+    let mut per_namespace: BTreeMap<&str, u32> = BTreeMap::new();
+    for d in &metrics.deployments {
+        *per_namespace.entry(&d.namespace).or_default() += 1;
+    }
+    write_family(
+        out,
+        "polis_deployments",
+        "Number of deployments",
+        Kind::Gauge,
+        per_namespace
+            .iter()
+            .map(|(namespace, count)| (vec![("namespace", *namespace)], *count as f64)),
+    )?;
+    write_family(
+        out,
+        "polis_deployment_replicas_desired",
+        "Replicas a deployment asks for",
+        Kind::Gauge,
+        metrics
+            .deployments
+            .iter()
+            .map(|d| (deployment_labels(d), d.replicas_desired as f64)),
+    )?;
+    write_family(
+        out,
+        "polis_deployment_replicas_ready",
+        "Replicas of a deployment whose container is running",
+        Kind::Gauge,
+        metrics
+            .deployments
+            .iter()
+            .map(|d| (deployment_labels(d), d.replicas_ready as f64)),
+    )?;
+    write_family(
+        out,
+        "polis_health_check_status",
+        "Latest health check result (1 = healthy)",
+        Kind::Gauge,
+        metrics.health_checks.iter().map(|check| {
+            (
+                vec![
+                    ("check", check.check.as_str()),
+                    ("target", check.target.as_str()),
+                    ("deployment", check.deployment.as_str()),
+                    ("namespace", check.namespace.as_str()),
+                ],
+                if check.healthy { 1.0 } else { 0.0 },
+            )
+        }),
+    )?;
+    write_family(
+        out,
+        "polis_load_balancer_requests_total",
+        "Requests handled by a load balancer",
+        Kind::Counter,
+        metrics
+            .load_balancers
+            .iter()
+            .map(|lb| (load_balancer_labels(lb), lb.requests_total as f64)),
+    )?;
+    write_family(
+        out,
+        "polis_load_balancer_successful_requests_total",
+        "Requests a load balancer answered successfully",
+        Kind::Counter,
+        metrics.load_balancers.iter().map(|lb| {
+            (
+                load_balancer_labels(lb),
+                lb.successful_requests_total as f64,
+            )
+        }),
+    )?;
+    write_family(
+        out,
+        "polis_load_balancer_failed_requests_total",
+        "Requests a load balancer failed to answer",
+        Kind::Counter,
+        metrics
+            .load_balancers
+            .iter()
+            .map(|lb| (load_balancer_labels(lb), lb.failed_requests_total as f64)),
+    )
+}
+
+fn deployment_labels(deployment: &DeploymentGauge) -> Labels<'_> {
+    vec![
+        ("deployment", deployment.deployment.as_str()),
+        ("namespace", deployment.namespace.as_str()),
+    ]
+}
+
+fn load_balancer_labels(lb: &LoadBalancerCounters) -> Labels<'_> {
+    vec![
+        ("service", lb.service.as_str()),
+        ("namespace", lb.namespace.as_str()),
+    ]
+}
+
+/// Write the `# HELP` and `# TYPE` lines of a family, then its samples
+fn write_family<'a, W: Write>(
+    out: &mut W,
+    name: &str,
+    help: &str,
+    kind: Kind,
+    samples: impl Iterator<Item = (Labels<'a>, f64)>,
+) -> std::fmt::Result {
+    let name = sanitize_metric_name(name);
+    writeln!(out, "# HELP {} {}", name, help)?;
+    writeln!(out, "# TYPE {} {}", name, kind.as_str())?;
+    for (labels, value) in samples {
+        out.write_str(&name)?;
+        for (i, (label, label_value)) in labels.iter().enumerate() {
+            out.write_str(if i == 0 { "{" } else { "," })?;
+            write!(out, "{}=\"", label)?;
+            write_escaped(out, label_value)?;
+            out.write_char('"')?;
+        }
+        if !labels.is_empty() {
+            out.write_char('}')?;
+        }
+        writeln!(out, " {}", value)?;
+    }
+    Ok(())
+}
+
+/// Escape a label value: backslash, double quote and newline
+fn write_escaped<W: Write>(out: &mut W, value: &str) -> std::fmt::Result {
+    for c in value.chars() {
+        match c {
+            '\\' => out.write_str("\\\\")?,
+            '"' => out.write_str("\\\"")?,
+            '\n' => out.write_str("\\n")?,
+            c => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+/// Turn `name` into a valid metric name (`[a-zA-Z_:][a-zA-Z0-9_:]*`) by
+/// replacing every other character with `_`
+pub fn sanitize_metric_name(name: &str) -> String {
+    sanitize(name, true)
+}
+
+/// Turn `name` into a valid label name (`[a-zA-Z_][a-zA-Z0-9_]*`) by
+/// replacing every other character with `_`. Names starting with `__` are
+/// reserved, so extra leading underscores are dropped.
+pub fn sanitize_label_name(name: &str) -> String {
+    let mut name = sanitize(name, false);
+    while name.starts_with("__") {
+        name.remove(0);
+    }
+    name
+}
+
+fn sanitize(name: &str, allow_colon: bool) -> String {
+    let mut out: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || (allow_colon && c == ':') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    out
+}
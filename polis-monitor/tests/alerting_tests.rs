@@ -0,0 +1,308 @@
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use polis_monitor::alerting::AlertRule;
+use polis_monitor::{
+    load_active_alerts, parse_alert_rules, AlertEngine, AlertSeverity, AlertState, ChannelNotifier,
+    Comparison, MetricSelector, ResourceAlert, WebhookNotifier,
+};
+use polis_stats::{ContainerMetrics, ContainerStatsCollector, MetricField};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, Mutex};
+
+fn memory(container_id: &str, usage_percent: f64) -> ContainerMetrics {
+    let mut metrics = ContainerMetrics {
+        container_id: container_id.to_string(),
+        ..Default::default()
+    };
+    metrics.memory.limit = 1000;
+    metrics.memory.usage = (usage_percent * 10.0) as u64;
+    metrics.memory.usage_percent = usage_percent;
+    metrics
+}
+
+fn memory_rule() -> AlertRule {
+    AlertRule::new(
+        "memory-high",
+        MetricSelector::new(MetricField::MemoryUsagePercent),
+        Comparison::Above,
+        90.0,
+    )
+    .with_for_duration(Duration::from_secs(120))
+    .with_severity(AlertSeverity::Critical)
+    .with_label("team", "web")
+}
+
+async fn engine_with(
+    rules: Vec<AlertRule>,
+) -> (Arc<AlertEngine>, mpsc::UnboundedReceiver<ResourceAlert>) {
+    let (notifier, receiver) = ChannelNotifier::new();
+    let engine = AlertEngine::new().with_notifier(Arc::new(notifier));
+    engine.set_rules(rules).await.unwrap();
+    (Arc::new(engine), receiver)
+}
+
+/// Feed one reading per minute
+async fn feed(engine: &AlertEngine, container_id: &str, readings: &[f64]) {
+    for reading in readings {
+        engine
+            .observe(&memory(container_id, *reading), &HashMap::new())
+            .await;
+        tokio::time::advance(Duration::from_secs(60)).await;
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_brief_breach_does_not_fire() {
+    let (engine, mut alerts) = engine_with(vec![memory_rule()]).await;
+
+    feed(&engine, "web-1", &[95.0, 97.0]).await;
+    let pending = engine.active_alerts().await;
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].state, AlertState::Pending);
+
+    // Back under the threshold before the two minutes are up
+    feed(&engine, "web-1", &[50.0, 95.0]).await;
+    assert!(alerts.try_recv().is_err());
+    let pending = engine.active_alerts().await;
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].value, 95.0);
+    assert_eq!(pending[0].state, AlertState::Pending);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_sustained_breach_fires_once_then_resolves() {
+    let (engine, mut alerts) = engine_with(vec![memory_rule()]).await;
+
+    feed(&engine, "web-1", &[92.0, 94.0, 96.0]).await;
+    let firing = alerts.try_recv().unwrap();
+    assert_eq!(firing.state, AlertState::Firing);
+    assert_eq!(firing.rule, "memory-high");
+    assert_eq!(firing.container_id, "web-1");
+    assert_eq!(firing.severity, AlertSeverity::Critical);
+    assert_eq!(firing.value, 96.0);
+    assert_eq!(firing.labels.get("team").map(String::as_str), Some("web"));
+    assert!(firing.fired_at.is_some());
+
+    // Still breached: deduplicated, no second notification
+    feed(&engine, "web-1", &[98.0, 99.0]).await;
+    assert!(alerts.try_recv().is_err());
+    assert_eq!(engine.active_alerts().await[0].value, 99.0);
+
+    // Another container is tracked on its own
+    feed(&engine, "web-2", &[91.0]).await;
+    assert_eq!(engine.active_alerts().await.len(), 2);
+
+    feed(&engine, "web-1", &[40.0]).await;
+    let resolved = alerts.try_recv().unwrap();
+    assert_eq!(resolved.state, AlertState::Resolved);
+    assert_eq!(resolved.value, 40.0);
+    assert!(resolved.resolved_at.is_some());
+    assert!(alerts.try_recv().is_err());
+
+    let active = engine.active_alerts().await;
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].container_id, "web-2");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_selector_comparison_and_zero_duration() {
+    let rule = AlertRule::new(
+        "cpu-idle",
+        MetricSelector::new(MetricField::CpuUsagePercent).with_label("tier", "batch"),
+        Comparison::AtMost,
+        1.0,
+    );
+    let (engine, mut alerts) = engine_with(vec![rule]).await;
+    let batch = HashMap::from([("tier".to_string(), "batch".to_string())]);
+
+    let idle = ContainerMetrics {
+        container_id: "job".to_string(),
+        ..Default::default()
+    };
+    engine.observe(&idle, &HashMap::new()).await;
+    assert!(alerts.try_recv().is_err());
+
+    // No `for_duration`: fires on the first breaching sample
+    engine.observe(&idle, &batch).await;
+    assert_eq!(alerts.try_recv().unwrap().state, AlertState::Firing);
+    assert_eq!(
+        engine.active_alerts().await[0].labels.get("tier"),
+        Some(&"batch".to_string())
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_engine_follows_the_collector() {
+    let rule = AlertRule::new(
+        "memory-high",
+        MetricSelector::new(MetricField::MemoryUsagePercent).with_container("web-1"),
+        Comparison::Above,
+        90.0,
+    );
+    let (engine, mut alerts) = engine_with(vec![rule]).await;
+    let collector = Arc::new(ContainerStatsCollector::default());
+    let task = engine.run(Arc::clone(&collector));
+
+    collector
+        .update_metrics("web-2", memory("web-2", 99.0))
+        .await
+        .unwrap();
+    collector
+        .update_metrics("web-1", memory("web-1", 99.0))
+        .await
+        .unwrap();
+    let fired = alerts.recv().await.unwrap();
+    assert_eq!(fired.container_id, "web-1");
+    assert_eq!(fired.state, AlertState::Firing);
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_rules_load_from_yaml_and_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("alerts.yaml");
+    std::fs::write(
+        &path,
+        r#"
+rules:
+  - name: memory-high
+    metric:
+      field: memory_usage_percent
+      match_labels:
+        app: web
+    threshold: 90
+    for_duration: 2m
+    severity: High
+    labels:
+      team: web
+  - name: cpu-low
+    metric:
+      field: cpu_usage_percent
+    comparison: "<"
+    threshold: 5
+"#,
+    )
+    .unwrap();
+
+    let (notifier, mut alerts) = ChannelNotifier::new();
+    let engine = AlertEngine::new().with_notifier(Arc::new(notifier));
+    assert_eq!(engine.load_rules_file(&path).await.unwrap(), 2);
+    let rules = engine.rules().await;
+    assert_eq!(rules[0].for_duration, Duration::from_secs(120));
+    assert_eq!(rules[0].severity, AlertSeverity::High);
+    assert_eq!(rules[0].comparison, Comparison::Above);
+    assert_eq!(rules[1].comparison, Comparison::Below);
+    assert_eq!(rules[1].for_duration, Duration::ZERO);
+
+    // Changing a rule resolves its firing alerts
+    let cpu_idle = memory("web-1", 10.0);
+    engine.observe(&cpu_idle, &HashMap::new()).await;
+    assert_eq!(alerts.try_recv().unwrap().state, AlertState::Firing);
+
+    std::fs::write(
+        &path,
+        "rules:\n  - name: cpu-low\n    metric:\n      field: cpu_usage_percent\n    comparison: \"<\"\n    threshold: 2\n",
+    )
+    .unwrap();
+    assert_eq!(engine.load_rules_file(&path).await.unwrap(), 1);
+    let resolved = alerts.try_recv().unwrap();
+    assert_eq!(resolved.rule, "cpu-low");
+    assert_eq!(resolved.state, AlertState::Resolved);
+    assert!(engine.active_alerts().await.is_empty());
+
+    // Broken files are rejected and keep the current rules
+    std::fs::write(&path, "rules:\n  - name: x\n    threshold: 1\n").unwrap();
+    assert!(engine.load_rules_file(&path).await.is_err());
+    assert_eq!(engine.rules().await[0].threshold, 2.0);
+
+    assert!(parse_alert_rules(
+        "rules:\n  - {name: a, metric: {field: rx_bytes}, threshold: 1}\n  - {name: a, metric: {field: tx_bytes}, threshold: 1}\n"
+    )
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_rule_file_is_hot_reloaded() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("alerts.yaml");
+    let rules = |threshold: u32| {
+        format!(
+            "rules:\n  - name: memory-high\n    metric:\n      field: memory_usage_percent\n    threshold: {}\n",
+            threshold
+        )
+    };
+    std::fs::write(&path, rules(90)).unwrap();
+
+    let engine = Arc::new(AlertEngine::new());
+    let watcher = engine.watch_rules_file(&path).await.unwrap();
+    assert_eq!(engine.rules().await[0].threshold, 90.0);
+
+    std::fs::write(&path, rules(75)).unwrap();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    while engine.rules().await[0].threshold != 75.0 {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "rules were not reloaded"
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    watcher.abort();
+}
+
+#[tokio::test]
+async fn test_webhook_and_state_file() {
+    let received: Arc<Mutex<Vec<ResourceAlert>>> = Arc::default();
+    let app = Router::new()
+        .route(
+            "/hook",
+            post(
+                |State(received): State<Arc<Mutex<Vec<ResourceAlert>>>>,
+                 Json(alert): Json<ResourceAlert>| async move {
+                    received.lock().await.push(alert);
+                },
+            ),
+        )
+        .with_state(Arc::clone(&received));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let dir = tempfile::tempdir().unwrap();
+    let state_file = dir.path().join("alerts").join("active.json");
+    let webhook = WebhookNotifier::new(&format!("http://{}/hook", addr)).unwrap();
+    let engine = AlertEngine::new()
+        .with_notifier(Arc::new(webhook))
+        .with_state_file(state_file.clone());
+    engine
+        .set_rules(vec![AlertRule::new(
+            "memory-high",
+            MetricSelector::new(MetricField::MemoryUsagePercent),
+            Comparison::Above,
+            90.0,
+        )])
+        .await
+        .unwrap();
+    assert!(load_active_alerts(&state_file).unwrap().is_empty());
+
+    engine
+        .observe(&memory("web-1", 95.0), &HashMap::new())
+        .await;
+    let saved = load_active_alerts(&state_file).unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].state, AlertState::Firing);
+    assert_eq!(received.lock().await.as_slice(), saved.as_slice());
+
+    engine
+        .observe(&memory("web-1", 20.0), &HashMap::new())
+        .await;
+    assert!(load_active_alerts(&state_file).unwrap().is_empty());
+    let delivered = received.lock().await;
+    assert_eq!(delivered.len(), 2);
+    assert_eq!(delivered[1].state, AlertState::Resolved);
+
+    assert!(WebhookNotifier::new("not a url").is_err());
+}
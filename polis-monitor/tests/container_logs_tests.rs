@@ -0,0 +1,182 @@
+use chrono::{TimeZone, Utc};
+use polis_core::{
+    rotated_log_path, Clock, ContainerId, LogRotation, LogStream, ManualClock, PolisConfig,
+};
+use polis_monitor::{ContainerLogReader, LogManager, LogReadOptions};
+use polis_runtime::{ContainerRuntime, PolisRuntime};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncWriteExt, DuplexStream};
+
+fn runtime(root_dir: &Path, clock: &ManualClock) -> PolisRuntime {
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = root_dir.to_path_buf();
+    config.storage.root_dir = root_dir.join("storage");
+    config.runtime.log_rotation = LogRotation {
+        max_size: 1024,
+        max_files: 4,
+    };
+    PolisRuntime::new(config).with_clock(Arc::new(clock.clone()))
+}
+
+async fn create(runtime: &PolisRuntime) -> ContainerId {
+    runtime
+        .create_container(
+            "web".to_string(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+        )
+        .await
+        .unwrap()
+}
+
+/// Fake process: the write halves play the process' stdout and stderr
+async fn spawn_process(runtime: &PolisRuntime, id: &ContainerId) -> (DuplexStream, DuplexStream) {
+    let (stdout, stdout_pipe) = tokio::io::duplex(4096);
+    let (stderr, stderr_pipe) = tokio::io::duplex(4096);
+    runtime
+        .capture_output(id, stdout_pipe, stderr_pipe)
+        .await
+        .unwrap();
+    (stdout, stderr)
+}
+
+async fn wait_for_line(runtime: &PolisRuntime, id: &ContainerId, line: &str) {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    loop {
+        let content = std::fs::read_to_string(runtime.log_path(id)).unwrap_or_default();
+        if content.contains(line) {
+            return;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "{} was not logged",
+            line
+        );
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+async fn next(reader: &mut ContainerLogReader) -> String {
+    tokio::time::timeout(Duration::from_secs(10), reader.next_record())
+        .await
+        .expect("no new line")
+        .unwrap()
+        .unwrap()
+        .log
+}
+
+async fn read_all(logs: &LogManager, id: &ContainerId, options: LogReadOptions) -> Vec<String> {
+    let mut reader = logs.read(id, options).await.unwrap();
+    let mut lines = Vec::new();
+    while let Some(record) = reader.next_record().await.unwrap() {
+        lines.push(record.log);
+    }
+    lines
+}
+
+#[tokio::test]
+async fn test_tail_spans_rotated_files() {
+    let root = tempfile::tempdir().unwrap();
+    let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap());
+    let runtime = runtime(root.path(), &clock);
+    let id = create(&runtime).await;
+    let (mut stdout, mut stderr) = spawn_process(&runtime, &id).await;
+
+    // ~70 bytes per record: 30 lines do not fit in one 1 KiB file
+    for n in 0..30 {
+        stdout
+            .write_all(format!("line {}\n", n).as_bytes())
+            .await
+            .unwrap();
+        if n == 15 {
+            wait_for_line(&runtime, &id, "line 15").await;
+            clock.advance(Duration::from_secs(60));
+            stderr.write_all(b"warning\n").await.unwrap();
+            wait_for_line(&runtime, &id, "warning").await;
+        }
+    }
+    wait_for_line(&runtime, &id, "line 29").await;
+    assert!(rotated_log_path(&runtime.log_path(&id), 1).exists());
+
+    let logs = LogManager::default().with_container_log_root(root.path());
+    let tail = read_all(
+        &logs,
+        &id,
+        LogReadOptions {
+            tail: Some(20),
+            streams: vec![LogStream::Stdout],
+            ..Default::default()
+        },
+    )
+    .await;
+    let expected: Vec<String> = (10..30).map(|n| format!("line {}", n)).collect();
+    assert_eq!(tail, expected);
+
+    let stderr_lines = read_all(
+        &logs,
+        &id,
+        LogReadOptions {
+            streams: vec![LogStream::Stderr],
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_eq!(stderr_lines, vec!["warning".to_string()]);
+
+    let since = read_all(
+        &logs,
+        &id,
+        LogReadOptions {
+            since: Some(clock.now()),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_eq!(since.first().map(String::as_str), Some("warning"));
+    assert_eq!(since.len(), 15);
+}
+
+#[tokio::test]
+async fn test_follow_continues_across_rotation() {
+    let root = tempfile::tempdir().unwrap();
+    let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap());
+    let runtime = runtime(root.path(), &clock);
+    let id = create(&runtime).await;
+    let (mut stdout, _stderr) = spawn_process(&runtime, &id).await;
+
+    stdout.write_all(b"first\n").await.unwrap();
+    wait_for_line(&runtime, &id, "first").await;
+
+    let logs = LogManager::default().with_container_log_root(root.path());
+    let mut reader = logs
+        .read(
+            &id,
+            LogReadOptions {
+                tail: Some(1),
+                follow: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(next(&mut reader).await, "first");
+
+    // Enough output to rotate the followed file more than once
+    for n in 0..40 {
+        stdout
+            .write_all(format!("line {}\n", n).as_bytes())
+            .await
+            .unwrap();
+    }
+    for n in 0..40 {
+        assert_eq!(next(&mut reader).await, format!("line {}", n));
+    }
+    assert!(rotated_log_path(&runtime.log_path(&id), 2).exists());
+
+    // Removing the container removes its logs
+    drop(stdout);
+    runtime.remove_container(id.clone()).await.unwrap();
+    assert!(!runtime.log_path(&id).exists());
+}
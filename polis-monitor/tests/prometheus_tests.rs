@@ -0,0 +1,236 @@
+use polis_monitor::{
+    sanitize_label_name, sanitize_metric_name, DeploymentGauge, HealthCheckGauge,
+    LoadBalancerCounters, OrchestratorMetrics, PrometheusExporter, PROMETHEUS_CONTENT_TYPE,
+};
+use polis_stats::{ContainerMetrics, ContainerStatsCollector};
+use prometheus_parse::{Sample, Scrape, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+fn sample(container_id: &str, rx_bytes: u64) -> ContainerMetrics {
+    let mut metrics = ContainerMetrics {
+        container_id: container_id.to_string(),
+        ..Default::default()
+    };
+    metrics.network.rx_bytes = rx_bytes;
+    metrics.memory.usage = 64 * 1024 * 1024;
+    metrics
+}
+
+fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+async fn collector() -> Arc<ContainerStatsCollector> {
+    let collector = Arc::new(ContainerStatsCollector::default());
+    collector
+        .start_collecting_with_labels(
+            "web-1",
+            labels(&[
+                ("deployment", "web"),
+                ("namespace", "prod"),
+                ("app.kubernetes.io/name", "shop"),
+            ]),
+        )
+        .await
+        .unwrap();
+    collector
+        .update_metrics("web-1", sample("web-1", 1_000))
+        .await
+        .unwrap();
+    collector
+        .update_metrics("adhoc", sample("adhoc", 10))
+        .await
+        .unwrap();
+    collector
+}
+
+fn orchestrator() -> OrchestratorMetrics {
+    OrchestratorMetrics {
+        deployments: vec![DeploymentGauge {
+            deployment: "web".to_string(),
+            namespace: "prod".to_string(),
+            replicas_desired: 3,
+            replicas_ready: 2,
+        }],
+        health_checks: vec![HealthCheckGauge {
+            check: "web-http".to_string(),
+            target: "web-1".to_string(),
+            deployment: "web".to_string(),
+            namespace: "prod".to_string(),
+            healthy: true,
+        }],
+        load_balancers: vec![LoadBalancerCounters {
+            service: "web".to_string(),
+            namespace: "prod".to_string(),
+            requests_total: 120,
+            successful_requests_total: 118,
+            failed_requests_total: 2,
+        }],
+    }
+}
+
+async fn scrape(url: &str) -> Scrape {
+    let response = reqwest::get(url).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        PROMETHEUS_CONTENT_TYPE
+    );
+    let body = response.text().await.unwrap();
+    Scrape::parse(body.lines().map(|line| Ok(line.to_string()))).unwrap()
+}
+
+fn find<'a>(scrape: &'a Scrape, name: &str, container_id: &str) -> &'a Sample {
+    scrape
+        .samples
+        .iter()
+        .find(|sample| {
+            sample.metric == name && sample.labels.get("container_id") == Some(container_id)
+        })
+        .unwrap_or_else(|| panic!("no {} sample for {}", name, container_id))
+}
+
+fn value(sample: &Sample) -> f64 {
+    match sample.value {
+        Value::Counter(v) | Value::Gauge(v) | Value::Untyped(v) => v,
+        _ => panic!("unexpected value type for {}", sample.metric),
+    }
+}
+
+fn label_names(sample: &Sample) -> BTreeSet<String> {
+    sample.labels.keys().cloned().collect()
+}
+
+#[tokio::test]
+async fn test_scrape_exposes_container_and_orchestrator_families() {
+    let collector = collector().await;
+    let exporter = PrometheusExporter::new(Arc::clone(&collector));
+    exporter.set_orchestrator_metrics(orchestrator()).await;
+    let server = exporter
+        .serve("127.0.0.1:0".parse().unwrap())
+        .await
+        .unwrap();
+    let url = format!("http://{}/metrics", server.local_addr());
+
+    let first = scrape(&url).await;
+    let families: BTreeSet<&str> = first.docs.keys().map(String::as_str).collect();
+    for family in [
+        "polis_container_memory_usage_bytes",
+        "polis_container_network_receive_bytes_total",
+        "polis_deployments",
+        "polis_deployment_replicas_desired",
+        "polis_deployment_replicas_ready",
+        "polis_health_check_status",
+        "polis_load_balancer_requests_total",
+        "polis_load_balancer_failed_requests_total",
+    ] {
+        assert!(families.contains(family), "{family} has no # HELP line");
+    }
+
+    // Every container sample carries the same label set
+    let web = find(&first, "polis_container_memory_usage_bytes", "web-1");
+    let adhoc = find(&first, "polis_container_memory_usage_bytes", "adhoc");
+    let expected: BTreeSet<String> = ["container_id", "deployment", "namespace"]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    assert_eq!(label_names(web), expected);
+    assert_eq!(label_names(adhoc), expected);
+    assert_eq!(web.labels.get("deployment"), Some("web"));
+    assert_eq!(web.labels.get("namespace"), Some("prod"));
+    assert_eq!(value(web), 67_108_864.0);
+
+    let find_family = |name: &str| {
+        first
+            .samples
+            .iter()
+            .find(|sample| sample.metric == name)
+            .unwrap_or_else(|| panic!("no {} sample", name))
+    };
+    let desired = find_family("polis_deployment_replicas_desired");
+    assert!(matches!(desired.value, Value::Gauge(v) if v == 3.0));
+    assert_eq!(desired.labels.get("deployment"), Some("web"));
+    assert_eq!(desired.labels.get("namespace"), Some("prod"));
+    assert_eq!(value(find_family("polis_deployment_replicas_ready")), 2.0);
+    assert_eq!(value(find_family("polis_deployments")), 1.0);
+
+    let health = find_family("polis_health_check_status");
+    assert_eq!(health.labels.get("check"), Some("web-http"));
+    assert_eq!(health.labels.get("target"), Some("web-1"));
+    assert_eq!(value(health), 1.0);
+
+    let requests = find_family("polis_load_balancer_requests_total");
+    assert!(matches!(requests.value, Value::Counter(v) if v == 120.0));
+    assert_eq!(requests.labels.get("service"), Some("web"));
+
+    // Counters are cumulative, so a later scrape never reads lower
+    collector
+        .update_metrics("web-1", sample("web-1", 1_500))
+        .await
+        .unwrap();
+    let later = scrape(&url).await;
+    let before = value(find(
+        &first,
+        "polis_container_network_receive_bytes_total",
+        "web-1",
+    ));
+    let after = value(find(
+        &later,
+        "polis_container_network_receive_bytes_total",
+        "web-1",
+    ));
+    assert_eq!((before, after), (1_000.0, 1_500.0));
+    assert!(matches!(
+        find(
+            &later,
+            "polis_container_network_receive_bytes_total",
+            "web-1"
+        )
+        .value,
+        Value::Counter(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_path_and_container_labels_are_configurable() {
+    let collector = collector().await;
+    let exporter = PrometheusExporter::new(collector)
+        .with_path("prom")
+        .with_container_labels(&["app.kubernetes.io/name", "deployment", "container_id"]);
+    assert_eq!(exporter.path(), "/prom");
+    let server = exporter
+        .serve("127.0.0.1:0".parse().unwrap())
+        .await
+        .unwrap();
+
+    let scraped = scrape(&format!("http://{}/prom", server.local_addr())).await;
+    let web = find(&scraped, "polis_container_cpu_cores", "web-1");
+    assert_eq!(web.labels.get("app_kubernetes_io_name"), Some("shop"));
+    assert_eq!(web.labels.get("deployment"), Some("web"));
+    assert_eq!(web.labels.get("namespace"), None);
+
+    let missing = reqwest::get(format!("http://{}/metrics", server.local_addr()))
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+}
+
+#[test]
+fn test_names_are_sanitized() {
+    assert_eq!(
+        sanitize_metric_name("polis:requests-total"),
+        "polis:requests_total"
+    );
+    assert_eq!(sanitize_metric_name("5xx rate"), "_5xx_rate");
+    assert_eq!(
+        sanitize_label_name("app.kubernetes.io/name"),
+        "app_kubernetes_io_name"
+    );
+    assert_eq!(sanitize_label_name("zone:a"), "zone_a");
+    assert_eq!(sanitize_label_name("__meta"), "_meta");
+    assert_eq!(sanitize_label_name(""), "_");
+}
@@ -20,7 +20,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Criar bridge adicional
     bridge_manager
-        .create_bridge("custom-bridge", "192.168.1.1", "192.168.1.0/24", 1500)
+        .create_bridge("custom-bridge", "192.168.1.1", "192.168.1.0/24", 1500, None)
         .await?;
 
     // Listar bridges
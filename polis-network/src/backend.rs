@@ -0,0 +1,55 @@
+use crate::{FirewallRule, PortForwardingRule};
+use polis_core::Result;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Operação a ser aplicada no kernel (netlink) pelos gerenciadores de rede.
+///
+/// `AddFdbEntry` cria a entrada de MAC zerado que inunda o tráfego BUM até
+/// o VTEP remoto; `InNetns` aplica a operação dentro do namespace de rede
+/// em `netns`, para onde `MoveToNetns` move a interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KernelOp {
+    CreateBridge { name: String, mtu: u16 },
+    DeleteBridge { name: String },
+    AddAddress { link: String, address: String },
+    SetLinkUp { name: String },
+    SetLinkDown { name: String },
+    AttachInterface { bridge: String, interface: String },
+    DetachInterface { bridge: String, interface: String },
+    CreateVxlan(VxlanDevice),
+    AddFdbEntry { link: String, destination: IpAddr },
+    CreateVethPair { name: String, peer: String },
+    DeleteLink { name: String },
+    MoveToNetns { link: String, netns: PathBuf },
+    InNetns { netns: PathBuf, op: Box<KernelOp> },
+}
+
+/// Dispositivo VXLAN sem aprendizado de MAC; os VTEPs remotos vêm do FDB
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VxlanDevice {
+    pub name: String,
+    pub vni: u32,
+    pub local: IpAddr,
+    /// Porta UDP de destino (4789 pela IANA)
+    pub port: u16,
+    pub mtu: u16,
+}
+
+/// Aplica operações de link/endereço no kernel
+pub trait NetlinkBackend: Send + Sync {
+    fn apply(&self, op: KernelOp) -> Result<()>;
+}
+
+/// Aplica regras de firewall no kernel (iptables/nftables)
+pub trait FirewallBackend: Send + Sync {
+    fn add_rule(&self, chain: &str, rule: &FirewallRule) -> Result<()>;
+    fn remove_rule(&self, chain: &str, rule_id: &str) -> Result<()>;
+    fn flush_chain(&self, chain: &str) -> Result<()>;
+}
+
+/// Instala no kernel (DNAT) as regras de port forwarding
+pub trait PortForwardingBackend: Send + Sync {
+    fn add_forward(&self, rule: &PortForwardingRule) -> Result<()>;
+    fn remove_forward(&self, rule: &PortForwardingRule) -> Result<()>;
+}
@@ -1,13 +1,17 @@
+use crate::{Ipv6Network, KernelOp, NetlinkBackend};
 use polis_core::{PolisError, Result};
 use std::collections::HashMap;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv6Addr};
 use std::str::FromStr;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct Bridge {
     pub name: String,
     pub ip: IpAddr,
     pub subnet: String,
+    /// Prefixo IPv6 de uma bridge dual-stack
+    pub ipv6_subnet: Option<Ipv6Network>,
     pub mtu: u16,
     pub interfaces: Vec<String>,
     pub enabled: bool,
@@ -29,6 +33,7 @@ pub struct BridgeStats {
 pub struct BridgeManager {
     bridges: HashMap<String, Bridge>,
     default_bridge: String,
+    netlink: Option<Arc<dyn NetlinkBackend>>,
 }
 
 impl BridgeManager {
@@ -36,6 +41,20 @@ impl BridgeManager {
         Self {
             bridges: HashMap::new(),
             default_bridge: "polis0".to_string(),
+            netlink: None,
+        }
+    }
+
+    /// Aplica as alterações no kernel através do backend
+    pub fn with_netlink(mut self, netlink: Arc<dyn NetlinkBackend>) -> Self {
+        self.netlink = Some(netlink);
+        self
+    }
+
+    fn apply(&self, op: KernelOp) -> Result<()> {
+        match &self.netlink {
+            Some(netlink) => netlink.apply(op),
+            None => Ok(()),
         }
     }
 
@@ -45,14 +64,38 @@ impl BridgeManager {
         ip: &str,
         subnet: &str,
         mtu: u16,
+        ipv6_subnet: Option<Ipv6Network>,
     ) -> Result<()> {
         let bridge_ip =
             IpAddr::from_str(ip).map_err(|e| PolisError::Network(format!("IP inválido: {}", e)))?;
 
+        self.apply(KernelOp::CreateBridge {
+            name: name.to_string(),
+            mtu,
+        })?;
+        self.apply(KernelOp::AddAddress {
+            link: name.to_string(),
+            address: format!(
+                "{}/{}",
+                bridge_ip,
+                subnet.rsplit('/').next().unwrap_or("32")
+            ),
+        })?;
+        if let Some(ipv6_subnet) = ipv6_subnet {
+            self.apply(KernelOp::AddAddress {
+                link: name.to_string(),
+                address: format!("{}/{}", ipv6_gateway(ipv6_subnet), ipv6_subnet.prefix()),
+            })?;
+        }
+        self.apply(KernelOp::SetLinkUp {
+            name: name.to_string(),
+        })?;
+
         let bridge = Bridge {
             name: name.to_string(),
             ip: bridge_ip,
             subnet: subnet.to_string(),
+            ipv6_subnet,
             mtu,
             interfaces: Vec::new(),
             enabled: true,
@@ -64,7 +107,11 @@ impl BridgeManager {
     }
 
     pub async fn delete_bridge(&mut self, name: &str) -> Result<()> {
-        if self.bridges.remove(name).is_some() {
+        if self.bridges.contains_key(name) {
+            self.apply(KernelOp::DeleteBridge {
+                name: name.to_string(),
+            })?;
+            self.bridges.remove(name);
             println!("� Bridge '{}' removida", name);
             Ok(())
         } else {
@@ -76,11 +123,23 @@ impl BridgeManager {
     }
 
     pub async fn add_interface(&mut self, bridge_name: &str, interface_name: &str) -> Result<()> {
-        let bridge = self.bridges.get_mut(bridge_name).ok_or_else(|| {
-            PolisError::Network(format!("Bridge '{}' não encontrada", bridge_name))
-        })?;
+        let attached = self
+            .bridges
+            .get(bridge_name)
+            .ok_or_else(|| PolisError::Network(format!("Bridge '{}' não encontrada", bridge_name)))?
+            .interfaces
+            .iter()
+            .any(|iface| iface == interface_name);
 
-        if !bridge.interfaces.contains(&interface_name.to_string()) {
+        if !attached {
+            self.apply(KernelOp::AttachInterface {
+                bridge: bridge_name.to_string(),
+                interface: interface_name.to_string(),
+            })?;
+            let bridge = self
+                .bridges
+                .get_mut(bridge_name)
+                .expect("bridge verificada acima");
             bridge.interfaces.push(interface_name.to_string());
             println!(
                 "� Interface '{}' adicionada à bridge '{}'",
@@ -96,10 +155,24 @@ impl BridgeManager {
         bridge_name: &str,
         interface_name: &str,
     ) -> Result<()> {
-        let bridge = self.bridges.get_mut(bridge_name).ok_or_else(|| {
-            PolisError::Network(format!("Bridge '{}' não encontrada", bridge_name))
-        })?;
+        let attached = self
+            .bridges
+            .get(bridge_name)
+            .ok_or_else(|| PolisError::Network(format!("Bridge '{}' não encontrada", bridge_name)))?
+            .interfaces
+            .iter()
+            .any(|iface| iface == interface_name);
 
+        if attached {
+            self.apply(KernelOp::DetachInterface {
+                bridge: bridge_name.to_string(),
+                interface: interface_name.to_string(),
+            })?;
+        }
+        let bridge = self
+            .bridges
+            .get_mut(bridge_name)
+            .expect("bridge verificada acima");
         bridge.interfaces.retain(|iface| iface != interface_name);
         println!(
             "� Interface '{}' removida da bridge '{}'",
@@ -109,7 +182,11 @@ impl BridgeManager {
     }
 
     pub async fn enable_bridge(&mut self, name: &str) -> Result<()> {
-        if let Some(bridge) = self.bridges.get_mut(name) {
+        if self.bridges.contains_key(name) {
+            self.apply(KernelOp::SetLinkUp {
+                name: name.to_string(),
+            })?;
+            let bridge = self.bridges.get_mut(name).expect("bridge verificada acima");
             bridge.enabled = true;
             println!("� Bridge '{}' habilitada", name);
             Ok(())
@@ -122,7 +199,11 @@ impl BridgeManager {
     }
 
     pub async fn disable_bridge(&mut self, name: &str) -> Result<()> {
-        if let Some(bridge) = self.bridges.get_mut(name) {
+        if self.bridges.contains_key(name) {
+            self.apply(KernelOp::SetLinkDown {
+                name: name.to_string(),
+            })?;
+            let bridge = self.bridges.get_mut(name).expect("bridge verificada acima");
             bridge.enabled = false;
             println!("� Bridge '{}' desabilitada", name);
             Ok(())
@@ -163,7 +244,7 @@ impl BridgeManager {
     }
 
     pub async fn create_default_bridge(&mut self) -> Result<()> {
-        self.create_bridge("polis0", "172.17.0.1", "172.17.0.0/16", 1500)
+        self.create_bridge("polis0", "172.17.0.1", "172.17.0.0/16", 1500, None)
             .await?;
         Ok(())
     }
@@ -198,6 +279,11 @@ impl BridgeManager {
     }
 }
 
+/// Endereço da bridge num prefixo IPv6: o primeiro host, como no IPv4
+pub fn ipv6_gateway(subnet: Ipv6Network) -> Ipv6Addr {
+    Ipv6Addr::from(u128::from(subnet.network()) + 1)
+}
+
 impl Default for BridgeManager {
     fn default() -> Self {
         Self::new()
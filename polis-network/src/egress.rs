@@ -0,0 +1,432 @@
+use crate::firewall::Protocol;
+use crate::{DnsManager, DnsRecord, DnsRecordType, FirewallAction, FirewallManager, FirewallRule};
+use polis_core::{EgressMode, EgressPolicy, PolisError, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::broadcast;
+
+/// Chain com as regras de saída dos containers com política de egress
+pub const EGRESS_CHAIN: &str = "POLIS-EGRESS";
+
+/// Zonas internas, sempre resolvíveis independentemente da política
+const INTERNAL_ZONES: &[&str] = &["polis.local", "container.local"];
+
+/// Rede em notação CIDR; um IP sem prefixo cobre apenas o próprio endereço
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    pub network: IpAddr,
+    pub prefix: u8,
+}
+
+impl Cidr {
+    pub fn parse(value: &str) -> Result<Self> {
+        let (address, prefix) = match value.trim().split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value.trim(), None),
+        };
+        let network = IpAddr::from_str(address)
+            .map_err(|e| PolisError::Network(format!("CIDR inválido '{}': {}", value, e)))?;
+        let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max_prefix)
+                .ok_or_else(|| PolisError::Network(format!("Prefixo inválido em '{}'", value)))?,
+            None => max_prefix,
+        };
+        Ok(Self { network, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// Se um nome casa com um domínio da lista: `exemplo.com` casa apenas o
+/// próprio nome e `*.exemplo.com` casa qualquer subdomínio (mas não `exemplo.com`)
+pub fn domain_matches(pattern: &str, name: &str) -> bool {
+    let pattern = normalize_name(pattern);
+    let name = normalize_name(name);
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => name.len() > suffix.len() + 1 && name.ends_with(&format!(".{}", suffix)),
+        None => pattern == name,
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Nomes de containers e das zonas internas não passam pelo filtro
+fn is_internal_name(name: &str) -> bool {
+    let name = normalize_name(name);
+    !name.contains('.')
+        || INTERNAL_ZONES
+            .iter()
+            .any(|zone| name == *zone || name.ends_with(&format!(".{}", zone)))
+}
+
+/// Resultado da verificação de um destino
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EgressDecision {
+    Allow,
+    /// Bloqueado: nomes recebem NXDOMAIN e IPs são descartados pelo firewall
+    Deny,
+    /// Fora da lista, mas permitido porque a política está em modo log-only
+    LogOnly,
+}
+
+/// O que aconteceu com uma tentativa de acesso fora da lista
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EgressDisposition {
+    Blocked,
+    Logged,
+}
+
+/// Tentativa de saída para um destino não permitido
+#[derive(Debug, Clone, PartialEq)]
+pub struct EgressViolation {
+    pub container_id: String,
+    /// Nome ou IP de destino
+    pub destination: String,
+    pub disposition: EgressDisposition,
+    pub timestamp: SystemTime,
+}
+
+/// IP liberado dinamicamente por uma resposta de DNS, válido até o fim do TTL
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicAllow {
+    pub ip: IpAddr,
+    pub name: String,
+    pub expires_at: Instant,
+}
+
+struct AttachedPolicy {
+    policy: EgressPolicy,
+    cidrs: Vec<Cidr>,
+    dynamic: HashMap<IpAddr, DynamicAllow>,
+}
+
+/// Aplica as políticas de egress combinando o filtro de DNS com regras de
+/// firewall: só os IPs das redes permitidas e os devolvidos pelo DNS para
+/// nomes permitidos ficam liberados, e estes expiram com o TTL da resposta
+pub struct EgressController {
+    policies: HashMap<String, AttachedPolicy>,
+    events: broadcast::Sender<EgressViolation>,
+}
+
+impl EgressController {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(256);
+        Self {
+            policies: HashMap::new(),
+            events,
+        }
+    }
+
+    /// Violações de política, para auditoria
+    pub fn subscribe(&self) -> broadcast::Receiver<EgressViolation> {
+        self.events.subscribe()
+    }
+
+    pub fn policy(&self, container_id: &str) -> Option<&EgressPolicy> {
+        self.policies
+            .get(container_id)
+            .map(|attached| &attached.policy)
+    }
+
+    /// Associa a política ao container e instala as regras de firewall
+    pub async fn attach(
+        &mut self,
+        firewall: &mut FirewallManager,
+        container_id: &str,
+        policy: EgressPolicy,
+    ) -> Result<()> {
+        let cidrs = policy
+            .allowed_cidrs
+            .iter()
+            .map(|cidr| Cidr::parse(cidr))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.policies.insert(
+            container_id.to_string(),
+            AttachedPolicy {
+                policy,
+                cidrs,
+                dynamic: HashMap::new(),
+            },
+        );
+        self.sync_rules(firewall, container_id).await
+    }
+
+    /// Remove a política e as regras do container
+    pub async fn detach(
+        &mut self,
+        firewall: &mut FirewallManager,
+        container_id: &str,
+    ) -> Result<()> {
+        if self.policies.remove(container_id).is_some() {
+            self.sync_rules(firewall, container_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Decide se o container pode resolver o nome, registrando a violação
+    pub fn check_name(&self, container_id: &str, name: &str) -> EgressDecision {
+        let Some(attached) = self.policies.get(container_id) else {
+            return EgressDecision::Allow;
+        };
+        if is_internal_name(name)
+            || attached
+                .policy
+                .allowed_domains
+                .iter()
+                .any(|pattern| domain_matches(pattern, name))
+        {
+            return EgressDecision::Allow;
+        }
+        self.violation(container_id, &attached.policy, normalize_name(name))
+    }
+
+    /// Decide se o container pode acessar o IP diretamente
+    pub fn check_ip(&self, container_id: &str, ip: IpAddr, now: Instant) -> EgressDecision {
+        let Some(attached) = self.policies.get(container_id) else {
+            return EgressDecision::Allow;
+        };
+        let dynamically_allowed = attached
+            .dynamic
+            .get(&ip)
+            .is_some_and(|allow| allow.expires_at > now);
+        if dynamically_allowed || attached.cidrs.iter().any(|cidr| cidr.contains(ip)) {
+            return EgressDecision::Allow;
+        }
+        self.violation(container_id, &attached.policy, ip.to_string())
+    }
+
+    /// Resolve um nome para o container pelo DNS embutido: nomes fora da
+    /// lista recebem NXDOMAIN e as respostas permitidas liberam os IPs no
+    /// firewall até expirar o TTL
+    pub async fn resolve(
+        &mut self,
+        dns: &DnsManager,
+        firewall: &mut FirewallManager,
+        container_id: &str,
+        name: &str,
+        record_type: DnsRecordType,
+        now: Instant,
+    ) -> Result<Vec<DnsRecord>> {
+        let decision = self.check_name(container_id, name);
+        if decision == EgressDecision::Deny {
+            return Err(PolisError::Network(format!(
+                "NXDOMAIN: '{}' não é permitido para o container {}",
+                name, container_id
+            )));
+        }
+
+        let records = dns.resolve(name, record_type).await?;
+        if decision == EgressDecision::Allow && !is_internal_name(name) {
+            self.allow_answers(firewall, container_id, name, &records, now)
+                .await?;
+        }
+        Ok(records)
+    }
+
+    /// Libera os IPs de uma resposta de DNS até o fim do TTL de cada registro
+    pub async fn allow_answers(
+        &mut self,
+        firewall: &mut FirewallManager,
+        container_id: &str,
+        name: &str,
+        records: &[DnsRecord],
+        now: Instant,
+    ) -> Result<()> {
+        let Some(attached) = self.policies.get_mut(container_id) else {
+            return Ok(());
+        };
+
+        let mut changed = false;
+        for record in records {
+            if !matches!(record.record_type, DnsRecordType::A | DnsRecordType::AAAA) {
+                continue;
+            }
+            let Ok(ip) = IpAddr::from_str(&record.value) else {
+                continue;
+            };
+            let expires_at = now + Duration::from_secs(record.ttl as u64);
+            let allow = attached.dynamic.entry(ip).or_insert_with(|| {
+                changed = true;
+                DynamicAllow {
+                    ip,
+                    name: normalize_name(name),
+                    expires_at,
+                }
+            });
+            allow.expires_at = allow.expires_at.max(expires_at);
+        }
+
+        if changed {
+            self.sync_rules(firewall, container_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove os IPs cujo TTL expirou; retorna quantos foram removidos
+    pub async fn expire(&mut self, firewall: &mut FirewallManager, now: Instant) -> Result<usize> {
+        let mut affected = Vec::new();
+        let mut removed = 0;
+        for (container_id, attached) in &mut self.policies {
+            let before = attached.dynamic.len();
+            attached.dynamic.retain(|_, allow| allow.expires_at > now);
+            if attached.dynamic.len() != before {
+                removed += before - attached.dynamic.len();
+                affected.push(container_id.clone());
+            }
+        }
+
+        for container_id in affected {
+            self.sync_rules(firewall, &container_id).await?;
+        }
+        Ok(removed)
+    }
+
+    /// IPs liberados dinamicamente para o container
+    pub fn dynamic_allows(&self, container_id: &str) -> Vec<DynamicAllow> {
+        let mut allows: Vec<DynamicAllow> = self
+            .policies
+            .get(container_id)
+            .map(|attached| attached.dynamic.values().cloned().collect())
+            .unwrap_or_default();
+        allows.sort_by_key(|allow| allow.ip);
+        allows
+    }
+
+    fn violation(
+        &self,
+        container_id: &str,
+        policy: &EgressPolicy,
+        destination: String,
+    ) -> EgressDecision {
+        let (decision, disposition) = match policy.mode {
+            EgressMode::Enforce => (EgressDecision::Deny, EgressDisposition::Blocked),
+            EgressMode::LogOnly => (EgressDecision::LogOnly, EgressDisposition::Logged),
+        };
+        tracing::warn!(
+            container = container_id,
+            destination = destination.as_str(),
+            ?disposition,
+            "Acesso de saída fora da política"
+        );
+        let _ = self.events.send(EgressViolation {
+            container_id: container_id.to_string(),
+            destination,
+            disposition,
+            timestamp: SystemTime::now(),
+        });
+        decision
+    }
+
+    /// Reescreve as regras do container: redes permitidas, IPs dinâmicos e,
+    /// no modo enforce, o bloqueio do restante
+    async fn sync_rules(&self, firewall: &mut FirewallManager, container_id: &str) -> Result<()> {
+        if !firewall.has_chain(EGRESS_CHAIN) {
+            firewall
+                .create_chain(EGRESS_CHAIN, FirewallAction::Allow)
+                .await?;
+        }
+
+        let prefix = format!("egress-{}-", container_id);
+        for rule in firewall.list_rules(Some(EGRESS_CHAIN)).await? {
+            if rule.id.starts_with(&prefix) {
+                firewall.remove_rule(EGRESS_CHAIN, &rule.id).await?;
+            }
+        }
+
+        let Some(attached) = self.policies.get(container_id) else {
+            return Ok(());
+        };
+
+        let mut rules = Vec::new();
+        for cidr in &attached.cidrs {
+            rules.push(egress_rule(
+                format!("{}cidr-{}", prefix, cidr),
+                container_id,
+                FirewallAction::Allow,
+                Some(*cidr),
+                format!("Rede permitida {}", cidr),
+            ));
+        }
+        let mut dynamic: Vec<&DynamicAllow> = attached.dynamic.values().collect();
+        dynamic.sort_by_key(|allow| allow.ip);
+        for allow in dynamic {
+            rules.push(egress_rule(
+                format!("{}dns-{}", prefix, allow.ip),
+                container_id,
+                FirewallAction::Allow,
+                Some(Cidr::parse(&allow.ip.to_string())?),
+                format!("Resolvido de {}", allow.name),
+            ));
+        }
+        if attached.policy.mode == EgressMode::Enforce {
+            rules.push(egress_rule(
+                format!("{}deny", prefix),
+                container_id,
+                FirewallAction::Deny,
+                None,
+                "Destinos fora da política".to_string(),
+            ));
+        }
+
+        for rule in rules {
+            firewall.add_rule(EGRESS_CHAIN, rule).await?;
+        }
+        Ok(())
+    }
+}
+
+fn egress_rule(
+    id: String,
+    container_id: &str,
+    action: FirewallAction,
+    destination: Option<Cidr>,
+    comment: String,
+) -> FirewallRule {
+    FirewallRule {
+        id,
+        action,
+        protocol: Protocol::All,
+        source_ip: None,
+        source_prefix: None,
+        source_port: None,
+        dest_ip: destination.map(|cidr| cidr.network),
+        dest_prefix: destination.map(|cidr| cidr.prefix),
+        dest_port: None,
+        interface: Some(format!("polis-{}", container_id)),
+        comment: Some(comment),
+    }
+}
+
+impl Default for EgressController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,8 @@
+use crate::{FirewallBackend, NetworkPolicy, PolicyPod};
 use polis_core::{PolisError, Result};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::net::IpAddr;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FirewallAction {
@@ -17,14 +19,18 @@ pub enum Protocol {
     All,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FirewallRule {
     pub id: String,
     pub action: FirewallAction,
     pub protocol: Protocol,
     pub source_ip: Option<IpAddr>,
+    /// Tamanho do prefixo de `source_ip` quando a regra cobre uma rede (CIDR)
+    pub source_prefix: Option<u8>,
     pub source_port: Option<u16>,
     pub dest_ip: Option<IpAddr>,
+    /// Tamanho do prefixo de `dest_ip` quando a regra cobre uma rede (CIDR)
+    pub dest_prefix: Option<u8>,
     pub dest_port: Option<u16>,
     pub interface: Option<String>,
     pub comment: Option<String>,
@@ -40,6 +46,13 @@ pub struct FirewallChain {
 pub struct FirewallManager {
     chains: HashMap<String, FirewallChain>,
     default_chain: String,
+    backend: Option<Arc<dyn FirewallBackend>>,
+    /// Políticas de rede aplicadas, por nome
+    pub(crate) network_policies: BTreeMap<String, NetworkPolicy>,
+    /// Pods considerados pelas políticas, por (namespace, nome)
+    pub(crate) policy_pods: BTreeMap<(String, String), PolicyPod>,
+    /// Labels de cada namespace, para os seletores de namespace
+    pub(crate) namespace_labels: HashMap<String, HashMap<String, String>>,
 }
 
 impl FirewallManager {
@@ -47,6 +60,10 @@ impl FirewallManager {
         let mut manager = Self {
             chains: HashMap::new(),
             default_chain: "POLIS-FILTER".to_string(),
+            backend: None,
+            network_policies: BTreeMap::new(),
+            policy_pods: BTreeMap::new(),
+            namespace_labels: HashMap::new(),
         };
 
         // Create default chains (synchronous initialization)
@@ -86,6 +103,12 @@ impl FirewallManager {
         manager
     }
 
+    /// Aplica as regras no kernel através do backend
+    pub fn with_backend(mut self, backend: Arc<dyn FirewallBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
     pub async fn create_chain(&mut self, name: &str, default_action: FirewallAction) -> Result<()> {
         let chain = FirewallChain {
             name: name.to_string(),
@@ -107,6 +130,9 @@ impl FirewallManager {
             .get_mut(chain_name)
             .ok_or_else(|| PolisError::Network(format!("Chain '{}' não encontrada", chain_name)))?;
 
+        if let Some(backend) = &self.backend {
+            backend.add_rule(chain_name, &rule)?;
+        }
         chain.rules.push(rule.clone());
         println!(
             "� Regra adicionada à chain '{}': {:?}",
@@ -121,6 +147,9 @@ impl FirewallManager {
             .get_mut(chain_name)
             .ok_or_else(|| PolisError::Network(format!("Chain '{}' não encontrada", chain_name)))?;
 
+        if let Some(backend) = &self.backend {
+            backend.remove_rule(chain_name, rule_id)?;
+        }
         chain.rules.retain(|rule| rule.id != rule_id);
         println!("� Regra '{}' removida da chain '{}'", rule_id, chain_name);
         Ok(())
@@ -148,8 +177,10 @@ impl FirewallManager {
             action,
             protocol: Protocol::All,
             source_ip: None,
+            source_prefix: None,
             source_port: None,
             dest_ip: None,
+            dest_prefix: None,
             dest_port: None,
             interface: Some(format!("polis-{}", container_id)),
             comment: Some(format!("Regra para container {}", container_id)),
@@ -172,8 +203,10 @@ impl FirewallManager {
             action,
             protocol: protocol.clone(),
             source_ip: None,
+            source_prefix: None,
             source_port: None,
             dest_ip: None,
+            dest_prefix: None,
             dest_port: Some(port),
             interface: None,
             comment: Some(format!("Regra para porta {} {:?}", port, protocol)),
@@ -195,8 +228,10 @@ impl FirewallManager {
             action,
             protocol: Protocol::All,
             source_ip: Some(source_ip),
+            source_prefix: None,
             source_port: None,
             dest_ip: None,
+            dest_prefix: None,
             dest_port: None,
             interface: None,
             comment: Some(format!("Regra para IP {}", source_ip)),
@@ -245,11 +280,18 @@ impl FirewallManager {
             .get_mut(chain_name)
             .ok_or_else(|| PolisError::Network(format!("Chain '{}' não encontrada", chain_name)))?;
 
+        if let Some(backend) = &self.backend {
+            backend.flush_chain(chain_name)?;
+        }
         chain.rules.clear();
         println!("� Chain '{}' limpa", chain_name);
         Ok(())
     }
 
+    pub fn has_chain(&self, name: &str) -> bool {
+        self.chains.contains_key(name)
+    }
+
     pub async fn list_chains(&self) -> Result<Vec<String>> {
         Ok(self.chains.keys().cloned().collect())
     }
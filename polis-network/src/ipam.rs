@@ -1,8 +1,12 @@
 use polis_core::{PolisError, Result};
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
 
+/// Endereços geridos por pool IPv6 quando o prefixo tem mais hosts que isso
+pub const DEFAULT_IPV6_POOL_SIZE: u64 = 65_536;
+
 #[derive(Debug, Clone)]
 pub struct IpPool {
     pub subnet: String,
@@ -19,8 +23,32 @@ pub struct IpAllocation {
     pub gateway: IpAddr,
 }
 
+/// Prefixo IPv6, guardado com a parte de host zerada
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv6Network {
+    network: Ipv6Addr,
+    prefix: u8,
+}
+
+/// Pool IPv6. Um prefixo como /64 tem endereços demais para listar, então
+/// só os primeiros `size` hosts são geridos, num bit-set indexado pela
+/// parte de host do endereço.
+#[derive(Debug, Clone)]
+pub struct Ipv6Pool {
+    pub prefix: Ipv6Network,
+    pub gateway: Ipv6Addr,
+    /// Bit `i` marca o host `i` como em uso
+    hosts: Vec<u64>,
+    size: u64,
+    used: u64,
+    /// Primeira palavra do bit-set que pode ter um host livre
+    next_word: usize,
+}
+
 pub struct IpamManager {
     pools: HashMap<String, IpPool>,
+    ipv6_pools: HashMap<String, Ipv6Pool>,
+    ipv6_pool_size: u64,
     default_pool: String,
 }
 
@@ -28,10 +56,18 @@ impl IpamManager {
     pub fn new() -> Self {
         Self {
             pools: HashMap::new(),
+            ipv6_pools: HashMap::new(),
+            ipv6_pool_size: DEFAULT_IPV6_POOL_SIZE,
             default_pool: "default".to_string(),
         }
     }
 
+    /// Limita os hosts geridos por pool IPv6 criado daqui em diante
+    pub fn with_ipv6_pool_size(mut self, size: u64) -> Self {
+        self.ipv6_pool_size = size;
+        self
+    }
+
     pub async fn create_pool(&mut self, name: &str, subnet: &str, gateway: &str) -> Result<()> {
         let gateway_ip = IpAddr::from_str(gateway)
             .map_err(|e| PolisError::Network(format!("Gateway inválido: {}", e)))?;
@@ -84,6 +120,36 @@ impl IpamManager {
         Ok(allocation)
     }
 
+    /// Aloca um IP específico, como o de um container que já o usava antes
+    /// de um reinício do daemon
+    pub async fn allocate_specific_ip(
+        &mut self,
+        container_id: &str,
+        ip: IpAddr,
+        pool_name: Option<&str>,
+    ) -> Result<IpAllocation> {
+        let pool_name = pool_name.unwrap_or(&self.default_pool);
+        let pool = self
+            .pools
+            .get_mut(pool_name)
+            .ok_or_else(|| PolisError::Network(format!("Pool '{}' não encontrado", pool_name)))?;
+
+        let position = pool
+            .available_ips
+            .iter()
+            .position(|available| *available == ip)
+            .ok_or_else(|| PolisError::Network(format!("IP {} não está disponível", ip)))?;
+        pool.available_ips.remove(position);
+        pool.allocated_ips.insert(container_id.to_string(), ip);
+
+        Ok(IpAllocation {
+            container_id: container_id.to_string(),
+            ip,
+            subnet: pool.subnet.clone(),
+            gateway: pool.gateway,
+        })
+    }
+
     pub async fn deallocate_ip(
         &mut self,
         container_id: &str,
@@ -147,6 +213,61 @@ impl IpamManager {
         Ok(allocations)
     }
 
+    pub async fn create_ipv6_pool(
+        &mut self,
+        name: &str,
+        prefix: Ipv6Network,
+        gateway: Ipv6Addr,
+    ) -> Result<()> {
+        if !prefix.contains(gateway) {
+            return Err(PolisError::Network(format!(
+                "Gateway {} fora do prefixo {}",
+                gateway, prefix
+            )));
+        }
+
+        let pool = Ipv6Pool::new(prefix, gateway, self.ipv6_pool_size);
+        self.ipv6_pools.insert(name.to_string(), pool);
+        println!(
+            "� Pool IPv6 '{}' criado: {} (gateway: {})",
+            name, prefix, gateway
+        );
+        Ok(())
+    }
+
+    pub async fn allocate_ipv6(&mut self, pool_name: &str) -> Result<Ipv6Addr> {
+        let pool = self.ipv6_pool_mut(pool_name)?;
+        let ip = pool.allocate().ok_or_else(|| {
+            PolisError::Network(format!("Nenhum IPv6 disponível no pool '{}'", pool_name))
+        })?;
+
+        println!("� IPv6 {} alocado do pool {}", ip, pool_name);
+        Ok(ip)
+    }
+
+    pub async fn release_ipv6(&mut self, pool_name: &str, ip: Ipv6Addr) -> Result<()> {
+        let pool = self.ipv6_pool_mut(pool_name)?;
+        if !pool.release(ip) {
+            return Err(PolisError::Network(format!(
+                "IPv6 {} não está alocado no pool '{}'",
+                ip, pool_name
+            )));
+        }
+
+        println!("� IPv6 {} liberado no pool {}", ip, pool_name);
+        Ok(())
+    }
+
+    pub async fn get_ipv6_pool(&self, pool_name: &str) -> Option<Ipv6Pool> {
+        self.ipv6_pools.get(pool_name).cloned()
+    }
+
+    fn ipv6_pool_mut(&mut self, pool_name: &str) -> Result<&mut Ipv6Pool> {
+        self.ipv6_pools
+            .get_mut(pool_name)
+            .ok_or_else(|| PolisError::Network(format!("Pool IPv6 '{}' não encontrado", pool_name)))
+    }
+
     pub async fn get_pool_stats(&self, pool_name: Option<&str>) -> Result<PoolStats> {
         let pool_name = pool_name.unwrap_or(&self.default_pool);
         let pool = self
@@ -204,6 +325,159 @@ impl IpamManager {
     }
 }
 
+impl Ipv6Network {
+    pub fn new(address: Ipv6Addr, prefix: u8) -> Result<Self> {
+        if prefix > 128 {
+            return Err(PolisError::Network(format!(
+                "Prefixo IPv6 inválido: {}",
+                prefix
+            )));
+        }
+        let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+        Ok(Self {
+            network: Ipv6Addr::from(u128::from(address) & mask),
+            prefix,
+        })
+    }
+
+    pub fn network(&self) -> Ipv6Addr {
+        self.network
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    pub fn contains(&self, ip: Ipv6Addr) -> bool {
+        let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+        u128::from(ip) & mask == u128::from(self.network)
+    }
+}
+
+impl FromStr for Ipv6Network {
+    type Err = PolisError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (address, prefix) = value
+            .trim()
+            .split_once('/')
+            .ok_or_else(|| PolisError::Network(format!("Prefixo IPv6 sem '/': '{}'", value)))?;
+        let address = Ipv6Addr::from_str(address).map_err(|e| {
+            PolisError::Network(format!("Prefixo IPv6 inválido '{}': {}", value, e))
+        })?;
+        let prefix = prefix.parse::<u8>().map_err(|e| {
+            PolisError::Network(format!("Prefixo IPv6 inválido '{}': {}", value, e))
+        })?;
+        Self::new(address, prefix)
+    }
+}
+
+impl fmt::Display for Ipv6Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+impl Ipv6Pool {
+    fn new(prefix: Ipv6Network, gateway: Ipv6Addr, max_size: u64) -> Self {
+        let host_bits = 128 - prefix.prefix() as u32;
+        let size = match 1u64.checked_shl(host_bits) {
+            Some(hosts) => hosts.min(max_size),
+            None => max_size,
+        };
+
+        let mut pool = Self {
+            prefix,
+            gateway,
+            hosts: vec![0; size.div_ceil(64) as usize],
+            size,
+            used: 0,
+            next_word: 0,
+        };
+        // O endereço zero do prefixo é o anycast dos roteadores da subnet
+        pool.mark(0);
+        if let Some(index) = pool.index_of(gateway) {
+            pool.mark(index);
+        }
+        pool
+    }
+
+    /// Hosts geridos pelo pool, incluindo os reservados
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Hosts em uso, incluindo os reservados
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn available(&self) -> u64 {
+        self.size - self.used
+    }
+
+    pub fn is_allocated(&self, ip: Ipv6Addr) -> bool {
+        match self.index_of(ip) {
+            Some(index) => self.hosts[(index / 64) as usize] & (1 << (index % 64)) != 0,
+            None => false,
+        }
+    }
+
+    fn allocate(&mut self) -> Option<Ipv6Addr> {
+        for word in self.next_word..self.hosts.len() {
+            let free = !self.hosts[word];
+            if free == 0 {
+                continue;
+            }
+            let index = word as u64 * 64 + free.trailing_zeros() as u64;
+            if index >= self.size {
+                break;
+            }
+            self.mark(index);
+            self.next_word = word;
+            return Some(self.address_of(index));
+        }
+        self.next_word = self.hosts.len();
+        None
+    }
+
+    fn release(&mut self, ip: Ipv6Addr) -> bool {
+        // O endereço zero e o gateway ficam reservados
+        let index = match self.index_of(ip) {
+            Some(index) if index != 0 && ip != self.gateway && self.is_allocated(ip) => index,
+            _ => return false,
+        };
+
+        let word = (index / 64) as usize;
+        self.hosts[word] &= !(1 << (index % 64));
+        self.used -= 1;
+        self.next_word = self.next_word.min(word);
+        true
+    }
+
+    fn mark(&mut self, index: u64) {
+        let word = (index / 64) as usize;
+        let bit = 1 << (index % 64);
+        if self.hosts[word] & bit == 0 {
+            self.hosts[word] |= bit;
+            self.used += 1;
+        }
+    }
+
+    /// Parte de host de `ip`, se estiver entre os hosts geridos
+    fn index_of(&self, ip: Ipv6Addr) -> Option<u64> {
+        if !self.prefix.contains(ip) {
+            return None;
+        }
+        let index = u128::from(ip) - u128::from(self.prefix.network());
+        u64::try_from(index).ok().filter(|index| *index < self.size)
+    }
+
+    fn address_of(&self, index: u64) -> Ipv6Addr {
+        Ipv6Addr::from(u128::from(self.prefix.network()) + index as u128)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PoolStats {
     pub name: String,
@@ -1,15 +1,25 @@
+pub mod backend;
 pub mod bridge;
 pub mod dns;
+pub mod egress;
 pub mod firewall;
 pub mod ipam;
+pub mod netlink;
 pub mod network;
+pub mod network_policy;
 pub mod port;
 pub mod port_forwarding;
+pub mod vxlan;
 
+pub use backend::*;
 pub use bridge::*;
 pub use dns::*;
+pub use egress::*;
 pub use firewall::{ChainStats, FirewallAction, FirewallManager, FirewallRule};
 pub use ipam::*;
+pub use netlink::*;
 pub use network::*;
+pub use network_policy::*;
 pub use port::*;
 pub use port_forwarding::{PortForwardingManager, PortForwardingRule, PortForwardingStats};
+pub use vxlan::*;
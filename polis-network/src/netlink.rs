@@ -0,0 +1,259 @@
+use crate::{KernelOp, NetlinkBackend, VxlanDevice};
+use futures::StreamExt;
+use netlink_packet_core::{
+    NetlinkMessage, NetlinkPayload, NLM_F_ACK, NLM_F_APPEND, NLM_F_CREATE, NLM_F_REQUEST,
+};
+use netlink_packet_route::{constants::NTF_SELF, RtnlMessage};
+use polis_core::{PolisError, Result};
+use rtnetlink::Handle;
+use std::fs::File;
+use std::net::IpAddr;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+/// Aplica as operações no kernel via rtnetlink.
+///
+/// Cada operação roda numa thread própria com um runtime de thread única:
+/// `apply` é síncrono e é chamado de dentro do runtime do daemon, e
+/// `InNetns` troca o namespace de rede da thread com setns(2), o que não
+/// pode vazar para as threads do tokio.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KernelNetlink;
+
+impl NetlinkBackend for KernelNetlink {
+    fn apply(&self, op: KernelOp) -> Result<()> {
+        std::thread::spawn(move || {
+            let (op, netns) = match op {
+                KernelOp::InNetns { netns, op } => (*op, Some(netns)),
+                op => (op, None),
+            };
+            if let Some(netns) = netns {
+                enter_netns(&netns)?;
+            }
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .build()
+                .map_err(|e| {
+                    PolisError::Network(format!("Erro ao criar runtime netlink: {}", e))
+                })?;
+            runtime.block_on(async {
+                let (connection, handle, _) = rtnetlink::new_connection().map_err(|e| {
+                    PolisError::Network(format!("Erro ao abrir socket netlink: {}", e))
+                })?;
+                tokio::spawn(connection);
+                execute(handle, op).await
+            })
+        })
+        .join()
+        .map_err(|_| PolisError::Network("Thread netlink terminou com pânico".to_string()))?
+    }
+}
+
+fn enter_netns(netns: &Path) -> Result<()> {
+    let file = File::open(netns).map_err(|e| {
+        PolisError::Network(format!(
+            "Erro ao abrir namespace {}: {}",
+            netns.display(),
+            e
+        ))
+    })?;
+    if unsafe { libc::setns(file.as_raw_fd(), libc::CLONE_NEWNET) } != 0 {
+        return Err(PolisError::Network(format!(
+            "Erro ao entrar no namespace {}: {}",
+            netns.display(),
+            std::io::Error::last_os_error()
+        )));
+    }
+    Ok(())
+}
+
+async fn execute(handle: Handle, op: KernelOp) -> Result<()> {
+    let describe = format!("{:?}", op);
+    let failed = |e: rtnetlink::Error| PolisError::Network(format!("{} falhou: {}", describe, e));
+    match op {
+        KernelOp::CreateBridge { name, mtu } => {
+            handle
+                .link()
+                .add()
+                .bridge(name.clone())
+                .execute()
+                .await
+                .map_err(failed)?;
+            let index = link_index(&name)?;
+            handle
+                .link()
+                .set(index)
+                .mtu(mtu as u32)
+                .execute()
+                .await
+                .map_err(failed)
+        }
+        KernelOp::DeleteBridge { name } | KernelOp::DeleteLink { name } => {
+            let index = link_index(&name)?;
+            handle.link().del(index).execute().await.map_err(failed)
+        }
+        KernelOp::AddAddress { link, address } => {
+            let (ip, prefix) = parse_cidr(&address)?;
+            let index = link_index(&link)?;
+            handle
+                .address()
+                .add(index, ip, prefix)
+                .execute()
+                .await
+                .map_err(failed)
+        }
+        KernelOp::SetLinkUp { name } => {
+            let index = link_index(&name)?;
+            handle
+                .link()
+                .set(index)
+                .up()
+                .execute()
+                .await
+                .map_err(failed)
+        }
+        KernelOp::SetLinkDown { name } => {
+            let index = link_index(&name)?;
+            handle
+                .link()
+                .set(index)
+                .down()
+                .execute()
+                .await
+                .map_err(failed)
+        }
+        KernelOp::AttachInterface { bridge, interface } => {
+            let master = link_index(&bridge)?;
+            let index = link_index(&interface)?;
+            handle
+                .link()
+                .set(index)
+                .master(master)
+                .execute()
+                .await
+                .map_err(failed)
+        }
+        KernelOp::DetachInterface { interface, .. } => {
+            let index = link_index(&interface)?;
+            handle
+                .link()
+                .set(index)
+                .nomaster()
+                .execute()
+                .await
+                .map_err(failed)
+        }
+        KernelOp::CreateVxlan(VxlanDevice {
+            name,
+            vni,
+            local,
+            port,
+            mtu,
+        }) => {
+            let request = handle
+                .link()
+                .add()
+                .vxlan(name.clone(), vni)
+                .port(port)
+                .learning(0);
+            let request = match local {
+                IpAddr::V4(local) => request.local(local),
+                IpAddr::V6(local) => request.local6(local),
+            };
+            request.execute().await.map_err(failed)?;
+            let index = link_index(&name)?;
+            handle
+                .link()
+                .set(index)
+                .mtu(mtu as u32)
+                .execute()
+                .await
+                .map_err(failed)
+        }
+        KernelOp::AddFdbEntry { link, destination } => {
+            let index = link_index(&link)?;
+            add_fdb_entry(handle, index, destination)
+                .await
+                .map_err(failed)
+        }
+        KernelOp::CreateVethPair { name, peer } => handle
+            .link()
+            .add()
+            .veth(name, peer)
+            .execute()
+            .await
+            .map_err(failed),
+        KernelOp::MoveToNetns { link, netns } => {
+            let file = File::open(&netns).map_err(|e| {
+                PolisError::Network(format!(
+                    "Erro ao abrir namespace {}: {}",
+                    netns.display(),
+                    e
+                ))
+            })?;
+            let index = link_index(&link)?;
+            handle
+                .link()
+                .set(index)
+                .setns_by_fd(file.as_raw_fd())
+                .execute()
+                .await
+                .map_err(failed)
+        }
+        KernelOp::InNetns { .. } => Err(PolisError::Network(format!(
+            "{}: namespaces aninhados não são suportados",
+            describe
+        ))),
+    }
+}
+
+/// Acrescenta uma entrada de MAC zerado ao FDB do VXLAN. O `execute` do
+/// rtnetlink usa NLM_F_EXCL, que recusaria o segundo VTEP remoto; o
+/// `bridge fdb append` do iproute2 usa NLM_F_APPEND.
+async fn add_fdb_entry(
+    mut handle: Handle,
+    index: u32,
+    destination: IpAddr,
+) -> std::result::Result<(), rtnetlink::Error> {
+    let mut request = handle
+        .neighbours()
+        .add_bridge(index, &[0; 6])
+        .destination(destination)
+        .flags(NTF_SELF);
+    let mut message =
+        NetlinkMessage::from(RtnlMessage::NewNeighbour(request.message_mut().clone()));
+    message.header.flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_APPEND;
+
+    let mut response = handle.request(message)?;
+    while let Some(message) = response.next().await {
+        if let NetlinkPayload::Error(err) = message.payload {
+            return Err(rtnetlink::Error::NetlinkError(err));
+        }
+    }
+    Ok(())
+}
+
+/// Índice da interface no namespace da thread. Não usa o dump de links do
+/// rtnetlink: o netlink-packet-route não decodifica os atributos de um
+/// VXLAN e a resposta é descartada, deixando a requisição pendente.
+fn link_index(name: &str) -> Result<u32> {
+    let c_name = std::ffi::CString::new(name)
+        .map_err(|_| PolisError::Network(format!("Nome de interface inválido: {}", name)))?;
+    match unsafe { libc::if_nametoindex(c_name.as_ptr()) } {
+        0 => Err(PolisError::Network(format!(
+            "Interface '{}' não encontrada: {}",
+            name,
+            std::io::Error::last_os_error()
+        ))),
+        index => Ok(index),
+    }
+}
+
+fn parse_cidr(address: &str) -> Result<(IpAddr, u8)> {
+    let invalid = || PolisError::Network(format!("Endereço inválido: {}", address));
+    let (ip, prefix) = address.split_once('/').ok_or_else(invalid)?;
+    Ok((
+        ip.parse().map_err(|_| invalid())?,
+        prefix.parse().map_err(|_| invalid())?,
+    ))
+}
@@ -0,0 +1,526 @@
+use crate::firewall::Protocol;
+use crate::{Cidr, FirewallAction, FirewallManager, FirewallRule};
+use polis_core::{PolisError, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+
+/// Chain com as regras geradas pelas políticas de rede
+pub const NETWORK_POLICY_CHAIN: &str = "POLIS-NETPOL";
+
+/// Seleciona pods ou namespaces cujos labels contêm todos os pares de
+/// `match_labels`; um seletor vazio seleciona todos
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelSelector {
+    pub match_labels: HashMap<String, String>,
+}
+
+impl LabelSelector {
+    /// Seletor que casa com tudo
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Exige o label `key` com o valor `value`
+    pub fn with_label(mut self, key: &str, value: &str) -> Self {
+        self.match_labels.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.match_labels
+            .iter()
+            .all(|(key, value)| labels.get(key) == Some(value))
+    }
+}
+
+/// Direção do tráfego isolada por uma política
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyType {
+    Ingress,
+    Egress,
+}
+
+/// Bloco de IPs, descontadas as faixas de `except`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpBlock {
+    pub cidr: Cidr,
+    pub except: Vec<Cidr>,
+}
+
+impl IpBlock {
+    /// Valida o bloco e suas exceções, que devem estar contidas nele
+    pub fn parse(cidr: &str, except: &[&str]) -> Result<Self> {
+        let cidr = normalize(Cidr::parse(cidr)?);
+        let mut ranges = Vec::with_capacity(except.len());
+        for range in except {
+            let range = normalize(Cidr::parse(range)?);
+            if range.prefix < cidr.prefix || !cidr.contains(range.network) {
+                return Err(PolisError::Network(format!(
+                    "Exceção '{}' fora do bloco '{}'",
+                    range, cidr
+                )));
+            }
+            ranges.push(range);
+        }
+        Ok(Self {
+            cidr,
+            except: ranges,
+        })
+    }
+
+    /// Redes que cobrem exatamente o bloco sem as exceções
+    pub fn ranges(&self) -> Vec<Cidr> {
+        let mut ranges = vec![self.cidr];
+        for except in &self.except {
+            ranges = ranges
+                .into_iter()
+                .flat_map(|range| subtract(range, *except))
+                .collect();
+        }
+        ranges
+    }
+}
+
+/// Origem (ingress) ou destino (egress) permitido por uma regra
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyPeer {
+    /// Pods do namespace da política selecionados por labels
+    Pods(LabelSelector),
+    /// Todos os pods dos namespaces selecionados (`namespaceSelector`)
+    Namespaces(LabelSelector),
+    /// Pods selecionados dentro dos namespaces selecionados
+    PodsInNamespaces {
+        namespace_selector: LabelSelector,
+        pod_selector: LabelSelector,
+    },
+    /// Faixa de IPs (`ipBlock`)
+    IpBlock(IpBlock),
+}
+
+/// Porta liberada por uma regra; sem `port`, todas as portas do protocolo
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyPort {
+    pub protocol: Protocol,
+    pub port: Option<u16>,
+}
+
+impl PolicyPort {
+    pub fn tcp(port: u16) -> Self {
+        Self {
+            protocol: Protocol::Tcp,
+            port: Some(port),
+        }
+    }
+
+    pub fn udp(port: u16) -> Self {
+        Self {
+            protocol: Protocol::Udp,
+            port: Some(port),
+        }
+    }
+}
+
+/// Tráfego de entrada permitido; `from` vazio aceita qualquer origem e
+/// `ports` vazio qualquer porta
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IngressRule {
+    pub from: Vec<PolicyPeer>,
+    pub ports: Vec<PolicyPort>,
+}
+
+/// Tráfego de saída permitido; `to` vazio aceita qualquer destino e
+/// `ports` vazio qualquer porta
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EgressRule {
+    pub to: Vec<PolicyPeer>,
+    pub ports: Vec<PolicyPort>,
+}
+
+/// Política de rede no estilo do Kubernetes: isola os pods de `namespace`
+/// selecionados por `pod_selector` nas direções de `policy_types`, que
+/// passam a aceitar apenas o tráfego permitido por alguma regra de alguma
+/// política
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkPolicy {
+    pub name: String,
+    pub namespace: String,
+    pub pod_selector: LabelSelector,
+    pub ingress: Vec<IngressRule>,
+    pub egress: Vec<EgressRule>,
+    pub policy_types: Vec<PolicyType>,
+}
+
+impl NetworkPolicy {
+    /// Política que isola a entrada dos pods selecionados; sem regras de
+    /// ingress, nenhuma entrada é permitida
+    pub fn new(name: &str, namespace: &str, pod_selector: LabelSelector) -> Self {
+        Self {
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            pod_selector,
+            ingress: Vec::new(),
+            egress: Vec::new(),
+            policy_types: vec![PolicyType::Ingress],
+        }
+    }
+
+    pub fn with_ingress(mut self, rule: IngressRule) -> Self {
+        self.ingress.push(rule);
+        self
+    }
+
+    /// Adiciona uma regra de saída; a saída dos pods passa a ser isolada
+    pub fn with_egress(mut self, rule: EgressRule) -> Self {
+        self.egress.push(rule);
+        if !self.policy_types.contains(&PolicyType::Egress) {
+            self.policy_types.push(PolicyType::Egress);
+        }
+        self
+    }
+
+    /// Define as direções isoladas, por exemplo apenas `Egress` para
+    /// bloquear toda a saída
+    pub fn with_policy_types(mut self, policy_types: Vec<PolicyType>) -> Self {
+        self.policy_types = policy_types;
+        self
+    }
+}
+
+/// Pod considerado na tradução das políticas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyPod {
+    pub name: String,
+    pub namespace: String,
+    pub ip: IpAddr,
+    pub labels: HashMap<String, String>,
+}
+
+/// Regras geradas por uma política: as permissões e o bloqueio de cada
+/// pod isolado
+#[derive(Default)]
+struct PolicyRules {
+    allow: Vec<FirewallRule>,
+    isolate: Vec<FirewallRule>,
+}
+
+impl FirewallManager {
+    /// Registra ou atualiza um pod e reaplica as políticas
+    pub async fn register_pod(&mut self, pod: PolicyPod) -> Result<()> {
+        self.policy_pods
+            .insert((pod.namespace.clone(), pod.name.clone()), pod);
+        self.sync_network_policies().await
+    }
+
+    /// Remove um pod e reaplica as políticas
+    pub async fn unregister_pod(&mut self, namespace: &str, name: &str) -> Result<()> {
+        self.policy_pods
+            .remove(&(namespace.to_string(), name.to_string()));
+        self.sync_network_policies().await
+    }
+
+    /// Define os labels de um namespace, usados pelos seletores de namespace
+    pub async fn set_namespace_labels(
+        &mut self,
+        namespace: &str,
+        labels: HashMap<String, String>,
+    ) -> Result<()> {
+        self.namespace_labels.insert(namespace.to_string(), labels);
+        self.sync_network_policies().await
+    }
+
+    /// Traduz a política em regras de firewall e as instala na chain
+    /// `POLIS-NETPOL`, substituindo uma política anterior de mesmo nome.
+    /// Retorna as regras da política; reaplicá-la sem mudanças não altera
+    /// a chain.
+    pub async fn apply_network_policy(
+        &mut self,
+        policy: NetworkPolicy,
+    ) -> Result<Vec<FirewallRule>> {
+        if policy.name.is_empty() {
+            return Err(PolisError::Network("Política de rede sem nome".to_string()));
+        }
+
+        let name = policy.name.clone();
+        self.network_policies.insert(name.clone(), policy);
+        self.sync_network_policies().await?;
+
+        let rules = self.translate_policy(&self.network_policies[&name]);
+        println!(
+            "� Política de rede '{}' aplicada com {} regras",
+            name,
+            rules.allow.len() + rules.isolate.len()
+        );
+        Ok(rules.allow.into_iter().chain(rules.isolate).collect())
+    }
+
+    /// Remove a política e as regras geradas por ela
+    pub async fn remove_network_policy(&mut self, policy_name: &str) -> Result<()> {
+        if self.network_policies.remove(policy_name).is_none() {
+            return Err(PolisError::Network(format!(
+                "Política de rede '{}' não encontrada",
+                policy_name
+            )));
+        }
+        self.sync_network_policies().await?;
+        println!("� Política de rede '{}' removida", policy_name);
+        Ok(())
+    }
+
+    pub fn get_network_policy(&self, name: &str) -> Option<&NetworkPolicy> {
+        self.network_policies.get(name)
+    }
+
+    /// Reescreve a chain com as permissões de todas as políticas seguidas
+    /// dos bloqueios, para que uma política nunca bloqueie o que outra
+    /// permite. Nada é alterado se as regras não mudaram.
+    async fn sync_network_policies(&mut self) -> Result<()> {
+        let mut allow = Vec::new();
+        let mut isolate = BTreeMap::new();
+        for policy in self.network_policies.values() {
+            let rules = self.translate_policy(policy);
+            allow.extend(rules.allow);
+            for rule in rules.isolate {
+                isolate.insert(rule.id.clone(), rule);
+            }
+        }
+        let rules: Vec<FirewallRule> = allow.into_iter().chain(isolate.into_values()).collect();
+
+        if !self.has_chain(NETWORK_POLICY_CHAIN) {
+            if rules.is_empty() {
+                return Ok(());
+            }
+            self.create_chain(NETWORK_POLICY_CHAIN, FirewallAction::Allow)
+                .await?;
+        }
+        if self.list_rules(Some(NETWORK_POLICY_CHAIN)).await? == rules {
+            return Ok(());
+        }
+
+        self.flush_chain(NETWORK_POLICY_CHAIN).await?;
+        for rule in rules {
+            self.add_rule(NETWORK_POLICY_CHAIN, rule).await?;
+        }
+        Ok(())
+    }
+
+    fn translate_policy(&self, policy: &NetworkPolicy) -> PolicyRules {
+        let mut rules = PolicyRules::default();
+        let targets: Vec<&PolicyPod> = self
+            .policy_pods
+            .values()
+            .filter(|pod| {
+                pod.namespace == policy.namespace && policy.pod_selector.matches(&pod.labels)
+            })
+            .collect();
+
+        let mut next = 0;
+        for target in targets {
+            let pod = host(target.ip);
+
+            if policy.policy_types.contains(&PolicyType::Ingress) {
+                for rule in &policy.ingress {
+                    for source in self.resolve_peers(policy, &rule.from, pod) {
+                        for port in ports(&rule.ports) {
+                            rules.allow.push(policy_rule(
+                                format!("netpol-{}-in-{}", policy.name, next),
+                                FirewallAction::Allow,
+                                source,
+                                Some(pod),
+                                port,
+                                format!("Entrada permitida pela política {}", policy.name),
+                            ));
+                            next += 1;
+                        }
+                    }
+                }
+                rules.isolate.push(policy_rule(
+                    format!("netpol-isolate-in-{}", target.ip),
+                    FirewallAction::Deny,
+                    None,
+                    Some(pod),
+                    None,
+                    format!("Entrada isolada de {}/{}", target.namespace, target.name),
+                ));
+            }
+
+            if policy.policy_types.contains(&PolicyType::Egress) {
+                for rule in &policy.egress {
+                    for destination in self.resolve_peers(policy, &rule.to, pod) {
+                        for port in ports(&rule.ports) {
+                            rules.allow.push(policy_rule(
+                                format!("netpol-{}-out-{}", policy.name, next),
+                                FirewallAction::Allow,
+                                Some(pod),
+                                destination,
+                                port,
+                                format!("Saída permitida pela política {}", policy.name),
+                            ));
+                            next += 1;
+                        }
+                    }
+                }
+                rules.isolate.push(policy_rule(
+                    format!("netpol-isolate-out-{}", target.ip),
+                    FirewallAction::Deny,
+                    Some(pod),
+                    None,
+                    None,
+                    format!("Saída isolada de {}/{}", target.namespace, target.name),
+                ));
+            }
+        }
+        rules
+    }
+
+    /// Redes de uma lista de peers na família de IP de `pod`; `None`
+    /// representa qualquer endereço (lista vazia)
+    fn resolve_peers(
+        &self,
+        policy: &NetworkPolicy,
+        peers: &[PolicyPeer],
+        pod: Cidr,
+    ) -> Vec<Option<Cidr>> {
+        if peers.is_empty() {
+            return vec![None];
+        }
+
+        let no_labels = HashMap::new();
+        let namespace_matches = |selector: &LabelSelector, namespace: &str| {
+            selector.matches(self.namespace_labels.get(namespace).unwrap_or(&no_labels))
+        };
+        let mut ranges = Vec::new();
+        for peer in peers {
+            match peer {
+                PolicyPeer::Pods(selector) => ranges.extend(
+                    self.policy_pods
+                        .values()
+                        .filter(|p| p.namespace == policy.namespace && selector.matches(&p.labels))
+                        .map(|p| host(p.ip)),
+                ),
+                PolicyPeer::Namespaces(selector) => ranges.extend(
+                    self.policy_pods
+                        .values()
+                        .filter(|p| namespace_matches(selector, &p.namespace))
+                        .map(|p| host(p.ip)),
+                ),
+                PolicyPeer::PodsInNamespaces {
+                    namespace_selector,
+                    pod_selector,
+                } => ranges.extend(
+                    self.policy_pods
+                        .values()
+                        .filter(|p| {
+                            namespace_matches(namespace_selector, &p.namespace)
+                                && pod_selector.matches(&p.labels)
+                        })
+                        .map(|p| host(p.ip)),
+                ),
+                PolicyPeer::IpBlock(block) => ranges.extend(block.ranges()),
+            }
+        }
+
+        let mut resolved: Vec<Option<Cidr>> = Vec::new();
+        for range in ranges {
+            if range.network.is_ipv4() == pod.network.is_ipv4() && !resolved.contains(&Some(range))
+            {
+                resolved.push(Some(range));
+            }
+        }
+        resolved
+    }
+}
+
+/// Portas de uma regra; `None` representa todas
+fn ports(ports: &[PolicyPort]) -> Vec<Option<&PolicyPort>> {
+    if ports.is_empty() {
+        vec![None]
+    } else {
+        ports.iter().map(Some).collect()
+    }
+}
+
+fn policy_rule(
+    id: String,
+    action: FirewallAction,
+    source: Option<Cidr>,
+    destination: Option<Cidr>,
+    port: Option<&PolicyPort>,
+    comment: String,
+) -> FirewallRule {
+    FirewallRule {
+        id,
+        action,
+        protocol: port.map_or(Protocol::All, |port| port.protocol.clone()),
+        source_ip: source.map(|cidr| cidr.network),
+        source_prefix: source.map(|cidr| cidr.prefix),
+        source_port: None,
+        dest_ip: destination.map(|cidr| cidr.network),
+        dest_prefix: destination.map(|cidr| cidr.prefix),
+        dest_port: port.and_then(|port| port.port),
+        interface: None,
+        comment: Some(comment),
+    }
+}
+
+/// Rede que cobre apenas `ip`
+fn host(ip: IpAddr) -> Cidr {
+    let prefix = if ip.is_ipv4() { 32 } else { 128 };
+    Cidr {
+        network: ip,
+        prefix,
+    }
+}
+
+/// Zera os bits de host do endereço de rede
+fn normalize(cidr: Cidr) -> Cidr {
+    let network = match cidr.network {
+        IpAddr::V4(ip) => {
+            let mask = u32::MAX.checked_shl(32 - cidr.prefix as u32).unwrap_or(0);
+            IpAddr::V4((u32::from(ip) & mask).into())
+        }
+        IpAddr::V6(ip) => {
+            let mask = u128::MAX.checked_shl(128 - cidr.prefix as u32).unwrap_or(0);
+            IpAddr::V6((u128::from(ip) & mask).into())
+        }
+    };
+    Cidr {
+        network,
+        prefix: cidr.prefix,
+    }
+}
+
+/// Partes de `range` fora de `except`, dividindo-o ao meio até isolar a
+/// exceção
+fn subtract(range: Cidr, except: Cidr) -> Vec<Cidr> {
+    if except.prefix <= range.prefix {
+        return if except.contains(range.network) {
+            Vec::new()
+        } else {
+            vec![range]
+        };
+    }
+    if !range.contains(except.network) {
+        return vec![range];
+    }
+
+    let prefix = range.prefix + 1;
+    let upper = match range.network {
+        IpAddr::V4(ip) => IpAddr::V4((u32::from(ip) | (1 << (32 - prefix as u32))).into()),
+        IpAddr::V6(ip) => IpAddr::V6((u128::from(ip) | (1 << (128 - prefix as u32))).into()),
+    };
+    let mut parts = subtract(
+        Cidr {
+            network: range.network,
+            prefix,
+        },
+        except,
+    );
+    parts.extend(subtract(
+        Cidr {
+            network: upper,
+            prefix,
+        },
+        except,
+    ));
+    parts
+}
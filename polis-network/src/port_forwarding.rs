@@ -1,6 +1,8 @@
+use crate::PortForwardingBackend;
 use polis_core::{PolisError, Result};
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Protocol {
@@ -33,6 +35,7 @@ pub struct PortForwardingStats {
 pub struct PortForwardingManager {
     rules: HashMap<String, PortForwardingRule>,
     next_id: u32,
+    backend: Option<Arc<dyn PortForwardingBackend>>,
 }
 
 impl PortForwardingManager {
@@ -40,9 +43,16 @@ impl PortForwardingManager {
         Self {
             rules: HashMap::new(),
             next_id: 1,
+            backend: None,
         }
     }
 
+    /// Instala as regras habilitadas no kernel através do backend
+    pub fn with_backend(mut self, backend: Arc<dyn PortForwardingBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
     pub async fn add_rule(
         &mut self,
         host_ip: IpAddr,
@@ -77,6 +87,9 @@ impl PortForwardingManager {
             )));
         }
 
+        if let Some(backend) = &self.backend {
+            backend.add_forward(&rule)?;
+        }
         self.rules.insert(rule_id.clone(), rule.clone());
         println!(
             "� Port forwarding criado: {}:{} -> {}:{} ({:?})",
@@ -87,7 +100,13 @@ impl PortForwardingManager {
     }
 
     pub async fn remove_rule(&mut self, rule_id: &str) -> Result<()> {
-        if self.rules.remove(rule_id).is_some() {
+        if let Some(rule) = self.rules.get(rule_id) {
+            if rule.enabled {
+                if let Some(backend) = &self.backend {
+                    backend.remove_forward(rule)?;
+                }
+            }
+            self.rules.remove(rule_id);
             println!("� Port forwarding removido: {}", rule_id);
             Ok(())
         } else {
@@ -100,6 +119,11 @@ impl PortForwardingManager {
 
     pub async fn enable_rule(&mut self, rule_id: &str) -> Result<()> {
         if let Some(rule) = self.rules.get_mut(rule_id) {
+            if !rule.enabled {
+                if let Some(backend) = &self.backend {
+                    backend.add_forward(rule)?;
+                }
+            }
             rule.enabled = true;
             println!("� Port forwarding habilitado: {}", rule_id);
             Ok(())
@@ -113,6 +137,11 @@ impl PortForwardingManager {
 
     pub async fn disable_rule(&mut self, rule_id: &str) -> Result<()> {
         if let Some(rule) = self.rules.get_mut(rule_id) {
+            if rule.enabled {
+                if let Some(backend) = &self.backend {
+                    backend.remove_forward(rule)?;
+                }
+            }
             rule.enabled = false;
             println!("� Port forwarding desabilitado: {}", rule_id);
             Ok(())
@@ -248,6 +277,11 @@ impl PortForwardingManager {
 
     pub async fn clear_rules(&mut self) -> Result<()> {
         let count = self.rules.len();
+        if let Some(backend) = &self.backend {
+            for rule in self.rules.values().filter(|rule| rule.enabled) {
+                backend.remove_forward(rule)?;
+            }
+        }
         self.rules.clear();
         println!("� {} regras de port forwarding removidas", count);
         Ok(())
@@ -263,7 +297,7 @@ impl PortForwardingManager {
         }
 
         for rule_id in to_remove {
-            self.rules.remove(&rule_id);
+            self.remove_rule(&rule_id).await?;
         }
 
         println!(
@@ -0,0 +1,337 @@
+use crate::{KernelNetlink, KernelOp, NetlinkBackend, VxlanDevice};
+use polis_core::{PolisError, Result};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Porta UDP do VXLAN atribuída pela IANA
+pub const VXLAN_PORT: u16 = 4789;
+
+/// Maior VNI representável nos 24 bits do cabeçalho VXLAN
+pub const MAX_VNI: u32 = 0x00FF_FFFF;
+
+/// MTU dos dispositivos da overlay: 1500 menos os 50 bytes do encapsulamento
+pub const OVERLAY_MTU: u16 = 1450;
+
+/// Prefixo dos endereços IPv4 atribuídos aos containers da overlay
+pub const OVERLAY_IPV4_PREFIX: u8 = 24;
+
+/// Prefixo dos endereços IPv6 atribuídos aos containers da overlay
+pub const OVERLAY_IPV6_PREFIX: u8 = 64;
+
+/// Maior nome de interface aceito pelo kernel (IFNAMSIZ - 1)
+const MAX_IFNAME: usize = 15;
+
+/// Container ligado a uma overlay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlayEndpoint {
+    pub ip: IpAddr,
+    pub netns: PathBuf,
+    /// Ponta da veth que fica no host, ligada à bridge da overlay
+    pub host_veth: String,
+}
+
+/// Estado de uma overlay VXLAN
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlayInfo {
+    /// Nome da overlay e da bridge que liga o VXLAN às veths dos containers
+    pub name: String,
+    pub vni: u32,
+    /// Nome do dispositivo VXLAN
+    pub device: String,
+    pub vtep_ip: IpAddr,
+    pub remote_vteps: Vec<IpAddr>,
+    pub endpoints: Vec<OverlayEndpoint>,
+    /// `operstate` do dispositivo no sysfs; `None` quando ele não existe
+    pub operstate: Option<String>,
+}
+
+/// Redes overlay VXLAN entre hosts.
+///
+/// Cada overlay é uma bridge com o nome da overlay à qual se ligam o
+/// dispositivo VXLAN (`vxlan<vni>`) e as veths dos containers. O VXLAN não
+/// aprende MACs: cada VTEP remoto recebe uma entrada de FDB com MAC zerado,
+/// que replica o tráfego de broadcast para ele.
+pub struct VxlanManager {
+    overlays: BTreeMap<String, OverlayInfo>,
+    netlink: Arc<dyn NetlinkBackend>,
+    sysfs_net: PathBuf,
+    next_veth: u64,
+}
+
+impl VxlanManager {
+    pub fn new() -> Self {
+        Self {
+            overlays: BTreeMap::new(),
+            netlink: Arc::new(KernelNetlink),
+            sysfs_net: PathBuf::from("/sys/class/net"),
+            next_veth: 0,
+        }
+    }
+
+    /// Aplica as alterações no kernel através de outro backend que não o
+    /// rtnetlink
+    pub fn with_netlink(mut self, netlink: Arc<dyn NetlinkBackend>) -> Self {
+        self.netlink = netlink;
+        self
+    }
+
+    /// Lê o estado dos dispositivos de outro diretório em vez de `/sys/class/net`
+    pub fn with_sysfs_net(mut self, path: PathBuf) -> Self {
+        self.sysfs_net = path;
+        self
+    }
+
+    fn apply(&self, op: KernelOp) -> Result<()> {
+        self.netlink.apply(op)
+    }
+
+    pub async fn create_overlay(
+        &mut self,
+        name: &str,
+        vni: u32,
+        vtep_ip: IpAddr,
+        remote_vteps: Vec<IpAddr>,
+    ) -> Result<()> {
+        if name.is_empty() || name.len() > MAX_IFNAME {
+            return Err(PolisError::Network(format!(
+                "Nome de overlay inválido '{}': use de 1 a {} caracteres",
+                name, MAX_IFNAME
+            )));
+        }
+        if vni == 0 || vni > MAX_VNI {
+            return Err(PolisError::Network(format!(
+                "VNI {} fora do intervalo 1-{}",
+                vni, MAX_VNI
+            )));
+        }
+        if self.overlays.contains_key(name) {
+            return Err(PolisError::Network(format!("Overlay '{}' já existe", name)));
+        }
+        if let Some(other) = self.overlays.values().find(|overlay| overlay.vni == vni) {
+            return Err(PolisError::Network(format!(
+                "VNI {} já usado pela overlay '{}'",
+                vni, other.name
+            )));
+        }
+
+        let mut remotes: Vec<IpAddr> = Vec::new();
+        for remote in remote_vteps {
+            if remote.is_ipv4() != vtep_ip.is_ipv4() {
+                return Err(PolisError::Network(format!(
+                    "VTEP remoto {} e VTEP local {} são de famílias diferentes",
+                    remote, vtep_ip
+                )));
+            }
+            if remote != vtep_ip && !remotes.contains(&remote) {
+                remotes.push(remote);
+            }
+        }
+
+        let device = format!("vxlan{}", vni);
+        self.apply(KernelOp::CreateBridge {
+            name: name.to_string(),
+            mtu: OVERLAY_MTU,
+        })?;
+        self.apply(KernelOp::CreateVxlan(VxlanDevice {
+            name: device.clone(),
+            vni,
+            local: vtep_ip,
+            port: VXLAN_PORT,
+            mtu: OVERLAY_MTU,
+        }))?;
+        self.apply(KernelOp::AttachInterface {
+            bridge: name.to_string(),
+            interface: device.clone(),
+        })?;
+        for remote in &remotes {
+            self.apply(KernelOp::AddFdbEntry {
+                link: device.clone(),
+                destination: *remote,
+            })?;
+        }
+        self.apply(KernelOp::SetLinkUp {
+            name: device.clone(),
+        })?;
+        self.apply(KernelOp::SetLinkUp {
+            name: name.to_string(),
+        })?;
+
+        self.overlays.insert(
+            name.to_string(),
+            OverlayInfo {
+                name: name.to_string(),
+                vni,
+                device,
+                vtep_ip,
+                remote_vteps: remotes,
+                endpoints: Vec::new(),
+                operstate: None,
+            },
+        );
+        println!("� Overlay '{}' criada: VNI {} via {}", name, vni, vtep_ip);
+        Ok(())
+    }
+
+    /// Remove a overlay, as veths dos seus containers e os dispositivos
+    pub async fn delete_overlay(&mut self, name: &str) -> Result<()> {
+        let overlay = self.overlay(name)?.clone();
+        for endpoint in &overlay.endpoints {
+            self.apply(KernelOp::DeleteLink {
+                name: endpoint.host_veth.clone(),
+            })?;
+        }
+        self.apply(KernelOp::DeleteLink {
+            name: overlay.device.clone(),
+        })?;
+        self.apply(KernelOp::DeleteBridge {
+            name: name.to_string(),
+        })?;
+        self.overlays.remove(name);
+        println!("� Overlay '{}' removida", name);
+        Ok(())
+    }
+
+    /// Adiciona um VTEP remoto a uma overlay existente, p. ex. um novo host
+    pub async fn add_remote_vtep(&mut self, name: &str, remote: IpAddr) -> Result<()> {
+        let overlay = self.overlay(name)?;
+        if remote.is_ipv4() != overlay.vtep_ip.is_ipv4() {
+            return Err(PolisError::Network(format!(
+                "VTEP remoto {} e VTEP local {} são de famílias diferentes",
+                remote, overlay.vtep_ip
+            )));
+        }
+        if remote == overlay.vtep_ip || overlay.remote_vteps.contains(&remote) {
+            return Ok(());
+        }
+        self.apply(KernelOp::AddFdbEntry {
+            link: overlay.device.clone(),
+            destination: remote,
+        })?;
+        self.overlay_mut(name)?.remote_vteps.push(remote);
+        Ok(())
+    }
+
+    /// Liga um container à overlay: cria uma veth, prende a ponta do host à
+    /// bridge e move a outra para o namespace `container_ns`, com o IP
+    pub async fn attach_container(
+        &mut self,
+        overlay: &str,
+        container_ns: &Path,
+        container_ip: IpAddr,
+    ) -> Result<()> {
+        let info = self.overlay(overlay)?;
+        if info.endpoints.iter().any(|e| e.ip == container_ip) {
+            return Err(PolisError::Network(format!(
+                "IP {} já está em uso na overlay '{}'",
+                container_ip, overlay
+            )));
+        }
+
+        let id = self.next_veth;
+        let host_veth = format!("vxh{}", id);
+        let peer = format!("vxc{}", id);
+        let prefix = if container_ip.is_ipv4() {
+            OVERLAY_IPV4_PREFIX
+        } else {
+            OVERLAY_IPV6_PREFIX
+        };
+        let in_netns = |op: KernelOp| KernelOp::InNetns {
+            netns: container_ns.to_path_buf(),
+            op: Box::new(op),
+        };
+
+        self.apply(KernelOp::CreateVethPair {
+            name: host_veth.clone(),
+            peer: peer.clone(),
+        })?;
+        self.apply(KernelOp::AttachInterface {
+            bridge: overlay.to_string(),
+            interface: host_veth.clone(),
+        })?;
+        self.apply(KernelOp::SetLinkUp {
+            name: host_veth.clone(),
+        })?;
+        self.apply(KernelOp::MoveToNetns {
+            link: peer.clone(),
+            netns: container_ns.to_path_buf(),
+        })?;
+        self.apply(in_netns(KernelOp::AddAddress {
+            link: peer.clone(),
+            address: format!("{}/{}", container_ip, prefix),
+        }))?;
+        self.apply(in_netns(KernelOp::SetLinkUp { name: peer }))?;
+
+        self.next_veth += 1;
+        self.overlay_mut(overlay)?.endpoints.push(OverlayEndpoint {
+            ip: container_ip,
+            netns: container_ns.to_path_buf(),
+            host_veth,
+        });
+        println!(
+            "� Container em {} ligado à overlay '{}' com IP {}",
+            container_ns.display(),
+            overlay,
+            container_ip
+        );
+        Ok(())
+    }
+
+    /// Desliga o container com `container_ip` da overlay; a ponta da veth no
+    /// namespace some junto com a do host
+    pub async fn detach_container(&mut self, overlay: &str, container_ip: IpAddr) -> Result<()> {
+        let endpoint = self
+            .overlay(overlay)?
+            .endpoints
+            .iter()
+            .find(|e| e.ip == container_ip)
+            .cloned()
+            .ok_or_else(|| {
+                PolisError::Network(format!(
+                    "IP {} não está ligado à overlay '{}'",
+                    container_ip, overlay
+                ))
+            })?;
+        self.apply(KernelOp::DeleteLink {
+            name: endpoint.host_veth,
+        })?;
+        self.overlay_mut(overlay)?
+            .endpoints
+            .retain(|e| e.ip != container_ip);
+        Ok(())
+    }
+
+    /// Overlays por nome, com o estado atual de cada dispositivo VXLAN
+    pub fn list_overlays(&self) -> Vec<OverlayInfo> {
+        self.overlays
+            .values()
+            .map(|overlay| OverlayInfo {
+                operstate: std::fs::read_to_string(
+                    self.sysfs_net.join(&overlay.device).join("operstate"),
+                )
+                .ok()
+                .map(|state| state.trim().to_string()),
+                ..overlay.clone()
+            })
+            .collect()
+    }
+
+    fn overlay(&self, name: &str) -> Result<&OverlayInfo> {
+        self.overlays
+            .get(name)
+            .ok_or_else(|| PolisError::Network(format!("Overlay '{}' não encontrada", name)))
+    }
+
+    fn overlay_mut(&mut self, name: &str) -> Result<&mut OverlayInfo> {
+        self.overlays
+            .get_mut(name)
+            .ok_or_else(|| PolisError::Network(format!("Overlay '{}' não encontrada", name)))
+    }
+}
+
+impl Default for VxlanManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,300 @@
+use polis_core::{EgressMode, EgressPolicy};
+use polis_network::{
+    domain_matches, Cidr, DnsManager, DnsRecord, DnsRecordType, EgressController, EgressDecision,
+    EgressDisposition, FirewallAction, FirewallManager, EGRESS_CHAIN,
+};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+fn policy(entries: &[&str], mode: EgressMode) -> EgressPolicy {
+    let entries: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+    EgressPolicy::from_allow_list(&entries, mode)
+}
+
+fn a_record(name: &str, ip: &str, ttl: u32) -> DnsRecord {
+    DnsRecord {
+        name: name.to_string(),
+        record_type: DnsRecordType::A,
+        value: ip.to_string(),
+        ttl,
+    }
+}
+
+fn ip(value: &str) -> IpAddr {
+    value.parse().unwrap()
+}
+
+async fn rule_ids(firewall: &FirewallManager) -> Vec<String> {
+    firewall
+        .list_rules(Some(EGRESS_CHAIN))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|rule| rule.id)
+        .collect()
+}
+
+#[test]
+fn test_domain_matching() {
+    assert!(domain_matches("api.example.com", "API.example.com."));
+    assert!(domain_matches("*.example.com", "a.b.example.com"));
+    assert!(!domain_matches("*.example.com", "example.com"));
+    assert!(!domain_matches("*.example.com", "badexample.com"));
+    assert!(!domain_matches("example.com", "api.example.com"));
+}
+
+#[test]
+fn test_cidr_contains() {
+    let network = Cidr::parse("10.1.0.0/16").unwrap();
+    assert!(network.contains(ip("10.1.200.3")));
+    assert!(!network.contains(ip("10.2.0.1")));
+    assert!(Cidr::parse("192.0.2.7").unwrap().contains(ip("192.0.2.7")));
+    assert!(Cidr::parse("2001:db8::/32")
+        .unwrap()
+        .contains(ip("2001:db8::1")));
+    assert!(Cidr::parse("10.0.0.0/33").is_err());
+}
+
+#[test]
+fn test_allow_list_splits_domains_and_cidrs() {
+    let policy = policy(
+        &["*.example.com", "10.0.0.0/8", "192.0.2.1"],
+        EgressMode::Enforce,
+    );
+    assert_eq!(policy.allowed_domains, vec!["*.example.com"]);
+    assert_eq!(policy.allowed_cidrs, vec!["10.0.0.0/8", "192.0.2.1"]);
+}
+
+#[tokio::test]
+async fn test_dns_filter_decisions_per_container() {
+    let mut firewall = FirewallManager::new();
+    let mut egress = EgressController::new();
+    let mut violations = egress.subscribe();
+    egress
+        .attach(
+            &mut firewall,
+            "web",
+            policy(
+                &["api.example.com", "*.cdn.example.net"],
+                EgressMode::Enforce,
+            ),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        egress.check_name("web", "api.example.com"),
+        EgressDecision::Allow
+    );
+    assert_eq!(
+        egress.check_name("web", "img.cdn.example.net"),
+        EgressDecision::Allow
+    );
+    assert_eq!(egress.check_name("web", "db"), EgressDecision::Allow);
+    assert_eq!(
+        egress.check_name("web", "cache.polis.local"),
+        EgressDecision::Allow
+    );
+    assert_eq!(
+        egress.check_name("web", "evil.example.org"),
+        EgressDecision::Deny
+    );
+
+    // Containers sem política não são filtrados
+    assert_eq!(
+        egress.check_name("worker", "evil.example.org"),
+        EgressDecision::Allow
+    );
+
+    let violation = violations.try_recv().unwrap();
+    assert_eq!(violation.container_id, "web");
+    assert_eq!(violation.destination, "evil.example.org");
+    assert_eq!(violation.disposition, EgressDisposition::Blocked);
+    assert!(violations.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_resolve_returns_nxdomain_for_denied_names() {
+    let dns = DnsManager::new();
+    let mut firewall = FirewallManager::new();
+    let mut egress = EgressController::new();
+    egress
+        .attach(
+            &mut firewall,
+            "web",
+            policy(&["google.com"], EgressMode::Enforce),
+        )
+        .await
+        .unwrap();
+    let now = Instant::now();
+
+    let error = egress
+        .resolve(
+            &dns,
+            &mut firewall,
+            "web",
+            "example.org",
+            DnsRecordType::A,
+            now,
+        )
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("NXDOMAIN"));
+
+    let records = egress
+        .resolve(
+            &dns,
+            &mut firewall,
+            "web",
+            "google.com",
+            DnsRecordType::A,
+            now,
+        )
+        .await
+        .unwrap();
+    let resolved = ip(&records[0].value);
+    assert_eq!(egress.check_ip("web", resolved, now), EgressDecision::Allow);
+    assert!(rule_ids(&firewall)
+        .await
+        .contains(&format!("egress-web-dns-{}", resolved)));
+}
+
+#[tokio::test]
+async fn test_dynamic_rules_expire_with_ttl() {
+    let mut firewall = FirewallManager::new();
+    let mut egress = EgressController::new();
+    egress
+        .attach(
+            &mut firewall,
+            "web",
+            policy(&["api.example.com", "10.0.0.0/8"], EgressMode::Enforce),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        rule_ids(&firewall).await,
+        vec!["egress-web-cidr-10.0.0.0/8", "egress-web-deny"]
+    );
+
+    let start = Instant::now();
+    egress
+        .allow_answers(
+            &mut firewall,
+            "web",
+            "api.example.com",
+            &[
+                a_record("api.example.com", "203.0.113.10", 60),
+                a_record("api.example.com", "203.0.113.11", 300),
+            ],
+            start,
+        )
+        .await
+        .unwrap();
+
+    // As regras dinâmicas ficam antes do bloqueio final
+    assert_eq!(
+        rule_ids(&firewall).await,
+        vec![
+            "egress-web-cidr-10.0.0.0/8",
+            "egress-web-dns-203.0.113.10",
+            "egress-web-dns-203.0.113.11",
+            "egress-web-deny",
+        ]
+    );
+    let rules = firewall.list_rules(Some(EGRESS_CHAIN)).await.unwrap();
+    assert_eq!(rules.last().unwrap().action, FirewallAction::Deny);
+    assert_eq!(rules[0].dest_prefix, Some(8));
+
+    assert_eq!(
+        egress.check_ip("web", ip("203.0.113.10"), start),
+        EgressDecision::Allow
+    );
+    assert_eq!(
+        egress.check_ip("web", ip("10.9.9.9"), start),
+        EgressDecision::Allow
+    );
+    assert_eq!(
+        egress.check_ip("web", ip("198.51.100.1"), start),
+        EgressDecision::Deny
+    );
+
+    // Após o TTL do primeiro registro, só o segundo continua liberado
+    let later = start + Duration::from_secs(61);
+    assert_eq!(
+        egress.check_ip("web", ip("203.0.113.10"), later),
+        EgressDecision::Deny
+    );
+    assert_eq!(egress.expire(&mut firewall, later).await.unwrap(), 1);
+    assert_eq!(
+        rule_ids(&firewall).await,
+        vec![
+            "egress-web-cidr-10.0.0.0/8",
+            "egress-web-dns-203.0.113.11",
+            "egress-web-deny",
+        ]
+    );
+
+    // Uma nova resposta renova o prazo
+    egress
+        .allow_answers(
+            &mut firewall,
+            "web",
+            "api.example.com",
+            &[a_record("api.example.com", "203.0.113.11", 600)],
+            later,
+        )
+        .await
+        .unwrap();
+    let renewed = later + Duration::from_secs(500);
+    assert_eq!(egress.expire(&mut firewall, renewed).await.unwrap(), 0);
+    assert_eq!(egress.dynamic_allows("web").len(), 1);
+
+    egress.detach(&mut firewall, "web").await.unwrap();
+    assert!(rule_ids(&firewall).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_log_only_mode_records_without_blocking() {
+    let dns = DnsManager::new();
+    let mut firewall = FirewallManager::new();
+    let mut egress = EgressController::new();
+    let mut violations = egress.subscribe();
+    egress
+        .attach(
+            &mut firewall,
+            "batch",
+            policy(&["api.example.com"], EgressMode::LogOnly),
+        )
+        .await
+        .unwrap();
+
+    // Nenhuma regra de bloqueio é instalada
+    assert!(rule_ids(&firewall).await.is_empty());
+
+    let records = egress
+        .resolve(
+            &dns,
+            &mut firewall,
+            "batch",
+            "google.com",
+            DnsRecordType::A,
+            Instant::now(),
+        )
+        .await
+        .unwrap();
+    assert!(!records.is_empty());
+    assert_eq!(
+        egress.check_ip("batch", ip("198.51.100.1"), Instant::now()),
+        EgressDecision::LogOnly
+    );
+
+    let first = violations.try_recv().unwrap();
+    assert_eq!(first.destination, "google.com");
+    assert_eq!(first.disposition, EgressDisposition::Logged);
+    let second = violations.try_recv().unwrap();
+    assert_eq!(second.destination, "198.51.100.1");
+    assert_eq!(second.disposition, EgressDisposition::Logged);
+
+    // Respostas para nomes fora da lista não geram liberações dinâmicas
+    assert!(egress.dynamic_allows("batch").is_empty());
+}
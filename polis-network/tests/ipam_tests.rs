@@ -0,0 +1,133 @@
+use polis_network::{
+    BridgeManager, IpamManager, Ipv6Network, KernelOp, NetlinkBackend, DEFAULT_IPV6_POOL_SIZE,
+};
+use std::collections::HashSet;
+use std::net::Ipv6Addr;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct RecordingNetlink(Mutex<Vec<KernelOp>>);
+
+impl NetlinkBackend for RecordingNetlink {
+    fn apply(&self, op: KernelOp) -> polis_core::Result<()> {
+        self.0.lock().unwrap().push(op);
+        Ok(())
+    }
+}
+
+fn addr(value: &str) -> Ipv6Addr {
+    value.parse().unwrap()
+}
+
+#[tokio::test]
+async fn test_ipv6_pool_allocates_until_exhausted_and_reuses_releases() {
+    let mut ipam = IpamManager::new().with_ipv6_pool_size(256);
+    let prefix: Ipv6Network = "fd00:17::/64".parse().unwrap();
+    ipam.create_ipv6_pool("v6", prefix, addr("fd00:17::1"))
+        .await
+        .unwrap();
+
+    // ::0 and the gateway are never handed out
+    let mut allocated = HashSet::new();
+    for _ in 0..254 {
+        let ip = ipam.allocate_ipv6("v6").await.unwrap();
+        assert!(prefix.contains(ip));
+        assert!(allocated.insert(ip), "{ip} allocated twice");
+    }
+    assert!(!allocated.contains(&addr("fd00:17::")));
+    assert!(!allocated.contains(&addr("fd00:17::1")));
+    assert!(allocated.contains(&addr("fd00:17::ff")));
+    assert!(ipam.allocate_ipv6("v6").await.is_err());
+
+    ipam.release_ipv6("v6", addr("fd00:17::42")).await.unwrap();
+    assert_eq!(ipam.allocate_ipv6("v6").await.unwrap(), addr("fd00:17::42"));
+    assert!(ipam.allocate_ipv6("v6").await.is_err());
+
+    let pool = ipam.get_ipv6_pool("v6").await.unwrap();
+    assert_eq!(pool.size(), 256);
+    assert_eq!(pool.available(), 0);
+}
+
+#[tokio::test]
+async fn test_ipv6_release_rejects_foreign_and_reserved_addresses() {
+    let mut ipam = IpamManager::new();
+    let prefix: Ipv6Network = "fd00:1:2:3::/64".parse().unwrap();
+    ipam.create_ipv6_pool("v6", prefix, addr("fd00:1:2:3::1"))
+        .await
+        .unwrap();
+    assert_eq!(
+        ipam.get_ipv6_pool("v6").await.unwrap().size(),
+        DEFAULT_IPV6_POOL_SIZE
+    );
+
+    let ip = ipam.allocate_ipv6("v6").await.unwrap();
+    assert_eq!(ip, addr("fd00:1:2:3::2"));
+    for foreign in [
+        "fd00:1:2:3::1",
+        "fd00:1:2:3::",
+        "fd00:1:2:3::9",
+        "fd00:9::2",
+    ] {
+        assert!(
+            ipam.release_ipv6("v6", addr(foreign)).await.is_err(),
+            "{foreign}"
+        );
+    }
+    ipam.release_ipv6("v6", ip).await.unwrap();
+    assert!(ipam.release_ipv6("v6", ip).await.is_err());
+
+    assert!(ipam.allocate_ipv6("missing").await.is_err());
+    assert!(ipam
+        .create_ipv6_pool("bad", prefix, addr("fd00:9::1"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_small_prefix_limits_the_pool() {
+    let mut ipam = IpamManager::new();
+    // The host bits are masked off: the pool is fd00::abcc-fd00::abcf
+    let prefix = Ipv6Network::new(addr("fd00::abcd"), 126).unwrap();
+    assert_eq!(prefix.to_string(), "fd00::abcc/126");
+    ipam.create_ipv6_pool("tiny", prefix, addr("fd00::abcd"))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        ipam.allocate_ipv6("tiny").await.unwrap(),
+        addr("fd00::abce")
+    );
+    assert_eq!(
+        ipam.allocate_ipv6("tiny").await.unwrap(),
+        addr("fd00::abcf")
+    );
+    assert!(ipam.allocate_ipv6("tiny").await.is_err());
+}
+
+#[tokio::test]
+async fn test_dual_stack_bridge_keeps_its_ipv6_subnet() {
+    let netlink = Arc::new(RecordingNetlink::default());
+    let mut bridges = BridgeManager::new().with_netlink(netlink.clone());
+    let prefix: Ipv6Network = "fd00:17::/64".parse().unwrap();
+    bridges
+        .create_bridge("polis0", "172.17.0.1", "172.17.0.0/16", 1500, Some(prefix))
+        .await
+        .unwrap();
+
+    let bridge = bridges.get_bridge("polis0").await.unwrap().unwrap();
+    assert_eq!(bridge.ipv6_subnet, Some(prefix));
+    let addresses: Vec<String> = netlink
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|op| match op {
+            KernelOp::AddAddress { address, .. } => Some(address.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(addresses, vec!["172.17.0.1/16", "fd00:17::1/64"]);
+
+    assert!("fd00::/129".parse::<Ipv6Network>().is_err());
+    assert!("fd00::".parse::<Ipv6Network>().is_err());
+}
@@ -0,0 +1,362 @@
+use polis_network::firewall::Protocol;
+use polis_network::{
+    EgressRule, FirewallAction, FirewallBackend, FirewallManager, FirewallRule, IngressRule,
+    IpBlock, LabelSelector, NetworkPolicy, PolicyPeer, PolicyPod, PolicyPort, NETWORK_POLICY_CHAIN,
+};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct RecordingFirewall(Mutex<Vec<String>>);
+
+impl FirewallBackend for RecordingFirewall {
+    fn add_rule(&self, chain: &str, rule: &FirewallRule) -> polis_core::Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("add {} {}", chain, rule.id));
+        Ok(())
+    }
+
+    fn remove_rule(&self, chain: &str, rule_id: &str) -> polis_core::Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("remove {} {}", chain, rule_id));
+        Ok(())
+    }
+
+    fn flush_chain(&self, chain: &str) -> polis_core::Result<()> {
+        self.0.lock().unwrap().push(format!("flush {}", chain));
+        Ok(())
+    }
+}
+
+fn pod(namespace: &str, name: &str, ip: &str, labels: &[(&str, &str)]) -> PolicyPod {
+    PolicyPod {
+        name: name.to_string(),
+        namespace: namespace.to_string(),
+        ip: ip.parse().unwrap(),
+        labels: labels
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    }
+}
+
+async fn firewall_with_pods(backend: Arc<RecordingFirewall>) -> FirewallManager {
+    let mut firewall = FirewallManager::new().with_backend(backend);
+    for pod in [
+        pod("team-a", "web-a", "10.0.0.2", &[("app", "web")]),
+        pod("team-a", "api", "10.0.0.3", &[("app", "api")]),
+        pod("team-b", "web-b", "10.0.1.2", &[("app", "web")]),
+        pod("frontend", "ui", "10.0.2.2", &[("app", "ui")]),
+    ] {
+        firewall.register_pod(pod).await.unwrap();
+    }
+    firewall
+}
+
+/// Regra como (id, ação, origem, destino, porta)
+fn summary(rule: &FirewallRule) -> (String, FirewallAction, String, String, Option<u16>) {
+    let cidr = |ip: Option<IpAddr>, prefix: Option<u8>| match (ip, prefix) {
+        (Some(ip), Some(prefix)) => format!("{}/{}", ip, prefix),
+        _ => "*".to_string(),
+    };
+    (
+        rule.id.clone(),
+        rule.action.clone(),
+        cidr(rule.source_ip, rule.source_prefix),
+        cidr(rule.dest_ip, rule.dest_prefix),
+        rule.dest_port,
+    )
+}
+
+fn expected(
+    rules: &[(&str, FirewallAction, &str, &str, Option<u16>)],
+) -> Vec<(String, FirewallAction, String, String, Option<u16>)> {
+    rules
+        .iter()
+        .map(|(id, action, source, dest, port)| {
+            (
+                id.to_string(),
+                action.clone(),
+                source.to_string(),
+                dest.to_string(),
+                *port,
+            )
+        })
+        .collect()
+}
+
+fn same_namespace() -> NetworkPolicy {
+    NetworkPolicy::new("same-ns", "team-a", LabelSelector::new()).with_ingress(IngressRule {
+        from: vec![PolicyPeer::Pods(LabelSelector::new())],
+        ports: Vec::new(),
+    })
+}
+
+#[tokio::test]
+async fn test_allow_from_same_namespace_is_idempotent() {
+    let backend = Arc::new(RecordingFirewall::default());
+    let mut firewall = firewall_with_pods(backend.clone()).await;
+
+    let rules = firewall
+        .apply_network_policy(same_namespace())
+        .await
+        .unwrap();
+    let allow = FirewallAction::Allow;
+    let deny = FirewallAction::Deny;
+    assert_eq!(
+        rules.iter().map(summary).collect::<Vec<_>>(),
+        expected(&[
+            (
+                "netpol-same-ns-in-0",
+                allow.clone(),
+                "10.0.0.3/32",
+                "10.0.0.3/32",
+                None
+            ),
+            (
+                "netpol-same-ns-in-1",
+                allow.clone(),
+                "10.0.0.2/32",
+                "10.0.0.3/32",
+                None
+            ),
+            (
+                "netpol-same-ns-in-2",
+                allow.clone(),
+                "10.0.0.3/32",
+                "10.0.0.2/32",
+                None
+            ),
+            (
+                "netpol-same-ns-in-3",
+                allow,
+                "10.0.0.2/32",
+                "10.0.0.2/32",
+                None
+            ),
+            (
+                "netpol-isolate-in-10.0.0.3",
+                deny.clone(),
+                "*",
+                "10.0.0.3/32",
+                None
+            ),
+            ("netpol-isolate-in-10.0.0.2", deny, "*", "10.0.0.2/32", None),
+        ])
+    );
+    assert!(rules.iter().all(|rule| rule.protocol == Protocol::All));
+    let installed = firewall
+        .list_rules(Some(NETWORK_POLICY_CHAIN))
+        .await
+        .unwrap();
+    assert_eq!(installed.len(), 6);
+
+    // Reapplying the same policy changes nothing, in memory or in the kernel
+    let calls = backend.0.lock().unwrap().len();
+    let again = firewall
+        .apply_network_policy(same_namespace())
+        .await
+        .unwrap();
+    assert_eq!(again, rules);
+    assert_eq!(
+        firewall
+            .list_rules(Some(NETWORK_POLICY_CHAIN))
+            .await
+            .unwrap(),
+        installed
+    );
+    assert_eq!(backend.0.lock().unwrap().len(), calls);
+
+    firewall.remove_network_policy("same-ns").await.unwrap();
+    assert!(firewall
+        .list_rules(Some(NETWORK_POLICY_CHAIN))
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(firewall.get_network_policy("same-ns").is_none());
+    assert!(firewall.remove_network_policy("same-ns").await.is_err());
+}
+
+#[tokio::test]
+async fn test_namespace_selector_ip_block_and_ports() {
+    let backend = Arc::new(RecordingFirewall::default());
+    let mut firewall = firewall_with_pods(backend).await;
+    firewall
+        .set_namespace_labels(
+            "frontend",
+            HashMap::from([("tier".to_string(), "frontend".to_string())]),
+        )
+        .await
+        .unwrap();
+
+    let policy = NetworkPolicy::new(
+        "api",
+        "team-a",
+        LabelSelector::new().with_label("app", "api"),
+    )
+    .with_ingress(IngressRule {
+        from: vec![
+            PolicyPeer::Namespaces(LabelSelector::new().with_label("tier", "frontend")),
+            PolicyPeer::IpBlock(IpBlock::parse("192.168.0.0/24", &["192.168.0.64/26"]).unwrap()),
+        ],
+        ports: vec![PolicyPort::tcp(8080)],
+    })
+    .with_egress(EgressRule {
+        to: vec![PolicyPeer::PodsInNamespaces {
+            namespace_selector: LabelSelector::new(),
+            pod_selector: LabelSelector::new().with_label("app", "web"),
+        }],
+        ports: vec![PolicyPort::udp(53)],
+    });
+    let rules = firewall.apply_network_policy(policy).await.unwrap();
+
+    let allow = FirewallAction::Allow;
+    let deny = FirewallAction::Deny;
+    assert_eq!(
+        rules.iter().map(summary).collect::<Vec<_>>(),
+        expected(&[
+            (
+                "netpol-api-in-0",
+                allow.clone(),
+                "10.0.2.2/32",
+                "10.0.0.3/32",
+                Some(8080)
+            ),
+            (
+                "netpol-api-in-1",
+                allow.clone(),
+                "192.168.0.0/26",
+                "10.0.0.3/32",
+                Some(8080)
+            ),
+            (
+                "netpol-api-in-2",
+                allow.clone(),
+                "192.168.0.128/25",
+                "10.0.0.3/32",
+                Some(8080)
+            ),
+            (
+                "netpol-api-out-3",
+                allow.clone(),
+                "10.0.0.3/32",
+                "10.0.0.2/32",
+                Some(53)
+            ),
+            (
+                "netpol-api-out-4",
+                allow,
+                "10.0.0.3/32",
+                "10.0.1.2/32",
+                Some(53)
+            ),
+            (
+                "netpol-isolate-in-10.0.0.3",
+                deny.clone(),
+                "*",
+                "10.0.0.3/32",
+                None
+            ),
+            (
+                "netpol-isolate-out-10.0.0.3",
+                deny,
+                "10.0.0.3/32",
+                "*",
+                None
+            ),
+        ])
+    );
+    assert_eq!(rules[0].protocol, Protocol::Tcp);
+    assert_eq!(rules[3].protocol, Protocol::Udp);
+
+    // A pod joining a selected namespace is picked up
+    firewall
+        .register_pod(pod("frontend", "ui-2", "10.0.2.3", &[]))
+        .await
+        .unwrap();
+    let sources: Vec<Option<IpAddr>> = firewall
+        .list_rules(Some(NETWORK_POLICY_CHAIN))
+        .await
+        .unwrap()
+        .iter()
+        .filter(|rule| rule.dest_port == Some(8080))
+        .map(|rule| rule.source_ip)
+        .collect();
+    assert!(sources.contains(&Some("10.0.2.3".parse().unwrap())));
+}
+
+#[tokio::test]
+async fn test_policies_selecting_the_same_pod_add_up() {
+    let backend = Arc::new(RecordingFirewall::default());
+    let mut firewall = firewall_with_pods(backend).await;
+
+    // Deny-all for the api pod, then a policy opening one port
+    let deny_all = NetworkPolicy::new(
+        "deny-all",
+        "team-a",
+        LabelSelector::new().with_label("app", "api"),
+    );
+    firewall.apply_network_policy(deny_all).await.unwrap();
+    let open = NetworkPolicy::new(
+        "open-http",
+        "team-a",
+        LabelSelector::new().with_label("app", "api"),
+    )
+    .with_ingress(IngressRule {
+        from: Vec::new(),
+        ports: vec![PolicyPort::tcp(80)],
+    });
+    firewall.apply_network_policy(open).await.unwrap();
+
+    let ids: Vec<String> = firewall
+        .list_rules(Some(NETWORK_POLICY_CHAIN))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|rule| rule.id)
+        .collect();
+    // Allows come before the single shared isolation rule
+    assert_eq!(
+        ids,
+        vec!["netpol-open-http-in-0", "netpol-isolate-in-10.0.0.3"]
+    );
+
+    firewall.remove_network_policy("deny-all").await.unwrap();
+    assert_eq!(
+        firewall
+            .list_rules(Some(NETWORK_POLICY_CHAIN))
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn test_ip_block_ranges() {
+    let ranges = |block: IpBlock| -> Vec<String> {
+        block.ranges().iter().map(|cidr| cidr.to_string()).collect()
+    };
+    assert_eq!(
+        ranges(IpBlock::parse("10.0.0.0/14", &["10.1.0.0/16"]).unwrap()),
+        vec!["10.0.0.0/16", "10.2.0.0/15"]
+    );
+    assert_eq!(
+        ranges(IpBlock::parse("10.0.0.0/8", &["10.1.0.0/16", "10.0.0.0/9"]).unwrap()),
+        vec!["10.128.0.0/9"]
+    );
+    assert_eq!(
+        ranges(IpBlock::parse("fd00::/64", &["fd00::/65"]).unwrap()),
+        vec!["fd00::8000:0:0:0/65"]
+    );
+
+    let host_bits = IpBlock::parse("172.16.5.9/12", &[]).unwrap();
+    assert_eq!(host_bits.cidr.to_string(), "172.16.0.0/12");
+    assert!(IpBlock::parse("10.0.0.0/16", &["10.1.0.0/24"]).is_err());
+    assert!(IpBlock::parse("10.0.0.0/16", &["10.0.0.0/8"]).is_err());
+}
@@ -0,0 +1,80 @@
+use polis_network::port_forwarding::Protocol;
+use polis_network::{PortForwardingBackend, PortForwardingManager, PortForwardingRule};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct RecordingForwarder(Mutex<Vec<String>>);
+
+impl RecordingForwarder {
+    fn ops(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl PortForwardingBackend for RecordingForwarder {
+    fn add_forward(&self, rule: &PortForwardingRule) -> polis_core::Result<()> {
+        self.0.lock().unwrap().push(format!(
+            "add {}:{} -> {}:{}",
+            rule.host_ip, rule.host_port, rule.container_ip, rule.container_port
+        ));
+        Ok(())
+    }
+
+    fn remove_forward(&self, rule: &PortForwardingRule) -> polis_core::Result<()> {
+        self.0.lock().unwrap().push(format!(
+            "remove {}:{} -> {}:{}",
+            rule.host_ip, rule.host_port, rule.container_ip, rule.container_port
+        ));
+        Ok(())
+    }
+}
+
+fn ip(ip: &str) -> IpAddr {
+    ip.parse().unwrap()
+}
+
+#[tokio::test]
+async fn test_rules_are_installed_through_backend() {
+    let backend = Arc::new(RecordingForwarder::default());
+    let mut manager = PortForwardingManager::new().with_backend(backend.clone());
+
+    let web = manager
+        .create_container_forwarding(ip("172.17.0.2"), 80, Some(8080), Protocol::Tcp)
+        .await
+        .unwrap();
+    manager
+        .create_container_forwarding(ip("172.17.0.2"), 53, Some(5353), Protocol::Udp)
+        .await
+        .unwrap();
+    manager
+        .create_container_forwarding(ip("172.17.0.3"), 443, None, Protocol::Tcp)
+        .await
+        .unwrap();
+
+    // A conflicting rule never reaches the kernel
+    assert!(manager
+        .create_container_forwarding(ip("172.17.0.3"), 8000, Some(8080), Protocol::Tcp)
+        .await
+        .is_err());
+
+    // Disabled rules are taken out of the kernel and not removed twice
+    manager.disable_rule(&web).await.unwrap();
+    manager.remove_rule(&web).await.unwrap();
+    manager
+        .clear_container_rules(ip("172.17.0.2"))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        backend.ops(),
+        [
+            "add 0.0.0.0:8080 -> 172.17.0.2:80",
+            "add 0.0.0.0:5353 -> 172.17.0.2:53",
+            "add 0.0.0.0:443 -> 172.17.0.3:443",
+            "remove 0.0.0.0:8080 -> 172.17.0.2:80",
+            "remove 0.0.0.0:5353 -> 172.17.0.2:53",
+        ]
+    );
+    assert_eq!(manager.list_rules().await.unwrap().len(), 1);
+}
@@ -0,0 +1,341 @@
+use polis_network::{KernelOp, NetlinkBackend, VxlanDevice, VxlanManager, OVERLAY_MTU, VXLAN_PORT};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct RecordingNetlink(Mutex<Vec<KernelOp>>);
+
+impl RecordingNetlink {
+    fn take(&self) -> Vec<KernelOp> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl NetlinkBackend for RecordingNetlink {
+    fn apply(&self, op: KernelOp) -> polis_core::Result<()> {
+        self.0.lock().unwrap().push(op);
+        Ok(())
+    }
+}
+
+fn ip(value: &str) -> IpAddr {
+    value.parse().unwrap()
+}
+
+fn up(name: &str) -> KernelOp {
+    KernelOp::SetLinkUp {
+        name: name.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_create_overlay_programs_device_and_fdb() {
+    let netlink = Arc::new(RecordingNetlink::default());
+    let mut vxlan = VxlanManager::new().with_netlink(netlink.clone());
+    vxlan
+        .create_overlay(
+            "ov-web",
+            42,
+            ip("192.168.1.10"),
+            vec![
+                ip("192.168.1.11"),
+                ip("192.168.1.10"),
+                ip("192.168.1.12"),
+                ip("192.168.1.11"),
+            ],
+        )
+        .await
+        .unwrap();
+
+    // The local VTEP and duplicates get no FDB entry
+    assert_eq!(
+        netlink.take(),
+        vec![
+            KernelOp::CreateBridge {
+                name: "ov-web".to_string(),
+                mtu: OVERLAY_MTU,
+            },
+            KernelOp::CreateVxlan(VxlanDevice {
+                name: "vxlan42".to_string(),
+                vni: 42,
+                local: ip("192.168.1.10"),
+                port: VXLAN_PORT,
+                mtu: OVERLAY_MTU,
+            }),
+            KernelOp::AttachInterface {
+                bridge: "ov-web".to_string(),
+                interface: "vxlan42".to_string(),
+            },
+            KernelOp::AddFdbEntry {
+                link: "vxlan42".to_string(),
+                destination: ip("192.168.1.11"),
+            },
+            KernelOp::AddFdbEntry {
+                link: "vxlan42".to_string(),
+                destination: ip("192.168.1.12"),
+            },
+            up("vxlan42"),
+            up("ov-web"),
+        ]
+    );
+
+    vxlan
+        .add_remote_vtep("ov-web", ip("192.168.1.13"))
+        .await
+        .unwrap();
+    vxlan
+        .add_remote_vtep("ov-web", ip("192.168.1.12"))
+        .await
+        .unwrap();
+    assert_eq!(
+        netlink.take(),
+        vec![KernelOp::AddFdbEntry {
+            link: "vxlan42".to_string(),
+            destination: ip("192.168.1.13"),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_overlay_validation() {
+    let netlink = Arc::new(RecordingNetlink::default());
+    let mut vxlan = VxlanManager::new().with_netlink(netlink.clone());
+    let local = ip("10.0.0.1");
+
+    assert!(vxlan.create_overlay("ov", 0, local, vec![]).await.is_err());
+    assert!(vxlan
+        .create_overlay("ov", 1 << 24, local, vec![])
+        .await
+        .is_err());
+    assert!(vxlan
+        .create_overlay("a-very-long-overlay", 7, local, vec![])
+        .await
+        .is_err());
+    assert!(vxlan
+        .create_overlay("ov", 7, local, vec![ip("fd00::2")])
+        .await
+        .is_err());
+    assert!(netlink.take().is_empty());
+
+    vxlan.create_overlay("ov", 7, local, vec![]).await.unwrap();
+    assert!(vxlan.create_overlay("ov", 8, local, vec![]).await.is_err());
+    assert!(vxlan.create_overlay("ov2", 7, local, vec![]).await.is_err());
+    assert!(vxlan
+        .attach_container("missing", Path::new("/run/netns/c1"), ip("10.1.0.2"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_attach_container_moves_veth_into_namespace() {
+    let netlink = Arc::new(RecordingNetlink::default());
+    let mut vxlan = VxlanManager::new().with_netlink(netlink.clone());
+    vxlan
+        .create_overlay("ov-web", 42, ip("192.168.1.10"), vec![ip("192.168.1.11")])
+        .await
+        .unwrap();
+    netlink.take();
+
+    let netns = PathBuf::from("/run/netns/web-1");
+    vxlan
+        .attach_container("ov-web", &netns, ip("10.42.0.2"))
+        .await
+        .unwrap();
+    let in_netns = |op: KernelOp| KernelOp::InNetns {
+        netns: netns.clone(),
+        op: Box::new(op),
+    };
+    assert_eq!(
+        netlink.take(),
+        vec![
+            KernelOp::CreateVethPair {
+                name: "vxh0".to_string(),
+                peer: "vxc0".to_string(),
+            },
+            KernelOp::AttachInterface {
+                bridge: "ov-web".to_string(),
+                interface: "vxh0".to_string(),
+            },
+            up("vxh0"),
+            KernelOp::MoveToNetns {
+                link: "vxc0".to_string(),
+                netns: netns.clone(),
+            },
+            in_netns(KernelOp::AddAddress {
+                link: "vxc0".to_string(),
+                address: "10.42.0.2/24".to_string(),
+            }),
+            in_netns(up("vxc0")),
+        ]
+    );
+
+    // The same address cannot be attached twice
+    assert!(vxlan
+        .attach_container("ov-web", Path::new("/run/netns/web-2"), ip("10.42.0.2"))
+        .await
+        .is_err());
+    vxlan
+        .attach_container("ov-web", Path::new("/run/netns/web-2"), ip("fd42::3"))
+        .await
+        .unwrap();
+    let ops = netlink.take();
+    assert!(ops.contains(&KernelOp::InNetns {
+        netns: PathBuf::from("/run/netns/web-2"),
+        op: Box::new(KernelOp::AddAddress {
+            link: "vxc1".to_string(),
+            address: "fd42::3/64".to_string(),
+        }),
+    }));
+
+    vxlan
+        .detach_container("ov-web", ip("10.42.0.2"))
+        .await
+        .unwrap();
+    assert_eq!(
+        netlink.take(),
+        vec![KernelOp::DeleteLink {
+            name: "vxh0".to_string()
+        }]
+    );
+    assert!(vxlan
+        .detach_container("ov-web", ip("10.42.0.2"))
+        .await
+        .is_err());
+
+    vxlan.delete_overlay("ov-web").await.unwrap();
+    assert_eq!(
+        netlink.take(),
+        vec![
+            KernelOp::DeleteLink {
+                name: "vxh1".to_string()
+            },
+            KernelOp::DeleteLink {
+                name: "vxlan42".to_string()
+            },
+            KernelOp::DeleteBridge {
+                name: "ov-web".to_string()
+            },
+        ]
+    );
+    assert!(vxlan.list_overlays().is_empty());
+}
+
+#[tokio::test]
+async fn test_list_overlays_reads_device_state() {
+    let sysfs = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(sysfs.path().join("vxlan100")).unwrap();
+    std::fs::write(sysfs.path().join("vxlan100").join("operstate"), "up\n").unwrap();
+
+    let mut vxlan = VxlanManager::new()
+        .with_netlink(Arc::new(RecordingNetlink::default()))
+        .with_sysfs_net(sysfs.path().to_path_buf());
+    vxlan
+        .create_overlay("ov-b", 200, ip("10.0.0.1"), vec![])
+        .await
+        .unwrap();
+    vxlan
+        .create_overlay("ov-a", 100, ip("10.0.0.1"), vec![ip("10.0.0.2")])
+        .await
+        .unwrap();
+    vxlan
+        .attach_container("ov-a", Path::new("/run/netns/c1"), ip("10.100.0.5"))
+        .await
+        .unwrap();
+
+    let overlays = vxlan.list_overlays();
+    let summary: Vec<(&str, u32, &str, Option<&str>)> = overlays
+        .iter()
+        .map(|o| {
+            (
+                o.name.as_str(),
+                o.vni,
+                o.device.as_str(),
+                o.operstate.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("ov-a", 100, "vxlan100", Some("up")),
+            ("ov-b", 200, "vxlan200", None),
+        ]
+    );
+    assert_eq!(overlays[0].remote_vteps, vec![ip("10.0.0.2")]);
+    assert_eq!(overlays[0].endpoints[0].ip, ip("10.100.0.5"));
+    assert_eq!(overlays[0].endpoints[0].host_veth, "vxh0");
+}
+
+#[cfg(feature = "integration")]
+fn run(args: &[&str]) -> String {
+    let output = std::process::Command::new(args[0])
+        .args(&args[1..])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}: {:?}", args, output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Builds an overlay through the default rtnetlink backend inside a
+/// private network namespace (requires root) and reads the result back
+/// with iproute2.
+#[cfg(feature = "integration")]
+#[tokio::test]
+async fn test_default_backend_programs_the_kernel() {
+    assert_eq!(unsafe { libc::unshare(libc::CLONE_NEWNET) }, 0);
+
+    // The container namespace lives as long as this thread is parked
+    let (tid_tx, tid_rx) = std::sync::mpsc::channel();
+    let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+    let container = std::thread::spawn(move || {
+        assert_eq!(unsafe { libc::unshare(libc::CLONE_NEWNET) }, 0);
+        tid_tx.send(unsafe { libc::gettid() }).unwrap();
+        let _ = done_rx.recv();
+    });
+    let netns = PathBuf::from(format!(
+        "/proc/{}/task/{}/ns/net",
+        std::process::id(),
+        tid_rx.recv().unwrap()
+    ));
+
+    let mut vxlan = VxlanManager::new();
+    vxlan
+        .create_overlay(
+            "ov-it",
+            42,
+            ip("10.0.0.1"),
+            vec![ip("10.0.0.2"), ip("10.0.0.3")],
+        )
+        .await
+        .unwrap();
+    vxlan
+        .attach_container("ov-it", &netns, ip("10.100.0.5"))
+        .await
+        .unwrap();
+
+    let device = run(&["ip", "-d", "link", "show", "vxlan42"]);
+    assert!(device.contains("mtu 1450"), "{}", device);
+    assert!(device.contains("master ov-it"), "{}", device);
+    assert!(device.contains("vxlan id 42"), "{}", device);
+    let fdb = run(&["bridge", "fdb", "show", "dev", "vxlan42"]);
+    assert!(fdb.contains("dst 10.0.0.2"), "{}", fdb);
+    assert!(fdb.contains("dst 10.0.0.3"), "{}", fdb);
+    let host_veth = run(&["ip", "link", "show", "vxh0"]);
+    assert!(host_veth.contains("master ov-it"), "{}", host_veth);
+
+    let net = format!("--net={}", netns.display());
+    let peer = run(&["nsenter", &net, "ip", "addr", "show", "vxc0"]);
+    assert!(peer.contains("inet 10.100.0.5/24"), "{}", peer);
+
+    vxlan.delete_overlay("ov-it").await.unwrap();
+    let links = run(&["ip", "link", "show"]);
+    assert!(
+        !links.contains("ov-it") && !links.contains("vxlan42"),
+        "{}",
+        links
+    );
+
+    done_tx.send(()).unwrap();
+    container.join().unwrap();
+}
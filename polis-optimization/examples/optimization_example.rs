@@ -75,6 +75,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         network_mode: polis_core::types::NetworkMode::Bridge,
         ports: vec![],
         volumes: vec![],
+        rootfs: Default::default(),
+        max_runtime: None,
+        deadline: None,
+        stop_reason: None,
+        egress: None,
     };
 
     cache_manager
@@ -1,11 +1,19 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use dashmap::DashMap;
+use futures::future::{BoxFuture, FutureExt, Shared};
 use lru::LruCache;
-use std::collections::HashMap;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::future::Future;
 use std::hash::Hash;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use tracing::warn;
 
 /// Generic cache trait
 pub trait Cache<K, V> {
@@ -160,19 +168,411 @@ impl<K: Hash + Eq + Clone, V: Clone> Cache<K, V> for TtlCache<K, V> {
     }
 }
 
+/// Eviction strategy used by a [`PolicyCache`] once it is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheEvictionPolicy {
+    /// Evict the least recently used entry
+    #[default]
+    Lru,
+    /// Evict the least frequently used entry; ties go to the least recently used
+    Lfu,
+    /// Adaptive Replacement Cache: balances recency and frequency using
+    /// ghost lists of recently evicted keys
+    ArcCache,
+}
+
+impl CacheEvictionPolicy {
+    pub fn build(self, capacity: usize) -> Box<dyn EvictionPolicy> {
+        match self {
+            CacheEvictionPolicy::Lru => Box::new(LruPolicy::default()),
+            CacheEvictionPolicy::Lfu => Box::new(LfuPolicy::default()),
+            CacheEvictionPolicy::ArcCache => Box::new(ArcPolicy::new(capacity)),
+        }
+    }
+}
+
+/// Bookkeeping that decides which key leaves a full cache
+pub trait EvictionPolicy: Send + Sync {
+    /// A cached key was read
+    fn on_access(&mut self, key: &str);
+    /// A key was stored, either new or overwritten
+    fn on_insert(&mut self, key: &str);
+    /// A key was removed explicitly
+    fn on_remove(&mut self, key: &str);
+    /// The key returned by `select_victim` was evicted
+    fn on_evict(&mut self, key: &str) {
+        self.on_remove(key);
+    }
+    /// Next key to evict, if any
+    fn select_victim(&self) -> Option<&str>;
+    fn clear(&mut self);
+}
+
+/// Keys ordered from least to most recently touched
+#[derive(Debug, Default)]
+struct RecencyList {
+    ticks: HashMap<String, u64>,
+    order: BTreeMap<u64, String>,
+    next_tick: u64,
+}
+
+impl RecencyList {
+    fn touch(&mut self, key: &str) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        if let Some(previous) = self.ticks.insert(key.to_string(), tick) {
+            self.order.remove(&previous);
+        }
+        self.order.insert(tick, key.to_string());
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        match self.ticks.remove(key) {
+            Some(tick) => {
+                self.order.remove(&tick);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.ticks.contains_key(key)
+    }
+
+    fn oldest(&self) -> Option<&str> {
+        self.order.values().next().map(String::as_str)
+    }
+
+    fn pop_oldest(&mut self) {
+        if let Some((_, key)) = self.order.pop_first() {
+            self.ticks.remove(&key);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.ticks.clear();
+        self.order.clear();
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LruPolicy {
+    entries: RecencyList,
+}
+
+impl EvictionPolicy for LruPolicy {
+    fn on_access(&mut self, key: &str) {
+        self.entries.touch(key);
+    }
+
+    fn on_insert(&mut self, key: &str) {
+        self.entries.touch(key);
+    }
+
+    fn on_remove(&mut self, key: &str) {
+        self.entries.remove(key);
+    }
+
+    fn select_victim(&self) -> Option<&str> {
+        self.entries.oldest()
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LfuPolicy {
+    /// Access count and last access tick of each key
+    counts: HashMap<String, (u64, u64)>,
+    order: BTreeSet<(u64, u64, String)>,
+    next_tick: u64,
+}
+
+impl LfuPolicy {
+    /// Number of accesses recorded for `key`, counting the insert
+    pub fn frequency(&self, key: &str) -> u64 {
+        self.counts.get(key).map(|(count, _)| *count).unwrap_or(0)
+    }
+
+    fn bump(&mut self, key: &str) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        let count = match self.counts.get(key) {
+            Some(&(count, last)) => {
+                self.order.remove(&(count, last, key.to_string()));
+                count + 1
+            }
+            None => 1,
+        };
+        self.counts.insert(key.to_string(), (count, tick));
+        self.order.insert((count, tick, key.to_string()));
+    }
+}
+
+impl EvictionPolicy for LfuPolicy {
+    fn on_access(&mut self, key: &str) {
+        if self.counts.contains_key(key) {
+            self.bump(key);
+        }
+    }
+
+    fn on_insert(&mut self, key: &str) {
+        self.bump(key);
+    }
+
+    fn on_remove(&mut self, key: &str) {
+        if let Some((count, last)) = self.counts.remove(key) {
+            self.order.remove(&(count, last, key.to_string()));
+        }
+    }
+
+    fn select_victim(&self) -> Option<&str> {
+        self.order.first().map(|(_, _, key)| key.as_str())
+    }
+
+    fn clear(&mut self) {
+        self.counts.clear();
+        self.order.clear();
+    }
+}
+
+/// ARC as described by Megiddo and Modha: `t1` holds keys seen once, `t2`
+/// keys seen again, and the ghost lists `b1`/`b2` remember keys evicted from
+/// each. A hit on a ghost shifts the target size `p` of `t1` towards the
+/// list that would have kept it.
+#[derive(Debug)]
+pub struct ArcPolicy {
+    capacity: usize,
+    target: usize,
+    t1: RecencyList,
+    t2: RecencyList,
+    b1: RecencyList,
+    b2: RecencyList,
+}
+
+impl ArcPolicy {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            target: 0,
+            t1: RecencyList::default(),
+            t2: RecencyList::default(),
+            b1: RecencyList::default(),
+            b2: RecencyList::default(),
+        }
+    }
+
+    /// Current target size of the recency list
+    pub fn target(&self) -> usize {
+        self.target
+    }
+}
+
+impl EvictionPolicy for ArcPolicy {
+    fn on_access(&mut self, key: &str) {
+        if self.t1.remove(key) || self.t2.contains(key) {
+            self.t2.touch(key);
+        }
+    }
+
+    fn on_insert(&mut self, key: &str) {
+        if self.t1.contains(key) || self.t2.contains(key) {
+            self.on_access(key);
+        } else if self.b1.remove(key) {
+            let delta = (self.b2.len() / (self.b1.len() + 1)).max(1);
+            self.target = (self.target + delta).min(self.capacity);
+            self.t2.touch(key);
+        } else if self.b2.remove(key) {
+            let delta = (self.b1.len() / (self.b2.len() + 1)).max(1);
+            self.target = self.target.saturating_sub(delta);
+            self.t2.touch(key);
+        } else {
+            self.t1.touch(key);
+            if self.t1.len() + self.b1.len() > self.capacity {
+                self.b1.pop_oldest();
+            }
+            while self.t1.len() + self.t2.len() + self.b1.len() + self.b2.len() > 2 * self.capacity
+                && !self.b2.is_empty()
+            {
+                self.b2.pop_oldest();
+            }
+        }
+    }
+
+    fn on_remove(&mut self, key: &str) {
+        self.t1.remove(key);
+        self.t2.remove(key);
+        self.b1.remove(key);
+        self.b2.remove(key);
+    }
+
+    fn on_evict(&mut self, key: &str) {
+        if self.t1.remove(key) {
+            self.b1.touch(key);
+        } else if self.t2.remove(key) {
+            self.b2.touch(key);
+        }
+    }
+
+    fn select_victim(&self) -> Option<&str> {
+        if !self.t1.is_empty() && (self.t1.len() > self.target || self.t2.is_empty()) {
+            self.t1.oldest()
+        } else {
+            self.t2.oldest()
+        }
+    }
+
+    fn clear(&mut self) {
+        self.target = 0;
+        self.t1.clear();
+        self.t2.clear();
+        self.b1.clear();
+        self.b2.clear();
+    }
+}
+
+/// Bounded cache that delegates the choice of victim to an [`EvictionPolicy`]
+pub struct PolicyCache<K, V> {
+    /// Entries indexed by the key's string form, which the policy tracks
+    entries: HashMap<String, (K, V)>,
+    capacity: usize,
+    kind: CacheEvictionPolicy,
+    // Reads also update the policy, so it sits behind its own lock
+    policy: Mutex<Box<dyn EvictionPolicy>>,
+}
+
+impl<K: Hash + Eq + Clone + AsRef<str>, V: Clone> PolicyCache<K, V> {
+    pub fn new(capacity: usize, policy: CacheEvictionPolicy) -> Self {
+        Self {
+            entries: HashMap::with_capacity(capacity),
+            capacity,
+            kind: policy,
+            policy: Mutex::new(policy.build(capacity)),
+        }
+    }
+
+    pub fn policy(&self) -> CacheEvictionPolicy {
+        self.kind
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key.as_ref())
+    }
+
+    /// Inserts the entry and returns the one evicted to make room for it
+    pub fn push(&mut self, key: K, value: V) -> Option<(K, V)> {
+        self.insert_entry(key, value).1
+    }
+
+    fn insert_entry(&mut self, key: K, value: V) -> (Option<V>, Option<(K, V)>) {
+        if self.capacity == 0 {
+            return (None, Some((key, value)));
+        }
+        let policy = self
+            .policy
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        let name = key.as_ref().to_string();
+        let mut evicted = None;
+        if !self.entries.contains_key(&name) && self.entries.len() >= self.capacity {
+            if let Some(victim) = policy.select_victim().map(str::to_string) {
+                policy.on_evict(&victim);
+                evicted = self.entries.remove(&victim);
+            }
+        }
+        policy.on_insert(&name);
+        let old = self.entries.insert(name, (key, value)).map(|(_, old)| old);
+        (old, evicted)
+    }
+
+    fn policy_mut(&self) -> MutexGuard<'_, Box<dyn EvictionPolicy>> {
+        self.policy.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<K: Hash + Eq + Clone + AsRef<str>, V: Clone> Cache<K, V> for PolicyCache<K, V> {
+    fn get(&self, key: &K) -> Option<V> {
+        let value = self
+            .entries
+            .get(key.as_ref())
+            .map(|(_, value)| value.clone());
+        if value.is_some() {
+            self.policy_mut().on_access(key.as_ref());
+        }
+        value
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_entry(key, value).0
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let value = self.entries.remove(key.as_ref()).map(|(_, value)| value);
+        if value.is_some() {
+            self.policy
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .on_remove(key.as_ref());
+        }
+        value
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.policy
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
 /// Multi-level cache
 pub struct MultiLevelCache<K, V> {
-    l1_cache: Arc<RwLock<LruCacheWrapper<K, V>>>,
+    l1_cache: Arc<RwLock<PolicyCache<K, V>>>,
     l2_cache: Arc<RwLock<TtlCache<K, V>>>,
     l3_cache: Arc<DashMap<K, V>>,
+    l2_hits: AtomicUsize,
+    l2_misses: AtomicUsize,
 }
 
-impl<K: Hash + Eq + Clone + Send + Sync, V: Clone + Send + Sync> MultiLevelCache<K, V> {
+impl<K: Hash + Eq + Clone + AsRef<str> + Send + Sync, V: Clone + Send + Sync>
+    MultiLevelCache<K, V>
+{
     pub fn new(l1_capacity: usize, l2_ttl: Duration) -> Self {
+        Self::with_policy(l1_capacity, l2_ttl, CacheEvictionPolicy::Lru)
+    }
+
+    /// Cache whose first level evicts according to `policy`
+    pub fn with_policy(l1_capacity: usize, l2_ttl: Duration, policy: CacheEvictionPolicy) -> Self {
         Self {
-            l1_cache: Arc::new(RwLock::new(LruCacheWrapper::new(l1_capacity))),
+            l1_cache: Arc::new(RwLock::new(PolicyCache::new(l1_capacity, policy))),
             l2_cache: Arc::new(RwLock::new(TtlCache::new(l2_ttl))),
             l3_cache: Arc::new(DashMap::new()),
+            l2_hits: AtomicUsize::new(0),
+            l2_misses: AtomicUsize::new(0),
         }
     }
 
@@ -184,12 +584,14 @@ impl<K: Hash + Eq + Clone + Send + Sync, V: Clone + Send + Sync> MultiLevelCache
 
         // Try L2 cache
         if let Some(value) = self.l2_cache.read().await.get(key) {
+            self.l2_hits.fetch_add(1, Ordering::Relaxed);
             // Promote to L1
             if let Ok(mut l1) = self.l1_cache.try_write() {
                 l1.insert(key.clone(), value.clone());
             }
             return Some(value);
         }
+        self.l2_misses.fetch_add(1, Ordering::Relaxed);
 
         // Try L3 cache
         if let Some(value) = self.l3_cache.get(key) {
@@ -269,36 +671,308 @@ impl<K: Hash + Eq + Clone + Send + Sync, V: Clone + Send + Sync> MultiLevelCache
             l2_entries: l2_len,
             l3_entries: l3_len,
             total_entries: l1_len + l2_len + l3_len,
+            l2_hits: self.l2_hits.load(Ordering::Relaxed),
+            l2_misses: self.l2_misses.load(Ordering::Relaxed),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CacheStats {
     pub l1_entries: usize,
     pub l2_entries: usize,
     pub l3_entries: usize,
     pub total_entries: usize,
+    /// Lookups that missed L1 and were served by L2
+    pub l2_hits: usize,
+    /// Lookups that missed both L1 and L2
+    pub l2_misses: usize,
+}
+
+/// On-disk key-value store with one JSON file per key. Keys are escaped
+/// into file names, so they are limited by the file system's name length.
+pub struct DiskStore {
+    dir: PathBuf,
+}
+
+impl DiskStore {
+    pub fn open(dir: PathBuf) -> Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub async fn get<V: DeserializeOwned>(&self, key: &str) -> Result<Option<V>> {
+        match tokio::fs::read(self.path(key)).await {
+            Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the value atomically: readers see the old file or the new one
+    pub async fn put<V: Serialize>(&self, key: &str, value: &V) -> Result<()> {
+        let path = self.path(key);
+        let temp = path.with_extension("tmp");
+        tokio::fs::write(&temp, serde_json::to_vec(value)?).await?;
+        tokio::fs::rename(&temp, &path).await?;
+        Ok(())
+    }
+
+    /// Removes the key, returning whether it was stored
+    pub async fn remove(&self, key: &str) -> Result<bool> {
+        match tokio::fs::remove_file(self.path(key)).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn clear(&self) -> Result<()> {
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            tokio::fs::remove_file(entry.path()).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn len(&self) -> Result<usize> {
+        let mut count = 0;
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            // Escaped keys never contain '.', unlike leftover temporary files
+            if !entry.file_name().to_string_lossy().contains('.') {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    pub async fn is_empty(&self) -> Result<bool> {
+        Ok(self.len().await? == 0)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let mut name = String::with_capacity(key.len());
+        for byte in key.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+                name.push(byte as char);
+            } else {
+                name.push_str(&format!("%{:02x}", byte));
+            }
+        }
+        self.dir.join(name)
+    }
 }
 
+/// Cache with a bounded in-memory first level and an on-disk second level.
+/// Entries evicted from L1 are written to L2 instead of being dropped, and
+/// an L2 hit moves the entry back to L1, so each key lives in one level.
+pub struct TwoLevelCache<V> {
+    l1_cache: RwLock<PolicyCache<String, V>>,
+    l2_store: DiskStore,
+    l2_hits: AtomicUsize,
+    l2_misses: AtomicUsize,
+}
+
+impl<V: Clone + Serialize + DeserializeOwned + Send + Sync> TwoLevelCache<V> {
+    pub fn open(l1_capacity: usize, l2_dir: PathBuf, policy: CacheEvictionPolicy) -> Result<Self> {
+        Ok(Self {
+            l1_cache: RwLock::new(PolicyCache::new(l1_capacity, policy)),
+            l2_store: DiskStore::open(l2_dir)?,
+            l2_hits: AtomicUsize::new(0),
+            l2_misses: AtomicUsize::new(0),
+        })
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<V>> {
+        let key = key.to_string();
+        if let Some(value) = self.l1_cache.read().await.get(&key) {
+            return Ok(Some(value));
+        }
+
+        // Holding the write lock keeps a concurrent insert from being
+        // overwritten by the promoted value
+        let mut l1 = self.l1_cache.write().await;
+        if let Some(value) = l1.get(&key) {
+            return Ok(Some(value));
+        }
+        let Some(value) = self.l2_store.get::<V>(&key).await? else {
+            self.l2_misses.fetch_add(1, Ordering::Relaxed);
+            return Ok(None);
+        };
+        self.l2_hits.fetch_add(1, Ordering::Relaxed);
+
+        self.l2_store.remove(&key).await?;
+        if let Some((evicted_key, evicted)) = l1.push(key, value.clone()) {
+            self.l2_store.put(&evicted_key, &evicted).await?;
+        }
+        Ok(Some(value))
+    }
+
+    pub async fn insert(&self, key: String, value: V) -> Result<()> {
+        let mut l1 = self.l1_cache.write().await;
+        // A copy demoted earlier would be stale once the key is in L1
+        self.l2_store.remove(&key).await?;
+        if let Some((evicted_key, evicted)) = l1.push(key, value) {
+            self.l2_store.put(&evicted_key, &evicted).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn remove(&self, key: &str) -> Result<Option<V>> {
+        let key = key.to_string();
+        let mut l1 = self.l1_cache.write().await;
+        let value = match l1.remove(&key) {
+            Some(value) => Some(value),
+            None => self.l2_store.get(&key).await?,
+        };
+        self.l2_store.remove(&key).await?;
+        Ok(value)
+    }
+
+    pub async fn clear(&self) -> Result<()> {
+        let mut l1 = self.l1_cache.write().await;
+        l1.clear();
+        self.l2_store.clear().await
+    }
+
+    pub async fn stats(&self) -> Result<CacheStats> {
+        let l1_len = self.l1_cache.read().await.len();
+        let l2_len = self.l2_store.len().await?;
+
+        Ok(CacheStats {
+            l1_entries: l1_len,
+            l2_entries: l2_len,
+            l3_entries: 0,
+            total_entries: l1_len + l2_len,
+            l2_hits: self.l2_hits.load(Ordering::Relaxed),
+            l2_misses: self.l2_misses.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// Levels behind one of the manager's typed caches
+enum TypedCache<V> {
+    Memory(MultiLevelCache<String, V>),
+    TwoLevel(TwoLevelCache<V>),
+}
+
+impl<V: Clone + Serialize + DeserializeOwned + Send + Sync> TypedCache<V> {
+    fn memory(l1_capacity: usize, l2_ttl: Duration, policy: CacheEvictionPolicy) -> Self {
+        Self::Memory(MultiLevelCache::with_policy(l1_capacity, l2_ttl, policy))
+    }
+
+    fn two_level(l1_capacity: usize, l2_dir: PathBuf, policy: CacheEvictionPolicy) -> Result<Self> {
+        Ok(Self::TwoLevel(TwoLevelCache::open(
+            l1_capacity,
+            l2_dir,
+            policy,
+        )?))
+    }
+
+    async fn get(&self, key: &str) -> Option<V> {
+        match self {
+            Self::Memory(cache) => cache.get(&key.to_string()).await,
+            Self::TwoLevel(cache) => cache.get(key).await.unwrap_or_else(|e| {
+                warn!("Failed to read '{}' from the on-disk cache: {}", key, e);
+                None
+            }),
+        }
+    }
+
+    async fn insert(&self, key: String, value: V) {
+        match self {
+            Self::Memory(cache) => {
+                cache.insert(key, value).await;
+            }
+            Self::TwoLevel(cache) => {
+                if let Err(e) = cache.insert(key.clone(), value).await {
+                    warn!("Failed to cache '{}' on disk: {}", key, e);
+                }
+            }
+        }
+    }
+
+    async fn cleanup(&self) {
+        // Only the in-memory second level expires entries
+        if let Self::Memory(cache) = self {
+            cache.cleanup().await;
+        }
+    }
+
+    async fn stats(&self) -> CacheStats {
+        match self {
+            Self::Memory(cache) => cache.stats().await,
+            Self::TwoLevel(cache) => cache.stats().await.unwrap_or_else(|e| {
+                warn!("Failed to read on-disk cache stats: {}", e);
+                CacheStats::default()
+            }),
+        }
+    }
+}
+
+type ComputedValue = Arc<dyn Any + Send + Sync>;
+
+/// Computation shared by every caller waiting on the same missing key
+type SharedComputation = Shared<BoxFuture<'static, Result<ComputedValue, Arc<anyhow::Error>>>>;
+
 /// Cache manager for different types of data
 pub struct CacheManager {
-    container_cache: MultiLevelCache<String, polis_core::types::Container>,
-    image_cache: MultiLevelCache<String, polis_core::types::Image>,
-    config_cache: MultiLevelCache<String, polis_core::PolisConfig>,
-    stats_cache: MultiLevelCache<String, serde_json::Value>,
+    container_cache: TypedCache<polis_core::types::Container>,
+    image_cache: TypedCache<polis_core::types::Image>,
+    config_cache: TypedCache<polis_core::PolisConfig>,
+    stats_cache: TypedCache<serde_json::Value>,
+    // Computed values are type-erased and cannot be written to disk
+    computed_cache: MultiLevelCache<String, ComputedValue>,
+    in_flight: tokio::sync::Mutex<HashMap<String, SharedComputation>>,
+    policy: CacheEvictionPolicy,
 }
 
 impl CacheManager {
     pub fn new() -> Self {
+        Self::new_with_policy(CacheEvictionPolicy::default())
+    }
+
+    /// Cache manager whose in-memory levels evict according to `policy`
+    pub fn new_with_policy(policy: CacheEvictionPolicy) -> Self {
         Self {
-            container_cache: MultiLevelCache::new(1000, Duration::from_secs(300)), // 5 minutes
-            image_cache: MultiLevelCache::new(500, Duration::from_secs(600)),      // 10 minutes
-            config_cache: MultiLevelCache::new(100, Duration::from_secs(3600)),    // 1 hour
-            stats_cache: MultiLevelCache::new(200, Duration::from_secs(60)),       // 1 minute
+            container_cache: TypedCache::memory(1000, Duration::from_secs(300), policy), // 5 minutes
+            image_cache: TypedCache::memory(500, Duration::from_secs(600), policy), // 10 minutes
+            config_cache: TypedCache::memory(100, Duration::from_secs(3600), policy), // 1 hour
+            stats_cache: TypedCache::memory(200, Duration::from_secs(60), policy),  // 1 minute
+            computed_cache: MultiLevelCache::with_policy(500, Duration::from_secs(300), policy), // 5 minutes
+            in_flight: tokio::sync::Mutex::new(HashMap::new()),
+            policy,
         }
     }
 
+    /// Cache manager that keeps up to `l1_capacity` entries of each kind in
+    /// memory and moves the ones evicted from there to `l2_dir` on disk
+    pub fn new_two_level(l1_capacity: usize, l2_dir: PathBuf) -> Result<Self> {
+        let policy = CacheEvictionPolicy::default();
+        Ok(Self {
+            container_cache: TypedCache::two_level(l1_capacity, l2_dir.join("containers"), policy)?,
+            image_cache: TypedCache::two_level(l1_capacity, l2_dir.join("images"), policy)?,
+            config_cache: TypedCache::two_level(l1_capacity, l2_dir.join("configs"), policy)?,
+            stats_cache: TypedCache::two_level(l1_capacity, l2_dir.join("stats"), policy)?,
+            computed_cache: MultiLevelCache::with_policy(
+                l1_capacity,
+                Duration::from_secs(300),
+                policy,
+            ),
+            in_flight: tokio::sync::Mutex::new(HashMap::new()),
+            policy,
+        })
+    }
+
+    pub fn eviction_policy(&self) -> CacheEvictionPolicy {
+        self.policy
+    }
+
     pub async fn get_container(&self, id: &str) -> Option<polis_core::types::Container> {
         self.container_cache.get(id).await
     }
@@ -331,11 +1005,74 @@ impl CacheManager {
         self.stats_cache.insert(key, stats).await;
     }
 
+    /// Returns the cached value for `key` or computes it. Concurrent callers
+    /// missing the same key share a single run of `compute` and all receive
+    /// its result; a failed computation is not cached, so the next call
+    /// retries it.
+    pub async fn get_or_compute<F, Fut, V>(&self, key: &str, compute: F) -> Result<V>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V>> + Send + 'static,
+        V: Clone + Send + Sync + 'static,
+    {
+        if let Some(value) = self.computed_cache.get(&key.to_string()).await {
+            return downcast_computed(key, &value);
+        }
+
+        let computation = {
+            let mut in_flight = self.in_flight.lock().await;
+            // The computation may have finished while we waited for the lock
+            if let Some(value) = self.computed_cache.get(&key.to_string()).await {
+                return downcast_computed(key, &value);
+            }
+            match in_flight.get(key) {
+                Some(computation) => computation.clone(),
+                None => {
+                    let future = compute();
+                    let computation = async move {
+                        match future.await {
+                            Ok(value) => Ok(Arc::new(value) as ComputedValue),
+                            Err(e) => Err(Arc::new(e)),
+                        }
+                    }
+                    .boxed()
+                    .shared();
+                    in_flight.insert(key.to_string(), computation.clone());
+                    computation
+                }
+            }
+        };
+
+        let result = computation.clone().await;
+
+        // The first waiter to finish stores the value and retires the computation
+        {
+            let mut in_flight = self.in_flight.lock().await;
+            if in_flight
+                .get(key)
+                .is_some_and(|current| current.ptr_eq(&computation))
+            {
+                if let Ok(value) = &result {
+                    self.computed_cache
+                        .insert(key.to_string(), value.clone())
+                        .await;
+                }
+                in_flight.remove(key);
+            }
+        }
+
+        match result {
+            Ok(value) => downcast_computed(key, &value),
+            Err(e) => Err(anyhow!("{:#}", e)),
+        }
+    }
+
     pub async fn cleanup_all(&self) {
         self.container_cache.cleanup().await;
         self.image_cache.cleanup().await;
         self.config_cache.cleanup().await;
         self.stats_cache.cleanup().await;
+        self.computed_cache.cleanup().await;
     }
 
     pub async fn get_all_stats(&self) -> HashMap<String, CacheStats> {
@@ -344,10 +1081,18 @@ impl CacheManager {
         stats.insert("images".to_string(), self.image_cache.stats().await);
         stats.insert("configs".to_string(), self.config_cache.stats().await);
         stats.insert("stats".to_string(), self.stats_cache.stats().await);
+        stats.insert("computed".to_string(), self.computed_cache.stats().await);
         stats
     }
 }
 
+fn downcast_computed<V: Clone + 'static>(key: &str, value: &ComputedValue) -> Result<V> {
+    value
+        .downcast_ref::<V>()
+        .cloned()
+        .ok_or_else(|| anyhow!("Cached value for '{}' has a different type", key))
+}
+
 /// Cache warming strategies
 pub struct CacheWarmer {
     cache_manager: Arc<CacheManager>,
@@ -379,6 +1124,11 @@ impl CacheWarmer {
                 network_mode: polis_core::types::NetworkMode::Bridge,
                 ports: vec![],
                 volumes: vec![],
+                rootfs: Default::default(),
+                max_runtime: None,
+                deadline: None,
+                stop_reason: None,
+                egress: None,
             };
             self.cache_manager.set_container(id, container).await;
         }
@@ -477,6 +1227,11 @@ mod tests {
             network_mode: polis_core::types::NetworkMode::Bridge,
             ports: vec![],
             volumes: vec![],
+            rootfs: Default::default(),
+            max_runtime: None,
+            deadline: None,
+            stop_reason: None,
+            egress: None,
         };
 
         manager
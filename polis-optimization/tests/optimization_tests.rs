@@ -1,9 +1,12 @@
 use polis_optimization::{
-    CacheManager, CompressionManager, CpuProfiler, LruCacheWrapper, MemoryOptimizer,
-    MemoryProfiler, MultiLevelCache, OptimizationAction, OptimizationCondition,
-    OptimizationManager, OptimizationRule, PerformanceOptimizer, Profiler, TtlCache,
+    Cache, CacheEvictionPolicy, CacheManager, CompressionManager, CpuProfiler, LruCacheWrapper,
+    MemoryOptimizer, MemoryProfiler, MultiLevelCache, OptimizationAction, OptimizationCondition,
+    OptimizationManager, OptimizationRule, PerformanceOptimizer, PolicyCache, Profiler, TtlCache,
+    TwoLevelCache,
 };
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 #[tokio::test]
@@ -64,6 +67,11 @@ async fn test_cache_manager() {
         network_mode: polis_core::types::NetworkMode::Bridge,
         ports: vec![],
         volumes: vec![],
+        rootfs: Default::default(),
+        max_runtime: None,
+        deadline: None,
+        stop_reason: None,
+        egress: None,
     };
 
     manager
@@ -222,6 +230,244 @@ async fn test_multi_level_cache() {
     assert!(stats.total_entries >= 0);
 }
 
+/// Fills a three-entry cache and reads `hot` three times, `warm` twice and
+/// `cold` once before a fourth key arrives
+fn filled_cache(policy: CacheEvictionPolicy) -> PolicyCache<String, u32> {
+    let mut cache = PolicyCache::new(3, policy);
+    cache.insert("cold".to_string(), 1);
+    cache.insert("warm".to_string(), 2);
+    cache.insert("hot".to_string(), 3);
+    for _ in 0..3 {
+        cache.get(&"hot".to_string());
+    }
+    for _ in 0..2 {
+        cache.get(&"warm".to_string());
+    }
+    cache.get(&"cold".to_string());
+    cache
+}
+
+#[tokio::test]
+async fn test_lfu_evicts_least_frequently_used() {
+    let mut cache = filled_cache(CacheEvictionPolicy::Lfu);
+
+    // `cold` was read last but least often
+    cache.insert("new".to_string(), 4);
+    assert_eq!(cache.len(), 3);
+    assert!(!cache.contains(&"cold".to_string()));
+    assert!(cache.contains(&"warm".to_string()));
+    assert!(cache.contains(&"hot".to_string()));
+
+    // `new` has a single access, below `warm` and `hot`
+    cache.insert("newer".to_string(), 5);
+    assert!(!cache.contains(&"new".to_string()));
+    assert!(cache.contains(&"warm".to_string()));
+
+    // Between equally frequent keys the least recently used goes first
+    let mut cache = PolicyCache::new(2, CacheEvictionPolicy::Lfu);
+    cache.insert("a".to_string(), 1);
+    cache.insert("b".to_string(), 2);
+    cache.get(&"a".to_string());
+    cache.get(&"b".to_string());
+    cache.insert("c".to_string(), 3);
+    assert!(!cache.contains(&"a".to_string()));
+}
+
+#[tokio::test]
+async fn test_lru_evicts_least_recently_used() {
+    let mut cache = filled_cache(CacheEvictionPolicy::Lru);
+
+    // `hot` is the most frequently read but `warm` was read after it
+    cache.insert("new".to_string(), 4);
+    assert!(!cache.contains(&"hot".to_string()));
+    assert!(cache.contains(&"warm".to_string()));
+    assert!(cache.contains(&"cold".to_string()));
+
+    // Removed keys are never chosen as victims
+    assert_eq!(cache.remove(&"warm".to_string()), Some(2));
+    cache.insert("newer".to_string(), 5);
+    assert_eq!(cache.len(), 3);
+    assert!(cache.contains(&"cold".to_string()));
+}
+
+#[tokio::test]
+async fn test_arc_keeps_reused_entries_through_a_scan() {
+    let mut cache = PolicyCache::new(3, CacheEvictionPolicy::ArcCache);
+    cache.insert("hot".to_string(), 0);
+    cache.get(&"hot".to_string());
+
+    // A scan of keys read only once does not push out the reused one
+    for i in 0..10 {
+        cache.insert(format!("scan-{}", i), i);
+    }
+    assert!(cache.contains(&"hot".to_string()));
+    assert_eq!(cache.len(), 3);
+
+    // A key requested again shortly after its eviction comes back as
+    // frequent and outlives the scan that follows
+    assert!(!cache.contains(&"scan-7".to_string()));
+    cache.insert("scan-7".to_string(), 7);
+    for i in 10..14 {
+        cache.insert(format!("scan-{}", i), i);
+    }
+    assert!(cache.contains(&"scan-7".to_string()));
+    assert!(!cache.contains(&"scan-10".to_string()));
+}
+
+#[tokio::test]
+async fn test_cache_manager_with_policy() {
+    let manager = CacheManager::new_with_policy(CacheEvictionPolicy::Lfu);
+    assert_eq!(manager.eviction_policy(), CacheEvictionPolicy::Lfu);
+    assert_eq!(
+        CacheManager::new().eviction_policy(),
+        CacheEvictionPolicy::Lru
+    );
+
+    let cache = MultiLevelCache::with_policy(2, Duration::from_secs(60), CacheEvictionPolicy::Lfu);
+    cache.insert("a", 1).await;
+    cache.insert("b", 2).await;
+    cache.get(&"a").await;
+    cache.insert("c", 3).await;
+    assert_eq!(cache.stats().await.l1_entries, 2);
+    // Evicted from the first level, still served by the lower ones
+    assert_eq!(cache.get(&"b").await, Some(2));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_get_or_compute_runs_once_per_missing_key() {
+    let manager = Arc::new(CacheManager::new());
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let mut tasks = Vec::new();
+    for _ in 0..32 {
+        let manager = manager.clone();
+        let runs = runs.clone();
+        tasks.push(tokio::spawn(async move {
+            manager
+                .get_or_compute("layer-index", || async move {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    Ok(vec!["sha256:abc".to_string()])
+                })
+                .await
+        }));
+    }
+    for task in tasks {
+        assert_eq!(task.await.unwrap().unwrap(), vec!["sha256:abc".to_string()]);
+    }
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    // Later calls are served from the cache
+    let cached: Vec<String> = manager
+        .get_or_compute("layer-index", || async {
+            Err(anyhow::anyhow!("recomputed"))
+        })
+        .await
+        .unwrap();
+    assert_eq!(cached, vec!["sha256:abc".to_string()]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_get_or_compute_retries_after_error() {
+    let manager = Arc::new(CacheManager::new());
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let mut tasks = Vec::new();
+    for _ in 0..8 {
+        let manager = manager.clone();
+        let runs = runs.clone();
+        tasks.push(tokio::spawn(async move {
+            manager
+                .get_or_compute("manifest", || async move {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Err::<u64, _>(anyhow::anyhow!("registry unavailable"))
+                })
+                .await
+        }));
+    }
+    for task in tasks {
+        let error = task.await.unwrap().unwrap_err();
+        assert!(error.to_string().contains("registry unavailable"));
+    }
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    // The failure was not cached
+    let value = manager
+        .get_or_compute("manifest", || async { Ok(42u64) })
+        .await
+        .unwrap();
+    assert_eq!(value, 42);
+}
+
+#[tokio::test]
+async fn test_two_level_cache_manager_spills_to_disk() {
+    let l2_dir = tempfile::tempdir().unwrap();
+    let manager = CacheManager::new_two_level(2, l2_dir.path().to_path_buf()).unwrap();
+
+    for i in 0..5 {
+        manager
+            .set_stats(format!("container-{}", i), serde_json::json!({ "cpu": i }))
+            .await;
+    }
+    // Only the two most recent entries fit in L1; the rest went to disk
+    let stats = manager.get_all_stats().await["stats"].clone();
+    assert_eq!((stats.l1_entries, stats.l2_entries), (2, 3));
+    assert_eq!(
+        std::fs::read_dir(l2_dir.path().join("stats"))
+            .unwrap()
+            .count(),
+        3
+    );
+
+    // Reading an evicted entry promotes it and demotes the L1 victim
+    assert_eq!(
+        manager.get_stats("container-0").await,
+        Some(serde_json::json!({ "cpu": 0 }))
+    );
+    let stats = manager.get_all_stats().await["stats"].clone();
+    assert_eq!((stats.l2_hits, stats.l2_misses), (1, 0));
+    assert_eq!((stats.l1_entries, stats.l2_entries), (2, 3));
+
+    // L1 hits leave the L2 counters alone
+    manager.get_stats("container-0").await;
+    assert_eq!(manager.get_all_stats().await["stats"].l2_hits, 1);
+
+    for i in 0..5 {
+        assert_eq!(
+            manager.get_stats(&format!("container-{}", i)).await,
+            Some(serde_json::json!({ "cpu": i }))
+        );
+    }
+    assert!(manager.get_stats("missing").await.is_none());
+    let stats = manager.get_all_stats().await["stats"].clone();
+    assert_eq!((stats.l2_hits, stats.l2_misses), (5, 1));
+    assert_eq!(stats.total_entries, 5);
+}
+
+#[tokio::test]
+async fn test_two_level_cache_overwrite_replaces_demoted_copy() {
+    let l2_dir = tempfile::tempdir().unwrap();
+    let cache =
+        TwoLevelCache::open(1, l2_dir.path().to_path_buf(), CacheEvictionPolicy::Lru).unwrap();
+
+    cache.insert("a".to_string(), 1).await.unwrap();
+    cache.insert("b".to_string(), 2).await.unwrap();
+    // `a` lives on disk now; a new value must not be shadowed by it later
+    cache.insert("a".to_string(), 10).await.unwrap();
+    cache.insert("c".to_string(), 3).await.unwrap();
+    assert_eq!(cache.get("a").await.unwrap(), Some(10));
+
+    assert_eq!(cache.remove("b").await.unwrap(), Some(2));
+    assert_eq!(cache.get("b").await.unwrap(), None);
+
+    // Keys are escaped into file names
+    cache.insert("../etc/passwd".to_string(), 4).await.unwrap();
+    cache.insert("d".to_string(), 5).await.unwrap();
+    assert!(l2_dir.path().join("%2e%2e%2fetc%2fpasswd").exists());
+    assert_eq!(cache.get("../etc/passwd").await.unwrap(), Some(4));
+}
+
 #[tokio::test]
 async fn test_optimization_manager() {
     let mut manager = OptimizationManager::new().unwrap();
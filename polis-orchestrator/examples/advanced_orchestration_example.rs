@@ -83,6 +83,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             headers: HashMap::new(),
             path: "/api/health".to_string(),
             method: "GET".to_string(),
+            body: None,
         };
 
         let response = load_balancer.handle_request(request).await?;
@@ -345,6 +346,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             headers: HashMap::new(),
             path: "/api/v1/users".to_string(),
             method: "GET".to_string(),
+            body: None,
         };
 
         let response = load_balancer.handle_request(request).await?;
@@ -1,20 +1,79 @@
-use anyhow::Result;
-use chrono::{DateTime, Utc};
+use crate::EVENT_CHANNEL_CAPACITY;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Timelike, Utc};
+use polis_core::{Clock, Container, SystemClock};
+use polis_runtime::ContainerRuntime;
+use polis_stats::ContainerStatsCollector;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
+use tracing::warn;
 
 // use polis_core::{PolisError, Result as PolisResult};
 
+/// CPU or memory utilization (%) above which scale-ups ignore the cooldown
+pub const EMERGENCY_UTILIZATION: f64 = 95.0;
+
+/// Fraction of every target below which a scale-to-zero policy treats the
+/// deployment as idle
+pub const SCALE_TO_ZERO_THRESHOLD: f64 = 0.1;
+
 /// Auto-scaling manager
 pub struct AutoScaler {
     policies: Arc<RwLock<HashMap<String, ScalingPolicy>>>,
     deployments: Arc<RwLock<HashMap<String, Deployment>>>,
     metrics_collector: Arc<MetricsCollector>,
     scaling_engine: Arc<ScalingEngine>,
-    event_sender: Arc<tokio::sync::mpsc::UnboundedSender<ScalingEvent>>,
+    event_sender: Arc<broadcast::Sender<ScalingEvent>>,
+    metrics_source: Option<Arc<dyn MetricsSource>>,
+    evaluation_interval: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+/// Where the scaling loop reads deployment metrics from
+#[async_trait]
+pub trait MetricsSource: Send + Sync {
+    async fn fetch(&self, deployment_id: &str) -> Result<ScalingMetrics>;
+}
+
+/// Domain-specific metric, such as queue depth or active sessions, that a
+/// scaling policy can target by the name the provider is registered under
+#[async_trait]
+pub trait MetricProvider: Send + Sync {
+    async fn collect(&self, deployment_id: &str) -> Result<f64>;
+}
+
+/// Selects the containers that belong to a deployment
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContainerSelector {
+    /// Containers carrying the label with this exact value
+    Label { key: String, value: String },
+    /// Containers whose name starts with the prefix
+    NamePrefix(String),
+}
+
+impl ContainerSelector {
+    pub fn matches(&self, container: &Container) -> bool {
+        match self {
+            ContainerSelector::Label { key, value } => container.labels.get(key) == Some(value),
+            ContainerSelector::NamePrefix(prefix) => container.name.starts_with(prefix.as_str()),
+        }
+    }
+}
+
+/// [`MetricsSource`] that averages the CPU and memory utilization reported
+/// by polis-stats over the containers of a deployment.
+///
+/// Deployments without an explicit selector match containers named
+/// `<deployment_id>-*`.
+pub struct ContainerStatsSource {
+    runtime: Arc<dyn ContainerRuntime + Send + Sync>,
+    stats: Arc<ContainerStatsCollector>,
+    selectors: RwLock<HashMap<String, ContainerSelector>>,
 }
 
 /// Scaling policy
@@ -30,11 +89,61 @@ pub struct ScalingPolicy {
     pub target_requests_per_second: f64,
     pub scale_up_cooldown: Duration,
     pub scale_down_cooldown: Duration,
+    #[serde(default)]
+    pub strategy: ScalingStrategy,
+    /// Scale up ahead of time when the metric trends would cross a target
+    /// within `predictive_horizon`
+    #[serde(default)]
+    pub predictive_enabled: bool,
+    /// How far ahead trends are extrapolated; they are fitted over the same
+    /// span of recent history
+    #[serde(default)]
+    pub predictive_horizon: Duration,
+    /// Time windows that replace `min_replicas` and `max_replicas` while
+    /// they are active
+    #[serde(default)]
+    pub schedules: Vec<ScalingSchedule>,
+    /// Let an idle deployment go below `min_replicas` down to zero; it comes
+    /// back through `AutoScaler::wake_deployment`
+    #[serde(default)]
+    pub scale_to_zero: bool,
+    /// Targets for custom metrics, by the name their provider is
+    /// registered under
+    #[serde(default)]
+    pub custom_metric_targets: HashMap<String, f64>,
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// How the replica count moves once a policy decides to scale
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScalingStrategy {
+    /// Double on high utilization, halve on low utilization
+    #[default]
+    Doubling,
+    /// Add `up` replicas on high utilization, remove `down` on low utilization
+    Step { up: u32, down: u32 },
+    /// Size the deployment so the busiest metric lands on its target:
+    /// `ceil(current * actual / target)`, as the Kubernetes HPA does
+    TargetTracking,
+}
+
+/// Replica bounds that apply during the minutes matched by a cron expression
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScalingSchedule {
+    /// Expression in the `cron` crate syntax, seconds first:
+    /// `0 * 9-17 * * Mon-Fri` is every minute of office hours
+    pub cron_expression: String,
+    pub min_replicas: u32,
+    pub max_replicas: u32,
+    /// Wins over lower priorities when several schedules are active
+    #[serde(default)]
+    pub priority: i32,
+    pub enabled: bool,
+}
+
 /// Deployment
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Deployment {
@@ -122,15 +231,60 @@ pub enum ScalingEvent {
     },
 }
 
+/// Metric whose trend can be computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrendField {
+    Cpu,
+    Memory,
+    RequestsPerSecond,
+}
+
+impl TrendField {
+    pub const ALL: [TrendField; 3] = [
+        TrendField::Cpu,
+        TrendField::Memory,
+        TrendField::RequestsPerSecond,
+    ];
+
+    pub fn reading(&self, metrics: &ScalingMetrics) -> f64 {
+        match self {
+            TrendField::Cpu => metrics.cpu_utilization,
+            TrendField::Memory => metrics.memory_utilization,
+            TrendField::RequestsPerSecond => metrics.requests_per_second,
+        }
+    }
+
+    fn set(&self, metrics: &mut ScalingMetrics, value: f64) {
+        match self {
+            TrendField::Cpu => metrics.cpu_utilization = value,
+            TrendField::Memory => metrics.memory_utilization = value,
+            TrendField::RequestsPerSecond => metrics.requests_per_second = value,
+        }
+    }
+}
+
+/// `, name=value` for each targeted custom metric, sorted by name
+fn describe_custom_metrics(policy: &ScalingPolicy, custom: &HashMap<String, f64>) -> String {
+    let mut names: Vec<&String> = custom
+        .keys()
+        .filter(|name| policy.custom_metric_targets.contains_key(*name))
+        .collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| format!(", {}={:.1}", name, custom[name]))
+        .collect()
+}
+
 /// Metrics collector
 pub struct MetricsCollector {
     metrics: Arc<RwLock<HashMap<String, Vec<ScalingMetrics>>>>,
+    providers: Arc<RwLock<HashMap<String, Arc<dyn MetricProvider + Send + Sync>>>>,
     collection_interval: Duration,
 }
 
 /// Scaling engine
 pub struct ScalingEngine {
-    auto_scaler: Arc<AutoScaler>,
     scaling_history: Arc<RwLock<Vec<ScalingAction>>>,
 }
 
@@ -156,29 +310,43 @@ pub enum ScalingActionType {
 
 impl AutoScaler {
     pub fn new() -> Self {
-        let (event_sender, _event_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let (event_sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
 
-        let auto_scaler = Arc::new(AutoScaler {
+        Self {
             policies: Arc::new(RwLock::new(HashMap::new())),
             deployments: Arc::new(RwLock::new(HashMap::new())),
             metrics_collector: Arc::new(MetricsCollector::new()),
             scaling_engine: Arc::new(ScalingEngine::new()),
             event_sender: Arc::new(event_sender),
-        });
+            metrics_source: None,
+            evaluation_interval: Duration::from_secs(30),
+            clock: Arc::new(SystemClock),
+        }
+    }
 
-        let scaling_engine = Arc::new(ScalingEngine::new());
-        scaling_engine.set_auto_scaler(Arc::clone(&auto_scaler));
+    /// Clock used to timestamp actions and check cooldowns
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
 
-        Self {
-            policies: auto_scaler.policies.clone(),
-            deployments: auto_scaler.deployments.clone(),
-            metrics_collector: auto_scaler.metrics_collector.clone(),
-            scaling_engine: auto_scaler.scaling_engine.clone(),
-            event_sender: auto_scaler.event_sender.clone(),
-        }
+    /// Pull metrics from `source` on every tick of the scaling loop
+    pub fn with_metrics_source(mut self, source: Arc<dyn MetricsSource>) -> Self {
+        self.metrics_source = Some(source);
+        self
+    }
+
+    /// Interval between scaling evaluations (30s by default)
+    pub fn with_evaluation_interval(mut self, interval: Duration) -> Self {
+        self.evaluation_interval = interval;
+        self
     }
 
     pub async fn create_scaling_policy(&self, policy: ScalingPolicy) -> Result<()> {
+        for schedule in &policy.schedules {
+            schedule.validate()?;
+        }
+
         let mut policies = self.policies.write().await;
         policies.insert(policy.id.clone(), policy.clone());
         drop(policies);
@@ -192,6 +360,10 @@ impl AutoScaler {
     }
 
     pub async fn update_scaling_policy(&self, policy: ScalingPolicy) -> Result<()> {
+        for schedule in &policy.schedules {
+            schedule.validate()?;
+        }
+
         let mut policies = self.policies.write().await;
         policies.insert(policy.id.clone(), policy.clone());
         drop(policies);
@@ -246,6 +418,72 @@ impl AutoScaler {
         Ok(())
     }
 
+    /// Stop scaling a deployment until it is resumed
+    pub async fn pause_deployment(&self, deployment_id: &str) -> Result<()> {
+        self.set_deployment_status(deployment_id, DeploymentStatus::Paused)
+            .await
+    }
+
+    /// Let the scaling loop manage a paused deployment again
+    pub async fn resume_deployment(&self, deployment_id: &str) -> Result<()> {
+        self.set_deployment_status(deployment_id, DeploymentStatus::Running)
+            .await
+    }
+
+    /// Bring a deployment scaled to zero back to the minimum of its policy
+    /// (at least one replica) without waiting for the next evaluation. Meant
+    /// to be called when traffic reaches a deployment with no replicas;
+    /// does nothing if it already has some.
+    pub async fn wake_deployment(&self, deployment_id: &str) -> Result<()> {
+        let deployment = self
+            .get_deployment(deployment_id)
+            .await
+            .ok_or_else(|| anyhow!("Deployment {} not found", deployment_id))?;
+        if deployment.replicas > 0 {
+            return Ok(());
+        }
+        if deployment.status == DeploymentStatus::Paused {
+            return Err(anyhow!("Deployment {} is paused", deployment_id));
+        }
+
+        let min_replicas = self
+            .get_scaling_policy_for_deployment(deployment_id)
+            .await
+            .map_or(1, |policy| policy.min_replicas.max(1));
+        let action = ScalingAction {
+            deployment_id: deployment_id.to_string(),
+            action_type: ScalingActionType::ScaleUp,
+            from_replicas: 0,
+            to_replicas: min_replicas,
+            reason: "Woken up by incoming traffic".to_string(),
+            timestamp: self.clock.now(),
+            success: true,
+        };
+        self.apply_scaling_action(&action).await?;
+        self.scaling_engine.add_scaling_action(action).await;
+        Ok(())
+    }
+
+    async fn set_deployment_status(
+        &self,
+        deployment_id: &str,
+        status: DeploymentStatus,
+    ) -> Result<()> {
+        let mut deployments = self.deployments.write().await;
+        let deployment = deployments
+            .get_mut(deployment_id)
+            .ok_or_else(|| anyhow!("Deployment {} not found", deployment_id))?;
+        deployment.status = status;
+        deployment.updated_at = self.clock.now();
+        drop(deployments);
+
+        let _ = self.event_sender.send(ScalingEvent::DeploymentUpdated {
+            deployment_id: deployment_id.to_string(),
+        });
+
+        Ok(())
+    }
+
     pub async fn get_deployment(&self, deployment_id: &str) -> Option<Deployment> {
         let deployments = self.deployments.read().await;
         deployments.get(deployment_id).cloned()
@@ -281,15 +519,32 @@ impl AutoScaler {
                 from_replicas: 0,
                 to_replicas: 0,
                 reason: "Missing policy, deployment, or metrics".to_string(),
-                timestamp: Utc::now(),
+                timestamp: self.clock.now(),
                 success: false,
             });
         }
 
-        let policy = policy.unwrap();
+        let mut policy = policy.unwrap();
         let deployment = deployment.unwrap();
         let metrics = metrics.unwrap();
 
+        if deployment.status == DeploymentStatus::Paused {
+            let reason = "deployment paused".to_string();
+            let _ = self.event_sender.send(ScalingEvent::ScalingBlocked {
+                deployment_id: deployment_id.to_string(),
+                reason: reason.clone(),
+            });
+            return Ok(ScalingAction {
+                deployment_id: deployment_id.to_string(),
+                action_type: ScalingActionType::NoAction,
+                from_replicas: deployment.replicas,
+                to_replicas: deployment.replicas,
+                reason,
+                timestamp: self.clock.now(),
+                success: true,
+            });
+        }
+
         if !policy.enabled {
             return Ok(ScalingAction {
                 deployment_id: deployment_id.to_string(),
@@ -297,42 +552,74 @@ impl AutoScaler {
                 from_replicas: deployment.replicas,
                 to_replicas: deployment.replicas,
                 reason: "Scaling policy disabled".to_string(),
-                timestamp: Utc::now(),
+                timestamp: self.clock.now(),
                 success: true,
             });
         }
 
+        let schedule = policy.active_schedule(self.clock.now()).cloned();
+        if let Some(schedule) = &schedule {
+            policy.min_replicas = schedule.min_replicas;
+            policy.max_replicas = schedule.max_replicas;
+        }
+
+        let custom = if policy.custom_metric_targets.is_empty() {
+            HashMap::new()
+        } else {
+            self.metrics_collector
+                .collect_custom_metrics(deployment_id)
+                .await
+        };
+
         let current_replicas = deployment.replicas;
-        let mut desired_replicas = current_replicas;
-        let mut reason = String::new();
+        let mut desired_replicas =
+            policy.desired_replicas_with_custom(current_replicas, &metrics, &custom);
+        let mut reason = if desired_replicas == current_replicas {
+            String::new()
+        } else {
+            format!(
+                "{} utilization: CPU={:.1}%, Memory={:.1}%, RPS={:.1}{}",
+                if desired_replicas > current_replicas {
+                    "High"
+                } else {
+                    "Low"
+                },
+                metrics.cpu_utilization,
+                metrics.memory_utilization,
+                metrics.requests_per_second,
+                describe_custom_metrics(&policy, &custom)
+            )
+        };
 
-        // Check if we need to scale up
-        if metrics.cpu_utilization > policy.target_cpu_utilization
-            || metrics.memory_utilization > policy.target_memory_utilization
-            || metrics.requests_per_second > policy.target_requests_per_second
-        {
-            if current_replicas < policy.max_replicas {
-                desired_replicas = (current_replicas * 2).min(policy.max_replicas);
-                reason = format!(
-                    "High utilization: CPU={:.1}%, Memory={:.1}%, RPS={:.1}",
-                    metrics.cpu_utilization,
-                    metrics.memory_utilization,
-                    metrics.requests_per_second
-                );
+        if desired_replicas <= current_replicas {
+            if let Some(predicted) = self
+                .predicted_metrics(deployment_id, &policy, &metrics)
+                .await
+            {
+                let predicted_replicas =
+                    policy.desired_replicas_with_custom(current_replicas, &predicted, &custom);
+                if predicted_replicas > current_replicas {
+                    desired_replicas = predicted_replicas;
+                    reason = format!(
+                        "Predicted utilization in {}s: CPU={:.1}%, Memory={:.1}%, RPS={:.1}",
+                        policy.predictive_horizon.as_secs(),
+                        predicted.cpu_utilization,
+                        predicted.memory_utilization,
+                        predicted.requests_per_second
+                    );
+                }
             }
         }
-        // Check if we need to scale down
-        else if metrics.cpu_utilization < policy.target_cpu_utilization * 0.5
-            && metrics.memory_utilization < policy.target_memory_utilization * 0.5
-            && metrics.requests_per_second < policy.target_requests_per_second * 0.5
-        {
-            if current_replicas > policy.min_replicas {
-                desired_replicas = (current_replicas / 2).max(policy.min_replicas);
+
+        // The metrics only move the count within the bounds; a schedule that
+        // starts or ends also pulls an out-of-bounds count into them
+        if let Some(schedule) = &schedule {
+            let bounded = desired_replicas.clamp(schedule.min_replicas, schedule.max_replicas);
+            if bounded != desired_replicas {
+                desired_replicas = bounded;
                 reason = format!(
-                    "Low utilization: CPU={:.1}%, Memory={:.1}%, RPS={:.1}",
-                    metrics.cpu_utilization,
-                    metrics.memory_utilization,
-                    metrics.requests_per_second
+                    "Schedule '{}' requires between {} and {} replicas",
+                    schedule.cron_expression, schedule.min_replicas, schedule.max_replicas
                 );
             }
         }
@@ -345,24 +632,117 @@ impl AutoScaler {
             ScalingActionType::NoAction
         };
 
+        if let Some((cooldown, remaining)) = self
+            .cooldown_remaining(deployment_id, &policy, &metrics, &action_type)
+            .await
+        {
+            let reason = format!(
+                "cooldown: {} blocked for another {}s of the {}s window",
+                if action_type == ScalingActionType::ScaleUp {
+                    "scale-up"
+                } else {
+                    "scale-down"
+                },
+                remaining.as_secs(),
+                cooldown.as_secs()
+            );
+            let _ = self.event_sender.send(ScalingEvent::ScalingBlocked {
+                deployment_id: deployment_id.to_string(),
+                reason: reason.clone(),
+            });
+            return Ok(ScalingAction {
+                deployment_id: deployment_id.to_string(),
+                action_type: ScalingActionType::NoAction,
+                from_replicas: current_replicas,
+                to_replicas: current_replicas,
+                reason,
+                timestamp: self.clock.now(),
+                success: true,
+            });
+        }
+
         let action = ScalingAction {
             deployment_id: deployment_id.to_string(),
             action_type,
             from_replicas: current_replicas,
             to_replicas: desired_replicas,
             reason,
-            timestamp: Utc::now(),
+            timestamp: self.clock.now(),
             success: true,
         };
 
         // Apply scaling action
         if action.action_type != ScalingActionType::NoAction {
             self.apply_scaling_action(&action).await?;
+            self.scaling_engine.add_scaling_action(action.clone()).await;
         }
 
         Ok(action)
     }
 
+    /// The cooldown window and the time left in it when the last action in
+    /// the same direction is too recent. Scale-ups and scale-downs have
+    /// separate windows; scale-ups driven by CPU or memory above
+    /// `EMERGENCY_UTILIZATION` are never held back.
+    async fn cooldown_remaining(
+        &self,
+        deployment_id: &str,
+        policy: &ScalingPolicy,
+        metrics: &ScalingMetrics,
+        action_type: &ScalingActionType,
+    ) -> Option<(Duration, Duration)> {
+        let cooldown = match action_type {
+            ScalingActionType::ScaleUp => {
+                if metrics.cpu_utilization > EMERGENCY_UTILIZATION
+                    || metrics.memory_utilization > EMERGENCY_UTILIZATION
+                {
+                    return None;
+                }
+                policy.scale_up_cooldown
+            }
+            ScalingActionType::ScaleDown => policy.scale_down_cooldown,
+            ScalingActionType::NoAction => return None,
+        };
+
+        let last = self
+            .scaling_engine
+            .last_action(deployment_id, action_type)
+            .await?;
+        let elapsed = (self.clock.now() - last.timestamp)
+            .to_std()
+            .unwrap_or_default();
+        (elapsed < cooldown).then(|| (cooldown, cooldown - elapsed))
+    }
+
+    /// Metrics extrapolated `predictive_horizon` ahead along their recent
+    /// trends, when the policy opts into predictive scaling
+    async fn predicted_metrics(
+        &self,
+        deployment_id: &str,
+        policy: &ScalingPolicy,
+        metrics: &ScalingMetrics,
+    ) -> Option<ScalingMetrics> {
+        let horizon = policy.predictive_horizon;
+        if !policy.predictive_enabled || horizon.is_zero() {
+            return None;
+        }
+
+        let mut predicted = metrics.clone();
+        let mut trending = false;
+        for field in TrendField::ALL {
+            if let Some(slope) = self
+                .metrics_collector
+                .get_trend(deployment_id, field, horizon)
+                .await
+            {
+                let value = field.reading(metrics) + slope * horizon.as_secs_f64();
+                field.set(&mut predicted, value);
+                trending = true;
+            }
+        }
+        trending.then_some(predicted)
+    }
+
     async fn get_scaling_policy_for_deployment(
         &self,
         deployment_id: &str,
@@ -379,7 +759,7 @@ impl AutoScaler {
         if let Some(deployment) = deployments.get_mut(&action.deployment_id) {
             deployment.replicas = action.to_replicas;
             deployment.desired_replicas = action.to_replicas;
-            deployment.updated_at = Utc::now();
+            deployment.updated_at = self.clock.now();
 
             if action.action_type == ScalingActionType::ScaleUp {
                 deployment.status = DeploymentStatus::Scaling;
@@ -409,16 +789,33 @@ impl AutoScaler {
         Ok(())
     }
 
+    /// Evaluate every deployment on each tick. With a metrics source, fresh
+    /// metrics are fetched first and a deployment whose fetch fails is
+    /// skipped for that tick; without one, the last metrics passed to
+    /// `collect_metrics` are used.
     pub async fn start_scaling_loop(&self) -> Result<()> {
-        let mut interval = tokio::time::interval(Duration::from_secs(30));
+        let mut interval = tokio::time::interval(self.evaluation_interval);
 
         loop {
             interval.tick().await;
 
             let deployments = self.list_deployments().await;
             for deployment in deployments {
+                if let Some(source) = &self.metrics_source {
+                    match source.fetch(&deployment.id).await {
+                        Ok(metrics) => self.collect_metrics(&deployment.id, metrics).await?,
+                        Err(e) => {
+                            warn!(
+                                "Error fetching metrics for deployment {}: {}",
+                                deployment.id, e
+                            );
+                            continue;
+                        }
+                    }
+                }
+
                 if let Err(e) = self.evaluate_scaling(&deployment.id).await {
-                    eprintln!(
+                    warn!(
                         "Error evaluating scaling for deployment {}: {}",
                         deployment.id, e
                     );
@@ -427,10 +824,20 @@ impl AutoScaler {
         }
     }
 
-    pub async fn get_scaling_events(&self) -> tokio::sync::mpsc::UnboundedReceiver<ScalingEvent> {
-        let (_sender, receiver) = tokio::sync::mpsc::unbounded_channel();
-        // This would need to be implemented to forward events
-        receiver
+    /// Make a custom metric available to the scaling policies
+    pub async fn register_metric_provider(
+        &self,
+        name: &str,
+        provider: Arc<dyn MetricProvider + Send + Sync>,
+    ) {
+        self.metrics_collector
+            .register_provider(name, provider)
+            .await;
+    }
+
+    /// Subscribe to scaling events emitted from now on; see [`EVENT_CHANNEL_CAPACITY`].
+    pub async fn get_scaling_events(&self) -> broadcast::Receiver<ScalingEvent> {
+        self.event_sender.subscribe()
     }
 
     pub async fn get_scaling_history(&self, deployment_id: &str) -> Vec<ScalingAction> {
@@ -442,10 +849,49 @@ impl MetricsCollector {
     pub fn new() -> Self {
         Self {
             metrics: Arc::new(RwLock::new(HashMap::new())),
+            providers: Arc::new(RwLock::new(HashMap::new())),
             collection_interval: Duration::from_secs(30),
         }
     }
 
+    /// Register a custom metric provider, replacing any under the same name
+    pub async fn register_provider(
+        &self,
+        name: &str,
+        provider: Arc<dyn MetricProvider + Send + Sync>,
+    ) {
+        self.providers
+            .write()
+            .await
+            .insert(name.to_string(), provider);
+    }
+
+    /// Current reading of every registered provider for a deployment.
+    /// Providers that fail are left out.
+    pub async fn collect_custom_metrics(&self, deployment_id: &str) -> HashMap<String, f64> {
+        let providers: Vec<_> = self
+            .providers
+            .read()
+            .await
+            .iter()
+            .map(|(name, provider)| (name.clone(), provider.clone()))
+            .collect();
+
+        let mut readings = HashMap::new();
+        for (name, provider) in providers {
+            match provider.collect(deployment_id).await {
+                Ok(value) => {
+                    readings.insert(name, value);
+                }
+                Err(e) => warn!(
+                    "Metric provider '{}' failed for deployment {}: {}",
+                    name, deployment_id, e
+                ),
+            }
+        }
+        readings
+    }
+
     pub async fn collect_metrics(
         &self,
         deployment_id: &str,
@@ -470,6 +916,46 @@ impl MetricsCollector {
         metrics_map.get(deployment_id)?.last().cloned()
     }
 
+    /// Slope, in units per second, of the least-squares line through the
+    /// samples of `field` taken within `window` of the latest one. `None`
+    /// without at least two samples at distinct times.
+    pub async fn get_trend(
+        &self,
+        deployment_id: &str,
+        field: TrendField,
+        window: Duration,
+    ) -> Option<f64> {
+        let metrics_map = self.metrics.read().await;
+        let metrics = metrics_map.get(deployment_id)?;
+        let latest = metrics.last()?.timestamp;
+        let cutoff = latest - chrono::Duration::from_std(window).ok()?;
+
+        let points: Vec<(f64, f64)> = metrics
+            .iter()
+            .filter(|m| m.timestamp >= cutoff)
+            .map(|m| {
+                let age = (latest - m.timestamp).num_milliseconds() as f64 / 1000.0;
+                (-age, field.reading(m))
+            })
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+
+        let count = points.len() as f64;
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / count;
+        let mean_v = points.iter().map(|(_, v)| v).sum::<f64>() / count;
+        let covariance: f64 = points
+            .iter()
+            .map(|(t, v)| (t - mean_t) * (v - mean_v))
+            .sum();
+        let variance: f64 = points.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+        if variance == 0.0 {
+            return None;
+        }
+        Some(covariance / variance)
+    }
+
     pub async fn get_metrics_history(
         &self,
         deployment_id: &str,
@@ -545,7 +1031,6 @@ impl MetricsCollector {
 impl ScalingEngine {
     pub fn new() -> Self {
         Self {
-            auto_scaler: Arc::new(AutoScaler::new()),
             scaling_history: Arc::new(RwLock::new(Vec::new())),
         }
     }
@@ -563,6 +1048,24 @@ impl ScalingEngine {
             .collect()
     }
 
+    /// Most recent applied action of the given type for the deployment
+    pub async fn last_action(
+        &self,
+        deployment_id: &str,
+        action_type: &ScalingActionType,
+    ) -> Option<ScalingAction> {
+        let history = self.scaling_history.read().await;
+        history
+            .iter()
+            .rev()
+            .find(|action| {
+                action.deployment_id == deployment_id
+                    && &action.action_type == action_type
+                    && action.success
+            })
+            .cloned()
+    }
+
     pub async fn add_scaling_action(&self, action: ScalingAction) {
         let mut history = self.scaling_history.write().await;
         history.push(action);
@@ -575,6 +1078,72 @@ impl ScalingEngine {
     }
 }
 
+impl ContainerStatsSource {
+    pub fn new(
+        runtime: Arc<dyn ContainerRuntime + Send + Sync>,
+        stats: Arc<ContainerStatsCollector>,
+    ) -> Self {
+        Self {
+            runtime,
+            stats,
+            selectors: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Select the containers of a deployment explicitly
+    pub async fn set_selector(&self, deployment_id: &str, selector: ContainerSelector) {
+        self.selectors
+            .write()
+            .await
+            .insert(deployment_id.to_string(), selector);
+    }
+
+    async fn selector_for(&self, deployment_id: &str) -> ContainerSelector {
+        self.selectors
+            .read()
+            .await
+            .get(deployment_id)
+            .cloned()
+            .unwrap_or_else(|| ContainerSelector::NamePrefix(format!("{}-", deployment_id)))
+    }
+}
+
+#[async_trait]
+impl MetricsSource for ContainerStatsSource {
+    async fn fetch(&self, deployment_id: &str) -> Result<ScalingMetrics> {
+        let selector = self.selector_for(deployment_id).await;
+        let containers = self.runtime.list_containers().await?;
+
+        let mut cpu = Vec::new();
+        let mut memory = Vec::new();
+        for container in containers.iter().filter(|c| selector.matches(c)) {
+            if let Some(metrics) = self.stats.get_metrics(&container.id.0.to_string()).await? {
+                cpu.push(metrics.cpu.usage_percent);
+                memory.push(metrics.memory.usage_percent);
+            }
+        }
+
+        // Without samples the averages would read as idle and scale down
+        if cpu.is_empty() {
+            return Err(anyhow!(
+                "No container stats available for deployment {}",
+                deployment_id
+            ));
+        }
+
+        Ok(ScalingMetrics {
+            deployment_id: deployment_id.to_string(),
+            timestamp: Utc::now(),
+            cpu_utilization: cpu.iter().sum::<f64>() / cpu.len() as f64,
+            memory_utilization: memory.iter().sum::<f64>() / memory.len() as f64,
+            requests_per_second: 0.0,
+            response_time: Duration::ZERO,
+            error_rate: 0.0,
+            active_connections: 0,
+        })
+    }
+}
+
 impl ScalingPolicy {
     pub fn new(
         id: String,
@@ -594,6 +1163,12 @@ impl ScalingPolicy {
             target_requests_per_second: 100.0,
             scale_up_cooldown: Duration::from_secs(300), // 5 minutes
             scale_down_cooldown: Duration::from_secs(600), // 10 minutes
+            strategy: ScalingStrategy::default(),
+            predictive_enabled: false,
+            predictive_horizon: Duration::ZERO,
+            schedules: Vec::new(),
+            scale_to_zero: false,
+            custom_metric_targets: HashMap::new(),
             enabled: true,
             created_at: Utc::now(),
             updated_at: Utc::now(),
@@ -624,6 +1199,171 @@ impl ScalingPolicy {
         self.scale_down_cooldown = cooldown;
         self
     }
+
+    pub fn with_strategy(mut self, strategy: ScalingStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Scale up before a target is crossed, looking `horizon` ahead
+    pub fn with_predictive_scaling(mut self, horizon: Duration) -> Self {
+        self.predictive_enabled = true;
+        self.predictive_horizon = horizon;
+        self
+    }
+
+    pub fn with_custom_metric_target(mut self, name: &str, target: f64) -> Self {
+        self.custom_metric_targets.insert(name.to_string(), target);
+        self
+    }
+
+    pub fn with_scale_to_zero(mut self) -> Self {
+        self.scale_to_zero = true;
+        self
+    }
+
+    pub fn with_schedule(mut self, schedule: ScalingSchedule) -> Self {
+        self.schedules.push(schedule);
+        self
+    }
+
+    /// The schedule whose bounds apply at `now`. Among overlapping schedules
+    /// the highest priority wins, then the one listed first.
+    pub fn active_schedule(&self, now: DateTime<Utc>) -> Option<&ScalingSchedule> {
+        self.schedules
+            .iter()
+            .rev()
+            .filter(|schedule| schedule.is_active(now))
+            .max_by_key(|schedule| schedule.priority)
+    }
+
+    /// Replica count the strategy asks for given the current count and
+    /// metrics. Scaling never moves past `min_replicas` or `max_replicas`.
+    ///
+    /// Doubling and step scale up when any metric is above its target and
+    /// down when all are below half of it. Target tracking resizes on any
+    /// deviation.
+    ///
+    /// With `scale_to_zero`, metrics below `SCALE_TO_ZERO_THRESHOLD` of every
+    /// target lower the floor to zero: doubling and step keep shrinking,
+    /// target tracking drops straight to zero. Any other load brings a
+    /// deployment at zero back to its minimum.
+    pub fn desired_replicas(&self, current: u32, metrics: &ScalingMetrics) -> u32 {
+        self.desired_replicas_with_custom(current, metrics, &HashMap::new())
+    }
+
+    /// [`desired_replicas`](Self::desired_replicas) also weighing custom
+    /// metric readings against `custom_metric_targets`, the same way as the
+    /// built-in metrics. Readings without a target are ignored.
+    pub fn desired_replicas_with_custom(
+        &self,
+        current: u32,
+        metrics: &ScalingMetrics,
+        custom: &HashMap<String, f64>,
+    ) -> u32 {
+        let readings = self.readings(metrics, custom);
+        let idle = self.scale_to_zero
+            && readings
+                .iter()
+                .all(|(actual, target)| *actual < target * SCALE_TO_ZERO_THRESHOLD);
+        if self.scale_to_zero && current == 0 && !idle {
+            return self.min_replicas.max(1).min(self.max_replicas);
+        }
+        let floor = if idle { 0 } else { self.min_replicas };
+
+        let (up, down) = match self.strategy {
+            ScalingStrategy::TargetTracking if idle => return 0,
+            ScalingStrategy::TargetTracking => {
+                let desired = readings
+                    .iter()
+                    .filter(|(_, target)| *target > 0.0)
+                    // The epsilon keeps exact ratios like 10 * 77 / 70 from rounding up
+                    .map(|(actual, target)| {
+                        (current as f64 * actual / target - 1e-9).ceil().max(0.0)
+                    })
+                    .fold(0.0, f64::max);
+                return (desired as u32).clamp(self.min_replicas, self.max_replicas);
+            }
+            ScalingStrategy::Doubling => (current.saturating_mul(2), current / 2),
+            ScalingStrategy::Step { up, down } => {
+                (current.saturating_add(up), current.saturating_sub(down))
+            }
+        };
+
+        if readings.iter().any(|(actual, target)| actual > target) {
+            if current < self.max_replicas {
+                return up.min(self.max_replicas);
+            }
+        } else if readings
+            .iter()
+            .all(|(actual, target)| *actual < target * 0.5)
+            && current > floor
+        {
+            return down.max(floor);
+        }
+        current
+    }
+
+    /// Reading and target of the built-in metrics, then of each custom
+    /// metric that has both
+    fn readings(&self, metrics: &ScalingMetrics, custom: &HashMap<String, f64>) -> Vec<(f64, f64)> {
+        let mut readings = vec![
+            (metrics.cpu_utilization, self.target_cpu_utilization),
+            (metrics.memory_utilization, self.target_memory_utilization),
+            (metrics.requests_per_second, self.target_requests_per_second),
+        ];
+        readings.extend(
+            self.custom_metric_targets
+                .iter()
+                .filter_map(|(name, target)| Some((*custom.get(name)?, *target))),
+        );
+        readings
+    }
+}
+
+impl ScalingSchedule {
+    pub fn new(cron_expression: &str, min_replicas: u32, max_replicas: u32) -> Self {
+        Self {
+            cron_expression: cron_expression.to_string(),
+            min_replicas,
+            max_replicas,
+            priority: 0,
+            enabled: true,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        cron::Schedule::from_str(&self.cron_expression)
+            .map_err(|e| anyhow!("Invalid cron expression '{}': {}", self.cron_expression, e))?;
+        if self.min_replicas > self.max_replicas {
+            return Err(anyhow!(
+                "Schedule '{}': min_replicas {} is above max_replicas {}",
+                self.cron_expression,
+                self.min_replicas,
+                self.max_replicas
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the minute containing `now` is matched by the cron
+    /// expression. Disabled or unparsable schedules are never active.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let Ok(schedule) = cron::Schedule::from_str(&self.cron_expression) else {
+            return false;
+        };
+        now.with_second(0)
+            .and_then(|minute| minute.with_nanosecond(0))
+            .is_some_and(|minute| schedule.includes(minute))
+    }
 }
 
 impl Deployment {
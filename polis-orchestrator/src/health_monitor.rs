@@ -1,19 +1,100 @@
+use crate::orchestrator::write_atomic;
+use crate::EVENT_CHANNEL_CAPACITY;
 use anyhow::Result;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use polis_core::{
+    Clock, ContainerId, EventBus, EventKind, HealthTransition, PolisError, Result as PolisResult,
+    SystemClock,
+};
+use polis_monitor::HealthCheckGauge;
+use polis_runtime::ContainerBackend;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, Mutex, RwLock};
 
-// use polis_core::{PolisError, Result as PolisResult};
+/// Number of results kept per health check unless configured otherwise
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// How long a result is kept unless configured otherwise
+pub const DEFAULT_HISTORY_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Consecutive failed checks that open a target's circuit by default
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+
+/// How long an open circuit waits before letting a probe through by default
+pub const DEFAULT_RESET_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Timeout of a single webhook delivery attempt by default
+pub const DEFAULT_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Message of the result recorded for a check skipped because of its
+/// dependencies
+pub const DEPENDENCY_UNHEALTHY: &str = "dependency unhealthy";
+
+/// Shortest time between two runs of the same failure action of a check by
+/// default
+pub const DEFAULT_ACTION_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Version of the persisted health check layout, bumped on incompatible
+/// changes
+pub const HEALTH_CHECKS_VERSION: u32 = 1;
+
+/// Delivery attempts per webhook notification
+const WEBHOOK_ATTEMPTS: u32 = 3;
+
+/// Wait before the second delivery attempt; doubled before each further one
+const WEBHOOK_RETRY_BACKOFF: Duration = Duration::from_millis(200);
 
 /// Health monitoring system
 pub struct HealthMonitor {
     checks: Arc<RwLock<HashMap<String, HealthCheck>>>,
-    results: Arc<RwLock<HashMap<String, HealthCheckResult>>>,
-    event_sender: Arc<tokio::sync::mpsc::UnboundedSender<HealthEvent>>,
+    /// Result history per check, newest first
+    results: Arc<RwLock<HashMap<String, VecDeque<HealthCheckResult>>>>,
+    history: HistoryPolicy,
+    event_sender: Arc<broadcast::Sender<HealthEvent>>,
+    /// Shared bus that also receives the status transitions
+    event_bus: EventBus,
     checker: Arc<HealthChecker>,
+    webhooks: Arc<WebhookDispatcher>,
+    /// Check id -> ids of the checks it depends on
+    dependencies: Arc<RwLock<HashMap<String, HashSet<String>>>>,
+    actions: Arc<ActionRunner>,
+    store: Option<Arc<CheckStore>>,
+}
+
+/// Bounds applied to the per-check result history
+#[derive(Clone)]
+struct HistoryPolicy {
+    limit: usize,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+/// File the check definitions, dependencies and latest results are
+/// persisted to
+struct CheckStore {
+    path: PathBuf,
+    /// Serializes writes so that the file ends up with the latest snapshot
+    lock: Mutex<()>,
+}
+
+/// Health checks as persisted
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedChecks {
+    /// Layout version; files written before versioning read as 0
+    #[serde(default)]
+    version: u32,
+    checks: Vec<HealthCheck>,
+    /// Check id -> ids of the checks it depends on
+    #[serde(default)]
+    dependencies: BTreeMap<String, Vec<String>>,
+    /// Latest result of each check
+    #[serde(default)]
+    results: Vec<HealthCheckResult>,
 }
 
 /// Health check definition
@@ -32,6 +113,19 @@ pub struct HealthCheck {
     pub updated_at: DateTime<Utc>,
     pub labels: HashMap<String, String>,
     pub annotations: HashMap<String, String>,
+    /// Consecutive failures before a healthy target is reported unhealthy
+    #[serde(default = "default_threshold")]
+    pub failure_threshold: u32,
+    /// Consecutive successes before an unhealthy target is reported healthy
+    #[serde(default = "default_threshold")]
+    pub success_threshold: u32,
+    /// Run, in order, when the failure threshold is crossed
+    #[serde(default)]
+    pub on_failure: Vec<FailureAction>,
+}
+
+fn default_threshold() -> u32 {
+    1
 }
 
 /// Target type for health checks
@@ -68,6 +162,9 @@ pub struct HealthCheckResult {
     pub consecutive_failures: u32,
     pub consecutive_successes: u32,
     pub metadata: HashMap<String, String>,
+    /// State of the check's circuit once this result was recorded
+    #[serde(default)]
+    pub circuit_state: CircuitState,
 }
 
 /// Health status
@@ -80,7 +177,7 @@ pub enum HealthStatus {
 }
 
 /// Health event
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum HealthEvent {
     CheckPassed {
         check_id: String,
@@ -112,10 +209,146 @@ pub enum HealthEvent {
     },
 }
 
+/// Variant of a `HealthEvent`, without its data
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HealthEventKind {
+    CheckPassed,
+    CheckFailed,
+    CheckDegraded,
+    CheckRecovered,
+    CheckCreated,
+    CheckDeleted,
+}
+
+/// Restricts which health events a webhook is notified of. Empty lists
+/// match everything; every label must be present on the check.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebhookFilter {
+    pub target_types: Vec<TargetType>,
+    pub events: Vec<HealthEventKind>,
+    pub labels: HashMap<String, String>,
+}
+
+/// Body POSTed to a webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub event: HealthEvent,
+    pub check_name: String,
+    pub target_type: TargetType,
+    pub labels: HashMap<String, String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// What to do about a target once its check crosses the failure threshold
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum FailureAction {
+    /// Restart the target, at most `max_restarts` times within `window`
+    RestartContainer { max_restarts: u32, window: Duration },
+    /// Send `body_template` to `url`. `{{check_id}}`, `{{check_name}}`,
+    /// `{{target_id}}`, `{{message}}` and `{{consecutive_failures}}` are
+    /// replaced with the failing result's values.
+    Webhook {
+        url: String,
+        method: String,
+        body_template: String,
+    },
+    /// Take the target out of service
+    MarkServiceUnhealthy,
+}
+
+/// How a failure action ended
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ActionOutcome {
+    Succeeded,
+    Failed(String),
+    /// Not run, to avoid acting on the target too often
+    RateLimited(String),
+}
+
+/// One run of a failure action
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActionRecord {
+    pub check_id: String,
+    pub target_id: String,
+    pub action: FailureAction,
+    pub outcome: ActionOutcome,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Acts on the targets of failing health checks on the monitor's behalf
+#[async_trait]
+pub trait TargetController: Send + Sync {
+    /// Restart the target of `check`
+    async fn restart(&self, check: &HealthCheck) -> Result<()>;
+
+    /// Take the target of `check` out of service
+    async fn mark_unhealthy(&self, check: &HealthCheck) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Marking {} unhealthy is not supported",
+            check.target_id
+        ))
+    }
+}
+
+/// Restarts container targets through the container runtime
+pub struct RuntimeTargetController {
+    backend: Arc<dyn ContainerBackend>,
+}
+
+/// Runs the failure actions of checks and keeps their history
+struct ActionRunner {
+    controller: Option<Arc<dyn TargetController>>,
+    client: reqwest::Client,
+    cooldown: Duration,
+    /// Runs per check, newest first
+    history: RwLock<HashMap<String, VecDeque<ActionRecord>>>,
+    /// Restarts per check, oldest first
+    restarts: RwLock<HashMap<String, VecDeque<DateTime<Utc>>>>,
+}
+
+/// Registered webhooks and the client that delivers to them
+struct WebhookDispatcher {
+    client: reqwest::Client,
+    timeout: Duration,
+    webhooks: RwLock<Vec<(String, WebhookFilter)>>,
+}
+
 /// Health checker
 pub struct HealthChecker {
     client: reqwest::Client,
     command_executor: Arc<CommandExecutor>,
+    breakers: RwLock<HashMap<String, CircuitBreaker>>,
+    failure_threshold: u32,
+    reset_timeout: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+/// State of a circuit breaker
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Checks run normally
+    #[default]
+    Closed,
+    /// Checks fail immediately without reaching the target
+    Open,
+    /// A single probe is allowed through to decide whether to close again
+    HalfOpen,
+}
+
+/// Stops calling a target that keeps failing, be it a health check target
+/// or a load balancer endpoint
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    state: CircuitState,
+    consecutive_failures: u32,
+    failure_threshold: u32,
+    reset_timeout: Duration,
+    opened_at: Option<DateTime<Utc>>,
+    /// Share of failures among the last `window` outcomes that opens the
+    /// circuit, as `(rate, window)`
+    error_rate: Option<(f64, usize)>,
+    /// Newest last, `true` for failures
+    outcomes: VecDeque<bool>,
 }
 
 /// Command executor
@@ -150,14 +383,157 @@ pub struct HealthSummary {
 
 impl HealthMonitor {
     pub fn new() -> Self {
-        let (event_sender, _event_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let (event_sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
 
         Self {
             checks: Arc::new(RwLock::new(HashMap::new())),
             results: Arc::new(RwLock::new(HashMap::new())),
+            history: HistoryPolicy {
+                limit: DEFAULT_HISTORY_LIMIT,
+                ttl: DEFAULT_HISTORY_TTL,
+                clock: Arc::new(SystemClock),
+            },
             event_sender: Arc::new(event_sender),
+            event_bus: EventBus::new(),
             checker: Arc::new(HealthChecker::new()),
+            webhooks: Arc::new(WebhookDispatcher::new(DEFAULT_WEBHOOK_TIMEOUT)),
+            dependencies: Arc::new(RwLock::new(HashMap::new())),
+            actions: Arc::new(ActionRunner::new(None, DEFAULT_ACTION_COOLDOWN)),
+            store: None,
+        }
+    }
+
+    /// Persist the checks, their dependencies and latest results to `path`,
+    /// as YAML when it ends in `.yaml` or `.yml` and as JSON otherwise
+    pub fn with_store(mut self, path: PathBuf) -> Self {
+        self.store = Some(Arc::new(CheckStore {
+            path,
+            lock: Mutex::new(()),
+        }));
+        self
+    }
+
+    /// Restore the checks persisted to the store and restart their loops.
+    /// Returns the number of checks restored; a missing file restores none.
+    pub async fn load(&self) -> Result<usize> {
+        let Some(store) = &self.store else {
+            return Ok(0);
+        };
+        let persisted = store.read().await?;
+
+        let ids: Vec<String> = persisted.checks.iter().map(|c| c.id.clone()).collect();
+        {
+            let mut checks = self.checks.write().await;
+            for check in persisted.checks {
+                checks.insert(check.id.clone(), check);
+            }
+        }
+        {
+            let mut dependencies = self.dependencies.write().await;
+            for (check_id, depends_on) in persisted.dependencies {
+                dependencies.entry(check_id).or_default().extend(depends_on);
+            }
+        }
+        {
+            let mut results = self.results.write().await;
+            for result in persisted.results {
+                results.insert(result.check_id.clone(), VecDeque::from([result]));
+            }
+        }
+
+        for check_id in &ids {
+            self.start_health_checking(check_id).await?;
         }
+        Ok(ids.len())
+    }
+
+    /// Write the checks to the store, if there is one
+    async fn save(&self) -> Result<()> {
+        match &self.store {
+            Some(store) => {
+                store
+                    .write(&self.checks, &self.dependencies, &self.results)
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Restart or mark unhealthy the targets of failing checks through
+    /// `controller`
+    pub fn with_target_controller(mut self, controller: Arc<dyn TargetController>) -> Self {
+        self.actions = Arc::new(ActionRunner::new(Some(controller), self.actions.cooldown));
+        self
+    }
+
+    /// Run the same failure action of a check at most once per `cooldown`
+    pub fn with_action_cooldown(mut self, cooldown: Duration) -> Self {
+        self.actions = Arc::new(ActionRunner::new(self.actions.controller.clone(), cooldown));
+        self
+    }
+
+    /// Failure actions run for a check, newest first
+    pub async fn get_action_history(&self, check_id: &str) -> Vec<ActionRecord> {
+        let history = self.actions.history.read().await;
+        history
+            .get(check_id)
+            .map(|records| records.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Give up on a webhook delivery attempt after `timeout`
+    pub fn with_webhook_timeout(mut self, timeout: Duration) -> Self {
+        self.webhooks = Arc::new(WebhookDispatcher::new(timeout));
+        self
+    }
+
+    /// POST every health event matching `filter` to `url`, replacing any
+    /// webhook already registered for that URL
+    pub async fn add_webhook(&self, url: String, filter: WebhookFilter) -> Result<()> {
+        reqwest::Url::parse(&url)
+            .map_err(|e| anyhow::anyhow!("Invalid webhook URL {}: {}", url, e))?;
+
+        let mut webhooks = self.webhooks.webhooks.write().await;
+        webhooks.retain(|(existing, _)| *existing != url);
+        webhooks.push((url, filter));
+        Ok(())
+    }
+
+    /// Stop notifying `url`
+    pub async fn disable_webhook(&self, url: &str) -> Result<()> {
+        let mut webhooks = self.webhooks.webhooks.write().await;
+        webhooks.retain(|(existing, _)| existing != url);
+        Ok(())
+    }
+
+    /// Keep at most `limit` results per check, evicting the oldest first
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.history.limit = limit.max(1);
+        self
+    }
+
+    /// Forget results older than `ttl`
+    pub fn with_history_ttl(mut self, ttl: Duration) -> Self {
+        self.history.ttl = ttl;
+        self
+    }
+
+    /// Clock used to timestamp results and expire the history
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.history.clock = clock;
+        self
+    }
+
+    /// Publish status transitions to `event_bus` as well
+    pub fn with_event_bus(mut self, event_bus: EventBus) -> Self {
+        self.event_bus = event_bus;
+        self
+    }
+
+    /// Run checks with `checker`, e.g. one with custom circuit breaker settings
+    pub fn with_checker(mut self, checker: HealthChecker) -> Self {
+        self.checker = Arc::new(checker);
+        self
     }
 
     pub async fn create_health_check(&self, check: HealthCheck) -> Result<()> {
@@ -167,12 +543,17 @@ impl HealthMonitor {
         let mut checks = self.checks.write().await;
         checks.insert(check_id.clone(), check);
         drop(checks);
+        self.save().await?;
 
         // Send event
-        let _ = self.event_sender.send(HealthEvent::CheckCreated {
+        let event = HealthEvent::CheckCreated {
             check_id: check_id.clone(),
             target_id: target_id.clone(),
-        });
+        };
+        let _ = self.event_sender.send(event.clone());
+        if let Some(check) = self.get_health_check(&check_id).await {
+            self.webhooks.notify(&event, &check).await;
+        }
 
         // Start health checking
         self.start_health_checking(&check_id).await?;
@@ -183,7 +564,8 @@ impl HealthMonitor {
     pub async fn update_health_check(&self, check: HealthCheck) -> Result<()> {
         let mut checks = self.checks.write().await;
         checks.insert(check.id.clone(), check);
-        Ok(())
+        drop(checks);
+        self.save().await
     }
 
     pub async fn delete_health_check(&self, check_id: &str) -> Result<()> {
@@ -191,17 +573,67 @@ impl HealthMonitor {
         let check = checks.remove(check_id);
         drop(checks);
 
+        self.results.write().await.remove(check_id);
+        self.checker.reset_circuit(check_id).await;
+        self.actions.forget(check_id).await;
+        {
+            let mut dependencies = self.dependencies.write().await;
+            dependencies.remove(check_id);
+            for depends_on in dependencies.values_mut() {
+                depends_on.remove(check_id);
+            }
+        }
+        self.save().await?;
+
         if let Some(check) = check {
             // Send event
-            let _ = self.event_sender.send(HealthEvent::CheckDeleted {
+            let event = HealthEvent::CheckDeleted {
                 check_id: check_id.to_string(),
-                target_id: check.target_id,
-            });
+                target_id: check.target_id.clone(),
+            };
+            let _ = self.event_sender.send(event.clone());
+            self.webhooks.notify(&event, &check).await;
         }
 
         Ok(())
     }
 
+    /// Only run `check_id` while the latest result of `depends_on` is
+    /// healthy. Either check may be created later.
+    pub async fn add_dependency(&self, check_id: &str, depends_on: &str) -> PolisResult<()> {
+        let mut dependencies = self.dependencies.write().await;
+
+        // The new edge closes a cycle if `check_id` is reachable from `depends_on`
+        if let Some(path) = dependency_path(&dependencies, depends_on, check_id) {
+            return Err(PolisError::Config(format!(
+                "circular dependency: {} -> {}",
+                check_id,
+                path.join(" -> ")
+            )));
+        }
+
+        dependencies
+            .entry(check_id.to_string())
+            .or_default()
+            .insert(depends_on.to_string());
+        drop(dependencies);
+
+        self.save()
+            .await
+            .map_err(|e| PolisError::Storage(format!("cannot persist health checks: {}", e)))
+    }
+
+    /// Checks `check_id` depends on directly
+    pub async fn get_dependencies(&self, check_id: &str) -> Vec<String> {
+        let dependencies = self.dependencies.read().await;
+        let mut ids: Vec<String> = dependencies
+            .get(check_id)
+            .map(|ids| ids.iter().cloned().collect())
+            .unwrap_or_default();
+        ids.sort();
+        ids
+    }
+
     pub async fn get_health_check(&self, check_id: &str) -> Option<HealthCheck> {
         let checks = self.checks.read().await;
         checks.get(check_id).cloned()
@@ -212,25 +644,105 @@ impl HealthMonitor {
         checks.values().cloned().collect()
     }
 
+    /// Latest status of every enabled check for the Prometheus exporter; a
+    /// check without a recent result counts as unhealthy. The `deployment`
+    /// and `namespace` labels of a check carry over.
+    pub async fn health_check_gauges(&self) -> Vec<HealthCheckGauge> {
+        let mut checks: Vec<HealthCheck> = self
+            .list_health_checks()
+            .await
+            .into_iter()
+            .filter(|check| check.enabled)
+            .collect();
+        checks.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+
+        let mut gauges = Vec::with_capacity(checks.len());
+        for check in checks {
+            let healthy = self
+                .get_health_check_result(&check.id)
+                .await
+                .is_some_and(|result| result.status == HealthStatus::Healthy);
+            let label = |key: &str| check.labels.get(key).cloned().unwrap_or_default();
+            gauges.push(HealthCheckGauge {
+                check: check.name.clone(),
+                target: check.target_id.clone(),
+                deployment: label("deployment"),
+                namespace: label("namespace"),
+                healthy,
+            });
+        }
+        gauges
+    }
+
+    /// Latest result of a check, if one is still within the history TTL
     pub async fn get_health_check_result(&self, check_id: &str) -> Option<HealthCheckResult> {
+        self.get_health_check_history(check_id, 1).await.pop()
+    }
+
+    /// Up to `limit` results of a check, newest first
+    pub async fn get_health_check_history(
+        &self,
+        check_id: &str,
+        limit: usize,
+    ) -> Vec<HealthCheckResult> {
+        let cutoff = self.history.cutoff();
         let results = self.results.read().await;
-        results.get(check_id).cloned()
+        results
+            .get(check_id)
+            .map(|history| {
+                history
+                    .iter()
+                    .take_while(|result| HistoryPolicy::is_live(result, cutoff))
+                    .take(limit)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
+    /// Latest result of every check, optionally restricted to one target
     pub async fn get_health_check_results(
         &self,
         target_id: Option<&str>,
     ) -> Vec<HealthCheckResult> {
+        let cutoff = self.history.cutoff();
         let results = self.results.read().await;
-        if let Some(target_id) = target_id {
-            results
-                .values()
-                .filter(|result| result.target_id == target_id)
-                .cloned()
-                .collect()
-        } else {
-            results.values().cloned().collect()
+        results
+            .values()
+            .filter_map(|history| history.front())
+            .filter(|result| HistoryPolicy::is_live(result, cutoff))
+            .filter(|result| target_id.is_none() || target_id == Some(result.target_id.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// Append a result to its check's history.
+    ///
+    /// The result is timestamped with the monitor's clock and its consecutive
+    /// failure/success counts are derived from the previous entry, so they
+    /// keep counting after older entries have been evicted.
+    pub async fn record_result(&self, result: HealthCheckResult) -> HealthCheckResult {
+        let check = self.get_health_check(&result.check_id).await;
+        let (result, previous_status) = {
+            let mut results = self.results.write().await;
+            self.history.record(&mut results, result, check.as_ref())
+        };
+
+        if let Some(event) = status_event(previous_status.clone(), &result) {
+            let _ = self.event_sender.send(event.clone());
+            publish_transition(&self.event_bus, &event);
+            if let Some(check) = &check {
+                self.webhooks.notify(&event, check).await;
+            }
+        }
+        if let Some(check) = &check {
+            if crossed_failure_threshold(previous_status.as_ref(), &result, check) {
+                self.actions
+                    .run(check, &result, self.history.clock.now(), self.history.limit)
+                    .await;
+            }
         }
+        result
     }
 
     pub async fn get_health_summary(&self, target_id: Option<&str>) -> HealthSummary {
@@ -337,8 +849,14 @@ impl HealthMonitor {
             let check_id = check_id.to_string();
             let checker = Arc::clone(&self.checker);
             let results = Arc::clone(&self.results);
+            let history = self.history.clone();
             let event_sender = Arc::clone(&self.event_sender);
+            let event_bus = self.event_bus.clone();
+            let webhooks = Arc::clone(&self.webhooks);
+            let dependencies = Arc::clone(&self.dependencies);
             let checks = Arc::clone(&self.checks);
+            let actions = Arc::clone(&self.actions);
+            let store = self.store.clone();
 
             tokio::spawn(async move {
                 let mut interval = tokio::time::interval(check.interval);
@@ -346,62 +864,49 @@ impl HealthMonitor {
                 loop {
                     interval.tick().await;
 
-                    // Check if the health check still exists and is enabled
-                    let should_continue = {
+                    // Stop once the health check is gone or disabled, and
+                    // pick up any update to it
+                    let current = {
                         let checks = checks.read().await;
-                        checks.get(&check_id).map_or(false, |c| c.enabled)
+                        checks.get(&check_id).filter(|c| c.enabled).cloned()
                     };
-
-                    if !should_continue {
+                    let Some(check) = current else {
                         break;
-                    }
+                    };
 
-                    // Perform health check
-                    let result = checker.check_health(&check).await;
+                    // Perform health check, unless a dependency is down
+                    let unhealthy =
+                        unhealthy_dependencies(&dependencies, &results, &history, &check_id).await;
+                    let result = if unhealthy.is_empty() {
+                        checker.check_health(&check).await
+                    } else {
+                        skipped_result(&check, &unhealthy)
+                    };
 
                     // Update results
-                    {
+                    let (result, previous_status) = {
                         let mut results = results.write().await;
-                        results.insert(check_id.clone(), result.clone());
-                    }
+                        history.record(&mut results, result, Some(&check))
+                    };
 
                     // Send events based on status change
-                    let previous_result = {
-                        let results = results.read().await;
-                        results.get(&check_id).cloned()
-                    };
+                    let event = status_event(previous_status.clone(), &result);
 
-                    if let Some(previous) = previous_result {
-                        match (previous.status, result.status) {
-                            (HealthStatus::Unhealthy, HealthStatus::Healthy) => {
-                                let _ = event_sender.send(HealthEvent::CheckRecovered {
-                                    check_id: check_id.clone(),
-                                    target_id: check.target_id.clone(),
-                                    message: result.message.clone(),
-                                });
-                            }
-                            (HealthStatus::Healthy, HealthStatus::Unhealthy) => {
-                                let _ = event_sender.send(HealthEvent::CheckFailed {
-                                    check_id: check_id.clone(),
-                                    target_id: check.target_id.clone(),
-                                    message: result.message.clone(),
-                                });
-                            }
-                            (_, HealthStatus::Degraded) => {
-                                let _ = event_sender.send(HealthEvent::CheckDegraded {
-                                    check_id: check_id.clone(),
-                                    target_id: check.target_id.clone(),
-                                    message: result.message.clone(),
-                                });
-                            }
-                            (HealthStatus::Degraded, HealthStatus::Healthy) => {
-                                let _ = event_sender.send(HealthEvent::CheckPassed {
-                                    check_id: check_id.clone(),
-                                    target_id: check.target_id.clone(),
-                                    message: result.message.clone(),
-                                });
-                            }
-                            _ => {}
+                    if let Some(event) = event {
+                        let _ = event_sender.send(event.clone());
+                        publish_transition(&event_bus, &event);
+                        webhooks.notify(&event, &check).await;
+                    }
+
+                    if crossed_failure_threshold(previous_status.as_ref(), &result, &check) {
+                        actions
+                            .run(&check, &result, history.clock.now(), history.limit)
+                            .await;
+                    }
+
+                    if let Some(store) = &store {
+                        if let Err(e) = store.write(&checks, &dependencies, &results).await {
+                            tracing::warn!("Failed to persist health check {}: {}", check_id, e);
                         }
                     }
                 }
@@ -411,10 +916,9 @@ impl HealthMonitor {
         Ok(())
     }
 
-    pub async fn get_health_events(&self) -> tokio::sync::mpsc::UnboundedReceiver<HealthEvent> {
-        let (_sender, receiver) = tokio::sync::mpsc::unbounded_channel();
-        // This would need to be implemented to forward events
-        receiver
+    /// Subscribe to health events emitted from now on; see [`EVENT_CHANNEL_CAPACITY`].
+    pub async fn get_health_events(&self) -> broadcast::Receiver<HealthEvent> {
+        self.event_sender.subscribe()
     }
 
     pub async fn run_health_check(&self, check_id: &str) -> Result<HealthCheckResult> {
@@ -425,14 +929,21 @@ impl HealthMonitor {
 
         match check {
             Some(check) => {
-                let result = self.checker.check_health(&check).await;
-
-                // Update results
-                {
-                    let mut results = self.results.write().await;
-                    results.insert(check_id.to_string(), result.clone());
-                }
+                let unhealthy = unhealthy_dependencies(
+                    &self.dependencies,
+                    &self.results,
+                    &self.history,
+                    check_id,
+                )
+                .await;
+                let result = if unhealthy.is_empty() {
+                    self.checker.check_health(&check).await
+                } else {
+                    skipped_result(&check, &unhealthy)
+                };
 
+                let result = self.record_result(result).await;
+                self.save().await?;
                 Ok(result)
             }
             None => Err(anyhow::anyhow!("Health check not found: {}", check_id)),
@@ -440,14 +951,769 @@ impl HealthMonitor {
     }
 }
 
+/// Dependency path from `from` to `to`, found by depth-first search
+fn dependency_path(
+    dependencies: &HashMap<String, HashSet<String>>,
+    from: &str,
+    to: &str,
+) -> Option<Vec<String>> {
+    fn visit(
+        dependencies: &HashMap<String, HashSet<String>>,
+        current: &str,
+        to: &str,
+        visited: &mut HashSet<String>,
+        path: &mut Vec<String>,
+    ) -> bool {
+        path.push(current.to_string());
+        if current == to {
+            return true;
+        }
+        if visited.insert(current.to_string()) {
+            for next in dependencies.get(current).into_iter().flatten() {
+                if visit(dependencies, next, to, visited, path) {
+                    return true;
+                }
+            }
+        }
+        path.pop();
+        false
+    }
+
+    let mut path = Vec::new();
+    visit(dependencies, from, to, &mut HashSet::new(), &mut path).then_some(path)
+}
+
+/// Dependencies of `check_id` whose latest result is missing, expired or not
+/// healthy
+async fn unhealthy_dependencies(
+    dependencies: &RwLock<HashMap<String, HashSet<String>>>,
+    results: &RwLock<HashMap<String, VecDeque<HealthCheckResult>>>,
+    history: &HistoryPolicy,
+    check_id: &str,
+) -> Vec<String> {
+    let depends_on = match dependencies.read().await.get(check_id) {
+        Some(depends_on) => depends_on.clone(),
+        None => return Vec::new(),
+    };
+
+    let cutoff = history.cutoff();
+    let results = results.read().await;
+    let mut unhealthy: Vec<String> = depends_on
+        .into_iter()
+        .filter(|dependency| {
+            !matches!(
+                results.get(dependency).and_then(|history| history.front()),
+                Some(latest) if latest.status == HealthStatus::Healthy
+                    && HistoryPolicy::is_live(latest, cutoff)
+            )
+        })
+        .collect();
+    unhealthy.sort();
+    unhealthy
+}
+
+/// Event for the change from `previous_status` to the status of `result`
+fn status_event(
+    previous_status: Option<HealthStatus>,
+    result: &HealthCheckResult,
+) -> Option<HealthEvent> {
+    let check_id = result.check_id.clone();
+    let target_id = result.target_id.clone();
+    let message = result.message.clone();
+    match (previous_status?, result.status.clone()) {
+        (HealthStatus::Unhealthy, HealthStatus::Healthy) => Some(HealthEvent::CheckRecovered {
+            check_id,
+            target_id,
+            message,
+        }),
+        (HealthStatus::Healthy, HealthStatus::Unhealthy) => Some(HealthEvent::CheckFailed {
+            check_id,
+            target_id,
+            message,
+        }),
+        (_, HealthStatus::Degraded) => Some(HealthEvent::CheckDegraded {
+            check_id,
+            target_id,
+            message,
+        }),
+        (HealthStatus::Degraded, HealthStatus::Healthy) => Some(HealthEvent::CheckPassed {
+            check_id,
+            target_id,
+            message,
+        }),
+        _ => None,
+    }
+}
+
+/// Publish a status transition to the shared event bus; created and deleted
+/// checks are not transitions and are left out
+fn publish_transition(event_bus: &EventBus, event: &HealthEvent) {
+    let (transition, check_id, target_id, message) = match event {
+        HealthEvent::CheckPassed {
+            check_id,
+            target_id,
+            message,
+        } => (HealthTransition::Passed, check_id, target_id, message),
+        HealthEvent::CheckFailed {
+            check_id,
+            target_id,
+            message,
+        } => (HealthTransition::Failed, check_id, target_id, message),
+        HealthEvent::CheckDegraded {
+            check_id,
+            target_id,
+            message,
+        } => (HealthTransition::Degraded, check_id, target_id, message),
+        HealthEvent::CheckRecovered {
+            check_id,
+            target_id,
+            message,
+        } => (HealthTransition::Recovered, check_id, target_id, message),
+        HealthEvent::CheckCreated { .. } | HealthEvent::CheckDeleted { .. } => return,
+    };
+    event_bus.publish(
+        "health",
+        EventKind::Health {
+            transition,
+            check_id: check_id.clone(),
+            target_id: target_id.clone(),
+            message: message.clone(),
+        },
+    );
+}
+
+/// Whether `result` is the first to reach its check's failure threshold
+/// since the target was last healthy
+fn crossed_failure_threshold(
+    previous_status: Option<&HealthStatus>,
+    result: &HealthCheckResult,
+    check: &HealthCheck,
+) -> bool {
+    let threshold = check.failure_threshold.max(1);
+    result.status == HealthStatus::Unhealthy
+        && result.consecutive_failures >= threshold
+        && (previous_status != Some(&HealthStatus::Unhealthy)
+            || result.consecutive_failures == threshold)
+}
+
+/// Result recorded instead of running a check whose dependencies are down
+fn skipped_result(check: &HealthCheck, unhealthy: &[String]) -> HealthCheckResult {
+    let mut metadata = HashMap::new();
+    metadata.insert("unhealthy_dependencies".to_string(), unhealthy.join(","));
+    HealthCheckResult {
+        check_id: check.id.clone(),
+        target_id: check.target_id.clone(),
+        status: HealthStatus::Unknown,
+        message: DEPENDENCY_UNHEALTHY.to_string(),
+        response_time: Duration::ZERO,
+        timestamp: Utc::now(),
+        consecutive_failures: 0,
+        consecutive_successes: 0,
+        metadata,
+        circuit_state: CircuitState::Closed,
+    }
+}
+
+impl HealthEvent {
+    pub fn kind(&self) -> HealthEventKind {
+        match self {
+            HealthEvent::CheckPassed { .. } => HealthEventKind::CheckPassed,
+            HealthEvent::CheckFailed { .. } => HealthEventKind::CheckFailed,
+            HealthEvent::CheckDegraded { .. } => HealthEventKind::CheckDegraded,
+            HealthEvent::CheckRecovered { .. } => HealthEventKind::CheckRecovered,
+            HealthEvent::CheckCreated { .. } => HealthEventKind::CheckCreated,
+            HealthEvent::CheckDeleted { .. } => HealthEventKind::CheckDeleted,
+        }
+    }
+}
+
+impl WebhookFilter {
+    pub fn with_target_type(mut self, target_type: TargetType) -> Self {
+        self.target_types.push(target_type);
+        self
+    }
+
+    pub fn with_event(mut self, kind: HealthEventKind) -> Self {
+        self.events.push(kind);
+        self
+    }
+
+    pub fn with_label(mut self, key: String, value: String) -> Self {
+        self.labels.insert(key, value);
+        self
+    }
+
+    pub fn matches(&self, event: &HealthEvent, check: &HealthCheck) -> bool {
+        (self.target_types.is_empty() || self.target_types.contains(&check.target_type))
+            && (self.events.is_empty() || self.events.contains(&event.kind()))
+            && self
+                .labels
+                .iter()
+                .all(|(key, value)| check.labels.get(key) == Some(value))
+    }
+}
+
+impl WebhookDispatcher {
+    fn new(timeout: Duration) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            timeout,
+            webhooks: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Deliver `event` to every matching webhook in the background, so that
+    /// slow or failing receivers never hold up health checking
+    async fn notify(&self, event: &HealthEvent, check: &HealthCheck) {
+        let urls: Vec<String> = {
+            let webhooks = self.webhooks.read().await;
+            webhooks
+                .iter()
+                .filter(|(_, filter)| filter.matches(event, check))
+                .map(|(url, _)| url.clone())
+                .collect()
+        };
+        if urls.is_empty() {
+            return;
+        }
+
+        let payload = WebhookPayload {
+            event: event.clone(),
+            check_name: check.name.clone(),
+            target_type: check.target_type.clone(),
+            labels: check.labels.clone(),
+            timestamp: Utc::now(),
+        };
+        for url in urls {
+            let client = self.client.clone();
+            let timeout = self.timeout;
+            let payload = payload.clone();
+            tokio::spawn(async move {
+                if let Err(e) = Self::deliver(&client, &url, &payload, timeout).await {
+                    tracing::warn!("Failed to deliver health event to webhook {}: {}", url, e);
+                }
+            });
+        }
+    }
+
+    /// POST `payload`, retrying server errors and connection failures
+    async fn deliver(
+        client: &reqwest::Client,
+        url: &str,
+        payload: &WebhookPayload,
+        timeout: Duration,
+    ) -> Result<()> {
+        let mut backoff = WEBHOOK_RETRY_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            let error = match client.post(url).json(payload).timeout(timeout).send().await {
+                Ok(response) if response.status().is_server_error() => {
+                    anyhow::anyhow!("HTTP {}", response.status())
+                }
+                Ok(response) if !response.status().is_success() => {
+                    return Err(anyhow::anyhow!("HTTP {}", response.status()));
+                }
+                Ok(_) => return Ok(()),
+                Err(e) => e.into(),
+            };
+
+            if attempt == WEBHOOK_ATTEMPTS {
+                return Err(error.context(format!("gave up after {} attempts", attempt)));
+            }
+            tracing::debug!(
+                "Webhook {} attempt {} failed: {}; retrying in {:?}",
+                url,
+                attempt,
+                error,
+                backoff
+            );
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+impl RuntimeTargetController {
+    pub fn new(backend: Arc<dyn ContainerBackend>) -> Self {
+        Self { backend }
+    }
+}
+
+#[async_trait]
+impl TargetController for RuntimeTargetController {
+    async fn restart(&self, check: &HealthCheck) -> Result<()> {
+        if check.target_type != TargetType::Container {
+            return Err(anyhow::anyhow!(
+                "Cannot restart {:?} target {}",
+                check.target_type,
+                check.target_id
+            ));
+        }
+        let id = uuid::Uuid::parse_str(&check.target_id)
+            .map(ContainerId)
+            .map_err(|e| anyhow::anyhow!("Invalid container id {}: {}", check.target_id, e))?;
+        self.backend.stop(&id).await?;
+        self.backend.start(&id).await?;
+        Ok(())
+    }
+}
+
+impl ActionRunner {
+    fn new(controller: Option<Arc<dyn TargetController>>, cooldown: Duration) -> Self {
+        Self {
+            controller,
+            client: reqwest::Client::new(),
+            cooldown,
+            history: RwLock::new(HashMap::new()),
+            restarts: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Run the failure actions of `check` for `result`, keeping at most
+    /// `limit` records per check
+    async fn run(
+        &self,
+        check: &HealthCheck,
+        result: &HealthCheckResult,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) {
+        for action in &check.on_failure {
+            let outcome = match self.admit(check, action, now).await {
+                Some(reason) => ActionOutcome::RateLimited(reason),
+                None => match self.execute(check, action, result).await {
+                    Ok(()) => ActionOutcome::Succeeded,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failure action {:?} of health check {} failed: {}",
+                            action,
+                            check.id,
+                            e
+                        );
+                        ActionOutcome::Failed(e.to_string())
+                    }
+                },
+            };
+
+            let mut history = self.history.write().await;
+            let records = history.entry(check.id.clone()).or_default();
+            records.push_front(ActionRecord {
+                check_id: check.id.clone(),
+                target_id: check.target_id.clone(),
+                action: action.clone(),
+                outcome,
+                timestamp: now,
+            });
+            records.truncate(limit);
+        }
+    }
+
+    /// Why `action` may not run now, if it may not
+    async fn admit(
+        &self,
+        check: &HealthCheck,
+        action: &FailureAction,
+        now: DateTime<Utc>,
+    ) -> Option<String> {
+        let last_run = {
+            let history = self.history.read().await;
+            history.get(&check.id).and_then(|records| {
+                records
+                    .iter()
+                    .find(|record| {
+                        record.action == *action
+                            && !matches!(record.outcome, ActionOutcome::RateLimited(_))
+                    })
+                    .map(|record| record.timestamp)
+            })
+        };
+        if let Some(last_run) = last_run {
+            if elapsed(last_run, now) < self.cooldown {
+                return Some(format!("ran less than {:?} ago", self.cooldown));
+            }
+        }
+
+        if let FailureAction::RestartContainer {
+            max_restarts,
+            window,
+        } = action
+        {
+            let mut restarts = self.restarts.write().await;
+            let times = restarts.entry(check.id.clone()).or_default();
+            while let Some(oldest) = times.front() {
+                if elapsed(*oldest, now) < *window {
+                    break;
+                }
+                times.pop_front();
+            }
+            if times.len() >= *max_restarts as usize {
+                return Some(format!(
+                    "restarted {} times within {:?}",
+                    times.len(),
+                    window
+                ));
+            }
+            times.push_back(now);
+        }
+        None
+    }
+
+    async fn execute(
+        &self,
+        check: &HealthCheck,
+        action: &FailureAction,
+        result: &HealthCheckResult,
+    ) -> Result<()> {
+        match action {
+            FailureAction::RestartContainer { .. } => self.controller()?.restart(check).await,
+            FailureAction::MarkServiceUnhealthy => self.controller()?.mark_unhealthy(check).await,
+            FailureAction::Webhook {
+                url,
+                method,
+                body_template,
+            } => {
+                let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes())
+                    .map_err(|e| anyhow::anyhow!("Invalid webhook method {}: {}", method, e))?;
+                let body = body_template
+                    .replace("{{check_id}}", &check.id)
+                    .replace("{{check_name}}", &check.name)
+                    .replace("{{target_id}}", &check.target_id)
+                    .replace("{{message}}", &result.message)
+                    .replace(
+                        "{{consecutive_failures}}",
+                        &result.consecutive_failures.to_string(),
+                    );
+                let response = self
+                    .client
+                    .request(method, url)
+                    .body(body)
+                    .timeout(DEFAULT_WEBHOOK_TIMEOUT)
+                    .send()
+                    .await?;
+                if !response.status().is_success() {
+                    return Err(anyhow::anyhow!("HTTP {}", response.status()));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn controller(&self) -> Result<&Arc<dyn TargetController>> {
+        self.controller
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No target controller configured"))
+    }
+
+    async fn forget(&self, check_id: &str) {
+        self.history.write().await.remove(check_id);
+        self.restarts.write().await.remove(check_id);
+    }
+}
+
+/// Time from `since` to `now`; zero if the clock went backwards
+fn elapsed(since: DateTime<Utc>, now: DateTime<Utc>) -> Duration {
+    now.signed_duration_since(since)
+        .to_std()
+        .unwrap_or_default()
+}
+
+impl CheckStore {
+    fn is_yaml(&self) -> bool {
+        matches!(
+            self.path.extension().and_then(|e| e.to_str()),
+            Some("yaml" | "yml")
+        )
+    }
+
+    async fn read(&self) -> Result<PersistedChecks> {
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(PersistedChecks::default())
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let persisted: PersistedChecks = if self.is_yaml() {
+            serde_yaml::from_str(&content)?
+        } else {
+            serde_json::from_str(&content)?
+        };
+        if persisted.version > HEALTH_CHECKS_VERSION {
+            anyhow::bail!(
+                "Health checks {} have schema version {}, newer than the supported version {}",
+                self.path.display(),
+                persisted.version,
+                HEALTH_CHECKS_VERSION
+            );
+        }
+        Ok(persisted)
+    }
+
+    async fn write(
+        &self,
+        checks: &RwLock<HashMap<String, HealthCheck>>,
+        dependencies: &RwLock<HashMap<String, HashSet<String>>>,
+        results: &RwLock<HashMap<String, VecDeque<HealthCheckResult>>>,
+    ) -> Result<()> {
+        // Snapshot under the lock so writes land in the order they were taken
+        let _guard = self.lock.lock().await;
+
+        let mut persisted = PersistedChecks {
+            version: HEALTH_CHECKS_VERSION,
+            checks: checks.read().await.values().cloned().collect(),
+            ..Default::default()
+        };
+        persisted.checks.sort_by(|a, b| a.id.cmp(&b.id));
+        let known: HashSet<&str> = persisted.checks.iter().map(|c| c.id.as_str()).collect();
+
+        for (check_id, depends_on) in dependencies.read().await.iter() {
+            let mut depends_on: Vec<String> = depends_on.iter().cloned().collect();
+            depends_on.sort();
+            persisted.dependencies.insert(check_id.clone(), depends_on);
+        }
+        persisted.results = results
+            .read()
+            .await
+            .iter()
+            .filter(|(check_id, _)| known.contains(check_id.as_str()))
+            .filter_map(|(_, history)| history.front().cloned())
+            .collect();
+        persisted
+            .results
+            .sort_by(|a, b| a.check_id.cmp(&b.check_id));
+
+        let content = if self.is_yaml() {
+            serde_yaml::to_string(&persisted)?.into_bytes()
+        } else {
+            serde_json::to_vec_pretty(&persisted)?
+        };
+        write_atomic(&self.path, &content).await?;
+        Ok(())
+    }
+}
+
+impl HistoryPolicy {
+    /// Results stamped before this instant have expired
+    fn cutoff(&self) -> Option<DateTime<Utc>> {
+        chrono::Duration::from_std(self.ttl)
+            .ok()
+            .and_then(|ttl| self.clock.now().checked_sub_signed(ttl))
+    }
+
+    fn is_live(result: &HealthCheckResult, cutoff: Option<DateTime<Utc>>) -> bool {
+        match cutoff {
+            Some(cutoff) => result.timestamp >= cutoff,
+            None => true,
+        }
+    }
+
+    /// Push `result` onto its history and return it together with the status
+    /// of the entry it replaced as latest.
+    ///
+    /// A healthy target stays healthy until `check`'s failure threshold is
+    /// reached, and an unhealthy one unhealthy until its success threshold is;
+    /// the status the probe saw is then kept in the `observed_status`
+    /// metadata.
+    fn record(
+        &self,
+        results: &mut HashMap<String, VecDeque<HealthCheckResult>>,
+        mut result: HealthCheckResult,
+        check: Option<&HealthCheck>,
+    ) -> (HealthCheckResult, Option<HealthStatus>) {
+        let cutoff = self.cutoff();
+        let history = results.entry(result.check_id.clone()).or_default();
+        while let Some(oldest) = history.back() {
+            if Self::is_live(oldest, cutoff) {
+                break;
+            }
+            history.pop_back();
+        }
+
+        let previous = history.front();
+        let (failures, successes) = previous.map_or((0, 0), |previous| {
+            (
+                previous.consecutive_failures,
+                previous.consecutive_successes,
+            )
+        });
+        (result.consecutive_failures, result.consecutive_successes) = match result.status {
+            HealthStatus::Healthy => (0, successes + 1),
+            HealthStatus::Unhealthy | HealthStatus::Unknown => (failures + 1, 0),
+            HealthStatus::Degraded => (0, 0),
+        };
+        let previous_status = previous.map(|previous| previous.status.clone());
+
+        let (failure_threshold, success_threshold) = match check {
+            Some(check) => (check.failure_threshold, check.success_threshold),
+            None => (1, 1),
+        };
+        let held = match (&previous_status, &result.status) {
+            (Some(HealthStatus::Healthy), HealthStatus::Unhealthy) => {
+                result.consecutive_failures < failure_threshold
+            }
+            (Some(HealthStatus::Unhealthy), HealthStatus::Healthy) => {
+                result.consecutive_successes < success_threshold
+            }
+            _ => false,
+        };
+        if let (true, Some(status)) = (held, &previous_status) {
+            result.metadata.insert(
+                "observed_status".to_string(),
+                format!("{:?}", result.status),
+            );
+            result.status = status.clone();
+        }
+
+        result.timestamp = self.clock.now();
+        history.push_front(result.clone());
+        history.truncate(self.limit);
+
+        (result, previous_status)
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, reset_timeout: Duration) -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            failure_threshold: failure_threshold.max(1),
+            reset_timeout,
+            opened_at: None,
+            error_rate: None,
+            outcomes: VecDeque::new(),
+        }
+    }
+
+    /// Also open the circuit once at least `rate` of the last `window`
+    /// outcomes were failures, even if they were not consecutive
+    pub fn with_error_rate(mut self, rate: f64, window: u32) -> Self {
+        self.error_rate = Some((rate.clamp(0.0, 1.0), window.max(1) as usize));
+        self
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    /// Whether a check may run at `now`. An open circuit whose reset timeout
+    /// has elapsed becomes half-open and lets exactly one probe through.
+    pub fn allow_request(&mut self, now: DateTime<Utc>) -> bool {
+        let allowed = self.is_available(now);
+        if allowed && self.state == CircuitState::Open {
+            self.state = CircuitState::HalfOpen;
+        }
+        allowed
+    }
+
+    /// Whether `allow_request` would let a request through at `now`, without
+    /// moving an open circuit to half-open
+    pub fn is_available(&self, now: DateTime<Utc>) -> bool {
+        match self.state {
+            CircuitState::Closed => true,
+            CircuitState::HalfOpen => false,
+            CircuitState::Open => match (
+                self.opened_at,
+                chrono::Duration::from_std(self.reset_timeout),
+            ) {
+                (Some(opened_at), Ok(reset_timeout)) => now - opened_at >= reset_timeout,
+                (Some(_), Err(_)) => false,
+                (None, _) => true,
+            },
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        if self.state != CircuitState::Closed {
+            self.outcomes.clear();
+        }
+        self.record_outcome(false);
+        self.state = CircuitState::Closed;
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+    }
+
+    /// Count a failed check, opening the circuit once the threshold is
+    /// reached or straight away when the half-open probe failed
+    pub fn record_failure(&mut self, now: DateTime<Utc>) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        self.record_outcome(true);
+        if self.state == CircuitState::HalfOpen
+            || self.consecutive_failures >= self.failure_threshold
+            || self.error_rate_exceeded()
+        {
+            self.state = CircuitState::Open;
+            self.opened_at = Some(now);
+            self.outcomes.clear();
+        }
+    }
+
+    fn record_outcome(&mut self, failed: bool) {
+        if let Some((_, window)) = self.error_rate {
+            self.outcomes.push_back(failed);
+            if self.outcomes.len() > window {
+                self.outcomes.pop_front();
+            }
+        }
+    }
+
+    fn error_rate_exceeded(&self) -> bool {
+        match self.error_rate {
+            Some((rate, window)) if self.outcomes.len() >= window => {
+                let failures = self.outcomes.iter().filter(|failed| **failed).count();
+                failures as f64 >= rate * window as f64
+            }
+            _ => false,
+        }
+    }
+}
+
 impl HealthChecker {
     pub fn new() -> Self {
         Self {
             client: reqwest::Client::new(),
             command_executor: Arc::new(CommandExecutor::new()),
+            breakers: RwLock::new(HashMap::new()),
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            reset_timeout: DEFAULT_RESET_TIMEOUT,
+            clock: Arc::new(SystemClock),
         }
     }
 
+    /// Open a check's circuit after this many consecutive failed checks
+    pub fn with_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold;
+        self
+    }
+
+    /// Keep an open circuit open this long before probing the target again
+    pub fn with_reset_timeout(mut self, reset_timeout: Duration) -> Self {
+        self.reset_timeout = reset_timeout;
+        self
+    }
+
+    /// Clock used to time open circuits
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Current state of a check's circuit
+    pub async fn circuit_state(&self, check_id: &str) -> CircuitState {
+        self.breakers
+            .read()
+            .await
+            .get(check_id)
+            .map_or(CircuitState::Closed, CircuitBreaker::state)
+    }
+
+    /// Forget a check's circuit, closing it
+    pub async fn reset_circuit(&self, check_id: &str) {
+        self.breakers.write().await.remove(check_id);
+    }
+
     pub async fn check_health(&self, check: &HealthCheck) -> HealthCheckResult {
         let start_time = Instant::now();
         let mut result = HealthCheckResult {
@@ -460,24 +1726,42 @@ impl HealthChecker {
             consecutive_failures: 0,
             consecutive_successes: 0,
             metadata: HashMap::new(),
+            circuit_state: CircuitState::Closed,
+        };
+
+        let state = {
+            let mut breakers = self.breakers.write().await;
+            let breaker = breakers
+                .entry(check.id.clone())
+                .or_insert_with(|| CircuitBreaker::new(self.failure_threshold, self.reset_timeout));
+            if !breaker.allow_request(self.clock.now()) {
+                result.status = HealthStatus::Unhealthy;
+                result.message = "Circuit open, health check skipped".to_string();
+                result.circuit_state = breaker.state();
+                return result;
+            }
+            breaker.state()
+        };
+
+        // A half-open circuit only lets a single probe through
+        let attempts = if state == CircuitState::HalfOpen {
+            check.retries.min(1)
+        } else {
+            check.retries
         };
 
-        for attempt in 0..check.retries {
+        for attempt in 0..attempts {
             match self.perform_check(check).await {
                 Ok(status) => {
                     result.status = status;
                     result.message = "Health check passed".to_string();
-                    result.consecutive_successes += 1;
-                    result.consecutive_failures = 0;
                     break;
                 }
                 Err(e) => {
                     result.message =
                         format!("Health check failed (attempt {}): {}", attempt + 1, e);
-                    result.consecutive_failures += 1;
-                    result.consecutive_successes = 0;
 
-                    if attempt < check.retries - 1 {
+                    if attempt < attempts - 1 {
                         tokio::time::sleep(Duration::from_millis(100)).await;
                     }
                 }
@@ -485,6 +1769,19 @@ impl HealthChecker {
         }
 
         result.response_time = start_time.elapsed();
+        result.circuit_state = {
+            let mut breakers = self.breakers.write().await;
+            let breaker = breakers
+                .entry(check.id.clone())
+                .or_insert_with(|| CircuitBreaker::new(self.failure_threshold, self.reset_timeout));
+            match result.status {
+                HealthStatus::Healthy | HealthStatus::Degraded => breaker.record_success(),
+                HealthStatus::Unhealthy | HealthStatus::Unknown => {
+                    breaker.record_failure(self.clock.now())
+                }
+            }
+            breaker.state()
+        };
         result
     }
 
@@ -673,9 +1970,33 @@ impl HealthCheck {
             updated_at: Utc::now(),
             labels: HashMap::new(),
             annotations: HashMap::new(),
+            failure_threshold: default_threshold(),
+            success_threshold: default_threshold(),
+            on_failure: Vec::new(),
         }
     }
 
+    /// Run `action` once the failure threshold is crossed, after any
+    /// actions added before it
+    pub fn with_failure_action(mut self, action: FailureAction) -> Self {
+        self.on_failure.push(action);
+        self
+    }
+
+    /// Report a healthy target unhealthy only after this many consecutive
+    /// failed checks
+    pub fn with_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    /// Report an unhealthy target healthy again only after this many
+    /// consecutive successful checks
+    pub fn with_success_threshold(mut self, success_threshold: u32) -> Self {
+        self.success_threshold = success_threshold.max(1);
+        self
+    }
+
     pub fn with_interval(mut self, interval: Duration) -> Self {
         self.interval = interval;
         self
@@ -793,4 +2114,427 @@ mod tests {
         assert_eq!(stats.total_checks, 0);
         assert_eq!(stats.uptime_percentage, 0.0);
     }
+
+    fn result(status: HealthStatus) -> HealthCheckResult {
+        HealthCheckResult {
+            check_id: "check".to_string(),
+            target_id: "target".to_string(),
+            status,
+            message: String::new(),
+            response_time: Duration::from_millis(5),
+            timestamp: Utc::now(),
+            consecutive_failures: 0,
+            consecutive_successes: 0,
+            metadata: HashMap::new(),
+            circuit_state: CircuitState::Closed,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_evicts_oldest_beyond_limit() {
+        let monitor = HealthMonitor::new().with_history_limit(3);
+
+        for _ in 0..2 {
+            monitor.record_result(result(HealthStatus::Healthy)).await;
+        }
+        for _ in 0..3 {
+            monitor.record_result(result(HealthStatus::Unhealthy)).await;
+        }
+
+        let history = monitor.get_health_check_history("check", 10).await;
+        assert_eq!(history.len(), 3);
+        assert!(history.iter().all(|r| r.status == HealthStatus::Unhealthy));
+        assert_eq!(
+            history
+                .iter()
+                .map(|r| r.consecutive_failures)
+                .collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+
+        assert_eq!(monitor.get_health_check_history("check", 2).await.len(), 2);
+        assert!(monitor
+            .get_health_check_history("missing", 10)
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_history_expires_after_ttl() {
+        let clock = Arc::new(polis_core::ManualClock::new(Utc::now()));
+        let monitor = HealthMonitor::new()
+            .with_history_ttl(Duration::from_secs(60))
+            .with_clock(clock.clone());
+
+        monitor.record_result(result(HealthStatus::Healthy)).await;
+        clock.advance(Duration::from_secs(30));
+        monitor.record_result(result(HealthStatus::Healthy)).await;
+        assert_eq!(monitor.get_health_check_history("check", 10).await.len(), 2);
+
+        clock.advance(Duration::from_secs(45));
+        let history = monitor.get_health_check_history("check", 10).await;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].consecutive_successes, 2);
+
+        clock.advance(Duration::from_secs(60));
+        assert!(monitor.get_health_check_result("check").await.is_none());
+        assert_eq!(monitor.get_health_summary(None).await.total_checks, 0);
+
+        // An expired streak does not carry over to new results
+        let latest = monitor.record_result(result(HealthStatus::Healthy)).await;
+        assert_eq!(latest.consecutive_successes, 1);
+        assert_eq!(monitor.get_health_check_history("check", 10).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_thresholds_decide_status_transitions() {
+        let monitor = HealthMonitor::new();
+        let mut check = HealthCheck::new(
+            "check".to_string(),
+            "check".to_string(),
+            TargetType::Custom,
+            "target".to_string(),
+            CheckType::Custom {
+                script: "true".to_string(),
+            },
+        )
+        .with_failure_threshold(3)
+        .with_success_threshold(2);
+        check.enabled = false;
+        monitor.create_health_check(check).await.unwrap();
+        let mut events = monitor.get_health_events().await;
+
+        let (pass, fail) = (HealthStatus::Healthy, HealthStatus::Unhealthy);
+        let script = [
+            pass.clone(),
+            fail.clone(),
+            fail.clone(),
+            pass.clone(),
+            fail.clone(),
+            fail.clone(),
+            fail.clone(),
+            fail.clone(),
+            pass.clone(),
+            pass.clone(),
+            pass.clone(),
+        ];
+        let mut reported = Vec::new();
+        let mut emitted = Vec::new();
+        for (run, status) in script.into_iter().enumerate() {
+            let observed = status.clone();
+            let result = monitor.record_result(result(status)).await;
+            if result.status != observed {
+                assert_eq!(
+                    result.metadata.get("observed_status"),
+                    Some(&format!("{:?}", observed))
+                );
+            }
+            reported.push(result.status);
+            while let Ok(event) = events.try_recv() {
+                emitted.push((run, event.kind()));
+            }
+        }
+
+        // Two failures stay below the threshold, the third flips the status
+        // and two successes bring it back
+        let healthy = HealthStatus::Healthy;
+        let unhealthy = HealthStatus::Unhealthy;
+        assert_eq!(
+            reported,
+            vec![
+                healthy.clone(),
+                healthy.clone(),
+                healthy.clone(),
+                healthy.clone(),
+                healthy.clone(),
+                healthy.clone(),
+                unhealthy.clone(),
+                unhealthy.clone(),
+                unhealthy,
+                healthy.clone(),
+                healthy,
+            ]
+        );
+        assert_eq!(
+            emitted,
+            vec![
+                (6, HealthEventKind::CheckFailed),
+                (9, HealthEventKind::CheckRecovered)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_consecutive_counts_survive_eviction() {
+        let monitor = HealthMonitor::new().with_history_limit(2);
+
+        for _ in 0..5 {
+            monitor.record_result(result(HealthStatus::Unhealthy)).await;
+        }
+        let latest = monitor.get_health_check_result("check").await.unwrap();
+        assert_eq!(latest.consecutive_failures, 5);
+        assert_eq!(latest.consecutive_successes, 0);
+
+        let recovered = monitor.record_result(result(HealthStatus::Healthy)).await;
+        assert_eq!(recovered.consecutive_failures, 0);
+        assert_eq!(recovered.consecutive_successes, 1);
+
+        let degraded = monitor.record_result(result(HealthStatus::Degraded)).await;
+        assert_eq!(degraded.consecutive_failures, 0);
+        assert_eq!(degraded.consecutive_successes, 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_health_check_records_history() {
+        let monitor = HealthMonitor::new();
+        let mut check = HealthCheck::new(
+            "cmd".to_string(),
+            "Command".to_string(),
+            TargetType::Container,
+            "test-container".to_string(),
+            CheckType::Command {
+                command: "false".to_string(),
+                args: Vec::new(),
+            },
+        );
+        check.enabled = false;
+        monitor.create_health_check(check).await.unwrap();
+
+        monitor.run_health_check("cmd").await.unwrap();
+        let second = monitor.run_health_check("cmd").await.unwrap();
+        assert_eq!(second.status, HealthStatus::Unhealthy);
+        assert_eq!(second.consecutive_failures, 2);
+
+        let history = monitor.get_health_check_history("cmd", 10).await;
+        assert_eq!(history.len(), 2);
+
+        monitor.delete_health_check("cmd").await.unwrap();
+        assert!(monitor.get_health_check_result("cmd").await.is_none());
+    }
+
+    /// Check running a script that logs every invocation to `calls` and
+    /// succeeds only while `up` exists
+    fn probe_check(id: &str, dir: &std::path::Path) -> HealthCheck {
+        HealthCheck::new(
+            id.to_string(),
+            "probe".to_string(),
+            TargetType::Custom,
+            "target".to_string(),
+            CheckType::Custom {
+                script: format!("echo probe >> {0}/calls; test -e {0}/up", dir.display()),
+            },
+        )
+        .with_retries(1)
+    }
+
+    fn probe_calls(dir: &std::path::Path) -> usize {
+        std::fs::read_to_string(dir.join("calls"))
+            .map(|calls| calls.lines().count())
+            .unwrap_or(0)
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_state_machine() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = Arc::new(polis_core::ManualClock::new(Utc::now()));
+        let checker = HealthChecker::new()
+            .with_failure_threshold(2)
+            .with_reset_timeout(Duration::from_secs(30))
+            .with_clock(clock.clone());
+        let check = probe_check("probe", dir.path());
+
+        let first = checker.check_health(&check).await;
+        assert_eq!(first.status, HealthStatus::Unhealthy);
+        assert_eq!(first.circuit_state, CircuitState::Closed);
+        let second = checker.check_health(&check).await;
+        assert_eq!(second.circuit_state, CircuitState::Open);
+        assert_eq!(probe_calls(dir.path()), 2);
+
+        // Open: fails fast without running the probe
+        let skipped = checker.check_health(&check).await;
+        assert_eq!(skipped.status, HealthStatus::Unhealthy);
+        assert_eq!(skipped.circuit_state, CircuitState::Open);
+        clock.advance(Duration::from_secs(29));
+        checker.check_health(&check).await;
+        assert_eq!(probe_calls(dir.path()), 2);
+
+        // Half-open probe fails: reopens with a fresh timeout
+        clock.advance(Duration::from_secs(1));
+        let probe = checker.check_health(&check).await;
+        assert_eq!(probe.circuit_state, CircuitState::Open);
+        assert_eq!(probe_calls(dir.path()), 3);
+        clock.advance(Duration::from_secs(29));
+        checker.check_health(&check).await;
+        assert_eq!(probe_calls(dir.path()), 3);
+
+        // Half-open probe succeeds: closes
+        std::fs::write(dir.path().join("up"), "").unwrap();
+        clock.advance(Duration::from_secs(1));
+        let probe = checker.check_health(&check).await;
+        assert_eq!(probe.status, HealthStatus::Healthy);
+        assert_eq!(probe.circuit_state, CircuitState::Closed);
+        assert_eq!(checker.circuit_state("probe").await, CircuitState::Closed);
+
+        checker.check_health(&check).await;
+        assert_eq!(probe_calls(dir.path()), 5);
+    }
+
+    #[tokio::test]
+    async fn test_circuits_are_per_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let monitor =
+            HealthMonitor::new().with_checker(HealthChecker::new().with_failure_threshold(1));
+        for id in ["a", "b"] {
+            let mut check = probe_check(id, dir.path());
+            check.enabled = false;
+            monitor.create_health_check(check).await.unwrap();
+        }
+
+        let result = monitor.run_health_check("a").await.unwrap();
+        assert_eq!(result.circuit_state, CircuitState::Open);
+        assert_eq!(
+            monitor.checker.circuit_state("b").await,
+            CircuitState::Closed
+        );
+
+        monitor.run_health_check("b").await.unwrap();
+        monitor.run_health_check("a").await.unwrap();
+        assert_eq!(probe_calls(dir.path()), 2);
+
+        // Deleting a check forgets its circuit
+        monitor.delete_health_check("a").await.unwrap();
+        assert_eq!(
+            monitor.checker.circuit_state("a").await,
+            CircuitState::Closed
+        );
+    }
+
+    #[test]
+    fn test_half_open_circuit_allows_a_single_probe() {
+        let now = Utc::now();
+        let mut breaker = CircuitBreaker::new(1, Duration::from_secs(10));
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        let later = now + chrono::Duration::seconds(10);
+        assert!(breaker.allow_request(later));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(!breaker.allow_request(later));
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.allow_request(later));
+    }
+
+    #[test]
+    fn test_error_rate_opens_circuit_without_consecutive_failures() {
+        let now = Utc::now();
+        let mut breaker = CircuitBreaker::new(100, Duration::from_secs(10)).with_error_rate(0.5, 4);
+
+        // Not enough outcomes yet
+        breaker.record_failure(now);
+        breaker.record_success();
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        // Two of the last four failed
+        breaker.record_success();
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // Closing after a probe starts a fresh window
+        assert!(breaker.allow_request(now + chrono::Duration::seconds(10)));
+        breaker.record_success();
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    /// Disabled probe check, so that only `run_health_check` runs it
+    async fn add_probe(monitor: &HealthMonitor, id: &str, dir: &std::path::Path) {
+        let mut check = probe_check(id, dir);
+        check.enabled = false;
+        monitor.create_health_check(check).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check_is_skipped_while_dependency_is_unhealthy() {
+        let db_dir = tempfile::tempdir().unwrap();
+        let api_dir = tempfile::tempdir().unwrap();
+        std::fs::write(api_dir.path().join("up"), "").unwrap();
+        let monitor = HealthMonitor::new();
+        add_probe(&monitor, "db", db_dir.path()).await;
+        add_probe(&monitor, "api", api_dir.path()).await;
+        monitor.add_dependency("api", "db").await.unwrap();
+        assert_eq!(monitor.get_dependencies("api").await, vec!["db"]);
+
+        monitor.run_health_check("db").await.unwrap();
+        let api = monitor.run_health_check("api").await.unwrap();
+        assert_eq!(api.status, HealthStatus::Unknown);
+        assert_eq!(api.message, DEPENDENCY_UNHEALTHY);
+        assert_eq!(api.metadata["unhealthy_dependencies"], "db");
+        assert_eq!(probe_calls(api_dir.path()), 0);
+
+        std::fs::write(db_dir.path().join("up"), "").unwrap();
+        monitor.run_health_check("db").await.unwrap();
+        let api = monitor.run_health_check("api").await.unwrap();
+        assert_eq!(api.status, HealthStatus::Healthy);
+        assert_eq!(probe_calls(api_dir.path()), 1);
+
+        // Deleting the dependency removes the edge
+        monitor.delete_health_check("db").await.unwrap();
+        assert!(monitor.get_dependencies("api").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_circular_dependencies_are_rejected() {
+        let monitor = HealthMonitor::new();
+        monitor.add_dependency("a", "b").await.unwrap();
+        monitor.add_dependency("b", "c").await.unwrap();
+
+        let error = monitor.add_dependency("c", "a").await.unwrap_err();
+        match error {
+            PolisError::Config(message) => {
+                assert!(message.contains("circular dependency"), "{}", message);
+                assert!(message.contains("c -> a -> b -> c"), "{}", message);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert!(monitor.add_dependency("a", "a").await.is_err());
+        assert!(monitor.get_dependencies("c").await.is_empty());
+
+        // Diamonds are not cycles
+        monitor.add_dependency("a", "c").await.unwrap();
+        monitor.add_dependency("d", "b").await.unwrap();
+        monitor.add_dependency("d", "c").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dependency_resolution_order_does_not_matter() {
+        let dirs: Vec<_> = (0..3).map(|_| tempfile::tempdir().unwrap()).collect();
+        for dir in &dirs {
+            std::fs::write(dir.path().join("up"), "").unwrap();
+        }
+
+        // Dependencies registered before the checks exist
+        let monitor = HealthMonitor::new();
+        monitor.add_dependency("web", "cache").await.unwrap();
+        monitor.add_dependency("web", "db").await.unwrap();
+        for (id, dir) in ["web", "cache", "db"].iter().zip(&dirs) {
+            add_probe(&monitor, id, dir.path()).await;
+        }
+
+        // Run before its dependencies have any result
+        let web = monitor.run_health_check("web").await.unwrap();
+        assert_eq!(web.status, HealthStatus::Unknown);
+        assert_eq!(web.metadata["unhealthy_dependencies"], "cache,db");
+
+        monitor.run_health_check("db").await.unwrap();
+        let web = monitor.run_health_check("web").await.unwrap();
+        assert_eq!(web.metadata["unhealthy_dependencies"], "cache");
+
+        monitor.run_health_check("cache").await.unwrap();
+        let web = monitor.run_health_check("web").await.unwrap();
+        assert_eq!(web.status, HealthStatus::Healthy);
+        assert_eq!(probe_calls(dirs[0].path()), 1);
+    }
 }
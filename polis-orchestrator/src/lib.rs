@@ -1,31 +1,59 @@
 pub mod auto_scaling;
 pub mod health_monitor;
 pub mod load_balancer;
+pub mod namespace;
 pub mod orchestrator;
 pub mod scheduler;
 pub mod service_discovery;
+pub mod sync;
+
+/// Buffer of each event broadcast channel (scaling, health and service
+/// events).
+///
+/// Every receiver gets every event sent after it subscribed, and sending
+/// never blocks. A receiver more than this many events behind gets
+/// `RecvError::Lagged` once and resumes from the oldest event still buffered.
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
 
 pub use auto_scaling::{
-    AutoScaler, Deployment, MetricsCollector, ScalingAction, ScalingActionType, ScalingEngine,
-    ScalingEvent, ScalingPolicy,
+    AutoScaler, ContainerSelector, ContainerStatsSource, Deployment, MetricProvider,
+    MetricsCollector, MetricsSource, ScalingAction, ScalingActionType, ScalingEngine, ScalingEvent,
+    ScalingMetrics, ScalingPolicy, ScalingSchedule, ScalingStrategy, TrendField,
+    EMERGENCY_UTILIZATION, SCALE_TO_ZERO_THRESHOLD,
 };
 pub use health_monitor::{
-    CheckType, CommandExecutor, HealthCheck as HealthCheckDef, HealthCheckResult, HealthEvent,
-    HealthMonitor, HealthStatus, TargetType,
+    ActionOutcome, ActionRecord, CheckType, CircuitBreaker, CircuitState, CommandExecutor,
+    FailureAction, HealthCheck as HealthCheckDef, HealthCheckResult, HealthEvent, HealthEventKind,
+    HealthMonitor, HealthStatus, RuntimeTargetController, TargetController, TargetType,
+    WebhookFilter, WebhookPayload, DEFAULT_ACTION_COOLDOWN, DEFAULT_FAILURE_THRESHOLD,
+    DEFAULT_HISTORY_LIMIT, DEFAULT_HISTORY_TTL, DEFAULT_RESET_TIMEOUT, DEFAULT_WEBHOOK_TIMEOUT,
+    DEPENDENCY_UNHEALTHY, HEALTH_CHECKS_VERSION,
 };
 pub use load_balancer::{
-    ConsistentHashRing, EndpointStats, LoadBalancer, LoadBalancerRequest, LoadBalancerResponse,
-    LoadBalancerStats,
+    Body, ConsistentHashRing, EndpointState, EndpointStats, HashKey, LoadBalancer,
+    LoadBalancerRequest, LoadBalancerResponse, LoadBalancerStats, RetryCondition, RetryPolicy,
+    RoutingRule, TlsConfig, DEFAULT_CIRCUIT_OPEN_THRESHOLD, DEFAULT_CIRCUIT_RESET_TIMEOUT,
+    DEFAULT_CONNECT_TIMEOUT, DEFAULT_EJECTION_BACKOFF, DEFAULT_EJECTION_THRESHOLD,
+    DEFAULT_ENDPOINT_LATENCY, DEFAULT_EWMA_ALPHA, DEFAULT_MAX_EJECTION_PERCENT,
+    DEFAULT_REQUEST_TIMEOUT, DEFAULT_VIRTUAL_NODES, TLS_METADATA_PREFIX,
 };
+pub use namespace::{Namespace, NamespaceManager, NamespaceQuota, NamespaceUsage};
 pub use orchestrator::{
     Orchestrator, OrchestratorConfig, DeploymentSpec, PortSpec, HealthCheckSpec, 
     ScalingPolicySpec, ResourceSpec, DeploymentStatusResult, DeploymentStatusType, OrchestratorStats,
     Service as OrchestratorService, ServiceEndpoint as OrchestratorServiceEndpoint, 
     ServiceStatus as OrchestratorServiceStatus, HealthStatus as OrchestratorHealthStatus, 
-    Deployment as OrchestratorDeployment
+    Deployment as OrchestratorDeployment, ApplyAction, ConfigSpec, CronJobSpec, JobSpec, Manifest,
+    ResourceKey, ResourceKind, DeploymentRevision, MAX_REVISIONS, STATE_VERSION
 };
 pub use scheduler::*;
 pub use service_discovery::{
-    DnsRecord, DnsResolver, HealthCheck, HealthChecker, LoadBalancerConfig, LoadBalancingAlgorithm,
-    Protocol, Service, ServiceDiscovery, ServiceEndpoint, ServiceEvent, ServiceStatus,
+    DnsRecord, DnsResolver, HealthCheck, HealthChecker, LabelRequirement, LabelSelector,
+    LoadBalancerConfig, LoadBalancingAlgorithm, Protocol, Service, ServiceDiscovery,
+    ServiceDnsServer, ServiceEndpoint, ServiceEvent, ServiceStatus, DEFAULT_DNS_DOMAIN,
+    DEFAULT_DNS_TTL,
+};
+pub use sync::{
+    ResourceResult, SyncConfig, SyncController, SyncParseError, SyncSource, SyncStatus,
+    SYNC_OWNER_LABEL,
 };
@@ -1,15 +1,24 @@
 use anyhow::Result;
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 
-use crate::service_discovery::{LoadBalancingAlgorithm, Protocol, ServiceEndpoint};
+use crate::health_monitor::{CircuitBreaker, CircuitState};
+use crate::service_discovery::{HealthStatus, LoadBalancingAlgorithm, Protocol, ServiceEndpoint};
+use polis_core::{Clock, PolisError, Result as PolisResult, SystemClock};
+use polis_monitor::LoadBalancerCounters;
 
-/// Load balancer for distributing traffic across service endpoints
+/// Load balancer for distributing traffic across service endpoints. Clones
+/// share the endpoints, their state and the statistics.
+#[derive(Clone)]
 pub struct LoadBalancer {
     algorithm: LoadBalancingAlgorithm,
     endpoints: Arc<RwLock<Vec<ServiceEndpoint>>>,
@@ -18,6 +27,290 @@ pub struct LoadBalancer {
     last_used: Arc<RwLock<HashMap<String, Instant>>>,
     sticky_sessions: Arc<RwLock<HashMap<String, String>>>, // session_id -> endpoint_id
     health_checker: Arc<HealthChecker>,
+    stats: Arc<RwLock<EndpointStatsTracker>>,
+    default_latency: Duration,
+    ewma_alpha: f64,
+    max_connections: Option<u32>,
+    outliers: Arc<RwLock<HashMap<String, OutlierState>>>,
+    ejection_threshold: u32,
+    ejection_backoff: Duration,
+    max_ejection_percent: u32,
+    hash_ring: Arc<RwLock<ConsistentHashRing>>,
+    hash_key: HashKey,
+    /// Named endpoint pools that routing rules send requests to
+    pools: Arc<RwLock<HashMap<String, Vec<ServiceEndpoint>>>>,
+    /// Sorted by descending priority
+    routing_rules: Arc<RwLock<Vec<RoutingRule>>>,
+    client: reqwest::Client,
+    connect_timeout: Duration,
+    request_timeout: Duration,
+    breakers: Arc<RwLock<HashMap<String, CircuitBreaker>>>,
+    circuit_open_threshold: u32,
+    circuit_reset_timeout: Duration,
+    /// Share of failed requests that opens a circuit, as `(rate, window)`
+    circuit_error_rate: Option<(f64, u32)>,
+    clock: Arc<dyn Clock>,
+    retry_policy: Option<RetryPolicy>,
+    /// Endpoints that get no new requests until they are removed
+    draining: Arc<RwLock<HashSet<String>>>,
+    /// Endpoints outside the primary pool that take a share of its traffic
+    canaries: Arc<RwLock<Vec<Canary>>>,
+    tls_config: TlsConfig,
+    tls: Option<UpstreamTls>,
+    /// Clients of endpoints with TLS settings of their own
+    endpoint_clients: Arc<RwLock<HashMap<String, EndpointClient>>>,
+}
+
+#[derive(Debug, Clone)]
+struct Canary {
+    endpoint: ServiceEndpoint,
+    /// Share of the primary traffic, in percent
+    weight_percent: u8,
+}
+
+/// TLS settings for connections to HTTPS endpoints
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TlsConfig {
+    /// PEM file with the certificates to trust besides the system ones
+    pub ca_bundle: Option<PathBuf>,
+    /// PEM certificate and private key presented to the endpoint
+    pub client_cert: Option<(PathBuf, PathBuf)>,
+    /// Accept any certificate; only meant for development
+    pub insecure_skip_verify: bool,
+    /// Server name sent and verified instead of the endpoint's address
+    pub sni_override: Option<String>,
+}
+
+/// `TlsConfig` with its files read and parsed
+#[derive(Clone)]
+struct UpstreamTls {
+    ca_certificates: Vec<reqwest::Certificate>,
+    identity: Option<reqwest::Identity>,
+    insecure_skip_verify: bool,
+    sni_override: Option<String>,
+}
+
+#[derive(Clone)]
+struct EndpointClient {
+    client: reqwest::Client,
+    /// Host put in request URLs, the overridden server name if there is one
+    host: String,
+}
+
+/// Retries failed requests against other endpoints
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts after the first one
+    pub max_retries: u32,
+    pub retry_on: Vec<RetryCondition>,
+    /// Time allowed for the response headers of a single attempt
+    pub per_try_timeout: Option<Duration>,
+    /// Wait before the first retry, growing linearly with every further one
+    pub backoff: Duration,
+    /// Methods safe to send twice; requests with other methods or with a
+    /// body are never retried
+    pub methods: Vec<String>,
+}
+
+/// Outcome of an attempt that makes `RetryPolicy` try another endpoint
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RetryCondition {
+    /// The endpoint answered with this status code
+    Status(u16),
+    /// No connection could be established
+    ConnectFailure,
+    /// The attempt ran out of time
+    Timeout,
+}
+
+/// Sends matching requests to a named endpoint pool instead of the primary
+/// endpoints
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoutingRule {
+    /// Matches this path and everything below it
+    pub path_prefix: Option<String>,
+    /// Headers the request must carry; names are compared case-insensitively
+    pub header_matches: HashMap<String, String>,
+    pub endpoint_pool: String,
+    /// Rules with a higher priority are evaluated first
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// Default weight of the newest sample in the moving average of response times
+pub const DEFAULT_EWMA_ALPHA: f64 = 0.2;
+
+/// Latency assumed for endpoints that have not served a request yet
+pub const DEFAULT_ENDPOINT_LATENCY: Duration = Duration::from_millis(100);
+
+/// Consecutive failures after which an endpoint is ejected
+pub const DEFAULT_EJECTION_THRESHOLD: u32 = 5;
+
+/// How long an endpoint stays ejected the first time before it is re-probed
+pub const DEFAULT_EJECTION_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Share of the endpoints that may be ejected at the same time
+pub const DEFAULT_MAX_EJECTION_PERCENT: u32 = 50;
+
+/// Repeated ejections back off up to this multiple of the base backoff
+const MAX_EJECTION_BACKOFF_MULTIPLIER: u32 = 10;
+
+/// Time allowed to establish a connection to an endpoint
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time allowed for a whole forwarded request, response body included
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Consecutive failed requests after which an endpoint's circuit opens
+pub const DEFAULT_CIRCUIT_OPEN_THRESHOLD: u32 = 5;
+
+/// How long an open circuit refuses requests before letting a probe through
+pub const DEFAULT_CIRCUIT_RESET_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a draining endpoint's connections are checked
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Endpoint metadata keys starting with this configure the endpoint's TLS
+/// settings, see `TlsConfig::for_endpoint`
+pub const TLS_METADATA_PREFIX: &str = "tls.";
+
+/// Points each endpoint gets on the consistent hash ring
+pub const DEFAULT_VIRTUAL_NODES: u32 = 160;
+
+/// Request attribute hashed by `LoadBalancingAlgorithm::ConsistentHash`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum HashKey {
+    #[default]
+    Path,
+    /// Value of a header; requests without it fall back to the path
+    Header(String),
+    /// Client address; requests without one fall back to the path
+    ClientIp,
+}
+
+impl HashKey {
+    fn key_for(&self, request: &LoadBalancerRequest) -> String {
+        let key = match self {
+            HashKey::Path => None,
+            HashKey::Header(name) => request
+                .headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone()),
+            HashKey::ClientIp => request.client_ip.map(|ip| ip.to_string()),
+        };
+        key.unwrap_or_else(|| request.path.clone())
+    }
+}
+
+/// Failure tracking of one endpoint
+#[derive(Debug, Clone, Default)]
+struct OutlierState {
+    consecutive_failures: u32,
+    /// Ejections since the endpoint last recovered
+    ejections: u32,
+    ejected_until: Option<Instant>,
+}
+
+/// Ejection state of an endpoint, as reported by `LoadBalancer::endpoint_states`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointState {
+    pub endpoint_id: String,
+    /// Pool of the endpoint, `None` for the primary endpoints
+    pub pool: Option<String>,
+    pub health_status: HealthStatus,
+    pub consecutive_failures: u32,
+    pub ejected: bool,
+    /// Ejections since the endpoint last recovered
+    pub ejections: u32,
+    /// Time left before an ejected endpoint is probed again
+    pub ejected_for: Option<Duration>,
+}
+
+/// Request counters of one endpoint, or of all of them
+#[derive(Debug, Clone, Default)]
+struct RequestCounters {
+    requests: u64,
+    /// 2xx responses
+    successful: u64,
+    /// 5xx responses and requests that got no response
+    failed: u64,
+    total_response_time: Duration,
+    ewma_response_time: Option<Duration>,
+    /// Like the EWMA, but jumps straight to any slower sample
+    peak_ewma_response_time: Option<Duration>,
+    last_error: Option<String>,
+}
+
+impl RequestCounters {
+    fn record(&mut self, status_code: u16, response_time: Duration, error: Option<&str>) {
+        self.requests += 1;
+        if (200..300).contains(&status_code) {
+            self.successful += 1;
+        } else if status_code >= 500 || error.is_some() {
+            self.failed += 1;
+            self.last_error = Some(match error {
+                Some(error) => error.to_string(),
+                None => format!("HTTP {}", status_code),
+            });
+        }
+        self.total_response_time += response_time;
+    }
+
+    /// Fold `response_time` into the moving averages, weighting it by `alpha`
+    fn observe(&mut self, response_time: Duration, alpha: f64) {
+        self.ewma_response_time = Some(match self.ewma_response_time {
+            Some(average) => average.mul_f64(1.0 - alpha) + response_time.mul_f64(alpha),
+            None => response_time,
+        });
+        self.peak_ewma_response_time = Some(match self.peak_ewma_response_time {
+            Some(peak) if response_time < peak => {
+                peak.mul_f64(1.0 - alpha) + response_time.mul_f64(alpha)
+            }
+            _ => response_time,
+        });
+    }
+
+    fn average_response_time(&self) -> Duration {
+        if self.requests == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos((self.total_response_time.as_nanos() / self.requests as u128) as u64)
+    }
+}
+
+/// Request statistics recorded for every forwarded request
+#[derive(Debug, Default)]
+struct EndpointStatsTracker {
+    endpoints: HashMap<String, RequestCounters>,
+    totals: RequestCounters,
+    retries: u64,
+    canary_requests: u64,
+    primary_requests: u64,
+}
+
+impl EndpointStatsTracker {
+    fn record(
+        &mut self,
+        endpoint_id: &str,
+        status_code: u16,
+        response_time: Duration,
+        error: Option<&str>,
+    ) {
+        self.endpoints
+            .entry(endpoint_id.to_string())
+            .or_default()
+            .record(status_code, response_time, error);
+        self.totals.record(status_code, response_time, error);
+    }
+
+    fn observe(&mut self, endpoint_id: &str, response_time: Duration, alpha: f64) {
+        self.endpoints
+            .entry(endpoint_id.to_string())
+            .or_default()
+            .observe(response_time, alpha);
+        self.totals.observe(response_time, alpha);
+    }
 }
 
 /// Load balancer statistics
@@ -27,6 +320,18 @@ pub struct LoadBalancerStats {
     pub successful_requests: u64,
     pub failed_requests: u64,
     pub average_response_time: Duration,
+    /// Endpoints whose circuit is open or half-open
+    #[serde(default)]
+    pub open_circuits: u32,
+    /// Attempts repeated by the retry policy
+    #[serde(default)]
+    pub retried_requests: u64,
+    /// Requests sent to canaries
+    #[serde(default)]
+    pub canary_requests: u64,
+    /// Requests sent to any other endpoint
+    #[serde(default)]
+    pub primary_requests: u64,
     pub endpoint_stats: HashMap<String, EndpointStats>,
 }
 
@@ -38,7 +343,20 @@ pub struct EndpointStats {
     pub successful_requests: u64,
     pub failed_requests: u64,
     pub average_response_time: Duration,
+    pub total_response_time: Duration,
+    /// Exponentially weighted moving average, favouring recent requests
+    pub ewma_response_time: Duration,
+    /// `ewma_response_time` in nanoseconds, for consumers without a duration type
+    #[serde(default)]
+    pub ewma_response_time_ns: u64,
+    /// Error of the last failed request
+    pub last_error: Option<String>,
     pub active_connections: u32,
+    #[serde(default)]
+    pub circuit_state: CircuitState,
+    /// Waiting for its connections to finish before it is removed
+    #[serde(default)]
+    pub draining: bool,
     #[serde(skip)]
     pub last_used: Option<Instant>,
 }
@@ -51,6 +369,9 @@ pub struct LoadBalancerRequest {
     pub headers: HashMap<String, String>,
     pub path: String,
     pub method: String,
+    /// Streamed to the endpoint as it is read
+    #[serde(skip)]
+    pub body: Option<Body>,
 }
 
 /// Load balancer response
@@ -58,10 +379,27 @@ pub struct LoadBalancerRequest {
 pub struct LoadBalancerResponse {
     pub endpoint: ServiceEndpoint,
     pub status_code: u16,
+    /// Time until the endpoint's response headers arrived
     pub response_time: Duration,
     pub error: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Streamed from the endpoint as it is read
+    #[serde(skip)]
+    pub body: Body,
+    /// Endpoints the request was sent to, retries included
+    #[serde(default)]
+    pub attempts: u32,
+    /// Whether an open circuit kept the request from an endpoint
+    #[serde(default)]
+    pub short_circuited: bool,
 }
 
+/// Request or response body passed through without buffering. Clones share
+/// the same stream, which can only be consumed once.
+#[derive(Clone, Default)]
+pub struct Body(Arc<std::sync::Mutex<Option<BoxStream<'static, std::io::Result<Bytes>>>>>);
+
 /// Health checker for load balancer
 pub struct HealthChecker {
     client: reqwest::Client,
@@ -92,36 +430,605 @@ impl LoadBalancer {
             last_used: Arc::new(RwLock::new(HashMap::new())),
             sticky_sessions: Arc::new(RwLock::new(HashMap::new())),
             health_checker: Arc::new(HealthChecker::new()),
+            stats: Arc::new(RwLock::new(EndpointStatsTracker::default())),
+            default_latency: DEFAULT_ENDPOINT_LATENCY,
+            ewma_alpha: DEFAULT_EWMA_ALPHA,
+            max_connections: None,
+            outliers: Arc::new(RwLock::new(HashMap::new())),
+            ejection_threshold: DEFAULT_EJECTION_THRESHOLD,
+            ejection_backoff: DEFAULT_EJECTION_BACKOFF,
+            max_ejection_percent: DEFAULT_MAX_EJECTION_PERCENT,
+            hash_ring: Arc::new(RwLock::new(ConsistentHashRing::new(
+                &[],
+                DEFAULT_VIRTUAL_NODES,
+            ))),
+            hash_key: HashKey::default(),
+            pools: Arc::new(RwLock::new(HashMap::new())),
+            routing_rules: Arc::new(RwLock::new(Vec::new())),
+            client: client_builder(DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT, None)
+                .build()
+                .unwrap_or_default(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            breakers: Arc::new(RwLock::new(HashMap::new())),
+            circuit_open_threshold: DEFAULT_CIRCUIT_OPEN_THRESHOLD,
+            circuit_reset_timeout: DEFAULT_CIRCUIT_RESET_TIMEOUT,
+            circuit_error_rate: None,
+            clock: Arc::new(SystemClock),
+            retry_policy: None,
+            draining: Arc::new(RwLock::new(HashSet::new())),
+            canaries: Arc::new(RwLock::new(Vec::new())),
+            tls_config: TlsConfig::default(),
+            tls: None,
+            endpoint_clients: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Connect to HTTPS endpoints with `tls`. Certificate files are read
+    /// here, so missing or invalid ones are reported right away.
+    pub fn with_tls(mut self, tls_config: TlsConfig) -> PolisResult<Self> {
+        let tls = tls_config.load()?;
+        self.client = client_builder(self.connect_timeout, self.request_timeout, Some(&tls))
+            .build()
+            .map_err(|e| PolisError::Config(format!("Invalid TLS configuration: {}", e)))?;
+        self.tls_config = tls_config;
+        self.tls = Some(tls);
+        Ok(self)
+    }
+
+    /// Retry failed idempotent requests as `policy` describes
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Also open an endpoint's circuit once at least `rate` of its last
+    /// `window` requests failed, even if they did not fail in a row
+    pub fn with_circuit_error_rate(mut self, rate: f64, window: u32) -> Self {
+        self.circuit_error_rate = Some((rate, window));
+        self
+    }
+
+    /// Open an endpoint's circuit after this many consecutive 5xx responses
+    /// or connection errors. Unlike ejection, circuits open regardless of how
+    /// many other endpoints are already refused.
+    pub fn with_circuit_open_threshold(mut self, threshold: u32) -> Self {
+        self.circuit_open_threshold = threshold.max(1);
+        self
+    }
+
+    /// Refuse requests to an open circuit this long, then let one probe
+    /// request through to decide whether to close it
+    pub fn with_circuit_reset_timeout(mut self, reset_timeout: Duration) -> Self {
+        self.circuit_reset_timeout = reset_timeout;
+        self
+    }
+
+    /// Clock that circuit breaker timeouts are measured with
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Circuit state of an endpoint; endpoints that never failed are closed
+    pub async fn circuit_state(&self, endpoint_id: &str) -> CircuitState {
+        let breakers = self.breakers.read().await;
+        match breakers.get(endpoint_id) {
+            Some(breaker) => breaker.state(),
+            None => CircuitState::Closed,
         }
     }
 
+    /// Limit connecting to an endpoint to `connect` and a whole forwarded
+    /// request, including streaming the response body, to `total`
+    pub fn with_timeouts(mut self, connect: Duration, total: Duration) -> Self {
+        self.client = client_builder(connect, total, self.tls.as_ref())
+            .build()
+            .unwrap_or_default();
+        self.connect_timeout = connect;
+        self.request_timeout = total;
+        self
+    }
+
+    /// Request attribute that `ConsistentHash` maps to an endpoint
+    pub fn with_hash_key(mut self, hash_key: HashKey) -> Self {
+        self.hash_key = hash_key;
+        self
+    }
+
+    /// Points per endpoint on the consistent hash ring; more points spread
+    /// keys more evenly
+    pub fn with_virtual_nodes(mut self, virtual_nodes: u32) -> Self {
+        self.hash_ring = Arc::new(RwLock::new(ConsistentHashRing::new(
+            &[],
+            virtual_nodes.max(1),
+        )));
+        self
+    }
+
+    /// Eject an endpoint after this many consecutive failed requests or
+    /// health checks
+    pub fn with_ejection_threshold(mut self, threshold: u32) -> Self {
+        self.ejection_threshold = threshold.max(1);
+        self
+    }
+
+    /// Keep an ejected endpoint out of the pool this long before probing it.
+    /// Every failed probe ejects it again for one more multiple of this.
+    pub fn with_ejection_backoff(mut self, backoff: Duration) -> Self {
+        self.ejection_backoff = backoff;
+        self
+    }
+
+    /// Never eject more than this percentage of the endpoints, nor all of them
+    pub fn with_max_ejection_percent(mut self, percent: u32) -> Self {
+        self.max_ejection_percent = percent.min(100);
+        self
+    }
+
+    /// Latency assumed by the latency-aware algorithms for endpoints without
+    /// samples. A low value makes new endpoints get tried quickly, a high
+    /// one keeps traffic on endpoints already known to be fast.
+    pub fn with_default_latency(mut self, latency: Duration) -> Self {
+        self.default_latency = latency;
+        self
+    }
+
+    /// Weight of the newest response time in the moving averages that
+    /// `LeastResponseTime` and `PeakEwma` select by. Higher values react
+    /// faster to changes, lower ones smooth out single slow requests.
+    pub fn with_ewma_alpha(mut self, alpha: f64) -> Self {
+        self.ewma_alpha = alpha.clamp(f64::EPSILON, 1.0);
+        self
+    }
+
+    /// Fold the response time of a request to `endpoint_id` into its moving
+    /// averages. Proxied requests are recorded automatically.
+    pub async fn record_response(&self, endpoint_id: &str, duration: Duration) {
+        let mut stats = self.stats.write().await;
+        stats.observe(endpoint_id, duration, self.ewma_alpha);
+    }
+
+    /// Skip endpoints with this many active connections in
+    /// `LeastResponseTime`, unless every endpoint has reached it
+    pub fn with_max_connections(mut self, max_connections: u32) -> Self {
+        self.max_connections = Some(max_connections);
+        self
+    }
+
+    /// Add an endpoint. Endpoints whose TLS settings cannot be loaded are
+    /// left out with a warning; `try_add_endpoint` returns the error instead.
     pub async fn add_endpoint(&self, endpoint: ServiceEndpoint) {
+        let endpoint_id = endpoint.id.clone();
+        if let Err(e) = self.try_add_endpoint(endpoint).await {
+            tracing::warn!("Endpoint {} not added: {}", endpoint_id, e);
+        }
+    }
+
+    /// Add an endpoint, failing if its TLS settings cannot be loaded
+    pub async fn try_add_endpoint(&self, endpoint: ServiceEndpoint) -> PolisResult<()> {
+        self.prepare_client(&endpoint).await?;
         let mut endpoints = self.endpoints.write().await;
         endpoints.push(endpoint);
+        let pools = self.pools.read().await;
+        self.rebuild_hash_ring(&endpoints, &pools).await;
+        Ok(())
+    }
+
+    /// Build the client of an endpoint that needs TLS settings of its own:
+    /// one with `tls.*` metadata, or any endpoint when the server name is
+    /// overridden
+    async fn prepare_client(&self, endpoint: &ServiceEndpoint) -> PolisResult<()> {
+        let tls_config = self.tls_config.for_endpoint(endpoint)?;
+        let has_metadata = endpoint
+            .metadata
+            .keys()
+            .any(|key| key.starts_with(TLS_METADATA_PREFIX));
+        if !has_metadata && tls_config.sni_override.is_none() {
+            return Ok(());
+        }
+
+        let tls = tls_config.load()?;
+        let mut builder = client_builder(self.connect_timeout, self.request_timeout, Some(&tls));
+        let host = match &tls.sni_override {
+            Some(server_name) => {
+                let ip: IpAddr = endpoint.address.parse().map_err(|_| {
+                    PolisError::Config(format!(
+                        "Endpoint {} needs an IP address to override its TLS server name",
+                        endpoint.id
+                    ))
+                })?;
+                builder = builder.resolve(server_name, SocketAddr::new(ip, endpoint.port));
+                server_name.clone()
+            }
+            None => endpoint.address.clone(),
+        };
+        let client = builder.build().map_err(|e| {
+            PolisError::Config(format!(
+                "Invalid TLS configuration for endpoint {}: {}",
+                endpoint.id, e
+            ))
+        })?;
+
+        let mut endpoint_clients = self.endpoint_clients.write().await;
+        endpoint_clients.insert(endpoint.id.clone(), EndpointClient { client, host });
+        Ok(())
+    }
+
+    /// Client to send requests for `endpoint` with, and the host to address
+    /// it by
+    async fn client_for(&self, endpoint: &ServiceEndpoint) -> (reqwest::Client, String) {
+        let endpoint_clients = self.endpoint_clients.read().await;
+        match endpoint_clients.get(&endpoint.id) {
+            Some(endpoint_client) => (endpoint_client.client.clone(), endpoint_client.host.clone()),
+            None => (self.client.clone(), endpoint.address.clone()),
+        }
     }
 
     pub async fn remove_endpoint(&self, endpoint_id: &str) {
+        let mut outliers = self.outliers.write().await;
         let mut endpoints = self.endpoints.write().await;
         endpoints.retain(|ep| ep.id != endpoint_id);
+        outliers.remove(endpoint_id);
+        let pools = self.pools.read().await;
+        self.rebuild_hash_ring(&endpoints, &pools).await;
+        self.breakers.write().await.remove(endpoint_id);
+        self.draining.write().await.remove(endpoint_id);
+        self.endpoint_clients.write().await.remove(endpoint_id);
+        self.canaries
+            .write()
+            .await
+            .retain(|canary| canary.endpoint.id != endpoint_id);
+    }
+
+    /// Stop routing new requests to an endpoint and remove it once its
+    /// active connections have finished, or when `grace_period` expires on
+    /// the load balancer's clock. The returned handle resolves after the
+    /// endpoint has been removed, or as soon as it stops draining because it
+    /// was removed in the meantime.
+    pub async fn drain_endpoint(
+        &self,
+        endpoint_id: &str,
+        grace_period: Duration,
+    ) -> JoinHandle<()> {
+        self.draining.write().await.insert(endpoint_id.to_string());
+
+        let lb = self.clone();
+        let endpoint_id = endpoint_id.to_string();
+        tokio::spawn(async move {
+            // No deadline when the grace period does not fit in the clock
+            let deadline = chrono::Duration::from_std(grace_period)
+                .ok()
+                .and_then(|grace_period| lb.clock.now().checked_add_signed(grace_period));
+            loop {
+                if !lb.draining.read().await.contains(&endpoint_id) {
+                    return;
+                }
+                let active = {
+                    let connection_counts = lb.connection_counts.read().await;
+                    connection_counts.get(&endpoint_id).copied().unwrap_or(0)
+                };
+                let remaining = deadline
+                    .map(|deadline| (deadline - lb.clock.now()).to_std().unwrap_or_default());
+                if active == 0 || remaining == Some(Duration::ZERO) {
+                    break;
+                }
+                tokio::time::sleep(remaining.map_or(DRAIN_POLL_INTERVAL, |remaining| {
+                    DRAIN_POLL_INTERVAL.min(remaining)
+                }))
+                .await;
+            }
+            // An endpoint removed and added back while draining is kept
+            if lb.draining.read().await.contains(&endpoint_id) {
+                lb.remove_endpoint(&endpoint_id).await;
+            }
+        })
+    }
+
+    /// Add an endpoint to a named pool, creating the pool if needed
+    pub async fn add_endpoint_to_pool(&self, pool: &str, endpoint: ServiceEndpoint) {
+        if let Err(e) = self.prepare_client(&endpoint).await {
+            tracing::warn!("Endpoint {} not added to pool {}: {}", endpoint.id, pool, e);
+            return;
+        }
+        let endpoints = self.endpoints.read().await;
+        let mut pools = self.pools.write().await;
+        pools.entry(pool.to_string()).or_default().push(endpoint);
+        self.rebuild_hash_ring(&endpoints, &pools).await;
+    }
+
+    /// Send `weight_percent` of the requests for the primary endpoints to
+    /// `endpoint`, whatever the algorithm would pick. Adding a canary again
+    /// changes its weight.
+    pub async fn add_canary(&self, endpoint: ServiceEndpoint, weight_percent: u8) {
+        if let Err(e) = self.prepare_client(&endpoint).await {
+            tracing::warn!("Canary {} not added: {}", endpoint.id, e);
+            return;
+        }
+        let weight_percent = weight_percent.min(100);
+        let mut canaries = self.canaries.write().await;
+        match canaries
+            .iter_mut()
+            .find(|canary| canary.endpoint.id == endpoint.id)
+        {
+            Some(canary) => {
+                canary.endpoint = endpoint;
+                canary.weight_percent = weight_percent;
+            }
+            None => canaries.push(Canary {
+                endpoint,
+                weight_percent,
+            }),
+        }
+    }
+
+    /// Move a canary that takes all of the traffic into the primary pool
+    pub async fn promote_canary(&self, endpoint_id: &str) -> PolisResult<()> {
+        let mut endpoints = self.endpoints.write().await;
+        let pools = self.pools.read().await;
+        let mut canaries = self.canaries.write().await;
+        let position = canaries
+            .iter()
+            .position(|canary| canary.endpoint.id == endpoint_id)
+            .ok_or_else(|| PolisError::Config(format!("{} is not a canary", endpoint_id)))?;
+        if canaries[position].weight_percent < 100 {
+            return Err(PolisError::Config(format!(
+                "Canary {} takes {}% of the traffic; only canaries at 100% can be promoted",
+                endpoint_id, canaries[position].weight_percent
+            )));
+        }
+
+        let canary = canaries.remove(position);
+        endpoints.push(canary.endpoint);
+        self.rebuild_hash_ring(&endpoints, &pools).await;
+        Ok(())
+    }
+
+    /// Route requests matching `rule` to its pool. Rules are evaluated by
+    /// descending priority, then in the order they were added; requests
+    /// matching none go to the primary endpoints.
+    pub async fn add_routing_rule(&self, rule: RoutingRule) {
+        let mut routing_rules = self.routing_rules.write().await;
+        let position = routing_rules
+            .iter()
+            .position(|existing| existing.priority < rule.priority)
+            .unwrap_or(routing_rules.len());
+        routing_rules.insert(position, rule);
+    }
+
+    /// Pool the first matching routing rule sends `request` to
+    async fn route(&self, request: &LoadBalancerRequest) -> Option<String> {
+        let routing_rules = self.routing_rules.read().await;
+        routing_rules
+            .iter()
+            .find(|rule| rule.matches(request))
+            .map(|rule| rule.endpoint_pool.clone())
     }
 
+    /// Replace the endpoints. Endpoints that are still ejected stay out of
+    /// the pool until they are re-probed.
     pub async fn update_endpoints(&self, new_endpoints: Vec<ServiceEndpoint>) {
+        let mut prepared = Vec::with_capacity(new_endpoints.len());
+        for endpoint in new_endpoints {
+            match self.prepare_client(&endpoint).await {
+                Ok(()) => prepared.push(endpoint),
+                Err(e) => tracing::warn!("Endpoint {} not added: {}", endpoint.id, e),
+            }
+        }
+
+        let mut outliers = self.outliers.write().await;
         let mut endpoints = self.endpoints.write().await;
-        *endpoints = new_endpoints;
+        *endpoints = prepared;
+
+        let pools = self.pools.read().await;
+        let known = |id: &String| {
+            endpoints
+                .iter()
+                .chain(pools.values().flatten())
+                .any(|ep| ep.id == *id)
+        };
+        outliers.retain(|id, _| known(id));
+        self.breakers.write().await.retain(|id, _| known(id));
+        self.endpoint_clients
+            .write()
+            .await
+            .retain(|id, _| known(id));
+        self.rebuild_hash_ring(&endpoints, &pools).await;
+        for endpoint in endpoints.iter_mut() {
+            if matches!(outliers.get(&endpoint.id), Some(state) if state.ejected_until.is_some()) {
+                endpoint.health_status = HealthStatus::Unhealthy;
+            }
+        }
+    }
+
+    /// The ring holds every endpoint, healthy or not, so that ejecting one
+    /// only moves the keys it owned
+    async fn rebuild_hash_ring(
+        &self,
+        endpoints: &[ServiceEndpoint],
+        pools: &HashMap<String, Vec<ServiceEndpoint>>,
+    ) {
+        let mut hash_ring = self.hash_ring.write().await;
+        let endpoints: Vec<&ServiceEndpoint> =
+            endpoints.iter().chain(pools.values().flatten()).collect();
+        *hash_ring = ConsistentHashRing::new(&endpoints, hash_ring.virtual_nodes);
     }
 
+    /// Failure tracking and ejection state of every endpoint
+    pub async fn endpoint_states(&self) -> Vec<EndpointState> {
+        let outliers = self.outliers.read().await;
+        let endpoints = self.endpoints.read().await;
+        let pools = self.pools.read().await;
+        let now = Instant::now();
+
+        let mut pool_names: Vec<&String> = pools.keys().collect();
+        pool_names.sort();
+        let pooled = pool_names.into_iter().flat_map(|pool| {
+            pools[pool]
+                .iter()
+                .map(move |endpoint| (Some(pool.clone()), endpoint))
+        });
+
+        endpoints
+            .iter()
+            .map(|endpoint| (None, endpoint))
+            .chain(pooled)
+            .map(|(pool, endpoint)| {
+                let state = outliers.get(&endpoint.id).cloned().unwrap_or_default();
+                EndpointState {
+                    endpoint_id: endpoint.id.clone(),
+                    pool,
+                    health_status: endpoint.health_status.clone(),
+                    consecutive_failures: state.consecutive_failures,
+                    ejected: state.ejected_until.is_some(),
+                    ejections: state.ejections,
+                    ejected_for: state
+                        .ejected_until
+                        .map(|until| until.saturating_duration_since(now)),
+                }
+            })
+            .collect()
+    }
+
+    /// Count a request or health check outcome of an endpoint in the pool,
+    /// ejecting it once it failed `ejection_threshold` times in a row
+    async fn record_outcome(&self, endpoint_id: &str, failed: bool) {
+        let mut outliers = self.outliers.write().await;
+        let state = outliers.entry(endpoint_id.to_string()).or_default();
+        if state.ejected_until.is_some() {
+            // Only the re-probe decides about ejected endpoints
+            return;
+        }
+        if !failed {
+            state.consecutive_failures = 0;
+            return;
+        }
+        state.consecutive_failures += 1;
+        if state.consecutive_failures < self.ejection_threshold {
+            return;
+        }
+
+        // The ejection limit applies within the endpoint's own pool
+        let mut endpoints = self.endpoints.write().await;
+        let mut pools = self.pools.write().await;
+        let Some(pool) = pool_containing(&mut endpoints, &mut pools, endpoint_id) else {
+            return;
+        };
+        let ejected = pool
+            .iter()
+            .filter(
+                |ep| matches!(outliers.get(&ep.id), Some(state) if state.ejected_until.is_some()),
+            )
+            .count();
+        let max_ejected = (pool.len() * self.max_ejection_percent as usize / 100)
+            .min(pool.len().saturating_sub(1));
+        if ejected < max_ejected {
+            if let Some(state) = outliers.get_mut(endpoint_id) {
+                self.eject(state, pool, endpoint_id);
+            }
+        }
+    }
+
+    /// Record the re-probe of an ejected endpoint
+    async fn finish_probe(&self, endpoint_id: &str, healthy: bool) {
+        let mut outliers = self.outliers.write().await;
+        let mut endpoints = self.endpoints.write().await;
+        let mut pools = self.pools.write().await;
+        let (Some(state), Some(pool)) = (
+            outliers.get_mut(endpoint_id),
+            pool_containing(&mut endpoints, &mut pools, endpoint_id),
+        ) else {
+            return;
+        };
+
+        if healthy {
+            *state = OutlierState::default();
+            if let Some(endpoint) = pool.iter_mut().find(|ep| ep.id == endpoint_id) {
+                endpoint.health_status = HealthStatus::Healthy;
+            }
+        } else {
+            self.eject(state, pool, endpoint_id);
+        }
+    }
+
+    fn eject(
+        &self,
+        state: &mut OutlierState,
+        endpoints: &mut [ServiceEndpoint],
+        endpoint_id: &str,
+    ) {
+        state.ejections += 1;
+        let backoff = self.ejection_backoff * state.ejections.min(MAX_EJECTION_BACKOFF_MULTIPLIER);
+        state.ejected_until = Some(Instant::now() + backoff);
+        if let Some(endpoint) = endpoints.iter_mut().find(|ep| ep.id == endpoint_id) {
+            endpoint.health_status = HealthStatus::Unhealthy;
+        }
+    }
+
+    /// Pick a healthy endpoint whose circuit lets requests through
     pub async fn select_endpoint(
         &self,
         request: &LoadBalancerRequest,
     ) -> Result<Option<ServiceEndpoint>> {
+        let (selected, _) = self.select_available(request, &HashSet::new()).await?;
+        Ok(selected)
+    }
+
+    /// Select among the healthy endpoints not in `excluded`, also reporting
+    /// whether any of them was passed over because its circuit is open
+    async fn select_available(
+        &self,
+        request: &LoadBalancerRequest,
+        excluded: &HashSet<String>,
+    ) -> Result<(Option<ServiceEndpoint>, bool)> {
+        let pool = self.route(request).await;
         let endpoints = self.endpoints.read().await;
-        let healthy_endpoints: Vec<&ServiceEndpoint> = endpoints
+        let pools = self.pools.read().await;
+        let breakers = self.breakers.read().await;
+        let draining = self.draining.read().await;
+        let now = self.clock.now();
+        let usable = |ep: &ServiceEndpoint| {
+            ep.health_status == HealthStatus::Healthy
+                && !excluded.contains(&ep.id)
+                && !draining.contains(&ep.id)
+        };
+        let closed = |ep: &ServiceEndpoint| match breakers.get(&ep.id) {
+            Some(breaker) => breaker.is_available(now),
+            None => true,
+        };
+
+        // Canaries take their share of the primary traffic first; the share
+        // of one that cannot take the request goes to the primary endpoints
+        if pool.is_none() {
+            let canaries = self.canaries.read().await;
+            use rand::Rng;
+            let roll = rand::rng().random_range(0..100u32);
+            let mut threshold = 0;
+            for canary in canaries.iter() {
+                threshold += canary.weight_percent as u32;
+                if roll < threshold {
+                    if usable(&canary.endpoint) && closed(&canary.endpoint) {
+                        return Ok((Some(canary.endpoint.clone()), false));
+                    }
+                    break;
+                }
+            }
+        }
+
+        let candidates: Vec<&ServiceEndpoint> =
+            routed_endpoints(&endpoints, &pools, pool.as_deref())
+                .iter()
+                .filter(|&ep| usable(ep))
+                .collect();
+        let healthy_endpoints: Vec<&ServiceEndpoint> = candidates
             .iter()
-            .filter(|ep| ep.health_status == crate::service_discovery::HealthStatus::Healthy)
+            .copied()
+            .filter(|&ep| closed(ep))
             .collect();
+        let short_circuited = healthy_endpoints.len() < candidates.len();
 
         if healthy_endpoints.is_empty() {
-            return Ok(None);
+            return Ok((None, short_circuited));
         }
 
         let selected = match self.algorithm {
@@ -137,12 +1044,16 @@ impl LoadBalancer {
                 self.select_ip_hash(&healthy_endpoints, request.client_ip)
             }
             LoadBalancingAlgorithm::ConsistentHash => {
-                self.select_consistent_hash(&healthy_endpoints, &request.path)
+                self.select_consistent_hash(&healthy_endpoints, &self.hash_key.key_for(request))
                     .await
             }
+            LoadBalancingAlgorithm::LeastResponseTime => {
+                self.select_least_response_time(&healthy_endpoints).await
+            }
+            LoadBalancingAlgorithm::PeakEwma => self.select_peak_ewma(&healthy_endpoints).await,
         };
 
-        Ok(selected.cloned())
+        Ok((selected.cloned(), short_circuited))
     }
 
     async fn select_round_robin<'a>(
@@ -211,6 +1122,57 @@ impl LoadBalancer {
         selected
     }
 
+    async fn select_least_response_time<'a>(
+        &self,
+        endpoints: &[&'a ServiceEndpoint],
+    ) -> Option<&'a ServiceEndpoint> {
+        let connection_counts = self.connection_counts.read().await;
+        let stats = self.stats.read().await;
+        let connections =
+            |endpoint: &ServiceEndpoint| connection_counts.get(&endpoint.id).copied().unwrap_or(0);
+
+        let below_cap: Vec<&'a ServiceEndpoint> = endpoints
+            .iter()
+            .copied()
+            .filter(|endpoint| match self.max_connections {
+                Some(max_connections) => connections(endpoint) < max_connections,
+                None => true,
+            })
+            .collect();
+        let candidates = if below_cap.is_empty() {
+            endpoints
+        } else {
+            &below_cap
+        };
+
+        candidates.iter().copied().min_by_key(|endpoint| {
+            let latency = stats
+                .endpoints
+                .get(&endpoint.id)
+                .and_then(|counters| counters.ewma_response_time)
+                .unwrap_or(self.default_latency);
+            (latency, connections(endpoint))
+        })
+    }
+
+    async fn select_peak_ewma<'a>(
+        &self,
+        endpoints: &[&'a ServiceEndpoint],
+    ) -> Option<&'a ServiceEndpoint> {
+        let connection_counts = self.connection_counts.read().await;
+        let stats = self.stats.read().await;
+
+        endpoints.iter().copied().min_by_key(|endpoint| {
+            let latency = stats
+                .endpoints
+                .get(&endpoint.id)
+                .and_then(|counters| counters.peak_ewma_response_time)
+                .unwrap_or(self.default_latency);
+            let connections = connection_counts.get(&endpoint.id).copied().unwrap_or(0);
+            latency * (connections + 1)
+        })
+    }
+
     fn select_random<'a>(&self, endpoints: &[&'a ServiceEndpoint]) -> Option<&'a ServiceEndpoint> {
         if endpoints.is_empty() {
             return None;
@@ -249,74 +1211,190 @@ impl LoadBalancer {
             return None;
         }
 
-        // Simplified consistent hash implementation
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        let mut hasher = DefaultHasher::new();
-        key.hash(&mut hasher);
-        let hash = hasher.finish();
-        let index = (hash as usize) % endpoints.len();
-        Some(endpoints[index])
+        // Walk the ring clockwise past endpoints that are out of the pool
+        let hash_ring = self.hash_ring.read().await;
+        let endpoint = hash_ring
+            .endpoint_ids(key)
+            .find_map(|id| endpoints.iter().copied().find(|ep| ep.id == id));
+        endpoint
     }
 
+    /// Forward `request` to an endpoint, retrying it on other endpoints if
+    /// the retry policy allows
     pub async fn handle_request(
         &self,
         request: LoadBalancerRequest,
     ) -> Result<LoadBalancerResponse> {
         let start_time = Instant::now();
+        let policy = self
+            .retry_policy
+            .as_ref()
+            .filter(|policy| policy.allows(&request));
+
+        let mut excluded = HashSet::new();
+        let mut short_circuited = false;
+        let mut attempts = 0;
+        let mut last_response = None;
+        loop {
+            let endpoint = match self
+                .next_endpoint(&request, attempts == 0, &mut excluded, &mut short_circuited)
+                .await?
+            {
+                Some(ep) => ep,
+                None => {
+                    if let Some(response) = last_response {
+                        return Ok(response);
+                    }
+                    let error = if short_circuited {
+                        "Circuit open for every healthy endpoint"
+                    } else {
+                        "No healthy endpoints available"
+                    };
+                    return Ok(LoadBalancerResponse {
+                        endpoint: ServiceEndpoint::new("".to_string(), 0, Protocol::Http),
+                        status_code: 503,
+                        response_time: start_time.elapsed(),
+                        error: Some(error.to_string()),
+                        headers: HashMap::new(),
+                        body: Body::empty(),
+                        attempts,
+                        short_circuited,
+                    });
+                }
+            };
 
-        // Check for sticky session
-        if let Some(session_id) = &request.session_id {
-            let sticky_sessions = self.sticky_sessions.read().await;
-            if let Some(endpoint_id) = sticky_sessions.get(session_id) {
-                let endpoints = self.endpoints.read().await;
-                if let Some(endpoint) = endpoints.iter().find(|ep| ep.id == *endpoint_id) {
-                    return self.forward_request(endpoint, request, start_time).await;
+            attempts += 1;
+            if attempts > 1 {
+                self.stats.write().await.retries += 1;
+            }
+            let per_try_timeout = policy.and_then(|policy| policy.per_try_timeout);
+            let (mut response, condition) = self
+                .forward_request(&endpoint, request.clone(), per_try_timeout)
+                .await?;
+            response.attempts = attempts;
+            response.short_circuited = short_circuited;
+
+            let retry = match (policy, condition) {
+                (Some(policy), Some(condition)) => {
+                    attempts <= policy.max_retries && policy.retry_on.contains(&condition)
                 }
+                _ => false,
+            };
+            if !retry {
+                return Ok(response);
+            }
+
+            excluded.insert(endpoint.id);
+            last_response = Some(response);
+            if let Some(policy) = policy {
+                tokio::time::sleep(policy.backoff * attempts).await;
             }
         }
+    }
 
-        // Select endpoint
-        let endpoint = match self.select_endpoint(&request).await? {
-            Some(ep) => ep,
-            None => {
-                return Ok(LoadBalancerResponse {
-                    endpoint: ServiceEndpoint::new("".to_string(), 0, Protocol::Http),
-                    status_code: 503,
-                    response_time: start_time.elapsed(),
-                    error: Some("No healthy endpoints available".to_string()),
-                });
+    /// Endpoint for the next attempt: the session's sticky endpoint on the
+    /// first one, otherwise a newly selected endpoint not tried before whose
+    /// circuit lets the request through
+    async fn next_endpoint(
+        &self,
+        request: &LoadBalancerRequest,
+        first_attempt: bool,
+        excluded: &mut HashSet<String>,
+        short_circuited: &mut bool,
+    ) -> Result<Option<ServiceEndpoint>> {
+        if first_attempt {
+            if let Some(endpoint) = self.sticky_endpoint(request).await {
+                if self.allow_request(&endpoint.id).await {
+                    return Ok(Some(endpoint));
+                }
+                *short_circuited = true;
+                excluded.insert(endpoint.id);
+            }
+        }
+
+        loop {
+            let (selected, skipped) = self.select_available(request, excluded).await?;
+            *short_circuited |= skipped;
+            let Some(endpoint) = selected else {
+                return Ok(None);
+            };
+            if !self.allow_request(&endpoint.id).await {
+                *short_circuited = true;
+                excluded.insert(endpoint.id);
+                continue;
             }
-        };
 
-        // Store sticky session
-        if let Some(session_id) = &request.session_id {
-            let mut sticky_sessions = self.sticky_sessions.write().await;
-            sticky_sessions.insert(session_id.clone(), endpoint.id.clone());
+            // Store sticky session
+            if let Some(session_id) = &request.session_id {
+                let mut sticky_sessions = self.sticky_sessions.write().await;
+                sticky_sessions.insert(session_id.clone(), endpoint.id.clone());
+            }
+            return Ok(Some(endpoint));
         }
+    }
 
-        // Update connection count
-        {
-            let mut connection_counts = self.connection_counts.write().await;
-            *connection_counts.entry(endpoint.id.clone()).or_insert(0) += 1;
+    async fn sticky_endpoint(&self, request: &LoadBalancerRequest) -> Option<ServiceEndpoint> {
+        let session_id = request.session_id.as_ref()?;
+        let pool = self.route(request).await;
+        let sticky_sessions = self.sticky_sessions.read().await;
+        let endpoints = self.endpoints.read().await;
+        let pools = self.pools.read().await;
+        let endpoint_id = sticky_sessions.get(session_id)?;
+        if self.draining.read().await.contains(endpoint_id) {
+            return None;
         }
+        routed_endpoints(&endpoints, &pools, pool.as_deref())
+            .iter()
+            .find(|ep| ep.id == *endpoint_id && ep.health_status == HealthStatus::Healthy)
+            .cloned()
+    }
 
-        // Update last used
-        {
-            let mut last_used = self.last_used.write().await;
-            last_used.insert(endpoint.id.clone(), Instant::now());
+    /// Ask the endpoint's circuit breaker to let a request through, moving an
+    /// open circuit whose reset timeout elapsed to half-open
+    async fn allow_request(&self, endpoint_id: &str) -> bool {
+        let mut breakers = self.breakers.write().await;
+        match breakers.get_mut(endpoint_id) {
+            Some(breaker) => breaker.allow_request(self.clock.now()),
+            None => true,
         }
+    }
 
-        self.forward_request(&endpoint, request, start_time).await
+    async fn record_circuit_outcome(&self, endpoint_id: &str, failed: bool) {
+        let mut breakers = self.breakers.write().await;
+        let breaker = breakers.entry(endpoint_id.to_string()).or_insert_with(|| {
+            let breaker =
+                CircuitBreaker::new(self.circuit_open_threshold, self.circuit_reset_timeout);
+            match self.circuit_error_rate {
+                Some((rate, window)) => breaker.with_error_rate(rate, window),
+                None => breaker,
+            }
+        });
+        if failed {
+            breaker.record_failure(self.clock.now());
+        } else {
+            breaker.record_success();
+        }
     }
 
+    /// Send one attempt of `request` to `endpoint`, along with the retry
+    /// condition its outcome matches
     async fn forward_request(
         &self,
         endpoint: &ServiceEndpoint,
         request: LoadBalancerRequest,
-        start_time: Instant,
-    ) -> Result<LoadBalancerResponse> {
+        per_try_timeout: Option<Duration>,
+    ) -> Result<(LoadBalancerResponse, Option<RetryCondition>)> {
+        let start_time = Instant::now();
+        {
+            let mut connection_counts = self.connection_counts.write().await;
+            *connection_counts.entry(endpoint.id.clone()).or_insert(0) += 1;
+        }
+        {
+            let mut last_used = self.last_used.write().await;
+            last_used.insert(endpoint.id.clone(), start_time);
+        }
+
+        let (client, host) = self.client_for(endpoint).await;
         let url = format!(
             "{}://{}:{}{}",
             match endpoint.protocol {
@@ -325,48 +1403,79 @@ impl LoadBalancer {
                 Protocol::Grpc => "http", // gRPC over HTTP
                 _ => "http",
             },
-            endpoint.address,
+            host,
             endpoint.port,
             request.path
         );
 
-        let client = reqwest::Client::new();
-        let response = client
-            .request(
-                match request.method.as_str() {
-                    "GET" => reqwest::Method::GET,
-                    "POST" => reqwest::Method::POST,
-                    "PUT" => reqwest::Method::PUT,
-                    "DELETE" => reqwest::Method::DELETE,
-                    _ => reqwest::Method::GET,
-                },
-                &url,
-            )
-            .headers({
-                let mut headers = reqwest::header::HeaderMap::new();
-                for (key, value) in &request.headers {
-                    if let (Ok(header_name), Ok(header_value)) = (
-                        key.parse::<reqwest::header::HeaderName>(),
-                        value.parse::<reqwest::header::HeaderValue>(),
-                    ) {
-                        headers.insert(header_name, header_value);
-                    }
-                }
-                headers
-            })
-            .timeout(Duration::from_secs(30))
-            .send()
-            .await;
+        let method = reqwest::Method::from_bytes(request.method.to_uppercase().as_bytes())
+            .unwrap_or(reqwest::Method::GET);
+        let mut builder = client
+            .request(method, &url)
+            .headers(forwarded_headers(&request));
+        if let Some(body) = request.body {
+            builder = builder.body(reqwest::Body::wrap_stream(body.into_stream()));
+        }
+        let response = match per_try_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, builder.send()).await,
+            None => Ok(builder.send().await),
+        };
 
         let response_time = start_time.elapsed();
-        let status_code = response
-            .as_ref()
-            .map(|r| r.status().as_u16())
-            .unwrap_or(500);
-        let error = if response.is_err() {
-            Some(response.err().unwrap().to_string())
-        } else {
-            None
+        let (status_code, headers, body, error, condition) = match response {
+            Ok(Ok(response)) => {
+                let status_code = response.status().as_u16();
+                let headers = response
+                    .headers()
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.to_string()))
+                    })
+                    .collect();
+                (
+                    status_code,
+                    headers,
+                    Body::from_stream(response.bytes_stream().map_err(std::io::Error::other)),
+                    None,
+                    Some(RetryCondition::Status(status_code)),
+                )
+            }
+            Ok(Err(e)) => {
+                let (status_code, error, condition) = if e.is_connect() && e.is_timeout() {
+                    (
+                        504,
+                        format!("Connect timeout after {:?}: {}", self.connect_timeout, e),
+                        Some(RetryCondition::Timeout),
+                    )
+                } else if e.is_timeout() {
+                    (
+                        504,
+                        format!("Request timeout after {:?}: {}", self.request_timeout, e),
+                        Some(RetryCondition::Timeout),
+                    )
+                } else if e.is_connect() {
+                    (500, e.to_string(), Some(RetryCondition::ConnectFailure))
+                } else {
+                    (500, e.to_string(), None)
+                };
+                (
+                    status_code,
+                    HashMap::new(),
+                    Body::empty(),
+                    Some(error),
+                    condition,
+                )
+            }
+            Err(_) => (
+                504,
+                HashMap::new(),
+                Body::empty(),
+                Some(format!(
+                    "Per-try timeout after {:?}",
+                    per_try_timeout.unwrap_or_default()
+                )),
+                Some(RetryCondition::Timeout),
+            ),
         };
 
         // Update connection count
@@ -377,36 +1486,82 @@ impl LoadBalancer {
             }
         }
 
-        Ok(LoadBalancerResponse {
+        let canary = {
+            let canaries = self.canaries.read().await;
+            canaries
+                .iter()
+                .any(|canary| canary.endpoint.id == endpoint.id)
+        };
+        {
+            let mut stats = self.stats.write().await;
+            stats.record(&endpoint.id, status_code, response_time, error.as_deref());
+            if canary {
+                stats.canary_requests += 1;
+            } else {
+                stats.primary_requests += 1;
+            }
+        }
+        self.record_response(&endpoint.id, response_time).await;
+        let failed = status_code >= 500 || error.is_some();
+        self.record_outcome(&endpoint.id, failed).await;
+        self.record_circuit_outcome(&endpoint.id, failed).await;
+
+        let response = LoadBalancerResponse {
             endpoint: endpoint.clone(),
             status_code,
             response_time,
             error,
-        })
+            headers,
+            body,
+            attempts: 1,
+            short_circuited: false,
+        };
+        Ok((response, condition))
     }
 
     pub async fn get_stats(&self) -> LoadBalancerStats {
         let endpoints = self.endpoints.read().await;
+        let pools = self.pools.read().await;
         let connection_counts = self.connection_counts.read().await;
         let last_used = self.last_used.read().await;
+        let tracker = self.stats.read().await;
+        let breakers = self.breakers.read().await;
+        let draining = self.draining.read().await;
+        let canaries = self.canaries.read().await;
 
         let mut endpoint_stats = HashMap::new();
-        let total_requests = 0u64;
-        let successful_requests = 0u64;
-        let failed_requests = 0u64;
-        let total_response_time = Duration::ZERO;
-
-        for endpoint in endpoints.iter() {
+        for endpoint in endpoints
+            .iter()
+            .chain(pools.values().flatten())
+            .chain(canaries.iter().map(|canary| &canary.endpoint))
+        {
             let connections = connection_counts.get(&endpoint.id).unwrap_or(&0);
             let last_used_time = last_used.get(&endpoint.id).cloned();
+            let counters = tracker
+                .endpoints
+                .get(&endpoint.id)
+                .cloned()
+                .unwrap_or_default();
 
             let stats = EndpointStats {
                 endpoint_id: endpoint.id.clone(),
-                requests: 0, // This would be tracked in a real implementation
-                successful_requests: 0,
-                failed_requests: 0,
-                average_response_time: Duration::ZERO,
+                requests: counters.requests,
+                successful_requests: counters.successful,
+                failed_requests: counters.failed,
+                average_response_time: counters.average_response_time(),
+                total_response_time: counters.total_response_time,
+                ewma_response_time: counters.ewma_response_time.unwrap_or_default(),
+                ewma_response_time_ns: counters
+                    .ewma_response_time
+                    .map(|ewma| ewma.as_nanos() as u64)
+                    .unwrap_or(0),
+                last_error: counters.last_error,
                 active_connections: *connections,
+                circuit_state: match breakers.get(&endpoint.id) {
+                    Some(breaker) => breaker.state(),
+                    None => CircuitState::Closed,
+                },
+                draining: draining.contains(&endpoint.id),
                 last_used: last_used_time,
             };
 
@@ -414,39 +1569,364 @@ impl LoadBalancer {
         }
 
         LoadBalancerStats {
-            total_requests,
-            successful_requests,
-            failed_requests,
-            average_response_time: if total_requests > 0 {
-                total_response_time / total_requests as u32
-            } else {
-                Duration::ZERO
-            },
+            total_requests: tracker.totals.requests,
+            successful_requests: tracker.totals.successful,
+            failed_requests: tracker.totals.failed,
+            average_response_time: tracker.totals.average_response_time(),
+            retried_requests: tracker.retries,
+            canary_requests: tracker.canary_requests,
+            primary_requests: tracker.primary_requests,
+            open_circuits: endpoint_stats
+                .values()
+                .filter(|stats| stats.circuit_state != CircuitState::Closed)
+                .count() as u32,
             endpoint_stats,
         }
     }
 
+    /// Request counters for the Prometheus exporter, labelled with the
+    /// service this load balancer fronts. They restart from zero after
+    /// `reset_stats`, which Prometheus reads as a counter reset.
+    pub async fn request_counters(&self, service: &str, namespace: &str) -> LoadBalancerCounters {
+        let tracker = self.stats.read().await;
+        LoadBalancerCounters {
+            service: service.to_string(),
+            namespace: namespace.to_string(),
+            requests_total: tracker.totals.requests,
+            successful_requests_total: tracker.totals.successful,
+            failed_requests_total: tracker.totals.failed,
+        }
+    }
+
+    /// Forget the request statistics recorded so far
+    pub async fn reset_stats(&self) {
+        *self.stats.write().await = EndpointStatsTracker::default();
+    }
+
+    /// Probe every endpoint once. Failed probes count towards ejection like
+    /// failed requests do, and ejected endpoints whose backoff has elapsed
+    /// are restored to the pool if their probe succeeds.
     pub async fn health_check_endpoints(&self) {
-        let endpoints = self.endpoints.read().await;
-        let mut unhealthy_endpoints = Vec::new();
+        let endpoints: Vec<ServiceEndpoint> = {
+            let endpoints = self.endpoints.read().await;
+            let pools = self.pools.read().await;
+            endpoints
+                .iter()
+                .chain(pools.values().flatten())
+                .cloned()
+                .collect()
+        };
+
+        for endpoint in endpoints {
+            let ejected_until = {
+                let outliers = self.outliers.read().await;
+                outliers
+                    .get(&endpoint.id)
+                    .and_then(|state| state.ejected_until)
+            };
+            if matches!(ejected_until, Some(until) if until > Instant::now()) {
+                continue;
+            }
+
+            let (client, host) = self.client_for(&endpoint).await;
+            let is_healthy = self
+                .health_checker
+                .check_endpoint_with(&client, &host, &endpoint)
+                .await;
+            if ejected_until.is_some() {
+                self.finish_probe(&endpoint.id, is_healthy).await;
+            } else {
+                self.record_outcome(&endpoint.id, !is_healthy).await;
+            }
+        }
+    }
+}
+
+/// Client for endpoints, trusting and presenting the certificates of `tls`
+fn client_builder(
+    connect: Duration,
+    total: Duration,
+    tls: Option<&UpstreamTls>,
+) -> reqwest::ClientBuilder {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(connect)
+        .timeout(total);
+    if let Some(tls) = tls {
+        builder = builder
+            .use_rustls_tls()
+            .danger_accept_invalid_certs(tls.insecure_skip_verify);
+        for certificate in &tls.ca_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        if let Some(identity) = &tls.identity {
+            builder = builder.identity(identity.clone());
+        }
+    }
+    builder
+}
+
+/// Request headers plus X-Forwarded-For and X-Forwarded-Proto
+fn forwarded_headers(request: &LoadBalancerRequest) -> reqwest::header::HeaderMap {
+    let mut headers = reqwest::header::HeaderMap::new();
+    for (key, value) in &request.headers {
+        if let (Ok(header_name), Ok(header_value)) = (
+            key.parse::<reqwest::header::HeaderName>(),
+            value.parse::<reqwest::header::HeaderValue>(),
+        ) {
+            headers.insert(header_name, header_value);
+        }
+    }
+
+    if let Some(client_ip) = request.client_ip {
+        let forwarded_for = match headers
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(previous) => format!("{}, {}", previous, client_ip),
+            None => client_ip.to_string(),
+        };
+        if let Ok(value) = forwarded_for.parse() {
+            headers.insert("x-forwarded-for", value);
+        }
+    }
+    if !headers.contains_key("x-forwarded-proto") {
+        headers.insert(
+            "x-forwarded-proto",
+            reqwest::header::HeaderValue::from_static("http"),
+        );
+    }
+    headers
+}
+
+impl Body {
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn from_bytes(bytes: impl Into<Bytes>) -> Self {
+        let bytes = bytes.into();
+        Self::from_stream(stream::once(async move { Ok(bytes) }))
+    }
+
+    pub fn from_stream<S>(stream: S) -> Self
+    where
+        S: futures::Stream<Item = std::io::Result<Bytes>> + Send + 'static,
+    {
+        Self(Arc::new(std::sync::Mutex::new(Some(stream.boxed()))))
+    }
+
+    /// Take the stream, leaving an empty body behind
+    pub fn into_stream(self) -> BoxStream<'static, std::io::Result<Bytes>> {
+        self.0
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_else(|| stream::empty().boxed())
+    }
+
+    /// Read the whole body into memory
+    pub async fn to_bytes(self) -> std::io::Result<Bytes> {
+        let chunks: Vec<Bytes> = self.into_stream().try_collect().await?;
+        Ok(chunks.concat().into())
+    }
+}
+
+impl std::fmt::Debug for Body {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Body")
+    }
+}
+
+/// The primary endpoints, or those of `pool` when a routing rule matched
+fn routed_endpoints<'a>(
+    endpoints: &'a [ServiceEndpoint],
+    pools: &'a HashMap<String, Vec<ServiceEndpoint>>,
+    pool: Option<&str>,
+) -> &'a [ServiceEndpoint] {
+    match pool {
+        Some(pool) => pools.get(pool).map(Vec::as_slice).unwrap_or_default(),
+        None => endpoints,
+    }
+}
+
+/// The primary endpoints or the pool that hold `endpoint_id`
+fn pool_containing<'a>(
+    endpoints: &'a mut Vec<ServiceEndpoint>,
+    pools: &'a mut HashMap<String, Vec<ServiceEndpoint>>,
+    endpoint_id: &str,
+) -> Option<&'a mut Vec<ServiceEndpoint>> {
+    if endpoints.iter().any(|ep| ep.id == endpoint_id) {
+        return Some(endpoints);
+    }
+    pools
+        .values_mut()
+        .find(|pool| pool.iter().any(|ep| ep.id == endpoint_id))
+}
 
-        for endpoint in endpoints.iter() {
-            let is_healthy = self.health_checker.check_endpoint(endpoint).await;
-            if !is_healthy {
-                unhealthy_endpoints.push(endpoint.id.clone());
+impl TlsConfig {
+    /// Settings for `endpoint`: these, overridden by the endpoint's
+    /// `tls.ca_bundle`, `tls.client_cert` and `tls.client_key`,
+    /// `tls.insecure_skip_verify` and `tls.sni_override` metadata
+    pub fn for_endpoint(&self, endpoint: &ServiceEndpoint) -> PolisResult<TlsConfig> {
+        let mut tls = self.clone();
+        let metadata = &endpoint.metadata;
+        if let Some(path) = metadata.get("tls.ca_bundle") {
+            tls.ca_bundle = Some(PathBuf::from(path));
+        }
+        match (
+            metadata.get("tls.client_cert"),
+            metadata.get("tls.client_key"),
+        ) {
+            (Some(cert), Some(key)) => {
+                tls.client_cert = Some((PathBuf::from(cert), PathBuf::from(key)));
+            }
+            (None, None) => {}
+            _ => {
+                return Err(PolisError::Config(format!(
+                    "Endpoint {} needs both tls.client_cert and tls.client_key",
+                    endpoint.id
+                )));
             }
         }
+        if let Some(value) = metadata.get("tls.insecure_skip_verify") {
+            tls.insecure_skip_verify = value.parse().map_err(|_| {
+                PolisError::Config(format!(
+                    "Invalid tls.insecure_skip_verify '{}' for endpoint {}",
+                    value, endpoint.id
+                ))
+            })?;
+        }
+        if let Some(server_name) = metadata.get("tls.sni_override") {
+            tls.sni_override = Some(server_name.clone());
+        }
+        Ok(tls)
+    }
+
+    fn load(&self) -> PolisResult<UpstreamTls> {
+        let ca_certificates = match &self.ca_bundle {
+            Some(path) => {
+                let certificates = reqwest::Certificate::from_pem_bundle(&read_pem(path)?)
+                    .map_err(|e| {
+                        PolisError::Config(format!("Invalid CA bundle {}: {}", path.display(), e))
+                    })?;
+                if certificates.is_empty() {
+                    return Err(PolisError::Config(format!(
+                        "No certificates in CA bundle {}",
+                        path.display()
+                    )));
+                }
+                certificates
+            }
+            None => Vec::new(),
+        };
+        let identity = match &self.client_cert {
+            Some((cert, key)) => {
+                let mut pem = read_pem(cert)?;
+                pem.push(b'\n');
+                pem.extend(read_pem(key)?);
+                let identity = reqwest::Identity::from_pem(&pem).map_err(|e| {
+                    PolisError::Config(format!(
+                        "Invalid client certificate {}: {}",
+                        cert.display(),
+                        e
+                    ))
+                })?;
+                Some(identity)
+            }
+            None => None,
+        };
 
-        drop(endpoints);
+        Ok(UpstreamTls {
+            ca_certificates,
+            identity,
+            insecure_skip_verify: self.insecure_skip_verify,
+            sni_override: self.sni_override.clone(),
+        })
+    }
+}
 
-        // Remove unhealthy endpoints
-        if !unhealthy_endpoints.is_empty() {
-            let mut endpoints = self.endpoints.write().await;
-            endpoints.retain(|ep| !unhealthy_endpoints.contains(&ep.id));
+fn read_pem(path: &Path) -> PolisResult<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|e| PolisError::Config(format!("Cannot read {}: {}", path.display(), e)))
+}
+
+impl Default for RetryPolicy {
+    /// Two retries of GET and HEAD requests on 502, 503 and 504 responses,
+    /// connection failures and timeouts
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            retry_on: vec![
+                RetryCondition::Status(502),
+                RetryCondition::Status(503),
+                RetryCondition::Status(504),
+                RetryCondition::ConnectFailure,
+                RetryCondition::Timeout,
+            ],
+            per_try_timeout: None,
+            backoff: Duration::from_millis(25),
+            methods: vec!["GET".to_string(), "HEAD".to_string()],
         }
     }
 }
 
+impl RetryPolicy {
+    /// Whether `request` may be sent more than once
+    fn allows(&self, request: &LoadBalancerRequest) -> bool {
+        request.body.is_none()
+            && self
+                .methods
+                .iter()
+                .any(|method| method.eq_ignore_ascii_case(&request.method))
+    }
+}
+
+impl RoutingRule {
+    pub fn new(endpoint_pool: &str) -> Self {
+        Self {
+            path_prefix: None,
+            header_matches: HashMap::new(),
+            endpoint_pool: endpoint_pool.to_string(),
+            priority: 0,
+        }
+    }
+
+    pub fn with_path_prefix(mut self, path_prefix: &str) -> Self {
+        self.path_prefix = Some(path_prefix.to_string());
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.header_matches
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn matches(&self, request: &LoadBalancerRequest) -> bool {
+        let path_matches = match &self.path_prefix {
+            Some(prefix) => {
+                let prefix = prefix.trim_end_matches('/');
+                request.path == prefix
+                    || matches!(request.path.strip_prefix(prefix), Some(rest) if rest.starts_with('/'))
+            }
+            None => true,
+        };
+
+        path_matches
+            && self.header_matches.iter().all(|(name, value)| {
+                request
+                    .headers
+                    .iter()
+                    .any(|(header, actual)| header.eq_ignore_ascii_case(name) && actual == value)
+            })
+    }
+}
+
 impl HealthChecker {
     pub fn new() -> Self {
         Self {
@@ -456,6 +1936,17 @@ impl HealthChecker {
     }
 
     pub async fn check_endpoint(&self, endpoint: &ServiceEndpoint) -> bool {
+        self.check_endpoint_with(&self.client, &endpoint.address, endpoint)
+            .await
+    }
+
+    /// Check `endpoint` through `client`, addressing it as `host`
+    pub async fn check_endpoint_with(
+        &self,
+        client: &reqwest::Client,
+        host: &str,
+        endpoint: &ServiceEndpoint,
+    ) -> bool {
         let url = format!(
             "{}://{}:{}",
             match endpoint.protocol {
@@ -463,11 +1954,11 @@ impl HealthChecker {
                 Protocol::Https => "https",
                 _ => "http",
             },
-            endpoint.address,
+            host,
             endpoint.port
         );
 
-        match self.client.get(&url).timeout(self.timeout).send().await {
+        match client.get(&url).timeout(self.timeout).send().await {
             Ok(response) => response.status().is_success(),
             Err(_) => false,
         }
@@ -503,13 +1994,31 @@ impl ConsistentHashRing {
             return None;
         }
 
-        let hash = Self::hash(key);
-        let index = self
-            .ring
-            .binary_search_by_key(&hash, |node| node.hash)
-            .unwrap_or_else(|index| index % self.ring.len());
+        Some(&self.ring[self.position(key)].endpoint)
+    }
 
-        Some(&self.ring[index].endpoint)
+    /// Endpoint ids in ring order starting at `key`, each listed once
+    pub fn endpoint_ids<'a>(&'a self, key: &str) -> impl Iterator<Item = &'a str> + 'a {
+        let start = if self.ring.is_empty() {
+            0
+        } else {
+            self.position(key)
+        };
+        let mut seen = std::collections::HashSet::new();
+        self.ring[start..]
+            .iter()
+            .chain(&self.ring[..start])
+            .map(|node| node.endpoint_id.as_str())
+            .filter(move |id| seen.insert(*id))
+    }
+
+    /// Index of the first node at or after the key's hash, wrapping around
+    fn position(&self, key: &str) -> usize {
+        let hash = Self::hash(key);
+        match self.ring.binary_search_by_key(&hash, |node| node.hash) {
+            Ok(index) => index,
+            Err(index) => index % self.ring.len(),
+        }
     }
 
     fn hash(key: &str) -> u64 {
@@ -555,10 +2064,11 @@ mod tests {
             headers: HashMap::new(),
             path: "/".to_string(),
             method: "GET".to_string(),
+            body: None,
         };
 
-        let selected1 = lb.select_endpoint(&request).await.unwrap();
-        let selected2 = lb.select_endpoint(&request).await.unwrap();
+        let selected1 = lb.select_endpoint(&request).await.unwrap().unwrap();
+        let selected2 = lb.select_endpoint(&request).await.unwrap().unwrap();
 
         // Should alternate between endpoints
         assert_ne!(selected1.id, selected2.id);
@@ -583,12 +2093,13 @@ mod tests {
             headers: HashMap::new(),
             path: "/".to_string(),
             method: "GET".to_string(),
+            body: None,
         };
 
         // Should select endpoint1 more often due to higher weight
         let mut endpoint1_count = 0;
         for _ in 0..100 {
-            let selected = lb.select_endpoint(&request).await.unwrap();
+            let selected = lb.select_endpoint(&request).await.unwrap().unwrap();
             if selected.id == "1" {
                 endpoint1_count += 1;
             }
@@ -613,11 +2124,12 @@ mod tests {
             headers: HashMap::new(),
             path: "/test".to_string(),
             method: "GET".to_string(),
+            body: None,
         };
 
         // Same key should always select the same endpoint
-        let selected1 = lb.select_endpoint(&request).await.unwrap();
-        let selected2 = lb.select_endpoint(&request).await.unwrap();
+        let selected1 = lb.select_endpoint(&request).await.unwrap().unwrap();
+        let selected2 = lb.select_endpoint(&request).await.unwrap().unwrap();
 
         assert_eq!(selected1.id, selected2.id);
     }
@@ -640,12 +2152,98 @@ mod tests {
             headers: HashMap::new(),
             path: "/".to_string(),
             method: "GET".to_string(),
+            body: None,
         };
 
         // Same IP should always select the same endpoint
-        let selected1 = lb.select_endpoint(&request).await.unwrap();
-        let selected2 = lb.select_endpoint(&request).await.unwrap();
+        let selected1 = lb.select_endpoint(&request).await.unwrap().unwrap();
+        let selected2 = lb.select_endpoint(&request).await.unwrap().unwrap();
 
         assert_eq!(selected1.id, selected2.id);
     }
+
+    fn get_request() -> LoadBalancerRequest {
+        LoadBalancerRequest {
+            client_ip: None,
+            session_id: None,
+            headers: HashMap::new(),
+            path: "/".to_string(),
+            method: "GET".to_string(),
+            body: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_least_response_time_respects_connection_cap() {
+        let lb =
+            LoadBalancer::new(LoadBalancingAlgorithm::LeastResponseTime).with_max_connections(2);
+        lb.add_endpoint(create_test_endpoint("fast", "127.0.0.1", 8080))
+            .await;
+        lb.add_endpoint(create_test_endpoint("slow", "127.0.0.1", 8081))
+            .await;
+        {
+            let mut stats = lb.stats.write().await;
+            stats.observe("fast", Duration::from_millis(5), DEFAULT_EWMA_ALPHA);
+            stats.observe("slow", Duration::from_millis(50), DEFAULT_EWMA_ALPHA);
+        }
+
+        let selected = lb.select_endpoint(&get_request()).await.unwrap().unwrap();
+        assert_eq!(selected.id, "fast");
+
+        lb.connection_counts
+            .write()
+            .await
+            .insert("fast".to_string(), 2);
+        let selected = lb.select_endpoint(&get_request()).await.unwrap().unwrap();
+        assert_eq!(selected.id, "slow");
+
+        // With every endpoint at the cap the fastest one is used again
+        lb.connection_counts
+            .write()
+            .await
+            .insert("slow".to_string(), 2);
+        let selected = lb.select_endpoint(&get_request()).await.unwrap().unwrap();
+        assert_eq!(selected.id, "fast");
+    }
+
+    #[tokio::test]
+    async fn test_peak_ewma_weighs_outstanding_requests() {
+        let lb = LoadBalancer::new(LoadBalancingAlgorithm::PeakEwma);
+        lb.add_endpoint(create_test_endpoint("fast", "127.0.0.1", 8080))
+            .await;
+        lb.add_endpoint(create_test_endpoint("slow", "127.0.0.1", 8081))
+            .await;
+        {
+            let mut stats = lb.stats.write().await;
+            stats.observe("fast", Duration::from_millis(10), DEFAULT_EWMA_ALPHA);
+            stats.observe("slow", Duration::from_millis(30), DEFAULT_EWMA_ALPHA);
+        }
+
+        let selected = lb.select_endpoint(&get_request()).await.unwrap().unwrap();
+        assert_eq!(selected.id, "fast");
+
+        // 10ms * 4 outstanding costs more than 30ms * 1
+        lb.connection_counts
+            .write()
+            .await
+            .insert("fast".to_string(), 3);
+        let selected = lb.select_endpoint(&get_request()).await.unwrap().unwrap();
+        assert_eq!(selected.id, "slow");
+    }
+
+    #[test]
+    fn test_peak_ewma_jumps_to_slower_samples() {
+        let mut counters = RequestCounters::default();
+        counters.observe(Duration::from_millis(10), DEFAULT_EWMA_ALPHA);
+        counters.observe(Duration::from_millis(100), DEFAULT_EWMA_ALPHA);
+        assert_eq!(
+            counters.peak_ewma_response_time,
+            Some(Duration::from_millis(100))
+        );
+        assert!(counters.ewma_response_time.unwrap() < Duration::from_millis(100));
+
+        counters.observe(Duration::from_millis(10), DEFAULT_EWMA_ALPHA);
+        let peak = counters.peak_ewma_response_time.unwrap();
+        assert!(peak < Duration::from_millis(100) && peak > Duration::from_millis(10));
+    }
 }
@@ -0,0 +1,164 @@
+use chrono::{DateTime, Utc};
+use polis_core::{PolisError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+/// Limits on what a namespace may hold; `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamespaceQuota {
+    pub max_deployments: Option<u32>,
+    /// Sum of the desired replicas of every deployment
+    pub max_replicas: Option<u32>,
+}
+
+/// What a namespace currently holds, as counted against its quota
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NamespaceUsage {
+    pub deployments: u32,
+    pub replicas: u32,
+}
+
+/// Namespace grouping deployments and other resources
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Namespace {
+    pub name: String,
+    #[serde(default)]
+    pub quota: NamespaceQuota,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Registry of namespaces and their quotas
+pub struct NamespaceManager {
+    namespaces: RwLock<HashMap<String, Namespace>>,
+    /// Namespace that always exists and cannot be deleted
+    default_namespace: String,
+}
+
+impl NamespaceManager {
+    pub fn new(default_namespace: &str, namespaces: HashMap<String, Namespace>) -> Self {
+        let mut namespaces = namespaces;
+        namespaces
+            .entry(default_namespace.to_string())
+            .or_insert_with(|| Namespace {
+                name: default_namespace.to_string(),
+                quota: NamespaceQuota::default(),
+                created_at: Utc::now(),
+            });
+        Self {
+            namespaces: RwLock::new(namespaces),
+            default_namespace: default_namespace.to_string(),
+        }
+    }
+
+    pub async fn create_namespace(&self, name: &str, quota: NamespaceQuota) -> Result<Namespace> {
+        if name.trim().is_empty() || name.contains('/') {
+            return Err(PolisError::Config(format!(
+                "Invalid namespace name '{}'",
+                name
+            )));
+        }
+
+        let mut namespaces = self.namespaces.write().await;
+        if namespaces.contains_key(name) {
+            return Err(PolisError::Config(format!(
+                "Namespace '{}' already exists",
+                name
+            )));
+        }
+        let namespace = Namespace {
+            name: name.to_string(),
+            quota,
+            created_at: Utc::now(),
+        };
+        namespaces.insert(name.to_string(), namespace.clone());
+        Ok(namespace)
+    }
+
+    /// Register a namespace that resources already refer to, without a quota
+    pub async fn ensure_namespace(&self, name: &str) {
+        let mut namespaces = self.namespaces.write().await;
+        if !namespaces.contains_key(name) {
+            namespaces.insert(
+                name.to_string(),
+                Namespace {
+                    name: name.to_string(),
+                    quota: NamespaceQuota::default(),
+                    created_at: Utc::now(),
+                },
+            );
+        }
+    }
+
+    /// Namespaces sorted by name
+    pub async fn list_namespaces(&self) -> Vec<Namespace> {
+        let mut namespaces: Vec<Namespace> =
+            self.namespaces.read().await.values().cloned().collect();
+        namespaces.sort_by(|a, b| a.name.cmp(&b.name));
+        namespaces
+    }
+
+    pub async fn get_namespace(&self, name: &str) -> Option<Namespace> {
+        self.namespaces.read().await.get(name).cloned()
+    }
+
+    /// Forget a namespace; its resources must already be gone
+    pub async fn delete_namespace(&self, name: &str) -> Result<()> {
+        if name == self.default_namespace {
+            return Err(PolisError::Config(format!(
+                "The default namespace '{}' cannot be deleted",
+                name
+            )));
+        }
+        self.namespaces
+            .write()
+            .await
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| PolisError::Config(format!("Namespace '{}' not found", name)))
+    }
+
+    /// Fail if growing `usage` by `deployments` and `replicas` would exceed
+    /// the quota of the namespace
+    pub async fn check_quota(
+        &self,
+        name: &str,
+        usage: NamespaceUsage,
+        deployments: u32,
+        replicas: u32,
+    ) -> Result<()> {
+        let quota = match self.namespaces.read().await.get(name) {
+            Some(namespace) => namespace.quota,
+            None => {
+                return Err(PolisError::Config(format!(
+                    "Namespace '{}' not found",
+                    name
+                )))
+            }
+        };
+
+        let wanted_deployments = usage.deployments.saturating_add(deployments);
+        if let Some(max) = quota
+            .max_deployments
+            .filter(|max| wanted_deployments > *max)
+        {
+            return Err(PolisError::Config(format!(
+                "Namespace '{}' quota exceeded: {} deployments requested, at most {} allowed",
+                name, wanted_deployments, max
+            )));
+        }
+        let wanted_replicas = usage.replicas.saturating_add(replicas);
+        if let Some(max) = quota.max_replicas.filter(|max| wanted_replicas > *max) {
+            return Err(PolisError::Config(format!(
+                "Namespace '{}' quota exceeded: {} replicas requested, at most {} allowed",
+                name, wanted_replicas, max
+            )));
+        }
+        Ok(())
+    }
+
+    /// Snapshot for persistence
+    pub(crate) async fn snapshot(&self) -> HashMap<String, Namespace> {
+        self.namespaces.read().await.clone()
+    }
+}
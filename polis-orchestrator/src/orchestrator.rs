@@ -1,19 +1,47 @@
-use polis_core::{PolisError, Result};
+use crate::auto_scaling::ScalingStrategy;
+use crate::namespace::{Namespace, NamespaceManager, NamespaceQuota, NamespaceUsage};
+use crate::scheduler::{Node, PlacementRequest, Scheduler};
+use polis_core::{
+    parse_duration, ContainerId, ContainerStatus, DeploymentAction, EgressPolicy, EventBus, EventKind, PolisError,
+    Result, RootfsConfig, WritablePath,
+};
+use polis_monitor::DeploymentGauge;
+use polis_runtime::{ContainerBackend, ContainerOptions};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
-use tracing::info;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
 use uuid::Uuid;
 
 /// Main orchestrator that coordinates all orchestration components
 pub struct Orchestrator {
     deployments: Arc<RwLock<HashMap<String, Deployment>>>,
     services: Arc<RwLock<HashMap<String, Service>>>,
+    configs: Arc<RwLock<HashMap<String, ConfigSpec>>>,
+    cron_jobs: Arc<RwLock<HashMap<String, CronJobSpec>>>,
+    jobs: Arc<RwLock<HashMap<String, JobSpec>>>,
+    namespaces: NamespaceManager,
+    scheduler: Scheduler,
     config: OrchestratorConfig,
+    backend: Arc<dyn ContainerBackend>,
+    /// Serializes state writes so a slower save cannot overwrite a newer one
+    save_lock: Mutex<()>,
+    event_bus: EventBus,
 }
 
+/// Version of the persisted state layout, bumped on incompatible changes
+pub const STATE_VERSION: u32 = 1;
+
+const STATE_FILE: &str = "orchestrator_state.json";
+const HEALTH_CHECKS_FILE: &str = "health_checks.json";
+
+/// Number of specification revisions kept per deployment
+pub const MAX_REVISIONS: usize = 10;
+
 /// Orchestrator configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrchestratorConfig {
@@ -24,6 +52,32 @@ pub struct OrchestratorConfig {
     pub auto_scaling_enabled: bool,
     pub max_replicas: u32,
     pub min_replicas: u32,
+    /// Directory where the orchestrator state is persisted
+    #[serde(default = "default_state_dir")]
+    pub state_dir: PathBuf,
+    /// Start with an empty state instead of failing when the state file
+    /// cannot be read; the unreadable file is kept next to it
+    #[serde(default)]
+    pub ignore_corrupt_state: bool,
+    /// Create missing namespaces on deploy instead of rejecting the deployment
+    #[serde(default)]
+    pub auto_create_namespaces: bool,
+}
+
+fn default_state_dir() -> PathBuf {
+    PathBuf::from("data")
+}
+
+impl OrchestratorConfig {
+    /// File holding the persisted orchestrator state
+    pub fn state_path(&self) -> PathBuf {
+        self.state_dir.join(STATE_FILE)
+    }
+
+    /// File holding the persisted health check definitions
+    pub fn health_checks_path(&self) -> PathBuf {
+        self.state_dir.join(HEALTH_CHECKS_FILE)
+    }
 }
 
 /// Service definition
@@ -90,6 +144,86 @@ pub struct Deployment {
     pub updated_at: chrono::DateTime<chrono::Utc>,
     pub labels: HashMap<String, String>,
     pub annotations: HashMap<String, String>,
+    #[serde(default)]
+    pub rootfs: RootfsConfig,
+    /// Last applied specification
+    #[serde(default)]
+    pub spec: Option<DeploymentSpec>,
+    /// Containers backing the replicas
+    #[serde(default)]
+    pub containers: Vec<ContainerId>,
+    /// Applied specifications, oldest first; the last one is active
+    #[serde(default)]
+    pub revisions: Vec<DeploymentRevision>,
+    /// Update applied while paused, rolled out on resume
+    #[serde(default)]
+    pub pending_spec: Option<DeploymentSpec>,
+    /// Node each replica is placed on, empty when no node is registered
+    #[serde(default)]
+    pub nodes: Vec<String>,
+    /// Why the replicas could not be placed on any node
+    #[serde(default)]
+    pub pending_reason: Option<String>,
+}
+
+/// Specification applied to a deployment at some point
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentRevision {
+    pub revision: u32,
+    pub spec: DeploymentSpec,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Deployment {
+    /// Record `spec` as the active revision, forgetting the oldest ones
+    /// beyond `MAX_REVISIONS`
+    fn record_revision(&mut self, spec: DeploymentSpec, now: chrono::DateTime<chrono::Utc>) {
+        let revision = self.revisions.last().map_or(1, |r| r.revision + 1);
+        self.revisions.push(DeploymentRevision {
+            revision,
+            spec,
+            created_at: now,
+        });
+        let excess = self.revisions.len().saturating_sub(MAX_REVISIONS);
+        self.revisions.drain(..excess);
+    }
+
+    /// Record the containers backing the replicas and how many are running
+    fn set_containers(&mut self, containers: Vec<ContainerId>, ready: u32) {
+        self.containers = containers;
+        self.ready_replicas = ready;
+        self.available_replicas = ready;
+        self.unavailable_replicas = self.desired_replicas.saturating_sub(ready);
+        if self.status == DeploymentStatus::Paused {
+            return;
+        }
+        self.status = if ready == self.desired_replicas {
+            DeploymentStatus::Running
+        } else {
+            DeploymentStatus::Pending
+        };
+    }
+
+    fn status_result(&self) -> DeploymentStatusResult {
+        DeploymentStatusResult {
+            name: self.name.clone(),
+            namespace: self.namespace.clone(),
+            desired_replicas: self.desired_replicas,
+            current_replicas: self.containers.len() as u32,
+            ready_replicas: self.ready_replicas,
+            available_replicas: self.available_replicas,
+            status: match self.status {
+                DeploymentStatus::Pending => DeploymentStatusType::Pending,
+                DeploymentStatus::Running => DeploymentStatusType::Running,
+                DeploymentStatus::Failed => DeploymentStatusType::Failed,
+                DeploymentStatus::Scaling => DeploymentStatusType::Running,
+                DeploymentStatus::Paused => DeploymentStatusType::Paused,
+            },
+            reason: self.pending_reason.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
 }
 
 /// Deployment status
@@ -112,28 +246,304 @@ impl Default for OrchestratorConfig {
             auto_scaling_enabled: true,
             max_replicas: 10,
             min_replicas: 1,
+            state_dir: default_state_dir(),
+            ignore_corrupt_state: false,
+            auto_create_namespaces: false,
         }
     }
 }
 
 /// Deployment specification
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DeploymentSpec {
     pub name: String,
+    #[serde(default = "default_namespace")]
     pub namespace: String,
     pub image: String,
+    #[serde(default = "default_replicas")]
     pub replicas: u32,
+    #[serde(default)]
     pub ports: Vec<PortSpec>,
+    #[serde(default)]
     pub env_vars: HashMap<String, String>,
+    #[serde(default)]
     pub labels: HashMap<String, String>,
+    #[serde(default)]
     pub annotations: HashMap<String, String>,
     pub health_check: Option<HealthCheckSpec>,
     pub scaling_policy: Option<ScalingPolicySpec>,
     pub resources: Option<ResourceSpec>,
+    #[serde(default)]
+    pub read_only_rootfs: bool,
+    /// Writable paths layered over a read-only rootfs (`/data`, `/tmp:size=64m`, `/host:/data`)
+    #[serde(default)]
+    pub writable_paths: Vec<String>,
+    /// Maximum runtime of each container (`30m`, `2h`) for job-like workloads
+    #[serde(default)]
+    pub max_runtime: Option<String>,
+    /// Allowed egress destinations for every replica
+    #[serde(default)]
+    pub egress: Option<EgressPolicy>,
+}
+
+impl DeploymentSpec {
+    /// Parse every deployment of a YAML manifest. Documents are separated by
+    /// `---` and may state `kind: Deployment`; errors name the document and
+    /// the offending field.
+    pub fn from_yaml_str(content: &str) -> Result<Vec<DeploymentSpec>> {
+        let mut specs: Vec<DeploymentSpec> = Vec::new();
+        // The raw pass checks the kind; the typed pass keeps the field path
+        // and location in deserialization errors
+        let raw = serde_yaml::Deserializer::from_str(content);
+        let typed = serde_yaml::Deserializer::from_str(content);
+        for (index, (raw, typed)) in raw.zip(typed).enumerate() {
+            let invalid = |message: String| PolisError::Config(format!("document {}: {}", index + 1, message));
+
+            match serde_yaml::Value::deserialize(raw).map_err(|e| invalid(e.to_string()))? {
+                serde_yaml::Value::Null => continue,
+                serde_yaml::Value::Mapping(mapping) => match mapping.get("kind") {
+                    None => {}
+                    Some(kind) if kind.as_str() == Some("Deployment") => {}
+                    Some(_) => return Err(invalid("kind: expected `Deployment`".to_string())),
+                },
+                _ => return Err(invalid("expected a mapping of deployment fields".to_string())),
+            }
+
+            let spec = DeploymentSpec::deserialize(typed).map_err(|e| invalid(e.to_string()))?;
+            spec.validate().map_err(invalid)?;
+            if specs.iter().any(|s| s.name == spec.name && s.namespace == spec.namespace) {
+                return Err(invalid(format!(
+                    "name: deployment '{}' in namespace '{}' is defined more than once",
+                    spec.name, spec.namespace
+                )));
+            }
+            specs.push(spec);
+        }
+        Ok(specs)
+    }
+
+    /// Check the values serde cannot, naming the offending field
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        for (field, value) in [("name", &self.name), ("namespace", &self.namespace), ("image", &self.image)] {
+            if value.trim().is_empty() {
+                return Err(format!("{}: must not be empty", field));
+            }
+        }
+        if self.name.contains('/') || self.namespace.contains('/') {
+            return Err("name: must not contain '/'".to_string());
+        }
+        for (index, port) in self.ports.iter().enumerate() {
+            if port.port == 0 || port.target_port == 0 {
+                return Err(format!("ports[{}]: port and target_port must be between 1 and 65535", index));
+            }
+        }
+        for (index, path) in self.writable_paths.iter().enumerate() {
+            WritablePath::parse(path).map_err(|e| format!("writable_paths[{}]: {}", index, e))?;
+        }
+        self.max_runtime().map_err(|e| format!("max_runtime: {}", e))?;
+        if let Some(policy) = &self.scaling_policy {
+            if policy.min_replicas > policy.max_replicas {
+                return Err(format!(
+                    "scaling_policy.min_replicas: {} is above max_replicas {}",
+                    policy.min_replicas, policy.max_replicas
+                ));
+            }
+            if policy.target_cpu <= 0.0 || policy.target_memory <= 0.0 {
+                return Err("scaling_policy: target_cpu and target_memory must be positive".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Parsed maximum container runtime
+    pub fn max_runtime(&self) -> Result<Option<Duration>> {
+        self.max_runtime.as_deref().map(parse_duration).transpose()
+    }
+
+    /// Runtime options for the containers of this deployment
+    pub fn container_options(&self) -> Result<ContainerOptions> {
+        Ok(ContainerOptions {
+            environment: self.env_vars.clone(),
+            labels: self.labels.clone(),
+            rootfs: self.rootfs_config()?,
+            max_runtime: self.max_runtime()?,
+            egress: self.egress.clone(),
+            ..Default::default()
+        })
+    }
+
+    /// Whether containers built from `other` differ from the ones of this
+    /// spec, so that existing replicas must be replaced
+    pub fn container_template_differs(&self, other: &DeploymentSpec) -> bool {
+        self.image != other.image
+            || self.env_vars != other.env_vars
+            || self.labels != other.labels
+            || self.resources != other.resources
+            || self.read_only_rootfs != other.read_only_rootfs
+            || self.writable_paths != other.writable_paths
+            || self.max_runtime != other.max_runtime
+            || self.egress != other.egress
+    }
+
+    /// Build the container rootfs configuration for this deployment
+    pub fn rootfs_config(&self) -> Result<RootfsConfig> {
+        let mut rootfs = RootfsConfig {
+            read_only: self.read_only_rootfs,
+            ..Default::default()
+        };
+        for path in &self.writable_paths {
+            rootfs = rootfs.with_writable_path(WritablePath::parse(path)?);
+        }
+        Ok(rootfs)
+    }
+}
+
+fn default_namespace() -> String {
+    "default".to_string()
+}
+
+fn default_replicas() -> u32 {
+    1
+}
+
+/// Configuration data specification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigSpec {
+    pub name: String,
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    #[serde(default)]
+    pub data: HashMap<String, String>,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+/// Scheduled job specification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CronJobSpec {
+    pub name: String,
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    /// Cron expression (`*/5 * * * *`)
+    pub schedule: String,
+    pub image: String,
+    #[serde(default)]
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub env_vars: HashMap<String, String>,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+/// Run-to-completion job specification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobSpec {
+    pub name: String,
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    pub image: String,
+    #[serde(default)]
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub env_vars: HashMap<String, String>,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    /// Seconds the job may run before it is stopped
+    #[serde(default)]
+    pub active_deadline_seconds: Option<u64>,
+}
+
+impl JobSpec {
+    /// Runtime options for the job container; the deadline is enforced by
+    /// the runtime's max-runtime mechanism
+    pub fn container_options(&self) -> ContainerOptions {
+        ContainerOptions {
+            environment: self.env_vars.clone(),
+            labels: self.labels.clone(),
+            max_runtime: self.active_deadline_seconds.map(Duration::from_secs),
+            ..Default::default()
+        }
+    }
+}
+
+/// Declarative resource document, tagged by `kind`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Manifest {
+    Deployment(Box<DeploymentSpec>),
+    Config(ConfigSpec),
+    CronJob(CronJobSpec),
+    Job(JobSpec),
+}
+
+/// Kind of a declarative resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ResourceKind {
+    Deployment,
+    Config,
+    CronJob,
+    Job,
+}
+
+/// Identity of a declarative resource
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ResourceKey {
+    pub kind: ResourceKind,
+    pub namespace: String,
+    pub name: String,
+}
+
+impl std::fmt::Display for ResourceKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}/{}/{}", self.kind, self.namespace, self.name)
+    }
+}
+
+impl Manifest {
+    pub fn key(&self) -> ResourceKey {
+        let (kind, namespace, name) = match self {
+            Manifest::Deployment(spec) => (ResourceKind::Deployment, &spec.namespace, &spec.name),
+            Manifest::Config(spec) => (ResourceKind::Config, &spec.namespace, &spec.name),
+            Manifest::CronJob(spec) => (ResourceKind::CronJob, &spec.namespace, &spec.name),
+            Manifest::Job(spec) => (ResourceKind::Job, &spec.namespace, &spec.name),
+        };
+        ResourceKey {
+            kind,
+            namespace: namespace.clone(),
+            name: name.clone(),
+        }
+    }
+
+    pub fn labels(&self) -> &HashMap<String, String> {
+        match self {
+            Manifest::Deployment(spec) => &spec.labels,
+            Manifest::Config(spec) => &spec.labels,
+            Manifest::CronJob(spec) => &spec.labels,
+            Manifest::Job(spec) => &spec.labels,
+        }
+    }
+
+    pub fn labels_mut(&mut self) -> &mut HashMap<String, String> {
+        match self {
+            Manifest::Deployment(spec) => &mut spec.labels,
+            Manifest::Config(spec) => &mut spec.labels,
+            Manifest::CronJob(spec) => &mut spec.labels,
+            Manifest::Job(spec) => &mut spec.labels,
+        }
+    }
+}
+
+/// Outcome of applying a resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApplyAction {
+    Create,
+    Update,
+    Unchanged,
+    Delete,
 }
 
 /// Port specification
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PortSpec {
     pub name: String,
     pub port: u16,
@@ -143,7 +553,7 @@ pub struct PortSpec {
 }
 
 /// Health check specification
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct HealthCheckSpec {
     pub http_path: Option<String>,
     pub tcp_port: Option<u16>,
@@ -154,7 +564,7 @@ pub struct HealthCheckSpec {
 }
 
 /// Scaling policy specification
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ScalingPolicySpec {
     pub min_replicas: u32,
     pub max_replicas: u32,
@@ -162,10 +572,12 @@ pub struct ScalingPolicySpec {
     pub target_memory: f64,
     pub scale_up_cooldown: Duration,
     pub scale_down_cooldown: Duration,
+    #[serde(default)]
+    pub strategy: ScalingStrategy,
 }
 
 /// Resource specification
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ResourceSpec {
     pub cpu_limit: Option<String>,
     pub memory_limit: Option<String>,
@@ -183,6 +595,9 @@ pub struct DeploymentStatusResult {
     pub ready_replicas: u32,
     pub available_replicas: u32,
     pub status: DeploymentStatusType,
+    /// Why a pending deployment is not progressing
+    #[serde(default)]
+    pub reason: Option<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -194,36 +609,426 @@ pub enum DeploymentStatusType {
     Running,
     Failed,
     Succeeded,
+    Paused,
     Unknown,
 }
 
 impl Orchestrator {
-    pub async fn new(config: OrchestratorConfig) -> Result<Self> {
-        let mut deployments = HashMap::new();
-        let mut services = HashMap::new();
-        
-        // Try to load existing state
-        if std::path::Path::new("data/orchestrator_state.json").exists() {
-            if let Ok(content) = std::fs::read_to_string("data/orchestrator_state.json") {
-                if let Ok(state) = serde_json::from_str::<OrchestratorState>(&content) {
-                    deployments = state.deployments;
-                    services = state.services;
-                }
+    /// Create an orchestrator whose deployments run on `backend`
+    pub async fn new(config: OrchestratorConfig, backend: Arc<dyn ContainerBackend>) -> Result<Self> {
+        let state = Self::load_state(&config).await?;
+
+        // State written before namespaces were managed only names them
+        let namespaces = NamespaceManager::new(&config.namespace, state.namespaces);
+        let referenced = state
+            .deployments
+            .values()
+            .map(|d| &d.namespace)
+            .chain(state.configs.values().map(|c| &c.namespace))
+            .chain(state.cron_jobs.values().map(|c| &c.namespace))
+            .chain(state.jobs.values().map(|j| &j.namespace));
+        for namespace in referenced {
+            namespaces.ensure_namespace(namespace).await;
+        }
+
+        let scheduler = Scheduler::new();
+        for node in state.nodes {
+            scheduler.register_node(node).await;
+        }
+        for (id, deployment) in &state.deployments {
+            if let Some(request) = deployment.spec.as_ref().and_then(|spec| PlacementRequest::from_spec(spec).ok()) {
+                scheduler.restore(id, request, deployment.nodes.clone()).await;
             }
         }
-        
+
         Ok(Self {
-            deployments: Arc::new(RwLock::new(deployments)),
-            services: Arc::new(RwLock::new(services)),
+            deployments: Arc::new(RwLock::new(state.deployments)),
+            services: Arc::new(RwLock::new(state.services)),
+            configs: Arc::new(RwLock::new(state.configs)),
+            cron_jobs: Arc::new(RwLock::new(state.cron_jobs)),
+            jobs: Arc::new(RwLock::new(state.jobs)),
+            namespaces,
+            scheduler,
             config,
+            backend,
+            save_lock: Mutex::new(()),
+            event_bus: EventBus::new(),
         })
     }
 
+    /// Publish deployment changes to the shared event bus
+    pub fn with_event_bus(mut self, event_bus: EventBus) -> Self {
+        self.event_bus = event_bus;
+        self
+    }
+
+    fn publish(&self, action: DeploymentAction, name: &str, namespace: &str, replicas: Option<u32>) {
+        self.event_bus.publish(
+            "orchestrator",
+            EventKind::Deployment {
+                action,
+                name: name.to_string(),
+                namespace: namespace.to_string(),
+                replicas,
+            },
+        );
+    }
+
+    /// Load the persisted state; a missing file is an empty state
+    async fn load_state(config: &OrchestratorConfig) -> Result<OrchestratorState> {
+        let path = config.state_path();
+        let content = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(OrchestratorState::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let loaded = match serde_json::from_str::<OrchestratorState>(&content) {
+            Ok(state) if state.version > STATE_VERSION => Err(format!(
+                "schema version {} is newer than the supported version {}",
+                state.version, STATE_VERSION
+            )),
+            Ok(state) => Ok(state),
+            Err(e) => Err(e.to_string()),
+        };
+
+        match loaded {
+            Ok(state) => Ok(state),
+            Err(reason) if config.ignore_corrupt_state => {
+                let backup = path.with_extension("json.corrupt");
+                tokio::fs::rename(&path, &backup).await?;
+                warn!(
+                    "Ignoring unreadable orchestrator state {} ({}); it was moved to {}",
+                    path.display(),
+                    reason,
+                    backup.display()
+                );
+                Ok(OrchestratorState::default())
+            }
+            Err(reason) => Err(PolisError::Config(format!(
+                "Orchestrator state {} cannot be loaded: {}; pass --ignore-corrupt-state to start empty",
+                path.display(),
+                reason
+            ))),
+        }
+    }
+
+    /// Orchestrator configuration
+    pub fn config(&self) -> &OrchestratorConfig {
+        &self.config
+    }
+
+    /// Compute what applying a manifest would do, without changing state
+    pub async fn plan(&self, manifest: &Manifest) -> Result<ApplyAction> {
+        let action = match manifest {
+            Manifest::Deployment(spec) => {
+                let deployments = self.deployments.read().await;
+                match deployments
+                    .values()
+                    .find(|d| d.name == spec.name && d.namespace == spec.namespace)
+                {
+                    None => ApplyAction::Create,
+                    Some(deployment)
+                        if deployment.pending_spec.as_ref().or(deployment.spec.as_ref()) == Some(&**spec) =>
+                    {
+                        ApplyAction::Unchanged
+                    }
+                    Some(_) => ApplyAction::Update,
+                }
+            }
+            Manifest::Config(spec) => {
+                let configs = self.configs.read().await;
+                Self::plan_stored(configs.get(&resource_id(&spec.namespace, &spec.name)), spec)
+            }
+            Manifest::CronJob(spec) => {
+                let cron_jobs = self.cron_jobs.read().await;
+                Self::plan_stored(cron_jobs.get(&resource_id(&spec.namespace, &spec.name)), spec)
+            }
+            Manifest::Job(spec) => {
+                let jobs = self.jobs.read().await;
+                Self::plan_stored(jobs.get(&resource_id(&spec.namespace, &spec.name)), spec)
+            }
+        };
+        Ok(action)
+    }
+
+    fn plan_stored<T: PartialEq>(current: Option<&T>, desired: &T) -> ApplyAction {
+        match current {
+            None => ApplyAction::Create,
+            Some(current) if current == desired => ApplyAction::Unchanged,
+            Some(_) => ApplyAction::Update,
+        }
+    }
+
+    /// Create or update a resource so that it matches the manifest
+    pub async fn apply(&self, manifest: Manifest) -> Result<ApplyAction> {
+        let action = self.plan(&manifest).await?;
+        if action == ApplyAction::Unchanged {
+            return Ok(action);
+        }
+
+        match manifest {
+            Manifest::Deployment(spec) => {
+                if action == ApplyAction::Create {
+                    self.deploy(*spec).await?;
+                } else {
+                    self.update_deployment(*spec).await?;
+                }
+            }
+            Manifest::Config(spec) => {
+                info!("Applying config '{}' in namespace '{}'", spec.name, spec.namespace);
+                let mut configs = self.configs.write().await;
+                configs.insert(resource_id(&spec.namespace, &spec.name), spec);
+                drop(configs);
+                self.save_state().await?;
+            }
+            Manifest::CronJob(spec) => {
+                info!("Applying cron job '{}' in namespace '{}'", spec.name, spec.namespace);
+                let mut cron_jobs = self.cron_jobs.write().await;
+                cron_jobs.insert(resource_id(&spec.namespace, &spec.name), spec);
+                drop(cron_jobs);
+                self.save_state().await?;
+            }
+            Manifest::Job(spec) => {
+                info!("Applying job '{}' in namespace '{}'", spec.name, spec.namespace);
+                let mut jobs = self.jobs.write().await;
+                jobs.insert(resource_id(&spec.namespace, &spec.name), spec);
+                drop(jobs);
+                self.save_state().await?;
+            }
+        }
+
+        Ok(action)
+    }
+
+    /// Update an existing deployment in place
+    async fn update_deployment(&self, spec: DeploymentSpec) -> Result<()> {
+        info!("Updating deployment '{}' in namespace '{}'", spec.name, spec.namespace);
+
+        let rootfs = spec.rootfs_config()?;
+        spec.max_runtime()?;
+        let now = chrono::Utc::now();
+
+        let mut deployments = self.deployments.write().await;
+        let deployment = deployments
+            .values_mut()
+            .find(|d| d.name == spec.name && d.namespace == spec.namespace)
+            .ok_or_else(|| PolisError::Config(format!("Deployment '{}' not found in namespace '{}'", spec.name, spec.namespace)))?;
+
+        if deployment.status == DeploymentStatus::Paused {
+            info!("Deployment '{}' is paused; the update rolls out when it is resumed", spec.name);
+            deployment.pending_spec = Some(spec);
+            deployment.updated_at = now;
+            drop(deployments);
+            return self.save_state().await;
+        }
+
+        deployment.image = spec.image.clone();
+        deployment.replicas = spec.replicas;
+        deployment.desired_replicas = spec.replicas;
+        deployment.labels = spec.labels.clone();
+        deployment.annotations = spec.annotations.clone();
+        deployment.rootfs = rootfs;
+        deployment.updated_at = now;
+        let deployment_id = deployment.id.clone();
+        deployment.record_revision(spec.clone(), now);
+        // Replicas of another template are replaced once the new ones run
+        let stale = match deployment.spec.replace(spec.clone()) {
+            Some(previous) if previous.container_template_differs(&spec) => std::mem::take(&mut deployment.containers),
+            _ => Vec::new(),
+        };
+        drop(deployments);
+
+        {
+            let mut services = self.services.write().await;
+            if let Some(service) = services.get_mut(&deployment_id) {
+                service.labels = spec.labels;
+                service.annotations = spec.annotations;
+                service.updated_at = now;
+            }
+        }
+
+        if let Err(e) = self.scale_deployment(&spec.name, &spec.namespace, spec.replicas).await {
+            if !stale.is_empty() {
+                // Keep serving from the previous replicas; listed last, they
+                // are the first to go when the deployment is scaled down
+                if let Some(deployment) = self.deployments.write().await.get_mut(&deployment_id) {
+                    deployment.containers.extend(stale);
+                }
+                self.save_state().await?;
+            }
+            return Err(e);
+        }
+
+        let mut result = Ok(());
+        for container in stale {
+            if let Err(e) = self.remove_replica(&container).await {
+                warn!("Failed to remove replaced container {} of deployment '{}': {}", container, spec.name, e);
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        self.publish(DeploymentAction::Updated, &spec.name, &spec.namespace, Some(spec.replicas));
+        result
+    }
+
+    /// Revisions of a deployment, oldest first; the last one is active
+    pub async fn get_deployment_history(&self, name: &str, namespace: &str) -> Result<Vec<DeploymentRevision>> {
+        self.deployments
+            .read()
+            .await
+            .values()
+            .find(|d| d.name == name && d.namespace == namespace)
+            .map(|d| d.revisions.clone())
+            .ok_or_else(|| PolisError::Config(format!("Deployment '{}' not found in namespace '{}'", name, namespace)))
+    }
+
+    /// Re-apply the specification of an earlier revision, the previous one
+    /// by default, and return the revision that was restored. The restored
+    /// specification is recorded as a new revision.
+    pub async fn rollback_deployment(&self, name: &str, namespace: &str, revision: Option<u32>) -> Result<u32> {
+        let history = self.get_deployment_history(name, namespace).await?;
+        let target = match revision {
+            Some(revision) => history.iter().find(|r| r.revision == revision).ok_or_else(|| {
+                PolisError::Config(format!("Revision {} of deployment '{}' not found", revision, name))
+            })?,
+            None => history.iter().rev().nth(1).ok_or_else(|| {
+                PolisError::Config(format!("Deployment '{}' has no previous revision", name))
+            })?,
+        };
+
+        info!("Rolling back deployment '{}' to revision {}", name, target.revision);
+        self.update_deployment(target.spec.clone()).await?;
+        self.publish(DeploymentAction::RolledBack, name, namespace, Some(target.spec.replicas));
+        Ok(target.revision)
+    }
+
+    /// Resources carrying the given label value
+    pub async fn list_resources_with_label(&self, key: &str, value: &str) -> Result<Vec<ResourceKey>> {
+        let matches = |labels: &HashMap<String, String>| labels.get(key).map(String::as_str) == Some(value);
+        let mut resources = Vec::new();
+
+        for deployment in self.deployments.read().await.values() {
+            if matches(&deployment.labels) {
+                resources.push(ResourceKey {
+                    kind: ResourceKind::Deployment,
+                    namespace: deployment.namespace.clone(),
+                    name: deployment.name.clone(),
+                });
+            }
+        }
+        for config in self.configs.read().await.values() {
+            if matches(&config.labels) {
+                resources.push(ResourceKey {
+                    kind: ResourceKind::Config,
+                    namespace: config.namespace.clone(),
+                    name: config.name.clone(),
+                });
+            }
+        }
+        for cron_job in self.cron_jobs.read().await.values() {
+            if matches(&cron_job.labels) {
+                resources.push(ResourceKey {
+                    kind: ResourceKind::CronJob,
+                    namespace: cron_job.namespace.clone(),
+                    name: cron_job.name.clone(),
+                });
+            }
+        }
+        for job in self.jobs.read().await.values() {
+            if matches(&job.labels) {
+                resources.push(ResourceKey {
+                    kind: ResourceKind::Job,
+                    namespace: job.namespace.clone(),
+                    name: job.name.clone(),
+                });
+            }
+        }
+
+        resources.sort();
+        Ok(resources)
+    }
+
+    /// Get a config resource
+    pub async fn get_config(&self, name: &str, namespace: &str) -> Option<ConfigSpec> {
+        self.configs.read().await.get(&resource_id(namespace, name)).cloned()
+    }
+
+    /// Get a cron job resource
+    pub async fn get_cron_job(&self, name: &str, namespace: &str) -> Option<CronJobSpec> {
+        self.cron_jobs.read().await.get(&resource_id(namespace, name)).cloned()
+    }
+
+    /// Get a job resource
+    pub async fn get_job(&self, name: &str, namespace: &str) -> Option<JobSpec> {
+        self.jobs.read().await.get(&resource_id(namespace, name)).cloned()
+    }
+
+    /// Delete any declarative resource
+    pub async fn delete_resource(&self, key: &ResourceKey) -> Result<()> {
+        let id = resource_id(&key.namespace, &key.name);
+        let removed = match key.kind {
+            ResourceKind::Deployment => return self.delete_deployment(&key.name, &key.namespace).await,
+            ResourceKind::Config => self.configs.write().await.remove(&id).is_some(),
+            ResourceKind::CronJob => self.cron_jobs.write().await.remove(&id).is_some(),
+            ResourceKind::Job => self.jobs.write().await.remove(&id).is_some(),
+        };
+
+        if !removed {
+            return Err(PolisError::Config(format!("Resource '{}' not found", key)));
+        }
+
+        info!("Resource '{}' deleted successfully", key);
+        self.save_state().await
+    }
+
     /// Deploy a new service
     pub async fn deploy(&self, spec: DeploymentSpec) -> Result<DeploymentStatusResult> {
         info!("Deploying service: {} in namespace: {}", spec.name, spec.namespace);
 
+        let rootfs = spec.rootfs_config()?;
+        spec.max_runtime()?;
+        if self.namespaces.get_namespace(&spec.namespace).await.is_none() {
+            if !self.config.auto_create_namespaces {
+                return Err(PolisError::Config(format!(
+                    "Namespace '{}' does not exist; create it first or enable auto_create_namespaces",
+                    spec.namespace
+                )));
+            }
+            info!("Creating namespace '{}' for deployment '{}'", spec.namespace, spec.name);
+            self.namespaces.ensure_namespace(&spec.namespace).await;
+        }
+        let usage = self.namespace_usage(&spec.namespace, None).await;
+        self.namespaces.check_quota(&spec.namespace, usage, 1, spec.replicas).await?;
+        let request = PlacementRequest::from_spec(&spec)?;
         let deployment_id = Uuid::new_v4().to_string();
+
+        // A deployment that fits on no node is kept pending without replicas
+        let (nodes, pending_reason) = match self.scheduler.schedule(&deployment_id, &request, spec.replicas).await {
+            Ok(nodes) => (nodes, None),
+            Err(reason) => {
+                warn!("Deployment '{}' cannot be scheduled: {}", spec.name, reason);
+                (Vec::new(), Some(reason))
+            }
+        };
+
+        // Start the replicas, removing the ones already created if any fails
+        let mut containers = Vec::new();
+        let started = match pending_reason {
+            Some(_) => Ok(()),
+            None => self.reconcile_replicas(&spec, &mut containers, spec.replicas).await,
+        };
+        if let Err(e) = started {
+            for id in &containers {
+                if let Err(cleanup) = self.remove_replica(id).await {
+                    warn!("Failed to remove container {} of failed deployment '{}': {}", id, spec.name, cleanup);
+                }
+            }
+            self.scheduler.release(&deployment_id).await;
+            return Err(e);
+        }
+        let ready = self.ready_replicas(&containers).await;
         let now = chrono::Utc::now();
 
         // Create service endpoints
@@ -264,7 +1069,7 @@ impl Orchestrator {
         }
 
         // Create deployment
-        let deployment = Deployment {
+        let mut deployment = Deployment {
             id: deployment_id.clone(),
             name: spec.name.clone(),
             namespace: spec.namespace.clone(),
@@ -277,9 +1082,19 @@ impl Orchestrator {
             status: DeploymentStatus::Pending,
             created_at: now,
             updated_at: now,
-            labels: spec.labels,
-            annotations: spec.annotations,
+            labels: spec.labels.clone(),
+            annotations: spec.annotations.clone(),
+            rootfs,
+            spec: Some(spec.clone()),
+            containers: Vec::new(),
+            revisions: Vec::new(),
+            pending_spec: None,
+            nodes,
+            pending_reason,
         };
+        deployment.record_revision(spec.clone(), now);
+        deployment.set_containers(containers, ready);
+        let status = deployment.status_result();
 
         // Store deployment
         {
@@ -287,20 +1102,9 @@ impl Orchestrator {
             deployments.insert(deployment_id.clone(), deployment);
         }
 
-        let status = DeploymentStatusResult {
-            name: spec.name.clone(),
-            namespace: spec.namespace.clone(),
-            desired_replicas: spec.replicas,
-            current_replicas: 0,
-            ready_replicas: 0,
-            available_replicas: 0,
-            status: DeploymentStatusType::Pending,
-            created_at: now,
-            updated_at: now,
-        };
-
         // Save state to disk
         self.save_state().await?;
+        self.publish(DeploymentAction::Created, &spec.name, &spec.namespace, Some(spec.replicas));
         
         info!("Service '{}' deployed successfully", spec.name);
         Ok(status)
@@ -312,25 +1116,7 @@ impl Orchestrator {
         
         for deployment in deployments.values() {
             if deployment.name == name && deployment.namespace == namespace {
-                let status = DeploymentStatusResult {
-                    name: deployment.name.clone(),
-                    namespace: deployment.namespace.clone(),
-                    desired_replicas: deployment.desired_replicas,
-                    current_replicas: deployment.replicas,
-                    ready_replicas: deployment.ready_replicas,
-                    available_replicas: deployment.available_replicas,
-                    status: match deployment.status {
-                        DeploymentStatus::Pending => DeploymentStatusType::Pending,
-                        DeploymentStatus::Running => DeploymentStatusType::Running,
-                        DeploymentStatus::Failed => DeploymentStatusType::Failed,
-                        DeploymentStatus::Scaling => DeploymentStatusType::Running,
-                        DeploymentStatus::Paused => DeploymentStatusType::Unknown,
-                    },
-                    created_at: deployment.created_at,
-                    updated_at: deployment.updated_at,
-                };
-                
-                return Ok(Some(status));
+                return Ok(Some(deployment.status_result()));
             }
         }
         
@@ -360,24 +1146,217 @@ impl Orchestrator {
     /// Scale a deployment
     pub async fn scale_deployment(&self, name: &str, namespace: &str, replicas: u32) -> Result<()> {
         info!("Scaling deployment '{}' to {} replicas", name, replicas);
-        
-        let mut deployments = self.deployments.write().await;
-        for deployment in deployments.values_mut() {
-            if deployment.name == name && deployment.namespace == namespace {
-                deployment.replicas = replicas;
-                deployment.desired_replicas = replicas;
-                deployment.updated_at = chrono::Utc::now();
-                
-                // Save state to disk
-                drop(deployments);
-                self.save_state().await?;
-                
-                info!("Deployment '{}' scaled to {} replicas", name, replicas);
-                return Ok(());
+
+        let not_found = || PolisError::Config(format!("Deployment '{}' not found in namespace '{}'", name, namespace));
+        let (id, spec, mut containers, current) = {
+            let deployments = self.deployments.read().await;
+            let (id, deployment) = deployments
+                .iter()
+                .find(|(_, d)| d.name == name && d.namespace == namespace)
+                .ok_or_else(not_found)?;
+            let spec = deployment.spec.clone().ok_or_else(|| {
+                PolisError::Config(format!("Deployment '{}' has no recorded specification", name))
+            })?;
+            (id.clone(), spec, deployment.containers.clone(), deployment.desired_replicas)
+        };
+
+        if replicas > current {
+            let usage = self.namespace_usage(namespace, Some(&id)).await;
+            self.namespaces.check_quota(namespace, usage, 0, replicas).await?;
+        }
+
+        let request = PlacementRequest::from_spec(&spec)?;
+        let nodes = self
+            .scheduler
+            .schedule(&id, &request, replicas)
+            .await
+            .map_err(|reason| PolisError::Config(format!("Deployment '{}' cannot be scheduled: {}", name, reason)))?;
+
+        let converged = self.reconcile_replicas(&spec, &mut containers, replicas).await;
+        let ready = self.ready_replicas(&containers).await;
+
+        {
+            let mut deployments = self.deployments.write().await;
+            let deployment = deployments.get_mut(&id).ok_or_else(not_found)?;
+            deployment.nodes = nodes;
+            deployment.pending_reason = None;
+            deployment.replicas = replicas;
+            deployment.desired_replicas = replicas;
+            deployment.set_containers(containers, ready);
+            if converged.is_err() && deployment.status != DeploymentStatus::Paused {
+                deployment.status = DeploymentStatus::Failed;
             }
+            deployment.updated_at = chrono::Utc::now();
         }
-        
-        Err(PolisError::Config(format!("Deployment '{}' not found in namespace '{}'", name, namespace)))
+
+        // Save state to disk
+        self.save_state().await?;
+        converged?;
+        self.publish(DeploymentAction::Scaled, name, namespace, Some(replicas));
+
+        info!("Deployment '{}' scaled to {} replicas", name, replicas);
+        Ok(())
+    }
+
+    /// Pause a deployment: updates are recorded but not rolled out until it
+    /// is resumed
+    pub async fn pause_deployment(&self, name: &str, namespace: &str) -> Result<()> {
+        info!("Pausing deployment '{}' in namespace '{}'", name, namespace);
+
+        {
+            let mut deployments = self.deployments.write().await;
+            let deployment = deployments
+                .values_mut()
+                .find(|d| d.name == name && d.namespace == namespace)
+                .ok_or_else(|| PolisError::Config(format!("Deployment '{}' not found in namespace '{}'", name, namespace)))?;
+            deployment.status = DeploymentStatus::Paused;
+            deployment.updated_at = chrono::Utc::now();
+        }
+
+        self.save_state().await?;
+        self.publish(DeploymentAction::Paused, name, namespace, None);
+        Ok(())
+    }
+
+    /// Resume a paused deployment and reconcile it right away, rolling out
+    /// the update applied while it was paused, if any
+    pub async fn resume_deployment(&self, name: &str, namespace: &str) -> Result<()> {
+        info!("Resuming deployment '{}' in namespace '{}'", name, namespace);
+
+        let (pending, replicas) = {
+            let mut deployments = self.deployments.write().await;
+            let deployment = deployments
+                .values_mut()
+                .find(|d| d.name == name && d.namespace == namespace)
+                .ok_or_else(|| PolisError::Config(format!("Deployment '{}' not found in namespace '{}'", name, namespace)))?;
+            if deployment.status == DeploymentStatus::Paused {
+                deployment.status = DeploymentStatus::Pending;
+            }
+            (deployment.pending_spec.take(), deployment.desired_replicas)
+        };
+
+        match pending {
+            Some(spec) => self.update_deployment(spec).await?,
+            None => self.scale_deployment(name, namespace, replicas).await?,
+        }
+        self.publish(DeploymentAction::Resumed, name, namespace, Some(replicas));
+        Ok(())
+    }
+
+    /// Register a node replicas can be placed on, or update it, and retry
+    /// the deployments no node could take so far
+    pub async fn register_node(&self, node: Node) -> Result<()> {
+        info!("Registering node '{}'", node.id);
+        self.scheduler.register_node(node).await;
+        self.save_state().await?;
+
+        let pending: Vec<(String, String, u32)> = self
+            .deployments
+            .read()
+            .await
+            .values()
+            .filter(|d| d.pending_reason.is_some() && d.status != DeploymentStatus::Paused)
+            .map(|d| (d.name.clone(), d.namespace.clone(), d.desired_replicas))
+            .collect();
+        for (name, namespace, replicas) in pending {
+            if let Err(e) = self.scale_deployment(&name, &namespace, replicas).await {
+                warn!("Deployment '{}' is still pending: {}", name, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a node; replicas already placed on it are left running
+    pub async fn remove_node(&self, id: &str) -> Result<()> {
+        self.scheduler
+            .remove_node(id)
+            .await
+            .ok_or_else(|| PolisError::Config(format!("Node '{}' not found", id)))?;
+        self.save_state().await
+    }
+
+    /// Registered nodes sorted by id
+    pub async fn list_nodes(&self) -> Vec<Node> {
+        self.scheduler.list_nodes().await
+    }
+
+    /// Create a namespace with the given quota
+    pub async fn create_namespace(&self, name: &str, quota: NamespaceQuota) -> Result<Namespace> {
+        let namespace = self.namespaces.create_namespace(name, quota).await?;
+        self.save_state().await?;
+        info!("Namespace '{}' created", name);
+        Ok(namespace)
+    }
+
+    /// List namespaces sorted by name
+    pub async fn list_namespaces(&self) -> Vec<Namespace> {
+        self.namespaces.list_namespaces().await
+    }
+
+    /// Delete a namespace. With `cascade` every resource in it is deleted
+    /// first; otherwise the namespace must be empty.
+    pub async fn delete_namespace(&self, name: &str, cascade: bool) -> Result<()> {
+        if name == self.config.namespace {
+            return Err(PolisError::Config(format!("The default namespace '{}' cannot be deleted", name)));
+        }
+        if self.namespaces.get_namespace(name).await.is_none() {
+            return Err(PolisError::Config(format!("Namespace '{}' not found", name)));
+        }
+
+        let resources = self.namespace_resources(name).await;
+        if !resources.is_empty() && !cascade {
+            return Err(PolisError::Config(format!(
+                "Namespace '{}' still holds {} resource(s); delete them or use cascade",
+                name,
+                resources.len()
+            )));
+        }
+        for key in &resources {
+            self.delete_resource(key).await?;
+        }
+
+        self.namespaces.delete_namespace(name).await?;
+        self.save_state().await?;
+        info!("Namespace '{}' deleted with {} resource(s)", name, resources.len());
+        Ok(())
+    }
+
+    /// Every declarative resource in a namespace
+    async fn namespace_resources(&self, namespace: &str) -> Vec<ResourceKey> {
+        let key = |kind, name: &String| ResourceKey {
+            kind,
+            namespace: namespace.to_string(),
+            name: name.clone(),
+        };
+        let mut resources = Vec::new();
+        for deployment in self.deployments.read().await.values().filter(|d| d.namespace == namespace) {
+            resources.push(key(ResourceKind::Deployment, &deployment.name));
+        }
+        for config in self.configs.read().await.values().filter(|c| c.namespace == namespace) {
+            resources.push(key(ResourceKind::Config, &config.name));
+        }
+        for cron_job in self.cron_jobs.read().await.values().filter(|c| c.namespace == namespace) {
+            resources.push(key(ResourceKind::CronJob, &cron_job.name));
+        }
+        for job in self.jobs.read().await.values().filter(|j| j.namespace == namespace) {
+            resources.push(key(ResourceKind::Job, &job.name));
+        }
+        resources.sort();
+        resources
+    }
+
+    /// Deployments and desired replicas in a namespace, leaving out the
+    /// deployment being resized
+    async fn namespace_usage(&self, namespace: &str, excluding: Option<&str>) -> NamespaceUsage {
+        let deployments = self.deployments.read().await;
+        let mut usage = NamespaceUsage::default();
+        for (id, deployment) in deployments.iter() {
+            if deployment.namespace == namespace && Some(id.as_str()) != excluding {
+                usage.deployments += 1;
+                usage.replicas = usage.replicas.saturating_add(deployment.desired_replicas);
+            }
+        }
+        usage
     }
 
     /// Delete a deployment
@@ -395,13 +1374,28 @@ impl Orchestrator {
         }
         
         if let Some(id) = to_remove {
-            // Remove deployment
-            deployments.remove(&id);
-            
-            // Save state to disk
+            // Remove deployment and its service
+            let deployment = deployments.remove(&id);
             drop(deployments);
+            self.services.write().await.remove(&id);
+            self.scheduler.release(&id).await;
+
+            // Tear down every replica, reporting the first failure
+            let mut result = Ok(());
+            for container in deployment.map(|d| d.containers).unwrap_or_default() {
+                if let Err(e) = self.remove_replica(&container).await {
+                    warn!("Failed to remove container {} of deployment '{}': {}", container, name, e);
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+            }
+
+            // Save state to disk
             self.save_state().await?;
-            
+            result?;
+            self.publish(DeploymentAction::Deleted, name, namespace, None);
+
             info!("Deployment '{}' deleted successfully", name);
             Ok(())
         } else {
@@ -438,23 +1432,83 @@ impl Orchestrator {
         })
     }
 
+    /// Desired and ready replicas of every deployment, for the Prometheus exporter
+    pub async fn deployment_gauges(&self) -> Vec<DeploymentGauge> {
+        let deployments = self.deployments.read().await;
+        let mut gauges: Vec<DeploymentGauge> = deployments
+            .values()
+            .map(|deployment| DeploymentGauge {
+                deployment: deployment.name.clone(),
+                namespace: deployment.namespace.clone(),
+                replicas_desired: deployment.desired_replicas,
+                replicas_ready: deployment.ready_replicas,
+            })
+            .collect();
+        gauges.sort_by(|a, b| (&a.namespace, &a.deployment).cmp(&(&b.namespace, &b.deployment)));
+        gauges
+    }
+
+    /// Create or remove containers until `containers` holds `desired` replicas.
+    /// On error `containers` still lists every container that exists.
+    async fn reconcile_replicas(&self, spec: &DeploymentSpec, containers: &mut Vec<ContainerId>, desired: u32) -> Result<()> {
+        let desired = desired as usize;
+        while containers.len() < desired {
+            let name = format!("{}-{}", spec.name, &Uuid::new_v4().simple().to_string()[..8]);
+            let id = self.backend.create(name, spec.image.clone(), spec.container_options()?).await?;
+            containers.push(id.clone());
+            self.backend.start(&id).await?;
+        }
+        // Newest replicas go first
+        while let Some(id) = containers.last().filter(|_| containers.len() > desired).cloned() {
+            self.remove_replica(&id).await?;
+            containers.pop();
+        }
+        Ok(())
+    }
+
+    /// Stop a replica if it is still running and remove it
+    async fn remove_replica(&self, id: &ContainerId) -> Result<()> {
+        match self.backend.status(id).await {
+            Ok(ContainerStatus::Running | ContainerStatus::Paused) => self.backend.stop(id).await?,
+            Ok(_) => {}
+            Err(e) => {
+                // Already gone, e.g. the runtime restarted since it was created
+                warn!("Container {} not found, skipping removal: {}", id, e);
+                return Ok(());
+            }
+        }
+        self.backend.remove(id).await
+    }
+
+    /// Number of replicas whose container is running
+    async fn ready_replicas(&self, containers: &[ContainerId]) -> u32 {
+        let mut ready = 0;
+        for id in containers {
+            if matches!(self.backend.status(id).await, Ok(ContainerStatus::Running)) {
+                ready += 1;
+            }
+        }
+        ready
+    }
+
     /// Save orchestrator state to disk
     async fn save_state(&self) -> Result<()> {
-        let deployments = self.deployments.read().await;
-        let services = self.services.read().await;
-        
+        // Snapshot under the lock so saves land in the order they were taken
+        let _guard = self.save_lock.lock().await;
+
         let state = OrchestratorState {
-            deployments: deployments.clone(),
-            services: services.clone(),
+            version: STATE_VERSION,
+            deployments: self.deployments.read().await.clone(),
+            services: self.services.read().await.clone(),
+            configs: self.configs.read().await.clone(),
+            cron_jobs: self.cron_jobs.read().await.clone(),
+            jobs: self.jobs.read().await.clone(),
+            namespaces: self.namespaces.snapshot().await,
+            nodes: self.scheduler.list_nodes().await,
         };
-        
-        // Ensure data directory exists
-        std::fs::create_dir_all("data")?;
-        
-        let content = serde_json::to_string_pretty(&state)?;
-        std::fs::write("data/orchestrator_state.json", content)?;
-        
-        Ok(())
+
+        let content = serde_json::to_vec_pretty(&state)?;
+        write_atomic(&self.config.state_path(), &content).await
     }
 
 }
@@ -472,8 +1526,41 @@ pub struct OrchestratorStats {
 }
 
 /// Orchestrator state for persistence
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct OrchestratorState {
+    /// Layout version; files written before versioning read as 0
+    #[serde(default)]
+    version: u32,
     deployments: HashMap<String, Deployment>,
     services: HashMap<String, Service>,
+    #[serde(default)]
+    configs: HashMap<String, ConfigSpec>,
+    #[serde(default)]
+    cron_jobs: HashMap<String, CronJobSpec>,
+    #[serde(default)]
+    jobs: HashMap<String, JobSpec>,
+    #[serde(default)]
+    namespaces: HashMap<String, Namespace>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+}
+
+/// Write through a temporary file renamed over `path`, so that a crash
+/// mid-write leaves either the old or the new contents, never a mix
+pub(crate) async fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let temp = path.with_extension(format!("{}.tmp", extension));
+    let mut file = tokio::fs::File::create(&temp).await?;
+    file.write_all(content).await?;
+    file.sync_all().await?;
+    drop(file);
+    tokio::fs::rename(&temp, path).await?;
+    Ok(())
+}
+
+fn resource_id(namespace: &str, name: &str) -> String {
+    format!("{}/{}", namespace, name)
 }
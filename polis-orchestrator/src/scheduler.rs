@@ -1,17 +1,404 @@
-use polis_core::Result;
+use crate::orchestrator::DeploymentSpec;
+use polis_core::{parse_size, PolisError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
 
+/// Annotation listing the node labels a deployment requires, as
+/// comma-separated `key=value` pairs
+pub const NODE_SELECTOR_ANNOTATION: &str = "polis.io/node-selector";
+
+/// Node replicas can be placed on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    pub id: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    /// Millicores available to replicas
+    pub allocatable_cpu: u64,
+    /// Bytes available to replicas
+    pub allocatable_memory: u64,
+}
+
+/// What one replica needs from the node it is placed on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlacementRequest {
+    pub node_selector: HashMap<String, String>,
+    /// Millicores
+    pub cpu: u64,
+    /// Bytes
+    pub memory: u64,
+}
+
+impl PlacementRequest {
+    /// Read the node selector annotation and the cpu/memory requests of a
+    /// deployment; missing requests count as zero
+    pub fn from_spec(spec: &DeploymentSpec) -> Result<Self> {
+        let mut node_selector = HashMap::new();
+        if let Some(selector) = spec.annotations.get(NODE_SELECTOR_ANNOTATION) {
+            for pair in selector.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                let (key, value) = pair.split_once('=').ok_or_else(|| {
+                    PolisError::Config(format!(
+                        "Invalid {} entry '{}': expected key=value",
+                        NODE_SELECTOR_ANNOTATION, pair
+                    ))
+                })?;
+                node_selector.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+
+        let resources = spec.resources.as_ref();
+        let cpu = match resources.and_then(|r| r.cpu_request.as_deref()) {
+            Some(cpu) => parse_cpu(cpu)?,
+            None => 0,
+        };
+        let memory = match resources.and_then(|r| r.memory_request.as_deref()) {
+            // Kubernetes-style binary suffixes (`256Mi`) are accepted too
+            Some(memory) => parse_size(memory.strip_suffix('i').unwrap_or(memory))?,
+            None => 0,
+        };
+
+        Ok(Self {
+            node_selector,
+            cpu,
+            memory,
+        })
+    }
+}
+
+/// Parse a CPU quantity in cores (`2`, `0.5`) or millicores (`500m`)
+fn parse_cpu(value: &str) -> Result<u64> {
+    let invalid = || PolisError::Config(format!("Invalid CPU quantity: {}", value));
+    let value = value.trim();
+    if let Some(millis) = value.strip_suffix('m') {
+        return millis.parse().map_err(|_| invalid());
+    }
+    let cores: f64 = value.parse().map_err(|_| invalid())?;
+    if !cores.is_finite() || cores < 0.0 {
+        return Err(invalid());
+    }
+    Ok((cores * 1000.0).round() as u64)
+}
+
+/// Nodes a deployment's replicas are placed on, with the request they were
+/// placed for
+#[derive(Debug, Clone)]
+struct Placement {
+    request: PlacementRequest,
+    nodes: Vec<String>,
+}
+
+/// Node registry placing replicas by node selector and least-allocated
+/// resources
 #[derive(Default)]
 pub struct Scheduler {
-    // Scheduler state
+    nodes: RwLock<HashMap<String, Node>>,
+    /// Placements by owning deployment
+    placements: RwLock<HashMap<String, Placement>>,
 }
 
 impl Scheduler {
     pub fn new() -> Self {
-        Self {}
+        Self::default()
+    }
+
+    /// Add a node or replace the one with the same id
+    pub async fn register_node(&self, node: Node) {
+        self.nodes.write().await.insert(node.id.clone(), node);
+    }
+
+    /// Forget a node; replicas already placed on it keep their reservation
+    pub async fn remove_node(&self, id: &str) -> Option<Node> {
+        self.nodes.write().await.remove(id)
+    }
+
+    /// Nodes sorted by id
+    pub async fn list_nodes(&self) -> Vec<Node> {
+        let mut nodes: Vec<Node> = self.nodes.read().await.values().cloned().collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        nodes
+    }
+
+    /// Place `replicas` replicas of `owner`, returning the node of each.
+    ///
+    /// Replicas already placed for the same request stay where they are and
+    /// the newest go first when shrinking; a changed request places every
+    /// replica again. Nothing changes when a replica fits nowhere and the
+    /// reason is returned instead. Without registered nodes placement is
+    /// skipped and no node is returned.
+    pub async fn schedule(
+        &self,
+        owner: &str,
+        request: &PlacementRequest,
+        replicas: u32,
+    ) -> std::result::Result<Vec<String>, String> {
+        let nodes = self.list_nodes().await;
+        let mut placements = self.placements.write().await;
+        let previous = placements.remove(owner);
+        if nodes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut placed = match &previous {
+            Some(previous) if previous.request == *request => previous.nodes.clone(),
+            _ => Vec::new(),
+        };
+        placed.truncate(replicas as usize);
+
+        let mut allocated = allocations(placements.values());
+        for node in &placed {
+            allocate(&mut allocated, node, request);
+        }
+        while placed.len() < replicas as usize {
+            match select_node(&nodes, &allocated, request) {
+                Ok(node) => {
+                    allocate(&mut allocated, &node.id, request);
+                    placed.push(node.id.clone());
+                }
+                Err(reason) => {
+                    if let Some(previous) = previous {
+                        placements.insert(owner.to_string(), previous);
+                    }
+                    return Err(reason);
+                }
+            }
+        }
+
+        placements.insert(
+            owner.to_string(),
+            Placement {
+                request: request.clone(),
+                nodes: placed.clone(),
+            },
+        );
+        Ok(placed)
+    }
+
+    /// Reserve placements made earlier, e.g. when reloading persisted state
+    pub async fn restore(&self, owner: &str, request: PlacementRequest, nodes: Vec<String>) {
+        if nodes.is_empty() {
+            return;
+        }
+        self.placements
+            .write()
+            .await
+            .insert(owner.to_string(), Placement { request, nodes });
+    }
+
+    /// Free every reservation of `owner`
+    pub async fn release(&self, owner: &str) {
+        self.placements.write().await.remove(owner);
+    }
+
+    /// Millicores and bytes reserved on each node
+    pub async fn allocated(&self) -> HashMap<String, (u64, u64)> {
+        allocations(self.placements.read().await.values())
+    }
+}
+
+fn allocations<'a>(placements: impl Iterator<Item = &'a Placement>) -> HashMap<String, (u64, u64)> {
+    let mut allocated = HashMap::new();
+    for placement in placements {
+        for node in &placement.nodes {
+            allocate(&mut allocated, node, &placement.request);
+        }
+    }
+    allocated
+}
+
+fn allocate(allocated: &mut HashMap<String, (u64, u64)>, node: &str, request: &PlacementRequest) {
+    let entry = allocated.entry(node.to_string()).or_default();
+    entry.0 = entry.0.saturating_add(request.cpu);
+    entry.1 = entry.1.saturating_add(request.memory);
+}
+
+/// Pick the node a replica fits on that would be left with the largest
+/// share of free cpu and memory; ties go to the lowest node id. `nodes`
+/// must be sorted by id.
+fn select_node<'a>(
+    nodes: &'a [Node],
+    allocated: &HashMap<String, (u64, u64)>,
+    request: &PlacementRequest,
+) -> std::result::Result<&'a Node, String> {
+    let mut best: Option<(&Node, f64)> = None;
+    let (mut unmatched, mut no_cpu, mut no_memory) = (0, 0, 0);
+
+    for node in nodes {
+        let selected = request
+            .node_selector
+            .iter()
+            .all(|(key, value)| node.labels.get(key) == Some(value));
+        if !selected {
+            unmatched += 1;
+            continue;
+        }
+
+        let (cpu, memory) = allocated.get(&node.id).copied().unwrap_or_default();
+        let cpu = cpu.saturating_add(request.cpu);
+        let memory = memory.saturating_add(request.memory);
+        if cpu > node.allocatable_cpu {
+            no_cpu += 1;
+            continue;
+        }
+        if memory > node.allocatable_memory {
+            no_memory += 1;
+            continue;
+        }
+
+        let score = (free_share(cpu, node.allocatable_cpu)
+            + free_share(memory, node.allocatable_memory))
+            / 2.0;
+        if best.map_or(f64::NEG_INFINITY, |(_, best_score)| best_score) < score {
+            best = Some((node, score));
+        }
+    }
+
+    if let Some((node, _)) = best {
+        return Ok(node);
+    }
+    let mut causes = Vec::new();
+    for (count, cause) in [
+        (unmatched, "did not match the node selector"),
+        (no_cpu, "had insufficient cpu"),
+        (no_memory, "had insufficient memory"),
+    ] {
+        if count > 0 {
+            causes.push(format!("{} node(s) {}", count, cause));
+        }
+    }
+    Err(format!(
+        "0/{} nodes are available: {}",
+        nodes.len(),
+        causes.join(", ")
+    ))
+}
+
+/// Fraction of `allocatable` left once `used` is taken
+fn free_share(used: u64, allocatable: u64) -> f64 {
+    if allocatable == 0 {
+        return 0.0;
+    }
+    (allocatable - used) as f64 / allocatable as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GI: u64 = 1024 * 1024 * 1024;
+
+    fn node(id: &str, labels: &[(&str, &str)], cpu: u64, memory: u64) -> Node {
+        Node {
+            id: id.to_string(),
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            allocatable_cpu: cpu,
+            allocatable_memory: memory,
+        }
+    }
+
+    fn request(selector: &[(&str, &str)], cpu: u64, memory: u64) -> PlacementRequest {
+        PlacementRequest {
+            node_selector: selector
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            cpu,
+            memory,
+        }
+    }
+
+    async fn scheduler(nodes: Vec<Node>) -> Scheduler {
+        let scheduler = Scheduler::new();
+        for node in nodes {
+            scheduler.register_node(node).await;
+        }
+        scheduler
+    }
+
+    #[tokio::test]
+    async fn test_filters_by_node_selector() {
+        let scheduler = scheduler(vec![
+            node("a", &[("disk", "hdd")], 4000, 8 * GI),
+            node("b", &[("disk", "ssd"), ("zone", "east")], 4000, 8 * GI),
+            node("c", &[("disk", "ssd")], 4000, 8 * GI),
+        ])
+        .await;
+
+        let ssd = request(&[("disk", "ssd"), ("zone", "east")], 500, GI);
+        let placed = scheduler.schedule("db", &ssd, 2).await.unwrap();
+        assert_eq!(placed, vec!["b", "b"]);
+        assert_eq!(scheduler.allocated().await["b"], (1000, 2 * GI));
+    }
+
+    #[tokio::test]
+    async fn test_spreads_by_least_allocated_and_breaks_ties_by_id() {
+        let scheduler = scheduler(vec![
+            node("b", &[], 2000, 4 * GI),
+            node("a", &[], 2000, 4 * GI),
+            node("c", &[], 4000, 8 * GI),
+        ])
+        .await;
+
+        // c has the most room; a and b then tie and the lowest id wins
+        let web = request(&[], 1000, 2 * GI);
+        let placed = scheduler.schedule("web", &web, 4).await.unwrap();
+        assert_eq!(placed, vec!["c", "a", "b", "c"]);
+
+        // Shrinking keeps the oldest replicas where they are
+        let placed = scheduler.schedule("web", &web, 2).await.unwrap();
+        assert_eq!(placed, vec!["c", "a"]);
+        assert!(!scheduler.allocated().await.contains_key("b"));
+    }
+
+    #[tokio::test]
+    async fn test_unschedulable_request_keeps_previous_placement() {
+        let scheduler = scheduler(vec![
+            node("a", &[("disk", "ssd")], 1000, 2 * GI),
+            node("b", &[], 4000, GI),
+        ])
+        .await;
+
+        let web = request(&[], 800, 512 * 1024 * 1024);
+        assert_eq!(
+            scheduler.schedule("web", &web, 2).await.unwrap(),
+            vec!["b", "a"]
+        );
+
+        let error = scheduler.schedule("web", &web, 4).await.unwrap_err();
+        assert_eq!(
+            error,
+            "0/2 nodes are available: 1 node(s) had insufficient cpu, 1 node(s) had insufficient memory"
+        );
+        let error = scheduler
+            .schedule("cache", &request(&[("disk", "nvme")], 0, 0), 1)
+            .await
+            .unwrap_err();
+        assert!(error.contains("2 node(s) did not match the node selector"));
+        assert_eq!(scheduler.allocated().await.len(), 2);
+
+        scheduler.release("web").await;
+        assert!(scheduler.allocated().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_without_nodes_placement_is_skipped() {
+        let scheduler = Scheduler::new();
+        let placed = scheduler
+            .schedule("web", &request(&[("disk", "ssd")], 64000, 0), 3)
+            .await
+            .unwrap();
+        assert!(placed.is_empty());
     }
 
-    pub async fn schedule(&self) -> Result<()> {
-        // TODO: Implement scheduling logic
-        Ok(())
+    #[test]
+    fn test_parse_cpu() {
+        assert_eq!(parse_cpu("500m").unwrap(), 500);
+        assert_eq!(parse_cpu("1.5").unwrap(), 1500);
+        assert_eq!(parse_cpu("2").unwrap(), 2000);
+        assert!(parse_cpu("lots").is_err());
+        assert!(parse_cpu("-1").is_err());
     }
 }
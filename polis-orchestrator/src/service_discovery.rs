@@ -1,20 +1,46 @@
+use crate::load_balancer::LoadBalancer;
+use crate::EVENT_CHANNEL_CAPACITY;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
 // use polis_core::{PolisError, Result as PolisResult};
 
-/// Service discovery manager
+/// Domain under which services resolve as `<name>.<namespace>.<domain>`
+pub const DEFAULT_DNS_DOMAIN: &str = "polis";
+
+/// TTL, in seconds, of the DNS records of services without a health check
+pub const DEFAULT_DNS_TTL: u32 = 30;
+
+/// Largest DNS response sent over UDP; larger ones are truncated so that
+/// the client retries over TCP
+const MAX_UDP_RESPONSE: usize = 512;
+
+const DNS_TYPE_A: u16 = 1;
+const DNS_TYPE_SRV: u16 = 33;
+const DNS_CLASS_IN: u16 = 1;
+const DNS_FORMAT_ERROR: u8 = 1;
+const DNS_NAME_ERROR: u8 = 3;
+const DNS_NOT_IMPLEMENTED: u8 = 4;
+
+/// Service discovery manager. Clones share the services and their events.
+#[derive(Clone)]
 pub struct ServiceDiscovery {
     services: Arc<RwLock<HashMap<String, Service>>>,
     health_checker: Arc<HealthChecker>,
     dns_resolver: Arc<DnsResolver>,
-    event_sender: Arc<tokio::sync::mpsc::UnboundedSender<ServiceEvent>>,
+    event_sender: Arc<broadcast::Sender<ServiceEvent>>,
+    /// Load balancers in front of each service, by service id
+    load_balancers: Arc<RwLock<HashMap<String, Vec<LoadBalancer>>>>,
 }
 
 /// Service definition
@@ -64,6 +90,8 @@ pub enum HealthStatus {
     Healthy,
     Unhealthy,
     Unknown,
+    /// Taking no new requests until it is removed
+    Draining,
 }
 
 /// Protocol types
@@ -108,6 +136,11 @@ pub enum LoadBalancingAlgorithm {
     Random,
     IpHash,
     ConsistentHash,
+    /// Lowest moving average of response times among endpoints below the
+    /// connection cap
+    LeastResponseTime,
+    /// Lowest peak-EWMA latency weighted by outstanding requests
+    PeakEwma,
 }
 
 /// Service event
@@ -137,6 +170,24 @@ pub enum ServiceEvent {
     },
 }
 
+/// Requirement on one label of a service
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum LabelRequirement {
+    /// The label is set to `value`
+    Equals { key: String, value: String },
+    /// The label is set to one of `values`
+    In { key: String, values: Vec<String> },
+    /// The label is missing or set to none of `values`
+    NotIn { key: String, values: Vec<String> },
+}
+
+/// Selects the services whose labels meet every requirement; an empty
+/// selector selects every service
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LabelSelector {
+    pub requirements: Vec<LabelRequirement>,
+}
+
 /// Health checker
 pub struct HealthChecker {
     client: reqwest::Client,
@@ -149,6 +200,53 @@ pub struct DnsResolver {
     ttl: Duration,
 }
 
+/// DNS server answering for the services of a `ServiceDiscovery`; it stops
+/// when dropped
+pub struct ServiceDnsServer {
+    local_addr: SocketAddr,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+/// Names served by a DNS server, backed by the live registry
+struct ServiceZone {
+    services: Arc<RwLock<HashMap<String, Service>>>,
+    /// Lowercase, without leading or trailing dots
+    domain: String,
+}
+
+/// Question of a DNS query
+struct DnsQuestion {
+    /// Lowercase, without the trailing dot
+    name: String,
+    record_type: u16,
+    /// The question as sent, echoed in the response
+    wire: Vec<u8>,
+}
+
+/// Outcome of a DNS lookup
+#[derive(Default)]
+struct DnsLookup {
+    response_code: u8,
+    answers: Vec<ResourceRecord>,
+    additional: Vec<ResourceRecord>,
+}
+
+struct ResourceRecord {
+    name: String,
+    ttl: u32,
+    data: RecordData,
+}
+
+enum RecordData {
+    A(Ipv4Addr),
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+}
+
 /// DNS record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DnsRecord {
@@ -159,13 +257,14 @@ pub struct DnsRecord {
 
 impl ServiceDiscovery {
     pub fn new() -> Self {
-        let (event_sender, _event_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let (event_sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
 
         Self {
             services: Arc::new(RwLock::new(HashMap::new())),
             health_checker: Arc::new(HealthChecker::new()),
             dns_resolver: Arc::new(DnsResolver::new()),
             event_sender: Arc::new(event_sender),
+            load_balancers: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -273,6 +372,119 @@ impl ServiceDiscovery {
         Ok(())
     }
 
+    /// Record the health of an endpoint, e.g. one probed elsewhere
+    pub async fn set_endpoint_health(
+        &self,
+        service_id: &str,
+        endpoint_id: &str,
+        status: HealthStatus,
+    ) -> Result<()> {
+        let mut services = self.services.write().await;
+        let endpoint = services
+            .get_mut(service_id)
+            .and_then(|service| service.endpoints.iter_mut().find(|ep| ep.id == endpoint_id))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Endpoint {} not found in service {}",
+                    endpoint_id,
+                    service_id
+                )
+            })?;
+        endpoint.last_health_check = Some(Utc::now());
+        if endpoint.health_status == status {
+            return Ok(());
+        }
+        endpoint.health_status = status.clone();
+        drop(services);
+
+        let _ = self.event_sender.send(ServiceEvent::HealthStatusChanged {
+            service_id: service_id.to_string(),
+            endpoint_id: endpoint_id.to_string(),
+            status,
+        });
+        Ok(())
+    }
+
+    /// Let `drain_endpoint` wait for the connections `load_balancer` has
+    /// open to the endpoints of a service
+    pub async fn attach_load_balancer(&self, service_id: &str, load_balancer: LoadBalancer) {
+        let mut load_balancers = self.load_balancers.write().await;
+        load_balancers
+            .entry(service_id.to_string())
+            .or_default()
+            .push(load_balancer);
+    }
+
+    /// Stop handing out an endpoint and remove it once the connections the
+    /// attached load balancers have open to it have finished, or when
+    /// `deadline` expires. Without a load balancer the open connections are
+    /// unknown, so the endpoint stays until the deadline.
+    ///
+    /// The endpoint is `Draining` meanwhile. The returned handle resolves
+    /// after it was removed and `EndpointRemoved` was sent.
+    pub async fn drain_endpoint(
+        &self,
+        service_id: &str,
+        endpoint_id: &str,
+        deadline: Duration,
+    ) -> Result<JoinHandle<()>> {
+        self.set_endpoint_health(service_id, endpoint_id, HealthStatus::Draining)
+            .await?;
+
+        let load_balancers = self
+            .load_balancers
+            .read()
+            .await
+            .get(service_id)
+            .cloned()
+            .unwrap_or_default();
+        let mut drains = Vec::with_capacity(load_balancers.len());
+        for load_balancer in &load_balancers {
+            drains.push(load_balancer.drain_endpoint(endpoint_id, deadline).await);
+        }
+
+        let discovery = self.clone();
+        let service_id = service_id.to_string();
+        let endpoint_id = endpoint_id.to_string();
+        Ok(tokio::spawn(async move {
+            if drains.is_empty() {
+                tokio::time::sleep(deadline).await;
+            }
+            for drain in drains {
+                let _ = drain.await;
+            }
+
+            // Removed by hand meanwhile; already reported
+            let present = discovery
+                .get_service(&service_id)
+                .await
+                .is_some_and(|service| service.endpoints.iter().any(|ep| ep.id == endpoint_id));
+            if present {
+                let _ = discovery.remove_endpoint(&service_id, &endpoint_id).await;
+            }
+        }))
+    }
+
+    /// Answer DNS queries for the registered services on `addr`, over UDP
+    /// and TCP. A queries for `<name>.<namespace>.<domain>` return the
+    /// addresses of the service's healthy endpoints and SRV queries their
+    /// address, port and weight, with the health check interval as TTL.
+    pub async fn serve_dns(&self, addr: SocketAddr, domain: &str) -> Result<ServiceDnsServer> {
+        let udp = Arc::new(UdpSocket::bind(addr).await?);
+        let local_addr = udp.local_addr()?;
+        let tcp = TcpListener::bind(local_addr).await?;
+
+        let zone = Arc::new(ServiceZone {
+            services: Arc::clone(&self.services),
+            domain: domain.trim_matches('.').to_ascii_lowercase(),
+        });
+        let tasks = vec![
+            tokio::spawn(serve_dns_udp(udp, Arc::clone(&zone))),
+            tokio::spawn(serve_dns_tcp(tcp, zone)),
+        ];
+        Ok(ServiceDnsServer { local_addr, tasks })
+    }
+
     pub async fn get_healthy_endpoints(&self, service_id: &str) -> Vec<ServiceEndpoint> {
         let services = self.services.read().await;
         if let Some(service) = services.get(service_id) {
@@ -319,30 +531,41 @@ impl ServiceDiscovery {
             loop {
                 interval.tick().await;
 
-                if let Some(service) = services.read().await.get(&service_id) {
+                // Release the read lock before updating the service below
+                let service = services.read().await.get(&service_id).cloned();
+                if let Some(service) = service {
                     for endpoint in &service.endpoints {
+                        // A draining endpoint keeps its status until removed
+                        if endpoint.health_status == HealthStatus::Draining {
+                            continue;
+                        }
                         let health_status =
                             health_checker.check_endpoint(endpoint, &health_check).await;
 
                         if health_status != endpoint.health_status {
-                            // Update health status
+                            // Update health status, unless a drain started
+                            // during the probe
                             let mut services = services.write().await;
-                            if let Some(service) = services.get_mut(&service_id) {
-                                if let Some(ep) =
-                                    service.endpoints.iter_mut().find(|ep| ep.id == endpoint.id)
-                                {
+                            let updated = match services.get_mut(&service_id).and_then(|service| {
+                                service.endpoints.iter_mut().find(|ep| ep.id == endpoint.id)
+                            }) {
+                                Some(ep) if ep.health_status != HealthStatus::Draining => {
                                     ep.health_status = health_status.clone();
                                     ep.last_health_check = Some(Utc::now());
+                                    true
                                 }
-                            }
+                                _ => false,
+                            };
                             drop(services);
 
                             // Send event
-                            let _ = event_sender.send(ServiceEvent::HealthStatusChanged {
-                                service_id: service_id.clone(),
-                                endpoint_id: endpoint.id.clone(),
-                                status: health_status,
-                            });
+                            if updated {
+                                let _ = event_sender.send(ServiceEvent::HealthStatusChanged {
+                                    service_id: service_id.clone(),
+                                    endpoint_id: endpoint.id.clone(),
+                                    status: health_status,
+                                });
+                            }
                         }
                     }
                 } else {
@@ -355,13 +578,481 @@ impl ServiceDiscovery {
         Ok(())
     }
 
-    pub async fn get_service_events(&self) -> tokio::sync::mpsc::UnboundedReceiver<ServiceEvent> {
-        let (_sender, receiver) = tokio::sync::mpsc::unbounded_channel();
-        // This would need to be implemented to forward events
+    /// Subscribe to service events emitted from now on; see [`EVENT_CHANNEL_CAPACITY`].
+    pub async fn get_service_events(&self) -> broadcast::Receiver<ServiceEvent> {
+        self.event_sender.subscribe()
+    }
+
+    /// Subscribe to the events of the services matched by `selector`,
+    /// including their endpoint and health changes.
+    ///
+    /// A service updated so that it no longer matches is reported as
+    /// deregistered. Events are forwarded until every receiver is dropped.
+    pub async fn watch(&self, selector: LabelSelector) -> broadcast::Receiver<ServiceEvent> {
+        self.start_watch(selector, false).await
+    }
+
+    /// Like `watch`, but the receiver first gets a `ServiceRegistered` event
+    /// for every service currently matched. A service registered while the
+    /// snapshot is taken may be reported twice.
+    pub async fn watch_with_snapshot(
+        &self,
+        selector: LabelSelector,
+    ) -> broadcast::Receiver<ServiceEvent> {
+        self.start_watch(selector, true).await
+    }
+
+    async fn start_watch(
+        &self,
+        selector: LabelSelector,
+        snapshot: bool,
+    ) -> broadcast::Receiver<ServiceEvent> {
+        // Subscribe before the snapshot so that no later change is missed
+        let mut upstream = self.event_sender.subscribe();
+        let matched: Vec<Service> = {
+            let services = self.services.read().await;
+            services
+                .values()
+                .filter(|service| selector.matches(&service.labels))
+                .cloned()
+                .collect()
+        };
+
+        let capacity = if snapshot {
+            EVENT_CHANNEL_CAPACITY.max(matched.len())
+        } else {
+            EVENT_CHANNEL_CAPACITY
+        };
+        let (sender, receiver) = broadcast::channel(capacity);
+        let mut watched: HashSet<String> = matched.iter().map(|s| s.id.clone()).collect();
+        if snapshot {
+            for service in matched {
+                let _ = sender.send(ServiceEvent::ServiceRegistered { service });
+            }
+        }
+
+        tokio::spawn(async move {
+            loop {
+                let event = match upstream.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Service watch skipped {} events", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                if let Some(event) = select_event(&selector, &mut watched, event) {
+                    if sender.send(event).is_err() {
+                        // Every receiver of the watch was dropped
+                        break;
+                    }
+                }
+            }
+        });
+
         receiver
     }
 }
 
+/// The event a watch with `selector` delivers for `event`, keeping
+/// `watched`, the ids of the services it matches, up to date
+fn select_event(
+    selector: &LabelSelector,
+    watched: &mut HashSet<String>,
+    event: ServiceEvent,
+) -> Option<ServiceEvent> {
+    match &event {
+        ServiceEvent::ServiceRegistered { service } | ServiceEvent::ServiceUpdated { service } => {
+            if selector.matches(&service.labels) {
+                watched.insert(service.id.clone());
+                Some(event)
+            } else if watched.remove(&service.id) {
+                Some(ServiceEvent::ServiceDeregistered {
+                    service_id: service.id.clone(),
+                })
+            } else {
+                None
+            }
+        }
+        ServiceEvent::ServiceDeregistered { service_id } => {
+            watched.remove(service_id).then_some(event)
+        }
+        ServiceEvent::EndpointAdded { service_id, .. }
+        | ServiceEvent::EndpointRemoved { service_id, .. }
+        | ServiceEvent::HealthStatusChanged { service_id, .. } => {
+            watched.contains(service_id).then_some(event)
+        }
+    }
+}
+
+impl ServiceDnsServer {
+    /// Address the server listens on, over both UDP and TCP
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for ServiceDnsServer {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn serve_dns_udp(socket: Arc<UdpSocket>, zone: Arc<ServiceZone>) {
+    let mut buffer = vec![0u8; 4096];
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buffer).await {
+            Ok(received) => received,
+            Err(e) => {
+                tracing::warn!("Failed to receive DNS query: {}", e);
+                continue;
+            }
+        };
+
+        let query = buffer[..len].to_vec();
+        let socket = Arc::clone(&socket);
+        let zone = Arc::clone(&zone);
+        tokio::spawn(async move {
+            if let Some(response) = zone.respond(&query, Some(MAX_UDP_RESPONSE)).await {
+                if let Err(e) = socket.send_to(&response, peer).await {
+                    tracing::debug!("Failed to answer DNS query from {}: {}", peer, e);
+                }
+            }
+        });
+    }
+}
+
+async fn serve_dns_tcp(listener: TcpListener, zone: Arc<ServiceZone>) {
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                tracing::warn!("Failed to accept DNS connection: {}", e);
+                continue;
+            }
+        };
+
+        let zone = Arc::clone(&zone);
+        tokio::spawn(async move {
+            // Each message is preceded by its length (RFC 1035, 4.2.2)
+            loop {
+                let mut length = [0u8; 2];
+                if stream.read_exact(&mut length).await.is_err() {
+                    break;
+                }
+                let mut query = vec![0u8; u16::from_be_bytes(length) as usize];
+                if stream.read_exact(&mut query).await.is_err() {
+                    break;
+                }
+                let Some(response) = zone.respond(&query, None).await else {
+                    break;
+                };
+
+                let mut framed = (response.len() as u16).to_be_bytes().to_vec();
+                framed.extend(response);
+                if stream.write_all(&framed).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+impl ServiceZone {
+    /// Response to a DNS message, truncated beyond `limit` bytes. Messages
+    /// too short to carry a header, and responses, are not answered.
+    async fn respond(&self, message: &[u8], limit: Option<usize>) -> Option<Vec<u8>> {
+        let header = message.get(..12)?;
+        let flags = u16::from_be_bytes([header[2], header[3]]);
+        if flags & 0x8000 != 0 {
+            return None;
+        }
+
+        let opcode = (flags >> 11) & 0xf;
+        let question = parse_dns_question(message);
+        let lookup = match &question {
+            _ if opcode != 0 => DnsLookup::failed(DNS_NOT_IMPLEMENTED),
+            Some(question) => self.lookup(question).await,
+            None => DnsLookup::failed(DNS_FORMAT_ERROR),
+        };
+
+        let response = encode_dns_response(header, question.as_ref(), &lookup, false);
+        match limit {
+            Some(limit) if response.len() > limit => Some(encode_dns_response(
+                header,
+                question.as_ref(),
+                &DnsLookup::failed(lookup.response_code),
+                true,
+            )),
+            _ => Some(response),
+        }
+    }
+
+    async fn lookup(&self, question: &DnsQuestion) -> DnsLookup {
+        let Some(relative) = question
+            .name
+            .strip_suffix(self.domain.as_str())
+            .and_then(|name| name.strip_suffix('.'))
+        else {
+            return DnsLookup::failed(DNS_NAME_ERROR);
+        };
+
+        // The `_service._proto` labels of SRV names do not narrow the answer
+        let labels: Vec<&str> = relative
+            .split('.')
+            .skip_while(|label| label.starts_with('_'))
+            .collect();
+        let (endpoint_id, name, namespace) = match labels.as_slice() {
+            [name, namespace] => (None, *name, *namespace),
+            [endpoint_id, name, namespace] => (Some(*endpoint_id), *name, *namespace),
+            _ => return DnsLookup::failed(DNS_NAME_ERROR),
+        };
+
+        let services = self.services.read().await;
+        let services: Vec<&Service> = services
+            .values()
+            .filter(|service| {
+                service.name.eq_ignore_ascii_case(name)
+                    && service.namespace.eq_ignore_ascii_case(namespace)
+            })
+            .collect();
+        let endpoints: Vec<(&ServiceEndpoint, u32)> = services
+            .iter()
+            .flat_map(|service| {
+                let ttl = dns_ttl(service);
+                service
+                    .endpoints
+                    .iter()
+                    .map(move |endpoint| (endpoint, ttl))
+            })
+            .filter(|(endpoint, _)| match endpoint_id {
+                Some(id) => endpoint.id.eq_ignore_ascii_case(id),
+                None => true,
+            })
+            .collect();
+        if services.is_empty() || (endpoint_id.is_some() && endpoints.is_empty()) {
+            return DnsLookup::failed(DNS_NAME_ERROR);
+        }
+
+        let service_name = format!("{}.{}.{}", name, namespace, self.domain);
+        let mut lookup = DnsLookup::default();
+        for (endpoint, ttl) in endpoints {
+            if endpoint.health_status != HealthStatus::Healthy {
+                continue;
+            }
+            let address = endpoint.address.parse::<Ipv4Addr>().ok();
+
+            match question.record_type {
+                DNS_TYPE_A => lookup.answers.extend(address.map(|address| ResourceRecord {
+                    name: question.name.clone(),
+                    ttl,
+                    data: RecordData::A(address),
+                })),
+                // An endpoint name has no SRV record of its own
+                DNS_TYPE_SRV if endpoint_id.is_none() => {
+                    let target = format!("{}.{}", endpoint.id, service_name);
+                    lookup
+                        .additional
+                        .extend(address.map(|address| ResourceRecord {
+                            name: target.clone(),
+                            ttl,
+                            data: RecordData::A(address),
+                        }));
+                    lookup.answers.push(ResourceRecord {
+                        name: question.name.clone(),
+                        ttl,
+                        data: RecordData::Srv {
+                            priority: u16::try_from(endpoint.priority).unwrap_or(u16::MAX),
+                            weight: u16::try_from(endpoint.weight).unwrap_or(u16::MAX),
+                            port: endpoint.port,
+                            target,
+                        },
+                    });
+                }
+                _ => {}
+            }
+        }
+        lookup
+    }
+}
+
+impl DnsLookup {
+    fn failed(response_code: u8) -> Self {
+        Self {
+            response_code,
+            ..Default::default()
+        }
+    }
+}
+
+/// TTL of a service's records: a resolver caching them longer than the
+/// health check interval would keep handing out endpoints found unhealthy
+fn dns_ttl(service: &Service) -> u32 {
+    match &service.health_check {
+        Some(check) if check.enabled => u32::try_from(check.interval.as_secs())
+            .unwrap_or(u32::MAX)
+            .max(1),
+        _ => DEFAULT_DNS_TTL,
+    }
+}
+
+/// The single question of a query; compressed names are not expected in
+/// queries and rejected
+fn parse_dns_question(message: &[u8]) -> Option<DnsQuestion> {
+    if u16::from_be_bytes([message[4], message[5]]) != 1 {
+        return None;
+    }
+
+    let mut labels = Vec::new();
+    let mut position = 12;
+    loop {
+        let length = *message.get(position)? as usize;
+        position += 1;
+        if length == 0 {
+            break;
+        }
+        if length > 63 {
+            return None;
+        }
+        let label = message.get(position..position + length)?;
+        labels.push(std::str::from_utf8(label).ok()?.to_ascii_lowercase());
+        position += length;
+    }
+
+    let fields = message.get(position..position + 4)?;
+    Some(DnsQuestion {
+        name: labels.join("."),
+        record_type: u16::from_be_bytes([fields[0], fields[1]]),
+        wire: message[12..position + 4].to_vec(),
+    })
+}
+
+/// Encode the response to the query whose header is `header`, without any
+/// record and with the TC bit set when `truncated`
+fn encode_dns_response(
+    header: &[u8],
+    question: Option<&DnsQuestion>,
+    lookup: &DnsLookup,
+    truncated: bool,
+) -> Vec<u8> {
+    let query_flags = u16::from_be_bytes([header[2], header[3]]);
+    // Opcode and recursion desired are echoed; answers are authoritative
+    let mut flags = (query_flags & 0x7900) | 0x8400 | lookup.response_code as u16;
+    if truncated {
+        flags |= 0x0200;
+    }
+    let (answers, additional): (&[ResourceRecord], &[ResourceRecord]) = if truncated {
+        (&[], &[])
+    } else {
+        (&lookup.answers, &lookup.additional)
+    };
+
+    let mut message = header[..2].to_vec();
+    for count in [
+        flags,
+        question.is_some() as u16,
+        answers.len() as u16,
+        0,
+        additional.len() as u16,
+    ] {
+        message.extend(count.to_be_bytes());
+    }
+    if let Some(question) = question {
+        message.extend(&question.wire);
+    }
+    for record in answers.iter().chain(additional) {
+        encode_dns_record(&mut message, record, question);
+    }
+    message
+}
+
+fn encode_dns_record(
+    message: &mut Vec<u8>,
+    record: &ResourceRecord,
+    question: Option<&DnsQuestion>,
+) {
+    // Point back at the name of the question, right after the header
+    if question.is_some_and(|question| question.name == record.name) {
+        message.extend([0xc0, 0x0c]);
+    } else {
+        encode_dns_name(message, &record.name);
+    }
+
+    let (record_type, data) = match &record.data {
+        RecordData::A(address) => (DNS_TYPE_A, address.octets().to_vec()),
+        RecordData::Srv {
+            priority,
+            weight,
+            port,
+            target,
+        } => {
+            let mut data = Vec::new();
+            for field in [priority, weight, port] {
+                data.extend(field.to_be_bytes());
+            }
+            encode_dns_name(&mut data, target);
+            (DNS_TYPE_SRV, data)
+        }
+    };
+    message.extend(record_type.to_be_bytes());
+    message.extend(DNS_CLASS_IN.to_be_bytes());
+    message.extend(record.ttl.to_be_bytes());
+    message.extend((data.len() as u16).to_be_bytes());
+    message.extend(data);
+}
+
+fn encode_dns_name(message: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        message.push(label.len() as u8);
+        message.extend(label.as_bytes());
+    }
+    message.push(0);
+}
+
+impl LabelSelector {
+    /// Selector matching every service
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require the label `key` to be set to `value`
+    pub fn with_equals(mut self, key: String, value: String) -> Self {
+        self.requirements
+            .push(LabelRequirement::Equals { key, value });
+        self
+    }
+
+    /// Require the label `key` to be set to one of `values`
+    pub fn with_in(mut self, key: String, values: Vec<String>) -> Self {
+        self.requirements.push(LabelRequirement::In { key, values });
+        self
+    }
+
+    /// Require the label `key` to be missing or set to none of `values`
+    pub fn with_not_in(mut self, key: String, values: Vec<String>) -> Self {
+        self.requirements
+            .push(LabelRequirement::NotIn { key, values });
+        self
+    }
+
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.requirements
+            .iter()
+            .all(|requirement| match requirement {
+                LabelRequirement::Equals { key, value } => labels.get(key) == Some(value),
+                LabelRequirement::In { key, values } => {
+                    labels.get(key).is_some_and(|value| values.contains(value))
+                }
+                LabelRequirement::NotIn { key, values } => {
+                    !labels.get(key).is_some_and(|value| values.contains(value))
+                }
+            })
+    }
+}
+
 impl HealthChecker {
     pub fn new() -> Self {
         Self {
@@ -560,7 +1251,7 @@ mod tests {
             Protocol::Http,
         ));
 
-        discovery.register_service(service).await.unwrap();
+        discovery.register_service(service.clone()).await.unwrap();
 
         let found_service = discovery.get_service(&service.id).await;
         assert!(found_service.is_some());
@@ -571,16 +1262,15 @@ mod tests {
     async fn test_service_resolution() {
         let discovery = ServiceDiscovery::new();
 
+        // Only healthy endpoints are resolved
+        let mut endpoint = ServiceEndpoint::new("127.0.0.1".to_string(), 8080, Protocol::Http);
+        endpoint.health_status = HealthStatus::Healthy;
         let service = Service::new(
             "test-service".to_string(),
             "default".to_string(),
             "1.0.0".to_string(),
         )
-        .with_endpoint(ServiceEndpoint::new(
-            "127.0.0.1".to_string(),
-            8080,
-            Protocol::Http,
-        ));
+        .with_endpoint(endpoint);
 
         discovery.register_service(service).await.unwrap();
 
@@ -0,0 +1,484 @@
+use crate::orchestrator::{ApplyAction, Manifest, Orchestrator, ResourceKey};
+use chrono::{DateTime, Utc};
+use notify::{RecursiveMode, Watcher};
+use polis_core::{PolisError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::{mpsc, RwLock};
+use tracing::{info, warn};
+
+/// Label marking resources managed by a sync; its value is the sync name
+pub const SYNC_OWNER_LABEL: &str = "polis.io/sync-owner";
+
+/// Where the desired state is read from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SyncSource {
+    /// Local directory, watched for changes
+    Directory { path: PathBuf },
+    /// Git repository, polled and shallow-fetched
+    Git {
+        url: String,
+        branch: String,
+        /// Directory inside the repository holding the specs
+        path: PathBuf,
+    },
+}
+
+impl std::fmt::Display for SyncSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncSource::Directory { path } => write!(f, "{}", path.display()),
+            SyncSource::Git { url, branch, path } => {
+                write!(f, "{}@{}:{}", url, branch, path.display())
+            }
+        }
+    }
+}
+
+/// Sync configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConfig {
+    /// Sync name, used as the owner label value
+    pub name: String,
+    pub source: SyncSource,
+    /// Delete owned resources that no longer exist in the source
+    pub prune: bool,
+    /// Poll interval for git sources and resync interval for directories
+    pub interval: Duration,
+}
+
+impl SyncConfig {
+    pub fn new(name: &str, source: SyncSource) -> Self {
+        Self {
+            name: name.to_string(),
+            source,
+            prune: false,
+            interval: Duration::from_secs(60),
+        }
+    }
+
+    pub fn with_prune(mut self, prune: bool) -> Self {
+        self.prune = prune;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+/// Result of applying (or pruning) a single resource
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceResult {
+    pub resource: ResourceKey,
+    /// File the resource was read from, relative to the source root
+    pub file: Option<PathBuf>,
+    pub action: Option<ApplyAction>,
+    pub error: Option<String>,
+}
+
+/// Document that could not be parsed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncParseError {
+    pub file: PathBuf,
+    /// Zero-based index of the document within the file
+    pub document: usize,
+    pub message: String,
+}
+
+/// Outcome of the last sync cycle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub name: String,
+    pub source: String,
+    /// Commit SHA for git sources, content digest for directories
+    pub revision: Option<String>,
+    pub last_sync: Option<DateTime<Utc>>,
+    pub results: Vec<ResourceResult>,
+    pub parse_errors: Vec<SyncParseError>,
+    /// Pruning is skipped when documents failed to parse
+    pub prune_skipped: bool,
+    /// Error that prevented reading the source at all
+    pub error: Option<String>,
+}
+
+impl SyncStatus {
+    fn new(config: &SyncConfig) -> Self {
+        Self {
+            name: config.name.clone(),
+            source: config.source.to_string(),
+            revision: None,
+            last_sync: None,
+            results: Vec::new(),
+            parse_errors: Vec::new(),
+            prune_skipped: false,
+            error: None,
+        }
+    }
+
+    /// Results that performed a change
+    pub fn changed(&self) -> Vec<&ResourceResult> {
+        self.results
+            .iter()
+            .filter(|r| matches!(r.action, Some(a) if a != ApplyAction::Unchanged))
+            .collect()
+    }
+
+    /// Load the persisted status of a sync
+    pub fn load(state_dir: &Path, name: &str) -> Result<Option<Self>> {
+        let path = status_path(state_dir, name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Load the persisted status of every sync
+    pub fn list(state_dir: &Path) -> Result<Vec<Self>> {
+        let dir = state_dir.join("sync");
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut statuses = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                let content = std::fs::read_to_string(&path)?;
+                statuses.push(serde_json::from_str::<SyncStatus>(&content)?);
+            }
+        }
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(statuses)
+    }
+
+    fn save(&self, state_dir: &Path) -> Result<()> {
+        let path = status_path(state_dir, &self.name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+fn status_path(state_dir: &Path, name: &str) -> PathBuf {
+    state_dir.join("sync").join(format!("{}.json", name))
+}
+
+/// Continuously reconciles the orchestrator with a directory or git repository of specs
+pub struct SyncController {
+    config: SyncConfig,
+    orchestrator: Arc<Orchestrator>,
+    status: Arc<RwLock<SyncStatus>>,
+    state_dir: PathBuf,
+}
+
+impl SyncController {
+    pub fn new(config: SyncConfig, orchestrator: Arc<Orchestrator>) -> Self {
+        let state_dir = orchestrator.config().state_dir.clone();
+        let status = SyncStatus::new(&config);
+        Self {
+            config,
+            orchestrator,
+            status: Arc::new(RwLock::new(status)),
+            state_dir,
+        }
+    }
+
+    /// Status of the last sync cycle
+    pub async fn status(&self) -> SyncStatus {
+        self.status.read().await.clone()
+    }
+
+    /// Run a single sync cycle
+    pub async fn sync_once(&self) -> Result<SyncStatus> {
+        let mut status = SyncStatus::new(&self.config);
+        status.last_sync = Some(Utc::now());
+
+        match self.checkout().await {
+            Ok((root, revision)) => {
+                status.revision = Some(revision);
+                self.reconcile(&root, &mut status).await;
+            }
+            Err(e) => {
+                warn!("Sync '{}' failed to read source: {}", self.config.name, e);
+                status.error = Some(e.to_string());
+                // Keep reporting the last revision that was read successfully
+                status.revision = self.status.read().await.revision.clone();
+            }
+        }
+
+        status.save(&self.state_dir)?;
+        *self.status.write().await = status.clone();
+        Ok(status)
+    }
+
+    /// Sync until the task is cancelled
+    pub async fn run(&self) -> Result<()> {
+        match &self.config.source {
+            SyncSource::Directory { path } => self.run_watching(path).await,
+            SyncSource::Git { .. } => loop {
+                self.sync_once().await?;
+                tokio::time::sleep(self.config.interval).await;
+            },
+        }
+    }
+
+    async fn run_watching(&self, path: &Path) -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if event.is_ok() {
+                    let _ = tx.send(());
+                }
+            })
+            .map_err(|e| {
+                PolisError::Config(format!("Failed to watch {}: {}", path.display(), e))
+            })?;
+        watcher.watch(path, RecursiveMode::Recursive).map_err(|e| {
+            PolisError::Config(format!("Failed to watch {}: {}", path.display(), e))
+        })?;
+
+        loop {
+            self.sync_once().await?;
+
+            tokio::select! {
+                Some(()) = rx.recv() => {
+                    // Editors emit bursts of events; wait for them to settle
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                    while rx.try_recv().is_ok() {}
+                }
+                _ = tokio::time::sleep(self.config.interval) => {}
+            }
+        }
+    }
+
+    /// Resolve the directory holding the specs and its revision
+    async fn checkout(&self) -> Result<(PathBuf, String)> {
+        match &self.config.source {
+            SyncSource::Directory { path } => {
+                let revision = directory_digest(path)?;
+                Ok((path.clone(), revision))
+            }
+            SyncSource::Git { url, branch, path } => {
+                let checkout = self
+                    .state_dir
+                    .join("sync")
+                    .join("checkouts")
+                    .join(&self.config.name);
+                if checkout.join(".git").exists() {
+                    git(&checkout, &["fetch", "--depth", "1", "origin", branch]).await?;
+                    git(&checkout, &["reset", "--hard", "FETCH_HEAD"]).await?;
+                } else {
+                    if let Some(parent) = checkout.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    let target = checkout.display().to_string();
+                    git(
+                        &self.state_dir,
+                        &[
+                            "clone",
+                            "--depth",
+                            "1",
+                            "--single-branch",
+                            "--branch",
+                            branch,
+                            url,
+                            &target,
+                        ],
+                    )
+                    .await?;
+                }
+                let revision = git(&checkout, &["rev-parse", "HEAD"]).await?;
+                Ok((checkout.join(path), revision))
+            }
+        }
+    }
+
+    async fn reconcile(&self, root: &Path, status: &mut SyncStatus) {
+        let mut files = Vec::new();
+        if let Err(e) = collect_spec_files(root, &mut files) {
+            status.error = Some(format!("Failed to list {}: {}", root.display(), e));
+            return;
+        }
+        files.sort();
+
+        let mut desired = HashSet::new();
+        for file in files {
+            let relative = file.strip_prefix(root).unwrap_or(&file).to_path_buf();
+            let manifests = match std::fs::read_to_string(&file) {
+                Ok(content) => parse_documents(&content, &relative, &mut status.parse_errors),
+                Err(e) => {
+                    status.parse_errors.push(SyncParseError {
+                        file: relative,
+                        document: 0,
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            for (document, mut manifest) in manifests {
+                let key = manifest.key();
+                if !desired.insert(key.clone()) {
+                    status.parse_errors.push(SyncParseError {
+                        file: relative.clone(),
+                        document,
+                        message: format!("Resource '{}' is defined more than once", key),
+                    });
+                    continue;
+                }
+
+                manifest
+                    .labels_mut()
+                    .insert(SYNC_OWNER_LABEL.to_string(), self.config.name.clone());
+
+                let result = self.orchestrator.apply(manifest).await;
+                status.results.push(ResourceResult {
+                    resource: key,
+                    file: Some(relative.clone()),
+                    action: result.as_ref().ok().copied(),
+                    error: result.err().map(|e| e.to_string()),
+                });
+            }
+        }
+
+        if !self.config.prune {
+            return;
+        }
+        if !status.parse_errors.is_empty() {
+            // A resource in a broken file would otherwise look deleted
+            warn!(
+                "Sync '{}' skipped pruning because of {} parse error(s)",
+                self.config.name,
+                status.parse_errors.len()
+            );
+            status.prune_skipped = true;
+            return;
+        }
+        self.prune(&desired, status).await;
+    }
+
+    async fn prune(&self, desired: &HashSet<ResourceKey>, status: &mut SyncStatus) {
+        let owned = match self
+            .orchestrator
+            .list_resources_with_label(SYNC_OWNER_LABEL, &self.config.name)
+            .await
+        {
+            Ok(owned) => owned,
+            Err(e) => {
+                status.error = Some(format!("Failed to list owned resources: {}", e));
+                return;
+            }
+        };
+
+        for key in owned.into_iter().filter(|key| !desired.contains(key)) {
+            info!("Sync '{}' pruning {}", self.config.name, key);
+            let result = self.orchestrator.delete_resource(&key).await;
+            status.results.push(ResourceResult {
+                resource: key,
+                file: None,
+                action: result.as_ref().ok().map(|_| ApplyAction::Delete),
+                error: result.err().map(|e| e.to_string()),
+            });
+        }
+    }
+}
+
+/// Parse every document of a spec file, recording failures instead of aborting
+fn parse_documents(
+    content: &str,
+    file: &Path,
+    errors: &mut Vec<SyncParseError>,
+) -> Vec<(usize, Manifest)> {
+    let mut manifests = Vec::new();
+    for (index, document) in serde_yaml::Deserializer::from_str(content).enumerate() {
+        let value = match serde_yaml::Value::deserialize(document) {
+            Ok(serde_yaml::Value::Null) => continue,
+            Ok(value) => value,
+            Err(e) => {
+                // Documents after a syntax error cannot be located reliably
+                errors.push(SyncParseError {
+                    file: file.to_path_buf(),
+                    document: index,
+                    message: e.to_string(),
+                });
+                break;
+            }
+        };
+
+        match serde_yaml::from_value::<Manifest>(value) {
+            Ok(manifest) => manifests.push((index, manifest)),
+            Err(e) => errors.push(SyncParseError {
+                file: file.to_path_buf(),
+                document: index,
+                message: e.to_string(),
+            }),
+        }
+    }
+    manifests
+}
+
+fn collect_spec_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if hidden {
+            continue;
+        }
+
+        if path.is_dir() {
+            collect_spec_files(&path, files)?;
+        } else if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("yaml" | "yml" | "json")
+        ) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Digest over the spec files of a directory, used as its revision
+fn directory_digest(dir: &Path) -> Result<String> {
+    let mut files = Vec::new();
+    collect_spec_files(dir, &mut files)?;
+    files.sort();
+
+    let mut hasher = DefaultHasher::new();
+    for file in files {
+        file.strip_prefix(dir).unwrap_or(&file).hash(&mut hasher);
+        std::fs::read(&file)?.hash(&mut hasher);
+    }
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(PolisError::Config(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -1,12 +1,24 @@
+use polis_orchestrator::auto_scaling::ResourceLimits;
+use polis_orchestrator::service_discovery::HealthStatus as EndpointHealth;
 use polis_orchestrator::{
-    AutoScaler, CheckType, Deployment, HealthCheck, HealthMonitor, HealthStatus, LoadBalancer,
-    LoadBalancerRequest, LoadBalancingAlgorithm, Protocol, ResourceLimits, ResourceRequests,
-    ScalingMetrics, ScalingPolicy, Service, ServiceDiscovery, ServiceEndpoint, TargetType,
+    AutoScaler, CheckType, ContainerStatsSource, Deployment, FailureAction, HealthCheckDef,
+    HealthMonitor, HealthStatus, LoadBalancer, LoadBalancerRequest, LoadBalancingAlgorithm,
+    MetricsSource, Protocol, RuntimeTargetController, ScalingMetrics, ScalingPolicy, Service,
+    ServiceDiscovery, ServiceEndpoint, ServiceEvent, TargetType,
 };
+use polis_runtime::ContainerRuntime;
+use polis_stats::{ContainerMetrics, ContainerStatsCollector};
+use polis_test_support::{FakeRuntime, RuntimeCall};
 use std::collections::HashMap;
-use std::net::IpAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
+/// Endpoints start with an unknown status and are only routed to once healthy
+fn healthy(mut endpoint: ServiceEndpoint) -> ServiceEndpoint {
+    endpoint.health_status = EndpointHealth::Healthy;
+    endpoint
+}
+
 #[tokio::test]
 async fn test_service_discovery() {
     let discovery = ServiceDiscovery::new();
@@ -39,6 +51,31 @@ async fn test_service_discovery() {
     assert_eq!(found_services[0].name, "test-service");
 }
 
+#[tokio::test]
+async fn test_service_events_are_broadcast() {
+    let discovery = ServiceDiscovery::new();
+    let mut first = discovery.get_service_events().await;
+    let mut second = discovery.get_service_events().await;
+
+    let service = Service::new(
+        "events".to_string(),
+        "default".to_string(),
+        "1.0.0".to_string(),
+    );
+    discovery.register_service(service.clone()).await.unwrap();
+
+    for receiver in [&mut first, &mut second] {
+        match receiver.recv().await.unwrap() {
+            ServiceEvent::ServiceRegistered {
+                service: registered,
+            } => {
+                assert_eq!(registered.id, service.id)
+            }
+            other => panic!("expected ServiceRegistered, got {:?}", other),
+        }
+    }
+}
+
 #[tokio::test]
 async fn test_service_endpoints() {
     let discovery = ServiceDiscovery::new();
@@ -49,7 +86,7 @@ async fn test_service_endpoints() {
         "1.0.0".to_string(),
     );
 
-    discovery.register_service(service).await.unwrap();
+    discovery.register_service(service.clone()).await.unwrap();
 
     let endpoint1 = ServiceEndpoint::new("127.0.0.1".to_string(), 8080, Protocol::Http)
         .with_weight(2)
@@ -60,11 +97,11 @@ async fn test_service_endpoints() {
         .with_priority(2);
 
     discovery
-        .add_endpoint(&service.id, endpoint1)
+        .add_endpoint(&service.id, healthy(endpoint1))
         .await
         .unwrap();
     discovery
-        .add_endpoint(&service.id, endpoint2)
+        .add_endpoint(&service.id, healthy(endpoint2))
         .await
         .unwrap();
 
@@ -85,8 +122,8 @@ async fn test_load_balancer_round_robin() {
     let endpoint1 = ServiceEndpoint::new("127.0.0.1".to_string(), 8080, Protocol::Http);
     let endpoint2 = ServiceEndpoint::new("127.0.0.1".to_string(), 8081, Protocol::Http);
 
-    lb.add_endpoint(endpoint1).await;
-    lb.add_endpoint(endpoint2).await;
+    lb.add_endpoint(healthy(endpoint1)).await;
+    lb.add_endpoint(healthy(endpoint2)).await;
 
     let request = LoadBalancerRequest {
         client_ip: None,
@@ -94,10 +131,11 @@ async fn test_load_balancer_round_robin() {
         headers: HashMap::new(),
         path: "/".to_string(),
         method: "GET".to_string(),
+        body: None,
     };
 
-    let selected1 = lb.select_endpoint(&request).await.unwrap();
-    let selected2 = lb.select_endpoint(&request).await.unwrap();
+    let selected1 = lb.select_endpoint(&request).await.unwrap().unwrap();
+    let selected2 = lb.select_endpoint(&request).await.unwrap().unwrap();
 
     // Should alternate between endpoints
     assert_ne!(selected1.id, selected2.id);
@@ -113,8 +151,8 @@ async fn test_load_balancer_weighted_round_robin() {
     let mut endpoint2 = ServiceEndpoint::new("127.0.0.1".to_string(), 8081, Protocol::Http);
     endpoint2.weight = 1;
 
-    lb.add_endpoint(endpoint1).await;
-    lb.add_endpoint(endpoint2).await;
+    lb.add_endpoint(healthy(endpoint1)).await;
+    lb.add_endpoint(healthy(endpoint2)).await;
 
     let request = LoadBalancerRequest {
         client_ip: None,
@@ -122,12 +160,13 @@ async fn test_load_balancer_weighted_round_robin() {
         headers: HashMap::new(),
         path: "/".to_string(),
         method: "GET".to_string(),
+        body: None,
     };
 
     // Should select endpoint1 more often due to higher weight
     let mut endpoint1_count = 0;
     for _ in 0..100 {
-        let selected = lb.select_endpoint(&request).await.unwrap();
+        let selected = lb.select_endpoint(&request).await.unwrap().unwrap();
         if selected.weight == 3 {
             endpoint1_count += 1;
         }
@@ -143,8 +182,8 @@ async fn test_load_balancer_ip_hash() {
     let endpoint1 = ServiceEndpoint::new("127.0.0.1".to_string(), 8080, Protocol::Http);
     let endpoint2 = ServiceEndpoint::new("127.0.0.1".to_string(), 8081, Protocol::Http);
 
-    lb.add_endpoint(endpoint1).await;
-    lb.add_endpoint(endpoint2).await;
+    lb.add_endpoint(healthy(endpoint1)).await;
+    lb.add_endpoint(healthy(endpoint2)).await;
 
     let request = LoadBalancerRequest {
         client_ip: Some(std::net::IpAddr::V4(std::net::Ipv4Addr::new(
@@ -154,11 +193,12 @@ async fn test_load_balancer_ip_hash() {
         headers: HashMap::new(),
         path: "/".to_string(),
         method: "GET".to_string(),
+        body: None,
     };
 
     // Same IP should always select the same endpoint
-    let selected1 = lb.select_endpoint(&request).await.unwrap();
-    let selected2 = lb.select_endpoint(&request).await.unwrap();
+    let selected1 = lb.select_endpoint(&request).await.unwrap().unwrap();
+    let selected2 = lb.select_endpoint(&request).await.unwrap().unwrap();
 
     assert_eq!(selected1.id, selected2.id);
 }
@@ -188,16 +228,35 @@ async fn test_auto_scaler() {
 
     auto_scaler.create_scaling_policy(policy).await.unwrap();
 
-    let metrics = ScalingMetrics {
-        deployment_id: "test-deployment".to_string(),
-        timestamp: chrono::Utc::now(),
-        cpu_utilization: 80.0,
-        memory_utilization: 60.0,
-        requests_per_second: 150.0,
-        response_time: Duration::from_millis(100),
-        error_rate: 0.01,
-        active_connections: 50,
-    };
+    // The deployment's replicas run on a fake runtime and report their usage
+    let runtime = Arc::new(FakeRuntime::new());
+    let stats = Arc::new(ContainerStatsCollector::default());
+    for (name, cpu, memory) in [
+        ("test-deployment-1", 90.0, 50.0),
+        ("test-deployment-2", 70.0, 70.0),
+    ] {
+        let id = runtime
+            .create_container(name.to_string(), "nginx:latest".to_string(), Vec::new())
+            .await
+            .unwrap();
+        let mut sample = ContainerMetrics {
+            container_id: id.0.to_string(),
+            ..Default::default()
+        };
+        sample.cpu.usage_percent = cpu;
+        sample.memory.usage_percent = memory;
+        stats
+            .update_metrics(&id.0.to_string(), sample)
+            .await
+            .unwrap();
+    }
+
+    let metrics = ContainerStatsSource::new(runtime, stats)
+        .fetch("test-deployment")
+        .await
+        .unwrap();
+    assert_eq!(metrics.cpu_utilization, 80.0);
+    assert_eq!(metrics.memory_utilization, 60.0);
 
     auto_scaler
         .collect_metrics("test-deployment", metrics)
@@ -256,7 +315,7 @@ async fn test_deployment() {
 async fn test_health_monitor() {
     let monitor = HealthMonitor::new();
 
-    let check = HealthCheck::new(
+    let check = HealthCheckDef::new(
         "test-check".to_string(),
         "test".to_string(),
         TargetType::Container,
@@ -287,7 +346,7 @@ async fn test_health_check_types() {
     let monitor = HealthMonitor::new();
 
     // HTTP check
-    let http_check = HealthCheck::new(
+    let http_check = HealthCheckDef::new(
         "http-check".to_string(),
         "HTTP".to_string(),
         TargetType::Service,
@@ -299,7 +358,7 @@ async fn test_health_check_types() {
     );
 
     // TCP check
-    let tcp_check = HealthCheck::new(
+    let tcp_check = HealthCheckDef::new(
         "tcp-check".to_string(),
         "TCP".to_string(),
         TargetType::Container,
@@ -308,7 +367,7 @@ async fn test_health_check_types() {
     );
 
     // Command check
-    let cmd_check = HealthCheck::new(
+    let cmd_check = HealthCheckDef::new(
         "cmd-check".to_string(),
         "Command".to_string(),
         TargetType::Container,
@@ -329,29 +388,49 @@ async fn test_health_check_types() {
 
 #[tokio::test]
 async fn test_health_check_result() {
-    let monitor = HealthMonitor::new();
+    let runtime = Arc::new(FakeRuntime::new());
+    let id = runtime
+        .create_container("web".to_string(), "nginx:latest".to_string(), Vec::new())
+        .await
+        .unwrap();
+    runtime.start_container(id.clone()).await.unwrap();
+    let monitor = HealthMonitor::new()
+        .with_target_controller(Arc::new(RuntimeTargetController::new(runtime.clone())));
 
-    let check = HealthCheck::new(
+    let check = HealthCheckDef::new(
         "test-check".to_string(),
         "test".to_string(),
         TargetType::Container,
-        "test-container".to_string(),
-        CheckType::Http {
-            path: "/health".to_string(),
-            expected_status: 200,
-        },
-    );
+        id.0.to_string(),
+        CheckType::Tcp { port: 1 },
+    )
+    .with_failure_threshold(1)
+    .with_failure_action(FailureAction::RestartContainer {
+        max_restarts: 3,
+        window: Duration::from_secs(60),
+    });
 
     monitor.create_health_check(check).await.unwrap();
 
     let result = monitor.run_health_check("test-check").await.unwrap();
     assert_eq!(result.check_id, "test-check");
-    assert_eq!(result.target_id, "test-container");
+    assert_eq!(result.target_id, id.0.to_string());
     // Status will likely be Unhealthy since we're not running a real service
     assert!(matches!(
         result.status,
         HealthStatus::Unhealthy | HealthStatus::Unknown
     ));
+
+    // The failing container was restarted through the runtime
+    let calls = runtime.calls();
+    assert!(calls.contains(&RuntimeCall::Stop(id.clone())));
+    assert_eq!(
+        calls
+            .iter()
+            .filter(|call| **call == RuntimeCall::Start(id.clone()))
+            .count(),
+        2
+    );
 }
 
 #[tokio::test]
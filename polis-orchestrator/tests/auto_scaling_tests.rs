@@ -0,0 +1,1023 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use polis_core::ManualClock;
+use polis_orchestrator::{
+    AutoScaler, ContainerSelector, ContainerStatsSource, Deployment, MetricProvider,
+    MetricsCollector, MetricsSource, ScalingAction, ScalingActionType, ScalingEvent,
+    ScalingMetrics, ScalingPolicy, ScalingPolicySpec, ScalingSchedule, ScalingStrategy, TrendField,
+};
+use polis_runtime::ContainerRuntime;
+use polis_stats::{ContainerMetrics, ContainerStatsCollector};
+use polis_test_support::FakeRuntime;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+
+/// Serves scripted metrics, one per fetch, and fails once they run out
+struct ScriptedSource {
+    samples: Mutex<VecDeque<ScalingMetrics>>,
+    fetches: Mutex<Vec<String>>,
+}
+
+impl ScriptedSource {
+    fn new(samples: Vec<ScalingMetrics>) -> Self {
+        Self {
+            samples: Mutex::new(samples.into()),
+            fetches: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl MetricsSource for ScriptedSource {
+    async fn fetch(&self, deployment_id: &str) -> Result<ScalingMetrics> {
+        self.fetches.lock().unwrap().push(deployment_id.to_string());
+        self.samples
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| anyhow!("no metrics for {}", deployment_id))
+    }
+}
+
+fn utilization(cpu: f64, memory: f64, requests_per_second: f64) -> ScalingMetrics {
+    ScalingMetrics {
+        deployment_id: "web".to_string(),
+        timestamp: Utc::now(),
+        cpu_utilization: cpu,
+        memory_utilization: memory,
+        requests_per_second,
+        response_time: Duration::from_millis(20),
+        error_rate: 0.0,
+        active_connections: 0,
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_scaling_loop_pulls_metrics_from_source() {
+    let source = Arc::new(ScriptedSource::new(vec![
+        utilization(90.0, 40.0, 10.0),
+        utilization(5.0, 10.0, 1.0),
+    ]));
+    let interval = Duration::from_secs(30);
+    let auto_scaler = Arc::new(
+        AutoScaler::new()
+            .with_metrics_source(source.clone())
+            .with_evaluation_interval(interval),
+    );
+
+    auto_scaler
+        .create_deployment(
+            Deployment::new(
+                "web".to_string(),
+                "web".to_string(),
+                "default".to_string(),
+                "nginx:latest".to_string(),
+            )
+            .with_replicas(2),
+        )
+        .await
+        .unwrap();
+    auto_scaler
+        .create_scaling_policy(
+            ScalingPolicy::new(
+                "web-policy".to_string(),
+                "web".to_string(),
+                "web".to_string(),
+                1,
+                8,
+            )
+            .with_target_cpu_utilization(50.0),
+        )
+        .await
+        .unwrap();
+
+    let scaling_loop = {
+        let auto_scaler = auto_scaler.clone();
+        tokio::spawn(async move { auto_scaler.start_scaling_loop().await })
+    };
+
+    // First tick fires immediately: high CPU doubles the replicas
+    tokio::time::sleep(interval / 2).await;
+    assert_eq!(auto_scaler.get_deployment("web").await.unwrap().replicas, 4);
+
+    // Second tick: everything below half the targets halves them again
+    tokio::time::sleep(interval).await;
+    assert_eq!(auto_scaler.get_deployment("web").await.unwrap().replicas, 2);
+
+    // Failed fetches skip the deployment instead of reusing stale metrics
+    tokio::time::sleep(interval * 2).await;
+    assert_eq!(auto_scaler.get_deployment("web").await.unwrap().replicas, 2);
+    assert_eq!(source.fetches.lock().unwrap().len(), 4);
+
+    scaling_loop.abort();
+}
+
+async fn record(stats: &ContainerStatsCollector, id: &str, cpu: f64, memory: f64) {
+    let mut metrics = ContainerMetrics {
+        container_id: id.to_string(),
+        ..Default::default()
+    };
+    metrics.cpu.usage_percent = cpu;
+    metrics.memory.usage_percent = memory;
+    stats.update_metrics(id, metrics).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_container_stats_source_aggregates_deployment_containers() {
+    let runtime = Arc::new(FakeRuntime::new());
+    let stats = Arc::new(ContainerStatsCollector::default());
+
+    for (name, cpu, memory) in [
+        ("web-1", 80.0, 30.0),
+        ("web-2", 40.0, 50.0),
+        ("db-1", 5.0, 90.0),
+    ] {
+        let id = runtime
+            .create_container(name.to_string(), "nginx".to_string(), Vec::new())
+            .await
+            .unwrap();
+        record(&stats, &id.0.to_string(), cpu, memory).await;
+    }
+    // Containers without samples do not drag the average down
+    runtime
+        .create_container("web-3".to_string(), "nginx".to_string(), Vec::new())
+        .await
+        .unwrap();
+
+    let source = ContainerStatsSource::new(runtime.clone(), stats.clone());
+    let web = source.fetch("web").await.unwrap();
+    assert_eq!(web.deployment_id, "web");
+    assert_eq!(web.cpu_utilization, 60.0);
+    assert_eq!(web.memory_utilization, 40.0);
+
+    source
+        .set_selector("database", ContainerSelector::NamePrefix("db-".to_string()))
+        .await;
+    let database = source.fetch("database").await.unwrap();
+    assert_eq!(database.cpu_utilization, 5.0);
+    assert_eq!(database.memory_utilization, 90.0);
+
+    assert!(source.fetch("cache").await.is_err());
+}
+
+async fn scaler_with_cooldowns(clock: &ManualClock, replicas: u32) -> AutoScaler {
+    let auto_scaler = AutoScaler::new().with_clock(Arc::new(clock.clone()));
+    auto_scaler
+        .create_deployment(
+            Deployment::new(
+                "web".to_string(),
+                "web".to_string(),
+                "default".to_string(),
+                "nginx:latest".to_string(),
+            )
+            .with_replicas(replicas),
+        )
+        .await
+        .unwrap();
+    auto_scaler
+        .create_scaling_policy(
+            ScalingPolicy::new(
+                "web-policy".to_string(),
+                "web".to_string(),
+                "web".to_string(),
+                1,
+                64,
+            )
+            .with_target_cpu_utilization(50.0)
+            .with_scale_up_cooldown(Duration::from_secs(300))
+            .with_scale_down_cooldown(Duration::from_secs(600)),
+        )
+        .await
+        .unwrap();
+    auto_scaler
+}
+
+async fn evaluate(auto_scaler: &AutoScaler, metrics: ScalingMetrics) -> ScalingAction {
+    auto_scaler.collect_metrics("web", metrics).await.unwrap();
+    auto_scaler.evaluate_scaling("web").await.unwrap()
+}
+
+#[tokio::test]
+async fn test_scale_up_blocked_inside_cooldown_and_allowed_after() {
+    let clock = ManualClock::new(Utc::now());
+    let auto_scaler = scaler_with_cooldowns(&clock, 2).await;
+
+    let first = evaluate(&auto_scaler, utilization(80.0, 40.0, 10.0)).await;
+    assert_eq!(first.action_type, ScalingActionType::ScaleUp);
+    assert_eq!(first.to_replicas, 4);
+
+    clock.advance(Duration::from_secs(120));
+    let blocked = evaluate(&auto_scaler, utilization(80.0, 40.0, 10.0)).await;
+    assert_eq!(blocked.action_type, ScalingActionType::NoAction);
+    assert_eq!(
+        blocked.reason,
+        "cooldown: scale-up blocked for another 180s of the 300s window"
+    );
+    assert_eq!(auto_scaler.get_deployment("web").await.unwrap().replicas, 4);
+
+    // The scale-down window is tracked separately from the scale-up one
+    let down = evaluate(&auto_scaler, utilization(5.0, 5.0, 1.0)).await;
+    assert_eq!(down.action_type, ScalingActionType::ScaleDown);
+    assert_eq!(down.to_replicas, 2);
+
+    clock.advance(Duration::from_secs(181));
+    let after = evaluate(&auto_scaler, utilization(80.0, 40.0, 10.0)).await;
+    assert_eq!(after.action_type, ScalingActionType::ScaleUp);
+    assert_eq!(after.to_replicas, 4);
+
+    let history = auto_scaler.get_scaling_history("web").await;
+    assert_eq!(history.len(), 3);
+}
+
+#[tokio::test]
+async fn test_scale_down_cooldown() {
+    let clock = ManualClock::new(Utc::now());
+    let auto_scaler = scaler_with_cooldowns(&clock, 16).await;
+
+    let idle = utilization(5.0, 5.0, 1.0);
+    assert_eq!(evaluate(&auto_scaler, idle.clone()).await.to_replicas, 8);
+
+    clock.advance(Duration::from_secs(599));
+    let blocked = evaluate(&auto_scaler, idle.clone()).await;
+    assert_eq!(
+        blocked.reason,
+        "cooldown: scale-down blocked for another 1s of the 600s window"
+    );
+    assert_eq!(auto_scaler.get_deployment("web").await.unwrap().replicas, 8);
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(evaluate(&auto_scaler, idle).await.to_replicas, 4);
+}
+
+#[tokio::test]
+async fn test_emergency_utilization_bypasses_cooldown() {
+    let clock = ManualClock::new(Utc::now());
+    let auto_scaler = scaler_with_cooldowns(&clock, 2).await;
+
+    evaluate(&auto_scaler, utilization(80.0, 40.0, 10.0)).await;
+    clock.advance(Duration::from_secs(10));
+
+    let cpu = evaluate(&auto_scaler, utilization(97.0, 40.0, 10.0)).await;
+    assert_eq!(cpu.action_type, ScalingActionType::ScaleUp);
+    assert_eq!(cpu.to_replicas, 8);
+
+    let memory = evaluate(&auto_scaler, utilization(60.0, 99.0, 10.0)).await;
+    assert_eq!(memory.action_type, ScalingActionType::ScaleUp);
+    assert_eq!(memory.to_replicas, 16);
+
+    // Exactly at the threshold is not an emergency
+    let at_threshold = evaluate(&auto_scaler, utilization(95.0, 40.0, 10.0)).await;
+    assert!(at_threshold.reason.starts_with("cooldown: scale-up"));
+}
+
+#[tokio::test]
+async fn test_paused_deployment_is_not_scaled() {
+    let clock = ManualClock::new(Utc::now());
+    let auto_scaler = scaler_with_cooldowns(&clock, 2).await;
+    let mut events = auto_scaler.get_scaling_events().await;
+    auto_scaler.pause_deployment("web").await.unwrap();
+
+    // Even an emergency does not scale a paused deployment
+    let paused = evaluate(&auto_scaler, utilization(99.0, 99.0, 500.0)).await;
+    assert_eq!(paused.action_type, ScalingActionType::NoAction);
+    assert_eq!(paused.reason, "deployment paused");
+    assert_eq!(auto_scaler.get_deployment("web").await.unwrap().replicas, 2);
+    assert!(auto_scaler.get_scaling_history("web").await.is_empty());
+
+    assert!(matches!(
+        events.try_recv().unwrap(),
+        ScalingEvent::DeploymentUpdated { .. }
+    ));
+    match events.try_recv().unwrap() {
+        ScalingEvent::ScalingBlocked {
+            deployment_id,
+            reason,
+        } => {
+            assert_eq!(deployment_id, "web");
+            assert_eq!(reason, "deployment paused");
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+
+    auto_scaler.resume_deployment("web").await.unwrap();
+    let resumed = evaluate(&auto_scaler, utilization(99.0, 99.0, 500.0)).await;
+    assert_eq!(resumed.action_type, ScalingActionType::ScaleUp);
+    assert!(auto_scaler.pause_deployment("missing").await.is_err());
+}
+
+#[tokio::test]
+async fn test_rapid_successive_evaluations_scale_once() {
+    let clock = ManualClock::new(Utc::now());
+    let auto_scaler = scaler_with_cooldowns(&clock, 2).await;
+    let mut events = auto_scaler.get_scaling_events().await;
+
+    let actions = [
+        evaluate(&auto_scaler, utilization(80.0, 40.0, 10.0)).await,
+        evaluate(&auto_scaler, utilization(85.0, 40.0, 10.0)).await,
+        evaluate(&auto_scaler, utilization(90.0, 40.0, 10.0)).await,
+    ];
+    assert_eq!(actions[0].action_type, ScalingActionType::ScaleUp);
+    for blocked in &actions[1..] {
+        assert_eq!(blocked.action_type, ScalingActionType::NoAction);
+        assert_eq!((blocked.from_replicas, blocked.to_replicas), (4, 4));
+    }
+    assert_eq!(auto_scaler.get_deployment("web").await.unwrap().replicas, 4);
+    assert_eq!(auto_scaler.get_scaling_history("web").await.len(), 1);
+
+    assert!(matches!(
+        events.try_recv().unwrap(),
+        ScalingEvent::ScaleUp { .. }
+    ));
+    for _ in 0..2 {
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            ScalingEvent::ScalingBlocked { reason, .. }
+                if reason == "cooldown: scale-up blocked for another 300s of the 300s window"
+        ));
+    }
+}
+
+/// Scaler whose CPU climbed 40% -> 55% over the last 30 seconds, still
+/// below the 70% target
+async fn scaler_with_rising_cpu(policy: ScalingPolicy) -> AutoScaler {
+    let auto_scaler = AutoScaler::new();
+    auto_scaler
+        .create_deployment(
+            Deployment::new(
+                "web".to_string(),
+                "web".to_string(),
+                "default".to_string(),
+                "nginx:latest".to_string(),
+            )
+            .with_replicas(2),
+        )
+        .await
+        .unwrap();
+    auto_scaler.create_scaling_policy(policy).await.unwrap();
+
+    let start = Utc::now() - chrono::Duration::seconds(30);
+    for (step, cpu) in [40.0, 45.0, 50.0, 55.0].into_iter().enumerate() {
+        let mut metrics = utilization(cpu, 30.0, 10.0);
+        metrics.timestamp = start + chrono::Duration::seconds(10 * step as i64);
+        auto_scaler.collect_metrics("web", metrics).await.unwrap();
+    }
+    auto_scaler
+}
+
+fn cpu_policy() -> ScalingPolicy {
+    ScalingPolicy::new(
+        "web-policy".to_string(),
+        "web".to_string(),
+        "web".to_string(),
+        1,
+        8,
+    )
+    .with_target_cpu_utilization(70.0)
+}
+
+#[tokio::test]
+async fn test_trend_is_the_regression_slope() {
+    let collector = MetricsCollector::new();
+    assert!(collector
+        .get_trend("web", TrendField::Cpu, Duration::from_secs(60))
+        .await
+        .is_none());
+
+    let start = Utc::now();
+    for (step, (cpu, rps)) in [(10.0, 100.0), (90.0, 80.0), (20.0, 60.0), (30.0, 40.0)]
+        .into_iter()
+        .enumerate()
+    {
+        let mut metrics = utilization(cpu, 30.0, rps);
+        metrics.timestamp = start + chrono::Duration::seconds(10 * step as i64);
+        collector.collect_metrics("web", metrics).await.unwrap();
+    }
+    let rps = collector
+        .get_trend(
+            "web",
+            TrendField::RequestsPerSecond,
+            Duration::from_secs(60),
+        )
+        .await
+        .unwrap();
+    assert!((rps + 2.0).abs() < 1e-9);
+    assert_eq!(
+        collector
+            .get_trend("web", TrendField::Memory, Duration::from_secs(60))
+            .await,
+        Some(0.0)
+    );
+    // The window only keeps the last two samples: 20% -> 30% in 10s
+    let cpu = collector
+        .get_trend("web", TrendField::Cpu, Duration::from_secs(10))
+        .await
+        .unwrap();
+    assert!((cpu - 1.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_predictive_scaling_fires_before_the_threshold() {
+    // Without prediction, 55% CPU stays below the 70% target
+    let reactive = scaler_with_rising_cpu(cpu_policy()).await;
+    let action = reactive.evaluate_scaling("web").await.unwrap();
+    assert_eq!(action.action_type, ScalingActionType::NoAction);
+
+    // +0.5%/s over the next minute reaches 85%, so double ahead of time
+    let predictive =
+        scaler_with_rising_cpu(cpu_policy().with_predictive_scaling(Duration::from_secs(60))).await;
+    let action = predictive.evaluate_scaling("web").await.unwrap();
+    assert_eq!(action.action_type, ScalingActionType::ScaleUp);
+    assert_eq!((action.from_replicas, action.to_replicas), (2, 4));
+    assert!(action
+        .reason
+        .starts_with("Predicted utilization in 60s: CPU=85.0%"));
+    assert_eq!(predictive.get_deployment("web").await.unwrap().replicas, 4);
+
+    // A short horizon does not reach the target yet
+    let short =
+        scaler_with_rising_cpu(cpu_policy().with_predictive_scaling(Duration::from_secs(20))).await;
+    let action = short.evaluate_scaling("web").await.unwrap();
+    assert_eq!(action.action_type, ScalingActionType::NoAction);
+}
+
+fn policy(strategy: ScalingStrategy) -> ScalingPolicy {
+    ScalingPolicy::new(
+        "web-policy".to_string(),
+        "web".to_string(),
+        "web".to_string(),
+        2,
+        30,
+    )
+    .with_target_cpu_utilization(70.0)
+    .with_target_memory_utilization(80.0)
+    .with_target_requests_per_second(100.0)
+    .with_strategy(strategy)
+}
+
+#[test]
+fn test_doubling_is_the_default_strategy() {
+    let doubling = ScalingPolicy::new("p".to_string(), "p".to_string(), "web".to_string(), 2, 30);
+    assert_eq!(doubling.strategy, ScalingStrategy::Doubling);
+
+    let doubling = policy(ScalingStrategy::default());
+    assert_eq!(
+        doubling.desired_replicas(4, &utilization(71.0, 10.0, 10.0)),
+        8
+    );
+    assert_eq!(
+        doubling.desired_replicas(20, &utilization(71.0, 10.0, 10.0)),
+        30
+    );
+    assert_eq!(
+        doubling.desired_replicas(8, &utilization(20.0, 10.0, 10.0)),
+        4
+    );
+    assert_eq!(
+        doubling.desired_replicas(3, &utilization(20.0, 10.0, 10.0)),
+        2
+    );
+    // Between half the target and the target nothing moves
+    assert_eq!(
+        doubling.desired_replicas(8, &utilization(50.0, 10.0, 10.0)),
+        8
+    );
+}
+
+#[test]
+fn test_step_strategy() {
+    let step = policy(ScalingStrategy::Step { up: 3, down: 1 });
+
+    assert_eq!(
+        step.desired_replicas(20, &utilization(71.0, 10.0, 10.0)),
+        23
+    );
+    assert_eq!(
+        step.desired_replicas(20, &utilization(60.0, 90.0, 10.0)),
+        23
+    );
+    assert_eq!(
+        step.desired_replicas(20, &utilization(20.0, 10.0, 10.0)),
+        19
+    );
+    assert_eq!(
+        step.desired_replicas(20, &utilization(50.0, 10.0, 10.0)),
+        20
+    );
+
+    // Clamped to max_replicas and min_replicas
+    assert_eq!(
+        step.desired_replicas(29, &utilization(99.0, 10.0, 10.0)),
+        30
+    );
+    assert_eq!(
+        step.desired_replicas(30, &utilization(99.0, 10.0, 10.0)),
+        30
+    );
+    assert_eq!(step.desired_replicas(2, &utilization(1.0, 1.0, 1.0)), 2);
+
+    let big_step = policy(ScalingStrategy::Step { up: 10, down: 10 });
+    assert_eq!(big_step.desired_replicas(5, &utilization(1.0, 1.0, 1.0)), 2);
+}
+
+#[test]
+fn test_target_tracking_strategy() {
+    let tracking = policy(ScalingStrategy::TargetTracking);
+
+    // 20 replicas at 71% CPU for a 70% target: ceil(20.29) = 21, not 40
+    assert_eq!(
+        tracking.desired_replicas(20, &utilization(71.0, 10.0, 10.0)),
+        21
+    );
+    assert_eq!(
+        tracking.desired_replicas(10, &utilization(77.0, 10.0, 10.0)),
+        11
+    );
+    assert_eq!(
+        tracking.desired_replicas(10, &utilization(70.0, 10.0, 10.0)),
+        10
+    );
+    assert_eq!(
+        tracking.desired_replicas(10, &utilization(35.0, 10.0, 10.0)),
+        5
+    );
+    // The busiest metric wins: memory at 120/80 beats CPU at 35/70
+    assert_eq!(
+        tracking.desired_replicas(4, &utilization(35.0, 120.0, 10.0)),
+        6
+    );
+    assert_eq!(
+        tracking.desired_replicas(4, &utilization(35.0, 10.0, 250.0)),
+        10
+    );
+
+    // Clamped to max_replicas and min_replicas
+    assert_eq!(
+        tracking.desired_replicas(20, &utilization(140.0, 10.0, 10.0)),
+        30
+    );
+    assert_eq!(tracking.desired_replicas(4, &utilization(1.0, 1.0, 1.0)), 2);
+}
+
+#[tokio::test]
+async fn test_evaluate_scaling_applies_the_strategy() {
+    let auto_scaler = AutoScaler::new();
+    auto_scaler
+        .create_deployment(
+            Deployment::new(
+                "web".to_string(),
+                "web".to_string(),
+                "default".to_string(),
+                "nginx:latest".to_string(),
+            )
+            .with_replicas(20),
+        )
+        .await
+        .unwrap();
+    auto_scaler
+        .create_scaling_policy(policy(ScalingStrategy::TargetTracking))
+        .await
+        .unwrap();
+
+    let action = evaluate(&auto_scaler, utilization(84.0, 10.0, 10.0)).await;
+    assert_eq!(action.action_type, ScalingActionType::ScaleUp);
+    assert_eq!(action.to_replicas, 24);
+    assert_eq!(
+        auto_scaler.get_deployment("web").await.unwrap().replicas,
+        24
+    );
+}
+
+#[test]
+fn test_strategy_round_trips_through_yaml() {
+    let spec: ScalingPolicySpec = serde_yaml::from_str(
+        r#"
+min_replicas: 2
+max_replicas: 10
+target_cpu: 70.0
+target_memory: 80.0
+scale_up_cooldown: { secs: 60, nanos: 0 }
+scale_down_cooldown: { secs: 300, nanos: 0 }
+strategy:
+  type: step
+  up: 2
+  down: 1
+"#,
+    )
+    .unwrap();
+    assert_eq!(spec.strategy, ScalingStrategy::Step { up: 2, down: 1 });
+
+    let yaml = serde_yaml::to_string(&spec).unwrap();
+    assert_eq!(
+        serde_yaml::from_str::<ScalingPolicySpec>(&yaml).unwrap(),
+        spec
+    );
+
+    let tracking = ScalingPolicySpec {
+        strategy: ScalingStrategy::TargetTracking,
+        ..spec
+    };
+    let yaml = serde_yaml::to_string(&tracking).unwrap();
+    assert!(yaml.contains("type: target_tracking"));
+    assert_eq!(
+        serde_yaml::from_str::<ScalingPolicySpec>(&yaml).unwrap(),
+        tracking
+    );
+
+    // Specs written before strategies existed keep doubling
+    let legacy: ScalingPolicySpec = serde_yaml::from_str(
+        "min_replicas: 1\nmax_replicas: 4\ntarget_cpu: 70.0\ntarget_memory: 80.0\n\
+         scale_up_cooldown: { secs: 60, nanos: 0 }\nscale_down_cooldown: { secs: 60, nanos: 0 }\n",
+    )
+    .unwrap();
+    assert_eq!(legacy.strategy, ScalingStrategy::Doubling);
+}
+
+#[tokio::test]
+async fn test_scaling_events_reach_every_subscriber() {
+    let clock = ManualClock::new(Utc::now());
+    let auto_scaler = AutoScaler::new().with_clock(Arc::new(clock.clone()));
+    let mut first = auto_scaler.get_scaling_events().await;
+    let mut second = auto_scaler.get_scaling_events().await;
+
+    auto_scaler
+        .create_deployment(
+            Deployment::new(
+                "web".to_string(),
+                "web".to_string(),
+                "default".to_string(),
+                "nginx:latest".to_string(),
+            )
+            .with_replicas(2),
+        )
+        .await
+        .unwrap();
+    auto_scaler
+        .create_scaling_policy(policy(ScalingStrategy::Doubling))
+        .await
+        .unwrap();
+    evaluate(&auto_scaler, utilization(90.0, 10.0, 10.0)).await;
+
+    for receiver in [&mut first, &mut second] {
+        assert!(matches!(
+            receiver.recv().await.unwrap(),
+            ScalingEvent::DeploymentUpdated { deployment_id } if deployment_id == "web"
+        ));
+        assert!(matches!(
+            receiver.recv().await.unwrap(),
+            ScalingEvent::PolicyUpdated { policy_id } if policy_id == "web-policy"
+        ));
+        match receiver.recv().await.unwrap() {
+            ScalingEvent::ScaleUp {
+                deployment_id,
+                from,
+                to,
+                ..
+            } => {
+                assert_eq!(deployment_id, "web");
+                assert_eq!((from, to), (2, 4));
+            }
+            other => panic!("expected ScaleUp, got {:?}", other),
+        }
+        assert!(matches!(receiver.try_recv(), Err(TryRecvError::Empty)));
+    }
+
+    // Subscribers only see events sent after they subscribed
+    let mut late = auto_scaler.get_scaling_events().await;
+    evaluate(&auto_scaler, utilization(90.0, 10.0, 10.0)).await;
+    assert!(matches!(
+        late.recv().await.unwrap(),
+        ScalingEvent::ScalingBlocked { reason, .. } if reason.starts_with("cooldown")
+    ));
+
+    // Dropping the scaler closes the channel once buffered events are read
+    drop(auto_scaler);
+    assert!(matches!(
+        first.recv().await,
+        Ok(ScalingEvent::ScalingBlocked { .. })
+    ));
+    assert!(matches!(first.recv().await, Err(RecvError::Closed)));
+}
+
+/// Every minute of office hours, Monday to Friday
+const OFFICE_HOURS: &str = "0 * 9-17 * * Mon-Fri";
+
+/// 2026-03-02 is a Monday
+fn monday_at(hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 3, 2, hour, minute, 15).unwrap()
+}
+
+async fn scaler_with_schedules(
+    clock: &ManualClock,
+    replicas: u32,
+    schedules: Vec<ScalingSchedule>,
+) -> AutoScaler {
+    let auto_scaler = AutoScaler::new().with_clock(Arc::new(clock.clone()));
+    auto_scaler
+        .create_deployment(
+            Deployment::new(
+                "web".to_string(),
+                "web".to_string(),
+                "default".to_string(),
+                "nginx:latest".to_string(),
+            )
+            .with_replicas(replicas),
+        )
+        .await
+        .unwrap();
+    let policy = schedules.into_iter().fold(
+        ScalingPolicy::new(
+            "web-policy".to_string(),
+            "web".to_string(),
+            "web".to_string(),
+            1,
+            64,
+        )
+        .with_target_cpu_utilization(50.0),
+        ScalingPolicy::with_schedule,
+    );
+    auto_scaler.create_scaling_policy(policy).await.unwrap();
+    auto_scaler
+}
+
+#[test]
+fn test_schedule_activation() {
+    let schedule = ScalingSchedule::new(OFFICE_HOURS, 4, 10);
+    assert!(schedule.is_active(monday_at(9, 0)));
+    assert!(schedule.is_active(monday_at(17, 59)));
+    assert!(!schedule.is_active(monday_at(8, 59)));
+    assert!(!schedule.is_active(monday_at(18, 0)));
+    let saturday = Utc.with_ymd_and_hms(2026, 3, 7, 12, 0, 0).unwrap();
+    assert!(!schedule.is_active(saturday));
+
+    let disabled = ScalingSchedule {
+        enabled: false,
+        ..schedule
+    };
+    assert!(!disabled.is_active(monday_at(12, 0)));
+    assert!(ScalingSchedule::new("not cron", 1, 2).validate().is_err());
+    assert!(ScalingSchedule::new(OFFICE_HOURS, 5, 2).validate().is_err());
+}
+
+#[tokio::test]
+async fn test_active_schedule_overrides_bounds() {
+    let clock = ManualClock::new(monday_at(10, 0));
+    let auto_scaler =
+        scaler_with_schedules(&clock, 2, vec![ScalingSchedule::new(OFFICE_HOURS, 4, 10)]).await;
+
+    // Moderate load keeps the count, but the schedule raises the floor
+    let floor = evaluate(&auto_scaler, utilization(40.0, 50.0, 10.0)).await;
+    assert_eq!(floor.action_type, ScalingActionType::ScaleUp);
+    assert_eq!(floor.to_replicas, 4);
+    assert_eq!(
+        floor.reason,
+        "Schedule '0 * 9-17 * * Mon-Fri' requires between 4 and 10 replicas"
+    );
+
+    clock.advance(Duration::from_secs(600));
+    let capped = evaluate(&auto_scaler, utilization(80.0, 50.0, 10.0)).await;
+    assert_eq!(capped.to_replicas, 8);
+    clock.advance(Duration::from_secs(600));
+    let capped = evaluate(&auto_scaler, utilization(80.0, 50.0, 10.0)).await;
+    assert_eq!(capped.to_replicas, 10);
+
+    let invalid = ScalingPolicy::new(
+        "bad".to_string(),
+        "bad".to_string(),
+        "web".to_string(),
+        1,
+        2,
+    )
+    .with_schedule(ScalingSchedule::new("every day", 1, 2));
+    assert!(auto_scaler.create_scaling_policy(invalid).await.is_err());
+}
+
+#[tokio::test]
+async fn test_overlapping_schedules_use_highest_priority() {
+    let lunch = ScalingSchedule::new("0 * 12 * * *", 8, 12).with_priority(5);
+    let office = ScalingSchedule::new(OFFICE_HOURS, 4, 10);
+    let policy = ScalingPolicy::new(
+        "web-policy".to_string(),
+        "web".to_string(),
+        "web".to_string(),
+        1,
+        64,
+    )
+    .with_schedule(office.clone())
+    .with_schedule(lunch.clone());
+    assert_eq!(policy.active_schedule(monday_at(12, 30)), Some(&lunch));
+    assert_eq!(policy.active_schedule(monday_at(13, 0)), Some(&office));
+
+    let clock = ManualClock::new(monday_at(12, 30));
+    let auto_scaler = scaler_with_schedules(&clock, 2, vec![office, lunch]).await;
+    let action = evaluate(&auto_scaler, utilization(40.0, 50.0, 10.0)).await;
+    assert_eq!(action.to_replicas, 8);
+    assert!(action.reason.starts_with("Schedule '0 * 12 * * *'"));
+}
+
+#[tokio::test]
+async fn test_policy_bounds_apply_when_no_schedule_is_active() {
+    let saturday = Utc.with_ymd_and_hms(2026, 3, 7, 12, 0, 0).unwrap();
+    let clock = ManualClock::new(saturday);
+    let auto_scaler =
+        scaler_with_schedules(&clock, 2, vec![ScalingSchedule::new(OFFICE_HOURS, 4, 10)]).await;
+
+    let steady = evaluate(&auto_scaler, utilization(40.0, 50.0, 10.0)).await;
+    assert_eq!(steady.action_type, ScalingActionType::NoAction);
+    assert_eq!(auto_scaler.get_deployment("web").await.unwrap().replicas, 2);
+
+    let idle = evaluate(&auto_scaler, utilization(5.0, 5.0, 1.0)).await;
+    assert_eq!(idle.action_type, ScalingActionType::ScaleDown);
+    assert_eq!(idle.to_replicas, 1);
+}
+
+async fn idle_scaler(clock: &ManualClock, policy: ScalingPolicy) -> AutoScaler {
+    let auto_scaler = AutoScaler::new().with_clock(Arc::new(clock.clone()));
+    auto_scaler
+        .create_deployment(
+            Deployment::new(
+                "web".to_string(),
+                "web".to_string(),
+                "default".to_string(),
+                "nginx:latest".to_string(),
+            )
+            .with_replicas(4),
+        )
+        .await
+        .unwrap();
+    auto_scaler.create_scaling_policy(policy).await.unwrap();
+    auto_scaler
+}
+
+fn zero_policy() -> ScalingPolicy {
+    ScalingPolicy::new(
+        "web-policy".to_string(),
+        "web".to_string(),
+        "web".to_string(),
+        2,
+        8,
+    )
+    .with_target_cpu_utilization(50.0)
+    .with_scale_down_cooldown(Duration::from_secs(60))
+}
+
+#[tokio::test]
+async fn test_scale_to_zero_and_wake() {
+    let clock = ManualClock::new(Utc::now());
+    let auto_scaler = idle_scaler(&clock, zero_policy().with_scale_to_zero()).await;
+
+    let mut replicas = Vec::new();
+    for _ in 0..4 {
+        let action = evaluate(&auto_scaler, utilization(1.0, 2.0, 0.5)).await;
+        replicas.push(action.to_replicas);
+        clock.advance(Duration::from_secs(60));
+    }
+    assert_eq!(replicas, vec![2, 1, 0, 0]);
+    assert_eq!(auto_scaler.get_deployment("web").await.unwrap().replicas, 0);
+
+    let mut events = auto_scaler.get_scaling_events().await;
+    auto_scaler.wake_deployment("web").await.unwrap();
+    let deployment = auto_scaler.get_deployment("web").await.unwrap();
+    assert_eq!((deployment.replicas, deployment.desired_replicas), (2, 2));
+    match events.try_recv().unwrap() {
+        ScalingEvent::ScaleUp { from, to, .. } => assert_eq!((from, to), (0, 2)),
+        other => panic!("unexpected event {:?}", other),
+    }
+
+    // Waking a deployment that has replicas is a no-op
+    auto_scaler.wake_deployment("web").await.unwrap();
+    assert!(matches!(events.try_recv(), Err(TryRecvError::Empty)));
+    assert!(auto_scaler.wake_deployment("missing").await.is_err());
+}
+
+#[tokio::test]
+async fn test_scale_to_zero_requires_idle_metrics_and_opt_in() {
+    let clock = ManualClock::new(Utc::now());
+    let auto_scaler = idle_scaler(&clock, zero_policy()).await;
+    for _ in 0..3 {
+        evaluate(&auto_scaler, utilization(1.0, 2.0, 0.5)).await;
+        clock.advance(Duration::from_secs(60));
+    }
+    assert_eq!(auto_scaler.get_deployment("web").await.unwrap().replicas, 2);
+
+    // Low but not idle: memory sits above 10% of its 80% target
+    let policy = zero_policy().with_scale_to_zero();
+    assert_eq!(policy.desired_replicas(2, &utilization(1.0, 20.0, 0.5)), 2);
+    assert_eq!(policy.desired_replicas(2, &utilization(1.0, 2.0, 0.5)), 1);
+    // Load on a deployment at zero brings back the minimum
+    assert_eq!(policy.desired_replicas(0, &utilization(30.0, 20.0, 5.0)), 2);
+}
+
+/// Queue depth that the test sets by hand
+#[derive(Default)]
+struct FakeQueueDepth {
+    depth: Mutex<f64>,
+}
+
+impl FakeQueueDepth {
+    fn set(&self, depth: f64) {
+        *self.depth.lock().unwrap() = depth;
+    }
+}
+
+#[async_trait]
+impl MetricProvider for FakeQueueDepth {
+    async fn collect(&self, _deployment_id: &str) -> Result<f64> {
+        Ok(*self.depth.lock().unwrap())
+    }
+}
+
+struct BrokenProvider;
+
+#[async_trait]
+impl MetricProvider for BrokenProvider {
+    async fn collect(&self, deployment_id: &str) -> Result<f64> {
+        Err(anyhow!("broker unreachable for {}", deployment_id))
+    }
+}
+
+async fn scaler_with_queue(queue: &Arc<FakeQueueDepth>, strategy: ScalingStrategy) -> AutoScaler {
+    let auto_scaler = AutoScaler::new();
+    auto_scaler
+        .create_deployment(
+            Deployment::new(
+                "web".to_string(),
+                "web".to_string(),
+                "default".to_string(),
+                "nginx:latest".to_string(),
+            )
+            .with_replicas(2),
+        )
+        .await
+        .unwrap();
+    auto_scaler
+        .create_scaling_policy(
+            ScalingPolicy::new(
+                "web-policy".to_string(),
+                "web".to_string(),
+                "web".to_string(),
+                1,
+                16,
+            )
+            .with_target_cpu_utilization(50.0)
+            .with_scale_up_cooldown(Duration::ZERO)
+            .with_scale_down_cooldown(Duration::ZERO)
+            .with_strategy(strategy)
+            .with_custom_metric_target("queue_depth", 100.0)
+            .with_custom_metric_target("broker_lag", 5.0),
+        )
+        .await
+        .unwrap();
+    auto_scaler
+        .register_metric_provider("queue_depth", queue.clone())
+        .await;
+    auto_scaler
+        .register_metric_provider("broker_lag", Arc::new(BrokenProvider))
+        .await;
+    auto_scaler
+}
+
+#[tokio::test]
+async fn test_queue_depth_drives_scaling() {
+    let queue = Arc::new(FakeQueueDepth::default());
+    let auto_scaler = scaler_with_queue(&queue, ScalingStrategy::Doubling).await;
+
+    // The built-in metrics alone would keep two replicas
+    queue.set(250.0);
+    let up = evaluate(&auto_scaler, utilization(30.0, 50.0, 10.0)).await;
+    assert_eq!(up.action_type, ScalingActionType::ScaleUp);
+    assert_eq!(up.to_replicas, 4);
+    assert_eq!(
+        up.reason,
+        "High utilization: CPU=30.0%, Memory=50.0%, RPS=10.0, queue_depth=250.0"
+    );
+
+    queue.set(80.0);
+    let steady = evaluate(&auto_scaler, utilization(10.0, 10.0, 1.0)).await;
+    assert_eq!(steady.action_type, ScalingActionType::NoAction);
+
+    // Scaling down needs the queue below half of its target as well
+    queue.set(20.0);
+    let down = evaluate(&auto_scaler, utilization(10.0, 10.0, 1.0)).await;
+    assert_eq!(down.action_type, ScalingActionType::ScaleDown);
+    assert_eq!(down.to_replicas, 2);
+}
+
+#[tokio::test]
+async fn test_queue_depth_with_target_tracking() {
+    let queue = Arc::new(FakeQueueDepth::default());
+    let auto_scaler = scaler_with_queue(&queue, ScalingStrategy::TargetTracking).await;
+
+    queue.set(300.0);
+    let action = evaluate(&auto_scaler, utilization(30.0, 50.0, 10.0)).await;
+    assert_eq!(action.to_replicas, 6);
+
+    let policy = auto_scaler.get_scaling_policy("web-policy").await.unwrap();
+    let custom = [("queue_depth".to_string(), 40.0)].into();
+    assert_eq!(
+        policy.desired_replicas_with_custom(6, &utilization(30.0, 50.0, 10.0), &custom),
+        4
+    );
+}
@@ -0,0 +1,194 @@
+use polis_core::ContainerStatus;
+use polis_orchestrator::{
+    ApplyAction, DeploymentSpec, DeploymentStatusType, Manifest, Orchestrator, OrchestratorConfig,
+};
+use polis_test_support::{FakeRuntime, Lifecycle, RuntimeCall};
+use std::path::Path;
+use std::sync::Arc;
+
+async fn orchestrator(state_dir: &Path, runtime: &Arc<FakeRuntime>) -> Orchestrator {
+    let config = OrchestratorConfig {
+        state_dir: state_dir.to_path_buf(),
+        ..Default::default()
+    };
+    Orchestrator::new(config, runtime.clone()).await.unwrap()
+}
+
+fn spec(image: &str, replicas: u32) -> DeploymentSpec {
+    serde_yaml::from_str(&format!(
+        r#"
+name: web
+image: {}
+replicas: {}
+env_vars:
+  LOG_LEVEL: debug
+labels:
+  app: web
+"#,
+        image, replicas
+    ))
+    .unwrap()
+}
+
+fn creates(runtime: &FakeRuntime) -> usize {
+    runtime.count_calls(|call| matches!(call, RuntimeCall::Create { .. }))
+}
+
+fn removes(runtime: &FakeRuntime) -> usize {
+    runtime.count_calls(|call| matches!(call, RuntimeCall::Remove(_)))
+}
+
+#[tokio::test]
+async fn test_deploy_starts_replicas() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    let orchestrator = orchestrator(temp.path(), &runtime).await;
+
+    let status = orchestrator.deploy(spec("nginx:1.25", 3)).await.unwrap();
+    assert_eq!(status.current_replicas, 3);
+    assert_eq!(status.ready_replicas, 3);
+    assert_eq!(status.available_replicas, 3);
+    assert_eq!(status.status, DeploymentStatusType::Running);
+    assert_eq!(creates(&runtime), 3);
+    assert_eq!(
+        runtime.count_calls(|call| matches!(call, RuntimeCall::Start(_))),
+        3
+    );
+
+    let containers = runtime.containers();
+    assert_eq!(containers.len(), 3);
+    for container in &containers {
+        assert!(container.name.starts_with("web-"));
+        assert_eq!(container.image.0, "nginx:1.25");
+        assert_eq!(container.status, ContainerStatus::Running);
+        assert_eq!(container.environment["LOG_LEVEL"], "debug");
+        assert_eq!(container.labels["app"], "web");
+    }
+}
+
+#[tokio::test]
+async fn test_scale_and_delete_converge_containers() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    let orchestrator = orchestrator(temp.path(), &runtime).await;
+    orchestrator.deploy(spec("nginx:1.25", 2)).await.unwrap();
+
+    orchestrator
+        .scale_deployment("web", "default", 5)
+        .await
+        .unwrap();
+    assert_eq!(creates(&runtime), 5);
+    assert_eq!(removes(&runtime), 0);
+    let status = orchestrator
+        .get_deployment_status("web", "default")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(status.current_replicas, 5);
+    assert_eq!(status.ready_replicas, 5);
+
+    let original = runtime.containers()[0].id.clone();
+    orchestrator
+        .scale_deployment("web", "default", 1)
+        .await
+        .unwrap();
+    assert_eq!(creates(&runtime), 5);
+    assert_eq!(removes(&runtime), 4);
+    assert_eq!(
+        runtime.count_calls(|call| matches!(call, RuntimeCall::Stop(_))),
+        4
+    );
+    let remaining = runtime.containers();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].id, original);
+
+    orchestrator
+        .delete_deployment("web", "default")
+        .await
+        .unwrap();
+    assert_eq!(removes(&runtime), 5);
+    assert!(runtime.containers().is_empty());
+    assert!(orchestrator
+        .get_deployment_status("web", "default")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_replicas_that_exit_are_not_ready() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    runtime.script("crashy:1", Lifecycle::exits_with(1));
+    let orchestrator = orchestrator(temp.path(), &runtime).await;
+
+    let status = orchestrator.deploy(spec("crashy:1", 2)).await.unwrap();
+    assert_eq!(status.current_replicas, 2);
+    assert_eq!(status.ready_replicas, 0);
+    assert_eq!(status.status, DeploymentStatusType::Pending);
+}
+
+#[tokio::test]
+async fn test_failed_deploy_removes_created_containers() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    runtime.script("broken:1", Lifecycle::fail_start("no entrypoint"));
+    let orchestrator = orchestrator(temp.path(), &runtime).await;
+
+    assert!(orchestrator.deploy(spec("broken:1", 3)).await.is_err());
+    assert_eq!(creates(&runtime), 1);
+    assert_eq!(removes(&runtime), 1);
+    assert!(runtime.containers().is_empty());
+    assert!(orchestrator
+        .list_deployments(None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_paused_deployment_holds_updates_until_resumed() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    let orchestrator = orchestrator(temp.path(), &runtime).await;
+    orchestrator.deploy(spec("nginx:1.25", 2)).await.unwrap();
+    orchestrator
+        .pause_deployment("web", "default")
+        .await
+        .unwrap();
+
+    orchestrator
+        .apply(Manifest::Deployment(Box::new(spec("nginx:1.26", 2))))
+        .await
+        .unwrap();
+    assert_eq!(creates(&runtime), 2);
+    let status = orchestrator
+        .get_deployment_status("web", "default")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(status.status, DeploymentStatusType::Paused);
+    assert_eq!(
+        orchestrator
+            .plan(&Manifest::Deployment(Box::new(spec("nginx:1.26", 2))))
+            .await
+            .unwrap(),
+        ApplyAction::Unchanged
+    );
+
+    orchestrator
+        .resume_deployment("web", "default")
+        .await
+        .unwrap();
+    let containers = runtime.containers();
+    assert_eq!(containers.len(), 2);
+    assert!(containers
+        .iter()
+        .all(|container| container.image.0 == "nginx:1.26"));
+    let status = orchestrator
+        .get_deployment_status("web", "default")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(status.status, DeploymentStatusType::Running);
+}
@@ -0,0 +1,204 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use mockito::{Matcher, Server};
+use polis_core::ManualClock;
+use polis_orchestrator::{
+    ActionOutcome, CheckType, CircuitState, FailureAction, HealthCheckDef, HealthCheckResult,
+    HealthMonitor, HealthStatus, TargetController, TargetType,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Counts the calls made to it instead of touching any target
+#[derive(Default)]
+struct MockController {
+    restarts: AtomicUsize,
+    marked: AtomicUsize,
+}
+
+#[async_trait]
+impl TargetController for MockController {
+    async fn restart(&self, _check: &HealthCheckDef) -> anyhow::Result<()> {
+        self.restarts.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn mark_unhealthy(&self, _check: &HealthCheckDef) -> anyhow::Result<()> {
+        self.marked.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+fn check(actions: Vec<FailureAction>) -> HealthCheckDef {
+    let mut check = HealthCheckDef::new(
+        "web".to_string(),
+        "web".to_string(),
+        TargetType::Container,
+        "web-1".to_string(),
+        CheckType::Custom {
+            script: "true".to_string(),
+        },
+    )
+    .with_failure_threshold(2);
+    for action in actions {
+        check = check.with_failure_action(action);
+    }
+    // Results are recorded by the tests
+    check.enabled = false;
+    check
+}
+
+fn result(status: HealthStatus) -> HealthCheckResult {
+    HealthCheckResult {
+        check_id: "web".to_string(),
+        target_id: "web-1".to_string(),
+        status,
+        message: "connection refused".to_string(),
+        response_time: Duration::from_millis(5),
+        timestamp: Utc::now(),
+        consecutive_failures: 0,
+        consecutive_successes: 0,
+        metadata: HashMap::new(),
+        circuit_state: CircuitState::Closed,
+    }
+}
+
+/// Records a success followed by enough failures to cross the threshold of 2
+/// and a few more
+async fn fail(monitor: &HealthMonitor) {
+    monitor.record_result(result(HealthStatus::Healthy)).await;
+    for _ in 0..4 {
+        monitor.record_result(result(HealthStatus::Unhealthy)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_webhook_fires_once_per_transition() {
+    let mut server = Server::new_async().await;
+    let hook = server
+        .mock("PUT", "/alerts")
+        .match_body(Matcher::Exact(
+            "web-1 down after 2 failures: connection refused".to_string(),
+        ))
+        .with_status(200)
+        .expect(2)
+        .create_async()
+        .await;
+
+    let clock = Arc::new(ManualClock::new(Utc::now()));
+    let monitor = HealthMonitor::new().with_clock(clock.clone());
+    monitor
+        .create_health_check(check(vec![FailureAction::Webhook {
+            url: format!("{}/alerts", server.url()),
+            method: "put".to_string(),
+            body_template:
+                "{{target_id}} down after {{consecutive_failures}} failures: {{message}}"
+                    .to_string(),
+        }]))
+        .await
+        .unwrap();
+
+    fail(&monitor).await;
+    assert_eq!(monitor.get_action_history("web").await.len(), 1);
+
+    // Recovering and failing again is a new transition
+    clock.advance(Duration::from_secs(120));
+    fail(&monitor).await;
+
+    hook.assert_async().await;
+    let history = monitor.get_action_history("web").await;
+    assert_eq!(history.len(), 2);
+    assert!(history
+        .iter()
+        .all(|record| record.outcome == ActionOutcome::Succeeded));
+}
+
+#[tokio::test]
+async fn test_restarts_are_capped_within_window() {
+    let controller = Arc::new(MockController::default());
+    let clock = Arc::new(ManualClock::new(Utc::now()));
+    let monitor = HealthMonitor::new()
+        .with_clock(clock.clone())
+        .with_target_controller(controller.clone())
+        .with_action_cooldown(Duration::from_secs(30));
+    monitor
+        .create_health_check(check(vec![
+            FailureAction::RestartContainer {
+                max_restarts: 2,
+                window: Duration::from_secs(600),
+            },
+            FailureAction::MarkServiceUnhealthy,
+        ]))
+        .await
+        .unwrap();
+
+    for _ in 0..3 {
+        fail(&monitor).await;
+        clock.advance(Duration::from_secs(60));
+    }
+    assert_eq!(controller.restarts.load(Ordering::SeqCst), 2);
+    assert_eq!(controller.marked.load(Ordering::SeqCst), 3);
+
+    let history = monitor.get_action_history("web").await;
+    let latest_restart = history
+        .iter()
+        .find(|record| matches!(record.action, FailureAction::RestartContainer { .. }))
+        .unwrap();
+    assert!(matches!(
+        latest_restart.outcome,
+        ActionOutcome::RateLimited(_)
+    ));
+
+    // Once the earlier restarts leave the window the target is restarted again
+    clock.advance(Duration::from_secs(600));
+    fail(&monitor).await;
+    assert_eq!(controller.restarts.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_cooldown_suppresses_flapping_targets() {
+    let controller = Arc::new(MockController::default());
+    let monitor = HealthMonitor::new().with_target_controller(controller.clone());
+    monitor
+        .create_health_check(check(vec![FailureAction::MarkServiceUnhealthy]))
+        .await
+        .unwrap();
+
+    fail(&monitor).await;
+    fail(&monitor).await;
+    assert_eq!(controller.marked.load(Ordering::SeqCst), 1);
+
+    let outcomes: Vec<ActionOutcome> = monitor
+        .get_action_history("web")
+        .await
+        .into_iter()
+        .map(|record| record.outcome)
+        .collect();
+    assert!(matches!(outcomes[0], ActionOutcome::RateLimited(_)));
+    assert_eq!(outcomes[1], ActionOutcome::Succeeded);
+}
+
+#[tokio::test]
+async fn test_actions_without_controller_are_recorded_as_failed() {
+    let monitor = HealthMonitor::new();
+    monitor
+        .create_health_check(check(vec![FailureAction::RestartContainer {
+            max_restarts: 3,
+            window: Duration::from_secs(60),
+        }]))
+        .await
+        .unwrap();
+
+    fail(&monitor).await;
+    let history = monitor.get_action_history("web").await;
+    assert_eq!(history.len(), 1);
+    let ActionOutcome::Failed(message) = &history[0].outcome else {
+        panic!("expected a failed restart, got {:?}", history[0].outcome);
+    };
+    assert!(message.contains("controller"), "{message}");
+
+    monitor.delete_health_check("web").await.unwrap();
+    assert!(monitor.get_action_history("web").await.is_empty());
+}
@@ -0,0 +1,119 @@
+use polis_orchestrator::{
+    CheckType, HealthCheckDef, HealthMonitor, HealthStatus, TargetType, HEALTH_CHECKS_VERSION,
+};
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Healthy while `dir/ready` exists
+fn file_check(id: &str, dir: &Path) -> HealthCheckDef {
+    HealthCheckDef::new(
+        id.to_string(),
+        id.to_string(),
+        TargetType::Custom,
+        dir.display().to_string(),
+        CheckType::File {
+            path: "ready".to_string(),
+            exists: true,
+        },
+    )
+    .with_interval(Duration::from_millis(50))
+    .with_retries(1)
+}
+
+#[tokio::test]
+async fn test_checks_survive_restart_and_resume_probing() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("health_checks.json");
+    std::fs::write(dir.path().join("ready"), "").unwrap();
+
+    {
+        let monitor = HealthMonitor::new().with_store(store.clone());
+        let mut disabled = file_check("db", dir.path());
+        disabled.enabled = false;
+        monitor.create_health_check(disabled).await.unwrap();
+        monitor
+            .create_health_check(file_check("web", dir.path()))
+            .await
+            .unwrap();
+        monitor.add_dependency("web", "db").await.unwrap();
+        let result = monitor.run_health_check("db").await.unwrap();
+        assert_eq!(result.status, HealthStatus::Healthy);
+    }
+    assert!(store.exists());
+    assert!(!store.with_extension("json.tmp").exists());
+
+    let monitor = HealthMonitor::new().with_store(store.clone());
+    assert_eq!(monitor.load().await.unwrap(), 2);
+
+    let mut ids: Vec<String> = monitor
+        .list_health_checks()
+        .await
+        .into_iter()
+        .map(|check| check.id)
+        .collect();
+    ids.sort();
+    assert_eq!(ids, vec!["db", "web"]);
+    assert_eq!(monitor.get_dependencies("web").await, vec!["db"]);
+
+    // The latest result of the disabled check is restored as is
+    let db = monitor.get_health_check_result("db").await.unwrap();
+    assert_eq!(db.status, HealthStatus::Healthy);
+
+    // The enabled check is probed again
+    let mut probed = false;
+    for _ in 0..50 {
+        if monitor.get_health_check_result("web").await.is_some() {
+            probed = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(probed, "the restored check was never probed");
+}
+
+#[tokio::test]
+async fn test_yaml_store_forgets_deleted_checks() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("checks.yaml");
+
+    let monitor = HealthMonitor::new().with_store(store.clone());
+    for id in ["api", "cache"] {
+        let mut check = file_check(id, dir.path());
+        check.enabled = false;
+        monitor.create_health_check(check).await.unwrap();
+    }
+    monitor.delete_health_check("cache").await.unwrap();
+
+    let content = std::fs::read_to_string(&store).unwrap();
+    let value: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+    assert_eq!(
+        value["version"].as_u64(),
+        Some(HEALTH_CHECKS_VERSION as u64)
+    );
+
+    let restored = HealthMonitor::new().with_store(store);
+    assert_eq!(restored.load().await.unwrap(), 1);
+    assert!(restored.get_health_check("api").await.is_some());
+    assert!(restored.get_health_check("cache").await.is_none());
+}
+
+#[tokio::test]
+async fn test_missing_store_restores_nothing_and_newer_layout_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let store = dir.path().join("health_checks.json");
+
+    let monitor = HealthMonitor::new().with_store(store.clone());
+    assert_eq!(monitor.load().await.unwrap(), 0);
+
+    std::fs::write(
+        &store,
+        format!(
+            r#"{{"version": {}, "checks": []}}"#,
+            HEALTH_CHECKS_VERSION + 1
+        ),
+    )
+    .unwrap();
+    let error = monitor.load().await.unwrap_err().to_string();
+    assert!(error.contains("newer"), "{error}");
+}
@@ -0,0 +1,212 @@
+use mockito::{Matcher, Mock, Server};
+use polis_orchestrator::{
+    CheckType, HealthCheckDef, HealthEventKind, HealthMonitor, TargetType, WebhookFilter,
+};
+use serde_json::json;
+use std::time::Duration;
+
+fn check(id: &str, target_type: TargetType) -> HealthCheckDef {
+    let mut check = HealthCheckDef::new(
+        id.to_string(),
+        id.to_string(),
+        target_type,
+        format!("{id}-target"),
+        CheckType::Command {
+            command: "true".to_string(),
+            args: Vec::new(),
+        },
+    )
+    .with_label("app".to_string(), "web".to_string());
+    // Only the events of creating and deleting the check are wanted here
+    check.enabled = false;
+    check
+}
+
+/// Waits until `mock` received the hits it expects, then a little longer so
+/// that unexpected extra requests would be seen as well
+async fn settle(mock: &Mock) {
+    for _ in 0..50 {
+        if mock.matched_async().await {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(300)).await;
+}
+
+#[tokio::test]
+async fn test_matching_event_is_posted_once() {
+    let mut server = Server::new_async().await;
+    let created = server
+        .mock("POST", "/hooks/health")
+        .match_header("content-type", "application/json")
+        .match_body(Matcher::PartialJson(json!({
+            "event": {"CheckCreated": {"check_id": "web", "target_id": "web-target"}},
+            "check_name": "web",
+            "target_type": "Container",
+            "labels": {"app": "web"},
+        })))
+        .with_status(200)
+        .expect(1)
+        .create_async()
+        .await;
+    let mut unwanted = Vec::new();
+    for event in [
+        json!({"CheckCreated": {"check_id": "svc"}}),
+        json!({"CheckDeleted": {"check_id": "web"}}),
+        json!({"CheckCreated": {"check_id": "db"}}),
+    ] {
+        let mock = server
+            .mock("POST", "/hooks/health")
+            .match_body(Matcher::PartialJson(json!({ "event": event })))
+            .with_status(200)
+            .expect(0)
+            .create_async()
+            .await;
+        unwanted.push(mock);
+    }
+
+    let monitor = HealthMonitor::new();
+    monitor
+        .add_webhook(
+            format!("{}/hooks/health", server.url()),
+            WebhookFilter::default()
+                .with_target_type(TargetType::Container)
+                .with_event(HealthEventKind::CheckCreated)
+                .with_label("app".to_string(), "web".to_string()),
+        )
+        .await
+        .unwrap();
+
+    monitor
+        .create_health_check(check("web", TargetType::Container))
+        .await
+        .unwrap();
+    // Filtered out by target type, event kind and label respectively
+    monitor
+        .create_health_check(check("svc", TargetType::Service))
+        .await
+        .unwrap();
+    monitor.delete_health_check("web").await.unwrap();
+    let mut unlabelled = check("db", TargetType::Container);
+    unlabelled.labels.clear();
+    monitor.create_health_check(unlabelled).await.unwrap();
+
+    settle(&created).await;
+    created.assert_async().await;
+    for mock in unwanted {
+        mock.assert_async().await;
+    }
+}
+
+#[tokio::test]
+async fn test_server_errors_are_retried_three_times() {
+    let mut server = Server::new_async().await;
+    let failing = server
+        .mock("POST", "/hook")
+        .with_status(503)
+        .expect(3)
+        .create_async()
+        .await;
+
+    let monitor = HealthMonitor::new();
+    monitor
+        .add_webhook(format!("{}/hook", server.url()), WebhookFilter::default())
+        .await
+        .unwrap();
+    monitor
+        .create_health_check(check("web", TargetType::Container))
+        .await
+        .unwrap();
+
+    settle(&failing).await;
+    failing.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_client_errors_are_not_retried() {
+    let mut server = Server::new_async().await;
+    let rejected = server
+        .mock("POST", "/hook")
+        .with_status(400)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let monitor = HealthMonitor::new();
+    monitor
+        .add_webhook(format!("{}/hook", server.url()), WebhookFilter::default())
+        .await
+        .unwrap();
+    monitor
+        .create_health_check(check("web", TargetType::Container))
+        .await
+        .unwrap();
+
+    settle(&rejected).await;
+    rejected.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_status_transition_is_posted_and_webhook_can_be_disabled() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("up"), "").unwrap();
+
+    let mut server = Server::new_async().await;
+    let failed = server
+        .mock("POST", "/hook")
+        .match_body(Matcher::PartialJson(json!({
+            "event": {"CheckFailed": {"check_id": "probe"}},
+        })))
+        .with_status(200)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let monitor = HealthMonitor::new();
+    let url = format!("{}/hook", server.url());
+    monitor
+        .add_webhook(
+            url.clone(),
+            WebhookFilter::default().with_event(HealthEventKind::CheckFailed),
+        )
+        .await
+        .unwrap();
+
+    let probe = HealthCheckDef::new(
+        "probe".to_string(),
+        "probe".to_string(),
+        TargetType::Custom,
+        "probe-target".to_string(),
+        CheckType::Custom {
+            script: format!("test -e {}/up", dir.path().display()),
+        },
+    )
+    .with_interval(Duration::from_millis(50))
+    .with_retries(1);
+    monitor.create_health_check(probe).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    std::fs::remove_file(dir.path().join("up")).unwrap();
+    settle(&failed).await;
+    failed.assert_async().await;
+
+    // Once disabled, a new failure is no longer posted
+    monitor.disable_webhook(&url).await.unwrap();
+    std::fs::write(dir.path().join("up"), "").unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    std::fs::remove_file(dir.path().join("up")).unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    failed.assert_async().await;
+
+    monitor.delete_health_check("probe").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_webhook_url_is_rejected() {
+    let monitor = HealthMonitor::new();
+    assert!(monitor
+        .add_webhook("not a url".to_string(), WebhookFilter::default())
+        .await
+        .is_err());
+}
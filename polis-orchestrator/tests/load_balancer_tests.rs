@@ -0,0 +1,1061 @@
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::Router;
+use bytes::Bytes;
+use futures::StreamExt;
+use polis_core::ManualClock;
+use polis_orchestrator::service_discovery::HealthStatus;
+use polis_orchestrator::{
+    Body, CircuitState, EndpointState, HashKey, LoadBalancer, LoadBalancerRequest,
+    LoadBalancerResponse, LoadBalancingAlgorithm, Protocol, RetryCondition, RetryPolicy,
+    RoutingRule, ServiceEndpoint,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// Serves `/ok` with 200 and `/fail` with 503 on a random local port
+async fn backend() -> u16 {
+    let app = Router::new().route("/ok", get(|| async { "ok" })).route(
+        "/fail",
+        get(|| async { (StatusCode::SERVICE_UNAVAILABLE, "down") }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    port
+}
+
+fn endpoint(id: &str, port: u16) -> ServiceEndpoint {
+    let mut endpoint = ServiceEndpoint::new("127.0.0.1".to_string(), port, Protocol::Http);
+    endpoint.id = id.to_string();
+    endpoint.health_status = HealthStatus::Healthy;
+    endpoint
+}
+
+fn request(path: &str) -> LoadBalancerRequest {
+    LoadBalancerRequest {
+        client_ip: None,
+        session_id: None,
+        headers: HashMap::new(),
+        path: path.to_string(),
+        method: "GET".to_string(),
+        body: None,
+    }
+}
+
+#[tokio::test]
+async fn test_stats_count_forwarded_requests() {
+    let port = backend().await;
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    lb.add_endpoint(endpoint("web-1", port)).await;
+
+    for path in ["/ok", "/ok", "/fail", "/ok", "/fail"] {
+        lb.handle_request(request(path)).await.unwrap();
+    }
+
+    let stats = lb.get_stats().await;
+    assert_eq!(stats.total_requests, 5);
+    assert_eq!(stats.successful_requests, 3);
+    assert_eq!(stats.failed_requests, 2);
+    assert!(stats.average_response_time > Duration::ZERO);
+
+    let web = &stats.endpoint_stats["web-1"];
+    assert_eq!(
+        (web.requests, web.successful_requests, web.failed_requests),
+        (5, 3, 2)
+    );
+    assert_eq!(web.last_error.as_deref(), Some("HTTP 503"));
+    assert_eq!(web.average_response_time, web.total_response_time / 5);
+    assert!(web.ewma_response_time > Duration::ZERO);
+    assert_eq!(web.active_connections, 0);
+
+    // The stats are what the REST API returns
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!(json["endpoint_stats"]["web-1"]["requests"], 5);
+
+    lb.reset_stats().await;
+    let stats = lb.get_stats().await;
+    assert_eq!(stats.total_requests, 0);
+    assert_eq!(stats.endpoint_stats["web-1"].requests, 0);
+    assert_eq!(stats.average_response_time, Duration::ZERO);
+}
+
+#[tokio::test]
+async fn test_unreachable_endpoint_counts_as_failure() {
+    let closed = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    lb.add_endpoint(endpoint("gone", closed)).await;
+
+    let response = lb.handle_request(request("/ok")).await.unwrap();
+    assert_eq!(response.status_code, 500);
+
+    let stats = lb.get_stats().await;
+    assert_eq!((stats.total_requests, stats.failed_requests), (1, 1));
+    let gone = &stats.endpoint_stats["gone"];
+    assert_eq!(gone.last_error, response.error);
+    assert!(gone.last_error.is_some());
+}
+
+/// Serves `/` after `latency`
+async fn backend_with_latency(latency: Duration) -> u16 {
+    let app = Router::new().route(
+        "/",
+        get(move || async move {
+            tokio::time::sleep(latency).await;
+            "ok"
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    port
+}
+
+/// Sends `requests` requests and returns how many the fast endpoint served
+async fn simulate(lb: LoadBalancer, requests: usize) -> usize {
+    let slow = backend_with_latency(Duration::from_millis(60)).await;
+    let fast = backend_with_latency(Duration::from_millis(5)).await;
+    // The slow endpoint comes first so that ties would favour it
+    lb.add_endpoint(endpoint("slow", slow)).await;
+    lb.add_endpoint(endpoint("fast", fast)).await;
+
+    let mut served_by_fast = 0;
+    for _ in 0..requests {
+        let response = lb.handle_request(request("/")).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        if response.endpoint.id == "fast" {
+            served_by_fast += 1;
+        }
+    }
+    served_by_fast
+}
+
+#[tokio::test]
+async fn test_least_response_time_prefers_faster_endpoint() {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::LeastResponseTime)
+        .with_default_latency(Duration::ZERO);
+
+    let served_by_fast = simulate(lb, 20).await;
+    assert!(served_by_fast >= 18, "fast served {served_by_fast}/20");
+}
+
+#[tokio::test]
+async fn test_peak_ewma_prefers_faster_endpoint() {
+    let lb =
+        LoadBalancer::new(LoadBalancingAlgorithm::PeakEwma).with_default_latency(Duration::ZERO);
+
+    let served_by_fast = simulate(lb, 20).await;
+    assert!(served_by_fast >= 18, "fast served {served_by_fast}/20");
+}
+
+#[tokio::test]
+async fn test_default_latency_decides_whether_new_endpoints_are_tried() {
+    // Assumed slower than the slow endpoint's real latency, the fast one is
+    // never tried
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::LeastResponseTime)
+        .with_default_latency(Duration::from_secs(1));
+
+    assert_eq!(simulate(lb, 5).await, 0);
+}
+
+#[tokio::test]
+async fn test_least_response_time_selects_lowest_ewma() {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::LeastResponseTime);
+    for (id, millis) in [("a", 40), ("b", 15), ("c", 90)] {
+        lb.add_endpoint(endpoint(id, 8080)).await;
+        lb.record_response(id, Duration::from_millis(millis)).await;
+    }
+
+    for _ in 0..10 {
+        let selected = lb.select_endpoint(&request("/")).await.unwrap().unwrap();
+        assert_eq!(selected.id, "b");
+    }
+
+    let stats = lb.get_stats().await;
+    assert_eq!(stats.endpoint_stats["b"].ewma_response_time_ns, 15_000_000);
+    assert_eq!(stats.endpoint_stats["c"].ewma_response_time_ns, 90_000_000);
+    // Only proxied requests are counted
+    assert_eq!(stats.total_requests, 0);
+}
+
+#[tokio::test]
+async fn test_ewma_alpha_sets_how_fast_averages_follow() {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::LeastResponseTime).with_ewma_alpha(0.5);
+    lb.add_endpoint(endpoint("a", 8080)).await;
+    lb.add_endpoint(endpoint("b", 8081)).await;
+    lb.record_response("a", Duration::from_millis(10)).await;
+    lb.record_response("b", Duration::from_millis(30)).await;
+
+    // Halfway from 10ms to 90ms overtakes b
+    lb.record_response("a", Duration::from_millis(90)).await;
+    let stats = lb.get_stats().await;
+    assert_eq!(stats.endpoint_stats["a"].ewma_response_time_ns, 50_000_000);
+    let selected = lb.select_endpoint(&request("/")).await.unwrap().unwrap();
+    assert_eq!(selected.id, "b");
+
+    // With the default alpha one slow request is not enough
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::LeastResponseTime);
+    lb.add_endpoint(endpoint("a", 8080)).await;
+    lb.add_endpoint(endpoint("b", 8081)).await;
+    lb.record_response("a", Duration::from_millis(10)).await;
+    lb.record_response("b", Duration::from_millis(30)).await;
+    lb.record_response("a", Duration::from_millis(90)).await;
+    let selected = lb.select_endpoint(&request("/")).await.unwrap().unwrap();
+    assert_eq!(selected.id, "a");
+}
+
+#[test]
+fn test_algorithm_serde_round_trip() {
+    // Existing configurations keep deserializing
+    let algorithm: LoadBalancingAlgorithm = serde_json::from_str("\"RoundRobin\"").unwrap();
+    assert_eq!(algorithm, LoadBalancingAlgorithm::RoundRobin);
+
+    for algorithm in [
+        LoadBalancingAlgorithm::LeastResponseTime,
+        LoadBalancingAlgorithm::PeakEwma,
+    ] {
+        let json = serde_json::to_string(&algorithm).unwrap();
+        assert_eq!(
+            serde_json::from_str::<LoadBalancingAlgorithm>(&json).unwrap(),
+            algorithm
+        );
+    }
+    assert_eq!(
+        serde_json::to_string(&LoadBalancingAlgorithm::PeakEwma).unwrap(),
+        "\"PeakEwma\""
+    );
+}
+
+/// Answers every path with 200 while `up` is set and 503 otherwise
+async fn toggled_backend(up: Arc<AtomicBool>) -> u16 {
+    let app = Router::new().fallback(move || {
+        let up = up.clone();
+        async move {
+            if up.load(Ordering::SeqCst) {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+        }
+    });
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    port
+}
+
+fn state<'a>(states: &'a [EndpointState], id: &str) -> &'a EndpointState {
+    states.iter().find(|state| state.endpoint_id == id).unwrap()
+}
+
+#[tokio::test]
+async fn test_failing_endpoint_is_ejected_and_recovers() {
+    let flaky_up = Arc::new(AtomicBool::new(false));
+    let flaky = toggled_backend(flaky_up.clone()).await;
+    let good = toggled_backend(Arc::new(AtomicBool::new(true))).await;
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_ejection_threshold(3)
+        .with_ejection_backoff(Duration::from_millis(200));
+    lb.add_endpoint(endpoint("flaky", flaky)).await;
+    lb.add_endpoint(endpoint("good", good)).await;
+
+    // Round robin sends every other request to the flaky endpoint until it
+    // has failed three times in a row
+    let mut served = Vec::new();
+    for _ in 0..10 {
+        served.push(lb.handle_request(request("/")).await.unwrap().endpoint.id);
+    }
+    assert_eq!(served.iter().filter(|id| *id == "flaky").count(), 3);
+    assert!(served[6..].iter().all(|id| id == "good"));
+
+    let states = lb.endpoint_states().await;
+    let flaky_state = state(&states, "flaky");
+    assert!(flaky_state.ejected);
+    assert_eq!(flaky_state.health_status, HealthStatus::Unhealthy);
+    assert_eq!(flaky_state.ejections, 1);
+    assert!(flaky_state.ejected_for.unwrap() <= Duration::from_millis(200));
+    assert!(!state(&states, "good").ejected);
+
+    // Not probed again before the backoff has elapsed, even once it is back
+    flaky_up.store(true, Ordering::SeqCst);
+    lb.health_check_endpoints().await;
+    assert!(state(&lb.endpoint_states().await, "flaky").ejected);
+
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    lb.health_check_endpoints().await;
+    let states = lb.endpoint_states().await;
+    let flaky_state = state(&states, "flaky");
+    assert!(!flaky_state.ejected);
+    assert_eq!(flaky_state.health_status, HealthStatus::Healthy);
+    assert_eq!(flaky_state.consecutive_failures, 0);
+
+    let mut served = Vec::new();
+    for _ in 0..4 {
+        served.push(lb.handle_request(request("/")).await.unwrap().endpoint.id);
+    }
+    assert_eq!(served.iter().filter(|id| *id == "flaky").count(), 2);
+}
+
+#[tokio::test]
+async fn test_failed_probe_extends_ejection() {
+    let down = toggled_backend(Arc::new(AtomicBool::new(false))).await;
+    let good = toggled_backend(Arc::new(AtomicBool::new(true))).await;
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_ejection_threshold(1)
+        .with_ejection_backoff(Duration::from_millis(100));
+    lb.add_endpoint(endpoint("down", down)).await;
+    lb.add_endpoint(endpoint("good", good)).await;
+
+    // Active health checks count as failures too
+    lb.health_check_endpoints().await;
+    assert_eq!(state(&lb.endpoint_states().await, "down").ejections, 1);
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    lb.health_check_endpoints().await;
+    let states = lb.endpoint_states().await;
+    let down_state = state(&states, "down");
+    assert!(down_state.ejected);
+    assert_eq!(down_state.ejections, 2);
+    assert!(down_state.ejected_for.unwrap() > Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn test_max_ejection_percent_keeps_endpoints_in_pool() {
+    let mut ports = Vec::new();
+    for _ in 0..3 {
+        ports.push(toggled_backend(Arc::new(AtomicBool::new(false))).await);
+    }
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_ejection_threshold(1)
+        .with_max_ejection_percent(100);
+    for (i, port) in ports.iter().enumerate() {
+        lb.add_endpoint(endpoint(&format!("web-{i}"), *port)).await;
+    }
+
+    for _ in 0..6 {
+        let response = lb.handle_request(request("/")).await.unwrap();
+        assert_eq!(response.status_code, 503);
+    }
+
+    // Even at 100% the last endpoint is never ejected
+    let states = lb.endpoint_states().await;
+    assert_eq!(states.iter().filter(|state| state.ejected).count(), 2);
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_ejection_threshold(1)
+        .with_max_ejection_percent(34);
+    for (i, port) in ports.iter().enumerate() {
+        lb.add_endpoint(endpoint(&format!("web-{i}"), *port)).await;
+    }
+    for _ in 0..6 {
+        lb.handle_request(request("/")).await.unwrap();
+    }
+    let states = lb.endpoint_states().await;
+    assert_eq!(states.iter().filter(|state| state.ejected).count(), 1);
+}
+
+async fn assignments(lb: &LoadBalancer, keys: &[String]) -> HashMap<String, String> {
+    let mut assignments = HashMap::new();
+    for key in keys {
+        let endpoint = lb.select_endpoint(&request(key)).await.unwrap().unwrap();
+        assignments.insert(key.clone(), endpoint.id);
+    }
+    assignments
+}
+
+#[tokio::test]
+async fn test_consistent_hash_moves_few_keys_when_an_endpoint_leaves() {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::ConsistentHash);
+    for i in 0..4 {
+        lb.add_endpoint(endpoint(&format!("web-{i}"), 8080 + i))
+            .await;
+    }
+    let keys: Vec<String> = (0..1000).map(|i| format!("/users/{i}")).collect();
+
+    let before = assignments(&lb, &keys).await;
+    for i in 0..4 {
+        let owned = before
+            .values()
+            .filter(|id| **id == format!("web-{i}"))
+            .count();
+        assert!(owned > 100, "web-{i} owns only {owned} keys");
+    }
+
+    lb.remove_endpoint("web-3").await;
+    let after = assignments(&lb, &keys).await;
+
+    let moved = keys
+        .iter()
+        .filter(|key| before[*key] != after[*key])
+        .count();
+    assert!(moved < 350, "{moved} of 1000 keys moved");
+    // Only the keys of the removed endpoint move
+    assert!(keys
+        .iter()
+        .filter(|key| before[*key] != after[*key])
+        .all(|key| before[key] == "web-3"));
+}
+
+#[tokio::test]
+async fn test_consistent_hash_skips_unhealthy_endpoints() {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::ConsistentHash);
+    let mut endpoints: Vec<ServiceEndpoint> = (0..3)
+        .map(|i| endpoint(&format!("web-{i}"), 8080 + i))
+        .collect();
+    lb.update_endpoints(endpoints.clone()).await;
+    let keys: Vec<String> = (0..300).map(|i| format!("/items/{i}")).collect();
+    let before = assignments(&lb, &keys).await;
+
+    endpoints[1].health_status = HealthStatus::Unhealthy;
+    lb.update_endpoints(endpoints).await;
+    let after = assignments(&lb, &keys).await;
+
+    assert!(after.values().all(|id| id != "web-1"));
+    assert!(keys
+        .iter()
+        .filter(|key| before[*key] != "web-1")
+        .all(|key| before[key] == after[key]));
+}
+
+#[tokio::test]
+async fn test_consistent_hash_key_sources() {
+    let header_lb = LoadBalancer::new(LoadBalancingAlgorithm::ConsistentHash)
+        .with_hash_key(HashKey::Header("X-User".to_string()));
+    let ip_lb =
+        LoadBalancer::new(LoadBalancingAlgorithm::ConsistentHash).with_hash_key(HashKey::ClientIp);
+    for i in 0..4 {
+        header_lb
+            .add_endpoint(endpoint(&format!("web-{i}"), 8080 + i))
+            .await;
+        ip_lb
+            .add_endpoint(endpoint(&format!("web-{i}"), 8080 + i))
+            .await;
+    }
+
+    // The same user lands on the same endpoint whatever the path
+    let mut user_request = request("/a");
+    user_request
+        .headers
+        .insert("x-user".to_string(), "alice".to_string());
+    let first = header_lb.select_endpoint(&user_request).await.unwrap();
+    let mut chosen = Vec::new();
+    for i in 0..50 {
+        user_request.path = format!("/path/{i}");
+        chosen.push(header_lb.select_endpoint(&user_request).await.unwrap());
+    }
+    assert!(chosen
+        .iter()
+        .all(|endpoint| endpoint.as_ref().map(|ep| &ep.id) == first.as_ref().map(|ep| &ep.id)));
+
+    // Requests are spread by client address
+    let mut ids = std::collections::HashSet::new();
+    for i in 0..50u8 {
+        let mut ip_request = request("/same");
+        ip_request.client_ip = Some(std::net::IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, i)));
+        ids.insert(
+            ip_lb
+                .select_endpoint(&ip_request)
+                .await
+                .unwrap()
+                .unwrap()
+                .id,
+        );
+    }
+    assert!(ids.len() > 1);
+}
+
+async fn routed_lb() -> LoadBalancer {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    lb.add_endpoint(endpoint("v1-a", 8080)).await;
+    lb.add_endpoint(endpoint("v1-b", 8081)).await;
+    lb.add_endpoint_to_pool("v2", endpoint("v2-a", 9080)).await;
+    lb.add_endpoint_to_pool("v2", endpoint("v2-b", 9081)).await;
+    lb.add_endpoint_to_pool("canary", endpoint("canary", 9180))
+        .await;
+    lb.add_routing_rule(RoutingRule::new("v2").with_path_prefix("/api/v2"))
+        .await;
+    lb
+}
+
+async fn selected(lb: &LoadBalancer, request: &LoadBalancerRequest) -> String {
+    lb.select_endpoint(request).await.unwrap().unwrap().id
+}
+
+#[tokio::test]
+async fn test_path_prefix_routes_to_pool() {
+    let lb = routed_lb().await;
+
+    for path in ["/api/v2", "/api/v2/users", "/api/v2/users/42"] {
+        for _ in 0..4 {
+            assert!(selected(&lb, &request(path)).await.starts_with("v2-"));
+        }
+    }
+
+    // Unmatched requests, including lookalike prefixes, use the primary pool
+    for path in ["/", "/api/v1/users", "/api/v20"] {
+        for _ in 0..4 {
+            assert!(selected(&lb, &request(path)).await.starts_with("v1-"));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_header_match_is_case_insensitive_and_rules_follow_priority() {
+    let lb = routed_lb().await;
+    lb.add_routing_rule(
+        RoutingRule::new("canary")
+            .with_header("X-Canary", "always")
+            .with_priority(10),
+    )
+    .await;
+
+    let mut canary = request("/api/v2/users");
+    canary
+        .headers
+        .insert("x-canary".to_string(), "always".to_string());
+    assert_eq!(selected(&lb, &canary).await, "canary");
+
+    let mut upper = request("/");
+    upper
+        .headers
+        .insert("X-CANARY".to_string(), "always".to_string());
+    assert_eq!(selected(&lb, &upper).await, "canary");
+
+    let mut other_value = request("/api/v2/users");
+    other_value
+        .headers
+        .insert("x-canary".to_string(), "never".to_string());
+    assert!(selected(&lb, &other_value).await.starts_with("v2-"));
+}
+
+#[tokio::test]
+async fn test_routed_pool_without_healthy_endpoints_returns_none() {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    lb.add_endpoint(endpoint("v1", 8080)).await;
+    lb.add_routing_rule(RoutingRule::new("missing").with_path_prefix("/v2"))
+        .await;
+
+    assert!(lb.select_endpoint(&request("/v2")).await.unwrap().is_none());
+    assert_eq!(selected(&lb, &request("/v1")).await, "v1");
+}
+
+#[tokio::test]
+async fn test_pool_endpoints_are_ejected_within_their_pool() {
+    let down = toggled_backend(Arc::new(AtomicBool::new(false))).await;
+    let up = toggled_backend(Arc::new(AtomicBool::new(true))).await;
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin).with_ejection_threshold(1);
+    lb.add_endpoint(endpoint("primary", up)).await;
+    lb.add_endpoint_to_pool("v2", endpoint("v2-down", down))
+        .await;
+    lb.add_endpoint_to_pool("v2", endpoint("v2-up", up)).await;
+    lb.add_routing_rule(RoutingRule::new("v2").with_path_prefix("/v2"))
+        .await;
+
+    for _ in 0..4 {
+        lb.handle_request(request("/v2/items")).await.unwrap();
+    }
+
+    let states = lb.endpoint_states().await;
+    let down_state = state(&states, "v2-down");
+    assert!(down_state.ejected);
+    assert_eq!(down_state.pool.as_deref(), Some("v2"));
+    assert_eq!(state(&states, "primary").pool, None);
+    assert_eq!(selected(&lb, &request("/v2/items")).await, "v2-up");
+
+    let stats = lb.get_stats().await;
+    assert!(stats.endpoint_stats["v2-up"].requests >= 2);
+}
+
+/// Streams POST bodies on `/echo` straight back, reports the forwarding
+/// headers on `/headers` and answers `/slow` after two seconds
+async fn echo_backend() -> u16 {
+    let app = Router::new()
+        .route(
+            "/echo",
+            post(|body: axum::body::Body| async move {
+                axum::body::Body::from_stream(body.into_data_stream())
+            }),
+        )
+        .route(
+            "/headers",
+            get(|headers: axum::http::HeaderMap| async move {
+                let header = |name: &str| {
+                    headers
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                format!(
+                    "{}|{}",
+                    header("x-forwarded-for"),
+                    header("x-forwarded-proto")
+                )
+            }),
+        )
+        .route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(2)).await;
+                "late"
+            }),
+        );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    port
+}
+
+#[tokio::test]
+async fn test_post_body_is_streamed_both_ways() {
+    const CHUNK: usize = 64 * 1024;
+    const CHUNKS: usize = 64;
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    lb.add_endpoint(endpoint("echo", echo_backend().await))
+        .await;
+
+    // Chunks are generated as the load balancer pulls them
+    let produced = Arc::new(AtomicUsize::new(0));
+    let counter = produced.clone();
+    let upload = futures::stream::iter(0..CHUNKS).map(move |i| {
+        counter.fetch_add(CHUNK, Ordering::SeqCst);
+        Ok(Bytes::from(vec![(i % 251) as u8; CHUNK]))
+    });
+    let mut echo = request("/echo");
+    echo.method = "POST".to_string();
+    echo.body = Some(Body::from_stream(upload));
+
+    let response = lb.handle_request(echo).await.unwrap();
+    assert_eq!(response.status_code, 200);
+
+    // Read back chunk by chunk, keeping only counters
+    let (mut received, mut checksum, mut chunks) = (0usize, 0u64, 0usize);
+    let mut download = response.body.into_stream();
+    while let Some(chunk) = download.next().await {
+        let chunk = chunk.unwrap();
+        received += chunk.len();
+        checksum += chunk.iter().map(|byte| *byte as u64).sum::<u64>();
+        chunks += 1;
+    }
+
+    let expected: u64 = (0..CHUNKS).map(|i| (i % 251) as u64 * CHUNK as u64).sum();
+    assert_eq!(produced.load(Ordering::SeqCst), CHUNK * CHUNKS);
+    assert_eq!(received, CHUNK * CHUNKS);
+    assert_eq!(checksum, expected);
+    assert!(chunks > 1, "response arrived in a single chunk");
+}
+
+#[tokio::test]
+async fn test_forwarding_headers_are_added() {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    lb.add_endpoint(endpoint("echo", echo_backend().await))
+        .await;
+
+    let mut direct = request("/headers");
+    direct.client_ip = Some("10.1.2.3".parse().unwrap());
+    let response = lb.handle_request(direct).await.unwrap();
+    assert_eq!(
+        response.body.to_bytes().await.unwrap(),
+        Bytes::from("10.1.2.3|http")
+    );
+    assert!(response.headers.contains_key("content-type"));
+
+    let mut proxied = request("/headers");
+    proxied.client_ip = Some("10.1.2.3".parse().unwrap());
+    proxied
+        .headers
+        .insert("X-Forwarded-For".to_string(), "203.0.113.7".to_string());
+    proxied
+        .headers
+        .insert("X-Forwarded-Proto".to_string(), "https".to_string());
+    let response = lb.handle_request(proxied).await.unwrap();
+    assert_eq!(
+        response.body.to_bytes().await.unwrap(),
+        Bytes::from("203.0.113.7, 10.1.2.3|https")
+    );
+}
+
+#[tokio::test]
+async fn test_request_timeout_is_reported() {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_timeouts(Duration::from_secs(1), Duration::from_millis(200));
+    lb.add_endpoint(endpoint("echo", echo_backend().await))
+        .await;
+
+    let response = lb.handle_request(request("/slow")).await.unwrap();
+    assert_eq!(response.status_code, 504);
+    assert!(response
+        .error
+        .as_deref()
+        .unwrap()
+        .starts_with("Request timeout after 200ms"));
+}
+
+/// Round robin over a healthy endpoint and a broken one whose circuit opens
+/// after three failures; ejection is kept out of the way
+async fn circuit_lb(clock: Arc<ManualClock>, broken_up: Arc<AtomicBool>) -> LoadBalancer {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_ejection_threshold(1000)
+        .with_circuit_open_threshold(3)
+        .with_circuit_reset_timeout(Duration::from_secs(30))
+        .with_clock(clock);
+    lb.add_endpoint(endpoint("broken", toggled_backend(broken_up).await))
+        .await;
+    lb.add_endpoint(endpoint(
+        "healthy",
+        toggled_backend(Arc::new(AtomicBool::new(true))).await,
+    ))
+    .await;
+    lb
+}
+
+#[tokio::test]
+async fn test_open_circuit_bypasses_failing_endpoint() {
+    let clock = Arc::new(ManualClock::new(chrono::Utc::now()));
+    let lb = circuit_lb(clock.clone(), Arc::new(AtomicBool::new(false))).await;
+
+    let mut served = Vec::new();
+    for _ in 0..20 {
+        let response = lb.handle_request(request("/")).await.unwrap();
+        if response.endpoint.id == "healthy" {
+            assert_eq!(response.status_code, 200);
+        }
+        served.push(response.endpoint.id);
+    }
+    assert_eq!(served.iter().filter(|id| *id == "broken").count(), 3);
+    assert!(served[6..].iter().all(|id| id == "healthy"));
+
+    assert_eq!(lb.circuit_state("broken").await, CircuitState::Open);
+    assert_eq!(lb.circuit_state("healthy").await, CircuitState::Closed);
+    let stats = lb.get_stats().await;
+    assert_eq!(stats.open_circuits, 1);
+    assert_eq!(
+        stats.endpoint_stats["broken"].circuit_state,
+        CircuitState::Open
+    );
+    assert_eq!(stats.endpoint_stats["broken"].requests, 3);
+    assert_eq!(stats.endpoint_stats["healthy"].failed_requests, 0);
+
+    // The endpoint itself stays in the pool; only its circuit refuses it
+    assert!(!state(&lb.endpoint_states().await, "broken").ejected);
+}
+
+#[tokio::test]
+async fn test_circuit_probe_after_reset_timeout() {
+    let clock = Arc::new(ManualClock::new(chrono::Utc::now()));
+    let broken_up = Arc::new(AtomicBool::new(false));
+    let lb = circuit_lb(clock.clone(), broken_up.clone()).await;
+
+    for _ in 0..6 {
+        lb.handle_request(request("/")).await.unwrap();
+    }
+    assert_eq!(lb.circuit_state("broken").await, CircuitState::Open);
+
+    // A failed probe opens the circuit again straight away
+    clock.advance(Duration::from_secs(31));
+    let mut probes = 0;
+    for _ in 0..4 {
+        if lb.handle_request(request("/")).await.unwrap().endpoint.id == "broken" {
+            probes += 1;
+        }
+    }
+    assert_eq!(probes, 1);
+    assert_eq!(lb.circuit_state("broken").await, CircuitState::Open);
+
+    // A successful probe closes it
+    broken_up.store(true, Ordering::SeqCst);
+    clock.advance(Duration::from_secs(31));
+    let mut served = Vec::new();
+    for _ in 0..4 {
+        served.push(lb.handle_request(request("/")).await.unwrap().endpoint.id);
+    }
+    assert_eq!(served.iter().filter(|id| *id == "broken").count(), 2);
+    assert_eq!(lb.circuit_state("broken").await, CircuitState::Closed);
+    assert_eq!(lb.get_stats().await.open_circuits, 0);
+}
+
+fn retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        backoff: Duration::ZERO,
+        ..RetryPolicy::default()
+    }
+}
+
+#[tokio::test]
+async fn test_idempotent_request_is_retried_on_another_endpoint() {
+    let lb =
+        LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin).with_retry_policy(retry_policy());
+    lb.add_endpoint(endpoint(
+        "flaky",
+        toggled_backend(Arc::new(AtomicBool::new(false))).await,
+    ))
+    .await;
+    lb.add_endpoint(endpoint(
+        "good",
+        toggled_backend(Arc::new(AtomicBool::new(true))).await,
+    ))
+    .await;
+
+    let response = lb.handle_request(request("/")).await.unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.endpoint.id, "good");
+    assert_eq!(response.attempts, 2);
+    assert!(!response.short_circuited);
+
+    let stats = lb.get_stats().await;
+    assert_eq!(stats.retried_requests, 1);
+    assert_eq!(stats.endpoint_stats["flaky"].failed_requests, 1);
+
+    // POST is not idempotent unless the policy lists it
+    let mut post = request("/");
+    post.method = "POST".to_string();
+    let response = lb.handle_request(post).await.unwrap();
+    assert_eq!(response.endpoint.id, "flaky");
+    assert_eq!(response.status_code, 503);
+    assert_eq!(response.attempts, 1);
+}
+
+#[tokio::test]
+async fn test_per_try_timeout_moves_on_to_next_endpoint() {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin).with_retry_policy(RetryPolicy {
+        per_try_timeout: Some(Duration::from_millis(200)),
+        retry_on: vec![RetryCondition::Timeout],
+        ..retry_policy()
+    });
+    lb.add_endpoint(endpoint("slow", echo_backend().await))
+        .await;
+    lb.add_endpoint(endpoint(
+        "fast",
+        toggled_backend(Arc::new(AtomicBool::new(true))).await,
+    ))
+    .await;
+
+    let response = lb.handle_request(request("/slow")).await.unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.endpoint.id, "fast");
+    assert_eq!(response.attempts, 2);
+    let slow = &lb.get_stats().await.endpoint_stats["slow"];
+    assert!(slow
+        .last_error
+        .as_deref()
+        .unwrap()
+        .starts_with("Per-try timeout after 200ms"));
+}
+
+#[tokio::test]
+async fn test_error_rate_breaker_rejects_calls_while_open() {
+    let clock = Arc::new(ManualClock::new(chrono::Utc::now()));
+    let up = Arc::new(AtomicBool::new(false));
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_ejection_threshold(1000)
+        .with_circuit_open_threshold(1000)
+        .with_circuit_error_rate(0.5, 4)
+        .with_circuit_reset_timeout(Duration::from_secs(30))
+        .with_clock(clock.clone())
+        .with_retry_policy(retry_policy());
+    lb.add_endpoint(endpoint("flaky", toggled_backend(up.clone()).await))
+        .await;
+
+    for _ in 0..4 {
+        let response = lb.handle_request(request("/")).await.unwrap();
+        assert_eq!(response.status_code, 503);
+        assert_eq!(response.attempts, 1);
+    }
+    assert_eq!(lb.circuit_state("flaky").await, CircuitState::Open);
+
+    // Rejected without reaching the endpoint
+    let response = lb.handle_request(request("/")).await.unwrap();
+    assert_eq!(response.status_code, 503);
+    assert_eq!(response.attempts, 0);
+    assert!(response.short_circuited);
+    assert_eq!(
+        response.error.as_deref(),
+        Some("Circuit open for every healthy endpoint")
+    );
+    assert_eq!(lb.get_stats().await.endpoint_stats["flaky"].requests, 4);
+
+    up.store(true, Ordering::SeqCst);
+    clock.advance(Duration::from_secs(30));
+    let response = lb.handle_request(request("/")).await.unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(lb.circuit_state("flaky").await, CircuitState::Closed);
+}
+
+/// Starts a `/slow` request on the `slow` endpoint and waits until it is in
+/// flight
+async fn draining_lb() -> (LoadBalancer, tokio::task::JoinHandle<LoadBalancerResponse>) {
+    draining(LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)).await
+}
+
+async fn draining(
+    lb: LoadBalancer,
+) -> (LoadBalancer, tokio::task::JoinHandle<LoadBalancerResponse>) {
+    lb.add_endpoint(endpoint("slow", echo_backend().await))
+        .await;
+    lb.add_endpoint(endpoint(
+        "fast",
+        toggled_backend(Arc::new(AtomicBool::new(true))).await,
+    ))
+    .await;
+
+    let in_flight = {
+        let lb = lb.clone();
+        tokio::spawn(async move { lb.handle_request(request("/slow")).await.unwrap() })
+    };
+    while lb.get_stats().await.endpoint_stats["slow"].active_connections == 0 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    (lb, in_flight)
+}
+
+#[tokio::test]
+async fn test_drained_endpoint_stays_until_its_request_completes() {
+    let (lb, in_flight) = draining_lb().await;
+    let drained = lb.drain_endpoint("slow", Duration::from_secs(10)).await;
+
+    // No new requests, but still present while its request is running
+    for _ in 0..4 {
+        let selected = lb.select_endpoint(&request("/")).await.unwrap().unwrap();
+        assert_eq!(selected.id, "fast");
+    }
+    let slow = &lb.get_stats().await.endpoint_stats["slow"];
+    assert!(slow.draining);
+    assert_eq!(slow.active_connections, 1);
+    assert!(!drained.is_finished());
+
+    let response = in_flight.await.unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.endpoint.id, "slow");
+
+    drained.await.unwrap();
+    let stats = lb.get_stats().await;
+    assert!(!stats.endpoint_stats.contains_key("slow"));
+    assert!(!stats.endpoint_stats["fast"].draining);
+}
+
+#[tokio::test]
+async fn test_drained_endpoint_is_removed_when_grace_period_expires() {
+    let (lb, in_flight) = draining_lb().await;
+    let started = std::time::Instant::now();
+    lb.drain_endpoint("slow", Duration::from_millis(200))
+        .await
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert!(!in_flight.is_finished());
+    assert!(!lb.get_stats().await.endpoint_stats.contains_key("slow"));
+}
+
+#[tokio::test]
+async fn test_drain_grace_period_follows_the_load_balancer_clock() {
+    let clock = Arc::new(ManualClock::new(chrono::Utc::now()));
+    let (lb, in_flight) =
+        draining(LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin).with_clock(clock.clone()))
+            .await;
+    let drained = lb.drain_endpoint("slow", Duration::from_millis(200)).await;
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(!drained.is_finished());
+    assert!(lb.get_stats().await.endpoint_stats.contains_key("slow"));
+
+    clock.advance(Duration::from_secs(1));
+    drained.await.unwrap();
+    assert!(!in_flight.is_finished());
+    assert!(!lb.get_stats().await.endpoint_stats.contains_key("slow"));
+}
+
+#[tokio::test]
+async fn test_endpoint_added_back_during_a_drain_is_kept() {
+    let (lb, _in_flight) = draining_lb().await;
+    let drained = lb.drain_endpoint("slow", Duration::from_secs(10)).await;
+
+    lb.remove_endpoint("slow").await;
+    lb.add_endpoint(endpoint("slow", echo_backend().await))
+        .await;
+    drained.await.unwrap();
+
+    let slow = &lb.get_stats().await.endpoint_stats["slow"];
+    assert!(!slow.draining);
+}
+
+/// Picks among `primary-a`, `primary-b` and a `canary` taking `weight_percent`
+async fn canary_selections(weight_percent: u8, selections: usize) -> HashMap<String, usize> {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    lb.add_endpoint(endpoint("primary-a", 8080)).await;
+    lb.add_endpoint(endpoint("primary-b", 8081)).await;
+    lb.add_canary(endpoint("canary", 8082), weight_percent)
+        .await;
+
+    let mut counts = HashMap::new();
+    for _ in 0..selections {
+        let selected = lb.select_endpoint(&request("/")).await.unwrap().unwrap();
+        *counts.entry(selected.id).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[tokio::test]
+async fn test_canary_receives_its_share_of_selections() {
+    for (weight_percent, tolerance) in [(50, 0.05), (10, 0.2)] {
+        let counts = canary_selections(weight_percent, 10_000).await;
+        let expected = 10_000.0 * weight_percent as f64 / 100.0;
+        let canary = counts["canary"] as f64;
+        assert!(
+            (canary - expected).abs() <= expected * tolerance,
+            "canary at {weight_percent}% got {canary} of 10000 selections"
+        );
+
+        // The rest is still round robin across the primary endpoints
+        let (a, b) = (counts["primary-a"], counts["primary-b"]);
+        assert!(a.abs_diff(b) <= 1, "primary split {a}/{b}");
+    }
+
+    assert!(!canary_selections(0, 1000).await.contains_key("canary"));
+    assert_eq!(canary_selections(100, 1000).await["canary"], 1000);
+}
+
+#[tokio::test]
+async fn test_canary_stats_and_promotion() {
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    lb.add_endpoint(endpoint("stable", backend().await)).await;
+    let canary_port = backend().await;
+    lb.add_canary(endpoint("canary", canary_port), 0).await;
+
+    for _ in 0..3 {
+        lb.handle_request(request("/ok")).await.unwrap();
+    }
+
+    // Only canaries carrying all of the traffic can be promoted
+    assert!(lb.promote_canary("canary").await.is_err());
+    assert!(lb.promote_canary("stable").await.is_err());
+
+    lb.add_canary(endpoint("canary", canary_port), 100).await;
+    for _ in 0..2 {
+        let response = lb.handle_request(request("/ok")).await.unwrap();
+        assert_eq!(response.endpoint.id, "canary");
+    }
+    let stats = lb.get_stats().await;
+    assert_eq!((stats.primary_requests, stats.canary_requests), (3, 2));
+    assert_eq!(stats.endpoint_stats["canary"].requests, 2);
+
+    lb.promote_canary("canary").await.unwrap();
+    let mut served = Vec::new();
+    for _ in 0..4 {
+        served.push(lb.handle_request(request("/ok")).await.unwrap().endpoint.id);
+    }
+    assert_eq!(served.iter().filter(|id| *id == "canary").count(), 2);
+    let stats = lb.get_stats().await;
+    assert_eq!((stats.primary_requests, stats.canary_requests), (7, 2));
+}
@@ -0,0 +1,222 @@
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use polis_core::PolisError;
+use polis_orchestrator::service_discovery::HealthStatus;
+use polis_orchestrator::{
+    LoadBalancer, LoadBalancerRequest, LoadBalancingAlgorithm, Protocol, ServiceEndpoint, TlsConfig,
+};
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+/// Self-signed CA and a server certificate it issued, written as PEM files
+struct TestPki {
+    dir: TempDir,
+}
+
+impl TestPki {
+    fn new(server_names: &[&str]) -> Self {
+        let dir = TempDir::new().unwrap();
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params
+            .distinguished_name
+            .push(DnType::CommonName, "Polis test CA");
+        let ca_cert = ca_params.self_signed(&ca_key).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let server_names: Vec<String> = server_names.iter().map(|name| name.to_string()).collect();
+        let server_cert = CertificateParams::new(server_names)
+            .unwrap()
+            .signed_by(&server_key, &ca_cert, &ca_key)
+            .unwrap();
+
+        std::fs::write(dir.path().join("ca.pem"), ca_cert.pem()).unwrap();
+        std::fs::write(dir.path().join("server.pem"), server_cert.pem()).unwrap();
+        std::fs::write(dir.path().join("server.key"), server_key.serialize_pem()).unwrap();
+        Self { dir }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    fn ca_config(&self) -> TlsConfig {
+        TlsConfig {
+            ca_bundle: Some(self.path("ca.pem")),
+            ..TlsConfig::default()
+        }
+    }
+
+    /// Serves "secure" on every path over HTTPS on a random local port
+    async fn backend(&self) -> u16 {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let config = RustlsConfig::from_pem_file(self.path("server.pem"), self.path("server.key"))
+            .await
+            .unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let app = Router::new().fallback(|| async { "secure" });
+        tokio::spawn(async move {
+            axum_server::from_tcp_rustls(listener, config)
+                .serve(app.into_make_service())
+                .await
+                .unwrap()
+        });
+        port
+    }
+}
+
+fn endpoint(port: u16) -> ServiceEndpoint {
+    let mut endpoint = ServiceEndpoint::new("127.0.0.1".to_string(), port, Protocol::Https);
+    endpoint.id = "secure".to_string();
+    endpoint.health_status = HealthStatus::Healthy;
+    endpoint
+}
+
+fn request() -> LoadBalancerRequest {
+    LoadBalancerRequest {
+        client_ip: None,
+        session_id: None,
+        headers: HashMap::new(),
+        path: "/".to_string(),
+        method: "GET".to_string(),
+        body: None,
+    }
+}
+
+/// Status code and body of a request through `lb`
+async fn fetch(lb: &LoadBalancer) -> (u16, String) {
+    let response = lb.handle_request(request()).await.unwrap();
+    let body = response.body.to_bytes().await.unwrap();
+    (
+        response.status_code,
+        String::from_utf8_lossy(&body).to_string(),
+    )
+}
+
+fn config_error(error: PolisError) -> String {
+    match error {
+        PolisError::Config(message) => message,
+        other => panic!("expected a configuration error, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_self_signed_endpoint_is_trusted_only_with_its_ca() {
+    let pki = TestPki::new(&["localhost", "127.0.0.1"]);
+    let port = pki.backend().await;
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    lb.add_endpoint(endpoint(port)).await;
+    let (status_code, _) = fetch(&lb).await;
+    assert_eq!(status_code, 500);
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_tls(pki.ca_config())
+        .unwrap();
+    lb.add_endpoint(endpoint(port)).await;
+    assert_eq!(fetch(&lb).await, (200, "secure".to_string()));
+}
+
+#[tokio::test]
+async fn test_insecure_skip_verify_accepts_unknown_certificates() {
+    let pki = TestPki::new(&["localhost"]);
+    let port = pki.backend().await;
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_tls(TlsConfig {
+            insecure_skip_verify: true,
+            ..TlsConfig::default()
+        })
+        .unwrap();
+    lb.add_endpoint(endpoint(port)).await;
+    assert_eq!(fetch(&lb).await.0, 200);
+}
+
+#[tokio::test]
+async fn test_invalid_tls_files_fail_at_construction() {
+    let pki = TestPki::new(&["localhost"]);
+
+    let missing = pki.path("missing.pem");
+    let error = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_tls(TlsConfig {
+            ca_bundle: Some(missing.clone()),
+            ..TlsConfig::default()
+        })
+        .err()
+        .unwrap();
+    assert!(config_error(error).contains(&missing.display().to_string()));
+
+    // A key is not a certificate bundle
+    let error = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_tls(TlsConfig {
+            ca_bundle: Some(pki.path("server.key")),
+            ..TlsConfig::default()
+        })
+        .err()
+        .unwrap();
+    assert!(config_error(error).contains("server.key"));
+
+    let error = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_tls(TlsConfig {
+            client_cert: Some((pki.path("server.pem"), missing)),
+            ..TlsConfig::default()
+        })
+        .err()
+        .unwrap();
+    assert!(config_error(error).contains("missing.pem"));
+}
+
+#[tokio::test]
+async fn test_endpoint_metadata_configures_tls() {
+    let pki = TestPki::new(&["127.0.0.1"]);
+    let port = pki.backend().await;
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    let mut secure = endpoint(port);
+    secure.metadata.insert(
+        "tls.ca_bundle".to_string(),
+        pki.path("ca.pem").display().to_string(),
+    );
+    lb.try_add_endpoint(secure).await.unwrap();
+    assert_eq!(fetch(&lb).await.0, 200);
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    let mut broken = endpoint(port);
+    broken
+        .metadata
+        .insert("tls.client_cert".to_string(), "client.pem".to_string());
+    let error = lb.try_add_endpoint(broken.clone()).await.unwrap_err();
+    assert!(config_error(error).contains("tls.client_key"));
+
+    // Left out rather than failing its requests later
+    lb.add_endpoint(broken).await;
+    assert!(lb.get_stats().await.endpoint_stats.is_empty());
+}
+
+#[tokio::test]
+async fn test_sni_override_verifies_the_configured_name() {
+    let pki = TestPki::new(&["upstream.test"]);
+    let port = pki.backend().await;
+
+    // The certificate does not cover the endpoint's address
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_tls(pki.ca_config())
+        .unwrap();
+    lb.add_endpoint(endpoint(port)).await;
+    assert_eq!(fetch(&lb).await.0, 500);
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin)
+        .with_tls(TlsConfig {
+            sni_override: Some("upstream.test".to_string()),
+            ..pki.ca_config()
+        })
+        .unwrap();
+    lb.add_endpoint(endpoint(port)).await;
+    assert_eq!(fetch(&lb).await, (200, "secure".to_string()));
+}
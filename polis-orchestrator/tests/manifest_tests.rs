@@ -0,0 +1,128 @@
+use polis_core::PolisError;
+use polis_orchestrator::{ApplyAction, DeploymentSpec, Manifest, Orchestrator, OrchestratorConfig};
+use polis_test_support::FakeRuntime;
+use std::sync::Arc;
+use std::time::Duration;
+
+const MINIMAL: &str = include_str!("fixtures/manifests/minimal.yaml");
+const MISSING_IMAGE: &str = include_str!("fixtures/manifests/missing_image.yaml");
+const INVALID_FIELD: &str = include_str!("fixtures/manifests/invalid_field.yaml");
+const BAD_TYPE: &str = include_str!("fixtures/manifests/bad_type.yaml");
+const STACK: &str = include_str!("fixtures/manifests/stack.yaml");
+
+fn parse_error(content: &str) -> String {
+    match DeploymentSpec::from_yaml_str(content) {
+        Err(PolisError::Config(message)) => message,
+        other => panic!("expected a config error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_minimal_manifest_takes_defaults() {
+    let specs = DeploymentSpec::from_yaml_str(MINIMAL).unwrap();
+    assert_eq!(specs.len(), 1);
+    let spec = &specs[0];
+    assert_eq!(spec.name, "web");
+    assert_eq!(spec.namespace, "default");
+    assert_eq!(spec.replicas, 1);
+    assert!(spec.ports.is_empty());
+    assert!(spec.env_vars.is_empty());
+    assert!(spec.health_check.is_none());
+    assert!(spec.scaling_policy.is_none());
+    assert!(!spec.read_only_rootfs);
+}
+
+#[test]
+fn test_multi_document_manifest() {
+    let specs = DeploymentSpec::from_yaml_str(STACK).unwrap();
+    assert_eq!(specs.len(), 2);
+
+    let web = &specs[0];
+    assert_eq!(web.replicas, 2);
+    assert_eq!(web.ports[0].target_port, 8080);
+    assert_eq!(web.env_vars["LOG_LEVEL"], "info");
+    assert_eq!(web.labels["tier"], "frontend");
+    let health_check = web.health_check.as_ref().unwrap();
+    assert_eq!(health_check.http_path.as_deref(), Some("/healthz"));
+    assert_eq!(health_check.interval, Duration::from_secs(10));
+    assert_eq!(web.scaling_policy.as_ref().unwrap().max_replicas, 6);
+    assert_eq!(
+        web.resources.as_ref().unwrap().memory_limit.as_deref(),
+        Some("256Mi")
+    );
+
+    assert_eq!(specs[1].name, "cache");
+    assert_eq!(specs[1].namespace, "backend");
+}
+
+#[test]
+fn test_errors_point_at_the_field() {
+    let missing = parse_error(MISSING_IMAGE);
+    assert!(missing.starts_with("document 1:"), "{}", missing);
+    assert!(missing.contains("missing field `image`"), "{}", missing);
+
+    let invalid = parse_error(INVALID_FIELD);
+    assert!(invalid.contains("ports[0]"), "{}", invalid);
+
+    let bad_type = parse_error(BAD_TYPE);
+    assert!(bad_type.contains("replicas"), "{}", bad_type);
+
+    // The failing document is located within a multi-document file
+    let second = parse_error(&format!("{}---\n{}", MINIMAL, "name: api\nimage: ''\n"));
+    assert!(second.starts_with("document 2: image"), "{}", second);
+
+    let duplicate = parse_error(&format!("{}---\n{}", MINIMAL, MINIMAL));
+    assert!(duplicate.contains("more than once"), "{}", duplicate);
+
+    let other_kind = parse_error("kind: Job\nname: web\nimage: nginx\n");
+    assert!(other_kind.contains("kind"), "{}", other_kind);
+}
+
+async fn apply(orchestrator: &Orchestrator, content: &str) -> Vec<ApplyAction> {
+    let mut actions = Vec::new();
+    for spec in DeploymentSpec::from_yaml_str(content).unwrap() {
+        actions.push(
+            orchestrator
+                .apply(Manifest::Deployment(Box::new(spec)))
+                .await
+                .unwrap(),
+        );
+    }
+    actions
+}
+
+#[tokio::test]
+async fn test_apply_is_idempotent() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    // The stack also deploys into the "backend" namespace
+    let config = OrchestratorConfig {
+        state_dir: temp.path().to_path_buf(),
+        auto_create_namespaces: true,
+        ..Default::default()
+    };
+    let orchestrator = Orchestrator::new(config, runtime.clone()).await.unwrap();
+
+    assert_eq!(
+        apply(&orchestrator, STACK).await,
+        vec![ApplyAction::Create, ApplyAction::Create]
+    );
+    assert_eq!(runtime.containers().len(), 3);
+    assert_eq!(
+        apply(&orchestrator, STACK).await,
+        vec![ApplyAction::Unchanged, ApplyAction::Unchanged]
+    );
+
+    let updated = STACK.replacen("image: nginx:1.25", "image: nginx:1.26", 1);
+    assert_eq!(
+        apply(&orchestrator, &updated).await,
+        vec![ApplyAction::Update, ApplyAction::Unchanged]
+    );
+    let web_images: Vec<String> = runtime
+        .containers()
+        .into_iter()
+        .filter(|container| container.name.starts_with("web-"))
+        .map(|container| container.image.0)
+        .collect();
+    assert_eq!(web_images, vec!["nginx:1.26"; 2]);
+}
@@ -0,0 +1,121 @@
+use axum::routing::get;
+use axum::Router;
+use polis_monitor::{OrchestratorMetrics, PrometheusExporter};
+use polis_orchestrator::service_discovery::HealthStatus;
+use polis_orchestrator::{
+    CheckType, DeploymentSpec, HealthCheckDef, HealthMonitor, LoadBalancer, LoadBalancerRequest,
+    LoadBalancingAlgorithm, Orchestrator, OrchestratorConfig, Protocol, ServiceEndpoint,
+    TargetType,
+};
+use polis_stats::ContainerStatsCollector;
+use polis_test_support::FakeRuntime;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+fn file_check(name: &str, dir: &Path, file: &str) -> HealthCheckDef {
+    HealthCheckDef::new(
+        name.to_string(),
+        name.to_string(),
+        TargetType::Custom,
+        dir.display().to_string(),
+        CheckType::File {
+            path: file.to_string(),
+            exists: true,
+        },
+    )
+    .with_failure_threshold(1)
+    .with_success_threshold(1)
+    .with_retries(1)
+    .with_label("deployment".to_string(), "web".to_string())
+    .with_label("namespace".to_string(), "default".to_string())
+}
+
+async fn backend() -> u16 {
+    let app = Router::new().route("/", get(|| async { "ok" }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    port
+}
+
+fn request() -> LoadBalancerRequest {
+    LoadBalancerRequest {
+        client_ip: None,
+        session_id: None,
+        headers: HashMap::new(),
+        path: "/".to_string(),
+        method: "GET".to_string(),
+        body: None,
+    }
+}
+
+#[tokio::test]
+async fn test_orchestrator_state_reaches_the_exporter() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = OrchestratorConfig {
+        state_dir: temp.path().to_path_buf(),
+        ..Default::default()
+    };
+    let orchestrator = Orchestrator::new(config, Arc::new(FakeRuntime::new()))
+        .await
+        .unwrap();
+    let spec: DeploymentSpec =
+        serde_yaml::from_str("name: web\nimage: nginx:1.25\nreplicas: 2\n").unwrap();
+    orchestrator.deploy(spec).await.unwrap();
+
+    std::fs::write(temp.path().join("ready"), "").unwrap();
+    let monitor = HealthMonitor::new();
+    for check in [
+        file_check("web-ready", temp.path(), "ready"),
+        file_check("web-missing", temp.path(), "missing"),
+    ] {
+        monitor.create_health_check(check).await.unwrap();
+    }
+    for id in ["web-ready", "web-missing"] {
+        monitor.run_health_check(id).await.unwrap();
+    }
+
+    let lb = LoadBalancer::new(LoadBalancingAlgorithm::RoundRobin);
+    let mut endpoint =
+        ServiceEndpoint::new("127.0.0.1".to_string(), backend().await, Protocol::Http);
+    endpoint.id = "web-1".to_string();
+    endpoint.health_status = HealthStatus::Healthy;
+    lb.add_endpoint(endpoint).await;
+    for _ in 0..3 {
+        lb.handle_request(request()).await.unwrap();
+    }
+
+    let metrics = OrchestratorMetrics {
+        deployments: orchestrator.deployment_gauges().await,
+        health_checks: monitor.health_check_gauges().await,
+        load_balancers: vec![lb.request_counters("web", "default").await],
+    };
+    assert_eq!(metrics.deployments.len(), 1);
+    assert_eq!(metrics.deployments[0].deployment, "web");
+    assert_eq!(metrics.deployments[0].namespace, "default");
+    assert_eq!(metrics.deployments[0].replicas_desired, 2);
+    assert_eq!(metrics.deployments[0].replicas_ready, 2);
+
+    let health: Vec<(&str, bool)> = metrics
+        .health_checks
+        .iter()
+        .map(|check| (check.check.as_str(), check.healthy))
+        .collect();
+    assert_eq!(health, vec![("web-missing", false), ("web-ready", true)]);
+    assert_eq!(metrics.health_checks[0].deployment, "web");
+
+    assert_eq!(metrics.load_balancers[0].requests_total, 3);
+    assert_eq!(metrics.load_balancers[0].successful_requests_total, 3);
+    assert_eq!(metrics.load_balancers[0].failed_requests_total, 0);
+
+    let exporter = PrometheusExporter::new(Arc::new(ContainerStatsCollector::default()));
+    exporter.set_orchestrator_metrics(metrics).await;
+    let text = exporter.render().await;
+    assert!(text
+        .contains("polis_deployment_replicas_ready{deployment=\"web\",namespace=\"default\"} 2\n"));
+    assert!(text.contains("polis_health_check_status{check=\"web-missing\",target=\""));
+    assert!(text
+        .contains("polis_load_balancer_requests_total{service=\"web\",namespace=\"default\"} 3\n"));
+}
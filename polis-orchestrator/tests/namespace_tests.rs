@@ -0,0 +1,180 @@
+use polis_orchestrator::{
+    ConfigSpec, DeploymentSpec, Manifest, NamespaceQuota, Orchestrator, OrchestratorConfig,
+};
+use polis_test_support::FakeRuntime;
+use std::path::Path;
+use std::sync::Arc;
+
+fn config(state_dir: &Path) -> OrchestratorConfig {
+    OrchestratorConfig {
+        state_dir: state_dir.to_path_buf(),
+        ..Default::default()
+    }
+}
+
+async fn orchestrator(config: OrchestratorConfig, runtime: &Arc<FakeRuntime>) -> Orchestrator {
+    Orchestrator::new(config, runtime.clone()).await.unwrap()
+}
+
+fn spec(name: &str, namespace: &str, replicas: u32) -> DeploymentSpec {
+    serde_yaml::from_str(&format!(
+        "name: {}\nnamespace: {}\nimage: nginx:1.25\nreplicas: {}\n",
+        name, namespace, replicas
+    ))
+    .unwrap()
+}
+
+fn quota(max_deployments: Option<u32>, max_replicas: Option<u32>) -> NamespaceQuota {
+    NamespaceQuota {
+        max_deployments,
+        max_replicas,
+    }
+}
+
+#[tokio::test]
+async fn test_deploy_requires_an_existing_namespace() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    let orchestrator = orchestrator(config(temp.path()), &runtime).await;
+
+    let error = orchestrator
+        .deploy(spec("web", "shop", 1))
+        .await
+        .err()
+        .unwrap();
+    assert!(error
+        .to_string()
+        .contains("Namespace 'shop' does not exist"));
+    assert!(runtime.containers().is_empty());
+
+    // The default namespace always exists
+    orchestrator
+        .deploy(spec("web", "default", 1))
+        .await
+        .unwrap();
+
+    let auto = Orchestrator::new(
+        OrchestratorConfig {
+            auto_create_namespaces: true,
+            ..config(&temp.path().join("auto"))
+        },
+        runtime.clone(),
+    )
+    .await
+    .unwrap();
+    auto.deploy(spec("web", "shop", 1)).await.unwrap();
+    let names: Vec<String> = auto
+        .list_namespaces()
+        .await
+        .into_iter()
+        .map(|namespace| namespace.name)
+        .collect();
+    assert_eq!(names, vec!["default", "shop"]);
+}
+
+#[tokio::test]
+async fn test_quota_rejects_the_extra_replica() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    let orchestrator = orchestrator(config(temp.path()), &runtime).await;
+    orchestrator
+        .create_namespace("shop", quota(Some(2), Some(3)))
+        .await
+        .unwrap();
+
+    orchestrator.deploy(spec("web", "shop", 2)).await.unwrap();
+    orchestrator.deploy(spec("api", "shop", 1)).await.unwrap();
+    let error = orchestrator
+        .scale_deployment("api", "shop", 2)
+        .await
+        .err()
+        .unwrap();
+    assert!(error
+        .to_string()
+        .contains("4 replicas requested, at most 3"));
+    assert_eq!(runtime.containers().len(), 3);
+
+    // Moving replicas between deployments stays within the quota
+    orchestrator
+        .scale_deployment("web", "shop", 1)
+        .await
+        .unwrap();
+    orchestrator
+        .scale_deployment("api", "shop", 2)
+        .await
+        .unwrap();
+
+    let error = orchestrator
+        .deploy(spec("worker", "shop", 0))
+        .await
+        .err()
+        .unwrap();
+    assert!(error
+        .to_string()
+        .contains("3 deployments requested, at most 2"));
+}
+
+#[tokio::test]
+async fn test_cascade_delete_removes_deployments() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    let orchestrator = orchestrator(config(temp.path()), &runtime).await;
+    orchestrator
+        .create_namespace("shop", NamespaceQuota::default())
+        .await
+        .unwrap();
+    orchestrator.deploy(spec("web", "shop", 2)).await.unwrap();
+    orchestrator
+        .deploy(spec("web", "default", 1))
+        .await
+        .unwrap();
+    orchestrator
+        .apply(Manifest::Config(ConfigSpec {
+            name: "settings".to_string(),
+            namespace: "shop".to_string(),
+            data: Default::default(),
+            labels: Default::default(),
+        }))
+        .await
+        .unwrap();
+
+    // Without cascade a namespace with resources is kept
+    let error = orchestrator
+        .delete_namespace("shop", false)
+        .await
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("2 resource(s)"));
+    assert!(orchestrator
+        .delete_namespace("default", true)
+        .await
+        .is_err());
+
+    orchestrator.delete_namespace("shop", true).await.unwrap();
+    assert_eq!(runtime.containers().len(), 1);
+    assert!(orchestrator
+        .get_deployment_status("web", "shop")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(orchestrator.get_config("settings", "shop").await.is_none());
+    assert!(orchestrator
+        .get_deployment_status("web", "default")
+        .await
+        .unwrap()
+        .is_some());
+
+    // Namespaces are persisted with the rest of the state
+    orchestrator
+        .create_namespace("staging", quota(None, Some(5)))
+        .await
+        .unwrap();
+    drop(orchestrator);
+    let reopened = Orchestrator::new(config(temp.path()), runtime.clone())
+        .await
+        .unwrap();
+    let namespaces = reopened.list_namespaces().await;
+    assert_eq!(namespaces.len(), 2);
+    assert_eq!(namespaces[1].name, "staging");
+    assert_eq!(namespaces[1].quota.max_replicas, Some(5));
+}
@@ -0,0 +1,130 @@
+use polis_core::PolisError;
+use polis_orchestrator::{
+    DeploymentSpec, Manifest, Orchestrator, OrchestratorConfig, MAX_REVISIONS,
+};
+use polis_test_support::FakeRuntime;
+use std::path::Path;
+use std::sync::Arc;
+
+async fn orchestrator(state_dir: &Path, runtime: &Arc<FakeRuntime>) -> Orchestrator {
+    let config = OrchestratorConfig {
+        state_dir: state_dir.to_path_buf(),
+        ..Default::default()
+    };
+    Orchestrator::new(config, runtime.clone()).await.unwrap()
+}
+
+fn spec(image: &str, replicas: u32) -> DeploymentSpec {
+    serde_yaml::from_str(&format!(
+        "name: web\nimage: {}\nreplicas: {}\n",
+        image, replicas
+    ))
+    .unwrap()
+}
+
+async fn update(orchestrator: &Orchestrator, spec: DeploymentSpec) {
+    orchestrator
+        .apply(Manifest::Deployment(Box::new(spec)))
+        .await
+        .unwrap();
+}
+
+fn images(runtime: &FakeRuntime) -> Vec<String> {
+    runtime
+        .containers()
+        .into_iter()
+        .map(|container| container.image.0)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_rollback_restores_previous_spec() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    let orchestrator = orchestrator(temp.path(), &runtime).await;
+    let v1 = spec("nginx:1.25", 2);
+
+    orchestrator.deploy(v1.clone()).await.unwrap();
+    update(&orchestrator, spec("nginx:1.26", 3)).await;
+    // Changing the image replaces every replica
+    assert_eq!(images(&runtime), vec!["nginx:1.26"; 3]);
+
+    let restored = orchestrator
+        .rollback_deployment("web", "default", None)
+        .await
+        .unwrap();
+    assert_eq!(restored, 1);
+    assert_eq!(images(&runtime), vec!["nginx:1.25"; 2]);
+
+    let history = orchestrator
+        .get_deployment_history("web", "default")
+        .await
+        .unwrap();
+    let revisions: Vec<u32> = history.iter().map(|r| r.revision).collect();
+    assert_eq!(revisions, vec![1, 2, 3]);
+    assert_eq!(history.last().unwrap().spec, v1);
+
+    let status = orchestrator
+        .get_deployment_status("web", "default")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(status.desired_replicas, 2);
+    assert_eq!(status.ready_replicas, 2);
+}
+
+#[tokio::test]
+async fn test_rollback_to_specific_revision_survives_restart() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    let first = orchestrator(temp.path(), &runtime).await;
+    first.deploy(spec("nginx:1.25", 1)).await.unwrap();
+    update(&first, spec("nginx:1.26", 1)).await;
+    update(&first, spec("nginx:1.27", 1)).await;
+    drop(first);
+
+    let reopened = orchestrator(temp.path(), &runtime).await;
+    assert_eq!(
+        reopened
+            .get_deployment_history("web", "default")
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+    reopened
+        .rollback_deployment("web", "default", Some(2))
+        .await
+        .unwrap();
+    assert_eq!(images(&runtime), vec!["nginx:1.26"]);
+
+    let error = reopened
+        .rollback_deployment("web", "default", Some(9))
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(error, PolisError::Config(_)));
+}
+
+#[tokio::test]
+async fn test_history_is_bounded() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    let orchestrator = orchestrator(temp.path(), &runtime).await;
+    orchestrator.deploy(spec("nginx:1.0", 1)).await.unwrap();
+    assert!(orchestrator
+        .rollback_deployment("web", "default", None)
+        .await
+        .is_err());
+
+    for minor in 1..=MAX_REVISIONS + 2 {
+        update(&orchestrator, spec(&format!("nginx:1.{}", minor), 1)).await;
+    }
+    let history = orchestrator
+        .get_deployment_history("web", "default")
+        .await
+        .unwrap();
+    assert_eq!(history.len(), MAX_REVISIONS);
+    assert_eq!(history[0].revision, 4);
+    assert_eq!(history.last().unwrap().revision, MAX_REVISIONS as u32 + 3);
+}
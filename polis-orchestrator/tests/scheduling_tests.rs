@@ -0,0 +1,124 @@
+use polis_orchestrator::{
+    DeploymentSpec, DeploymentStatusType, Node, Orchestrator, OrchestratorConfig,
+};
+use polis_test_support::FakeRuntime;
+use std::path::Path;
+use std::sync::Arc;
+
+const MI: u64 = 1024 * 1024;
+
+async fn orchestrator(state_dir: &Path, runtime: &Arc<FakeRuntime>) -> Orchestrator {
+    let config = OrchestratorConfig {
+        state_dir: state_dir.to_path_buf(),
+        ..Default::default()
+    };
+    Orchestrator::new(config, runtime.clone()).await.unwrap()
+}
+
+fn node(id: &str, disk: &str, cpu: u64) -> Node {
+    Node {
+        id: id.to_string(),
+        labels: [("disk".to_string(), disk.to_string())].into(),
+        allocatable_cpu: cpu,
+        allocatable_memory: 1024 * MI,
+    }
+}
+
+fn spec(replicas: u32) -> DeploymentSpec {
+    serde_yaml::from_str(&format!(
+        r#"
+name: db
+image: postgres:16
+replicas: {}
+annotations:
+  polis.io/node-selector: disk=ssd
+resources:
+  cpu_request: 500m
+  memory_request: 256Mi
+"#,
+        replicas
+    ))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_unschedulable_deployment_stays_pending_until_a_node_fits() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    let orchestrator = orchestrator(temp.path(), &runtime).await;
+    orchestrator
+        .register_node(node("hdd-1", "hdd", 8000))
+        .await
+        .unwrap();
+    orchestrator
+        .register_node(node("ssd-1", "ssd", 1000))
+        .await
+        .unwrap();
+
+    let status = orchestrator.deploy(spec(3)).await.unwrap();
+    assert_eq!(status.status, DeploymentStatusType::Pending);
+    assert_eq!(status.current_replicas, 0);
+    assert!(runtime.containers().is_empty());
+    let reason = orchestrator
+        .get_deployment_status("db", "default")
+        .await
+        .unwrap()
+        .unwrap()
+        .reason
+        .unwrap();
+    assert_eq!(
+        reason,
+        "0/2 nodes are available: 1 node(s) did not match the node selector, 1 node(s) had insufficient cpu"
+    );
+
+    // A node with room lets the pending deployment start
+    orchestrator
+        .register_node(node("ssd-2", "ssd", 2000))
+        .await
+        .unwrap();
+    let status = orchestrator
+        .get_deployment_status("db", "default")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(status.status, DeploymentStatusType::Running);
+    assert_eq!(status.ready_replicas, 3);
+    assert!(status.reason.is_none());
+    assert_eq!(runtime.containers().len(), 3);
+}
+
+#[tokio::test]
+async fn test_scaling_past_capacity_is_rejected() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = Arc::new(FakeRuntime::new());
+    let orchestrator = orchestrator(temp.path(), &runtime).await;
+    orchestrator
+        .register_node(node("ssd-1", "ssd", 1000))
+        .await
+        .unwrap();
+    orchestrator.deploy(spec(2)).await.unwrap();
+
+    let error = orchestrator
+        .scale_deployment("db", "default", 3)
+        .await
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("insufficient cpu"), "{}", error);
+    assert_eq!(runtime.containers().len(), 2);
+
+    // Placements and nodes survive a restart, so the node is still full
+    drop(orchestrator);
+    let reopened = Orchestrator::new(
+        OrchestratorConfig {
+            state_dir: temp.path().to_path_buf(),
+            ..Default::default()
+        },
+        runtime.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(reopened.list_nodes().await.len(), 1);
+    assert!(reopened.scale_deployment("db", "default", 3).await.is_err());
+    reopened.scale_deployment("db", "default", 1).await.unwrap();
+    assert_eq!(runtime.containers().len(), 1);
+}
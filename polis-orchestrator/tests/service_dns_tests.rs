@@ -0,0 +1,206 @@
+use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::{Name, RData, RecordType};
+use polis_orchestrator::service_discovery::HealthStatus;
+use polis_orchestrator::{
+    HealthCheck, Protocol, Service, ServiceDiscovery, ServiceDnsServer, ServiceEndpoint,
+};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+
+fn query(name: &str, record_type: RecordType) -> Vec<u8> {
+    let mut message = Message::new();
+    message
+        .set_id(query_id(name))
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(true);
+    message.add_query(Query::query(Name::from_ascii(name).unwrap(), record_type));
+    message.to_vec().unwrap()
+}
+
+fn query_id(name: &str) -> u16 {
+    name.bytes()
+        .fold(7u16, |id, b| id.wrapping_mul(31).wrapping_add(b as u16))
+}
+
+async fn ask_udp(server: SocketAddr, name: &str, record_type: RecordType) -> Message {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    socket
+        .send_to(&query(name, record_type), server)
+        .await
+        .unwrap();
+    let mut buffer = [0u8; 4096];
+    let (len, _) = tokio::time::timeout(Duration::from_secs(2), socket.recv_from(&mut buffer))
+        .await
+        .expect("no DNS response")
+        .unwrap();
+    let response = Message::from_vec(&buffer[..len]).unwrap();
+    assert_eq!(response.id(), query_id(name));
+    response
+}
+
+async fn ask_tcp(server: SocketAddr, name: &str, record_type: RecordType) -> Message {
+    let mut stream = TcpStream::connect(server).await.unwrap();
+    let message = query(name, record_type);
+    stream
+        .write_all(&(message.len() as u16).to_be_bytes())
+        .await
+        .unwrap();
+    stream.write_all(&message).await.unwrap();
+
+    let mut length = [0u8; 2];
+    stream.read_exact(&mut length).await.unwrap();
+    let mut buffer = vec![0u8; u16::from_be_bytes(length) as usize];
+    stream.read_exact(&mut buffer).await.unwrap();
+    Message::from_vec(&buffer).unwrap()
+}
+
+fn addresses(response: &Message) -> Vec<Ipv4Addr> {
+    let mut addresses: Vec<Ipv4Addr> = response
+        .answers()
+        .iter()
+        .filter_map(|record| match record.data() {
+            Some(RData::A(address)) => Some(address.0),
+            _ => None,
+        })
+        .collect();
+    addresses.sort();
+    addresses
+}
+
+/// `web.default` with two healthy endpoints, checked every 10 seconds
+async fn start() -> (ServiceDiscovery, ServiceDnsServer, Service) {
+    let discovery = ServiceDiscovery::new();
+    let mut service = Service::new("web".to_string(), "default".to_string(), "1.0".to_string())
+        .with_endpoint(
+            ServiceEndpoint::new("10.0.0.1".to_string(), 8080, Protocol::Http).with_weight(3),
+        )
+        .with_endpoint(ServiceEndpoint::new(
+            "10.0.0.2".to_string(),
+            8081,
+            Protocol::Http,
+        ));
+    // Updating instead of registering starts no probe of the made up
+    // addresses; the health is set below instead
+    service.health_check = Some(HealthCheck {
+        enabled: true,
+        interval: Duration::from_secs(10),
+        timeout: Duration::from_secs(1),
+        retries: 1,
+        path: None,
+        port: None,
+        protocol: Protocol::Http,
+        headers: HashMap::new(),
+    });
+    discovery.update_service(service.clone()).await.unwrap();
+    for endpoint in &service.endpoints {
+        discovery
+            .set_endpoint_health(&service.id, &endpoint.id, HealthStatus::Healthy)
+            .await
+            .unwrap();
+    }
+
+    let server = discovery
+        .serve_dns("127.0.0.1:0".parse().unwrap(), "polis")
+        .await
+        .unwrap();
+    (discovery, server, service)
+}
+
+#[tokio::test]
+async fn test_a_and_srv_records_follow_endpoint_health() {
+    let (discovery, server, service) = start().await;
+    let addr = server.local_addr();
+
+    let response = ask_udp(addr, "web.default.polis.", RecordType::A).await;
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert!(response.authoritative());
+    assert_eq!(
+        addresses(&response),
+        vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)]
+    );
+    // The TTL is the health check interval
+    assert!(response.answers().iter().all(|record| record.ttl() == 10));
+
+    let response = ask_udp(addr, "_http._tcp.web.default.polis.", RecordType::SRV).await;
+    let mut srv: Vec<(u16, u16, String)> = response
+        .answers()
+        .iter()
+        .filter_map(|record| match record.data() {
+            Some(RData::SRV(srv)) => Some((srv.port(), srv.weight(), srv.target().to_string())),
+            _ => None,
+        })
+        .collect();
+    srv.sort();
+    let first = &service.endpoints[0];
+    assert_eq!(srv.len(), 2);
+    assert_eq!(
+        srv[0],
+        (8080, 3, format!("{}.web.default.polis.", first.id))
+    );
+    assert_eq!(srv[1].0, 8081);
+    // The targets resolve through the additional section
+    assert_eq!(response.additionals().len(), 2);
+
+    discovery
+        .set_endpoint_health(&service.id, &first.id, HealthStatus::Unhealthy)
+        .await
+        .unwrap();
+    let response = ask_udp(addr, "WEB.default.polis.", RecordType::A).await;
+    assert_eq!(addresses(&response), vec![Ipv4Addr::new(10, 0, 0, 2)]);
+    let response = ask_udp(addr, "web.default.polis.", RecordType::SRV).await;
+    assert_eq!(response.answers().len(), 1);
+
+    // The name still exists while no endpoint is healthy
+    let second = &service.endpoints[1];
+    discovery
+        .set_endpoint_health(&service.id, &second.id, HealthStatus::Unhealthy)
+        .await
+        .unwrap();
+    let response = ask_udp(addr, "web.default.polis.", RecordType::A).await;
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert!(response.answers().is_empty());
+}
+
+#[tokio::test]
+async fn test_unknown_names_are_nxdomain() {
+    let (_discovery, server, _service) = start().await;
+    let addr = server.local_addr();
+
+    for name in [
+        "api.default.polis.",
+        "web.staging.polis.",
+        "web.default.example.",
+        "deadbeef.web.default.polis.",
+    ] {
+        let response = ask_udp(addr, name, RecordType::A).await;
+        assert_eq!(response.response_code(), ResponseCode::NXDomain, "{name}");
+        assert!(response.answers().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_queries_over_udp_and_tcp() {
+    let (_discovery, server, service) = start().await;
+    let addr = server.local_addr();
+
+    let queries = (0..20).map(|i| async move {
+        if i % 2 == 0 {
+            ask_udp(addr, "web.default.polis.", RecordType::A).await
+        } else {
+            ask_tcp(addr, "web.default.polis.", RecordType::A).await
+        }
+    });
+    for response in futures::future::join_all(queries).await {
+        assert_eq!(addresses(&response).len(), 2);
+    }
+
+    // Endpoint names resolve to their own address
+    let endpoint = &service.endpoints[1];
+    let name = format!("{}.web.default.polis.", endpoint.id);
+    let response = ask_tcp(addr, &name, RecordType::A).await;
+    assert_eq!(addresses(&response), vec![Ipv4Addr::new(10, 0, 0, 2)]);
+}
@@ -0,0 +1,240 @@
+use axum::routing::get;
+use axum::Router;
+use polis_orchestrator::service_discovery::HealthStatus;
+use polis_orchestrator::{
+    LoadBalancer, LoadBalancerRequest, LoadBalancingAlgorithm, Protocol, Service, ServiceDiscovery,
+    ServiceEndpoint, ServiceEvent,
+};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+
+/// Serves `/` right away and `/slow` after two seconds
+async fn backend() -> u16 {
+    let app = Router::new().route("/", get(|| async { "ok" })).route(
+        "/slow",
+        get(|| async {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            "late"
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    port
+}
+
+fn endpoint(id: &str, port: u16) -> ServiceEndpoint {
+    let mut endpoint = ServiceEndpoint::new("127.0.0.1".to_string(), port, Protocol::Http);
+    endpoint.id = id.to_string();
+    endpoint.health_status = HealthStatus::Healthy;
+    endpoint
+}
+
+fn request(path: &str) -> LoadBalancerRequest {
+    LoadBalancerRequest {
+        client_ip: None,
+        session_id: None,
+        headers: HashMap::new(),
+        path: path.to_string(),
+        method: "GET".to_string(),
+        body: None,
+    }
+}
+
+/// Service `web` with the given endpoints, behind a load balancer
+async fn web(
+    algorithm: LoadBalancingAlgorithm,
+    endpoints: Vec<ServiceEndpoint>,
+) -> (ServiceDiscovery, LoadBalancer, Service) {
+    let discovery = ServiceDiscovery::new();
+    let lb = LoadBalancer::new(algorithm);
+    let mut service = Service::new("web".to_string(), "default".to_string(), "1.0".to_string());
+    for endpoint in endpoints {
+        lb.add_endpoint(endpoint.clone()).await;
+        service = service.with_endpoint(endpoint);
+    }
+    discovery.register_service(service.clone()).await.unwrap();
+    discovery
+        .attach_load_balancer(&service.id, lb.clone())
+        .await;
+    (discovery, lb, service)
+}
+
+async fn endpoint_ids(discovery: &ServiceDiscovery, service: &Service) -> Vec<String> {
+    let service = discovery.get_service(&service.id).await.unwrap();
+    service.endpoints.into_iter().map(|ep| ep.id).collect()
+}
+
+/// Drain events received so far, as "<kind> <endpoint id>"
+fn drain_events(receiver: &mut broadcast::Receiver<ServiceEvent>) -> Vec<String> {
+    let mut events = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        match event {
+            ServiceEvent::HealthStatusChanged {
+                endpoint_id,
+                status,
+                ..
+            } => events.push(format!("{:?} {}", status, endpoint_id)),
+            ServiceEvent::EndpointRemoved { endpoint_id, .. } => {
+                events.push(format!("removed {}", endpoint_id))
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+#[tokio::test]
+async fn test_drained_endpoint_is_removed_after_its_requests_finish() {
+    let (discovery, lb, service) = web(
+        LoadBalancingAlgorithm::RoundRobin,
+        vec![
+            endpoint("old", backend().await),
+            endpoint("new", backend().await),
+        ],
+    )
+    .await;
+    let mut events = discovery.get_service_events().await;
+
+    // One long request on each endpoint
+    let in_flight: Vec<_> = (0..2)
+        .map(|_| {
+            let lb = lb.clone();
+            tokio::spawn(async move { lb.handle_request(request("/slow")).await.unwrap() })
+        })
+        .collect();
+    while lb.get_stats().await.endpoint_stats["old"].active_connections == 0 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let drained = discovery
+        .drain_endpoint(&service.id, "old", Duration::from_secs(10))
+        .await
+        .unwrap();
+
+    // Still listed, but handed out to nobody
+    let healthy = discovery.get_healthy_endpoints(&service.id).await;
+    assert_eq!(
+        healthy.iter().map(|ep| ep.id.as_str()).collect::<Vec<_>>(),
+        vec!["new"]
+    );
+    for _ in 0..4 {
+        let selected = lb.select_endpoint(&request("/")).await.unwrap().unwrap();
+        assert_eq!(selected.id, "new");
+    }
+    assert_eq!(endpoint_ids(&discovery, &service).await, vec!["old", "new"]);
+    assert!(!drained.is_finished());
+
+    let mut served_by = Vec::new();
+    for request in in_flight {
+        let response = request.await.unwrap();
+        assert_eq!(response.status_code, 200);
+        served_by.push(response.endpoint.id);
+    }
+    assert!(served_by.contains(&"old".to_string()));
+
+    drained.await.unwrap();
+    assert_eq!(endpoint_ids(&discovery, &service).await, vec!["new"]);
+    assert!(!lb.get_stats().await.endpoint_stats.contains_key("old"));
+    assert_eq!(
+        drain_events(&mut events),
+        vec!["Draining old", "removed old"]
+    );
+}
+
+#[tokio::test]
+async fn test_deadline_cuts_a_drain_short() {
+    let (discovery, lb, service) = web(
+        LoadBalancingAlgorithm::RoundRobin,
+        vec![endpoint("old", backend().await)],
+    )
+    .await;
+    let in_flight = {
+        let lb = lb.clone();
+        tokio::spawn(async move { lb.handle_request(request("/slow")).await })
+    };
+    while lb.get_stats().await.endpoint_stats["old"].active_connections == 0 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let started = Instant::now();
+    discovery
+        .drain_endpoint(&service.id, "old", Duration::from_millis(200))
+        .await
+        .unwrap()
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert!(!in_flight.is_finished());
+    assert!(endpoint_ids(&discovery, &service).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_without_load_balancer_the_drain_lasts_until_the_deadline() {
+    let discovery = ServiceDiscovery::new();
+    let service = Service::new("api".to_string(), "default".to_string(), "1.0".to_string())
+        .with_endpoint(endpoint("only", 1));
+    discovery.register_service(service.clone()).await.unwrap();
+
+    let started = Instant::now();
+    let drained = discovery
+        .drain_endpoint(&service.id, "only", Duration::from_millis(300))
+        .await
+        .unwrap();
+    let endpoint = &discovery.get_service(&service.id).await.unwrap().endpoints[0];
+    assert_eq!(endpoint.health_status, HealthStatus::Draining);
+    assert!(discovery
+        .resolve_service("api", Some("default"))
+        .await
+        .unwrap()
+        .is_empty());
+
+    drained.await.unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    assert!(endpoint_ids(&discovery, &service).await.is_empty());
+
+    // Unknown endpoints cannot be drained
+    assert!(discovery
+        .drain_endpoint(&service.id, "only", Duration::from_millis(10))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_consistent_hash_only_moves_the_drained_keys() {
+    let (discovery, lb, service) = web(
+        LoadBalancingAlgorithm::ConsistentHash,
+        vec![
+            endpoint("a", 8080),
+            endpoint("b", 8081),
+            endpoint("c", 8082),
+        ],
+    )
+    .await;
+    let keys: Vec<String> = (0..200).map(|i| format!("/user/{}", i)).collect();
+    let mut before = Vec::new();
+    for key in &keys {
+        before.push(lb.select_endpoint(&request(key)).await.unwrap().unwrap().id);
+    }
+    assert!(before.iter().any(|id| id == "b"));
+
+    // Nothing is in flight, so the endpoint goes right away
+    discovery
+        .drain_endpoint(&service.id, "b", Duration::from_secs(10))
+        .await
+        .unwrap()
+        .await
+        .unwrap();
+
+    for (key, owner) in keys.iter().zip(before) {
+        let selected = lb.select_endpoint(&request(key)).await.unwrap().unwrap();
+        if owner == "b" {
+            assert_ne!(selected.id, "b");
+        } else {
+            assert_eq!(selected.id, owner, "{key} moved");
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use polis_orchestrator::{
+    HealthCheck, LabelSelector, Protocol, Service, ServiceDiscovery, ServiceEndpoint, ServiceEvent,
+};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+fn service(name: &str, labels: &[(&str, &str)]) -> Service {
+    let mut service = Service::new(name.to_string(), "default".to_string(), "1.0".to_string());
+    for (key, value) in labels {
+        service = service.with_label(key.to_string(), value.to_string());
+    }
+    service
+}
+
+/// Events received until none arrives for a while, as "<kind> <service id>"
+async fn drain(receiver: &mut broadcast::Receiver<ServiceEvent>) -> Vec<String> {
+    let mut events = Vec::new();
+    while let Ok(Ok(event)) =
+        tokio::time::timeout(Duration::from_millis(200), receiver.recv()).await
+    {
+        events.push(match event {
+            ServiceEvent::ServiceRegistered { service } => format!("registered {}", service.id),
+            ServiceEvent::ServiceUpdated { service } => format!("updated {}", service.id),
+            ServiceEvent::ServiceDeregistered { service_id } => {
+                format!("deregistered {}", service_id)
+            }
+            ServiceEvent::EndpointAdded { service_id, .. } => format!("endpoint+ {}", service_id),
+            ServiceEvent::EndpointRemoved { service_id, .. } => {
+                format!("endpoint- {}", service_id)
+            }
+            ServiceEvent::HealthStatusChanged { service_id, .. } => {
+                format!("health {}", service_id)
+            }
+        });
+    }
+    events
+}
+
+fn frontend() -> LabelSelector {
+    LabelSelector::new().with_equals("tier".to_string(), "frontend".to_string())
+}
+
+#[tokio::test]
+async fn test_watchers_only_see_their_services() {
+    let discovery = ServiceDiscovery::new();
+    let mut frontends = discovery.watch(frontend()).await;
+    let mut live_backends = discovery
+        .watch(
+            LabelSelector::new()
+                .with_in(
+                    "env".to_string(),
+                    vec!["prod".to_string(), "staging".to_string()],
+                )
+                .with_not_in("tier".to_string(), vec!["frontend".to_string()]),
+        )
+        .await;
+
+    let web = service("web", &[("tier", "frontend"), ("env", "prod")]);
+    let db = service("db", &[("tier", "backend"), ("env", "prod")]);
+    let queue = service("queue", &[("env", "staging")]);
+    let cache = service("cache", &[("env", "dev")]);
+    let endpoint = ServiceEndpoint::new("10.0.0.1".to_string(), 80, Protocol::Http);
+    let web_endpoint = endpoint.id.clone();
+    let web = web.with_endpoint(endpoint);
+    for service in [&web, &db, &queue, &cache] {
+        discovery.register_service(service.clone()).await.unwrap();
+    }
+
+    let endpoint = ServiceEndpoint::new("10.0.0.2".to_string(), 5432, Protocol::Tcp);
+    discovery.add_endpoint(&db.id, endpoint).await.unwrap();
+    discovery
+        .remove_endpoint(&web.id, &web_endpoint)
+        .await
+        .unwrap();
+    discovery.deregister_service(&web.id).await.unwrap();
+    discovery.deregister_service(&cache.id).await.unwrap();
+
+    // Leaving the selector reads as a deregistration
+    let mut moved = db.clone();
+    moved.labels.insert("env".to_string(), "dev".to_string());
+    discovery.update_service(moved).await.unwrap();
+
+    assert_eq!(
+        drain(&mut frontends).await,
+        vec![
+            format!("registered {}", web.id),
+            format!("endpoint- {}", web.id),
+            format!("deregistered {}", web.id),
+        ]
+    );
+    assert_eq!(
+        drain(&mut live_backends).await,
+        vec![
+            format!("registered {}", db.id),
+            format!("registered {}", queue.id),
+            format!("endpoint+ {}", db.id),
+            format!("deregistered {}", db.id),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_lists_matching_services_first() {
+    let discovery = ServiceDiscovery::new();
+    let web = service("web", &[("tier", "frontend")]);
+    let db = service("db", &[("tier", "backend")]);
+    discovery.register_service(web.clone()).await.unwrap();
+    discovery.register_service(db.clone()).await.unwrap();
+
+    let mut with_snapshot = discovery.watch_with_snapshot(frontend()).await;
+    let mut without_snapshot = discovery.watch(frontend()).await;
+
+    let admin = service("admin", &[("tier", "frontend")]);
+    discovery.register_service(admin.clone()).await.unwrap();
+
+    assert_eq!(
+        drain(&mut with_snapshot).await,
+        vec![
+            format!("registered {}", web.id),
+            format!("registered {}", admin.id),
+        ]
+    );
+    assert_eq!(
+        drain(&mut without_snapshot).await,
+        vec![format!("registered {}", admin.id)]
+    );
+}
+
+#[tokio::test]
+async fn test_health_changes_reach_matching_watchers() {
+    let discovery = ServiceDiscovery::new();
+    let mut frontends = discovery.watch(frontend()).await;
+    let mut everything = discovery.watch(LabelSelector::new()).await;
+    let mut backends = discovery
+        .watch(LabelSelector::new().with_equals("tier".to_string(), "backend".to_string()))
+        .await;
+
+    // Nothing listens on port 1, so the endpoint turns unhealthy
+    let web = service("web", &[("tier", "frontend")])
+        .with_endpoint(ServiceEndpoint::new(
+            "127.0.0.1".to_string(),
+            1,
+            Protocol::Http,
+        ))
+        .with_health_check(HealthCheck {
+            enabled: true,
+            interval: Duration::from_millis(50),
+            timeout: Duration::from_millis(500),
+            retries: 1,
+            path: None,
+            port: None,
+            protocol: Protocol::Http,
+            headers: HashMap::new(),
+        });
+    discovery.register_service(web.clone()).await.unwrap();
+
+    let expected = vec![
+        format!("registered {}", web.id),
+        format!("health {}", web.id),
+    ];
+    assert_eq!(drain(&mut frontends).await, expected);
+    assert_eq!(drain(&mut everything).await, expected);
+    assert!(drain(&mut backends).await.is_empty());
+    discovery.deregister_service(&web.id).await.unwrap();
+}
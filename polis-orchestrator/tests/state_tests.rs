@@ -0,0 +1,131 @@
+use polis_core::PolisError;
+use polis_orchestrator::{DeploymentSpec, Orchestrator, OrchestratorConfig, STATE_VERSION};
+use polis_test_support::FakeRuntime;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+fn config(state_dir: &Path) -> OrchestratorConfig {
+    OrchestratorConfig {
+        state_dir: state_dir.to_path_buf(),
+        ..Default::default()
+    }
+}
+
+async fn open(config: OrchestratorConfig) -> polis_core::Result<Orchestrator> {
+    Orchestrator::new(config, Arc::new(FakeRuntime::new())).await
+}
+
+fn spec(name: &str) -> DeploymentSpec {
+    serde_yaml::from_str(&format!("name: {}\nimage: nginx:1.25\nreplicas: 1\n", name)).unwrap()
+}
+
+#[tokio::test]
+async fn test_state_round_trips_with_version() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = config(&temp.path().join("state"));
+    let orchestrator = open(config.clone()).await.unwrap();
+    orchestrator.deploy(spec("web")).await.unwrap();
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(config.state_path()).unwrap()).unwrap();
+    assert_eq!(saved["version"], STATE_VERSION);
+    assert!(!config.state_path().with_extension("json.tmp").exists());
+
+    let reopened = open(config).await.unwrap();
+    assert!(reopened
+        .get_deployment_status("web", "default")
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn test_corrupt_state_fails_loudly() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = config(temp.path());
+    fs::write(config.state_path(), "{\"deployments\": {").unwrap();
+
+    let error = open(config.clone()).await.err().unwrap();
+    assert!(matches!(error, PolisError::Config(_)));
+    assert!(error.to_string().contains("--ignore-corrupt-state"));
+    // The file is left untouched for inspection
+    assert_eq!(
+        fs::read_to_string(config.state_path()).unwrap(),
+        "{\"deployments\": {"
+    );
+}
+
+#[tokio::test]
+async fn test_ignore_corrupt_state_starts_empty_and_keeps_backup() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = OrchestratorConfig {
+        ignore_corrupt_state: true,
+        ..config(temp.path())
+    };
+    fs::write(config.state_path(), "not json").unwrap();
+
+    let orchestrator = open(config.clone()).await.unwrap();
+    assert!(orchestrator
+        .list_deployments(None)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        fs::read_to_string(config.state_path().with_extension("json.corrupt")).unwrap(),
+        "not json"
+    );
+
+    orchestrator.deploy(spec("web")).await.unwrap();
+    let reopened = open(config).await.unwrap();
+    assert_eq!(reopened.list_deployments(None).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_partial_write_keeps_previous_state() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = config(temp.path());
+    let orchestrator = open(config.clone()).await.unwrap();
+    orchestrator.deploy(spec("web")).await.unwrap();
+    drop(orchestrator);
+
+    // A save interrupted before the rename leaves only a truncated temp file
+    let complete = fs::read_to_string(config.state_path()).unwrap();
+    let temp_file = config.state_path().with_extension("json.tmp");
+    fs::write(&temp_file, &complete[..complete.len() / 2]).unwrap();
+
+    let reopened = open(config.clone()).await.unwrap();
+    assert_eq!(reopened.list_deployments(None).await.unwrap().len(), 1);
+
+    reopened.deploy(spec("api")).await.unwrap();
+    assert!(!temp_file.exists());
+    let reopened = open(config).await.unwrap();
+    assert_eq!(reopened.list_deployments(None).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_state_versions() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = config(temp.path());
+
+    // Files written before versioning still load
+    fs::write(
+        config.state_path(),
+        r#"{"deployments": {}, "services": {}}"#,
+    )
+    .unwrap();
+    assert!(open(config.clone()).await.is_ok());
+
+    fs::write(
+        config.state_path(),
+        format!(
+            r#"{{"version": {}, "deployments": {{}}, "services": {{}}}}"#,
+            STATE_VERSION + 1
+        ),
+    )
+    .unwrap();
+    let error = open(config).await.err().unwrap();
+    assert!(error
+        .to_string()
+        .contains("newer than the supported version"));
+}
@@ -0,0 +1,270 @@
+use polis_orchestrator::{
+    ApplyAction, DeploymentSpec, Orchestrator, OrchestratorConfig, ResourceKind, SyncConfig,
+    SyncController, SyncSource, SyncStatus, SYNC_OWNER_LABEL,
+};
+use polis_test_support::{FakeRuntime, FileTree};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+const WEB: &str = r#"
+kind: Deployment
+name: web
+image: nginx:1.25
+replicas: 2
+"#;
+
+const WEB_SCALED: &str = r#"
+kind: Deployment
+name: web
+image: nginx:1.25
+replicas: 4
+"#;
+
+const SUPPORT: &str = r#"
+kind: Config
+name: web-config
+data:
+  LOG_LEVEL: info
+---
+kind: CronJob
+name: cleanup
+schedule: "0 3 * * *"
+image: busybox
+command: ["rm", "-rf", "/tmp/cache"]
+"#;
+
+async fn orchestrator(state_dir: &Path) -> Arc<Orchestrator> {
+    let config = OrchestratorConfig {
+        state_dir: state_dir.to_path_buf(),
+        ..Default::default()
+    };
+    Arc::new(
+        Orchestrator::new(config, Arc::new(FakeRuntime::new()))
+            .await
+            .unwrap(),
+    )
+}
+
+fn action_for(status: &SyncStatus, name: &str) -> Option<ApplyAction> {
+    status
+        .results
+        .iter()
+        .find(|r| r.resource.name == name)
+        .and_then(|r| r.action)
+}
+
+#[tokio::test]
+async fn test_sync_applies_updates_and_noops() {
+    let temp = tempfile::tempdir().unwrap();
+    let source = temp.path().join("specs");
+    FileTree::new()
+        .file("apps/web.yaml", WEB)
+        .file("support.yml", SUPPORT)
+        .write_to(&source)
+        .unwrap();
+
+    let orchestrator = orchestrator(&temp.path().join("state")).await;
+    let controller = SyncController::new(
+        SyncConfig::new(
+            "prod",
+            SyncSource::Directory {
+                path: source.clone(),
+            },
+        ),
+        orchestrator.clone(),
+    );
+
+    let first = controller.sync_once().await.unwrap();
+    assert!(first.parse_errors.is_empty());
+    assert_eq!(first.results.len(), 3);
+    assert!(first
+        .results
+        .iter()
+        .all(|r| r.action == Some(ApplyAction::Create)));
+    assert_eq!(
+        orchestrator
+            .get_config("web-config", "default")
+            .await
+            .unwrap()
+            .labels[SYNC_OWNER_LABEL],
+        "prod"
+    );
+
+    // Unchanged source: nothing is applied and the revision is stable
+    let second = controller.sync_once().await.unwrap();
+    assert!(second.changed().is_empty());
+    assert_eq!(second.revision, first.revision);
+
+    FileTree::new()
+        .file("apps/web.yaml", WEB_SCALED)
+        .write_to(&source)
+        .unwrap();
+    let third = controller.sync_once().await.unwrap();
+    assert_ne!(third.revision, first.revision);
+    assert_eq!(action_for(&third, "web"), Some(ApplyAction::Update));
+    assert_eq!(action_for(&third, "cleanup"), Some(ApplyAction::Unchanged));
+
+    let status = orchestrator
+        .get_deployment_status("web", "default")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(status.desired_replicas, 4);
+
+    // The persisted status is what `polis sync status` shows
+    let persisted = SyncStatus::load(&temp.path().join("state"), "prod")
+        .unwrap()
+        .unwrap();
+    assert_eq!(persisted.revision, third.revision);
+}
+
+#[tokio::test]
+async fn test_prune_is_gated_by_owner_label() {
+    let temp = tempfile::tempdir().unwrap();
+    let source = temp.path().join("specs");
+    FileTree::new()
+        .file("web.yaml", WEB)
+        .file("support.yaml", SUPPORT)
+        .write_to(&source)
+        .unwrap();
+
+    let orchestrator = orchestrator(&temp.path().join("state")).await;
+
+    // Created outside the sync, without the owner label
+    orchestrator
+        .deploy(DeploymentSpec {
+            name: "manual".to_string(),
+            namespace: "default".to_string(),
+            image: "redis:7".to_string(),
+            replicas: 1,
+            ports: Vec::new(),
+            env_vars: HashMap::new(),
+            labels: HashMap::new(),
+            annotations: HashMap::new(),
+            health_check: None,
+            scaling_policy: None,
+            resources: None,
+            read_only_rootfs: false,
+            writable_paths: Vec::new(),
+            max_runtime: None,
+            egress: None,
+        })
+        .await
+        .unwrap();
+
+    let source_config = SyncSource::Directory {
+        path: source.clone(),
+    };
+    let without_prune = SyncController::new(
+        SyncConfig::new("prod", source_config.clone()),
+        orchestrator.clone(),
+    );
+    without_prune.sync_once().await.unwrap();
+
+    fs::remove_file(source.join("support.yaml")).unwrap();
+    let status = without_prune.sync_once().await.unwrap();
+    assert!(status.changed().is_empty());
+    assert!(orchestrator
+        .get_config("web-config", "default")
+        .await
+        .is_some());
+
+    let with_prune = SyncController::new(
+        SyncConfig::new("prod", source_config).with_prune(true),
+        orchestrator.clone(),
+    );
+    let status = with_prune.sync_once().await.unwrap();
+    let pruned: Vec<_> = status
+        .results
+        .iter()
+        .filter(|r| r.action == Some(ApplyAction::Delete))
+        .map(|r| r.resource.kind)
+        .collect();
+    assert_eq!(pruned, vec![ResourceKind::Config, ResourceKind::CronJob]);
+
+    assert!(orchestrator
+        .get_config("web-config", "default")
+        .await
+        .is_none());
+    assert!(orchestrator
+        .get_cron_job("cleanup", "default")
+        .await
+        .is_none());
+    assert!(orchestrator
+        .get_deployment_status("web", "default")
+        .await
+        .unwrap()
+        .is_some());
+    assert!(orchestrator
+        .get_deployment_status("manual", "default")
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn test_malformed_document_is_isolated() {
+    let temp = tempfile::tempdir().unwrap();
+    let source = temp.path().join("specs");
+    FileTree::new()
+        .file("web.yaml", WEB)
+        .file("support.yaml", SUPPORT)
+        .write_to(&source)
+        .unwrap();
+
+    let orchestrator = orchestrator(&temp.path().join("state")).await;
+    let controller = SyncController::new(
+        SyncConfig::new(
+            "prod",
+            SyncSource::Directory {
+                path: source.clone(),
+            },
+        )
+        .with_prune(true),
+        orchestrator.clone(),
+    );
+    controller.sync_once().await.unwrap();
+
+    // An invalid document next to a valid one, plus a file with broken YAML
+    fs::write(
+        source.join("support.yaml"),
+        "kind: Config\nname: web-config\ndata:\n  LOG_LEVEL: debug\n---\nkind: Unknown\nname: x\n",
+    )
+    .unwrap();
+    fs::write(
+        source.join("broken.yaml"),
+        "kind: Deployment\nname: [unclosed\n",
+    )
+    .unwrap();
+
+    let status = controller.sync_once().await.unwrap();
+    assert_eq!(status.parse_errors.len(), 2);
+    assert!(status
+        .parse_errors
+        .iter()
+        .any(|e| e.file == Path::new("support.yaml") && e.document == 1));
+    assert!(status
+        .parse_errors
+        .iter()
+        .any(|e| e.file == Path::new("broken.yaml")));
+
+    assert_eq!(action_for(&status, "web-config"), Some(ApplyAction::Update));
+    assert_eq!(action_for(&status, "web"), Some(ApplyAction::Unchanged));
+    assert_eq!(
+        orchestrator
+            .get_config("web-config", "default")
+            .await
+            .unwrap()
+            .data["LOG_LEVEL"],
+        "debug"
+    );
+
+    // The cron job left the source but is kept while there are parse errors
+    assert!(status.prune_skipped);
+    assert!(orchestrator
+        .get_cron_job("cleanup", "default")
+        .await
+        .is_some());
+}
@@ -0,0 +1,48 @@
+use crate::{ContainerOptions, ContainerRuntime, PolisRuntime};
+use async_trait::async_trait;
+use polis_core::{ContainerId, ContainerStatus, Result};
+
+/// Ciclo de vida de containers usado pelo orquestrador para gerenciar réplicas
+#[async_trait]
+pub trait ContainerBackend: Send + Sync {
+    async fn create(
+        &self,
+        name: String,
+        image: String,
+        options: ContainerOptions,
+    ) -> Result<ContainerId>;
+    async fn start(&self, id: &ContainerId) -> Result<()>;
+    async fn stop(&self, id: &ContainerId) -> Result<()>;
+    async fn remove(&self, id: &ContainerId) -> Result<()>;
+    /// Estado atual do container
+    async fn status(&self, id: &ContainerId) -> Result<ContainerStatus>;
+}
+
+#[async_trait]
+impl ContainerBackend for PolisRuntime {
+    async fn create(
+        &self,
+        name: String,
+        image: String,
+        options: ContainerOptions,
+    ) -> Result<ContainerId> {
+        self.create_container_with_options(name, image, Vec::new(), options)
+            .await
+    }
+
+    async fn start(&self, id: &ContainerId) -> Result<()> {
+        self.start_container(id.clone()).await
+    }
+
+    async fn stop(&self, id: &ContainerId) -> Result<()> {
+        self.stop_container(id.clone()).await
+    }
+
+    async fn remove(&self, id: &ContainerId) -> Result<()> {
+        self.remove_container(id.clone()).await
+    }
+
+    async fn status(&self, id: &ContainerId) -> Result<ContainerStatus> {
+        Ok(self.get_container(id.clone()).await?.status)
+    }
+}
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use polis_core::{ContainerId, PolisError, Result, StopReason};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Eventos do ciclo de vida de containers emitidos pelo runtime
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContainerEvent {
+    Started {
+        id: ContainerId,
+        deadline: Option<DateTime<Utc>>,
+    },
+    /// O prazo de execução está próximo
+    DeadlineWarning {
+        id: ContainerId,
+        deadline: DateTime<Utc>,
+        remaining: Duration,
+    },
+    Stopped {
+        id: ContainerId,
+        exit_code: Option<i32>,
+        reason: StopReason,
+    },
+}
+
+/// Prazo de execução de um container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadlineRecord {
+    pub container_id: ContainerId,
+    pub max_runtime: Duration,
+    pub deadline: DateTime<Utc>,
+    /// Se o aviso de proximidade já foi emitido
+    pub warned: bool,
+}
+
+impl DeadlineRecord {
+    /// Tempo restante até o prazo; zero se já expirou
+    pub fn remaining(&self, now: DateTime<Utc>) -> Duration {
+        (self.deadline - now).to_std().unwrap_or(Duration::ZERO)
+    }
+}
+
+/// Prazos que exigem ação em uma verificação
+#[derive(Debug, Clone, Default)]
+pub struct DueDeadlines {
+    pub warnings: Vec<DeadlineRecord>,
+    pub expired: Vec<DeadlineRecord>,
+}
+
+/// Prazos de execução persistidos para sobreviver a reinícios do daemon
+#[derive(Debug)]
+pub struct DeadlineTracker {
+    path: PathBuf,
+    records: Vec<DeadlineRecord>,
+}
+
+impl DeadlineTracker {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            records: Vec::new(),
+        }
+    }
+
+    /// Carrega os prazos salvos
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let records = if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            serde_json::from_str(&content).map_err(|e| {
+                PolisError::Runtime(format!("Erro ao ler prazos de {}: {}", path.display(), e))
+            })?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { path, records })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, id: &ContainerId) -> Option<&DeadlineRecord> {
+        self.records.iter().find(|r| &r.container_id == id)
+    }
+
+    pub fn records(&self) -> &[DeadlineRecord] {
+        &self.records
+    }
+
+    /// Registra o prazo de um container iniciado em `started_at`
+    pub fn arm(
+        &mut self,
+        id: &ContainerId,
+        max_runtime: Duration,
+        started_at: DateTime<Utc>,
+    ) -> Result<DeadlineRecord> {
+        let max = chrono::Duration::from_std(max_runtime)
+            .map_err(|_| PolisError::Container("Tempo máximo de execução inválido".to_string()))?;
+        let record = DeadlineRecord {
+            container_id: id.clone(),
+            max_runtime,
+            deadline: started_at + max,
+            warned: false,
+        };
+
+        self.records.retain(|r| &r.container_id != id);
+        self.records.push(record.clone());
+        self.save()?;
+        Ok(record)
+    }
+
+    /// Remove o prazo de um container
+    pub fn disarm(&mut self, id: &ContainerId) -> Result<()> {
+        let before = self.records.len();
+        self.records.retain(|r| &r.container_id != id);
+        if self.records.len() != before {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Prazos que expiraram ou entraram na janela de aviso; os avisos são
+    /// emitidos uma única vez
+    pub fn due(&mut self, now: DateTime<Utc>, warning: Duration) -> Result<DueDeadlines> {
+        let mut due = DueDeadlines::default();
+        let mut changed = false;
+
+        for record in &mut self.records {
+            let remaining = record.remaining(now);
+            if remaining.is_zero() {
+                due.expired.push(record.clone());
+            } else if !record.warned && remaining <= warning {
+                record.warned = true;
+                changed = true;
+                due.warnings.push(record.clone());
+            }
+        }
+
+        if changed {
+            self.save()?;
+        }
+        Ok(due)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.records)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use crate::MountEntry;
+use chrono::{DateTime, Utc};
+use polis_core::{
+    Container, ContainerId, ContainerStatus, EgressPolicy, NetworkMode, PortMapping,
+    ResourceLimits, RootfsConfig, StopReason,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Descrição completa de um container (`polis container inspect` e
+/// `GET /v1/containers/{id}`). O que o runtime não sabe fica `null`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerInspect {
+    pub id: ContainerId,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub config: InspectConfig,
+    pub state: InspectState,
+    pub network: InspectNetwork,
+    pub mounts: Vec<MountEntry>,
+    /// Última amostra do coletor de estatísticas; `None` quando o container
+    /// não está sendo monitorado
+    pub stats: Option<serde_json::Value>,
+}
+
+/// Especificação com que o container foi criado
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InspectConfig {
+    pub image: String,
+    pub command: Vec<String>,
+    pub working_dir: PathBuf,
+    pub environment: HashMap<String, String>,
+    pub labels: HashMap<String, String>,
+    /// O runtime ainda não reinicia containers, então não há política
+    pub restart_policy: Option<String>,
+    pub resource_limits: ResourceLimits,
+    pub rootfs: RootfsConfig,
+    pub max_runtime: Option<std::time::Duration>,
+}
+
+/// Estado de execução
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InspectState {
+    pub status: ContainerStatus,
+    /// PID no host do processo inicial, enquanto o container roda
+    pub pid: Option<u32>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub exit_code: Option<i32>,
+    pub oom_killed: bool,
+    pub stop_reason: Option<StopReason>,
+    pub deadline: Option<DateTime<Utc>>,
+}
+
+/// Configuração de rede
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InspectNetwork {
+    pub mode: NetworkMode,
+    /// IP alocado pelo IPAM enquanto as portas estão publicadas
+    pub ip_address: Option<IpAddr>,
+    pub ports: Vec<PortMapping>,
+    pub egress: Option<EgressPolicy>,
+}
+
+impl ContainerInspect {
+    /// Descrição com apenas o que está no próprio container: sem PID, IP,
+    /// montagens ou estatísticas
+    pub fn from_container(container: Container) -> Self {
+        Self {
+            id: container.id,
+            name: container.name,
+            created_at: container.created_at,
+            config: InspectConfig {
+                image: container.image.0,
+                command: container.command,
+                working_dir: container.working_dir,
+                environment: container.environment,
+                labels: container.labels,
+                restart_policy: None,
+                resource_limits: container.resource_limits,
+                rootfs: container.rootfs,
+                max_runtime: container.max_runtime,
+            },
+            state: InspectState {
+                status: container.status,
+                pid: None,
+                started_at: container.started_at,
+                finished_at: container.finished_at,
+                exit_code: container.exit_code,
+                oom_killed: container.stop_reason == Some(StopReason::OomKilled),
+                stop_reason: container.stop_reason,
+                deadline: container.deadline,
+            },
+            network: InspectNetwork {
+                mode: container.network_mode,
+                ip_address: None,
+                ports: container.ports,
+                egress: container.egress,
+            },
+            mounts: Vec::new(),
+            stats: None,
+        }
+    }
+}
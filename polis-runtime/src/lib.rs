@@ -1,7 +1,25 @@
+pub mod backend;
 pub mod container;
+pub mod deadline;
+pub mod inspect;
+pub mod log_capture;
+pub mod names;
 pub mod process;
+pub mod rootfs;
 pub mod runtime;
+pub mod spec;
+pub mod store;
+pub mod top;
 
+pub use backend::*;
 pub use container::*;
+pub use deadline::*;
+pub use inspect::*;
+pub use log_capture::*;
+pub use names::*;
 pub use process::*;
+pub use rootfs::*;
 pub use runtime::*;
+pub use spec::*;
+pub use store::*;
+pub use top::*;
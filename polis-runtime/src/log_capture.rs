@@ -0,0 +1,48 @@
+use polis_core::{Clock, ContainerLogRecord, LogStream, Result, RotatingLogWriter};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+/// Copia as saídas de um processo para o arquivo de log do container, uma
+/// linha por registro, até as duas se encerrarem
+pub async fn capture_output<O, E>(
+    writer: RotatingLogWriter,
+    clock: Arc<dyn Clock>,
+    stdout: O,
+    stderr: E,
+) -> Result<()>
+where
+    O: AsyncRead + Unpin,
+    E: AsyncRead + Unpin,
+{
+    let writer = Mutex::new(writer);
+    let (stdout, stderr) = tokio::join!(
+        pump(&writer, clock.as_ref(), LogStream::Stdout, stdout),
+        pump(&writer, clock.as_ref(), LogStream::Stderr, stderr),
+    );
+    stdout.and(stderr)
+}
+
+async fn pump<R: AsyncRead + Unpin>(
+    writer: &Mutex<RotatingLogWriter>,
+    clock: &dyn Clock,
+    stream: LogStream,
+    output: R,
+) -> Result<()> {
+    let mut output = BufReader::new(output);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if output.read_until(b'\n', &mut line).await? == 0 {
+            return Ok(());
+        }
+        if line.ends_with(b"\n") {
+            line.pop();
+        }
+        let record = ContainerLogRecord {
+            time: clock.now(),
+            stream,
+            log: String::from_utf8_lossy(&line).into_owned(),
+        };
+        writer.lock().unwrap().write(&record)?;
+    }
+}
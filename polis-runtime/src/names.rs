@@ -0,0 +1,181 @@
+use polis_core::{ContainerId, PolisError, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Palavras dos nomes gerados (`adjetivo_sobrenome`), como no Docker
+#[rustfmt::skip]
+const ADJECTIVES: &[&str] = &[
+    "admiring", "agitated", "bold", "brave", "busy", "calm", "clever", "cool", "eager", "elegant",
+    "epic", "focused", "gallant", "happy", "hopeful", "jolly", "keen", "loving", "modest", "nifty",
+    "peaceful", "quirky", "serene", "sharp", "stoic", "tender", "upbeat", "vibrant", "wizardly",
+    "zealous",
+];
+
+#[rustfmt::skip]
+const SURNAMES: &[&str] = &[
+    "archimedes", "babbage", "bohr", "curie", "darwin", "dijkstra", "einstein", "euclid", "fermat",
+    "feynman", "galileo", "gauss", "hopper", "hypatia", "kepler", "knuth", "lamport", "lovelace",
+    "meitner", "newton", "noether", "pascal", "ritchie", "santos", "shannon", "tesla", "thompson",
+    "torvalds", "turing", "wozniak",
+];
+
+/// Índice nome ↔ ID dos containers, persistido junto do estado do runtime
+/// para que nomes sobrevivam a reinícios
+#[derive(Debug, Default)]
+pub struct NameIndex {
+    /// Arquivo do índice; `None` enquanto o índice vive só em memória
+    path: Option<PathBuf>,
+    names: BTreeMap<String, ContainerId>,
+}
+
+impl NameIndex {
+    /// Índice só em memória, até ser ligado a um arquivo com [`attach`]
+    ///
+    /// [`attach`]: NameIndex::attach
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Carrega o índice salvo
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let names = if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            serde_json::from_str(&content).map_err(|e| {
+                PolisError::Runtime(format!("Erro ao ler nomes de {}: {}", path.display(), e))
+            })?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            path: Some(path),
+            names,
+        })
+    }
+
+    /// Passa a persistir o índice em `path`, juntando os nomes salvos lá
+    /// aos reservados enquanto o índice estava só em memória
+    pub fn attach(&mut self, path: PathBuf) -> Result<()> {
+        let mut saved = Self::load(path)?;
+        for (name, id) in std::mem::take(&mut self.names) {
+            if !saved.names.values().any(|owner| *owner == id) {
+                saved.insert_unsaved(&name, &id)?;
+            }
+        }
+        saved.save()?;
+        *self = saved;
+        Ok(())
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ContainerId> {
+        self.names.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains_key(name)
+    }
+
+    /// Reserva `name` para o container; falha se outro já o usa
+    pub fn insert(&mut self, name: &str, id: &ContainerId) -> Result<()> {
+        self.insert_unsaved(name, id)?;
+        self.save()
+    }
+
+    fn insert_unsaved(&mut self, name: &str, id: &ContainerId) -> Result<()> {
+        if let Some(owner) = self.names.get(name) {
+            return Err(PolisError::Container(format!(
+                "Nome '{}' já está em uso pelo container {}",
+                name, owner.0
+            )));
+        }
+        self.names.insert(name.to_string(), id.clone());
+        Ok(())
+    }
+
+    /// Libera o nome do container
+    pub fn remove(&mut self, id: &ContainerId) -> Result<()> {
+        let before = self.names.len();
+        self.names.retain(|_, owner| owner != id);
+        if self.names.len() != before {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Nome livre no estilo `adjetivo_sobrenome`, escolhido a partir do ID;
+    /// em caso de colisão recebe um sufixo numérico
+    pub fn generate_name(&self, id: &ContainerId) -> String {
+        let bytes = id.0.as_bytes();
+        let base = format!(
+            "{}_{}",
+            ADJECTIVES[bytes[0] as usize % ADJECTIVES.len()],
+            SURNAMES[bytes[1] as usize % SURNAMES.len()]
+        );
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while self.contains(&name) {
+            name = format!("{}{}", base, suffix);
+            suffix += 1;
+        }
+        name
+    }
+
+    /// Resolve um nome, um ID completo ou um prefixo de ID
+    pub fn resolve(&self, reference: &str) -> Result<ContainerId> {
+        resolve_container(
+            reference,
+            self.names.iter().map(|(name, id)| (name.as_str(), id)),
+        )
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(&self.names)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// Encontra o container referenciado entre `(nome, ID)`: um ID completo ou
+/// um nome valem antes de prefixos; um prefixo que casa com vários IDs é
+/// [`PolisError::Ambiguous`]
+pub fn resolve_container<'a>(
+    reference: &str,
+    containers: impl IntoIterator<Item = (&'a str, &'a ContainerId)>,
+) -> Result<ContainerId> {
+    let containers: Vec<_> = containers.into_iter().collect();
+    if let Some((_, id)) = containers
+        .iter()
+        .find(|(name, id)| id.0.to_string() == reference || *name == reference)
+    {
+        return Ok((*id).clone());
+    }
+
+    let mut matches: Vec<&ContainerId> = Vec::new();
+    if !reference.is_empty() {
+        for (_, id) in &containers {
+            if id.0.to_string().starts_with(reference) && !matches.contains(id) {
+                matches.push(id);
+            }
+        }
+    }
+    match matches.as_slice() {
+        [id] => Ok((*id).clone()),
+        [] => Err(PolisError::Container(format!(
+            "Container '{}' não encontrado",
+            reference
+        ))),
+        _ => Err(PolisError::Ambiguous {
+            reference: reference.to_string(),
+            matches: matches.iter().map(|id| id.0.to_string()).collect(),
+        }),
+    }
+}
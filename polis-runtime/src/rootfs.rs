@@ -0,0 +1,308 @@
+use polis_core::{
+    Container, PolisError, Result, VolumeMode, VolumeMount, WritablePath, WritableSource,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Entrada da tabela de montagens planejada para um container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MountEntry {
+    pub destination: PathBuf,
+    pub source: String,
+    pub fs_type: String,
+    pub options: Vec<String>,
+}
+
+/// Planeja a tabela de montagens do container: primeiro o rootfs (somente
+/// leitura quando configurado), depois os caminhos graváveis e por fim os volumes.
+pub fn plan_mounts(container: &Container, rootfs_path: &Path) -> Result<Vec<MountEntry>> {
+    // Um padrão sob (ou sobre) um volume dá lugar a ele; só os caminhos
+    // declarados são validados contra os volumes
+    let writable_paths: Vec<WritablePath> = container
+        .rootfs
+        .effective_writable_paths()
+        .into_iter()
+        .filter(|path| {
+            container.rootfs.writable_paths.contains(path)
+                || overlapping_volume(container, &path.destination).is_none()
+        })
+        .collect();
+    validate_writable_paths(container, &writable_paths)?;
+    validate_working_dir(container, &writable_paths)?;
+
+    let root_mode = if container.rootfs.read_only {
+        "ro"
+    } else {
+        "rw"
+    };
+    let mut mounts = vec![MountEntry {
+        destination: PathBuf::from("/"),
+        source: rootfs_path.display().to_string(),
+        fs_type: "overlay".to_string(),
+        options: vec![root_mode.to_string()],
+    }];
+
+    // Diretórios pais devem ser montados antes dos filhos
+    let mut overlays = writable_paths;
+    overlays.sort_by_key(|p| p.destination.components().count());
+
+    for path in overlays {
+        mounts.push(match path.source {
+            WritableSource::Tmpfs { size } => {
+                let mut options = vec![
+                    "rw".to_string(),
+                    "nosuid".to_string(),
+                    "nodev".to_string(),
+                    "mode=1777".to_string(),
+                ];
+                if let Some(size) = size {
+                    options.push(format!("size={}", size));
+                }
+                MountEntry {
+                    destination: path.destination,
+                    source: "tmpfs".to_string(),
+                    fs_type: "tmpfs".to_string(),
+                    options,
+                }
+            }
+            WritableSource::Bind(source) => MountEntry {
+                destination: path.destination,
+                source,
+                fs_type: "bind".to_string(),
+                options: vec!["rbind".to_string(), "rw".to_string()],
+            },
+        });
+    }
+
+    for volume in &container.volumes {
+        let access = if volume.read_only { "ro" } else { "rw" }.to_string();
+        mounts.push(match volume.mode {
+            VolumeMode::Tmpfs => MountEntry {
+                destination: volume.destination.clone(),
+                source: "tmpfs".to_string(),
+                fs_type: "tmpfs".to_string(),
+                options: vec![access, "nosuid".to_string(), "nodev".to_string()],
+            },
+            VolumeMode::Bind | VolumeMode::Volume => MountEntry {
+                destination: volume.destination.clone(),
+                source: volume.source.clone(),
+                fs_type: "bind".to_string(),
+                options: vec!["rbind".to_string(), access],
+            },
+        });
+    }
+
+    Ok(mounts)
+}
+
+/// Executa as montagens da tabela planejada
+pub trait MountBackend: Send + Sync {
+    /// Monta `entry` sob `root`, o diretório do rootfs no host
+    fn mount(&self, root: &Path, entry: &MountEntry) -> Result<()>;
+    /// Desfaz a montagem de `entry`
+    fn unmount(&self, root: &Path, entry: &MountEntry) -> Result<()>;
+}
+
+/// Aplica a tabela na ordem planejada. Os pontos de montagem são criados
+/// antes, enquanto o rootfs ainda aceita escrita; se uma montagem falha, as
+/// anteriores são desfeitas.
+pub fn apply_mounts(backend: &dyn MountBackend, root: &Path, mounts: &[MountEntry]) -> Result<()> {
+    for entry in mounts {
+        std::fs::create_dir_all(mount_target(root, &entry.destination))?;
+    }
+    for (index, entry) in mounts.iter().enumerate() {
+        if let Err(e) = backend.mount(root, entry) {
+            let _ = release_mounts(backend, root, &mounts[..index]);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Desmonta a tabela na ordem inversa, tentando todas as entradas; retorna
+/// o primeiro erro
+pub fn release_mounts(
+    backend: &dyn MountBackend,
+    root: &Path,
+    mounts: &[MountEntry],
+) -> Result<()> {
+    let mut first_error = None;
+    for entry in mounts.iter().rev() {
+        if let Err(e) = backend.unmount(root, entry) {
+            warn!("Falha ao desmontar {}: {}", entry.destination.display(), e);
+            first_error.get_or_insert(e);
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Caminho no host onde `destination` aparece dentro do container
+pub fn mount_target(root: &Path, destination: &Path) -> PathBuf {
+    root.join(destination.strip_prefix("/").unwrap_or(destination))
+}
+
+/// Montagens feitas com mount(2) no namespace de montagem do runtime
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KernelMounts;
+
+impl MountBackend for KernelMounts {
+    fn mount(&self, root: &Path, entry: &MountEntry) -> Result<()> {
+        let target = mount_target(root, &entry.destination);
+        let mut flags: libc::c_ulong = 0;
+        let mut data = Vec::new();
+        for option in &entry.options {
+            match option.as_str() {
+                "ro" => flags |= libc::MS_RDONLY,
+                "rw" => {}
+                "nosuid" => flags |= libc::MS_NOSUID,
+                "nodev" => flags |= libc::MS_NODEV,
+                "rbind" => flags |= libc::MS_BIND | libc::MS_REC,
+                other => data.push(other),
+            }
+        }
+
+        // O rootfs já preparado pelo armazenamento é montado sobre si mesmo
+        // para poder ser remontado somente leitura
+        let (source, fs_type) = if entry.destination == Path::new("/") {
+            flags |= libc::MS_BIND | libc::MS_REC;
+            (entry.source.as_str(), "none")
+        } else if entry.fs_type == "bind" {
+            (entry.source.as_str(), "none")
+        } else {
+            (entry.source.as_str(), entry.fs_type.as_str())
+        };
+        sys_mount(source, &target, fs_type, flags, &data.join(","))?;
+
+        // Num bind o kernel ignora MS_RDONLY: o modo vem de um remount
+        if flags & libc::MS_BIND != 0 && flags & libc::MS_RDONLY != 0 {
+            let remount = libc::MS_REMOUNT | libc::MS_BIND | (flags & !libc::MS_REC);
+            sys_mount("none", &target, "none", remount, "")?;
+        }
+        Ok(())
+    }
+
+    fn unmount(&self, root: &Path, entry: &MountEntry) -> Result<()> {
+        let target = c_path(&mount_target(root, &entry.destination))?;
+        if unsafe { libc::umount2(target.as_ptr(), libc::MNT_DETACH) } != 0 {
+            return Err(PolisError::Runtime(format!(
+                "Erro ao desmontar {}: {}",
+                entry.destination.display(),
+                std::io::Error::last_os_error()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn sys_mount(
+    source: &str,
+    target: &Path,
+    fs_type: &str,
+    flags: libc::c_ulong,
+    data: &str,
+) -> Result<()> {
+    let to_c = |value: &str| {
+        std::ffi::CString::new(value)
+            .map_err(|_| PolisError::Runtime(format!("Opção de montagem inválida: {}", value)))
+    };
+    let source = to_c(source)?;
+    let fs_type = to_c(fs_type)?;
+    let data = to_c(data)?;
+    let target_c = c_path(target)?;
+    let data_ptr = if data.as_bytes().is_empty() {
+        std::ptr::null()
+    } else {
+        data.as_ptr() as *const libc::c_void
+    };
+    let result = unsafe {
+        libc::mount(
+            source.as_ptr(),
+            target_c.as_ptr(),
+            fs_type.as_ptr(),
+            flags,
+            data_ptr,
+        )
+    };
+    if result != 0 {
+        return Err(PolisError::Runtime(format!(
+            "Erro ao montar {}: {}",
+            target.display(),
+            std::io::Error::last_os_error()
+        )));
+    }
+    Ok(())
+}
+
+fn c_path(path: &Path) -> Result<std::ffi::CString> {
+    use std::os::unix::ffi::OsStrExt;
+
+    std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| PolisError::Runtime(format!("Caminho inválido: {}", path.display())))
+}
+
+fn validate_writable_paths(container: &Container, writable_paths: &[WritablePath]) -> Result<()> {
+    for (index, path) in writable_paths.iter().enumerate() {
+        if !path.destination.is_absolute() || path.destination == Path::new("/") {
+            return Err(PolisError::Container(format!(
+                "Caminho gravável inválido: {}",
+                path.destination.display()
+            )));
+        }
+
+        if writable_paths[..index]
+            .iter()
+            .any(|other| other.destination == path.destination)
+        {
+            return Err(PolisError::Container(format!(
+                "Caminho gravável declarado mais de uma vez: {}",
+                path.destination.display()
+            )));
+        }
+
+        if let Some(volume) = overlapping_volume(container, &path.destination) {
+            return Err(PolisError::Container(format!(
+                "Caminho gravável {} conflita com o volume montado de {} em {}",
+                path.destination.display(),
+                volume.source,
+                volume.destination.display()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Volume montado em `destination`, acima ou abaixo dele: um dentro do
+/// outro, a montagem mais rasa esconderia a outra
+fn overlapping_volume<'a>(container: &'a Container, destination: &Path) -> Option<&'a VolumeMount> {
+    container.volumes.iter().find(|volume| {
+        volume.destination.starts_with(destination) || destination.starts_with(&volume.destination)
+    })
+}
+
+fn validate_working_dir(container: &Container, writable_paths: &[WritablePath]) -> Result<()> {
+    let working_dir = &container.working_dir;
+    if !working_dir.is_absolute() {
+        return Err(PolisError::Container(format!(
+            "Diretório de trabalho deve ser absoluto: {}",
+            working_dir.display()
+        )));
+    }
+
+    // Um tmpfs vazio esconde o conteúdo da imagem abaixo do ponto de montagem
+    for path in writable_paths {
+        let hidden = matches!(path.source, WritableSource::Tmpfs { .. })
+            && working_dir != &path.destination
+            && working_dir.starts_with(&path.destination);
+        if hidden {
+            return Err(PolisError::Container(format!(
+                "Diretório de trabalho {} ficaria inacessível sob o tmpfs em {}",
+                working_dir.display(),
+                path.destination.display()
+            )));
+        }
+    }
+
+    Ok(())
+}
@@ -1,15 +1,30 @@
-use crate::{ContainerManager, ProcessManager};
+use crate::{
+    apply_mounts, capture_output, plan_mounts, release_mounts, ContainerEvent, ContainerInspect,
+    ContainerManager, ContainerRecord, ContainerStore, DeadlineRecord, DeadlineTracker,
+    KernelMounts, MountBackend, MountEntry, NameIndex, ProcessIdentity, ProcessManager,
+    ProcessTable, TopColumn,
+};
 use async_trait::async_trait;
-use chrono::Utc;
 use polis_core::{
-    log_container_created, log_container_removed, log_container_started, log_container_stopped,
-    Container, ContainerId, ContainerStatus, ImageId, NetworkMode, PolisConfig, PolisError,
-    ResourceLimits, Result,
+    container_log_dir, log_container_created, log_container_removed, log_container_started,
+    log_container_stopped, Clock, Container, ContainerAction, ContainerId, ContainerStatus,
+    EgressPolicy, EventBus, EventKind, ImageId, NetworkMode, PolisConfig, PolisError, PortMapping,
+    ProcReader, Protocol, ResourceLimits, Result, RootfsConfig, RotatingLogWriter, StopReason,
+    SystemClock, VolumeMount, CONTAINER_LOG_FILE,
 };
+use polis_network::port_forwarding::Protocol as ForwardProtocol;
+use polis_network::{IpamManager, PortForwardingManager};
+use polis_security::{CgroupManager, Sandbox};
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::io::AsyncRead;
+use tokio::sync::{broadcast, Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tracing::warn;
 
 #[async_trait]
 pub trait ContainerRuntime {
@@ -26,6 +41,32 @@ pub trait ContainerRuntime {
     async fn get_container(&self, id: ContainerId) -> Result<Container>;
     async fn pause_container(&self, id: ContainerId) -> Result<()>;
     async fn unpause_container(&self, id: ContainerId) -> Result<()>;
+    async fn top(&self, id: ContainerId, columns: &[TopColumn]) -> Result<ProcessTable>;
+    async fn inspect_container(&self, id: ContainerId) -> Result<ContainerInspect>;
+    /// Resolve um nome, um ID completo ou um prefixo não ambíguo de ID
+    async fn resolve(&self, reference: &str) -> Result<ContainerId>;
+}
+
+/// Opções adicionais de criação de container
+#[derive(Debug, Clone, Default)]
+pub struct ContainerOptions {
+    pub working_dir: Option<PathBuf>,
+    pub environment: HashMap<String, String>,
+    pub labels: HashMap<String, String>,
+    pub volumes: Vec<VolumeMount>,
+    pub rootfs: RootfsConfig,
+    /// Tempo máximo de execução; o container é parado ao atingi-lo
+    pub max_runtime: Option<Duration>,
+    /// Política de saída aplicada pela rede do container
+    pub egress: Option<EgressPolicy>,
+    /// Executa o container sem root, mesmo com `rootless` desligado na
+    /// configuração
+    pub rootless: bool,
+    /// Limites de CPU, memória e processos aplicados pelo cgroup do
+    /// container
+    pub resource_limits: ResourceLimits,
+    /// Portas do host publicadas para o container
+    pub ports: Vec<PortMapping>,
 }
 
 pub struct PolisRuntime {
@@ -34,6 +75,38 @@ pub struct PolisRuntime {
     #[allow(dead_code)]
     container_manager: ContainerManager,
     process_manager: ProcessManager,
+    proc_root: PathBuf,
+    cgroup_root: PathBuf,
+    cgroup_mount: PathBuf,
+    /// Criado na primeira vez que um container com limites é iniciado
+    cgroups: Arc<Mutex<Option<CgroupManager>>>,
+    /// Criado com a subnet da configuração no primeiro container com portas
+    /// publicadas
+    ipam: Arc<Mutex<Option<IpamManager>>>,
+    port_forwarding: Arc<Mutex<PortForwardingManager>>,
+    /// Regras de port forwarding instaladas para cada container iniciado
+    published_ports: Arc<RwLock<HashMap<ContainerId, Vec<String>>>>,
+    clock: Arc<dyn Clock>,
+    deadlines: Arc<RwLock<DeadlineTracker>>,
+    /// Nomes únicos dos containers; só em memória, a menos que o estado seja
+    /// persistente
+    names: Arc<RwLock<NameIndex>>,
+    /// Metadados de cada container, regravados a cada mudança de estado
+    /// quando o estado é persistente
+    store: ContainerStore,
+    /// Processo inicial de cada container em execução
+    processes: Arc<RwLock<HashMap<ContainerId, ProcessIdentity>>>,
+    /// Grava o estado dos containers sob os diretórios da configuração a
+    /// partir de `initialize`
+    persistent: bool,
+    /// Ligado por `initialize` quando o estado é persistente
+    persisting: AtomicBool,
+    events: broadcast::Sender<ContainerEvent>,
+    event_bus: EventBus,
+    /// Sandboxes dos containers executados sem root
+    sandboxes: Arc<RwLock<HashMap<ContainerId, Sandbox>>>,
+    /// Monta o rootfs somente leitura e os caminhos graváveis
+    mounts: Arc<dyn MountBackend>,
 }
 
 impl PolisRuntime {
@@ -41,13 +114,325 @@ impl PolisRuntime {
         let containers = Arc::new(RwLock::new(HashMap::new()));
         let container_manager = ContainerManager::new(containers.clone());
         let process_manager = ProcessManager::new();
+        let deadlines = DeadlineTracker::new(config.runtime.root_dir.join("deadlines.json"));
+        let names = NameIndex::new();
+        let store = ContainerStore::new(config.storage.root_dir.join("containers"));
+        let (events, _) = broadcast::channel(256);
 
         Self {
             config,
             containers,
             container_manager,
             process_manager,
+            proc_root: PathBuf::from("/proc"),
+            cgroup_root: PathBuf::from("/sys/fs/cgroup/polis"),
+            cgroup_mount: PathBuf::from("/sys/fs/cgroup"),
+            cgroups: Arc::new(Mutex::new(None)),
+            ipam: Arc::new(Mutex::new(None)),
+            port_forwarding: Arc::new(Mutex::new(PortForwardingManager::new())),
+            published_ports: Arc::new(RwLock::new(HashMap::new())),
+            clock: Arc::new(SystemClock),
+            deadlines: Arc::new(RwLock::new(deadlines)),
+            names: Arc::new(RwLock::new(names)),
+            store,
+            processes: Arc::new(RwLock::new(HashMap::new())),
+            persistent: false,
+            persisting: AtomicBool::new(false),
+            events,
+            event_bus: EventBus::new(),
+            sandboxes: Arc::new(RwLock::new(HashMap::new())),
+            mounts: Arc::new(KernelMounts),
+        }
+    }
+
+    /// Publica o ciclo de vida dos containers no barramento de eventos
+    /// compartilhado
+    pub fn with_event_bus(mut self, event_bus: EventBus) -> Self {
+        self.event_bus = event_bus;
+        self
+    }
+
+    fn publish(
+        &self,
+        action: ContainerAction,
+        id: &ContainerId,
+        name: &str,
+        exit_code: Option<i32>,
+    ) {
+        self.event_bus.publish(
+            "runtime",
+            EventKind::Container {
+                action,
+                id: id.0.to_string(),
+                name: name.to_string(),
+                exit_code,
+            },
+        );
+    }
+
+    /// Mantém o estado dos containers entre execuções: `initialize` recarrega
+    /// o que foi salvo nos diretórios da configuração e passa a gravar lá
+    pub fn with_persistent_state(mut self) -> Self {
+        self.persistent = true;
+        self
+    }
+
+    /// Usa outro relógio (ex.: relógio manual em testes)
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Recebe os eventos de ciclo de vida dos containers
+    pub fn subscribe_events(&self) -> broadcast::Receiver<ContainerEvent> {
+        self.events.subscribe()
+    }
+
+    /// Usa outro diretório /proc (ex.: fixtures em testes)
+    pub fn with_proc_root(mut self, proc_root: impl Into<PathBuf>) -> Self {
+        self.proc_root = proc_root.into();
+        self
+    }
+
+    /// Usa outra raiz para os cgroups dos containers
+    pub fn with_cgroup_root(mut self, cgroup_root: impl Into<PathBuf>) -> Self {
+        self.cgroup_root = cgroup_root.into();
+        self
+    }
+
+    /// Detecta a versão dos cgroups em outro ponto de montagem; no v1 os
+    /// containers ficam em `<mount>/<controlador>/polis`
+    pub fn with_cgroup_mount(mut self, cgroup_mount: impl Into<PathBuf>) -> Self {
+        self.cgroup_mount = cgroup_mount.into();
+        self
+    }
+
+    /// Aloca os IPs dos containers no pool `default` de outro IPAM
+    pub fn with_ipam(mut self, ipam: IpamManager) -> Self {
+        self.ipam = Arc::new(Mutex::new(Some(ipam)));
+        self
+    }
+
+    /// Instala as portas publicadas através de outro gerenciador de port
+    /// forwarding
+    pub fn with_port_forwarding(mut self, port_forwarding: PortForwardingManager) -> Self {
+        self.port_forwarding = Arc::new(Mutex::new(port_forwarding));
+        self
+    }
+
+    /// Aplica as montagens através de outro backend
+    pub fn with_mount_backend(mut self, mounts: Arc<dyn MountBackend>) -> Self {
+        self.mounts = mounts;
+        self
+    }
+
+    /// Diretório do cgroup de um container
+    pub fn cgroup_path(&self, id: &ContainerId) -> PathBuf {
+        self.cgroup_root.join(id.0.to_string())
+    }
+
+    /// Cria o cgroup do container e aplica seus limites. Containers sem
+    /// limites continuam no cgroup do runtime.
+    async fn setup_cgroup(&self, container: &Container) -> Result<()> {
+        if container.resource_limits == ResourceLimits::default() {
+            return Ok(());
+        }
+
+        let mut cgroups = self.cgroups.lock().await;
+        if cgroups.is_none() {
+            *cgroups = CgroupManager::detect(&self.cgroup_mount, self.cgroup_root.clone());
+        }
+        let manager = cgroups.as_mut().ok_or_else(|| {
+            PolisError::Runtime(format!(
+                "Nenhuma hierarquia de cgroups montada em {}",
+                self.cgroup_mount.display()
+            ))
+        })?;
+        manager
+            .create_cgroup(
+                &container.id.0.to_string(),
+                container.resource_limits.clone(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Remove o cgroup criado para o container, se houver
+    async fn remove_cgroup(&self, id: &ContainerId) -> Result<()> {
+        let name = id.0.to_string();
+        let mut cgroups = self.cgroups.lock().await;
+        let Some(manager) = cgroups.as_mut() else {
+            return Ok(());
+        };
+        if manager.list_cgroups().await?.iter().any(|c| c.name == name) {
+            manager.delete_cgroup(&name).await?;
+        }
+        Ok(())
+    }
+
+    /// Desfaz o que `start_container` preparou quando o processo não chega a
+    /// ser iniciado: o prazo, as portas, as montagens e o cgroup
+    async fn rollback_start(&self, container: &Container) {
+        let id = &container.id;
+        if let Err(e) = self.deadlines.write().await.disarm(id) {
+            warn!("Falha ao desarmar o prazo do container {}: {}", id.0, e);
+        }
+        if let Err(e) = self.unpublish_ports(id).await {
+            warn!(
+                "Falha ao remover as portas publicadas do container {}: {}",
+                id.0, e
+            );
+        }
+        self.unmount_rootfs(container);
+        self.discard_cgroup(id).await;
+    }
+
+    /// Remove o cgroup de um start que falhou
+    async fn discard_cgroup(&self, id: &ContainerId) {
+        if let Err(e) = self.remove_cgroup(id).await {
+            warn!("Falha ao remover o cgroup do container {}: {}", id.0, e);
+        }
+    }
+
+    /// Rejeita portas do host repetidas na lista ou já publicadas por outro
+    /// container
+    async fn check_port_conflicts(&self, ports: &[PortMapping]) -> Result<()> {
+        let containers = self.containers.read().await;
+        let mut conflicts = Vec::new();
+        for (i, port) in ports.iter().enumerate() {
+            if ports[..i].iter().any(|other| other.conflicts_with(port)) {
+                return Err(PolisError::Container(format!(
+                    "Porta {}/{} do host publicada mais de uma vez",
+                    port.host_port, port.protocol
+                )));
+            }
+            for owner in containers.values() {
+                if owner.ports.iter().any(|other| other.conflicts_with(port)) {
+                    conflicts.push(format!(
+                        "{}/{} (container {}, {})",
+                        port.host_port, port.protocol, owner.name, owner.id.0
+                    ));
+                }
+            }
+        }
+
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(PolisError::Container(format!(
+                "Portas do host já publicadas: {}",
+                conflicts.join(", ")
+            )))
+        }
+    }
+
+    /// Aloca o IP do container (`ip`, se informado) e instala o port
+    /// forwarding das portas publicadas
+    async fn publish_ports(&self, container: &Container, ip: Option<IpAddr>) -> Result<()> {
+        if container.ports.is_empty() {
+            return Ok(());
+        }
+
+        let name = container.id.0.to_string();
+        let container_ip = {
+            let mut ipam = self.ipam.lock().await;
+            if ipam.is_none() {
+                let network = &self.config.network;
+                let (Some(subnet), Some(gateway)) = (&network.subnet, &network.gateway) else {
+                    return Err(PolisError::Network(
+                        "Nenhuma subnet configurada para os containers".to_string(),
+                    ));
+                };
+                let mut manager = IpamManager::new();
+                manager.create_pool("default", subnet, gateway).await?;
+                *ipam = Some(manager);
+            }
+            let ipam = ipam.as_mut().expect("IPAM inicializado acima");
+            match ip {
+                Some(ip) => ipam.allocate_specific_ip(&name, ip, None).await?.ip,
+                None => ipam.allocate_ip(&name, None).await?.ip,
+            }
+        };
+
+        let mut rule_ids = Vec::new();
+        let mut forwarding = self.port_forwarding.lock().await;
+        for port in &container.ports {
+            match self
+                .add_forwarding(&mut forwarding, container, port, container_ip)
+                .await
+            {
+                Ok(rule_id) => rule_ids.push(rule_id),
+                Err(e) => {
+                    // Desfaz as regras já instaladas e libera o IP
+                    for rule_id in &rule_ids {
+                        let _ = forwarding.remove_rule(rule_id).await;
+                    }
+                    if let Some(ipam) = self.ipam.lock().await.as_mut() {
+                        let _ = ipam.deallocate_ip(&name, None).await;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        self.published_ports
+            .write()
+            .await
+            .insert(container.id.clone(), rule_ids);
+        Ok(())
+    }
+
+    async fn add_forwarding(
+        &self,
+        forwarding: &mut PortForwardingManager,
+        container: &Container,
+        port: &PortMapping,
+        container_ip: IpAddr,
+    ) -> Result<String> {
+        let host_ip = port
+            .host_ip
+            .as_deref()
+            .unwrap_or("0.0.0.0")
+            .parse()
+            .map_err(|e| PolisError::Network(format!("IP do host inválido: {}", e)))?;
+        let protocol = match port.protocol {
+            Protocol::Tcp => ForwardProtocol::Tcp,
+            Protocol::Udp => ForwardProtocol::Udp,
+        };
+        forwarding
+            .add_rule(
+                host_ip,
+                port.host_port,
+                container_ip,
+                port.container_port,
+                protocol,
+                Some(format!("Container {}", container.name)),
+            )
+            .await
+    }
+
+    /// Remove o port forwarding do container e libera seu IP
+    async fn unpublish_ports(&self, id: &ContainerId) -> Result<()> {
+        let Some(rule_ids) = self.published_ports.write().await.remove(id) else {
+            return Ok(());
+        };
+
+        // Remove o máximo possível mesmo se uma regra falhar
+        let mut result = Ok(());
+        {
+            let mut forwarding = self.port_forwarding.lock().await;
+            for rule_id in rule_ids {
+                if let Err(e) = forwarding.remove_rule(&rule_id).await {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+            }
+        }
+        if let Some(ipam) = self.ipam.lock().await.as_mut() {
+            ipam.deallocate_ip(&id.0.to_string(), None).await?;
         }
+        result
     }
 
     pub async fn initialize(&self) -> Result<()> {
@@ -62,50 +447,405 @@ impl PolisRuntime {
         )
         .init();
 
+        // Recarregar prazos de execução de uma execução anterior
+        let tracker = DeadlineTracker::load(self.config.runtime.root_dir.join("deadlines.json"))?;
+        *self.deadlines.write().await = tracker;
+        if !self.persistent {
+            return Ok(());
+        }
+        self.names
+            .write()
+            .await
+            .attach(self.config.runtime.root_dir.join("names.json"))?;
+
+        // Recarregar os containers. Os que rodavam voltam a ser acompanhados
+        // se o processo ainda existe; os demais terminaram com o daemon parado.
+        let reader = ProcReader::with_root(&self.proc_root);
+        for record in self.store.load_all()? {
+            let mut container = record.container;
+            let id = container.id.clone();
+            if matches!(
+                container.status,
+                ContainerStatus::Running | ContainerStatus::Paused
+            ) {
+                // O horário de início evita confundir o processo com outro
+                // que recebeu o mesmo PID
+                let alive = record.process.filter(|process| {
+                    reader
+                        .read_process(process.pid)
+                        .ok()
+                        .flatten()
+                        .is_some_and(|entry| entry.start_time == process.start_time)
+                });
+                match alive {
+                    Some(process) => {
+                        self.processes.write().await.insert(id.clone(), process);
+                        if let Err(e) = self.publish_ports(&container, record.ip_address).await {
+                            warn!(
+                                "Falha ao publicar novamente as portas do container {}: {}",
+                                id.0, e
+                            );
+                        }
+                    }
+                    None => {
+                        container.status = ContainerStatus::Exited;
+                        container.finished_at = Some(self.clock.now());
+                    }
+                }
+            }
+            self.containers.write().await.insert(id, container);
+        }
+
+        // Gravar também os containers criados antes da inicialização
+        self.persisting.store(true, Ordering::SeqCst);
+        let containers: Vec<Container> = self.containers.read().await.values().cloned().collect();
+        for container in &containers {
+            self.persist(container).await?;
+        }
+
         Ok(())
     }
-}
 
-#[async_trait]
-impl ContainerRuntime for PolisRuntime {
-    async fn create_container(
+    /// Diretório com os metadados persistidos dos containers
+    pub fn container_store(&self) -> &ContainerStore {
+        &self.store
+    }
+
+    /// Grava os metadados atuais do container
+    async fn persist(&self, container: &Container) -> Result<()> {
+        if !self.persisting.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let process = self.processes.read().await.get(&container.id).copied();
+        let ip_address = match self.ipam.lock().await.as_ref() {
+            Some(ipam) => ipam
+                .get_allocation(&container.id.0.to_string(), None)
+                .await
+                .ok()
+                .flatten()
+                .map(|allocation| allocation.ip),
+            None => None,
+        };
+        self.store.save(&ContainerRecord {
+            container: container.clone(),
+            process,
+            ip_address,
+        })
+    }
+
+    /// Processo inicial do container: o PID 1 do seu cgroup ou, sem ele, o
+    /// processo criado; `None` se não aparece no /proc
+    fn process_identity(&self, id: &ContainerId, spawned_pid: u32) -> Option<ProcessIdentity> {
+        let reader = ProcReader::with_root(&self.proc_root);
+        reader
+            .list_cgroup_processes(&self.cgroup_path(id))
+            .ok()
+            .and_then(|processes| {
+                processes
+                    .into_iter()
+                    .find(|process| process.container_pid == 1)
+            })
+            .or_else(|| reader.read_process(spawned_pid).ok().flatten())
+            .map(|process| ProcessIdentity {
+                pid: process.host_pid,
+                start_time: process.start_time,
+            })
+    }
+
+    /// Prazo de execução registrado para o container
+    pub async fn deadline(&self, id: &ContainerId) -> Option<DeadlineRecord> {
+        self.deadlines.read().await.get(id).cloned()
+    }
+
+    /// Tempo restante até o prazo do container
+    pub async fn remaining_runtime(&self, id: &ContainerId) -> Option<Duration> {
+        let now = self.clock.now();
+        self.deadline(id).await.map(|record| record.remaining(now))
+    }
+
+    /// Emite avisos de prazo próximo e para os containers cujo prazo expirou.
+    /// Retorna os containers parados.
+    pub async fn enforce_deadlines(&self) -> Result<Vec<ContainerId>> {
+        let now = self.clock.now();
+        let warning = Duration::from_secs(self.config.runtime.deadline_warning);
+        let due = self.deadlines.write().await.due(now, warning)?;
+
+        for record in due.warnings {
+            let remaining = record.remaining(now);
+            warn!(
+                "Container {} será parado em {}s por prazo de execução",
+                record.container_id,
+                remaining.as_secs()
+            );
+            let _ = self.events.send(ContainerEvent::DeadlineWarning {
+                id: record.container_id,
+                deadline: record.deadline,
+                remaining,
+            });
+        }
+
+        let mut stopped = Vec::new();
+        for record in due.expired {
+            let id = record.container_id;
+            let running = matches!(
+                self.containers.read().await.get(&id).map(|c| &c.status),
+                Some(ContainerStatus::Running | ContainerStatus::Paused)
+            );
+
+            if running {
+                self.stop_with_reason(id.clone(), StopReason::DeadlineExceeded)
+                    .await?;
+                stopped.push(id);
+            } else {
+                // Container já parado ou desconhecido após um reinício
+                self.deadlines.write().await.disarm(&id)?;
+            }
+        }
+
+        Ok(stopped)
+    }
+
+    /// Registra que o processo do container foi encerrado pelo OOM killer
+    pub async fn report_oom_kill(&self, id: ContainerId) -> Result<()> {
+        self.stop_with_reason(id, StopReason::OomKilled).await
+    }
+
+    /// Verifica os prazos periodicamente até a task ser cancelada
+    pub async fn run_deadline_monitor(&self, interval: Duration) -> Result<()> {
+        loop {
+            self.enforce_deadlines().await?;
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn stop_with_reason(&self, id: ContainerId, reason: StopReason) -> Result<()> {
+        let mut container = {
+            let mut containers = self.containers.write().await;
+            containers
+                .get_mut(&id)
+                .ok_or_else(|| PolisError::Container("Container não encontrado".to_string()))?
+                .clone()
+        };
+
+        if !matches!(
+            container.status,
+            ContainerStatus::Running | ContainerStatus::Paused
+        ) {
+            return Err(PolisError::Container(
+                "Container não está rodando".to_string(),
+            ));
+        }
+
+        // Parar processo (SIGTERM e SIGKILL após o período de carência)
+        self.process_manager.kill(123).await?; // Simular PID
+        if let Err(e) = self.unpublish_ports(&id).await {
+            warn!(
+                "Falha ao remover as portas publicadas do container {}: {}",
+                id.0, e
+            );
+        }
+        self.unmount_rootfs(&container);
+
+        // Atualizar status
+        container.status = ContainerStatus::Stopped;
+        container.finished_at = Some(self.clock.now());
+        // 128 + SIGKILL, como o shell reporta processos mortos pelo kernel
+        let exit_code = if reason == StopReason::OomKilled {
+            137
+        } else {
+            0
+        };
+        container.exit_code = Some(exit_code);
+        container.stop_reason = Some(reason);
+
+        // Atualizar container no storage
+        let container_name = container.name.clone();
+        self.processes.write().await.remove(&id);
+        self.persist(&container).await?;
+        {
+            let mut containers = self.containers.write().await;
+            containers.insert(id.clone(), container);
+        }
+        self.deadlines.write().await.disarm(&id)?;
+
+        let _ = self.events.send(ContainerEvent::Stopped {
+            id: id.clone(),
+            exit_code: Some(exit_code),
+            reason,
+        });
+        let action = if reason == StopReason::OomKilled {
+            ContainerAction::OomKilled
+        } else {
+            ContainerAction::Stopped
+        };
+        self.publish(action, &id, &container_name, Some(exit_code));
+        log_container_stopped(&id.0.to_string(), &container_name, Some(exit_code));
+        Ok(())
+    }
+
+    /// Monta a tabela planejada para containers com rootfs somente leitura;
+    /// os demais usam o diretório do rootfs como está
+    fn mount_rootfs(&self, container: &Container) -> Result<()> {
+        if !container.rootfs.read_only {
+            return Ok(());
+        }
+        let root = self.rootfs_path(&container.id);
+        let mounts = plan_mounts(container, &root)?;
+        apply_mounts(self.mounts.as_ref(), &root, &mounts)
+    }
+
+    /// Desfaz as montagens de `mount_rootfs`
+    fn unmount_rootfs(&self, container: &Container) {
+        if !container.rootfs.read_only {
+            return;
+        }
+        let root = self.rootfs_path(&container.id);
+        let released = plan_mounts(container, &root)
+            .and_then(|mounts| release_mounts(self.mounts.as_ref(), &root, &mounts));
+        if let Err(e) = released {
+            warn!(
+                "Falha ao desmontar o rootfs do container {}: {}",
+                container.id.0, e
+            );
+        }
+    }
+
+    /// Diretório do rootfs (overlay) de um container
+    pub fn rootfs_path(&self, id: &ContainerId) -> PathBuf {
+        self.config
+            .runtime
+            .root_dir
+            .join("containers")
+            .join(id.0.to_string())
+            .join("rootfs")
+    }
+
+    /// Arquivo de log atual com a saída de um container
+    pub fn log_path(&self, id: &ContainerId) -> PathBuf {
+        container_log_dir(&self.config.runtime.root_dir, id).join(CONTAINER_LOG_FILE)
+    }
+
+    /// Grava stdout e stderr do processo do container em arquivos de log
+    /// rotacionados conforme `runtime.log_rotation`
+    pub async fn capture_output<O, E>(
+        &self,
+        id: &ContainerId,
+        stdout: O,
+        stderr: E,
+    ) -> Result<JoinHandle<Result<()>>>
+    where
+        O: AsyncRead + Unpin + Send + 'static,
+        E: AsyncRead + Unpin + Send + 'static,
+    {
+        self.get_container(id.clone()).await?;
+        let writer = RotatingLogWriter::open(self.log_path(id), self.config.runtime.log_rotation)?;
+        let clock = self.clock.clone();
+        Ok(tokio::spawn(capture_output(writer, clock, stdout, stderr)))
+    }
+
+    pub async fn create_container_with_options(
         &self,
         name: String,
         image: String,
         command: Vec<String>,
+        options: ContainerOptions,
     ) -> Result<ContainerId> {
+        self.check_port_conflicts(&options.ports).await?;
+
         let container_id = ContainerId::new();
+        let name = if name.is_empty() {
+            self.names.read().await.generate_name(&container_id)
+        } else {
+            name
+        };
         let image_id = ImageId::from_string(&image);
+        let sandbox = if options.rootless || self.config.rootless {
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            Some(Sandbox::new_rootless(uid, gid)?)
+        } else {
+            None
+        };
 
         let container = Container {
             id: container_id.clone(),
             name: name.clone(),
             image: image_id,
             status: ContainerStatus::Created,
-            created_at: Utc::now(),
+            created_at: self.clock.now(),
             started_at: None,
             finished_at: None,
             exit_code: None,
             command,
-            working_dir: PathBuf::from("/"),
-            environment: HashMap::new(),
-            labels: HashMap::new(),
-            resource_limits: ResourceLimits::default(),
+            working_dir: options.working_dir.unwrap_or_else(|| PathBuf::from("/")),
+            environment: options.environment,
+            labels: options.labels,
+            resource_limits: options.resource_limits,
             network_mode: NetworkMode::default(),
-            ports: Vec::new(),
-            volumes: Vec::new(),
+            ports: options.ports,
+            volumes: options.volumes,
+            rootfs: options.rootfs,
+            max_runtime: options.max_runtime,
+            deadline: None,
+            stop_reason: None,
+            egress: options.egress,
         };
 
+        // Validar a tabela de montagens antes de registrar o container
+        plan_mounts(&container, &self.rootfs_path(&container_id))?;
+        self.names.write().await.insert(&name, &container_id)?;
+        if let Err(e) = self.persist(&container).await {
+            let _ = self.names.write().await.remove(&container_id);
+            return Err(e);
+        }
+
         // Armazenar container
         {
             let mut containers = self.containers.write().await;
             containers.insert(container_id.clone(), container);
         }
+        if let Some(sandbox) = sandbox {
+            self.sandboxes
+                .write()
+                .await
+                .insert(container_id.clone(), sandbox);
+        }
 
+        self.publish(ContainerAction::Created, &container_id, &name, None);
         log_container_created(&container_id.0.to_string(), &name);
         Ok(container_id)
     }
 
+    /// Sandbox sem root do container; `None` para containers executados como
+    /// root
+    pub async fn rootless_sandbox(&self, id: &ContainerId) -> Option<Sandbox> {
+        self.sandboxes.read().await.get(id).cloned()
+    }
+
+    /// Tabela de montagens planejada para o container
+    pub async fn get_mount_plan(&self, id: &ContainerId) -> Result<Vec<MountEntry>> {
+        let container = self.get_container(id.clone()).await?;
+        plan_mounts(&container, &self.rootfs_path(id))
+    }
+
+    /// Especificação OCI gerada para o container
+    pub async fn get_oci_spec(&self, id: &ContainerId) -> Result<oci_spec::runtime::Spec> {
+        let container = self.get_container(id.clone()).await?;
+        crate::generate_oci_spec(&container, &self.rootfs_path(id))
+    }
+}
+
+#[async_trait]
+impl ContainerRuntime for PolisRuntime {
+    async fn create_container(
+        &self,
+        name: String,
+        image: String,
+        command: Vec<String>,
+    ) -> Result<ContainerId> {
+        self.create_container_with_options(name, image, command, ContainerOptions::default())
+            .await
+    }
+
     async fn start_container(&self, id: ContainerId) -> Result<()> {
         let mut container = {
             let mut containers = self.containers.write().await;
@@ -121,59 +861,76 @@ impl ContainerRuntime for PolisRuntime {
             ));
         }
 
+        // O cgroup e o rootfs precisam estar prontos antes do processo do
+        // container
+        self.setup_cgroup(&container).await?;
+        if let Err(e) = self.mount_rootfs(&container) {
+            self.discard_cgroup(&id).await;
+            return Err(e);
+        }
+        if let Err(e) = self.publish_ports(&container, None).await {
+            self.unmount_rootfs(&container);
+            self.discard_cgroup(&id).await;
+            return Err(e);
+        }
+
         // Atualizar status
+        let started_at = self.clock.now();
         container.status = ContainerStatus::Running;
-        container.started_at = Some(Utc::now());
+        container.started_at = Some(started_at);
+        container.stop_reason = None;
+
+        // O prazo conta a partir do início e é persistido
+        if let Some(max_runtime) = container.max_runtime {
+            let armed = self
+                .deadlines
+                .write()
+                .await
+                .arm(&id, max_runtime, started_at);
+            match armed {
+                Ok(record) => container.deadline = Some(record.deadline),
+                Err(e) => {
+                    self.rollback_start(&container).await;
+                    return Err(e);
+                }
+            }
+        }
 
         // Simular execução do processo
-        let _process_id = self
+        let spawned = self
             .process_manager
             .spawn(container.command.clone(), container.environment.clone())
-            .await?;
+            .await;
+        let pid = match spawned {
+            Ok(pid) => pid,
+            Err(e) => {
+                self.rollback_start(&container).await;
+                return Err(e);
+            }
+        };
+        if let Some(process) = self.process_identity(&id, pid) {
+            self.processes.write().await.insert(id.clone(), process);
+        }
 
         // Atualizar container no storage
         let container_name = container.name.clone();
+        self.persist(&container).await?;
         {
             let mut containers = self.containers.write().await;
             containers.insert(id.clone(), container);
         }
 
+        let _ = self.events.send(ContainerEvent::Started {
+            id: id.clone(),
+            deadline: self.deadline(&id).await.map(|r| r.deadline),
+        });
+        self.publish(ContainerAction::Started, &id, &container_name, None);
         log_container_started(&id.0.to_string(), &container_name);
         Ok(())
     }
 
     async fn stop_container(&self, id: ContainerId) -> Result<()> {
-        let mut container = {
-            let mut containers = self.containers.write().await;
-            containers
-                .get_mut(&id)
-                .ok_or_else(|| PolisError::Container("Container não encontrado".to_string()))?
-                .clone()
-        };
-
-        if container.status != ContainerStatus::Running {
-            return Err(PolisError::Container(
-                "Container não está rodando".to_string(),
-            ));
-        }
-
-        // Parar processo
-        self.process_manager.kill(123).await?; // Simular PID
-
-        // Atualizar status
-        container.status = ContainerStatus::Stopped;
-        container.finished_at = Some(Utc::now());
-        container.exit_code = Some(0);
-
-        // Atualizar container no storage
-        let container_name = container.name.clone();
-        {
-            let mut containers = self.containers.write().await;
-            containers.insert(id.clone(), container);
-        }
-
-        log_container_stopped(&id.0.to_string(), &container_name, Some(0));
-        Ok(())
+        self.stop_with_reason(id, StopReason::Requested).await
     }
 
     async fn remove_container(&self, id: ContainerId) -> Result<()> {
@@ -190,6 +947,27 @@ impl ContainerRuntime for PolisRuntime {
             ));
         }
 
+        self.sandboxes.write().await.remove(&id);
+        if let Err(e) = self.names.write().await.remove(&id) {
+            warn!("Falha ao liberar o nome do container {}: {}", id.0, e);
+        }
+        if let Err(e) = self.unpublish_ports(&id).await {
+            warn!(
+                "Falha ao remover as portas publicadas do container {}: {}",
+                id.0, e
+            );
+        }
+        if let Err(e) = self.remove_cgroup(&id).await {
+            warn!("Falha ao remover o cgroup do container {}: {}", id.0, e);
+        }
+        self.processes.write().await.remove(&id);
+        self.store.remove(&id)?;
+        let log_dir = container_log_dir(&self.config.runtime.root_dir, &id);
+        if log_dir.exists() {
+            tokio::fs::remove_dir_all(&log_dir).await?;
+        }
+
+        self.publish(ContainerAction::Removed, &id, &container.name, None);
         log_container_removed(&id.0.to_string(), &container.name);
         Ok(())
     }
@@ -223,6 +1001,7 @@ impl ContainerRuntime for PolisRuntime {
         }
 
         container.status = ContainerStatus::Paused;
+        self.persist(&container).await?;
 
         {
             let mut containers = self.containers.write().await;
@@ -248,6 +1027,7 @@ impl ContainerRuntime for PolisRuntime {
         }
 
         container.status = ContainerStatus::Running;
+        self.persist(&container).await?;
 
         {
             let mut containers = self.containers.write().await;
@@ -256,4 +1036,59 @@ impl ContainerRuntime for PolisRuntime {
 
         Ok(())
     }
+
+    async fn top(&self, id: ContainerId, columns: &[TopColumn]) -> Result<ProcessTable> {
+        let container = self.get_container(id.clone()).await?;
+        if !matches!(
+            container.status,
+            ContainerStatus::Running | ContainerStatus::Paused
+        ) {
+            return Err(PolisError::Container(
+                "Container não está rodando".to_string(),
+            ));
+        }
+
+        let reader = ProcReader::with_root(&self.proc_root)
+            .with_passwd(&self.rootfs_path(&id).join("etc/passwd"));
+        let processes = reader.list_cgroup_processes(&self.cgroup_path(&id))?;
+
+        Ok(ProcessTable::new(columns, processes))
+    }
+
+    async fn inspect_container(&self, id: ContainerId) -> Result<ContainerInspect> {
+        let container = self.get_container(id.clone()).await?;
+        let running = matches!(
+            container.status,
+            ContainerStatus::Running | ContainerStatus::Paused
+        );
+        let mounts = plan_mounts(&container, &self.rootfs_path(&id))?;
+        let mut inspect = ContainerInspect::from_container(container);
+        inspect.mounts = mounts;
+
+        // Sem processos no cgroup (ou sem /proc legível) o PID fica desconhecido
+        if running {
+            inspect.state.pid = ProcReader::with_root(&self.proc_root)
+                .list_cgroup_processes(&self.cgroup_path(&id))
+                .ok()
+                .and_then(|processes| {
+                    processes
+                        .into_iter()
+                        .find(|process| process.container_pid == 1)
+                })
+                .map(|process| process.host_pid);
+        }
+        if let Some(ipam) = self.ipam.lock().await.as_ref() {
+            inspect.network.ip_address = ipam
+                .get_allocation(&id.0.to_string(), None)
+                .await
+                .ok()
+                .flatten()
+                .map(|allocation| allocation.ip);
+        }
+        Ok(inspect)
+    }
+
+    async fn resolve(&self, reference: &str) -> Result<ContainerId> {
+        self.names.read().await.resolve(reference)
+    }
 }
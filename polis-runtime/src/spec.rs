@@ -0,0 +1,50 @@
+use crate::rootfs::plan_mounts;
+use oci_spec::runtime::{MountBuilder, RootBuilder, Spec};
+use polis_core::{Container, PolisError, Result};
+use std::path::Path;
+
+/// Gera a especificação OCI (config.json) do container
+pub fn generate_oci_spec(container: &Container, rootfs_path: &Path) -> Result<Spec> {
+    let planned = plan_mounts(container, rootfs_path)?;
+    let mut spec = Spec::default();
+
+    let root = RootBuilder::default()
+        .path(rootfs_path)
+        .readonly(container.rootfs.read_only)
+        .build()
+        .map_err(oci_error)?;
+    spec.set_root(Some(root));
+
+    // O rootfs é representado por `root`; as demais entradas viram montagens
+    let mut mounts = spec.mounts().clone().unwrap_or_default();
+    for entry in planned.into_iter().skip(1) {
+        let mount = MountBuilder::default()
+            .destination(entry.destination)
+            .typ(entry.fs_type)
+            .source(entry.source)
+            .options(entry.options)
+            .build()
+            .map_err(oci_error)?;
+        mounts.push(mount);
+    }
+    spec.set_mounts(Some(mounts));
+
+    let mut process = spec.process().clone().unwrap_or_default();
+    process.set_args(Some(container.command.clone()));
+    process.set_cwd(container.working_dir.clone());
+    process.set_env(Some(
+        container
+            .environment
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect(),
+    ));
+    spec.set_process(Some(process));
+    spec.set_hostname(Some(container.name.clone()));
+
+    Ok(spec)
+}
+
+fn oci_error(e: oci_spec::OciSpecError) -> PolisError {
+    PolisError::Runtime(format!("Erro ao gerar spec OCI: {}", e))
+}
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use polis_core::{Container, ContainerId, PolisError, Result};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Processo inicial de um container. O horário de início distingue o
+/// processo original de outro que reutilizou o mesmo PID.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ProcessIdentity {
+    pub pid: u32,
+    pub start_time: DateTime<Utc>,
+}
+
+/// Metadados persistidos de um container: a especificação e o estado, mais o
+/// necessário para reencontrar processo e rede após um reinício do daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerRecord {
+    pub container: Container,
+    pub process: Option<ProcessIdentity>,
+    /// IP alocado pelo IPAM enquanto as portas estão publicadas
+    pub ip_address: Option<IpAddr>,
+}
+
+/// Diretório com um `<id>/config.json` por container
+#[derive(Debug, Clone)]
+pub struct ContainerStore {
+    root: PathBuf,
+}
+
+impl ContainerStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn config_path(&self, id: &ContainerId) -> PathBuf {
+        self.root.join(id.0.to_string()).join("config.json")
+    }
+
+    /// Grava os metadados atomicamente: um leitor vê o arquivo anterior ou o
+    /// novo, nunca um parcial
+    pub fn save(&self, record: &ContainerRecord) -> Result<()> {
+        let path = self.config_path(&record.container.id);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_vec_pretty(record)?)?;
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    /// Carrega todos os containers salvos; arquivos ilegíveis são ignorados
+    /// com um aviso para não impedir a inicialização
+    pub fn load_all(&self) -> Result<Vec<ContainerRecord>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut records = Vec::new();
+        for entry in std::fs::read_dir(&self.root)? {
+            let path = entry?.path().join("config.json");
+            if !path.exists() {
+                continue;
+            }
+            let parsed = std::fs::read(&path)
+                .map_err(PolisError::from)
+                .and_then(|content| Ok(serde_json::from_slice(&content)?));
+            match parsed {
+                Ok(record) => records.push(record),
+                Err(e) => warn!("Ignorando metadados de {}: {}", path.display(), e),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Remove o diretório do container
+    pub fn remove(&self, id: &ContainerId) -> Result<()> {
+        let dir = self.root.join(id.0.to_string());
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
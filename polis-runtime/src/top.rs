@@ -0,0 +1,169 @@
+use polis_core::{PolisError, ProcessEntry, Result};
+use serde::{Deserialize, Serialize};
+
+/// Coluna da listagem de processos (`polis container top -o ...`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TopColumn {
+    Pid,
+    HostPid,
+    User,
+    Uid,
+    State,
+    Threads,
+    Cpu,
+    Rss,
+    Start,
+    Comm,
+    Cmd,
+}
+
+/// Colunas exibidas quando nenhuma é especificada
+pub const DEFAULT_TOP_COLUMNS: &[TopColumn] = &[
+    TopColumn::Pid,
+    TopColumn::HostPid,
+    TopColumn::User,
+    TopColumn::Cpu,
+    TopColumn::Rss,
+    TopColumn::Start,
+    TopColumn::Cmd,
+];
+
+impl TopColumn {
+    /// Interpreta uma lista no estilo de `ps -o`, ex.: `pid,user,cmd`
+    pub fn parse_list(format: &str) -> Result<Vec<TopColumn>> {
+        format
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(TopColumn::parse)
+            .collect()
+    }
+
+    pub fn parse(name: &str) -> Result<TopColumn> {
+        match name.to_ascii_lowercase().as_str() {
+            "pid" => Ok(TopColumn::Pid),
+            "hostpid" | "host_pid" => Ok(TopColumn::HostPid),
+            "user" => Ok(TopColumn::User),
+            "uid" => Ok(TopColumn::Uid),
+            "state" | "stat" => Ok(TopColumn::State),
+            "threads" | "nlwp" => Ok(TopColumn::Threads),
+            "cpu" | "%cpu" | "pcpu" => Ok(TopColumn::Cpu),
+            "rss" => Ok(TopColumn::Rss),
+            "start" | "stime" => Ok(TopColumn::Start),
+            "comm" => Ok(TopColumn::Comm),
+            "cmd" | "args" | "command" => Ok(TopColumn::Cmd),
+            _ => Err(PolisError::Runtime(format!(
+                "Coluna desconhecida para top: {}",
+                name
+            ))),
+        }
+    }
+
+    pub fn header(&self) -> &'static str {
+        match self {
+            TopColumn::Pid => "PID",
+            TopColumn::HostPid => "HOST PID",
+            TopColumn::User => "USER",
+            TopColumn::Uid => "UID",
+            TopColumn::State => "S",
+            TopColumn::Threads => "THREADS",
+            TopColumn::Cpu => "%CPU",
+            TopColumn::Rss => "RSS",
+            TopColumn::Start => "START",
+            TopColumn::Comm => "COMM",
+            TopColumn::Cmd => "CMD",
+        }
+    }
+
+    pub fn value(&self, process: &ProcessEntry) -> String {
+        match self {
+            TopColumn::Pid => process.container_pid.to_string(),
+            TopColumn::HostPid => process.host_pid.to_string(),
+            TopColumn::User => process.user.clone(),
+            TopColumn::Uid => process
+                .uid
+                .map(|uid| uid.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            TopColumn::State => process.state.to_string(),
+            TopColumn::Threads => process.threads.to_string(),
+            TopColumn::Cpu => format!("{:.1}", process.cpu_percent),
+            TopColumn::Rss => (process.rss / 1024).to_string(),
+            TopColumn::Start => process.start_time.format("%H:%M:%S").to_string(),
+            TopColumn::Comm => process.comm.clone(),
+            TopColumn::Cmd => process.cmdline.clone(),
+        }
+    }
+}
+
+/// Tabela de processos de um container
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessTable {
+    pub columns: Vec<TopColumn>,
+    pub processes: Vec<ProcessEntry>,
+}
+
+impl ProcessTable {
+    pub fn new(columns: &[TopColumn], mut processes: Vec<ProcessEntry>) -> Self {
+        let columns = if columns.is_empty() {
+            DEFAULT_TOP_COLUMNS.to_vec()
+        } else {
+            columns.to_vec()
+        };
+        processes.sort_by_key(|p| p.container_pid);
+        Self { columns, processes }
+    }
+
+    pub fn headers(&self) -> Vec<&'static str> {
+        self.columns.iter().map(TopColumn::header).collect()
+    }
+
+    pub fn rows(&self) -> Vec<Vec<String>> {
+        self.processes
+            .iter()
+            .map(|p| self.columns.iter().map(|c| c.value(p)).collect())
+            .collect()
+    }
+
+    /// Os `n` processos que mais consomem memória
+    pub fn top_by_memory(&self, n: usize) -> Vec<&ProcessEntry> {
+        let mut processes: Vec<&ProcessEntry> = self.processes.iter().collect();
+        processes.sort_by_key(|p| std::cmp::Reverse(p.rss));
+        processes.truncate(n);
+        processes
+    }
+
+    /// Renderiza a tabela com colunas alinhadas; a última não recebe padding
+    pub fn render(&self) -> String {
+        let headers = self.headers();
+        let rows = self.rows();
+
+        let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+        for row in &rows {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = (*width).max(value.len());
+            }
+        }
+
+        let format_row = |values: Vec<&str>| {
+            let last = values.len().saturating_sub(1);
+            values
+                .iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    if index == last {
+                        value.to_string()
+                    } else {
+                        format!("{:<width$}", value, width = widths[index])
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+        };
+
+        let mut lines = vec![format_row(headers)];
+        for row in &rows {
+            lines.push(format_row(row.iter().map(String::as_str).collect()));
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,158 @@
+use chrono::{TimeZone, Utc};
+use polis_core::{
+    format_duration, parse_duration, Clock, ContainerStatus, ManualClock, PolisConfig, StopReason,
+};
+use polis_runtime::{ContainerEvent, ContainerOptions, ContainerRuntime, PolisRuntime};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+fn runtime(root_dir: &Path, clock: &ManualClock) -> PolisRuntime {
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = root_dir.to_path_buf();
+    config.storage.root_dir = root_dir.join("storage");
+    config.runtime.deadline_warning = 10 * 60;
+    PolisRuntime::new(config).with_clock(Arc::new(clock.clone()))
+}
+
+async fn start_with_deadline(
+    runtime: &PolisRuntime,
+    max_runtime: Duration,
+) -> polis_core::ContainerId {
+    let id = runtime
+        .create_container_with_options(
+            "batch".to_string(),
+            "alpine:latest".to_string(),
+            vec!["sleep".to_string(), "infinity".to_string()],
+            ContainerOptions {
+                max_runtime: Some(max_runtime),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    runtime.start_container(id.clone()).await.unwrap();
+    id
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_duration("2h").unwrap(), 120 * MINUTE);
+    assert_eq!(parse_duration("1h30m").unwrap(), 90 * MINUTE);
+    assert_eq!(parse_duration("1d").unwrap(), 24 * 60 * MINUTE);
+    assert!(parse_duration("1h30").is_err());
+    assert!(parse_duration("2w").is_err());
+    assert!(parse_duration("").is_err());
+    assert_eq!(
+        format_duration(90 * MINUTE + Duration::from_secs(5)),
+        "1h30m5s"
+    );
+    assert_eq!(format_duration(Duration::ZERO), "0s");
+}
+
+#[tokio::test]
+async fn test_warning_and_stop_fire_at_deadline() {
+    let temp = tempfile::tempdir().unwrap();
+    let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let clock = ManualClock::new(start);
+    let runtime = runtime(temp.path(), &clock);
+    let mut events = runtime.subscribe_events();
+
+    let id = start_with_deadline(&runtime, 120 * MINUTE).await;
+    let container = runtime.get_container(id.clone()).await.unwrap();
+    assert_eq!(container.deadline, Some(start + chrono::Duration::hours(2)));
+    assert!(matches!(
+        events.try_recv().unwrap(),
+        ContainerEvent::Started {
+            deadline: Some(_),
+            ..
+        }
+    ));
+
+    // 11 minutos antes do prazo: nenhum aviso ainda
+    clock.advance(109 * MINUTE);
+    assert!(runtime.enforce_deadlines().await.unwrap().is_empty());
+    assert!(events.try_recv().is_err());
+    assert_eq!(runtime.remaining_runtime(&id).await, Some(11 * MINUTE));
+
+    // Dentro da janela de aviso: avisa uma única vez
+    clock.advance(MINUTE);
+    assert!(runtime.enforce_deadlines().await.unwrap().is_empty());
+    match events.try_recv().unwrap() {
+        ContainerEvent::DeadlineWarning {
+            id: warned,
+            remaining,
+            ..
+        } => {
+            assert_eq!(warned, id);
+            assert_eq!(remaining, 10 * MINUTE);
+        }
+        other => panic!("evento inesperado: {:?}", other),
+    }
+    clock.advance(MINUTE);
+    runtime.enforce_deadlines().await.unwrap();
+    assert!(events.try_recv().is_err());
+
+    // Prazo atingido: container parado com o motivo registrado
+    clock.advance(9 * MINUTE);
+    assert_eq!(runtime.enforce_deadlines().await.unwrap(), vec![id.clone()]);
+
+    let container = runtime.get_container(id.clone()).await.unwrap();
+    assert_eq!(container.status, ContainerStatus::Stopped);
+    assert_eq!(container.stop_reason, Some(StopReason::DeadlineExceeded));
+    assert_eq!(
+        container.finished_at,
+        Some(start + chrono::Duration::hours(2))
+    );
+    assert_eq!(
+        events.try_recv().unwrap(),
+        ContainerEvent::Stopped {
+            id: id.clone(),
+            exit_code: Some(0),
+            reason: StopReason::DeadlineExceeded,
+        }
+    );
+
+    assert!(runtime.deadline(&id).await.is_none());
+    assert!(runtime.enforce_deadlines().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_requested_stop_clears_deadline() {
+    let temp = tempfile::tempdir().unwrap();
+    let clock = ManualClock::new(Utc::now());
+    let runtime = runtime(temp.path(), &clock);
+
+    let id = start_with_deadline(&runtime, 30 * MINUTE).await;
+    runtime.stop_container(id.clone()).await.unwrap();
+
+    let container = runtime.get_container(id.clone()).await.unwrap();
+    assert_eq!(container.stop_reason, Some(StopReason::Requested));
+    assert!(runtime.deadline(&id).await.is_none());
+}
+
+#[tokio::test]
+async fn test_deadline_survives_restart() {
+    let temp = tempfile::tempdir().unwrap();
+    let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 6, 1, 8, 0, 0).unwrap());
+
+    let id = {
+        let runtime = runtime(temp.path(), &clock);
+        runtime.initialize().await.unwrap();
+        start_with_deadline(&runtime, 45 * MINUTE).await
+    };
+    let expected = clock.now() + chrono::Duration::minutes(45);
+
+    // Nova instância do daemon sobre o mesmo diretório
+    clock.advance(20 * MINUTE);
+    let restarted = runtime(temp.path(), &clock);
+    restarted.initialize().await.unwrap();
+
+    let record = restarted.deadline(&id).await.unwrap();
+    assert_eq!(record.deadline, expected);
+    assert_eq!(record.max_runtime, 45 * MINUTE);
+    assert_eq!(restarted.remaining_runtime(&id).await, Some(25 * MINUTE));
+}
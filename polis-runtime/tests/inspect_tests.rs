@@ -0,0 +1,101 @@
+use polis_core::{parse_port_mapping, ContainerStatus, PolisConfig, ResourceLimits, StopReason};
+use polis_runtime::{ContainerInspect, ContainerOptions, ContainerRuntime, PolisRuntime};
+use std::collections::HashMap;
+use std::path::Path;
+
+fn runtime(temp: &Path) -> PolisRuntime {
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = temp.join("root");
+    config.storage.root_dir = temp.join("storage");
+    config.network.subnet = Some("10.88.0.0/29".to_string());
+    config.network.gateway = Some("10.88.0.1".to_string());
+    PolisRuntime::new(config)
+}
+
+async fn create(runtime: &PolisRuntime) -> polis_core::ContainerId {
+    runtime
+        .create_container_with_options(
+            "web".to_string(),
+            "nginx:latest".to_string(),
+            vec!["nginx".to_string(), "-g".to_string()],
+            ContainerOptions {
+                environment: HashMap::from([("MODE".to_string(), "prod".to_string())]),
+                labels: HashMap::from([("team".to_string(), "edge".to_string())]),
+                resource_limits: ResourceLimits {
+                    memory_limit: Some(64 * 1024 * 1024),
+                    ..Default::default()
+                },
+                ports: parse_port_mapping("8080:80").unwrap(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_inspect_round_trips_through_json() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = runtime(temp.path());
+    let id = create(&runtime).await;
+    runtime.start_container(id.clone()).await.unwrap();
+
+    let inspect = runtime.inspect_container(id.clone()).await.unwrap();
+    let json = serde_json::to_value(&inspect).unwrap();
+    let decoded: ContainerInspect = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&decoded).unwrap(), json);
+
+    assert_eq!(json["id"], serde_json::json!(id));
+    assert_eq!(json["name"], "web");
+    assert_eq!(json["config"]["image"], "nginx:latest");
+    assert_eq!(
+        json["config"]["command"],
+        serde_json::json!(["nginx", "-g"])
+    );
+    assert_eq!(json["config"]["environment"]["MODE"], "prod");
+    assert_eq!(json["config"]["labels"]["team"], "edge");
+    assert_eq!(
+        json["config"]["resource_limits"]["memory_limit"],
+        64 * 1024 * 1024
+    );
+    assert_eq!(json["state"]["status"], "Running");
+    assert_eq!(json["network"]["ip_address"], "10.88.0.6");
+    assert_eq!(json["network"]["ports"][0]["host_port"], 8080);
+    assert_eq!(json["mounts"][0]["destination"], "/");
+
+    // What the runtime does not know is null rather than made up
+    assert!(json["config"]["restart_policy"].is_null());
+    assert!(json["state"]["pid"].is_null());
+    assert!(json["state"]["finished_at"].is_null());
+    assert!(json["state"]["exit_code"].is_null());
+    assert!(json["stats"].is_null());
+}
+
+#[tokio::test]
+async fn test_stopped_container_reports_exit() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = runtime(temp.path());
+
+    let id = create(&runtime).await;
+    runtime.start_container(id.clone()).await.unwrap();
+    runtime.stop_container(id.clone()).await.unwrap();
+    let inspect = runtime.inspect_container(id.clone()).await.unwrap();
+    assert_eq!(inspect.state.status, ContainerStatus::Stopped);
+    assert_eq!(inspect.state.exit_code, Some(0));
+    assert!(inspect.state.finished_at.unwrap() >= inspect.state.started_at.unwrap());
+    assert_eq!(inspect.state.stop_reason, Some(StopReason::Requested));
+    assert!(!inspect.state.oom_killed);
+    // The IP is released with the ports
+    assert_eq!(inspect.network.ip_address, None);
+    runtime.remove_container(id).await.unwrap();
+
+    let id = create(&runtime).await;
+    runtime.start_container(id.clone()).await.unwrap();
+    runtime.report_oom_kill(id.clone()).await.unwrap();
+    let inspect = runtime.inspect_container(id).await.unwrap();
+    assert_eq!(inspect.state.exit_code, Some(137));
+    assert!(inspect.state.oom_killed);
+
+    let missing = polis_core::ContainerId::new();
+    assert!(runtime.inspect_container(missing).await.is_err());
+}
@@ -0,0 +1,138 @@
+use polis_core::{ContainerId, PolisConfig, PolisError};
+use polis_runtime::{ContainerRuntime, PolisRuntime};
+use std::collections::HashMap;
+use std::path::Path;
+
+fn runtime(root: &Path) -> PolisRuntime {
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = root.to_path_buf();
+    config.storage.root_dir = root.join("storage");
+    PolisRuntime::new(config).with_persistent_state()
+}
+
+async fn create(runtime: &PolisRuntime, name: &str) -> polis_core::Result<ContainerId> {
+    runtime
+        .create_container(
+            name.to_string(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+        )
+        .await
+}
+
+#[tokio::test]
+async fn test_duplicate_names_are_rejected() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = runtime(temp.path());
+
+    let web = create(&runtime, "web").await.unwrap();
+    let error = create(&runtime, "web").await.unwrap_err().to_string();
+    assert!(error.contains("web"), "{}", error);
+    assert!(error.contains(&web.0.to_string()), "{}", error);
+    assert_eq!(runtime.list_containers().await.unwrap().len(), 1);
+
+    // Removing the owner frees the name
+    runtime.remove_container(web.clone()).await.unwrap();
+    let again = create(&runtime, "web").await.unwrap();
+    assert_ne!(again, web);
+    assert_eq!(runtime.resolve("web").await.unwrap(), again);
+
+    // Without a name one is generated, and generated names do not repeat
+    let first = create(&runtime, "").await.unwrap();
+    let second = create(&runtime, "").await.unwrap();
+    let first = runtime.get_container(first).await.unwrap().name;
+    let second = runtime.get_container(second).await.unwrap().name;
+    assert!(first.contains('_'), "{}", first);
+    assert_ne!(first, second);
+}
+
+#[tokio::test]
+async fn test_resolve_by_id_prefix_and_ambiguity() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = runtime(temp.path());
+
+    // With 17 IDs at least two share their first hex digit
+    let mut by_first_digit: HashMap<char, Vec<ContainerId>> = HashMap::new();
+    for i in 0..17 {
+        let id = create(&runtime, &format!("c{}", i)).await.unwrap();
+        let first = id.0.to_string().chars().next().unwrap();
+        by_first_digit.entry(first).or_default().push(id);
+    }
+    let (digit, shared) = by_first_digit
+        .iter()
+        .find(|(_, ids)| ids.len() > 1)
+        .unwrap();
+
+    match runtime.resolve(&digit.to_string()).await.unwrap_err() {
+        PolisError::Ambiguous { reference, matches } => {
+            assert_eq!(reference, digit.to_string());
+            assert_eq!(matches.len(), shared.len());
+            for id in shared {
+                assert!(matches.contains(&id.0.to_string()), "{:?}", matches);
+            }
+        }
+        other => panic!("expected an ambiguous reference, got {}", other),
+    }
+
+    // Full IDs, unambiguous prefixes and names all resolve
+    let id = &shared[0];
+    let full = id.0.to_string();
+    assert_eq!(runtime.resolve(&full).await.unwrap(), *id);
+    assert_eq!(runtime.resolve(&full[..30]).await.unwrap(), *id);
+    let name = runtime.get_container(id.clone()).await.unwrap().name;
+    assert_eq!(runtime.resolve(&name).await.unwrap(), *id);
+    assert!(matches!(
+        runtime.resolve("missing").await,
+        Err(PolisError::Container(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_names_survive_a_restart() {
+    let temp = tempfile::tempdir().unwrap();
+    let web = {
+        let runtime = runtime(temp.path());
+        runtime.initialize().await.unwrap();
+        let web = create(&runtime, "web").await.unwrap();
+        let removed = create(&runtime, "removed").await.unwrap();
+        runtime.remove_container(removed).await.unwrap();
+        web
+    };
+
+    let runtime = runtime(temp.path());
+    runtime.initialize().await.unwrap();
+    assert_eq!(runtime.resolve("web").await.unwrap(), web);
+    assert_eq!(runtime.resolve(&web.0.to_string()[..8]).await.unwrap(), web);
+    assert!(runtime.resolve("removed").await.is_err());
+    assert!(create(&runtime, "web").await.is_err());
+}
+
+#[tokio::test]
+async fn test_names_stay_in_memory_until_initialize() {
+    let temp = tempfile::tempdir().unwrap();
+    let index = temp.path().join("names.json");
+    let early = runtime(temp.path());
+    let id = create(&early, "early").await.unwrap();
+    assert!(!index.exists());
+
+    // Names reserved before initialize are kept and written to the root
+    early.initialize().await.unwrap();
+    assert!(index.exists());
+    assert_eq!(early.resolve("early").await.unwrap(), id);
+
+    let restarted = runtime(temp.path());
+    restarted.initialize().await.unwrap();
+    assert_eq!(restarted.resolve("early").await.unwrap(), id);
+}
+
+#[tokio::test]
+async fn test_names_are_not_persisted_by_default() {
+    let temp = tempfile::tempdir().unwrap();
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = temp.path().to_path_buf();
+    config.storage.root_dir = temp.path().join("storage");
+    let runtime = PolisRuntime::new(config);
+    runtime.initialize().await.unwrap();
+    create(&runtime, "web").await.unwrap();
+    assert!(!temp.path().join("names.json").exists());
+}
@@ -0,0 +1,172 @@
+use polis_core::{parse_port_mapping, ContainerId, ContainerStatus, PolisConfig};
+use polis_runtime::{ContainerOptions, ContainerRuntime, PolisRuntime};
+use std::fs;
+use std::path::Path;
+
+fn runtime(root: &Path) -> PolisRuntime {
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = root.join("root");
+    config.storage.root_dir = root.join("storage");
+    config.network.subnet = Some("10.88.0.0/29".to_string());
+    config.network.gateway = Some("10.88.0.1".to_string());
+    PolisRuntime::new(config)
+        .with_persistent_state()
+        .with_proc_root(root.join("proc"))
+        .with_cgroup_root(root.join("cgroup"))
+}
+
+/// /proc/<pid> for a process started `start_ticks` after boot
+fn write_process(proc_root: &Path, pid: u32, start_ticks: u64) {
+    let dir = proc_root.join(pid.to_string());
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("stat"),
+        format!(
+            "{} (sh) S 1 {} {} 0 -1 4194560 100 0 0 0 5 5 0 0 20 0 1 0 {} 1000000 512\n",
+            pid, pid, pid, start_ticks
+        ),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("status"),
+        format!(
+            "Name:\tsh\nState:\tS (sleeping)\nUid:\t0\t0\t0\t0\nNSpid:\t{}\t1\nVmRSS:\t1024 kB\n",
+            pid
+        ),
+    )
+    .unwrap();
+    fs::write(dir.join("cmdline"), "sh\0").unwrap();
+}
+
+/// Starts the container with `pid` as the init process of its cgroup
+async fn start(runtime: &PolisRuntime, root: &Path, id: &ContainerId, pid: u32) {
+    let cgroup = root.join("cgroup").join(id.0.to_string());
+    fs::create_dir_all(&cgroup).unwrap();
+    fs::write(cgroup.join("cgroup.procs"), format!("{}\n", pid)).unwrap();
+    runtime.start_container(id.clone()).await.unwrap();
+}
+
+async fn create(runtime: &PolisRuntime, name: &str, ports: &str) -> ContainerId {
+    let ports = if ports.is_empty() {
+        Vec::new()
+    } else {
+        parse_port_mapping(ports).unwrap()
+    };
+    runtime
+        .create_container_with_options(
+            name.to_string(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+            ContainerOptions {
+                ports,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_containers_survive_a_restart() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path();
+    let proc_root = root.join("proc");
+    fs::create_dir_all(&proc_root).unwrap();
+    fs::write(proc_root.join("uptime"), "1000.00 3000.00\n").unwrap();
+    fs::write(proc_root.join("stat"), "cpu  1 2 3 4\nbtime 1700000000\n").unwrap();
+    write_process(&proc_root, 4242, 50000);
+    write_process(&proc_root, 4343, 60000);
+
+    let (web, worker, created, stopped, before) = {
+        let runtime = runtime(root);
+        runtime.initialize().await.unwrap();
+
+        let web = create(&runtime, "web", "8080:80").await;
+        start(&runtime, root, &web, 4242).await;
+        let worker = create(&runtime, "worker", "").await;
+        start(&runtime, root, &worker, 4343).await;
+        let created = create(&runtime, "created", "").await;
+        let stopped = create(&runtime, "stopped", "").await;
+        start(&runtime, root, &stopped, 4343).await;
+        runtime.stop_container(stopped.clone()).await.unwrap();
+        let removed = create(&runtime, "removed", "").await;
+        runtime.remove_container(removed.clone()).await.unwrap();
+        assert!(!runtime
+            .container_store()
+            .config_path(&removed)
+            .parent()
+            .unwrap()
+            .exists());
+
+        let before = runtime.inspect_container(web.clone()).await.unwrap();
+        (web, worker, created, stopped, before)
+    };
+    let store = root.join("storage/containers");
+    assert!(store.join(web.0.to_string()).join("config.json").exists());
+    assert!(!store
+        .join(web.0.to_string())
+        .join("config.json.tmp")
+        .exists());
+
+    // The worker's PID now belongs to a process started later
+    write_process(&proc_root, 4343, 70000);
+
+    let runtime = runtime(root);
+    runtime.initialize().await.unwrap();
+    let mut containers = runtime.list_containers().await.unwrap();
+    containers.sort_by_key(|container| container.name.clone());
+    let listed: Vec<_> = containers
+        .iter()
+        .map(|container| (container.name.as_str(), container.status.clone()))
+        .collect();
+    assert_eq!(
+        listed,
+        vec![
+            ("created", ContainerStatus::Created),
+            ("stopped", ContainerStatus::Stopped),
+            ("web", ContainerStatus::Running),
+            ("worker", ContainerStatus::Exited),
+        ]
+    );
+
+    // The running container keeps its spec, timestamps and address
+    let after = runtime.inspect_container(web.clone()).await.unwrap();
+    assert_eq!(after.created_at, before.created_at);
+    assert_eq!(after.state.started_at, before.state.started_at);
+    assert_eq!(after.network.ports, before.network.ports);
+    assert_eq!(after.network.ip_address, before.network.ip_address);
+    assert!(after.network.ip_address.is_some());
+    assert_eq!(after.state.pid, Some(4242));
+
+    let worker = runtime.get_container(worker).await.unwrap();
+    assert!(worker.finished_at.is_some());
+    assert_eq!(worker.exit_code, None);
+    let stopped = runtime.get_container(stopped).await.unwrap();
+    assert_eq!(stopped.exit_code, Some(0));
+    assert_eq!(runtime.resolve("created").await.unwrap(), created);
+
+    // The reattached container can be stopped and removed as usual
+    runtime.stop_container(web.clone()).await.unwrap();
+    runtime.remove_container(web.clone()).await.unwrap();
+    assert!(!store.join(web.0.to_string()).exists());
+}
+
+#[tokio::test]
+async fn test_metadata_is_written_only_after_initialize() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path();
+    let store = root.join("storage/containers");
+
+    let first = runtime(root);
+    let early = create(&first, "early", "").await;
+    assert!(!store.exists());
+
+    // Containers created before initialize are written along with the rest
+    first.initialize().await.unwrap();
+    assert!(first.container_store().config_path(&early).exists());
+
+    let restarted = runtime(root);
+    restarted.initialize().await.unwrap();
+    let container = restarted.get_container(early).await.unwrap();
+    assert_eq!(container.name, "early");
+}
@@ -0,0 +1,166 @@
+use polis_core::{parse_port_mapping, ContainerId, PolisConfig, PortMapping};
+use polis_network::{PortForwardingBackend, PortForwardingManager, PortForwardingRule};
+use polis_runtime::{ContainerOptions, ContainerRuntime, PolisRuntime};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct RecordingForwarder(Mutex<Vec<String>>);
+
+impl RecordingForwarder {
+    fn ops(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl PortForwardingBackend for RecordingForwarder {
+    fn add_forward(&self, rule: &PortForwardingRule) -> polis_core::Result<()> {
+        self.0.lock().unwrap().push(format!(
+            "add {} -> {}:{} {:?}",
+            rule.host_port, rule.container_ip, rule.container_port, rule.protocol
+        ));
+        Ok(())
+    }
+
+    fn remove_forward(&self, rule: &PortForwardingRule) -> polis_core::Result<()> {
+        self.0.lock().unwrap().push(format!(
+            "remove {} -> {}:{} {:?}",
+            rule.host_port, rule.container_ip, rule.container_port, rule.protocol
+        ));
+        Ok(())
+    }
+}
+
+fn runtime(temp: &Path, backend: Arc<RecordingForwarder>) -> PolisRuntime {
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = temp.join("root");
+    config.storage.root_dir = temp.join("storage");
+    config.network.subnet = Some("10.88.0.0/29".to_string());
+    config.network.gateway = Some("10.88.0.1".to_string());
+    PolisRuntime::new(config)
+        .with_port_forwarding(PortForwardingManager::new().with_backend(backend))
+}
+
+fn ports(specs: &[&str]) -> Vec<PortMapping> {
+    specs
+        .iter()
+        .flat_map(|spec| parse_port_mapping(spec).unwrap())
+        .collect()
+}
+
+async fn create(
+    runtime: &PolisRuntime,
+    name: &str,
+    ports: Vec<PortMapping>,
+) -> polis_core::Result<ContainerId> {
+    runtime
+        .create_container_with_options(
+            name.to_string(),
+            "nginx:latest".to_string(),
+            vec!["nginx".to_string()],
+            ContainerOptions {
+                ports,
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+#[tokio::test]
+async fn test_rules_follow_container_lifecycle() {
+    let temp = tempfile::tempdir().unwrap();
+    let backend = Arc::new(RecordingForwarder::default());
+    let runtime = runtime(temp.path(), backend.clone());
+
+    let id = create(&runtime, "web", ports(&["8080:80", "5353:53/udp"]))
+        .await
+        .unwrap();
+    assert_eq!(
+        runtime.get_container(id.clone()).await.unwrap().ports,
+        ports(&["8080:80", "5353:53/udp"])
+    );
+    // Nothing is forwarded until the container starts
+    assert!(backend.ops().is_empty());
+
+    runtime.start_container(id.clone()).await.unwrap();
+    runtime.stop_container(id.clone()).await.unwrap();
+    runtime.remove_container(id).await.unwrap();
+
+    // The container IP comes from the configured subnet
+    assert_eq!(
+        backend.ops(),
+        [
+            "add 8080 -> 10.88.0.6:80 Tcp",
+            "add 5353 -> 10.88.0.6:53 Udp",
+            "remove 8080 -> 10.88.0.6:80 Tcp",
+            "remove 5353 -> 10.88.0.6:53 Udp",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_crashed_container_releases_ports_and_ip() {
+    let temp = tempfile::tempdir().unwrap();
+    let backend = Arc::new(RecordingForwarder::default());
+    let runtime = runtime(temp.path(), backend.clone());
+
+    let first = create(&runtime, "first", ports(&["8080:80"]))
+        .await
+        .unwrap();
+    runtime.start_container(first.clone()).await.unwrap();
+    runtime.report_oom_kill(first.clone()).await.unwrap();
+    runtime.remove_container(first).await.unwrap();
+
+    // The released IP and host port are handed to the next container
+    let second = create(&runtime, "second", ports(&["8080:80"]))
+        .await
+        .unwrap();
+    runtime.start_container(second).await.unwrap();
+    assert_eq!(
+        backend.ops(),
+        [
+            "add 8080 -> 10.88.0.6:80 Tcp",
+            "remove 8080 -> 10.88.0.6:80 Tcp",
+            "add 8080 -> 10.88.0.6:80 Tcp",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_conflicting_host_port_is_rejected_at_create() {
+    let temp = tempfile::tempdir().unwrap();
+    let backend = Arc::new(RecordingForwarder::default());
+    let runtime = runtime(temp.path(), backend.clone());
+
+    let web = create(&runtime, "web", ports(&["8080:80", "8443:443"]))
+        .await
+        .unwrap();
+
+    let error = create(
+        &runtime,
+        "api",
+        ports(&["127.0.0.1:8080:3000", "9000:9000"]),
+    )
+    .await
+    .unwrap_err()
+    .to_string();
+    assert!(error.contains("8080/tcp"), "{}", error);
+    assert!(error.contains("web"), "{}", error);
+    assert!(error.contains(&web.0.to_string()), "{}", error);
+    assert_eq!(runtime.list_containers().await.unwrap().len(), 1);
+
+    // Other protocols and ports are free; a port cannot repeat in one container
+    create(&runtime, "dns", ports(&["8080:53/udp"]))
+        .await
+        .unwrap();
+    assert!(create(&runtime, "twice", ports(&["9000:80", "9000:81"]))
+        .await
+        .is_err());
+
+    // Removing the owner frees the port
+    runtime.remove_container(web).await.unwrap();
+    create(&runtime, "api", ports(&["8080:3000"]))
+        .await
+        .unwrap();
+    assert!(backend.ops().is_empty());
+}
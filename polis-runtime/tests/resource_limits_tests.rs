@@ -0,0 +1,145 @@
+use polis_core::{parse_size, ContainerStatus, PolisConfig, ResourceLimits};
+use polis_runtime::{ContainerOptions, ContainerRuntime, PolisRuntime};
+use std::fs;
+use std::path::Path;
+
+fn runtime(temp: &Path) -> PolisRuntime {
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = temp.join("root");
+    config.storage.root_dir = temp.join("storage");
+    PolisRuntime::new(config)
+        .with_cgroup_mount(temp.join("cgroup"))
+        .with_cgroup_root(temp.join("cgroup").join("polis"))
+}
+
+async fn create(
+    runtime: &PolisRuntime,
+    resource_limits: ResourceLimits,
+) -> polis_core::ContainerId {
+    runtime
+        .create_container_with_options(
+            "web".to_string(),
+            "alpine:latest".to_string(),
+            vec!["sleep".to_string(), "infinity".to_string()],
+            ContainerOptions {
+                resource_limits,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_start_applies_limits_and_remove_cleans_up() {
+    let temp = tempfile::tempdir().unwrap();
+    fs::create_dir_all(temp.path().join("cgroup")).unwrap();
+    fs::write(
+        temp.path().join("cgroup/cgroup.controllers"),
+        "cpu memory pids\n",
+    )
+    .unwrap();
+    let runtime = runtime(temp.path());
+
+    let limits = ResourceLimits {
+        memory_limit: Some(parse_size("256m").unwrap()),
+        cpu_quota: Some(0.5),
+        pids_limit: Some(100),
+        ..Default::default()
+    };
+    let id = create(&runtime, limits.clone()).await;
+    assert_eq!(
+        runtime
+            .get_container(id.clone())
+            .await
+            .unwrap()
+            .resource_limits,
+        limits
+    );
+
+    // The cgroup is created when the container starts
+    let cgroup = runtime.cgroup_path(&id);
+    assert!(!cgroup.exists());
+    runtime.start_container(id.clone()).await.unwrap();
+    assert_eq!(
+        fs::read_to_string(cgroup.join("memory.max")).unwrap(),
+        "268435456"
+    );
+    assert_eq!(
+        fs::read_to_string(cgroup.join("cpu.max")).unwrap(),
+        "50000 100000"
+    );
+    assert_eq!(fs::read_to_string(cgroup.join("pids.max")).unwrap(), "100");
+
+    runtime.stop_container(id.clone()).await.unwrap();
+    assert_eq!(
+        runtime.get_container(id.clone()).await.unwrap().status,
+        ContainerStatus::Stopped
+    );
+    runtime.remove_container(id).await.unwrap();
+    assert!(!cgroup.exists());
+}
+
+#[tokio::test]
+async fn test_container_without_limits_gets_no_cgroup() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = runtime(temp.path());
+
+    let id = create(&runtime, ResourceLimits::default()).await;
+    runtime.start_container(id.clone()).await.unwrap();
+    assert!(!runtime.cgroup_path(&id).exists());
+}
+
+#[tokio::test]
+async fn test_limits_without_cgroups_fail_to_start() {
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = runtime(temp.path());
+
+    let id = create(
+        &runtime,
+        ResourceLimits {
+            memory_limit: Some(64 * 1024 * 1024),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert!(runtime.start_container(id.clone()).await.is_err());
+    assert_eq!(
+        runtime.get_container(id).await.unwrap().status,
+        ContainerStatus::Created
+    );
+}
+
+#[tokio::test]
+async fn test_failed_spawn_releases_the_cgroup_and_the_deadline() {
+    let temp = tempfile::tempdir().unwrap();
+    fs::create_dir_all(temp.path().join("cgroup")).unwrap();
+    fs::write(temp.path().join("cgroup/cgroup.controllers"), "memory\n").unwrap();
+    let runtime = runtime(temp.path());
+
+    // An empty command cannot be spawned
+    let id = runtime
+        .create_container_with_options(
+            "batch".to_string(),
+            "alpine:latest".to_string(),
+            Vec::new(),
+            ContainerOptions {
+                resource_limits: ResourceLimits {
+                    memory_limit: Some(64 * 1024 * 1024),
+                    ..Default::default()
+                },
+                max_runtime: Some(std::time::Duration::from_secs(60)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(runtime.start_container(id.clone()).await.is_err());
+
+    assert!(!runtime.cgroup_path(&id).exists());
+    assert!(runtime.deadline(&id).await.is_none());
+    assert!(runtime.enforce_deadlines().await.unwrap().is_empty());
+    let container = runtime.get_container(id).await.unwrap();
+    assert_eq!(container.status, ContainerStatus::Created);
+    assert!(container.deadline.is_none());
+}
@@ -0,0 +1,471 @@
+use polis_core::{
+    ContainerId, ContainerStatus, PolisConfig, PolisError, Result, RootfsConfig, VolumeMode,
+    VolumeMount, WritablePath,
+};
+use polis_runtime::{ContainerOptions, ContainerRuntime, MountBackend, MountEntry, PolisRuntime};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Records the mount table instead of calling mount(2)
+#[derive(Default)]
+struct RecordingMounts {
+    calls: Mutex<Vec<String>>,
+    fail_at: Option<PathBuf>,
+}
+
+impl RecordingMounts {
+    fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl MountBackend for RecordingMounts {
+    fn mount(&self, _root: &Path, entry: &MountEntry) -> Result<()> {
+        if self.fail_at.as_ref() == Some(&entry.destination) {
+            return Err(PolisError::Runtime("mount failed".to_string()));
+        }
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("mount {}", entry.destination.display()));
+        Ok(())
+    }
+
+    fn unmount(&self, _root: &Path, entry: &MountEntry) -> Result<()> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("umount {}", entry.destination.display()));
+        Ok(())
+    }
+}
+
+fn runtime(root: &Path, mounts: Arc<dyn MountBackend>) -> PolisRuntime {
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = root.to_path_buf();
+    config.storage.root_dir = root.join("storage");
+    PolisRuntime::new(config).with_mount_backend(mounts)
+}
+
+async fn create(runtime: &PolisRuntime, rootfs: RootfsConfig) -> ContainerId {
+    runtime
+        .create_container_with_options(
+            String::new(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+            ContainerOptions {
+                rootfs,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+}
+
+fn data_volume() -> VolumeMount {
+    VolumeMount {
+        source: "/var/lib/polis/volumes/data".to_string(),
+        destination: PathBuf::from("/data"),
+        mode: VolumeMode::Volume,
+        read_only: false,
+    }
+}
+
+#[tokio::test]
+async fn test_readonly_mount_plan_ordering() {
+    let runtime = PolisRuntime::new(PolisConfig::default());
+
+    let options = ContainerOptions {
+        rootfs: RootfsConfig::read_only()
+            .with_writable_path(WritablePath::tmpfs(
+                "/var/cache/app",
+                Some(64 * 1024 * 1024),
+            ))
+            .with_writable_path(WritablePath::bind("/srv/app-data", "/app/data")),
+        volumes: vec![data_volume()],
+        ..Default::default()
+    };
+
+    let id = runtime
+        .create_container_with_options(
+            "readonly".to_string(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+            options,
+        )
+        .await
+        .unwrap();
+
+    let plan = runtime.get_mount_plan(&id).await.unwrap();
+    let destinations: Vec<String> = plan
+        .iter()
+        .map(|m| m.destination.display().to_string())
+        .collect();
+
+    assert_eq!(
+        destinations,
+        vec![
+            "/",
+            "/tmp",
+            "/run",
+            "/var/tmp",
+            "/app/data",
+            "/var/cache/app",
+            "/data"
+        ]
+    );
+
+    // Rootfs somente leitura vem primeiro
+    assert_eq!(plan[0].fs_type, "overlay");
+    assert_eq!(plan[0].options, vec!["ro".to_string()]);
+
+    // Overlays graváveis sobre o rootfs
+    assert_eq!(plan[1].fs_type, "tmpfs");
+    assert_eq!(plan[4].fs_type, "bind");
+    assert_eq!(plan[4].source, "/srv/app-data");
+    assert!(plan[5]
+        .options
+        .contains(&format!("size={}", 64 * 1024 * 1024)));
+}
+
+#[tokio::test]
+async fn test_readonly_without_default_writable_paths() {
+    let runtime = PolisRuntime::new(PolisConfig::default());
+
+    let options = ContainerOptions {
+        rootfs: RootfsConfig::read_only()
+            .without_default_writable_paths()
+            .with_writable_path(WritablePath::tmpfs("/tmp", None)),
+        ..Default::default()
+    };
+
+    let id = runtime
+        .create_container_with_options(
+            "minimal".to_string(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+            options,
+        )
+        .await
+        .unwrap();
+
+    let plan = runtime.get_mount_plan(&id).await.unwrap();
+    assert_eq!(plan.len(), 2);
+    assert_eq!(plan[1].destination, PathBuf::from("/tmp"));
+}
+
+#[tokio::test]
+async fn test_writable_path_conflicts_with_volume() {
+    let runtime = PolisRuntime::new(PolisConfig::default());
+
+    let options = ContainerOptions {
+        rootfs: RootfsConfig::read_only().with_writable_path(WritablePath::tmpfs("/data", None)),
+        volumes: vec![data_volume()],
+        ..Default::default()
+    };
+
+    let result = runtime
+        .create_container_with_options(
+            "conflict".to_string(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+            options,
+        )
+        .await;
+
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("/data"));
+    assert!(error.contains("conflita"));
+    assert!(runtime.list_containers().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_working_dir_hidden_by_tmpfs() {
+    let runtime = PolisRuntime::new(PolisConfig::default());
+
+    let options = ContainerOptions {
+        working_dir: Some(PathBuf::from("/run/app")),
+        rootfs: RootfsConfig::read_only(),
+        ..Default::default()
+    };
+
+    let result = runtime
+        .create_container_with_options(
+            "workdir".to_string(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+            options,
+        )
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_oci_spec_readonly_root() {
+    let runtime = PolisRuntime::new(PolisConfig::default());
+
+    let options = ContainerOptions {
+        rootfs: RootfsConfig::read_only(),
+        ..Default::default()
+    };
+
+    let id = runtime
+        .create_container_with_options(
+            "spec".to_string(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+            options,
+        )
+        .await
+        .unwrap();
+
+    let spec = runtime.get_oci_spec(&id).await.unwrap();
+    let root = spec.root().as_ref().unwrap();
+    assert_eq!(root.readonly(), &Some(true));
+
+    let mounts = spec.mounts().as_ref().unwrap();
+    assert!(mounts
+        .iter()
+        .any(|m| m.destination() == &PathBuf::from("/tmp") && m.typ().as_deref() == Some("tmpfs")));
+}
+
+#[test]
+fn test_parse_writable_path() {
+    assert_eq!(
+        WritablePath::parse("/tmp:size=64m").unwrap(),
+        WritablePath::tmpfs("/tmp", Some(64 * 1024 * 1024))
+    );
+    assert_eq!(
+        WritablePath::parse("/srv/data:/data").unwrap(),
+        WritablePath::bind("/srv/data", "/data")
+    );
+    assert!(WritablePath::parse("relative").is_err());
+}
+
+#[tokio::test]
+async fn test_nested_writable_path_and_volume_conflict() {
+    let runtime = PolisRuntime::new(PolisConfig::default());
+
+    // A writable path under a volume and a volume under a writable path
+    for (writable, volume) in [("/data/cache", "/data"), ("/data", "/data/cache")] {
+        let options = ContainerOptions {
+            rootfs: RootfsConfig::read_only()
+                .with_writable_path(WritablePath::tmpfs(writable, None)),
+            volumes: vec![VolumeMount {
+                destination: PathBuf::from(volume),
+                ..data_volume()
+            }],
+            ..Default::default()
+        };
+        let error = runtime
+            .create_container_with_options(
+                "nested".to_string(),
+                "alpine:latest".to_string(),
+                vec!["sh".to_string()],
+                options,
+            )
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("conflita"), "{}", error);
+    }
+
+    // Sibling paths sharing a prefix do not overlap
+    let options = ContainerOptions {
+        rootfs: RootfsConfig::read_only()
+            .with_writable_path(WritablePath::tmpfs("/database", None)),
+        volumes: vec![data_volume()],
+        ..Default::default()
+    };
+    runtime
+        .create_container_with_options(
+            "siblings".to_string(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+            options,
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_volume_under_a_default_writable_path_replaces_it() {
+    let runtime = PolisRuntime::new(PolisConfig::default());
+
+    let options = ContainerOptions {
+        rootfs: RootfsConfig::read_only(),
+        volumes: vec![
+            VolumeMount {
+                destination: PathBuf::from("/run/secrets"),
+                read_only: true,
+                ..data_volume()
+            },
+            VolumeMount {
+                destination: PathBuf::from("/tmp"),
+                ..data_volume()
+            },
+        ],
+        ..Default::default()
+    };
+    let id = runtime
+        .create_container_with_options(
+            "secrets".to_string(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+            options,
+        )
+        .await
+        .unwrap();
+
+    // Only /var/tmp is left of the defaults; the volumes take /run and /tmp
+    let destinations: Vec<String> = runtime
+        .get_mount_plan(&id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|m| m.destination.display().to_string())
+        .collect();
+    assert_eq!(destinations, vec!["/", "/var/tmp", "/run/secrets", "/tmp"]);
+}
+
+#[tokio::test]
+async fn test_start_applies_the_mount_plan() {
+    let temp = tempfile::tempdir().unwrap();
+    let mounts = Arc::new(RecordingMounts::default());
+    let runtime = runtime(temp.path(), mounts.clone());
+
+    let id = create(
+        &runtime,
+        RootfsConfig::read_only()
+            .without_default_writable_paths()
+            .with_writable_path(WritablePath::tmpfs("/tmp", None))
+            .with_writable_path(WritablePath::tmpfs("/var/cache/app", None)),
+    )
+    .await;
+    assert!(mounts.calls().is_empty());
+
+    runtime.start_container(id.clone()).await.unwrap();
+    assert_eq!(
+        mounts.calls(),
+        vec!["mount /", "mount /tmp", "mount /var/cache/app"]
+    );
+    // Mount points exist in the rootfs before it turns read-only
+    assert!(runtime.rootfs_path(&id).join("var/cache/app").is_dir());
+
+    runtime.stop_container(id).await.unwrap();
+    assert_eq!(
+        mounts.calls()[3..],
+        ["umount /var/cache/app", "umount /tmp", "umount /"]
+    );
+
+    // A writable rootfs is used as it is
+    let writable = create(&runtime, RootfsConfig::default()).await;
+    runtime.start_container(writable).await.unwrap();
+    assert_eq!(mounts.calls().len(), 6);
+}
+
+#[tokio::test]
+async fn test_failed_mount_rolls_back_and_keeps_the_container_created() {
+    let temp = tempfile::tempdir().unwrap();
+    let mounts = Arc::new(RecordingMounts {
+        fail_at: Some(PathBuf::from("/var/tmp")),
+        ..Default::default()
+    });
+    let runtime = runtime(temp.path(), mounts.clone());
+
+    let id = create(&runtime, RootfsConfig::read_only()).await;
+    assert!(runtime.start_container(id.clone()).await.is_err());
+    assert_eq!(
+        mounts.calls(),
+        vec![
+            "mount /",
+            "mount /tmp",
+            "mount /run",
+            "umount /run",
+            "umount /tmp",
+            "umount /"
+        ]
+    );
+    let container = runtime.get_container(id).await.unwrap();
+    assert_eq!(container.status, ContainerStatus::Created);
+}
+
+/// Creates `path` from a child process chrooted into `rootfs`, as the
+/// container's process sees it; returns whether the write succeeded
+#[cfg(feature = "integration")]
+fn write_inside(rootfs: &Path, path: &str) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    // Everything the child needs is allocated before the fork
+    let root = std::ffi::CString::new(rootfs.as_os_str().as_bytes()).unwrap();
+    let slash = std::ffi::CString::new("/").unwrap();
+    let path = std::ffi::CString::new(path).unwrap();
+    match unsafe { libc::fork() } {
+        0 => unsafe {
+            let ok = libc::chroot(root.as_ptr()) == 0 && libc::chdir(slash.as_ptr()) == 0 && {
+                let fd = libc::open(
+                    path.as_ptr(),
+                    libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC,
+                    0o644,
+                );
+                fd >= 0 && libc::write(fd, b"x".as_ptr() as *const libc::c_void, 1) == 1
+            };
+            libc::_exit(if ok { 0 } else { 1 })
+        },
+        pid => {
+            assert!(pid > 0);
+            let mut status = 0;
+            assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+            libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0
+        }
+    }
+}
+
+/// Starts a read-only container through the runtime with real mounts and
+/// checks, from a process inside its root, that only the writable paths
+/// accept writes. The runtime only simulates the container's process, so a
+/// child chrooted into the rootfs stands in for it. Needs CAP_SYS_ADMIN;
+/// the mounts stay in a private mount namespace of the test thread.
+#[cfg(feature = "integration")]
+#[tokio::test]
+async fn test_readonly_rootfs_in_real_namespace() {
+    assert_eq!(unsafe { libc::unshare(libc::CLONE_NEWNS) }, 0);
+    let root = std::ffi::CString::new("/").unwrap();
+    let none = std::ffi::CString::new("none").unwrap();
+    let private = unsafe {
+        libc::mount(
+            none.as_ptr(),
+            root.as_ptr(),
+            std::ptr::null(),
+            libc::MS_REC | libc::MS_PRIVATE,
+            std::ptr::null(),
+        )
+    };
+    assert_eq!(private, 0);
+
+    let temp = tempfile::tempdir().unwrap();
+    let runtime = runtime(temp.path(), Arc::new(polis_runtime::KernelMounts));
+    let id = create(&runtime, RootfsConfig::read_only()).await;
+    let rootfs = runtime.rootfs_path(&id);
+    std::fs::create_dir_all(rootfs.join("etc")).unwrap();
+    std::fs::write(rootfs.join("etc/hostname"), "polis\n").unwrap();
+
+    runtime.start_container(id.clone()).await.unwrap();
+    assert!(!write_inside(&rootfs, "/etc/polis-denied"));
+    assert!(!write_inside(&rootfs, "/etc/hostname"));
+    assert!(!write_inside(&rootfs, "/polis-denied"));
+    assert!(write_inside(&rootfs, "/tmp/polis-ok"));
+    assert!(write_inside(&rootfs, "/run/polis-ok"));
+    assert!(write_inside(&rootfs, "/var/tmp/polis-ok"));
+    assert_eq!(
+        std::fs::read_to_string(rootfs.join("etc/hostname")).unwrap(),
+        "polis\n"
+    );
+    assert!(!rootfs.join("etc/polis-denied").exists());
+
+    // Stopping releases the overlays: the tmpfs contents are gone
+    runtime.stop_container(id).await.unwrap();
+    assert!(!rootfs.join("tmp/polis-ok").exists());
+    assert!(write_inside(&rootfs, "/etc/polis-after-stop"));
+}
@@ -1,5 +1,5 @@
 use polis_core::{ContainerId, ContainerStatus, PolisConfig, ResourceLimits};
-use polis_runtime::{ContainerRuntime, PolisRuntime};
+use polis_runtime::{ContainerOptions, ContainerRuntime, PolisRuntime};
 use std::sync::Arc;
 
 #[tokio::test]
@@ -249,3 +249,51 @@ async fn test_concurrent_operations() {
         runtime.remove_container(container_id).await.unwrap();
     }
 }
+
+#[tokio::test]
+async fn test_rootless_containers_get_a_sandbox() {
+    let temp = tempfile::tempdir().unwrap();
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = temp.path().to_path_buf();
+    config.storage.root_dir = temp.path().join("storage");
+    let runtime = PolisRuntime::new(config.clone());
+
+    let rootless = runtime
+        .create_container_with_options(
+            "rootless".to_string(),
+            "alpine:latest".to_string(),
+            Vec::new(),
+            ContainerOptions {
+                rootless: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let sandbox = runtime.rootless_sandbox(&rootless).await.unwrap();
+    assert_eq!(sandbox.uid(), unsafe { libc::getuid() });
+    assert_eq!(sandbox.uid_map()[0].container_id, 0);
+    assert_eq!(sandbox.uid_map()[0].host_id, sandbox.uid());
+
+    let rootful = runtime
+        .create_container(
+            "rootful".to_string(),
+            "alpine:latest".to_string(),
+            Vec::new(),
+        )
+        .await
+        .unwrap();
+    assert!(runtime.rootless_sandbox(&rootful).await.is_none());
+
+    runtime.remove_container(rootless.clone()).await.unwrap();
+    assert!(runtime.rootless_sandbox(&rootless).await.is_none());
+
+    // With rootless on in the configuration every container gets a sandbox
+    config.rootless = true;
+    let runtime = PolisRuntime::new(config);
+    let id = runtime
+        .create_container("web".to_string(), "alpine:latest".to_string(), Vec::new())
+        .await
+        .unwrap();
+    assert!(runtime.rootless_sandbox(&id).await.is_some());
+}
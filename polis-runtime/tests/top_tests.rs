@@ -0,0 +1,196 @@
+use polis_core::{ContainerStatus, PolisConfig, ProcReader};
+use polis_runtime::{ContainerRuntime, PolisRuntime, TopColumn};
+use std::fs;
+use std::path::Path;
+
+const UID_MAP: &str = "         0     100000      65536\n";
+
+/// Cria /proc/<pid> com stat, status, cmdline e uid_map
+fn write_process(
+    proc_root: &Path,
+    pid: u32,
+    comm: &str,
+    nspid: u32,
+    host_uid: u32,
+    rss_kb: u64,
+    cmdline: &str,
+) {
+    let dir = proc_root.join(pid.to_string());
+    fs::create_dir_all(&dir).unwrap();
+
+    // utime=500 stime=500 (10s de CPU), starttime=50000 ticks (500s após o boot)
+    fs::write(
+        dir.join("stat"),
+        format!(
+            "{} ({}) S 1 {} {} 0 -1 4194560 100 0 0 0 500 500 0 0 20 0 3 0 50000 1000000 512\n",
+            pid, comm, pid, pid
+        ),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("status"),
+        format!(
+            "Name:\t{}\nState:\tS (sleeping)\nUid:\t{uid}\t{uid}\t{uid}\t{uid}\nNSpid:\t{}\t{}\nVmRSS:\t{} kB\n",
+            comm,
+            pid,
+            nspid,
+            rss_kb,
+            uid = host_uid
+        ),
+    )
+    .unwrap();
+    fs::write(dir.join("cmdline"), cmdline).unwrap();
+    fs::write(dir.join("uid_map"), UID_MAP).unwrap();
+}
+
+fn write_fixture(proc_root: &Path, cgroup_dir: &Path) {
+    fs::create_dir_all(proc_root).unwrap();
+    fs::write(proc_root.join("uptime"), "1000.00 3000.00\n").unwrap();
+    fs::write(
+        proc_root.join("stat"),
+        "cpu  1 2 3 4\nbtime 1700000000\nprocesses 100\n",
+    )
+    .unwrap();
+
+    write_process(
+        proc_root,
+        4242,
+        "sh",
+        1,
+        100000,
+        2048,
+        "/bin/sh\0-c\0run.sh\0",
+    );
+    write_process(
+        proc_root,
+        4300,
+        "my app) x",
+        7,
+        101000,
+        10240,
+        "python3\0app.py\0",
+    );
+    write_process(proc_root, 4301, "kworker", 8, 200000, 0, "");
+
+    // 4999 termina entre a leitura de cgroup.procs e a do /proc
+    fs::create_dir_all(cgroup_dir).unwrap();
+    fs::write(cgroup_dir.join("cgroup.procs"), "4242\n4300\n4999\n4301\n").unwrap();
+}
+
+#[test]
+fn test_proc_reader_parses_fixture() {
+    let temp = tempfile::tempdir().unwrap();
+    let proc_root = temp.path().join("proc");
+    let cgroup_dir = temp.path().join("cgroup");
+    write_fixture(&proc_root, &cgroup_dir);
+
+    let processes = ProcReader::with_root(&proc_root)
+        .list_cgroup_processes(&cgroup_dir)
+        .unwrap();
+    assert_eq!(processes.len(), 3);
+
+    let shell = &processes[0];
+    assert_eq!(shell.host_pid, 4242);
+    assert_eq!(shell.container_pid, 1);
+    assert_eq!(shell.uid, Some(0));
+    assert_eq!(shell.user, "0");
+    assert_eq!(shell.rss, 2048 * 1024);
+    assert_eq!(shell.threads, 3);
+    assert!((shell.cpu_percent - 2.0).abs() < 1e-9);
+    assert_eq!(shell.start_time.timestamp(), 1700000000 + 500);
+    assert_eq!(shell.cmdline, "/bin/sh -c run.sh");
+
+    let app = &processes[1];
+    assert_eq!(app.comm, "my app) x");
+    assert_eq!(app.container_pid, 7);
+    assert_eq!(app.uid, Some(1000));
+
+    // Fora da faixa do uid_map e sem cmdline
+    let worker = &processes[2];
+    assert_eq!(worker.uid, None);
+    assert_eq!(worker.user, "nobody");
+    assert_eq!(worker.cmdline, "[kworker]");
+
+    assert!(ProcReader::with_root(&proc_root)
+        .read_process(4999)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_parse_top_columns() {
+    assert_eq!(
+        TopColumn::parse_list("pid,user,cmd").unwrap(),
+        vec![TopColumn::Pid, TopColumn::User, TopColumn::Cmd]
+    );
+    assert_eq!(
+        TopColumn::parse_list("%cpu, args").unwrap(),
+        vec![TopColumn::Cpu, TopColumn::Cmd]
+    );
+    assert!(TopColumn::parse_list("pid,bogus").is_err());
+}
+
+#[tokio::test]
+async fn test_container_top() {
+    let temp = tempfile::tempdir().unwrap();
+    let mut config = PolisConfig::default();
+    config.runtime.root_dir = temp.path().join("root");
+    config.storage.root_dir = temp.path().join("storage");
+
+    let runtime = PolisRuntime::new(config)
+        .with_proc_root(temp.path().join("proc"))
+        .with_cgroup_root(temp.path().join("cgroup"));
+
+    let id = runtime
+        .create_container(
+            "web".to_string(),
+            "alpine:latest".to_string(),
+            vec!["sh".to_string()],
+        )
+        .await
+        .unwrap();
+
+    // Container parado não tem processos para listar
+    assert!(runtime.top(id.clone(), &[]).await.is_err());
+
+    runtime.start_container(id.clone()).await.unwrap();
+    assert_eq!(
+        runtime.get_container(id.clone()).await.unwrap().status,
+        ContainerStatus::Running
+    );
+
+    write_fixture(&temp.path().join("proc"), &runtime.cgroup_path(&id));
+    let passwd = runtime.rootfs_path(&id).join("etc/passwd");
+    fs::create_dir_all(passwd.parent().unwrap()).unwrap();
+    fs::write(
+        &passwd,
+        "root:x:0:0:root:/root:/bin/sh\napp:x:1000:1000::/home/app:/bin/sh\n",
+    )
+    .unwrap();
+
+    let columns = TopColumn::parse_list("pid,user,cmd").unwrap();
+    let table = runtime.top(id.clone(), &columns).await.unwrap();
+    assert_eq!(table.headers(), vec!["PID", "USER", "CMD"]);
+    assert_eq!(
+        table.rows(),
+        vec![
+            vec!["1", "root", "/bin/sh -c run.sh"],
+            vec!["7", "app", "python3 app.py"],
+            vec!["8", "nobody", "[kworker]"],
+        ]
+    );
+
+    let rendered = table.render();
+    assert!(rendered.starts_with("PID  USER    CMD\n"));
+
+    let heaviest: Vec<u32> = table
+        .top_by_memory(2)
+        .iter()
+        .map(|p| p.container_pid)
+        .collect();
+    assert_eq!(heaviest, vec![7, 1]);
+
+    // Sem colunas, usa o conjunto padrão
+    let default_table = runtime.top(id, &[]).await.unwrap();
+    assert_eq!(default_table.headers()[..2], ["PID", "HOST PID"]);
+}
@@ -180,8 +180,9 @@ async fn main() -> Result<()> {
     let new_limits = ResourceLimits {
         memory_limit: Some(512 * 1024 * 1024), // 512MB
         memory_swap: Some(512 * 1024 * 1024),  // 512MB
-        cpu_quota: Some(50000.0),              // 50% de CPU
+        cpu_quota: Some(0.5),                  // 50% de CPU
         cpu_period: Some(100000),
+        cpu_shares: None,
         pids_limit: Some(100),
         disk_quota: Some(1024 * 1024 * 1024), // 1GB
     };
@@ -27,8 +27,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let limits = ResourceLimits {
         memory_limit: Some(512 * 1024 * 1024), // 512MB
-        cpu_quota: Some(50000.0),              // 50% CPU
+        cpu_quota: Some(0.5),                  // 50% CPU
         cpu_period: Some(100000),              // 100ms period
+        cpu_shares: None,
         pids_limit: Some(100),
         disk_quota: Some(1024 * 1024 * 1024),  // 1GB
         memory_swap: Some(1024 * 1024 * 1024), // 1GB swap
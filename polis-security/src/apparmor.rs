@@ -1,5 +1,8 @@
+use crate::Capability;
 use polis_core::{PolisError, Result};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
 use std::process::Command;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,15 +22,30 @@ pub enum AppArmorMode {
 #[derive(Debug, Clone)]
 pub struct AppArmorManager {
     pub profiles: std::collections::HashMap<String, AppArmorProfile>,
+    profile_dir: PathBuf,
 }
 
 impl AppArmorManager {
     pub fn new() -> Self {
         Self {
             profiles: std::collections::HashMap::new(),
+            profile_dir: PathBuf::from("/etc/apparmor.d"),
         }
     }
 
+    /// Grava os perfis carregados em outro diretório em vez de /etc/apparmor.d
+    pub fn with_profile_dir(mut self, profile_dir: impl Into<PathBuf>) -> Self {
+        self.profile_dir = profile_dir.into();
+        self
+    }
+
+    /// Arquivo em que `load_profile` grava um perfil: o nome deriva do
+    /// conteúdo, então recarregar o mesmo perfil reaproveita o arquivo
+    pub fn profile_path(&self, profile_text: &str) -> PathBuf {
+        let digest = format!("{:x}", Sha256::digest(profile_text.as_bytes()));
+        self.profile_dir.join(format!("polis-{}", &digest[..16]))
+    }
+
     pub async fn is_available(&self) -> bool {
         // Verificar se o AppArmor está disponível no sistema
         Command::new("aa-status")
@@ -57,24 +75,22 @@ impl AppArmorManager {
         Ok(profile)
     }
 
-    pub async fn load_profile(&self, profile: &AppArmorProfile) -> Result<()> {
+    /// Grava um perfil em texto no diretório do AppArmor e o carrega (ou
+    /// recarrega) com `apparmor_parser -r`
+    pub async fn load_profile(&self, profile_text: &str) -> Result<()> {
         if !self.is_available().await {
             return Err(PolisError::Security(
                 "AppArmor não está disponível no sistema".to_string(),
             ));
         }
 
-        // Criar arquivo de perfil temporário
-        let temp_file = format!("/tmp/polis-{}.conf", profile.name);
-        let profile_content = self.generate_profile_content(profile);
-
-        std::fs::write(&temp_file, profile_content)
+        let path = self.profile_path(profile_text);
+        std::fs::write(&path, profile_text)
             .map_err(|e| PolisError::Security(format!("Erro ao escrever perfil: {}", e)))?;
 
-        // Carregar perfil no AppArmor
         let output = Command::new("apparmor_parser")
             .arg("-r")
-            .arg(&temp_file)
+            .arg(&path)
             .output()
             .map_err(|e| {
                 PolisError::Security(format!("Erro ao executar apparmor_parser: {}", e))
@@ -88,9 +104,6 @@ impl AppArmorManager {
             )));
         }
 
-        // Limpar arquivo temporário
-        let _ = std::fs::remove_file(&temp_file);
-
         Ok(())
     }
 
@@ -209,7 +222,7 @@ impl AppArmorManager {
         })
     }
 
-    fn generate_profile_content(&self, profile: &AppArmorProfile) -> String {
+    pub fn generate_profile_content(&self, profile: &AppArmorProfile) -> String {
         let mut content = format!("#include <tunables/global>\n\n");
         content.push_str(&format!("polis-{} {{\n", profile.name));
 
@@ -281,3 +294,203 @@ pub struct AppArmorStatus {
     pub profiles: Vec<String>,
     pub mode: String,
 }
+
+/// Permissões aceitas em regras de arquivo
+const FILE_PERMISSIONS: &str = "rwalkmixuUpPC";
+
+/// Gera o texto de um perfil AppArmor para um container.
+///
+/// Sem regras de capability o AppArmor nega todas, então só as liberadas
+/// com `allow_capability` ficam disponíveis.
+#[derive(Debug, Clone)]
+pub struct AppArmorProfileGenerator {
+    container_name: String,
+    network: bool,
+    denied_networks: Vec<String>,
+    capabilities: Vec<Capability>,
+    file_access: bool,
+    path_rules: Vec<(bool, String, String)>,
+    deny_mounts: bool,
+}
+
+impl AppArmorProfileGenerator {
+    pub fn new(container_name: &str) -> Self {
+        Self {
+            container_name: container_name.to_string(),
+            network: false,
+            denied_networks: Vec::new(),
+            capabilities: Vec::new(),
+            file_access: false,
+            path_rules: Vec::new(),
+            deny_mounts: false,
+        }
+    }
+
+    /// Base restritiva: rede liberada exceto sockets raw e packet, um
+    /// conjunto mínimo de capabilities, escrita negada em /proc e /sys e
+    /// nenhuma montagem
+    pub fn default_container_profile(container_name: &str) -> Self {
+        let mut generator = Self::new(container_name);
+        generator
+            .allow_network()
+            .deny_network("raw")
+            .deny_network("packet")
+            .allow_file_access()
+            .deny_path("/proc/sys/**", "wklx")
+            .deny_path("/proc/sysrq-trigger", "rwklx")
+            .deny_path("/proc/kcore", "rwklx")
+            .deny_path("/sys/**", "wklx")
+            .deny_path("/sys/firmware/**", "rwklx")
+            .deny_path("/sys/kernel/security/**", "rwklx")
+            .deny_mounts();
+        for capability in [
+            Capability::Chown,
+            Capability::DacOverride,
+            Capability::Fowner,
+            Capability::Fsetid,
+            Capability::Kill,
+            Capability::Setgid,
+            Capability::Setuid,
+            Capability::Setpcap,
+            Capability::NetBindService,
+            Capability::SysChroot,
+            Capability::AuditWrite,
+            Capability::Setfcap,
+        ] {
+            generator.allow_capability(capability);
+        }
+        generator
+    }
+
+    pub fn allow_path(&mut self, path: &str, permissions: &str) -> &mut Self {
+        self.path_rules
+            .push((false, path.to_string(), permissions.to_string()));
+        self
+    }
+
+    pub fn deny_path(&mut self, path: &str, permissions: &str) -> &mut Self {
+        self.path_rules
+            .push((true, path.to_string(), permissions.to_string()));
+        self
+    }
+
+    /// Libera o acesso a todos os arquivos; use `deny_path` para as exceções
+    pub fn allow_file_access(&mut self) -> &mut Self {
+        self.file_access = true;
+        self
+    }
+
+    pub fn allow_network(&mut self) -> &mut Self {
+        self.network = true;
+        self
+    }
+
+    /// Nega uma família (`inet6`, `packet`) ou tipo (`raw`) de socket
+    pub fn deny_network(&mut self, family: &str) -> &mut Self {
+        if !self.denied_networks.iter().any(|f| f == family) {
+            self.denied_networks.push(family.to_string());
+        }
+        self
+    }
+
+    pub fn allow_capability(&mut self, cap: Capability) -> &mut Self {
+        if !self.capabilities.contains(&cap) {
+            self.capabilities.push(cap);
+        }
+        self
+    }
+
+    pub fn deny_mounts(&mut self) -> &mut Self {
+        self.deny_mounts = true;
+        self
+    }
+
+    /// Nome do perfil no kernel
+    pub fn profile_name(&self) -> String {
+        format!("polis-{}", self.container_name)
+    }
+
+    pub fn generate(&self) -> Result<String> {
+        let valid_name = !self.container_name.is_empty()
+            && self
+                .container_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid_name {
+            return Err(PolisError::Security(format!(
+                "Nome de container inválido para perfil AppArmor: '{}'",
+                self.container_name
+            )));
+        }
+        for family in &self.denied_networks {
+            if family.is_empty() || !family.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(PolisError::Security(format!(
+                    "Família de rede inválida: '{}'",
+                    family
+                )));
+            }
+        }
+        for (_, path, permissions) in &self.path_rules {
+            let valid_path = (path.starts_with('/') || path.starts_with("@{"))
+                && !path
+                    .chars()
+                    .any(|c| c.is_whitespace() || matches!(c, ',' | '#' | '"'));
+            if !valid_path {
+                return Err(PolisError::Security(format!(
+                    "Caminho inválido em regra AppArmor: '{}'",
+                    path
+                )));
+            }
+            if permissions.is_empty() || !permissions.chars().all(|c| FILE_PERMISSIONS.contains(c))
+            {
+                return Err(PolisError::Security(format!(
+                    "Permissões inválidas para '{}': '{}'",
+                    path, permissions
+                )));
+            }
+        }
+
+        let mut sections: Vec<Vec<String>> = Vec::new();
+        let mut network = Vec::new();
+        if self.network {
+            network.push("network,".to_string());
+        }
+        for family in &self.denied_networks {
+            network.push(format!("deny network {},", family));
+        }
+        sections.push(network);
+        sections.push(
+            self.capabilities
+                .iter()
+                .map(|cap| format!("capability {},", cap.name()))
+                .collect(),
+        );
+        let mut files = Vec::new();
+        if self.file_access {
+            files.push("file,".to_string());
+        }
+        for (deny, path, permissions) in &self.path_rules {
+            let prefix = if *deny { "deny " } else { "" };
+            files.push(format!("{}{} {},", prefix, path, permissions));
+        }
+        sections.push(files);
+        if self.deny_mounts {
+            sections.push(vec!["deny mount,".to_string(), "deny umount,".to_string()]);
+        }
+
+        let mut profile = String::from("#include <tunables/global>\n\n");
+        profile.push_str(&format!(
+            "profile {} flags=(attach_disconnected,mediate_deleted) {{\n",
+            self.profile_name()
+        ));
+        profile.push_str("  #include <abstractions/base>\n");
+        for section in sections.iter().filter(|section| !section.is_empty()) {
+            profile.push('\n');
+            for rule in section {
+                profile.push_str(&format!("  {}\n", rule));
+            }
+        }
+        profile.push_str("}\n");
+        Ok(profile)
+    }
+}
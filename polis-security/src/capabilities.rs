@@ -45,6 +45,52 @@ pub enum Capability {
     AuditRead,
 }
 
+impl Capability {
+    /// Nome da capability no kernel, sem o prefixo `CAP_` (ex.: `net_bind_service`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capability::Chown => "chown",
+            Capability::DacOverride => "dac_override",
+            Capability::DacReadSearch => "dac_read_search",
+            Capability::Fowner => "fowner",
+            Capability::Fsetid => "fsetid",
+            Capability::Kill => "kill",
+            Capability::Setgid => "setgid",
+            Capability::Setuid => "setuid",
+            Capability::Setpcap => "setpcap",
+            Capability::LinuxImmutable => "linux_immutable",
+            Capability::NetBindService => "net_bind_service",
+            Capability::NetBroadcast => "net_broadcast",
+            Capability::NetAdmin => "net_admin",
+            Capability::NetRaw => "net_raw",
+            Capability::IpcLock => "ipc_lock",
+            Capability::IpcOwner => "ipc_owner",
+            Capability::SysModule => "sys_module",
+            Capability::SysRawio => "sys_rawio",
+            Capability::SysChroot => "sys_chroot",
+            Capability::SysPtrace => "sys_ptrace",
+            Capability::SysPacct => "sys_pacct",
+            Capability::SysAdmin => "sys_admin",
+            Capability::SysBoot => "sys_boot",
+            Capability::SysNice => "sys_nice",
+            Capability::SysResource => "sys_resource",
+            Capability::SysTime => "sys_time",
+            Capability::SysTtyConfig => "sys_tty_config",
+            Capability::Mknod => "mknod",
+            Capability::Lease => "lease",
+            Capability::AuditWrite => "audit_write",
+            Capability::AuditControl => "audit_control",
+            Capability::Setfcap => "setfcap",
+            Capability::MacOverride => "mac_override",
+            Capability::MacAdmin => "mac_admin",
+            Capability::Syslog => "syslog",
+            Capability::WakeAlarm => "wake_alarm",
+            Capability::BlockSuspend => "block_suspend",
+            Capability::AuditRead => "audit_read",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CapabilitySet {
     pub effective: HashSet<Capability>,
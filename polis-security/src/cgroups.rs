@@ -1,9 +1,28 @@
 use polis_core::{PolisError, ResourceLimits, Result};
 use std::fs;
-use std::path::PathBuf;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Período padrão da quota de CPU, em microssegundos
+pub const DEFAULT_CPU_PERIOD: u64 = 100_000;
+/// Controladores v1 em que os containers ganham um cgroup
+pub const CGROUP_V1_CONTROLLERS: [&str; 4] = ["memory", "cpu", "cpuacct", "pids"];
+/// Grupo pai dos containers em cada controlador v1
+const CGROUP_V1_PARENT: &str = "polis";
+
+/// Hierarquia de cgroups em que os limites são aplicados
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgroupVersion {
+    /// Uma hierarquia por controlador (`memory/`, `cpu/`, ...)
+    V1,
+    /// Hierarquia unificada
+    V2,
+}
 
 pub struct CgroupManager {
+    /// v2: diretório pai dos cgroups dos containers; v1: ponto de montagem
     cgroup_path: PathBuf,
+    version: CgroupVersion,
     cgroups: Vec<CgroupInfo>,
 }
 
@@ -14,62 +33,231 @@ pub struct CgroupInfo {
     pub limits: ResourceLimits,
 }
 
+/// Arquivos de um cgroup v2 e os valores que aplicam `limits`
+pub fn cgroup_v2_settings(limits: &ResourceLimits) -> Result<Vec<(&'static str, String)>> {
+    let mut settings = Vec::new();
+    if let Some(memory) = limits.memory_limit {
+        settings.push(("memory.max", memory.to_string()));
+        // No v2 o swap é limitado à parte, sem incluir a memória
+        if let Some(total) = limits.memory_swap {
+            settings.push(("memory.swap.max", total.saturating_sub(memory).to_string()));
+        }
+    }
+    if let Some((quota, period)) = cpu_quota_us(limits)? {
+        settings.push(("cpu.max", format!("{} {}", quota, period)));
+    }
+    if let Some(shares) = limits.cpu_shares {
+        settings.push(("cpu.weight", shares_to_weight(shares).to_string()));
+    }
+    if let Some(pids) = limits.pids_limit {
+        settings.push(("pids.max", pids_value(pids)));
+    }
+    Ok(settings)
+}
+
+/// Controlador, arquivo e valor de cada limite em um cgroup v1
+pub fn cgroup_v1_settings(
+    limits: &ResourceLimits,
+) -> Result<Vec<(&'static str, &'static str, String)>> {
+    let mut settings = Vec::new();
+    if let Some(memory) = limits.memory_limit {
+        settings.push(("memory", "memory.limit_in_bytes", memory.to_string()));
+        if let Some(total) = limits.memory_swap {
+            settings.push((
+                "memory",
+                "memory.memsw.limit_in_bytes",
+                total.max(memory).to_string(),
+            ));
+        }
+    }
+    if let Some((quota, period)) = cpu_quota_us(limits)? {
+        settings.push(("cpu", "cpu.cfs_period_us", period.to_string()));
+        settings.push(("cpu", "cpu.cfs_quota_us", quota.to_string()));
+    }
+    if let Some(shares) = limits.cpu_shares {
+        settings.push(("cpu", "cpu.shares", shares.clamp(2, 262_144).to_string()));
+    }
+    if let Some(pids) = limits.pids_limit {
+        settings.push(("pids", "pids.max", pids_value(pids)));
+    }
+    Ok(settings)
+}
+
+/// Quota e período de CPU em microssegundos a partir da fração de CPUs
+fn cpu_quota_us(limits: &ResourceLimits) -> Result<Option<(u64, u64)>> {
+    let Some(cpus) = limits.cpu_quota else {
+        return Ok(None);
+    };
+    if !cpus.is_finite() || cpus <= 0.0 {
+        return Err(PolisError::Security(format!(
+            "Quota de CPU inválida: {}",
+            cpus
+        )));
+    }
+    let period = limits.cpu_period.unwrap_or(DEFAULT_CPU_PERIOD);
+    // O kernel não aceita quotas abaixo de 1ms
+    let quota = ((cpus * period as f64).round() as u64).max(1_000);
+    Ok(Some((quota, period)))
+}
+
+/// Converte `cpu.shares` (2..262144) para `cpu.weight` (1..10000)
+fn shares_to_weight(shares: u64) -> u64 {
+    let shares = shares.clamp(2, 262_144);
+    1 + (shares - 2) * 9_999 / 262_142
+}
+
+fn pids_value(pids: i64) -> String {
+    if pids > 0 {
+        pids.to_string()
+    } else {
+        "max".to_string()
+    }
+}
+
+fn write_setting(path: &Path, value: &str) -> Result<()> {
+    fs::write(path, value).map_err(|e| {
+        PolisError::Security(format!(
+            "Erro ao gravar {} em {}: {}",
+            value,
+            path.display(),
+            e
+        ))
+    })
+}
+
 impl CgroupManager {
+    /// Cgroups v2 criados dentro de `cgroup_path`
     pub fn new(cgroup_path: PathBuf) -> Self {
         Self {
             cgroup_path,
+            version: CgroupVersion::V2,
+            cgroups: Vec::new(),
+        }
+    }
+
+    /// Cgroups v1 criados em `<mount>/<controlador>/polis`
+    pub fn v1(mount: PathBuf) -> Self {
+        Self {
+            cgroup_path: mount,
+            version: CgroupVersion::V1,
             cgroups: Vec::new(),
         }
     }
 
+    /// Usa a hierarquia montada em `mount`: v2 em `cgroup_root` quando
+    /// disponível, senão v1. `None` se não houver cgroups montados.
+    pub fn detect(mount: &Path, cgroup_root: PathBuf) -> Option<Self> {
+        if mount.join("cgroup.controllers").is_file() {
+            Some(Self::new(cgroup_root))
+        } else if mount.join("memory").is_dir() {
+            Some(Self::v1(mount.to_path_buf()))
+        } else {
+            None
+        }
+    }
+
+    pub fn version(&self) -> CgroupVersion {
+        self.version
+    }
+
+    /// Diretórios do cgroup `name`: um no v2, um por controlador montado no v1
+    pub fn cgroup_dirs(&self, name: &str) -> Vec<PathBuf> {
+        match self.version {
+            CgroupVersion::V2 => vec![self.cgroup_path.join(name)],
+            CgroupVersion::V1 => CGROUP_V1_CONTROLLERS
+                .iter()
+                .map(|controller| self.cgroup_path.join(controller))
+                .filter(|controller| controller.is_dir())
+                .map(|controller| controller.join(CGROUP_V1_PARENT).join(name))
+                .collect(),
+        }
+    }
+
     pub async fn create_cgroup(
         &mut self,
         name: &str,
         limits: ResourceLimits,
     ) -> Result<CgroupInfo> {
-        let cgroup_path = self.cgroup_path.join(name);
+        let dirs = self.cgroup_dirs(name);
+        let cgroup_path = dirs.first().cloned().ok_or_else(|| {
+            PolisError::Security("Nenhum controlador de cgroup montado".to_string())
+        })?;
 
-        // Create cgroup directory
-        fs::create_dir_all(&cgroup_path)
-            .map_err(|e| PolisError::Security(format!("Erro ao criar cgroup: {}", e)))?;
+        if self.version == CgroupVersion::V2 {
+            self.enable_controllers(&limits)?;
+        }
+        for dir in &dirs {
+            fs::create_dir_all(dir)
+                .map_err(|e| PolisError::Security(format!("Erro ao criar cgroup: {}", e)))?;
+        }
 
         let cgroup_info = CgroupInfo {
             name: name.to_string(),
-            path: cgroup_path.clone(),
-            limits: limits.clone(),
+            path: cgroup_path,
+            limits,
         };
 
         // Apply resource limits
         self.apply_limits(&cgroup_info).await?;
 
+        self.cgroups.retain(|c| c.name != name);
         self.cgroups.push(cgroup_info.clone());
         Ok(cgroup_info)
     }
 
-    pub async fn apply_limits(&self, cgroup_info: &CgroupInfo) -> Result<()> {
-        // This is a simplified implementation for demonstration
-        // In a real implementation, you would use cgroups v1/v2 APIs
-
-        println!(
-            " Aplicando limites de recursos para cgroup: {}",
-            cgroup_info.name
-        );
-
-        if let Some(memory_limit) = cgroup_info.limits.memory_limit {
-            println!("  - Limite de memória: {} bytes", memory_limit);
+    /// Habilita no pai os controladores que os limites usam; no v2 os
+    /// arquivos de um controlador só existem nos filhos após isso
+    fn enable_controllers(&self, limits: &ResourceLimits) -> Result<()> {
+        let mut controllers = Vec::new();
+        if limits.memory_limit.is_some() {
+            controllers.push("+memory");
         }
-
-        if let Some(cpu_quota) = cgroup_info.limits.cpu_quota {
-            println!("  - Quota de CPU: {}", cpu_quota);
+        if limits.cpu_quota.is_some() || limits.cpu_shares.is_some() {
+            controllers.push("+cpu");
         }
-
-        if let Some(pids_limit) = cgroup_info.limits.pids_limit {
-            println!("  - Limite de PIDs: {}", pids_limit);
+        if limits.pids_limit.is_some() {
+            controllers.push("+pids");
         }
+        if controllers.is_empty() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.cgroup_path)
+            .map_err(|e| PolisError::Security(format!("Erro ao criar cgroup: {}", e)))?;
+        write_setting(
+            &self.cgroup_path.join("cgroup.subtree_control"),
+            &controllers.join(" "),
+        )
+    }
 
+    pub async fn apply_limits(&self, cgroup_info: &CgroupInfo) -> Result<()> {
+        match self.version {
+            CgroupVersion::V2 => {
+                for (file, value) in cgroup_v2_settings(&cgroup_info.limits)? {
+                    write_setting(&cgroup_info.path.join(file), &value)?;
+                }
+            }
+            CgroupVersion::V1 => {
+                for (controller, file, value) in cgroup_v1_settings(&cgroup_info.limits)? {
+                    let dir = self
+                        .cgroup_path
+                        .join(controller)
+                        .join(CGROUP_V1_PARENT)
+                        .join(&cgroup_info.name);
+                    if !dir.is_dir() {
+                        return Err(PolisError::Security(format!(
+                            "Controlador de cgroup {} não está montado",
+                            controller
+                        )));
+                    }
+                    write_setting(&dir.join(file), &value)?;
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Move o processo `pid` para o cgroup
     pub async fn add_process(&self, cgroup_name: &str, pid: u32) -> Result<()> {
         let _cgroup_info = self
             .cgroups
@@ -77,8 +265,9 @@ impl CgroupManager {
             .find(|c| c.name == cgroup_name)
             .ok_or_else(|| PolisError::Security("Cgroup não encontrado".to_string()))?;
 
-        // This is a simplified implementation for demonstration
-        println!(" Adicionando processo {} ao cgroup {}", pid, cgroup_name);
+        for dir in self.cgroup_dirs(cgroup_name) {
+            write_setting(&dir.join("cgroup.procs"), &pid.to_string())?;
+        }
         Ok(())
     }
 
@@ -95,15 +284,20 @@ impl CgroupManager {
     }
 
     pub async fn delete_cgroup(&mut self, name: &str) -> Result<()> {
-        let cgroup_info = self
-            .cgroups
-            .iter()
-            .find(|c| c.name == name)
-            .ok_or_else(|| PolisError::Security("Cgroup não encontrado".to_string()))?;
+        if !self.cgroups.iter().any(|c| c.name == name) {
+            return Err(PolisError::Security("Cgroup não encontrado".to_string()));
+        }
 
-        // Remove cgroup directory
-        fs::remove_dir_all(&cgroup_info.path)
-            .map_err(|e| PolisError::Security(format!("Erro ao remover cgroup: {}", e)))?;
+        for dir in self.cgroup_dirs(name) {
+            // Um cgroup real é removido com rmdir mesmo contendo os arquivos
+            // de interface; diretórios comuns precisam da remoção recursiva
+            let removed = match fs::remove_dir(&dir) {
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+                Err(e) if e.kind() == ErrorKind::DirectoryNotEmpty => fs::remove_dir_all(&dir),
+                result => result,
+            };
+            removed.map_err(|e| PolisError::Security(format!("Erro ao remover cgroup: {}", e)))?;
+        }
 
         // Remove from list
         self.cgroups.retain(|c| c.name != name);
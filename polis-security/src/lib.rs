@@ -6,6 +6,7 @@ pub mod sandbox;
 pub mod seccomp;
 pub mod security_manager;
 pub mod selinux;
+mod syscalls;
 
 pub use apparmor::*;
 pub use capabilities::*;
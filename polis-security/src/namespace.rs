@@ -1,4 +1,8 @@
 use polis_core::{PolisError, Result};
+use std::path::{Path, PathBuf};
+
+/// Máximo de linhas aceito pelo kernel em `uid_map` e `gid_map`
+pub const MAX_ID_MAPPINGS: usize = 340;
 
 #[derive(Debug, Clone)]
 pub struct NamespaceInfo {
@@ -18,18 +22,200 @@ pub enum NamespaceType {
     Cgroup,
 }
 
-#[derive(Default)]
+/// Faixa de IDs de um namespace de usuário: `size` IDs a partir de
+/// `container_id` correspondem aos IDs a partir de `host_id` no host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdMapping {
+    pub container_id: u32,
+    pub host_id: u32,
+    pub size: u32,
+}
+
+impl IdMapping {
+    /// Linha no formato de `/proc/<pid>/uid_map`
+    pub fn to_map_line(&self) -> String {
+        format!("{} {} {}\n", self.container_id, self.host_id, self.size)
+    }
+
+    fn container_end(&self) -> u64 {
+        self.container_id as u64 + self.size as u64
+    }
+
+    fn host_end(&self) -> u64 {
+        self.host_id as u64 + self.size as u64
+    }
+}
+
+/// Mapas de UID e GID de um namespace de usuário
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserNamespaceConfig {
+    pub uid_mappings: Vec<IdMapping>,
+    pub gid_mappings: Vec<IdMapping>,
+}
+
+impl UserNamespaceConfig {
+    /// Mapeia o root do container e os IDs seguintes para as faixas
+    /// subordinadas de `user` em `/etc/subuid` e `/etc/subgid`
+    pub fn subid_range(user: &str) -> Result<Self> {
+        Self::subid_range_from(user, Path::new("/etc/subuid"), Path::new("/etc/subgid"))
+    }
+
+    /// Como `subid_range`, lendo as faixas de outros arquivos
+    pub fn subid_range_from(user: &str, subuid: &Path, subgid: &Path) -> Result<Self> {
+        let range = |path: &Path| {
+            read_subordinate_ids(path, &[user])?.ok_or_else(|| {
+                PolisError::Security(format!(
+                    "Usuário '{}' não tem faixa subordinada em {}",
+                    user,
+                    path.display()
+                ))
+            })
+        };
+        let mapping = |(start, count)| IdMapping {
+            container_id: 0,
+            host_id: start,
+            size: count,
+        };
+        Ok(Self {
+            uid_mappings: vec![mapping(range(subuid)?)],
+            gid_mappings: vec![mapping(range(subgid)?)],
+        })
+    }
+
+    /// Rejeita mapas vazios, faixas de tamanho zero ou além de `u32`, mais de
+    /// `MAX_ID_MAPPINGS` faixas e faixas sobrepostas, que o kernel recusaria
+    pub fn validate(&self) -> Result<()> {
+        validate_mappings("UID", &self.uid_mappings)?;
+        validate_mappings("GID", &self.gid_mappings)
+    }
+
+    /// Conteúdo de `/proc/<pid>/uid_map`
+    pub fn uid_map(&self) -> String {
+        self.uid_mappings
+            .iter()
+            .map(IdMapping::to_map_line)
+            .collect()
+    }
+
+    /// Conteúdo de `/proc/<pid>/gid_map`
+    pub fn gid_map(&self) -> String {
+        self.gid_mappings
+            .iter()
+            .map(IdMapping::to_map_line)
+            .collect()
+    }
+}
+
+fn validate_mappings(kind: &str, mappings: &[IdMapping]) -> Result<()> {
+    let invalid = |reason: String| {
+        Err(PolisError::Security(format!(
+            "Mapa de {} inválido: {}",
+            kind, reason
+        )))
+    };
+    if mappings.is_empty() {
+        return invalid("nenhuma faixa".to_string());
+    }
+    if mappings.len() > MAX_ID_MAPPINGS {
+        return invalid(format!("mais de {} faixas", MAX_ID_MAPPINGS));
+    }
+    for mapping in mappings {
+        if mapping.size == 0 {
+            return invalid(format!("faixa vazia em {}", mapping.to_map_line().trim()));
+        }
+        if mapping.container_end() > u32::MAX as u64 + 1 || mapping.host_end() > u32::MAX as u64 + 1
+        {
+            return invalid(format!(
+                "faixa {} ultrapassa o maior ID",
+                mapping.to_map_line().trim()
+            ));
+        }
+    }
+    for (i, a) in mappings.iter().enumerate() {
+        for b in &mappings[i + 1..] {
+            let container_overlap = a.container_end() > b.container_id as u64
+                && b.container_end() > a.container_id as u64;
+            let host_overlap = a.host_end() > b.host_id as u64 && b.host_end() > a.host_id as u64;
+            if container_overlap || host_overlap {
+                return invalid(format!(
+                    "faixas {} e {} se sobrepõem",
+                    a.to_map_line().trim(),
+                    b.to_map_line().trim()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Faixa subordinada `(início, quantidade)` do primeiro de `owners` presente
+/// em um arquivo no formato de `/etc/subuid` e `/etc/subgid`; o dono aparece
+/// pelo nome ou pelo ID. Um arquivo inexistente não tem faixas.
+pub fn read_subordinate_ids(path: &Path, owners: &[&str]) -> Result<Option<(u32, u32)>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    for line in content.lines().map(str::trim) {
+        let fields: Vec<&str> = line.split(':').collect();
+        let [owner, start, count] = fields.as_slice() else {
+            continue;
+        };
+        if !owners.contains(owner) {
+            continue;
+        }
+        if let (Ok(start), Ok(count)) = (start.parse(), count.parse()) {
+            return Ok(Some((start, count)));
+        }
+    }
+    Ok(None)
+}
+
 pub struct NamespaceManager {
     namespaces: Vec<NamespaceInfo>,
+    proc_root: PathBuf,
+}
+
+impl Default for NamespaceManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl NamespaceManager {
     pub fn new() -> Self {
         Self {
             namespaces: Vec::new(),
+            proc_root: PathBuf::from("/proc"),
         }
     }
 
+    /// Usa outro `/proc`, p. ex. um diretório temporário em testes
+    pub fn with_proc_root(mut self, proc_root: impl Into<PathBuf>) -> Self {
+        self.proc_root = proc_root.into();
+        self
+    }
+
+    /// Grava os mapas de UID e GID do namespace de usuário do processo `pid`.
+    /// Só pode ser feito uma vez por namespace, por um processo com
+    /// `CAP_SETUID`/`CAP_SETGID` no namespace pai.
+    pub fn configure_user_ns(&self, pid: u32, config: &UserNamespaceConfig) -> Result<()> {
+        config.validate()?;
+        let proc_dir = self.proc_root.join(pid.to_string());
+        for (file, content) in [("uid_map", config.uid_map()), ("gid_map", config.gid_map())] {
+            // O kernel exige o mapa inteiro numa única escrita
+            std::fs::write(proc_dir.join(file), content).map_err(|e| {
+                PolisError::Security(format!(
+                    "Erro ao gravar {}: {}",
+                    proc_dir.join(file).display(),
+                    e
+                ))
+            })?;
+        }
+        Ok(())
+    }
+
     pub async fn create_namespace(
         &mut self,
         namespace_type: NamespaceType,
@@ -1,4 +1,8 @@
-use polis_core::Result;
+use crate::{read_subordinate_ids, IdMapping, NamespaceManager, UserNamespaceConfig};
+use polis_core::{PolisError, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
 
 #[derive(Default)]
 pub struct SandboxManager {
@@ -15,3 +19,271 @@ impl SandboxManager {
         Ok(())
     }
 }
+
+/// Como os cgroups de um container sem root são criados
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CgroupDelegation {
+    /// O helper `newcgroup` cria o cgroup delegado e imprime o seu caminho
+    NewCgroup(PathBuf),
+    /// Subárvore cgroup v2 delegada ao usuário (ex.: `user@<uid>.service`)
+    UserDelegated(PathBuf),
+    /// Sem delegação: os limites de recursos não são aplicados
+    Unavailable,
+}
+
+impl CgroupDelegation {
+    /// Prefere o `newcgroup` do PATH; sem ele, usa a subárvore do usuário sob
+    /// `cgroup_root` se ela for cgroup v2 e gravável
+    pub fn detect(uid: u32, cgroup_root: &Path) -> Self {
+        if let Some(helper) = find_in_path("newcgroup") {
+            return Self::NewCgroup(helper);
+        }
+        if !cgroup_root.join("cgroup.controllers").exists() {
+            return Self::Unavailable;
+        }
+        let user_dir = cgroup_root
+            .join("user.slice")
+            .join(format!("user-{}.slice", uid))
+            .join(format!("user@{}.service", uid));
+        if is_writable(&user_dir) {
+            Self::UserDelegated(user_dir)
+        } else {
+            Self::Unavailable
+        }
+    }
+
+    /// Cria o cgroup `name` delegado ao usuário e devolve o seu caminho
+    pub fn create_cgroup(&self, name: &str) -> Result<PathBuf> {
+        match self {
+            Self::NewCgroup(helper) => {
+                let output = Command::new(helper).arg(name).output()?;
+                if !output.status.success() {
+                    return Err(PolisError::Security(format!(
+                        "newcgroup falhou para '{}': {}",
+                        name,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                Ok(PathBuf::from(
+                    String::from_utf8_lossy(&output.stdout).trim(),
+                ))
+            }
+            Self::UserDelegated(root) => {
+                let path = root.join(name);
+                std::fs::create_dir_all(&path).map_err(|e| {
+                    PolisError::Security(format!("Erro ao criar cgroup delegado: {}", e))
+                })?;
+                Ok(path)
+            }
+            Self::Unavailable => Err(PolisError::Security(
+                "Nenhuma delegação de cgroup disponível para o usuário".to_string(),
+            )),
+        }
+    }
+}
+
+/// Sandbox de um container executado sem root: o usuário do host vira root
+/// dentro de um namespace de usuário próprio
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    uid: u32,
+    gid: u32,
+    user_ns: UserNamespaceConfig,
+    cgroup: CgroupDelegation,
+}
+
+impl Sandbox {
+    /// Prepara um sandbox para o usuário `uid`/`gid` do host. O ID 0 do
+    /// container corresponde a ele; as faixas de `/etc/subuid` e
+    /// `/etc/subgid` cobrem os demais IDs quando `newuidmap`/`newgidmap`
+    /// estão instalados.
+    pub fn new_rootless(uid: u32, gid: u32) -> Result<Self> {
+        let name = user_name(uid);
+        let owners = |id: u32| {
+            let mut owners = vec![id.to_string()];
+            owners.extend(name.clone());
+            owners
+        };
+        let helpers = find_in_path("newuidmap").is_some() && find_in_path("newgidmap").is_some();
+        let (sub_uids, sub_gids) = if helpers {
+            (
+                subordinate_ids("/etc/subuid", &owners(uid))?,
+                subordinate_ids("/etc/subgid", &owners(gid))?,
+            )
+        } else {
+            (None, None)
+        };
+        if !helpers {
+            tracing::warn!(
+                "newuidmap/newgidmap não encontrados: apenas o usuário {} é mapeado",
+                uid
+            );
+        }
+
+        Ok(Self {
+            uid,
+            gid,
+            user_ns: UserNamespaceConfig {
+                uid_mappings: id_map(uid, sub_uids),
+                gid_mappings: id_map(gid, sub_gids),
+            },
+            cgroup: CgroupDelegation::detect(uid, Path::new("/sys/fs/cgroup")),
+        })
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    pub fn uid_map(&self) -> &[IdMapping] {
+        &self.user_ns.uid_mappings
+    }
+
+    pub fn gid_map(&self) -> &[IdMapping] {
+        &self.user_ns.gid_mappings
+    }
+
+    pub fn user_namespace(&self) -> &UserNamespaceConfig {
+        &self.user_ns
+    }
+
+    pub fn cgroup_delegation(&self) -> &CgroupDelegation {
+        &self.cgroup
+    }
+
+    /// Move o processo atual para um novo namespace de usuário e grava os
+    /// mapas de UID/GID. Deve ser chamado antes de criar qualquer outro
+    /// namespace, para que eles pertençam ao namespace de usuário, e com o
+    /// processo ainda com uma única thread, como exige `unshare(2)`.
+    pub fn enter_rootless(&self) -> Result<()> {
+        // Faixas subordinadas só podem ser gravadas por newuidmap/newgidmap
+        // rodando fora do novo namespace, então o helper nasce antes dele
+        let helper = if self.uses_subordinate_ids() {
+            Some(self.spawn_map_helper()?)
+        } else {
+            None
+        };
+
+        if unsafe { libc::unshare(libc::CLONE_NEWUSER) } != 0 {
+            let error = std::io::Error::last_os_error();
+            if let Some(mut helper) = helper {
+                let _ = helper.kill();
+                let _ = helper.wait();
+            }
+            return Err(PolisError::Security(format!(
+                "Erro ao criar namespace de usuário: {}",
+                error
+            )));
+        }
+
+        match helper {
+            Some(helper) => Self::finish_map_helper(helper),
+            None => self.write_own_maps(),
+        }
+    }
+
+    fn uses_subordinate_ids(&self) -> bool {
+        self.uid_map().len() > 1 || self.gid_map().len() > 1
+    }
+
+    /// Sem faixas subordinadas, o próprio processo pode mapear o seu usuário,
+    /// desde que negue `setgroups` antes de gravar o mapa de GIDs
+    fn write_own_maps(&self) -> Result<()> {
+        std::fs::write("/proc/self/setgroups", "deny").map_err(|e| {
+            PolisError::Security(format!("Erro ao gravar /proc/self/setgroups: {}", e))
+        })?;
+        NamespaceManager::new().configure_user_ns(std::process::id(), &self.user_ns)
+    }
+
+    /// Helper que espera o processo entrar no namespace (uma linha na
+    /// entrada padrão) e então mapeia os IDs dele
+    fn spawn_map_helper(&self) -> Result<Child> {
+        let pid = std::process::id().to_string();
+        let args = |map: &[IdMapping]| {
+            map.iter()
+                .map(|m| format!("{} {} {}", m.container_id, m.host_id, m.size))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let script = format!(
+            "read _ && newuidmap {pid} {} && newgidmap {pid} {}",
+            args(self.uid_map()),
+            args(self.gid_map()),
+        );
+        Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                PolisError::Security(format!("Erro ao iniciar newuidmap/newgidmap: {}", e))
+            })
+    }
+
+    fn finish_map_helper(mut helper: Child) -> Result<()> {
+        if let Some(mut stdin) = helper.stdin.take() {
+            stdin.write_all(b"\n")?;
+        }
+        let output = helper.wait_with_output()?;
+        if !output.status.success() {
+            return Err(PolisError::Security(format!(
+                "Erro ao mapear UIDs/GIDs: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn id_map(id: u32, subordinate: Option<(u32, u32)>) -> Vec<IdMapping> {
+    let mut map = vec![IdMapping {
+        container_id: 0,
+        host_id: id,
+        size: 1,
+    }];
+    if let Some((start, count)) = subordinate.filter(|(_, count)| *count > 0) {
+        map.push(IdMapping {
+            container_id: 1,
+            host_id: start,
+            size: count,
+        });
+    }
+    map
+}
+
+fn subordinate_ids(path: &str, owners: &[String]) -> Result<Option<(u32, u32)>> {
+    let owners: Vec<&str> = owners.iter().map(String::as_str).collect();
+    read_subordinate_ids(Path::new(path), &owners)
+}
+
+fn user_name(uid: u32) -> Option<String> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    let uid = uid.to_string();
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        (fields.nth(1)? == uid).then(|| name.to_string())
+    })
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|path| path.is_file())
+    })
+}
+
+fn is_writable(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
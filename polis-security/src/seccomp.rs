@@ -1,22 +1,226 @@
+use crate::syscalls;
 use polis_core::{PolisError, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Perfil seccomp, serializado no formato JSON da especificação OCI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SeccompProfile {
+    #[serde(default, skip_serializing)]
     pub name: String,
     pub default_action: SeccompAction,
     pub syscalls: Vec<SeccompRule>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl SeccompProfile {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Compila o perfil num programa BPF clássico para a arquitetura do
+    /// host, no formato aceito por `seccomp(SECCOMP_SET_MODE_FILTER)`
+    pub fn to_bpf_bytes(&self) -> Result<Vec<u8>> {
+        let mut program = bpf_header()?;
+
+        for rule in &self.syscalls {
+            if rule.args.as_ref().is_some_and(|args| !args.is_empty()) {
+                return Err(PolisError::Security(format!(
+                    "Filtros por argumento não são suportados no BPF do perfil Seccomp '{}'",
+                    self.name
+                )));
+            }
+            for name in &rule.names {
+                let nr = syscalls::number(name).ok_or_else(|| {
+                    PolisError::Security(format!(
+                        "Syscall '{}' desconhecida na arquitetura do host",
+                        name
+                    ))
+                })?;
+                program.push(BpfInstruction::jump(BPF_JMP_JEQ_K, nr, 0, 1));
+                program.push(BpfInstruction::ret(rule.action.ret_value()));
+            }
+        }
+        program.push(BpfInstruction::ret(self.default_action.ret_value()));
+
+        if program.len() > BPF_MAXINSNS {
+            return Err(PolisError::Security(format!(
+                "Perfil Seccomp '{}' excede o limite de {} instruções BPF",
+                self.name, BPF_MAXINSNS
+            )));
+        }
+        Ok(program
+            .into_iter()
+            .flat_map(BpfInstruction::to_bytes)
+            .collect())
+    }
+
+    /// Reconstrói o perfil a partir de um programa gerado por
+    /// [`SeccompProfile::to_bpf_bytes`]. Syscalls consecutivas com a mesma
+    /// ação voltam na mesma regra; o nome do perfil não faz parte do BPF.
+    pub fn from_bpf_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = |reason: &str| {
+            PolisError::Security(format!("Programa Seccomp BPF inválido: {}", reason))
+        };
+
+        if bytes.is_empty() || !bytes.len().is_multiple_of(BPF_INSTRUCTION_SIZE) {
+            return Err(invalid("tamanho não é múltiplo de uma instrução"));
+        }
+        let program: Vec<BpfInstruction> = bytes
+            .chunks_exact(BPF_INSTRUCTION_SIZE)
+            .map(BpfInstruction::from_bytes)
+            .collect();
+
+        let body = program
+            .strip_prefix(bpf_header()?.as_slice())
+            .ok_or_else(|| invalid("cabeçalho de arquitetura ausente ou de outra arquitetura"))?;
+        let (default, checks) = body
+            .split_last()
+            .ok_or_else(|| invalid("ação padrão ausente"))?;
+        if default.code != BPF_RET_K || checks.len() % 2 != 0 {
+            return Err(invalid("ação padrão ausente"));
+        }
+
+        let mut rules: Vec<SeccompRule> = Vec::new();
+        for check in checks.chunks_exact(2) {
+            let (jump, ret) = (&check[0], &check[1]);
+            if jump.code != BPF_JMP_JEQ_K || jump.jt != 0 || jump.jf != 1 || ret.code != BPF_RET_K {
+                return Err(invalid("instrução inesperada na lista de syscalls"));
+            }
+            let name = syscalls::name(jump.k).ok_or_else(|| {
+                PolisError::Security(format!(
+                    "Syscall número {} desconhecida na arquitetura do host",
+                    jump.k
+                ))
+            })?;
+            let action = SeccompAction::from_ret_value(ret.k)
+                .ok_or_else(|| invalid("ação de retorno desconhecida"))?;
+
+            match rules.last_mut() {
+                Some(rule) if rule.action == action => rule.names.push(name.to_string()),
+                _ => rules.push(SeccompRule {
+                    names: vec![name.to_string()],
+                    action,
+                    args: None,
+                }),
+            }
+        }
+
+        Ok(Self {
+            name: String::new(),
+            default_action: SeccompAction::from_ret_value(default.k)
+                .ok_or_else(|| invalid("ação padrão desconhecida"))?,
+            syscalls: rules,
+        })
+    }
+}
+
+const BPF_INSTRUCTION_SIZE: usize = 8;
+/// Limite de instruções de um filtro imposto pelo kernel
+const BPF_MAXINSNS: usize = 4096;
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JMP_JEQ_K: u16 = 0x15;
+const BPF_JMP_JGE_K: u16 = 0x35;
+const BPF_RET_K: u16 = 0x06;
+
+/// Offsets em `struct seccomp_data`
+const SECCOMP_DATA_NR_OFFSET: u32 = 0;
+const SECCOMP_DATA_ARCH_OFFSET: u32 = 4;
+
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_KILL_THREAD: u32 = 0x0000_0000;
+const SECCOMP_RET_TRAP: u32 = 0x0003_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_TRACE: u32 = 0x7ff0_0000;
+const SECCOMP_RET_LOG: u32 = 0x7ffc_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+const SECCOMP_RET_ACTION_FULL: u32 = 0xffff_0000;
+
+/// Confere a arquitetura e carrega o número da syscall; chamadas de outra
+/// ABI (como x32) matam o processo em vez de cair nas regras
+fn bpf_header() -> Result<Vec<BpfInstruction>> {
+    let arch = syscalls::AUDIT_ARCH.ok_or_else(|| {
+        PolisError::Security(
+            "Arquitetura do host não suportada pelos filtros Seccomp BPF".to_string(),
+        )
+    })?;
+
+    let mut header = vec![
+        BpfInstruction::load(SECCOMP_DATA_ARCH_OFFSET),
+        BpfInstruction::jump(BPF_JMP_JEQ_K, arch, 1, 0),
+        BpfInstruction::ret(SECCOMP_RET_KILL_PROCESS),
+        BpfInstruction::load(SECCOMP_DATA_NR_OFFSET),
+    ];
+    if let Some(x32_bit) = syscalls::X32_SYSCALL_BIT {
+        header.push(BpfInstruction::jump(BPF_JMP_JGE_K, x32_bit, 0, 1));
+        header.push(BpfInstruction::ret(SECCOMP_RET_KILL_PROCESS));
+    }
+    Ok(header)
+}
+
+/// `struct sock_filter`, na ordem de bytes do host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BpfInstruction {
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32,
+}
+
+impl BpfInstruction {
+    fn load(offset: u32) -> Self {
+        Self {
+            code: BPF_LD_W_ABS,
+            jt: 0,
+            jf: 0,
+            k: offset,
+        }
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> Self {
+        Self { code, jt, jf, k }
+    }
+
+    fn ret(k: u32) -> Self {
+        Self {
+            code: BPF_RET_K,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; BPF_INSTRUCTION_SIZE] {
+        let mut bytes = [0; BPF_INSTRUCTION_SIZE];
+        bytes[0..2].copy_from_slice(&self.code.to_ne_bytes());
+        bytes[2] = self.jt;
+        bytes[3] = self.jf;
+        bytes[4..8].copy_from_slice(&self.k.to_ne_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            code: u16::from_ne_bytes([bytes[0], bytes[1]]),
+            jt: bytes[2],
+            jf: bytes[3],
+            k: u32::from_ne_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SeccompRule {
     pub names: Vec<String>,
     pub action: SeccompAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub args: Option<Vec<SeccompArg>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SeccompArg {
     pub index: u32,
     pub value: u64,
@@ -24,27 +228,169 @@ pub struct SeccompArg {
     pub op: SeccompOp,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SeccompAction {
+    #[serde(rename = "SCMP_ACT_ALLOW")]
     Allow,
+    /// Falha a syscall com erro
+    #[serde(rename = "SCMP_ACT_ERRNO")]
     Deny,
+    #[serde(rename = "SCMP_ACT_TRAP")]
     Trap,
+    #[serde(rename = "SCMP_ACT_KILL")]
     Kill,
+    #[serde(rename = "SCMP_ACT_TRACE")]
     Trace,
+    #[serde(rename = "SCMP_ACT_LOG")]
     Log,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl SeccompAction {
+    /// Valor `SECCOMP_RET_*` devolvido pelo filtro BPF
+    fn ret_value(self) -> u32 {
+        match self {
+            SeccompAction::Allow => SECCOMP_RET_ALLOW,
+            SeccompAction::Deny => SECCOMP_RET_ERRNO | libc::EPERM as u32,
+            SeccompAction::Trap => SECCOMP_RET_TRAP,
+            SeccompAction::Kill => SECCOMP_RET_KILL_THREAD,
+            SeccompAction::Trace => SECCOMP_RET_TRACE,
+            SeccompAction::Log => SECCOMP_RET_LOG,
+        }
+    }
+
+    fn from_ret_value(value: u32) -> Option<Self> {
+        match value & SECCOMP_RET_ACTION_FULL {
+            SECCOMP_RET_ALLOW => Some(SeccompAction::Allow),
+            SECCOMP_RET_ERRNO => Some(SeccompAction::Deny),
+            SECCOMP_RET_TRAP => Some(SeccompAction::Trap),
+            SECCOMP_RET_KILL_THREAD | SECCOMP_RET_KILL_PROCESS => Some(SeccompAction::Kill),
+            SECCOMP_RET_TRACE => Some(SeccompAction::Trace),
+            SECCOMP_RET_LOG => Some(SeccompAction::Log),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SeccompOp {
+    #[serde(rename = "SCMP_CMP_EQ")]
     Equals,
+    #[serde(rename = "SCMP_CMP_NE")]
     NotEquals,
+    #[serde(rename = "SCMP_CMP_GT")]
     GreaterThan,
+    #[serde(rename = "SCMP_CMP_GE")]
     GreaterThanOrEquals,
+    #[serde(rename = "SCMP_CMP_LT")]
     LessThan,
+    #[serde(rename = "SCMP_CMP_LE")]
     LessThanOrEquals,
+    #[serde(rename = "SCMP_CMP_MASKED_EQ")]
     MaskedEquals,
 }
 
+/// Monta um perfil que libera apenas as syscalls observadas numa execução
+/// do programa, p. ex. a saída de `strace -f -o app.strace <comando>`
+#[derive(Debug, Clone, Default)]
+pub struct SeccompProfileBuilder {
+    name: String,
+    syscalls: BTreeSet<String>,
+}
+
+impl SeccompProfileBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            syscalls: BTreeSet::new(),
+        }
+    }
+
+    /// Lê um log do strace; o perfil recebe o nome do arquivo
+    pub fn from_strace_log(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self::new(&name).with_strace_output(&content))
+    }
+
+    /// Adiciona as syscalls encontradas na saída do strace
+    pub fn with_strace_output(mut self, output: &str) -> Self {
+        self.syscalls
+            .extend(output.lines().filter_map(parse_strace_line));
+        self
+    }
+
+    pub fn with_syscall(mut self, name: &str) -> Self {
+        self.syscalls.insert(name.to_string());
+        self
+    }
+
+    /// Syscalls observadas, em ordem alfabética
+    pub fn syscalls(&self) -> Vec<&str> {
+        self.syscalls.iter().map(String::as_str).collect()
+    }
+
+    /// Perfil que libera as syscalls observadas e aplica `default_action` às
+    /// demais
+    pub fn build(&self, default_action: SeccompAction) -> Result<SeccompProfile> {
+        if default_action == SeccompAction::Allow {
+            return Err(PolisError::Security(
+                "A ação padrão de um perfil por lista de permissões não pode ser Allow".to_string(),
+            ));
+        }
+        if self.syscalls.is_empty() {
+            return Err(PolisError::Security(format!(
+                "Nenhuma syscall observada para o perfil Seccomp '{}'",
+                self.name
+            )));
+        }
+
+        Ok(SeccompProfile {
+            name: self.name.clone(),
+            default_action,
+            syscalls: vec![SeccompRule {
+                names: self.syscalls.iter().cloned().collect(),
+                action: SeccompAction::Allow,
+                args: None,
+            }],
+        })
+    }
+}
+
+/// Nome da syscall numa linha do strace. Aceita as variações de `-f`
+/// (`1234 read(...)`, `[pid 1234] read(...)`), de `-t`/`-tt`/`-ttt` e as
+/// chamadas interrompidas (`<... read resumed>`); sinais, saídas de processo
+/// e mensagens do próprio strace são ignorados.
+fn parse_strace_line(line: &str) -> Option<String> {
+    let mut rest = line.trim_start();
+    if let Some(after) = rest.strip_prefix("[pid") {
+        rest = after.split_once(']')?.1.trim_start();
+    }
+    // PID e horário antes da chamada
+    while let Some((token, after)) = rest.split_once(char::is_whitespace) {
+        if token.is_empty()
+            || !token
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == '.' || c == ':')
+        {
+            break;
+        }
+        rest = after.trim_start();
+    }
+
+    let name = match rest.strip_prefix("<... ") {
+        Some(resumed) => resumed.split_once(" resumed>")?.0,
+        None => rest.split_once('(')?.0,
+    };
+    let valid = name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    valid.then(|| name.to_string())
+}
+
 #[derive(Default)]
 pub struct SeccompManager {
     profiles: HashMap<String, SeccompProfile>,
@@ -114,6 +460,36 @@ impl SeccompManager {
         Ok(())
     }
 
+    /// Grava o perfil como programa Seccomp BPF binário
+    pub async fn export_profile(&self, profile_id: &str, path: &Path) -> Result<()> {
+        let profile = self.profiles.get(profile_id).ok_or_else(|| {
+            PolisError::Security(format!("Perfil Seccomp '{}' não encontrado", profile_id))
+        })?;
+        tokio::fs::write(path, profile.to_bpf_bytes()?).await?;
+        Ok(())
+    }
+
+    /// Carrega um programa Seccomp BPF exportado e registra o perfil com o
+    /// nome do arquivo; retorna esse nome
+    pub async fn import_profile(&mut self, path: &Path) -> Result<String> {
+        let bytes = tokio::fs::read(path).await?;
+        let mut profile = SeccompProfile::from_bpf_bytes(&bytes)?;
+        profile.name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .filter(|stem| !stem.is_empty())
+            .ok_or_else(|| {
+                PolisError::Security(format!(
+                    "Não foi possível derivar o nome do perfil de '{}'",
+                    path.display()
+                ))
+            })?;
+
+        let name = profile.name.clone();
+        self.load_profile(profile).await?;
+        Ok(name)
+    }
+
     pub async fn list_profiles(&self) -> Result<Vec<String>> {
         Ok(self.profiles.keys().cloned().collect())
     }
@@ -1,4 +1,4 @@
-use polis_core::types::{ContainerId, ResourceLimits};
+use polis_core::types::{ContainerId, ResourceLimits, RootfsConfig, WritableSource};
 use polis_core::{PolisError, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -18,6 +18,10 @@ pub struct ContainerSecurityProfile {
     pub capabilities: Vec<String>,
     pub apparmor_profile: Option<String>,
     pub selinux_context: Option<crate::SELinuxContext>,
+    /// Rootfs montado somente leitura, com escrita apenas nos caminhos
+    /// graváveis
+    #[serde(default)]
+    pub readonly_rootfs: bool,
     pub sandbox_config: Option<SandboxConfig>,
 }
 
@@ -100,6 +104,7 @@ impl SecurityManager {
             ],
             apparmor_profile: None,
             selinux_context: None,
+            readonly_rootfs: false,
             sandbox_config: Some(SandboxConfig {
                 read_only_rootfs: false,
                 no_new_privileges: true,
@@ -131,9 +136,10 @@ impl SecurityManager {
                 .apparmor_manager
                 .create_container_profile(&container_id.0.to_string())
                 .await?;
-            self.apparmor_manager
-                .load_profile(&apparmor_profile)
-                .await?;
+            let profile_text = self
+                .apparmor_manager
+                .generate_profile_content(&apparmor_profile);
+            self.apparmor_manager.load_profile(&profile_text).await?;
             profile.apparmor_profile = Some(apparmor_profile.name);
         }
 
@@ -185,6 +191,36 @@ impl SecurityManager {
         Ok(())
     }
 
+    pub async fn update_rootfs_config(
+        &mut self,
+        container_id: &ContainerId,
+        rootfs: &RootfsConfig,
+    ) -> Result<()> {
+        let profile = self
+            .container_profiles
+            .get_mut(container_id)
+            .ok_or_else(|| {
+                PolisError::Security("Perfil de segurança não encontrado".to_string())
+            })?;
+        let sandbox_config = profile.sandbox_config.as_mut().ok_or_else(|| {
+            PolisError::Security(format!(
+                "Perfil de segurança do container {} não tem sandbox para aplicar o rootfs",
+                container_id.0
+            ))
+        })?;
+
+        sandbox_config.read_only_rootfs = rootfs.read_only;
+        sandbox_config.tmpfs_mounts = rootfs
+            .effective_writable_paths()
+            .into_iter()
+            .filter(|path| matches!(path.source, WritableSource::Tmpfs { .. }))
+            .map(|path| path.destination.display().to_string())
+            .collect();
+        profile.readonly_rootfs = rootfs.read_only;
+
+        Ok(())
+    }
+
     pub async fn update_capabilities(
         &mut self,
         container_id: &ContainerId,
@@ -228,6 +264,7 @@ impl SecurityManager {
             "SETUID".to_string(),
         ];
 
+        profile.readonly_rootfs = true;
         if let Some(sandbox_config) = &mut profile.sandbox_config {
             sandbox_config.read_only_rootfs = true;
             sandbox_config.no_new_privileges = true;
@@ -254,6 +291,7 @@ impl SecurityManager {
         // Configurações privilegiadas
         profile.capabilities = vec!["ALL".to_string()];
 
+        profile.readonly_rootfs = false;
         if let Some(sandbox_config) = &mut profile.sandbox_config {
             sandbox_config.read_only_rootfs = false;
             sandbox_config.no_new_privileges = false;
@@ -1,4 +1,4 @@
-use polis_core::types::ContainerId;
+use polis_core::types::{ContainerId, RootfsConfig};
 use polis_security::{AppArmorManager, SELinuxManager, SecurityManager};
 
 #[tokio::test]
@@ -268,3 +268,47 @@ async fn test_error_handling() {
         .await;
     assert!(result.is_ok()); // Deve ser ok mesmo se não existir
 }
+
+#[tokio::test]
+async fn test_update_rootfs_config() {
+    let mut security_manager = SecurityManager::new();
+    let container_id = ContainerId::new();
+    let rootfs = RootfsConfig::read_only();
+
+    // Without a profile there is nothing to update
+    assert!(security_manager
+        .update_rootfs_config(&container_id, &rootfs)
+        .await
+        .is_err());
+
+    security_manager
+        .create_container_profile(&container_id)
+        .await
+        .unwrap();
+    security_manager
+        .update_rootfs_config(&container_id, &rootfs)
+        .await
+        .unwrap();
+    let profile = security_manager
+        .get_container_profile(&container_id)
+        .await
+        .unwrap();
+    assert!(profile.readonly_rootfs);
+    let sandbox_config = profile.sandbox_config.as_ref().unwrap();
+    assert!(sandbox_config.read_only_rootfs);
+    assert_eq!(
+        sandbox_config.tmpfs_mounts,
+        vec!["/tmp", "/run", "/var/tmp"]
+    );
+
+    // A profile without a sandbox cannot take the rootfs settings
+    security_manager
+        .container_profiles
+        .get_mut(&container_id)
+        .unwrap()
+        .sandbox_config = None;
+    assert!(security_manager
+        .update_rootfs_config(&container_id, &rootfs)
+        .await
+        .is_err());
+}
@@ -0,0 +1,115 @@
+use polis_security::{AppArmorManager, AppArmorProfileGenerator, Capability};
+
+#[test]
+fn test_generate_custom_profile() {
+    let mut generator = AppArmorProfileGenerator::new("web");
+    generator
+        .allow_path("/app/**", "r")
+        .allow_path("/data/**", "rw")
+        .deny_network("inet6")
+        .allow_capability(Capability::NetBindService)
+        .allow_capability(Capability::Setuid)
+        .allow_capability(Capability::NetBindService);
+
+    assert_eq!(generator.profile_name(), "polis-web");
+    assert_eq!(
+        generator.generate().unwrap(),
+        "#include <tunables/global>
+
+profile polis-web flags=(attach_disconnected,mediate_deleted) {
+  #include <abstractions/base>
+
+  deny network inet6,
+
+  capability net_bind_service,
+  capability setuid,
+
+  /app/** r,
+  /data/** rw,
+}
+"
+    );
+}
+
+#[test]
+fn test_default_container_profile() {
+    let profile = AppArmorProfileGenerator::default_container_profile("db-1")
+        .generate()
+        .unwrap();
+    assert_eq!(
+        profile,
+        "#include <tunables/global>
+
+profile polis-db-1 flags=(attach_disconnected,mediate_deleted) {
+  #include <abstractions/base>
+
+  network,
+  deny network raw,
+  deny network packet,
+
+  capability chown,
+  capability dac_override,
+  capability fowner,
+  capability fsetid,
+  capability kill,
+  capability setgid,
+  capability setuid,
+  capability setpcap,
+  capability net_bind_service,
+  capability sys_chroot,
+  capability audit_write,
+  capability setfcap,
+
+  file,
+  deny /proc/sys/** wklx,
+  deny /proc/sysrq-trigger rwklx,
+  deny /proc/kcore rwklx,
+  deny /sys/** wklx,
+  deny /sys/firmware/** rwklx,
+  deny /sys/kernel/security/** rwklx,
+
+  deny mount,
+  deny umount,
+}
+"
+    );
+}
+
+#[test]
+fn test_generate_rejects_invalid_rules() {
+    assert!(AppArmorProfileGenerator::new("bad name")
+        .generate()
+        .is_err());
+    assert!(AppArmorProfileGenerator::new("web")
+        .allow_path("relative/path", "r")
+        .generate()
+        .is_err());
+    assert!(AppArmorProfileGenerator::new("web")
+        .allow_path("/data, /etc/shadow", "r")
+        .generate()
+        .is_err());
+    assert!(AppArmorProfileGenerator::new("web")
+        .allow_path("/data/**", "rwz")
+        .generate()
+        .is_err());
+    assert!(AppArmorProfileGenerator::new("web")
+        .deny_network("inet,\n  capability sys_admin")
+        .generate()
+        .is_err());
+}
+
+#[test]
+fn test_profile_path_is_derived_from_the_content() {
+    let manager = AppArmorManager::new().with_profile_dir("/tmp/apparmor.d");
+    let profile = AppArmorProfileGenerator::default_container_profile("web")
+        .generate()
+        .unwrap();
+
+    let path = manager.profile_path(&profile);
+    assert_eq!(path, manager.profile_path(&profile));
+    assert_ne!(path, manager.profile_path("profile polis-other {}\n"));
+    assert!(path.starts_with("/tmp/apparmor.d"));
+    let file_name = path.file_name().unwrap().to_str().unwrap();
+    assert!(file_name.starts_with("polis-"));
+    assert_eq!(file_name.len(), "polis-".len() + 16);
+}
@@ -0,0 +1,204 @@
+use polis_core::ResourceLimits;
+use polis_security::{cgroup_v1_settings, cgroup_v2_settings, CgroupManager, CgroupVersion};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn limits() -> ResourceLimits {
+    ResourceLimits {
+        memory_limit: Some(256 * 1024 * 1024),
+        memory_swap: Some(512 * 1024 * 1024),
+        cpu_quota: Some(0.5),
+        cpu_shares: Some(512),
+        pids_limit: Some(100),
+        ..Default::default()
+    }
+}
+
+fn read(path: PathBuf) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn test_cgroup_v2_settings() {
+    let settings = cgroup_v2_settings(&limits()).unwrap();
+    assert_eq!(
+        settings,
+        [
+            ("memory.max", "268435456".to_string()),
+            ("memory.swap.max", "268435456".to_string()),
+            ("cpu.max", "50000 100000".to_string()),
+            ("cpu.weight", "20".to_string()),
+            ("pids.max", "100".to_string()),
+        ]
+    );
+
+    // Unset limits write nothing; a non-positive pids limit means no limit
+    let settings = cgroup_v2_settings(&ResourceLimits {
+        cpu_quota: Some(2.0),
+        cpu_period: Some(50_000),
+        pids_limit: Some(0),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(
+        settings,
+        [
+            ("cpu.max", "100000 50000".to_string()),
+            ("pids.max", "max".to_string()),
+        ]
+    );
+    assert!(cgroup_v2_settings(&ResourceLimits::default())
+        .unwrap()
+        .is_empty());
+    assert!(cgroup_v2_settings(&ResourceLimits {
+        cpu_quota: Some(-1.0),
+        ..Default::default()
+    })
+    .is_err());
+}
+
+#[test]
+fn test_cgroup_v1_settings() {
+    let settings = cgroup_v1_settings(&limits()).unwrap();
+    assert_eq!(
+        settings,
+        [
+            ("memory", "memory.limit_in_bytes", "268435456".to_string()),
+            (
+                "memory",
+                "memory.memsw.limit_in_bytes",
+                "536870912".to_string()
+            ),
+            ("cpu", "cpu.cfs_period_us", "100000".to_string()),
+            ("cpu", "cpu.cfs_quota_us", "50000".to_string()),
+            ("cpu", "cpu.shares", "512".to_string()),
+            ("pids", "pids.max", "100".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_create_v2_cgroup_writes_limits() {
+    let mount = tempfile::tempdir().unwrap();
+    fs::write(mount.path().join("cgroup.controllers"), "cpu memory pids\n").unwrap();
+    let root = mount.path().join("polis");
+
+    let mut manager = CgroupManager::detect(mount.path(), root.clone()).unwrap();
+    assert_eq!(manager.version(), CgroupVersion::V2);
+    let info = manager.create_cgroup("web", limits()).await.unwrap();
+    assert_eq!(info.path, root.join("web"));
+
+    assert_eq!(
+        read(root.join("cgroup.subtree_control")),
+        "+memory +cpu +pids"
+    );
+    assert_eq!(read(info.path.join("memory.max")), "268435456");
+    assert_eq!(read(info.path.join("memory.swap.max")), "268435456");
+    assert_eq!(read(info.path.join("cpu.max")), "50000 100000");
+    assert_eq!(read(info.path.join("cpu.weight")), "20");
+    assert_eq!(read(info.path.join("pids.max")), "100");
+
+    manager.add_process("web", 4242).await.unwrap();
+    assert_eq!(read(info.path.join("cgroup.procs")), "4242");
+
+    manager.delete_cgroup("web").await.unwrap();
+    assert!(!info.path.exists());
+    assert!(manager.list_cgroups().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_create_v1_cgroup_writes_each_controller() {
+    let mount = tempfile::tempdir().unwrap();
+    for controller in ["memory", "cpu", "cpuacct", "pids"] {
+        fs::create_dir(mount.path().join(controller)).unwrap();
+    }
+
+    let mut manager = CgroupManager::detect(mount.path(), mount.path().join("unified")).unwrap();
+    assert_eq!(manager.version(), CgroupVersion::V1);
+    manager.create_cgroup("db", limits()).await.unwrap();
+
+    let dir = |controller: &str| mount.path().join(controller).join("polis").join("db");
+    assert_eq!(
+        read(dir("memory").join("memory.limit_in_bytes")),
+        "268435456"
+    );
+    assert_eq!(
+        read(dir("memory").join("memory.memsw.limit_in_bytes")),
+        "536870912"
+    );
+    assert_eq!(read(dir("cpu").join("cpu.cfs_quota_us")), "50000");
+    assert_eq!(read(dir("cpu").join("cpu.shares")), "512");
+    assert_eq!(read(dir("pids").join("pids.max")), "100");
+    assert!(dir("cpuacct").is_dir());
+
+    manager.delete_cgroup("db").await.unwrap();
+    for controller in ["memory", "cpu", "cpuacct", "pids"] {
+        assert!(!dir(controller).exists());
+    }
+}
+
+#[test]
+fn test_detect_without_cgroups() {
+    let mount = tempfile::tempdir().unwrap();
+    assert!(CgroupManager::detect(mount.path(), mount.path().join("polis")).is_none());
+}
+
+/// Run `sh -c script` inside a new cgroup limited to `memory_limit` bytes
+/// and return its exit status
+async fn run_limited(mount: &Path, memory_limit: u64, script: &str) -> std::process::ExitStatus {
+    let name = format!("oom-test-{}", std::process::id());
+    let mut manager = CgroupManager::detect(mount, mount.join("polis")).unwrap();
+    manager
+        .create_cgroup(
+            &name,
+            ResourceLimits {
+                memory_limit: Some(memory_limit),
+                memory_swap: Some(memory_limit),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    // The child waits on stdin until it has been moved into the cgroup
+    let mut child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("read _; {}", script))
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    manager
+        .add_process(&name, child.id().unwrap())
+        .await
+        .unwrap();
+    drop(child.stdin.take());
+    let status = child.wait().await.unwrap();
+
+    manager.delete_cgroup(&name).await.unwrap();
+    status
+}
+
+#[tokio::test]
+#[ignore = "requires root and a writable /sys/fs/cgroup"]
+async fn test_memory_hog_is_oom_killed() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let mount = Path::new("/sys/fs/cgroup");
+    let status = run_limited(
+        mount,
+        32 * 1024 * 1024,
+        "head -c 256m /dev/zero | tail > /dev/null",
+    )
+    .await;
+    // tail holds the whole input in memory and is killed by the OOM killer
+    assert!(!status.success());
+
+    let status = run_limited(
+        mount,
+        32 * 1024 * 1024,
+        "head -c 1m /dev/zero | tail > /dev/null",
+    )
+    .await;
+    assert!(status.success());
+    assert_eq!(status.signal(), None);
+}
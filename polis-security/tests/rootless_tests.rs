@@ -0,0 +1,77 @@
+use polis_security::{CgroupDelegation, Sandbox};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::FromRawFd;
+
+/// Exit code of the child when the kernel refuses user namespaces
+const USERNS_UNAVAILABLE: u8 = 77;
+
+fn pipe() -> (File, File) {
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+}
+
+fn host_uid(pid: libc::pid_t) -> u32 {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).unwrap();
+    let line = status
+        .lines()
+        .find(|line| line.starts_with("Uid:"))
+        .unwrap();
+    line.split_whitespace().nth(2).unwrap().parse().unwrap()
+}
+
+#[test]
+fn test_enter_rootless_maps_host_user_to_root() {
+    let uid = unsafe { libc::getuid() };
+    let gid = unsafe { libc::getgid() };
+    let sandbox = Sandbox::new_rootless(uid, gid).unwrap();
+    assert_eq!(sandbox.uid_map()[0].host_id, uid);
+
+    let (mut from_child, mut child_out) = pipe();
+    let (mut child_in, mut to_child) = pipe();
+
+    // unshare(CLONE_NEWUSER) needs a single-threaded process: run it in a
+    // forked child and check it from the parent, which sees host IDs
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        let code = match sandbox.enter_rootless() {
+            Ok(()) if unsafe { libc::geteuid() } == 0 => 0,
+            Ok(()) => 1,
+            Err(_) => USERNS_UNAVAILABLE,
+        };
+        let _ = child_out.write_all(&[code]);
+        let _ = child_in.read(&mut [0]);
+        unsafe { libc::_exit(0) };
+    }
+    drop(child_out);
+    drop(child_in);
+
+    let mut code = [0];
+    from_child.read_exact(&mut code).unwrap();
+    let uid_outside = host_uid(pid);
+    to_child.write_all(b"x").unwrap();
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+
+    if code[0] == USERNS_UNAVAILABLE {
+        eprintln!("user namespaces unavailable, skipping");
+        return;
+    }
+    assert_eq!(code[0], 0, "effective UID inside the namespace is not 0");
+    assert_eq!(uid_outside, uid);
+}
+
+#[test]
+fn test_user_delegated_cgroup() {
+    let dir = tempfile::tempdir().unwrap();
+    let delegation = CgroupDelegation::UserDelegated(dir.path().to_path_buf());
+    let path = delegation.create_cgroup("polis-web").unwrap();
+    assert_eq!(path, dir.path().join("polis-web"));
+    assert!(path.is_dir());
+
+    assert!(CgroupDelegation::Unavailable
+        .create_cgroup("polis-web")
+        .is_err());
+}
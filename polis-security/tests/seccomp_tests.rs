@@ -0,0 +1,215 @@
+use polis_security::{
+    SeccompAction, SeccompManager, SeccompProfile, SeccompProfileBuilder, SeccompRule,
+};
+
+const STRACE_LOG: &str = r#"execve("/usr/bin/app", ["app", "--port", "8080"], 0x7ffd3c1c6a18 /* 12 vars */) = 0
+brk(NULL)                               = 0x55d4c0a2b000
+openat(AT_FDCWD, "/etc/ld.so.cache", O_RDONLY|O_CLOEXEC) = 3
+read(3, "\177ELF\2\1\1\0\0\0\0\0\0\0\0\0\3\0>\0\1\0\0\0"..., 832) = 832
+close(3)                                = 0
+12345 12:30:01.000123 write(1, "listening\n", 10) = 10
+[pid 12346] futex(0x7f1c2c0008c8, FUTEX_WAIT_PRIVATE, 0, NULL <unfinished ...>
+12345 1700000000.123456 read(4,  <unfinished ...>
+[pid 12346] <... futex resumed>)        = 0
+12345 <... epoll_wait resumed>[{events=EPOLLIN, data={u32=4, u64=4}}], 1024, -1) = 1
+--- SIGCHLD {si_signo=SIGCHLD, si_code=CLD_EXITED, si_pid=12347, si_uid=0, si_status=0} ---
+strace: Process 12347 attached
+write(2, "done\n", 5)                   = 5
+exit_group(0)                           = ?
++++ exited with 0 +++
+"#;
+
+#[test]
+fn test_profile_from_strace_log() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("web-app.strace");
+    std::fs::write(&path, STRACE_LOG).unwrap();
+
+    let builder = SeccompProfileBuilder::from_strace_log(&path).unwrap();
+    assert_eq!(
+        builder.syscalls(),
+        vec![
+            "brk",
+            "close",
+            "epoll_wait",
+            "execve",
+            "exit_group",
+            "futex",
+            "openat",
+            "read",
+            "write"
+        ]
+    );
+
+    let profile = builder.build(SeccompAction::Deny).unwrap();
+    assert_eq!(profile.name, "web-app");
+    assert_eq!(profile.default_action, SeccompAction::Deny);
+    assert_eq!(profile.syscalls.len(), 1);
+    let allowed = &profile.syscalls[0];
+    assert_eq!(allowed.action, SeccompAction::Allow);
+    for syscall in ["read", "write", "openat", "execve"] {
+        assert!(allowed.names.iter().any(|name| name == syscall));
+    }
+}
+
+#[test]
+fn test_profile_serializes_to_oci_json() {
+    let profile = SeccompProfileBuilder::new("app")
+        .with_strace_output("read(0, \"\", 1) = 0\nread(0, \"\", 1) = 0\nwrite(1, \"x\", 1) = 1\n")
+        .build(SeccompAction::Deny)
+        .unwrap();
+
+    let json: serde_json::Value = serde_json::from_str(&profile.to_json().unwrap()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "defaultAction": "SCMP_ACT_ERRNO",
+            "syscalls": [
+                {"names": ["read", "write"], "action": "SCMP_ACT_ALLOW"}
+            ]
+        })
+    );
+
+    let parsed: SeccompProfile = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.syscalls, profile.syscalls);
+}
+
+#[test]
+fn test_build_rejects_empty_or_permissive_profiles() {
+    assert!(SeccompProfileBuilder::new("empty")
+        .with_strace_output("+++ exited with 0 +++\n")
+        .build(SeccompAction::Deny)
+        .is_err());
+    assert!(SeccompProfileBuilder::new("open")
+        .with_syscall("read")
+        .build(SeccompAction::Allow)
+        .is_err());
+    assert!(
+        SeccompProfileBuilder::from_strace_log(std::path::Path::new("/nonexistent.strace"))
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_export_and_import_bpf_profile() {
+    let profile = SeccompProfile {
+        name: "small".to_string(),
+        default_action: SeccompAction::Deny,
+        syscalls: vec![
+            SeccompRule {
+                names: vec![
+                    "read".to_string(),
+                    "write".to_string(),
+                    "exit_group".to_string(),
+                ],
+                action: SeccompAction::Allow,
+                args: None,
+            },
+            SeccompRule {
+                names: vec!["ptrace".to_string()],
+                action: SeccompAction::Kill,
+                args: None,
+            },
+        ],
+    };
+
+    let mut manager = SeccompManager::new();
+    manager.load_profile(profile.clone()).await.unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("small-audit.bpf");
+    manager.export_profile("small", &path).await.unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(bytes.len() % 8, 0);
+    assert_eq!(bytes, profile.to_bpf_bytes().unwrap());
+
+    let imported = manager.import_profile(&path).await.unwrap();
+    assert_eq!(imported, "small-audit");
+    assert!(manager
+        .list_profiles()
+        .await
+        .unwrap()
+        .contains(&"small-audit".to_string()));
+
+    let reconstructed = SeccompProfile::from_bpf_bytes(&bytes).unwrap();
+    assert_eq!(reconstructed.default_action, profile.default_action);
+    assert_eq!(reconstructed.syscalls, profile.syscalls);
+
+    assert!(manager
+        .export_profile("missing", &dir.path().join("missing.bpf"))
+        .await
+        .is_err());
+}
+
+#[test]
+fn test_bpf_rejects_unknown_syscalls_and_malformed_programs() {
+    let profile = SeccompProfileBuilder::new("bogus")
+        .with_syscall("not_a_syscall")
+        .build(SeccompAction::Deny)
+        .unwrap();
+    assert!(profile.to_bpf_bytes().is_err());
+
+    assert!(SeccompProfile::from_bpf_bytes(&[]).is_err());
+    assert!(SeccompProfile::from_bpf_bytes(&[0; 7]).is_err());
+    assert!(SeccompProfile::from_bpf_bytes(&[0; 64]).is_err());
+}
+
+/// Last syscall in the hand-written table; newer ones are not compiled yet
+#[cfg(target_arch = "x86_64")]
+const LAST_TABLE_SYSCALL: u32 = 446;
+
+/// Checks the hand-written syscall table against the kernel headers: each
+/// syscall compiles to the header's number and decodes back to its name.
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_syscall_numbers_match_kernel_headers() {
+    let header = [
+        "/usr/include/x86_64-linux-gnu/asm/unistd_64.h",
+        "/usr/include/asm/unistd_64.h",
+    ]
+    .iter()
+    .find_map(|path| std::fs::read_to_string(path).ok());
+    let Some(header) = header else {
+        eprintln!("asm/unistd_64.h not found, skipping");
+        return;
+    };
+
+    let expected: Vec<(String, u32)> = header
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.strip_prefix("#define __NR_")?.split_whitespace();
+            let name = fields.next()?.to_string();
+            let nr = fields.next()?.parse().ok()?;
+            Some((name, nr))
+        })
+        .filter(|(_, nr)| *nr <= LAST_TABLE_SYSCALL)
+        .collect();
+    assert!(
+        expected.len() > 300,
+        "{} syscalls in header",
+        expected.len()
+    );
+
+    let profile = SeccompProfile {
+        name: "headers".to_string(),
+        default_action: SeccompAction::Deny,
+        syscalls: vec![SeccompRule {
+            names: expected.iter().map(|(name, _)| name.clone()).collect(),
+            action: SeccompAction::Allow,
+            args: None,
+        }],
+    };
+    let bytes = profile.to_bpf_bytes().unwrap();
+
+    // Each syscall is a JEQ/RET pair before the default action; `k` is the
+    // last field of `struct sock_filter`
+    let instructions: Vec<&[u8]> = bytes.chunks_exact(8).collect();
+    let checks = &instructions[instructions.len() - 1 - 2 * expected.len()..];
+    for ((name, nr), check) in expected.iter().zip(checks.chunks_exact(2)) {
+        let k = u32::from_ne_bytes(check[0][4..8].try_into().unwrap());
+        assert_eq!(k, *nr, "syscall {}", name);
+    }
+
+    let decoded = SeccompProfile::from_bpf_bytes(&bytes).unwrap();
+    assert_eq!(decoded.syscalls[0].names, profile.syscalls[0].names);
+}
@@ -63,6 +63,7 @@ async fn test_cgroup_manager() {
         memory_swap: Some(1024 * 1024 * 1024), // 1GB
         cpu_quota: Some(0.5),                  // 50% CPU
         cpu_period: Some(100000),              // 100ms
+        cpu_shares: None,
         pids_limit: Some(100),
         disk_quota: Some(5 * 1024 * 1024 * 1024), // 5GB
     };
@@ -0,0 +1,110 @@
+use polis_security::{
+    read_subordinate_ids, IdMapping, NamespaceManager, UserNamespaceConfig, MAX_ID_MAPPINGS,
+};
+use std::path::Path;
+
+fn mapping(container_id: u32, host_id: u32, size: u32) -> IdMapping {
+    IdMapping {
+        container_id,
+        host_id,
+        size,
+    }
+}
+
+/// Fake `/proc` with an entry for `pid`
+fn proc_root(root: &Path, pid: u32) -> NamespaceManager {
+    std::fs::create_dir_all(root.join(pid.to_string())).unwrap();
+    NamespaceManager::new().with_proc_root(root)
+}
+
+#[test]
+fn test_configure_user_ns_writes_maps() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = proc_root(dir.path(), 4242);
+    let config = UserNamespaceConfig {
+        uid_mappings: vec![mapping(0, 1000, 1), mapping(1, 100000, 65536)],
+        gid_mappings: vec![mapping(0, 1000, 1)],
+    };
+
+    manager.configure_user_ns(4242, &config).unwrap();
+
+    let uid_map = std::fs::read_to_string(dir.path().join("4242/uid_map")).unwrap();
+    let gid_map = std::fs::read_to_string(dir.path().join("4242/gid_map")).unwrap();
+    assert_eq!(uid_map, "0 1000 1\n1 100000 65536\n");
+    assert_eq!(gid_map, "0 1000 1\n");
+}
+
+#[test]
+fn test_invalid_mappings_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = proc_root(dir.path(), 7);
+    let valid = vec![mapping(0, 1000, 1)];
+    let invalid = [
+        // Overlapping container IDs
+        vec![mapping(0, 1000, 10), mapping(5, 200000, 10)],
+        // Overlapping host IDs
+        vec![mapping(0, 100000, 10), mapping(10, 100005, 10)],
+        vec![mapping(0, 1000, 0)],
+        vec![mapping(0, u32::MAX, 2)],
+        Vec::new(),
+        (0..=MAX_ID_MAPPINGS as u32)
+            .map(|n| mapping(n, 1000 + n, 1))
+            .collect(),
+    ];
+
+    for mappings in invalid {
+        let uids = UserNamespaceConfig {
+            uid_mappings: mappings.clone(),
+            gid_mappings: valid.clone(),
+        };
+        let gids = UserNamespaceConfig {
+            uid_mappings: valid.clone(),
+            gid_mappings: mappings.clone(),
+        };
+        assert!(
+            manager.configure_user_ns(7, &uids).is_err(),
+            "{:?}",
+            mappings
+        );
+        assert!(
+            manager.configure_user_ns(7, &gids).is_err(),
+            "{:?}",
+            mappings
+        );
+    }
+    // Nothing is written for a rejected configuration
+    assert!(!dir.path().join("7/uid_map").exists());
+
+    // Adjacent ranges do not overlap
+    let adjacent = UserNamespaceConfig {
+        uid_mappings: vec![mapping(0, 1000, 1), mapping(1, 1001, 10)],
+        gid_mappings: valid,
+    };
+    assert!(manager.configure_user_ns(7, &adjacent).is_ok());
+}
+
+#[test]
+fn test_subid_range() {
+    let dir = tempfile::tempdir().unwrap();
+    let subuid = dir.path().join("subuid");
+    let subgid = dir.path().join("subgid");
+    std::fs::write(&subuid, "bob:300000:1000\nalice:100000:65536\n").unwrap();
+    std::fs::write(&subgid, "alice:200000:65536\n").unwrap();
+
+    let config = UserNamespaceConfig::subid_range_from("alice", &subuid, &subgid).unwrap();
+    assert_eq!(config.uid_mappings, vec![mapping(0, 100000, 65536)]);
+    assert_eq!(config.gid_mappings, vec![mapping(0, 200000, 65536)]);
+    assert_eq!(config.uid_map(), "0 100000 65536\n");
+
+    // bob has no subordinate GIDs
+    assert!(UserNamespaceConfig::subid_range_from("bob", &subuid, &subgid).is_err());
+
+    assert_eq!(
+        read_subordinate_ids(&subuid, &["1001", "bob"]).unwrap(),
+        Some((300000, 1000))
+    );
+    assert_eq!(
+        read_subordinate_ids(&dir.path().join("missing"), &["alice"]).unwrap(),
+        None
+    );
+}
@@ -0,0 +1,112 @@
+use crate::ContainerMetrics;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Labels a container must carry, all with the given values.
+/// An empty selector matches every container.
+pub type LabelSelector = HashMap<String, String>;
+
+/// Whether `labels` satisfy every entry of `selector`
+pub fn selector_matches(selector: &LabelSelector, labels: &HashMap<String, String>) -> bool {
+    selector
+        .iter()
+        .all(|(key, value)| labels.get(key) == Some(value))
+}
+
+/// Sum, average and maximum of one metric across containers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Aggregate<T> {
+    pub sum: T,
+    pub avg: f64,
+    pub max: T,
+}
+
+impl Aggregate<u64> {
+    /// Aggregate counter values; the sum saturates instead of overflowing
+    pub fn of_counters(values: &[u64]) -> Self {
+        let sum = values
+            .iter()
+            .fold(0u64, |sum, value| sum.saturating_add(*value));
+        Self {
+            sum,
+            avg: average(values.iter().map(|value| *value as f64), values.len()),
+            max: values.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+impl Aggregate<f64> {
+    /// Aggregate gauge values such as percentages
+    pub fn of_gauges(values: &[f64]) -> Self {
+        Self {
+            sum: values.iter().sum(),
+            avg: average(values.iter().copied(), values.len()),
+            max: values.iter().copied().fold(0.0, f64::max),
+        }
+    }
+}
+
+fn average(values: impl Iterator<Item = f64>, count: usize) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        values.sum::<f64>() / count as f64
+    }
+}
+
+/// Metrics of a group of containers, e.g. every replica of a deployment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregatedMetrics {
+    /// Number of containers aggregated
+    pub container_count: usize,
+    pub cpu_usage_percent: Aggregate<f64>,
+    /// Memory usage in bytes
+    pub memory_usage: Aggregate<u64>,
+    pub memory_usage_percent: Aggregate<f64>,
+    pub network_rx_bytes: Aggregate<u64>,
+    pub network_tx_bytes: Aggregate<u64>,
+    pub network_rx_packets: Aggregate<u64>,
+    pub network_tx_packets: Aggregate<u64>,
+    pub disk_read_bytes: Aggregate<u64>,
+    pub disk_write_bytes: Aggregate<u64>,
+    pub disk_read_ops: Aggregate<u64>,
+    pub disk_write_ops: Aggregate<u64>,
+    pub process_count: Aggregate<u64>,
+    pub thread_count: Aggregate<u64>,
+}
+
+impl AggregatedMetrics {
+    /// Aggregate the given samples; `None` when there are none
+    pub fn from_metrics<'a>(
+        metrics: impl IntoIterator<Item = &'a ContainerMetrics>,
+    ) -> Option<Self> {
+        let metrics: Vec<&ContainerMetrics> = metrics.into_iter().collect();
+        if metrics.is_empty() {
+            return None;
+        }
+
+        let counters = |field: fn(&ContainerMetrics) -> u64| {
+            Aggregate::of_counters(&metrics.iter().map(|m| field(m)).collect::<Vec<_>>())
+        };
+        let gauges = |field: fn(&ContainerMetrics) -> f64| {
+            Aggregate::of_gauges(&metrics.iter().map(|m| field(m)).collect::<Vec<_>>())
+        };
+
+        Some(Self {
+            container_count: metrics.len(),
+            cpu_usage_percent: gauges(|m| m.cpu.usage_percent),
+            memory_usage: counters(|m| m.memory.usage),
+            memory_usage_percent: gauges(|m| m.memory.usage_percent),
+            network_rx_bytes: counters(|m| m.network.rx_bytes),
+            network_tx_bytes: counters(|m| m.network.tx_bytes),
+            network_rx_packets: counters(|m| m.network.rx_packets),
+            network_tx_packets: counters(|m| m.network.tx_packets),
+            disk_read_bytes: counters(|m| m.disk.read_bytes),
+            disk_write_bytes: counters(|m| m.disk.write_bytes),
+            disk_read_ops: counters(|m| m.disk.read_ops),
+            disk_write_ops: counters(|m| m.disk.write_ops),
+            process_count: counters(|m| m.processes.process_count as u64),
+            thread_count: counters(|m| m.processes.thread_count as u64),
+        })
+    }
+}
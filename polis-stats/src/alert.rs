@@ -0,0 +1,109 @@
+use crate::{ContainerMetrics, MetricField};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+
+/// Callback invoked when a rule starts or stops alarming
+pub type AlertCallback =
+    Arc<dyn Fn(AlertEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Transition of an alert rule for one container
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    pub container_id: String,
+    pub field: MetricField,
+    /// Value of the sample that caused the transition
+    pub value: f64,
+    pub threshold: f64,
+    /// `true` when entering the alarmed state, `false` on recovery
+    pub alarm: bool,
+}
+
+/// Alarm when a field stays above a threshold for a while
+#[derive(Clone)]
+pub struct AlertRule {
+    pub field: MetricField,
+    pub threshold: f64,
+    /// How long the threshold must be exceeded without interruption
+    pub duration: Duration,
+    callback: AlertCallback,
+}
+
+impl AlertRule {
+    pub fn new<F, Fut>(field: MetricField, threshold: f64, duration: Duration, callback: F) -> Self
+    where
+        F: Fn(AlertEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self {
+            field,
+            threshold,
+            duration,
+            callback: Arc::new(move |event| Box::pin(callback(event))),
+        }
+    }
+}
+
+impl fmt::Debug for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlertRule")
+            .field("field", &self.field)
+            .field("threshold", &self.threshold)
+            .field("duration", &self.duration)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A rule and how long it has been breached
+#[derive(Debug)]
+pub(crate) struct AlertState {
+    rule: AlertRule,
+    breached_since: Option<Instant>,
+    alarmed: bool,
+}
+
+impl AlertState {
+    pub(crate) fn new(rule: AlertRule) -> Self {
+        Self {
+            rule,
+            breached_since: None,
+            alarmed: false,
+        }
+    }
+
+    /// Feed a sample taken at `now`; returns the event to deliver, if the
+    /// rule changed state, with its callback
+    pub(crate) fn observe(
+        &mut self,
+        sample: &ContainerMetrics,
+        container_id: &str,
+        now: Instant,
+    ) -> Option<(AlertCallback, AlertEvent)> {
+        let value = self.rule.field.reading(sample);
+        let alarm = if value > self.rule.threshold {
+            let since = *self.breached_since.get_or_insert(now);
+            if self.alarmed || now.saturating_duration_since(since) < self.rule.duration {
+                return None;
+            }
+            true
+        } else {
+            self.breached_since = None;
+            if !self.alarmed {
+                return None;
+            }
+            false
+        };
+
+        self.alarmed = alarm;
+        let event = AlertEvent {
+            container_id: container_id.to_string(),
+            field: self.rule.field,
+            value,
+            threshold: self.rule.threshold,
+            alarm,
+        };
+        Some((Arc::clone(&self.rule.callback), event))
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{ContainerMetrics, Result, StatsError};
+use std::path::{Path, PathBuf};
+
+/// On-disk store of previous samples and named baselines, per user
+#[derive(Debug, Clone)]
+pub struct SampleStore {
+    dir: PathBuf,
+}
+
+impl SampleStore {
+    /// Create a store rooted at `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Store under the user's state directory
+    /// (`$XDG_STATE_HOME/polis/stats` or `~/.local/state/polis/stats`)
+    pub fn for_current_user() -> Self {
+        let base = std::env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+            })
+            .unwrap_or_else(std::env::temp_dir);
+        Self::new(base.join("polis").join("stats"))
+    }
+
+    /// Root directory of the store
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Load the sample stored by the previous invocation
+    pub fn load_last(&self, container_id: &str) -> Result<Option<ContainerMetrics>> {
+        load(&self.last_path(container_id))
+    }
+
+    /// Replace the stored previous sample
+    pub fn save_last(&self, metrics: &ContainerMetrics) -> Result<()> {
+        save(&self.last_path(&metrics.container_id), metrics)
+    }
+
+    /// Load a named baseline
+    pub fn load_baseline(
+        &self,
+        name: &str,
+        container_id: &str,
+    ) -> Result<Option<ContainerMetrics>> {
+        load(&self.baseline_path(name, container_id)?)
+    }
+
+    /// Save a named baseline
+    pub fn save_baseline(&self, name: &str, metrics: &ContainerMetrics) -> Result<()> {
+        save(&self.baseline_path(name, &metrics.container_id)?, metrics)
+    }
+
+    fn last_path(&self, container_id: &str) -> PathBuf {
+        self.dir.join("last").join(format!("{}.json", container_id))
+    }
+
+    fn baseline_path(&self, name: &str, container_id: &str) -> Result<PathBuf> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(StatsError::Parse(format!(
+                "Invalid baseline name: {}",
+                name
+            )));
+        }
+        Ok(self
+            .dir
+            .join("baselines")
+            .join(name)
+            .join(format!("{}.json", container_id)))
+    }
+}
+
+fn load(path: &Path) -> Result<Option<ContainerMetrics>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path)?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| StatsError::Parse(format!("Failed to parse sample {}: {}", path.display(), e)))
+}
+
+fn save(path: &Path, metrics: &ContainerMetrics) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(metrics)
+        .map_err(|e| StatsError::Parse(format!("Failed to serialize sample: {}", e)))?;
+    std::fs::write(path, content)?;
+    Ok(())
+}
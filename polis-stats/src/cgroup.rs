@@ -0,0 +1,100 @@
+use std::path::Path;
+use std::time::Duration;
+
+/// cgroup hierarchy mounted at the cgroup root
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgroupVersion {
+    /// Legacy per-controller hierarchies (`cpuacct/`, `memory/`, ...)
+    V1,
+    /// Unified hierarchy
+    V2,
+}
+
+/// Detect the hierarchy mounted at `mount` (usually `/sys/fs/cgroup`).
+///
+/// The unified hierarchy exposes `cgroup.controllers` at its root; a v1
+/// host has a `cpuacct` controller directory instead.
+pub fn detect_cgroup_version(mount: &Path) -> Option<CgroupVersion> {
+    if mount.join("cgroup.controllers").is_file() {
+        Some(CgroupVersion::V2)
+    } else if mount.join("cpuacct").is_dir() {
+        Some(CgroupVersion::V1)
+    } else {
+        None
+    }
+}
+
+/// Parse `cpuacct.usage_percpu`: cumulative nanoseconds per CPU
+pub fn parse_usage_percpu(content: &str) -> Vec<u64> {
+    content
+        .split_whitespace()
+        .map(|value| value.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Parse the per-CPU `usage_usec.cpu<N>` entries of a v2 `cpu.stat`, when
+/// the kernel exposes them, into cumulative nanoseconds per CPU. Missing
+/// CPUs between two present indexes are reported as 0; a `cpu.stat` with
+/// only the aggregate counters yields an empty Vec.
+pub fn parse_cpu_stat_percpu(content: &str) -> Vec<u64> {
+    let mut per_core: Vec<u64> = Vec::new();
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(key), Some(value)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Some(index) = key
+            .strip_prefix("usage_usec.cpu")
+            .and_then(|cpu| cpu.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        let Ok(usec) = value.parse::<u64>() else {
+            continue;
+        };
+        if per_core.len() <= index {
+            per_core.resize(index + 1, 0);
+        }
+        per_core[index] = usec.saturating_mul(1_000);
+    }
+    per_core
+}
+
+/// Utilization of each core over `interval`, in percent of that core, from
+/// two cumulative per-CPU readings. Empty when the readings are missing,
+/// cover a different number of CPUs or the interval is zero; a core whose
+/// counter went backwards reports 0.
+pub fn per_core_usage(before: &[u64], after: &[u64], interval: Duration) -> Vec<f64> {
+    if before.is_empty() || before.len() != after.len() || interval.is_zero() {
+        return Vec::new();
+    }
+    let interval_ns = interval.as_nanos() as f64;
+    before
+        .iter()
+        .zip(after)
+        .map(|(before, after)| after.saturating_sub(*before) as f64 / interval_ns * 100.0)
+        .collect()
+}
+
+/// Values at or above this in a v1 `memory.*limit_in_bytes` mean no limit
+/// (the kernel reports `PAGE_COUNTER_MAX` rounded to the page size)
+const V1_UNLIMITED: u64 = 1 << 62;
+
+/// Parse a cgroup memory limit (`memory.max`, `memory.limit_in_bytes`, ...)
+/// into bytes; `max` and the v1 sentinel for no limit yield 0 (unlimited)
+pub fn parse_memory_limit(content: &str) -> u64 {
+    match content.trim().parse::<u64>() {
+        Ok(limit) if limit < V1_UNLIMITED => limit,
+        _ => 0,
+    }
+}
+
+/// Parse the `oom_kill` counter of a v2 `memory.events` or a v1
+/// `memory.oom_control`
+pub fn parse_oom_kills(content: &str) -> u64 {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or(0)
+}
@@ -1,11 +1,24 @@
-use crate::{ContainerMetrics, CpuMetrics, MemoryMetrics, NetworkMetrics, DiskMetrics, ProcessMetrics, Result, StatsError};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::{
+    detect_cgroup_version, detect_gpu_backend, parse_cpu_stat_percpu, parse_memory_limit, parse_oom_kills, parse_usage_percpu, per_core_usage, read_interfaces, CgroupVersion,
+    ContainerMetrics, CpuMetrics, GpuBackend, GpuStats, MemoryMetrics, NetworkMetrics, DiskMetrics, ProcessMetrics, Result, StatsError,
+};
+use polis_core::ProcReader;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use sysinfo::{System, Pid};
 
 /// System metrics collector
 #[derive(Debug)]
 pub struct MetricsCollector {
     system: System,
+    proc_reader: ProcReader,
+    cgroup_root: PathBuf,
+    cgroup_mount: PathBuf,
+    /// Previous per-core reading per container, to turn counters into utilization
+    previous_per_core: HashMap<String, (Instant, Vec<u64>)>,
+    gpu: Option<Arc<dyn GpuBackend>>,
 }
 
 impl MetricsCollector {
@@ -13,21 +26,57 @@ impl MetricsCollector {
     pub fn new() -> Self {
         let mut system = System::new_all();
         system.refresh_all();
-        Self { system }
+        Self {
+            system,
+            proc_reader: ProcReader::new(),
+            cgroup_root: PathBuf::from("/sys/fs/cgroup/polis"),
+            cgroup_mount: PathBuf::from("/sys/fs/cgroup"),
+            previous_per_core: HashMap::new(),
+            gpu: detect_gpu_backend(),
+        }
+    }
+
+    /// Read processes from another /proc directory
+    pub fn with_proc_root(mut self, proc_root: impl Into<PathBuf>) -> Self {
+        self.proc_reader = ProcReader::with_root(proc_root);
+        self
+    }
+
+    /// Look up container cgroups under another root
+    pub fn with_cgroup_root(mut self, cgroup_root: impl Into<PathBuf>) -> Self {
+        self.cgroup_root = cgroup_root.into();
+        self
+    }
+
+    /// Detect the cgroup version under another mount point; cgroup v1
+    /// containers are looked up under `<mount>/cpuacct/polis`
+    pub fn with_cgroup_mount(mut self, cgroup_mount: impl Into<PathBuf>) -> Self {
+        self.cgroup_mount = cgroup_mount.into();
+        self
+    }
+
+    /// Read GPU statistics from another backend
+    pub fn with_gpu_backend(mut self, backend: Arc<dyn GpuBackend>) -> Self {
+        self.gpu = Some(backend);
+        self
     }
 
     /// Collect metrics for a specific container
     pub async fn collect_container_metrics(&mut self, container_id: &str) -> Result<ContainerMetrics> {
         self.system.refresh_all();
         
+        let mut cpu = self.collect_cpu_metrics(container_id).await?;
+        self.collect_per_core_usage(container_id, &mut cpu);
+
         let mut metrics = ContainerMetrics {
             container_id: container_id.to_string(),
             timestamp: SystemTime::now(),
-            cpu: self.collect_cpu_metrics(container_id).await?,
+            cpu,
             memory: self.collect_memory_metrics(container_id).await?,
             network: self.collect_network_metrics(container_id).await?,
             disk: self.collect_disk_metrics(container_id).await?,
             processes: self.collect_process_metrics(container_id).await?,
+            gpu: self.collect_gpu_metrics(),
         };
 
         Ok(metrics)
@@ -37,7 +86,7 @@ impl MetricsCollector {
     async fn collect_cpu_metrics(&self, container_id: &str) -> Result<CpuMetrics> {
         // For now, we'll use system-wide CPU metrics
         // In a real implementation, we'd read from /proc/[pid]/stat
-        let cpu_usage = self.system.global_cpu_info().cpu_usage();
+        let cpu_usage = self.system.global_cpu_usage();
         let cores = self.system.cpus().len();
         
         Ok(CpuMetrics {
@@ -48,9 +97,54 @@ impl MetricsCollector {
             total_time: 0, // Would read from /proc/[pid]/stat
             throttled_count: 0, // Would read from /proc/[pid]/cgroup
             throttled_time: 0, // Would read from /proc/[pid]/cgroup
+            per_core_time: Vec::new(),
+            per_core_usage: Vec::new(),
         })
     }
 
+    /// Fill per-core CPU time from the container cgroup and, from the second
+    /// sample on, per-core utilization. Leaves both empty when the active
+    /// cgroup version does not report per-core usage.
+    fn collect_per_core_usage(&mut self, container_id: &str, cpu: &mut CpuMetrics) {
+        let per_core_time = self.read_per_core_time(container_id);
+        if per_core_time.is_empty() {
+            self.previous_per_core.remove(container_id);
+            return;
+        }
+
+        let now = Instant::now();
+        if let Some((at, previous)) = self.previous_per_core.get(container_id) {
+            cpu.per_core_usage = per_core_usage(previous, &per_core_time, now.duration_since(*at));
+        }
+        self.previous_per_core
+            .insert(container_id.to_string(), (now, per_core_time.clone()));
+        cpu.per_core_time = per_core_time;
+    }
+
+    /// Cumulative CPU time per core (nanoseconds) for the container cgroup
+    fn read_per_core_time(&self, container_id: &str) -> Vec<u64> {
+        match detect_cgroup_version(&self.cgroup_mount) {
+            Some(CgroupVersion::V1) => {
+                let path = self
+                    .cgroup_mount
+                    .join("cpuacct")
+                    .join("polis")
+                    .join(container_id)
+                    .join("cpuacct.usage_percpu");
+                std::fs::read_to_string(path)
+                    .map(|content| parse_usage_percpu(&content))
+                    .unwrap_or_default()
+            }
+            Some(CgroupVersion::V2) => {
+                let path = self.cgroup_root.join(container_id).join("cpu.stat");
+                std::fs::read_to_string(path)
+                    .map(|content| parse_cpu_stat_percpu(&content))
+                    .unwrap_or_default()
+            }
+            None => Vec::new(),
+        }
+    }
+
     /// Collect memory metrics for a container
     async fn collect_memory_metrics(&self, container_id: &str) -> Result<MemoryMetrics> {
         // For now, we'll use system-wide memory metrics
@@ -58,25 +152,100 @@ impl MetricsCollector {
         let total_memory = self.system.total_memory();
         let used_memory = self.system.used_memory();
         let memory_usage_percent = (used_memory as f64 / total_memory as f64) * 100.0;
+        let cgroup = self.read_memory_cgroup(container_id);
         
         Ok(MemoryMetrics {
             usage: used_memory * 1024, // Convert from KB to bytes
-            limit: total_memory * 1024, // Convert from KB to bytes
+            limit: cgroup.limit,
             usage_percent: memory_usage_percent,
             peak_usage: used_memory * 1024, // Would track peak usage
             cache: 0, // Would read from /proc/[pid]/status
             rss: used_memory * 1024, // Would read from /proc/[pid]/status
             swap: 0, // Would read from /proc/[pid]/status
-            swap_limit: 0, // Would read from /proc/[pid]/cgroup
-            oom_kills: 0, // Would read from /proc/[pid]/cgroup
+            swap_limit: cgroup.swap_limit,
+            oom_kills: cgroup.oom_kills,
         })
     }
 
+    /// Memory limits and OOM kills of the container cgroup; a container
+    /// without a cgroup or without limits reports 0 (unlimited)
+    fn read_memory_cgroup(&self, container_id: &str) -> MemoryCgroup {
+        let read = |path: PathBuf| std::fs::read_to_string(path).ok();
+        match detect_cgroup_version(&self.cgroup_mount) {
+            Some(CgroupVersion::V1) => {
+                let dir = self.cgroup_mount.join("memory").join("polis").join(container_id);
+                let limit = read(dir.join("memory.limit_in_bytes"))
+                    .map(|content| parse_memory_limit(&content))
+                    .unwrap_or(0);
+                // memsw covers memory plus swap
+                let total = read(dir.join("memory.memsw.limit_in_bytes"))
+                    .map(|content| parse_memory_limit(&content))
+                    .unwrap_or(0);
+                MemoryCgroup {
+                    limit,
+                    swap_limit: total.saturating_sub(limit),
+                    oom_kills: read(dir.join("memory.oom_control"))
+                        .map(|content| parse_oom_kills(&content))
+                        .unwrap_or(0),
+                }
+            }
+            Some(CgroupVersion::V2) => {
+                let dir = self.cgroup_root.join(container_id);
+                MemoryCgroup {
+                    limit: read(dir.join("memory.max"))
+                        .map(|content| parse_memory_limit(&content))
+                        .unwrap_or(0),
+                    swap_limit: read(dir.join("memory.swap.max"))
+                        .map(|content| parse_memory_limit(&content))
+                        .unwrap_or(0),
+                    oom_kills: read(dir.join("memory.events"))
+                        .map(|content| parse_oom_kills(&content))
+                        .unwrap_or(0),
+                }
+            }
+            None => MemoryCgroup::default(),
+        }
+    }
+
     /// Collect network metrics for a container
     async fn collect_network_metrics(&self, container_id: &str) -> Result<NetworkMetrics> {
-        // For now, we'll return default values
-        // In a real implementation, we'd read from /proc/[pid]/net/dev
-        Ok(NetworkMetrics::default())
+        // Interfaces as seen from the container network namespace, through
+        // the root of its first process
+        let cgroup_dir = self.cgroup_root.join(container_id);
+        if !cgroup_dir.exists() {
+            return Ok(NetworkMetrics::default());
+        }
+        let pids = self
+            .proc_reader
+            .cgroup_pids(&cgroup_dir)
+            .map_err(|e| StatsError::System(e.to_string()))?;
+        let Some(pid) = pids.first() else {
+            return Ok(NetworkMetrics::default());
+        };
+
+        let net_dir = self
+            .proc_reader
+            .proc_root()
+            .join(pid.to_string())
+            .join("root/sys/class/net");
+        if !net_dir.is_dir() {
+            return Ok(NetworkMetrics::default());
+        }
+        Ok(NetworkMetrics::from_interfaces(read_interfaces(&net_dir)?))
+    }
+
+    /// Collect GPU metrics. GPUs are optional, so a missing backend or a
+    /// failing read leaves the field empty instead of failing the sample.
+    fn collect_gpu_metrics(&self) -> Option<GpuStats> {
+        let backend = self.gpu.as_ref()?;
+        match backend.devices() {
+            Ok(devices) if !devices.is_empty() => Some(GpuStats { devices }),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::debug!("Failed to read {} GPU stats: {}", backend.vendor(), e);
+                None
+            }
+        }
     }
 
     /// Collect disk metrics for a container
@@ -88,17 +257,26 @@ impl MetricsCollector {
 
     /// Collect process metrics for a container
     async fn collect_process_metrics(&self, container_id: &str) -> Result<ProcessMetrics> {
-        // For now, we'll use system-wide process metrics
-        // In a real implementation, we'd count processes in the container's PID namespace
-        let process_count = self.system.processes().len() as u32;
-        let thread_count = self.system.processes().len() as u32; // Simplified for Windows
-        
+        // Same cgroup enumeration used by `polis container top`
+        let cgroup_dir = self.cgroup_root.join(container_id);
+        if !cgroup_dir.exists() {
+            return Ok(ProcessMetrics {
+                state: "stopped".to_string(),
+                ..ProcessMetrics::default()
+            });
+        }
+
+        let processes = self
+            .proc_reader
+            .list_cgroup_processes(&cgroup_dir)
+            .map_err(|e| StatsError::System(e.to_string()))?;
+
         Ok(ProcessMetrics {
-            process_count,
-            thread_count,
+            process_count: processes.len() as u32,
+            thread_count: processes.iter().map(|p| p.threads).sum(),
             fd_count: 0, // Would read from /proc/[pid]/fd
             open_files: 0, // Would read from /proc/[pid]/fd
-            state: "running".to_string(), // Would read from /proc/[pid]/status
+            state: "running".to_string(),
         })
     }
 
@@ -110,13 +288,21 @@ impl MetricsCollector {
             total_swap: self.system.total_swap() * 1024, // Convert to bytes
             used_swap: self.system.used_swap() * 1024, // Convert to bytes
             cpu_count: self.system.cpus().len(),
-            cpu_usage: self.system.global_cpu_info().cpu_usage() as f64,
+            cpu_usage: self.system.global_cpu_usage() as f64,
             uptime: System::uptime(),
             load_average: System::load_average(),
         }
     }
 }
 
+/// Memory settings read from a container cgroup
+#[derive(Debug, Default)]
+struct MemoryCgroup {
+    limit: u64,
+    swap_limit: u64,
+    oom_kills: u64,
+}
+
 /// System information
 #[derive(Debug, Clone)]
 pub struct SystemInfo {
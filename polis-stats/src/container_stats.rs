@@ -1,15 +1,97 @@
-use crate::{ContainerMetrics, Result, StatsError};
-use std::collections::HashMap;
+use crate::{
+    alert::AlertState, selector_matches, AggregatedMetrics, AlertRule, ContainerMetrics, CounterDelta, LabelSelector, MetricField, MetricsDelta, PrometheusExporter, Result, StatsError,
+};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::SystemTime;
+use tokio::sync::{broadcast, RwLock};
 use tokio::time::{Duration, Instant};
 use tracing::{info, warn, error};
 
+/// Default number of samples retained per container
+pub const DEFAULT_HISTORY_LIMIT: usize = 300;
+
+/// Default age after which samples are evicted
+pub const DEFAULT_RETENTION: Duration = Duration::from_secs(30 * 60);
+
+/// Default capacity of each broadcast channel; slow subscribers skip
+/// ahead instead of holding samples back
+pub const DEFAULT_SUBSCRIBER_CAPACITY: usize = 64;
+
+/// Bounds applied to every container's history and live feed
+#[derive(Debug, Clone, Copy)]
+struct Retention {
+    max_samples: usize,
+    max_age: Duration,
+    subscriber_capacity: usize,
+}
+
+/// Bounded sample history and live feed for one container
+#[derive(Debug)]
+struct SampleHistory {
+    /// Samples with the monotonic instant they were recorded at
+    samples: VecDeque<(Instant, ContainerMetrics)>,
+    sender: broadcast::Sender<ContainerMetrics>,
+}
+
+impl SampleHistory {
+    fn new(retention: Retention) -> Self {
+        let (sender, _) = broadcast::channel(retention.subscriber_capacity);
+        Self {
+            samples: VecDeque::with_capacity(retention.max_samples.min(DEFAULT_HISTORY_LIMIT)),
+            sender,
+        }
+    }
+
+    /// Append a sample, evicting the oldest once the sample limit is reached
+    fn push(&mut self, at: Instant, metrics: ContainerMetrics, retention: Retention) {
+        while self.samples.len() >= retention.max_samples.max(1) {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((at, metrics.clone()));
+        self.evict_expired(at, retention.max_age);
+        // No receivers is not an error: nobody is following this container
+        let _ = self.sender.send(metrics);
+    }
+
+    /// Drop samples older than `max_age`
+    fn evict_expired(&mut self, now: Instant, max_age: Duration) {
+        while let Some((at, _)) = self.samples.front() {
+            if now.saturating_duration_since(*at) <= max_age {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    /// Samples recorded within `window` of `now`, oldest first
+    fn window(&self, now: Instant, window: Duration) -> impl Iterator<Item = &(Instant, ContainerMetrics)> {
+        self.samples
+            .iter()
+            .filter(move |(at, _)| now.saturating_duration_since(*at) <= window)
+    }
+}
+
 /// Container statistics collector with real-time monitoring
 #[derive(Debug)]
 pub struct ContainerStatsCollector {
     /// Container metrics cache
     metrics: Arc<RwLock<HashMap<String, ContainerMetrics>>>,
+    /// Recent samples and subscribers per container
+    history: Arc<RwLock<HashMap<String, SampleHistory>>>,
+    /// Labels of each container, for selector-based aggregation
+    labels: Arc<RwLock<HashMap<String, HashMap<String, String>>>>,
+    /// Alert rules of each container and whether they are alarming
+    alerts: Arc<RwLock<HashMap<String, Vec<AlertState>>>>,
+    /// Maximum samples kept per container
+    history_limit: usize,
+    /// Maximum age of a retained sample
+    retention_duration: Duration,
+    /// Capacity of every broadcast channel
+    subscriber_capacity: usize,
+    /// Samples of every container, for `subscribe_all`
+    feed: broadcast::Sender<ContainerMetrics>,
     /// Collection interval
     collection_interval: Duration,
     /// Running state
@@ -21,11 +103,61 @@ impl ContainerStatsCollector {
     pub fn new(collection_interval: Duration) -> Self {
         Self {
             metrics: Arc::new(RwLock::new(HashMap::new())),
+            history: Arc::new(RwLock::new(HashMap::new())),
+            labels: Arc::new(RwLock::new(HashMap::new())),
+            alerts: Arc::new(RwLock::new(HashMap::new())),
+            history_limit: DEFAULT_HISTORY_LIMIT,
+            retention_duration: DEFAULT_RETENTION,
+            subscriber_capacity: DEFAULT_SUBSCRIBER_CAPACITY,
+            feed: broadcast::channel(DEFAULT_SUBSCRIBER_CAPACITY).0,
             collection_interval,
             running: Arc::new(RwLock::new(false)),
         }
     }
 
+    /// Keep at most `limit` samples per container (minimum 1)
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = limit.max(1);
+        self
+    }
+
+    /// Evict samples older than `retention` on each collection tick
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention_duration = retention;
+        self
+    }
+
+    /// Buffer up to `capacity` samples (minimum 1) for each subscriber before
+    /// it starts lagging; applies to channels created afterwards
+    pub fn with_subscriber_capacity(mut self, capacity: usize) -> Self {
+        self.subscriber_capacity = capacity.max(1);
+        self.feed = broadcast::channel(self.subscriber_capacity).0;
+        self
+    }
+
+    /// Change the sampling interval; takes effect on the next `start_monitoring`
+    pub fn set_collection_interval(&mut self, collection_interval: Duration) {
+        self.collection_interval = collection_interval;
+    }
+
+    /// Maximum samples kept per container
+    pub fn history_limit(&self) -> usize {
+        self.history_limit
+    }
+
+    /// Maximum age of a retained sample
+    pub fn retention_duration(&self) -> Duration {
+        self.retention_duration
+    }
+
+    fn retention(&self) -> Retention {
+        Retention {
+            max_samples: self.history_limit,
+            max_age: self.retention_duration,
+            subscriber_capacity: self.subscriber_capacity,
+        }
+    }
+
     /// Start collecting statistics for a container
     pub async fn start_collecting(&self, container_id: &str) -> Result<()> {
         let mut metrics = self.metrics.write().await;
@@ -36,34 +168,259 @@ impl ContainerStatsCollector {
         Ok(())
     }
 
+    /// Start collecting statistics for a container carrying `labels`
+    pub async fn start_collecting_with_labels(
+        &self,
+        container_id: &str,
+        labels: HashMap<String, String>,
+    ) -> Result<()> {
+        self.labels.write().await.insert(container_id.to_string(), labels);
+        self.start_collecting(container_id).await
+    }
+
     /// Stop collecting statistics for a container
     pub async fn stop_collecting(&self, container_id: &str) -> Result<()> {
         let mut metrics = self.metrics.write().await;
         if metrics.remove(container_id).is_some() {
             info!("Stopped collecting stats for container: {}", container_id);
         }
+        // Dropping the sender ends every subscription for the container
+        self.history.write().await.remove(container_id);
+        self.labels.write().await.remove(container_id);
+        self.alerts.write().await.remove(container_id);
         Ok(())
     }
 
+    /// Evaluate `rule` against every new sample of a container.
+    ///
+    /// The callback runs once when the threshold has been exceeded for the
+    /// whole rule duration and once when the value drops back below it.
+    pub async fn set_alert_rule(&self, container_id: &str, rule: AlertRule) {
+        self.alerts
+            .write()
+            .await
+            .entry(container_id.to_string())
+            .or_default()
+            .push(AlertState::new(rule));
+    }
+
+    /// Remove every alert rule of a container
+    pub async fn clear_alert_rules(&self, container_id: &str) {
+        self.alerts.write().await.remove(container_id);
+    }
+
     /// Get current metrics for a container
     pub async fn get_metrics(&self, container_id: &str) -> Result<Option<ContainerMetrics>> {
         let metrics = self.metrics.read().await;
         Ok(metrics.get(container_id).cloned())
     }
 
+    /// Get retained samples for a container, oldest first.
+    ///
+    /// Only samples taken after `since` are returned; when more than `limit`
+    /// match, the most recent `limit` are kept. A `limit` of 0 means no limit.
+    /// Samples past the retention duration are skipped even before the next
+    /// collection tick evicts them.
+    pub async fn get_metrics_history(
+        &self,
+        container_id: &str,
+        since: Option<DateTime<Utc>>,
+        limit: usize,
+    ) -> Result<Vec<ContainerMetrics>> {
+        let history = self.history.read().await;
+        let Some(entry) = history.get(container_id) else {
+            return Ok(Vec::new());
+        };
+
+        let since = since.map(SystemTime::from);
+        let mut samples: Vec<ContainerMetrics> = entry
+            .window(Instant::now(), self.retention_duration)
+            .map(|(_, sample)| sample)
+            .filter(|sample| match since {
+                Some(since) => sample.timestamp > since,
+                None => true,
+            })
+            .cloned()
+            .collect();
+        if limit > 0 && samples.len() > limit {
+            samples.drain(..samples.len() - limit);
+        }
+        Ok(samples)
+    }
+
+    /// Receive every new sample for a container as it is recorded.
+    ///
+    /// Subscribing before collection starts is allowed. Receivers that fall
+    /// behind get `RecvError::Lagged` and continue with newer samples; the
+    /// channel closes when collection for the container stops.
+    pub async fn subscribe(&self, container_id: &str) -> broadcast::Receiver<ContainerMetrics> {
+        let mut history = self.history.write().await;
+        history
+            .entry(container_id.to_string())
+            .or_insert_with(|| SampleHistory::new(self.retention()))
+            .sender
+            .subscribe()
+    }
+
+    /// Receive the samples of every container on a single channel.
+    ///
+    /// Lagging and closing behave as for [`subscribe`](Self::subscribe),
+    /// except that the channel stays open while the collector exists.
+    pub fn subscribe_all(&self) -> broadcast::Receiver<ContainerMetrics> {
+        self.feed.subscribe()
+    }
+
+    /// Get the samples recorded within the last `window`, oldest first
+    pub async fn get_metrics_window(&self, container_id: &str, window: Duration) -> Vec<ContainerMetrics> {
+        let history = self.history.read().await;
+        match history.get(container_id) {
+            Some(entry) => entry
+                .window(Instant::now(), window)
+                .map(|(_, sample)| sample.clone())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Average per-second increase of a counter over the last `window`.
+    ///
+    /// Uses every sample in the window, so gaps in the history only widen
+    /// the interval. When the counter goes backwards (a restart) the value
+    /// after the reset counts as the increase, as it started from zero.
+    /// Needs at least two samples taken at different instants; gauges
+    /// have no rate.
+    pub async fn get_rate(&self, container_id: &str, field: MetricField, window: Duration) -> Option<f64> {
+        if !field.is_counter() {
+            return None;
+        }
+        let history = self.history.read().await;
+        let samples: Vec<&(Instant, ContainerMetrics)> = history
+            .get(container_id)?
+            .window(Instant::now(), window)
+            .collect();
+
+        let (first_at, _) = samples.first()?;
+        let (last_at, _) = samples.last()?;
+        let elapsed = last_at.saturating_duration_since(*first_at);
+        if elapsed.is_zero() {
+            return None;
+        }
+
+        let increase: u64 = samples
+            .windows(2)
+            .map(|pair| {
+                let before = field.value(&pair[0].1);
+                let after = field.value(&pair[1].1);
+                match CounterDelta::between(before, after, pair[1].0 - pair[0].0) {
+                    CounterDelta::Value { delta, .. } => delta,
+                    CounterDelta::Reset => after,
+                }
+            })
+            .sum();
+        Some(increase as f64 / elapsed.as_secs_f64())
+    }
+
+    /// Deltas between the two most recent retained samples of a container,
+    /// or `None` until two samples have been recorded
+    pub async fn get_delta(&self, container_id: &str) -> Option<MetricsDelta> {
+        let history = self.history.read().await;
+        let samples: Vec<&ContainerMetrics> = history
+            .get(container_id)?
+            .window(Instant::now(), self.retention_duration)
+            .map(|(_, sample)| sample)
+            .collect();
+        match samples.as_slice() {
+            [.., older, newer] => Some(older.diff(newer)),
+            _ => None,
+        }
+    }
+
     /// Get all container metrics
     pub async fn get_all_metrics(&self) -> Result<Vec<ContainerMetrics>> {
         let metrics = self.metrics.read().await;
         Ok(metrics.values().cloned().collect())
     }
 
+    /// Labels of every container started with labels, by container id
+    pub async fn get_all_labels(&self) -> HashMap<String, HashMap<String, String>> {
+        self.labels.read().await.clone()
+    }
+
+    /// Labels of a container; empty when it was started without labels
+    pub async fn get_labels(&self, container_id: &str) -> HashMap<String, String> {
+        self.labels.read().await.get(container_id).cloned().unwrap_or_default()
+    }
+
+    /// Aggregate the current metrics of the containers matching `selector`.
+    /// Containers started without labels only match the empty selector.
+    pub async fn get_aggregate(&self, selector: &LabelSelector) -> Option<AggregatedMetrics> {
+        let metrics = self.metrics.read().await;
+        let labels = self.labels.read().await;
+        let no_labels = HashMap::new();
+        AggregatedMetrics::from_metrics(metrics.iter().filter_map(|(id, sample)| {
+            selector_matches(selector, labels.get(id).unwrap_or(&no_labels)).then_some(sample)
+        }))
+    }
+
+    /// Current metrics of every container in the Prometheus text format
+    pub async fn export_prometheus(&self) -> String {
+        let metrics = self.metrics.read().await;
+        let mut samples: Vec<ContainerMetrics> = metrics.values().cloned().collect();
+        samples.sort_by(|a, b| a.container_id.cmp(&b.container_id));
+        PrometheusExporter::default().render_text(&samples)
+    }
+
     /// Update metrics for a container
     pub async fn update_metrics(&self, container_id: &str, new_metrics: ContainerMetrics) -> Result<()> {
-        let mut metrics = self.metrics.write().await;
-        metrics.insert(container_id.to_string(), new_metrics);
+        Self::record(&self.metrics, &self.history, &self.alerts, &self.feed, self.retention(), container_id, new_metrics).await;
         Ok(())
     }
 
+    /// Store a sample as the latest, append it to the history, publish it
+    /// and evaluate the container's alert rules against it
+    async fn record(
+        metrics: &Arc<RwLock<HashMap<String, ContainerMetrics>>>,
+        history: &Arc<RwLock<HashMap<String, SampleHistory>>>,
+        alerts: &Arc<RwLock<HashMap<String, Vec<AlertState>>>>,
+        feed: &broadcast::Sender<ContainerMetrics>,
+        retention: Retention,
+        container_id: &str,
+        new_metrics: ContainerMetrics,
+    ) {
+        let now = Instant::now();
+        metrics.write().await.insert(container_id.to_string(), new_metrics.clone());
+
+        // Callbacks run after the lock is released so they may use the collector
+        let transitions: Vec<_> = match alerts.write().await.get_mut(container_id) {
+            Some(states) => states
+                .iter_mut()
+                .filter_map(|state| state.observe(&new_metrics, container_id, now))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        // No receivers is not an error: nobody is following the collector
+        let _ = feed.send(new_metrics.clone());
+        history
+            .write()
+            .await
+            .entry(container_id.to_string())
+            .or_insert_with(|| SampleHistory::new(retention))
+            .push(now, new_metrics, retention);
+
+        for (callback, event) in transitions {
+            callback(event).await;
+        }
+    }
+
+    /// Evict expired samples from every container, including idle ones
+    async fn evict_expired(history: &Arc<RwLock<HashMap<String, SampleHistory>>>, max_age: Duration) {
+        let now = Instant::now();
+        for entry in history.write().await.values_mut() {
+            entry.evict_expired(now, max_age);
+        }
+    }
+
     /// Get container statistics summary
     pub async fn get_summary(&self) -> Result<ContainerStatsSummary> {
         let metrics = self.metrics.read().await;
@@ -98,6 +455,10 @@ impl ContainerStatsCollector {
         drop(running);
 
         let metrics = Arc::clone(&self.metrics);
+        let history = Arc::clone(&self.history);
+        let alerts = Arc::clone(&self.alerts);
+        let feed = self.feed.clone();
+        let retention = self.retention();
         let collection_interval = self.collection_interval;
         let running = Arc::clone(&self.running);
 
@@ -123,10 +484,12 @@ impl ContainerStatsCollector {
                 };
 
                 for container_id in container_ids {
-                    if let Err(e) = Self::update_container_metrics(&metrics, &container_id).await {
+                    if let Err(e) = Self::update_container_metrics(&metrics, &history, &alerts, &feed, retention, &container_id).await {
                         error!("Failed to update metrics for container {}: {}", container_id, e);
                     }
                 }
+
+                Self::evict_expired(&history, retention.max_age).await;
             }
         });
 
@@ -145,6 +508,10 @@ impl ContainerStatsCollector {
     /// Update metrics for a specific container
     async fn update_container_metrics(
         metrics: &Arc<RwLock<HashMap<String, ContainerMetrics>>>,
+        history: &Arc<RwLock<HashMap<String, SampleHistory>>>,
+        alerts: &Arc<RwLock<HashMap<String, Vec<AlertState>>>>,
+        feed: &broadcast::Sender<ContainerMetrics>,
+        retention: Retention,
         container_id: &str,
     ) -> Result<()> {
         // This would typically read from /proc/[pid]/stat, /proc/[pid]/status, etc.
@@ -180,8 +547,11 @@ impl ContainerStatsCollector {
         new_metrics.processes.fd_count = rand::random::<u32>() % 100;
         new_metrics.processes.state = "running".to_string();
 
-        let mut metrics_guard = metrics.write().await;
-        metrics_guard.insert(container_id.to_string(), new_metrics);
+        // Collection may have been stopped while this sample was taken
+        if !metrics.read().await.contains_key(container_id) {
+            return Ok(());
+        }
+        Self::record(metrics, history, alerts, feed, retention, container_id, new_metrics).await;
         
         Ok(())
     }
@@ -0,0 +1,315 @@
+use crate::ContainerMetrics;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Change of a cumulative counter between two samples
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CounterDelta {
+    /// Counter increased (or stayed the same) over the interval
+    Value {
+        /// Increase since the previous sample
+        delta: u64,
+        /// Average increase per second
+        rate: f64,
+    },
+    /// Counter went backwards, e.g. because the container restarted
+    Reset,
+}
+
+impl CounterDelta {
+    /// Compute the change from `before` to `after` over `interval`
+    pub fn between(before: u64, after: u64, interval: Duration) -> Self {
+        match after.checked_sub(before) {
+            Some(delta) => {
+                let seconds = interval.as_secs_f64();
+                let rate = if seconds > 0.0 {
+                    delta as f64 / seconds
+                } else {
+                    0.0
+                };
+                CounterDelta::Value { delta, rate }
+            }
+            None => CounterDelta::Reset,
+        }
+    }
+
+    /// Whether the counter was reset
+    pub fn is_reset(&self) -> bool {
+        matches!(self, CounterDelta::Reset)
+    }
+
+    /// Increase since the previous sample, if not reset
+    pub fn delta(&self) -> Option<u64> {
+        match self {
+            CounterDelta::Value { delta, .. } => Some(*delta),
+            CounterDelta::Reset => None,
+        }
+    }
+
+    /// Average increase per second, if not reset
+    pub fn rate(&self) -> Option<f64> {
+        match self {
+            CounterDelta::Value { rate, .. } => Some(*rate),
+            CounterDelta::Reset => None,
+        }
+    }
+
+    /// Increase since the previous sample, saturating to 0 on a reset
+    pub fn saturating_delta(&self) -> u64 {
+        self.delta().unwrap_or(0)
+    }
+}
+
+/// Field of a [`ContainerMetrics`] sample: a cumulative counter or a gauge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricField {
+    /// CPU time consumed (nanoseconds)
+    CpuTime,
+    RxBytes,
+    TxBytes,
+    RxPackets,
+    TxPackets,
+    ReadBytes,
+    WriteBytes,
+    ReadOps,
+    WriteOps,
+    /// CPU usage percentage (gauge)
+    CpuUsagePercent,
+    /// Memory usage in bytes (gauge)
+    MemoryUsage,
+    /// Memory usage percentage of the limit (gauge)
+    MemoryUsagePercent,
+}
+
+impl MetricField {
+    /// Every counter field
+    pub const ALL: [MetricField; 9] = [
+        MetricField::CpuTime,
+        MetricField::RxBytes,
+        MetricField::TxBytes,
+        MetricField::RxPackets,
+        MetricField::TxPackets,
+        MetricField::ReadBytes,
+        MetricField::WriteBytes,
+        MetricField::ReadOps,
+        MetricField::WriteOps,
+    ];
+
+    /// Every gauge field
+    pub const GAUGES: [MetricField; 3] = [
+        MetricField::CpuUsagePercent,
+        MetricField::MemoryUsage,
+        MetricField::MemoryUsagePercent,
+    ];
+
+    /// Whether the field only ever grows between restarts
+    pub fn is_counter(&self) -> bool {
+        !Self::GAUGES.contains(self)
+    }
+
+    /// Read the field from a sample; percentages are rounded down, use
+    /// [`reading`](Self::reading) for the exact value
+    pub fn value(&self, metrics: &ContainerMetrics) -> u64 {
+        match self {
+            MetricField::CpuTime => metrics.cpu.total_time,
+            MetricField::RxBytes => metrics.network.rx_bytes,
+            MetricField::TxBytes => metrics.network.tx_bytes,
+            MetricField::RxPackets => metrics.network.rx_packets,
+            MetricField::TxPackets => metrics.network.tx_packets,
+            MetricField::ReadBytes => metrics.disk.read_bytes,
+            MetricField::WriteBytes => metrics.disk.write_bytes,
+            MetricField::ReadOps => metrics.disk.read_ops,
+            MetricField::WriteOps => metrics.disk.write_ops,
+            MetricField::CpuUsagePercent => metrics.cpu.usage_percent as u64,
+            MetricField::MemoryUsage => metrics.memory.usage,
+            MetricField::MemoryUsagePercent => metrics.memory.usage_percent as u64,
+        }
+    }
+
+    /// Read the field from a sample as a float
+    pub fn reading(&self, metrics: &ContainerMetrics) -> f64 {
+        match self {
+            MetricField::CpuUsagePercent => metrics.cpu.usage_percent,
+            MetricField::MemoryUsagePercent => metrics.memory.usage_percent,
+            _ => self.value(metrics) as f64,
+        }
+    }
+
+    /// Name used in deltas and output, e.g. `rx_bytes`
+    pub fn name(&self) -> &'static str {
+        match self {
+            MetricField::CpuTime => "cpu_time",
+            MetricField::RxBytes => "rx_bytes",
+            MetricField::TxBytes => "tx_bytes",
+            MetricField::RxPackets => "rx_packets",
+            MetricField::TxPackets => "tx_packets",
+            MetricField::ReadBytes => "read_bytes",
+            MetricField::WriteBytes => "write_bytes",
+            MetricField::ReadOps => "read_ops",
+            MetricField::WriteOps => "write_ops",
+            MetricField::CpuUsagePercent => "cpu_usage_percent",
+            MetricField::MemoryUsage => "memory_usage",
+            MetricField::MemoryUsagePercent => "memory_usage_percent",
+        }
+    }
+}
+
+/// Differences between two metric samples of the same container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsDelta {
+    /// Container ID
+    pub container_id: String,
+    /// Time between the two samples
+    pub interval: Duration,
+    /// Whether any counter went backwards
+    pub reset: bool,
+    /// Average CPU usage over the interval, from cumulative CPU time (100% = one core)
+    pub cpu_percent: Option<f64>,
+    /// Mean of the two samples' `cpu.usage_percent`
+    pub avg_cpu_usage_percent: f64,
+    /// Mean of the two samples' `memory.usage_percent`
+    pub avg_memory_usage_percent: f64,
+    /// CPU time consumed (nanoseconds)
+    pub cpu_time: CounterDelta,
+    /// User CPU time (nanoseconds)
+    pub user_time: CounterDelta,
+    /// System CPU time (nanoseconds)
+    pub system_time: CounterDelta,
+    /// CPU throttling events
+    pub throttled_count: CounterDelta,
+    /// CPU throttling time (nanoseconds)
+    pub throttled_time: CounterDelta,
+    /// Change in memory usage in bytes (a gauge, so it may be negative)
+    pub memory_change: i64,
+    /// Bytes received
+    pub rx_bytes: CounterDelta,
+    /// Bytes transmitted
+    pub tx_bytes: CounterDelta,
+    /// Packets received
+    pub rx_packets: CounterDelta,
+    /// Packets transmitted
+    pub tx_packets: CounterDelta,
+    /// Receive errors
+    pub rx_errors: CounterDelta,
+    /// Transmit errors
+    pub tx_errors: CounterDelta,
+    /// Receive dropped packets
+    pub rx_dropped: CounterDelta,
+    /// Transmit dropped packets
+    pub tx_dropped: CounterDelta,
+    /// Bytes read from disk
+    pub read_bytes: CounterDelta,
+    /// Bytes written to disk
+    pub write_bytes: CounterDelta,
+    /// Disk read operations
+    pub read_ops: CounterDelta,
+    /// Disk write operations
+    pub write_ops: CounterDelta,
+    /// Time spent reading from disk (nanoseconds)
+    pub read_time: CounterDelta,
+    /// Time spent writing to disk (nanoseconds)
+    pub write_time: CounterDelta,
+}
+
+impl ContainerMetrics {
+    /// Compute deltas and rates from `self` (the earlier sample) to `later`.
+    ///
+    /// Also callable as `ContainerMetrics::diff(&older, &newer)`.
+    pub fn diff(&self, later: &ContainerMetrics) -> MetricsDelta {
+        // Samples taken out of order are treated as having no elapsed time
+        let interval = later
+            .timestamp
+            .duration_since(self.timestamp)
+            .unwrap_or(Duration::ZERO);
+        let counter = |before: u64, after: u64| CounterDelta::between(before, after, interval);
+
+        let cpu_time = counter(self.cpu.total_time, later.cpu.total_time);
+        let cpu_percent = match cpu_time.delta() {
+            Some(delta) if !interval.is_zero() => {
+                Some(delta as f64 / interval.as_nanos() as f64 * 100.0)
+            }
+            _ => None,
+        };
+
+        let mut delta = MetricsDelta {
+            container_id: later.container_id.clone(),
+            interval,
+            reset: false,
+            cpu_percent,
+            avg_cpu_usage_percent: (self.cpu.usage_percent + later.cpu.usage_percent) / 2.0,
+            avg_memory_usage_percent: (self.memory.usage_percent + later.memory.usage_percent)
+                / 2.0,
+            cpu_time,
+            user_time: counter(self.cpu.user_time, later.cpu.user_time),
+            system_time: counter(self.cpu.system_time, later.cpu.system_time),
+            throttled_count: counter(self.cpu.throttled_count, later.cpu.throttled_count),
+            throttled_time: counter(self.cpu.throttled_time, later.cpu.throttled_time),
+            memory_change: later.memory.usage as i64 - self.memory.usage as i64,
+            rx_bytes: counter(self.network.rx_bytes, later.network.rx_bytes),
+            tx_bytes: counter(self.network.tx_bytes, later.network.tx_bytes),
+            rx_packets: counter(self.network.rx_packets, later.network.rx_packets),
+            tx_packets: counter(self.network.tx_packets, later.network.tx_packets),
+            rx_errors: counter(self.network.rx_errors, later.network.rx_errors),
+            tx_errors: counter(self.network.tx_errors, later.network.tx_errors),
+            rx_dropped: counter(self.network.rx_dropped, later.network.rx_dropped),
+            tx_dropped: counter(self.network.tx_dropped, later.network.tx_dropped),
+            read_bytes: counter(self.disk.read_bytes, later.disk.read_bytes),
+            write_bytes: counter(self.disk.write_bytes, later.disk.write_bytes),
+            read_ops: counter(self.disk.read_ops, later.disk.read_ops),
+            write_ops: counter(self.disk.write_ops, later.disk.write_ops),
+            read_time: counter(self.disk.read_time, later.disk.read_time),
+            write_time: counter(self.disk.write_time, later.disk.write_time),
+        };
+        delta.reset = delta.counters().iter().any(|(_, c)| c.is_reset());
+        delta
+    }
+}
+
+impl MetricsDelta {
+    /// All counter deltas with their names
+    pub fn counters(&self) -> Vec<(&'static str, CounterDelta)> {
+        vec![
+            ("cpu_time", self.cpu_time),
+            ("user_time", self.user_time),
+            ("system_time", self.system_time),
+            ("throttled_count", self.throttled_count),
+            ("throttled_time", self.throttled_time),
+            ("rx_bytes", self.rx_bytes),
+            ("tx_bytes", self.tx_bytes),
+            ("rx_packets", self.rx_packets),
+            ("tx_packets", self.tx_packets),
+            ("rx_errors", self.rx_errors),
+            ("tx_errors", self.tx_errors),
+            ("rx_dropped", self.rx_dropped),
+            ("tx_dropped", self.tx_dropped),
+            ("read_bytes", self.read_bytes),
+            ("write_bytes", self.write_bytes),
+            ("read_ops", self.read_ops),
+            ("write_ops", self.write_ops),
+            ("read_time", self.read_time),
+            ("write_time", self.write_time),
+        ]
+    }
+
+    /// Time between the two samples
+    pub fn duration(&self) -> Duration {
+        self.interval
+    }
+
+    /// Per-second rate of `delta` over the interval; 0 when both samples
+    /// were taken at the same instant
+    pub fn rate<T: Into<f64>>(&self, delta: T) -> f64 {
+        let seconds = self.interval.as_secs_f64();
+        if seconds > 0.0 {
+            delta.into() / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Compute the deltas between two samples of the same container
+pub fn diff(before: &ContainerMetrics, after: &ContainerMetrics) -> MetricsDelta {
+    before.diff(after)
+}
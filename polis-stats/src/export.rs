@@ -0,0 +1,253 @@
+use crate::{ContainerMetrics, ContainerStatsCollector, Result, StatsError};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Output format of a `StatsExporter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// A JSON array of `ContainerMetrics` snapshots
+    #[default]
+    Json,
+    /// One row per container per tick, with flattened columns
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = StatsError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            other => Err(StatsError::Parse(format!(
+                "unknown export format '{}' (expected json or csv)",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+        })
+    }
+}
+
+/// A CSV column and how to read it from a sample
+struct Column {
+    name: &'static str,
+    value: fn(&ContainerMetrics) -> String,
+}
+
+const COLUMNS: &[Column] = &[
+    Column {
+        name: "timestamp",
+        value: |m| DateTime::<Utc>::from(m.timestamp).to_rfc3339_opts(SecondsFormat::Millis, true),
+    },
+    Column {
+        name: "container_id",
+        value: |m| m.container_id.clone(),
+    },
+    Column {
+        name: "cpu_percent",
+        value: |m| m.cpu.usage_percent.to_string(),
+    },
+    Column {
+        name: "cpu_cores",
+        value: |m| m.cpu.cores.to_string(),
+    },
+    Column {
+        name: "cpu_total_ns",
+        value: |m| m.cpu.total_time.to_string(),
+    },
+    Column {
+        name: "cpu_throttled",
+        value: |m| m.cpu.throttled_count.to_string(),
+    },
+    Column {
+        name: "mem_usage",
+        value: |m| m.memory.usage.to_string(),
+    },
+    Column {
+        name: "mem_limit",
+        value: |m| m.memory.limit.to_string(),
+    },
+    Column {
+        name: "mem_percent",
+        value: |m| m.memory.usage_percent.to_string(),
+    },
+    Column {
+        name: "mem_oom_kills",
+        value: |m| m.memory.oom_kills.to_string(),
+    },
+    Column {
+        name: "rx_bytes",
+        value: |m| m.network.rx_bytes.to_string(),
+    },
+    Column {
+        name: "tx_bytes",
+        value: |m| m.network.tx_bytes.to_string(),
+    },
+    Column {
+        name: "rx_packets",
+        value: |m| m.network.rx_packets.to_string(),
+    },
+    Column {
+        name: "tx_packets",
+        value: |m| m.network.tx_packets.to_string(),
+    },
+    Column {
+        name: "disk_read_bytes",
+        value: |m| m.disk.read_bytes.to_string(),
+    },
+    Column {
+        name: "disk_write_bytes",
+        value: |m| m.disk.write_bytes.to_string(),
+    },
+    Column {
+        name: "disk_read_ops",
+        value: |m| m.disk.read_ops.to_string(),
+    },
+    Column {
+        name: "disk_write_ops",
+        value: |m| m.disk.write_ops.to_string(),
+    },
+    Column {
+        name: "processes",
+        value: |m| m.processes.process_count.to_string(),
+    },
+    Column {
+        name: "threads",
+        value: |m| m.processes.thread_count.to_string(),
+    },
+    Column {
+        name: "fds",
+        value: |m| m.processes.fd_count.to_string(),
+    },
+];
+
+/// Names of the CSV columns, in order
+pub fn csv_columns() -> Vec<&'static str> {
+    COLUMNS.iter().map(|column| column.name).collect()
+}
+
+/// Writes container samples as JSON or CSV, one tick at a time.
+///
+/// Every tick is flushed as soon as it is written, so a long capture never
+/// holds more than one tick in memory. The JSON array is only closed by
+/// `finish`; the CSV header is written before the first row, even when the
+/// first tick is empty.
+pub struct StatsExporter<W: Write> {
+    out: W,
+    format: ExportFormat,
+    started: bool,
+    records: usize,
+}
+
+impl<W: Write> StatsExporter<W> {
+    pub fn new(out: W, format: ExportFormat) -> Self {
+        Self {
+            out,
+            format,
+            started: false,
+            records: 0,
+        }
+    }
+
+    pub fn format(&self) -> ExportFormat {
+        self.format
+    }
+
+    /// Samples written so far
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    /// Write the samples of one tick and flush them
+    pub fn write_tick(&mut self, metrics: &[ContainerMetrics]) -> Result<()> {
+        self.start()?;
+        for sample in metrics {
+            match self.format {
+                ExportFormat::Json => {
+                    self.out
+                        .write_all(if self.records == 0 { b"\n" } else { b",\n" })?;
+                    serde_json::to_writer(&mut self.out, sample).map_err(std::io::Error::from)?;
+                }
+                ExportFormat::Csv => {
+                    let row: Vec<String> = COLUMNS
+                        .iter()
+                        .map(|column| csv_field(&(column.value)(sample)))
+                        .collect();
+                    writeln!(self.out, "{}", row.join(","))?;
+                }
+            }
+            self.records += 1;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Terminate the output and hand the writer back
+    pub fn finish(mut self) -> Result<W> {
+        self.start()?;
+        if self.format == ExportFormat::Json {
+            self.out
+                .write_all(if self.records == 0 { b"]\n" } else { b"\n]\n" })?;
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn start(&mut self) -> Result<()> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+        match self.format {
+            ExportFormat::Json => self.out.write_all(b"[")?,
+            ExportFormat::Csv => writeln!(self.out, "{}", csv_columns().join(","))?,
+        }
+        Ok(())
+    }
+}
+
+/// Quote a CSV field when it contains a separator, a quote or a line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Sample every container of `collector` once per `interval` for
+/// `duration`, starting immediately, and write each tick to `exporter`.
+/// Containers are written in id order. Returns the number of ticks taken,
+/// at least one even when `duration` is shorter than `interval`.
+pub async fn capture<W: Write>(
+    collector: &ContainerStatsCollector,
+    exporter: &mut StatsExporter<W>,
+    duration: Duration,
+    interval: Duration,
+) -> Result<u64> {
+    if interval.is_zero() {
+        return Err(StatsError::Parse(
+            "export interval must be greater than zero".to_string(),
+        ));
+    }
+    let ticks = (duration.as_nanos() / interval.as_nanos()).max(1) as u64;
+    let mut timer = tokio::time::interval(interval);
+    for _ in 0..ticks {
+        timer.tick().await;
+        let mut metrics = collector.get_all_metrics().await?;
+        metrics.sort_by(|a, b| a.container_id.cmp(&b.container_id));
+        exporter.write_tick(&metrics)?;
+    }
+    Ok(ticks)
+}
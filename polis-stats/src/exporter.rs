@@ -0,0 +1,482 @@
+use crate::ContainerMetrics;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::UNIX_EPOCH;
+
+/// Prometheus metric type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Counter,
+    Gauge,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+        }
+    }
+}
+
+/// A metric family with one sample per container
+struct Family {
+    name: &'static str,
+    help: &'static str,
+    kind: Kind,
+    value: fn(&ContainerMetrics) -> f64,
+}
+
+const FAMILIES: &[Family] = &[
+    Family {
+        name: "polis_container_cpu_usage_percent",
+        help: "CPU usage percentage",
+        kind: Kind::Gauge,
+        value: |m| m.cpu.usage_percent,
+    },
+    Family {
+        name: "polis_container_cpu_cores",
+        help: "Number of CPU cores",
+        kind: Kind::Gauge,
+        value: |m| m.cpu.cores as f64,
+    },
+    Family {
+        name: "polis_container_cpu_user_nanoseconds_total",
+        help: "CPU time in user mode",
+        kind: Kind::Counter,
+        value: |m| m.cpu.user_time as f64,
+    },
+    Family {
+        name: "polis_container_cpu_system_nanoseconds_total",
+        help: "CPU time in system mode",
+        kind: Kind::Counter,
+        value: |m| m.cpu.system_time as f64,
+    },
+    Family {
+        name: "polis_container_cpu_nanoseconds_total",
+        help: "Total CPU time",
+        kind: Kind::Counter,
+        value: |m| m.cpu.total_time as f64,
+    },
+    Family {
+        name: "polis_container_cpu_throttled_total",
+        help: "CPU throttling events",
+        kind: Kind::Counter,
+        value: |m| m.cpu.throttled_count as f64,
+    },
+    Family {
+        name: "polis_container_cpu_throttled_nanoseconds_total",
+        help: "Time spent throttled",
+        kind: Kind::Counter,
+        value: |m| m.cpu.throttled_time as f64,
+    },
+    Family {
+        name: "polis_container_memory_usage_bytes",
+        help: "Memory usage",
+        kind: Kind::Gauge,
+        value: |m| m.memory.usage as f64,
+    },
+    Family {
+        name: "polis_container_memory_limit_bytes",
+        help: "Memory limit (0 = unlimited)",
+        kind: Kind::Gauge,
+        value: |m| m.memory.limit as f64,
+    },
+    Family {
+        name: "polis_container_memory_usage_percent",
+        help: "Memory usage percentage",
+        kind: Kind::Gauge,
+        value: |m| m.memory.usage_percent,
+    },
+    Family {
+        name: "polis_container_memory_peak_bytes",
+        help: "Peak memory usage",
+        kind: Kind::Gauge,
+        value: |m| m.memory.peak_usage as f64,
+    },
+    Family {
+        name: "polis_container_memory_cache_bytes",
+        help: "Memory used as cache",
+        kind: Kind::Gauge,
+        value: |m| m.memory.cache as f64,
+    },
+    Family {
+        name: "polis_container_memory_rss_bytes",
+        help: "Resident set size",
+        kind: Kind::Gauge,
+        value: |m| m.memory.rss as f64,
+    },
+    Family {
+        name: "polis_container_memory_swap_bytes",
+        help: "Swap usage",
+        kind: Kind::Gauge,
+        value: |m| m.memory.swap as f64,
+    },
+    Family {
+        name: "polis_container_memory_swap_limit_bytes",
+        help: "Swap limit",
+        kind: Kind::Gauge,
+        value: |m| m.memory.swap_limit as f64,
+    },
+    Family {
+        name: "polis_container_memory_oom_kills_total",
+        help: "Processes killed by the OOM killer",
+        kind: Kind::Counter,
+        value: |m| m.memory.oom_kills as f64,
+    },
+    Family {
+        name: "polis_container_network_receive_bytes_total",
+        help: "Bytes received, excluding loopback",
+        kind: Kind::Counter,
+        value: |m| m.network.rx_bytes as f64,
+    },
+    Family {
+        name: "polis_container_network_transmit_bytes_total",
+        help: "Bytes transmitted, excluding loopback",
+        kind: Kind::Counter,
+        value: |m| m.network.tx_bytes as f64,
+    },
+    Family {
+        name: "polis_container_network_receive_packets_total",
+        help: "Packets received, excluding loopback",
+        kind: Kind::Counter,
+        value: |m| m.network.rx_packets as f64,
+    },
+    Family {
+        name: "polis_container_network_transmit_packets_total",
+        help: "Packets transmitted, excluding loopback",
+        kind: Kind::Counter,
+        value: |m| m.network.tx_packets as f64,
+    },
+    Family {
+        name: "polis_container_network_receive_errors_total",
+        help: "Receive errors, excluding loopback",
+        kind: Kind::Counter,
+        value: |m| m.network.rx_errors as f64,
+    },
+    Family {
+        name: "polis_container_network_transmit_errors_total",
+        help: "Transmit errors, excluding loopback",
+        kind: Kind::Counter,
+        value: |m| m.network.tx_errors as f64,
+    },
+    Family {
+        name: "polis_container_network_receive_dropped_total",
+        help: "Received packets dropped, excluding loopback",
+        kind: Kind::Counter,
+        value: |m| m.network.rx_dropped as f64,
+    },
+    Family {
+        name: "polis_container_network_transmit_dropped_total",
+        help: "Transmitted packets dropped, excluding loopback",
+        kind: Kind::Counter,
+        value: |m| m.network.tx_dropped as f64,
+    },
+    Family {
+        name: "polis_container_disk_read_bytes_total",
+        help: "Bytes read from disk",
+        kind: Kind::Counter,
+        value: |m| m.disk.read_bytes as f64,
+    },
+    Family {
+        name: "polis_container_disk_write_bytes_total",
+        help: "Bytes written to disk",
+        kind: Kind::Counter,
+        value: |m| m.disk.write_bytes as f64,
+    },
+    Family {
+        name: "polis_container_disk_reads_total",
+        help: "Disk read operations",
+        kind: Kind::Counter,
+        value: |m| m.disk.read_ops as f64,
+    },
+    Family {
+        name: "polis_container_disk_writes_total",
+        help: "Disk write operations",
+        kind: Kind::Counter,
+        value: |m| m.disk.write_ops as f64,
+    },
+    Family {
+        name: "polis_container_disk_read_nanoseconds_total",
+        help: "Time spent reading from disk",
+        kind: Kind::Counter,
+        value: |m| m.disk.read_time as f64,
+    },
+    Family {
+        name: "polis_container_disk_write_nanoseconds_total",
+        help: "Time spent writing to disk",
+        kind: Kind::Counter,
+        value: |m| m.disk.write_time as f64,
+    },
+    Family {
+        name: "polis_container_processes",
+        help: "Number of processes",
+        kind: Kind::Gauge,
+        value: |m| m.processes.process_count as f64,
+    },
+    Family {
+        name: "polis_container_threads",
+        help: "Number of threads",
+        kind: Kind::Gauge,
+        value: |m| m.processes.thread_count as f64,
+    },
+    Family {
+        name: "polis_container_file_descriptors",
+        help: "Number of file descriptors",
+        kind: Kind::Gauge,
+        value: |m| m.processes.fd_count as f64,
+    },
+    Family {
+        name: "polis_container_open_files",
+        help: "Number of open files",
+        kind: Kind::Gauge,
+        value: |m| m.processes.open_files as f64,
+    },
+    Family {
+        name: "polis_container_last_sample_timestamp_seconds",
+        help: "When the sample was taken, in seconds since the epoch",
+        kind: Kind::Gauge,
+        value: |m| {
+            m.timestamp
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs_f64())
+                .unwrap_or(0.0)
+        },
+    },
+];
+
+/// A per-interface metric family, labelled with `interface`
+struct InterfaceFamily {
+    name: &'static str,
+    help: &'static str,
+    value: fn(&crate::InterfaceStats) -> u64,
+}
+
+const INTERFACE_FAMILIES: &[InterfaceFamily] = &[
+    InterfaceFamily {
+        name: "polis_container_interface_receive_bytes_total",
+        help: "Bytes received per interface",
+        value: |s| s.rx_bytes,
+    },
+    InterfaceFamily {
+        name: "polis_container_interface_transmit_bytes_total",
+        help: "Bytes transmitted per interface",
+        value: |s| s.tx_bytes,
+    },
+    InterfaceFamily {
+        name: "polis_container_interface_receive_packets_total",
+        help: "Packets received per interface",
+        value: |s| s.rx_packets,
+    },
+    InterfaceFamily {
+        name: "polis_container_interface_transmit_packets_total",
+        help: "Packets transmitted per interface",
+        value: |s| s.tx_packets,
+    },
+    InterfaceFamily {
+        name: "polis_container_interface_receive_errors_total",
+        help: "Receive errors per interface",
+        value: |s| s.rx_errors,
+    },
+    InterfaceFamily {
+        name: "polis_container_interface_transmit_errors_total",
+        help: "Transmit errors per interface",
+        value: |s| s.tx_errors,
+    },
+    InterfaceFamily {
+        name: "polis_container_interface_receive_dropped_total",
+        help: "Received packets dropped per interface",
+        value: |s| s.rx_dropped,
+    },
+    InterfaceFamily {
+        name: "polis_container_interface_transmit_dropped_total",
+        help: "Transmitted packets dropped per interface",
+        value: |s| s.tx_dropped,
+    },
+];
+
+/// Renders container metrics in the Prometheus text exposition format.
+///
+/// Every family is written once with its `# HELP` and `# TYPE` lines, then
+/// one sample per container labelled with `container_id`. Per-core CPU
+/// samples carry a `core` label and per-interface samples an `interface`
+/// label; the per-interface families can be left out to keep the series
+/// count down on hosts with many virtual interfaces.
+#[derive(Debug, Clone)]
+pub struct PrometheusExporter {
+    include_interfaces: bool,
+}
+
+impl Default for PrometheusExporter {
+    fn default() -> Self {
+        Self {
+            include_interfaces: true,
+        }
+    }
+}
+
+impl PrometheusExporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Include or exclude the per-interface network families (included by default)
+    pub fn with_interfaces(mut self, include: bool) -> Self {
+        self.include_interfaces = include;
+        self
+    }
+
+    /// Render every metric of the given samples
+    pub fn render_text(&self, metrics: &[ContainerMetrics]) -> String {
+        let mut out = String::with_capacity(metrics.len() * 4096 + 1024);
+        // Writing into a String cannot fail
+        let _ = self.write_text(&mut out, metrics);
+        out
+    }
+
+    /// Render into an existing buffer
+    pub fn write_text<W: Write>(
+        &self,
+        out: &mut W,
+        metrics: &[ContainerMetrics],
+    ) -> std::fmt::Result {
+        self.write_text_with_labels(out, metrics, &HashMap::new())
+    }
+
+    /// Render into an existing buffer, adding `labels[container_id]` to every
+    /// sample of that container after `container_id`. Label names are written
+    /// as given and must already be valid Prometheus label names.
+    pub fn write_text_with_labels<W: Write>(
+        &self,
+        out: &mut W,
+        metrics: &[ContainerMetrics],
+        labels: &HashMap<String, Vec<(String, String)>>,
+    ) -> std::fmt::Result {
+        for family in FAMILIES {
+            write_header(out, family.name, family.help, family.kind)?;
+            for sample in metrics {
+                write_sample(
+                    out,
+                    family.name,
+                    &sample.container_id,
+                    labels_of(labels, &sample.container_id),
+                    None,
+                    (family.value)(sample),
+                )?;
+            }
+        }
+
+        write_header(
+            out,
+            "polis_container_cpu_core_nanoseconds_total",
+            "CPU time per core",
+            Kind::Counter,
+        )?;
+        for sample in metrics {
+            for (core, time) in sample.cpu.per_core_time.iter().enumerate() {
+                write_sample(
+                    out,
+                    "polis_container_cpu_core_nanoseconds_total",
+                    &sample.container_id,
+                    labels_of(labels, &sample.container_id),
+                    Some(("core", &core.to_string())),
+                    *time as f64,
+                )?;
+            }
+        }
+
+        write_header(
+            out,
+            "polis_container_cpu_core_usage_percent",
+            "CPU usage per core since the previous sample",
+            Kind::Gauge,
+        )?;
+        for sample in metrics {
+            for (core, usage) in sample.cpu.per_core_usage.iter().enumerate() {
+                write_sample(
+                    out,
+                    "polis_container_cpu_core_usage_percent",
+                    &sample.container_id,
+                    labels_of(labels, &sample.container_id),
+                    Some(("core", &core.to_string())),
+                    *usage,
+                )?;
+            }
+        }
+
+        if self.include_interfaces {
+            for family in INTERFACE_FAMILIES {
+                write_header(out, family.name, family.help, Kind::Counter)?;
+                for sample in metrics {
+                    let mut interfaces: Vec<_> = sample.network.interfaces.iter().collect();
+                    interfaces.sort_by(|a, b| a.0.cmp(b.0));
+                    for (name, stats) in interfaces {
+                        write_sample(
+                            out,
+                            family.name,
+                            &sample.container_id,
+                            labels_of(labels, &sample.container_id),
+                            Some(("interface", name)),
+                            (family.value)(stats) as f64,
+                        )?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn labels_of<'a>(
+    labels: &'a HashMap<String, Vec<(String, String)>>,
+    container_id: &str,
+) -> &'a [(String, String)] {
+    labels.get(container_id).map(Vec::as_slice).unwrap_or(&[])
+}
+
+fn write_header<W: Write>(out: &mut W, name: &str, help: &str, kind: Kind) -> std::fmt::Result {
+    writeln!(out, "# HELP {} {}", name, help)?;
+    writeln!(out, "# TYPE {} {}", name, kind.as_str())
+}
+
+fn write_sample<W: Write>(
+    out: &mut W,
+    name: &str,
+    container_id: &str,
+    labels: &[(String, String)],
+    extra: Option<(&str, &str)>,
+    value: f64,
+) -> std::fmt::Result {
+    write!(out, "{}{{container_id=\"", name)?;
+    write_escaped(out, container_id)?;
+    for (label, label_value) in labels {
+        write!(out, "\",{}=\"", label)?;
+        write_escaped(out, label_value)?;
+    }
+    if let Some((label, label_value)) = extra {
+        write!(out, "\",{}=\"", label)?;
+        write_escaped(out, label_value)?;
+    }
+    out.write_str("\"} ")?;
+    if value.is_nan() {
+        out.write_str("NaN")?;
+    } else if value.is_infinite() {
+        out.write_str(if value > 0.0 { "+Inf" } else { "-Inf" })?;
+    } else {
+        write!(out, "{}", value)?;
+    }
+    out.write_char('\n')
+}
+
+/// Escape a label value: backslash, double quote and newline
+fn write_escaped<W: Write>(out: &mut W, value: &str) -> std::fmt::Result {
+    for c in value.chars() {
+        match c {
+            '\\' => out.write_str("\\\\")?,
+            '"' => out.write_str("\\\"")?,
+            '\n' => out.write_str("\\n")?,
+            c => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use crate::{GpuDeviceStats, Result};
+use std::fmt;
+use std::sync::Arc;
+
+/// Source of GPU device statistics.
+///
+/// Backends report every GPU visible to the host; a container given only
+/// some of them should be paired with a backend restricted to those.
+pub trait GpuBackend: Send + Sync + fmt::Debug {
+    /// Vendor name, e.g. `nvidia`
+    fn vendor(&self) -> &'static str;
+
+    /// Current statistics of every device
+    fn devices(&self) -> Result<Vec<GpuDeviceStats>>;
+}
+
+/// First GPU backend enabled at build time that initializes on this host.
+///
+/// Returns `None` on hosts without a GPU, without the vendor driver, or when
+/// neither `gpu-nvidia` nor `gpu-amd` is enabled.
+pub fn detect_gpu_backend() -> Option<Arc<dyn GpuBackend>> {
+    #[cfg(feature = "gpu-nvidia")]
+    if let Some(backend) = nvidia::NvidiaBackend::init() {
+        return Some(Arc::new(backend));
+    }
+    #[cfg(feature = "gpu-amd")]
+    if let Some(backend) = amd::AmdBackend::detect() {
+        return Some(Arc::new(backend));
+    }
+    None
+}
+
+/// NVIDIA GPUs through NVML
+#[cfg(feature = "gpu-nvidia")]
+pub mod nvidia {
+    use super::GpuBackend;
+    use crate::{GpuDeviceStats, Result, StatsError};
+    use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
+    use nvml_wrapper::Nvml;
+    use std::fmt;
+
+    /// The NVML calls used by [`NvidiaBackend`], by device index
+    pub trait NvmlApi: Send + Sync {
+        fn device_count(&self) -> Result<u32>;
+        fn uuid(&self, index: u32) -> Result<String>;
+        /// GPU busy percentage over the last sample period
+        fn utilization(&self, index: u32) -> Result<u32>;
+        /// Used and total device memory in bytes
+        fn memory(&self, index: u32) -> Result<(u64, u64)>;
+        /// Core temperature in degrees Celsius
+        fn temperature(&self, index: u32) -> Result<u32>;
+    }
+
+    fn nvml_error(error: nvml_wrapper::error::NvmlError) -> StatsError {
+        StatsError::System(format!("NVML: {}", error))
+    }
+
+    impl NvmlApi for Nvml {
+        fn device_count(&self) -> Result<u32> {
+            Nvml::device_count(self).map_err(nvml_error)
+        }
+
+        fn uuid(&self, index: u32) -> Result<String> {
+            self.device_by_index(index)
+                .and_then(|device| device.uuid())
+                .map_err(nvml_error)
+        }
+
+        fn utilization(&self, index: u32) -> Result<u32> {
+            self.device_by_index(index)
+                .and_then(|device| device.utilization_rates())
+                .map(|rates| rates.gpu)
+                .map_err(nvml_error)
+        }
+
+        fn memory(&self, index: u32) -> Result<(u64, u64)> {
+            self.device_by_index(index)
+                .and_then(|device| device.memory_info())
+                .map(|memory| (memory.used, memory.total))
+                .map_err(nvml_error)
+        }
+
+        fn temperature(&self, index: u32) -> Result<u32> {
+            self.device_by_index(index)
+                .and_then(|device| device.temperature(TemperatureSensor::Gpu))
+                .map_err(nvml_error)
+        }
+    }
+
+    /// NVML-backed GPU statistics
+    pub struct NvidiaBackend<A: NvmlApi = Nvml> {
+        api: A,
+    }
+
+    impl NvidiaBackend<Nvml> {
+        /// Load NVML; `None` when the library or driver is missing
+        pub fn init() -> Option<Self> {
+            Nvml::init().ok().map(|api| Self { api })
+        }
+    }
+
+    impl<A: NvmlApi> NvidiaBackend<A> {
+        pub fn with_api(api: A) -> Self {
+            Self { api }
+        }
+    }
+
+    impl<A: NvmlApi> fmt::Debug for NvidiaBackend<A> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("NvidiaBackend").finish_non_exhaustive()
+        }
+    }
+
+    impl<A: NvmlApi> GpuBackend for NvidiaBackend<A> {
+        fn vendor(&self) -> &'static str {
+            "nvidia"
+        }
+
+        fn devices(&self) -> Result<Vec<GpuDeviceStats>> {
+            (0..self.api.device_count()?)
+                .map(|index| {
+                    let (memory_used, memory_total) = self.api.memory(index)?;
+                    Ok(GpuDeviceStats {
+                        device_id: self.api.uuid(index)?,
+                        utilization_percent: self.api.utilization(index)? as f64,
+                        memory_used,
+                        memory_total,
+                        // Some boards have no readable sensor
+                        temperature_celsius: self.api.temperature(index).ok().map(f64::from),
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+/// AMD GPUs through the amdgpu sysfs interface read by ROCm SMI
+#[cfg(feature = "gpu-amd")]
+pub mod amd {
+    use super::GpuBackend;
+    use crate::{GpuDeviceStats, Result, StatsError};
+    use std::path::{Path, PathBuf};
+
+    /// amdgpu statistics under a DRM class directory (`/sys/class/drm`)
+    #[derive(Debug, Clone)]
+    pub struct AmdBackend {
+        drm_root: PathBuf,
+    }
+
+    impl AmdBackend {
+        pub fn new(drm_root: impl Into<PathBuf>) -> Self {
+            Self {
+                drm_root: drm_root.into(),
+            }
+        }
+
+        /// `None` when no amdgpu card is present
+        pub fn detect() -> Option<Self> {
+            let backend = Self::new("/sys/class/drm");
+            match backend.cards() {
+                Ok(cards) if !cards.is_empty() => Some(backend),
+                _ => None,
+            }
+        }
+
+        /// `cardN` directories exposing `gpu_busy_percent`, in name order
+        fn cards(&self) -> Result<Vec<(String, PathBuf)>> {
+            let mut cards = Vec::new();
+            for entry in std::fs::read_dir(&self.drm_root)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                // Connectors such as card0-DP-1 share the prefix
+                if !name.starts_with("card") || name.contains('-') {
+                    continue;
+                }
+                let device = entry.path().join("device");
+                if device.join("gpu_busy_percent").is_file() {
+                    cards.push((name, device));
+                }
+            }
+            cards.sort();
+            Ok(cards)
+        }
+    }
+
+    fn read_number(path: &Path) -> Result<u64> {
+        std::fs::read_to_string(path)?
+            .trim()
+            .parse()
+            .map_err(|_| StatsError::Parse(format!("invalid value in {}", path.display())))
+    }
+
+    /// First `hwmon*/temp1_input`, in millidegrees
+    fn read_temperature(device: &Path) -> Option<f64> {
+        let mut hwmons: Vec<PathBuf> = std::fs::read_dir(device.join("hwmon"))
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        hwmons.sort();
+        hwmons
+            .iter()
+            .find_map(|hwmon| read_number(&hwmon.join("temp1_input")).ok())
+            .map(|millidegrees| millidegrees as f64 / 1000.0)
+    }
+
+    impl GpuBackend for AmdBackend {
+        fn vendor(&self) -> &'static str {
+            "amd"
+        }
+
+        fn devices(&self) -> Result<Vec<GpuDeviceStats>> {
+            self.cards()?
+                .into_iter()
+                .map(|(name, device)| {
+                    Ok(GpuDeviceStats {
+                        device_id: name,
+                        utilization_percent: read_number(&device.join("gpu_busy_percent"))? as f64,
+                        memory_used: read_number(&device.join("mem_info_vram_used"))?,
+                        memory_total: read_number(&device.join("mem_info_vram_total"))?,
+                        temperature_celsius: read_temperature(&device),
+                    })
+                })
+                .collect()
+        }
+    }
+}
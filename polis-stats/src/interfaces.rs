@@ -0,0 +1,42 @@
+use crate::{InterfaceStats, Result, StatsError};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Read the counters of every interface under a `/sys/class/net` directory.
+///
+/// Fails if an interface lacks one of its `statistics/` files or holds a
+/// value that is not a counter.
+pub fn read_interfaces(net_dir: &Path) -> Result<HashMap<String, InterfaceStats>> {
+    let mut interfaces = HashMap::new();
+    for entry in std::fs::read_dir(net_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let stats = read_interface_stats(&entry.path())?;
+        interfaces.insert(name, stats);
+    }
+    Ok(interfaces)
+}
+
+/// Read `statistics/` of a single interface directory
+pub fn read_interface_stats(interface_dir: &Path) -> Result<InterfaceStats> {
+    let statistics = interface_dir.join("statistics");
+    let counter = |name: &str| -> Result<u64> {
+        let path = statistics.join(name);
+        let content = std::fs::read_to_string(&path)?;
+        content
+            .trim()
+            .parse()
+            .map_err(|_| StatsError::Parse(format!("invalid counter in {}", path.display())))
+    };
+
+    Ok(InterfaceStats {
+        rx_bytes: counter("rx_bytes")?,
+        tx_bytes: counter("tx_bytes")?,
+        rx_packets: counter("rx_packets")?,
+        tx_packets: counter("tx_packets")?,
+        rx_errors: counter("rx_errors")?,
+        tx_errors: counter("tx_errors")?,
+        rx_dropped: counter("rx_dropped")?,
+        tx_dropped: counter("tx_dropped")?,
+    })
+}
@@ -3,21 +3,46 @@
 //! Container statistics and monitoring for Polis.
 //! 
 //! This crate provides real-time monitoring of container resources including:
-//! - CPU usage
+//! - CPU usage, overall and per core
 //! - Memory usage  
-//! - Network I/O
+//! - Network I/O, overall and per interface
 //! - Disk I/O
 //! - Process count
 //! - File descriptor count
+//! - GPU utilization and memory (`gpu-nvidia` and `gpu-amd` features)
+//! - Deltas and rates between samples, with stored baselines
+//! - Bounded sample history, counter rates and live subscriptions per container
+//! - Aggregates across containers selected by label
+//! - Threshold alerts with a minimum breach duration
+//! - Prometheus text exposition export
+//! - JSON and CSV capture of samples over a time window
 
 pub mod stats;
 pub mod collector;
 pub mod metrics;
 pub mod error;
 pub mod container_stats;
+pub mod delta;
+pub mod baseline;
+pub mod cgroup;
+pub mod interfaces;
+pub mod exporter;
+pub mod gpu;
+pub mod aggregate;
+pub mod alert;
+pub mod export;
 
 pub use stats::*;
 pub use collector::*;
 pub use metrics::*;
 pub use error::*;
-pub use container_stats::*;
\ No newline at end of file
+pub use container_stats::*;
+pub use delta::*;
+pub use baseline::*;
+pub use cgroup::*;
+pub use interfaces::*;
+pub use exporter::*;
+pub use gpu::*;
+pub use aggregate::*;
+pub use alert::{AlertCallback, AlertEvent, AlertRule};
+pub use export::*;
\ No newline at end of file
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::SystemTime;
 
 /// Container resource metrics
@@ -18,6 +19,9 @@ pub struct ContainerMetrics {
     pub disk: DiskMetrics,
     /// Process metrics
     pub processes: ProcessMetrics,
+    /// GPU metrics; `None` when no GPU backend is available
+    #[serde(default)]
+    pub gpu: Option<GpuStats>,
 }
 
 /// CPU usage metrics
@@ -37,6 +41,27 @@ pub struct CpuMetrics {
     pub throttled_count: u64,
     /// CPU throttling time (nanoseconds)
     pub throttled_time: u64,
+    /// Cumulative CPU time per core (nanoseconds); empty when the cgroup
+    /// does not report per-core usage
+    #[serde(default)]
+    pub per_core_time: Vec<u64>,
+    /// Utilization per core since the previous sample (0.0 - 100.0 each);
+    /// empty on the first sample or when per-core usage is unavailable
+    #[serde(default)]
+    pub per_core_usage: Vec<f64>,
+}
+
+impl CpuMetrics {
+    /// Index of the core with the highest utilization; ties go to the
+    /// lowest index
+    pub fn dominant_core(&self) -> Option<usize> {
+        self.per_core_usage
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
 }
 
 /// Memory usage metrics
@@ -81,6 +106,54 @@ pub struct NetworkMetrics {
     pub rx_dropped: u64,
     /// Transmit dropped packets
     pub tx_dropped: u64,
+    /// Per-interface counters, including loopback; the fields above are
+    /// their sum without loopback
+    #[serde(default)]
+    pub interfaces: HashMap<String, InterfaceStats>,
+}
+
+/// Counters of a single network interface
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterfaceStats {
+    /// Bytes received
+    pub rx_bytes: u64,
+    /// Bytes transmitted
+    pub tx_bytes: u64,
+    /// Packets received
+    pub rx_packets: u64,
+    /// Packets transmitted
+    pub tx_packets: u64,
+    /// Receive errors
+    pub rx_errors: u64,
+    /// Transmit errors
+    pub tx_errors: u64,
+    /// Receive dropped packets
+    pub rx_dropped: u64,
+    /// Transmit dropped packets
+    pub tx_dropped: u64,
+}
+
+impl NetworkMetrics {
+    /// Build the metrics from per-interface counters, summing every
+    /// interface except loopback into the aggregate fields
+    pub fn from_interfaces(interfaces: HashMap<String, InterfaceStats>) -> Self {
+        let mut metrics = Self::default();
+        for (name, stats) in &interfaces {
+            if name == "lo" {
+                continue;
+            }
+            metrics.rx_bytes += stats.rx_bytes;
+            metrics.tx_bytes += stats.tx_bytes;
+            metrics.rx_packets += stats.rx_packets;
+            metrics.tx_packets += stats.tx_packets;
+            metrics.rx_errors += stats.rx_errors;
+            metrics.tx_errors += stats.tx_errors;
+            metrics.rx_dropped += stats.rx_dropped;
+            metrics.tx_dropped += stats.tx_dropped;
+        }
+        metrics.interfaces = interfaces;
+        metrics
+    }
 }
 
 /// Disk I/O metrics
@@ -115,6 +188,28 @@ pub struct ProcessMetrics {
     pub state: String,
 }
 
+/// GPU metrics
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuStats {
+    /// One entry per visible GPU
+    pub devices: Vec<GpuDeviceStats>,
+}
+
+/// Statistics of a single GPU
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GpuDeviceStats {
+    /// Stable device identifier (UUID for NVIDIA, DRM card for AMD)
+    pub device_id: String,
+    /// GPU busy percentage (0.0 - 100.0)
+    pub utilization_percent: f64,
+    /// Device memory in use in bytes
+    pub memory_used: u64,
+    /// Total device memory in bytes
+    pub memory_total: u64,
+    /// GPU temperature, when the device reports it
+    pub temperature_celsius: Option<f64>,
+}
+
 impl Default for ContainerMetrics {
     fn default() -> Self {
         Self {
@@ -125,6 +220,7 @@ impl Default for ContainerMetrics {
             network: NetworkMetrics::default(),
             disk: DiskMetrics::default(),
             processes: ProcessMetrics::default(),
+            gpu: None,
         }
     }
 }
@@ -139,6 +235,8 @@ impl Default for CpuMetrics {
             total_time: 0,
             throttled_count: 0,
             throttled_time: 0,
+            per_core_time: Vec::new(),
+            per_core_usage: Vec::new(),
         }
     }
 }
@@ -170,6 +268,7 @@ impl Default for NetworkMetrics {
             tx_errors: 0,
             rx_dropped: 0,
             tx_dropped: 0,
+            interfaces: HashMap::new(),
         }
     }
 }
@@ -0,0 +1,118 @@
+use polis_stats::{Aggregate, ContainerMetrics, ContainerStatsCollector, LabelSelector};
+use std::collections::HashMap;
+
+fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn sample(container_id: &str, cpu: f64, memory: u64, rx_bytes: u64) -> ContainerMetrics {
+    let mut metrics = ContainerMetrics {
+        container_id: container_id.to_string(),
+        ..Default::default()
+    };
+    metrics.cpu.usage_percent = cpu;
+    metrics.memory.usage = memory;
+    metrics.network.rx_bytes = rx_bytes;
+    metrics.processes.process_count = 2;
+    metrics
+}
+
+async fn collector() -> ContainerStatsCollector {
+    let collector = ContainerStatsCollector::default();
+    for (id, app, tier, cpu, memory, rx) in [
+        ("nginx-1", "nginx", "frontend", 20.0, 100, 1_000),
+        ("nginx-2", "nginx", "frontend", 60.0, 300, 3_000),
+        ("redis-1", "redis", "backend", 10.0, 50, 500),
+    ] {
+        collector
+            .start_collecting_with_labels(id, labels(&[("app", app), ("tier", tier)]))
+            .await
+            .unwrap();
+        collector
+            .update_metrics(id, sample(id, cpu, memory, rx))
+            .await
+            .unwrap();
+    }
+    collector
+}
+
+#[tokio::test]
+async fn test_empty_selector_aggregates_every_container() {
+    let collector = collector().await;
+    collector.start_collecting("unlabelled").await.unwrap();
+    collector
+        .update_metrics("unlabelled", sample("unlabelled", 10.0, 50, 0))
+        .await
+        .unwrap();
+
+    let all = collector
+        .get_aggregate(&LabelSelector::new())
+        .await
+        .unwrap();
+    assert_eq!(all.container_count, 4);
+    assert_eq!(all.cpu_usage_percent.sum, 100.0);
+    assert_eq!(all.cpu_usage_percent.avg, 25.0);
+    assert_eq!(all.cpu_usage_percent.max, 60.0);
+    assert_eq!(all.memory_usage.sum, 500);
+    assert_eq!(all.process_count.sum, 8);
+}
+
+#[tokio::test]
+async fn test_partial_match_selects_subset() {
+    let collector = collector().await;
+
+    let nginx = collector
+        .get_aggregate(&labels(&[("app", "nginx")]))
+        .await
+        .unwrap();
+    assert_eq!(nginx.container_count, 2);
+    assert_eq!(
+        nginx.cpu_usage_percent,
+        Aggregate {
+            sum: 80.0,
+            avg: 40.0,
+            max: 60.0
+        }
+    );
+    assert_eq!(
+        nginx.network_rx_bytes,
+        Aggregate {
+            sum: 4_000,
+            avg: 2_000.0,
+            max: 3_000
+        }
+    );
+    assert_eq!(nginx.memory_usage.avg, 200.0);
+
+    // Every label of the selector must match
+    let frontend_redis = collector
+        .get_aggregate(&labels(&[("app", "redis"), ("tier", "frontend")]))
+        .await;
+    assert!(frontend_redis.is_none());
+}
+
+#[tokio::test]
+async fn test_no_match_and_stopped_containers() {
+    let collector = collector().await;
+    assert!(collector
+        .get_aggregate(&labels(&[("app", "postgres")]))
+        .await
+        .is_none());
+
+    collector.stop_collecting("redis-1").await.unwrap();
+    assert!(collector
+        .get_aggregate(&labels(&[("app", "redis")]))
+        .await
+        .is_none());
+    assert_eq!(
+        collector
+            .get_aggregate(&LabelSelector::new())
+            .await
+            .unwrap()
+            .container_count,
+        2
+    );
+}
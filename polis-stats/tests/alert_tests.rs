@@ -0,0 +1,137 @@
+use polis_stats::{AlertEvent, AlertRule, ContainerMetrics, ContainerStatsCollector, MetricField};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn sample(cpu: f64, memory_percent: f64) -> ContainerMetrics {
+    let mut metrics = ContainerMetrics {
+        container_id: "web".to_string(),
+        ..Default::default()
+    };
+    metrics.cpu.usage_percent = cpu;
+    metrics.memory.usage_percent = memory_percent;
+    metrics
+}
+
+fn recording_rule(
+    field: MetricField,
+    threshold: f64,
+    duration: Duration,
+) -> (AlertRule, Arc<Mutex<Vec<AlertEvent>>>) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    let rule = AlertRule::new(field, threshold, duration, move |event| {
+        let sink = Arc::clone(&sink);
+        async move { sink.lock().unwrap().push(event) }
+    });
+    (rule, events)
+}
+
+/// Record CPU samples, advancing the paused clock before each one
+async fn feed(collector: &ContainerStatsCollector, samples: &[(u64, f64)]) {
+    for (after_secs, cpu) in samples {
+        tokio::time::advance(Duration::from_secs(*after_secs)).await;
+        collector
+            .update_metrics("web", sample(*cpu, 0.0))
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_alarm_after_continuous_breach_and_recovery() {
+    let collector = ContainerStatsCollector::default();
+    let (rule, events) =
+        recording_rule(MetricField::CpuUsagePercent, 90.0, Duration::from_secs(30));
+    collector.set_alert_rule("web", rule).await;
+
+    // Interrupted breach restarts the duration guard
+    feed(&collector, &[(0, 95.0), (10, 95.0), (10, 50.0)]).await;
+    feed(&collector, &[(10, 95.0), (20, 96.0)]).await;
+    assert!(events.lock().unwrap().is_empty());
+
+    feed(&collector, &[(10, 97.0), (10, 99.0)]).await;
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![AlertEvent {
+            container_id: "web".to_string(),
+            field: MetricField::CpuUsagePercent,
+            value: 97.0,
+            threshold: 90.0,
+            alarm: true,
+        }]
+    );
+
+    feed(&collector, &[(10, 40.0), (10, 30.0)]).await;
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 2);
+    assert!(!events[1].alarm);
+    assert_eq!(events[1].value, 40.0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rules_are_independent_per_field() {
+    let collector = ContainerStatsCollector::default();
+    let (cpu_rule, cpu_events) = recording_rule(MetricField::CpuUsagePercent, 80.0, Duration::ZERO);
+    let (memory_rule, memory_events) = recording_rule(
+        MetricField::MemoryUsagePercent,
+        50.0,
+        Duration::from_secs(60),
+    );
+    collector.set_alert_rule("web", cpu_rule).await;
+    collector.set_alert_rule("web", memory_rule).await;
+
+    collector
+        .update_metrics("web", sample(85.0, 75.0))
+        .await
+        .unwrap();
+    // A zero duration alarms on the first sample above the threshold
+    assert_eq!(cpu_events.lock().unwrap().len(), 1);
+    assert!(memory_events.lock().unwrap().is_empty());
+
+    tokio::time::advance(Duration::from_secs(60)).await;
+    collector
+        .update_metrics("web", sample(85.0, 75.0))
+        .await
+        .unwrap();
+    assert_eq!(cpu_events.lock().unwrap().len(), 1);
+    assert_eq!(memory_events.lock().unwrap().len(), 1);
+    assert!(memory_events.lock().unwrap()[0].alarm);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_cleared_rules_stop_firing() {
+    let collector = ContainerStatsCollector::default();
+    let (rule, events) = recording_rule(MetricField::CpuUsagePercent, 10.0, Duration::ZERO);
+    collector.set_alert_rule("web", rule.clone()).await;
+    collector.set_alert_rule("db", rule).await;
+
+    collector.clear_alert_rules("web").await;
+    collector.start_collecting("db").await.unwrap();
+    collector.stop_collecting("db").await.unwrap();
+
+    feed(&collector, &[(0, 50.0)]).await;
+    collector
+        .update_metrics("db", sample(50.0, 0.0))
+        .await
+        .unwrap();
+    assert!(events.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_gauges_have_no_rate() {
+    let collector = ContainerStatsCollector::default();
+    collector
+        .update_metrics("web", sample(10.0, 0.0))
+        .await
+        .unwrap();
+    assert!(!MetricField::CpuUsagePercent.is_counter());
+    assert!(MetricField::RxBytes.is_counter());
+    assert_eq!(
+        MetricField::CpuUsagePercent.reading(&sample(12.5, 0.0)),
+        12.5
+    );
+    assert!(collector
+        .get_rate("web", MetricField::CpuUsagePercent, Duration::from_secs(60))
+        .await
+        .is_none());
+}
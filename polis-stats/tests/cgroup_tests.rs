@@ -0,0 +1,164 @@
+use polis_stats::{
+    detect_cgroup_version, parse_cpu_stat_percpu, parse_memory_limit, parse_oom_kills,
+    parse_usage_percpu, per_core_usage, CgroupVersion, CpuMetrics, MetricsCollector,
+};
+use polis_test_support::CgroupFixture;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+#[test]
+fn test_parse_v1_usage_percpu() {
+    assert_eq!(
+        parse_usage_percpu("1000 2000 0 4000 \n"),
+        vec![1_000, 2_000, 0, 4_000]
+    );
+    assert!(parse_usage_percpu("").is_empty());
+}
+
+#[test]
+fn test_parse_v2_cpu_stat_percpu() {
+    let content = "usage_usec 300\nuser_usec 200\nsystem_usec 100\n\
+                   usage_usec.cpu0 120\nusage_usec.cpu2 180\n";
+    assert_eq!(parse_cpu_stat_percpu(content), vec![120_000, 0, 180_000]);
+
+    // Kernels without the per-CPU breakdown only report aggregate counters
+    assert!(parse_cpu_stat_percpu("usage_usec 300\nuser_usec 200\n").is_empty());
+}
+
+#[test]
+fn test_detect_cgroup_version() {
+    let root = tempfile::tempdir().unwrap();
+    assert_eq!(detect_cgroup_version(root.path()), None);
+
+    fs::create_dir(root.path().join("cpuacct")).unwrap();
+    assert_eq!(detect_cgroup_version(root.path()), Some(CgroupVersion::V1));
+
+    fs::write(root.path().join("cgroup.controllers"), "cpu memory pids\n").unwrap();
+    assert_eq!(detect_cgroup_version(root.path()), Some(CgroupVersion::V2));
+}
+
+#[test]
+fn test_per_core_usage_between_readings() {
+    let before = [0, 1_000_000_000, 500_000_000];
+    let after = [500_000_000, 1_000_000_000, 400_000_000];
+    let usage = per_core_usage(&before, &after, Duration::from_secs(1));
+    assert_eq!(usage, vec![50.0, 0.0, 0.0]);
+
+    assert!(per_core_usage(&before, &after[..2], Duration::from_secs(1)).is_empty());
+    assert!(per_core_usage(&before, &after, Duration::ZERO).is_empty());
+    assert!(per_core_usage(&[], &[], Duration::from_secs(1)).is_empty());
+}
+
+#[test]
+fn test_dominant_core() {
+    let mut cpu = CpuMetrics::default();
+    assert_eq!(cpu.dominant_core(), None);
+
+    cpu.per_core_usage = vec![12.5, 80.0, 3.0, 80.0];
+    assert_eq!(cpu.dominant_core(), Some(1));
+}
+
+fn write_cpu_stat(dir: &Path, cpu0_usec: u64, cpu1_usec: u64) {
+    fs::write(
+        dir.join("cpu.stat"),
+        format!(
+            "usage_usec {}\nusage_usec.cpu0 {}\nusage_usec.cpu1 {}\n",
+            cpu0_usec + cpu1_usec,
+            cpu0_usec,
+            cpu1_usec
+        ),
+    )
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_collector_reports_per_core_usage_on_cgroup_v2() {
+    let mount = tempfile::tempdir().unwrap();
+    fs::write(mount.path().join("cgroup.controllers"), "cpu\n").unwrap();
+    let cgroup_root = mount.path().join("polis");
+    let container = cgroup_root.join("web");
+    fs::create_dir_all(&container).unwrap();
+    fs::write(container.join("cgroup.procs"), "").unwrap();
+
+    let mut collector = MetricsCollector::new()
+        .with_cgroup_mount(mount.path())
+        .with_cgroup_root(&cgroup_root);
+
+    write_cpu_stat(&container, 1_000, 1_000);
+    let first = collector.collect_container_metrics("web").await.unwrap();
+    assert_eq!(first.cpu.per_core_time, vec![1_000_000, 1_000_000]);
+    // A single reading has no interval to compute utilization over
+    assert!(first.cpu.per_core_usage.is_empty());
+    assert_eq!(first.cpu.dominant_core(), None);
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    write_cpu_stat(&container, 1_000, 11_000);
+    let second = collector.collect_container_metrics("web").await.unwrap();
+    assert_eq!(second.cpu.per_core_usage.len(), 2);
+    assert_eq!(second.cpu.per_core_usage[0], 0.0);
+    assert!(second.cpu.per_core_usage[1] > 0.0);
+    assert_eq!(second.cpu.dominant_core(), Some(1));
+}
+
+#[tokio::test]
+async fn test_collector_reads_cgroup_v1_usage_percpu() {
+    let mount = tempfile::tempdir().unwrap();
+    let container = mount.path().join("cpuacct").join("polis").join("db");
+    fs::create_dir_all(&container).unwrap();
+    fs::write(container.join("cpuacct.usage_percpu"), "300 100 200\n").unwrap();
+
+    let mut collector = MetricsCollector::new()
+        .with_cgroup_mount(mount.path())
+        .with_cgroup_root(mount.path().join("unified"));
+    let metrics = collector.collect_container_metrics("db").await.unwrap();
+    assert_eq!(metrics.cpu.per_core_time, vec![300, 100, 200]);
+
+    // Containers without a cgroup report no per-core data
+    let missing = collector.collect_container_metrics("gone").await.unwrap();
+    assert!(missing.cpu.per_core_time.is_empty());
+    assert!(missing.cpu.per_core_usage.is_empty());
+}
+
+#[test]
+fn test_parse_memory_limit() {
+    assert_eq!(parse_memory_limit("268435456\n"), 268435456);
+    assert_eq!(parse_memory_limit("max\n"), 0);
+    // v1 reports "no limit" as a page-aligned i64::MAX
+    assert_eq!(parse_memory_limit("9223372036854771712\n"), 0);
+    assert_eq!(
+        parse_oom_kills("low 0\nhigh 0\nmax 12\noom 3\noom_kill 2\n"),
+        2
+    );
+    assert_eq!(parse_oom_kills("oom_kill_disable 0\nunder_oom 0\n"), 0);
+}
+
+#[tokio::test]
+async fn test_collector_reports_cgroup_memory_limit() {
+    let mount = tempfile::tempdir().unwrap();
+    fs::write(mount.path().join("cgroup.controllers"), "memory\n").unwrap();
+    let cgroup_root = mount.path().join("polis");
+    let container = CgroupFixture::create(&cgroup_root, "web");
+    container
+        .procs(&[])
+        .memory(1024, Some(256 * 1024 * 1024))
+        .file("memory.swap.max", "0\n")
+        .file("memory.events", "oom 1\noom_kill 1\n");
+    CgroupFixture::create(&cgroup_root, "unlimited")
+        .procs(&[])
+        .memory(1024, None);
+
+    let mut collector = MetricsCollector::new()
+        .with_cgroup_mount(mount.path())
+        .with_cgroup_root(&cgroup_root);
+    let metrics = collector.collect_container_metrics("web").await.unwrap();
+    assert_eq!(metrics.memory.limit, 256 * 1024 * 1024);
+    assert_eq!(metrics.memory.swap_limit, 0);
+    assert_eq!(metrics.memory.oom_kills, 1);
+
+    let unlimited = collector
+        .collect_container_metrics("unlimited")
+        .await
+        .unwrap();
+    assert_eq!(unlimited.memory.limit, 0);
+}
@@ -0,0 +1,171 @@
+use polis_stats::{diff, ContainerMetrics, ContainerStatsCollector, CounterDelta, SampleStore};
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+fn sample(at: SystemTime, cpu_ns: u64, rx: u64, tx: u64, reads: u64) -> ContainerMetrics {
+    let mut metrics = ContainerMetrics {
+        container_id: "web".to_string(),
+        timestamp: at,
+        ..Default::default()
+    };
+    metrics.cpu.total_time = cpu_ns;
+    metrics.network.rx_bytes = rx;
+    metrics.network.tx_bytes = tx;
+    metrics.disk.read_ops = reads;
+    metrics
+}
+
+#[test]
+fn test_diff_computes_deltas_and_rates() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let before = sample(start, 1_000_000_000, 1_000, 500, 10);
+    let after = sample(
+        start + Duration::from_secs(10),
+        6_000_000_000,
+        21_000,
+        1_500,
+        60,
+    );
+
+    let delta = before.diff(&after);
+
+    assert_eq!(delta.interval, Duration::from_secs(10));
+    assert!(!delta.reset);
+    assert_eq!(
+        delta.rx_bytes,
+        CounterDelta::Value {
+            delta: 20_000,
+            rate: 2_000.0
+        }
+    );
+    assert_eq!(delta.tx_bytes.delta(), Some(1_000));
+    assert_eq!(delta.read_ops.rate(), Some(5.0));
+    // 5s of CPU time over a 10s interval is half a core
+    assert!((delta.cpu_percent.unwrap() - 50.0).abs() < f64::EPSILON);
+    assert_eq!(diff(&before, &after), delta);
+}
+
+#[test]
+fn test_diff_flags_counter_reset() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let before = sample(start, 9_000_000_000, 50_000, 500, 100);
+    let after = sample(start + Duration::from_secs(5), 1_000_000_000, 2_000, 700, 3);
+
+    let delta = before.diff(&after);
+
+    assert!(delta.reset);
+    assert_eq!(delta.rx_bytes, CounterDelta::Reset);
+    assert_eq!(delta.read_ops, CounterDelta::Reset);
+    assert_eq!(delta.cpu_time, CounterDelta::Reset);
+    assert_eq!(delta.cpu_percent, None);
+    assert_eq!(delta.tx_bytes.delta(), Some(200));
+}
+
+#[test]
+fn test_diff_with_zero_interval() {
+    let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let delta = sample(now, 0, 0, 0, 0).diff(&sample(now, 100, 100, 0, 0));
+
+    assert_eq!(delta.rx_bytes.rate(), Some(0.0));
+    assert_eq!(delta.cpu_percent, None);
+}
+
+#[test]
+fn test_snapshot_diff_saturates_on_counter_reset() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let mut older = sample(start, 4_000_000_000, 80_000, 900, 40);
+    older.network.rx_errors = 7;
+    older.disk.write_time = 5_000;
+    older.cpu.usage_percent = 30.0;
+    older.memory.usage_percent = 10.0;
+    let mut newer = sample(
+        start + Duration::from_secs(4),
+        5_000_000_000,
+        1_000,
+        1_300,
+        10,
+    );
+    newer.network.rx_errors = 9;
+    newer.disk.write_time = 1_000;
+    newer.cpu.usage_percent = 50.0;
+    newer.memory.usage_percent = 20.0;
+
+    let delta = ContainerMetrics::diff(&older, &newer);
+
+    assert_eq!(delta.duration(), Duration::from_secs(4));
+    assert_eq!(delta.rx_bytes.saturating_delta(), 0);
+    assert_eq!(delta.read_ops.saturating_delta(), 0);
+    assert_eq!(delta.write_time.saturating_delta(), 0);
+    assert_eq!(delta.tx_bytes.saturating_delta(), 400);
+    assert_eq!(delta.rx_errors.saturating_delta(), 2);
+    assert_eq!(delta.rate(delta.tx_bytes.saturating_delta() as f64), 100.0);
+    assert_eq!(delta.rate(2u32), 0.5);
+    assert_eq!(delta.avg_cpu_usage_percent, 40.0);
+    assert_eq!(delta.avg_memory_usage_percent, 15.0);
+    assert!(delta.reset);
+}
+
+#[test]
+fn test_rate_guards_zero_duration() {
+    let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let delta = ContainerMetrics::diff(&sample(now, 0, 0, 0, 0), &sample(now, 0, 500, 0, 0));
+
+    assert_eq!(delta.duration(), Duration::ZERO);
+    assert_eq!(delta.rate(500.0), 0.0);
+    assert_eq!(delta.rx_bytes.saturating_delta(), 500);
+
+    // Samples out of order also count as no elapsed time
+    let backwards = ContainerMetrics::diff(
+        &sample(now + Duration::from_secs(5), 0, 0, 0, 0),
+        &sample(now, 0, 10, 0, 0),
+    );
+    assert_eq!(backwards.duration(), Duration::ZERO);
+    assert_eq!(backwards.rate(10.0), 0.0);
+}
+
+#[tokio::test]
+async fn test_collector_delta_uses_two_latest_samples() {
+    let collector = ContainerStatsCollector::default();
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    assert!(collector.get_delta("web").await.is_none());
+
+    collector
+        .update_metrics("web", sample(start, 0, 100, 0, 0))
+        .await
+        .unwrap();
+    assert!(collector.get_delta("web").await.is_none());
+
+    for (second, rx) in [(10, 1_100), (20, 4_100)] {
+        collector
+            .update_metrics(
+                "web",
+                sample(start + Duration::from_secs(second), 0, rx, 0, 0),
+            )
+            .await
+            .unwrap();
+    }
+
+    let delta = collector.get_delta("web").await.unwrap();
+    assert_eq!(delta.duration(), Duration::from_secs(10));
+    assert_eq!(delta.rx_bytes.delta(), Some(3_000));
+    assert_eq!(delta.rx_bytes.rate(), Some(300.0));
+}
+
+#[test]
+fn test_sample_store_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let store = SampleStore::new(dir.path());
+    let metrics = sample(SystemTime::UNIX_EPOCH + Duration::from_secs(42), 1, 2, 3, 4);
+
+    assert!(store.load_last("web").unwrap().is_none());
+    store.save_last(&metrics).unwrap();
+    let loaded = store.load_last("web").unwrap().unwrap();
+    assert_eq!(loaded.timestamp, metrics.timestamp);
+    assert_eq!(loaded.network.rx_bytes, 2);
+
+    store.save_baseline("before-load", &metrics).unwrap();
+    let baseline = store.load_baseline("before-load", "web").unwrap().unwrap();
+    assert_eq!(baseline.disk.read_ops, 4);
+    assert!(store.load_baseline("after-load", "web").unwrap().is_none());
+    assert!(store.save_baseline("../escape", &metrics).is_err());
+}
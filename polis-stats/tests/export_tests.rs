@@ -0,0 +1,133 @@
+use polis_stats::{
+    capture, csv_columns, ContainerMetrics, ContainerStatsCollector, ExportFormat, StatsExporter,
+};
+use std::time::{Duration, SystemTime};
+
+fn sample(container_id: &str, rx_bytes: u64) -> ContainerMetrics {
+    let mut metrics = ContainerMetrics {
+        container_id: container_id.to_string(),
+        timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        ..Default::default()
+    };
+    metrics.cpu.usage_percent = 12.5;
+    metrics.memory.usage = 64 * 1024 * 1024;
+    metrics.network.rx_bytes = rx_bytes;
+    metrics.processes.state = "running".to_string();
+    metrics
+}
+
+fn export(format: ExportFormat, ticks: &[Vec<ContainerMetrics>]) -> String {
+    let mut exporter = StatsExporter::new(Vec::new(), format);
+    for tick in ticks {
+        exporter.write_tick(tick).unwrap();
+    }
+    String::from_utf8(exporter.finish().unwrap()).unwrap()
+}
+
+#[test]
+fn test_csv_rows_line_up_with_the_header() {
+    let text = export(
+        ExportFormat::Csv,
+        &[
+            vec![sample("web", 100), sample("db", 7)],
+            vec![sample("web", 250)],
+        ],
+    );
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 4);
+
+    let header: Vec<&str> = lines[0].split(',').collect();
+    assert_eq!(header, csv_columns());
+    for line in &lines[1..] {
+        assert_eq!(line.split(',').count(), header.len(), "{line}");
+    }
+
+    let column = |name: &str| header.iter().position(|h| *h == name).unwrap();
+    let last: Vec<&str> = lines[3].split(',').collect();
+    assert_eq!(last[column("container_id")], "web");
+    assert_eq!(last[column("rx_bytes")], "250");
+    assert_eq!(last[column("cpu_percent")], "12.5");
+    assert_eq!(last[column("mem_usage")], "67108864");
+    assert_eq!(last[column("timestamp")], "2023-11-14T22:13:20.000Z");
+}
+
+#[test]
+fn test_csv_quotes_fields_with_separators() {
+    let text = export(ExportFormat::Csv, &[vec![sample("odd,\"id\"", 1)]]);
+    let row = text.lines().nth(1).unwrap();
+    assert!(row.contains(",\"odd,\"\"id\"\"\","), "{row}");
+}
+
+#[test]
+fn test_json_deserializes_into_samples() {
+    let text = export(
+        ExportFormat::Json,
+        &[
+            vec![sample("web", 100), sample("db", 7)],
+            vec![sample("web", 250)],
+        ],
+    );
+    let samples: Vec<ContainerMetrics> = serde_json::from_str(&text).unwrap();
+    let read: Vec<(&str, u64)> = samples
+        .iter()
+        .map(|m| (m.container_id.as_str(), m.network.rx_bytes))
+        .collect();
+    assert_eq!(read, vec![("web", 100), ("db", 7), ("web", 250)]);
+    assert_eq!(samples[0].timestamp, sample("web", 0).timestamp);
+
+    let empty: Vec<ContainerMetrics> =
+        serde_json::from_str(&export(ExportFormat::Json, &[])).unwrap();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_format_parses_case_insensitively() {
+    assert_eq!("JSON".parse::<ExportFormat>().unwrap(), ExportFormat::Json);
+    assert_eq!("csv".parse::<ExportFormat>().unwrap(), ExportFormat::Csv);
+    assert!("xml".parse::<ExportFormat>().is_err());
+    assert_eq!(ExportFormat::Csv.to_string(), "csv");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_capture_writes_one_tick_per_interval() {
+    let collector = ContainerStatsCollector::default();
+    collector
+        .update_metrics("web", sample("web", 1))
+        .await
+        .unwrap();
+    collector
+        .update_metrics("db", sample("db", 2))
+        .await
+        .unwrap();
+
+    let mut exporter = StatsExporter::new(Vec::new(), ExportFormat::Csv);
+    let ticks = capture(
+        &collector,
+        &mut exporter,
+        Duration::from_secs(60),
+        Duration::from_secs(5),
+    )
+    .await
+    .unwrap();
+    assert_eq!(ticks, 12);
+    assert_eq!(exporter.records(), 24);
+
+    let text = String::from_utf8(exporter.finish().unwrap()).unwrap();
+    let ids: Vec<&str> = text
+        .lines()
+        .skip(1)
+        .take(2)
+        .map(|line| line.split(',').nth(1).unwrap())
+        .collect();
+    assert_eq!(ids, vec!["db", "web"]);
+
+    let mut exporter = StatsExporter::new(Vec::new(), ExportFormat::Json);
+    assert!(capture(
+        &collector,
+        &mut exporter,
+        Duration::from_secs(1),
+        Duration::ZERO
+    )
+    .await
+    .is_err());
+}
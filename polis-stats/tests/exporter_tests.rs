@@ -0,0 +1,178 @@
+use polis_stats::{ContainerMetrics, ContainerStatsCollector, InterfaceStats, PrometheusExporter};
+use prometheus_parse::{Scrape, Value};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+fn sample(container_id: &str) -> ContainerMetrics {
+    let mut metrics = ContainerMetrics {
+        container_id: container_id.to_string(),
+        timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        ..Default::default()
+    };
+    metrics.cpu.usage_percent = 42.5;
+    metrics.cpu.cores = 4;
+    metrics.cpu.total_time = 9_000_000_000;
+    metrics.cpu.per_core_time = vec![6_000_000_000, 3_000_000_000];
+    metrics.cpu.per_core_usage = vec![75.0, 10.0];
+    metrics.memory.usage = 256 * 1024 * 1024;
+    metrics.memory.limit = 512 * 1024 * 1024;
+    metrics.disk.write_ops = 17;
+    metrics.processes.process_count = 3;
+    metrics
+}
+
+fn with_interfaces(mut metrics: ContainerMetrics) -> ContainerMetrics {
+    let interface = |rx_bytes, tx_bytes| InterfaceStats {
+        rx_bytes,
+        tx_bytes,
+        ..Default::default()
+    };
+    metrics.network = polis_stats::NetworkMetrics::from_interfaces(HashMap::from([
+        ("eth0".to_string(), interface(1_000, 200)),
+        ("lo".to_string(), interface(50, 50)),
+    ]));
+    metrics
+}
+
+fn parse(text: &str) -> Scrape {
+    Scrape::parse(text.lines().map(|line| Ok(line.to_string()))).unwrap()
+}
+
+fn value(scrape: &Scrape, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+    scrape
+        .samples
+        .iter()
+        .find(|sample| {
+            sample.metric == name
+                && labels
+                    .iter()
+                    .all(|(key, value)| sample.labels.get(key) == Some(*value))
+        })
+        .map(|sample| match sample.value {
+            Value::Counter(v) | Value::Gauge(v) | Value::Untyped(v) => v,
+            _ => panic!("unexpected value type for {}", name),
+        })
+}
+
+#[test]
+fn test_render_text_parses_with_expected_values() {
+    let metrics = [with_interfaces(sample("web")), sample("db")];
+    let text = PrometheusExporter::new().render_text(&metrics);
+    let scrape = parse(&text);
+
+    let web = [("container_id", "web")];
+    assert_eq!(
+        value(&scrape, "polis_container_cpu_usage_percent", &web),
+        Some(42.5)
+    );
+    assert_eq!(value(&scrape, "polis_container_cpu_cores", &web), Some(4.0));
+    assert_eq!(
+        value(&scrape, "polis_container_cpu_nanoseconds_total", &web),
+        Some(9e9)
+    );
+    assert_eq!(
+        value(&scrape, "polis_container_memory_limit_bytes", &web),
+        Some(536_870_912.0)
+    );
+    assert_eq!(
+        value(&scrape, "polis_container_disk_writes_total", &web),
+        Some(17.0)
+    );
+    assert_eq!(value(&scrape, "polis_container_processes", &web), Some(3.0));
+    assert_eq!(
+        value(
+            &scrape,
+            "polis_container_last_sample_timestamp_seconds",
+            &web
+        ),
+        Some(1_700_000_000.0)
+    );
+    assert_eq!(
+        value(
+            &scrape,
+            "polis_container_cpu_core_usage_percent",
+            &[("container_id", "web"), ("core", "0")]
+        ),
+        Some(75.0)
+    );
+
+    // Aggregates leave loopback out; the breakdown keeps it
+    assert_eq!(
+        value(&scrape, "polis_container_network_receive_bytes_total", &web),
+        Some(1_000.0)
+    );
+    assert_eq!(
+        value(
+            &scrape,
+            "polis_container_interface_receive_bytes_total",
+            &[("container_id", "web"), ("interface", "lo")]
+        ),
+        Some(50.0)
+    );
+
+    assert_eq!(
+        value(
+            &scrape,
+            "polis_container_cpu_usage_percent",
+            &[("container_id", "db")]
+        ),
+        Some(42.5)
+    );
+
+    // Every family is documented and typed exactly once
+    assert_eq!(
+        text.matches("# TYPE polis_container_cpu_usage_percent gauge\n")
+            .count(),
+        1
+    );
+    assert!(text.contains("# TYPE polis_container_disk_writes_total counter\n"));
+    assert!(text.contains("# HELP polis_container_memory_usage_bytes "));
+}
+
+#[test]
+fn test_interface_breakdown_can_be_excluded() {
+    let metrics = [with_interfaces(sample("web"))];
+    let text = PrometheusExporter::new()
+        .with_interfaces(false)
+        .render_text(&metrics);
+    let scrape = parse(&text);
+
+    assert!(!text.contains("polis_container_interface_"));
+    assert_eq!(
+        value(
+            &scrape,
+            "polis_container_network_transmit_bytes_total",
+            &[("container_id", "web")]
+        ),
+        Some(200.0)
+    );
+}
+
+#[test]
+fn test_label_values_are_escaped() {
+    let text = PrometheusExporter::new().render_text(&[sample("odd\"id\\x")]);
+    assert!(text.contains(r#"polis_container_cpu_cores{container_id="odd\"id\\x"} 4"#));
+}
+
+#[tokio::test]
+async fn test_collector_exports_current_metrics() {
+    let collector = ContainerStatsCollector::default();
+    assert!(!collector
+        .export_prometheus()
+        .await
+        .contains("container_id="));
+
+    collector
+        .update_metrics("web", sample("web"))
+        .await
+        .unwrap();
+    let scrape = parse(&collector.export_prometheus().await);
+    assert_eq!(
+        value(
+            &scrape,
+            "polis_container_memory_usage_bytes",
+            &[("container_id", "web")]
+        ),
+        Some(268_435_456.0)
+    );
+}
@@ -0,0 +1,159 @@
+use polis_stats::{GpuBackend, GpuDeviceStats, MetricsCollector, Result, StatsError};
+use polis_test_support::{CgroupFixture, ProcFixture, ProcProcess};
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Debug)]
+struct FixedBackend(Option<Vec<GpuDeviceStats>>);
+
+impl GpuBackend for FixedBackend {
+    fn vendor(&self) -> &'static str {
+        "fixed"
+    }
+
+    fn devices(&self) -> Result<Vec<GpuDeviceStats>> {
+        self.0
+            .clone()
+            .ok_or_else(|| StatsError::System("driver unloaded".to_string()))
+    }
+}
+
+fn device(id: &str, utilization_percent: f64) -> GpuDeviceStats {
+    GpuDeviceStats {
+        device_id: id.to_string(),
+        utilization_percent,
+        memory_used: 2 << 30,
+        memory_total: 16 << 30,
+        temperature_celsius: Some(61.0),
+    }
+}
+
+fn collector(root: &Path) -> MetricsCollector {
+    let proc = ProcFixture::create(&root.join("proc"), 1_000.0, 1_700_000_000);
+    proc.process(&ProcProcess::new(42, "trainer"));
+    CgroupFixture::create(&root.join("cgroup"), "ml").procs(&[42]);
+    MetricsCollector::new()
+        .with_proc_root(proc.path())
+        .with_cgroup_root(root.join("cgroup"))
+}
+
+#[tokio::test]
+async fn test_collector_reports_gpu_devices() {
+    let root = tempfile::tempdir().unwrap();
+    let devices = vec![device("GPU-0", 87.0), device("GPU-1", 12.5)];
+    let mut collector =
+        collector(root.path()).with_gpu_backend(Arc::new(FixedBackend(Some(devices.clone()))));
+
+    let metrics = collector.collect_container_metrics("ml").await.unwrap();
+    assert_eq!(metrics.gpu.unwrap().devices, devices);
+}
+
+#[tokio::test]
+async fn test_gpu_is_empty_without_devices_or_on_error() {
+    let root = tempfile::tempdir().unwrap();
+
+    let mut failing = collector(root.path()).with_gpu_backend(Arc::new(FixedBackend(None)));
+    let metrics = failing.collect_container_metrics("ml").await.unwrap();
+    assert!(metrics.gpu.is_none());
+
+    let mut no_devices =
+        collector(root.path()).with_gpu_backend(Arc::new(FixedBackend(Some(Vec::new()))));
+    let metrics = no_devices.collect_container_metrics("ml").await.unwrap();
+    assert!(metrics.gpu.is_none());
+}
+
+#[cfg(not(any(feature = "gpu-nvidia", feature = "gpu-amd")))]
+#[tokio::test]
+async fn test_gpu_is_empty_without_backend() {
+    let root = tempfile::tempdir().unwrap();
+    let mut collector = collector(root.path());
+
+    let metrics = collector.collect_container_metrics("ml").await.unwrap();
+    assert!(metrics.gpu.is_none());
+
+    let json = serde_json::to_value(&metrics).unwrap();
+    let mut legacy = json.as_object().unwrap().clone();
+    legacy.remove("gpu");
+    let parsed: polis_stats::ContainerMetrics = serde_json::from_value(legacy.into()).unwrap();
+    assert!(parsed.gpu.is_none());
+}
+
+#[cfg(feature = "gpu-nvidia")]
+mod nvidia {
+    use polis_stats::nvidia::{NvidiaBackend, NvmlApi};
+    use polis_stats::{GpuBackend, Result, StatsError};
+
+    struct MockNvml;
+
+    impl NvmlApi for MockNvml {
+        fn device_count(&self) -> Result<u32> {
+            Ok(2)
+        }
+
+        fn uuid(&self, index: u32) -> Result<String> {
+            Ok(format!("GPU-{}", index))
+        }
+
+        fn utilization(&self, index: u32) -> Result<u32> {
+            Ok(40 + index * 10)
+        }
+
+        fn memory(&self, _index: u32) -> Result<(u64, u64)> {
+            Ok((1 << 30, 8 << 30))
+        }
+
+        fn temperature(&self, index: u32) -> Result<u32> {
+            if index == 0 {
+                Ok(70)
+            } else {
+                Err(StatsError::System("NVML: not supported".to_string()))
+            }
+        }
+    }
+
+    #[test]
+    fn test_nvidia_backend_populates_devices() {
+        let backend = NvidiaBackend::with_api(MockNvml);
+        assert_eq!(backend.vendor(), "nvidia");
+
+        let devices = backend.devices().unwrap();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].device_id, "GPU-0");
+        assert_eq!(devices[0].utilization_percent, 40.0);
+        assert_eq!(devices[0].memory_used, 1 << 30);
+        assert_eq!(devices[0].memory_total, 8 << 30);
+        assert_eq!(devices[0].temperature_celsius, Some(70.0));
+        assert_eq!(devices[1].utilization_percent, 50.0);
+        assert_eq!(devices[1].temperature_celsius, None);
+    }
+}
+
+#[cfg(feature = "gpu-amd")]
+mod amd {
+    use polis_stats::amd::AmdBackend;
+    use polis_stats::GpuBackend;
+    use polis_test_support::FileTree;
+
+    #[test]
+    fn test_amd_backend_reads_sysfs() {
+        let drm = tempfile::tempdir().unwrap();
+        FileTree::new()
+            .file("card0/device/gpu_busy_percent", "73\n")
+            .file("card0/device/mem_info_vram_used", "1073741824\n")
+            .file("card0/device/mem_info_vram_total", "8589934592\n")
+            .file("card0/device/hwmon/hwmon3/temp1_input", "54000\n")
+            .dir("card0-DP-1")
+            // Integrated display controller without amdgpu counters
+            .file("card1/device/vendor", "0x8086\n")
+            .write_to(drm.path())
+            .unwrap();
+
+        let devices = AmdBackend::new(drm.path()).devices().unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].device_id, "card0");
+        assert_eq!(devices[0].utilization_percent, 73.0);
+        assert_eq!(devices[0].memory_used, 1 << 30);
+        assert_eq!(devices[0].memory_total, 8 << 30);
+        assert_eq!(devices[0].temperature_celsius, Some(54.0));
+    }
+}
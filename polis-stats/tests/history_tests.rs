@@ -0,0 +1,287 @@
+use chrono::{DateTime, Utc};
+use polis_stats::{
+    ContainerMetrics, ContainerStatsCollector, MetricField, DEFAULT_HISTORY_LIMIT,
+    DEFAULT_RETENTION,
+};
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast::error::RecvError;
+
+fn sample(container_id: &str, second: u64) -> ContainerMetrics {
+    let mut metrics = ContainerMetrics {
+        container_id: container_id.to_string(),
+        timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000 + second),
+        ..Default::default()
+    };
+    metrics.cpu.total_time = second;
+    metrics
+}
+
+fn seconds(samples: &[ContainerMetrics]) -> Vec<u64> {
+    samples.iter().map(|m| m.cpu.total_time).collect()
+}
+
+#[tokio::test]
+async fn test_history_is_trimmed_to_the_limit() {
+    let collector = ContainerStatsCollector::default().with_history_limit(5);
+    assert_eq!(
+        ContainerStatsCollector::default().history_limit(),
+        DEFAULT_HISTORY_LIMIT
+    );
+
+    for second in 0..12 {
+        collector
+            .update_metrics("web", sample("web", second))
+            .await
+            .unwrap();
+    }
+
+    let history = collector.get_metrics_history("web", None, 0).await.unwrap();
+    assert_eq!(seconds(&history), vec![7, 8, 9, 10, 11]);
+
+    let latest = collector.get_metrics("web").await.unwrap().unwrap();
+    assert_eq!(latest.cpu.total_time, 11);
+
+    // Limit keeps the newest samples, still in chronological order
+    let recent = collector.get_metrics_history("web", None, 2).await.unwrap();
+    assert_eq!(seconds(&recent), vec![10, 11]);
+
+    let since = DateTime::<Utc>::from(SystemTime::UNIX_EPOCH + Duration::from_secs(1_008));
+    let after = collector
+        .get_metrics_history("web", Some(since), 0)
+        .await
+        .unwrap();
+    assert_eq!(seconds(&after), vec![9, 10, 11]);
+
+    assert!(collector
+        .get_metrics_history("unknown", None, 0)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_multiple_subscribers_receive_every_sample() {
+    let collector = ContainerStatsCollector::default();
+    let mut first = collector.subscribe("web").await;
+    let mut second = collector.subscribe("web").await;
+    let mut other = collector.subscribe("db").await;
+
+    for value in 0..3 {
+        collector
+            .update_metrics("web", sample("web", value))
+            .await
+            .unwrap();
+    }
+
+    for receiver in [&mut first, &mut second] {
+        for expected in 0..3 {
+            let metrics = receiver.recv().await.unwrap();
+            assert_eq!(metrics.container_id, "web");
+            assert_eq!(metrics.cpu.total_time, expected);
+        }
+    }
+    assert!(other.try_recv().is_err());
+
+    // Stopping collection closes the feed
+    collector.start_collecting("web").await.unwrap();
+    collector.stop_collecting("web").await.unwrap();
+    assert!(matches!(first.recv().await, Err(RecvError::Closed)));
+    assert!(collector
+        .get_metrics_history("web", None, 0)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_subscribe_all_fans_in_every_container() {
+    let collector = ContainerStatsCollector::default();
+    let receivers = [collector.subscribe_all(), collector.subscribe_all()];
+
+    let consumers = receivers.map(|mut receiver| {
+        tokio::spawn(async move {
+            let mut received = Vec::new();
+            for _ in 0..6 {
+                let metrics = receiver.recv().await.unwrap();
+                received.push((metrics.container_id, metrics.cpu.total_time));
+            }
+            received
+        })
+    });
+    for value in 0..3 {
+        for id in ["web", "db"] {
+            collector
+                .update_metrics(id, sample(id, value))
+                .await
+                .unwrap();
+        }
+    }
+
+    let expected: Vec<(String, u64)> = (0..3)
+        .flat_map(|value| [("web".to_string(), value), ("db".to_string(), value)])
+        .collect();
+    for consumer in consumers {
+        assert_eq!(consumer.await.unwrap(), expected);
+    }
+}
+
+#[tokio::test]
+async fn test_slow_subscriber_lags_without_blocking() {
+    let collector = ContainerStatsCollector::default().with_subscriber_capacity(4);
+    let mut slow = collector.subscribe("web").await;
+    let mut slow_all = collector.subscribe_all();
+    let mut fast = collector.subscribe("web").await;
+
+    // Nobody reads from `slow` while the collector keeps publishing
+    for value in 0..10 {
+        collector
+            .update_metrics("web", sample("web", value))
+            .await
+            .unwrap();
+        assert_eq!(fast.recv().await.unwrap().cpu.total_time, value);
+    }
+
+    for receiver in [&mut slow, &mut slow_all] {
+        assert!(matches!(receiver.recv().await, Err(RecvError::Lagged(6))));
+        for expected in 6..10 {
+            assert_eq!(receiver.recv().await.unwrap().cpu.total_time, expected);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_monitoring_publishes_samples_to_subscribers() {
+    let collector = ContainerStatsCollector::new(Duration::from_millis(10));
+    collector.start_collecting("web").await.unwrap();
+    let mut receiver = collector.subscribe("web").await;
+    collector.start_monitoring().await.unwrap();
+
+    let metrics = tokio::time::timeout(Duration::from_secs(2), receiver.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(metrics.container_id, "web");
+    collector.stop_monitoring().await.unwrap();
+
+    assert!(!collector
+        .get_metrics_history("web", None, 0)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+fn with_rx(second: u64, rx_bytes: u64) -> ContainerMetrics {
+    let mut metrics = sample("web", second);
+    metrics.network.rx_bytes = rx_bytes;
+    metrics
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_samples_older_than_retention_are_evicted() {
+    let collector = ContainerStatsCollector::default().with_retention(Duration::from_secs(60));
+    assert_eq!(
+        ContainerStatsCollector::default().retention_duration(),
+        DEFAULT_RETENTION
+    );
+
+    for second in [0, 30, 70] {
+        collector
+            .update_metrics("web", sample("web", second))
+            .await
+            .unwrap();
+        tokio::time::advance(Duration::from_secs(if second == 0 { 30 } else { 40 })).await;
+    }
+
+    // The first sample was evicted when the third was recorded; the second
+    // has aged out since and is no longer reported
+    let history = collector.get_metrics_history("web", None, 0).await.unwrap();
+    assert_eq!(seconds(&history), vec![70]);
+
+    collector
+        .update_metrics("web", sample("web", 110))
+        .await
+        .unwrap();
+    let history = collector.get_metrics_history("web", None, 0).await.unwrap();
+    assert_eq!(seconds(&history), vec![70, 110]);
+
+    let window = collector
+        .get_metrics_window("web", Duration::from_secs(10))
+        .await;
+    assert_eq!(seconds(&window), vec![110]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_over_sparse_history() {
+    let collector = ContainerStatsCollector::default();
+
+    // Samples at t=0, 10 and 40s: the gap only widens the interval
+    for (advance, second, rx) in [(0, 0, 1_000), (10, 10, 2_000), (30, 40, 5_000)] {
+        tokio::time::advance(Duration::from_secs(advance)).await;
+        collector
+            .update_metrics("web", with_rx(second, rx))
+            .await
+            .unwrap();
+    }
+    let rate = collector
+        .get_rate("web", MetricField::RxBytes, Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert!((rate - 100.0).abs() < 1e-9);
+
+    // Counter reset at t=50: the new value counts as the increase
+    tokio::time::advance(Duration::from_secs(10)).await;
+    collector
+        .update_metrics("web", with_rx(50, 500))
+        .await
+        .unwrap();
+    let rate = collector
+        .get_rate("web", MetricField::RxBytes, Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert!((rate - 90.0).abs() < 1e-9);
+
+    let recent = collector
+        .get_rate("web", MetricField::RxBytes, Duration::from_secs(15))
+        .await
+        .unwrap();
+    assert!((recent - 50.0).abs() < 1e-9);
+
+    // Counters that never moved have a zero rate
+    let ops = collector
+        .get_rate("web", MetricField::WriteOps, Duration::from_secs(60))
+        .await;
+    assert_eq!(ops, Some(0.0));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_with_empty_or_single_sample_history() {
+    let collector = ContainerStatsCollector::default();
+    assert!(collector
+        .get_rate("web", MetricField::RxBytes, Duration::from_secs(60))
+        .await
+        .is_none());
+    assert!(collector
+        .get_metrics_window("web", Duration::from_secs(60))
+        .await
+        .is_empty());
+
+    collector
+        .update_metrics("web", with_rx(0, 1_000))
+        .await
+        .unwrap();
+    assert!(collector
+        .get_rate("web", MetricField::RxBytes, Duration::from_secs(60))
+        .await
+        .is_none());
+
+    // A window that excludes all but the latest sample
+    tokio::time::advance(Duration::from_secs(120)).await;
+    collector
+        .update_metrics("web", with_rx(120, 9_000))
+        .await
+        .unwrap();
+    assert!(collector
+        .get_rate("web", MetricField::RxBytes, Duration::from_secs(60))
+        .await
+        .is_none());
+}
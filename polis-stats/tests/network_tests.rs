@@ -0,0 +1,123 @@
+use polis_stats::{
+    read_interface_stats, read_interfaces, InterfaceStats, MetricsCollector, NetworkMetrics,
+    StatsError,
+};
+use polis_test_support::{CgroupFixture, ProcFixture, ProcProcess};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+fn counters(rx_bytes: u64, tx_bytes: u64) -> InterfaceStats {
+    InterfaceStats {
+        rx_bytes,
+        tx_bytes,
+        rx_packets: rx_bytes / 100,
+        tx_packets: tx_bytes / 100,
+        rx_errors: 1,
+        tx_errors: 0,
+        rx_dropped: 2,
+        tx_dropped: 0,
+    }
+}
+
+fn write_interface(net_dir: &Path, name: &str, stats: &InterfaceStats) {
+    let statistics = net_dir.join(name).join("statistics");
+    fs::create_dir_all(&statistics).unwrap();
+    for (file, value) in [
+        ("rx_bytes", stats.rx_bytes),
+        ("tx_bytes", stats.tx_bytes),
+        ("rx_packets", stats.rx_packets),
+        ("tx_packets", stats.tx_packets),
+        ("rx_errors", stats.rx_errors),
+        ("tx_errors", stats.tx_errors),
+        ("rx_dropped", stats.rx_dropped),
+        ("tx_dropped", stats.tx_dropped),
+    ] {
+        fs::write(statistics.join(file), format!("{}\n", value)).unwrap();
+    }
+}
+
+#[test]
+fn test_aggregate_excludes_loopback() {
+    let interfaces = HashMap::from([
+        ("eth0".to_string(), counters(1_000, 500)),
+        ("eth1".to_string(), counters(300, 200)),
+        ("lo".to_string(), counters(9_000, 9_000)),
+    ]);
+
+    let network = NetworkMetrics::from_interfaces(interfaces);
+    assert_eq!(network.rx_bytes, 1_300);
+    assert_eq!(network.tx_bytes, 700);
+    assert_eq!(network.rx_packets, 13);
+    assert_eq!(network.rx_errors, 2);
+    assert_eq!(network.rx_dropped, 4);
+    assert_eq!(network.interfaces.len(), 3);
+    assert_eq!(network.interfaces["lo"].rx_bytes, 9_000);
+
+    let loopback_only =
+        NetworkMetrics::from_interfaces(HashMap::from([("lo".to_string(), counters(10, 10))]));
+    assert_eq!(loopback_only.rx_bytes, 0);
+    assert_eq!(loopback_only.tx_bytes, 0);
+}
+
+#[test]
+fn test_read_interfaces_from_sysfs() {
+    let net_dir = tempfile::tempdir().unwrap();
+    write_interface(net_dir.path(), "eth0", &counters(4_200, 1_100));
+    write_interface(net_dir.path(), "lo", &counters(64, 64));
+
+    let interfaces = read_interfaces(net_dir.path()).unwrap();
+    assert_eq!(interfaces.len(), 2);
+    assert_eq!(interfaces["eth0"], counters(4_200, 1_100));
+    assert_eq!(interfaces["lo"], counters(64, 64));
+}
+
+#[test]
+fn test_missing_or_invalid_counter_files() {
+    let net_dir = tempfile::tempdir().unwrap();
+    write_interface(net_dir.path(), "eth0", &counters(1, 1));
+    fs::remove_file(net_dir.path().join("eth0/statistics/tx_dropped")).unwrap();
+
+    assert!(matches!(
+        read_interface_stats(&net_dir.path().join("eth0")),
+        Err(StatsError::Io(_))
+    ));
+    assert!(read_interfaces(net_dir.path()).is_err());
+
+    fs::write(net_dir.path().join("eth0/statistics/tx_dropped"), "n/a\n").unwrap();
+    assert!(matches!(
+        read_interface_stats(&net_dir.path().join("eth0")),
+        Err(StatsError::Parse(_))
+    ));
+
+    assert!(matches!(
+        read_interfaces(&net_dir.path().join("missing")),
+        Err(StatsError::Io(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_collector_reads_container_interfaces() {
+    let root = tempfile::tempdir().unwrap();
+    let proc = ProcFixture::create(&root.path().join("proc"), 1_000.0, 1_700_000_000);
+    proc.process(&ProcProcess::new(42, "nginx"));
+    let cgroup = CgroupFixture::create(&root.path().join("cgroup"), "web");
+    cgroup.procs(&[42]);
+
+    let net_dir = proc.path().join("42/root/sys/class/net");
+    write_interface(&net_dir, "eth0", &counters(2_000, 800));
+    write_interface(&net_dir, "lo", &counters(50, 50));
+
+    let mut collector = MetricsCollector::new()
+        .with_proc_root(proc.path())
+        .with_cgroup_root(root.path().join("cgroup"));
+    let metrics = collector.collect_container_metrics("web").await.unwrap();
+    assert_eq!(metrics.network.rx_bytes, 2_000);
+    assert_eq!(metrics.network.tx_bytes, 800);
+    assert_eq!(metrics.network.interfaces.len(), 2);
+
+    // Containers that are not running report no traffic
+    let stopped = collector.collect_container_metrics("db").await.unwrap();
+    assert_eq!(stopped.network.rx_bytes, 0);
+    assert!(stopped.network.interfaces.is_empty());
+}
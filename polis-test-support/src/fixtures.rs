@@ -0,0 +1,398 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// `sha256:<hex>` digest of the data
+pub fn sha256_digest(data: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(data))
+}
+
+#[derive(Debug, Clone)]
+enum Entry {
+    File { contents: Vec<u8>, mode: u32 },
+    Dir,
+    Symlink(PathBuf),
+}
+
+/// A set of files, directories and symlinks that can be written to disk or
+/// packed into an image layer. Paths are relative and kept in insertion
+/// order, so the generated tars (and their digests) are reproducible.
+#[derive(Debug, Clone, Default)]
+pub struct FileTree {
+    entries: Vec<(PathBuf, Entry)>,
+}
+
+impl FileTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Regular file with mode 0644
+    pub fn file(self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Self {
+        self.file_with_mode(path, contents, 0o644)
+    }
+
+    /// Regular file with mode 0755
+    pub fn executable(self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Self {
+        self.file_with_mode(path, contents, 0o755)
+    }
+
+    pub fn file_with_mode(
+        mut self,
+        path: impl AsRef<Path>,
+        contents: impl AsRef<[u8]>,
+        mode: u32,
+    ) -> Self {
+        self.entries.push((
+            path.as_ref().to_path_buf(),
+            Entry::File {
+                contents: contents.as_ref().to_vec(),
+                mode,
+            },
+        ));
+        self
+    }
+
+    pub fn dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.entries.push((path.as_ref().to_path_buf(), Entry::Dir));
+        self
+    }
+
+    pub fn symlink(mut self, path: impl AsRef<Path>, target: impl AsRef<Path>) -> Self {
+        self.entries.push((
+            path.as_ref().to_path_buf(),
+            Entry::Symlink(target.as_ref().to_path_buf()),
+        ));
+        self
+    }
+
+    /// OCI whiteout (`.wh.<name>`) hiding `path` from lower layers
+    pub fn whiteout(self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .expect("whiteout path needs a file name")
+            .to_string_lossy();
+        let marker = path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(format!(".wh.{}", name));
+        self.file(marker, b"")
+    }
+
+    /// Create the tree under `root`, creating parent directories as needed
+    pub fn write_to(&self, root: &Path) -> std::io::Result<()> {
+        for (path, entry) in &self.entries {
+            let target = root.join(path);
+            match entry {
+                Entry::Dir => fs::create_dir_all(&target)?,
+                Entry::File { contents, mode } => {
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&target, contents)?;
+                    set_mode(&target, *mode)?;
+                }
+                Entry::Symlink(link) => {
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    if fs::symlink_metadata(&target).is_ok() {
+                        fs::remove_file(&target)?;
+                    }
+                    make_symlink(link, &target)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pack the tree into a layer
+    pub fn layer(&self) -> Layer {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, entry) in &self.entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_mtime(0);
+            header.set_uid(0);
+            header.set_gid(0);
+            match entry {
+                Entry::Dir => {
+                    header.set_entry_type(tar::EntryType::Directory);
+                    header.set_mode(0o755);
+                    header.set_size(0);
+                    builder
+                        .append_data(&mut header, path, std::io::empty())
+                        .expect("append directory");
+                }
+                Entry::File { contents, mode } => {
+                    header.set_entry_type(tar::EntryType::Regular);
+                    header.set_mode(*mode);
+                    header.set_size(contents.len() as u64);
+                    builder
+                        .append_data(&mut header, path, contents.as_slice())
+                        .expect("append file");
+                }
+                Entry::Symlink(link) => {
+                    header.set_entry_type(tar::EntryType::Symlink);
+                    header.set_mode(0o777);
+                    header.set_size(0);
+                    builder
+                        .append_link(&mut header, path, link)
+                        .expect("append symlink");
+                }
+            }
+        }
+        let tar = builder.into_inner().expect("finish layer tar");
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&tar).expect("compress layer");
+        let compressed = encoder.finish().expect("finish layer gzip");
+
+        Layer {
+            diff_id: sha256_digest(&tar),
+            digest: sha256_digest(&compressed),
+            tar,
+            compressed,
+        }
+    }
+}
+
+/// Image layer generated from a [`FileTree`]
+#[derive(Debug, Clone)]
+pub struct Layer {
+    /// Uncompressed tar
+    pub tar: Vec<u8>,
+    /// Gzip-compressed tar, as served by a registry
+    pub compressed: Vec<u8>,
+    /// Digest of the uncompressed tar (`rootfs.diff_ids`)
+    pub diff_id: String,
+    /// Digest of the compressed blob (manifest `layers[].digest`)
+    pub digest: String,
+}
+
+impl Layer {
+    /// Size of the compressed blob
+    pub fn size(&self) -> u64 {
+        self.compressed.len() as u64
+    }
+
+    /// Manifest descriptor for the layer, in the repo's snake_case layout
+    pub fn descriptor(&self) -> serde_json::Value {
+        serde_json::json!({
+            "media_type": "application/vnd.oci.image.layer.v1.tar+gzip",
+            "size": self.size(),
+            "digest": self.digest,
+        })
+    }
+}
+
+/// cgroup v2 directory with the interface files read by stats and `top`
+pub struct CgroupFixture {
+    dir: PathBuf,
+}
+
+impl CgroupFixture {
+    /// Create `root/<name>`
+    pub fn create(root: &Path, name: &str) -> Self {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).expect("create cgroup fixture");
+        Self { dir }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// `cgroup.procs`, one pid per line
+    pub fn procs(&self, pids: &[u32]) -> &Self {
+        let contents: String = pids.iter().map(|pid| format!("{}\n", pid)).collect();
+        self.file("cgroup.procs", &contents)
+    }
+
+    /// `memory.current` and `memory.max` (`None` is written as `max`)
+    pub fn memory(&self, current: u64, max: Option<u64>) -> &Self {
+        self.file("memory.current", &format!("{}\n", current));
+        self.file("memory.max", &format!("{}\n", limit(max)))
+    }
+
+    /// `cpu.stat` usage counters, in microseconds
+    pub fn cpu_stat(&self, usage_usec: u64, user_usec: u64, system_usec: u64) -> &Self {
+        self.file(
+            "cpu.stat",
+            &format!(
+                "usage_usec {}\nuser_usec {}\nsystem_usec {}\nnr_periods 0\nnr_throttled 0\nthrottled_usec 0\n",
+                usage_usec, user_usec, system_usec
+            ),
+        )
+    }
+
+    /// `pids.current` and `pids.max` (`None` is written as `max`)
+    pub fn pids(&self, current: u64, max: Option<u64>) -> &Self {
+        self.file("pids.current", &format!("{}\n", current));
+        self.file("pids.max", &format!("{}\n", limit(max)))
+    }
+
+    /// Any other interface file
+    pub fn file(&self, name: &str, contents: &str) -> &Self {
+        fs::write(self.dir.join(name), contents).expect("write cgroup file");
+        self
+    }
+}
+
+fn limit(value: Option<u64>) -> String {
+    value.map_or_else(|| "max".to_string(), |v| v.to_string())
+}
+
+/// Process as exposed under `/proc/<pid>`
+#[derive(Debug, Clone)]
+pub struct ProcProcess {
+    pub pid: u32,
+    pub comm: String,
+    pub state: char,
+    /// Innermost pid (last `NSpid` value); defaults to `pid`
+    pub nspid: Option<u32>,
+    pub uid: u32,
+    pub rss_kb: u64,
+    pub threads: u32,
+    /// User and system time, in clock ticks
+    pub utime: u64,
+    pub stime: u64,
+    /// Start time after boot, in clock ticks
+    pub starttime: u64,
+    pub cmdline: Vec<String>,
+    /// Contents of `uid_map`; identity mapping when `None`
+    pub uid_map: Option<String>,
+}
+
+impl ProcProcess {
+    pub fn new(pid: u32, comm: &str) -> Self {
+        Self {
+            pid,
+            comm: comm.to_string(),
+            state: 'S',
+            nspid: None,
+            uid: 0,
+            rss_kb: 0,
+            threads: 1,
+            utime: 0,
+            stime: 0,
+            starttime: 0,
+            cmdline: Vec::new(),
+            uid_map: None,
+        }
+    }
+}
+
+/// Fake `/proc` with `uptime`, `stat` and per-process files
+pub struct ProcFixture {
+    root: PathBuf,
+}
+
+impl ProcFixture {
+    /// Create the root with the given uptime (seconds) and boot time (epoch)
+    pub fn create(root: &Path, uptime_secs: f64, btime: u64) -> Self {
+        fs::create_dir_all(root).expect("create proc fixture");
+        fs::write(
+            root.join("uptime"),
+            format!("{:.2} {:.2}\n", uptime_secs, uptime_secs * 3.0),
+        )
+        .expect("write uptime");
+        fs::write(
+            root.join("stat"),
+            format!("cpu  1 2 3 4\nbtime {}\nprocesses 100\n", btime),
+        )
+        .expect("write stat");
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Write `stat`, `status`, `cmdline` and `uid_map` for a process
+    pub fn process(&self, process: &ProcProcess) -> &Self {
+        let dir = self.root.join(process.pid.to_string());
+        fs::create_dir_all(&dir).expect("create process dir");
+
+        let pid = process.pid;
+        fs::write(
+            dir.join("stat"),
+            format!(
+                "{} ({}) {} 1 {} {} 0 -1 4194560 100 0 0 0 {} {} 0 0 20 0 {} 0 {} 1000000 512\n",
+                pid,
+                process.comm,
+                process.state,
+                pid,
+                pid,
+                process.utime,
+                process.stime,
+                process.threads,
+                process.starttime
+            ),
+        )
+        .expect("write process stat");
+        fs::write(
+            dir.join("status"),
+            format!(
+                "Name:\t{}\nState:\t{}\nUid:\t{uid}\t{uid}\t{uid}\t{uid}\nNSpid:\t{}\t{}\nVmRSS:\t{} kB\n",
+                process.comm,
+                process.state,
+                pid,
+                process.nspid.unwrap_or(pid),
+                process.rss_kb,
+                uid = process.uid
+            ),
+        )
+        .expect("write process status");
+
+        let cmdline: String = process
+            .cmdline
+            .iter()
+            .map(|arg| format!("{}\0", arg))
+            .collect();
+        fs::write(dir.join("cmdline"), cmdline).expect("write process cmdline");
+        fs::write(
+            dir.join("uid_map"),
+            process
+                .uid_map
+                .as_deref()
+                .unwrap_or("         0          0 4294967295\n"),
+        )
+        .expect("write process uid_map");
+        self
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn make_symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn make_symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn make_symlink(_target: &Path, _link: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "symlinks are not supported on this platform",
+    ))
+}
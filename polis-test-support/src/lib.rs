@@ -0,0 +1,13 @@
+//! Test doubles and fixtures shared by the workspace test suites.
+//!
+//! This crate is only meant to be used as a dev-dependency.
+
+pub mod fixtures;
+pub mod network;
+pub mod registry;
+pub mod runtime;
+
+pub use fixtures::*;
+pub use network::*;
+pub use registry::*;
+pub use runtime::*;
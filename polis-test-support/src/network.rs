@@ -0,0 +1,108 @@
+use polis_core::{PolisError, Result};
+use polis_network::{FirewallBackend, FirewallRule, KernelOp, NetlinkBackend};
+use std::sync::Mutex;
+
+/// [`NetlinkBackend`] that records the operations instead of applying them
+#[derive(Default)]
+pub struct FakeNetlink {
+    ops: Mutex<Vec<KernelOp>>,
+    fail_on: Mutex<Option<KernelOp>>,
+}
+
+impl FakeNetlink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Operations applied so far, in order
+    pub fn ops(&self) -> Vec<KernelOp> {
+        self.ops.lock().unwrap().clone()
+    }
+
+    /// Reject the given operation, as the kernel would on EPERM/EEXIST
+    pub fn fail_on(&self, op: KernelOp) {
+        *self.fail_on.lock().unwrap() = Some(op);
+    }
+}
+
+impl NetlinkBackend for FakeNetlink {
+    fn apply(&self, op: KernelOp) -> Result<()> {
+        if self.fail_on.lock().unwrap().as_ref() == Some(&op) {
+            return Err(PolisError::Network(format!("netlink rejected {:?}", op)));
+        }
+        self.ops.lock().unwrap().push(op);
+        Ok(())
+    }
+}
+
+/// A firewall change captured by [`FakeFirewall`]
+#[derive(Debug, Clone)]
+pub enum FirewallOp {
+    AddRule { chain: String, rule: FirewallRule },
+    RemoveRule { chain: String, rule_id: String },
+    FlushChain { chain: String },
+}
+
+/// [`FirewallBackend`] that records rule changes and mirrors the resulting
+/// kernel rule set, so tests can assert on both the sequence and the outcome
+#[derive(Default)]
+pub struct FakeFirewall {
+    ops: Mutex<Vec<FirewallOp>>,
+    installed: Mutex<Vec<(String, FirewallRule)>>,
+}
+
+impl FakeFirewall {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Changes received so far, in order
+    pub fn ops(&self) -> Vec<FirewallOp> {
+        self.ops.lock().unwrap().clone()
+    }
+
+    /// Ids of the rules currently installed in a chain, in order
+    pub fn rule_ids(&self, chain: &str) -> Vec<String> {
+        self.installed
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(c, _)| c == chain)
+            .map(|(_, rule)| rule.id.clone())
+            .collect()
+    }
+}
+
+impl FirewallBackend for FakeFirewall {
+    fn add_rule(&self, chain: &str, rule: &FirewallRule) -> Result<()> {
+        self.ops.lock().unwrap().push(FirewallOp::AddRule {
+            chain: chain.to_string(),
+            rule: rule.clone(),
+        });
+        self.installed
+            .lock()
+            .unwrap()
+            .push((chain.to_string(), rule.clone()));
+        Ok(())
+    }
+
+    fn remove_rule(&self, chain: &str, rule_id: &str) -> Result<()> {
+        self.ops.lock().unwrap().push(FirewallOp::RemoveRule {
+            chain: chain.to_string(),
+            rule_id: rule_id.to_string(),
+        });
+        self.installed
+            .lock()
+            .unwrap()
+            .retain(|(c, rule)| !(c == chain && rule.id == rule_id));
+        Ok(())
+    }
+
+    fn flush_chain(&self, chain: &str) -> Result<()> {
+        self.ops.lock().unwrap().push(FirewallOp::FlushChain {
+            chain: chain.to_string(),
+        });
+        self.installed.lock().unwrap().retain(|(c, _)| c != chain);
+        Ok(())
+    }
+}
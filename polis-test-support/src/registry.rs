@@ -0,0 +1,641 @@
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri};
+use axum::response::Response;
+use axum::Router;
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+const OCI_MANIFEST: &str = "application/vnd.oci.image.manifest.v1+json";
+
+/// Failure injected into a registry response
+#[derive(Debug, Clone)]
+pub enum Fault {
+    /// Wait before answering; the response itself is served normally
+    Latency(Duration),
+    /// `429 Too Many Requests` with an optional `Retry-After` (seconds) and
+    /// extra headers such as `ratelimit-remaining`
+    TooManyRequests {
+        retry_after: Option<u64>,
+        headers: Vec<(String, String)>,
+    },
+    /// Announce the full `Content-Length` but send only the first bytes of
+    /// the body, so the client sees a premature end of stream
+    Truncate(usize),
+    /// `401 Unauthorized` with a bearer `WWW-Authenticate` challenge
+    AuthChallenge { realm: String, service: String },
+    /// A status with a distribution-spec error body
+    /// (`{"errors": [{"code", "message"}]}`)
+    RegistryError {
+        status: u16,
+        code: String,
+        message: String,
+    },
+    /// Any other status with an empty body
+    Status(u16),
+}
+
+impl Fault {
+    /// `429` with rate limit headers in the `name: value` form
+    pub fn rate_limited(retry_after: Option<u64>, headers: &[(&str, &str)]) -> Self {
+        Fault::TooManyRequests {
+            retry_after,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+}
+
+/// A request received by [`MockRegistry`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub authorization: Option<String>,
+    pub content_range: Option<String>,
+    pub query: Option<String>,
+}
+
+#[derive(Clone)]
+struct Content {
+    body: Bytes,
+    content_type: String,
+}
+
+#[derive(Default)]
+struct Shared {
+    content: HashMap<String, Content>,
+    faults: VecDeque<Fault>,
+    persistent: Option<Fault>,
+    headers: Vec<(String, String)>,
+    requests: Vec<RecordedRequest>,
+    chunk_size: Option<usize>,
+    /// Bytes received so far by each open upload session
+    uploads: HashMap<String, Vec<u8>>,
+    next_upload: u64,
+    /// Tags per page of `tags/list` when the client does not ask with `n`
+    tags_page_size: Option<usize>,
+    /// Bearer token required on every `/v2/` request
+    token: Option<String>,
+}
+
+/// Distribution-API registry served over HTTP on a random local port.
+///
+/// Manifests and blobs are served by path (`/v2/<repo>/manifests/<ref>`,
+/// `/v2/<repo>/blobs/<digest>`); unknown paths return 404. `tags/list` lists
+/// the tags of the manifests added to a repository, in lexical order and
+/// paginated with `n`/`last` and a `Link` header. Pushes follow the
+/// distribution spec: `POST .../blobs/uploads/` opens a session, `PATCH`
+/// appends to it (from the offset in `Content-Range`, else `416`), `GET`
+/// reports its progress and `PUT ...?digest=` stores the blob once the digest
+/// matches; `PUT .../manifests/<ref>` stores a manifest. Faults queued with
+/// [`inject`] apply to the next requests, one each, before the persistent
+/// fault set with [`inject_always`] is considered. With [`require_token`],
+/// requests without the token get a `401` whose challenge points at the
+/// registry's own `/token` endpoint, which hands the token out.
+///
+/// [`inject`]: MockRegistry::inject
+/// [`inject_always`]: MockRegistry::inject_always
+/// [`require_token`]: MockRegistry::require_token
+pub struct MockRegistry {
+    address: SocketAddr,
+    shared: Arc<Mutex<Shared>>,
+    server: JoinHandle<()>,
+}
+
+impl MockRegistry {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock registry");
+        let address = listener.local_addr().expect("mock registry address");
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        let app = Router::new().fallback(handle).with_state(shared.clone());
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Self {
+            address,
+            shared,
+            server,
+        }
+    }
+
+    /// Base URL of the API, ending in `/v2`
+    pub fn base_url(&self) -> String {
+        format!("http://{}/v2", self.address)
+    }
+
+    /// Host and port, as used in image references
+    pub fn host(&self) -> String {
+        self.address.to_string()
+    }
+
+    /// Serve a manifest; the content type comes from its `mediaType`
+    pub fn add_manifest(&self, repository: &str, reference: &str, body: &str) {
+        let content_type = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|doc| {
+                doc.get("mediaType")
+                    .or_else(|| doc.get("media_type"))
+                    .and_then(|value| value.as_str())
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| OCI_MANIFEST.to_string());
+        self.add_content(
+            format!("/v2/{}/manifests/{}", repository, reference),
+            Bytes::from(body.to_string()),
+            content_type,
+        );
+    }
+
+    /// Serve a blob (layer or config) under its digest
+    pub fn add_blob(&self, repository: &str, digest: &str, data: impl Into<Bytes>) {
+        self.add_content(
+            format!("/v2/{}/blobs/{}", repository, digest),
+            data.into(),
+            "application/octet-stream".to_string(),
+        );
+    }
+
+    /// Header added to every response, including injected faults
+    pub fn set_header(&self, name: &str, value: &str) {
+        let mut shared = self.lock();
+        shared.headers.retain(|(existing, _)| existing != name);
+        shared.headers.push((name.to_string(), value.to_string()));
+    }
+
+    /// Send bodies in chunks of `size` bytes with a short pause between
+    /// them, the way a slow network delivers a large blob
+    pub fn stream_in_chunks(&self, size: usize) {
+        self.lock().chunk_size = Some(size.max(1));
+    }
+
+    /// Split `tags/list` into pages of `size` tags linked with `rel="next"`
+    pub fn paginate_tags(&self, size: usize) {
+        self.lock().tags_page_size = Some(size.max(1));
+    }
+
+    /// Require `Authorization: Bearer <token>` on every `/v2/` request
+    pub fn require_token(&self, token: &str) {
+        self.lock().token = Some(token.to_string());
+    }
+
+    /// Apply a fault to the next request that has no earlier fault queued
+    pub fn inject(&self, fault: Fault) {
+        self.lock().faults.push_back(fault);
+    }
+
+    /// Apply a fault to every request once the queue is empty
+    pub fn inject_always(&self, fault: Fault) {
+        self.lock().persistent = Some(fault);
+    }
+
+    /// Stop injecting faults
+    pub fn clear_faults(&self) {
+        let mut shared = self.lock();
+        shared.faults.clear();
+        shared.persistent = None;
+    }
+
+    /// Requests received so far
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.lock().requests.clone()
+    }
+
+    /// Blob stored under `digest`, whether added or pushed
+    pub fn blob(&self, repository: &str, digest: &str) -> Option<Bytes> {
+        self.content(&format!("/v2/{}/blobs/{}", repository, digest))
+    }
+
+    /// Manifest stored under `reference`, whether added or pushed
+    pub fn manifest(&self, repository: &str, reference: &str) -> Option<String> {
+        self.content(&format!("/v2/{}/manifests/{}", repository, reference))
+            .map(|body| String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// `METHOD path` of each request so far, in order
+    pub fn requested_calls(&self) -> Vec<String> {
+        self.lock()
+            .requests
+            .iter()
+            .map(|request| format!("{} {}", request.method, request.path))
+            .collect()
+    }
+
+    /// Paths requested so far, in order
+    pub fn requested_paths(&self) -> Vec<String> {
+        self.lock()
+            .requests
+            .iter()
+            .map(|request| request.path.clone())
+            .collect()
+    }
+
+    fn add_content(&self, path: String, body: Bytes, content_type: String) {
+        self.lock()
+            .content
+            .insert(path, Content { body, content_type });
+    }
+
+    fn content(&self, path: &str) -> Option<Bytes> {
+        self.lock()
+            .content
+            .get(path)
+            .map(|content| content.body.clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockRegistry {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+async fn handle(
+    State(shared): State<Arc<Mutex<Shared>>>,
+    method: Method,
+    uri: Uri,
+    request_headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let path = uri.path().to_string();
+    let (content, fault, headers, chunk_size) = {
+        let mut shared = shared.lock().unwrap_or_else(|e| e.into_inner());
+        shared.requests.push(RecordedRequest {
+            method: method.to_string(),
+            path: path.clone(),
+            authorization: request_headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            content_range: request_headers
+                .get(header::CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            query: uri.query().map(str::to_string),
+        });
+        if let Some(token) = &shared.token {
+            if path == "/token" {
+                let body = serde_json::json!({ "token": token }).to_string();
+                return content_response(StatusCode::OK, Body::from(body), "application/json");
+            }
+            let expected = format!("Bearer {}", token);
+            let authorized = request_headers
+                .get(header::AUTHORIZATION)
+                .is_some_and(|value| value.as_bytes() == expected.as_bytes());
+            if !authorized {
+                let host = request_headers
+                    .get(header::HOST)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default();
+                return unauthorized(host, &path);
+            }
+        }
+        let fault = shared
+            .faults
+            .pop_front()
+            .or_else(|| shared.persistent.clone());
+        (
+            shared.content.get(&path).cloned(),
+            fault,
+            shared.headers.clone(),
+            shared.chunk_size,
+        )
+    };
+
+    let mut response = match fault {
+        Some(Fault::Latency(delay)) => {
+            tokio::time::sleep(delay).await;
+            receive(&shared, &method, &uri, &request_headers, body)
+                .or_else(|| list_tags(&shared, &uri))
+                .unwrap_or_else(|| serve(&path, content, &method, None, chunk_size))
+        }
+        Some(Fault::Truncate(length)) => serve(&path, content, &method, Some(length), None),
+        Some(Fault::TooManyRequests {
+            retry_after,
+            headers,
+        }) => {
+            let mut response = empty(StatusCode::TOO_MANY_REQUESTS);
+            if let Some(seconds) = retry_after {
+                insert_header(&mut response, "Retry-After", &seconds.to_string());
+            }
+            for (name, value) in &headers {
+                insert_header(&mut response, name, value);
+            }
+            response
+        }
+        Some(Fault::AuthChallenge { realm, service }) => {
+            let mut response = empty(StatusCode::UNAUTHORIZED);
+            insert_header(
+                &mut response,
+                "WWW-Authenticate",
+                &format!(
+                    "Bearer realm=\"{}\",service=\"{}\",scope=\"{}\"",
+                    realm,
+                    service,
+                    scope_for(&path)
+                ),
+            );
+            response
+        }
+        Some(Fault::RegistryError {
+            status,
+            code,
+            message,
+        }) => error_response(status, &code, &message),
+        Some(Fault::Status(code)) => {
+            empty(StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+        }
+        None => receive(&shared, &method, &uri, &request_headers, body)
+            .or_else(|| list_tags(&shared, &uri))
+            .unwrap_or_else(|| serve(&path, content, &method, None, chunk_size)),
+    };
+
+    for (name, value) in &headers {
+        if !response.headers().contains_key(name.as_str()) {
+            insert_header(&mut response, name, value);
+        }
+    }
+    response
+}
+
+/// Answers the push endpoints; `None` for requests that only read
+fn receive(
+    shared: &Mutex<Shared>,
+    method: &Method,
+    uri: &Uri,
+    request_headers: &HeaderMap,
+    body: Bytes,
+) -> Option<Response> {
+    let path = uri.path();
+    let mut shared = shared.lock().unwrap_or_else(|e| e.into_inner());
+
+    if *method == Method::POST {
+        let repository = path.strip_prefix("/v2/")?.strip_suffix("/blobs/uploads/")?;
+        shared.next_upload += 1;
+        let session = format!("/v2/{}/blobs/uploads/{}", repository, shared.next_upload);
+        shared.uploads.insert(session.clone(), Vec::new());
+        let mut response = empty(StatusCode::ACCEPTED);
+        insert_header(&mut response, "Location", &session);
+        insert_header(&mut response, "Range", "0-0");
+        return Some(response);
+    }
+
+    if path.contains("/blobs/uploads/") {
+        let Some(received) = shared.uploads.get_mut(path) else {
+            return Some(empty(StatusCode::NOT_FOUND));
+        };
+        if *method == Method::GET {
+            let mut response = empty(StatusCode::NO_CONTENT);
+            insert_header(&mut response, "Location", path);
+            insert_header(&mut response, "Range", &upload_range(received.len()));
+            return Some(response);
+        }
+        if *method == Method::PATCH {
+            // A chunk must start where the session left off
+            let start = request_headers
+                .get(header::CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|range| range.split('-').next())
+                .and_then(|start| start.parse::<usize>().ok());
+            if start.is_some_and(|start| start != received.len()) {
+                let mut response = empty(StatusCode::RANGE_NOT_SATISFIABLE);
+                insert_header(&mut response, "Range", &upload_range(received.len()));
+                return Some(response);
+            }
+            received.extend_from_slice(&body);
+            let mut response = empty(StatusCode::ACCEPTED);
+            insert_header(&mut response, "Location", path);
+            insert_header(&mut response, "Range", &upload_range(received.len()));
+            return Some(response);
+        }
+        received.extend_from_slice(&body);
+        if *method != Method::PUT {
+            return None;
+        }
+
+        let digest = uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("digest="))
+            .map(|digest| digest.replace("%3A", ":"))
+            .unwrap_or_default();
+        let data = shared.uploads.remove(path).unwrap_or_default();
+        if digest != format!("sha256:{:x}", Sha256::digest(&data)) {
+            return Some(empty(StatusCode::BAD_REQUEST));
+        }
+        let repository = path.trim_start_matches("/v2/").split("/blobs/").next()?;
+        let blob = format!("/v2/{}/blobs/{}", repository, digest);
+        shared.content.insert(
+            blob.clone(),
+            Content {
+                body: Bytes::from(data),
+                content_type: "application/octet-stream".to_string(),
+            },
+        );
+        let mut response = empty(StatusCode::CREATED);
+        insert_header(&mut response, "Location", &blob);
+        insert_header(&mut response, "Docker-Content-Digest", &digest);
+        return Some(response);
+    }
+
+    if *method == Method::PUT && path.contains("/manifests/") {
+        let content_type = request_headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or(OCI_MANIFEST)
+            .to_string();
+        let digest = format!("sha256:{:x}", Sha256::digest(&body));
+        shared
+            .content
+            .insert(path.to_string(), Content { body, content_type });
+        let mut response = empty(StatusCode::CREATED);
+        insert_header(&mut response, "Location", path);
+        insert_header(&mut response, "Docker-Content-Digest", &digest);
+        return Some(response);
+    }
+    None
+}
+
+/// Answers `GET /v2/<repo>/tags/list`; `None` for any other path
+fn list_tags(shared: &Mutex<Shared>, uri: &Uri) -> Option<Response> {
+    let repository = uri
+        .path()
+        .strip_prefix("/v2/")?
+        .strip_suffix("/tags/list")?;
+    let shared = shared.lock().unwrap_or_else(|e| e.into_inner());
+    let prefix = format!("/v2/{}/manifests/", repository);
+    let mut tags: Vec<&str> = shared
+        .content
+        .keys()
+        .filter_map(|path| path.strip_prefix(&prefix))
+        .filter(|reference| !reference.starts_with("sha256:"))
+        .collect();
+    if tags.is_empty() {
+        return Some(empty(StatusCode::NOT_FOUND));
+    }
+    tags.sort();
+
+    let query: HashMap<&str, &str> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .collect();
+    if let Some(last) = query.get("last") {
+        tags.retain(|tag| tag > last);
+    }
+    let page_size = query
+        .get("n")
+        .and_then(|n| n.parse().ok())
+        .or(shared.tags_page_size)
+        .unwrap_or(tags.len());
+    let more = tags.len() > page_size;
+    tags.truncate(page_size);
+
+    let body = serde_json::json!({ "name": repository, "tags": tags }).to_string();
+    let mut response = content_response(StatusCode::OK, Body::from(body), "application/json");
+    if let (true, Some(last)) = (more, tags.last()) {
+        let next = format!(
+            "</v2/{}/tags/list?n={}&last={}>; rel=\"next\"",
+            repository, page_size, last
+        );
+        insert_header(&mut response, "Link", &next);
+    }
+    Some(response)
+}
+
+/// `Range` of an upload session holding `length` bytes
+fn upload_range(length: usize) -> String {
+    format!("0-{}", length.max(1) - 1)
+}
+
+fn serve(
+    path: &str,
+    content: Option<Content>,
+    method: &Method,
+    truncate: Option<usize>,
+    chunk_size: Option<usize>,
+) -> Response {
+    if path == "/v2" || path == "/v2/" {
+        return content_response(StatusCode::OK, Body::from("{}"), "application/json");
+    }
+    let Some(content) = content else {
+        return empty(StatusCode::NOT_FOUND);
+    };
+
+    let length = content.body.len();
+    let body = if method == Method::HEAD {
+        Body::empty()
+    } else {
+        match truncate {
+            // Failing the stream after the first bytes aborts the connection
+            Some(keep) => {
+                let partial = content.body.slice(..keep.min(length));
+                Body::from_stream(futures::stream::iter([
+                    Ok(partial),
+                    Err(std::io::Error::new(
+                        std::io::ErrorKind::ConnectionReset,
+                        "truncated by fault injection",
+                    )),
+                ]))
+            }
+            None => match chunk_size {
+                Some(size) => {
+                    let body = content.body;
+                    let chunks: Vec<Bytes> = (0..length)
+                        .step_by(size)
+                        .map(|start| body.slice(start..(start + size).min(length)))
+                        .collect();
+                    Body::from_stream(futures::StreamExt::then(
+                        futures::stream::iter(chunks),
+                        |chunk| async move {
+                            tokio::time::sleep(Duration::from_millis(5)).await;
+                            Ok::<_, std::io::Error>(chunk)
+                        },
+                    ))
+                }
+                None => Body::from(content.body),
+            },
+        }
+    };
+    let mut response = content_response(StatusCode::OK, body, &content.content_type);
+    insert_header(&mut response, "Content-Length", &length.to_string());
+    response
+}
+
+fn content_response(status: StatusCode, body: Body, content_type: &str) -> Response {
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    insert_header(&mut response, "Content-Type", content_type);
+    response
+}
+
+fn error_response(status: u16, code: &str, message: &str) -> Response {
+    let body = serde_json::json!({
+        "errors": [{ "code": code, "message": message, "detail": null }],
+    })
+    .to_string();
+    content_response(
+        StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        Body::from(body),
+        "application/json",
+    )
+}
+
+/// `401` asking for a token from the registry's `/token` endpoint
+fn unauthorized(host: &str, path: &str) -> Response {
+    let mut response = error_response(401, "UNAUTHORIZED", "authentication required");
+    insert_header(
+        &mut response,
+        "WWW-Authenticate",
+        &format!(
+            "Bearer realm=\"http://{}/token\",service=\"mock-registry\",scope=\"{}\"",
+            host,
+            scope_for(path)
+        ),
+    );
+    response
+}
+
+fn empty(status: StatusCode) -> Response {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+fn insert_header(response: &mut Response, name: &str, value: &str) {
+    if let (Ok(name), Ok(value)) = (
+        HeaderName::from_bytes(name.as_bytes()),
+        HeaderValue::from_str(value),
+    ) {
+        response.headers_mut().insert(name, value);
+    }
+}
+
+/// `repository:<name>:pull` for a `/v2/<name>/manifests|blobs/...` path
+fn scope_for(path: &str) -> String {
+    let repository = path
+        .trim_start_matches("/v2/")
+        .split("/manifests/")
+        .next()
+        .and_then(|rest| rest.split("/blobs/").next())
+        .unwrap_or("");
+    format!("repository:{}:pull", repository)
+}
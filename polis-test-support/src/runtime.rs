@@ -0,0 +1,420 @@
+use async_trait::async_trait;
+use polis_core::{
+    Container, ContainerId, ContainerStatus, ImageId, NetworkMode, PolisError, ProcessEntry,
+    ResourceLimits, Result,
+};
+use polis_runtime::{
+    resolve_container, ContainerBackend, ContainerInspect, ContainerOptions, ContainerRuntime,
+    ProcessTable, TopColumn,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// A call received by [`FakeRuntime`], in the order it was made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeCall {
+    Create { name: String, image: String },
+    Start(ContainerId),
+    Stop(ContainerId),
+    Remove(ContainerId),
+    Pause(ContainerId),
+    Unpause(ContainerId),
+    List,
+    Get(ContainerId),
+    Top(ContainerId),
+    Inspect(ContainerId),
+    Resolve(String),
+}
+
+/// Scripted behaviour for containers created from a given image
+#[derive(Debug, Clone, Default)]
+pub struct Lifecycle {
+    /// Fail `create_container` with this message
+    pub fail_create: Option<String>,
+    /// Fail `start_container` with this message
+    pub fail_start: Option<String>,
+    /// Exit immediately after starting with this code
+    pub exit_on_start: Option<i32>,
+    /// Exit code reported when the container is stopped
+    pub stop_exit_code: Option<i32>,
+}
+
+impl Lifecycle {
+    pub fn fail_create(message: &str) -> Self {
+        Self {
+            fail_create: Some(message.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn fail_start(message: &str) -> Self {
+        Self {
+            fail_start: Some(message.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn exits_with(code: i32) -> Self {
+        Self {
+            exit_on_start: Some(code),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    containers: HashMap<ContainerId, Container>,
+    order: Vec<ContainerId>,
+    lifecycles: HashMap<String, Lifecycle>,
+    processes: HashMap<ContainerId, Vec<ProcessEntry>>,
+    calls: Vec<RuntimeCall>,
+}
+
+/// In-memory [`ContainerRuntime`] and [`ContainerBackend`] with scriptable
+/// lifecycles.
+///
+/// Containers follow the same state machine as the real runtime
+/// (created → running ⇄ paused → stopped/exited) without touching the
+/// kernel. Every call is recorded and can be asserted with [`calls`].
+///
+/// [`calls`]: FakeRuntime::calls
+#[derive(Default)]
+pub struct FakeRuntime {
+    state: Mutex<State>,
+}
+
+impl FakeRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Script the lifecycle of every container created from `image`
+    pub fn script(&self, image: &str, lifecycle: Lifecycle) {
+        self.lock().lifecycles.insert(image.to_string(), lifecycle);
+    }
+
+    /// Processes returned by `top` for a container
+    pub fn set_processes(&self, id: &ContainerId, processes: Vec<ProcessEntry>) {
+        self.lock().processes.insert(id.clone(), processes);
+    }
+
+    /// Simulate the main process of a running container exiting
+    pub fn exit(&self, id: &ContainerId, code: i32) -> Result<()> {
+        let mut state = self.lock();
+        let container = find_mut(&mut state, id)?;
+        if !matches!(
+            container.status,
+            ContainerStatus::Running | ContainerStatus::Paused
+        ) {
+            return Err(PolisError::Runtime(format!(
+                "Container {} is not running",
+                id
+            )));
+        }
+        mark_exited(container, code);
+        Ok(())
+    }
+
+    /// Calls received so far
+    pub fn calls(&self) -> Vec<RuntimeCall> {
+        self.lock().calls.clone()
+    }
+
+    /// Forget the recorded calls, keeping the containers
+    pub fn clear_calls(&self) {
+        self.lock().calls.clear();
+    }
+
+    /// Container looked up by name
+    pub fn container_named(&self, name: &str) -> Option<Container> {
+        self.lock()
+            .containers
+            .values()
+            .find(|c| c.name == name)
+            .cloned()
+    }
+
+    /// Number of recorded calls matching a predicate
+    pub fn count_calls(&self, matches: impl Fn(&RuntimeCall) -> bool) -> usize {
+        self.lock()
+            .calls
+            .iter()
+            .filter(|call| matches(call))
+            .count()
+    }
+
+    /// Containers currently known to the runtime, in creation order
+    pub fn containers(&self) -> Vec<Container> {
+        let state = self.lock();
+        state
+            .order
+            .iter()
+            .filter_map(|id| state.containers.get(id).cloned())
+            .collect()
+    }
+
+    fn create_with(
+        &self,
+        name: String,
+        image: String,
+        command: Vec<String>,
+        options: ContainerOptions,
+    ) -> Result<ContainerId> {
+        let mut state = self.lock();
+        state.calls.push(RuntimeCall::Create {
+            name: name.clone(),
+            image: image.clone(),
+        });
+        if let Some(message) = state
+            .lifecycles
+            .get(&image)
+            .and_then(|l| l.fail_create.clone())
+        {
+            return Err(PolisError::Runtime(message));
+        }
+
+        let id = ContainerId::new();
+        let container = Container {
+            id: id.clone(),
+            name,
+            image: ImageId::from_string(&image),
+            status: ContainerStatus::Created,
+            created_at: chrono::Utc::now(),
+            started_at: None,
+            finished_at: None,
+            exit_code: None,
+            command,
+            working_dir: options.working_dir.unwrap_or_else(|| PathBuf::from("/")),
+            environment: options.environment,
+            labels: options.labels,
+            resource_limits: ResourceLimits::default(),
+            network_mode: NetworkMode::default(),
+            ports: Vec::new(),
+            volumes: options.volumes,
+            rootfs: options.rootfs,
+            max_runtime: options.max_runtime,
+            deadline: None,
+            stop_reason: None,
+            egress: options.egress,
+        };
+        state.containers.insert(id.clone(), container);
+        state.order.push(id.clone());
+        Ok(id)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn transition(
+        &self,
+        call: RuntimeCall,
+        id: &ContainerId,
+        apply: impl FnOnce(&mut Container, &Lifecycle) -> Result<()>,
+    ) -> Result<()> {
+        let mut state = self.lock();
+        state.calls.push(call);
+        let lifecycle = state
+            .containers
+            .get(id)
+            .and_then(|c| state.lifecycles.get(&c.image.0))
+            .cloned()
+            .unwrap_or_default();
+        let container = find_mut(&mut state, id)?;
+        apply(container, &lifecycle)
+    }
+}
+
+fn not_found(id: &ContainerId) -> PolisError {
+    PolisError::Container(format!("Container {} not found", id))
+}
+
+fn find_mut<'a>(state: &'a mut State, id: &ContainerId) -> Result<&'a mut Container> {
+    state.containers.get_mut(id).ok_or_else(|| not_found(id))
+}
+
+fn mark_exited(container: &mut Container, code: i32) {
+    container.status = ContainerStatus::Exited;
+    container.exit_code = Some(code);
+    container.finished_at = Some(chrono::Utc::now());
+}
+
+fn invalid_state(id: &ContainerId, status: &ContainerStatus, action: &str) -> PolisError {
+    PolisError::Runtime(format!(
+        "Cannot {} container {} in state {:?}",
+        action, id, status
+    ))
+}
+
+#[async_trait]
+impl ContainerRuntime for FakeRuntime {
+    async fn create_container(
+        &self,
+        name: String,
+        image: String,
+        command: Vec<String>,
+    ) -> Result<ContainerId> {
+        self.create_with(name, image, command, ContainerOptions::default())
+    }
+
+    async fn start_container(&self, id: ContainerId) -> Result<()> {
+        self.transition(RuntimeCall::Start(id.clone()), &id, |container, script| {
+            match container.status {
+                ContainerStatus::Created | ContainerStatus::Stopped | ContainerStatus::Exited => {}
+                ref other => return Err(invalid_state(&id, other, "start")),
+            }
+            if let Some(message) = &script.fail_start {
+                return Err(PolisError::Runtime(message.clone()));
+            }
+            container.status = ContainerStatus::Running;
+            container.started_at = Some(chrono::Utc::now());
+            container.finished_at = None;
+            container.exit_code = None;
+            if let Some(code) = script.exit_on_start {
+                mark_exited(container, code);
+            }
+            Ok(())
+        })
+    }
+
+    async fn stop_container(&self, id: ContainerId) -> Result<()> {
+        self.transition(RuntimeCall::Stop(id.clone()), &id, |container, script| {
+            match container.status {
+                ContainerStatus::Running | ContainerStatus::Paused => {}
+                ref other => return Err(invalid_state(&id, other, "stop")),
+            }
+            container.status = ContainerStatus::Stopped;
+            container.exit_code = Some(script.stop_exit_code.unwrap_or(0));
+            container.finished_at = Some(chrono::Utc::now());
+            Ok(())
+        })
+    }
+
+    async fn remove_container(&self, id: ContainerId) -> Result<()> {
+        let mut state = self.lock();
+        state.calls.push(RuntimeCall::Remove(id.clone()));
+        let container = find_mut(&mut state, &id)?;
+        if matches!(
+            container.status,
+            ContainerStatus::Running | ContainerStatus::Paused
+        ) {
+            let status = container.status.clone();
+            return Err(invalid_state(&id, &status, "remove"));
+        }
+        state.containers.remove(&id);
+        state.order.retain(|existing| existing != &id);
+        state.processes.remove(&id);
+        Ok(())
+    }
+
+    async fn list_containers(&self) -> Result<Vec<Container>> {
+        let mut state = self.lock();
+        state.calls.push(RuntimeCall::List);
+        Ok(state
+            .order
+            .iter()
+            .filter_map(|id| state.containers.get(id).cloned())
+            .collect())
+    }
+
+    async fn get_container(&self, id: ContainerId) -> Result<Container> {
+        let mut state = self.lock();
+        state.calls.push(RuntimeCall::Get(id.clone()));
+        state
+            .containers
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| not_found(&id))
+    }
+
+    async fn pause_container(&self, id: ContainerId) -> Result<()> {
+        self.transition(RuntimeCall::Pause(id.clone()), &id, |container, _| {
+            if container.status != ContainerStatus::Running {
+                return Err(invalid_state(&id, &container.status, "pause"));
+            }
+            container.status = ContainerStatus::Paused;
+            Ok(())
+        })
+    }
+
+    async fn unpause_container(&self, id: ContainerId) -> Result<()> {
+        self.transition(RuntimeCall::Unpause(id.clone()), &id, |container, _| {
+            if container.status != ContainerStatus::Paused {
+                return Err(invalid_state(&id, &container.status, "unpause"));
+            }
+            container.status = ContainerStatus::Running;
+            Ok(())
+        })
+    }
+
+    async fn top(&self, id: ContainerId, columns: &[TopColumn]) -> Result<ProcessTable> {
+        let mut state = self.lock();
+        state.calls.push(RuntimeCall::Top(id.clone()));
+        let container = find_mut(&mut state, &id)?;
+        if container.status != ContainerStatus::Running {
+            let status = container.status.clone();
+            return Err(invalid_state(&id, &status, "inspect processes of"));
+        }
+        let processes = state.processes.get(&id).cloned().unwrap_or_default();
+        Ok(ProcessTable::new(columns, processes))
+    }
+
+    async fn inspect_container(&self, id: ContainerId) -> Result<ContainerInspect> {
+        let mut state = self.lock();
+        state.calls.push(RuntimeCall::Inspect(id.clone()));
+        let container = state
+            .containers
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| not_found(&id))?;
+        Ok(ContainerInspect::from_container(container))
+    }
+
+    async fn resolve(&self, reference: &str) -> Result<ContainerId> {
+        let mut state = self.lock();
+        state
+            .calls
+            .push(RuntimeCall::Resolve(reference.to_string()));
+        resolve_container(
+            reference,
+            state
+                .containers
+                .values()
+                .map(|container| (container.name.as_str(), &container.id)),
+        )
+    }
+}
+
+#[async_trait]
+impl ContainerBackend for FakeRuntime {
+    async fn create(
+        &self,
+        name: String,
+        image: String,
+        options: ContainerOptions,
+    ) -> Result<ContainerId> {
+        self.create_with(name, image, Vec::new(), options)
+    }
+
+    async fn start(&self, id: &ContainerId) -> Result<()> {
+        self.start_container(id.clone()).await
+    }
+
+    async fn stop(&self, id: &ContainerId) -> Result<()> {
+        self.stop_container(id.clone()).await
+    }
+
+    async fn remove(&self, id: &ContainerId) -> Result<()> {
+        self.remove_container(id.clone()).await
+    }
+
+    async fn status(&self, id: &ContainerId) -> Result<ContainerStatus> {
+        self.lock()
+            .containers
+            .get(id)
+            .map(|container| container.status.clone())
+            .ok_or_else(|| not_found(id))
+    }
+}
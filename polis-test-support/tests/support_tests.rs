@@ -0,0 +1,360 @@
+use polis_core::{ContainerStatus, EgressMode, EgressPolicy, ProcReader};
+use polis_network::{BridgeManager, EgressController, FirewallManager, KernelOp, EGRESS_CHAIN};
+use polis_runtime::{ContainerRuntime, TopColumn};
+use polis_test_support::{
+    sha256_digest, CgroupFixture, FakeFirewall, FakeNetlink, FakeRuntime, Fault, FileTree,
+    FirewallOp, Lifecycle, MockRegistry, ProcFixture, ProcProcess, RuntimeCall,
+};
+use std::io::Read;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_fake_runtime_lifecycle_and_call_log() {
+    let runtime = FakeRuntime::new();
+    let id = runtime
+        .create_container("web".to_string(), "nginx:latest".to_string(), vec![])
+        .await
+        .unwrap();
+
+    runtime.start_container(id.clone()).await.unwrap();
+    runtime.pause_container(id.clone()).await.unwrap();
+    assert!(runtime.remove_container(id.clone()).await.is_err());
+    runtime.unpause_container(id.clone()).await.unwrap();
+    runtime.stop_container(id.clone()).await.unwrap();
+
+    let container = runtime.get_container(id.clone()).await.unwrap();
+    assert_eq!(container.status, ContainerStatus::Stopped);
+    assert_eq!(container.exit_code, Some(0));
+
+    runtime.remove_container(id.clone()).await.unwrap();
+    assert!(runtime.list_containers().await.unwrap().is_empty());
+
+    assert_eq!(
+        runtime.calls(),
+        vec![
+            RuntimeCall::Create {
+                name: "web".to_string(),
+                image: "nginx:latest".to_string()
+            },
+            RuntimeCall::Start(id.clone()),
+            RuntimeCall::Pause(id.clone()),
+            RuntimeCall::Remove(id.clone()),
+            RuntimeCall::Unpause(id.clone()),
+            RuntimeCall::Stop(id.clone()),
+            RuntimeCall::Get(id.clone()),
+            RuntimeCall::Remove(id.clone()),
+            RuntimeCall::List,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_fake_runtime_scripted_failures_and_exits() {
+    let runtime = FakeRuntime::new();
+    runtime.script("broken", Lifecycle::fail_start("exec format error"));
+    runtime.script("oneshot", Lifecycle::exits_with(3));
+    runtime.script("missing", Lifecycle::fail_create("image not found"));
+
+    let error = runtime
+        .create_container("m".to_string(), "missing".to_string(), vec![])
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("image not found"));
+
+    let broken = runtime
+        .create_container("b".to_string(), "broken".to_string(), vec![])
+        .await
+        .unwrap();
+    let error = runtime.start_container(broken.clone()).await.unwrap_err();
+    assert!(error.to_string().contains("exec format error"));
+    assert_eq!(
+        runtime.get_container(broken).await.unwrap().status,
+        ContainerStatus::Created
+    );
+
+    let oneshot = runtime
+        .create_container("o".to_string(), "oneshot".to_string(), vec![])
+        .await
+        .unwrap();
+    runtime.start_container(oneshot.clone()).await.unwrap();
+    let container = runtime.get_container(oneshot).await.unwrap();
+    assert_eq!(container.status, ContainerStatus::Exited);
+    assert_eq!(container.exit_code, Some(3));
+
+    // A running container exiting on its own
+    let server = runtime
+        .create_container("s".to_string(), "server".to_string(), vec![])
+        .await
+        .unwrap();
+    runtime.start_container(server.clone()).await.unwrap();
+    assert!(runtime
+        .top(server.clone(), &[TopColumn::Pid])
+        .await
+        .unwrap()
+        .processes
+        .is_empty());
+    runtime.exit(&server, 137).unwrap();
+    assert_eq!(runtime.container_named("s").unwrap().exit_code, Some(137));
+    assert!(runtime.exit(&server, 1).is_err());
+}
+
+#[tokio::test]
+async fn test_fake_netlink_captures_bridge_operations() {
+    let netlink = Arc::new(FakeNetlink::new());
+    let mut bridges = BridgeManager::new().with_netlink(netlink.clone());
+
+    bridges
+        .create_bridge("polis0", "172.17.0.1", "172.17.0.0/16", 1500, None)
+        .await
+        .unwrap();
+    bridges.add_interface("polis0", "veth-a").await.unwrap();
+    bridges.add_interface("polis0", "veth-a").await.unwrap();
+    bridges.remove_interface("polis0", "veth-a").await.unwrap();
+    bridges.delete_bridge("polis0").await.unwrap();
+
+    assert_eq!(
+        netlink.ops(),
+        vec![
+            KernelOp::CreateBridge {
+                name: "polis0".to_string(),
+                mtu: 1500
+            },
+            KernelOp::AddAddress {
+                link: "polis0".to_string(),
+                address: "172.17.0.1/16".to_string()
+            },
+            KernelOp::SetLinkUp {
+                name: "polis0".to_string()
+            },
+            KernelOp::AttachInterface {
+                bridge: "polis0".to_string(),
+                interface: "veth-a".to_string()
+            },
+            KernelOp::DetachInterface {
+                bridge: "polis0".to_string(),
+                interface: "veth-a".to_string()
+            },
+            KernelOp::DeleteBridge {
+                name: "polis0".to_string()
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_rejected_kernel_operation_leaves_state_untouched() {
+    let netlink = Arc::new(FakeNetlink::new());
+    netlink.fail_on(KernelOp::AttachInterface {
+        bridge: "polis0".to_string(),
+        interface: "veth-b".to_string(),
+    });
+    let mut bridges = BridgeManager::new().with_netlink(netlink.clone());
+    bridges
+        .create_bridge("polis0", "172.17.0.1", "172.17.0.0/16", 1500, None)
+        .await
+        .unwrap();
+
+    assert!(bridges.add_interface("polis0", "veth-b").await.is_err());
+    let bridge = bridges.get_bridge("polis0").await.unwrap().unwrap();
+    assert!(bridge.interfaces.is_empty());
+}
+
+#[tokio::test]
+async fn test_fake_firewall_mirrors_egress_rules() {
+    let backend = Arc::new(FakeFirewall::new());
+    let mut firewall = FirewallManager::new().with_backend(backend.clone());
+    let mut egress = EgressController::new();
+    let policy = EgressPolicy::from_allow_list(&["10.0.0.0/8".to_string()], EgressMode::Enforce);
+
+    egress.attach(&mut firewall, "web", policy).await.unwrap();
+    assert_eq!(
+        backend.rule_ids(EGRESS_CHAIN),
+        vec!["egress-web-cidr-10.0.0.0/8", "egress-web-deny"]
+    );
+
+    egress.detach(&mut firewall, "web").await.unwrap();
+    assert!(backend.rule_ids(EGRESS_CHAIN).is_empty());
+    assert!(matches!(
+        backend.ops().last(),
+        Some(FirewallOp::RemoveRule { rule_id, .. }) if rule_id == "egress-web-deny"
+    ));
+}
+
+#[test]
+fn test_layer_digests_are_reproducible() {
+    let tree = FileTree::new()
+        .dir("etc")
+        .file("etc/hostname", "polis\n")
+        .executable("bin/app", "#!/bin/sh\n")
+        .symlink("bin/sh", "busybox")
+        .whiteout("var/cache");
+
+    let layer = tree.layer();
+    let again = tree.clone().layer();
+    assert_eq!(layer.diff_id, again.diff_id);
+    assert_eq!(layer.digest, again.digest);
+    assert_eq!(layer.diff_id, sha256_digest(&layer.tar));
+    assert_eq!(layer.digest, sha256_digest(&layer.compressed));
+    assert_eq!(layer.descriptor()["size"], layer.size());
+
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(&layer.compressed[..]));
+    let mut seen = Vec::new();
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let path = entry.path().unwrap().to_string_lossy().to_string();
+        if path == "etc/hostname" {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            assert_eq!(contents, "polis\n");
+        }
+        seen.push(path);
+    }
+    assert_eq!(
+        seen,
+        vec!["etc", "etc/hostname", "bin/app", "bin/sh", "var/.wh.cache"]
+    );
+
+    let changed = FileTree::new().file("etc/hostname", "other\n").layer();
+    assert_ne!(changed.diff_id, layer.diff_id);
+}
+
+#[test]
+fn test_file_tree_writes_to_disk() {
+    let temp = tempfile::tempdir().unwrap();
+    FileTree::new()
+        .file("apps/web.yaml", "name: web\n")
+        .dir("empty")
+        .write_to(temp.path())
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(temp.path().join("apps/web.yaml")).unwrap(),
+        "name: web\n"
+    );
+    assert!(temp.path().join("empty").is_dir());
+}
+
+#[test]
+fn test_proc_and_cgroup_fixtures_are_readable() {
+    let temp = tempfile::tempdir().unwrap();
+    let proc = ProcFixture::create(&temp.path().join("proc"), 1000.0, 1_700_000_000);
+    proc.process(&ProcProcess {
+        nspid: Some(1),
+        rss_kb: 2048,
+        threads: 3,
+        starttime: 50000,
+        cmdline: vec![
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            "run.sh".to_string(),
+        ],
+        ..ProcProcess::new(4242, "sh")
+    });
+    let cgroup = CgroupFixture::create(temp.path(), "polis/web");
+    cgroup
+        .procs(&[4242, 4999])
+        .memory(4096, None)
+        .cpu_stat(1500, 1000, 500);
+
+    let processes = ProcReader::with_root(proc.path())
+        .list_cgroup_processes(cgroup.path())
+        .unwrap();
+    assert_eq!(processes.len(), 1);
+    assert_eq!(processes[0].host_pid, 4242);
+    assert_eq!(processes[0].container_pid, 1);
+    assert_eq!(processes[0].threads, 3);
+    assert_eq!(processes[0].cmdline, "/bin/sh -c run.sh");
+    assert_eq!(
+        std::fs::read_to_string(cgroup.path().join("memory.max")).unwrap(),
+        "max\n"
+    );
+}
+
+#[tokio::test]
+async fn test_mock_registry_serves_content_and_faults() {
+    let registry = MockRegistry::start().await;
+    let layer = FileTree::new().file("hello.txt", "hi").layer();
+    registry.add_manifest(
+        "library/alpine",
+        "latest",
+        r#"{"mediaType":"application/vnd.docker.distribution.manifest.v2+json"}"#,
+    );
+    registry.add_blob("library/alpine", &layer.digest, layer.compressed.clone());
+    registry.set_header("ratelimit-remaining", "99;w=21600");
+
+    let client = reqwest::Client::new();
+    let manifest_url = format!("{}/library/alpine/manifests/latest", registry.base_url());
+
+    registry.inject(Fault::rate_limited(
+        Some(1),
+        &[("ratelimit-remaining", "0;w=21600")],
+    ));
+    let throttled = client.get(&manifest_url).send().await.unwrap();
+    assert_eq!(throttled.status(), 429);
+    assert_eq!(throttled.headers()["retry-after"], "1");
+    assert_eq!(throttled.headers()["ratelimit-remaining"], "0;w=21600");
+
+    let ok = client.get(&manifest_url).send().await.unwrap();
+    assert_eq!(ok.status(), 200);
+    assert_eq!(
+        ok.headers()["content-type"],
+        "application/vnd.docker.distribution.manifest.v2+json"
+    );
+    assert_eq!(ok.headers()["ratelimit-remaining"], "99;w=21600");
+
+    registry.inject(Fault::AuthChallenge {
+        realm: "https://auth.example.com/token".to_string(),
+        service: "registry.example.com".to_string(),
+    });
+    let challenged = client.get(&manifest_url).send().await.unwrap();
+    assert_eq!(challenged.status(), 401);
+    assert!(challenged.headers()["www-authenticate"]
+        .to_str()
+        .unwrap()
+        .contains("scope=\"repository:library/alpine:pull\""));
+
+    let blob_url = format!(
+        "{}/library/alpine/blobs/{}",
+        registry.base_url(),
+        layer.digest
+    );
+    let blob = client.get(&blob_url).send().await.unwrap();
+    assert_eq!(blob.bytes().await.unwrap().to_vec(), layer.compressed);
+
+    registry.inject(Fault::Truncate(4));
+    // hyper may notice the short body while reading the response head
+    let truncated = match client.get(&blob_url).send().await {
+        Ok(response) => response.bytes().await,
+        Err(error) => Err(error),
+    };
+    assert!(truncated.is_err());
+
+    registry.inject(Fault::Latency(Duration::from_millis(50)));
+    let started = Instant::now();
+    let delayed = client.get(&manifest_url).send().await.unwrap();
+    assert_eq!(delayed.status(), 200);
+    assert!(started.elapsed() >= Duration::from_millis(50));
+
+    registry.inject_always(Fault::Status(503));
+    assert_eq!(
+        client.get(&manifest_url).send().await.unwrap().status(),
+        503
+    );
+    registry.clear_faults();
+
+    let missing = client
+        .get(format!(
+            "{}/library/alpine/manifests/nope",
+            registry.base_url()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+    assert_eq!(registry.requests().len(), 8);
+    assert_eq!(
+        registry.requested_paths()[0],
+        "/v2/library/alpine/manifests/latest"
+    );
+}